@@ -6,8 +6,11 @@
 
 use std::fmt::Display;
 
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Argon2, PasswordHasher,
+};
 use prettytable::{Attr, Cell, Row, Table};
-use pwhash::sha512_crypt;
 use reqwest::Method;
 use serde_json::Value;
 
@@ -37,7 +40,7 @@ impl AccountCommands {
                     .into(),
                     quota,
                     name: name.clone().into(),
-                    secrets: vec![sha512_crypt::hash(password).unwrap()],
+                    secrets: vec![hash_password(&password)],
                     emails: addresses.unwrap_or_default(),
                     member_of: member_of.unwrap_or_default(),
                     description,
@@ -68,7 +71,7 @@ impl AccountCommands {
                 if let Some(password) = password {
                     changes.push(PrincipalUpdate::add_item(
                         PrincipalField::Secrets,
-                        PrincipalValue::String(sha512_crypt::hash(password).unwrap()),
+                        PrincipalValue::String(hash_password(&password)),
                     ));
                 }
                 if let Some(description) = description {
@@ -365,3 +368,11 @@ impl Display for Type {
         }
     }
 }
+
+/// Hashes passwords with Argon2id, the server's default scheme.
+fn hash_password(password: &str) -> String {
+    Argon2::default()
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .unwrap()
+        .to_string()
+}
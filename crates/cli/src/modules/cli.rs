@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use jmap_client::client::Credentials;
 use mail_parser::DateTime;
 use serde::Deserialize;
@@ -63,6 +63,9 @@ pub enum Commands {
     /// Manage SMTP DMARC/TLS report queue
     #[clap(subcommand)]
     Report(ReportCommands),
+
+    /// Search messages stored in an account
+    Search(SearchCommand),
 }
 
 pub struct Client {
@@ -398,12 +401,36 @@ pub enum ServerCommands {
     /// Perform database maintenance
     DatabaseMaintenance {},
 
+    /// Show the storage used by each subsystem and the largest accounts
+    StorageUsage {
+        /// Store to scan, defaults to the data store
+        store: Option<String>,
+        /// Maximum number of accounts to display
+        #[clap(short, long)]
+        limit: Option<usize>,
+    },
+
+    /// Recompress old messages with a stronger algorithm to reclaim space
+    ArchiveMessages {
+        /// Account to process, defaults to all accounts
+        account: Option<String>,
+        /// Minimum message age in months, defaults to 12
+        #[clap(short, long)]
+        months: Option<u64>,
+    },
+
     /// Reload TLS certificates
     ReloadCertificates {},
 
     /// Reload configuration
     ReloadConfig {},
 
+    /// Validate a configuration file and preview its changes without applying it
+    CheckConfig {
+        /// Path to the configuration file, use '-' to read from stdin
+        path: String,
+    },
+
     /// Create a new configuration key
     AddConfig {
         /// Key to add
@@ -548,6 +575,51 @@ pub enum ReportFormat {
     Tls,
 }
 
+#[derive(Args)]
+pub struct SearchCommand {
+    /// Account to search
+    #[clap(short, long)]
+    pub account: String,
+    /// Filter by text in the message headers or body
+    #[clap(short = 'x', long)]
+    pub text: Option<String>,
+    /// Filter by sender
+    #[clap(short, long)]
+    pub from: Option<String>,
+    /// Filter by recipient
+    #[clap(short = 'r', long)]
+    pub to: Option<String>,
+    /// Filter by sender or recipient
+    #[clap(short, long)]
+    pub participant: Option<String>,
+    /// Filter by subject
+    #[clap(short, long)]
+    pub subject: Option<String>,
+    /// Filter messages received before a certain datetime
+    #[clap(short, long)]
+    #[arg(value_parser = parse_datetime)]
+    pub before: Option<DateTime>,
+    /// Filter messages received after a certain datetime
+    #[clap(short = 'n', long)]
+    #[arg(value_parser = parse_datetime)]
+    pub after: Option<DateTime>,
+    /// Maximum number of messages to return
+    #[clap(short, long)]
+    pub limit: Option<usize>,
+    /// Output format
+    #[clap(short = 'o', long)]
+    #[clap(value_enum, default_value_t = SearchFormat::Table)]
+    pub format: SearchFormat,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum SearchFormat {
+    /// Table
+    Table,
+    /// JSON
+    Json,
+}
+
 fn parse_datetime(arg: &str) -> Result<DateTime, &'static str> {
     if arg.contains('T') {
         DateTime::parse_rfc3339(arg).ok_or("Failed to parse RFC3339 datetime")
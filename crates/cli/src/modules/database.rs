@@ -10,10 +10,80 @@ use prettytable::{Attr, Cell, Row, Table};
 use reqwest::Method;
 use serde_json::Value;
 
-use crate::modules::Response;
+use crate::modules::{read_file, Response};
 
 use super::cli::{Client, ServerCommands};
 
+#[derive(Debug, serde::Deserialize)]
+struct KeySpaceUsage {
+    keys: u64,
+    bytes: u64,
+    subsystems: Vec<SubsystemUsage>,
+    accounts: Vec<AccountUsage>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SubsystemUsage {
+    subsystem: String,
+    keys: u64,
+    bytes: u64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountUsage {
+    account_id: u32,
+    name: Option<String>,
+    keys: u64,
+    bytes: u64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ConfigCheckReport {
+    errors: HashMap<String, ConfigIssue>,
+    warnings: HashMap<String, ConfigIssue>,
+    effective: Vec<EffectiveSetting>,
+    changes: Vec<ConfigChange>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ConfigIssue {
+    #[serde(rename = "type")]
+    typ: String,
+    error: Option<String>,
+    value: Option<String>,
+    default: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct EffectiveSetting {
+    key: String,
+    value: String,
+    source: String,
+    expression: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ConfigChange {
+    #[serde(rename = "type")]
+    typ: String,
+    key: String,
+    value: Option<String>,
+    old: Option<String>,
+    new: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveReport {
+    account_id: u32,
+    name: Option<String>,
+    messages: u64,
+    blobs: u64,
+    bytes_before: u64,
+    bytes_after: u64,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type")]
 pub enum UpdateSettings {
@@ -39,6 +109,120 @@ impl ServerCommands {
                     .await;
                 eprintln!("Success.");
             }
+            ServerCommands::StorageUsage { store, limit } => {
+                let mut url = "/api/store/usage".to_string();
+                if let Some(store) = store {
+                    url.push('/');
+                    url.push_str(&store);
+                }
+                if let Some(limit) = limit {
+                    url.push_str(&format!("?limit={limit}"));
+                }
+                let usage = client
+                    .http_request::<KeySpaceUsage, String>(Method::GET, &url, None)
+                    .await;
+
+                let mut table = Table::new();
+                table.add_row(Row::new(vec![
+                    Cell::new("Subsystem").with_style(Attr::Bold),
+                    Cell::new("Keys").with_style(Attr::Bold),
+                    Cell::new("Bytes").with_style(Attr::Bold),
+                ]));
+                for subsystem in &usage.subsystems {
+                    table.add_row(Row::new(vec![
+                        Cell::new(&subsystem.subsystem),
+                        Cell::new(&subsystem.keys.to_string()),
+                        Cell::new(&subsystem.bytes.to_string()),
+                    ]));
+                }
+                table.add_row(Row::new(vec![
+                    Cell::new("Total").with_style(Attr::Bold),
+                    Cell::new(&usage.keys.to_string()),
+                    Cell::new(&usage.bytes.to_string()),
+                ]));
+                eprintln!();
+                table.printstd();
+
+                if !usage.accounts.is_empty() {
+                    let mut table = Table::new();
+                    table.add_row(Row::new(vec![
+                        Cell::new("Account").with_style(Attr::Bold),
+                        Cell::new("Keys").with_style(Attr::Bold),
+                        Cell::new("Bytes").with_style(Attr::Bold),
+                    ]));
+                    for account in &usage.accounts {
+                        table.add_row(Row::new(vec![
+                            Cell::new(
+                                &account
+                                    .name
+                                    .clone()
+                                    .unwrap_or_else(|| format!("#{}", account.account_id)),
+                            ),
+                            Cell::new(&account.keys.to_string()),
+                            Cell::new(&account.bytes.to_string()),
+                        ]));
+                    }
+                    eprintln!();
+                    table.printstd();
+                }
+                eprintln!();
+            }
+            ServerCommands::ArchiveMessages { account, months } => {
+                let mut url = "/api/store/archive".to_string();
+                if let Some(account) = account {
+                    url.push('/');
+                    url.push_str(&account);
+                }
+                if let Some(months) = months {
+                    url.push_str(&format!("?months={months}"));
+                }
+                let reports = client
+                    .http_request::<Vec<ArchiveReport>, String>(Method::GET, &url, None)
+                    .await;
+
+                if !reports.is_empty() {
+                    let mut table = Table::new();
+                    table.add_row(Row::new(vec![
+                        Cell::new("Account").with_style(Attr::Bold),
+                        Cell::new("Messages").with_style(Attr::Bold),
+                        Cell::new("Blobs").with_style(Attr::Bold),
+                        Cell::new("Bytes before").with_style(Attr::Bold),
+                        Cell::new("Bytes after").with_style(Attr::Bold),
+                        Cell::new("Reclaimed").with_style(Attr::Bold),
+                    ]));
+                    for report in &reports {
+                        table.add_row(Row::new(vec![
+                            Cell::new(
+                                &report
+                                    .name
+                                    .clone()
+                                    .unwrap_or_else(|| format!("#{}", report.account_id)),
+                            ),
+                            Cell::new(&report.messages.to_string()),
+                            Cell::new(&report.blobs.to_string()),
+                            Cell::new(&report.bytes_before.to_string()),
+                            Cell::new(&report.bytes_after.to_string()),
+                            Cell::new(
+                                &report
+                                    .bytes_before
+                                    .saturating_sub(report.bytes_after)
+                                    .to_string(),
+                            ),
+                        ]));
+                    }
+                    eprintln!();
+                    table.printstd();
+                }
+
+                eprintln!(
+                    "\nReclaimed {} bytes from {} accounts.\n",
+                    reports
+                        .iter()
+                        .map(|report| report.bytes_before.saturating_sub(report.bytes_after))
+                        .sum::<u64>(),
+                    reports.len()
+                );
+            }
             ServerCommands::ReloadCertificates {} => {
                 client
                     .http_request::<Value, String>(Method::GET, "/api/reload/certificate", None)
@@ -51,6 +235,100 @@ impl ServerCommands {
                     .await;
                 eprintln!("Success.");
             }
+            ServerCommands::CheckConfig { path } => {
+                let toml = String::from_utf8(read_file(&path)).unwrap_or_else(|_| {
+                    eprintln!("Configuration file is not valid UTF-8.");
+                    std::process::exit(1);
+                });
+                let report = client
+                    .try_http_request_raw::<ConfigCheckReport>(
+                        Method::POST,
+                        "/api/reload/check",
+                        Some(toml),
+                    )
+                    .await
+                    .unwrap_or_else(|| {
+                        eprintln!("Request failed: No data returned.");
+                        std::process::exit(1);
+                    });
+
+                if !report.changes.is_empty() {
+                    let mut table = Table::new();
+                    table.add_row(Row::new(vec![
+                        Cell::new("Change").with_style(Attr::Bold),
+                        Cell::new("Key").with_style(Attr::Bold),
+                        Cell::new("Current").with_style(Attr::Bold),
+                        Cell::new("New").with_style(Attr::Bold),
+                    ]));
+                    for change in &report.changes {
+                        let (current, new) = match change.typ.as_str() {
+                            "added" => (None, change.value.as_deref()),
+                            "removed" => (change.value.as_deref(), None),
+                            _ => (change.old.as_deref(), change.new.as_deref()),
+                        };
+                        table.add_row(Row::new(vec![
+                            Cell::new(&change.typ),
+                            Cell::new(&change.key),
+                            Cell::new(current.unwrap_or_default()),
+                            Cell::new(new.unwrap_or_default()),
+                        ]));
+                    }
+                    eprintln!();
+                    table.printstd();
+                }
+
+                if !report.effective.is_empty() {
+                    let mut table = Table::new();
+                    table.add_row(Row::new(vec![
+                        Cell::new("Key").with_style(Attr::Bold),
+                        Cell::new("Effective value").with_style(Attr::Bold),
+                        Cell::new("Source").with_style(Attr::Bold),
+                    ]));
+                    for setting in &report.effective {
+                        table.add_row(Row::new(vec![
+                            Cell::new(&setting.key),
+                            Cell::new(&setting.value),
+                            Cell::new(setting.expression.as_deref().unwrap_or(&setting.source)),
+                        ]));
+                    }
+                    eprintln!();
+                    table.printstd();
+                }
+
+                for (title, issues) in [("Warning", &report.warnings), ("Error", &report.errors)] {
+                    if !issues.is_empty() {
+                        let mut issues = issues.iter().collect::<Vec<_>>();
+                        issues.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+                        let mut table = Table::new();
+                        table.add_row(Row::new(vec![
+                            Cell::new("Key").with_style(Attr::Bold),
+                            Cell::new(title).with_style(Attr::Bold),
+                        ]));
+                        for (key, issue) in issues {
+                            table.add_row(Row::new(vec![
+                                Cell::new(key),
+                                Cell::new(&issue.to_string()),
+                            ]));
+                        }
+                        eprintln!();
+                        table.printstd();
+                    }
+                }
+
+                eprintln!(
+                    "\n{} change{}, {} warning{}, {} error{}.\n",
+                    report.changes.len(),
+                    if report.changes.len() == 1 { "" } else { "s" },
+                    report.warnings.len(),
+                    if report.warnings.len() == 1 { "" } else { "s" },
+                    report.errors.len(),
+                    if report.errors.len() == 1 { "" } else { "s" },
+                );
+                if !report.errors.is_empty() {
+                    std::process::exit(1);
+                }
+            }
             ServerCommands::AddConfig { key, value } => {
                 client
                     .http_request::<Value, _>(
@@ -112,3 +390,26 @@ impl ServerCommands {
         }
     }
 }
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.typ.as_str() {
+            "Unread" => write!(
+                f,
+                "Unknown setting with value {:?}",
+                self.value.as_deref().unwrap_or_default()
+            ),
+            "Missing" => write!(f, "Missing setting"),
+            "AppliedDefault" => write!(
+                f,
+                "Missing setting, applied default {:?}",
+                self.default.as_deref().unwrap_or_default()
+            ),
+            typ => write!(
+                f,
+                "{typ} error: {}",
+                self.error.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
@@ -22,6 +22,7 @@ pub mod import;
 pub mod list;
 pub mod queue;
 pub mod report;
+pub mod search;
 
 const RETRY_ATTEMPTS: usize = 5;
 
@@ -0,0 +1,126 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{
+    cli::{Client, SearchCommand, SearchFormat},
+    List,
+};
+use human_size::{Byte, SpecificSize};
+use mail_parser::DateTime;
+use prettytable::{Attr, Cell, Row, Table};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub id: String,
+    pub received_at: String,
+    pub size: usize,
+    #[serde(default)]
+    pub message_id: Option<String>,
+    #[serde(default)]
+    pub from: Vec<String>,
+    #[serde(default)]
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub subject: Option<String>,
+}
+
+impl SearchCommand {
+    pub async fn exec(self, client: Client) {
+        let mut query = form_urlencoded::Serializer::new("/api/search".to_string());
+        query.append_pair("account", &self.account);
+        for (name, value) in [
+            ("text", &self.text),
+            ("from", &self.from),
+            ("to", &self.to),
+            ("participant", &self.participant),
+            ("subject", &self.subject),
+        ] {
+            if let Some(value) = value {
+                query.append_pair(name, value);
+            }
+        }
+        if let Some(before) = &self.before {
+            query.append_pair("before", &before.to_rfc3339());
+        }
+        if let Some(after) = &self.after {
+            query.append_pair("after", &after.to_rfc3339());
+        }
+        if let Some(limit) = self.limit {
+            query.append_pair("limit", &limit.to_string());
+        }
+
+        let results = client
+            .http_request::<List<SearchResult>, String>(Method::GET, &query.finish(), None)
+            .await;
+
+        match self.format {
+            SearchFormat::Json => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&results.items).unwrap_or_default()
+                );
+            }
+            SearchFormat::Table => {
+                let mut table = Table::new();
+                table.add_row(Row::new(
+                    [
+                        "ID",
+                        "Received",
+                        "From",
+                        "To",
+                        "Subject",
+                        "Message-ID",
+                        "Size",
+                    ]
+                    .iter()
+                    .map(|p| Cell::new(p).with_style(Attr::Bold))
+                    .collect(),
+                ));
+                for result in &results.items {
+                    table.add_row(Row::new(vec![
+                        Cell::new(&result.id),
+                        Cell::new(
+                            &DateTime::parse_rfc3339(&result.received_at)
+                                .map(|dt| dt.to_rfc822())
+                                .unwrap_or_else(|| result.received_at.clone()),
+                        ),
+                        Cell::new(&result.from.join("\n")),
+                        Cell::new(
+                            &result
+                                .to
+                                .iter()
+                                .chain(&result.cc)
+                                .cloned()
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        ),
+                        Cell::new(result.subject.as_deref().unwrap_or_default()),
+                        Cell::new(result.message_id.as_deref().unwrap_or_default()),
+                        Cell::new(
+                            &SpecificSize::new(result.size as u32, Byte)
+                                .unwrap()
+                                .to_string(),
+                        ),
+                    ]));
+                }
+
+                eprintln!();
+                table.printstd();
+                eprintln!();
+                eprintln!(
+                    "\n{} of {} matching message(s) shown.",
+                    results.items.len(),
+                    results.total
+                );
+            }
+        }
+    }
+}
@@ -10,7 +10,7 @@ use directory::{backend::RcptType, Directory};
 use utils::config::{utils::AsKey, Config};
 
 use crate::{
-    config::smtp::session::AddressMapping,
+    config::smtp::session::{AddressMapping, CatchAll, DomainAddressing, Rcpt},
     expr::{
         functions::ResolveVariable, if_block::IfBlock, tokenizer::TokenMap, Variable, V_RECIPIENT,
     },
@@ -24,29 +24,14 @@ impl Server {
         email: &str,
         session_id: u64,
     ) -> trc::Result<Option<u32>> {
-        let mut address = self
-            .core
-            .smtp
-            .session
-            .rcpt
-            .subaddressing
-            .to_subaddress(self, email, session_id)
-            .await;
+        let mut address = self.resolve_subaddress(email, session_id).await;
 
         for _ in 0..2 {
             let result = directory.email_to_id(address.as_ref()).await?;
 
             if result.is_some() {
                 return Ok(result);
-            } else if let Some(catch_all) = self
-                .core
-                .smtp
-                .session
-                .rcpt
-                .catch_all
-                .to_catch_all(self, email, session_id)
-                .await
-            {
+            } else if let Some(catch_all) = self.resolve_catch_all(email, session_id).await {
                 address = catch_all;
             } else {
                 break;
@@ -63,28 +48,19 @@ impl Server {
         session_id: u64,
     ) -> trc::Result<RcptType> {
         // Expand subaddress
-        let mut address = self
-            .core
-            .smtp
-            .session
-            .rcpt
-            .subaddressing
-            .to_subaddress(self, email, session_id)
-            .await;
+        let mut address = self.resolve_subaddress(email, session_id).await;
 
         for _ in 0..2 {
             let rcpt_type = directory.rcpt(address.as_ref()).await?;
             if rcpt_type != RcptType::Invalid {
                 return Ok(rcpt_type);
-            } else if let Some(catch_all) = self
-                .core
-                .smtp
-                .session
-                .rcpt
-                .catch_all
-                .to_catch_all(self, email, session_id)
-                .await
+            } else if self.core.smtp.lists.get_by_bounce(email).is_some()
+                // Disposable aliases are part of the address filter
+                || (!directory.is_unknown_address(email)
+                    && self.disposable_alias_account(email).await?.is_some())
             {
+                return Ok(RcptType::Mailbox);
+            } else if let Some(catch_all) = self.resolve_catch_all(email, session_id).await {
                 address = catch_all;
             } else {
                 break;
@@ -101,16 +77,7 @@ impl Server {
         session_id: u64,
     ) -> trc::Result<Vec<String>> {
         directory
-            .vrfy(
-                self.core
-                    .smtp
-                    .session
-                    .rcpt
-                    .subaddressing
-                    .to_subaddress(self, address, session_id)
-                    .await
-                    .as_ref(),
-            )
+            .vrfy(self.resolve_subaddress(address, session_id).await.as_ref())
             .await
     }
 
@@ -121,18 +88,89 @@ impl Server {
         session_id: u64,
     ) -> trc::Result<Vec<String>> {
         directory
-            .expn(
-                self.core
-                    .smtp
-                    .session
-                    .rcpt
-                    .subaddressing
-                    .to_subaddress(self, address, session_id)
-                    .await
-                    .as_ref(),
-            )
+            .expn(self.resolve_subaddress(address, session_id).await.as_ref())
             .await
     }
+
+    async fn resolve_subaddress<'x>(&'x self, address: &'x str, session_id: u64) -> Cow<'x, str> {
+        let rcpt = &self.core.smtp.session.rcpt;
+        if let Some(addressing) = rcpt.domain_addressing(address) {
+            addressing.to_subaddress(address)
+        } else {
+            rcpt.subaddressing
+                .to_subaddress(self, address, session_id)
+                .await
+        }
+    }
+
+    async fn resolve_catch_all<'x>(
+        &'x self,
+        address: &'x str,
+        session_id: u64,
+    ) -> Option<Cow<'x, str>> {
+        let rcpt = &self.core.smtp.session.rcpt;
+        match rcpt
+            .domain_addressing(address)
+            .map(|addressing| &addressing.catch_all)
+        {
+            Some(CatchAll::Disable) => None,
+            Some(CatchAll::Domain) => address
+                .rsplit_once('@')
+                .map(|(_, domain_part)| Cow::Owned(format!("@{domain_part}"))),
+            Some(CatchAll::Address(catch_all)) => Some(Cow::Borrowed(catch_all.as_str())),
+            Some(CatchAll::Inherit) | None => {
+                rcpt.catch_all.to_catch_all(self, address, session_id).await
+            }
+        }
+    }
+}
+
+impl Rcpt {
+    pub fn domain_addressing(&self, address: &str) -> Option<&DomainAddressing> {
+        if self.addressing.is_empty() {
+            return None;
+        }
+        let domain = address.rsplit_once('@')?.1;
+        self.addressing.iter().find(|addressing| {
+            addressing
+                .domains
+                .iter()
+                .any(|d| d.eq_ignore_ascii_case(domain))
+        })
+    }
+
+    /// Returns the folder a sub-address should be filed into, along with
+    /// whether the folder should be created when missing.
+    pub fn subaddress_folder<'x>(&self, address: &'x str) -> Option<(&'x str, bool)> {
+        let subaddressing = self
+            .domain_addressing(address)?
+            .subaddressing
+            .as_ref()
+            .filter(|subaddressing| subaddressing.file_into)?;
+        let (local_part, _) = address.rsplit_once('@')?;
+        local_part
+            .split_once(subaddressing.delimiters.as_slice())
+            .filter(|(local_part, folder)| !local_part.is_empty() && !folder.is_empty())
+            .map(|(_, folder)| folder)
+            .map(|folder| (folder, subaddressing.create_folder))
+    }
+}
+
+impl DomainAddressing {
+    pub fn to_subaddress<'x>(&self, address: &'x str) -> Cow<'x, str> {
+        if let Some((subaddressing, (local_part, domain_part))) =
+            self.subaddressing.as_ref().zip(address.rsplit_once('@'))
+        {
+            if let Some((local_part, _)) = local_part
+                .split_once(subaddressing.delimiters.as_slice())
+                .filter(|(local_part, _)| !local_part.is_empty())
+            {
+                return format!("{local_part}@{domain_part}").into();
+            }
+        }
+
+        address.into()
+    }
 }
 
 impl AddressMapping {
@@ -222,3 +260,33 @@ impl AddressMapping {
         }
     }
 }
+
+/// Encodes a recipient in the local part of a return path, so that bounces
+/// can be attributed to it, producing `sender+user=domain@sender-domain`.
+pub fn verp_encode(return_path: &str, rcpt: &str) -> String {
+    match (return_path.rsplit_once('@'), rcpt.rsplit_once('@')) {
+        (Some((local_part, domain_part)), Some((rcpt_local, rcpt_domain))) => {
+            format!("{local_part}+{rcpt_local}={rcpt_domain}@{domain_part}")
+        }
+        _ => return_path.to_string(),
+    }
+}
+
+/// Decodes a VERP address into the original return path and recipient.
+pub fn verp_decode(address: &str) -> Option<(String, String)> {
+    let (local_part, domain_part) = address.rsplit_once('@')?;
+    let (local_part, rcpt) = local_part.split_once('+')?;
+    let (rcpt_local, rcpt_domain) = rcpt.rsplit_once('=')?;
+    if !local_part.is_empty()
+        && !rcpt_local.is_empty()
+        && rcpt_domain.contains('.')
+        && !domain_part.is_empty()
+    {
+        Some((
+            format!("{local_part}@{domain_part}"),
+            format!("{rcpt_local}@{rcpt_domain}"),
+        ))
+    } else {
+        None
+    }
+}
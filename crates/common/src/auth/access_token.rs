@@ -27,7 +27,9 @@ use utils::map::{
 
 use crate::Server;
 
-use super::{roles::RolePermissions, AccessToken, ResourceToken, TenantInfo};
+use super::{
+    delegation::Delegation, roles::RolePermissions, AccessToken, ResourceToken, TenantInfo,
+};
 
 impl Server {
     pub async fn build_access_token(&self, mut principal: Principal) -> trc::Result<AccessToken> {
@@ -94,6 +96,12 @@ impl Server {
 
         // SPDX-SnippetEnd
 
+        // Obtain accounts delegated to this principal
+        let delegated_from = self
+            .get_delegators(principal.id())
+            .await
+            .caused_by(trc::location!())?;
+
         Ok(AccessToken {
             primary_id: principal.id(),
             member_of: principal
@@ -109,6 +117,7 @@ impl Server {
                 .unwrap_or_default(),
             quota: principal.quota(),
             permissions,
+            delegated_from,
         })
     }
 
@@ -155,6 +164,10 @@ impl Server {
                 if !access_token.is_member(acl_item.to_account_id) {
                     let acl = Bitmap::<Acl>::from(acl_item.permissions);
                     let collection = Collection::from(acl_item.to_collection);
+                    if collection == Collection::Principal {
+                        // Delegations are loaded when the token is built
+                        continue;
+                    }
                     if !collection.is_valid() {
                         return Err(trc::StoreEvent::DataCorruption
                             .ctx(trc::Key::Reason, "Corrupted collection found in ACL key.")
@@ -183,6 +196,27 @@ impl Server {
             }
         }
 
+        // Add delegated mailboxes
+        for account_id in access_token
+            .delegated_from
+            .iter()
+            .filter(|account| {
+                account.rights.contains(Delegation::ReadMailbox)
+                    || account.rights.contains(Delegation::ManageMailbox)
+            })
+            .map(|account| account.account_id)
+            .collect::<Vec<_>>()
+        {
+            access_token
+                .access_to
+                .get_mut_or_insert_with(account_id, Bitmap::new)
+                .union(&Bitmap::from_iter([
+                    Collection::Mailbox,
+                    Collection::Email,
+                    Collection::Thread,
+                ]));
+        }
+
         Ok(access_token)
     }
 
@@ -407,6 +441,9 @@ impl AccessToken {
             RequestMethod::CopyBlob(_) => Permission::JmapBlobCopy,
             RequestMethod::ImportEmail(_) => Permission::JmapEmailImport,
             RequestMethod::ParseEmail(_) => Permission::JmapEmailParse,
+            RequestMethod::ShareEmail(_) => Permission::JmapEmailShare,
+            RequestMethod::GetAbsence(_) => Permission::JmapAbsenceGet,
+            RequestMethod::SetAbsence(_) => Permission::JmapAbsenceSet,
             RequestMethod::QueryChanges(m) => match m.arguments {
                 jmap_proto::method::query::RequestArguments::Email(_) => {
                     Permission::JmapEmailQueryChanges
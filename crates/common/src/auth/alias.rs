@@ -0,0 +1,311 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::backend::RcptType;
+use jmap_proto::types::{collection::Collection, property::Property};
+use store::{
+    rand::{distributions::Alphanumeric, thread_rng, Rng},
+    write::{
+        assert::{AssertValue, HashedValue},
+        now, BatchBuilder, Bincode, LookupClass, ValueClass, F_CLEAR, F_VALUE,
+    },
+    IterateParams, LookupStore, Serialize, ValueKey,
+};
+use trc::AddContext;
+
+use crate::Server;
+
+const SUFFIX_LEN: usize = 6;
+const MAX_PREFIX_LEN: usize = 16;
+
+/// Random address delivering to an account's mailbox, minted by the account
+/// owner and revocable at any time.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DisposableAlias {
+    pub account_id: u32,
+    pub address: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub created: u64,
+    pub expires: Option<u64>,
+}
+
+impl DisposableAlias {
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.expires.map_or(true, |expires| expires > now())
+    }
+}
+
+impl Server {
+    /// Returns the aliases minted by an account, including disabled and
+    /// expired ones.
+    pub async fn disposable_aliases(&self, account_id: u32) -> trc::Result<Vec<DisposableAlias>> {
+        let mut aliases = Vec::new();
+        for address in self
+            .disposable_alias_index(account_id)
+            .await?
+            .map(|index| index.inner.inner)
+            .unwrap_or_default()
+        {
+            if let Some(alias) = self.disposable_alias_get(&address).await? {
+                aliases.push(alias);
+            }
+        }
+
+        Ok(aliases)
+    }
+
+    pub async fn disposable_alias_get(
+        &self,
+        address: &str,
+    ) -> trc::Result<Option<DisposableAlias>> {
+        self.core
+            .storage
+            .lookup
+            .key_get::<Bincode<DisposableAlias>>(alias_key(address))
+            .await
+            .caused_by(trc::location!())
+            .map(|alias| alias.map(|alias| alias.inner))
+    }
+
+    /// Returns the account an alias delivers to, or `None` if the alias does
+    /// not exist, is disabled or has expired.
+    pub async fn disposable_alias_account(&self, address: &str) -> trc::Result<Option<u32>> {
+        if self.core.jmap.disposable_aliases.is_none() {
+            return Ok(None);
+        }
+
+        Ok(self
+            .disposable_alias_get(address)
+            .await?
+            .filter(|alias| alias.is_active())
+            .map(|alias| alias.account_id))
+    }
+
+    /// Mints a new alias in the form `<prefix>-<random>@<domain>`. Only
+    /// lowercase letters and digits are kept from the prefix.
+    pub async fn disposable_alias_create(
+        &self,
+        account_id: u32,
+        prefix: &str,
+        domain: &str,
+        description: Option<String>,
+        expires: Option<u64>,
+    ) -> trc::Result<DisposableAlias> {
+        let mut prefix = prefix
+            .chars()
+            .filter(|ch| ch.is_ascii_alphanumeric())
+            .map(|ch| ch.to_ascii_lowercase())
+            .take(MAX_PREFIX_LEN)
+            .collect::<String>();
+        if prefix.is_empty() {
+            prefix = "alias".to_string();
+        }
+
+        for _ in 0..10 {
+            let suffix = thread_rng()
+                .sample_iter(Alphanumeric)
+                .take(SUFFIX_LEN)
+                .map(|ch| char::from(ch).to_ascii_lowercase())
+                .collect::<String>();
+            let address = format!("{prefix}-{suffix}@{}", domain.to_lowercase());
+            if self.disposable_alias_get(&address).await?.is_some()
+                || self.core.storage.directory.rcpt(&address).await? != RcptType::Invalid
+            {
+                continue;
+            }
+
+            let alias = DisposableAlias {
+                account_id,
+                address,
+                description,
+                enabled: true,
+                created: now(),
+                expires,
+            };
+            self.disposable_alias_set(&alias).await?;
+            self.disposable_alias_update_index(account_id, &alias.address, true)
+                .await?;
+
+            // Accept mail for the new alias
+            self.invalidate_address_filters().await;
+
+            return Ok(alias);
+        }
+
+        Err(trc::StoreEvent::UnexpectedError
+            .caused_by(trc::location!())
+            .details("Failed to generate a unique alias"))
+    }
+
+    pub async fn disposable_alias_set(&self, alias: &DisposableAlias) -> trc::Result<()> {
+        self.core
+            .storage
+            .lookup
+            .key_set(
+                alias_key(&alias.address),
+                Bincode::new(alias.clone()).serialize(),
+                None,
+            )
+            .await
+            .caused_by(trc::location!())
+    }
+
+    /// Removes an alias along with its counters, returning `false` if the
+    /// alias does not belong to the account.
+    pub async fn disposable_alias_delete(
+        &self,
+        account_id: u32,
+        address: &str,
+    ) -> trc::Result<bool> {
+        if !self
+            .disposable_alias_get(address)
+            .await?
+            .is_some_and(|alias| alias.account_id == account_id)
+        {
+            return Ok(false);
+        }
+
+        let lookup = &self.core.storage.lookup;
+        lookup
+            .key_delete(alias_key(address))
+            .await
+            .caused_by(trc::location!())?;
+        lookup
+            .counter_delete(received_key(address))
+            .await
+            .caused_by(trc::location!())?;
+
+        self.disposable_alias_update_index(account_id, address, false)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Returns the addresses of all aliases, including disabled and expired
+    /// ones, to be added to the address filters of directories.
+    pub async fn disposable_alias_addresses(&self) -> trc::Result<Vec<String>> {
+        if self.core.jmap.disposable_aliases.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let LookupStore::Store(store) = &self.core.storage.lookup else {
+            return Err(trc::StoreEvent::NotSupported
+                .into_err()
+                .details("Disposable aliases can only be listed from a data store"));
+        };
+
+        let mut addresses = Vec::new();
+        store
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Lookup(LookupClass::Key(alias_key("")))),
+                    ValueKey::from(ValueClass::Lookup(LookupClass::Key(b"alias;".to_vec()))),
+                )
+                .no_values(),
+                |key, _| {
+                    if let Some(address) = key.strip_prefix(b"alias:") {
+                        addresses.push(String::from_utf8_lossy(address).into_owned());
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(addresses)
+    }
+
+    pub async fn disposable_alias_received(&self, address: &str) -> trc::Result<i64> {
+        self.counter_get_coalesced(received_key(address))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    /// Resolves a recipient to the account an active alias delivers to and
+    /// counts the delivery against the alias.
+    pub async fn disposable_alias_deliver(&self, address: &str) -> trc::Result<Option<u32>> {
+        let account_id = self.disposable_alias_account(address).await?;
+        if account_id.is_some() {
+            self.counter_incr_coalesced(received_key(address), 1, None)
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(account_id)
+    }
+
+    async fn disposable_alias_index(
+        &self,
+        account_id: u32,
+    ) -> trc::Result<Option<HashedValue<Bincode<Vec<String>>>>> {
+        self.core
+            .storage
+            .data
+            .get_value::<HashedValue<Bincode<Vec<String>>>>(ValueKey {
+                account_id,
+                collection: Collection::Principal.into(),
+                document_id: 0,
+                class: ValueClass::Property(Property::DisposableAliases.into()),
+            })
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn disposable_alias_update_index(
+        &self,
+        account_id: u32,
+        address: &str,
+        add: bool,
+    ) -> trc::Result<()> {
+        let mut try_count = 0;
+        loop {
+            let (mut index, assert_value) = match self.disposable_alias_index(account_id).await? {
+                Some(index) => (index.inner.inner, AssertValue::Hash(index.hash)),
+                None => (Vec::new(), AssertValue::None),
+            };
+            if add {
+                index.push(address.to_string());
+            } else if index.iter().any(|item| item == address) {
+                index.retain(|item| item != address);
+            } else {
+                return Ok(());
+            }
+
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Principal)
+                .update_document(0)
+                .assert_value(
+                    ValueClass::Property(Property::DisposableAliases.into()),
+                    assert_value,
+                );
+            if !index.is_empty() {
+                batch.value(Property::DisposableAliases, Bincode::new(index), F_VALUE);
+            } else {
+                batch.value(Property::DisposableAliases, (), F_VALUE | F_CLEAR);
+            }
+
+            match self.core.storage.data.write(batch.build()).await {
+                Ok(_) => return Ok(()),
+                Err(err) if err.is_assertion_failure() && try_count < 3 => {
+                    try_count += 1;
+                }
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+        }
+    }
+}
+
+fn alias_key(address: &str) -> Vec<u8> {
+    format!("alias:{}", address.to_lowercase()).into_bytes()
+}
+
+fn received_key(address: &str) -> Vec<u8> {
+    format!("alias-received:{}", address.to_lowercase()).into_bytes()
+}
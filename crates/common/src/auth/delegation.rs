@@ -0,0 +1,190 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::{backend::internal::PrincipalField, QueryBy};
+use jmap_proto::types::{acl::Acl, collection::Collection};
+use store::{
+    query::acl::AclQuery,
+    write::{BatchBuilder, Operation},
+    Serialize,
+};
+use trc::AddContext;
+use utils::map::bitmap::{Bitmap, BitmapItem};
+
+use crate::Server;
+
+use super::AccessToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[repr(u8)]
+pub enum Delegation {
+    SendAs = 0,
+    SendOnBehalf = 1,
+    ReadMailbox = 2,
+    ManageMailbox = 3,
+    None = 4,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DelegatedAccount {
+    pub account_id: u32,
+    pub name: String,
+    pub emails: Vec<String>,
+    pub rights: Bitmap<Delegation>,
+}
+
+impl Server {
+    /// Returns the principals an account has delegated rights to.
+    pub async fn get_delegates(
+        &self,
+        account_id: u32,
+    ) -> trc::Result<Vec<(u32, Bitmap<Delegation>)>> {
+        self.store()
+            .acl_grants(account_id, Collection::Principal.into(), account_id)
+            .await
+            .caused_by(trc::location!())
+            .map(|grants| {
+                grants
+                    .into_iter()
+                    .map(|(delegate_id, rights)| (delegate_id, Bitmap::from(rights)))
+                    .collect()
+            })
+    }
+
+    /// Returns the accounts that have delegated rights to a principal.
+    pub async fn get_delegators(&self, account_id: u32) -> trc::Result<Vec<DelegatedAccount>> {
+        let mut delegators = Vec::new();
+        for acl_item in self
+            .store()
+            .acl_query(AclQuery::HasAccess {
+                grant_account_id: account_id,
+            })
+            .await
+            .caused_by(trc::location!())?
+        {
+            if acl_item.to_collection != u8::from(Collection::Principal)
+                || acl_item.to_account_id != acl_item.to_document_id
+            {
+                continue;
+            }
+
+            if let Some(mut principal) = self
+                .directory()
+                .query(QueryBy::Id(acl_item.to_account_id), false)
+                .await
+                .caused_by(trc::location!())?
+            {
+                delegators.push(DelegatedAccount {
+                    account_id: acl_item.to_account_id,
+                    name: principal.take_str(PrincipalField::Name).unwrap_or_default(),
+                    emails: principal
+                        .take_str_array(PrincipalField::Emails)
+                        .unwrap_or_default(),
+                    rights: Bitmap::from(acl_item.permissions),
+                });
+            }
+        }
+
+        Ok(delegators)
+    }
+
+    /// Grants (or revokes, when `rights` is empty) delegated rights on an account.
+    pub async fn set_delegation(
+        &self,
+        account_id: u32,
+        delegate_id: u32,
+        rights: Bitmap<Delegation>,
+    ) -> trc::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Principal)
+            .update_document(account_id);
+        batch.ops.push(Operation::acl(
+            delegate_id,
+            (!rights.is_empty()).then(|| rights.bitmap.serialize()),
+        ));
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        // Refresh the delegate's access token
+        self.invalidate_access_token(delegate_id);
+
+        Ok(())
+    }
+}
+
+impl AccessToken {
+    /// Returns the delegated account that owns an address, if the principal
+    /// has been granted rights to send from it.
+    pub fn delegated_sender(&self, address: &str) -> Option<&DelegatedAccount> {
+        self.delegated_from.iter().find(|account| {
+            (account.rights.contains(Delegation::SendAs)
+                || account.rights.contains(Delegation::SendOnBehalf))
+                && account
+                    .emails
+                    .iter()
+                    .any(|e| e == address || (e.starts_with('@') && address.ends_with(e.as_str())))
+        })
+    }
+
+    /// Returns the rights a principal has on an account's mailboxes as a
+    /// result of delegation.
+    pub fn delegated_acl(&self, account_id: u32) -> Bitmap<Acl> {
+        match self
+            .delegated_from
+            .iter()
+            .find(|account| account.account_id == account_id)
+        {
+            Some(account) if account.rights.contains(Delegation::ManageMailbox) => {
+                let mut acl = Bitmap::all();
+                acl.remove(Acl::Administer);
+                acl
+            }
+            Some(account) if account.rights.contains(Delegation::ReadMailbox) => {
+                Bitmap::from_iter([Acl::Read, Acl::ReadItems])
+            }
+            _ => Bitmap::new(),
+        }
+    }
+
+    pub fn has_delegation(&self, account_id: u32, right: Delegation) -> bool {
+        self.delegated_from
+            .iter()
+            .any(|account| account.account_id == account_id && account.rights.contains(right))
+    }
+}
+
+impl BitmapItem for Delegation {
+    fn max() -> u64 {
+        Delegation::None as u64
+    }
+
+    fn is_valid(&self) -> bool {
+        !matches!(self, Delegation::None)
+    }
+}
+
+impl From<Delegation> for u64 {
+    fn from(value: Delegation) -> Self {
+        value as u64
+    }
+}
+
+impl From<u64> for Delegation {
+    fn from(value: u64) -> Self {
+        match value {
+            0 => Delegation::SendAs,
+            1 => Delegation::SendOnBehalf,
+            2 => Delegation::ReadMailbox,
+            3 => Delegation::ManageMailbox,
+            _ => Delegation::None,
+        }
+    }
+}
@@ -0,0 +1,356 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use directory::{
+    backend::internal::{PrincipalField, SpecialSecrets},
+    Directory, Principal, QueryBy,
+};
+use mail_builder::{headers::HeaderType, mime::make_boundary, MessageBuilder};
+use mail_send::Credentials;
+use store::{
+    blake3,
+    rand::{distributions::Alphanumeric, thread_rng, Rng},
+    write::now,
+};
+use tokio::sync::oneshot;
+use trc::AddContext;
+
+use crate::{
+    config::network::LoginProtection,
+    ipc::{DeliveryEvent, IngestMessage},
+    Server,
+};
+
+use super::{AuthRequest, CredentialsUsername};
+
+const DEVICE_TOKEN_LEN: usize = 43;
+
+impl Server {
+    /// Rejects locked out logins and delays repeated failures, trusted devices are exempt.
+    /// Failures are counted per login, client address and client fingerprint so that
+    /// failed attempts from elsewhere cannot lock the legitimate user out.
+    pub(crate) async fn check_login_protection(&self, req: &AuthRequest<'_>) -> trc::Result<()> {
+        if let (Some(protection), Some(login)) =
+            (&self.core.network.login_protection, req.credentials.login())
+        {
+            if self.is_trusted_device(req, login).await? {
+                return Ok(());
+            }
+
+            if self
+                .lookup_store()
+                .key_exists(attempt_key("ll", req, login))
+                .await
+                .caused_by(trc::location!())?
+            {
+                return Err(trc::SecurityEvent::AuthenticationLockout
+                    .into_err()
+                    .ctx(trc::Key::RemoteIp, req.remote_ip)
+                    .ctx(trc::Key::AccountName, login.to_string()));
+            }
+
+            let failures = self
+                .lookup_store()
+                .counter_get(attempt_key("lf", req, login))
+                .await
+                .caused_by(trc::location!())?
+                .max(0) as u64;
+            if failures >= protection.delay_after {
+                tokio::time::sleep(protection.delay(failures)).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn login_protection_failure(
+        &self,
+        req: &AuthRequest<'_>,
+        directory: &Directory,
+    ) -> trc::Result<()> {
+        if let (Some(protection), Some(login)) =
+            (&self.core.network.login_protection, req.credentials.login())
+        {
+            let failures = self
+                .lookup_store()
+                .counter_incr(
+                    attempt_key("lf", req, login),
+                    1,
+                    protection.window.into(),
+                    true,
+                )
+                .await
+                .caused_by(trc::location!())?;
+
+            // Every failure past the threshold locks the login again, for longer each time
+            if failures >= protection.lockout_after as i64 {
+                let level = self
+                    .lookup_store()
+                    .counter_incr(
+                        attempt_key("lv", req, login),
+                        1,
+                        protection
+                            .window
+                            .max(protection.lockout_duration_max)
+                            .into(),
+                        true,
+                    )
+                    .await
+                    .caused_by(trc::location!())?;
+                let duration = protection.lockout_duration(level.max(1) as u64);
+                self.lookup_store()
+                    .key_set(attempt_key("ll", req, login), vec![1], duration.into())
+                    .await
+                    .caused_by(trc::location!())?;
+
+                trc::event!(
+                    Security(trc::SecurityEvent::AuthenticationLockout),
+                    AccountName = login.to_string(),
+                    RemoteIp = req.remote_ip,
+                    SpanId = req.session_id,
+                    Total = failures,
+                    Expires = trc::Value::Timestamp(now() + duration),
+                );
+
+                // Notify the account owner at most once per maximum lockout duration
+                if let Some(from) = &protection.notify_from {
+                    if self
+                        .lookup_store()
+                        .counter_incr(
+                            login_key("ln", login),
+                            1,
+                            protection.lockout_duration_max.into(),
+                            true,
+                        )
+                        .await
+                        .caused_by(trc::location!())?
+                        == 1
+                    {
+                        self.send_lockout_notification(req, login, from, directory)
+                            .await;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn login_protection_success(
+        &self,
+        req: &AuthRequest<'_>,
+        principal: &Principal,
+    ) -> trc::Result<()> {
+        if let (Some(protection), Some(login)) =
+            (&self.core.network.login_protection, req.credentials.login())
+        {
+            let failure_key = attempt_key("lf", req, login);
+            if self
+                .lookup_store()
+                .counter_get(failure_key.clone())
+                .await
+                .caused_by(trc::location!())?
+                > 0
+            {
+                for key in [failure_key, attempt_key("lv", req, login)] {
+                    self.lookup_store()
+                        .counter_delete(key)
+                        .await
+                        .caused_by(trc::location!())?;
+                }
+            }
+
+            // Devices that completed a second factor are trusted for a while
+            if let (Some(issued), Credentials::Plain { secret, .. }) =
+                (req.issued_device_token, &req.credentials)
+            {
+                if issued.get().is_none()
+                    && has_totp_code(secret)
+                    && principal
+                        .iter_str(PrincipalField::Secrets)
+                        .any(|secret| secret.is_otp_auth())
+                {
+                    let token = thread_rng()
+                        .sample_iter(Alphanumeric)
+                        .take(DEVICE_TOKEN_LEN)
+                        .map(char::from)
+                        .collect::<String>();
+                    self.lookup_store()
+                        .key_set(
+                            trusted_device_key(self, login, &token),
+                            vec![1],
+                            protection.trusted_device_expiry.into(),
+                        )
+                        .await
+                        .caused_by(trc::location!())?;
+                    let _ = issued.set(token);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn is_trusted_device(&self, req: &AuthRequest<'_>, login: &str) -> trc::Result<bool> {
+        match &req.device_token {
+            Some(token) if token.len() == DEVICE_TOKEN_LEN => self
+                .lookup_store()
+                .key_exists(trusted_device_key(self, login, token))
+                .await
+                .caused_by(trc::location!()),
+            _ => Ok(false),
+        }
+    }
+
+    async fn send_lockout_notification(
+        &self,
+        req: &AuthRequest<'_>,
+        login: &str,
+        from: &str,
+        directory: &Directory,
+    ) {
+        let rcpt = match directory.query(QueryBy::Name(login), false).await {
+            Ok(Some(principal)) => {
+                if let Some(email) = principal.iter_str(PrincipalField::Emails).next() {
+                    email.to_string()
+                } else {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(err) => {
+                trc::error!(err
+                    .span_id(req.session_id)
+                    .details("Failed to obtain lockout notification recipients")
+                    .caused_by(trc::location!()));
+                return;
+            }
+        };
+
+        let message = MessageBuilder::new()
+            .from(("Account Security", from))
+            .to(rcpt.as_str())
+            .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+            .message_id(format!("<{}@lockout>", make_boundary(".")))
+            .subject("Your account has been temporarily locked")
+            .text_body(format!(
+                concat!(
+                    "Logins to your account {} have been temporarily locked after ",
+                    "repeated authentication failures from {}.\r\n\r\n",
+                    "If these attempts were not made by you, consider changing ",
+                    "your password and enabling two-factor authentication.\r\n"
+                ),
+                login, req.remote_ip
+            ))
+            .write_to_vec()
+            .unwrap_or_default();
+
+        let server = self.clone();
+        let from = from.to_string();
+        let session_id = req.session_id;
+        tokio::spawn(async move {
+            if let Err(err) = server
+                .deliver_notification(from, vec![rcpt], message, session_id)
+                .await
+            {
+                trc::error!(err
+                    .span_id(session_id)
+                    .details("Failed to deliver lockout notification"));
+            }
+        });
+    }
+
+    async fn deliver_notification(
+        &self,
+        sender_address: String,
+        recipients: Vec<String>,
+        message: Vec<u8>,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        let message_size = message.len();
+        let message_blob = self
+            .put_temporary_blob(&message)
+            .await
+            .caused_by(trc::location!())?;
+
+        let (result_tx, result_rx) = oneshot::channel();
+        self.inner
+            .ipc
+            .delivery_tx
+            .send(DeliveryEvent::Ingest {
+                message: IngestMessage {
+                    sender_address,
+                    recipients,
+                    message_blob,
+                    message_size,
+                    session_id,
+                },
+                result_tx,
+            })
+            .await
+            .map_err(|_| {
+                trc::EventType::Server(trc::ServerEvent::ThreadError)
+                    .into_err()
+                    .details("Delivery channel closed")
+                    .caused_by(trc::location!())
+            })?;
+        let _ = result_rx.await;
+
+        Ok(())
+    }
+}
+
+impl LoginProtection {
+    pub fn delay(&self, failures: u64) -> Duration {
+        let exp = failures.saturating_sub(self.delay_after).min(16) as u32;
+        self.delay_base
+            .saturating_mul(1u32 << exp)
+            .min(self.delay_max)
+    }
+
+    pub fn lockout_duration(&self, level: u64) -> u64 {
+        let exp = level.saturating_sub(1).min(16) as u32;
+        self.lockout_duration
+            .saturating_mul(1u64 << exp)
+            .min(self.lockout_duration_max)
+    }
+}
+
+fn login_key(prefix: &str, login: &str) -> Vec<u8> {
+    format!("{prefix}:{login}").into_bytes()
+}
+
+fn attempt_key(prefix: &str, req: &AuthRequest<'_>, login: &str) -> Vec<u8> {
+    let fingerprint = req
+        .fingerprint
+        .as_deref()
+        .map(|fingerprint| blake3::hash(fingerprint.as_bytes()).to_hex()[..16].to_string())
+        .unwrap_or_default();
+    format!("{prefix}:{login}:{}:{fingerprint}", req.remote_ip).into_bytes()
+}
+
+/// Only a keyed hash of the device token is stored, bound to the login it was issued for.
+fn trusted_device_key(server: &Server, login: &str, token: &str) -> Vec<u8> {
+    let key = blake3::derive_key(
+        "login trusted device",
+        server.core.oauth.oauth_key.as_bytes(),
+    );
+    let mut hasher = blake3::Hasher::new_keyed(&key);
+    hasher.update(login.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(token.as_bytes());
+    format!("lt:{}", hasher.finalize().to_hex()).into_bytes()
+}
+
+fn has_totp_code(secret: &str) -> bool {
+    secret.rsplit_once('$').is_some_and(|(code, token)| {
+        !code.is_empty()
+            && (6..=8).contains(&token.len())
+            && token.as_bytes().iter().all(|b| b.is_ascii_digit())
+    })
+}
@@ -4,7 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{net::IpAddr, sync::Arc, time::Instant};
+use std::{
+    net::IpAddr,
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
 
 use directory::{
     core::secret::verify_secret_hash, Directory, Permission, Permissions, Principal, QueryBy,
@@ -16,7 +20,12 @@ use utils::map::{bitmap::Bitmap, ttl_dashmap::TtlMap, vec_map::VecMap};
 
 use crate::Server;
 
+use self::delegation::DelegatedAccount;
+
 pub mod access_token;
+pub mod alias;
+pub mod delegation;
+pub mod lockout;
 pub mod oauth;
 pub mod roles;
 pub mod sasl;
@@ -32,6 +41,7 @@ pub struct AccessToken {
     pub quota: u64,
     pub permissions: Permissions,
     pub tenant: Option<TenantInfo>,
+    pub delegated_from: Vec<DelegatedAccount>,
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -53,6 +63,9 @@ pub struct AuthRequest<'x> {
     remote_ip: IpAddr,
     return_member_of: bool,
     directory: Option<&'x Directory>,
+    device_token: Option<String>,
+    issued_device_token: Option<&'x OnceLock<String>>,
+    fingerprint: Option<String>,
 }
 
 impl Server {
@@ -102,6 +115,9 @@ impl Server {
         req: &AuthRequest<'_>,
         directory: &Directory,
     ) -> trc::Result<Principal> {
+        // Reject locked out accounts before verifying credentials
+        self.check_login_protection(req).await?;
+
         // First try to authenticate the user against the default directory
         let result = match directory
             .query(QueryBy::Credentials(&req.credentials), req.return_member_of)
@@ -115,6 +131,8 @@ impl Server {
                     SpanId = req.session_id,
                 );
 
+                self.login_protection_success(req, &principal).await?;
+
                 return Ok(principal);
             }
             Ok(None) => Ok(()),
@@ -175,6 +193,8 @@ impl Server {
         if let Err(err) = result {
             Err(err)
         } else if self.has_auth_fail2ban() {
+            self.login_protection_failure(req, directory).await?;
+
             let login = req.credentials.login();
             if self.is_auth_fail2banned(req.remote_ip, login).await? {
                 Err(trc::SecurityEvent::AuthenticationBan
@@ -187,6 +207,7 @@ impl Server {
                     .ctx_opt(trc::Key::AccountName, login.map(|s| s.to_string())))
             }
         } else {
+            self.login_protection_failure(req, directory).await?;
             Err(trc::AuthEvent::Failed
                 .ctx(trc::Key::RemoteIp, req.remote_ip)
                 .ctx_opt(
@@ -217,6 +238,9 @@ impl<'x> AuthRequest<'x> {
             remote_ip,
             return_member_of: true,
             directory: None,
+            device_token: None,
+            issued_device_token: None,
+            fingerprint: None,
         }
     }
 
@@ -245,6 +269,24 @@ impl<'x> AuthRequest<'x> {
         self.directory = Some(directory);
         self
     }
+
+    /// Presents the token of a trusted device, if any, and provides where to store the
+    /// token issued after a successful login with a second factor.
+    pub fn with_trusted_device(
+        mut self,
+        device_token: Option<String>,
+        issued_device_token: &'x OnceLock<String>,
+    ) -> Self {
+        self.device_token = device_token;
+        self.issued_device_token = Some(issued_device_token);
+        self
+    }
+
+    /// Identifies the client software, login failures are tracked separately for each one.
+    pub fn with_fingerprint(mut self, fingerprint: Option<String>) -> Self {
+        self.fingerprint = fingerprint;
+        self
+    }
 }
 
 pub(crate) trait CredentialsUsername {
@@ -6,12 +6,13 @@
 
 use std::time::SystemTime;
 
-use directory::{backend::internal::PrincipalField, QueryBy};
+use directory::{backend::internal::PrincipalField, DirectoryInner, QueryBy};
 use mail_builder::encoders::base64::base64_encode;
 use mail_parser::decoders::base64::base64_decode;
 use store::{
     blake3,
     rand::{thread_rng, Rng},
+    write::DirectoryClass,
 };
 use trc::AddContext;
 use utils::codec::leb128::{Leb128Iterator, Leb128Vec};
@@ -46,9 +47,10 @@ impl Server {
                 .details("Client id too long"));
         }
 
-        // Include password hash if expiration is over 1 hour
-        let password_hash = if expiry_in > 3600 {
-            self.password_hash(account_id)
+        // Include the credential revision if expiration is over 1 hour, so that
+        // changing the password revokes long-lived tokens
+        let credential_revision = if expiry_in > 3600 {
+            self.credential_revision(account_id)
                 .await
                 .caused_by(trc::location!())?
         } else {
@@ -61,7 +63,7 @@ impl Server {
             grant_type.as_str(),
             client_id,
             account_id,
-            password_hash
+            credential_revision
         );
 
         // Set expiration time
@@ -73,8 +75,8 @@ impl Server {
 
         // Calculate nonce
         let mut hasher = blake3::Hasher::new();
-        if !password_hash.is_empty() {
-            hasher.update(password_hash.as_bytes());
+        if !credential_revision.is_empty() {
+            hasher.update(credential_revision.as_bytes());
         }
         hasher.update(grant_type.as_str().as_bytes());
         hasher.update(issued_at.to_be_bytes().as_slice());
@@ -155,9 +157,9 @@ impl Server {
                 .details("Invalid grant type"));
         }
 
-        // Obtain password hash
-        let password_hash = if expiry - issued_at > 3600 {
-            self.password_hash(account_id)
+        // Obtain credential revision
+        let credential_revision = if expiry - issued_at > 3600 {
+            self.credential_revision(account_id)
                 .await
                 .map_err(|err| trc::AuthEvent::Error.into_err().ctx(trc::Key::Details, err))?
         } else {
@@ -171,13 +173,13 @@ impl Server {
             grant_type.as_str(),
             client_id,
             account_id,
-            password_hash
+            credential_revision
         );
 
         // Calculate nonce
         let mut hasher = blake3::Hasher::new();
-        if !password_hash.is_empty() {
-            hasher.update(password_hash.as_bytes());
+        if !credential_revision.is_empty() {
+            hasher.update(credential_revision.as_bytes());
         }
         hasher.update(grant_type.as_str().as_bytes());
         hasher.update(issued_at.to_be_bytes().as_slice());
@@ -215,9 +217,13 @@ impl Server {
         })
     }
 
-    pub async fn password_hash(&self, account_id: u32) -> trc::Result<String> {
+    /// Returns the value that tokens valid for over an hour are bound to. The
+    /// internal directory keeps a revision that is only advanced by password
+    /// changes, other directories never re-hash so their stored hash is used.
+    pub async fn credential_revision(&self, account_id: u32) -> trc::Result<String> {
         if account_id != u32::MAX {
-            self.core
+            let mut principal = self
+                .core
                 .storage
                 .directory
                 .query(QueryBy::Id(account_id), false)
@@ -227,7 +233,17 @@ impl Server {
                     trc::AuthEvent::Error
                         .into_err()
                         .details("Account no longer exists")
-                })?
+                })?;
+
+            if let DirectoryInner::Internal(store) = &self.core.storage.directory.store {
+                return store
+                    .get_counter(DirectoryClass::CredentialRevision(account_id))
+                    .await
+                    .caused_by(trc::location!())
+                    .map(|revision| revision.to_string());
+            }
+
+            principal
                 .take_str_array(PrincipalField::Secrets)
                 .unwrap_or_default()
                 .into_iter()
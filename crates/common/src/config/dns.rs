@@ -0,0 +1,193 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use ahash::AHashMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dns_update::{providers::rfc2136::DnsAddress, DnsUpdater, TsigAlgorithm};
+use utils::config::Config;
+
+use crate::dns::{route53::Route53, DnsProvider, DnsProviders, DnsProvisioning};
+
+impl DnsProviders {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut providers = AHashMap::new();
+
+        for provider_id in config
+            .sub_keys("dns-provider", ".provider")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            let provider_id = provider_id.as_str();
+            let provider =
+                if config.value(("dns-provider", provider_id, "provider")) == Some("route53") {
+                    build_route53(config, provider_id).map(DnsProvider::Route53)
+                } else {
+                    build_dns_updater(config, "dns-provider", provider_id).map(DnsProvider::Updater)
+                };
+
+            if let Some(provider) = provider {
+                providers.insert(provider_id.to_string(), provider);
+            }
+        }
+
+        // Parse domain provisioning settings
+        let provisioning = config
+            .value("dns-provisioning.provider")
+            .map(|s| s.trim().to_string())
+            .and_then(|provider| {
+                if providers.contains_key(&provider) {
+                    Some(DnsProvisioning {
+                        provider,
+                        ttl: config
+                            .property_or_default::<Duration>("dns-provisioning.ttl", "1h")
+                            .unwrap_or_else(|| Duration::from_secs(3600))
+                            .as_secs() as u32,
+                        propagation_timeout: config
+                            .property_or_default("dns-provisioning.propagation-timeout", "15m")
+                            .unwrap_or_else(|| Duration::from_secs(15 * 60)),
+                        polling_interval: config
+                            .property_or_default("dns-provisioning.polling-interval", "30s")
+                            .unwrap_or_else(|| Duration::from_secs(30)),
+                    })
+                } else {
+                    config.new_parse_error(
+                        "dns-provisioning.provider",
+                        format!("DNS provider {provider:?} not found"),
+                    );
+                    None
+                }
+            });
+
+        DnsProviders {
+            providers,
+            provisioning,
+        }
+    }
+}
+
+fn build_route53(config: &mut Config, id: &str) -> Option<Route53> {
+    let timeout = config
+        .property_or_default(("dns-provider", id, "timeout"), "30s")
+        .unwrap_or_else(|| Duration::from_secs(30));
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .danger_accept_invalid_certs(
+            config
+                .property_or_default(("dns-provider", id, "allow-invalid-certs"), "false")
+                .unwrap_or_default(),
+        )
+        .build()
+        .map_err(|err| {
+            config.new_build_error(
+                ("dns-provider", id, "provider"),
+                format!("Failed to create Route53 DNS updater: {err}"),
+            )
+        })
+        .ok()?;
+
+    let access_key = config
+        .value_require(("dns-provider", id, "access-key"))?
+        .trim()
+        .to_string();
+    let secret_key = config
+        .value_require(("dns-provider", id, "secret-key"))?
+        .trim()
+        .to_string();
+    let [session_token, zone_id, endpoint, region] =
+        ["session-token", "zone-id", "endpoint", "region"].map(|key| {
+            config
+                .value(("dns-provider", id, key))
+                .map(|s| s.trim().to_string())
+        });
+
+    Route53::new(
+        client,
+        access_key,
+        secret_key,
+        session_token,
+        zone_id,
+        endpoint,
+        region,
+    )
+    .into()
+}
+
+#[allow(clippy::unnecessary_to_owned)]
+pub(crate) fn build_dns_updater(config: &mut Config, prefix: &str, id: &str) -> Option<DnsUpdater> {
+    match config.value_require((prefix, id, "provider"))? {
+        "rfc2136-tsig" => {
+            let algorithm: TsigAlgorithm = config
+                .value_require((prefix, id, "tsig-algorithm"))?
+                .parse()
+                .map_err(|_| {
+                    config.new_parse_error((prefix, id, "tsig-algorithm"), "Invalid algorithm")
+                })
+                .ok()?;
+            let key = STANDARD
+                .decode(config.value_require((prefix, id, "secret"))?.trim())
+                .map_err(|_| {
+                    config.new_parse_error((prefix, id, "secret"), "Failed to base64 decode secret")
+                })
+                .ok()?;
+            let host = config.property_require::<IpAddr>((prefix, id, "host"))?;
+            let port = config
+                .property_or_default::<u16>((prefix, id, "port"), "53")
+                .unwrap_or(53);
+            let addr = if config.value((prefix, id, "protocol")) == Some("tcp") {
+                DnsAddress::Tcp(SocketAddr::new(host, port))
+            } else {
+                DnsAddress::Udp(SocketAddr::new(host, port))
+            };
+
+            DnsUpdater::new_rfc2136_tsig(
+                addr,
+                config
+                    .value_require((prefix, id, "key"))?
+                    .trim()
+                    .to_string(),
+                key,
+                algorithm,
+            )
+            .map_err(|err| {
+                config.new_build_error(
+                    (prefix, id, "provider"),
+                    format!("Failed to create RFC2136-TSIG DNS updater: {err}"),
+                )
+            })
+            .ok()
+        }
+        "cloudflare" => {
+            let timeout = config
+                .property_or_default((prefix, id, "timeout"), "30s")
+                .unwrap_or_else(|| Duration::from_secs(30));
+
+            DnsUpdater::new_cloudflare(
+                config
+                    .value_require((prefix, id, "secret"))?
+                    .trim()
+                    .to_string(),
+                config.value((prefix, id, "user")).map(|s| s.trim()),
+                timeout.into(),
+            )
+            .map_err(|err| {
+                config.new_build_error(
+                    (prefix, id, "provider"),
+                    format!("Failed to create Cloudflare DNS updater: {err}"),
+                )
+            })
+            .ok()
+        }
+        _ => {
+            config.new_parse_error((prefix, id, "provider"), "Unsupported provider");
+            None
+        }
+    }
+}
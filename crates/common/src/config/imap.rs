@@ -20,6 +20,11 @@ pub struct ImapConfig {
 
     pub rate_requests: Option<Rate>,
     pub rate_concurrent: Option<u64>,
+
+    pub metadata_max_size: usize,
+    pub metadata_max_entries: usize,
+
+    pub shared_private_seen: bool,
 }
 
 impl ImapConfig {
@@ -49,6 +54,15 @@ impl ImapConfig {
             allow_plain_auth: config
                 .property_or_default("imap.auth.allow-plain-text", "false")
                 .unwrap_or(false),
+            metadata_max_size: config
+                .property_or_default("imap.metadata.max-size", "65536")
+                .unwrap_or(65536),
+            metadata_max_entries: config
+                .property_or_default("imap.metadata.max-entries", "100")
+                .unwrap_or(100),
+            shared_private_seen: config
+                .property_or_default("imap.shared.private-seen", "false")
+                .unwrap_or(false),
         }
     }
 }
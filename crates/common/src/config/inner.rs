@@ -7,11 +7,12 @@
 use std::{sync::Arc, time::Duration};
 
 use ahash::{AHashMap, AHashSet, RandomState};
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use dashmap::DashMap;
 use mail_send::smtp::tls::build_tls_connector;
 use nlp::bayes::cache::BayesTokenCache;
 use parking_lot::RwLock;
+use store::write::coalesce::CounterCoalescer;
 use utils::{
     config::Config,
     lru_cache::{LruCache, LruCached},
@@ -20,8 +21,9 @@ use utils::{
 };
 
 use crate::{
-    listener::blocked::BlockedIps, manager::webadmin::WebAdminManager, Data,
-    ThrottleKeyHasherBuilder, TlsConnectors,
+    listener::{blocked::BlockedIps, geoip::GeoIpSettings},
+    manager::webadmin::WebAdminManager,
+    Data, ThrottleKeyHasherBuilder, TlsConnectors,
 };
 
 use super::server::tls::{build_self_signed_cert, parse_certificates};
@@ -60,10 +62,20 @@ impl Data {
             })
             .ok()
             .map(Arc::new),
+            tls_sources: Default::default(),
+            listeners: Default::default(),
+            interactive_latency: Default::default(),
             access_tokens: TtlDashMap::with_capacity(capacity, shard_amount),
             http_auth_cache: TtlDashMap::with_capacity(capacity, shard_amount),
             blocked_ips: RwLock::new(BlockedIps::parse(config).blocked_ip_addresses),
             blocked_ips_version: 0.into(),
+            geoip: ArcSwapOption::new(GeoIpSettings::parse(config).and_then(|settings| {
+                settings
+                    .load()
+                    .map_err(|err| config.new_build_error("server.geoip.database", err))
+                    .ok()
+                    .map(Arc::new)
+            })),
             permissions: Default::default(),
             permissions_version: 0.into(),
             jmap_id_gen: id_generator.clone(),
@@ -74,6 +86,11 @@ impl Data {
                 .map(|path| WebAdminManager::new(path.into()))
                 .unwrap_or_default(),
             config_version: 0.into(),
+            read_only: config
+                .property_or_default::<bool>("server.read-only", "false")
+                .unwrap_or_default()
+                .into(),
+            fts_degraded: Default::default(),
             jmap_limiter: DashMap::with_capacity_and_hasher_and_shard_amount(
                 capacity,
                 RandomState::default(),
@@ -105,6 +122,8 @@ impl Data {
                 shard_amount,
             ),
             smtp_connectors: TlsConnectors::default(),
+            smtp_in_flight: Default::default(),
+            smtp_ip_pool_next: Default::default(),
             bayes_cache: BayesTokenCache::new(
                 config
                     .property_or_default("cache.bayes.capacity", "8192")
@@ -116,6 +135,17 @@ impl Data {
                     .property_or_default("cache.bayes.ttl.negative", "1h")
                     .unwrap_or_else(|| Duration::from_secs(3600)),
             ),
+            counter_coalescer: config
+                .property_or_default::<Option<Duration>>("lookup.coalesce.interval", "5s")
+                .unwrap_or_default()
+                .map(|interval| {
+                    CounterCoalescer::new(
+                        config
+                            .property_or_default("lookup.coalesce.max-pending", "1000")
+                            .unwrap_or(1000),
+                        interval,
+                    )
+                }),
             remote_lists: Default::default(),
         }
     }
@@ -126,10 +156,14 @@ impl Default for Data {
         Self {
             tls_certificates: Default::default(),
             tls_self_signed_cert: Default::default(),
+            tls_sources: Default::default(),
+            listeners: Default::default(),
+            interactive_latency: Default::default(),
             access_tokens: Default::default(),
             http_auth_cache: Default::default(),
             blocked_ips: Default::default(),
             blocked_ips_version: 0.into(),
+            geoip: Default::default(),
             permissions: Default::default(),
             permissions_version: 0.into(),
             remote_lists: Default::default(),
@@ -138,6 +172,8 @@ impl Default for Data {
             span_id_gen: Default::default(),
             webadmin: Default::default(),
             config_version: Default::default(),
+            read_only: Default::default(),
+            fts_degraded: Default::default(),
             jmap_limiter: Default::default(),
             imap_limiter: Default::default(),
             account_cache: LruCache::with_capacity(2048),
@@ -147,11 +183,14 @@ impl Default for Data {
             smtp_session_throttle: Default::default(),
             smtp_queue_throttle: Default::default(),
             smtp_connectors: Default::default(),
+            smtp_in_flight: Default::default(),
+            smtp_ip_pool_next: Default::default(),
             bayes_cache: BayesTokenCache::new(
                 8192,
                 Duration::from_secs(3600),
                 Duration::from_secs(3600),
             ),
+            counter_coalescer: None,
         }
     }
 }
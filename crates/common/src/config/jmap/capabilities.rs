@@ -9,7 +9,7 @@ use jmap_proto::{
     request::capability::{
         BlobCapabilities, Capabilities, Capability, CoreCapabilities, EmptyCapabilities,
         MailCapabilities, SieveAccountCapabilities, SieveSessionCapabilities,
-        SubmissionCapabilities,
+        SubmissionCapabilities, VacationResponseCapabilities,
     },
     types::type_state::DataType,
 };
@@ -95,7 +95,9 @@ impl JmapConfig {
         );
         self.capabilities.account.append(
             Capability::VacationResponse,
-            Capabilities::Empty(EmptyCapabilities::default()),
+            Capabilities::VacationResponse(VacationResponseCapabilities {
+                sieve_strategy: self.sieve_vacation_strategy.as_str(),
+            }),
         );
 
         // Add Sieve capabilities
@@ -174,5 +176,17 @@ impl JmapConfig {
             Capability::Quota,
             Capabilities::Empty(EmptyCapabilities::default()),
         );
+
+        // Add S/MIME verification capabilities
+        if self.smime_verify.is_some() {
+            self.capabilities.session.append(
+                Capability::SmimeVerify,
+                Capabilities::Empty(EmptyCapabilities::default()),
+            );
+            self.capabilities.account.append(
+                Capability::SmimeVerify,
+                Capabilities::Empty(EmptyCapabilities::default()),
+            );
+        }
     }
 }
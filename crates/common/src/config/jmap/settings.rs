@@ -6,7 +6,8 @@
 
 use std::{str::FromStr, time::Duration};
 
-use jmap_proto::request::capability::BaseCapabilities;
+use ahash::AHashMap;
+use jmap_proto::{request::capability::BaseCapabilities, types::collection::Collection};
 use mail_parser::HeaderName;
 use nlp::language::Language;
 use utils::config::{cron::SimpleCron, utils::ParseValue, Config, Rate};
@@ -14,11 +15,17 @@ use utils::config::{cron::SimpleCron, utils::ParseValue, Config, Rate};
 #[derive(Default, Clone)]
 pub struct JmapConfig {
     pub default_language: Language,
+    pub fts_fallback: bool,
+    pub fts_retry_interval: Duration,
     pub query_max_results: usize,
     pub snippet_max_results: usize,
 
     pub changes_max_results: usize,
-    pub changes_max_history: Option<Duration>,
+    pub changes_max_history: Vec<(Collection, Duration)>,
+
+    pub snapshot_retention: Duration,
+    pub snapshot_interval: Option<Duration>,
+    pub trash_retention: Option<Duration>,
 
     pub request_max_size: usize,
     pub request_max_calls: usize,
@@ -38,11 +45,16 @@ pub struct JmapConfig {
     pub mailbox_name_max_len: usize,
     pub mail_attachments_max_size: usize,
     pub mail_parse_max_items: usize,
+    pub mail_share_expiry: u64,
+    pub mail_share_max_expiry: u64,
+    pub mail_pdf_renderer: PdfRenderer,
+    pub mail_pdf_rate: Option<Rate>,
     pub mail_max_size: usize,
     pub mail_autoexpunge_after: Option<Duration>,
 
     pub sieve_max_script_name: usize,
     pub sieve_max_scripts: usize,
+    pub sieve_vacation_strategy: VacationStrategy,
 
     pub session_cache_ttl: Duration,
     pub rate_authenticated: Option<Rate>,
@@ -66,8 +78,16 @@ pub struct JmapConfig {
     pub master_user: Option<(String, String)>,
 
     pub spam_header: Option<(HeaderName<'static>, String)>,
+    pub spam_training: Option<SpamTraining>,
+    pub spam_quarantine: Option<SpamQuarantine>,
+    pub disposable_aliases: Option<DisposableAliases>,
+    pub smime_verify: Option<SmimeVerify>,
+    pub pgp_verify: Option<PgpVerify>,
     pub default_folders: Vec<DefaultFolder>,
+    pub folder_templates: Vec<FolderTemplate>,
     pub shared_folder: String,
+    pub public_folders: Option<PublicFolders>,
+    pub account_defaults: Vec<AccountDefaults>,
 
     pub http_headers: Vec<(hyper::header::HeaderName, hyper::header::HeaderValue)>,
     pub http_use_forwarded: bool,
@@ -84,11 +104,130 @@ pub struct JmapConfig {
 pub struct DefaultFolder {
     pub name: String,
     pub aliases: Vec<String>,
+    pub localized: AHashMap<String, String>,
     pub special_use: SpecialUse,
     pub subscribe: bool,
     pub create: bool,
 }
 
+/// Organization-wide folders owned by a single account and listed by IMAP
+/// under their own namespace.
+#[derive(Clone, Debug)]
+pub struct PublicFolders {
+    pub account: String,
+    pub name: String,
+}
+
+/// Folders created for accounts on the listed domains, replacing the default folders.
+#[derive(Clone, Debug)]
+pub struct FolderTemplate {
+    pub domains: Vec<String>,
+    pub folders: Vec<DefaultFolder>,
+}
+
+/// Settings applied to new accounts created on a domain or tenant, unless
+/// the principal provides its own values.
+#[derive(Clone, Debug, Default)]
+pub struct AccountDefaults {
+    pub scope: String,
+    pub quota: Option<u64>,
+    pub roles: Vec<String>,
+    pub enabled_permissions: Vec<String>,
+    pub disabled_permissions: Vec<String>,
+    pub sieve_script: Option<String>,
+    pub spam_threshold: Option<u32>,
+    pub folders: Vec<String>,
+    pub welcome: Option<WelcomeMessage>,
+}
+
+/// Message delivered to the Inbox of new accounts, the subject and body may
+/// contain `{name}`, `{display_name}`, `{email}`, `{domain}` and `{quota}`.
+#[derive(Clone, Debug)]
+pub struct WelcomeMessage {
+    pub from: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Bayes training from messages users move into or out of their Junk folder.
+#[derive(Clone, Debug)]
+pub struct SpamTraining {
+    pub account: bool,
+    pub global: bool,
+    pub store: Option<String>,
+    pub expiry: Option<u64>,
+}
+
+/// Messages scoring above `threshold` are held outside the mailbox until they
+/// are released or `retention` elapses.
+#[derive(Clone, Debug)]
+pub struct SpamQuarantine {
+    pub threshold: f64,
+    pub retention: u64,
+    pub digest: Option<QuarantineDigest>,
+}
+
+/// Periodic summary of held messages, release links are signed and point to
+/// `url`.
+#[derive(Clone, Debug)]
+pub struct QuarantineDigest {
+    pub frequency: SimpleCron,
+    pub from: String,
+    pub subject: String,
+    pub url: String,
+}
+
+/// Random aliases users can mint for their own mailbox. Aliases are created
+/// under `domain`, or under the domain of the account's primary address when
+/// unset.
+#[derive(Clone, Debug)]
+pub struct DisposableAliases {
+    pub domain: Option<String>,
+    pub max_aliases: usize,
+    pub max_expiry: Option<u64>,
+}
+
+/// S/MIME signatures are verified at ingestion, signer certificates must
+/// chain up to one of the DER encoded `trust_anchors` to be trusted.
+#[derive(Clone, Debug)]
+pub struct SmimeVerify {
+    pub trust_anchors: Vec<Vec<u8>>,
+}
+
+/// OpenPGP signatures are verified at ingestion against the armored
+/// certificates in `keyring` or the sender's Autocrypt key, and the outcome
+/// is recorded in an `Authentication-Results` header issued by `authserv_id`.
+/// When `decrypt` is set, accounts may escrow a secret key to have their
+/// encrypted messages stored in the clear.
+#[derive(Clone, Debug)]
+pub struct PgpVerify {
+    pub keyring: Vec<Vec<u8>>,
+    pub authserv_id: String,
+    pub decrypt: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum PdfRenderer {
+    #[default]
+    Disabled,
+    Builtin,
+    Command {
+        command: String,
+        arguments: Vec<String>,
+        timeout: Duration,
+    },
+}
+
+/// How a JMAP VacationResponse coexists with the active Sieve script.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum VacationStrategy {
+    /// The vacation rule runs ahead of the active script.
+    #[default]
+    Merge,
+    /// Enabling the vacation response deactivates all other scripts.
+    Replace,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum SpecialUse {
     Inbox,
@@ -101,6 +240,204 @@ pub enum SpecialUse {
     None,
 }
 
+impl DefaultFolder {
+    /// Returns the folder name for a locale such as `pt-BR`, falling back to
+    /// the primary language and then to the default name.
+    pub fn localized_name(&self, locale: Option<&str>) -> &str {
+        if let Some(locale) = locale.filter(|_| !self.localized.is_empty()) {
+            let locale = locale.to_lowercase().replace('_', "-");
+            if let Some(name) = self.localized.get(&locale).or_else(|| {
+                locale
+                    .split_once('-')
+                    .and_then(|(lang, _)| self.localized.get(lang))
+            }) {
+                return name;
+            }
+        }
+
+        &self.name
+    }
+}
+
+impl FolderTemplate {
+    fn parse(config: &mut Config, default_folders: &[DefaultFolder]) -> Vec<Self> {
+        let mut templates = Vec::new();
+        for id in config
+            .sub_keys("jmap.folder-templates", "")
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+        {
+            let prefix = format!("jmap.folder-templates.{id}");
+            let domains = config
+                .values((prefix.as_str(), "domains"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+            let (mut folders, _) = parse_folders(config, &format!("{prefix}.folders"));
+
+            // Roles missing from the template are taken from the default folders
+            for folder in default_folders {
+                if !folders.iter().any(|f| f.special_use == folder.special_use) {
+                    folders.push(folder.clone());
+                }
+            }
+
+            templates.push(FolderTemplate { domains, folders });
+        }
+
+        templates
+    }
+}
+
+fn parse_folders(config: &mut Config, prefix: &str) -> (Vec<DefaultFolder>, Option<String>) {
+    let mut folders = Vec::new();
+    let mut shared_folder = None;
+    for key in config
+        .sub_keys(prefix, ".name")
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+    {
+        match SpecialUse::parse_value(&key) {
+            Ok(SpecialUse::Shared) => {
+                if let Some(value) = config.value((prefix, key.as_str(), "name")) {
+                    shared_folder = value.to_string().into();
+                }
+            }
+            Ok(special_use) => {
+                let subscribe = config
+                    .property_or_default((prefix, key.as_str(), "subscribe"), "true")
+                    .unwrap_or(true);
+                let create = config
+                    .property_or_default((prefix, key.as_str(), "create"), "true")
+                    .unwrap_or(true)
+                    | [SpecialUse::Inbox, SpecialUse::Trash, SpecialUse::Junk]
+                        .contains(&special_use);
+                if let Some(name) = config
+                    .value((prefix, key.as_str(), "name"))
+                    .map(|name| name.trim())
+                    .filter(|name| !name.is_empty())
+                {
+                    let localized_prefix = format!("{prefix}.{key}.localized");
+                    folders.push(DefaultFolder {
+                        name: name.to_string(),
+                        aliases: config
+                            .value((prefix, key.as_str(), "aliases"))
+                            .unwrap_or_default()
+                            .split(',')
+                            .map(|s| s.trim().to_string())
+                            .filter(|s| !s.is_empty())
+                            .collect(),
+                        localized: config
+                            .sub_keys(localized_prefix.as_str(), "")
+                            .filter_map(|lang| {
+                                config
+                                    .value((localized_prefix.as_str(), lang))
+                                    .map(|name| name.trim())
+                                    .filter(|name| !name.is_empty())
+                                    .map(|name| (lang.to_lowercase(), name.to_string()))
+                            })
+                            .collect(),
+                        special_use,
+                        subscribe,
+                        create,
+                    });
+                }
+            }
+            Err(err) => {
+                config.new_parse_error(key, err);
+            }
+        }
+    }
+
+    (folders, shared_folder)
+}
+
+fn parse_changes_history(config: &mut Config) -> Vec<(Collection, Duration)> {
+    let default_history = config
+        .property_or_default::<Option<Duration>>("jmap.protocol.changes.max-history", "30d")
+        .unwrap_or_default();
+
+    // Sieve script changes are kept forever unless configured otherwise
+    [
+        (Collection::Email, default_history),
+        (Collection::Mailbox, default_history),
+        (Collection::Thread, default_history),
+        (Collection::Identity, default_history),
+        (Collection::EmailSubmission, default_history),
+        (Collection::SieveScript, None),
+    ]
+    .into_iter()
+    .filter_map(|(collection, default_history)| {
+        config
+            .property::<Option<Duration>>((
+                "jmap.protocol.changes.max-history",
+                collection.as_str(),
+            ))
+            .unwrap_or(default_history)
+            .map(|history| (collection, history))
+    })
+    .collect()
+}
+
+impl AccountDefaults {
+    fn parse(config: &mut Config) -> Vec<Self> {
+        let mut defaults = Vec::new();
+        for id in config
+            .sub_keys("account.defaults", ".scope")
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+        {
+            let id = id.as_str();
+            let scope = if let Some(scope) = config
+                .value(("account.defaults", id, "scope"))
+                .map(|scope| scope.trim().to_lowercase())
+                .filter(|scope| !scope.is_empty())
+            {
+                scope
+            } else {
+                continue;
+            };
+            let list = |key: &str| {
+                config
+                    .values(("account.defaults", id, key))
+                    .map(|(_, v)| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect::<Vec<_>>()
+            };
+
+            defaults.push(AccountDefaults {
+                roles: list("roles"),
+                enabled_permissions: list("permissions.enabled"),
+                disabled_permissions: list("permissions.disabled"),
+                folders: list("folders"),
+                quota: config.property(("account.defaults", id, "quota")),
+                sieve_script: config
+                    .value(("account.defaults", id, "sieve"))
+                    .filter(|script| !script.trim().is_empty())
+                    .map(|script| script.to_string()),
+                spam_threshold: config.property(("account.defaults", id, "spam-threshold")),
+                welcome: config
+                    .value(("account.defaults", id, "welcome.body"))
+                    .map(|body| body.to_string())
+                    .map(|body| WelcomeMessage {
+                        from: config
+                            .value(("account.defaults", id, "welcome.from"))
+                            .unwrap_or("postmaster@localhost")
+                            .to_string(),
+                        subject: config
+                            .value(("account.defaults", id, "welcome.subject"))
+                            .unwrap_or("Welcome")
+                            .to_string(),
+                        body,
+                    }),
+                scope,
+            });
+        }
+
+        defaults
+    }
+}
+
 impl JmapConfig {
     pub fn parse(config: &mut Config) -> Self {
         // Parse HTTP headers
@@ -134,53 +471,8 @@ impl JmapConfig {
             .unwrap_or_default();
 
         // Parse default folders
-        let mut default_folders = Vec::new();
-        let mut shared_folder = "Shared Folders".to_string();
-        for key in config
-            .sub_keys("jmap.folders", ".name")
-            .map(|v| v.to_string())
-            .collect::<Vec<_>>()
-        {
-            match SpecialUse::parse_value(&key) {
-                Ok(SpecialUse::Shared) => {
-                    if let Some(value) = config.value(&key) {
-                        shared_folder = value.to_string();
-                    }
-                }
-                Ok(special_use) => {
-                    let subscribe = config
-                        .property_or_default(("jmap.folders", key.as_str(), "subscribe"), "true")
-                        .unwrap_or(true);
-                    let create = config
-                        .property_or_default(("jmap.folders", key.as_str(), "create"), "true")
-                        .unwrap_or(true)
-                        | [SpecialUse::Inbox, SpecialUse::Trash, SpecialUse::Junk]
-                            .contains(&special_use);
-                    if let Some(name) = config
-                        .value(("jmap.folders", key.as_str(), "name"))
-                        .map(|name| name.trim())
-                        .filter(|name| !name.is_empty())
-                    {
-                        default_folders.push(DefaultFolder {
-                            name: name.to_string(),
-                            aliases: config
-                                .value(("jmap.folders", key.as_str(), "aliases"))
-                                .unwrap_or_default()
-                                .split(',')
-                                .map(|s| s.trim().to_string())
-                                .filter(|s| !s.is_empty())
-                                .collect(),
-                            special_use,
-                            subscribe,
-                            create,
-                        });
-                    }
-                }
-                Err(err) => {
-                    config.new_parse_error(key, err);
-                }
-            }
-        }
+        let (mut default_folders, shared_folder) = parse_folders(config, "jmap.folders");
+        let shared_folder = shared_folder.unwrap_or_else(|| "Shared Folders".to_string());
         for (special_use, name) in [
             (SpecialUse::Inbox, "Inbox"),
             (SpecialUse::Trash, "Deleted Items"),
@@ -192,12 +484,23 @@ impl JmapConfig {
                 default_folders.push(DefaultFolder {
                     name: name.to_string(),
                     aliases: Vec::new(),
+                    localized: AHashMap::new(),
                     special_use,
                     subscribe: true,
                     create: true,
                 });
             }
         }
+        let folder_templates = FolderTemplate::parse(config, &default_folders);
+        let public_folders = config.value("jmap.public-folders.account").map(|account| {
+            let name = config
+                .value("jmap.public-folders.name")
+                .unwrap_or("#public");
+            PublicFolders {
+                account: account.trim().to_string(),
+                name: name.to_string(),
+            }
+        });
 
         // Add permissive CORS headers
         if config
@@ -239,14 +542,27 @@ impl JmapConfig {
                     .unwrap_or("en"),
             )
             .unwrap_or(Language::English),
+            fts_fallback: config
+                .property_or_default("storage.full-text.fallback.enable", "true")
+                .unwrap_or(true),
+            fts_retry_interval: config
+                .property_or_default("storage.full-text.fallback.retry", "1m")
+                .unwrap_or(Duration::from_secs(60)),
             query_max_results: config
                 .property("jmap.protocol.query.max-results")
                 .unwrap_or(5000),
             changes_max_results: config
                 .property("jmap.protocol.changes.max-results")
                 .unwrap_or(5000),
-            changes_max_history: config
-                .property_or_default::<Option<Duration>>("jmap.protocol.changes.max-history", "30d")
+            changes_max_history: parse_changes_history(config),
+            snapshot_retention: config
+                .property_or_default("storage.snapshot.retention", "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400)),
+            snapshot_interval: config
+                .property_or_default::<Option<Duration>>("storage.snapshot.interval", "false")
+                .unwrap_or_default(),
+            trash_retention: config
+                .property_or_default::<Option<Duration>>("storage.trash.retention", "false")
                 .unwrap_or_default(),
             snippet_max_results: config
                 .property("jmap.protocol.search-snippet.max-results")
@@ -291,6 +607,18 @@ impl JmapConfig {
                 .unwrap_or(50000000),
             mail_max_size: config.property("jmap.email.max-size").unwrap_or(75000000),
             mail_parse_max_items: config.property("jmap.email.parse.max-items").unwrap_or(10),
+            mail_share_expiry: config
+                .property_or_default::<Duration>("jmap.email.share.expiry", "7d")
+                .unwrap_or_else(|| Duration::from_secs(7 * 86400))
+                .as_secs(),
+            mail_share_max_expiry: config
+                .property_or_default::<Duration>("jmap.email.share.max-expiry", "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+                .as_secs(),
+            mail_pdf_renderer: PdfRenderer::parse(config),
+            mail_pdf_rate: config
+                .property_or_default::<Option<Rate>>("jmap.email.pdf.rate-limit", "10/1m")
+                .unwrap_or_default(),
             mail_autoexpunge_after: config
                 .property_or_default::<Option<Duration>>("jmap.email.auto-expunge", "30d")
                 .unwrap_or_default(),
@@ -300,6 +628,9 @@ impl JmapConfig {
             sieve_max_scripts: config
                 .property("sieve.untrusted.limits.max-scripts")
                 .unwrap_or(256),
+            sieve_vacation_strategy: config
+                .property_or_default("jmap.vacation-response.strategy", "merge")
+                .unwrap_or_default(),
             capabilities: BaseCapabilities::default(),
             session_cache_ttl: config
                 .property("cache.session.ttl")
@@ -345,6 +676,11 @@ impl JmapConfig {
                         )
                     })
                 }),
+            spam_training: SpamTraining::parse(config),
+            spam_quarantine: SpamQuarantine::parse(config),
+            disposable_aliases: DisposableAliases::parse(config),
+            smime_verify: SmimeVerify::parse(config),
+            pgp_verify: PgpVerify::parse(config),
             http_use_forwarded: config
                 .property("server.http.use-x-forwarded")
                 .unwrap_or(false),
@@ -387,6 +723,9 @@ impl JmapConfig {
             }),
             default_folders,
             shared_folder,
+            public_folders,
+            folder_templates,
+            account_defaults: AccountDefaults::parse(config),
         };
 
         // Add capabilities
@@ -395,6 +734,227 @@ impl JmapConfig {
     }
 }
 
+impl SpamTraining {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("spam.bayes.user-training.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        Some(SpamTraining {
+            account: config
+                .property_or_default("spam.bayes.user-training.account", "true")
+                .unwrap_or(true),
+            global: config
+                .property_or_default("spam.bayes.user-training.global", "false")
+                .unwrap_or(false),
+            store: config
+                .value("spam.bayes.user-training.store")
+                .filter(|store| !store.is_empty())
+                .map(|store| store.to_string()),
+            expiry: config
+                .property_or_default::<Option<Duration>>("spam.bayes.user-training.expiry", "180d")
+                .unwrap_or_default()
+                .map(|expiry| expiry.as_secs()),
+        })
+    }
+}
+
+impl DisposableAliases {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("jmap.account.alias.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        Some(DisposableAliases {
+            domain: config
+                .value("jmap.account.alias.domain")
+                .filter(|domain| !domain.is_empty())
+                .map(|domain| domain.to_lowercase()),
+            max_aliases: config
+                .property_or_default("jmap.account.alias.max-aliases", "50")
+                .unwrap_or(50),
+            max_expiry: config
+                .property::<Duration>("jmap.account.alias.max-expiry")
+                .map(|expiry| expiry.as_secs()),
+        })
+    }
+}
+
+impl SmimeVerify {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("jmap.email.smime.verify", "true")
+            .unwrap_or(true)
+        {
+            return None;
+        }
+
+        let mut trust_anchors = Vec::new();
+        for (key, value) in config
+            .values("jmap.email.smime.trust-anchors")
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+        {
+            match pem::parse_many(value.as_bytes()) {
+                Ok(pems) => {
+                    trust_anchors.extend(
+                        pems.into_iter()
+                            .filter(|pem| pem.tag() == "CERTIFICATE")
+                            .map(|pem| pem.into_contents()),
+                    );
+                }
+                Err(err) => {
+                    config.new_parse_error(key, format!("Failed to parse certificate: {err}"));
+                }
+            }
+        }
+
+        Some(SmimeVerify { trust_anchors })
+    }
+}
+
+impl PgpVerify {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("jmap.email.pgp.verify", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        Some(PgpVerify {
+            keyring: config
+                .values("jmap.email.pgp.keyring")
+                .map(|(_, v)| v.as_bytes().to_vec())
+                .collect(),
+            authserv_id: config
+                .value("lookup.default.hostname")
+                .unwrap_or("localhost")
+                .to_string(),
+            decrypt: config
+                .property_or_default("jmap.email.pgp.decrypt", "false")
+                .unwrap_or(false),
+        })
+    }
+}
+
+impl SpamQuarantine {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("spam.quarantine.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        let digest = if config
+            .property_or_default("spam.quarantine.digest.enable", "true")
+            .unwrap_or(true)
+        {
+            if let Some(url) = config.value("spam.quarantine.digest.url") {
+                Some(QuarantineDigest {
+                    url: url.trim_end_matches('/').to_string(),
+                    frequency: config
+                        .property_or_default::<SimpleCron>(
+                            "spam.quarantine.digest.frequency",
+                            "0 8 *",
+                        )
+                        .unwrap_or_else(|| SimpleCron::parse_value("0 8 *").unwrap()),
+                    from: config
+                        .value("spam.quarantine.digest.from")
+                        .unwrap_or("postmaster@localhost")
+                        .to_string(),
+                    subject: config
+                        .value("spam.quarantine.digest.subject")
+                        .unwrap_or("Quarantined messages")
+                        .to_string(),
+                })
+            } else {
+                config.new_missing_property("spam.quarantine.digest.url");
+                None
+            }
+        } else {
+            None
+        };
+
+        Some(SpamQuarantine {
+            threshold: config
+                .property_or_default("spam.quarantine.threshold", "15.0")
+                .unwrap_or(15.0),
+            retention: config
+                .property_or_default::<Duration>("spam.quarantine.retention", "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+                .as_secs(),
+            digest,
+        })
+    }
+}
+
+impl PdfRenderer {
+    pub fn parse(config: &mut Config) -> Self {
+        match config
+            .value("jmap.email.pdf.renderer")
+            .unwrap_or("builtin")
+            .to_string()
+            .as_str()
+        {
+            "builtin" => PdfRenderer::Builtin,
+            "command" => {
+                if let Some(command) = config
+                    .value_require("jmap.email.pdf.command")
+                    .map(|command| command.to_string())
+                {
+                    PdfRenderer::Command {
+                        command,
+                        arguments: config
+                            .values("jmap.email.pdf.arguments")
+                            .map(|(_, argument)| argument.to_string())
+                            .collect(),
+                        timeout: config
+                            .property_or_default::<Duration>("jmap.email.pdf.timeout", "30s")
+                            .unwrap_or_else(|| Duration::from_secs(30)),
+                    }
+                } else {
+                    PdfRenderer::Disabled
+                }
+            }
+            "disable" | "disabled" => PdfRenderer::Disabled,
+            other => {
+                config.new_parse_error(
+                    "jmap.email.pdf.renderer",
+                    format!("Unknown PDF renderer {other:?}"),
+                );
+                PdfRenderer::Disabled
+            }
+        }
+    }
+}
+
+impl VacationStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VacationStrategy::Merge => "merge",
+            VacationStrategy::Replace => "replace",
+        }
+    }
+}
+
+impl ParseValue for VacationStrategy {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "merge" => Ok(VacationStrategy::Merge),
+            "replace" => Ok(VacationStrategy::Replace),
+            other => Err(format!("Unknown vacation response strategy {other:?}")),
+        }
+    }
+}
+
 impl ParseValue for SpecialUse {
     fn parse_value(value: &str) -> Result<Self, String> {
         match value {
@@ -19,8 +19,13 @@ use telemetry::Metrics;
 use utils::config::{utils::AsKey, Config};
 
 use crate::{
-    auth::oauth::config::OAuthConfig, expr::*, listener::tls::AcmeProviders,
-    manager::config::ConfigManager, Core, Network, Security,
+    auth::oauth::config::OAuthConfig,
+    dns::DnsProviders,
+    expr::*,
+    listener::{certificates::CertificateSources, tls::AcmeProviders},
+    manager::config::ConfigManager,
+    priority::StorePriority,
+    Core, Network, Security,
 };
 
 use self::{
@@ -28,6 +33,7 @@ use self::{
     storage::Storage,
 };
 
+pub mod dns;
 pub mod imap;
 pub mod inner;
 pub mod jmap;
@@ -170,6 +176,8 @@ impl Core {
             )
         }
 
+        let dns = DnsProviders::parse(config);
+
         Self {
             #[cfg(feature = "enterprise")]
             enterprise,
@@ -179,7 +187,9 @@ impl Core {
             jmap: JmapConfig::parse(config),
             imap: ImapConfig::parse(config),
             oauth: OAuthConfig::parse(config),
-            acme: AcmeProviders::parse(config),
+            acme: AcmeProviders::parse(config, &dns),
+            certificates: CertificateSources::parse(config),
+            dns,
             metrics: Metrics::parse(config),
             storage: Storage {
                 data,
@@ -189,11 +199,13 @@ impl Core {
                 directory,
                 directories: directories.directories,
                 purge_schedules: stores.purge_schedules,
+                priority: StorePriority::parse(config),
                 config: config_manager,
                 stores: stores.stores,
                 lookups: stores.lookup_stores,
                 blobs: stores.blob_stores,
                 ftss: stores.fts_stores,
+                settings: stores.settings,
             },
         }
     }
@@ -4,18 +4,79 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use crate::expr::{if_block::IfBlock, tokenizer::TokenMap};
-use utils::config::{Config, Rate};
+use std::time::Duration;
+
+use ahash::AHashMap;
+use utils::config::{ipmask::IpAddrMask, utils::ParseValue, Config, Rate};
+
+use crate::{
+    expr::{if_block::IfBlock, tokenizer::TokenMap},
+    listener::geoip::GeoIpSettings,
+};
 
 use super::*;
 
 #[derive(Clone)]
 pub struct Network {
     pub node_id: u64,
+    pub cluster_share: ClusterShare,
+    pub cluster_notify: Option<String>,
+    pub health: HealthCheck,
+    pub grace_period: Duration,
     pub security: Security,
     pub contact_form: Option<ContactForm>,
+    pub login_protection: Option<LoginProtection>,
+    pub auth_challenge: Option<AuthChallenge>,
     pub http_response_url: IfBlock,
     pub http_allowed_endpoint: IfBlock,
+    pub ip_sets: AHashMap<String, Vec<IpAddrMask>>,
+    pub geoip: Option<GeoIpSettings>,
+}
+
+/// Decisions that are broadcast to the other nodes of the cluster.
+#[derive(Clone, Default)]
+pub struct ClusterShare {
+    pub reputation: bool,
+    pub auto_ban: bool,
+}
+
+/// Settings used when checking the health of the server components.
+#[derive(Clone)]
+pub struct HealthCheck {
+    pub store_timeout: Duration,
+}
+
+#[derive(Clone)]
+pub struct LoginProtection {
+    pub window: u64,
+    pub delay_after: u64,
+    pub delay_base: Duration,
+    pub delay_max: Duration,
+    pub lockout_after: u64,
+    pub lockout_duration: u64,
+    pub lockout_duration_max: u64,
+    pub notify_from: Option<String>,
+    pub trusted_device_expiry: u64,
+}
+
+#[derive(Clone)]
+pub struct AuthChallenge {
+    pub enable: IfBlock,
+    pub threshold: Rate,
+    pub provider: ChallengeProvider,
+}
+
+#[derive(Clone)]
+pub enum ChallengeProvider {
+    ProofOfWork {
+        difficulty: u32,
+        expiry: u64,
+    },
+    Captcha {
+        verify_url: String,
+        site_key: String,
+        secret: String,
+    },
 }
 
 #[derive(Clone)]
@@ -55,13 +116,21 @@ impl Default for Network {
         Self {
             security: Default::default(),
             contact_form: None,
+            login_protection: None,
+            auth_challenge: None,
             node_id: 0,
+            cluster_share: Default::default(),
+            cluster_notify: None,
+            health: Default::default(),
+            grace_period: Duration::from_secs(30),
             http_response_url: IfBlock::new::<()>(
                 "server.http.url",
                 [],
                 "protocol + '://' + key_get('default', 'hostname') + ':' + local_port",
             ),
             http_allowed_endpoint: IfBlock::new::<()>("server.http.allowed-endpoint", [], "200"),
+            ip_sets: Default::default(),
+            geoip: None,
         }
     }
 }
@@ -108,6 +177,132 @@ impl ContactForm {
     }
 }
 
+impl LoginProtection {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default::<bool>("authentication.lockout.enable", "false")
+            .unwrap_or_default()
+        {
+            return None;
+        }
+
+        let notify_from = if config
+            .property_or_default::<bool>("authentication.lockout.notify.enable", "true")
+            .unwrap_or(true)
+        {
+            config
+                .value("authentication.lockout.notify.from")
+                .map(|from| from.to_string())
+                .or_else(|| {
+                    config
+                        .value("lookup.default.domain")
+                        .map(|domain| format!("postmaster@{domain}"))
+                })
+                .unwrap_or_else(|| "postmaster@localhost".to_string())
+                .into()
+        } else {
+            None
+        };
+
+        Some(LoginProtection {
+            window: config
+                .property_or_default::<Duration>("authentication.lockout.window", "1h")
+                .unwrap_or_else(|| Duration::from_secs(3600))
+                .as_secs(),
+            delay_after: config
+                .property_or_default("authentication.lockout.delay.after", "3")
+                .unwrap_or(3),
+            delay_base: config
+                .property_or_default::<Duration>("authentication.lockout.delay.base", "1s")
+                .unwrap_or_else(|| Duration::from_secs(1)),
+            delay_max: config
+                .property_or_default::<Duration>("authentication.lockout.delay.max", "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+            lockout_after: config
+                .property_or_default("authentication.lockout.threshold", "10")
+                .unwrap_or(10),
+            lockout_duration: config
+                .property_or_default::<Duration>("authentication.lockout.duration", "15m")
+                .unwrap_or_else(|| Duration::from_secs(900))
+                .as_secs(),
+            lockout_duration_max: config
+                .property_or_default::<Duration>("authentication.lockout.duration-max", "24h")
+                .unwrap_or_else(|| Duration::from_secs(86400))
+                .as_secs(),
+            notify_from,
+            trusted_device_expiry: config
+                .property_or_default::<Duration>(
+                    "authentication.lockout.trusted-device.expiry",
+                    "30d",
+                )
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+                .as_secs(),
+        })
+    }
+}
+
+impl AuthChallenge {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        let provider = match config
+            .value("authentication.challenge.type")
+            .unwrap_or("disable")
+            .to_string()
+            .as_str()
+        {
+            "pow" => ChallengeProvider::ProofOfWork {
+                difficulty: config
+                    .property_or_default::<u32>("authentication.challenge.pow.difficulty", "18")
+                    .unwrap_or(18)
+                    .clamp(1, 32),
+                expiry: config
+                    .property_or_default::<Duration>("authentication.challenge.pow.expiry", "5m")
+                    .unwrap_or_else(|| Duration::from_secs(300))
+                    .as_secs(),
+            },
+            typ @ ("hcaptcha" | "turnstile") => ChallengeProvider::Captcha {
+                verify_url: config
+                    .value("authentication.challenge.captcha.verify-url")
+                    .unwrap_or(if typ == "hcaptcha" {
+                        "https://api.hcaptcha.com/siteverify"
+                    } else {
+                        "https://challenges.cloudflare.com/turnstile/v0/siteverify"
+                    })
+                    .to_string(),
+                site_key: config
+                    .value_require("authentication.challenge.captcha.site-key")?
+                    .to_string(),
+                secret: config
+                    .value_require("authentication.challenge.captcha.secret")?
+                    .to_string(),
+            },
+            "disable" => return None,
+            other => {
+                config.new_parse_error(
+                    "authentication.challenge.type",
+                    format!("Unknown challenge type {other:?}"),
+                );
+                return None;
+            }
+        };
+
+        Some(AuthChallenge {
+            enable: IfBlock::try_parse(
+                config,
+                "authentication.challenge.enable",
+                &TokenMap::default().with_variables(HTTP_VARS),
+            )
+            .unwrap_or_else(|| IfBlock::new::<()>("authentication.challenge.enable", [], "true")),
+            threshold: config
+                .property_or_default::<Rate>("authentication.challenge.threshold", "5/1h")
+                .unwrap_or(Rate {
+                    requests: 5,
+                    period: Duration::from_secs(3600),
+                }),
+            provider,
+        })
+    }
+}
+
 impl FieldOrDefault {
     pub fn parse(config: &mut Config, key: &str, default: &str) -> Self {
         FieldOrDefault {
@@ -120,12 +315,86 @@ impl FieldOrDefault {
     }
 }
 
+impl ClusterShare {
+    pub fn parse(config: &mut Config) -> Self {
+        if config.value("cluster.bind-addr").is_none() {
+            return ClusterShare::default();
+        }
+
+        ClusterShare {
+            reputation: config
+                .property_or_default("cluster.share.reputation", "false")
+                .unwrap_or_default(),
+            auto_ban: config
+                .property_or_default("cluster.share.auto-ban", "true")
+                .unwrap_or(true),
+        }
+    }
+}
+
+/// Returns the channel used to publish cache invalidations through the data store.
+fn parse_cluster_notify(config: &mut Config) -> Option<String> {
+    if !config
+        .property_or_default::<bool>("cluster.notify.enable", "false")
+        .unwrap_or_default()
+    {
+        return None;
+    }
+
+    let channel = config
+        .value("cluster.notify.channel")
+        .unwrap_or("cache_invalidation")
+        .to_string();
+    if !channel.is_empty()
+        && channel.len() < 64
+        && channel
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+    {
+        Some(channel)
+    } else {
+        config.new_parse_error(
+            "cluster.notify.channel",
+            "Channel names may only contain letters, digits and underscores",
+        );
+        None
+    }
+}
+
+impl HealthCheck {
+    pub fn parse(config: &mut Config) -> Self {
+        HealthCheck {
+            store_timeout: config
+                .property_or_default::<Duration>("server.health.store-timeout", "1s")
+                .unwrap_or_else(|| Duration::from_secs(1)),
+        }
+    }
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            store_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
 impl Network {
     pub fn parse(config: &mut Config) -> Self {
         let mut network = Network {
             node_id: config.property("cluster.node-id").unwrap_or_default(),
+            cluster_share: ClusterShare::parse(config),
+            cluster_notify: parse_cluster_notify(config),
+            health: HealthCheck::parse(config),
+            grace_period: config
+                .property_or_default("server.shutdown.grace-period", "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
             security: Security::parse(config),
             contact_form: ContactForm::parse(config),
+            login_protection: LoginProtection::parse(config),
+            auth_challenge: AuthChallenge::parse(config),
+            ip_sets: parse_ip_sets(config),
+            geoip: GeoIpSettings::parse(config),
             ..Default::default()
         };
         let token_map = &TokenMap::default().with_variables(HTTP_VARS);
@@ -145,3 +414,29 @@ impl Network {
         network
     }
 }
+
+/// Named lists of networks that can be tested with the `ip_in_set` function.
+fn parse_ip_sets(config: &mut Config) -> AHashMap<String, Vec<IpAddrMask>> {
+    let mut ip_sets = AHashMap::new();
+
+    for name in config
+        .sub_keys("server.ip-set", "")
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>()
+    {
+        let mut networks = Vec::new();
+        for (key, value) in config
+            .values(("server.ip-set", name.as_str()))
+            .map(|(key, value)| (key.to_string(), IpAddrMask::parse_value(value)))
+            .collect::<Vec<_>>()
+        {
+            match value {
+                Ok(network) => networks.push(network),
+                Err(err) => config.new_parse_error(key, err),
+            }
+        }
+        ip_sets.insert(name, networks);
+    }
+
+    ip_sets
+}
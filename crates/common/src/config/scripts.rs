@@ -6,6 +6,7 @@
 
 use std::{
     collections::HashSet,
+    path::PathBuf,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -13,7 +14,7 @@ use std::{
 use ahash::AHashMap;
 use sieve::{compiler::grammar::Capability, Compiler, Runtime, Sieve};
 use store::Stores;
-use utils::config::Config;
+use utils::{config::Config, suffixlist::PublicSuffix};
 
 use crate::scripts::{
     functions::{register_functions_trusted, register_functions_untrusted},
@@ -32,6 +33,14 @@ pub struct Scripting {
     pub sign: IfBlock,
     pub trusted_scripts: AHashMap<String, Arc<Sieve>>,
     pub untrusted_scripts: AHashMap<String, Arc<Sieve>>,
+    pub public_suffix: Option<Arc<PublicSuffix>>,
+    pub spam_export: Option<SpamExport>,
+}
+
+/// File receiving the spam filter decisions exported for offline retraining.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpamExport {
+    pub path: PathBuf,
 }
 
 #[derive(Clone)]
@@ -326,6 +335,19 @@ impl Scripting {
             }
         }
 
+        // Parse public suffix list, falling back to the bundled list
+        let public_suffix = if config
+            .values("sieve.trusted.public-suffix")
+            .next()
+            .is_some()
+        {
+            Some(PublicSuffix::parse(config, "sieve.trusted.public-suffix").await)
+                .filter(|list| !list.suffixes.is_empty())
+                .map(Arc::new)
+        } else {
+            None
+        };
+
         let token_map = TokenMap::default().with_variables(SMTP_RCPT_TO_VARS);
 
         Scripting {
@@ -360,6 +382,10 @@ impl Scripting {
             ),
             untrusted_scripts,
             trusted_scripts,
+            public_suffix,
+            spam_export: config.value("spam.export.path").map(|path| SpamExport {
+                path: PathBuf::from(path),
+            }),
         }
     }
 }
@@ -387,6 +413,8 @@ impl Default for Scripting {
             ),
             untrusted_scripts: AHashMap::new(),
             trusted_scripts: AHashMap::new(),
+            public_suffix: None,
+            spam_export: None,
         }
     }
 }
@@ -403,6 +431,8 @@ impl Clone for Scripting {
             sign: self.sign.clone(),
             trusted_scripts: self.trusted_scripts.clone(),
             untrusted_scripts: self.untrusted_scripts.clone(),
+            public_suffix: self.public_suffix.clone(),
+            spam_export: self.spam_export.clone(),
         }
     }
 }
@@ -195,6 +195,19 @@ impl Listeners {
         for (_, network) in config.properties(proxy_keys) {
             proxy_networks.push(network);
         }
+        let proxy_required = config
+            .property_or_else(
+                ("server.listener", id, "proxy.required"),
+                "server.proxy.required",
+                "false",
+            )
+            .unwrap_or(false);
+        if proxy_required && proxy_networks.is_empty() {
+            config.new_build_error(
+                ("server.listener", id, "proxy.required"),
+                "PROXY protocol is required but no trusted networks are configured",
+            );
+        }
 
         let span_id_gen = self.span_id_gen.clone();
         self.servers.push(Listener {
@@ -209,6 +222,7 @@ impl Listeners {
             protocol,
             listeners,
             proxy_networks,
+            proxy_required,
             span_id_gen,
         });
     }
@@ -334,6 +348,8 @@ impl ParseValue for ServerProtocol {
             Ok(Self::ManageSieve)
         } else if value.eq_ignore_ascii_case("pop3") {
             Ok(Self::Pop3)
+        } else if value.eq_ignore_ascii_case("health") {
+            Ok(Self::Health)
         } else {
             Err(format!("Invalid server protocol type {:?}.", value,))
         }
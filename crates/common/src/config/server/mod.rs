@@ -29,6 +29,7 @@ pub struct Listener {
     pub protocol: ServerProtocol,
     pub listeners: Vec<TcpListener>,
     pub proxy_networks: Vec<IpAddrMask>,
+    pub proxy_required: bool,
     pub max_connections: u64,
     pub span_id_gen: Arc<SnowflakeIdGenerator>,
 }
@@ -54,6 +55,7 @@ pub enum ServerProtocol {
     Pop3,
     Http,
     ManageSieve,
+    Health,
 }
 
 impl ServerProtocol {
@@ -65,6 +67,7 @@ impl ServerProtocol {
             ServerProtocol::Http => "http",
             ServerProtocol::Pop3 => "pop3",
             ServerProtocol::ManageSieve => "managesieve",
+            ServerProtocol::Health => "health",
         }
     }
 }
@@ -6,17 +6,14 @@
 
 use std::{
     io::Cursor,
-    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    net::{Ipv4Addr, Ipv6Addr},
+    path::PathBuf,
     sync::Arc,
     time::Duration,
 };
 
 use ahash::{AHashMap, AHashSet};
-use base64::{
-    engine::general_purpose::{self, STANDARD},
-    Engine,
-};
-use dns_update::{providers::rfc2136::DnsAddress, DnsUpdater, TsigAlgorithm};
+use base64::{engine::general_purpose, Engine};
 use rcgen::generate_simple_self_signed;
 use rustls::{
     crypto::ring::sign::any_supported_type,
@@ -24,7 +21,7 @@ use rustls::{
     version::{TLS12, TLS13},
     SupportedProtocolVersion,
 };
-use rustls_pemfile::{certs, read_one, Item};
+use rustls_pemfile::{certs, read_all, Item};
 use rustls_pki_types::PrivateKeyDer;
 use utils::config::Config;
 use x509_parser::{
@@ -33,18 +30,26 @@ use x509_parser::{
     extensions::{GeneralName, ParsedExtension},
 };
 
-use crate::listener::{
-    acme::{
-        directory::LETS_ENCRYPT_PRODUCTION_DIRECTORY, AcmeProvider, ChallengeSettings, EabSettings,
+use crate::{
+    config::dns::build_dns_updater,
+    dns::{DnsProvider, DnsProviders},
+    listener::{
+        acme::{
+            directory::LETS_ENCRYPT_PRODUCTION_DIRECTORY, AcmeProvider, ChallengeSettings,
+            EabSettings,
+        },
+        certificates::{
+            CertificateSource, CertificateSourceKind, CertificateSources, VaultSettings,
+        },
+        tls::AcmeProviders,
     },
-    tls::AcmeProviders,
 };
 
 pub static TLS13_VERSION: &[&SupportedProtocolVersion] = &[&TLS13];
 pub static TLS12_VERSION: &[&SupportedProtocolVersion] = &[&TLS12];
 
 impl AcmeProviders {
-    pub fn parse(config: &mut Config) -> Self {
+    pub fn parse(config: &mut Config, dns: &DnsProviders) -> Self {
         let mut providers = AHashMap::new();
 
         // Parse ACME providers
@@ -91,9 +96,9 @@ impl AcmeProviders {
             {
                 "tls-alpn-01" => ChallengeSettings::TlsAlpn01,
                 "http-01" => ChallengeSettings::Http01,
-                "dns-01" => match build_dns_updater(config, acme_id) {
-                    Some(updater) => ChallengeSettings::Dns01 {
-                        updater,
+                "dns-01" => match build_acme_dns_provider(config, dns, acme_id) {
+                    Some(provider) => ChallengeSettings::Dns01 {
+                        provider,
                         origin: config
                             .value(("acme", acme_id, "origin"))
                             .map(|s| s.to_string()),
@@ -192,78 +197,133 @@ impl AcmeProviders {
     }
 }
 
-#[allow(clippy::unnecessary_to_owned)]
-fn build_dns_updater(config: &mut Config, acme_id: &str) -> Option<DnsUpdater> {
-    match config.value_require(("acme", acme_id, "provider"))? {
-        "rfc2136-tsig" => {
-            let algorithm: TsigAlgorithm = config
-                .value_require(("acme", acme_id, "tsig-algorithm"))?
-                .parse()
-                .map_err(|_| {
-                    config.new_parse_error(("acme", acme_id, "tsig-algorithm"), "Invalid algorithm")
-                })
-                .ok()?;
-            let key = STANDARD
-                .decode(config.value_require(("acme", acme_id, "secret"))?.trim())
-                .map_err(|_| {
-                    config.new_parse_error(
-                        ("acme", acme_id, "secret"),
-                        "Failed to base64 decode secret",
-                    )
-                })
-                .ok()?;
-            let host = config.property_require::<IpAddr>(("acme", acme_id, "host"))?;
-            let port = config
-                .property_or_default::<u16>(("acme", acme_id, "port"), "53")
-                .unwrap_or(53);
-            let addr = if config.value(("acme", acme_id, "protocol")) == Some("tcp") {
-                DnsAddress::Tcp(SocketAddr::new(host, port))
-            } else {
-                DnsAddress::Udp(SocketAddr::new(host, port))
+fn build_acme_dns_provider(
+    config: &mut Config,
+    dns: &DnsProviders,
+    acme_id: &str,
+) -> Option<DnsProvider> {
+    // Use one of the shared DNS providers, or fall back to the inline settings
+    if let Some(provider_id) = config
+        .value(("acme", acme_id, "dns-provider"))
+        .map(|s| s.trim().to_string())
+    {
+        let provider = dns.get(&provider_id).cloned();
+        if provider.is_none() {
+            config.new_parse_error(
+                ("acme", acme_id, "dns-provider"),
+                format!("DNS provider {provider_id:?} not found"),
+            );
+        }
+        provider
+    } else {
+        build_dns_updater(config, "acme", acme_id).map(DnsProvider::Updater)
+    }
+}
+
+impl CertificateSources {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut sources = AHashMap::new();
+
+        for source_id in config
+            .sub_keys("certificate-source", ".type")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            let source_id = source_id.as_str();
+            let kind = match config.value(("certificate-source", source_id, "type")) {
+                Some("directory") => {
+                    let Some(path) = config
+                        .value_require(("certificate-source", source_id, "path"))
+                        .map(PathBuf::from)
+                    else {
+                        continue;
+                    };
+                    CertificateSourceKind::Directory {
+                        path,
+                        watch: config
+                            .property_or_default(("certificate-source", source_id, "watch"), "true")
+                            .unwrap_or(true),
+                    }
+                }
+                Some("vault") => {
+                    let (Some(url), Some(token)) = (
+                        config
+                            .value_require(("certificate-source", source_id, "url"))
+                            .map(|s| s.to_string()),
+                        config
+                            .value_require(("certificate-source", source_id, "token"))
+                            .map(|s| s.to_string()),
+                    ) else {
+                        continue;
+                    };
+                    CertificateSourceKind::Vault(VaultSettings {
+                        url,
+                        token,
+                        namespace: config
+                            .value(("certificate-source", source_id, "namespace"))
+                            .map(|s| s.to_string()),
+                        mount: config
+                            .value(("certificate-source", source_id, "mount"))
+                            .unwrap_or("secret")
+                            .trim_matches('/')
+                            .to_string(),
+                        path: config
+                            .value(("certificate-source", source_id, "path"))
+                            .unwrap_or_default()
+                            .to_string(),
+                        cert_field: config
+                            .value(("certificate-source", source_id, "fields.certificate"))
+                            .unwrap_or("certificate")
+                            .to_string(),
+                        key_field: config
+                            .value(("certificate-source", source_id, "fields.private-key"))
+                            .unwrap_or("private_key")
+                            .to_string(),
+                        timeout: config
+                            .property_or_default(
+                                ("certificate-source", source_id, "timeout"),
+                                "30s",
+                            )
+                            .unwrap_or_else(|| Duration::from_secs(30)),
+                        tls_allow_invalid_certs: config
+                            .property_or_default(
+                                ("certificate-source", source_id, "allow-invalid-certs"),
+                                "false",
+                            )
+                            .unwrap_or_default(),
+                    })
+                }
+                Some(typ) => {
+                    let err = format!("Invalid certificate source type {typ:?}");
+                    config.new_parse_error(("certificate-source", source_id, "type"), err);
+                    continue;
+                }
+                None => continue,
             };
 
-            DnsUpdater::new_rfc2136_tsig(
-                addr,
-                config
-                    .value_require(("acme", acme_id, "key"))?
-                    .trim()
-                    .to_string(),
-                key,
-                algorithm,
-            )
-            .map_err(|err| {
-                config.new_build_error(
-                    ("acme", acme_id, "provider"),
-                    format!("Failed to create RFC2136-TSIG DNS updater: {err}"),
+            // Watched directories are reloaded on change, everything else is polled
+            let refresh = config
+                .property_or_default::<Option<Duration>>(
+                    ("certificate-source", source_id, "refresh"),
+                    if matches!(kind, CertificateSourceKind::Vault(_)) {
+                        "1h"
+                    } else {
+                        "false"
+                    },
                 )
-            })
-            .ok()
-        }
-        "cloudflare" => {
-            let timeout = config
-                .property_or_default(("acme", acme_id, "timeout"), "30s")
-                .unwrap_or_else(|| Duration::from_secs(30));
+                .unwrap_or_default();
 
-            DnsUpdater::new_cloudflare(
-                config
-                    .value_require(("acme", acme_id, "secret"))?
-                    .trim()
-                    .to_string(),
-                config.value(("acme", acme_id, "user")).map(|s| s.trim()),
-                timeout.into(),
-            )
-            .map_err(|err| {
-                config.new_build_error(
-                    ("acme", acme_id, "provider"),
-                    format!("Failed to create Cloudflare DNS updater: {err}"),
-                )
-            })
-            .ok()
-        }
-        _ => {
-            config.new_parse_error(("acme", acme_id, "provider"), "Unsupported provider");
-            None
+            sources.insert(
+                source_id.to_string(),
+                CertificateSource {
+                    id: source_id.to_string(),
+                    kind,
+                    refresh,
+                },
+            );
         }
+
+        CertificateSources { sources }
     }
 }
 
@@ -288,80 +348,36 @@ pub(crate) fn parse_certificates(
         let pk = config.value_require(key_pk).map(|s| s.as_bytes().to_vec());
 
         if let (Some(cert), Some(pk)) = (cert, pk) {
-            match build_certified_key(cert, pk) {
-                Ok(cert) => {
-                    match cert
-                        .end_entity_cert()
-                        .map_err(|err| format!("Failed to obtain end entity cert: {err}"))
-                        .and_then(|cert| {
-                            X509Certificate::from_der(cert.as_ref())
-                                .map_err(|err| format!("Failed to parse end entity cert: {err}"))
-                        }) {
-                        Ok((_, parsed)) => {
-                            // Add CNs and SANs to the list of names
-                            let mut names = AHashSet::new();
-                            for name in parsed.subject().iter_common_name() {
-                                if let Ok(name) = name.as_str() {
-                                    names.insert(name.to_string());
-                                }
-                            }
-                            for ext in parsed.extensions() {
-                                if let ParsedExtension::SubjectAlternativeName(san) =
-                                    ext.parsed_extension()
-                                {
-                                    for name in &san.general_names {
-                                        let name = match name {
-                                            GeneralName::DNSName(name) => name.to_string(),
-                                            GeneralName::IPAddress(ip) => match ip.len() {
-                                                4 => Ipv4Addr::from(
-                                                    <[u8; 4]>::try_from(*ip).unwrap(),
-                                                )
-                                                .to_string(),
-                                                16 => Ipv6Addr::from(
-                                                    <[u8; 16]>::try_from(*ip).unwrap(),
-                                                )
-                                                .to_string(),
-                                                _ => continue,
-                                            },
-                                            _ => {
-                                                continue;
-                                            }
-                                        };
-                                        names.insert(name);
-                                    }
-                                }
-                            }
-
-                            // Add custom SNIs
-                            names.extend(
-                                config
-                                    .values(("certificate", cert_id, "subjects"))
-                                    .map(|(_, v)| v.trim().to_string()),
-                            );
-
-                            // Add domain names
-                            subject_names.extend(names.iter().cloned());
-
-                            // Add certificates
-                            let cert = Arc::new(cert);
-                            for name in names {
-                                certificates.insert(
-                                    name.strip_prefix("*.")
-                                        .map(|name| name.to_string())
-                                        .unwrap_or(name),
-                                    cert.clone(),
-                                );
-                            }
-
-                            // Add default certificate
-                            if config
-                                .property::<bool>(("certificate", cert_id, "default"))
-                                .unwrap_or_default()
-                            {
-                                certificates.insert("*".to_string(), cert.clone());
-                            }
-                        }
-                        Err(err) => config.new_build_error(format!("certificate.{cert_id}"), err),
+            match build_certified_key(cert, pk)
+                .and_then(|cert| certificate_names(&cert).map(|names| (Arc::new(cert), names)))
+            {
+                Ok((cert, mut names)) => {
+                    // Add custom SNIs
+                    names.extend(
+                        config
+                            .values(("certificate", cert_id, "subjects"))
+                            .map(|(_, v)| v.trim().to_string()),
+                    );
+
+                    // Add domain names
+                    subject_names.extend(names.iter().cloned());
+
+                    // Add certificates
+                    for name in names {
+                        certificates.insert(
+                            name.strip_prefix("*.")
+                                .map(|name| name.to_string())
+                                .unwrap_or(name),
+                            cert.clone(),
+                        );
+                    }
+
+                    // Add default certificate
+                    if config
+                        .property::<bool>(("certificate", cert_id, "default"))
+                        .unwrap_or_default()
+                    {
+                        certificates.insert("*".to_string(), cert.clone());
                     }
                 }
                 Err(err) => config.new_build_error(format!("certificate.{cert_id}"), err),
@@ -370,6 +386,44 @@ pub(crate) fn parse_certificates(
     }
 }
 
+/// Returns the CNs and SANs of the end entity certificate.
+pub(crate) fn certificate_names(cert: &CertifiedKey) -> Result<AHashSet<String>, String> {
+    let (_, parsed) = cert
+        .end_entity_cert()
+        .map_err(|err| format!("Failed to obtain end entity cert: {err}"))
+        .and_then(|cert| {
+            X509Certificate::from_der(cert.as_ref())
+                .map_err(|err| format!("Failed to parse end entity cert: {err}"))
+        })?;
+
+    let mut names = AHashSet::new();
+    for name in parsed.subject().iter_common_name() {
+        if let Ok(name) = name.as_str() {
+            names.insert(name.to_string());
+        }
+    }
+    for ext in parsed.extensions() {
+        if let ParsedExtension::SubjectAlternativeName(san) = ext.parsed_extension() {
+            for name in &san.general_names {
+                let name = match name {
+                    GeneralName::DNSName(name) => name.to_string(),
+                    GeneralName::IPAddress(ip) => match ip.len() {
+                        4 => Ipv4Addr::from(<[u8; 4]>::try_from(*ip).unwrap()).to_string(),
+                        16 => Ipv6Addr::from(<[u8; 16]>::try_from(*ip).unwrap()).to_string(),
+                        _ => continue,
+                    },
+                    _ => {
+                        continue;
+                    }
+                };
+                names.insert(name);
+            }
+        }
+    }
+
+    Ok(names)
+}
+
 pub(crate) fn build_certified_key(cert: Vec<u8>, pk: Vec<u8>) -> Result<CertifiedKey, String> {
     let cert = certs(&mut Cursor::new(cert))
         .collect::<Result<Vec<_>, _>>()
@@ -377,10 +431,11 @@ pub(crate) fn build_certified_key(cert: Vec<u8>, pk: Vec<u8>) -> Result<Certifie
     if cert.is_empty() {
         return Err("No certificates found.".to_string());
     }
-    let pk = match read_one(&mut Cursor::new(pk))
+    // Skip any certificates bundled in the same PEM file as the key
+    let pk = match read_all(&mut Cursor::new(pk))
+        .find(|item| !matches!(item, Ok(Item::X509Certificate(_))))
+        .transpose()
         .map_err(|err| format!("Failed to read private keys.: {err}",))?
-        .into_iter()
-        .next()
     {
         Some(Item::Pkcs8Key(key)) => PrivateKeyDer::Pkcs8(key),
         Some(Item::Pkcs1Key(key)) => PrivateKeyDer::Pkcs1(key),
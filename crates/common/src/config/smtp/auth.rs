@@ -34,6 +34,18 @@ pub struct MailAuthConfig {
 
     pub signers: AHashMap<String, Arc<DkimSigner>>,
     pub sealers: AHashMap<String, Arc<ArcSealer>>,
+    pub rotations: AHashMap<String, DkimRotation>,
+}
+
+/// Key rotation policy of a DKIM signature. The rotation state is kept
+/// under `signature.<id>.rotation.*` and managed by the server.
+#[derive(Debug, Clone)]
+pub struct DkimRotation {
+    pub interval: Duration,
+    pub overlap: Duration,
+    pub dns_provider: Option<String>,
+    pub dns_origin: Option<String>,
+    pub ttl: u32,
 }
 
 #[derive(Clone)]
@@ -152,6 +164,7 @@ impl Default for MailAuthConfig {
             },
             signers: Default::default(),
             sealers: Default::default(),
+            rotations: Default::default(),
         }
     }
 }
@@ -200,6 +213,9 @@ impl MailAuthConfig {
         {
             let id = id.to_string();
             if let Some((signer, sealer)) = build_signature(config, &id) {
+                if let Some(rotation) = DkimRotation::parse(config, &id) {
+                    mail_auth.rotations.insert(id.clone(), rotation);
+                }
                 mail_auth.signers.insert(id.clone(), Arc::new(signer));
                 mail_auth.sealers.insert(id, Arc::new(sealer));
             }
@@ -209,6 +225,40 @@ impl MailAuthConfig {
     }
 }
 
+impl DkimRotation {
+    fn parse(config: &mut Config, id: &str) -> Option<Self> {
+        let interval = config.property::<Duration>(("signature", id, "rotation.interval"))?;
+        let overlap = config
+            .property_or_default::<Duration>(("signature", id, "rotation.overlap"), "7d")
+            .unwrap_or(Duration::from_secs(7 * 86400));
+        if overlap >= interval {
+            config.new_parse_error(
+                ("signature", id, "rotation.overlap"),
+                "Overlap must be shorter than the rotation interval",
+            );
+            return None;
+        }
+
+        // Rotation state keys are managed by the server
+        let _ = config.iterate_prefix(("signature", id, "rotation"));
+
+        Some(DkimRotation {
+            interval,
+            overlap,
+            dns_provider: config
+                .value(("signature", id, "rotation.dns-provider"))
+                .map(|s| s.to_string()),
+            dns_origin: config
+                .value(("signature", id, "rotation.dns-origin"))
+                .map(|s| s.to_string()),
+            ttl: config
+                .property_or_default::<Duration>(("signature", id, "rotation.ttl"), "1h")
+                .unwrap_or(Duration::from_secs(3600))
+                .as_secs() as u32,
+        })
+    }
+}
+
 fn build_signature(config: &mut Config, id: &str) -> Option<(DkimSigner, ArcSealer)> {
     match config.property_require::<Algorithm>(("signature", id, "algorithm"))? {
         Algorithm::RsaSha256 => {
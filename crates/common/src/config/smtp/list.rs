@@ -0,0 +1,167 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{sync::Arc, time::Duration};
+
+use ahash::AHashMap;
+use utils::config::Config;
+
+use crate::addresses::verp_decode;
+
+/// Mailing lists managed by the server. Each list is backed by a list
+/// principal whose members receive the posts, while the settings here
+/// control how posts are rewritten, moderated and archived.
+#[derive(Debug, Clone, Default)]
+pub struct MailingLists {
+    pub lists: AHashMap<String, Arc<MailingList>>,
+    pub bounces: AHashMap<String, Arc<MailingList>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailingList {
+    pub id: String,
+    pub address: String,
+    pub bounce_address: String,
+    pub name: Option<String>,
+    pub subject_tag: Option<String>,
+    pub moderation: Moderation,
+    pub moderators: Vec<String>,
+    pub archive: Option<String>,
+    pub archive_url: Option<String>,
+    pub unsubscribe: Option<String>,
+    pub max_bounces: u32,
+    pub bounce_expiry: u64,
+    pub hold_expiry: u64,
+    pub verp: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Moderation {
+    /// Anyone can post to the list.
+    Open,
+    /// Posts from non-members are held for approval.
+    Members,
+    /// All posts are held for approval.
+    Moderated,
+}
+
+impl MailingLists {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut lists = MailingLists::default();
+
+        for id in config
+            .sub_keys("mailing-list", "")
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+        {
+            if let Some(list) = MailingList::parse(config, &id) {
+                let list = Arc::new(list);
+                lists
+                    .bounces
+                    .insert(list.bounce_address.clone(), list.clone());
+                lists.lists.insert(list.address.clone(), list);
+            }
+        }
+
+        lists
+    }
+
+    pub fn get(&self, address: &str) -> Option<&Arc<MailingList>> {
+        self.lists.get(address)
+    }
+
+    /// Returns the list a bounce address belongs to, including the VERP
+    /// addresses generated for each member.
+    pub fn get_by_bounce(&self, address: &str) -> Option<&Arc<MailingList>> {
+        self.bounces.get(address).or_else(|| {
+            verp_decode(address).and_then(|(bounce_address, _)| self.bounces.get(&bounce_address))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+}
+
+impl MailingList {
+    fn parse(config: &mut Config, id: &str) -> Option<Self> {
+        let prefix = format!("mailing-list.{id}");
+        if !config
+            .property_or_default::<bool>((prefix.as_str(), "enable"), "true")
+            .unwrap_or(true)
+        {
+            return None;
+        }
+
+        let address = config
+            .value_require((prefix.as_str(), "address"))?
+            .trim()
+            .to_lowercase();
+        let Some((local_part, domain_part)) = address.rsplit_once('@') else {
+            let err = format!("Invalid list address {address:?}");
+            config.new_parse_error((prefix.as_str(), "address"), err);
+            return None;
+        };
+        let bounce_address = format!("{local_part}-bounces@{domain_part}");
+
+        let moderation = match config
+            .value((prefix.as_str(), "moderation"))
+            .unwrap_or("members")
+        {
+            "open" => Moderation::Open,
+            "members" => Moderation::Members,
+            "moderated" => Moderation::Moderated,
+            other => {
+                let err = format!("Invalid moderation policy {other:?}");
+                config.new_parse_error((prefix.as_str(), "moderation"), err);
+                Moderation::Members
+            }
+        };
+
+        Some(MailingList {
+            id: id.to_string(),
+            name: config
+                .value((prefix.as_str(), "name"))
+                .map(|v| v.to_string()),
+            subject_tag: config
+                .value((prefix.as_str(), "subject-tag"))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            moderation,
+            moderators: config
+                .values((prefix.as_str(), "moderators"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            archive: config
+                .value((prefix.as_str(), "archive.address"))
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty()),
+            archive_url: config
+                .value((prefix.as_str(), "archive.url"))
+                .map(|v| v.to_string()),
+            unsubscribe: config
+                .value((prefix.as_str(), "unsubscribe"))
+                .map(|v| v.to_string()),
+            max_bounces: config
+                .property_or_default((prefix.as_str(), "bounce.max"), "3")
+                .unwrap_or(3),
+            bounce_expiry: config
+                .property_or_default::<Duration>((prefix.as_str(), "bounce.expiry"), "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+                .as_secs(),
+            hold_expiry: config
+                .property_or_default::<Duration>((prefix.as_str(), "hold.expiry"), "7d")
+                .unwrap_or_else(|| Duration::from_secs(7 * 86400))
+                .as_secs(),
+            verp: config
+                .property_or_default((prefix.as_str(), "bounce.verp"), "false")
+                .unwrap_or_default(),
+            address,
+            bounce_address,
+        })
+    }
+}
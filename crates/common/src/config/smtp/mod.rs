@@ -7,6 +7,7 @@
 use utils::config::{Config, Rate};
 
 pub mod auth;
+pub mod list;
 pub mod queue;
 pub mod report;
 pub mod resolver;
@@ -16,8 +17,8 @@ pub mod throttle;
 use crate::expr::{tokenizer::TokenMap, Expression};
 
 use self::{
-    auth::MailAuthConfig, queue::QueueConfig, report::ReportConfig, resolver::Resolvers,
-    session::SessionConfig,
+    auth::MailAuthConfig, list::MailingLists, queue::QueueConfig, report::ReportConfig,
+    resolver::Resolvers, session::SessionConfig,
 };
 
 use super::*;
@@ -29,6 +30,7 @@ pub struct SmtpConfig {
     pub resolvers: Resolvers,
     pub mail_auth: MailAuthConfig,
     pub report: ReportConfig,
+    pub lists: MailingLists,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -93,7 +95,7 @@ pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 15] = &[
     V_PRIORITY,
     V_HELO_DOMAIN,
 ];
-pub(crate) const SMTP_QUEUE_HOST_VARS: &[u32; 14] = &[
+pub(crate) const SMTP_QUEUE_HOST_VARS: &[u32; 15] = &[
     V_SENDER,
     V_SENDER_DOMAIN,
     V_RECIPIENT_DOMAIN,
@@ -108,8 +110,9 @@ pub(crate) const SMTP_QUEUE_HOST_VARS: &[u32; 14] = &[
     V_QUEUE_EXPIRES_IN,
     V_QUEUE_LAST_STATUS,
     V_QUEUE_LAST_ERROR,
+    V_SIZE,
 ];
-pub(crate) const SMTP_QUEUE_RCPT_VARS: &[u32; 10] = &[
+pub(crate) const SMTP_QUEUE_RCPT_VARS: &[u32; 11] = &[
     V_RECIPIENT_DOMAIN,
     V_RECIPIENTS,
     V_SENDER,
@@ -120,8 +123,9 @@ pub(crate) const SMTP_QUEUE_RCPT_VARS: &[u32; 10] = &[
     V_QUEUE_EXPIRES_IN,
     V_QUEUE_LAST_STATUS,
     V_QUEUE_LAST_ERROR,
+    V_SIZE,
 ];
-pub(crate) const SMTP_QUEUE_SENDER_VARS: &[u32; 8] = &[
+pub(crate) const SMTP_QUEUE_SENDER_VARS: &[u32; 9] = &[
     V_SENDER,
     V_SENDER_DOMAIN,
     V_PRIORITY,
@@ -130,8 +134,9 @@ pub(crate) const SMTP_QUEUE_SENDER_VARS: &[u32; 8] = &[
     V_QUEUE_EXPIRES_IN,
     V_QUEUE_LAST_STATUS,
     V_QUEUE_LAST_ERROR,
+    V_SIZE,
 ];
-pub(crate) const SMTP_QUEUE_MX_VARS: &[u32; 11] = &[
+pub(crate) const SMTP_QUEUE_MX_VARS: &[u32; 12] = &[
     V_RECIPIENT_DOMAIN,
     V_RECIPIENTS,
     V_SENDER,
@@ -143,6 +148,7 @@ pub(crate) const SMTP_QUEUE_MX_VARS: &[u32; 11] = &[
     V_QUEUE_EXPIRES_IN,
     V_QUEUE_LAST_STATUS,
     V_QUEUE_LAST_ERROR,
+    V_SIZE,
 ];
 
 impl SmtpConfig {
@@ -153,6 +159,7 @@ impl SmtpConfig {
             resolvers: Resolvers::parse(config).await,
             mail_auth: MailAuthConfig::parse(config),
             report: ReportConfig::parse(config),
+            lists: MailingLists::parse(config),
         }
     }
 }
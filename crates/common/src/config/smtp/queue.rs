@@ -4,7 +4,9 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use ahash::AHashMap;
+use std::{net::IpAddr, time::Duration};
+
+use ahash::{AHashMap, AHashSet};
 use mail_auth::IpLookupStrategy;
 use mail_send::Credentials;
 use utils::config::{
@@ -34,6 +36,7 @@ pub struct QueueConfig {
     pub max_mx: IfBlock,
     pub max_multihomed: IfBlock,
     pub ip_strategy: IfBlock,
+    pub max_buffer: IfBlock,
     pub source_ip: QueueOutboundSourceIp,
     pub tls: QueueOutboundTls,
     pub dsn: Dsn,
@@ -47,6 +50,16 @@ pub struct QueueConfig {
 
     // Relay hosts
     pub relay_hosts: AHashMap<String, RelayHost>,
+    pub routes: Vec<Route>,
+
+    // Source IP pools
+    pub ip_pools: Vec<IpPool>,
+
+    // Bounce suppression
+    pub suppression: Suppression,
+
+    // Delivery history
+    pub history: History,
 }
 
 #[derive(Clone)]
@@ -60,6 +73,33 @@ pub struct Dsn {
     pub name: IfBlock,
     pub address: IfBlock,
     pub sign: IfBlock,
+    pub templates: Vec<DsnTemplate>,
+}
+
+/// Custom text for delivery status notifications, selected by the domain of
+/// the return path and the language of the original message. Texts may
+/// contain `{brand}`, `{support}`, `{sender}` and `{hostname}`, and any text
+/// missing from the template uses the built-in English one.
+#[derive(Debug, Clone, Default)]
+pub struct DsnTemplate {
+    pub id: String,
+    pub domains: Vec<String>,
+    pub languages: Vec<String>,
+    pub brand: String,
+    pub support: String,
+    pub subject: AHashMap<DsnKind, String>,
+    pub intro: AHashMap<DsnKind, String>,
+    pub heading: AHashMap<DsnKind, String>,
+    pub footer: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DsnKind {
+    Success,
+    Delay,
+    Failure,
+    Partial,
+    Mixed,
 }
 
 #[derive(Clone)]
@@ -113,6 +153,61 @@ pub struct RelayHost {
     pub auth: Option<Credentials<String>>,
     pub tls_implicit: bool,
     pub tls_allow_invalid_certs: bool,
+    pub tls_start: Option<RequireOptional>,
+    pub source_ips: Vec<IpAddr>,
+}
+
+/// Smarthost route, routes are evaluated before `queue.outbound.next-hop` in
+/// the order of their identifiers and the first matching one is used. Empty
+/// criteria match any message.
+#[derive(Debug, Clone, Default)]
+pub struct Route {
+    pub id: String,
+    pub senders: AHashSet<String>,
+    pub sender_domains: Vec<String>,
+    pub rcpt_domains: Vec<String>,
+    pub min_size: Option<usize>,
+    pub max_size: Option<usize>,
+    pub relay: String,
+}
+
+/// Pool of local addresses outbound connections are bound to, pools are
+/// matched by sender domain in the order of their identifiers and a pool
+/// without sender domains is used for any sender.
+#[derive(Debug, Clone, Default)]
+pub struct IpPool {
+    pub id: String,
+    pub addresses: Vec<IpAddr>,
+    pub sender_domains: Vec<String>,
+    pub strategy: IpPoolStrategy,
+    pub hourly_limit: Option<u64>,
+    pub warmup: Vec<u64>,
+    pub max_failure_rate: Option<f64>,
+    pub min_samples: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IpPoolStrategy {
+    #[default]
+    RoundRobin,
+    SenderDomain,
+}
+
+/// Recipients that bounced permanently are suppressed for the sender that
+/// received the bounce, until the entry expires or is removed.
+#[derive(Debug, Clone, Default)]
+pub struct Suppression {
+    pub enable: bool,
+    pub expiry: Option<u64>,
+}
+
+/// Outcome of each delivery attempt is kept per message, for the configured
+/// retention period after the last attempt.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    pub enable: bool,
+    pub retention: Option<u64>,
+    pub max_attempts: usize,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -153,6 +248,7 @@ impl Default for QueueConfig {
                 [],
                 "ipv4_then_ipv6",
             ),
+            max_buffer: IfBlock::new::<()>("queue.outbound.limits.buffer", [], "16777216"),
             source_ip: QueueOutboundSourceIp {
                 ipv4: IfBlock::empty("queue.outbound.source-ip.v4"),
                 ipv6: IfBlock::empty("queue.outbound.source-ip.v6"),
@@ -193,6 +289,7 @@ impl Default for QueueConfig {
                     [],
                     "['rsa-' + key_get('default', 'domain'), 'ed25519-' + key_get('default', 'domain')]",
                 ),
+                templates: Default::default(),
             },
             timeout: QueueOutboundTimeout {
                 connect: IfBlock::new::<()>("queue.outbound.timeouts.connect", [], "5m"),
@@ -215,6 +312,10 @@ impl Default for QueueConfig {
                 rcpt_domain: Default::default(),
             },
             relay_hosts: Default::default(),
+            routes: Default::default(),
+            ip_pools: Default::default(),
+            suppression: Default::default(),
+            history: Default::default(),
         }
     }
 }
@@ -246,6 +347,11 @@ impl QueueConfig {
                 "queue.outbound.ip-strategy",
                 &ip_strategy_vars,
             ),
+            (
+                &mut queue.max_buffer,
+                "queue.outbound.limits.buffer",
+                &host_vars,
+            ),
             (
                 &mut queue.source_ip.ipv4,
                 "queue.outbound.source-ip.v4",
@@ -326,6 +432,15 @@ impl QueueConfig {
             }
         }
 
+        // Parse DSN templates
+        queue.dsn.templates = config
+            .sub_keys("report.dsn.template", "")
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|id| parse_dsn_template(config, &id))
+            .collect();
+
         // Parse queue quotas and throttles
         queue.throttle = parse_queue_throttle(config);
         queue.quota = parse_queue_quota(config);
@@ -348,14 +463,305 @@ impl QueueConfig {
                 protocol: ServerProtocol::Http,
                 tls_implicit: Default::default(),
                 tls_allow_invalid_certs: Default::default(),
+                tls_start: None,
+                source_ips: Vec::new(),
                 auth: None,
             },
         );
 
+        // Parse smarthost routes
+        queue.routes = config
+            .sub_keys("queue.route", ".relay")
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|id| parse_route(config, &id, &queue.relay_hosts))
+            .collect();
+
+        // Parse source IP pools
+        queue.ip_pools = config
+            .sub_keys("queue.pool", "")
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|id| parse_ip_pool(config, &id))
+            .collect();
+
+        // Parse suppression list settings
+        queue.suppression = Suppression {
+            enable: config
+                .property_or_default("queue.suppression.enable", "false")
+                .unwrap_or_default(),
+            expiry: config
+                .property::<Duration>("queue.suppression.expiry")
+                .map(|expiry| expiry.as_secs()),
+        };
+
+        // Parse delivery history settings
+        queue.history = History {
+            enable: config
+                .property_or_default("queue.history.enable", "false")
+                .unwrap_or_default(),
+            retention: config
+                .property_or_default::<Option<Duration>>("queue.history.retention", "30d")
+                .unwrap_or_default()
+                .map(|retention| retention.as_secs()),
+            max_attempts: config
+                .property_or_default("queue.history.max-attempts", "50")
+                .unwrap_or(50),
+        };
+
         queue
     }
 }
 
+impl Route {
+    pub fn matches(
+        &self,
+        sender: &str,
+        sender_domain: &str,
+        rcpt_domain: &str,
+        size: usize,
+    ) -> bool {
+        (self.senders.is_empty() || self.senders.contains(sender))
+            && (self.sender_domains.is_empty()
+                || self
+                    .sender_domains
+                    .iter()
+                    .any(|domain| domain_matches(domain, sender_domain)))
+            && (self.rcpt_domains.is_empty()
+                || self
+                    .rcpt_domains
+                    .iter()
+                    .any(|domain| domain_matches(domain, rcpt_domain)))
+            && self.min_size.map_or(true, |min_size| size >= min_size)
+            && self.max_size.map_or(true, |max_size| size <= max_size)
+    }
+}
+
+impl IpPool {
+    pub fn matches(&self, sender_domain: &str) -> bool {
+        self.sender_domains.is_empty()
+            || self
+                .sender_domains
+                .iter()
+                .any(|domain| domain_matches(domain, sender_domain))
+    }
+
+    /// Returns the hourly limit for an address that was first used
+    /// `days` days ago, following the warmup schedule while it lasts.
+    pub fn hourly_limit(&self, days: u64) -> Option<u64> {
+        self.warmup
+            .get(days as usize)
+            .copied()
+            .or(self.hourly_limit)
+    }
+}
+
+impl Dsn {
+    /// Returns the first template for the domain that covers one of the
+    /// languages, in order of preference, or else the first one for the
+    /// domain without languages.
+    pub fn template(&self, domain: &str, languages: &[String]) -> Option<&DsnTemplate> {
+        let templates = self
+            .templates
+            .iter()
+            .filter(|template| {
+                template.domains.is_empty()
+                    || template
+                        .domains
+                        .iter()
+                        .any(|pattern| domain_matches(pattern, domain))
+            })
+            .collect::<Vec<_>>();
+
+        languages
+            .iter()
+            .find_map(|language| {
+                templates
+                    .iter()
+                    .find(|template| template.has_language(language))
+            })
+            .or_else(|| {
+                templates
+                    .iter()
+                    .find(|template| template.languages.is_empty())
+            })
+            .copied()
+    }
+}
+
+impl DsnTemplate {
+    /// Returns whether the template covers a language, "de" covers "de-at".
+    pub fn has_language(&self, language: &str) -> bool {
+        let primary = language.split('-').next().unwrap_or_default();
+        self.languages
+            .iter()
+            .any(|item| item == language || item == primary)
+    }
+}
+
+impl DsnKind {
+    pub const ALL: [DsnKind; 5] = [
+        DsnKind::Success,
+        DsnKind::Delay,
+        DsnKind::Failure,
+        DsnKind::Partial,
+        DsnKind::Mixed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DsnKind::Success => "success",
+            DsnKind::Delay => "delay",
+            DsnKind::Failure => "failure",
+            DsnKind::Partial => "partial",
+            DsnKind::Mixed => "mixed",
+        }
+    }
+}
+
+/// Matches a domain against a pattern, "*.example.org" matches any subdomain.
+fn domain_matches(pattern: &str, domain: &str) -> bool {
+    if let Some(suffix) = pattern.strip_prefix("*.") {
+        domain
+            .strip_suffix(suffix)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+    } else {
+        pattern == domain
+    }
+}
+
+fn parse_route(
+    config: &mut Config,
+    id: &str,
+    relay_hosts: &AHashMap<String, RelayHost>,
+) -> Option<Route> {
+    let relay = config
+        .value_require(("queue.route", id, "relay"))?
+        .to_string();
+    if !relay_hosts.contains_key(&relay) {
+        config.new_build_error(
+            ("queue.route", id, "relay"),
+            format!("Relay host {relay:?} not found"),
+        );
+        return None;
+    }
+
+    Some(Route {
+        id: id.to_string(),
+        senders: config
+            .values(("queue.route", id, "sender"))
+            .map(|(_, sender)| sender.to_lowercase())
+            .collect(),
+        sender_domains: config
+            .values(("queue.route", id, "sender-domain"))
+            .map(|(_, domain)| domain.to_lowercase())
+            .collect(),
+        rcpt_domains: config
+            .values(("queue.route", id, "rcpt-domain"))
+            .map(|(_, domain)| domain.to_lowercase())
+            .collect(),
+        min_size: config.property(("queue.route", id, "size.min")),
+        max_size: config.property(("queue.route", id, "size.max")),
+        relay,
+    })
+}
+
+fn parse_ip_pool(config: &mut Config, id: &str) -> Option<IpPool> {
+    let addresses = config
+        .properties::<IpAddr>(("queue.pool", id, "addresses"))
+        .into_iter()
+        .map(|(_, ip)| ip)
+        .collect::<Vec<_>>();
+    if addresses.is_empty() {
+        config.new_build_error(
+            ("queue.pool", id, "addresses"),
+            "At least one source address is required",
+        );
+        return None;
+    }
+
+    let strategy = match config
+        .value(("queue.pool", id, "strategy"))
+        .unwrap_or("round-robin")
+        .to_string()
+        .as_str()
+    {
+        "round-robin" => IpPoolStrategy::RoundRobin,
+        "sender-domain" => IpPoolStrategy::SenderDomain,
+        other => {
+            config.new_parse_error(
+                ("queue.pool", id, "strategy"),
+                format!("Invalid IP pool strategy {other:?}"),
+            );
+            return None;
+        }
+    };
+
+    Some(IpPool {
+        id: id.to_string(),
+        addresses,
+        sender_domains: config
+            .values(("queue.pool", id, "sender-domain"))
+            .map(|(_, domain)| domain.to_lowercase())
+            .collect(),
+        strategy,
+        hourly_limit: config.property(("queue.pool", id, "limits.hourly")),
+        warmup: config
+            .properties::<u64>(("queue.pool", id, "warmup.schedule"))
+            .into_iter()
+            .map(|(_, limit)| limit)
+            .collect(),
+        max_failure_rate: config.property(("queue.pool", id, "reputation.max-failure-rate")),
+        min_samples: config
+            .property(("queue.pool", id, "reputation.min-samples"))
+            .unwrap_or(100),
+    })
+}
+
+fn parse_dsn_template(config: &mut Config, id: &str) -> DsnTemplate {
+    let mut template = DsnTemplate {
+        id: id.to_string(),
+        domains: config
+            .values(("report.dsn.template", id, "domains"))
+            .map(|(_, domain)| domain.trim().to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect(),
+        languages: config
+            .values(("report.dsn.template", id, "languages"))
+            .map(|(_, language)| language.trim().to_lowercase())
+            .filter(|language| !language.is_empty())
+            .collect(),
+        brand: config
+            .value(("report.dsn.template", id, "brand"))
+            .unwrap_or_default()
+            .to_string(),
+        support: config
+            .value(("report.dsn.template", id, "support"))
+            .unwrap_or_default()
+            .to_string(),
+        footer: config
+            .value(("report.dsn.template", id, "footer"))
+            .map(|footer| footer.to_string()),
+        ..Default::default()
+    };
+
+    for kind in DsnKind::ALL {
+        for (texts, key) in [
+            (&mut template.subject, "subject"),
+            (&mut template.intro, "intro"),
+            (&mut template.heading, "heading"),
+        ] {
+            if let Some(text) = config.value(("report.dsn.template", id, key, kind.as_str())) {
+                texts.insert(kind, text.to_string());
+            }
+        }
+    }
+
+    template
+}
+
 fn parse_relay_host(config: &mut Config, id: &str) -> Option<RelayHost> {
     Some(RelayHost {
         address: config.property_require(("remote", id, "address"))?,
@@ -379,6 +785,12 @@ fn parse_relay_host(config: &mut Config, id: &str) -> Option<RelayHost> {
         tls_allow_invalid_certs: config
             .property(("remote", id, "tls.allow-invalid-certs"))
             .unwrap_or(false),
+        tls_start: config.property(("remote", id, "tls.starttls")),
+        source_ips: config
+            .properties::<IpAddr>(("remote", id, "source-ip"))
+            .into_iter()
+            .map(|(_, ip)| ip)
+            .collect(),
     })
 }
 
@@ -629,6 +1041,8 @@ impl std::fmt::Debug for RelayHost {
             .field("protocol", &self.protocol)
             .field("tls_implicit", &self.tls_implicit)
             .field("tls_allow_invalid_certs", &self.tls_allow_invalid_certs)
+            .field("tls_start", &self.tls_start)
+            .field("source_ips", &self.source_ips)
             .finish()
     }
 }
@@ -16,12 +16,17 @@ use hyper::{
     header::{HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
     HeaderMap,
 };
+use regex::Regex;
+use sha2::{Digest, Sha256};
 use smtp_proto::*;
-use utils::config::{utils::ParseValue, Config};
+use utils::config::{utils::ParseValue, Config, Rate};
+
+use wasmtime::Module;
 
 use crate::{
     config::CONNECTION_VARS,
     expr::{if_block::IfBlock, tokenizer::TokenMap, *},
+    scripts::wasm::{self, WasmLimits},
 };
 
 use self::{resolver::Policy, throttle::parse_throttle};
@@ -43,9 +48,13 @@ pub struct SessionConfig {
     pub data: Data,
     pub extensions: Extensions,
     pub mta_sts_policy: Option<Policy>,
+    pub anomaly: Option<SubmissionAnomaly>,
+    pub reputation: Option<SenderReputation>,
 
     pub milters: Vec<Milter>,
     pub hooks: Vec<MTAHook>,
+    pub wasm_hooks: Vec<WasmHook>,
+    pub rewrite: Vec<AddressRewrite>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -83,6 +92,70 @@ pub struct Extensions {
     pub mt_priority: IfBlock,
 }
 
+#[derive(Clone)]
+pub struct SubmissionAnomaly {
+    pub account_volume: Option<Rate>,
+    pub ip_volume: Option<Rate>,
+    pub unique_recipients: Option<Rate>,
+    pub new_networks: Option<Rate>,
+    pub network_expiry: u64,
+    pub suspend_duration: u64,
+    pub notify_from: String,
+    pub notify_to: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct SenderReputation {
+    pub expiry: u64,
+    pub half_life: u64,
+    pub min_samples: f64,
+    pub factor: f64,
+    pub throttle: Option<ReputationThrottle>,
+    pub feed: Option<ReputationFeed>,
+}
+
+#[derive(Clone)]
+pub struct ReputationThrottle {
+    pub threshold: f64,
+    pub action: ReputationAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReputationAction {
+    Delay(Duration),
+    Reject,
+}
+
+#[derive(Clone)]
+pub struct ReputationFeed {
+    pub weight: f64,
+    pub expiry: u64,
+    pub threshold: f64,
+    pub max_entries: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReputationKind {
+    Ip,
+    Domain,
+    Dkim,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReputationSignal {
+    Ham,
+    Spam,
+    Bounce,
+    Complaint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ReputationEntity {
+    pub kind: ReputationKind,
+    pub value: String,
+}
+
 #[derive(Clone)]
 pub struct Auth {
     pub directory: IfBlock,
@@ -117,6 +190,25 @@ pub struct Rcpt {
     // Catch-all and sub-addressing
     pub catch_all: AddressMapping,
     pub subaddressing: AddressMapping,
+    pub addressing: Vec<DomainAddressing>,
+
+    // External address verification
+    pub verify: Option<RcptVerify>,
+}
+
+/// External HTTP service consulted to verify recipients that are not
+/// managed by a directory.
+#[derive(Clone)]
+pub struct RcptVerify {
+    pub enable: IfBlock,
+    pub url: String,
+    pub timeout: Duration,
+    pub headers: HeaderMap,
+    pub tls_allow_invalid_certs: bool,
+    pub tempfail_on_error: bool,
+    pub cache_valid: Duration,
+    pub cache_invalid: Duration,
+    pub max_response_size: usize,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -127,6 +219,30 @@ pub enum AddressMapping {
     Disable,
 }
 
+/// Catch-all and sub-addressing settings that override the global ones for
+/// a set of domains.
+#[derive(Debug, Clone)]
+pub struct DomainAddressing {
+    pub domains: Vec<String>,
+    pub catch_all: CatchAll,
+    pub subaddressing: Option<SubAddressing>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatchAll {
+    Inherit,
+    Disable,
+    Domain,
+    Address(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct SubAddressing {
+    pub delimiters: Vec<char>,
+    pub file_into: bool,
+    pub create_folder: bool,
+}
+
 #[derive(Clone)]
 pub struct Data {
     pub script: IfBlock,
@@ -144,6 +260,118 @@ pub struct Data {
     pub add_auth_results: IfBlock,
     pub add_message_id: IfBlock,
     pub add_date: IfBlock,
+    pub add_classification: IfBlock,
+
+    // Classification
+    pub classification_tags: Vec<ClassificationTag>,
+    pub strip_classification: bool,
+
+    // Outbound spam filtering
+    pub outbound_spam: Option<OutboundSpam>,
+
+    // External spam classifier
+    pub rspamd: Option<Rspamd>,
+
+    // Malware scanner
+    pub antivirus: Option<Antivirus>,
+
+    // Attachment policies
+    pub attachment_policies: Vec<AttachmentPolicy>,
+
+    // Per-recipient return paths
+    pub verp: IfBlock,
+}
+
+/// Rspamd compatible spam classifier queried over HTTP.
+#[derive(Clone)]
+pub struct Rspamd {
+    pub enable: IfBlock,
+    pub url: String,
+    pub timeout: Duration,
+    pub headers: HeaderMap,
+    pub tls_allow_invalid_certs: bool,
+    pub tempfail_on_error: bool,
+    pub max_response_size: usize,
+    pub add_result_header: bool,
+}
+
+/// Malware scanner that message parts are streamed to, verdicts are cached
+/// by content hash for `cache_ttl`.
+#[derive(Clone)]
+pub struct Antivirus {
+    pub enable: IfBlock,
+    pub scanner: AntivirusScanner,
+    pub timeout: Duration,
+    pub action: AntivirusAction,
+    pub tempfail_on_error: bool,
+    pub cache_ttl: Duration,
+    pub max_part_size: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AntivirusScanner {
+    Clamd {
+        address: String,
+    },
+    Icap {
+        address: String,
+        host: String,
+        url: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AntivirusAction {
+    Reject,
+    Quarantine,
+    Tag,
+}
+
+/// Attachment policy, the first enabled policy of each stage applies which
+/// allows per-domain policies to override a catch-all one. Policies enforced
+/// at delivery always fail the recipients of the domain, which generates an NDN.
+#[derive(Clone)]
+pub struct AttachmentPolicy {
+    pub id: String,
+    pub enable: IfBlock,
+    pub stage: AttachmentStage,
+    pub action: AttachmentAction,
+    pub block_extensions: AHashSet<String>,
+    pub block_types: AHashSet<String>,
+    pub max_size: Option<usize>,
+    pub oversize: AttachmentAction,
+    pub mismatch: Option<AttachmentAction>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttachmentStage {
+    Data,
+    Delivery,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttachmentAction {
+    Reject,
+    Strip,
+}
+
+#[derive(Clone)]
+pub struct OutboundSpam {
+    pub script: String,
+    pub threshold: f64,
+    pub action: OutboundSpamAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutboundSpamAction {
+    Reject,
+    Hold,
+}
+
+#[derive(Clone)]
+pub struct ClassificationTag {
+    pub name: String,
+    pub value: IfBlock,
 }
 
 // Ceci n'est pas une pipe
@@ -193,6 +421,39 @@ pub struct MTAHook {
     pub max_response_size: usize,
 }
 
+#[derive(Clone)]
+pub struct WasmHook {
+    pub enable: IfBlock,
+    pub id: String,
+    pub module: Module,
+    pub limits: WasmLimits,
+    pub tempfail_on_error: bool,
+    pub run_on_stage: AHashSet<Stage>,
+}
+
+/// Canonical map applied to envelope addresses before they are resolved
+/// by the directory, in the spirit of Postfix's canonical maps.
+#[derive(Clone)]
+pub struct AddressRewrite {
+    pub id: String,
+    pub enable: IfBlock,
+    pub direction: RewriteDirection,
+    pub map: RewriteMap,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RewriteDirection {
+    Sender,
+    Recipient,
+    Both,
+}
+
+#[derive(Clone)]
+pub enum RewriteMap {
+    Regex { pattern: Regex, replacement: String },
+    Lookup { store: String },
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Stage {
     Connect,
@@ -201,6 +462,7 @@ pub enum Stage {
     Mail,
     Rcpt,
     Data,
+    Classify,
 }
 
 impl SessionConfig {
@@ -215,6 +477,8 @@ impl SessionConfig {
         let mut session = SessionConfig::default();
         session.rcpt.catch_all = AddressMapping::parse(config, "session.rcpt.catch-all");
         session.rcpt.subaddressing = AddressMapping::parse(config, "session.rcpt.sub-addressing");
+        session.rcpt.addressing = DomainAddressing::parse(config);
+        session.rcpt.verify = RcptVerify::parse(config, &has_rcpt_vars);
         session.milters = config
             .sub_keys("session.milter", ".hostname")
             .map(|s| s.to_string())
@@ -229,6 +493,20 @@ impl SessionConfig {
             .into_iter()
             .filter_map(|id| parse_hooks(config, &id, &has_rcpt_vars))
             .collect();
+        session.wasm_hooks = config
+            .sub_keys("session.hook", ".module")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|id| parse_wasm_hooks(config, &id, &has_rcpt_vars))
+            .collect();
+        session.rewrite = config
+            .sub_keys("session.rewrite", ".type")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|id| parse_rewrite(config, &id, &has_rcpt_vars))
+            .collect();
         session.data.pipe_commands = config
             .sub_keys("session.data.pipe", "")
             .map(|s| s.to_string())
@@ -236,8 +514,40 @@ impl SessionConfig {
             .into_iter()
             .filter_map(|id| parse_pipe(config, &id, &has_rcpt_vars))
             .collect();
+        session.data.classification_tags = config
+            .sub_keys("session.data.classification.tag", "")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|name| {
+                IfBlock::try_parse(
+                    config,
+                    ("session.data.classification.tag", name.as_str()),
+                    &has_rcpt_vars,
+                )
+                .map(|value| ClassificationTag { name, value })
+            })
+            .collect();
+        session.data.strip_classification = config
+            .property_or_default("session.data.classification.strip-on-egress", "true")
+            .unwrap_or(true);
         session.throttle = SessionThrottle::parse(config);
         session.mta_sts_policy = Policy::try_parse(config);
+        session.anomaly = SubmissionAnomaly::parse(config);
+        session.reputation = SenderReputation::parse(config);
+        session.data.outbound_spam = OutboundSpam::parse(config);
+        session.data.rspamd = Rspamd::parse(config, &has_rcpt_vars);
+        session.data.antivirus = Antivirus::parse(config, &has_rcpt_vars);
+        let has_queue_rcpt_vars = TokenMap::default().with_variables(SMTP_QUEUE_RCPT_VARS);
+        session.data.attachment_policies = config
+            .sub_keys("session.data.attachments", "")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|id| {
+                AttachmentPolicy::parse(config, &id, &has_rcpt_vars, &has_queue_rcpt_vars)
+            })
+            .collect();
 
         for (value, key, token_map) in [
             (&mut session.duration, "session.duration", &has_conn_vars),
@@ -457,6 +767,12 @@ impl SessionConfig {
                 "session.data.add-headers.date",
                 &has_rcpt_vars,
             ),
+            (
+                &mut session.data.add_classification,
+                "session.data.add-headers.classification",
+                &has_rcpt_vars,
+            ),
+            (&mut session.data.verp, "session.data.verp", &has_rcpt_vars),
         ] {
             if let Some(if_block) = IfBlock::try_parse(config, key, token_map) {
                 *value = if_block;
@@ -467,6 +783,470 @@ impl SessionConfig {
     }
 }
 
+impl SubmissionAnomaly {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default::<bool>("session.auth.anomaly.enable", "false")
+            .unwrap_or_default()
+        {
+            return None;
+        }
+
+        Some(SubmissionAnomaly {
+            account_volume: config
+                .property_or_default::<Option<Rate>>(
+                    "session.auth.anomaly.volume.account",
+                    "500/1h",
+                )
+                .unwrap_or_default(),
+            ip_volume: config
+                .property_or_default::<Option<Rate>>("session.auth.anomaly.volume.ip", "1000/1h")
+                .unwrap_or_default(),
+            unique_recipients: config
+                .property_or_default::<Option<Rate>>("session.auth.anomaly.recipients", "200/1h")
+                .unwrap_or_default(),
+            new_networks: config
+                .property_or_default::<Option<Rate>>("session.auth.anomaly.network.rate", "3/1d")
+                .unwrap_or_default(),
+            network_expiry: config
+                .property_or_default::<Duration>("session.auth.anomaly.network.expiry", "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+                .as_secs(),
+            suspend_duration: config
+                .property_or_default::<Duration>("session.auth.anomaly.suspend", "1h")
+                .unwrap_or_else(|| Duration::from_secs(3600))
+                .as_secs(),
+            notify_from: config
+                .value("session.auth.anomaly.notify.from")
+                .map(|from| from.to_string())
+                .or_else(|| {
+                    config
+                        .value("lookup.default.domain")
+                        .map(|domain| format!("postmaster@{domain}"))
+                })
+                .unwrap_or_else(|| "postmaster@localhost".to_string()),
+            notify_to: config
+                .values("session.auth.anomaly.notify.to")
+                .map(|(_, addr)| addr.to_string())
+                .collect(),
+        })
+    }
+}
+
+impl RcptVerify {
+    pub fn parse(config: &mut Config, token_map: &TokenMap) -> Option<Self> {
+        let url = config.value("session.rcpt.verify.url")?.to_string();
+
+        Some(RcptVerify {
+            enable: IfBlock::try_parse(config, "session.rcpt.verify.enable", token_map)
+                .unwrap_or_else(|| IfBlock::new::<()>("session.rcpt.verify.enable", [], "true")),
+            url,
+            timeout: config
+                .property_or_default("session.rcpt.verify.timeout", "10s")
+                .unwrap_or_else(|| Duration::from_secs(10)),
+            headers: parse_http_headers(config, "session.rcpt.verify"),
+            tls_allow_invalid_certs: config
+                .property_or_default("session.rcpt.verify.allow-invalid-certs", "false")
+                .unwrap_or_default(),
+            tempfail_on_error: config
+                .property_or_default("session.rcpt.verify.options.tempfail-on-error", "true")
+                .unwrap_or(true),
+            cache_valid: config
+                .property_or_default("session.rcpt.verify.cache.valid", "1h")
+                .unwrap_or_else(|| Duration::from_secs(3600)),
+            cache_invalid: config
+                .property_or_default("session.rcpt.verify.cache.invalid", "10m")
+                .unwrap_or_else(|| Duration::from_secs(600)),
+            max_response_size: config
+                .property_or_default("session.rcpt.verify.options.max-response-size", "65536")
+                .unwrap_or(65536),
+        })
+    }
+}
+
+impl SenderReputation {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default::<bool>("session.reputation.enable", "false")
+            .unwrap_or_default()
+        {
+            return None;
+        }
+
+        let throttle = if config
+            .property_or_default::<bool>("session.reputation.throttle.enable", "true")
+            .unwrap_or(true)
+        {
+            let action = config
+                .value("session.reputation.throttle.action")
+                .unwrap_or("delay")
+                .to_string();
+            let delay = config
+                .property_or_default::<Duration>("session.reputation.throttle.delay", "5s")
+                .unwrap_or_else(|| Duration::from_secs(5));
+
+            Some(ReputationThrottle {
+                threshold: config
+                    .property_or_default("session.reputation.throttle.threshold", "0.9")
+                    .unwrap_or(0.9),
+                action: match action.as_str() {
+                    "reject" => ReputationAction::Reject,
+                    "delay" => ReputationAction::Delay(delay),
+                    _ => {
+                        config.new_parse_error(
+                            "session.reputation.throttle.action",
+                            format!("Invalid reputation throttle action {action:?}"),
+                        );
+                        ReputationAction::Delay(delay)
+                    }
+                },
+            })
+        } else {
+            None
+        };
+
+        let feed = if config
+            .property_or_default::<bool>("session.reputation.feed.enable", "false")
+            .unwrap_or_default()
+        {
+            Some(ReputationFeed {
+                weight: config
+                    .property_or_default("session.reputation.feed.weight", "0.5")
+                    .unwrap_or(0.5),
+                expiry: config
+                    .property_or_default::<Duration>("session.reputation.feed.expiry", "7d")
+                    .unwrap_or_else(|| Duration::from_secs(7 * 86400))
+                    .as_secs(),
+                threshold: config
+                    .property_or_default("session.reputation.feed.threshold", "0.8")
+                    .unwrap_or(0.8),
+                max_entries: config
+                    .property_or_default::<usize>("session.reputation.feed.max-entries", "10000")
+                    .unwrap_or(10000)
+                    .max(1),
+            })
+        } else {
+            None
+        };
+
+        Some(SenderReputation {
+            expiry: config
+                .property_or_default::<Duration>("session.reputation.expiry", "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+                .as_secs(),
+            half_life: config
+                .property_or_default::<Duration>("session.reputation.half-life", "7d")
+                .unwrap_or_else(|| Duration::from_secs(7 * 86400))
+                .as_secs()
+                .max(1),
+            min_samples: config
+                .property_or_default("session.reputation.min-samples", "5")
+                .unwrap_or(5.0),
+            factor: config
+                .property_or_default("session.reputation.factor", "5.0")
+                .unwrap_or(5.0),
+            throttle,
+            feed,
+        })
+    }
+}
+
+impl ReputationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReputationKind::Ip => "ip",
+            ReputationKind::Domain => "domain",
+            ReputationKind::Dkim => "dkim",
+        }
+    }
+}
+
+impl ReputationEntity {
+    pub fn key(&self) -> Vec<u8> {
+        format!("rep:{}:{}", self.kind.as_str(), self.value).into_bytes()
+    }
+
+    /// Returns the anonymized identifier of the entity used in reputation feeds.
+    pub fn feed_hash(&self) -> String {
+        Sha256::digest(format!("{}:{}", self.kind.as_str(), self.value.to_lowercase()).as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    pub fn feed_key(&self) -> Vec<u8> {
+        format!("rep:feed:{}", self.feed_hash()).into_bytes()
+    }
+}
+
+impl OutboundSpam {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default::<bool>("session.data.outbound-spam.enable", "false")
+            .unwrap_or_default()
+        {
+            return None;
+        }
+
+        let action = config
+            .value("session.data.outbound-spam.action")
+            .unwrap_or("hold")
+            .to_string();
+        Some(OutboundSpam {
+            script: config
+                .value("session.data.outbound-spam.script")
+                .unwrap_or("spam-filter")
+                .to_string(),
+            threshold: config
+                .property_or_default("session.data.outbound-spam.threshold", "5.0")
+                .unwrap_or(5.0),
+            action: match action.as_str() {
+                "reject" => OutboundSpamAction::Reject,
+                "hold" => OutboundSpamAction::Hold,
+                _ => {
+                    config.new_parse_error(
+                        "session.data.outbound-spam.action",
+                        format!("Invalid outbound spam action {action:?}"),
+                    );
+                    OutboundSpamAction::Hold
+                }
+            },
+        })
+    }
+}
+
+impl Rspamd {
+    pub fn parse(config: &mut Config, token_map: &TokenMap) -> Option<Self> {
+        let url = config
+            .value("session.data.rspamd.url")?
+            .trim_end_matches('/');
+        let url = if url.ends_with("/checkv2") {
+            url.to_string()
+        } else {
+            format!("{url}/checkv2")
+        };
+
+        let mut headers = HeaderMap::new();
+        if let Some(password) = config.value("session.data.rspamd.password") {
+            match HeaderValue::from_str(password) {
+                Ok(password) => {
+                    headers.insert(HeaderName::from_static("password"), password);
+                }
+                Err(err) => {
+                    config.new_parse_error(
+                        "session.data.rspamd.password",
+                        format!("Invalid Rspamd password: {err}"),
+                    );
+                }
+            }
+        }
+
+        Some(Rspamd {
+            enable: IfBlock::try_parse(config, "session.data.rspamd.enable", token_map)
+                .unwrap_or_else(|| IfBlock::new::<()>("session.data.rspamd.enable", [], "true")),
+            url,
+            timeout: config
+                .property_or_default("session.data.rspamd.timeout", "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+            headers,
+            tls_allow_invalid_certs: config
+                .property_or_default("session.data.rspamd.allow-invalid-certs", "false")
+                .unwrap_or_default(),
+            tempfail_on_error: config
+                .property_or_default("session.data.rspamd.options.tempfail-on-error", "false")
+                .unwrap_or_default(),
+            max_response_size: config
+                .property_or_default("session.data.rspamd.options.max-response-size", "1048576")
+                .unwrap_or(1048576),
+            add_result_header: config
+                .property_or_default("session.data.rspamd.add-headers.result", "true")
+                .unwrap_or(true),
+        })
+    }
+}
+
+impl Antivirus {
+    pub fn parse(config: &mut Config, token_map: &TokenMap) -> Option<Self> {
+        let scanner_type = config.value("session.data.antivirus.type")?.to_string();
+        let action = config
+            .value("session.data.antivirus.action")
+            .unwrap_or("reject")
+            .to_string();
+        let on_error = config
+            .value("session.data.antivirus.on-error")
+            .unwrap_or("tempfail")
+            .to_string();
+        let scanner = match scanner_type.as_str() {
+            "clamd" => AntivirusScanner::Clamd {
+                address: config
+                    .value_require("session.data.antivirus.address")?
+                    .to_string(),
+            },
+            "icap" => {
+                let url = config
+                    .value_require("session.data.antivirus.url")?
+                    .to_string();
+                let Some(authority) = url
+                    .strip_prefix("icap://")
+                    .and_then(|url| url.split('/').next())
+                    .filter(|authority| !authority.is_empty())
+                    .map(|authority| authority.to_string())
+                else {
+                    config.new_parse_error(
+                        "session.data.antivirus.url",
+                        format!("Invalid ICAP URL {url:?}"),
+                    );
+                    return None;
+                };
+
+                AntivirusScanner::Icap {
+                    address: if authority.contains(':') {
+                        authority.clone()
+                    } else {
+                        format!("{authority}:1344")
+                    },
+                    host: authority,
+                    url,
+                }
+            }
+            _ => {
+                config.new_parse_error(
+                    "session.data.antivirus.type",
+                    format!("Invalid malware scanner type {scanner_type:?}"),
+                );
+                return None;
+            }
+        };
+
+        Some(Antivirus {
+            enable: IfBlock::try_parse(config, "session.data.antivirus.enable", token_map)
+                .unwrap_or_else(|| IfBlock::new::<()>("session.data.antivirus.enable", [], "true")),
+            scanner,
+            timeout: config
+                .property_or_default("session.data.antivirus.timeout", "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+            action: match action.as_str() {
+                "reject" => AntivirusAction::Reject,
+                "quarantine" => AntivirusAction::Quarantine,
+                "tag" => AntivirusAction::Tag,
+                _ => {
+                    config.new_parse_error(
+                        "session.data.antivirus.action",
+                        format!("Invalid malware action {action:?}"),
+                    );
+                    AntivirusAction::Reject
+                }
+            },
+            tempfail_on_error: match on_error.as_str() {
+                "tempfail" => true,
+                "accept" => false,
+                _ => {
+                    config.new_parse_error(
+                        "session.data.antivirus.on-error",
+                        format!("Invalid malware scanner error action {on_error:?}"),
+                    );
+                    true
+                }
+            },
+            cache_ttl: config
+                .property_or_default("session.data.antivirus.cache.ttl", "1d")
+                .unwrap_or_else(|| Duration::from_secs(86400)),
+            max_part_size: config
+                .property_or_default("session.data.antivirus.max-part-size", "26214400")
+                .unwrap_or(26214400),
+        })
+    }
+}
+
+impl AttachmentPolicy {
+    pub fn parse(
+        config: &mut Config,
+        id: &str,
+        rcpt_vars: &TokenMap,
+        queue_rcpt_vars: &TokenMap,
+    ) -> Option<Self> {
+        let prefix = ("session.data.attachments", id);
+        let stage = match config
+            .value((prefix.0, prefix.1, "stage"))
+            .unwrap_or("data")
+            .to_string()
+            .as_str()
+        {
+            "data" => AttachmentStage::Data,
+            "delivery" => AttachmentStage::Delivery,
+            stage => {
+                config.new_parse_error(
+                    (prefix.0, prefix.1, "stage"),
+                    format!("Invalid attachment policy stage {stage:?}"),
+                );
+                return None;
+            }
+        };
+        let action = parse_attachment_action(config, id, "action", "reject")?;
+        let oversize = parse_attachment_action(config, id, "oversize", "strip")?;
+        let mismatch = match config
+            .value((prefix.0, prefix.1, "mismatch"))
+            .unwrap_or("allow")
+            .to_string()
+            .as_str()
+        {
+            "allow" => None,
+            _ => parse_attachment_action(config, id, "mismatch", "reject")?.into(),
+        };
+        Some(AttachmentPolicy {
+            id: id.to_string(),
+            enable: IfBlock::try_parse(
+                config,
+                (prefix.0, prefix.1, "enable"),
+                match stage {
+                    AttachmentStage::Data => rcpt_vars,
+                    AttachmentStage::Delivery => queue_rcpt_vars,
+                },
+            )
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(format!("session.data.attachments.{id}.enable"), [], "true")
+            }),
+            stage,
+            action,
+            block_extensions: config
+                .values((prefix.0, prefix.1, "block.extensions"))
+                .map(|(_, ext)| ext.trim_start_matches('.').to_ascii_lowercase())
+                .collect(),
+            block_types: config
+                .values((prefix.0, prefix.1, "block.types"))
+                .map(|(_, file_type)| file_type.to_ascii_lowercase())
+                .collect(),
+            max_size: config.property((prefix.0, prefix.1, "max-size")),
+            oversize,
+            mismatch,
+        })
+    }
+}
+
+fn parse_attachment_action(
+    config: &mut Config,
+    id: &str,
+    key: &str,
+    default: &str,
+) -> Option<AttachmentAction> {
+    match config
+        .value(("session.data.attachments", id, key))
+        .unwrap_or(default)
+        .to_string()
+        .as_str()
+    {
+        "reject" => Some(AttachmentAction::Reject),
+        "strip" => Some(AttachmentAction::Strip),
+        action => {
+            config.new_parse_error(
+                ("session.data.attachments", id, key),
+                format!("Invalid attachment policy action {action:?}"),
+            );
+            None
+        }
+    }
+}
+
 impl SessionThrottle {
     pub fn parse(config: &mut Config) -> Self {
         let mut throttle = SessionThrottle::default();
@@ -598,32 +1378,215 @@ fn parse_milter(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<M
 }
 
 fn parse_hooks(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<MTAHook> {
+    let headers = parse_http_headers(config, &format!("session.hook.{id}"));
+
+    Some(MTAHook {
+        enable: IfBlock::try_parse(config, ("session.hook", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(format!("session.hook.{id}.enable"), [], "false")
+            }),
+        id: id.to_string(),
+        url: config
+            .value_require(("session.hook", id, "url"))?
+            .to_string(),
+        timeout: config
+            .property_or_default(("session.hook", id, "timeout"), "30s")
+            .unwrap_or_else(|| Duration::from_secs(30)),
+        tls_allow_invalid_certs: config
+            .property_or_default(("session.hook", id, "allow-invalid-certs"), "false")
+            .unwrap_or_default(),
+        tempfail_on_error: config
+            .property_or_default(("session.hook", id, "options.tempfail-on-error"), "true")
+            .unwrap_or(true),
+        run_on_stage: parse_stages(config, "session.hook", id),
+        max_response_size: config
+            .property_or_default(
+                ("session.hook", id, "options.max-response-size"),
+                "52428800",
+            )
+            .unwrap_or(52428800),
+        headers,
+    })
+}
+
+fn parse_wasm_hooks(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<WasmHook> {
+    let module = wasm::load_module(config.value_require(("session.hook", id, "module"))?)
+        .map_err(|err| config.new_build_error(("session.hook", id, "module"), err))
+        .ok()?;
+
+    Some(WasmHook {
+        enable: IfBlock::try_parse(config, ("session.hook", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(format!("session.hook.{id}.enable"), [], "false")
+            }),
+        id: id.to_string(),
+        module,
+        limits: WasmLimits {
+            fuel: config
+                .property_or_default(("session.hook", id, "limits.fuel"), "100000000")
+                .unwrap_or(100_000_000),
+            memory: config
+                .property_or_default(("session.hook", id, "limits.memory"), "16777216")
+                .unwrap_or(16777216),
+        },
+        tempfail_on_error: config
+            .property_or_default(("session.hook", id, "options.tempfail-on-error"), "true")
+            .unwrap_or(true),
+        run_on_stage: parse_stages(config, "session.hook", id),
+    })
+}
+
+impl DomainAddressing {
+    fn parse(config: &mut Config) -> Vec<Self> {
+        let mut addressing = Vec::new();
+        for id in config
+            .sub_keys("session.rcpt.addressing", "")
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+        {
+            let prefix = format!("session.rcpt.addressing.{id}");
+            let domains = config
+                .values((prefix.as_str(), "domains"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+            if domains.is_empty() {
+                config.new_parse_error(
+                    (prefix.as_str(), "domains"),
+                    "At least one domain is required",
+                );
+                continue;
+            }
+
+            let catch_all = match config.value((prefix.as_str(), "catch-all")) {
+                None => CatchAll::Inherit,
+                Some("false") => CatchAll::Disable,
+                Some("true") => CatchAll::Domain,
+                Some(address) if address.contains('@') => {
+                    CatchAll::Address(address.trim().to_lowercase())
+                }
+                Some(address) => {
+                    let err = format!("Invalid catch-all address {address:?}");
+                    config.new_parse_error((prefix.as_str(), "catch-all"), err);
+                    CatchAll::Inherit
+                }
+            };
+
+            let subaddressing = if config
+                .property_or_default((prefix.as_str(), "sub-addressing.enable"), "true")
+                .unwrap_or(true)
+            {
+                let delimiters = config
+                    .value((prefix.as_str(), "sub-addressing.delimiter"))
+                    .unwrap_or("+")
+                    .chars()
+                    .collect::<Vec<_>>();
+                if delimiters.is_empty() {
+                    config.new_parse_error(
+                        (prefix.as_str(), "sub-addressing.delimiter"),
+                        "Delimiter cannot be empty",
+                    );
+                }
+
+                Some(SubAddressing {
+                    delimiters,
+                    file_into: config
+                        .property_or_default((prefix.as_str(), "sub-addressing.file-into"), "false")
+                        .unwrap_or(false),
+                    create_folder: config
+                        .property_or_default(
+                            (prefix.as_str(), "sub-addressing.create-folder"),
+                            "false",
+                        )
+                        .unwrap_or(false),
+                })
+                .filter(|subaddressing| !subaddressing.delimiters.is_empty())
+            } else {
+                None
+            };
+
+            addressing.push(DomainAddressing {
+                domains,
+                catch_all,
+                subaddressing,
+            });
+        }
+
+        addressing
+    }
+}
+
+fn parse_rewrite(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<AddressRewrite> {
+    let map = match config.value_require(("session.rewrite", id, "type"))? {
+        "regex" => {
+            let pattern = config
+                .value_require(("session.rewrite", id, "pattern"))?
+                .to_string();
+            RewriteMap::Regex {
+                pattern: Regex::new(&pattern)
+                    .map_err(|err| {
+                        config.new_parse_error(
+                            ("session.rewrite", id, "pattern"),
+                            format!("Invalid regular expression {pattern:?}: {err}"),
+                        )
+                    })
+                    .ok()?,
+                replacement: config
+                    .value_require(("session.rewrite", id, "replacement"))?
+                    .to_string(),
+            }
+        }
+        "lookup" => RewriteMap::Lookup {
+            store: config
+                .value_require(("session.rewrite", id, "store"))?
+                .to_string(),
+        },
+        other => {
+            let other = other.to_string();
+            config.new_parse_error(
+                ("session.rewrite", id, "type"),
+                format!("Invalid rewrite type {other:?}"),
+            );
+            return None;
+        }
+    };
+
+    Some(AddressRewrite {
+        enable: IfBlock::try_parse(config, ("session.rewrite", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(format!("session.rewrite.{id}.enable"), [], "true")
+            }),
+        id: id.to_string(),
+        direction: config
+            .property_or_default(("session.rewrite", id, "direction"), "recipient")
+            .unwrap_or(RewriteDirection::Recipient),
+        map,
+    })
+}
+
+fn parse_http_headers(config: &mut Config, prefix: &str) -> HeaderMap {
     let mut headers = HeaderMap::new();
 
     for (header, value) in config
-        .values(("session.hook", id, "headers"))
+        .values((prefix, "headers"))
         .map(|(_, v)| {
             if let Some((k, v)) = v.split_once(':') {
                 Ok((
                     HeaderName::from_str(k.trim()).map_err(|err| {
-                        format!(
-                            "Invalid header found in property \"session.hook.{id}.headers\": {err}",
-                        )
+                        format!("Invalid header found in property \"{prefix}.headers\": {err}",)
                     })?,
                     HeaderValue::from_str(v.trim()).map_err(|err| {
-                        format!(
-                            "Invalid header found in property \"session.hook.{id}.headers\": {err}",
-                        )
+                        format!("Invalid header found in property \"{prefix}.headers\": {err}",)
                     })?,
                 ))
             } else {
                 Err(format!(
-                    "Invalid header found in property \"session.hook.{id}.headers\": {v}",
+                    "Invalid header found in property \"{prefix}.headers\": {v}",
                 ))
             }
         })
         .collect::<Result<Vec<(HeaderName, HeaderValue)>, String>>()
-        .map_err(|e| config.new_parse_error(("session.hook", id, "headers"), e))
+        .map_err(|e| config.new_parse_error((prefix, "headers"), e))
         .unwrap_or_default()
     {
         headers.insert(header, value);
@@ -631,8 +1594,8 @@ fn parse_hooks(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<MT
 
     headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
     if let (Some(name), Some(secret)) = (
-        config.value(("session.hook", id, "auth.username")),
-        config.value(("session.hook", id, "auth.secret")),
+        config.value((prefix, "auth.username")),
+        config.value((prefix, "auth.secret")),
     ) {
         headers.insert(
             AUTHORIZATION,
@@ -642,33 +1605,7 @@ fn parse_hooks(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<MT
         );
     }
 
-    Some(MTAHook {
-        enable: IfBlock::try_parse(config, ("session.hook", id, "enable"), token_map)
-            .unwrap_or_else(|| {
-                IfBlock::new::<()>(format!("session.hook.{id}.enable"), [], "false")
-            }),
-        id: id.to_string(),
-        url: config
-            .value_require(("session.hook", id, "url"))?
-            .to_string(),
-        timeout: config
-            .property_or_default(("session.hook", id, "timeout"), "30s")
-            .unwrap_or_else(|| Duration::from_secs(30)),
-        tls_allow_invalid_certs: config
-            .property_or_default(("session.hook", id, "allow-invalid-certs"), "false")
-            .unwrap_or_default(),
-        tempfail_on_error: config
-            .property_or_default(("session.hook", id, "options.tempfail-on-error"), "true")
-            .unwrap_or(true),
-        run_on_stage: parse_stages(config, "session.hook", id),
-        max_response_size: config
-            .property_or_default(
-                ("session.hook", id, "options.max-response-size"),
-                "52428800",
-            )
-            .unwrap_or(52428800),
-        headers,
-    })
+    headers
 }
 
 fn parse_stages(config: &mut Config, prefix: &str, id: &str) -> AHashSet<Stage> {
@@ -683,6 +1620,7 @@ fn parse_stages(config: &mut Config, prefix: &str, id: &str) -> AHashSet<Stage>
             "mail" => Stage::Mail,
             "rcpt" => Stage::Rcpt,
             "data" => Stage::Data,
+            "classify" => Stage::Classify,
             _ => {
                 invalid.push(value);
                 continue;
@@ -797,6 +1735,8 @@ impl Default for SessionConfig {
                 max_recipients: IfBlock::new::<()>("session.rcpt.max-recipients", [], "100"),
                 catch_all: AddressMapping::Enable,
                 subaddressing: AddressMapping::Enable,
+                addressing: Default::default(),
+                verify: None,
             },
             data: Data {
                 #[cfg(feature = "test_mode")]
@@ -845,6 +1785,18 @@ impl Default for SessionConfig {
                     [("local_port == 25", "true")],
                     "false",
                 ),
+                add_classification: IfBlock::new::<()>(
+                    "session.data.add-headers.classification",
+                    [],
+                    "false",
+                ),
+                classification_tags: Default::default(),
+                strip_classification: true,
+                outbound_spam: None,
+                rspamd: None,
+                antivirus: None,
+                attachment_policies: Default::default(),
+                verp: IfBlock::new::<()>("session.data.verp", [], "false"),
             },
             extensions: Extensions {
                 pipelining: IfBlock::new::<()>("session.extensions.pipelining", [], "true"),
@@ -883,8 +1835,12 @@ impl Default for SessionConfig {
                 ),
             },
             mta_sts_policy: None,
+            anomaly: None,
+            reputation: None,
             milters: Default::default(),
             hooks: Default::default(),
+            wasm_hooks: Default::default(),
+            rewrite: Default::default(),
         }
     }
 }
@@ -892,6 +1848,27 @@ impl Default for SessionConfig {
 #[derive(Default)]
 pub struct Mechanism(u64);
 
+impl ParseValue for RewriteDirection {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "sender" => Ok(RewriteDirection::Sender),
+            "recipient" => Ok(RewriteDirection::Recipient),
+            "both" => Ok(RewriteDirection::Both),
+            _ => Err(format!("Invalid rewrite direction {value:?}")),
+        }
+    }
+}
+
+impl RewriteDirection {
+    pub fn is_sender(&self) -> bool {
+        matches!(self, RewriteDirection::Sender | RewriteDirection::Both)
+    }
+
+    pub fn is_recipient(&self) -> bool {
+        matches!(self, RewriteDirection::Recipient | RewriteDirection::Both)
+    }
+}
+
 impl ParseValue for Mechanism {
     fn parse_value(value: &str) -> Result<Self, String> {
         Ok(Mechanism(match value.to_ascii_uppercase().as_str() {
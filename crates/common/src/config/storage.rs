@@ -10,7 +10,7 @@ use ahash::AHashMap;
 use directory::Directory;
 use store::{write::purge::PurgeSchedule, BlobStore, FtsStore, LookupStore, Store};
 
-use crate::manager::config::ConfigManager;
+use crate::{manager::config::ConfigManager, priority::StorePriority};
 
 #[derive(Default, Clone)]
 pub struct Storage {
@@ -21,10 +21,12 @@ pub struct Storage {
     pub directory: Arc<Directory>,
     pub directories: AHashMap<String, Arc<Directory>>,
     pub purge_schedules: Vec<PurgeSchedule>,
+    pub priority: StorePriority,
     pub config: ConfigManager,
 
     pub stores: AHashMap<String, Store>,
     pub blobs: AHashMap<String, BlobStore>,
     pub lookups: AHashMap<String, LookupStore>,
     pub ftss: AHashMap<String, FtsStore>,
+    pub settings: AHashMap<String, u64>,
 }
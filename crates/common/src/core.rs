@@ -4,15 +4,16 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::sync::Arc;
+use std::sync::{atomic::Ordering, Arc};
 
 use directory::{backend::internal::manage::ManageDirectory, Directory, Type};
 use sieve::Sieve;
 use store::{
-    write::{QueueClass, ValueClass},
-    BlobStore, FtsStore, IterateParams, LookupStore, Store, ValueKey,
+    write::{now, BatchBuilder, BlobOp, QueueClass, ValueClass},
+    BlobStore, FtsStore, IterateParams, LookupStore, Serialize, Store, ValueKey,
 };
 use trc::AddContext;
+use utils::BlobHash;
 
 use crate::{
     config::smtp::{
@@ -80,6 +81,62 @@ impl Server {
         })
     }
 
+    /// Increments a counter in the default lookup store without returning its
+    /// value. When write coalescing is enabled the increment is merged with
+    /// other pending ones and written by the housekeeper.
+    pub async fn counter_incr_coalesced(
+        &self,
+        key: Vec<u8>,
+        value: i64,
+        expires: Option<u64>,
+    ) -> trc::Result<()> {
+        if let Some(coalescer) = &self.inner.data.counter_coalescer {
+            if coalescer.add(key, value, expires) {
+                let server = self.clone();
+                tokio::spawn(async move {
+                    if let Some(coalescer) = &server.inner.data.counter_coalescer {
+                        if let Err(err) = coalescer.flush_claimed(server.lookup_store()).await {
+                            trc::error!(err.details("Failed to flush coalesced counters."));
+                        }
+                    }
+                });
+            }
+            Ok(())
+        } else {
+            self.lookup_store()
+                .counter_incr(key, value, expires, false)
+                .await
+                .map(|_| ())
+                .caused_by(trc::location!())
+        }
+    }
+
+    /// Returns a counter from the default lookup store, including increments
+    /// that have not been written yet.
+    pub async fn counter_get_coalesced(&self, key: Vec<u8>) -> trc::Result<i64> {
+        let pending = self
+            .inner
+            .data
+            .counter_coalescer
+            .as_ref()
+            .map_or(0, |coalescer| coalescer.pending(&key));
+        self.lookup_store()
+            .counter_get(key)
+            .await
+            .map(|value| value + pending)
+            .caused_by(trc::location!())
+    }
+
+    pub async fn flush_counters(&self) {
+        if let Some(coalescer) = &self.inner.data.counter_coalescer {
+            if !coalescer.is_empty() {
+                if let Err(err) = coalescer.flush(self.lookup_store()).await {
+                    trc::error!(err.details("Failed to flush coalesced counters."));
+                }
+            }
+        }
+    }
+
     pub fn get_arc_sealer(&self, name: &str, session_id: u64) -> Option<&ArcSealer> {
         self.core
             .smtp
@@ -152,6 +209,15 @@ impl Server {
         })
     }
 
+    #[inline(always)]
+    pub fn is_read_only(&self) -> bool {
+        self.inner.data.read_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        self.inner.data.read_only.store(read_only, Ordering::Relaxed);
+    }
+
     pub async fn total_queued_messages(&self) -> trc::Result<u64> {
         let mut total = 0;
         self.store()
@@ -185,6 +251,37 @@ impl Server {
             .await
             .caused_by(trc::location!())
     }
+
+    pub async fn blob_dedup_ratio(&self) -> trc::Result<u64> {
+        self.store()
+            .blob_usage()
+            .await
+            .caused_by(trc::location!())
+            .map(|usage| usage.dedup_ratio())
+    }
+
+    /// Writes a message blob that is kept for two minutes, long enough to be ingested.
+    pub async fn put_temporary_blob(&self, data: &[u8]) -> trc::Result<BlobHash> {
+        let hash = BlobHash::from(data);
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            BlobOp::Reserve {
+                hash: hash.clone(),
+                until: now() + 120,
+            },
+            0u32.serialize(),
+        );
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+        self.blob_store()
+            .put_blob(hash.as_slice(), data)
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(hash)
+    }
 }
 
 pub trait BuildServer {
@@ -0,0 +1,230 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    fmt::Display,
+    net::{Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use ahash::AHashMap;
+use dns_update::DnsUpdater;
+
+use self::route53::Route53;
+
+pub mod route53;
+
+#[derive(Default, Clone)]
+pub struct DnsProviders {
+    pub providers: AHashMap<String, DnsProvider>,
+    pub provisioning: Option<DnsProvisioning>,
+}
+
+/// Settings used to publish the records of new domains.
+#[derive(Clone)]
+pub struct DnsProvisioning {
+    pub provider: String,
+    pub ttl: u32,
+    pub propagation_timeout: Duration,
+    pub polling_interval: Duration,
+}
+
+#[derive(Clone)]
+pub enum DnsProvider {
+    Updater(DnsUpdater),
+    Route53(Route53),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsRecord {
+    A {
+        content: Ipv4Addr,
+    },
+    AAAA {
+        content: Ipv6Addr,
+    },
+    CNAME {
+        content: String,
+    },
+    NS {
+        content: String,
+    },
+    MX {
+        content: String,
+        priority: u16,
+    },
+    TXT {
+        content: String,
+    },
+    SRV {
+        content: String,
+        priority: u16,
+        weight: u16,
+        port: u16,
+    },
+    TLSA {
+        content: String,
+    },
+}
+
+#[derive(Debug)]
+pub enum DnsError {
+    Updater(dns_update::Error),
+    Api(String),
+}
+
+impl DnsProvider {
+    /// Creates or replaces the records of the given type under `name`.
+    pub async fn create(
+        &self,
+        name: &str,
+        record: DnsRecord,
+        ttl: u32,
+        origin: &str,
+    ) -> Result<(), DnsError> {
+        self.create_many(name, vec![record], ttl, origin).await
+    }
+
+    /// Creates or replaces the records under `name`, all records must be of the same type.
+    pub async fn create_many(
+        &self,
+        name: &str,
+        records: Vec<DnsRecord>,
+        ttl: u32,
+        origin: &str,
+    ) -> Result<(), DnsError> {
+        match self {
+            DnsProvider::Updater(updater) => {
+                for record in records {
+                    updater
+                        .create(name, record.try_into()?, ttl, origin)
+                        .await
+                        .map_err(DnsError::Updater)?;
+                }
+                Ok(())
+            }
+            DnsProvider::Route53(route53) => route53.upsert(name, records, ttl, origin).await,
+        }
+    }
+
+    /// Removes all records under `name`.
+    pub async fn delete(&self, name: &str, origin: &str) -> Result<(), DnsError> {
+        match self {
+            DnsProvider::Updater(updater) => updater
+                .delete(name, origin)
+                .await
+                .map_err(DnsError::Updater),
+            DnsProvider::Route53(route53) => route53.delete(name, origin).await,
+        }
+    }
+}
+
+impl DnsProviders {
+    pub fn get(&self, id: &str) -> Option<&DnsProvider> {
+        self.providers.get(id)
+    }
+}
+
+impl DnsRecord {
+    pub fn record_type(&self) -> &'static str {
+        match self {
+            DnsRecord::A { .. } => "A",
+            DnsRecord::AAAA { .. } => "AAAA",
+            DnsRecord::CNAME { .. } => "CNAME",
+            DnsRecord::NS { .. } => "NS",
+            DnsRecord::MX { .. } => "MX",
+            DnsRecord::TXT { .. } => "TXT",
+            DnsRecord::SRV { .. } => "SRV",
+            DnsRecord::TLSA { .. } => "TLSA",
+        }
+    }
+
+    /// Parses a record from its type and presentation format content.
+    pub fn parse(typ: &str, content: &str) -> Option<Self> {
+        let mut parts = content.split_ascii_whitespace();
+        match typ.to_ascii_uppercase().as_str() {
+            "A" => content.parse().ok().map(|content| DnsRecord::A { content }),
+            "AAAA" => content
+                .parse()
+                .ok()
+                .map(|content| DnsRecord::AAAA { content }),
+            "CNAME" => DnsRecord::CNAME {
+                content: content.trim_end_matches('.').to_string(),
+            }
+            .into(),
+            "NS" => DnsRecord::NS {
+                content: content.trim_end_matches('.').to_string(),
+            }
+            .into(),
+            "MX" => DnsRecord::MX {
+                priority: parts.next()?.parse().ok()?,
+                content: parts.next()?.trim_end_matches('.').to_string(),
+            }
+            .into(),
+            "TXT" => DnsRecord::TXT {
+                content: content.to_string(),
+            }
+            .into(),
+            "SRV" => DnsRecord::SRV {
+                priority: parts.next()?.parse().ok()?,
+                weight: parts.next()?.parse().ok()?,
+                port: parts.next()?.parse().ok()?,
+                content: parts.next()?.trim_end_matches('.').to_string(),
+            }
+            .into(),
+            "TLSA" => DnsRecord::TLSA {
+                content: content.to_string(),
+            }
+            .into(),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<DnsRecord> for dns_update::DnsRecord {
+    type Error = DnsError;
+
+    fn try_from(record: DnsRecord) -> Result<Self, Self::Error> {
+        match record {
+            DnsRecord::A { content } => Ok(dns_update::DnsRecord::A { content }),
+            DnsRecord::AAAA { content } => Ok(dns_update::DnsRecord::AAAA { content }),
+            DnsRecord::CNAME { content } => Ok(dns_update::DnsRecord::CNAME { content }),
+            DnsRecord::NS { content } => Ok(dns_update::DnsRecord::NS { content }),
+            DnsRecord::MX { content, priority } => {
+                Ok(dns_update::DnsRecord::MX { content, priority })
+            }
+            DnsRecord::TXT { content } => Ok(dns_update::DnsRecord::TXT { content }),
+            DnsRecord::SRV {
+                content,
+                priority,
+                weight,
+                port,
+            } => Ok(dns_update::DnsRecord::SRV {
+                content,
+                priority,
+                weight,
+                port,
+            }),
+            DnsRecord::TLSA { .. } => Err(DnsError::Api(
+                "TLSA records are not supported by this provider".to_string(),
+            )),
+        }
+    }
+}
+
+/// Returns the zone a domain belongs to, used as the default origin.
+pub fn default_origin(domain: &str) -> &str {
+    psl::domain_str(domain).unwrap_or(domain)
+}
+
+impl Display for DnsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DnsError::Updater(err) => err.fmt(f),
+            DnsError::Api(err) => f.write_str(err),
+        }
+    }
+}
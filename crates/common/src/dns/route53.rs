@@ -0,0 +1,379 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::fmt::Write;
+
+use chrono::Utc;
+use reqwest::Method;
+use ring::{digest, hmac};
+
+use super::{DnsError, DnsRecord};
+
+pub const ROUTE53_ENDPOINT: &str = "https://route53.amazonaws.com";
+const API_VERSION: &str = "2013-04-01";
+const XML_NS: &str = "https://route53.amazonaws.com/doc/2013-04-01/";
+
+/// Minimal Amazon Route 53 client supporting record upserts and deletions,
+/// authenticated using AWS Signature Version 4.
+#[derive(Clone)]
+pub struct Route53 {
+    client: reqwest::Client,
+    endpoint: String,
+    host: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    hosted_zone_id: Option<String>,
+}
+
+impl Route53 {
+    pub fn new(
+        client: reqwest::Client,
+        access_key: impl Into<String>,
+        secret_key: impl Into<String>,
+        session_token: Option<String>,
+        hosted_zone_id: Option<String>,
+        endpoint: Option<String>,
+        region: Option<String>,
+    ) -> Self {
+        let endpoint = endpoint
+            .unwrap_or_else(|| ROUTE53_ENDPOINT.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let host = endpoint
+            .split_once("://")
+            .map_or(endpoint.as_str(), |(_, host)| host)
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        Route53 {
+            client,
+            endpoint,
+            host,
+            region: region.unwrap_or_else(|| "us-east-1".to_string()),
+            access_key: access_key.into(),
+            secret_key: secret_key.into(),
+            session_token,
+            hosted_zone_id: hosted_zone_id
+                .map(|id| id.trim_start_matches("/hostedzone/").to_string()),
+        }
+    }
+
+    pub async fn upsert(
+        &self,
+        name: &str,
+        records: Vec<DnsRecord>,
+        ttl: u32,
+        origin: &str,
+    ) -> Result<(), DnsError> {
+        let typ = records
+            .first()
+            .map(|record| record.record_type())
+            .ok_or_else(|| DnsError::Api("No records to publish".to_string()))?;
+        let zone_id = self.hosted_zone_id(origin).await?;
+        let mut values = String::new();
+        for record in records {
+            let _ = write!(
+                values,
+                "<ResourceRecord><Value>{}</Value></ResourceRecord>",
+                xml_escape(&record_value(record))
+            );
+        }
+
+        self.change(
+            &zone_id,
+            format!(
+                concat!(
+                    "<Change><Action>UPSERT</Action><ResourceRecordSet>",
+                    "<Name>{}</Name><Type>{}</Type><TTL>{}</TTL>",
+                    "<ResourceRecords>{}</ResourceRecords>",
+                    "</ResourceRecordSet></Change>"
+                ),
+                xml_escape(&fqdn(name)),
+                typ,
+                ttl,
+                values
+            ),
+        )
+        .await
+    }
+
+    pub async fn delete(&self, name: &str, origin: &str) -> Result<(), DnsError> {
+        let zone_id = self.hosted_zone_id(origin).await?;
+        let name = fqdn(name);
+
+        // Route 53 requires the exact record set to be deleted, so fetch it first
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/{API_VERSION}/hostedzone/{zone_id}/rrset"),
+                &[("maxitems", "100"), ("name", &name)],
+                String::new(),
+            )
+            .await?;
+        let mut changes = String::new();
+        for record_set in xml_elements(&response, "ResourceRecordSet") {
+            if xml_elements(record_set, "Name")
+                .next()
+                .is_some_and(|set_name| set_name.eq_ignore_ascii_case(&name))
+                && !matches!(
+                    xml_elements(record_set, "Type").next(),
+                    Some("SOA" | "NS") | None
+                )
+            {
+                let _ = write!(
+                    changes,
+                    "<Change><Action>DELETE</Action><ResourceRecordSet>{record_set}</ResourceRecordSet></Change>",
+                );
+            }
+        }
+
+        if !changes.is_empty() {
+            self.change(&zone_id, changes).await
+        } else {
+            Err(DnsError::Api(format!("No records found for {name}")))
+        }
+    }
+
+    async fn change(&self, zone_id: &str, changes: String) -> Result<(), DnsError> {
+        self.request(
+            Method::POST,
+            &format!("/{API_VERSION}/hostedzone/{zone_id}/rrset"),
+            &[],
+            format!(
+                concat!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+                    "<ChangeResourceRecordSetsRequest xmlns=\"{}\">",
+                    "<ChangeBatch><Changes>{}</Changes></ChangeBatch>",
+                    "</ChangeResourceRecordSetsRequest>"
+                ),
+                XML_NS, changes
+            ),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn hosted_zone_id(&self, origin: &str) -> Result<String, DnsError> {
+        if let Some(zone_id) = &self.hosted_zone_id {
+            return Ok(zone_id.clone());
+        }
+
+        let origin = fqdn(origin);
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/{API_VERSION}/hostedzonesbyname"),
+                &[("dnsname", &origin), ("maxitems", "1")],
+                String::new(),
+            )
+            .await?;
+
+        let zone_id = xml_elements(&response, "HostedZone")
+            .find(|zone| {
+                xml_elements(zone, "Name")
+                    .next()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(&origin))
+            })
+            .and_then(|zone| xml_elements(zone, "Id").next())
+            .map(|id| id.trim_start_matches("/hostedzone/").to_string())
+            .ok_or_else(|| DnsError::Api(format!("No hosted zone found for {origin}")));
+        zone_id
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: String,
+    ) -> Result<String, DnsError> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let query = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", uri_encode(key), uri_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        // Build canonical request
+        let mut canonical_headers = format!("host:{}\nx-amz-date:{amz_date}\n", self.host);
+        let mut signed_headers = "host;x-amz-date".to_string();
+        if let Some(token) = &self.session_token {
+            let _ = writeln!(canonical_headers, "x-amz-security-token:{token}");
+            signed_headers.push_str(";x-amz-security-token");
+        }
+        let canonical_request = format!(
+            "{}\n{path}\n{query}\n{canonical_headers}\n{signed_headers}\n{}",
+            method.as_str(),
+            hex_digest(body.as_bytes())
+        );
+
+        // Sign request
+        let scope = format!("{date}/{}/route53/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex_digest(canonical_request.as_bytes())
+        );
+        let mut key = format!("AWS4{}", self.secret_key).into_bytes();
+        for part in [
+            date.as_str(),
+            self.region.as_str(),
+            "route53",
+            "aws4_request",
+        ] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        let signature = hex_encode(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        let mut request = self
+            .client
+            .request(
+                method,
+                if query.is_empty() {
+                    format!("{}{path}", self.endpoint)
+                } else {
+                    format!("{}{path}?{query}", self.endpoint)
+                },
+            )
+            .header("x-amz-date", &amz_date)
+            .header(
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                    self.access_key
+                ),
+            );
+        if let Some(token) = &self.session_token {
+            request = request.header("x-amz-security-token", token);
+        }
+        if !body.is_empty() {
+            request = request.header("content-type", "text/xml").body(body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| DnsError::Api(format!("Route53 request failed: {err}")))?;
+        let status = response.status();
+        let response = response
+            .text()
+            .await
+            .map_err(|err| DnsError::Api(format!("Failed to read Route53 response: {err}")))?;
+
+        if status.is_success() {
+            Ok(response)
+        } else {
+            Err(DnsError::Api(format!(
+                "Route53 request failed with status {status}: {}",
+                xml_elements(&response, "Message")
+                    .next()
+                    .unwrap_or(response.as_str())
+            )))
+        }
+    }
+}
+
+fn record_value(record: DnsRecord) -> String {
+    match record {
+        DnsRecord::A { content } => content.to_string(),
+        DnsRecord::AAAA { content } => content.to_string(),
+        DnsRecord::CNAME { content } => fqdn(&content),
+        DnsRecord::NS { content } => fqdn(&content),
+        DnsRecord::MX { content, priority } => format!("{priority} {}", fqdn(&content)),
+        DnsRecord::TXT { content } => {
+            // TXT values are limited to 255 characters per string
+            let mut value = String::with_capacity(content.len() + 4);
+            for (pos, chunk) in content.as_bytes().chunks(255).enumerate() {
+                if pos > 0 {
+                    value.push(' ');
+                }
+                value.push('"');
+                for ch in String::from_utf8_lossy(chunk).chars() {
+                    if matches!(ch, '"' | '\\') {
+                        value.push('\\');
+                    }
+                    value.push(ch);
+                }
+                value.push('"');
+            }
+            value
+        }
+        DnsRecord::SRV {
+            content,
+            priority,
+            weight,
+            port,
+        } => format!("{priority} {weight} {port} {}", fqdn(&content)),
+        DnsRecord::TLSA { content } => content,
+    }
+}
+
+fn fqdn(name: &str) -> String {
+    format!("{}.", name.trim_end_matches('.'))
+}
+
+/// Returns the contents of all elements with the given tag name.
+fn xml_elements<'x>(xml: &'x str, tag: &str) -> impl Iterator<Item = &'x str> {
+    let start_tag = format!("<{tag}>");
+    let end_tag = format!("</{tag}>");
+    let mut xml = xml;
+
+    std::iter::from_fn(move || {
+        let start = xml.find(&start_tag)? + start_tag.len();
+        let end = start + xml[start..].find(&end_tag)?;
+        let contents = &xml[start..end];
+        xml = &xml[end + end_tag.len()..];
+        Some(contents)
+    })
+}
+
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn uri_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+fn hex_digest(data: &[u8]) -> String {
+    hex_encode(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+fn hex_encode(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(data.len() * 2);
+    for byte in data {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
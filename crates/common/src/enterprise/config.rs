@@ -27,7 +27,7 @@ use crate::{
 
 use super::{
     license::LicenseKey, llm::AiApiConfig, AlertContent, AlertContentToken, AlertMethod,
-    Enterprise, MetricAlert, MetricStore, TraceStore, Undelete,
+    EncryptionEscrow, Enterprise, MetricAlert, MetricStore, TraceStore, Undelete,
 };
 
 impl Enterprise {
@@ -198,6 +198,23 @@ impl Enterprise {
             metrics_store,
             metrics_alerts: parse_metric_alerts(config),
             ai_apis,
+            encryption_escrow: EncryptionEscrow {
+                pgp: config
+                    .value("enterprise.encryption.escrow.pgp")
+                    .map(|s| s.to_string()),
+                smime: config
+                    .value("enterprise.encryption.escrow.smime")
+                    .map(|s| s.to_string()),
+                notify_from: config
+                    .value("enterprise.encryption.escrow.notify-from")
+                    .map(|from| from.to_string())
+                    .or_else(|| {
+                        config
+                            .value("lookup.default.domain")
+                            .map(|domain| format!("postmaster@{domain}"))
+                    })
+                    .unwrap_or_else(|| "postmaster@localhost".to_string()),
+            },
         })
     }
 }
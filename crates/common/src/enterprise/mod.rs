@@ -39,6 +39,14 @@ pub struct Enterprise {
     pub metrics_store: Option<MetricStore>,
     pub metrics_alerts: Vec<MetricAlert>,
     pub ai_apis: AHashMap<String, AiApiConfig>,
+    pub encryption_escrow: EncryptionEscrow,
+}
+
+#[derive(Clone, Default)]
+pub struct EncryptionEscrow {
+    pub pgp: Option<String>,
+    pub smime: Option<String>,
+    pub notify_from: String,
 }
 
 #[derive(Clone)]
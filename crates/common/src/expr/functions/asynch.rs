@@ -98,6 +98,23 @@ impl Server {
             }
             F_DNS_QUERY => self.dns_query(params).await,
             F_SQL_QUERY => self.sql_query(params, session_id).await,
+            F_IP_IN_SET => {
+                let set = params.next_as_string();
+                let ip = params.next_as_string().parse::<IpAddr>();
+
+                Ok(match (self.core.network.ip_sets.get(set.as_ref()), ip) {
+                    (Some(networks), Ok(ip)) => networks.iter().any(|network| network.matches(&ip)),
+                    _ => false,
+                }
+                .into())
+            }
+            F_GEOIP_COUNTRY => Ok(params
+                .next_as_string()
+                .parse::<IpAddr>()
+                .ok()
+                .and_then(|ip| self.geoip_country(ip))
+                .unwrap_or_default()
+                .into()),
             _ => Ok(Variable::default()),
         }
     }
@@ -89,6 +89,8 @@ pub const F_COUNTER_INCR: u32 = 5;
 pub const F_COUNTER_GET: u32 = 6;
 pub const F_SQL_QUERY: u32 = 7;
 pub const F_DNS_QUERY: u32 = 8;
+pub const F_IP_IN_SET: u32 = 9;
+pub const F_GEOIP_COUNTRY: u32 = 10;
 
 pub const ASYNC_FUNCTIONS: &[(&str, u32, u32)] = &[
     ("is_local_domain", F_IS_LOCAL_DOMAIN, 2),
@@ -100,4 +102,6 @@ pub const ASYNC_FUNCTIONS: &[(&str, u32, u32)] = &[
     ("counter_get", F_COUNTER_GET, 2),
     ("dns_query", F_DNS_QUERY, 2),
     ("sql_query", F_SQL_QUERY, 3),
+    ("ip_in_set", F_IP_IN_SET, 2),
+    ("geoip_country", F_GEOIP_COUNTRY, 1),
 ];
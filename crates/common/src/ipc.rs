@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{borrow::Cow, sync::Arc, time::Instant};
+use std::{borrow::Cow, net::IpAddr, sync::Arc, time::Instant};
 
 use ahash::RandomState;
 use jmap_proto::types::{state::StateChange, type_state::DataType};
@@ -21,6 +21,7 @@ use crate::{
     config::smtp::{
         report::AggregateFrequency,
         resolver::{Policy, Tlsa},
+        session::{ReputationEntity, ReputationSignal},
     },
     listener::limiter::ConcurrencyLimiter,
 };
@@ -62,6 +63,9 @@ pub enum HousekeeperEvent {
     },
     Purge(PurgeType),
     ReloadSettings,
+    CertificateSourceChanged(String),
+    GeoIpChanged,
+    AddressesChanged,
     Exit,
 }
 
@@ -72,6 +76,27 @@ pub enum PurgeType {
     Account(Option<u32>),
 }
 
+/// Events shared with the other nodes of the cluster over the gossip protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastEvent {
+    ReputationUpdate {
+        entities: Vec<ReputationEntity>,
+        signal: ReputationSignal,
+    },
+    BlockedIp(IpAddr),
+}
+
+/// Cache invalidations published to the other nodes of the cluster through
+/// the shared data store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheInvalidation {
+    Settings,
+    BlockedIps,
+    Permissions,
+    Addresses,
+    AccessToken(u32),
+}
+
 #[derive(Debug)]
 pub enum StateEvent {
     Subscribe {
@@ -132,7 +157,7 @@ pub struct OnHold<T> {
     pub message: T,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct QueueEventLock {
     pub due: u64,
     pub queue_id: u64,
@@ -231,3 +256,40 @@ impl From<(&Option<Arc<Policy>>, &Option<Arc<Tlsa>>)> for PolicyType {
         }
     }
 }
+
+impl CacheInvalidation {
+    pub fn to_payload(&self, sender_id: u64) -> String {
+        match self {
+            CacheInvalidation::Settings => format!("{sender_id}:settings"),
+            CacheInvalidation::BlockedIps => format!("{sender_id}:blocked-ips"),
+            CacheInvalidation::Permissions => format!("{sender_id}:permissions"),
+            CacheInvalidation::Addresses => format!("{sender_id}:addresses"),
+            CacheInvalidation::AccessToken(account_id) => {
+                format!("{sender_id}:access-token:{account_id}")
+            }
+        }
+    }
+
+    pub fn parse_payload(payload: &str) -> Option<(u64, Self)> {
+        let (sender_id, event) = payload.split_once(':')?;
+        let event = match event {
+            "settings" => CacheInvalidation::Settings,
+            "blocked-ips" => CacheInvalidation::BlockedIps,
+            "permissions" => CacheInvalidation::Permissions,
+            "addresses" => CacheInvalidation::Addresses,
+            _ => CacheInvalidation::AccessToken(event.strip_prefix("access-token:")?.parse().ok()?),
+        };
+
+        Some((sender_id.parse().ok()?, event))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheInvalidation::Settings => "settings",
+            CacheInvalidation::BlockedIps => "blocked_ips",
+            CacheInvalidation::Permissions => "permissions",
+            CacheInvalidation::Addresses => "addresses",
+            CacheInvalidation::AccessToken(_) => "access_token",
+        }
+    }
+}
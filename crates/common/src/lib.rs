@@ -8,11 +8,14 @@ use std::{
     collections::BTreeMap,
     hash::{BuildHasher, Hasher},
     net::IpAddr,
-    sync::{atomic::AtomicU8, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicU8},
+        Arc,
+    },
 };
 
 use ahash::{AHashMap, AHashSet, RandomState};
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use auth::{oauth::config::OAuthConfig, roles::RolePermissions, AccessToken};
 use config::{
     imap::ImapConfig,
@@ -24,17 +27,26 @@ use config::{
     telemetry::Metrics,
 };
 use dashmap::DashMap;
+use dns::DnsProviders;
 
 use futures::StreamExt;
 use imap_proto::protocol::list::Attribute;
-use ipc::{DeliveryEvent, HousekeeperEvent, QueueEvent, ReportingEvent, StateEvent};
-use listener::{blocked::Security, limiter::ConcurrencyLimiter, tls::AcmeProviders};
+use ipc::{
+    BroadcastEvent, DeliveryEvent, HousekeeperEvent, QueueEvent, QueueEventLock, ReportingEvent,
+    StateEvent,
+};
+use listener::{
+    blocked::Security, certificates::CertificateSources, geoip::GeoIpReader,
+    health::ListenerStatus, limiter::ConcurrencyLimiter, tls::AcmeProviders,
+};
 
 use manager::webadmin::{Resource, WebAdminManager};
 use nlp::bayes::cache::BayesTokenCache;
 use parking_lot::{Mutex, RwLock};
+use priority::LatencyTracker;
 use reqwest::Response;
 use rustls::sign::CertifiedKey;
+use store::write::coalesce::CounterCoalescer;
 use tokio::sync::{mpsc, Notify};
 use tokio_rustls::TlsConnector;
 use utils::{
@@ -47,12 +59,14 @@ pub mod addresses;
 pub mod auth;
 pub mod config;
 pub mod core;
+pub mod dns;
 #[cfg(feature = "enterprise")]
 pub mod enterprise;
 pub mod expr;
 pub mod ipc;
 pub mod listener;
 pub mod manager;
+pub mod priority;
 pub mod scripts;
 pub mod telemetry;
 
@@ -78,17 +92,23 @@ pub struct Inner {
 pub struct Data {
     pub tls_certificates: ArcSwap<AHashMap<String, Arc<CertifiedKey>>>,
     pub tls_self_signed_cert: Option<Arc<CertifiedKey>>,
+    pub tls_sources: Mutex<AHashMap<String, AHashMap<String, Arc<CertifiedKey>>>>,
+
+    pub listeners: Mutex<AHashMap<String, ListenerStatus>>,
+    pub interactive_latency: LatencyTracker,
 
     pub access_tokens: TtlDashMap<u32, Arc<AccessToken>>,
     pub http_auth_cache: TtlDashMap<String, u32>,
 
     pub blocked_ips: RwLock<AHashSet<IpAddr>>,
     pub blocked_ips_version: AtomicU8,
+    pub geoip: ArcSwapOption<GeoIpReader>,
 
     pub permissions: ADashMap<u32, Arc<RolePermissions>>,
     pub permissions_version: AtomicU8,
 
     pub bayes_cache: BayesTokenCache,
+    pub counter_coalescer: Option<CounterCoalescer>,
     pub remote_lists: RwLock<AHashMap<String, RemoteList>>,
 
     pub jmap_id_gen: SnowflakeIdGenerator,
@@ -97,6 +117,8 @@ pub struct Data {
 
     pub webadmin: WebAdminManager,
     pub config_version: AtomicU8,
+    pub read_only: AtomicBool,
+    pub fts_degraded: AtomicBool,
 
     pub jmap_limiter: DashMap<u32, Arc<ConcurrencyLimiters>, RandomState>,
     pub imap_limiter: DashMap<u32, Arc<ConcurrencyLimiters>, RandomState>,
@@ -110,6 +132,8 @@ pub struct Data {
     pub smtp_session_throttle: DashMap<ThrottleKey, ConcurrencyLimiter, ThrottleKeyHasherBuilder>,
     pub smtp_queue_throttle: DashMap<ThrottleKey, ConcurrencyLimiter, ThrottleKeyHasherBuilder>,
     pub smtp_connectors: TlsConnectors,
+    pub smtp_in_flight: Mutex<AHashMap<u64, QueueEventLock>>,
+    pub smtp_ip_pool_next: Mutex<AHashMap<String, usize>>,
 }
 
 pub struct Ipc {
@@ -119,6 +143,7 @@ pub struct Ipc {
     pub index_tx: Arc<Notify>,
     pub queue_tx: mpsc::Sender<QueueEvent>,
     pub report_tx: mpsc::Sender<ReportingEvent>,
+    pub broadcast_tx: mpsc::Sender<BroadcastEvent>,
 }
 
 pub struct TlsConnectors {
@@ -202,6 +227,8 @@ pub struct Core {
     pub sieve: Scripting,
     pub network: Network,
     pub acme: AcmeProviders,
+    pub certificates: CertificateSources,
+    pub dns: DnsProviders,
     pub oauth: OAuthConfig,
     pub smtp: SmtpConfig,
     pub jmap: JmapConfig,
@@ -341,6 +368,7 @@ impl Default for Ipc {
             index_tx: Default::default(),
             queue_tx: mpsc::channel(IPC_CHANNEL_BUFFER).0,
             report_tx: mpsc::channel(IPC_CHANNEL_BUFFER).0,
+            broadcast_tx: mpsc::channel(IPC_CHANNEL_BUFFER).0,
         }
     }
 }
@@ -13,10 +13,9 @@ pub mod resolver;
 use std::{fmt::Debug, sync::Arc, time::Duration};
 
 use arc_swap::ArcSwap;
-use dns_update::DnsUpdater;
 use rustls::sign::CertifiedKey;
 
-use crate::Server;
+use crate::{dns::DnsProvider, Server};
 
 use self::directory::{Account, ChallengeType};
 
@@ -43,7 +42,7 @@ pub enum ChallengeSettings {
     Http01,
     TlsAlpn01,
     Dns01 {
-        updater: DnsUpdater,
+        provider: DnsProvider,
         origin: Option<String>,
         polling_interval: Duration,
         propagation_timeout: Duration,
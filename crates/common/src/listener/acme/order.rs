@@ -1,7 +1,6 @@
 // Adapted from rustls-acme (https://github.com/FlorianUekermann/rustls-acme), licensed under MIT/Apache-2.0.
 
 use chrono::{DateTime, TimeZone, Utc};
-use dns_update::DnsRecord;
 use futures::future::try_join_all;
 use rcgen::{CertificateParams, DistinguishedName, PKCS_ECDSA_P256_SHA256};
 use rustls::crypto::ring::sign::any_ecdsa_type;
@@ -12,6 +11,7 @@ use std::time::{Duration, Instant};
 use trc::{AcmeEvent, EventType};
 use x509_parser::parse_x509_certificate;
 
+use crate::dns::{default_origin, DnsRecord};
 use crate::listener::acme::directory::Identifier;
 use crate::listener::acme::ChallengeSettings;
 use crate::Server;
@@ -98,11 +98,19 @@ impl Server {
         loop {
             match order.status {
                 OrderStatus::Pending => {
-                    let auth_futures = order
-                        .authorizations
-                        .iter()
-                        .map(|url| self.authorize(provider, &account, url));
-                    try_join_all(auth_futures).await?;
+                    if matches!(provider.challenge, ChallengeSettings::Dns01 { .. }) {
+                        // Wildcard and apex domains share the same challenge record,
+                        // authorize them one at a time.
+                        for url in &order.authorizations {
+                            self.authorize(provider, &account, url).await?;
+                        }
+                    } else {
+                        let auth_futures = order
+                            .authorizations
+                            .iter()
+                            .map(|url| self.authorize(provider, &account, url));
+                        try_join_all(auth_futures).await?;
+                    }
                     trc::event!(
                         Acme(AcmeEvent::AuthCompleted),
                         Id = provider.id.to_string(),
@@ -225,7 +233,7 @@ impl Server {
                             .await?;
                     }
                     ChallengeSettings::Dns01 {
-                        updater,
+                        provider: dns_provider,
                         origin,
                         polling_interval,
                         propagation_timeout,
@@ -236,12 +244,11 @@ impl Server {
                         let name = format!("_acme-challenge.{}", domain);
                         let origin = origin
                             .as_deref()
-                            .or_else(|| psl::domain_str(domain))
-                            .unwrap_or(domain)
+                            .unwrap_or_else(|| default_origin(domain))
                             .to_string();
 
                         // First try deleting the record
-                        if let Err(err) = updater.delete(&name, &origin).await {
+                        if let Err(err) = dns_provider.delete(&name, &origin).await {
                             // Errors are expected if the record does not exist
                             trc::event!(
                                 Acme(AcmeEvent::DnsRecordDeletionFailed),
@@ -253,7 +260,7 @@ impl Server {
                         }
 
                         // Create the record
-                        if let Err(err) = updater
+                        if let Err(err) = dns_provider
                             .create(
                                 &name,
                                 DnsRecord::TXT {
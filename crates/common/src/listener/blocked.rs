@@ -16,7 +16,11 @@ use utils::{
     glob::GlobPattern,
 };
 
-use crate::{manager::config::MatchType, Server};
+use crate::{
+    ipc::{BroadcastEvent, CacheInvalidation},
+    manager::config::MatchType,
+    Server,
+};
 
 #[derive(Debug, Clone)]
 pub struct Security {
@@ -228,6 +232,33 @@ impl Server {
         self.inner.data.blocked_ips.write().insert(ip);
 
         // Write blocked IP to config
+        self.write_blocked_ip(ip).await?;
+
+        // Increment version
+        self.increment_blocked_version();
+
+        // Share with other nodes in the cluster
+        if self.core.network.cluster_share.auto_ban {
+            let _ = self
+                .inner
+                .ipc
+                .broadcast_tx
+                .try_send(BroadcastEvent::BlockedIp(ip));
+        }
+
+        Ok(())
+    }
+
+    /// Adds an IP address that was banned by another node in the cluster.
+    pub async fn block_cluster_ip(&self, ip: IpAddr) -> trc::Result<bool> {
+        if !self.is_ip_allowed(&ip) && self.inner.data.blocked_ips.write().insert(ip) {
+            self.write_blocked_ip(ip).await.map(|_| true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn write_blocked_ip(&self, ip: IpAddr) -> trc::Result<()> {
         self.core
             .storage
             .config
@@ -235,12 +266,7 @@ impl Server {
                 key: format!("{}.{}", BLOCKED_IP_KEY, ip),
                 value: String::new(),
             }])
-            .await?;
-
-        // Increment version
-        self.increment_blocked_version();
-
-        Ok(())
+            .await
     }
 
     pub fn has_auth_fail2ban(&self) -> bool {
@@ -276,6 +302,7 @@ impl Server {
             .data
             .blocked_ips_version
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.notify_cluster(CacheInvalidation::BlockedIps);
     }
 }
 
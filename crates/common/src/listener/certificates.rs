@@ -0,0 +1,369 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use ahash::AHashMap;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use reqwest::header::HeaderMap;
+use rustls::sign::CertifiedKey;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use trc::TlsEvent;
+
+use crate::{
+    config::server::tls::{build_certified_key, certificate_names},
+    ipc::HousekeeperEvent,
+    HttpLimitResponse, Server,
+};
+
+const MAX_VAULT_RESPONSE_SIZE: usize = 1024 * 1024;
+
+pub type CertificateWatcher = RecommendedWatcher;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CertificateSources {
+    pub sources: AHashMap<String, CertificateSource>,
+}
+
+/// External location certificates are loaded from, in addition to the
+/// ones defined under the `certificate` section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateSource {
+    pub id: String,
+    pub kind: CertificateSourceKind,
+    pub refresh: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificateSourceKind {
+    Directory { path: PathBuf, watch: bool },
+    Vault(VaultSettings),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultSettings {
+    pub url: String,
+    pub token: String,
+    pub namespace: Option<String>,
+    pub mount: String,
+    pub path: String,
+    pub cert_field: String,
+    pub key_field: String,
+    pub timeout: Duration,
+    pub tls_allow_invalid_certs: bool,
+}
+
+#[derive(Deserialize)]
+struct VaultResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct VaultList {
+    keys: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct VaultSecret {
+    data: AHashMap<String, String>,
+}
+
+impl Server {
+    /// Loads the certificates of a source, replacing any certificates
+    /// previously obtained from it.
+    pub async fn reload_certificate_source(&self, source: &CertificateSource) -> trc::Result<()> {
+        let certs = match &source.kind {
+            CertificateSourceKind::Directory { path, .. } => source.load_directory(path).await,
+            CertificateSourceKind::Vault(vault) => source.load_vault(vault).await,
+        }
+        .map_err(|err| {
+            trc::EventType::Tls(TlsEvent::CertificateSourceError)
+                .reason(err)
+                .id(source.id.clone())
+        })?;
+
+        // Index certificates by subject name
+        let total = certs.len();
+        let mut names = AHashMap::new();
+        for (entry, cert) in certs {
+            match certificate_names(&cert) {
+                Ok(cert_names) => {
+                    let cert = Arc::new(cert);
+                    for name in cert_names {
+                        names.insert(
+                            name.strip_prefix("*.")
+                                .map(|name| name.to_string())
+                                .unwrap_or(name),
+                            cert.clone(),
+                        );
+                    }
+                }
+                Err(err) => {
+                    trc::event!(
+                        Tls(TlsEvent::CertificateSourceError),
+                        Id = source.id.clone(),
+                        Details = entry,
+                        Reason = err,
+                    );
+                }
+            }
+        }
+
+        trc::event!(
+            Tls(TlsEvent::CertificatesReloaded),
+            Id = source.id.clone(),
+            Total = total,
+        );
+
+        self.set_source_certificates(&source.id, names);
+
+        Ok(())
+    }
+
+    /// Removes the certificates obtained from sources that are no longer configured.
+    pub fn unload_certificate_sources(&self) {
+        let removed = self
+            .inner
+            .data
+            .tls_sources
+            .lock()
+            .keys()
+            .filter(|id| !self.core.certificates.sources.contains_key(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for id in removed {
+            self.set_source_certificates(&id, AHashMap::new());
+        }
+    }
+
+    fn set_source_certificates(&self, id: &str, names: AHashMap<String, Arc<CertifiedKey>>) {
+        let mut sources = self.inner.data.tls_sources.lock();
+        let mut certificates = self.inner.data.tls_certificates.load().as_ref().clone();
+
+        // Remove the certificates previously provided by this source, unless
+        // they have been replaced in the meantime
+        if let Some(previous) = sources.remove(id) {
+            for (name, cert) in previous {
+                if certificates
+                    .get(&name)
+                    .is_some_and(|current| Arc::ptr_eq(current, &cert))
+                {
+                    certificates.remove(&name);
+                }
+            }
+        }
+
+        for (name, cert) in &names {
+            certificates.insert(name.clone(), cert.clone());
+        }
+        if !names.is_empty() {
+            sources.insert(id.to_string(), names);
+        }
+
+        self.inner.data.tls_certificates.store(certificates.into());
+    }
+}
+
+impl CertificateSource {
+    /// Watches the source for changes, notifying the housekeeper when
+    /// any of its files are modified.
+    pub fn watch(
+        &self,
+        tx: mpsc::Sender<HousekeeperEvent>,
+    ) -> Result<Option<CertificateWatcher>, String> {
+        match &self.kind {
+            CertificateSourceKind::Directory { path, watch: true } => {
+                let id = self.id.clone();
+                let mut watcher =
+                    notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                        if event.is_ok_and(|event| {
+                            event.kind.is_create()
+                                || event.kind.is_modify()
+                                || event.kind.is_remove()
+                        }) {
+                            let _ =
+                                tx.try_send(HousekeeperEvent::CertificateSourceChanged(id.clone()));
+                        }
+                    })
+                    .map_err(|err| format!("Failed to create file watcher: {err}"))?;
+                watcher
+                    .watch(path, RecursiveMode::NonRecursive)
+                    .map_err(|err| format!("Failed to watch {}: {err}", path.display()))?;
+
+                Ok(Some(watcher))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn load_directory(&self, path: &Path) -> Result<Vec<(String, CertifiedKey)>, String> {
+        let mut entries = tokio::fs::read_dir(path)
+            .await
+            .map_err(|err| format!("Failed to read directory {}: {err}", path.display()))?;
+        let mut certs = Vec::new();
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|err| format!("Failed to read directory {}: {err}", path.display()))?
+        {
+            let cert_path = entry.path();
+            if !cert_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| matches!(ext, "pem" | "crt" | "cer"))
+            {
+                continue;
+            }
+
+            // Keys are either stored in a .key file with the same name or
+            // bundled in the certificate file
+            let key_path = cert_path.with_extension("key");
+            let result = match tokio::fs::read(&cert_path).await {
+                Ok(cert) => match tokio::fs::read(&key_path).await {
+                    Ok(key) => build_certified_key(cert, key),
+                    Err(_) => build_certified_key(cert.clone(), cert),
+                },
+                Err(err) => Err(format!("Failed to read file: {err}")),
+            };
+
+            match result {
+                Ok(cert) => certs.push((cert_path.display().to_string(), cert)),
+                Err(err) => {
+                    trc::event!(
+                        Tls(TlsEvent::CertificateSourceError),
+                        Id = self.id.clone(),
+                        Details = cert_path.display().to_string(),
+                        Reason = err,
+                    );
+                }
+            }
+        }
+
+        Ok(certs)
+    }
+
+    async fn load_vault(
+        &self,
+        vault: &VaultSettings,
+    ) -> Result<Vec<(String, CertifiedKey)>, String> {
+        let client = reqwest::Client::builder()
+            .timeout(vault.timeout)
+            .danger_accept_invalid_certs(vault.tls_allow_invalid_certs)
+            .build()
+            .map_err(|err| format!("Failed to create HTTP client: {err}"))?;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Vault-Token",
+            vault
+                .token
+                .parse()
+                .map_err(|_| "Invalid Vault token".to_string())?,
+        );
+        if let Some(namespace) = &vault.namespace {
+            headers.insert(
+                "X-Vault-Namespace",
+                namespace
+                    .parse()
+                    .map_err(|_| "Invalid Vault namespace".to_string())?,
+            );
+        }
+        let base_url = vault.url.trim_end_matches('/');
+        let prefix = vault.path.trim_matches('/');
+        let prefix = if !prefix.is_empty() {
+            format!("{prefix}/")
+        } else {
+            String::new()
+        };
+
+        // List secrets
+        let list = vault_request::<VaultList>(
+            &client,
+            &headers,
+            &format!("{base_url}/v1/{}/metadata/{prefix}?list=true", vault.mount),
+        )
+        .await?;
+
+        let mut certs = Vec::new();
+        for key in list.keys {
+            // Skip folders
+            if key.ends_with('/') {
+                continue;
+            }
+
+            let result = vault_request::<VaultSecret>(
+                &client,
+                &headers,
+                &format!("{base_url}/v1/{}/data/{prefix}{key}", vault.mount),
+            )
+            .await
+            .and_then(|mut secret| {
+                match (
+                    secret.data.remove(&vault.cert_field),
+                    secret.data.remove(&vault.key_field),
+                ) {
+                    (Some(cert), Some(pk)) => {
+                        build_certified_key(cert.into_bytes(), pk.into_bytes())
+                    }
+                    _ => Err(format!(
+                        "Secret is missing the {:?} or {:?} fields",
+                        vault.cert_field, vault.key_field
+                    )),
+                }
+            });
+
+            match result {
+                Ok(cert) => certs.push((key, cert)),
+                Err(err) => {
+                    trc::event!(
+                        Tls(TlsEvent::CertificateSourceError),
+                        Id = self.id.clone(),
+                        Details = key,
+                        Reason = err,
+                    );
+                }
+            }
+        }
+
+        Ok(certs)
+    }
+}
+
+async fn vault_request<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    headers: &HeaderMap,
+    url: &str,
+) -> Result<T, String> {
+    let response = client
+        .get(url)
+        .headers(headers.clone())
+        .send()
+        .await
+        .map_err(|err| format!("Vault request failed: {err}"))?;
+
+    if response.status().is_success() {
+        serde_json::from_slice::<VaultResponse<T>>(
+            &response
+                .bytes_with_limit(MAX_VAULT_RESPONSE_SIZE)
+                .await
+                .map_err(|err| format!("Failed to read Vault response: {err}"))?
+                .ok_or_else(|| "Vault response too large".to_string())?,
+        )
+        .map(|response| response.data)
+        .map_err(|err| format!("Failed to parse Vault response: {err}"))
+    } else {
+        Err(format!(
+            "Vault request to {url} failed with code {}",
+            response.status().as_u16()
+        ))
+    }
+}
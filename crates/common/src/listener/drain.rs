@@ -0,0 +1,101 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use trc::ServerEvent;
+
+use crate::{ipc::QueueEventLock, Server};
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Server {
+    /// Waits for the in-flight sessions and deliveries to finish, up to the
+    /// configured grace period. Returns the deliveries that did not complete
+    /// in time so they can be released for another node or the next start.
+    pub async fn drain(&self) -> Vec<QueueEventLock> {
+        let grace_period = self.core.network.grace_period;
+        let start_time = Instant::now();
+        let mut is_draining = false;
+
+        loop {
+            let sessions = self.in_flight_sessions();
+            let deliveries = self.inner.data.smtp_in_flight.lock().len();
+
+            if sessions == 0 && deliveries == 0 {
+                if is_draining {
+                    trc::event!(
+                        Server(ServerEvent::DrainComplete),
+                        Elapsed = start_time.elapsed(),
+                    );
+                }
+                return vec![];
+            } else if !is_draining {
+                trc::event!(
+                    Server(ServerEvent::DrainStart),
+                    Total = sessions,
+                    QueueId = self.in_flight_queue_ids(),
+                    Limit = grace_period,
+                );
+                is_draining = true;
+            }
+
+            let elapsed = start_time.elapsed();
+            if elapsed >= grace_period {
+                trc::event!(
+                    Server(ServerEvent::DrainTimeout),
+                    Total = sessions,
+                    QueueId = self.in_flight_queue_ids(),
+                    Elapsed = elapsed,
+                );
+                return self
+                    .inner
+                    .data
+                    .smtp_in_flight
+                    .lock()
+                    .drain()
+                    .map(|(_, event)| event)
+                    .collect();
+            }
+
+            tokio::time::sleep(DRAIN_POLL_INTERVAL.min(grace_period - elapsed)).await;
+        }
+    }
+
+    /// Returns the number of sessions being handled by all listeners.
+    pub fn in_flight_sessions(&self) -> u64 {
+        self.inner
+            .data
+            .listeners
+            .lock()
+            .values()
+            .map(|status| status.limiter.concurrent.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    fn in_flight_queue_ids(&self) -> Vec<trc::Value> {
+        self.inner
+            .data
+            .smtp_in_flight
+            .lock()
+            .keys()
+            .map(|queue_id| trc::Value::from(*queue_id))
+            .collect()
+    }
+}
+
+/// Returns the read timeout of a session, shortened to the time left
+/// before the grace period expires while the session is being drained.
+pub fn drain_timeout(timeout: Duration, deadline: Option<Instant>) -> Duration {
+    deadline.map_or(timeout, |deadline| {
+        deadline
+            .saturating_duration_since(Instant::now())
+            .min(timeout)
+    })
+}
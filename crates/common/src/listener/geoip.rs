@@ -0,0 +1,131 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use maxminddb::{geoip2, Reader};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use utils::config::Config;
+
+use crate::{ipc::HousekeeperEvent, Server};
+
+pub type GeoIpReader = Reader<Vec<u8>>;
+pub type GeoIpWatcher = RecommendedWatcher;
+
+/// MaxMind database used to resolve the country of an IP address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeoIpSettings {
+    pub path: PathBuf,
+    pub watch: bool,
+}
+
+impl GeoIpSettings {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        Some(GeoIpSettings {
+            path: config.value("server.geoip.database")?.into(),
+            watch: config
+                .property_or_default("server.geoip.watch", "true")
+                .unwrap_or(true),
+        })
+    }
+
+    pub fn load(&self) -> Result<GeoIpReader, String> {
+        Reader::open_readfile(&self.path).map_err(|err| {
+            format!(
+                "Failed to load GeoIP database {}: {err}",
+                self.path.display()
+            )
+        })
+    }
+
+    pub fn watch(
+        &self,
+        tx: mpsc::Sender<HousekeeperEvent>,
+    ) -> Result<Option<GeoIpWatcher>, String> {
+        if !self.watch {
+            return Ok(None);
+        }
+
+        // Updates usually rename a new file over the database, so the
+        // parent directory is watched rather than the file itself
+        let directory = self
+            .path
+            .parent()
+            .filter(|path| !path.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let file_name = self.path.file_name().map(|name| name.to_os_string());
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if event.is_ok_and(|event| {
+                    (event.kind.is_create() || event.kind.is_modify())
+                        && event
+                            .paths
+                            .iter()
+                            .any(|path| path.file_name() == file_name.as_deref())
+                }) {
+                    let _ = tx.try_send(HousekeeperEvent::GeoIpChanged);
+                }
+            })
+            .map_err(|err| format!("Failed to create file watcher: {err}"))?;
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(|err| format!("Failed to watch {}: {err}", directory.display()))?;
+
+        Ok(Some(watcher))
+    }
+}
+
+impl Server {
+    /// Returns the ISO 3166-1 code of the country an IP address belongs to.
+    pub fn geoip_country(&self, ip: IpAddr) -> Option<String> {
+        let reader = self.inner.data.geoip.load();
+        reader
+            .as_ref()?
+            .lookup::<geoip2::Country>(ip)
+            .ok()?
+            .country?
+            .iso_code
+            .map(|code| code.to_string())
+    }
+
+    /// Returns the autonomous system number of an IP address, available when
+    /// the configured database includes ASN data.
+    pub fn geoip_asn(&self, ip: IpAddr) -> Option<u32> {
+        let reader = self.inner.data.geoip.load();
+        reader
+            .as_ref()?
+            .lookup::<geoip2::Asn>(ip)
+            .ok()?
+            .autonomous_system_number
+    }
+
+    pub async fn reload_geoip(&self) -> trc::Result<()> {
+        let Some(settings) = self.core.network.geoip.clone() else {
+            self.inner.data.geoip.store(None);
+            return Ok(());
+        };
+
+        let path = settings.path.display().to_string();
+        let reader = tokio::task::spawn_blocking(move || settings.load())
+            .await
+            .map_err(|err| {
+                trc::EventType::Server(trc::ServerEvent::ThreadError)
+                    .reason(err)
+                    .caused_by(trc::location!())
+            })?
+            .map_err(|err| trc::ResourceEvent::Error.into_err().details(err))?;
+        self.inner.data.geoip.store(Some(Arc::new(reader)));
+
+        trc::event!(Resource(trc::ResourceEvent::GeoIpLoaded), Path = path);
+
+        Ok(())
+    }
+}
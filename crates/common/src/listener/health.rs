@@ -0,0 +1,239 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    future::Future,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use store::{write::ValueClass, ValueKey};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use trc::NetworkEvent;
+
+use crate::{config::server::ServerProtocol, core::BuildServer, Inner, Server};
+
+use super::{limiter::ConcurrencyLimiter, SessionData, SessionManager, SessionStream};
+
+// Time to wait for the service name optionally sent by the agent-check client
+const AGENT_READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub components: Vec<ComponentHealth>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentHealth {
+    pub component: String,
+    pub healthy: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+/// State of a listener as tracked for health checks.
+#[derive(Debug, Clone)]
+pub struct ListenerStatus {
+    pub protocol: ServerProtocol,
+    pub limiter: ConcurrencyLimiter,
+    pub bound: usize,
+    pub failed: usize,
+}
+
+#[derive(Clone)]
+pub struct HealthSessionManager {
+    pub inner: Arc<Inner>,
+}
+
+impl HealthSessionManager {
+    pub fn new(inner: Arc<Inner>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Inner {
+    pub fn update_listener_status(&self, id: &str, f: impl FnOnce(&mut ListenerStatus)) {
+        if let Some(status) = self.data.listeners.lock().get_mut(id) {
+            f(status);
+        }
+    }
+}
+
+impl Server {
+    /// Checks the health of the stores and listeners. When a filter is provided,
+    /// only the listeners matching the protocol or listener id are included.
+    pub async fn health_report(&self, filter: Option<&str>) -> HealthReport {
+        let mut components = vec![
+            self.check_data_store().await,
+            self.check_lookup_store().await,
+        ];
+        let mut listeners = self
+            .inner
+            .data
+            .listeners
+            .lock()
+            .iter()
+            .filter(|(id, status)| {
+                filter.map_or(true, |filter| {
+                    filter == id.as_str() || filter == status.protocol.as_str()
+                })
+            })
+            .map(|(id, status)| {
+                let concurrent = status.limiter.concurrent.load(Ordering::Relaxed);
+                let details = if status.bound == 0 {
+                    Some("Listener is not accepting connections".to_string())
+                } else if status.failed > 0 {
+                    Some(format!(
+                        "Failed to bind {} of {} addresses",
+                        status.failed,
+                        status.failed + status.bound
+                    ))
+                } else if status.limiter.max_concurrent > 0
+                    && concurrent >= status.limiter.max_concurrent
+                {
+                    Some("Maximum number of connections reached".to_string())
+                } else {
+                    None
+                };
+
+                ComponentHealth {
+                    component: format!("listener.{id}"),
+                    healthy: details.is_none(),
+                    details,
+                    latency_ms: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        listeners.sort_unstable_by(|a, b| a.component.cmp(&b.component));
+        components.extend(listeners);
+
+        let healthy = components.iter().all(|component| component.healthy);
+        if !healthy {
+            trc::event!(
+                Network(NetworkEvent::HealthCheckFailed),
+                Details = components
+                    .iter()
+                    .filter(|component| !component.healthy)
+                    .map(|component| component.component.clone())
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        HealthReport {
+            healthy,
+            components,
+        }
+    }
+
+    async fn check_data_store(&self) -> ComponentHealth {
+        let store = self.store().clone();
+        self.check_store("store.data", async move {
+            if !store.is_none() {
+                store
+                    .get_value::<()>(ValueKey::from(ValueClass::Config(b"health".to_vec())))
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            } else {
+                Err("Data store is not configured".to_string())
+            }
+        })
+        .await
+    }
+
+    async fn check_lookup_store(&self) -> ComponentHealth {
+        let store = self.lookup_store().clone();
+        self.check_store("store.lookup", async move {
+            store
+                .key_exists(b"health".to_vec())
+                .await
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .await
+    }
+
+    async fn check_store(
+        &self,
+        component: &str,
+        check: impl Future<Output = Result<(), String>>,
+    ) -> ComponentHealth {
+        let timeout = self.core.network.health.store_timeout;
+        let start_time = Instant::now();
+        let result = match tokio::time::timeout(timeout, check).await {
+            Ok(result) => result,
+            Err(_) => Err(format!(
+                "Store did not respond within {} ms",
+                timeout.as_millis()
+            )),
+        };
+
+        ComponentHealth {
+            component: component.to_string(),
+            healthy: result.is_ok(),
+            details: result.err(),
+            latency_ms: Some(start_time.elapsed().as_millis() as u64),
+        }
+    }
+}
+
+impl SessionManager for HealthSessionManager {
+    #[allow(clippy::manual_async_fn)]
+    fn handle<T: SessionStream>(
+        self,
+        mut session: SessionData<T>,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        async move {
+            // HAProxy can be configured to send the name of the checked service
+            let mut buf = [0u8; 128];
+            let filter =
+                match tokio::time::timeout(AGENT_READ_TIMEOUT, session.stream.read(&mut buf)).await
+                {
+                    Ok(Ok(len)) => std::str::from_utf8(&buf[..len])
+                        .ok()
+                        .map(|filter| filter.trim().to_ascii_lowercase())
+                        .filter(|filter| !filter.is_empty()),
+                    _ => None,
+                };
+
+            // Reply using the HAProxy agent-check format
+            let report = self
+                .inner
+                .build_server()
+                .health_report(filter.as_deref())
+                .await;
+            let response = if report.healthy {
+                "up ready\n".to_string()
+            } else {
+                format!(
+                    "down #{}\n",
+                    report
+                        .components
+                        .iter()
+                        .filter(|component| !component.healthy)
+                        .map(|component| component.component.as_str())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+            };
+
+            let _ = session.stream.write_all(response.as_bytes()).await;
+            let _ = session.stream.shutdown().await;
+        }
+    }
+
+    #[allow(clippy::manual_async_fn)]
+    fn shutdown(&self) -> impl std::future::Future<Output = ()> + Send {
+        async {}
+    }
+}
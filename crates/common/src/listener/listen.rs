@@ -14,7 +14,7 @@ use proxy_header::io::ProxiedStream;
 use rustls::crypto::ring::cipher_suite::TLS13_AES_128_GCM_SHA256;
 use tokio::{net::TcpStream, sync::watch};
 use tokio_rustls::server::TlsStream;
-use trc::{EventType, HttpEvent, ImapEvent, ManageSieveEvent, Pop3Event, SmtpEvent};
+use trc::{EventType, HttpEvent, ImapEvent, ManageSieveEvent, NetworkEvent, Pop3Event, SmtpEvent};
 use utils::{config::Config, UnwrapFailure};
 
 use crate::{
@@ -24,8 +24,8 @@ use crate::{
 };
 
 use super::{
-    limiter::ConcurrencyLimiter, ServerInstance, SessionData, SessionManager, SessionStream,
-    TcpAcceptor,
+    health::ListenerStatus, limiter::ConcurrencyLimiter, ServerInstance, SessionData,
+    SessionManager, SessionStream, TcpAcceptor,
 };
 
 impl Listener {
@@ -41,6 +41,7 @@ impl Listener {
             id: self.id,
             protocol: self.protocol,
             proxy_networks: self.proxy_networks,
+            proxy_required: self.proxy_required,
             limiter: ConcurrencyLimiter::new(self.max_connections),
             acceptor,
             shutdown_rx,
@@ -50,6 +51,17 @@ impl Listener {
         let is_https = is_tls && self.protocol == ServerProtocol::Http;
         let has_proxies = !instance.proxy_networks.is_empty();
 
+        // Track listener status for health checks
+        inner.data.listeners.lock().insert(
+            instance.id.clone(),
+            ListenerStatus {
+                protocol: self.protocol,
+                limiter: instance.limiter.clone(),
+                bound: 0,
+                failed: 0,
+            },
+        );
+
         // Spawn listeners
         for listener in self.listeners {
             let local_addr = listener.addr;
@@ -71,6 +83,7 @@ impl Listener {
                         LocalPort = local_addr.port(),
                         Tls = is_tls,
                     );
+                    inner.update_listener_status(&instance.id, |status| status.bound += 1);
 
                     listener
                 }
@@ -83,6 +96,7 @@ impl Listener {
                         Tls = is_tls,
                         Reason = err,
                     );
+                    inner.update_listener_status(&instance.id, |status| status.failed += 1);
 
                     continue;
                 }
@@ -115,6 +129,10 @@ impl Listener {
                         EventType::ManageSieve(ManageSieveEvent::ConnectionStart),
                         EventType::ManageSieve(ManageSieveEvent::ConnectionEnd),
                     ),
+                    ServerProtocol::Health => (
+                        EventType::Network(NetworkEvent::HealthCheck),
+                        EventType::Network(NetworkEvent::Closed),
+                    ),
                 };
 
                 loop {
@@ -135,10 +153,26 @@ impl Listener {
                                         tokio::spawn(async move {
                                             match ProxiedStream::create_from_tokio(stream, Default::default()).await {
                                                 Ok(stream) =>{
+                                                    let proxy_addr = remote_addr;
                                                     let remote_addr = stream.proxy_header()
                                                                             .proxied_address()
                                                                             .map(|addr| addr.source)
                                                                             .unwrap_or(remote_addr);
+                                                    let proxy_info = stream.proxy_info().unwrap_or_default();
+
+                                                    trc::event!(
+                                                        Network(trc::NetworkEvent::ProxyConnection),
+                                                        ListenerId = instance.id.clone(),
+                                                        LocalIp = local_addr.ip(),
+                                                        LocalPort = local_addr.port(),
+                                                        RemoteIp = remote_addr.ip(),
+                                                        RemotePort = remote_addr.port(),
+                                                        Source = proxy_addr.ip(),
+                                                        Hostname = proxy_info.authority,
+                                                        Tls = stream.is_tls(),
+                                                        Details = proxy_info.ssl.and_then(|ssl| ssl.client_cn),
+                                                    );
+
                                                     if let Some(session) = instance.build_session(stream, local_addr, remote_addr, &server) {
                                                         // Spawn session
                                                         manager.spawn(session, is_tls, enable_acme, span_start, span_end);
@@ -156,6 +190,15 @@ impl Listener {
                                                 }
                                             }
                                         });
+                                    } else if instance.proxy_required {
+                                        trc::event!(
+                                            Network(trc::NetworkEvent::ProxyUntrusted),
+                                            ListenerId = instance.id.clone(),
+                                            LocalIp = local_addr.ip(),
+                                            LocalPort = local_addr.port(),
+                                            RemoteIp = remote_addr.ip(),
+                                            RemotePort = remote_addr.port(),
+                                        );
                                     } else if let Some(session) = instance.build_session(stream, local_addr, remote_addr, &server) {
                                         // Set socket options
                                         opts.apply(&session.stream);
@@ -186,6 +229,9 @@ impl Listener {
                                 LocalPort = local_addr.port(),
                             );
 
+                            inner.update_listener_status(&instance.id, |status| {
+                                status.bound = status.bound.saturating_sub(1);
+                            });
                             manager.shutdown().await;
                             break;
                         }
@@ -26,6 +26,10 @@ use self::limiter::{ConcurrencyLimiter, InFlight};
 
 pub mod acme;
 pub mod blocked;
+pub mod certificates;
+pub mod drain;
+pub mod geoip;
+pub mod health;
 pub mod limiter;
 pub mod listen;
 pub mod stream;
@@ -37,6 +41,7 @@ pub struct ServerInstance {
     pub acceptor: TcpAcceptor,
     pub limiter: ConcurrencyLimiter,
     pub proxy_networks: Vec<IpAddrMask>,
+    pub proxy_required: bool,
     pub shutdown_rx: watch::Receiver<bool>,
     pub span_id_gen: Arc<SnowflakeIdGenerator>,
 }
@@ -77,6 +82,26 @@ pub struct SessionData<T: SessionStream> {
 pub trait SessionStream: AsyncRead + AsyncWrite + Unpin + 'static + Sync + Send {
     fn is_tls(&self) -> bool;
     fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>);
+    fn proxy_info(&self) -> Option<ProxyInfo> {
+        None
+    }
+}
+
+/// Connection details sent by an upstream proxy as PROXY protocol v2 TLVs.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProxyInfo {
+    pub authority: Option<String>,
+    pub unique_id: Option<Vec<u8>>,
+    pub ssl: Option<ProxySslInfo>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProxySslInfo {
+    pub version: Option<String>,
+    pub cipher: Option<String>,
+    pub client_cert: bool,
+    pub client_cert_verified: bool,
+    pub client_cn: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -6,14 +6,14 @@
 
 use std::borrow::Cow;
 
-use proxy_header::io::ProxiedStream;
+use proxy_header::{io::ProxiedStream, ProxyHeader, Tlv};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpStream,
 };
 use tokio_rustls::server::TlsStream;
 
-use super::SessionStream;
+use super::{ProxyInfo, ProxySslInfo, SessionStream};
 
 impl SessionStream for TcpStream {
     fn is_tls(&self) -> bool {
@@ -62,6 +62,10 @@ impl<T: SessionStream> SessionStream for TlsStream<T> {
             .into(),
         )
     }
+
+    fn proxy_info(&self) -> Option<ProxyInfo> {
+        self.get_ref().0.proxy_info()
+    }
 }
 
 impl SessionStream for ProxiedStream<TcpStream> {
@@ -82,6 +86,33 @@ impl SessionStream for ProxiedStream<TcpStream> {
             })
             .unwrap_or((Cow::Borrowed("unknown"), Cow::Borrowed("unknown")))
     }
+
+    fn proxy_info(&self) -> Option<ProxyInfo> {
+        Some(ProxyInfo::from(self.proxy_header()))
+    }
+}
+
+impl From<&ProxyHeader<'_>> for ProxyInfo {
+    fn from(header: &ProxyHeader<'_>) -> Self {
+        ProxyInfo {
+            authority: header.authority().map(|authority| authority.to_string()),
+            unique_id: header.unique_id().map(|id| id.to_vec()),
+            ssl: header.ssl().map(|ssl| {
+                let client_cert = ssl.client_cert_conn() || ssl.client_cert_sess();
+
+                ProxySslInfo {
+                    version: ssl.version().map(|version| version.to_string()),
+                    cipher: ssl.cipher().map(|cipher| cipher.to_string()),
+                    client_cert,
+                    client_cert_verified: client_cert && ssl.verify() == 0,
+                    client_cn: ssl.tlvs().find_map(|tlv| match tlv {
+                        Ok(Tlv::SslCn(cn)) => Some(cn.to_string()),
+                        _ => None,
+                    }),
+                }
+            }),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -9,14 +9,17 @@ use std::{
     io::{BufWriter, Write},
     ops::Range,
     path::{Path, PathBuf},
-    sync::mpsc::{self, SyncSender},
+    sync::{
+        mpsc::{self, SyncSender},
+        Arc,
+    },
 };
 
 use ahash::{AHashMap, AHashSet};
 use jmap_proto::types::{collection::Collection, property::Property};
 use store::{
     write::{
-        key::DeserializeBigEndian, AnyKey, BitmapClass, BitmapHash, BlobOp, DirectoryClass,
+        key::DeserializeBigEndian, now, AnyKey, BitmapClass, BitmapHash, BlobOp, DirectoryClass,
         LookupClass, QueueClass, QueueEvent, TagValue, ValueClass,
     },
     BitmapKey, Deserialize, IndexKey, IterateParams, LogKey, Serialize, ValueKey,
@@ -32,6 +35,7 @@ use crate::Core;
 
 pub(super) const MAGIC_MARKER: u8 = 123;
 pub(super) const FILE_VERSION: u8 = 2;
+pub(super) const MANIFEST_FILE: &str = "manifest.json";
 
 #[derive(Debug)]
 pub(super) enum Op {
@@ -65,8 +69,36 @@ type TaskHandle = (tokio::task::JoinHandle<()>, std::thread::JoinHandle<()>);
 pub struct BackupParams {
     dest: PathBuf,
     families: AHashSet<Family>,
+    since: Option<PathBuf>,
+}
+
+/// Describes the state of the store at the time a backup was taken. Incremental
+/// backups reference their parent, forming a chain that ends in a full backup.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(super) struct BackupManifest {
+    pub id: u64,
+    pub parent: Option<BackupParent>,
+    /// Accounts exported by an incremental backup, these are reset on restore.
+    /// Full backups leave this empty.
+    pub accounts: Option<Vec<u32>>,
+    /// Last change id of every (account, collection) pair.
+    pub change_ids: Vec<(u32, u8, u64)>,
+    /// Backup watermark of every account.
+    #[serde(default)]
+    pub watermarks: Vec<(u32, u64)>,
+    /// Committed blobs present in the store at backup time.
+    pub blobs: Vec<BlobHash>,
 }
 
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(super) struct BackupParent {
+    pub id: u64,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+struct AccountFilter(Option<Arc<AHashSet<u32>>>);
+
 impl Core {
     pub async fn backup(&self, params: BackupParams) {
         if !params.dest.exists() {
@@ -76,21 +108,55 @@ impl Core {
             std::process::exit(1);
         }
 
+        // Snapshot the change log and committed blobs
+        let mut manifest = BackupManifest {
+            id: now(),
+            change_ids: self.backup_change_ids().await,
+            watermarks: self.backup_watermarks().await,
+            blobs: self.backup_committed_blobs().await,
+            ..Default::default()
+        };
+
+        // Only export accounts that changed since the parent backup
+        let mut filter = AccountFilter::default();
+        let mut known_blobs = AHashSet::new();
+        if let Some(since) = &params.since {
+            let parent = BackupManifest::read(since);
+            let changed = manifest.changed_accounts(&parent);
+
+            println!(
+                "Exporting {} changed account(s) since backup {}.",
+                changed.len(),
+                parent.id
+            );
+
+            let mut accounts = changed.iter().copied().collect::<Vec<_>>();
+            accounts.sort_unstable();
+            manifest.accounts = Some(accounts);
+            manifest.parent = Some(BackupParent {
+                id: parent.id,
+                path: std::fs::canonicalize(since).failed("Failed to resolve parent backup path"),
+            });
+            filter = AccountFilter(Some(Arc::new(changed)));
+            known_blobs = parent.blobs.into_iter().collect();
+        }
+        let known_blobs = Arc::new(known_blobs);
+
         let mut sync_handles = Vec::new();
 
         for (async_handle, sync_handle) in [
             params
                 .has_family(Family::Property)
-                .then(|| self.backup_properties(&params.dest)),
+                .then(|| self.backup_properties(&params.dest, filter.clone())),
             params
                 .has_family(Family::FtsIndex)
-                .then(|| self.backup_fts_index(&params.dest)),
+                .then(|| self.backup_fts_index(&params.dest, filter.clone())),
             params
                 .has_family(Family::Acl)
-                .then(|| self.backup_acl(&params.dest)),
+                .then(|| self.backup_acl(&params.dest, filter.clone())),
             params
                 .has_family(Family::Blob)
-                .then(|| self.backup_blob(&params.dest)),
+                .then(|| self.backup_blob(&params.dest, filter.clone(), known_blobs.clone())),
             params
                 .has_family(Family::Config)
                 .then(|| self.backup_config(&params.dest)),
@@ -105,13 +171,13 @@ impl Core {
                 .then(|| self.backup_queue(&params.dest)),
             params
                 .has_family(Family::Index)
-                .then(|| self.backup_index(&params.dest)),
+                .then(|| self.backup_index(&params.dest, filter.clone())),
             params
                 .has_family(Family::Bitmap)
-                .then(|| self.backup_bitmaps(&params.dest)),
+                .then(|| self.backup_bitmaps(&params.dest, filter.clone())),
             params
                 .has_family(Family::Log)
-                .then(|| self.backup_logs(&params.dest)),
+                .then(|| self.backup_logs(&params.dest, filter.clone())),
         ]
         .into_iter()
         .flatten()
@@ -123,9 +189,140 @@ impl Core {
         for handle in sync_handles {
             handle.join().expect("Failed to join thread");
         }
+
+        manifest.write(&params.dest);
+    }
+
+    async fn backup_change_ids(&self) -> Vec<(u32, u8, u64)> {
+        let mut change_ids: Vec<(u32, u8, u64)> = Vec::new();
+
+        self.storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    LogKey {
+                        account_id: 0,
+                        collection: 0,
+                        change_id: 0,
+                    },
+                    LogKey {
+                        account_id: u32::MAX,
+                        collection: u8::MAX,
+                        change_id: u64::MAX,
+                    },
+                )
+                .no_values(),
+                |key, _| {
+                    let account_id = key.deserialize_be_u32(0)?;
+                    let collection = key.deserialize_u8(U32_LEN)?;
+                    let change_id = key.deserialize_be_u64(U32_LEN + 1)?;
+
+                    match change_ids.last_mut() {
+                        Some(last) if last.0 == account_id && last.1 == collection => {
+                            last.2 = change_id;
+                        }
+                        _ => {
+                            change_ids.push((account_id, collection, change_id));
+                        }
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .failed("Failed to iterate over data store");
+
+        change_ids
+    }
+
+    async fn backup_watermarks(&self) -> Vec<(u32, u64)> {
+        let mut account_ids = Vec::new();
+
+        self.storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Directory(DirectoryClass::BackupWatermark(0))),
+                    ValueKey::from(ValueClass::Directory(DirectoryClass::BackupWatermark(
+                        u32::MAX,
+                    ))),
+                )
+                .no_values(),
+                |key, _| {
+                    account_ids.push(key.deserialize_be_u32(1)?);
+
+                    Ok(true)
+                },
+            )
+            .await
+            .failed("Failed to iterate over data store");
+
+        let mut watermarks = Vec::with_capacity(account_ids.len());
+        for account_id in account_ids {
+            let watermark = self
+                .storage
+                .data
+                .get_counter(ValueKey::from(ValueClass::Directory(
+                    DirectoryClass::BackupWatermark(account_id),
+                )))
+                .await
+                .failed("Failed to get counter");
+
+            if watermark > 0 {
+                watermarks.push((account_id, watermark as u64));
+            }
+        }
+
+        watermarks
     }
 
-    fn backup_properties(&self, dest: &Path) -> TaskHandle {
+    async fn backup_committed_blobs(&self) -> Vec<BlobHash> {
+        let mut hashes = Vec::new();
+
+        self.storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey {
+                        account_id: 0,
+                        collection: 0,
+                        document_id: 0,
+                        class: ValueClass::Blob(BlobOp::Link {
+                            hash: Default::default(),
+                        }),
+                    },
+                    ValueKey {
+                        account_id: u32::MAX,
+                        collection: u8::MAX,
+                        document_id: u32::MAX,
+                        class: ValueClass::Blob(BlobOp::Link {
+                            hash: BlobHash::new_max(),
+                        }),
+                    },
+                )
+                .no_values(),
+                |key, _| {
+                    let account_id = key.deserialize_be_u32(BLOB_HASH_LEN)?;
+                    let document_id = key.deserialize_be_u32(BLOB_HASH_LEN + U32_LEN + 1)?;
+
+                    if account_id == u32::MAX || document_id == u32::MAX {
+                        hashes.push(
+                            BlobHash::try_from_hash_slice(key.range(0..BLOB_HASH_LEN)?)
+                                .failed("Invalid blob hash"),
+                        );
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .failed("Failed to iterate over data store");
+
+        hashes.dedup();
+        hashes
+    }
+
+    fn backup_properties(&self, dest: &Path, filter: AccountFilter) -> TaskHandle {
         let store = self.storage.data.clone();
         let (handle, writer) = spawn_writer(dest.join("property"));
         (
@@ -159,7 +356,9 @@ impl Core {
                             let field = key.deserialize_u8(U32_LEN + 1)?;
                             let document_id = key.deserialize_be_u32(U32_LEN + 2)?;
 
-                            keys.insert((account_id, collection, document_id, field));
+                            if filter.matches(account_id) {
+                                keys.insert((account_id, collection, document_id, field));
+                            }
 
                             Ok(true)
                         },
@@ -232,12 +431,51 @@ impl Core {
                         .send(Op::KeyValue((vec![field], value)))
                         .failed("Failed to send key value");
                 }
+
+                // Export the metadata shared between the recipients of a message
+                let mut is_first = true;
+                store
+                    .iterate(
+                        IterateParams::new(
+                            ValueKey::from(ValueClass::Blob(BlobOp::Metadata {
+                                hash: BlobHash::default(),
+                            })),
+                            ValueKey::from(ValueClass::Blob(BlobOp::Metadata {
+                                hash: BlobHash::new_max(),
+                            })),
+                        ),
+                        |key, value| {
+                            if is_first {
+                                writer
+                                    .send(Op::AccountId(u32::MAX))
+                                    .failed("Failed to send account id");
+                                writer
+                                    .send(Op::Collection(u8::MAX))
+                                    .failed("Failed to send collection");
+                                writer
+                                    .send(Op::DocumentId(u32::MAX))
+                                    .failed("Failed to send document id");
+                                is_first = false;
+                            }
+
+                            writer
+                                .send(Op::KeyValue((
+                                    key.range(key.len() - BLOB_HASH_LEN..key.len())?.to_vec(),
+                                    value.to_vec(),
+                                )))
+                                .failed("Failed to send key value");
+
+                            Ok(true)
+                        },
+                    )
+                    .await
+                    .failed("Failed to iterate over data store");
             }),
             handle,
         )
     }
 
-    fn backup_fts_index(&self, dest: &Path) -> TaskHandle {
+    fn backup_fts_index(&self, dest: &Path, filter: AccountFilter) -> TaskHandle {
         let store = self.storage.data.clone();
         let (handle, writer) = spawn_writer(dest.join("fts_index"));
         (
@@ -276,6 +514,10 @@ impl Core {
                             let collection = key.deserialize_u8(key.len() - U32_LEN - 1)?;
                             let document_id = key.deserialize_be_u32(key.len() - U32_LEN)?;
 
+                            if !filter.matches(account_id) {
+                                return Ok(true);
+                            }
+
                             if account_id != last_account_id {
                                 writer
                                     .send(Op::AccountId(account_id))
@@ -311,7 +553,7 @@ impl Core {
         )
     }
 
-    fn backup_acl(&self, dest: &Path) -> TaskHandle {
+    fn backup_acl(&self, dest: &Path, filter: AccountFilter) -> TaskHandle {
         let store = self.storage.data.clone();
         let (handle, writer) = spawn_writer(dest.join("acl"));
         (
@@ -346,6 +588,11 @@ impl Core {
                             let collection = key.deserialize_u8(U32_LEN * 2)?;
                             let document_id = key.deserialize_be_u32((U32_LEN * 2) + 1)?;
 
+                            // Account resets remove both the granted and the owned ACLs
+                            if !filter.matches(account_id) && !filter.matches(grant_account_id) {
+                                return Ok(true);
+                            }
+
                             if account_id != last_account_id {
                                 writer
                                     .send(Op::AccountId(account_id))
@@ -384,7 +631,12 @@ impl Core {
         )
     }
 
-    fn backup_blob(&self, dest: &Path) -> TaskHandle {
+    fn backup_blob(
+        &self,
+        dest: &Path,
+        filter: AccountFilter,
+        known_blobs: Arc<AHashSet<BlobHash>>,
+    ) -> TaskHandle {
         let store = self.storage.data.clone();
         let blob_store = self.storage.blob.clone();
         let (handle, writer) = spawn_writer(dest.join("blob"));
@@ -425,6 +677,10 @@ impl Core {
                             let hash = key.range(0..BLOB_HASH_LEN)?.to_vec();
 
                             if account_id != u32::MAX && document_id != u32::MAX {
+                                if !filter.matches(account_id) {
+                                    return Ok(true);
+                                }
+
                                 writer
                                     .send(Op::AccountId(account_id))
                                     .failed("Failed to send account id");
@@ -437,7 +693,9 @@ impl Core {
                                 writer
                                     .send(Op::KeyValue((hash, vec![])))
                                     .failed("Failed to send key value");
-                            } else {
+                            } else if BlobHash::try_from_hash_slice(&hash)
+                                .map_or(true, |hash| !known_blobs.contains(&hash))
+                            {
                                 hashes.push(hash);
                             }
 
@@ -781,7 +1039,7 @@ impl Core {
         )
     }
 
-    fn backup_index(&self, dest: &Path) -> TaskHandle {
+    fn backup_index(&self, dest: &Path, filter: AccountFilter) -> TaskHandle {
         let store = self.storage.data.clone();
         let (handle, writer) = spawn_writer(dest.join("index"));
         (
@@ -817,6 +1075,10 @@ impl Core {
                             let collection = key.deserialize_u8(U32_LEN)?;
                             let document_id = key.deserialize_be_u32(key.len() - U32_LEN)?;
 
+                            if !filter.matches(account_id) {
+                                return Ok(true);
+                            }
+
                             let key = key.range(U32_LEN + 1..key.len() - U32_LEN)?.to_vec();
 
                             if account_id != last_account_id {
@@ -851,7 +1113,7 @@ impl Core {
         )
     }
 
-    fn backup_bitmaps(&self, dest: &Path) -> TaskHandle {
+    fn backup_bitmaps(&self, dest: &Path, filter: AccountFilter) -> TaskHandle {
         let store = self.storage.data.clone();
 
         let (handle, writer) = spawn_writer(dest.join("bitmap"));
@@ -885,6 +1147,9 @@ impl Core {
                             .no_values(),
                             |key, _| {
                                 let account_id = key.deserialize_be_u32(0)?;
+                                if !filter.matches(account_id) {
+                                    return Ok(true);
+                                }
 
                                 let key = key.range(0..key.len() - U32_LEN)?;
 
@@ -1023,7 +1288,7 @@ impl Core {
         )
     }
 
-    fn backup_logs(&self, dest: &Path) -> TaskHandle {
+    fn backup_logs(&self, dest: &Path, filter: AccountFilter) -> TaskHandle {
         let store = self.storage.data.clone();
         let (handle, writer) = spawn_writer(dest.join("log"));
         (
@@ -1058,6 +1323,10 @@ impl Core {
                                 failed(&format!("Found invalid log entry {key:?} {value:?}"));
                             }
 
+                            if !filter.matches(account_id) {
+                                return Ok(true);
+                            }
+
                             if account_id != last_account_id {
                                 writer
                                     .send(Op::AccountId(account_id))
@@ -1169,15 +1438,25 @@ impl BackupParams {
         let mut params = Self {
             dest,
             families: AHashSet::new(),
+            since: None,
         };
 
         if let Ok(families) = std::env::var("EXPORT_TYPES") {
             params.parse_families(&families);
         }
 
+        if let Ok(since) = std::env::var("EXPORT_SINCE") {
+            params.since = Some(since.into());
+        }
+
         params
     }
 
+    pub fn with_since(mut self, since: PathBuf) -> Self {
+        self.since = Some(since);
+        self
+    }
+
     fn parse_families(&mut self, families: &str) {
         for family in families.split(',') {
             let family = family.trim();
@@ -1198,6 +1477,101 @@ impl BackupParams {
     }
 }
 
+impl BackupManifest {
+    pub fn read(path: &Path) -> Self {
+        let path = path.join(MANIFEST_FILE);
+        let contents = std::fs::read(&path).failed(&format!(
+            "Failed to read backup manifest {}",
+            path.display()
+        ));
+
+        serde_json::from_slice(&contents).failed(&format!(
+            "Failed to parse backup manifest {}",
+            path.display()
+        ))
+    }
+
+    pub fn try_read(path: &Path) -> Option<Self> {
+        if path.join(MANIFEST_FILE).exists() {
+            Some(Self::read(path))
+        } else {
+            None
+        }
+    }
+
+    fn write(&self, dest: &Path) {
+        std::fs::write(
+            dest.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(self).failed("Failed to serialize backup manifest"),
+        )
+        .failed("Failed to write backup manifest");
+    }
+
+    /// Returns the accounts whose change log or backup watermark differs from
+    /// the parent's, including accounts that no longer exist.
+    pub fn changed_accounts(&self, parent: &BackupManifest) -> AHashSet<u32> {
+        let previous = parent
+            .watermarks
+            .iter()
+            .copied()
+            .collect::<AHashMap<_, _>>();
+        let current = self.watermarks.iter().copied().collect::<AHashMap<_, _>>();
+        let mut changed = AHashSet::new();
+
+        for (account_id, watermark) in &current {
+            if previous.get(account_id) != Some(watermark) {
+                changed.insert(*account_id);
+            }
+        }
+
+        for account_id in previous.keys() {
+            if !current.contains_key(account_id) {
+                changed.insert(*account_id);
+            }
+        }
+
+        changed.extend(self.changed_log_accounts(parent));
+
+        changed
+    }
+
+    fn changed_log_accounts(&self, parent: &BackupManifest) -> AHashSet<u32> {
+        let previous = parent
+            .change_ids
+            .iter()
+            .map(|(account_id, collection, change_id)| ((*account_id, *collection), *change_id))
+            .collect::<AHashMap<_, _>>();
+        let current = self
+            .change_ids
+            .iter()
+            .map(|(account_id, collection, _)| (*account_id, *collection))
+            .collect::<AHashSet<_>>();
+        let mut changed = AHashSet::new();
+
+        for (account_id, collection, change_id) in &self.change_ids {
+            if previous.get(&(*account_id, *collection)) != Some(change_id) {
+                changed.insert(*account_id);
+            }
+        }
+
+        for (account_id, collection) in previous.keys() {
+            if !current.contains(&(*account_id, *collection)) {
+                changed.insert(*account_id);
+            }
+        }
+
+        changed
+    }
+}
+
+impl AccountFilter {
+    fn matches(&self, account_id: u32) -> bool {
+        self.0
+            .as_ref()
+            .map_or(true, |accounts| accounts.contains(&account_id))
+    }
+}
+
 impl Family {
     pub fn parse(family: &str) -> Result<Self, String> {
         match family {
@@ -7,7 +7,7 @@
 use std::{path::PathBuf, sync::Arc};
 
 use arc_swap::ArcSwap;
-use pwhash::sha512_crypt;
+use directory::core::secret::PasswordHashing;
 use store::{
     rand::{distributions::Alphanumeric, thread_rng, Rng},
     Stores,
@@ -20,7 +20,9 @@ use utils::{
 
 use crate::{
     config::{server::Listeners, telemetry::Telemetry},
-    ipc::{DeliveryEvent, HousekeeperEvent, QueueEvent, ReportingEvent, StateEvent},
+    ipc::{
+        BroadcastEvent, DeliveryEvent, HousekeeperEvent, QueueEvent, ReportingEvent, StateEvent,
+    },
     Core, Data, Inner, Ipc, IPC_CHANNEL_BUFFER,
 };
 
@@ -28,6 +30,7 @@ use super::{
     backup::BackupParams,
     config::{ConfigManager, Patterns},
     console::store_console,
+    restore::verify_backup,
     WEBADMIN_KEY,
 };
 
@@ -44,6 +47,7 @@ pub struct IpcReceivers {
     pub delivery_rx: Option<mpsc::Receiver<DeliveryEvent>>,
     pub queue_rx: Option<mpsc::Receiver<QueueEvent>>,
     pub report_rx: Option<mpsc::Receiver<ReportingEvent>>,
+    pub broadcast_rx: Option<mpsc::Receiver<BroadcastEvent>>,
 }
 
 const HELP: &str = concat!(
@@ -57,6 +61,7 @@ Options:
   -c, --config <PATH>              Start server with the specified configuration file
   -e, --export <PATH>              Export all store data to a specific path
   -i, --import <PATH>              Import store data from a specific path
+  -v, --verify <PATH>              Verify that a backup chain restores consistently
   -o, --console                    Open the store console
   -I, --init <PATH>                Initialize a new server at a specific path
   -h, --help                       Print help
@@ -113,6 +118,10 @@ impl BootManager {
                     ("import" | "i", Some(value)) => {
                         import_export = StoreOp::Import(value.into());
                     }
+                    ("verify" | "v", Some(value)) => {
+                        verify_backup(value.into()).await;
+                        std::process::exit(0);
+                    }
                     ("console" | "o", None) => {
                         import_export = StoreOp::Console;
                     }
@@ -157,8 +166,10 @@ impl BootManager {
         // Bind ports and drop privileges
         servers.bind_and_drop_priv(&mut config);
 
-        // Resolve file and configuration macros
-        config.resolve_macros(&["file", "cfg"]).await;
+        // Resolve file, secret and configuration macros
+        config
+            .resolve_macros(&["file", "vault", "aws-sm", "cfg"])
+            .await;
 
         // Load stores
         let mut stores = Stores::parse(&mut config).await;
@@ -415,6 +426,7 @@ pub fn build_ipc() -> (Ipc, IpcReceivers) {
     let (housekeeper_tx, housekeeper_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
     let (queue_tx, queue_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
     let (report_tx, report_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
+    let (broadcast_tx, broadcast_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
     (
         Ipc {
             state_tx,
@@ -422,6 +434,7 @@ pub fn build_ipc() -> (Ipc, IpcReceivers) {
             delivery_tx,
             queue_tx,
             report_tx,
+            broadcast_tx,
             index_tx: Arc::new(Notify::new()),
         },
         IpcReceivers {
@@ -430,6 +443,7 @@ pub fn build_ipc() -> (Ipc, IpcReceivers) {
             delivery_rx: Some(delivery_rx),
             queue_rx: Some(queue_rx),
             report_rx: Some(report_rx),
+            broadcast_rx: Some(broadcast_rx),
         },
     )
 }
@@ -460,7 +474,10 @@ fn quickstart(path: impl Into<PathBuf>) {
         path.join("etc").join("config.toml"),
         QUICKSTART_CONFIG
             .replace("_P_", &path.to_string_lossy())
-            .replace("_S_", &sha512_crypt::hash(&admin_pass).unwrap()),
+            .replace(
+                "_S_",
+                &PasswordHashing::default().hash(&admin_pass).unwrap(),
+            ),
     )
     .failed("Failed to write configuration file");
 
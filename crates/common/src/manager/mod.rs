@@ -16,6 +16,7 @@ pub mod backup;
 pub mod boot;
 pub mod config;
 pub mod console;
+pub mod notify;
 pub mod reload;
 pub mod restore;
 pub mod webadmin;
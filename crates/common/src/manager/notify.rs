@@ -0,0 +1,149 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::LazyLock;
+
+use trc::ClusterEvent;
+
+use crate::{
+    ipc::{CacheInvalidation, HousekeeperEvent},
+    Server,
+};
+
+// Identifies the notifications published by this node, which are also
+// delivered back to it by the store.
+static SENDER_ID: LazyLock<u64> = LazyLock::new(store::rand::random);
+
+impl Server {
+    /// Publishes a cache invalidation to the other nodes sharing the data store.
+    pub fn notify_cluster(&self, event: CacheInvalidation) {
+        if let Some(channel) = &self.core.network.cluster_notify {
+            let store = self.core.storage.data.clone();
+            let channel = channel.clone();
+            tokio::spawn(async move {
+                if let Err(err) = store.notify(&channel, &event.to_payload(*SENDER_ID)).await {
+                    trc::error!(err
+                        .details("Failed to publish cache invalidation")
+                        .caused_by(trc::location!()));
+                }
+            });
+        }
+    }
+
+    /// Removes an access token from the cache on this and every other node.
+    pub fn invalidate_access_token(&self, account_id: u32) {
+        self.inner.data.access_tokens.remove(&account_id);
+        self.notify_cluster(CacheInvalidation::AccessToken(account_id));
+    }
+
+    /// Disables the address filters on this and every other node until they
+    /// are rebuilt, so that newly added addresses are not rejected.
+    pub async fn invalidate_address_filters(&self) {
+        self.reset_address_filters().await;
+        self.notify_cluster(CacheInvalidation::Addresses);
+    }
+
+    async fn reset_address_filters(&self) {
+        let mut has_filters = false;
+        for directory in self.core.storage.directories.values() {
+            if directory.filter.is_some() {
+                directory.invalidate_filter();
+                has_filters = true;
+            }
+        }
+
+        if has_filters
+            && self
+                .inner
+                .ipc
+                .housekeeper_tx
+                .send(HousekeeperEvent::AddressesChanged)
+                .await
+                .is_err()
+        {
+            trc::event!(
+                Server(trc::ServerEvent::ThreadError),
+                Details = "Failed to send address change event to housekeeper",
+                CausedBy = trc::location!(),
+            );
+        }
+    }
+
+    /// Applies a cache invalidation received from another node.
+    pub async fn handle_cache_invalidation(&self, payload: &str) {
+        let event = match CacheInvalidation::parse_payload(payload) {
+            Some((sender_id, event)) if sender_id != *SENDER_ID => event,
+            Some(_) => return,
+            None => {
+                trc::event!(
+                    Cluster(ClusterEvent::InvalidPacket),
+                    Contents = payload.to_string(),
+                );
+                return;
+            }
+        };
+
+        trc::event!(
+            Cluster(ClusterEvent::PeerHasChanges),
+            Details = event.as_str()
+        );
+
+        match event {
+            CacheInvalidation::Settings => self.reload_from_cluster(true).await,
+            CacheInvalidation::BlockedIps => self.reload_from_cluster(false).await,
+            CacheInvalidation::Permissions => {
+                self.inner.data.permissions.clear();
+            }
+            CacheInvalidation::Addresses => self.reset_address_filters().await,
+            CacheInvalidation::AccessToken(account_id) => {
+                self.inner.data.access_tokens.remove(&account_id);
+                self.inner
+                    .data
+                    .http_auth_cache
+                    .retain(|_, id| id.item != account_id);
+            }
+        }
+    }
+
+    /// Reloads the settings, or only the blocked IP addresses, after they
+    /// were changed by another node of the cluster.
+    pub async fn reload_from_cluster(&self, update_config: bool) {
+        let result = if update_config {
+            self.reload().await
+        } else {
+            self.reload_blocked_ips().await
+        };
+        match result {
+            Ok(result) => {
+                if let Some(new_core) = result.new_core {
+                    // Update core
+                    self.inner.shared_core.store(new_core.into());
+
+                    // Reload ACME
+                    if self
+                        .inner
+                        .ipc
+                        .housekeeper_tx
+                        .send(HousekeeperEvent::ReloadSettings)
+                        .await
+                        .is_err()
+                    {
+                        trc::event!(
+                            Server(trc::ServerEvent::ThreadError),
+                            Details = "Failed to send setting reload event to housekeeper",
+                            CausedBy = trc::location!(),
+                        );
+                    }
+                }
+            }
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to reload settings")
+                    .caused_by(trc::location!()));
+            }
+        }
+    }
+}
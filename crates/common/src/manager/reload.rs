@@ -4,10 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::collections::BTreeMap;
+
 use ahash::AHashMap;
 use arc_swap::ArcSwap;
+use serde::Serialize;
 use store::Stores;
-use utils::config::Config;
+use utils::config::{Config, ConfigError, ConfigWarning};
 
 use crate::{
     config::{
@@ -15,7 +18,7 @@ use crate::{
         telemetry::Telemetry,
     },
     listener::blocked::{BlockedIps, BLOCKED_IP_KEY},
-    Core, Server,
+    Core, Data, Server,
 };
 
 use super::config::{ConfigManager, Patterns};
@@ -26,6 +29,56 @@ pub struct ReloadResult {
     pub tracers: Option<Telemetry>,
 }
 
+/// Candidate configuration parsed without being applied, along with the
+/// settings that would change compared to the running configuration.
+pub struct ConfigCheck {
+    pub config: Config,
+    pub changes: Vec<ConfigChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigCheckReport {
+    pub errors: AHashMap<String, ConfigError>,
+    pub warnings: AHashMap<String, ConfigWarning>,
+    pub effective: Vec<EffectiveSetting>,
+    pub changes: Vec<ConfigChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EffectiveSetting {
+    pub key: String,
+    pub value: String,
+    #[serde(flatten)]
+    pub source: SettingSource,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "source")]
+#[serde(rename_all = "camelCase")]
+pub enum SettingSource {
+    Macro { expression: String },
+    Default,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigChange {
+    Added {
+        key: String,
+        value: String,
+    },
+    Removed {
+        key: String,
+        value: String,
+    },
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
 impl Server {
     pub async fn reload_blocked_ips(&self) -> trc::Result<ReloadResult> {
         let mut config = self
@@ -77,6 +130,7 @@ impl Server {
             fts_stores: self.core.storage.ftss.clone(),
             lookup_stores: self.core.storage.lookups.clone(),
             purge_schedules: Default::default(),
+            settings: self.core.storage.settings.clone(),
         };
         stores.parse_stores(&mut config).await;
         stores.parse_lookups(&mut config).await;
@@ -137,6 +191,143 @@ impl Server {
             config.into()
         })
     }
+
+    /// Parses a candidate configuration file against the running stores, without
+    /// replacing the active core or binding any listeners.
+    pub async fn check_config(&self, toml: &str) -> trc::Result<ConfigCheck> {
+        let mut config = Config::default();
+        if let Err(err) = config.parse(toml) {
+            config.new_parse_error("*", err);
+            return Ok(ConfigCheck {
+                config,
+                changes: Vec::new(),
+            });
+        }
+        config.resolve_all_macros().await;
+        self.core
+            .storage
+            .config
+            .extend_config(&mut config, "")
+            .await?;
+
+        // Compare against the running configuration
+        let current = self.core.storage.config.build_config("").await?;
+        let changes = ConfigChange::diff(&current.keys, &config.keys);
+
+        // Load stores
+        let mut stores = Stores {
+            stores: self.core.storage.stores.clone(),
+            blob_stores: self.core.storage.blobs.clone(),
+            fts_stores: self.core.storage.ftss.clone(),
+            lookup_stores: self.core.storage.lookups.clone(),
+            purge_schedules: Default::default(),
+            settings: self.core.storage.settings.clone(),
+        };
+        stores.parse_stores(&mut config).await;
+        stores.parse_lookups(&mut config).await;
+        Telemetry::parse(&mut config, &stores);
+
+        // Parse settings
+        let manager = ConfigManager {
+            cfg_local: ArcSwap::from_pointee(
+                self.core.storage.config.cfg_local.load().as_ref().clone(),
+            ),
+            cfg_local_path: self.core.storage.config.cfg_local_path.clone(),
+            cfg_local_patterns: Patterns::parse(&mut config).into(),
+            cfg_store: config
+                .value("storage.data")
+                .and_then(|id| stores.stores.get(id))
+                .cloned()
+                .unwrap_or_default(),
+        };
+        Core::parse(&mut config, stores, manager).await;
+        Data::parse(&mut config);
+        Listeners::parse(&mut config);
+
+        // Settings read during startup
+        config.property_or_default::<bool>("webadmin.auto-update", "false");
+        config.value("version.spam-filter");
+
+        Ok(ConfigCheck { config, changes })
+    }
+}
+
+impl ConfigCheck {
+    pub fn report(mut self) -> ConfigCheckReport {
+        self.config.warn_unread_keys();
+
+        let mut effective = self
+            .config
+            .macros
+            .iter()
+            .filter_map(|(key, expression)| {
+                Some(EffectiveSetting {
+                    key: key.clone(),
+                    value: self.config.keys.get(key)?.clone(),
+                    source: SettingSource::Macro {
+                        expression: expression.clone(),
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        effective.extend(
+            self.config
+                .defaults
+                .iter()
+                .filter(|(key, _)| !self.config.keys.contains_key(*key))
+                .map(|(key, value)| EffectiveSetting {
+                    key: key.clone(),
+                    value: value.clone(),
+                    source: SettingSource::Default,
+                }),
+        );
+        effective.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+
+        ConfigCheckReport {
+            errors: self.config.errors,
+            warnings: self.config.warnings,
+            effective,
+            changes: self.changes,
+        }
+    }
+}
+
+impl ConfigChange {
+    pub fn diff(current: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<Self> {
+        let mut changes = Vec::new();
+        for (key, value) in new {
+            match current.get(key) {
+                Some(old) if old != value => changes.push(ConfigChange::Changed {
+                    key: key.clone(),
+                    old: old.clone(),
+                    new: value.clone(),
+                }),
+                None => changes.push(ConfigChange::Added {
+                    key: key.clone(),
+                    value: value.clone(),
+                }),
+                _ => (),
+            }
+        }
+        for (key, value) in current {
+            if !new.contains_key(key) {
+                changes.push(ConfigChange::Removed {
+                    key: key.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        changes.sort_unstable_by(|a, b| a.key().cmp(b.key()));
+        changes
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            ConfigChange::Added { key, .. }
+            | ConfigChange::Removed { key, .. }
+            | ConfigChange::Changed { key, .. } => key,
+        }
+    }
 }
 
 impl From<Config> for ReloadResult {
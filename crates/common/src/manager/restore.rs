@@ -10,6 +10,7 @@ use std::{
 };
 
 use crate::Core;
+use ahash::{AHashMap, AHashSet};
 use jmap_proto::types::{collection::Collection, property::Property};
 use store::{
     roaring::RoaringBitmap,
@@ -18,7 +19,7 @@ use store::{
         FtsQueueClass, LookupClass, MaybeDynamicId, MaybeDynamicValue, Operation, TagValue,
         ValueClass,
     },
-    BlobStore, Serialize, Store, U32_LEN,
+    BlobStore, Serialize, Store, ValueKey, U32_LEN,
 };
 use store::{
     write::{QueueClass, QueueEvent},
@@ -28,20 +29,35 @@ use tokio::{
     fs::File,
     io::{AsyncReadExt, BufReader},
 };
-use utils::{failed, BlobHash, UnwrapFailure};
+use utils::{failed, BlobHash, UnwrapFailure, BLOB_HASH_LEN};
 
-use super::backup::{DeserializeBytes, Family, Op, FILE_VERSION, MAGIC_MARKER};
+use super::backup::{
+    BackupManifest, DeserializeBytes, Family, Op, FILE_VERSION, MAGIC_MARKER, MANIFEST_FILE,
+};
 
 impl Core {
     pub async fn restore(&self, src: PathBuf) {
         // Backup the core
         if src.is_dir() {
+            // Incremental backups replace the data of every exported account
+            if let Some(accounts) = BackupManifest::try_read(&src).and_then(|m| m.accounts) {
+                println!("Resetting {} account(s) before import.", accounts.len());
+
+                for account_id in accounts {
+                    self.storage
+                        .data
+                        .purge_account(account_id)
+                        .await
+                        .failed("Failed to purge account");
+                }
+            }
+
             // Iterate directory and spawn a task for each file
             let mut tasks = Vec::new();
             for entry in std::fs::read_dir(&src).failed("Failed to read directory") {
                 let entry = entry.failed("Failed to read entry");
                 let path = entry.path();
-                if path.is_file() {
+                if path.is_file() && !path.ends_with(MANIFEST_FILE) {
                     let storage = self.storage.clone();
                     let blob_store = self.storage.blob.clone();
                     tasks.push(tokio::spawn(async move {
@@ -92,6 +108,15 @@ async fn restore_file(store: Store, blob_store: BlobStore, path: &Path) {
                 batch_size += key.len() + value.len() + U32_LEN * 2;
 
                 match family {
+                    Family::Property if key.len() == BLOB_HASH_LEN => {
+                        batch.set(
+                            ValueClass::Blob(BlobOp::Metadata {
+                                hash: BlobHash::try_from_hash_slice(&key)
+                                    .expect("Invalid blob hash"),
+                            }),
+                            value,
+                        );
+                    }
                     Family::Property => {
                         let field = key
                             .as_slice()
@@ -170,10 +195,10 @@ async fn restore_file(store: Store, blob_store: BlobStore, path: &Path) {
                         batch.set(ValueClass::Lookup(LookupClass::Key(key)), value);
                     }
                     Family::LookupCounter => {
-                        batch.add(
-                            ValueClass::Lookup(LookupClass::Counter(key)),
-                            i64::deserialize(&value).expect("Failed to deserialize counter"),
-                        );
+                        let class = LookupClass::Counter(key);
+                        let value =
+                            counter_delta(&store, ValueClass::Lookup(class.clone()), &value).await;
+                        batch.add(ValueClass::Lookup(class), value);
                     }
                     Family::Directory => {
                         let key = key.as_slice();
@@ -201,16 +226,15 @@ async fn restore_file(store: Store, blob_store: BlobStore, path: &Path) {
                                         .to_vec(),
                                 ),*/
                                 4 => {
-                                    batch.add(
-                                        ValueClass::Directory(DirectoryClass::UsedQuota(
-                                            key.get(1..)
-                                                .expect("Failed to read principal id")
-                                                .deserialize_leb128()
-                                                .expect("Failed to read principal id"),
-                                        )),
-                                        i64::deserialize(&value)
-                                            .expect("Failed to deserialize quota"),
-                                    );
+                                    let principal_id = key
+                                        .get(1..)
+                                        .expect("Failed to read principal id")
+                                        .deserialize_leb128()
+                                        .expect("Failed to read principal id");
+                                    let value =
+                                        counter_delta(&store, used_quota(principal_id), &value)
+                                            .await;
+                                    batch.add(used_quota(principal_id), value);
 
                                     continue;
                                 }
@@ -381,6 +405,198 @@ async fn restore_file(store: Store, blob_store: BlobStore, path: &Path) {
     }
 }
 
+// Counters are restored as a delta so importing an incremental backup
+// on top of its parent does not count them twice.
+async fn counter_delta(store: &Store, class: ValueClass<u32>, value: &[u8]) -> i64 {
+    i64::deserialize(value).expect("Failed to deserialize counter")
+        - store
+            .get_counter(ValueKey::from(class))
+            .await
+            .failed("Failed to get counter")
+}
+
+fn used_quota<T>(principal_id: u32) -> ValueClass<T> {
+    ValueClass::Directory(DirectoryClass::UsedQuota(principal_id))
+}
+
+pub async fn verify_backup(path: PathBuf) {
+    let errors = verify_backup_chain(path).await;
+
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("{error}");
+        }
+        eprintln!(
+            "Backup chain verification failed with {} error(s).",
+            errors.len()
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Verifies the backup chain ending at `path`, returning the inconsistencies found.
+pub async fn verify_backup_chain(path: PathBuf) -> Vec<String> {
+    // Walk the chain back to the full backup
+    let mut chain: Vec<(PathBuf, BackupManifest)> = Vec::new();
+    let mut next = Some(path);
+    while let Some(path) = next.take() {
+        let manifest = BackupManifest::read(&path);
+        if let Some((child_path, child)) = chain.last() {
+            if child.parent.as_ref().map(|p| p.id) != Some(manifest.id) {
+                failed(&format!(
+                    "Backup {} references parent {:?} but {} has id {}",
+                    child_path.display(),
+                    child.parent.as_ref().map(|p| p.id),
+                    path.display(),
+                    manifest.id
+                ));
+            } else if manifest.id > child.id {
+                failed(&format!(
+                    "Backup {} is newer than its child {}",
+                    path.display(),
+                    child_path.display()
+                ));
+            }
+        }
+        next = manifest.parent.as_ref().map(|p| p.path.clone());
+        chain.push((path, manifest));
+    }
+    chain.reverse();
+
+    if chain[0].1.accounts.is_some() {
+        failed(&format!(
+            "Backup chain does not start with a full backup, {} is incremental",
+            chain[0].0.display()
+        ));
+    }
+
+    let mut errors = Vec::new();
+    let mut links: AHashMap<u32, AHashSet<BlobHash>> = AHashMap::new();
+    let mut blobs: AHashSet<BlobHash> = AHashSet::new();
+
+    for (pos, (path, manifest)) in chain.iter().enumerate() {
+        let reset = manifest
+            .accounts
+            .as_ref()
+            .map(|accounts| accounts.iter().copied().collect::<AHashSet<_>>());
+
+        // Every account modified since the parent must be part of the increment
+        if let (Some(reset), Some((_, parent))) = (&reset, pos.checked_sub(1).map(|p| &chain[p])) {
+            for account_id in manifest.changed_accounts(parent) {
+                if !reset.contains(&account_id) {
+                    errors.push(format!(
+                        "{}: account {account_id} changed but was not exported",
+                        path.display()
+                    ));
+                }
+            }
+        }
+
+        // Replay the account links of this snapshot
+        match &reset {
+            Some(reset) => links.retain(|account_id, _| !reset.contains(account_id)),
+            None => links.clear(),
+        }
+
+        for entry in std::fs::read_dir(path).failed("Failed to read directory") {
+            let file = entry.failed("Failed to read entry").path();
+            if !file.is_file() || file.ends_with(MANIFEST_FILE) {
+                continue;
+            }
+
+            let mut reader = OpReader::new(&file).await;
+            let mut family = Family::None;
+            let mut account_id = u32::MAX;
+            let mut document_id = u32::MAX;
+
+            while let Some(op) = reader.next().await {
+                match op {
+                    Op::Family(f) => family = f,
+                    Op::AccountId(a) => {
+                        account_id = a;
+                        if account_id != u32::MAX
+                            && reset.as_ref().map_or(false, |r| !r.contains(&account_id))
+                            && matches!(
+                                family,
+                                Family::Property
+                                    | Family::FtsIndex
+                                    | Family::Blob
+                                    | Family::Index
+                                    | Family::Bitmap
+                                    | Family::Log
+                            )
+                        {
+                            errors.push(format!(
+                                "{}: contains data for unchanged account {account_id}",
+                                file.display()
+                            ));
+                        }
+                    }
+                    Op::DocumentId(d) => document_id = d,
+                    Op::KeyValue((key, _)) if family == Family::Acl => {
+                        // ACLs are exported for both the owner and the grantee
+                        let grant_account_id = key
+                            .as_slice()
+                            .deserialize_be_u32(0)
+                            .expect("Invalid ACL key");
+                        if reset.as_ref().map_or(false, |r| {
+                            !r.contains(&account_id) && !r.contains(&grant_account_id)
+                        }) {
+                            errors.push(format!(
+                                "{}: contains data for unchanged account {account_id}",
+                                file.display()
+                            ));
+                        }
+                    }
+                    Op::KeyValue((key, value)) if family == Family::Blob => {
+                        let hash = BlobHash::try_from_hash_slice(&key).expect("Invalid blob hash");
+                        if account_id != u32::MAX && document_id != u32::MAX {
+                            links.entry(account_id).or_default().insert(hash);
+                        } else if BlobHash::from(value.as_slice()) == hash {
+                            blobs.insert(hash);
+                        } else {
+                            errors.push(format!(
+                                "{}: blob {} does not match its contents",
+                                file.display(),
+                                hash.to_hex()
+                            ));
+                        }
+                    }
+                    Op::Collection(_) | Op::KeyValue(_) => (),
+                }
+            }
+        }
+
+        println!(
+            "Verified backup {} ({}).",
+            path.display(),
+            if reset.is_some() {
+                "incremental"
+            } else {
+                "full"
+            }
+        );
+    }
+
+    // All blobs referenced by the restored state must be part of the chain
+    for (account_id, hashes) in &links {
+        for hash in hashes {
+            if !blobs.contains(hash) {
+                errors.push(format!(
+                    "Account {account_id} links to blob {} which is missing from the chain",
+                    hash.to_hex()
+                ));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        println!("Backup chain of {} snapshot(s) is consistent.", chain.len());
+    }
+
+    errors
+}
+
 struct OpReader {
     version: u8,
     file: BufReader<File>,
@@ -0,0 +1,132 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use trc::StoreEvent;
+use utils::config::Config;
+
+use crate::Server;
+
+// Weight given to new samples when updating the average latency
+const LATENCY_WEIGHT: f64 = 0.2;
+const MIN_THROTTLE_DELAY: Duration = Duration::from_millis(10);
+const MAX_THROTTLE_DELAY: Duration = Duration::from_millis(500);
+
+/// Settings used to prioritize interactive store requests (such as IMAP FETCH
+/// or JMAP get) over background tasks (such as indexing or purging).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorePriority {
+    pub target_latency: Option<Duration>,
+    pub max_delay: Duration,
+    pub window: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    inner: Mutex<Option<LatencySample>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LatencySample {
+    average: f64,
+    updated: Instant,
+}
+
+impl StorePriority {
+    pub fn parse(config: &mut Config) -> Self {
+        StorePriority {
+            target_latency: config
+                .property_or_default::<Option<Duration>>("storage.priority.target-latency", "false")
+                .unwrap_or_default(),
+            max_delay: config
+                .property_or_default("storage.priority.max-delay", "5s")
+                .unwrap_or_else(|| Duration::from_secs(5)),
+            window: config
+                .property_or_default("storage.priority.window", "10s")
+                .unwrap_or_else(|| Duration::from_secs(10)),
+        }
+    }
+}
+
+impl Default for StorePriority {
+    fn default() -> Self {
+        Self {
+            target_latency: None,
+            max_delay: Duration::from_secs(5),
+            window: Duration::from_secs(10),
+        }
+    }
+}
+
+impl LatencyTracker {
+    pub fn record(&self, elapsed: Duration) {
+        let elapsed = elapsed.as_secs_f64();
+        let mut sample = self.inner.lock();
+        *sample = Some(LatencySample {
+            average: sample.map_or(elapsed, |sample| {
+                sample.average + LATENCY_WEIGHT * (elapsed - sample.average)
+            }),
+            updated: Instant::now(),
+        });
+    }
+
+    /// Returns the average latency of the interactive requests, or `None`
+    /// if no requests were received within the window.
+    pub fn average(&self, window: Duration) -> Option<Duration> {
+        self.inner
+            .lock()
+            .filter(|sample| sample.updated.elapsed() < window)
+            .map(|sample| Duration::from_secs_f64(sample.average))
+    }
+}
+
+impl Server {
+    /// Records the latency of an interactive request.
+    pub fn record_interactive_latency(&self, elapsed: Duration) {
+        if self.core.storage.priority.target_latency.is_some() {
+            self.inner.data.interactive_latency.record(elapsed);
+        }
+    }
+
+    /// Delays a background task while the latency of interactive
+    /// requests exceeds the configured target.
+    pub async fn yield_to_interactive(&self) {
+        let priority = &self.core.storage.priority;
+        let Some(target_latency) = priority.target_latency else {
+            return;
+        };
+
+        let start_time = Instant::now();
+        let mut delay = MIN_THROTTLE_DELAY;
+        let mut latency = None;
+        while let Some(average) = self
+            .inner
+            .data
+            .interactive_latency
+            .average(priority.window)
+            .filter(|average| *average > target_latency)
+        {
+            let elapsed = start_time.elapsed();
+            if elapsed >= priority.max_delay {
+                break;
+            }
+            latency = Some(average);
+            tokio::time::sleep(delay.min(priority.max_delay - elapsed)).await;
+            delay = (delay * 2).min(MAX_THROTTLE_DELAY);
+        }
+
+        if let Some(latency) = latency {
+            trc::event!(
+                Store(StoreEvent::BackgroundThrottled),
+                Details = latency,
+                Limit = target_latency,
+                Elapsed = start_time.elapsed(),
+            );
+        }
+    }
+}
@@ -70,6 +70,7 @@ pub fn register_functions_trusted() -> FunctionMap {
         .with_function_args("cosine_similarity", fn_cosine_similarity, 2)
         .with_function_args("jaccard_similarity", fn_jaccard_similarity, 2)
         .with_function_args("levenshtein_distance", fn_levenshtein_distance, 2)
+        .with_function_args("lookalike_domain", fn_lookalike_domain, 2)
         .with_function_args("html_has_tag", fn_html_has_tag, 2)
         .with_function_args("html_attr", fn_html_attr, 2)
         .with_function_args("html_attrs", fn_html_attrs, 3)
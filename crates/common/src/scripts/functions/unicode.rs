@@ -43,6 +43,32 @@ pub fn fn_has_obscured<'x>(_: &'x Context<'x>, v: Vec<Variable>) -> Variable {
     .into()
 }
 
+pub fn fn_lookalike_domain<'x>(_: &'x Context<'x>, v: Vec<Variable>) -> Variable {
+    let domain = v[0].to_string().to_lowercase();
+    if domain.is_empty() {
+        return Variable::default();
+    }
+
+    // Domains look alike when they differ but share the same confusable skeleton
+    let skeleton = unicode_security::skeleton(&domain).collect::<String>();
+    let is_lookalike = |candidate: &Variable| {
+        let candidate = candidate.to_string().to_lowercase();
+        !candidate.is_empty()
+            && candidate != domain
+            && unicode_security::skeleton(&candidate).eq(skeleton.chars())
+    };
+
+    match &v[1] {
+        Variable::Array(candidates) => candidates
+            .iter()
+            .find(|candidate| is_lookalike(candidate))
+            .cloned()
+            .unwrap_or_default(),
+        candidate if is_lookalike(candidate) => candidate.clone(),
+        _ => Variable::default(),
+    }
+}
+
 trait CharUtils {
     fn is_zwsp(&self) -> bool;
     fn is_obscured(&self) -> bool;
@@ -13,6 +13,7 @@ use crate::IntoString;
 
 pub mod functions;
 pub mod plugins;
+pub mod wasm;
 
 #[derive(Debug, serde::Serialize)]
 #[serde(tag = "action")]
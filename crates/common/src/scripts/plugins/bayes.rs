@@ -12,9 +12,11 @@ use nlp::{
     tokenizers::osb::{OsbToken, OsbTokenizer},
 };
 use sieve::{runtime::Variable, FunctionMap};
-use store::{write::key::KeySerializer, LookupStore, U64_LEN};
+use store::{write::key::KeySerializer, LookupStore, U32_LEN, U64_LEN};
 use trc::AddContext;
 
+use crate::Server;
+
 use super::PluginContext;
 
 pub fn register_train(plugin_id: u32, fnc_map: &mut FunctionMap) {
@@ -54,6 +56,35 @@ async fn train(ctx: PluginContext<'_>, is_train: bool) -> trc::Result<Variable>
 
     let text = ctx.arguments[1].to_string();
     let is_spam = ctx.arguments[2].to_bool();
+    if !is_train {
+        //TODO: Implement untrain
+        return Ok(false.into());
+    }
+
+    let total = bayes_train(ctx.server, store, None, text.as_ref(), is_spam, None).await?;
+
+    trc::event!(
+        Spam(trc::SpamEvent::Train),
+        SpanId = ctx.session_id,
+        Details = is_spam,
+        Total = total,
+    );
+
+    Ok(true.into())
+}
+
+/// Trains the global model, or the model of a single account when an account id
+/// is provided, returning the number of tokens learned. Account tokens bypass the
+/// shared token cache. Tokens and training counts expire once they have not been
+/// trained for `expiry` seconds.
+pub async fn bayes_train(
+    server: &Server,
+    store: &LookupStore,
+    account_id: Option<u32>,
+    text: &str,
+    is_spam: bool,
+    expiry: Option<u64>,
+) -> trc::Result<usize> {
     if text.is_empty() {
         trc::bail!(trc::SpamEvent::TrainError
             .into_err()
@@ -62,69 +93,69 @@ async fn train(ctx: PluginContext<'_>, is_train: bool) -> trc::Result<Variable>
 
     // Train the model
     let mut model = BayesModel::default();
-    model.train(
-        OsbTokenizer::new(BayesTokenizer::new(text.as_ref()), 5),
-        is_spam,
-    );
+    model.train(OsbTokenizer::new(BayesTokenizer::new(text), 5), is_spam);
     if model.weights.is_empty() {
         trc::bail!(trc::SpamEvent::TrainError
             .into_err()
             .reason("No weights found"));
     }
-
-    trc::event!(
-        Spam(trc::SpamEvent::Train),
-        SpanId = ctx.session_id,
-        Details = is_spam,
-        Total = model.weights.len(),
-    );
+    let total = model.weights.len();
 
     // Update weight and invalidate cache
-    let bayes_cache = &ctx.server.inner.data.bayes_cache;
-    if is_train {
-        for (hash, weights) in model.weights {
-            store
-                .counter_incr(
-                    KeySerializer::new(U64_LEN)
-                        .write(hash.h1)
-                        .write(hash.h2)
-                        .finalize(),
-                    weights.into(),
-                    None,
-                    false,
-                )
-                .await
-                .caused_by(trc::location!())?;
-
-            bayes_cache.invalidate(&hash);
-        }
-
-        // Update training counts
-        let weights = if is_spam {
-            Weights { spam: 1, ham: 0 }
-        } else {
-            Weights { spam: 0, ham: 1 }
-        };
+    let bayes_cache = &server.inner.data.bayes_cache;
+    for (hash, weights) in model.weights {
         store
             .counter_incr(
-                KeySerializer::new(U64_LEN)
-                    .write(0u64)
-                    .write(0u64)
-                    .finalize(),
+                bayes_token_key(account_id, &hash),
                 weights.into(),
-                None,
+                expiry,
                 false,
             )
             .await
             .caused_by(trc::location!())?;
+
+        if account_id.is_none() {
+            bayes_cache.invalidate(&hash);
+        }
+    }
+
+    // Update training counts
+    let weights = if is_spam {
+        Weights { spam: 1, ham: 0 }
     } else {
-        //TODO: Implement untrain
-        return Ok(false.into());
+        Weights { spam: 0, ham: 1 }
+    };
+    store
+        .counter_incr(
+            bayes_token_key(account_id, &TokenHash::default()),
+            weights.into(),
+            expiry,
+            false,
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+    if account_id.is_none() {
+        bayes_cache.invalidate(&TokenHash::default());
     }
 
-    bayes_cache.invalidate(&TokenHash::default());
+    Ok(total)
+}
 
-    Ok(true.into())
+/// Returns the key of a token in the global model or in an account's model.
+pub fn bayes_token_key(account_id: Option<u32>, hash: &TokenHash) -> Vec<u8> {
+    if let Some(account_id) = account_id {
+        KeySerializer::new(U32_LEN + U64_LEN * 2)
+            .write(account_id)
+            .write(hash.h1)
+            .write(hash.h2)
+            .finalize()
+    } else {
+        KeySerializer::new(U64_LEN)
+            .write(hash.h1)
+            .write(hash.h2)
+            .finalize()
+    }
 }
 
 pub async fn exec_classify(ctx: PluginContext<'_>) -> trc::Result<Variable> {
@@ -144,8 +175,9 @@ pub async fn exec_classify(ctx: PluginContext<'_>) -> trc::Result<Variable> {
             .reason("Empty message"));
     }
 
-    // Create classifier from defaults
+    // Create classifier from defaults, optionally using an account's own model
     let mut classifier = BayesClassifier::default();
+    let mut account_id = None;
     if let Some(params) = ctx.arguments[2].as_array() {
         if let Some(Variable::Integer(value)) = params.first() {
             classifier.min_token_hits = *value as u32;
@@ -159,14 +191,16 @@ pub async fn exec_classify(ctx: PluginContext<'_>) -> trc::Result<Variable> {
         if let Some(Variable::Integer(value)) = params.get(3) {
             classifier.min_learns = *value as u32;
         }
+        if let Some(Variable::Integer(value)) = params.get(4) {
+            account_id = Some(*value as u32);
+        }
     }
 
     // Obtain training counts
-    let bayes_cache = &ctx.server.inner.data.bayes_cache;
-    let (spam_learns, ham_learns) = bayes_cache
-        .get_or_update(TokenHash::default(), store)
-        .await
-        .map(|w| (w.spam, w.ham))?;
+    let (spam_learns, ham_learns) =
+        token_weights(ctx.server, store, account_id, TokenHash::default())
+            .await
+            .map(|w| (w.spam, w.ham))?;
 
     // Make sure we have enough training data
     if spam_learns < classifier.min_learns || ham_learns < classifier.min_learns {
@@ -185,7 +219,7 @@ pub async fn exec_classify(ctx: PluginContext<'_>) -> trc::Result<Variable> {
     // Classify the text
     let mut tokens = Vec::new();
     for token in OsbTokenizer::<_, TokenHash>::new(BayesTokenizer::new(text.as_ref()), 5) {
-        let weights = bayes_cache.get_or_update(token.inner, store).await?;
+        let weights = token_weights(ctx.server, store, account_id, token.inner).await?;
         tokens.push(OsbToken {
             inner: weights,
             idx: token.idx,
@@ -262,6 +296,34 @@ pub async fn exec_is_balanced(ctx: PluginContext<'_>) -> trc::Result<Variable> {
     Ok(result.into())
 }
 
+async fn token_weights(
+    server: &Server,
+    store: &LookupStore,
+    account_id: Option<u32>,
+    hash: TokenHash,
+) -> trc::Result<Weights> {
+    if account_id.is_some() {
+        store
+            .counter_get(bayes_token_key(account_id, &hash))
+            .await
+            .caused_by(trc::location!())
+            .map(|num| {
+                if num != 0 {
+                    Weights::from(num)
+                } else {
+                    Weights::default()
+                }
+            })
+    } else {
+        server
+            .inner
+            .data
+            .bayes_cache
+            .get_or_update(hash, store)
+            .await
+    }
+}
+
 trait LookupOrInsert {
     async fn get_or_update(&self, hash: TokenHash, get_token: &LookupStore)
         -> trc::Result<Weights>;
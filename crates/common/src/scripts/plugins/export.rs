@@ -0,0 +1,83 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use sieve::{compiler::Number, runtime::Variable, FunctionMap};
+use store::write::now;
+use tokio::io::AsyncWriteExt;
+
+use super::PluginContext;
+
+const DISPOSITIONS: [&str; 4] = ["ham", "spam", "discard", "reject"];
+
+pub fn register(plugin_id: u32, fnc_map: &mut FunctionMap) {
+    fnc_map.set_external_function("spam_export", plugin_id, 3);
+}
+
+/// Appends the tags hit by a message, its final score and disposition to the
+/// export file as a JSON line. Only tag names and numbers are written, any
+/// other value passed by the script is dropped so no message contents or
+/// addresses can end up in the export.
+pub async fn exec(ctx: PluginContext<'_>) -> trc::Result<Variable> {
+    let Some(export) = &ctx.server.core.sieve.spam_export else {
+        return Ok(false.into());
+    };
+
+    let mut features = serde_json::Map::new();
+    for feature in ctx.arguments[0].to_string().split(',') {
+        if let Some((tag, score)) = feature.trim().split_once('=') {
+            if !tag.is_empty()
+                && tag
+                    .chars()
+                    .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_')
+            {
+                if let Ok(score) = score.trim().parse::<f64>() {
+                    features.insert(tag.to_string(), score.into());
+                }
+            }
+        }
+    }
+    let score = match ctx.arguments[1].to_number_checked() {
+        Some(Number::Integer(n)) => n as f64,
+        Some(Number::Float(n)) => n,
+        None => 0.0,
+    };
+    let disposition = ctx.arguments[2].to_string();
+    let Some(disposition) = DISPOSITIONS.iter().find(|d| **d == disposition) else {
+        return Err(trc::SieveEvent::RuntimeError
+            .ctx(trc::Key::Value, disposition.into_owned())
+            .details("Invalid spam filter disposition"));
+    };
+
+    let mut record = serde_json::json!({
+        "timestamp": now(),
+        "score": score,
+        "disposition": disposition,
+        "features": features,
+    })
+    .to_string();
+    record.push('\n');
+
+    // Records are written with a single append so concurrent sessions
+    // never interleave their lines
+    let result = match tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&export.path)
+        .await
+    {
+        Ok(mut file) => file.write_all(record.as_bytes()).await,
+        Err(err) => Err(err),
+    };
+    result.map_err(|err| {
+        trc::SieveEvent::RuntimeError
+            .into_err()
+            .reason(err)
+            .ctx(trc::Key::Path, export.path.display().to_string())
+            .details("Failed to write spam filter export")
+    })?;
+
+    Ok(true.into())
+}
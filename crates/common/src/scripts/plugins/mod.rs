@@ -7,6 +7,7 @@
 pub mod bayes;
 pub mod dns;
 pub mod exec;
+pub mod export;
 pub mod headers;
 pub mod http;
 pub mod llm_prompt;
@@ -33,7 +34,7 @@ pub struct PluginContext<'x> {
     pub arguments: Vec<Variable>,
 }
 
-const PLUGINS_REGISTER: [RegisterPluginFnc; 19] = [
+const PLUGINS_REGISTER: [RegisterPluginFnc; 20] = [
     query::register,
     exec::register,
     lookup::register,
@@ -53,6 +54,7 @@ const PLUGINS_REGISTER: [RegisterPluginFnc; 19] = [
     text::register_tokenize,
     text::register_domain_part,
     llm_prompt::register,
+    export::register,
 ];
 
 pub trait RegisterSievePlugins {
@@ -107,6 +109,7 @@ impl Core {
             16 => text::exec_tokenize(ctx),
             17 => text::exec_domain_part(ctx),
             18 => llm_prompt::exec(ctx).await,
+            19 => export::exec(ctx).await,
             _ => unreachable!(),
         };
 
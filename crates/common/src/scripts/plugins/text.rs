@@ -6,6 +6,7 @@
 
 use nlp::tokenizers::types::{TokenType, TypesTokenizer};
 use sieve::{runtime::Variable, FunctionMap};
+use utils::suffixlist;
 
 use crate::scripts::functions::{html::html_to_tokens, text::tokenize_words, ApplyString};
 
@@ -67,9 +68,18 @@ pub fn exec_domain_part(ctx: PluginContext<'_>) -> trc::Result<Variable> {
         _ => return Ok(Variable::default()),
     };
 
+    let public_suffix = ctx.server.core.sieve.public_suffix.as_deref();
     Ok(v[0].transform(|domain| {
         match part {
-            DomainPart::Sld => psl::domain_str(domain),
+            DomainPart::Sld => {
+                if let Some(public_suffix) = public_suffix {
+                    return public_suffix
+                        .domain_part(domain, suffixlist::DomainPart::Sld)
+                        .map(Variable::from)
+                        .unwrap_or_default();
+                }
+                psl::domain_str(domain)
+            }
             DomainPart::Tld => domain.rsplit_once('.').map(|(_, tld)| tld),
             DomainPart::Host => domain.split_once('.').map(|(host, _)| host),
         }
@@ -0,0 +1,89 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{path::Path, sync::LazyLock};
+
+use wasmtime::{Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut config = wasmtime::Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config).expect("Failed to create WebAssembly engine")
+});
+
+/// Resources a module may consume on each invocation.
+#[derive(Debug, Clone, Copy)]
+pub struct WasmLimits {
+    pub fuel: u64,
+    pub memory: usize,
+}
+
+pub fn load_module(path: impl AsRef<Path>) -> Result<Module, String> {
+    let path = path.as_ref();
+    Module::from_file(&ENGINE, path).map_err(|err| {
+        format!(
+            "Failed to load WebAssembly module {}: {err}",
+            path.display()
+        )
+    })
+}
+
+/// Invokes an exported function with the guest ABI used by hooks and plugins:
+/// the module exports its `memory` and an `alloc(len) -> ptr` function, the
+/// input is copied into the guest and the function returns the location of
+/// its output packed as `ptr << 32 | len`.
+///
+/// Each call runs in a fresh instance without any imports, so modules cannot
+/// reach the filesystem or the network and no state survives between calls.
+pub fn call_module(
+    module: &Module,
+    limits: WasmLimits,
+    function: &str,
+    input: &[u8],
+) -> Result<Vec<u8>, String> {
+    let mut store = Store::new(
+        &ENGINE,
+        StoreLimitsBuilder::new()
+            .memory_size(limits.memory)
+            .instances(1)
+            .build(),
+    );
+    store.limiter(|limits: &mut StoreLimits| limits);
+    store
+        .set_fuel(limits.fuel)
+        .map_err(|err| format!("Failed to set fuel: {err}"))?;
+
+    let instance = Instance::new(&mut store, module, &[])
+        .map_err(|err| format!("Failed to instantiate module: {err}"))?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| "Module does not export its memory".to_string())?;
+    let alloc = instance
+        .get_typed_func::<u32, u32>(&mut store, "alloc")
+        .map_err(|err| format!("Invalid alloc export: {err}"))?;
+    let entry = instance
+        .get_typed_func::<(u32, u32), u64>(&mut store, function)
+        .map_err(|err| format!("Invalid {function} export: {err}"))?;
+
+    let input_len =
+        u32::try_from(input.len()).map_err(|_| "Input exceeds the module address space")?;
+    let input_ptr = alloc
+        .call(&mut store, input_len)
+        .map_err(|err| format!("Module failed to allocate memory: {err}"))?;
+    memory
+        .write(&mut store, input_ptr as usize, input)
+        .map_err(|err| format!("Failed to write module input: {err}"))?;
+
+    let result = entry
+        .call(&mut store, (input_ptr, input_len))
+        .map_err(|err| format!("Module {function} failed: {err}"))?;
+    let mut output = vec![0u8; (result & 0xFFFF_FFFF) as usize];
+    memory
+        .read(&store, (result >> 32) as usize, &mut output)
+        .map_err(|err| format!("Failed to read module output: {err}"))?;
+
+    Ok(output)
+}
@@ -11,6 +11,7 @@
 use std::{future::Future, time::Duration};
 
 use ahash::{AHashMap, AHashSet};
+use mail_parser::DateTime;
 use store::{
     write::{key::DeserializeBigEndian, BatchBuilder, MaybeDynamicId, TelemetryClass, ValueClass},
     Deserialize, IterateParams, Store, ValueKey, U64_LEN,
@@ -138,6 +139,23 @@ pub enum TracingQuery {
     Keywords(String),
 }
 
+/// Filters applied to each stored event when searching the tracing history.
+/// Account and keyword filters also match the keys of the span an event
+/// belongs to.
+#[derive(Debug, Default)]
+pub struct EventQuery {
+    pub types: Vec<EventType>,
+    pub accounts: Vec<String>,
+    pub keywords: Vec<String>,
+}
+
+/// Position of an event within the tracing history, used to resume a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventPosition {
+    pub span_id: u64,
+    pub index: u32,
+}
+
 pub trait TracingStore: Sync + Send {
     fn get_span(
         &self,
@@ -153,6 +171,14 @@ pub trait TracingStore: Sync + Send {
         from_span_id: u64,
         to_span_id: u64,
     ) -> impl Future<Output = trc::Result<Vec<u64>>> + Send;
+    fn query_events(
+        &self,
+        query: &EventQuery,
+        from_span_id: u64,
+        to_span_id: u64,
+        after: Option<EventPosition>,
+        limit: usize,
+    ) -> impl Future<Output = trc::Result<(Vec<Event<EventDetails>>, Option<EventPosition>)>> + Send;
     fn purge_spans(&self, period: Duration) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
@@ -245,6 +271,75 @@ impl TracingStore for Store {
         Ok(spans.into_vec())
     }
 
+    async fn query_events(
+        &self,
+        query: &EventQuery,
+        from_span_id: u64,
+        to_span_id: u64,
+        after: Option<EventPosition>,
+        limit: usize,
+    ) -> trc::Result<(Vec<Event<EventDetails>>, Option<EventPosition>)> {
+        let mut to_span_id = if to_span_id != 0 {
+            to_span_id
+        } else {
+            u64::MAX
+        };
+        if let Some(after) = after {
+            to_span_id = to_span_id.min(after.span_id);
+        }
+        let mut results = Vec::new();
+        let mut last_position = None;
+
+        // Spans are returned newest first, events within a span in the
+        // order they were emitted
+        self.iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Telemetry(TelemetryClass::Span {
+                    span_id: from_span_id,
+                })),
+                ValueKey::from(ValueClass::Telemetry(TelemetryClass::Span {
+                    span_id: to_span_id,
+                })),
+            )
+            .descending(),
+            |key, value| {
+                let span_id = key
+                    .deserialize_be_u64(key.len() - U64_LEN)
+                    .caused_by(trc::location!())?;
+                let events = deserialize_events(value).caused_by(trc::location!())?;
+                let span_keys = events
+                    .first()
+                    .map(|span| span.keys.as_slice())
+                    .unwrap_or_default();
+
+                for (index, event) in events.iter().enumerate() {
+                    let position = EventPosition {
+                        span_id,
+                        index: index as u32,
+                    };
+                    if after.map_or(true, |after| {
+                        after.span_id != span_id || position.index > after.index
+                    }) && query.matches(event, span_keys)
+                    {
+                        if limit != 0 && results.len() == limit {
+                            return Ok(false);
+                        }
+                        results.push(event.clone());
+                        last_position = Some(position);
+                    }
+                }
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+        let cursor = last_position.filter(|_| limit != 0 && results.len() == limit);
+
+        Ok((results, cursor))
+    }
+
     async fn purge_spans(&self, period: Duration) -> trc::Result<()> {
         let until_span_id = SnowflakeIdGenerator::from_duration(period).ok_or_else(|| {
             trc::StoreEvent::UnexpectedError
@@ -312,6 +407,75 @@ impl TracingStore for Store {
     }
 }
 
+impl EventPosition {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(U64_LEN + std::mem::size_of::<u32>());
+        bytes.extend_from_slice(&self.span_id.to_be_bytes());
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(EventPosition {
+            span_id: u64::from_be_bytes(bytes.get(..U64_LEN)?.try_into().ok()?),
+            index: u32::from_be_bytes(bytes.get(U64_LEN..)?.try_into().ok()?),
+        })
+    }
+}
+
+impl EventQuery {
+    pub fn matches(&self, event: &Event<EventDetails>, span_keys: &[(Key, Value)]) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&event.inner.typ) {
+            return false;
+        }
+
+        let keys = || event.keys.iter().chain(span_keys.iter());
+        if !self.accounts.is_empty()
+            && !keys().any(|(key, value)| match (key, value) {
+                (Key::AccountName, Value::String(name)) => self
+                    .accounts
+                    .iter()
+                    .any(|account| account.eq_ignore_ascii_case(name)),
+                (Key::AccountId, Value::UInt(id)) => self.accounts.iter().any(|account| {
+                    account
+                        .parse::<u64>()
+                        .map_or(false, |account| account == *id)
+                }),
+                _ => false,
+            })
+        {
+            return false;
+        }
+
+        self.keywords
+            .iter()
+            .all(|keyword| keys().any(|(_, value)| value_contains(value, keyword)))
+    }
+}
+
+/// Returns whether the textual representation of a value contains the needle.
+pub fn value_contains(value: &Value, needle: &str) -> bool {
+    match value {
+        Value::Static(haystack) => haystack.contains(needle),
+        Value::String(haystack) => haystack.contains(needle),
+        Value::Timestamp(haystack) => DateTime::from_timestamp(*haystack as i64)
+            .to_rfc3339()
+            .contains(needle),
+        Value::Bool(true) => needle == "true",
+        Value::Bool(false) => needle == "false",
+        Value::Ipv4(haystack) => haystack.to_string().contains(needle),
+        Value::Ipv6(haystack) => haystack.to_string().contains(needle),
+        Value::Array(values) => values.iter().any(|value| value_contains(value, needle)),
+        Value::Event(_)
+        | Value::UInt(_)
+        | Value::Int(_)
+        | Value::Float(_)
+        | Value::Duration(_)
+        | Value::Bytes(_)
+        | Value::None => false,
+    }
+}
+
 enum SpanCollector {
     Vec(Vec<u64>),
     HashSet(AHashSet<u64>),
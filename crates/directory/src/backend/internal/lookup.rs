@@ -11,7 +11,11 @@ use store::{
 };
 use trc::AddContext;
 
-use crate::{backend::RcptType, Principal, QueryBy, Type};
+use crate::{
+    backend::RcptType,
+    core::secret::{PasswordHashing, VerifiedSecret},
+    Principal, QueryBy, Type,
+};
 
 use super::{manage::ManageDirectory, PrincipalField, PrincipalInfo};
 
@@ -22,6 +26,12 @@ pub trait DirectoryStore: Sync + Send {
         by: QueryBy<'_>,
         return_member_of: bool,
     ) -> trc::Result<Option<Principal>>;
+    async fn query_with_rehash(
+        &self,
+        by: QueryBy<'_>,
+        return_member_of: bool,
+        hashing: Option<&PasswordHashing>,
+    ) -> trc::Result<Option<Principal>>;
     async fn email_to_id(&self, address: &str) -> trc::Result<Option<u32>>;
     async fn is_local_domain(&self, domain: &str) -> trc::Result<bool>;
     async fn rcpt(&self, address: &str) -> trc::Result<RcptType>;
@@ -35,6 +45,15 @@ impl DirectoryStore for Store {
         &self,
         by: QueryBy<'_>,
         return_member_of: bool,
+    ) -> trc::Result<Option<Principal>> {
+        self.query_with_rehash(by, return_member_of, None).await
+    }
+
+    async fn query_with_rehash(
+        &self,
+        by: QueryBy<'_>,
+        return_member_of: bool,
+        hashing: Option<&PasswordHashing>,
     ) -> trc::Result<Option<Principal>> {
         let (account_id, secret) = match by {
             QueryBy::Name(name) => (self.get_principal_id(name).await?, None),
@@ -57,8 +76,18 @@ impl DirectoryStore for Store {
         if let Some(account_id) = account_id {
             if let Some(mut principal) = self.get_principal(account_id).await? {
                 if let Some(secret) = secret {
-                    if !principal.verify_secret(secret).await? {
-                        return Ok(None);
+                    match principal.verified_secret(secret).await? {
+                        Some(VerifiedSecret::Password { hash, secret }) => {
+                            // Upgrade legacy password hashes after a successful login
+                            if let Some(hashing) =
+                                hashing.filter(|hashing| hashing.needs_rehash(hash))
+                            {
+                                // Updating the principal may query the directory again
+                                Box::pin(hashing.rehash(self, account_id, hash, secret)).await;
+                            }
+                        }
+                        Some(VerifiedSecret::AppPassword) => {}
+                        None => return Ok(None),
                     }
                 }
 
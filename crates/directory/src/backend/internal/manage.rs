@@ -17,8 +17,8 @@ use trc::AddContext;
 use utils::sanitize_email;
 
 use crate::{
-    backend::RcptType, Permission, Permissions, Principal, QueryBy, Type, MAX_TYPE_ID, ROLE_ADMIN,
-    ROLE_TENANT_ADMIN, ROLE_USER,
+    backend::RcptType, core::filter::addresses_changed, Permission, Permissions, Principal,
+    QueryBy, Type, MAX_TYPE_ID, ROLE_ADMIN, ROLE_TENANT_ADMIN, ROLE_USER,
 };
 
 use super::{
@@ -35,6 +35,9 @@ pub struct MemberOf {
 pub struct PrincipalList {
     pub items: Vec<Principal>,
     pub total: u64,
+    /// Name of the last principal returned, used to resume the listing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
 }
 
 pub struct UpdatePrincipal<'x> {
@@ -43,6 +46,7 @@ pub struct UpdatePrincipal<'x> {
     changes: Vec<PrincipalUpdate>,
     tenant_id: Option<u32>,
     create_domains: bool,
+    is_rehash: bool,
 }
 
 #[allow(async_fn_in_trait)]
@@ -70,6 +74,17 @@ pub trait ManageDirectory: Sized {
         page: usize,
         limit: usize,
     ) -> trc::Result<PrincipalList>;
+    #[allow(clippy::too_many_arguments)]
+    async fn list_principals_after(
+        &self,
+        filter: Option<&str>,
+        tenant_id: Option<u32>,
+        types: &[Type],
+        fields: &[PrincipalField],
+        after: Option<&str>,
+        page: usize,
+        limit: usize,
+    ) -> trc::Result<PrincipalList>;
     async fn count_principals(
         &self,
         filter: Option<&str>,
@@ -432,11 +447,13 @@ impl ManageDirectory for Store {
             );
 
         // Write email to id mapping
+        let mut has_emails = false;
         if let Some(emails) = principal
             .take(PrincipalField::Emails)
             .map(|v| v.into_str_array())
         {
             for email in emails {
+                has_emails = true;
                 batch.set(
                     ValueClass::Directory(DirectoryClass::EmailToId(email.into_bytes())),
                     pinfo_email,
@@ -478,9 +495,17 @@ impl ManageDirectory for Store {
             );
         }
 
-        self.write(batch.build())
+        let principal_id = self
+            .write(batch.build())
             .await
-            .and_then(|r| r.last_document_id())
+            .and_then(|r| r.last_document_id())?;
+
+        // Address filters built before this point do not know the new addresses
+        if has_emails {
+            addresses_changed();
+        }
+
+        Ok(principal_id)
     }
 
     async fn delete_principal(&self, by: QueryBy<'_>) -> trc::Result<()> {
@@ -622,17 +647,12 @@ impl ManageDirectory for Store {
         }
         // SPDX-SnippetEnd
 
-        // Unlink all principal's blobs
-        self.blob_hash_unlink_account(principal_id)
-            .await
-            .caused_by(trc::location!())?;
-
         // Revoke ACLs
         self.acl_revoke_all(principal_id)
             .await
             .caused_by(trc::location!())?;
 
-        // Delete principal data
+        // Delete principal data and unlink its blobs
         self.purge_account(principal_id)
             .await
             .caused_by(trc::location!())?;
@@ -649,7 +669,8 @@ impl ManageDirectory for Store {
             .clear(DirectoryClass::Principal(MaybeDynamicId::Static(
                 principal_id,
             )))
-            .clear(DirectoryClass::UsedQuota(principal_id));
+            .clear(DirectoryClass::UsedQuota(principal_id))
+            .clear(DirectoryClass::CredentialRevision(principal_id));
 
         if let Some(emails) = principal.take_str_array(PrincipalField::Emails) {
             for email in emails {
@@ -706,6 +727,7 @@ impl ManageDirectory for Store {
         };
         let changes = params.changes;
         let tenant_id = params.tenant_id;
+        let is_rehash = params.is_rehash;
 
         // Fetch principal
         let mut principal = self
@@ -733,6 +755,7 @@ impl ManageDirectory for Store {
 
         // Prepare changes
         let mut batch = BatchBuilder::new();
+        let mut has_new_emails = false;
         let mut pinfo_name =
             PrincipalInfo::new(principal_id, principal.inner.typ, principal.inner.tenant())
                 .serialize();
@@ -757,6 +780,19 @@ impl ManageDirectory for Store {
             );
         }
 
+        // Changing a password revokes long-lived OAuth tokens, re-hashing does not
+        if !is_rehash
+            && changes.iter().any(|c| {
+                c.field == PrincipalField::Secrets
+                    && match &c.value {
+                        PrincipalValue::String(secret) => secret.is_password(),
+                        _ => c.action == PrincipalAction::Set,
+                    }
+            })
+        {
+            batch.add(DirectoryClass::CredentialRevision(principal_id), 1);
+        }
+
         let mut used_quota: Option<i64> = None;
 
         // SPDX-SnippetBegin
@@ -966,7 +1002,7 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::Set,
-                    PrincipalField::Description | PrincipalField::Picture,
+                    PrincipalField::Description | PrincipalField::Picture | PrincipalField::Locale,
                     PrincipalValue::String(value),
                 ) => {
                     if !value.is_empty() {
@@ -1024,6 +1060,7 @@ impl ManageDirectory for Store {
                                 )),
                                 pinfo_email.clone(),
                             );
+                            has_new_emails = true;
                         }
                     }
 
@@ -1058,6 +1095,7 @@ impl ManageDirectory for Store {
                             pinfo_email.clone(),
                         );
                         principal.inner.append_str(PrincipalField::Emails, email);
+                        has_new_emails = true;
                     }
                 }
                 (
@@ -1505,6 +1543,10 @@ impl ManageDirectory for Store {
             .await
             .caused_by(trc::location!())?;
 
+        if has_new_emails {
+            addresses_changed();
+        }
+
         Ok(())
     }
 
@@ -1517,7 +1559,30 @@ impl ManageDirectory for Store {
         page: usize,
         limit: usize,
     ) -> trc::Result<PrincipalList> {
-        let from_key = ValueKey::from(ValueClass::Directory(DirectoryClass::NameToId(vec![])));
+        self.list_principals_after(filter, tenant_id, types, fields, None, page, limit)
+            .await
+    }
+
+    async fn list_principals_after(
+        &self,
+        filter: Option<&str>,
+        tenant_id: Option<u32>,
+        types: &[Type],
+        fields: &[PrincipalField],
+        after: Option<&str>,
+        page: usize,
+        limit: usize,
+    ) -> trc::Result<PrincipalList> {
+        // Principals are listed in name order, resuming after the last name seen
+        let from_key = ValueKey::from(ValueClass::Directory(DirectoryClass::NameToId(
+            after
+                .map(|name| {
+                    let mut key = name.as_bytes().to_vec();
+                    key.push(0);
+                    key
+                })
+                .unwrap_or_default(),
+        )));
         let to_key = ValueKey::from(ValueClass::Directory(DirectoryClass::NameToId(vec![
             u8::MAX;
             10
@@ -1547,13 +1612,21 @@ impl ManageDirectory for Store {
             && !fields.is_empty()
             && fields.iter().all(|f| matches!(f, PrincipalField::Name))
         {
+            let total = results.len() as u64;
+            let items = results
+                .into_iter()
+                .skip(page.saturating_sub(1) * limit)
+                .take(if limit > 0 { limit } else { usize::MAX })
+                .collect::<Vec<_>>();
+            let cursor = items
+                .last()
+                .filter(|_| limit != 0 && items.len() == limit)
+                .map(|principal| principal.name().to_string());
+
             return Ok(PrincipalList {
-                total: results.len() as u64,
-                items: results
-                    .into_iter()
-                    .skip(page.saturating_sub(1) * limit)
-                    .take(if limit > 0 { limit } else { usize::MAX })
-                    .collect(),
+                items,
+                total,
+                cursor,
             });
         }
 
@@ -1603,6 +1676,7 @@ impl ManageDirectory for Store {
 
                 if offset == 0 {
                     if !is_done {
+                        let name = principal.name().to_string();
                         if !fields.is_empty() {
                             principal.fields.retain(|k, _| fields.contains(k));
                         }
@@ -1614,6 +1688,9 @@ impl ManageDirectory for Store {
                         }
                         result.items.push(principal);
                         is_done = limit != 0 && result.items.len() >= limit;
+                        if is_done {
+                            result.cursor = Some(name);
+                        }
                     }
                 } else {
                     offset -= 1;
@@ -1939,6 +2016,7 @@ impl<'x> UpdatePrincipal<'x> {
             create_domains: false,
             tenant_id: None,
             allowed_permissions: None,
+            is_rehash: false,
         }
     }
 
@@ -1949,6 +2027,7 @@ impl<'x> UpdatePrincipal<'x> {
             create_domains: false,
             tenant_id: None,
             allowed_permissions: None,
+            is_rehash: false,
         }
     }
 
@@ -1971,6 +2050,11 @@ impl<'x> UpdatePrincipal<'x> {
         self.create_domains = true;
         self
     }
+
+    pub fn as_rehash(mut self) -> Self {
+        self.is_rehash = true;
+        self
+    }
 }
 
 fn validate_member_of(
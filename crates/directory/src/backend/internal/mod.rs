@@ -413,6 +413,7 @@ pub enum PrincipalField {
     Picture,
     Urls,
     ExternalMembers,
+    Locale,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -493,6 +494,7 @@ impl PrincipalField {
             PrincipalField::Picture => 14,
             PrincipalField::Urls => 15,
             PrincipalField::ExternalMembers => 16,
+            PrincipalField::Locale => 17,
         }
     }
 
@@ -515,6 +517,7 @@ impl PrincipalField {
             14 => Some(PrincipalField::Picture),
             15 => Some(PrincipalField::Urls),
             16 => Some(PrincipalField::ExternalMembers),
+            17 => Some(PrincipalField::Locale),
             _ => None,
         }
     }
@@ -538,6 +541,7 @@ impl PrincipalField {
             PrincipalField::Picture => "picture",
             PrincipalField::Urls => "urls",
             PrincipalField::ExternalMembers => "externalMembers",
+            PrincipalField::Locale => "locale",
         }
     }
 
@@ -560,6 +564,7 @@ impl PrincipalField {
             "picture" => Some(PrincipalField::Picture),
             "urls" => Some(PrincipalField::Urls),
             "externalMembers" => Some(PrincipalField::ExternalMembers),
+            "locale" => Some(PrincipalField::Locale),
             _ => None,
         }
     }
@@ -22,7 +22,7 @@ use crate::{
     Directories, Directory, DirectoryInner,
 };
 
-use super::cache::CachedDirectory;
+use super::{cache::CachedDirectory, filter::AddressFilter, secret::PasswordHashing};
 
 impl Directories {
     pub async fn parse(
@@ -108,9 +108,18 @@ impl Directories {
                     continue;
                 }
 
+                // Only the internal directory can list its addresses
+                let filter = if matches!(store, DirectoryInner::Internal(_)) {
+                    AddressFilter::try_from_config(config, ("directory", id))
+                } else {
+                    None
+                };
+
                 let directory = Arc::new(Directory {
                     store,
                     cache: CachedDirectory::try_from_config(config, ("directory", id)),
+                    filter,
+                    password: PasswordHashing::parse(config, ("directory", id)),
                 });
 
                 // Add directory
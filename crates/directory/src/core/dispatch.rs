@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use mail_send::Credentials;
 use trc::AddContext;
 
 use crate::{
@@ -18,7 +19,11 @@ impl Directory {
         return_member_of: bool,
     ) -> trc::Result<Option<Principal>> {
         match &self.store {
-            DirectoryInner::Internal(store) => store.query(by, return_member_of).await,
+            DirectoryInner::Internal(store) => {
+                let hashing = matches!(by, QueryBy::Credentials(Credentials::Plain { .. }))
+                    .then_some(&self.password);
+                store.query_with_rehash(by, return_member_of, hashing).await
+            }
             DirectoryInner::Ldap(store) => store.query(by, return_member_of).await,
             DirectoryInner::Sql(store) => store.query(by, return_member_of).await,
             DirectoryInner::Imap(store) => store.query(by).await,
@@ -31,6 +36,10 @@ impl Directory {
     }
 
     pub async fn email_to_id(&self, address: &str) -> trc::Result<Option<u32>> {
+        if self.is_unknown_address(address) {
+            return Ok(None);
+        }
+
         match &self.store {
             DirectoryInner::Internal(store) => store.email_to_id(address).await,
             DirectoryInner::Ldap(store) => store.email_to_id(address).await,
@@ -79,6 +88,9 @@ impl Directory {
                 return Ok(result);
             }
         }
+        if self.is_unknown_address(email) {
+            return Ok(RcptType::Invalid);
+        }
 
         let result = match &self.store {
             DirectoryInner::Internal(store) => store.rcpt(email).await,
@@ -0,0 +1,220 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    f64::consts::LN_2,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use futures::{pin_mut, TryStreamExt};
+use parking_lot::RwLock;
+use store::{
+    dispatch::stream::DEFAULT_CHUNK_SIZE,
+    write::{DirectoryClass, ValueClass},
+    IterateParams, ValueKey,
+};
+use trc::AddContext;
+use utils::config::{utils::AsKey, Config};
+
+use crate::{Directory, DirectoryInner};
+
+const MAX_HASHES: u32 = 16;
+
+// Bumped by the directory write path whenever addresses are added, filters
+// built before the last change are ignored until they are rebuilt.
+static ADDRESS_CHANGES: AtomicU64 = AtomicU64::new(0);
+
+/// Bloom filter over the addresses of a directory, used to reject unknown
+/// recipients without querying the store. The filter only answers negative
+/// lookups: until it has been built, and after it has been invalidated, every
+/// address is considered to be possibly known.
+pub struct AddressFilter {
+    bloom: RwLock<Option<BloomFilter>>,
+    generation: AtomicU64,
+    pub false_positive_rate: f64,
+    pub refresh: Duration,
+}
+
+struct BloomFilter {
+    changes: u64,
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    hasher: ahash::RandomState,
+}
+
+impl AddressFilter {
+    pub fn new(false_positive_rate: f64, refresh: Duration) -> Self {
+        AddressFilter {
+            bloom: RwLock::new(None),
+            generation: AtomicU64::new(0),
+            false_positive_rate: false_positive_rate.clamp(0.000001, 0.5),
+            refresh,
+        }
+    }
+
+    pub fn try_from_config(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+        let prefix = prefix.as_key();
+        if !config
+            .property_or_default::<bool>((&prefix, "filter.enable"), "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        Some(AddressFilter::new(
+            config
+                .property_or_default((&prefix, "filter.false-positive-rate"), "0.01")
+                .unwrap_or(0.01),
+            config
+                .property_or_default((&prefix, "filter.refresh"), "15m")
+                .unwrap_or_else(|| Duration::from_secs(15 * 60)),
+        ))
+    }
+
+    /// Returns `true` if the address is certainly not in the directory.
+    pub fn is_unknown(&self, address: &str) -> bool {
+        self.bloom
+            .read()
+            .as_ref()
+            .is_some_and(|bloom| bloom.is_current() && !bloom.contains(address.as_bytes()))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.bloom
+            .read()
+            .as_ref()
+            .is_some_and(|bloom| bloom.is_current())
+    }
+
+    /// Disables the filter until it is rebuilt, which has to happen whenever
+    /// addresses are added to the directory.
+    pub fn invalidate(&self) {
+        let mut bloom = self.bloom.write();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        *bloom = None;
+    }
+
+    fn replace(&self, generation: u64, new_bloom: BloomFilter) -> bool {
+        // Discard filters built from a scan that started before an invalidation
+        let mut bloom = self.bloom.write();
+        if self.generation.load(Ordering::Relaxed) == generation {
+            *bloom = Some(new_bloom);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub(crate) fn addresses_changed() {
+    ADDRESS_CHANGES.fetch_add(1, Ordering::Relaxed);
+}
+
+impl Directory {
+    /// Rebuilds the address filter from the addresses in the directory and
+    /// any addresses delivered outside of it, such as disposable aliases. Only
+    /// the internal directory can list its addresses, the filter is never
+    /// enabled for other directory types.
+    pub async fn refresh_filter(&self, extra_addresses: Vec<String>) -> trc::Result<bool> {
+        let (Some(filter), DirectoryInner::Internal(store)) = (&self.filter, &self.store) else {
+            return Ok(false);
+        };
+        let generation = filter.generation.load(Ordering::Relaxed);
+        let changes = ADDRESS_CHANGES.load(Ordering::Relaxed);
+
+        let stream = store.stream(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Directory(DirectoryClass::EmailToId(vec![0u8]))),
+                ValueKey::from(ValueClass::Directory(DirectoryClass::EmailToId(vec![
+                    u8::MAX;
+                    10
+                ]))),
+            )
+            .no_values(),
+            DEFAULT_CHUNK_SIZE,
+        );
+        pin_mut!(stream);
+        let mut addresses = Vec::new();
+        while let Some((key, _)) = stream.try_next().await.caused_by(trc::location!())? {
+            if let Some(address) = key.get(1..) {
+                addresses.push(address.to_vec());
+            }
+        }
+
+        let mut bloom = BloomFilter::new(
+            addresses.len() + extra_addresses.len(),
+            filter.false_positive_rate,
+            changes,
+        );
+        for address in &addresses {
+            bloom.insert(address);
+        }
+        for address in &extra_addresses {
+            bloom.insert(address.as_bytes());
+        }
+
+        Ok(filter.replace(generation, bloom))
+    }
+
+    pub fn invalidate_filter(&self) {
+        if let Some(filter) = &self.filter {
+            filter.invalidate();
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_unknown_address(&self, address: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_some_and(|filter| filter.is_unknown(address))
+    }
+}
+
+impl BloomFilter {
+    fn new(num_items: usize, false_positive_rate: f64, changes: u64) -> Self {
+        let num_items = num_items.max(1) as f64;
+        let num_bits = ((-num_items * false_positive_rate.ln()) / (LN_2 * LN_2))
+            .ceil()
+            .max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / num_items) * LN_2)
+            .round()
+            .clamp(1.0, MAX_HASHES as f64) as u32;
+
+        BloomFilter {
+            changes,
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            hasher: ahash::RandomState::new(),
+        }
+    }
+
+    fn is_current(&self) -> bool {
+        self.changes == ADDRESS_CHANGES.load(Ordering::Relaxed)
+    }
+
+    fn insert(&mut self, item: &[u8]) {
+        for bit in self.bit_positions(item) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.bit_positions(item)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    fn bit_positions(&self, item: &[u8]) -> impl Iterator<Item = u64> {
+        // Derive all positions from a single 64-bit hash (Kirsch-Mitzenmacher)
+        let hash = self.hasher.hash_one(item);
+        let h1 = hash & 0xFFFF_FFFF;
+        let h2 = (hash >> 32) | 1;
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
@@ -9,6 +9,7 @@ use crate::Permission;
 pub mod cache;
 pub mod config;
 pub mod dispatch;
+pub mod filter;
 pub mod principal;
 pub mod secret;
 
@@ -199,6 +200,35 @@ impl Permission {
             Permission::OauthClientDelete => "Remove OAuth clients",
             Permission::AiModelInteract => "Interact with AI models",
             Permission::Troubleshoot => "Perform troubleshooting",
+            Permission::ReadOnlyMode => "Enable or disable read-only mode",
+            Permission::JmapEmailShare => "Create public links to emails via JMAP",
+            Permission::ManageDelegation => "Manage account delegates",
+            Permission::QuarantineList => "View and search quarantined messages",
+            Permission::QuarantineRelease => "Release quarantined messages",
+            Permission::QuarantineDelete => "Remove quarantined messages",
+            Permission::JmapAbsenceGet => "Retrieve absence windows via JMAP",
+            Permission::JmapAbsenceSet => "Modify absence windows via JMAP",
+            Permission::MessageSearch => "Search messages across accounts",
+            Permission::ReputationFeedExport => "Export the anonymized sender reputation feed",
+            Permission::ReputationFeedImport => "Import sender reputation feeds",
+            Permission::StoreUsage => "View the storage usage report",
+            Permission::SieveListVacation => "List recipients of Sieve vacation replies",
+            Permission::SieveClearVacation => "Clear recipients of Sieve vacation replies",
+            Permission::StoreArchive => "Recompress old messages to reclaim storage",
+            Permission::ManageAliases => "Manage disposable aliases",
+            Permission::MailingListMembers => "List mailing list members and held posts",
+            Permission::MailingListModerate => "Approve or reject held mailing list posts",
+            Permission::SuppressionList => "List suppressed recipients of a sender",
+            Permission::SuppressionManage => "Add or remove suppressed recipients",
+            Permission::MessageQueueLive => "Stream message queue events in real time",
+            Permission::ImapMetadataGet => "Retrieve mailbox and server annotations via IMAP",
+            Permission::ImapMetadataSet => "Modify mailbox and private server annotations via IMAP",
+            Permission::ImapMetadataServer => "Modify shared server annotations via IMAP",
+            Permission::PublicFoldersList => "List public folders and their access rights",
+            Permission::PublicFoldersManage => "Manage public folders and their access rights",
+            Permission::StoreRepair => "Verify and repair the consistency of the data store",
+            Permission::StoreRestore => "Take account snapshots and restore accounts from them",
+            Permission::StoreBackup => "Perform online backups of the data store",
         }
     }
 }
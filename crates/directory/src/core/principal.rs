@@ -818,7 +818,8 @@ impl<'de> serde::Deserialize<'de> for Principal {
                         }
                         PrincipalField::Description
                         | PrincipalField::Tenant
-                        | PrincipalField::Picture => {
+                        | PrincipalField::Picture
+                        | PrincipalField::Locale => {
                             if let Some(v) = map.next_value::<Option<String>>()? {
                                 if v.len() <= MAX_STRING_LEN {
                                     PrincipalValue::String(v)
@@ -1003,6 +1004,8 @@ impl Permission {
                 | Permission::EmailReceive
                 | Permission::ManageEncryption
                 | Permission::ManagePasswords
+                | Permission::ManageDelegation
+                | Permission::ManageAliases
                 | Permission::JmapEmailGet
                 | Permission::JmapMailboxGet
                 | Permission::JmapThreadGet
@@ -1030,6 +1033,9 @@ impl Permission {
                 | Permission::JmapBlobCopy
                 | Permission::JmapEmailImport
                 | Permission::JmapEmailParse
+                | Permission::JmapEmailShare
+                | Permission::JmapAbsenceGet
+                | Permission::JmapAbsenceSet
                 | Permission::JmapEmailQueryChanges
                 | Permission::JmapMailboxQueryChanges
                 | Permission::JmapEmailSubmissionQueryChanges
@@ -1073,6 +1079,8 @@ impl Permission {
                 | Permission::ImapStore
                 | Permission::ImapSubscribe
                 | Permission::ImapThread
+                | Permission::ImapMetadataGet
+                | Permission::ImapMetadataSet
                 | Permission::Pop3Authenticate
                 | Permission::Pop3List
                 | Permission::Pop3Uidl
@@ -1088,6 +1096,8 @@ impl Permission {
                 | Permission::SieveRenameScript
                 | Permission::SieveCheckScript
                 | Permission::SieveHaveSpace
+                | Permission::SieveListVacation
+                | Permission::SieveClearVacation
         )
     }
 
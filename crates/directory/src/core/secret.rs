@@ -4,12 +4,17 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use argon2::Argon2;
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Algorithm, Argon2, Params, PasswordHasher, Version,
+};
 use mail_builder::encoders::base64::base64_encode;
 use mail_parser::decoders::base64::base64_decode;
 use password_hash::PasswordHash;
 use pbkdf2::Pbkdf2;
-use pwhash::{bcrypt, bsdi_crypt, md5_crypt, sha1_crypt, sha256_crypt, sha512_crypt, unix_crypt};
+use pwhash::{
+    bcrypt, bsdi_crypt, md5_crypt, sha1_crypt, sha256_crypt, sha512_crypt, unix_crypt, HashSetup,
+};
 use scrypt::Scrypt;
 use sha1::Digest;
 use sha1::Sha1;
@@ -17,18 +22,38 @@ use sha2::Sha256;
 use sha2::Sha512;
 use tokio::sync::oneshot;
 use totp_rs::TOTP;
+use utils::config::{utils::AsKey, Config};
 
+use store::Store;
+use trc::AddContext;
+
+use crate::backend::internal::manage::{ManageDirectory, UpdatePrincipal};
 use crate::backend::internal::PrincipalField;
+use crate::backend::internal::PrincipalUpdate;
+use crate::backend::internal::PrincipalValue;
 use crate::backend::internal::SpecialSecrets;
 use crate::Principal;
 
+pub enum VerifiedSecret<'x> {
+    Password { hash: &'x str, secret: &'x str },
+    AppPassword,
+}
+
 impl Principal {
-    pub async fn verify_secret(&self, mut code: &str) -> trc::Result<bool> {
+    pub async fn verify_secret(&self, code: &str) -> trc::Result<bool> {
+        self.verified_secret(code).await.map(|v| v.is_some())
+    }
+
+    /// Verifies a secret and returns the password hash it matched, if any.
+    pub async fn verified_secret<'x>(
+        &'x self,
+        mut code: &'x str,
+    ) -> trc::Result<Option<VerifiedSecret<'x>>> {
         let mut totp_token = None;
         let mut is_totp_token_missing = false;
         let mut is_totp_required = false;
         let mut is_totp_verified = false;
-        let mut is_authenticated = false;
+        let mut authenticated_hash = None;
         let mut is_app_authenticated = false;
 
         for secret in self.iter_str(PrincipalField::Secrets) {
@@ -63,22 +88,24 @@ impl Principal {
                         .check_current(totp_token)
                         .unwrap_or(false);
                 }
-            } else if !is_authenticated && !is_app_authenticated {
+            } else if authenticated_hash.is_none() && !is_app_authenticated {
                 if let Some((_, app_secret)) =
                     secret.strip_prefix("$app$").and_then(|s| s.split_once('$'))
                 {
                     is_app_authenticated = verify_secret_hash(app_secret, code).await?;
-                } else {
-                    is_authenticated = verify_secret_hash(secret, code).await?;
+                } else if verify_secret_hash(secret, code).await? {
+                    authenticated_hash = Some(secret);
                 }
             }
         }
 
-        if is_authenticated {
+        if let Some(hash) = authenticated_hash {
+            let verified = VerifiedSecret::Password { hash, secret: code };
+
             if !is_totp_required {
                 // Authenticated without TOTP enabled
 
-                Ok(true)
+                Ok(Some(verified))
             } else if is_totp_token_missing {
                 // Only let the client know if the TOTP code is missing
                 // if the password is correct
@@ -87,23 +114,26 @@ impl Principal {
             } else {
                 // Return the TOTP verification status
 
-                Ok(is_totp_verified)
+                Ok(is_totp_verified.then_some(verified))
             }
         } else if is_app_authenticated {
             // App passwords do not require TOTP
 
-            Ok(true)
+            Ok(Some(VerifiedSecret::AppPassword))
         } else {
             if is_totp_verified {
                 // TOTP URL appeared after password hash in secrets list
                 for secret in self.iter_str(PrincipalField::Secrets) {
                     if secret.is_password() && verify_secret_hash(secret, code).await? {
-                        return Ok(true);
+                        return Ok(Some(VerifiedSecret::Password {
+                            hash: secret,
+                            secret: code,
+                        }));
                     }
                 }
             }
 
-            Ok(false)
+            Ok(None)
         }
     }
 }
@@ -268,3 +298,204 @@ pub async fn verify_secret_hash(hashed_secret: &str, secret: &str) -> trc::Resul
         Ok(hashed_secret == secret)
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordScheme {
+    Argon2id,
+    Bcrypt,
+    Sha512Crypt,
+}
+
+#[derive(Debug, Clone)]
+pub struct PasswordHashing {
+    pub scheme: PasswordScheme,
+    pub rehash: bool,
+    pub argon2_memory_cost: u32,
+    pub argon2_time_cost: u32,
+    pub argon2_parallelism: u32,
+    pub bcrypt_cost: u32,
+    pub sha512_rounds: u32,
+}
+
+impl Default for PasswordHashing {
+    fn default() -> Self {
+        PasswordHashing {
+            scheme: PasswordScheme::Argon2id,
+            rehash: true,
+            argon2_memory_cost: Params::DEFAULT_M_COST,
+            argon2_time_cost: Params::DEFAULT_T_COST,
+            argon2_parallelism: Params::DEFAULT_P_COST,
+            bcrypt_cost: 12,
+            sha512_rounds: 5000,
+        }
+    }
+}
+
+impl PasswordHashing {
+    pub fn parse(config: &mut Config, prefix: impl AsKey) -> Self {
+        let prefix = prefix.as_key();
+        let default = PasswordHashing::default();
+        let scheme = match config
+            .value((&prefix, "password.scheme"))
+            .unwrap_or("argon2id")
+        {
+            "argon2id" | "argon2" => PasswordScheme::Argon2id,
+            "bcrypt" => PasswordScheme::Bcrypt,
+            "sha512-crypt" | "sha512" => PasswordScheme::Sha512Crypt,
+            other => {
+                let err = format!("Unsupported password scheme {other:?}");
+                config.new_parse_error((&prefix, "password.scheme"), err);
+                PasswordScheme::Argon2id
+            }
+        };
+
+        PasswordHashing {
+            scheme,
+            rehash: config
+                .property_or_default((&prefix, "password.rehash"), "true")
+                .unwrap_or(true),
+            argon2_memory_cost: config
+                .property((&prefix, "password.argon2.memory-cost"))
+                .unwrap_or(default.argon2_memory_cost),
+            argon2_time_cost: config
+                .property((&prefix, "password.argon2.time-cost"))
+                .unwrap_or(default.argon2_time_cost),
+            argon2_parallelism: config
+                .property((&prefix, "password.argon2.parallelism"))
+                .unwrap_or(default.argon2_parallelism),
+            bcrypt_cost: config
+                .property((&prefix, "password.bcrypt.cost"))
+                .unwrap_or(default.bcrypt_cost),
+            sha512_rounds: config
+                .property((&prefix, "password.sha512-crypt.rounds"))
+                .unwrap_or(default.sha512_rounds),
+        }
+    }
+
+    pub fn hash(&self, secret: &str) -> trc::Result<String> {
+        match self.scheme {
+            PasswordScheme::Argon2id => {
+                let params = Params::new(
+                    self.argon2_memory_cost,
+                    self.argon2_time_cost,
+                    self.argon2_parallelism,
+                    None,
+                )
+                .map_err(|err| trc::AuthEvent::Error.reason(err))?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password(secret.as_bytes(), &SaltString::generate(&mut OsRng))
+                    .map(|hash| hash.to_string())
+                    .map_err(|err| trc::AuthEvent::Error.reason(err))
+            }
+            PasswordScheme::Bcrypt => bcrypt::hash_with(
+                bcrypt::BcryptSetup {
+                    cost: Some(self.bcrypt_cost),
+                    ..Default::default()
+                },
+                secret,
+            )
+            .map_err(|err| trc::AuthEvent::Error.reason(err)),
+            PasswordScheme::Sha512Crypt => sha512_crypt::hash_with(
+                HashSetup {
+                    salt: None,
+                    rounds: Some(self.sha512_rounds),
+                },
+                secret,
+            )
+            .map_err(|err| trc::AuthEvent::Error.reason(err)),
+        }
+    }
+
+    pub fn needs_rehash(&self, hashed_secret: &str) -> bool {
+        if !self.rehash {
+            return false;
+        }
+
+        match self.scheme {
+            PasswordScheme::Argon2id => {
+                if hashed_secret.starts_with("$argon2id$") {
+                    // Upgrade hashes created with weaker parameters
+                    PasswordHash::new(hashed_secret)
+                        .ok()
+                        .and_then(|hash| Params::try_from(&hash).ok())
+                        .map_or(false, |params| {
+                            params.m_cost() < self.argon2_memory_cost
+                                || params.t_cost() < self.argon2_time_cost
+                                || params.p_cost() < self.argon2_parallelism
+                        })
+                } else {
+                    is_legacy_hash(hashed_secret)
+                }
+            }
+            PasswordScheme::Bcrypt => {
+                if hashed_secret.starts_with("$2") {
+                    hashed_secret
+                        .get(4..6)
+                        .and_then(|cost| cost.parse::<u32>().ok())
+                        .map_or(false, |cost| cost < self.bcrypt_cost)
+                } else {
+                    is_legacy_hash(hashed_secret)
+                }
+            }
+            PasswordScheme::Sha512Crypt => {
+                if let Some(hash) = hashed_secret.strip_prefix("$6$") {
+                    // SHA-512 crypt defaults to 5000 rounds when not specified
+                    hash.strip_prefix("rounds=")
+                        .and_then(|hash| hash.split_once('$'))
+                        .and_then(|(rounds, _)| rounds.parse::<u32>().ok())
+                        .unwrap_or(5000)
+                        < self.sha512_rounds
+                } else {
+                    is_legacy_hash(hashed_secret)
+                }
+            }
+        }
+    }
+
+    /// Replaces an outdated password hash after a successful login. The caller
+    /// has already verified the secret against the outdated hash. OAuth tokens
+    /// are bound to the credential revision, which re-hashing leaves untouched.
+    pub async fn rehash(
+        &self,
+        store: &Store,
+        principal_id: u32,
+        outdated_secret: &str,
+        secret: &str,
+    ) {
+        let hashing = self.clone();
+        let secret = secret.to_string();
+        let result = match tokio::task::spawn_blocking(move || hashing.hash(&secret)).await {
+            Ok(Ok(new_secret)) => store
+                .update_principal(
+                    UpdatePrincipal::by_id(principal_id)
+                        .with_updates(vec![
+                            PrincipalUpdate::remove_item(
+                                PrincipalField::Secrets,
+                                PrincipalValue::String(outdated_secret.to_string()),
+                            ),
+                            PrincipalUpdate::add_item(
+                                PrincipalField::Secrets,
+                                PrincipalValue::String(new_secret),
+                            ),
+                        ])
+                        .as_rehash(),
+                )
+                .await
+                .caused_by(trc::location!()),
+            Ok(Err(err)) => Err(err),
+            Err(err) => Err(trc::EventType::Server(trc::ServerEvent::ThreadError)
+                .caused_by(trc::location!())
+                .reason(err)),
+        };
+
+        if let Err(err) = result {
+            trc::error!(err
+                .account_id(principal_id)
+                .details("Failed to re-hash password"));
+        }
+    }
+}
+
+fn is_legacy_hash(hashed_secret: &str) -> bool {
+    !hashed_secret.starts_with("$argon2id$")
+}
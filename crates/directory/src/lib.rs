@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use core::cache::CachedDirectory;
+use core::{cache::CachedDirectory, filter::AddressFilter, secret::PasswordHashing};
 use std::{fmt::Debug, sync::Arc};
 
 use ahash::AHashMap;
@@ -29,6 +29,8 @@ pub mod core;
 pub struct Directory {
     pub store: DirectoryInner,
     pub cache: Option<CachedDirectory>,
+    pub filter: Option<AddressFilter>,
+    pub password: PasswordHashing,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -264,7 +266,36 @@ pub enum Permission {
     OauthClientOverride,
 
     AiModelInteract,
-    Troubleshoot, // WARNING: add new ids at the end (TODO: use static ids)
+    Troubleshoot,
+    ReadOnlyMode,
+    JmapEmailShare,
+    ManageDelegation,
+    QuarantineList,
+    QuarantineRelease,
+    QuarantineDelete,
+    JmapAbsenceGet,
+    JmapAbsenceSet,
+    MessageSearch,
+    ReputationFeedExport,
+    ReputationFeedImport,
+    StoreUsage,
+    SieveListVacation,
+    SieveClearVacation,
+    StoreArchive,
+    ManageAliases,
+    MailingListMembers,
+    MailingListModerate,
+    SuppressionList,
+    SuppressionManage,
+    MessageQueueLive,
+    ImapMetadataGet,
+    ImapMetadataSet,
+    ImapMetadataServer,
+    PublicFoldersList,
+    PublicFoldersManage,
+    StoreRepair,
+    StoreRestore,
+    StoreBackup, // WARNING: add new ids at the end (TODO: use static ids)
 }
 
 pub type Permissions = Bitset<{ Permission::COUNT.div_ceil(std::mem::size_of::<usize>()) }>;
@@ -295,6 +326,8 @@ impl Default for Directory {
         Self {
             store: DirectoryInner::Internal(Store::None),
             cache: None,
+            filter: None,
+            password: PasswordHashing::default(),
         }
     }
 }
@@ -72,6 +72,10 @@ pub enum Command {
 
     // RFC 2971
     Id,
+
+    // RFC 5464
+    GetMetadata,
+    SetMetadata,
 }
 
 impl Command {
@@ -150,6 +154,16 @@ pub enum ResponseCode {
 
     // USEATTR
     UseAttr,
+
+    // METADATA
+    MetadataLongEntries {
+        size: usize,
+    },
+    MetadataMaxSize {
+        size: usize,
+    },
+    MetadataTooMany,
+    MetadataNoPrivate,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
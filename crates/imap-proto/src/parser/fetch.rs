@@ -313,6 +313,8 @@ impl Request<Command> {
                         attributes.push_unique(Attribute::EmailId);
                     } else if value.eq_ignore_ascii_case(b"THREADID") {
                         attributes.push_unique(Attribute::ThreadId);
+                    } else if value.eq_ignore_ascii_case(b"SAVEDATE") {
+                        attributes.push_unique(Attribute::SaveDate);
                     } else {
                         return Err(bad(
                             self.tag,
@@ -0,0 +1,320 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::borrow::Cow;
+
+use crate::{
+    protocol::{
+        metadata::{Depth, GetArguments, SetArguments},
+        ProtocolVersion,
+    },
+    receiver::{bad, Request, Token},
+    utf7::utf7_maybe_decode,
+    Command,
+};
+
+/*
+
+   getmetadata     = "GETMETADATA" [SP getmetadata-options]
+                     SP mailbox SP entries
+
+   getmetadata-options = "(" getmetadata-option
+                         *(SP getmetadata-option) ")"
+
+   getmetadata-option  = "MAXSIZE" SP number / "DEPTH" SP ("0" / "1" / "infinity")
+
+   entries         = entry / "(" entry *(SP entry) ")"
+
+   setmetadata     = "SETMETADATA" SP mailbox SP entry-values
+
+   entry-values    = "(" entry-value *(SP entry-value) ")"
+
+   entry-value     = entry SP value
+
+   value           = nstring / literal8
+
+*/
+
+impl Request<Command> {
+    pub fn parse_get_metadata(self, version: ProtocolVersion) -> trc::Result<GetArguments> {
+        let mut tokens = self.tokens.into_iter().peekable();
+        let mut max_size = None;
+        let mut depth = Depth::Zero;
+
+        // Parse options
+        if tokens
+            .peek()
+            .map_or(false, |token| token.is_parenthesis_open())
+        {
+            tokens.next();
+            loop {
+                match tokens.next() {
+                    Some(Token::ParenthesisClose) => break,
+                    Some(token) if token.eq_ignore_ascii_case(b"MAXSIZE") => {
+                        max_size = tokens
+                            .next()
+                            .and_then(|token| token.unwrap_string().ok())
+                            .and_then(|value| value.parse::<usize>().ok())
+                            .ok_or_else(|| bad(self.tag.to_string(), "Invalid MAXSIZE value."))?
+                            .into();
+                    }
+                    Some(token) if token.eq_ignore_ascii_case(b"DEPTH") => {
+                        depth = match tokens.next() {
+                            Some(token) if token.eq_ignore_ascii_case(b"0") => Depth::Zero,
+                            Some(token) if token.eq_ignore_ascii_case(b"1") => Depth::One,
+                            Some(token) if token.eq_ignore_ascii_case(b"infinity") => {
+                                Depth::Infinity
+                            }
+                            _ => return Err(bad(self.tag.to_string(), "Invalid DEPTH value.")),
+                        };
+                    }
+                    Some(token) => {
+                        return Err(bad(
+                            self.tag.to_string(),
+                            format!("Unsupported GETMETADATA option {:?}.", token.to_string()),
+                        ))
+                    }
+                    None => return Err(bad(self.tag.to_string(), "Missing closing parenthesis.")),
+                }
+            }
+        }
+
+        let mailbox_name = parse_mailbox_name(&self.tag, tokens.next(), version)?;
+
+        // Parse entries
+        let mut entries = Vec::new();
+        match tokens.next() {
+            Some(Token::ParenthesisOpen) => loop {
+                match tokens.next() {
+                    Some(Token::ParenthesisClose) => break,
+                    Some(token) => {
+                        entries.push(parse_entry(token).map_err(|v| bad(self.tag.to_string(), v))?);
+                    }
+                    None => return Err(bad(self.tag.to_string(), "Missing closing parenthesis.")),
+                }
+            },
+            Some(token) => {
+                entries.push(parse_entry(token).map_err(|v| bad(self.tag.to_string(), v))?);
+            }
+            None => (),
+        }
+
+        if !entries.is_empty() {
+            Ok(GetArguments {
+                tag: self.tag,
+                mailbox_name,
+                entries,
+                max_size,
+                depth,
+            })
+        } else {
+            Err(bad(self.tag, "At least one entry is required."))
+        }
+    }
+
+    pub fn parse_set_metadata(self, version: ProtocolVersion) -> trc::Result<SetArguments> {
+        let mut tokens = self.tokens.into_iter();
+        let mailbox_name = parse_mailbox_name(&self.tag, tokens.next(), version)?;
+
+        if tokens
+            .next()
+            .map_or(true, |token| !token.is_parenthesis_open())
+        {
+            return Err(bad(
+                self.tag.to_string(),
+                "Expected parenthesis after mailbox name.",
+            ));
+        }
+
+        let mut entries = Vec::new();
+        loop {
+            match tokens.next() {
+                Some(Token::ParenthesisClose) => break,
+                Some(token) => {
+                    let entry = parse_entry(token).map_err(|v| bad(self.tag.to_string(), v))?;
+                    let value = match tokens.next() {
+                        Some(token) if token.eq_ignore_ascii_case(b"NIL") => None,
+                        Some(token @ (Token::Argument(_) | Token::Nil)) => token
+                            .unwrap_string()
+                            .map_err(|v| bad(self.tag.to_string(), v))?
+                            .into(),
+                        _ => {
+                            return Err(bad(
+                                self.tag.to_string(),
+                                format!("Missing value for entry {entry:?}."),
+                            ))
+                        }
+                    };
+                    entries.push((entry, value));
+                }
+                None => return Err(bad(self.tag.to_string(), "Missing closing parenthesis.")),
+            }
+        }
+
+        if !entries.is_empty() {
+            Ok(SetArguments {
+                tag: self.tag,
+                mailbox_name,
+                entries,
+            })
+        } else {
+            Err(bad(self.tag, "At least one entry is required."))
+        }
+    }
+}
+
+fn parse_mailbox_name(
+    tag: &str,
+    token: Option<Token>,
+    version: ProtocolVersion,
+) -> trc::Result<String> {
+    Ok(utf7_maybe_decode(
+        token
+            .ok_or_else(|| bad(tag.to_string(), "Missing mailbox name."))?
+            .unwrap_string()
+            .map_err(|v| bad(tag.to_string(), v))?,
+        version,
+    ))
+}
+
+/// Parses an entry name, which is case-insensitive and has to be placed
+/// under either the /private or the /shared hierarchy.
+fn parse_entry(token: Token) -> super::Result<String> {
+    let entry = token.unwrap_string()?.to_lowercase();
+    let is_valid = (entry.starts_with("/private/") || entry.starts_with("/shared/"))
+        && !entry.ends_with('/')
+        && !entry.contains("//")
+        && !entry
+            .chars()
+            .any(|ch| ch.is_ascii_control() || ch == '*' || ch == '%');
+
+    if is_valid {
+        Ok(entry)
+    } else {
+        Err(Cow::from(format!("Invalid entry name {entry:?}.")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{
+            metadata::{Depth, GetArguments, SetArguments},
+            ProtocolVersion,
+        },
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_get_metadata() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "a GETMETADATA \"\" /shared/comment\r\n",
+                GetArguments {
+                    tag: "a".to_string(),
+                    mailbox_name: "".to_string(),
+                    entries: vec!["/shared/comment".to_string()],
+                    max_size: None,
+                    depth: Depth::Zero,
+                },
+            ),
+            (
+                "a GETMETADATA INBOX (/shared/Comment /private/comment)\r\n",
+                GetArguments {
+                    tag: "a".to_string(),
+                    mailbox_name: "INBOX".to_string(),
+                    entries: vec![
+                        "/shared/comment".to_string(),
+                        "/private/comment".to_string(),
+                    ],
+                    max_size: None,
+                    depth: Depth::Zero,
+                },
+            ),
+            (
+                "a GETMETADATA (MAXSIZE 1024 DEPTH infinity) INBOX (/private/vendor)\r\n",
+                GetArguments {
+                    tag: "a".to_string(),
+                    mailbox_name: "INBOX".to_string(),
+                    entries: vec!["/private/vendor".to_string()],
+                    max_size: Some(1024),
+                    depth: Depth::Infinity,
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_get_metadata(ProtocolVersion::Rev2)
+                    .unwrap(),
+                arguments,
+                "{:?}",
+                command
+            );
+        }
+
+        for command in [
+            "a GETMETADATA INBOX\r\n",
+            "a GETMETADATA INBOX /comment\r\n",
+            "a GETMETADATA INBOX /shared/\r\n",
+            "a GETMETADATA (DEPTH 2) INBOX /shared/comment\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_get_metadata(ProtocolVersion::Rev2)
+                    .is_err(),
+                "{:?}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn parse_set_metadata() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "a SETMETADATA INBOX (/private/comment \"My comment\")\r\n",
+                SetArguments {
+                    tag: "a".to_string(),
+                    mailbox_name: "INBOX".to_string(),
+                    entries: vec![(
+                        "/private/comment".to_string(),
+                        Some("My comment".to_string()),
+                    )],
+                },
+            ),
+            (
+                "a SETMETADATA \"\" (/shared/comment NIL /shared/admin \"\")\r\n",
+                SetArguments {
+                    tag: "a".to_string(),
+                    mailbox_name: "".to_string(),
+                    entries: vec![
+                        ("/shared/comment".to_string(), None),
+                        ("/shared/admin".to_string(), Some("".to_string())),
+                    ],
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_set_metadata(ProtocolVersion::Rev2)
+                    .unwrap(),
+                arguments,
+                "{:?}",
+                command
+            );
+        }
+    }
+}
@@ -15,6 +15,7 @@ pub mod fetch;
 pub mod list;
 pub mod login;
 pub mod lsub;
+pub mod metadata;
 pub mod rename;
 pub mod search;
 pub mod select;
@@ -77,6 +78,8 @@ impl CommandParser for Command {
             b"MYRIGHTS" => Some(Command::MyRights),
             b"UNAUTHENTICATE" => Some(Command::Unauthenticate),
             b"ID" => Some(Command::Id),
+            b"GETMETADATA" => Some(Command::GetMetadata),
+            b"SETMETADATA" => Some(Command::SetMetadata),
             _ => None,
         }
     }
@@ -240,6 +240,29 @@ pub fn parse_filters(
                             .ok_or_else(|| Cow::from("Expected integer"))?
                             .unwrap_bytes(),
                     )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDBEFORE") {
+                    filters.push(Filter::SavedBefore(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDON") {
+                    filters.push(Filter::SavedOn(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDSINCE") {
+                    filters.push(Filter::SavedSince(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDATESUPPORTED") {
+                    filters.push(Filter::SaveDateSupported);
                 } else if value.eq_ignore_ascii_case(b"OLD") {
                     filters.push(Filter::Old);
                 } else if value.eq_ignore_ascii_case(b"NEW") {
@@ -741,6 +764,31 @@ mod tests {
                     sort: None,
                 },
             ),
+            (
+                b"6 SEARCH SAVEDATESUPPORTED SAVEDSINCE 1-Dec-2023 SAVEDBEFORE 2-Dec-2023\r\n"
+                    .to_vec(),
+                search::Arguments {
+                    tag: "6".to_string(),
+                    result_options: vec![],
+                    filter: vec![
+                        Filter::SaveDateSupported,
+                        Filter::SavedSince(1701388800),
+                        Filter::SavedBefore(1701475200),
+                    ],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
+            (
+                b"7 SEARCH SAVEDON 1-Dec-2023\r\n".to_vec(),
+                search::Arguments {
+                    tag: "7".to_string(),
+                    result_options: vec![],
+                    filter: vec![Filter::SavedOn(1701388800)],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
         ] {
             let command_str = String::from_utf8_lossy(&command).into_owned();
             assert_eq!(
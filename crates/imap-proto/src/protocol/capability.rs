@@ -48,6 +48,9 @@ pub enum Capability {
     ObjectId,
     Preview,
     Utf8Accept,
+    Metadata,
+    MetadataServer, //METADATA-SERVER
+    SaveDate,
     Auth(Mechanism),
 }
 
@@ -94,6 +97,9 @@ impl Capability {
             Capability::CreateSpecialUse => b"CREATE-SPECIAL-USE",
             Capability::Move => b"MOVE",
             Capability::Utf8Accept => b"UTF8=ACCEPT",
+            Capability::Metadata => b"METADATA",
+            Capability::MetadataServer => b"METADATA-SERVER",
+            Capability::SaveDate => b"SAVEDATE",
         });
     }
 
@@ -136,6 +142,9 @@ impl Capability {
                 Capability::StatusSize,
                 Capability::ObjectId,
                 Capability::Preview,
+                Capability::Metadata,
+                Capability::MetadataServer,
+                Capability::SaveDate,
             ]);
         } else {
             capabilities.extend([
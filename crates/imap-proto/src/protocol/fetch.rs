@@ -64,6 +64,7 @@ pub enum Attribute {
     ModSeq,
     EmailId,
     ThreadId,
+    SaveDate,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -133,6 +134,9 @@ pub enum DataItem<'x> {
     ThreadId {
         thread_id: String,
     },
+    SaveDate {
+        date: Option<i64>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -844,6 +848,14 @@ impl DataItem<'_> {
                 buf.extend_from_slice(thread_id.as_bytes());
                 buf.push(b')');
             }
+            DataItem::SaveDate { date } => {
+                buf.extend_from_slice(b"SAVEDATE ");
+                if let Some(date) = date {
+                    quoted_timestamp(buf, *date);
+                } else {
+                    buf.extend_from_slice(b"NIL");
+                }
+            }
         }
     }
 }
@@ -1358,6 +1370,13 @@ mod tests {
                 super::DataItem::InternalDate { date: 482374938 },
                 "INTERNALDATE \"15-Apr-1985 01:02:18 +0000\"",
             ),
+            (
+                super::DataItem::SaveDate {
+                    date: Some(482374938),
+                },
+                "SAVEDATE \"15-Apr-1985 01:02:18 +0000\"",
+            ),
+            (super::DataItem::SaveDate { date: None }, "SAVEDATE NIL"),
         ] {
             let mut buf = Vec::with_capacity(100);
 
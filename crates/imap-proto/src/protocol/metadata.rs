@@ -0,0 +1,143 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::utf7::utf7_encode;
+
+use super::{quoted_or_literal_string_or_nil, quoted_string};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetArguments {
+    pub tag: String,
+    pub mailbox_name: String,
+    pub entries: Vec<String>,
+    pub max_size: Option<usize>,
+    pub depth: Depth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetArguments {
+    pub tag: String,
+    pub mailbox_name: String,
+    pub entries: Vec<(String, Option<String>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Depth {
+    #[default]
+    Zero,
+    One,
+    Infinity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub mailbox_name: String,
+    pub entries: Vec<(String, Option<String>)>,
+}
+
+impl Depth {
+    /// Returns whether an entry is returned for the requested entry name
+    /// at this depth.
+    pub fn matches(&self, requested: &str, entry: &str) -> bool {
+        if requested == entry {
+            true
+        } else if let Some(child) = entry
+            .strip_prefix(requested)
+            .and_then(|child| child.strip_prefix('/'))
+        {
+            match self {
+                Depth::Zero => false,
+                Depth::One => !child.contains('/'),
+                Depth::Infinity => true,
+            }
+        } else {
+            false
+        }
+    }
+}
+
+impl Response {
+    pub fn into_bytes(self, is_rev2: bool) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(
+            self.mailbox_name.len()
+                + self
+                    .entries
+                    .iter()
+                    .map(|(entry, value)| entry.len() + value.as_ref().map_or(3, |v| v.len() + 2))
+                    .sum::<usize>()
+                + 16,
+        );
+        buf.extend_from_slice(b"* METADATA ");
+        if is_rev2 {
+            quoted_string(&mut buf, &self.mailbox_name);
+        } else {
+            quoted_string(&mut buf, &utf7_encode(&self.mailbox_name));
+        }
+        buf.extend_from_slice(b" (");
+        for (pos, (entry, value)) in self.entries.iter().enumerate() {
+            if pos > 0 {
+                buf.push(b' ');
+            }
+            quoted_string(&mut buf, entry);
+            buf.push(b' ');
+            quoted_or_literal_string_or_nil(&mut buf, value.as_deref());
+        }
+        buf.extend_from_slice(b")\r\n");
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Depth, Response};
+
+    #[test]
+    fn serialize_metadata() {
+        assert_eq!(
+            String::from_utf8(
+                Response {
+                    mailbox_name: "INBOX".to_string(),
+                    entries: vec![
+                        (
+                            "/shared/comment".to_string(),
+                            Some("Shared comment".to_string())
+                        ),
+                        (
+                            "/private/comment".to_string(),
+                            Some("Line 1\r\nLine 2".to_string())
+                        ),
+                        ("/private/vendor/none".to_string(), None),
+                    ],
+                }
+                .into_bytes(true)
+            )
+            .unwrap(),
+            concat!(
+                "* METADATA \"INBOX\" (\"/shared/comment\" \"Shared comment\" ",
+                "\"/private/comment\" {14}\r\nLine 1\r\nLine 2 ",
+                "\"/private/vendor/none\" NIL)\r\n"
+            )
+        );
+    }
+
+    #[test]
+    fn metadata_depth() {
+        for (depth, entry, expected) in [
+            (Depth::Zero, "/shared/comment", true),
+            (Depth::Zero, "/shared/comment/a", false),
+            (Depth::One, "/shared/comment/a", true),
+            (Depth::One, "/shared/comment/a/b", false),
+            (Depth::Infinity, "/shared/comment/a/b", true),
+            (Depth::Infinity, "/shared/comments", false),
+        ] {
+            assert_eq!(
+                depth.matches("/shared/comment", entry),
+                expected,
+                "{depth:?} {entry}"
+            );
+        }
+    }
+}
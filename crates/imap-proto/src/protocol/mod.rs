@@ -24,6 +24,7 @@ pub mod expunge;
 pub mod fetch;
 pub mod list;
 pub mod login;
+pub mod metadata;
 pub mod namespace;
 pub mod rename;
 pub mod search;
@@ -379,6 +380,18 @@ impl ResponseCode {
                 return;
             }
             ResponseCode::UseAttr => b"USEATTR",
+            ResponseCode::MetadataLongEntries { size } => {
+                buf.extend_from_slice(b"METADATA LONGENTRIES ");
+                buf.extend_from_slice(size.to_string().as_bytes());
+                return;
+            }
+            ResponseCode::MetadataMaxSize { size } => {
+                buf.extend_from_slice(b"METADATA MAXSIZE ");
+                buf.extend_from_slice(size.to_string().as_bytes());
+                return;
+            }
+            ResponseCode::MetadataTooMany => b"METADATA TOOMANY",
+            ResponseCode::MetadataNoPrivate => b"METADATA NOPRIVATE",
         });
     }
 
@@ -422,6 +435,10 @@ impl ResponseCode {
             ResponseCode::MailboxId { .. } => "MAILBOXID",
             ResponseCode::HighestModseq { .. } => "HIGHESTMODSEQ",
             ResponseCode::UseAttr => "USEATTR",
+            ResponseCode::MetadataLongEntries { .. } => "METADATA LONGENTRIES",
+            ResponseCode::MetadataMaxSize { .. } => "METADATA MAXSIZE",
+            ResponseCode::MetadataTooMany => "METADATA TOOMANY",
+            ResponseCode::MetadataNoPrivate => "METADATA NOPRIVATE",
         }
     }
 }
@@ -611,6 +628,8 @@ impl Display for Command {
             Command::MyRights => write!(f, "MYRIGHTS"),
             Command::Unauthenticate => write!(f, "UNAUTHENTICATE"),
             Command::Id => write!(f, "ID"),
+            Command::GetMetadata => write!(f, "GETMETADATA"),
+            Command::SetMetadata => write!(f, "SETMETADATA"),
         }
     }
 }
@@ -8,18 +8,65 @@ use super::{quoted_string, ImapResponse};
 
 pub struct Response {
     pub shared_prefix: Option<String>,
+    pub public_prefix: Option<String>,
 }
 
 impl ImapResponse for Response {
     fn serialize(self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(64);
-        if let Some(shared_prefix) = &self.shared_prefix {
-            buf.extend_from_slice(b"* NAMESPACE ((\"\" \"/\")) ((");
-            quoted_string(&mut buf, shared_prefix);
-            buf.extend_from_slice(b" \"/\")) NIL\r\n");
-        } else {
-            buf.extend_from_slice(b"* NAMESPACE ((\"\" \"/\")) NIL NIL\r\n");
+        buf.extend_from_slice(b"* NAMESPACE ((\"\" \"/\"))");
+        for prefix in [&self.shared_prefix, &self.public_prefix] {
+            if let Some(prefix) = prefix {
+                buf.extend_from_slice(b" ((");
+                quoted_string(&mut buf, prefix);
+                buf.extend_from_slice(b" \"/\"))");
+            } else {
+                buf.extend_from_slice(b" NIL");
+            }
         }
+        buf.extend_from_slice(b"\r\n");
         buf
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::ImapResponse;
+
+    #[test]
+    fn serialize_namespace() {
+        for (shared_prefix, public_prefix, expected) in [
+            (None, None, "* NAMESPACE ((\"\" \"/\")) NIL NIL\r\n"),
+            (
+                Some("Shared Folders"),
+                None,
+                "* NAMESPACE ((\"\" \"/\")) ((\"Shared Folders\" \"/\")) NIL\r\n",
+            ),
+            (
+                Some("Shared Folders"),
+                Some("#public"),
+                concat!(
+                    "* NAMESPACE ((\"\" \"/\")) ((\"Shared Folders\" \"/\")) ",
+                    "((\"#public\" \"/\"))\r\n"
+                ),
+            ),
+            (
+                None,
+                Some("#public"),
+                "* NAMESPACE ((\"\" \"/\")) NIL ((\"#public\" \"/\"))\r\n",
+            ),
+        ] {
+            assert_eq!(
+                String::from_utf8(
+                    super::Response {
+                        shared_prefix: shared_prefix.map(String::from),
+                        public_prefix: public_prefix.map(String::from),
+                    }
+                    .serialize()
+                )
+                .unwrap(),
+                expected
+            );
+        }
+    }
+}
@@ -106,6 +106,12 @@ pub enum Filter {
     Older(u32),
     Younger(u32),
 
+    // RFC 8514 - SAVEDATE
+    SavedBefore(i64),
+    SavedOn(i64),
+    SavedSince(i64),
+    SaveDateSupported,
+
     // RFC 4551 - CONDSTORE
     ModSeq((u64, ModSeqEntry)),
 
@@ -92,6 +92,10 @@ impl<T: CommandParser> Receiver<T> {
         }
     }
 
+    pub fn is_request_pending(&self) -> bool {
+        self.current_request_size > 0
+    }
+
     pub fn error_reset(&mut self, message: impl Into<trc::Value>) -> Error {
         let request = std::mem::take(&mut self.request);
         let err = Error::err(
@@ -12,7 +12,7 @@ use common::{
 };
 use imap_proto::{
     receiver::{self, Request},
-    Command, ResponseType, StatusResponse,
+    Command, ResponseCode, ResponseType, StatusResponse,
 };
 use trc::SecurityEvent;
 
@@ -238,6 +238,14 @@ impl<T: SessionStream> Session<T> {
                     .handle_id(request)
                     .await
                     .map(|_| SessionResult::Continue),
+                Command::GetMetadata => self
+                    .handle_get_metadata(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::SetMetadata => self
+                    .handle_set_metadata(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
             };
 
             match result {
@@ -301,6 +309,32 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Reject mutations while the server is in read-only mode
+        if self.server.is_read_only()
+            && matches!(
+                request.command,
+                Command::Create
+                    | Command::Delete
+                    | Command::Rename
+                    | Command::Subscribe
+                    | Command::Unsubscribe
+                    | Command::Append
+                    | Command::Expunge(_)
+                    | Command::Store(_)
+                    | Command::Copy(_)
+                    | Command::Move(_)
+                    | Command::SetAcl
+                    | Command::DeleteAcl
+                    | Command::SetMetadata
+            )
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Server is in read-only mode.")
+                .code(ResponseCode::Unavailable)
+                .id(request.tag));
+        }
+
         match &request.command {
             Command::Capability | Command::Noop | Command::Logout | Command::Id => Ok(request),
             Command::StartTls => {
@@ -366,6 +400,8 @@ impl<T: SessionStream> Session<T> {
             | Command::GetAcl
             | Command::ListRights
             | Command::MyRights
+            | Command::GetMetadata
+            | Command::SetMetadata
             | Command::Unauthenticate => {
                 if let State::Authenticated { .. } | State::Selected { .. } = state {
                     Ok(request)
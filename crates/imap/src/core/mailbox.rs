@@ -15,7 +15,8 @@ use imap_proto::protocol::list::Attribute;
 use jmap::{
     auth::acl::{AclMethods, EffectiveAcl},
     changes::get::ChangesLookup,
-    mailbox::{get::MailboxGet, set::MailboxSet, INBOX_ID},
+    email::seen::PrivateSeen,
+    mailbox::{counters::MailboxCounterCache, set::MailboxSet, INBOX_ID},
     JmapMethods,
 };
 use jmap_proto::{
@@ -23,7 +24,10 @@ use jmap_proto::{
     types::{acl::Acl, collection::Collection, id::Id, property::Property, value::Value},
 };
 use parking_lot::Mutex;
-use store::query::log::{Change, Query};
+use store::{
+    query::log::{Change, Query},
+    roaring::RoaringBitmap,
+};
 use trc::AddContext;
 use utils::lru_cache::LruCached;
 
@@ -59,21 +63,7 @@ impl<T: SessionStream> SessionData<T> {
                 session
                     .fetch_account_mailboxes(
                         account_id,
-                        format!(
-                            "{}/{}",
-                            session.server.core.jmap.shared_folder,
-                            session
-                                .server
-                                .core
-                                .storage
-                                .directory
-                                .query(QueryBy::Id(account_id), false)
-                                .await
-                                .unwrap_or_default()
-                                .and_then(|mut p| p.take_str(PrincipalField::Name))
-                                .unwrap_or_else(|| Id::from(account_id).to_string())
-                        )
-                        .into(),
+                        session.shared_account_prefix(account_id).await?.into(),
                         &access_token,
                     )
                     .await
@@ -86,6 +76,28 @@ impl<T: SessionStream> SessionData<T> {
         Ok(session)
     }
 
+    /// Returns the folder under which the mailboxes of a shared account are
+    /// listed, which is the public namespace for the public folders account.
+    async fn shared_account_prefix(&self, account_id: u32) -> trc::Result<String> {
+        let name = self
+            .server
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .caused_by(trc::location!())?
+            .and_then(|mut p| p.take_str(PrincipalField::Name))
+            .unwrap_or_else(|| Id::from(account_id).to_string());
+
+        match &self.server.core.jmap.public_folders {
+            Some(public_folders) if public_folders.account == name => {
+                Ok(public_folders.name.clone())
+            }
+            _ => Ok(format!("{}/{}", self.server.core.jmap.shared_folder, name)),
+        }
+    }
+
     async fn fetch_account_mailboxes(
         &self,
         account_id: u32,
@@ -195,11 +207,27 @@ impl<T: SessionStream> SessionData<T> {
         let mut parent_id = 0;
         let mut path = Vec::new();
         let mut iter_stack = Vec::new();
-        let message_ids = self
-            .server
-            .get_document_ids(account_id, Collection::Email)
-            .await
-            .caused_by(trc::location!())?;
+
+        // Obtain the messages unseen by this user if the \Seen state is private
+        let private_unseen_ids = if self.has_private_seen(account_id) {
+            let message_ids = self
+                .server
+                .get_document_ids(account_id, Collection::Email)
+                .await
+                .caused_by(trc::location!())?;
+            let seen_ids = self
+                .server
+                .private_seen_ids(account_id, self.account_id)
+                .await
+                .caused_by(trc::location!())?;
+            Some(
+                message_ids
+                    .as_ref()
+                    .map_or_else(RoaringBitmap::new, |message_ids| message_ids - &seen_ids),
+            )
+        } else {
+            None
+        };
 
         if let Some(mailbox_prefix) = &mailbox_prefix {
             path.push(mailbox_prefix.to_string());
@@ -233,6 +261,12 @@ impl<T: SessionStream> SessionData<T> {
                         .iter()
                         .any(|(_, child_parent_id, _)| *child_parent_id == *mailbox_id + 1);
 
+                    let counters = self
+                        .server
+                        .mailbox_counters(account_id, *mailbox_id)
+                        .await
+                        .caused_by(trc::location!())?;
+
                     account.mailbox_state.insert(
                         *mailbox_id,
                         Mailbox {
@@ -252,27 +286,23 @@ impl<T: SessionStream> SessionData<T> {
                                     _ => None,
                                 },
                             ),
-                            total_messages: self
-                                .server
-                                .get_tag(
-                                    account_id,
-                                    Collection::Email,
-                                    Property::MailboxIds,
-                                    *mailbox_id,
-                                )
-                                .await
-                                .caused_by(trc::location!())?
-                                .map(|v| v.len() as u32)
-                                .unwrap_or(0)
-                                .into(),
-                            total_unseen: self
-                                .server
-                                .mailbox_unread_tags(account_id, *mailbox_id, &message_ids)
-                                .await
-                                .caused_by(trc::location!())?
-                                .map(|v| v.len() as u32)
-                                .unwrap_or(0)
-                                .into(),
+                            total_messages: Some(counters.total_emails as u32),
+                            total_unseen: if let Some(unseen_ids) = &private_unseen_ids {
+                                self.server
+                                    .get_tag(
+                                        account_id,
+                                        Collection::Email,
+                                        Property::MailboxIds,
+                                        *mailbox_id,
+                                    )
+                                    .await
+                                    .caused_by(trc::location!())?
+                                    .map(|v| (v & unseen_ids).len() as u32)
+                            } else {
+                                Some(counters.unread_emails as u32)
+                            }
+                            .unwrap_or(0)
+                            .into(),
                             ..Default::default()
                         },
                     );
@@ -389,19 +419,7 @@ impl<T: SessionStream> SessionData<T> {
 
             // Fetch mailboxes for each new shared account
             for account_id in added_account_ids {
-                let prefix = format!(
-                    "{}/{}",
-                    self.server.core.jmap.shared_folder,
-                    self.server
-                        .core
-                        .storage
-                        .directory
-                        .query(QueryBy::Id(account_id), false)
-                        .await
-                        .caused_by(trc::location!())?
-                        .and_then(|mut p| p.take_str(PrincipalField::Name))
-                        .unwrap_or_else(|| Id::from(account_id).to_string())
-                );
+                let prefix = self.shared_account_prefix(account_id).await?;
                 added_accounts.push(
                     self.fetch_account_mailboxes(account_id, prefix.into(), &access_token)
                         .await?,
@@ -499,20 +517,7 @@ impl<T: SessionStream> SessionData<T> {
                 } else {
                     // Refresh mailboxes for changed account
                     let mailbox_prefix = if !access_token.is_primary_id(account_id) {
-                        format!(
-                            "{}/{}",
-                            self.server.core.jmap.shared_folder,
-                            self.server
-                                .core
-                                .storage
-                                .directory
-                                .query(QueryBy::Id(account_id), false)
-                                .await
-                                .caused_by(trc::location!())?
-                                .and_then(|mut p| p.take_str(PrincipalField::Name))
-                                .unwrap_or_else(|| Id::from(account_id).to_string())
-                        )
-                        .into()
+                        self.shared_account_prefix(account_id).await?.into()
                     } else {
                         None
                     };
@@ -625,6 +630,7 @@ impl<T: SessionStream> SessionData<T> {
     ) -> trc::Result<bool> {
         let access_token = self.get_access_token().await?;
         Ok(access_token.is_member(account_id)
+            || access_token.delegated_acl(account_id).contains(item)
             || self
                 .server
                 .get_property::<Object<Value>>(
@@ -9,7 +9,7 @@ use std::{collections::BTreeMap, sync::Arc};
 use ahash::AHashMap;
 use common::{listener::SessionStream, NextMailboxState};
 use imap_proto::protocol::{expunge, select::Exists, Sequence};
-use jmap::{mailbox::UidMailbox, JmapMethods};
+use jmap::{email::seen::PrivateSeen, mailbox::UidMailbox, JmapMethods};
 use jmap_proto::{
     object::Object,
     types::{collection::Collection, property::Property, value::Value},
@@ -242,6 +242,12 @@ impl<T: SessionStream> SessionData<T> {
             })
             .map(|v| v as u32)
     }
+
+    /// Returns whether this user keeps their own \Seen state on the
+    /// messages of an account.
+    pub fn has_private_seen(&self, account_id: u32) -> bool {
+        self.server.has_private_seen(account_id, self.account_id)
+    }
 }
 
 impl SelectedMailbox {
@@ -4,11 +4,14 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use common::{
     core::BuildServer,
-    listener::{stream::NullIo, SessionData, SessionManager, SessionResult, SessionStream},
+    listener::{
+        drain::drain_timeout, stream::NullIo, SessionData, SessionManager, SessionResult,
+        SessionStream,
+    },
 };
 use imap_proto::{
     protocol::{ProtocolVersion, SerializeResponse},
@@ -48,21 +51,28 @@ impl<T: SessionStream> Session<T> {
     pub async fn handle_conn(&mut self) -> bool {
         let mut buf = vec![0; 8192];
         let mut shutdown_rx = self.instance.shutdown_rx.clone();
+        let mut drain_deadline = None;
 
         loop {
             tokio::select! {
                 result = tokio::time::timeout(
-                    if !matches!(self.state, State::NotAuthenticated {..}) {
+                    drain_timeout(if !matches!(self.state, State::NotAuthenticated {..}) {
                         self.server.core.imap.timeout_auth
                     } else {
                         self.server.core.imap.timeout_unauth
-                    },
+                    }, drain_deadline),
                     self.stream_rx.read(&mut buf)) => {
                     match result {
                         Ok(Ok(bytes_read)) => {
                             if bytes_read > 0 {
                                 match self.ingest(&buf[..bytes_read]).await {
-                                    SessionResult::Continue => (),
+                                    SessionResult::Continue => {
+                                        // Close drained sessions once the pending request is complete
+                                        if drain_deadline.is_some() && !self.receiver.is_request_pending() {
+                                            self.close_on_shutdown().await;
+                                            break;
+                                        }
+                                    }
                                     SessionResult::UpgradeTls => {
                                         return true;
                                     }
@@ -88,6 +98,10 @@ impl<T: SessionStream> Session<T> {
                             );
                             break;
                         },
+                        Err(_) if drain_deadline.is_some() => {
+                            self.close_on_shutdown().await;
+                            break;
+                        }
                         Err(_) => {
                             trc::event!(
                                 Network(trc::NetworkEvent::Timeout),
@@ -99,15 +113,15 @@ impl<T: SessionStream> Session<T> {
                         }
                     }
                 },
-                _ = shutdown_rx.changed() => {
-                    trc::event!(
-                        Network(trc::NetworkEvent::Closed),
-                        SpanId = self.session_id,
-                        Reason = "Server shutting down",
-                        CausedBy = trc::location!()
-                    );
-                    self.write_bytes(&b"* BYE Server shutting down.\r\n"[..]).await.ok();
-                    break;
+                _ = shutdown_rx.changed(), if drain_deadline.is_none() => {
+                    // Allow partially received requests to complete within the grace period
+                    let grace_period = self.server.core.network.grace_period;
+                    if self.receiver.is_request_pending() && !grace_period.is_zero() {
+                        drain_deadline = Some(Instant::now() + grace_period);
+                    } else {
+                        self.close_on_shutdown().await;
+                        break;
+                    }
                 }
             };
         }
@@ -115,6 +129,18 @@ impl<T: SessionStream> Session<T> {
         false
     }
 
+    async fn close_on_shutdown(&mut self) {
+        trc::event!(
+            Network(trc::NetworkEvent::Closed),
+            SpanId = self.session_id,
+            Reason = "Server shutting down",
+            CausedBy = trc::location!()
+        );
+        self.write_bytes(&b"* BYE Server shutting down.\r\n"[..])
+            .await
+            .ok();
+    }
+
     pub async fn new(
         mut session: SessionData<T>,
         manager: ImapSessionManager,
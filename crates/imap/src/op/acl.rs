@@ -17,8 +17,11 @@ use imap_proto::{
 };
 
 use jmap::{
-    auth::acl::EffectiveAcl, changes::write::ChangeLog, mailbox::set::SCHEMA,
-    services::state::StateManager, JmapMethods,
+    auth::acl::EffectiveAcl,
+    changes::write::ChangeLog,
+    mailbox::{public::PublicFolderMethods, set::SCHEMA},
+    services::state::StateManager,
+    JmapMethods,
 };
 use jmap_proto::{
     object::{index::ObjectIndexBuilder, Object},
@@ -159,7 +162,8 @@ impl<T: SessionStream> Session<T> {
                 .await
                 .imap_ctx(&arguments.tag, trc::location!())?;
             let rights = if access_token.is_shared(mailbox.account_id) {
-                let acl = values.inner.effective_acl(&access_token);
+                let mut acl = values.inner.effective_acl(&access_token);
+                acl.union(&access_token.delegated_acl(mailbox.account_id));
                 let mut rights = Vec::with_capacity(5);
                 if acl.contains(Acl::ReadItems) {
                     rights.push(Rights::Read);
@@ -241,7 +245,7 @@ impl<T: SessionStream> Session<T> {
         spawn_op!(data, {
             // Validate mailbox
             let (mailbox, values, _) = data
-                .get_acl_mailbox(&arguments, false)
+                .get_acl_mailbox(&arguments, true)
                 .await
                 .imap_ctx(&arguments.tag, trc::location!())?;
 
@@ -335,40 +339,63 @@ impl<T: SessionStream> Session<T> {
                 .map(|r| trc::Value::from(r.account_id))
                 .collect::<Vec<_>>();
 
-            // Write changes
-            let mailbox_id = mailbox.mailbox_id;
-            let mut batch = BatchBuilder::new();
-            batch
-                .with_account_id(mailbox.account_id)
-                .with_collection(Collection::Mailbox)
-                .update_document(mailbox_id)
-                .custom(
-                    ObjectIndexBuilder::new(SCHEMA)
-                        .with_changes(changes)
-                        .with_current(values),
-                );
-            if !batch.is_empty() {
+            // Write changes, public folders apply the rights to all their descendants
+            if data
+                .server
+                .public_account_id()
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?
+                == Some(mailbox.account_id)
+            {
+                let rights = acl
+                    .iter()
+                    .find(|item| item.account_id == acl_account_id)
+                    .map(|item| item.grants)
+                    .unwrap_or_default();
                 data.server
-                    .write_batch(batch)
-                    .await
-                    .imap_ctx(&arguments.tag, trc::location!())?;
-                let mut changes = ChangeLogBuilder::new();
-                changes.log_update(Collection::Mailbox, mailbox_id);
-                let change_id = data
-                    .server
-                    .commit_changes(mailbox.account_id, changes)
+                    .public_folder_set_rights(
+                        mailbox.account_id,
+                        mailbox.mailbox_id,
+                        acl_account_id,
+                        rights,
+                    )
                     .await
                     .imap_ctx(&arguments.tag, trc::location!())?;
-                data.server
-                    .broadcast_state_change(
-                        StateChange::new(mailbox.account_id)
-                            .with_change(DataType::Mailbox, change_id),
-                    )
-                    .await;
+            } else {
+                let mailbox_id = mailbox.mailbox_id;
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(mailbox.account_id)
+                    .with_collection(Collection::Mailbox)
+                    .update_document(mailbox_id)
+                    .custom(
+                        ObjectIndexBuilder::new(SCHEMA)
+                            .with_changes(changes)
+                            .with_current(values),
+                    );
+                if !batch.is_empty() {
+                    data.server
+                        .write_batch(batch)
+                        .await
+                        .imap_ctx(&arguments.tag, trc::location!())?;
+                    let mut changes = ChangeLogBuilder::new();
+                    changes.log_update(Collection::Mailbox, mailbox_id);
+                    let change_id = data
+                        .server
+                        .commit_changes(mailbox.account_id, changes)
+                        .await
+                        .imap_ctx(&arguments.tag, trc::location!())?;
+                    data.server
+                        .broadcast_state_change(
+                            StateChange::new(mailbox.account_id)
+                                .with_change(DataType::Mailbox, change_id),
+                        )
+                        .await;
+                }
             }
 
             // Invalidate ACLs
-            data.server.inner.data.access_tokens.remove(&acl_account_id);
+            data.server.invalidate_access_token(acl_account_id);
 
             trc::event!(
                 Imap(trc::ImapEvent::SetAcl),
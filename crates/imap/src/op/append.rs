@@ -114,6 +114,7 @@ impl<T: SessionStream> SessionData<T> {
                     received_at: message.received_at.map(|d| d as u64),
                     source: IngestSource::Imap,
                     encrypt: self.server.core.jmap.encrypt && self.server.core.jmap.encrypt_append,
+                    shared_metadata: false,
                     session_id: self.session_id,
                 })
                 .await
@@ -13,22 +13,29 @@ use imap_proto::{
 };
 
 use crate::{
-    core::{SelectedMailbox, Session, SessionData},
+    core::{message::MAX_RETRIES, SelectedMailbox, Session, SessionData},
     spawn_op,
 };
 use common::{listener::SessionStream, MailboxId};
 use jmap::{
     changes::write::ChangeLog,
-    email::{copy::EmailCopy, ingest::EmailIngest, set::TagManager},
-    mailbox::UidMailbox,
+    email::{
+        copy::EmailCopy,
+        index::SaveDate,
+        ingest::EmailIngest,
+        seen::{replace_seen, PrivateSeen, PrivateSeenBatch},
+        set::TagManager,
+        train::EmailSpamTrain,
+    },
+    mailbox::{UidMailbox, JUNK_ID, TRASH_ID},
     services::state::StateManager,
     JmapMethods,
 };
 use jmap_proto::{
     error::set::SetErrorType,
     types::{
-        acl::Acl, collection::Collection, id::Id, property::Property, state::StateChange,
-        type_state::DataType,
+        acl::Acl, collection::Collection, id::Id, keyword::Keyword, property::Property,
+        state::StateChange, type_state::DataType,
     },
 };
 use store::{
@@ -125,6 +132,11 @@ impl<T: SessionStream> SessionData<T> {
                 .id(arguments.tag));
         }
 
+        // Process messages in UID order, so that the destination UIDs are assigned
+        // in the same order as the source UIDs reported in COPYUID.
+        let mut ids = ids.into_iter().collect::<Vec<_>>();
+        ids.sort_unstable_by_key(|(_, imap_id)| imap_id.uid);
+
         // Verify that the user can delete messages from the source mailbox.
         if is_move
             && !self
@@ -175,72 +187,137 @@ impl<T: SessionStream> SessionData<T> {
             // Mailboxes are in the same account
             let account_id = src_mailbox.id.account_id;
             let dest_mailbox_id = UidMailbox::new_unassigned(dest_mailbox_id);
-            for (id, imap_id) in ids {
-                // Obtain mailbox tags
-                let (mut mailboxes, thread_id) = if let Some(result) = self
-                    .get_mailbox_tags(account_id, id)
-                    .await
-                    .imap_ctx(&arguments.tag, trc::location!())?
-                {
-                    result
-                } else {
-                    continue;
-                };
+            let mut train_ids = Vec::new();
 
-                // Make sure the message still belongs to this mailbox
-                if !mailboxes
-                    .current()
-                    .contains(&UidMailbox::new_unassigned(src_mailbox.id.mailbox_id))
-                    || mailboxes.current().contains(&dest_mailbox_id)
-                {
-                    continue;
-                }
+            // Reserve a UID range in the destination mailbox, the range is allocated
+            // atomically so messages added concurrently on other nodes never reuse it.
+            let mut next_uid = self
+                .server
+                .assign_imap_uids(account_id, dest_mailbox_id.mailbox_id, ids.len() as u32)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
 
-                // Add destination folder
-                mailboxes.update(dest_mailbox_id, true);
-                if is_move {
-                    mailboxes.update(UidMailbox::new_unassigned(src_mailbox.id.mailbox_id), false);
-                }
+            'outer: for (id, imap_id) in ids {
+                let mut try_count = 0;
+                loop {
+                    // Obtain mailbox tags
+                    let (mut mailboxes, thread_id) = if let Some(result) = self
+                        .get_mailbox_tags(account_id, id)
+                        .await
+                        .imap_ctx(&arguments.tag, trc::location!())?
+                    {
+                        result
+                    } else {
+                        continue 'outer;
+                    };
+
+                    // Make sure the message still belongs to this mailbox
+                    if !mailboxes
+                        .current()
+                        .contains(&UidMailbox::new_unassigned(src_mailbox.id.mailbox_id))
+                        || mailboxes.current().contains(&dest_mailbox_id)
+                    {
+                        continue 'outer;
+                    }
 
-                // Assign IMAP UIDs
-                for uid_mailbox in mailboxes.inner_tags_mut() {
-                    if uid_mailbox.uid == 0 {
-                        let assigned_uid = self
+                    // Add destination folder
+                    mailboxes.update(dest_mailbox_id, true);
+                    if is_move {
+                        mailboxes
+                            .update(UidMailbox::new_unassigned(src_mailbox.id.mailbox_id), false);
+                    }
+
+                    // Assign IMAP UIDs
+                    for uid_mailbox in mailboxes.inner_tags_mut() {
+                        if uid_mailbox.uid == 0 {
+                            uid_mailbox.uid =
+                                if uid_mailbox.mailbox_id == dest_mailbox_id.mailbox_id {
+                                    next_uid
+                                } else {
+                                    self.server
+                                        .assign_imap_uid(account_id, uid_mailbox.mailbox_id)
+                                        .await
+                                        .imap_ctx(&arguments.tag, trc::location!())?
+                                };
+                            debug_assert!(uid_mailbox.uid > 0);
+                        }
+                    }
+
+                    // Write changes
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(Collection::Email)
+                        .update_document(id);
+                    mailboxes.update_batch(&mut batch, Property::MailboxIds);
+                    batch.save_date(
+                        self.server
+                            .get_property::<u64>(
+                                account_id,
+                                Collection::Email,
+                                id,
+                                Property::SavedAt,
+                            )
+                            .await
+                            .imap_ctx(&arguments.tag, trc::location!())?,
+                    );
+                    if changelog.change_id == u64::MAX {
+                        changelog.change_id = self
                             .server
-                            .assign_imap_uid(account_id, uid_mailbox.mailbox_id)
+                            .assign_change_id(account_id)
                             .await
                             .imap_ctx(&arguments.tag, trc::location!())?;
-                        debug_assert!(assigned_uid > 0);
-                        copied_ids.push((imap_id.uid, assigned_uid));
-                        uid_mailbox.uid = assigned_uid;
                     }
+                    batch.value(Property::Cid, changelog.change_id, F_VALUE);
+                    match self.server.write_batch(batch).await {
+                        Ok(_) => {
+                            // Only UIDs of committed messages are reported
+                            copied_ids.push((imap_id.uid, next_uid));
+                            next_uid += 1;
+                            changelog.log_update(Collection::Email, Id::from_parts(thread_id, id));
+                            changelog
+                                .log_child_update(Collection::Mailbox, dest_mailbox_id.mailbox_id);
+                            if is_move {
+                                changelog.log_child_update(
+                                    Collection::Mailbox,
+                                    src_mailbox.id.mailbox_id,
+                                );
+                                did_move = true;
+                            }
+                            train_ids.push(id);
+                        }
+                        Err(err) if err.is_assertion_failure() => {
+                            // The message was modified concurrently, retry with the same UID
+                            if try_count < MAX_RETRIES {
+                                try_count += 1;
+                                continue;
+                            } else {
+                                response.rtype = ResponseType::No;
+                                response.message = if is_move {
+                                    "Some messages could not be moved."
+                                } else {
+                                    "Some messages could not be copied."
+                                }
+                                .into();
+                            }
+                        }
+                        Err(err) => {
+                            return Err(err).imap_ctx(&arguments.tag, trc::location!());
+                        }
+                    }
+                    break;
                 }
+            }
 
-                // Write changes
-                let mut batch = BatchBuilder::new();
-                batch
-                    .with_account_id(account_id)
-                    .with_collection(Collection::Email)
-                    .update_document(id);
-                mailboxes.update_batch(&mut batch, Property::MailboxIds);
-                if changelog.change_id == u64::MAX {
-                    changelog.change_id = self
-                        .server
-                        .assign_change_id(account_id)
-                        .await
-                        .imap_ctx(&arguments.tag, trc::location!())?;
-                }
-                batch.value(Property::Cid, changelog.change_id, F_VALUE);
+            // Messages moved into or out of Junk are used to train the spam filter
+            if dest_mailbox_id.mailbox_id == JUNK_ID {
+                self.server.email_spam_feedback(account_id, train_ids, true);
+            } else if is_move
+                && src_mailbox.id.mailbox_id == JUNK_ID
+                && dest_mailbox_id.mailbox_id != TRASH_ID
+            {
                 self.server
-                    .write_batch(batch)
-                    .await
-                    .imap_ctx(&arguments.tag, trc::location!())?;
-                changelog.log_update(Collection::Email, Id::from_parts(thread_id, id));
-                changelog.log_child_update(Collection::Mailbox, dest_mailbox_id.mailbox_id);
-                if is_move {
-                    changelog.log_child_update(Collection::Mailbox, src_mailbox.id.mailbox_id);
-                    did_move = true;
-                }
+                    .email_spam_feedback(account_id, train_ids, false);
             }
         } else {
             // Obtain quota for target account
@@ -254,7 +331,38 @@ impl<T: SessionStream> SessionData<T> {
                 .imap_ctx(&arguments.tag, trc::location!())?
                 .as_resource_token();
             let mut destroy_ids = RoaringBitmap::new();
+
+            // Flags are copied as seen by this user, which might keep their own
+            // \Seen state on either account
+            let src_seen_ids = if self.has_private_seen(src_account_id) {
+                Some(
+                    self.server
+                        .private_seen_ids(src_account_id, self.account_id)
+                        .await
+                        .imap_ctx(&arguments.tag, trc::location!())?,
+                )
+            } else {
+                None
+            };
+            let is_dest_private_seen = self.has_private_seen(dest_account_id);
+
             for (id, imap_id) in ids {
+                let keywords = self
+                    .server
+                    .get_property::<Vec<Keyword>>(
+                        src_account_id,
+                        Collection::Email,
+                        id,
+                        Property::Keywords,
+                    )
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?
+                    .unwrap_or_default();
+                let is_seen = match &src_seen_ids {
+                    Some(src_seen_ids) => src_seen_ids.contains(id),
+                    None => keywords.contains(&Keyword::Seen),
+                };
+
                 match self
                     .server
                     .copy_message(
@@ -262,7 +370,7 @@ impl<T: SessionStream> SessionData<T> {
                         id,
                         &resource_token,
                         vec![dest_mailbox_id],
-                        Vec::new(),
+                        replace_seen(&keywords, is_seen && !is_dest_private_seen),
                         None,
                         self.session_id,
                     )
@@ -271,6 +379,18 @@ impl<T: SessionStream> SessionData<T> {
                 {
                     Ok(email) => {
                         dest_change_id = email.change_id.into();
+                        if is_seen && is_dest_private_seen {
+                            let mut batch = BatchBuilder::new();
+                            batch
+                                .with_account_id(dest_account_id)
+                                .with_collection(Collection::Email)
+                                .update_document(email.id.document_id())
+                                .private_seen(None, self.account_id, true);
+                            self.server
+                                .write_batch(batch)
+                                .await
+                                .imap_ctx(&arguments.tag, trc::location!())?;
+                        }
                         if let Some(assigned_uid) = email.imap_uids.first() {
                             debug_assert!(*assigned_uid > 0);
                             copied_ids.push((imap_id.uid, *assigned_uid));
@@ -363,8 +483,6 @@ impl<T: SessionStream> SessionData<T> {
             src_uids.push(src_uid);
             dest_uids.push(dest_uid);
         }
-        src_uids.sort_unstable();
-        dest_uids.sort_unstable();
 
         trc::event!(
             Imap(if is_move {
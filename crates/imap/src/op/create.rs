@@ -19,7 +19,10 @@ use imap_proto::{
     Command, ResponseCode, StatusResponse,
 };
 use jmap::{
-    changes::write::ChangeLog, mailbox::set::SCHEMA, services::state::StateManager, JmapMethods,
+    changes::write::ChangeLog,
+    mailbox::{public::PublicFolderMethods, set::SCHEMA},
+    services::state::StateManager,
+    JmapMethods,
 };
 use jmap_proto::{
     object::{index::ObjectIndexBuilder, Object},
@@ -82,6 +85,16 @@ impl<T: SessionStream> SessionData<T> {
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
 
+        // Public folders inherit the ACL of their parent
+        let acl = match params.parent_mailbox_id {
+            Some(parent_mailbox_id) if params.is_public => self
+                .server
+                .public_folder_inherited_acl(params.account_id, parent_mailbox_id)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?,
+            _ => Vec::new(),
+        };
+
         let mut parent_id = params.parent_mailbox_id.map(|id| id + 1).unwrap_or(0);
         let mut create_ids = Vec::with_capacity(params.path.len());
         for (pos, &path_item) in params.path.iter().enumerate() {
@@ -97,6 +110,9 @@ impl<T: SessionStream> SessionData<T> {
                     mailbox.set(Property::Role, mailbox_role);
                 }
             }
+            if !acl.is_empty() {
+                mailbox.set(Property::Acl, Value::Acl(acl.clone()));
+            }
             let mut batch = BatchBuilder::new();
             batch
                 .with_account_id(params.account_id)
@@ -294,6 +310,14 @@ impl<T: SessionStream> SessionData<T> {
         let full_path = path.join("/");
         let mut parent_mailbox_id = None;
         let mut parent_mailbox_name = None;
+        let public_prefix = self
+            .server
+            .core
+            .jmap
+            .public_folders
+            .as_ref()
+            .map(|public_folders| public_folders.name.as_str());
+        let is_public = public_prefix == path.first().copied();
         let (account_id, path) = {
             let mailboxes = self.mailboxes.lock();
             let first_path_item = path.first().unwrap();
@@ -321,6 +345,28 @@ impl<T: SessionStream> SessionData<T> {
                         prefix.unwrap_or_default()
                     )));
                 }
+            } else if is_public {
+                // #public/<folder>
+                if path.len() < 2 {
+                    return Err(trc::ImapEvent::Error
+                        .into_err()
+                        .details("Mailboxes under the public namespace root are not allowed.")
+                        .code(ResponseCode::Cannot));
+                }
+
+                // Locate account
+                if let Some(account) = mailboxes
+                    .iter()
+                    .skip(1)
+                    .find(|account| account.prefix.as_deref() == public_prefix)
+                {
+                    account
+                } else {
+                    return Err(trc::ImapEvent::Error
+                        .into_err()
+                        .details("Public folders are not available.")
+                        .code(ResponseCode::NoPerm));
+                }
             } else if let Some(account) = mailboxes.first() {
                 account
             } else {
@@ -371,17 +417,18 @@ impl<T: SessionStream> SessionData<T> {
                     .details("You are not allowed to create sub mailboxes under this mailbox.")
                     .code(ResponseCode::NoPerm));
             }
-        } else if self.account_id != account_id
-            && !self
-                .get_access_token()
-                .await
-                .caused_by(trc::location!())?
-                .is_member(account_id)
-        {
-            return Err(trc::ImapEvent::Error
-                .into_err()
-                .details("You are not allowed to create root folders under shared folders.")
-                .code(ResponseCode::Cannot));
+        } else if self.account_id != account_id {
+            let access_token = self.get_access_token().await.caused_by(trc::location!())?;
+            if !access_token.is_member(account_id)
+                && !access_token
+                    .delegated_acl(account_id)
+                    .contains(Acl::CreateChild)
+            {
+                return Err(trc::ImapEvent::Error
+                    .into_err()
+                    .details("You are not allowed to create root folders under shared folders.")
+                    .code(ResponseCode::Cannot));
+            }
         }
 
         Ok(CreateParams {
@@ -416,6 +463,7 @@ impl<T: SessionStream> SessionData<T> {
                 None
             },
             is_rename: false,
+            is_public,
         })
     }
 }
@@ -429,4 +477,5 @@ pub struct CreateParams<'x> {
     pub parent_mailbox_name: Option<String>,
     pub special_use: Option<Attribute>,
     pub is_rename: bool,
+    pub is_public: bool,
 }
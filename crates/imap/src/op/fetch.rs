@@ -29,7 +29,10 @@ use imap_proto::{
 use jmap::{
     blob::download::BlobDownload,
     changes::{get::ChangesLookup, write::ChangeLog},
-    email::metadata::MessageMetadata,
+    email::{
+        metadata::MessageMetadata,
+        seen::{replace_seen, PrivateSeen, PrivateSeenBatch},
+    },
     services::state::StateManager,
     JmapMethods,
 };
@@ -272,6 +275,18 @@ impl<T: SessionStream> SessionData<T> {
 
         let mut set_seen_ids = Vec::new();
 
+        // Obtain the messages seen by this user if the \Seen state is private
+        let private_seen_ids = if self.has_private_seen(account_id) {
+            Some(
+                self.server
+                    .private_seen_ids(account_id, self.account_id)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?,
+            )
+        } else {
+            None
+        };
+
         // Process each message
         let mut ids = ids
             .into_iter()
@@ -285,7 +300,7 @@ impl<T: SessionStream> SessionData<T> {
 
         for (seqnum, uid, id) in ids {
             // Obtain attributes and keywords
-            let (email, keywords) = if let (Some(email), Some(keywords)) = (
+            let (email, mut keywords) = if let (Some(email), Some(keywords)) = (
                 self.server
                     .get_property::<Bincode<MessageMetadata>>(
                         account_id,
@@ -318,6 +333,11 @@ impl<T: SessionStream> SessionData<T> {
                 continue;
             };
 
+            // Replace the \Seen flag with the state of this user
+            if let Some(private_seen_ids) = &private_seen_ids {
+                keywords.inner = replace_seen(&keywords.inner, private_seen_ids.contains(id));
+            }
+
             // Fetch and parse blob
             let raw_message = if needs_blobs {
                 // Retrieve raw message if needed
@@ -502,6 +522,21 @@ impl<T: SessionStream> SessionData<T> {
                             thread_id: Id::from_parts(account_id, thread_id).to_string(),
                         });
                     }
+                    Attribute::SaveDate => {
+                        items.push(DataItem::SaveDate {
+                            date: self
+                                .server
+                                .get_property::<u64>(
+                                    account_id,
+                                    Collection::Email,
+                                    id,
+                                    Property::SavedAt,
+                                )
+                                .await
+                                .imap_ctx(&arguments.tag, trc::location!())?
+                                .map(|date| date as i64),
+                        });
+                    }
                 }
             }
 
@@ -535,16 +570,26 @@ impl<T: SessionStream> SessionData<T> {
                 .await
                 .imap_ctx(&arguments.tag, trc::location!())?;
             for (id, mut keywords) in set_seen_ids {
-                keywords.inner.push(Keyword::Seen);
                 let mut batch = BatchBuilder::new();
                 batch
                     .with_account_id(account_id)
                     .with_collection(Collection::Email)
-                    .update_document(id.document_id())
-                    .assert_value(Property::Keywords, &keywords)
-                    .value(Property::Keywords, keywords.inner, F_VALUE)
-                    .value(Property::Keywords, Keyword::Seen, F_BITMAP)
-                    .value(Property::Cid, changelog.change_id, F_VALUE);
+                    .update_document(id.document_id());
+                if private_seen_ids.is_some() {
+                    let seen_by = self
+                        .server
+                        .seen_by(account_id, id.document_id())
+                        .await
+                        .imap_ctx(&arguments.tag, trc::location!())?;
+                    batch.private_seen(seen_by, self.account_id, true);
+                } else {
+                    keywords.inner.push(Keyword::Seen);
+                    batch
+                        .assert_value(Property::Keywords, &keywords)
+                        .value(Property::Keywords, keywords.inner, F_VALUE)
+                        .value(Property::Keywords, Keyword::Seen, F_BITMAP);
+                }
+                batch.value(Property::Cid, changelog.change_id, F_VALUE);
                 match self.server.write_batch(batch).await {
                     Ok(_) => {
                         changelog.log_update(Collection::Email, id);
@@ -585,6 +630,7 @@ impl<T: SessionStream> SessionData<T> {
                 .collect::<Vec<_>>(),
             Elapsed = op_start.elapsed()
         );
+        self.server.record_interactive_latency(op_start.elapsed());
 
         // Condstore was enabled with this command
         if enabled_condstore {
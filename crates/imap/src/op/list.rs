@@ -169,9 +169,16 @@ impl<T: SessionStream> SessionData<T> {
 
         // Add mailboxes
         let mut added_shared_folder = false;
+        let public_prefix = self
+            .server
+            .core
+            .jmap
+            .public_folders
+            .as_ref()
+            .map(|public_folders| public_folders.name.as_str());
         for account in self.mailboxes.lock().iter() {
             if let Some(prefix) = &account.prefix {
-                if !added_shared_folder {
+                if !added_shared_folder && public_prefix != Some(prefix.as_str()) {
                     if !filter_subscribed
                         && matches_pattern(&patterns, &self.server.core.jmap.shared_folder)
                     {
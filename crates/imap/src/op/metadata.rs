@@ -0,0 +1,377 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use crate::{
+    core::{Session, SessionData},
+    spawn_op,
+};
+use common::{listener::SessionStream, MailboxId, Server};
+use directory::Permission;
+use imap_proto::{
+    protocol::metadata::{Response, SetArguments},
+    receiver::Request,
+    Command, ResponseCode, StatusResponse,
+};
+use jmap::{
+    auth::acl::EffectiveAcl, changes::write::ChangeLog, mailbox::annotations::MailboxAnnotations,
+    mailbox::set::SCHEMA, services::state::StateManager, JmapMethods,
+};
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{
+        acl::Acl, collection::Collection, property::Property, state::StateChange,
+        type_state::DataType, value::Value,
+    },
+};
+use store::{
+    write::{assert::HashedValue, BatchBuilder, Bincode},
+    Serialize,
+};
+use trc::AddContext;
+
+use super::ImapContext;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_get_metadata(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapMetadataGet)?;
+
+        let op_start = Instant::now();
+        let arguments = request.parse_get_metadata(self.version)?;
+        let is_rev2 = self.version.is_rev2();
+        let data = self.state.session_data();
+
+        spawn_op!(data, {
+            // Obtain the annotations visible to this account
+            let (annotations, mailbox) = if !arguments.mailbox_name.is_empty() {
+                let (mailbox, values) = data
+                    .get_metadata_mailbox(&arguments.mailbox_name, Acl::Read)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?;
+                (values.inner.annotations(data.account_id), Some(mailbox))
+            } else {
+                let mut annotations = server_annotations(&data.server, None)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?;
+                annotations.extend(
+                    server_annotations(&data.server, Some(data.account_id))
+                        .await
+                        .imap_ctx(&arguments.tag, trc::location!())?,
+                );
+                (annotations, None)
+            };
+
+            // Filter the requested entries
+            let mut entries = Vec::new();
+            let mut long_entries = 0;
+            for requested in &arguments.entries {
+                let mut found = false;
+                for (entry, value) in &annotations {
+                    if arguments.depth.matches(requested, entry)
+                        && !entries.iter().any(|(e, _)| e == entry)
+                    {
+                        found = true;
+                        if arguments.max_size.map_or(true, |max| value.len() <= max) {
+                            entries.push((entry.clone(), Some(value.clone())));
+                        } else {
+                            long_entries = long_entries.max(value.len());
+                        }
+                    }
+                }
+                if !found {
+                    entries.push((requested.clone(), None));
+                }
+            }
+
+            trc::event!(
+                Imap(trc::ImapEvent::GetMetadata),
+                SpanId = data.session_id,
+                MailboxName = arguments.mailbox_name.clone(),
+                AccountId = mailbox.map(|m| m.account_id),
+                MailboxId = mailbox.map(|m| m.mailbox_id),
+                Total = entries.len(),
+                Elapsed = op_start.elapsed()
+            );
+
+            let mut response = StatusResponse::completed(Command::GetMetadata);
+            if long_entries > 0 {
+                response =
+                    response.with_code(ResponseCode::MetadataLongEntries { size: long_entries });
+            }
+
+            data.write_bytes(
+                response.with_tag(arguments.tag).serialize(
+                    Response {
+                        mailbox_name: arguments.mailbox_name,
+                        entries,
+                    }
+                    .into_bytes(is_rev2),
+                ),
+            )
+            .await
+        })
+    }
+
+    pub async fn handle_set_metadata(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapMetadataSet)?;
+
+        let op_start = Instant::now();
+        let arguments = request.parse_set_metadata(self.version)?;
+        if arguments.mailbox_name.is_empty()
+            && arguments
+                .entries
+                .iter()
+                .any(|(entry, _)| entry.starts_with("/shared/"))
+        {
+            self.assert_has_permission(Permission::ImapMetadataServer)?;
+        }
+        let data = self.state.session_data();
+
+        spawn_op!(data, {
+            let response = data.set_metadata(arguments, op_start).await?;
+
+            data.write_bytes(response.into_bytes()).await
+        })
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    async fn set_metadata(
+        &self,
+        arguments: SetArguments,
+        op_start: Instant,
+    ) -> trc::Result<StatusResponse> {
+        // Validate values
+        let config = &self.server.core.imap;
+        if arguments.entries.iter().any(|(_, value)| {
+            value
+                .as_ref()
+                .map_or(false, |value| value.len() > config.metadata_max_size)
+        }) {
+            return Ok(StatusResponse::no("Annotation value is too large.")
+                .with_code(ResponseCode::MetadataMaxSize {
+                    size: config.metadata_max_size,
+                })
+                .with_tag(arguments.tag));
+        }
+
+        let mailbox = if !arguments.mailbox_name.is_empty() {
+            // Shared annotations require permission to modify the mailbox
+            let (mailbox, values) = self
+                .get_metadata_mailbox(
+                    &arguments.mailbox_name,
+                    if arguments
+                        .entries
+                        .iter()
+                        .any(|(entry, _)| entry.starts_with("/shared/"))
+                    {
+                        Acl::Modify
+                    } else {
+                        Acl::Read
+                    },
+                )
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
+
+            if let Some(value) = values
+                .inner
+                .annotate(self.account_id, arguments.entries.iter().cloned())
+            {
+                let mut new_values = values.inner.clone();
+                new_values.set(Property::Annotations, value.clone());
+                if new_values.annotations(self.account_id).len() > config.metadata_max_entries {
+                    return Ok(too_many_entries(arguments.tag));
+                }
+
+                // Write changes
+                let mut changes = self
+                    .server
+                    .begin_changes(mailbox.account_id)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?;
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(mailbox.account_id)
+                    .with_collection(Collection::Mailbox)
+                    .update_document(mailbox.mailbox_id)
+                    .custom(
+                        ObjectIndexBuilder::new(SCHEMA)
+                            .with_current(values)
+                            .with_changes(
+                                Object::with_capacity(1)
+                                    .with_property(Property::Annotations, value),
+                            ),
+                    );
+                changes.log_update(Collection::Mailbox, mailbox.mailbox_id);
+
+                let change_id = changes.change_id;
+                batch.custom(changes);
+                self.server
+                    .write_batch(batch)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?;
+
+                // Broadcast changes
+                self.server
+                    .broadcast_state_change(
+                        StateChange::new(mailbox.account_id)
+                            .with_change(DataType::Mailbox, change_id),
+                    )
+                    .await;
+
+                // Update mailbox cache
+                for account in self.mailboxes.lock().iter_mut() {
+                    if account.account_id == mailbox.account_id {
+                        account.state_mailbox = change_id.into();
+                        break;
+                    }
+                }
+            }
+
+            Some(mailbox)
+        } else {
+            for account_id in [None, Some(self.account_id)] {
+                let is_private = account_id.is_some();
+                let changes = arguments
+                    .entries
+                    .iter()
+                    .filter(|(entry, _)| entry.starts_with("/private/") == is_private)
+                    .collect::<Vec<_>>();
+                if changes.is_empty() {
+                    continue;
+                }
+
+                let mut annotations = server_annotations(&self.server, account_id)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?;
+                for (entry, value) in changes {
+                    annotations.retain(|(e, _)| e != entry);
+                    if let Some(value) = value {
+                        annotations.push((entry.clone(), value.clone()));
+                    }
+                }
+                if annotations.len() > config.metadata_max_entries {
+                    return Ok(too_many_entries(arguments.tag));
+                }
+
+                set_server_annotations(&self.server, account_id, annotations)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?;
+            }
+
+            None
+        };
+
+        trc::event!(
+            Imap(trc::ImapEvent::SetMetadata),
+            SpanId = self.session_id,
+            MailboxName = arguments.mailbox_name,
+            AccountId = mailbox.map(|m| m.account_id),
+            MailboxId = mailbox.map(|m| m.mailbox_id),
+            Details = arguments
+                .entries
+                .into_iter()
+                .map(|(entry, _)| trc::Value::String(entry))
+                .collect::<Vec<_>>(),
+            Elapsed = op_start.elapsed()
+        );
+
+        Ok(StatusResponse::completed(Command::SetMetadata).with_tag(arguments.tag))
+    }
+
+    async fn get_metadata_mailbox(
+        &self,
+        mailbox_name: &str,
+        acl: Acl,
+    ) -> trc::Result<(MailboxId, HashedValue<Object<Value>>)> {
+        let mailbox = self.get_mailbox_by_name(mailbox_name).ok_or_else(|| {
+            trc::ImapEvent::Error
+                .into_err()
+                .details("Mailbox does not exist.")
+                .code(ResponseCode::NonExistent)
+        })?;
+        let values = self
+            .server
+            .get_property::<HashedValue<Object<Value>>>(
+                mailbox.account_id,
+                Collection::Mailbox,
+                mailbox.mailbox_id,
+                Property::Value,
+            )
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| {
+                trc::ImapEvent::Error
+                    .into_err()
+                    .details("Mailbox does not exist.")
+                    .code(ResponseCode::NonExistent)
+            })?;
+
+        let access_token = self.get_access_token().await.caused_by(trc::location!())?;
+        if access_token.is_member(mailbox.account_id)
+            || access_token.delegated_acl(mailbox.account_id).contains(acl)
+            || values.inner.effective_acl(&access_token).contains(acl)
+        {
+            Ok((mailbox, values))
+        } else {
+            Err(trc::ImapEvent::Error
+                .into_err()
+                .details("You do not have enough permissions to perform this operation.")
+                .code(ResponseCode::NoPerm))
+        }
+    }
+}
+
+fn too_many_entries(tag: String) -> StatusResponse {
+    StatusResponse::no("Too many annotations.")
+        .with_code(ResponseCode::MetadataTooMany)
+        .with_tag(tag)
+}
+
+// Server annotations are kept in the lookup store, shared entries under a
+// single key and private entries under a key for each account.
+async fn server_annotations(
+    server: &Server,
+    account_id: Option<u32>,
+) -> trc::Result<Vec<(String, String)>> {
+    server
+        .lookup_store()
+        .key_get::<Bincode<Vec<(String, String)>>>(server_annotations_key(account_id))
+        .await
+        .caused_by(trc::location!())
+        .map(|annotations| {
+            annotations
+                .map(|annotations| annotations.inner)
+                .unwrap_or_default()
+        })
+}
+
+async fn set_server_annotations(
+    server: &Server,
+    account_id: Option<u32>,
+    annotations: Vec<(String, String)>,
+) -> trc::Result<()> {
+    let key = server_annotations_key(account_id);
+    let lookup = server.lookup_store();
+    if !annotations.is_empty() {
+        lookup
+            .key_set(key, Bincode::new(annotations).serialize(), None)
+            .await
+    } else {
+        lookup.key_delete(key).await
+    }
+    .caused_by(trc::location!())
+}
+
+fn server_annotations_key(account_id: Option<u32>) -> Vec<u8> {
+    match account_id {
+        Some(account_id) => format!("imap-metadata:{account_id}").into_bytes(),
+        None => b"imap-metadata:shared".to_vec(),
+    }
+}
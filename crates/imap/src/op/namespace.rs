@@ -24,16 +24,44 @@ impl<T: SessionStream> Session<T> {
             Elapsed = trc::Value::Duration(0)
         );
 
+        // Public folders are listed under their own namespace
+        let public_prefix = self
+            .server
+            .core
+            .jmap
+            .public_folders
+            .as_ref()
+            .map(|public_folders| public_folders.name.as_str());
+        let (has_shared, has_public) = self
+            .state
+            .session_data()
+            .mailboxes
+            .lock()
+            .iter()
+            .skip(1)
+            .fold((false, false), |(has_shared, has_public), account| {
+                if account.prefix.is_some() && account.prefix.as_deref() == public_prefix {
+                    (has_shared, true)
+                } else {
+                    (true, has_public)
+                }
+            });
+
         self.write_bytes(
             StatusResponse::completed(Command::Namespace)
                 .with_tag(request.tag)
                 .serialize(
                     Response {
-                        shared_prefix: if self.state.session_data().mailboxes.lock().len() > 1 {
+                        shared_prefix: if has_shared {
                             self.server.core.jmap.shared_folder.clone().into()
                         } else {
                             None
                         },
+                        public_prefix: if has_public {
+                            public_prefix.map(String::from)
+                        } else {
+                            None
+                        },
                     }
                     .serialize(),
                 ),
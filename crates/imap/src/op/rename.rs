@@ -123,6 +123,9 @@ impl<T: SessionStream> SessionData<T> {
                 .inner
                 .effective_acl(&access_token)
                 .contains(Acl::Modify)
+            && !access_token
+                .delegated_acl(params.account_id)
+                .contains(Acl::Modify)
         {
             return Err(trc::ImapEvent::Error
                 .into_err()
@@ -14,9 +14,9 @@ use imap_proto::{
         Sequence,
     },
     receiver::Request,
-    Command, StatusResponse,
+    Command, ResponseCode, StatusResponse,
 };
-use jmap::{changes::get::ChangesLookup, JmapMethods};
+use jmap::{changes::get::ChangesLookup, email::seen::PrivateSeen, JmapMethods};
 use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
 use mail_parser::HeaderName;
 use nlp::language::Language;
@@ -272,6 +272,21 @@ impl<T: SessionStream> SessionData<T> {
             .unwrap_or_default();
         filters.push(query::Filter::is_in_set(message_ids.clone()));
 
+        // Obtain the messages seen by this user if the \Seen state is private
+        let private_seen_ids = if self.has_private_seen(mailbox.id.account_id) {
+            Some(
+                self.server
+                    .private_seen_ids(mailbox.id.account_id, self.account_id)
+                    .await?,
+            )
+        } else {
+            None
+        };
+        let seen_filter = || match &private_seen_ids {
+            Some(private_seen_ids) => query::Filter::is_in_set(private_seen_ids.clone()),
+            None => query::Filter::is_in_bitmap(Property::Keywords, Keyword::Seen),
+        };
+
         // Convert query
         let mut include_highest_modseq = false;
         for filter_group in imap_filter.into_filter_group() {
@@ -416,11 +431,22 @@ impl<T: SessionStream> SessionData<T> {
                         }
                     }
 
-                    filters.push(query::Filter::is_in_set(
-                        self.server
-                            .fts_filter(mailbox.id.account_id, Collection::Email, fts_filters)
-                            .await?,
-                    ));
+                    let result = self
+                        .server
+                        .fts_filter(mailbox.id.account_id, Collection::Email, fts_filters)
+                        .await?;
+                    if result.degraded {
+                        // Let the client know that the results might be incomplete
+                        self.write_bytes(
+                            StatusResponse::ok(
+                                "Full-text search is unavailable, results might be incomplete.",
+                            )
+                            .with_code(ResponseCode::Alert)
+                            .into_bytes(),
+                        )
+                        .await?;
+                    }
+                    filters.push(query::Filter::is_in_set(result.documents));
                 }
                 FilterGroup::Store(cond) => match cond {
                     search::Filter::Sequence(sequence, uid_filter) => {
@@ -478,10 +504,10 @@ impl<T: SessionStream> SessionData<T> {
                         ));
                     }
                     search::Filter::Keyword(keyword) => {
-                        filters.push(query::Filter::is_in_bitmap(
-                            Property::Keywords,
-                            Keyword::from(keyword),
-                        ));
+                        filters.push(match Keyword::from(keyword) {
+                            Keyword::Seen => seen_filter(),
+                            keyword => query::Filter::is_in_bitmap(Property::Keywords, keyword),
+                        });
                     }
                     search::Filter::Larger(size) => {
                         filters.push(query::Filter::gt(Property::Size, size));
@@ -496,10 +522,7 @@ impl<T: SessionStream> SessionData<T> {
                         filters.push(query::Filter::End);
                     }
                     search::Filter::Seen => {
-                        filters.push(query::Filter::is_in_bitmap(
-                            Property::Keywords,
-                            Keyword::Seen,
-                        ));
+                        filters.push(seen_filter());
                     }
                     search::Filter::SentBefore(date) => {
                         filters.push(query::Filter::lt(Property::SentAt, date as u64));
@@ -553,18 +576,15 @@ impl<T: SessionStream> SessionData<T> {
                     }
                     search::Filter::Unkeyword(keyword) => {
                         filters.push(query::Filter::Not);
-                        filters.push(query::Filter::is_in_bitmap(
-                            Property::Keywords,
-                            Keyword::from(keyword),
-                        ));
+                        filters.push(match Keyword::from(keyword) {
+                            Keyword::Seen => seen_filter(),
+                            keyword => query::Filter::is_in_bitmap(Property::Keywords, keyword),
+                        });
                         filters.push(query::Filter::End);
                     }
                     search::Filter::Unseen => {
                         filters.push(query::Filter::Not);
-                        filters.push(query::Filter::is_in_bitmap(
-                            Property::Keywords,
-                            Keyword::Seen,
-                        ));
+                        filters.push(seen_filter());
                         filters.push(query::Filter::End);
                     }
                     search::Filter::And => {
@@ -610,6 +630,21 @@ impl<T: SessionStream> SessionData<T> {
                             now().saturating_sub(secs as u64),
                         ));
                     }
+                    search::Filter::SavedBefore(date) => {
+                        filters.push(query::Filter::lt(Property::SavedAt, date as u64));
+                    }
+                    search::Filter::SavedOn(date) => {
+                        filters.push(query::Filter::And);
+                        filters.push(query::Filter::ge(Property::SavedAt, date as u64));
+                        filters.push(query::Filter::lt(Property::SavedAt, (date + 86400) as u64));
+                        filters.push(query::Filter::End);
+                    }
+                    search::Filter::SavedSince(date) => {
+                        filters.push(query::Filter::ge(Property::SavedAt, date as u64));
+                    }
+                    search::Filter::SaveDateSupported => {
+                        filters.push(query::Filter::is_in_set(message_ids.clone()));
+                    }
                     search::Filter::ModSeq((modseq, _)) => {
                         let mut set = RoaringBitmap::new();
                         for change in self
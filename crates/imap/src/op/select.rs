@@ -35,7 +35,8 @@ impl<T: SessionStream> Session<T> {
         })?;
 
         let op_start = Instant::now();
-        let is_select = request.command == Command::Select;
+        // Mailboxes are always opened read-only while the server is in read-only mode
+        let is_select = request.command == Command::Select && !self.server.is_read_only();
         let command = request.command;
         let arguments = request.parse_select(self.version)?;
         let data = self.state.session_data();
@@ -19,7 +19,7 @@ use imap_proto::{
     receiver::Request,
     Command, ResponseCode, StatusResponse,
 };
-use jmap::JmapMethods;
+use jmap::{email::seen::PrivateSeen, mailbox::counters::MailboxCounterCache, JmapMethods};
 use jmap_proto::{
     object::Object,
     types::{collection::Collection, id::Id, keyword::Keyword, property::Property, value::Value},
@@ -93,6 +93,13 @@ impl<T: SessionStream> SessionData<T> {
                     .map_or(false, |(base_name, path)| {
                         base_name == self.server.core.jmap.shared_folder && !path.contains('/')
                     })
+                || self
+                    .server
+                    .core
+                    .jmap
+                    .public_folders
+                    .as_ref()
+                    .map_or(false, |public_folders| public_folders.name == mailbox_name)
             {
                 Ok(StatusItem {
                     mailbox_name,
@@ -230,7 +237,13 @@ impl<T: SessionStream> SessionData<T> {
 
             for item in items_update {
                 let result = match item {
-                    Status::Messages => mailbox_message_ids.as_ref().map(|v| v.len()).unwrap_or(0),
+                    Status::Messages => {
+                        self.server
+                            .mailbox_counters(mailbox.account_id, mailbox.mailbox_id)
+                            .await
+                            .caused_by(trc::location!())?
+                            .total_emails
+                    }
                     Status::UidNext => {
                         (self
                             .server
@@ -267,26 +280,23 @@ impl<T: SessionStream> SessionData<T> {
                                 .document_id(mailbox.mailbox_id)
                         })?,
                     Status::Unseen => {
-                        if let (Some(message_ids), Some(mailbox_message_ids)) =
+                        if !self.has_private_seen(mailbox.account_id) {
+                            self.server
+                                .mailbox_counters(mailbox.account_id, mailbox.mailbox_id)
+                                .await
+                                .caused_by(trc::location!())?
+                                .unread_emails
+                        } else if let (Some(message_ids), Some(mailbox_message_ids)) =
                             (&message_ids, &mailbox_message_ids)
                         {
-                            if let Some(mut seen) = self
+                            let mut unseen = self
                                 .server
-                                .get_tag(
-                                    mailbox.account_id,
-                                    Collection::Email,
-                                    Property::Keywords,
-                                    Keyword::Seen,
-                                )
+                                .private_seen_ids(mailbox.account_id, self.account_id)
                                 .await
-                                .caused_by(trc::location!())?
-                            {
-                                seen ^= message_ids;
-                                seen &= mailbox_message_ids.as_ref();
-                                seen.len()
-                            } else {
-                                mailbox_message_ids.len()
-                            }
+                                .caused_by(trc::location!())?;
+                            unseen ^= message_ids;
+                            unseen &= mailbox_message_ids.as_ref();
+                            unseen.len()
                         } else {
                             0
                         }
@@ -24,7 +24,10 @@ use imap_proto::{
 };
 use jmap::{
     changes::{get::ChangesLookup, write::ChangeLog},
-    email::set::TagManager,
+    email::{
+        seen::{replace_seen, PrivateSeen, PrivateSeenBatch},
+        set::TagManager,
+    },
     mailbox::UidMailbox,
     services::state::StateManager,
     JmapMethods,
@@ -191,6 +194,7 @@ impl<T: SessionStream> SessionData<T> {
             .iter()
             .map(|k| Keyword::from(k.clone()))
             .collect::<Vec<_>>();
+        let is_private_seen = self.has_private_seen(account_id);
         let mut changelog = ChangeLogBuilder::new();
         let mut changed_mailboxes = AHashSet::new();
         'outer: for (id, imap_id) in &ids {
@@ -217,46 +221,88 @@ impl<T: SessionStream> SessionData<T> {
                     continue 'outer;
                 };
 
-                // Apply changes
+                // Obtain the users that have seen this message
+                let seen_by = if is_private_seen {
+                    self.server
+                        .seen_by(account_id, *id)
+                        .await
+                        .imap_ctx(response.tag.as_ref().unwrap(), trc::location!())?
+                } else {
+                    None
+                };
+                let mut is_seen = seen_by.as_ref().map_or(false, |seen_by| {
+                    seen_by.inner.inner.contains(&self.account_id)
+                });
+
+                // Apply changes, the \Seen flag is kept apart when private
+                let mut set_seen = None;
                 match arguments.operation {
                     Operation::Set => {
-                        keywords.set(set_keywords.clone());
+                        if is_private_seen {
+                            set_seen = Some(set_keywords.contains(&Keyword::Seen));
+                            keywords.set(replace_seen(
+                                &set_keywords,
+                                keywords.current().contains(&Keyword::Seen),
+                            ));
+                        } else {
+                            keywords.set(set_keywords.clone());
+                        }
                     }
                     Operation::Add => {
                         for keyword in &set_keywords {
-                            keywords.update(keyword.clone(), true);
+                            if is_private_seen && keyword == &Keyword::Seen {
+                                set_seen = Some(true);
+                            } else {
+                                keywords.update(keyword.clone(), true);
+                            }
                         }
                     }
                     Operation::Clear => {
                         for keyword in &set_keywords {
-                            keywords.update(keyword.clone(), false);
+                            if is_private_seen && keyword == &Keyword::Seen {
+                                set_seen = Some(false);
+                            } else {
+                                keywords.update(keyword.clone(), false);
+                            }
                         }
                     }
                 }
 
-                if keywords.has_changes() {
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(account_id)
+                    .with_collection(Collection::Email)
+                    .update_document(*id);
+                let private_seen_changed = if let Some(set_seen) = set_seen {
+                    is_seen = set_seen;
+                    batch.private_seen(seen_by, self.account_id, set_seen)
+                } else {
+                    false
+                };
+
+                if keywords.has_changes() || private_seen_changed {
                     // Convert keywords to flags
-                    let seen_changed = keywords
-                        .changed_tags()
-                        .any(|keyword| keyword == &Keyword::Seen);
+                    let seen_changed = private_seen_changed
+                        || keywords
+                            .changed_tags()
+                            .any(|keyword| keyword == &Keyword::Seen);
                     let flags = if !arguments.is_silent {
-                        keywords
-                            .current()
-                            .iter()
-                            .cloned()
-                            .map(Flag::from)
-                            .collect::<Vec<_>>()
+                        if is_private_seen {
+                            replace_seen(keywords.current(), is_seen)
+                        } else {
+                            keywords.current().to_vec()
+                        }
+                        .into_iter()
+                        .map(Flag::from)
+                        .collect::<Vec<_>>()
                     } else {
                         vec![]
                     };
 
                     // Write changes
-                    let mut batch = BatchBuilder::new();
-                    batch
-                        .with_account_id(account_id)
-                        .with_collection(Collection::Email)
-                        .update_document(*id);
-                    keywords.update_batch(&mut batch, Property::Keywords);
+                    if keywords.has_changes() {
+                        keywords.update_batch(&mut batch, Property::Keywords);
+                    }
                     if changelog.change_id == u64::MAX {
                         changelog.change_id = self
                             .server
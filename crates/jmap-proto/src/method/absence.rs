@@ -0,0 +1,188 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    parser::{json::Parser, Ignore, JsonObjectParser, Token},
+    request::RequestProperty,
+    types::{date::UTCDate, id::Id},
+};
+
+#[derive(Debug, Clone)]
+pub struct GetAbsenceRequest {
+    pub account_id: Id,
+}
+
+#[derive(Debug, Clone)]
+pub struct SetAbsenceRequest {
+    pub account_id: Id,
+    pub windows: Vec<AbsenceWindow>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct AbsenceResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    #[serde(rename = "windows")]
+    pub windows: Vec<AbsenceWindow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AbsenceWindow {
+    #[serde(rename = "id")]
+    pub id: String,
+
+    #[serde(rename = "fromDate")]
+    pub from_date: UTCDate,
+
+    #[serde(rename = "toDate")]
+    pub to_date: UTCDate,
+
+    #[serde(rename = "forwardTo")]
+    pub forward_to: String,
+
+    #[serde(rename = "fileInto")]
+    pub file_into: Option<Id>,
+
+    #[serde(rename = "senderContains")]
+    pub sender_contains: Option<String>,
+
+    #[serde(rename = "subjectContains")]
+    pub subject_contains: Option<String>,
+}
+
+impl JsonObjectParser for GetAbsenceRequest {
+    fn parse(parser: &mut Parser<'_>) -> trc::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = GetAbsenceRequest {
+            account_id: Id::default(),
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+impl JsonObjectParser for SetAbsenceRequest {
+    fn parse(parser: &mut Parser<'_>) -> trc::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = SetAbsenceRequest {
+            account_id: Id::default(),
+            windows: Vec::new(),
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                0x0073_776f_646e_6977 if !key.is_ref => {
+                    parser
+                        .next_token::<Ignore>()?
+                        .assert_jmap(Token::ArrayStart)?;
+                    request.windows = parse_windows(parser)?;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+fn parse_windows(parser: &mut Parser) -> trc::Result<Vec<AbsenceWindow>> {
+    let mut windows = vec![];
+
+    loop {
+        match parser.next_token::<String>()? {
+            Token::DictStart => {
+                let mut window = AbsenceWindow {
+                    id: String::new(),
+                    from_date: UTCDate::from_timestamp(0),
+                    to_date: UTCDate::from_timestamp(0),
+                    forward_to: String::new(),
+                    file_into: None,
+                    sender_contains: None,
+                    subject_contains: None,
+                };
+                while let Some(key) = parser.next_dict_key::<u128>()? {
+                    match key {
+                        0x6469 => {
+                            window.id = parser
+                                .next_token::<String>()?
+                                .unwrap_string_or_null("id")?
+                                .unwrap_or_default();
+                        }
+                        0x6574_6144_6d6f_7266 => {
+                            window.from_date =
+                                parser.next_token::<UTCDate>()?.unwrap_string("fromDate")?;
+                        }
+                        0x6574_6144_6f74 => {
+                            window.to_date =
+                                parser.next_token::<UTCDate>()?.unwrap_string("toDate")?;
+                        }
+                        0x006f_5464_7261_7772_6f66 => {
+                            window.forward_to =
+                                parser.next_token::<String>()?.unwrap_string("forwardTo")?;
+                        }
+                        0x6f74_6e49_656c_6966 => {
+                            window.file_into = parser
+                                .next_token::<Id>()?
+                                .unwrap_string_or_null("fileInto")?;
+                        }
+                        0x736e_6961_746e_6f43_7265_646e_6573 => {
+                            window.sender_contains = parser
+                                .next_token::<String>()?
+                                .unwrap_string_or_null("senderContains")?;
+                        }
+                        0x0073_6e69_6174_6e6f_4374_6365_6a62_7573 => {
+                            window.subject_contains = parser
+                                .next_token::<String>()?
+                                .unwrap_string_or_null("subjectContains")?;
+                        }
+                        _ => {
+                            parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                        }
+                    }
+                }
+                windows.push(window);
+            }
+            Token::Comma => (),
+            Token::ArrayEnd => {
+                break;
+            }
+            token => {
+                return Err(token.error("windows", "object"));
+            }
+        }
+    }
+
+    Ok(windows)
+}
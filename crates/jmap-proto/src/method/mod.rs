@@ -6,6 +6,7 @@
 
 use ahash::AHashMap;
 
+pub mod absence;
 pub mod changes;
 pub mod copy;
 pub mod get;
@@ -16,6 +17,7 @@ pub mod query;
 pub mod query_changes;
 pub mod search_snippet;
 pub mod set;
+pub mod share;
 pub mod upload;
 pub mod validate;
 
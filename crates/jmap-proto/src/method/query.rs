@@ -52,6 +52,10 @@ pub struct QueryResponse {
     #[serde(rename = "limit")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<usize>,
+
+    #[serde(rename = "fullTextDegraded")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_text_degraded: Option<bool>,
 }
 
 #[derive(Clone, Debug)]
@@ -93,6 +97,9 @@ pub enum Filter {
     SentAfter(UTCDate),
     InThread(Id),
     ParentId(Option<Id>),
+    AncestorId(Id),
+    MaxDepth(u32),
+    NamePrefix(String),
     Role(Option<String>),
     HasAnyRole(bool),
     IsSubscribed(bool),
@@ -402,6 +409,18 @@ pub fn parse_filter(parser: &mut Parser) -> trc::Result<Vec<Filter>> {
                                 .next_token::<Id>()?
                                 .unwrap_string_or_null("parentId")?,
                         ),
+                        (0x6449_726f_7473_6563_6e61, _) => Filter::AncestorId(
+                            parser.next_token::<Id>()?.unwrap_string("ancestorId")?,
+                        ),
+                        (0x6874_7065_4478_616d, _) => Filter::MaxDepth(
+                            parser
+                                .next_token::<String>()?
+                                .unwrap_uint_or_null("maxDepth")?
+                                .unwrap_or_default() as u32,
+                        ),
+                        (0x7869_6665_7250_656d_616e, _) => Filter::NamePrefix(
+                            parser.next_token::<String>()?.unwrap_string("namePrefix")?,
+                        ),
                         (0x656c_6f72, _) => Filter::Role(
                             parser
                                 .next_token::<String>()?
@@ -622,6 +641,9 @@ impl Display for Filter {
             Filter::SentAfter(_) => "sentAfter",
             Filter::InThread(_) => "inThread",
             Filter::ParentId(_) => "parentId",
+            Filter::AncestorId(_) => "ancestorId",
+            Filter::MaxDepth(_) => "maxDepth",
+            Filter::NamePrefix(_) => "namePrefix",
             Filter::Role(_) => "role",
             Filter::HasAnyRole(_) => "hasAnyRole",
             Filter::IsSubscribed(_) => "isSubscribed",
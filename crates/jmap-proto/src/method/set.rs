@@ -325,10 +325,9 @@ impl JsonObjectParser for Object<SetValue> {
                         parser.next_token()?,
                         parser,
                     )?),
-                    Property::Parameters => SetValue::Value(Value::parse::<String, String>(
-                        parser.next_token()?,
-                        parser,
-                    )?),
+                    Property::Parameters | Property::Annotations => SetValue::Value(
+                        Value::parse::<String, String>(parser.next_token()?, parser)?,
+                    ),
                     Property::Members => SetValue::Value(Value::parse::<ObjectProperty, Id>(
                         parser.next_token()?,
                         parser,
@@ -0,0 +1,92 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use utils::map::vec_map::VecMap;
+
+use crate::{
+    parser::{json::Parser, Ignore, JsonObjectParser, Token},
+    request::RequestProperty,
+    types::{date::UTCDate, id::Id},
+};
+
+#[derive(Debug, Clone)]
+pub struct ShareEmailRequest {
+    pub account_id: Id,
+    pub email_ids: Vec<Id>,
+    pub expires_in: Option<u64>,
+    pub revoke: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ShareEmailResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    #[serde(rename = "created")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub created: VecMap<Id, ShareLink>,
+
+    #[serde(rename = "notFound")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub not_found: Vec<Id>,
+
+    #[serde(rename = "revoked")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub revoked: Vec<String>,
+
+    #[serde(rename = "notRevoked")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub not_revoked: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ShareLink {
+    pub id: String,
+    pub url: String,
+    pub expires: UTCDate,
+}
+
+impl JsonObjectParser for ShareEmailRequest {
+    fn parse(parser: &mut Parser<'_>) -> trc::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = ShareEmailRequest {
+            account_id: Id::default(),
+            email_ids: Vec::new(),
+            expires_in: None,
+            revoke: Vec::new(),
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                0x7364_496c_6961_6d65 if !key.is_ref => {
+                    request.email_ids = <Vec<Id>>::parse(parser)?;
+                }
+                0x006e_4973_6572_6970_7865 if !key.is_ref => {
+                    request.expires_in = parser
+                        .next_token::<Ignore>()?
+                        .unwrap_uint_or_null("expiresIn")?;
+                }
+                0x656b_6f76_6572 if !key.is_ref => {
+                    request.revoke = <Vec<String>>::parse(parser)?;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
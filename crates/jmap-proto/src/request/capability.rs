@@ -71,6 +71,8 @@ pub enum Capability {
     Blob = 1 << 8,
     #[serde(rename(serialize = "urn:ietf:params:jmap:quota"))]
     Quota = 1 << 9,
+    #[serde(rename(serialize = "urn:ietf:params:jmap:smimeverify"))]
+    SmimeVerify = 1 << 10,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -83,6 +85,7 @@ pub enum Capabilities {
     WebSocket(WebSocketCapabilities),
     SieveAccount(SieveAccountCapabilities),
     SieveSession(SieveSessionCapabilities),
+    VacationResponse(VacationResponseCapabilities),
     Blob(BlobCapabilities),
     Empty(EmptyCapabilities),
 }
@@ -121,6 +124,12 @@ pub struct SieveSessionCapabilities {
     pub implementation: &'static str,
 }
 
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VacationResponseCapabilities {
+    #[serde(rename(serialize = "sieveStrategy"))]
+    pub sieve_strategy: &'static str,
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SieveAccountCapabilities {
     #[serde(rename(serialize = "maxSizeScriptName"))]
@@ -340,6 +349,7 @@ impl JsonObjectParser for Capability {
                 0x0065_7665_6973 => Ok(Capability::Sieve),
                 0x626f_6c62 => Ok(Capability::Blob),
                 0x0061_746f_7571 => Ok(Capability::Quota),
+                0x0079_6669_7265_7665_6d69_6d73 => Ok(Capability::SmimeVerify),
                 _ => Err(parser.error_capability()),
             },
             Err(err) if err.is_jmap_method_error() => Err(parser.error_capability()),
@@ -29,6 +29,7 @@ pub enum MethodObject {
     SieveScript,
     Principal,
     Quota,
+    Absence,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,6 +45,7 @@ pub enum MethodFunction {
     Validate,
     Lookup,
     Upload,
+    Share,
     Echo,
 }
 
@@ -96,6 +98,7 @@ impl JsonObjectParser for MethodName {
                 0x0074_7069_7263_5365_7665_6953 => MethodObject::SieveScript,
                 0x006c_6170_6963_6e69_7250 => MethodObject::Principal,
                 0x0061_746f_7551 => MethodObject::Quota,
+                0x0065_636e_6573_6241 => MethodObject::Absence,
                 0x6572_6f43 => MethodObject::Core,
                 _ => return Err(parser.error_value()),
             },
@@ -111,6 +114,7 @@ impl JsonObjectParser for MethodName {
                 0x6574_6164_696c_6176 => MethodFunction::Validate,
                 0x7075_6b6f_6f6c => MethodFunction::Lookup,
                 0x6461_6f6c_7075 => MethodFunction::Upload,
+                0x0065_7261_6873 => MethodFunction::Share,
                 0x6f68_6365 => MethodFunction::Echo,
                 _ => return Err(parser.error_value()),
             },
@@ -158,6 +162,7 @@ impl MethodName {
             (MethodFunction::Copy, MethodObject::Email) => "Email/copy",
             (MethodFunction::Import, MethodObject::Email) => "Email/import",
             (MethodFunction::Parse, MethodObject::Email) => "Email/parse",
+            (MethodFunction::Share, MethodObject::Email) => "Email/share",
 
             (MethodFunction::Get, MethodObject::SearchSnippet) => "SearchSnippet/get",
 
@@ -190,6 +195,9 @@ impl MethodName {
             (MethodFunction::Query, MethodObject::Quota) => "Quota/query",
             (MethodFunction::QueryChanges, MethodObject::Quota) => "Quota/queryChanges",
 
+            (MethodFunction::Get, MethodObject::Absence) => "Absence/get",
+            (MethodFunction::Set, MethodObject::Absence) => "Absence/set",
+
             (MethodFunction::Get, MethodObject::Blob) => "Blob/get",
             (MethodFunction::Copy, MethodObject::Blob) => "Blob/copy",
             (MethodFunction::Lookup, MethodObject::Blob) => "Blob/lookup",
@@ -217,6 +225,7 @@ impl Display for MethodObject {
             MethodObject::Thread => "Thread",
             MethodObject::Email => "Email",
             MethodObject::Quota => "Quota",
+            MethodObject::Absence => "Absence",
         })
     }
 }
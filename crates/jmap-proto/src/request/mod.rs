@@ -18,6 +18,7 @@ use std::{
 
 use crate::{
     method::{
+        absence::{GetAbsenceRequest, SetAbsenceRequest},
         changes::ChangesRequest,
         copy::{self, CopyBlobRequest, CopyRequest},
         get::{self, GetRequest},
@@ -28,6 +29,7 @@ use crate::{
         query_changes::QueryChangesRequest,
         search_snippet::GetSearchSnippetRequest,
         set::{self, SetRequest},
+        share::ShareEmailRequest,
         upload::BlobUploadRequest,
         validate::ValidateSieveScriptRequest,
     },
@@ -66,6 +68,9 @@ pub enum RequestMethod {
     CopyBlob(CopyBlobRequest),
     ImportEmail(ImportEmailRequest),
     ParseEmail(ParseEmailRequest),
+    ShareEmail(ShareEmailRequest),
+    GetAbsence(GetAbsenceRequest),
+    SetAbsence(SetAbsenceRequest),
     QueryChanges(QueryChangesRequest),
     Query(QueryRequest<query::RequestArguments>),
     SearchSnippet(GetSearchSnippetRequest),
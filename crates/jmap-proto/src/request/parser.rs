@@ -8,6 +8,7 @@ use std::collections::HashMap;
 
 use crate::{
     method::{
+        absence::{GetAbsenceRequest, SetAbsenceRequest},
         changes::ChangesRequest,
         copy::{CopyBlobRequest, CopyRequest},
         get::GetRequest,
@@ -18,6 +19,7 @@ use crate::{
         query_changes::QueryChangesRequest,
         search_snippet::GetSearchSnippetRequest,
         set::SetRequest,
+        share::ShareEmailRequest,
         upload::BlobUploadRequest,
         validate::ValidateSieveScriptRequest,
     },
@@ -134,6 +136,12 @@ impl Request {
                                 | MethodObject::Quota
                                 | MethodObject::Blob,
                             ) => GetRequest::parse(parser).map(RequestMethod::Get),
+                            (MethodFunction::Get, MethodObject::Absence) => {
+                                GetAbsenceRequest::parse(parser).map(RequestMethod::GetAbsence)
+                            }
+                            (MethodFunction::Set, MethodObject::Absence) => {
+                                SetAbsenceRequest::parse(parser).map(RequestMethod::SetAbsence)
+                            }
                             (MethodFunction::Get, MethodObject::SearchSnippet) => {
                                 GetSearchSnippetRequest::parse(parser)
                                     .map(RequestMethod::SearchSnippet)
@@ -168,6 +176,9 @@ impl Request {
                             (MethodFunction::Parse, MethodObject::Email) => {
                                 ParseEmailRequest::parse(parser).map(RequestMethod::ParseEmail)
                             }
+                            (MethodFunction::Share, MethodObject::Email) => {
+                                ShareEmailRequest::parse(parser).map(RequestMethod::ShareEmail)
+                            }
                             (MethodFunction::Validate, MethodObject::SieveScript) => {
                                 ValidateSieveScriptRequest::parse(parser)
                                     .map(RequestMethod::ValidateScript)
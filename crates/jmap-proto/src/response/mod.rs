@@ -12,6 +12,7 @@ use std::collections::HashMap;
 use crate::{
     error::method::MethodErrorWrapper,
     method::{
+        absence::AbsenceResponse,
         changes::ChangesResponse,
         copy::{CopyBlobResponse, CopyResponse},
         get::GetResponse,
@@ -22,6 +23,7 @@ use crate::{
         query_changes::QueryChangesResponse,
         search_snippet::GetSearchSnippetResponse,
         set::SetResponse,
+        share::ShareEmailResponse,
         upload::BlobUploadResponse,
         validate::ValidateSieveScriptResponse,
     },
@@ -41,6 +43,8 @@ pub enum ResponseMethod {
     CopyBlob(CopyBlobResponse),
     ImportEmail(ImportEmailResponse),
     ParseEmail(ParseEmailResponse),
+    ShareEmail(ShareEmailResponse),
+    Absence(AbsenceResponse),
     QueryChanges(QueryChangesResponse),
     Query(QueryResponse),
     SearchSnippet(GetSearchSnippetResponse),
@@ -154,6 +158,18 @@ impl From<ParseEmailResponse> for ResponseMethod {
     }
 }
 
+impl From<ShareEmailResponse> for ResponseMethod {
+    fn from(share_email: ShareEmailResponse) -> Self {
+        ResponseMethod::ShareEmail(share_email)
+    }
+}
+
+impl From<AbsenceResponse> for ResponseMethod {
+    fn from(absence: AbsenceResponse) -> Self {
+        ResponseMethod::Absence(absence)
+    }
+}
+
 impl From<QueryChangesResponse> for ResponseMethod {
     fn from(query_changes: QueryChangesResponse) -> Self {
         ResponseMethod::QueryChanges(query_changes)
@@ -60,7 +60,23 @@ impl JsonObjectParser for Acl {
 }
 
 impl Acl {
-    fn as_str(&self) -> &'static str {
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "read" => Some(Acl::Read),
+            "modify" => Some(Acl::Modify),
+            "delete" => Some(Acl::Delete),
+            "readItems" => Some(Acl::ReadItems),
+            "addItems" => Some(Acl::AddItems),
+            "modifyItems" => Some(Acl::ModifyItems),
+            "removeItems" => Some(Acl::RemoveItems),
+            "createChild" => Some(Acl::CreateChild),
+            "administer" => Some(Acl::Administer),
+            "submit" => Some(Acl::Submit),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
         match self {
             Acl::Read => "read",
             Acl::Modify => "modify",
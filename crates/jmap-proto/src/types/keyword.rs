@@ -306,6 +306,24 @@ impl Keyword {
             Keyword::Other(string) => Err(string),
         }
     }
+
+    pub fn try_from_id(id: u32) -> Option<Self> {
+        match id as usize {
+            SEEN => Some(Keyword::Seen),
+            DRAFT => Some(Keyword::Draft),
+            FLAGGED => Some(Keyword::Flagged),
+            ANSWERED => Some(Keyword::Answered),
+            RECENT => Some(Keyword::Recent),
+            IMPORTANT => Some(Keyword::Important),
+            PHISHING => Some(Keyword::Phishing),
+            JUNK => Some(Keyword::Junk),
+            NOTJUNK => Some(Keyword::NotJunk),
+            DELETED => Some(Keyword::Deleted),
+            FORWARDED => Some(Keyword::Forwarded),
+            MDN_SENT => Some(Keyword::MdnSent),
+            _ => None,
+        }
+    }
 }
 
 impl From<Keyword> for TagValue<u32> {
@@ -119,6 +119,19 @@ pub enum Property {
     WarnLimit,
     SoftLimit,
     Scope,
+    Absence,
+    VacationReplies,
+    SieveConflict,
+    SmimeStatus,
+    SmimeErrors,
+    SmimeVerifiedAt,
+    PgpEscrowKey,
+    Annotations,
+    SavedAt,
+    SeenBy,
+    Counters,
+    ShareLinks,
+    DisposableAliases,
     Digest(DigestProperty),
     Data(DataProperty),
     _T(String),
@@ -334,6 +347,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         b'a' => match hash {
             0x6c63 => Property::Acl,
             0x7365_7361_696c => Property::Aliases,
+            0x736e_6f69_7461_746f_6e6e => Property::Annotations,
             0x7374_6e65_6d68_6361_7474 => Property::Attachments,
             _ => return None,
         },
@@ -349,6 +363,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x63 => Property::Cc,
             0x7465_7372_6168 => Property::Charset,
             0x6469 => Property::Cid,
+            0x0073_7265_746e_756f => Property::Counters,
             _ => return None,
         },
         b'd' => match hash {
@@ -431,11 +446,17 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             _ => return None,
         },
         b's' => match hash {
+            0x7441_6465_7661 => Property::SavedAt,
             0x0074_6572_6365 => Property::Secret,
+            0x0079_426e_6565 => Property::SeenBy,
             0x0074_4164_6e65 => Property::SendAt,
             0x0072_6564_6e65 => Property::Sender,
             0x0074_4174_6e65 => Property::SentAt,
+            0x7463_696c_666e_6f43_6576_6569 => Property::SieveConflict,
             0x0065_7a69 => Property::Size,
+            0x7372_6f72_7245_656d_696d => Property::SmimeErrors,
+            0x7375_7461_7453_656d_696d => Property::SmimeStatus,
+            0x7441_6465_6966_6972_6556_656d_696d => Property::SmimeVerifiedAt,
             0x7265_6472_4f74_726f => Property::SortOrder,
             0x7463_656a_6275 => Property::Subject,
             0x7374_7261_5062_7573 => Property::SubParts,
@@ -881,6 +902,19 @@ impl Display for Property {
             Property::Used => write!(f, "used"),
             Property::HardLimit => write!(f, "hardLimit"),
             Property::Scope => write!(f, "scope"),
+            Property::Absence => write!(f, "absence"),
+            Property::VacationReplies => write!(f, "vacationReplies"),
+            Property::SieveConflict => write!(f, "sieveConflict"),
+            Property::SmimeStatus => write!(f, "smimeStatus"),
+            Property::SmimeErrors => write!(f, "smimeErrors"),
+            Property::SmimeVerifiedAt => write!(f, "smimeVerifiedAt"),
+            Property::PgpEscrowKey => write!(f, "pgpEscrowKey"),
+            Property::Annotations => write!(f, "annotations"),
+            Property::SavedAt => write!(f, "savedAt"),
+            Property::SeenBy => write!(f, "seenBy"),
+            Property::Counters => write!(f, "counters"),
+            Property::ShareLinks => write!(f, "shareLinks"),
+            Property::DisposableAliases => write!(f, "disposableAliases"),
             Property::WarnLimit => write!(f, "warnLimit"),
             Property::SoftLimit => write!(f, "softLimit"),
             Property::_T(s) => write!(f, "{s}"),
@@ -1063,6 +1097,19 @@ impl From<&Property> for u8 {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::Absence => 104,
+            Property::VacationReplies => 105,
+            Property::SieveConflict => 106,
+            Property::SmimeStatus => 107,
+            Property::SmimeErrors => 108,
+            Property::SmimeVerifiedAt => 109,
+            Property::PgpEscrowKey => 110,
+            Property::Annotations => 111,
+            Property::SavedAt => 112,
+            Property::SeenBy => 113,
+            Property::Counters => 114,
+            Property::ShareLinks => 115,
+            Property::DisposableAliases => 116,
             Property::Digest(_) | Property::Data(_) => unreachable!("invalid property"),
         }
     }
@@ -1205,6 +1252,19 @@ impl SerializeInto for Property {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::Absence => 104,
+            Property::VacationReplies => 105,
+            Property::SieveConflict => 106,
+            Property::SmimeStatus => 107,
+            Property::SmimeErrors => 108,
+            Property::SmimeVerifiedAt => 109,
+            Property::PgpEscrowKey => 110,
+            Property::Annotations => 111,
+            Property::SavedAt => 112,
+            Property::SeenBy => 113,
+            Property::Counters => 114,
+            Property::ShareLinks => 115,
+            Property::DisposableAliases => 116,
             Property::Digest(_) | Property::Data(_) => {
                 unreachable!("Property::Digest and Property::Data are not serializable")
             }
@@ -1323,6 +1383,19 @@ impl DeserializeFrom for Property {
             101 => Some(Property::WarnLimit),
             102 => Some(Property::SoftLimit),
             103 => Some(Property::Scope),
+            104 => Some(Property::Absence),
+            105 => Some(Property::VacationReplies),
+            106 => Some(Property::SieveConflict),
+            107 => Some(Property::SmimeStatus),
+            108 => Some(Property::SmimeErrors),
+            109 => Some(Property::SmimeVerifiedAt),
+            110 => Some(Property::PgpEscrowKey),
+            111 => Some(Property::Annotations),
+            112 => Some(Property::SavedAt),
+            113 => Some(Property::SeenBy),
+            114 => Some(Property::Counters),
+            115 => Some(Property::ShareLinks),
+            116 => Some(Property::DisposableAliases),
             _ => None,
         }
     }
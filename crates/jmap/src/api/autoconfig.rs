@@ -8,25 +8,39 @@ use std::fmt::Write;
 
 use common::{manager::webadmin::Resource, Server};
 use directory::{backend::internal::PrincipalField, QueryBy};
+use hyper::StatusCode;
 use quick_xml::events::Event;
-use quick_xml::Reader;
+use quick_xml::{escape::escape, Reader};
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use trc::AddContext;
 use utils::url_params::UrlParams;
 
 use crate::api::http::ToHttpResponse;
 
-use super::{HttpRequest, HttpResponse};
+use super::{HttpRequest, HttpResponse, JsonResponse};
 use std::future::Future;
 
 pub trait Autoconfig: Sync + Send {
     fn handle_autoconfig_request(
         &self,
         req: &HttpRequest,
+        base_url: &str,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
     fn handle_autodiscover_request(
         &self,
         body: Option<Vec<u8>>,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+    fn handle_autodiscover_json_request(
+        &self,
+        req: &HttpRequest,
+        emailaddress: Option<&str>,
+        base_url: &str,
+    ) -> trc::Result<HttpResponse>;
+    fn handle_mobileconfig_request(
+        &self,
+        req: &HttpRequest,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
     fn autoconfig_parameters<'x>(
         &self,
         emailaddress: &'x str,
@@ -34,7 +48,11 @@ pub trait Autoconfig: Sync + Send {
 }
 
 impl Autoconfig for Server {
-    async fn handle_autoconfig_request(&self, req: &HttpRequest) -> trc::Result<HttpResponse> {
+    async fn handle_autoconfig_request(
+        &self,
+        req: &HttpRequest,
+        base_url: &str,
+    ) -> trc::Result<HttpResponse> {
         // Obtain parameters
         let params = UrlParams::new(req.uri().query());
         let emailaddress = params
@@ -77,6 +95,18 @@ impl Autoconfig for Server {
             let _ = writeln!(&mut config, "\t\t</{tag}>");
         }
 
+        // JMAP clients locate the account through the session resource
+        if base_url.starts_with("https://") {
+            config.push_str("\t\t<incomingServer type=\"jmap\">\n");
+            let _ = writeln!(&mut config, "\t\t\t<url>{base_url}/.well-known/jmap</url>");
+            let _ = writeln!(&mut config, "\t\t\t<username>{account_name}</username>");
+            let _ = writeln!(
+                &mut config,
+                "\t\t\t<authentication>password-cleartext</authentication>"
+            );
+            config.push_str("\t\t</incomingServer>\n");
+        }
+
         config.push_str("\t</emailProvider>\n");
         let _ = writeln!(
             &mut config,
@@ -169,6 +199,165 @@ impl Autoconfig for Server {
         )
     }
 
+    fn handle_autodiscover_json_request(
+        &self,
+        req: &HttpRequest,
+        emailaddress: Option<&str>,
+        base_url: &str,
+    ) -> trc::Result<HttpResponse> {
+        // Obtain parameters
+        let params = UrlParams::new(req.uri().query());
+        let emailaddress = emailaddress
+            .or_else(|| params.get("Email"))
+            .unwrap_or_default();
+        if !emailaddress.contains('@') {
+            return Err(trc::ResourceEvent::BadParameters
+                .into_err()
+                .details("Missing domain in email address"));
+        }
+
+        // Only the POX endpoint is available, Exchange protocols are not supported
+        let protocol = params.get("Protocol").unwrap_or_default();
+        if protocol.eq_ignore_ascii_case("AutodiscoverV1") {
+            Ok(JsonResponse::new(json!({
+                "Protocol": "AutodiscoverV1",
+                "Url": format!("{base_url}/autodiscover/autodiscover.xml"),
+            }))
+            .into_http_response())
+        } else {
+            Ok(JsonResponse::with_status(
+                StatusCode::BAD_REQUEST,
+                json!({
+                    "ErrorCode": "ProtocolNotSupported",
+                    "ErrorMessage": format!(
+                        "The given protocol value '{protocol}' is invalid. Supported values are 'AutodiscoverV1'."
+                    ),
+                }),
+            )
+            .into_http_response())
+        }
+    }
+
+    async fn handle_mobileconfig_request(&self, req: &HttpRequest) -> trc::Result<HttpResponse> {
+        // Obtain parameters
+        let params = UrlParams::new(req.uri().query());
+        let emailaddress = params
+            .get("emailaddress")
+            .unwrap_or_default()
+            .to_lowercase();
+        let (account_name, server_name, domain) = self.autoconfig_parameters(&emailaddress).await?;
+        let services = self.core.storage.config.get_services().await?;
+
+        // Apple Mail accounts have a single incoming server, IMAP is preferred over POP3
+        let incoming = services
+            .iter()
+            .find(|(protocol, _, _)| protocol == "imap")
+            .or_else(|| services.iter().find(|(protocol, _, _)| protocol == "pop3"));
+        let outgoing = services
+            .iter()
+            .find(|(protocol, port, _)| protocol == "smtp" && *port != 25);
+        let (Some((protocol, in_port, in_tls)), Some((_, out_port, out_tls))) =
+            (incoming, outgoing)
+        else {
+            return Err(trc::ResourceEvent::NotFound
+                .into_err()
+                .details("No IMAP, POP3 or SMTP submission listeners configured"));
+        };
+
+        // Build property list
+        let emailaddress = escape(emailaddress.as_str()).into_owned();
+        let account_name = escape(account_name.as_str()).into_owned();
+        let identifier =
+            escape(domain.rsplit('.').collect::<Vec<_>>().join(".").as_str()).into_owned();
+        let account_type = if protocol == "imap" {
+            "EmailTypeIMAP"
+        } else {
+            "EmailTypePOP"
+        };
+        let mut config = String::with_capacity(2048);
+        config.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        config.push_str("<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n");
+        config.push_str("<plist version=\"1.0\">\n<dict>\n");
+        config.push_str("\t<key>PayloadContent</key>\n\t<array>\n\t\t<dict>\n");
+        for (key, value) in [
+            ("EmailAccountDescription", emailaddress.as_str()),
+            ("EmailAccountName", emailaddress.as_str()),
+            ("EmailAccountType", account_type),
+            ("EmailAddress", emailaddress.as_str()),
+            ("IncomingMailServerAuthentication", "EmailAuthPassword"),
+            ("IncomingMailServerHostName", server_name.as_str()),
+            ("IncomingMailServerUsername", account_name.as_str()),
+            ("OutgoingMailServerAuthentication", "EmailAuthPassword"),
+            ("OutgoingMailServerHostName", server_name.as_str()),
+            ("OutgoingMailServerUsername", account_name.as_str()),
+            ("PayloadDisplayName", emailaddress.as_str()),
+            ("PayloadType", "com.apple.mail.managed"),
+        ] {
+            let _ = writeln!(
+                &mut config,
+                "\t\t\t<key>{key}</key>\n\t\t\t<string>{value}</string>"
+            );
+        }
+        for (key, value) in [
+            ("IncomingMailServerPortNumber", *in_port),
+            ("OutgoingMailServerPortNumber", *out_port),
+            ("PayloadVersion", 1),
+        ] {
+            let _ = writeln!(
+                &mut config,
+                "\t\t\t<key>{key}</key>\n\t\t\t<integer>{value}</integer>"
+            );
+        }
+        for (key, value) in [
+            ("IncomingMailServerUseSSL", *in_tls),
+            ("OutgoingMailServerUseSSL", *out_tls),
+            ("OutgoingPasswordSameAsIncomingPassword", true),
+        ] {
+            let _ = writeln!(&mut config, "\t\t\t<key>{key}</key>\n\t\t\t<{value}/>");
+        }
+        let uuid = profile_uuid(&emailaddress, "account");
+        let _ = writeln!(
+            &mut config,
+            "\t\t\t<key>PayloadIdentifier</key>\n\t\t\t<string>{identifier}.mail.{uuid}</string>"
+        );
+        let _ = writeln!(
+            &mut config,
+            "\t\t\t<key>PayloadUUID</key>\n\t\t\t<string>{uuid}</string>"
+        );
+        config.push_str("\t\t</dict>\n\t</array>\n");
+
+        // The profile identifiers are derived from the address so that
+        // installing it again replaces the existing account
+        let uuid = profile_uuid(&emailaddress, "profile");
+        let _ = writeln!(
+            &mut config,
+            "\t<key>PayloadDisplayName</key>\n\t<string>{}</string>",
+            escape(domain)
+        );
+        let _ = writeln!(
+            &mut config,
+            "\t<key>PayloadIdentifier</key>\n\t<string>{identifier}.{uuid}</string>"
+        );
+        config.push_str("\t<key>PayloadRemovalDisallowed</key>\n\t<false/>\n");
+        config.push_str("\t<key>PayloadType</key>\n\t<string>Configuration</string>\n");
+        let _ = writeln!(
+            &mut config,
+            "\t<key>PayloadUUID</key>\n\t<string>{uuid}</string>"
+        );
+        config.push_str("\t<key>PayloadVersion</key>\n\t<integer>1</integer>\n");
+        config.push_str("</dict>\n</plist>\n");
+
+        let mut response = Resource::new("application/x-apple-aspen-config", config.into_bytes())
+            .into_http_response();
+        response.content_disposition = format!(
+            "attachment; filename=\"{}.mobileconfig\"",
+            domain.replace('\"', "")
+        )
+        .into();
+
+        Ok(response)
+    }
+
     async fn autoconfig_parameters<'x>(
         &self,
         emailaddress: &'x str,
@@ -223,6 +412,24 @@ impl Autoconfig for Server {
     }
 }
 
+/// Builds a stable UUID for a profile payload from the hash of the address.
+fn profile_uuid(emailaddress: &str, payload: &str) -> String {
+    let hash = Sha256::digest(format!("{payload}:{emailaddress}").as_bytes());
+    let mut hex = String::with_capacity(32);
+    for byte in hash.iter().take(16) {
+        let _ = write!(&mut hex, "{byte:02X}");
+    }
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
 fn parse_autodiscover_request(bytes: &[u8]) -> Result<String, String> {
     if bytes.is_empty() {
         return Err("Empty request body".to_string());
@@ -326,4 +533,17 @@ mod tests {
             "email@example.com"
         );
     }
+
+    #[test]
+    fn profile_uuid() {
+        let uuid = super::profile_uuid("email@example.com", "account");
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(
+            uuid.split('-').map(|part| part.len()).collect::<Vec<_>>(),
+            [8, 4, 4, 4, 12]
+        );
+        assert_eq!(uuid, super::profile_uuid("email@example.com", "account"));
+        assert_ne!(uuid, super::profile_uuid("email@example.com", "profile"));
+        assert_ne!(uuid, super::profile_uuid("other@example.com", "account"));
+    }
 }
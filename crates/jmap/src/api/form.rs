@@ -22,13 +22,7 @@ use mail_builder::{
     MessageBuilder,
 };
 use serde_json::json;
-use store::{
-    write::{now, BatchBuilder, BlobOp},
-    Serialize,
-};
 use trc::AddContext;
-use utils::BlobHash;
-use x509_parser::nom::AsBytes;
 
 use crate::{auth::oauth::FormData, services::ingest::MailDelivery};
 
@@ -178,23 +172,10 @@ impl FormHandler for Server {
                 .write_to_vec()
                 .unwrap_or_default();
 
-            // Reserve and write blob
-            let message_blob = BlobHash::from(message.as_bytes());
+            // Write blob
             let message_size = message.len();
-            let mut batch = BatchBuilder::new();
-            batch.set(
-                BlobOp::Reserve {
-                    hash: message_blob.clone(),
-                    until: now() + 120,
-                },
-                0u32.serialize(),
-            );
-            self.store()
-                .write(batch.build())
-                .await
-                .caused_by(trc::location!())?;
-            self.blob_store()
-                .put_blob(message_blob.as_slice(), message.as_ref())
+            let message_blob = self
+                .put_temporary_blob(message.as_ref())
                 .await
                 .caused_by(trc::location!())?;
 
@@ -4,7 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{borrow::Cow, net::IpAddr, sync::Arc};
+use std::{
+    borrow::Cow,
+    net::IpAddr,
+    sync::{Arc, OnceLock},
+};
 
 use common::{
     auth::{oauth::GrantType, AccessToken},
@@ -40,7 +44,8 @@ use crate::api::management::enterprise::telemetry::TelemetryApi;
 
 use crate::{
     auth::{
-        authenticate::{Authenticator, HttpHeaders},
+        authenticate::{Authenticator, HttpHeaders, TRUSTED_DEVICE_HEADER},
+        challenge::ChallengeHandler,
         oauth::{
             auth::OAuthApiHandler, openid::OpenIdHandler, registration::ClientRegistrationHandler,
             token::TokenHandler, FormData,
@@ -48,6 +53,7 @@ use crate::{
         rate_limit::RateLimiter,
     },
     blob::{download::BlobDownload, upload::BlobUpload, DownloadResponse, UploadResponse},
+    email::{pdf::EmailPdf, quarantine::EmailQuarantine, share::EmailShare},
     websocket::upgrade::WebSocketUpgrade,
 };
 
@@ -55,7 +61,10 @@ use super::{
     autoconfig::Autoconfig,
     event_source::EventSourceHandler,
     form::FormHandler,
-    management::{troubleshoot::TroubleshootApi, ManagementApi, ManagementApiError},
+    management::{
+        decode_path_element, events::LiveEvents, troubleshoot::TroubleshootApi, ManagementApi,
+        ManagementApiError,
+    },
     request::RequestHandler,
     session::SessionHandler,
     HtmlResponse, HttpRequest, HttpResponse, HttpResponseBody, JmapSessionManager, JsonResponse,
@@ -69,6 +78,7 @@ pub struct HttpSessionData {
     pub remote_port: u16,
     pub is_tls: bool,
     pub session_id: u64,
+    pub trusted_device: Arc<OnceLock<String>>,
 }
 
 pub trait ParseHttp: Sync + Send {
@@ -203,6 +213,48 @@ impl ParseHttp for Server {
 
                         return self.handle_event_source(req, access_token).await;
                     }
+                    ("pdf", &Method::GET) => {
+                        // Authenticate request
+                        let (_in_flight, access_token) =
+                            self.authenticate_headers(&req, &session, false).await?;
+
+                        if let (Some(account_id), Some(email_id)) = (
+                            path.next().and_then(|p| Id::from_bytes(p.as_bytes())),
+                            path.next().and_then(|p| Id::from_bytes(p.as_bytes())),
+                        ) {
+                            return self
+                                .email_pdf(
+                                    &access_token,
+                                    account_id.document_id(),
+                                    email_id.document_id(),
+                                )
+                                .await
+                                .map(|pdf| {
+                                    DownloadResponse {
+                                        filename: format!("{email_id}.pdf"),
+                                        content_type: "application/pdf".to_string(),
+                                        blob: pdf,
+                                    }
+                                    .into_http_response()
+                                });
+                        }
+                    }
+                    ("share", &Method::GET) => {
+                        // Limit anonymous requests
+                        self.is_anonymous_allowed(&session.remote_ip).await?;
+
+                        return self
+                            .handle_share_request(path.next(), path.next(), path.next(), &session)
+                            .await;
+                    }
+                    ("quarantine", method @ (&Method::GET | &Method::POST)) => {
+                        // Limit anonymous requests
+                        self.is_anonymous_allowed(&session.remote_ip).await?;
+
+                        return self
+                            .handle_quarantine_request(path.next(), method == Method::POST)
+                            .await;
+                    }
                     ("ws", &Method::GET) => {
                         // Authenticate request
                         let (_in_flight, access_token) =
@@ -265,13 +317,17 @@ impl ParseHttp for Server {
                     };
                 }
                 ("mail-v1.xml", &Method::GET) => {
-                    return self.handle_autoconfig_request(&req).await;
+                    return self
+                        .handle_autoconfig_request(&req, &ctx.resolve_response_url(self).await)
+                        .await;
                 }
                 ("autoconfig", &Method::GET) => {
                     if path.next().unwrap_or_default() == "mail"
                         && path.next().unwrap_or_default() == "config-v1.1.xml"
                     {
-                        return self.handle_autoconfig_request(&req).await;
+                        return self
+                            .handle_autoconfig_request(&req, &ctx.resolve_response_url(self).await)
+                            .await;
                     }
                 }
                 (_, &Method::OPTIONS) => {
@@ -285,6 +341,11 @@ impl ParseHttp for Server {
 
                     return self.handle_device_auth(&mut req, session).await;
                 }
+                ("challenge", &Method::GET) => {
+                    self.is_anonymous_allowed(&session.remote_ip).await?;
+
+                    return self.handle_challenge_request(&session).await;
+                }
                 ("token", &Method::POST) => {
                     self.is_anonymous_allowed(&session.remote_ip).await?;
 
@@ -364,6 +425,9 @@ impl ParseHttp for Server {
                                 (Some("troubleshoot"), _, Some(token)) => {
                                     (GrantType::Troubleshoot, token)
                                 }
+                                (Some("events"), Some("live"), Some(token)) => {
+                                    (GrantType::LiveEvents, token)
+                                }
                                 _ => return Err(err),
                             };
                             let token_info =
@@ -395,6 +459,15 @@ impl ParseHttp for Server {
                                     )
                                     .await
                                 }
+                                GrantType::LiveEvents => {
+                                    self.handle_live_events(
+                                        &mut req,
+                                        &AccessToken::from_id(token_info.account_id)
+                                            .with_permission(Permission::TracingLive),
+                                        &session,
+                                    )
+                                    .await
+                                }
                                 _ => unreachable!(),
                             };
                         }
@@ -407,19 +480,38 @@ impl ParseHttp for Server {
                 if req.method() == Method::GET
                     && path.next().unwrap_or_default() == "config-v1.1.xml"
                 {
-                    return self.handle_autoconfig_request(&req).await;
+                    return self
+                        .handle_autoconfig_request(&req, &ctx.resolve_response_url(self).await)
+                        .await;
                 }
             }
-            "autodiscover" => {
-                if req.method() == Method::POST
-                    && path.next().unwrap_or_default() == "autodiscover.xml"
-                {
+            "autodiscover" => match (path.next().unwrap_or_default(), req.method()) {
+                ("autodiscover.xml", &Method::POST) => {
                     return self
                         .handle_autodiscover_request(
                             fetch_body(&mut req, 8192, session.session_id).await,
                         )
                         .await;
                 }
+                ("autodiscover.json", &Method::GET) => {
+                    // The address is either a path element or the Email parameter
+                    let emailaddress = path
+                        .next()
+                        .filter(|version| version.eq_ignore_ascii_case("v1.0"))
+                        .and_then(|_| path.next())
+                        .map(decode_path_element);
+                    return self.handle_autodiscover_json_request(
+                        &req,
+                        emailaddress.as_deref(),
+                        &ctx.resolve_response_url(self).await,
+                    );
+                }
+                _ => (),
+            },
+            "mobileconfig" => {
+                if req.method() == Method::GET {
+                    return self.handle_mobileconfig_request(&req).await;
+                }
             }
             "robots.txt" => {
                 return Ok(
@@ -432,13 +524,16 @@ impl ParseHttp for Server {
                     return Ok(StatusCode::OK.into_http_response());
                 }
                 "ready" => {
-                    return Ok({
-                        if !self.core.storage.data.is_none() {
+                    let report = self.health_report(path.next()).await;
+
+                    return Ok(JsonResponse::with_status(
+                        if report.healthy {
                             StatusCode::OK
                         } else {
                             StatusCode::SERVICE_UNAVAILABLE
-                        }
-                    }
+                        },
+                        report,
+                    )
                     .into_http_response());
                 }
                 _ => (),
@@ -555,8 +650,9 @@ impl ParseHttp for Server {
 async fn handle_session<T: SessionStream>(inner: Arc<Inner>, session: SessionData<T>) {
     let _in_flight = session.in_flight;
     let is_tls = session.stream.is_tls();
+    let mut shutdown_rx = session.instance.shutdown_rx.clone();
 
-    if let Err(http_err) = http1::Builder::new()
+    let conn = http1::Builder::new()
         .keep_alive(true)
         .serve_connection(
             TokioIo::new(session.stream),
@@ -630,6 +726,7 @@ async fn handle_session<T: SessionStream>(inner: Arc<Inner>, session: SessionDat
                     };
 
                     // Parse HTTP request
+                    let trusted_device = Arc::new(OnceLock::new());
                     let response = match server
                         .parse_http_request(
                             req,
@@ -641,6 +738,7 @@ async fn handle_session<T: SessionStream>(inner: Arc<Inner>, session: SessionDat
                                 remote_port: session.remote_port,
                                 is_tls,
                                 session_id: session.session_id,
+                                trusted_device: trusted_device.clone(),
                             },
                         )
                         .await
@@ -678,13 +776,34 @@ async fn handle_session<T: SessionStream>(inner: Arc<Inner>, session: SessionDat
                         }
                     }
 
+                    // Return the token issued to a newly trusted device
+                    if let Some(value) = trusted_device
+                        .get()
+                        .and_then(|token| header::HeaderValue::from_str(token).ok())
+                    {
+                        response.headers_mut().insert(TRUSTED_DEVICE_HEADER, value);
+                    }
+
                     Ok::<_, hyper::Error>(response)
                 }
             }),
         )
-        .with_upgrades()
-        .await
-    {
+        .with_upgrades();
+    tokio::pin!(conn);
+
+    // Finish in-flight requests before closing the connection on shutdown
+    let mut is_shutdown = false;
+    let result = loop {
+        tokio::select! {
+            result = conn.as_mut() => break result,
+            _ = shutdown_rx.changed(), if !is_shutdown => {
+                conn.as_mut().graceful_shutdown();
+                is_shutdown = true;
+            }
+        }
+    };
+
+    if let Err(http_err) = result {
         match inner
             .build_server()
             .is_scanner_fail2banned(session.remote_ip)
@@ -796,6 +915,41 @@ impl ResolveVariable for HttpContext<'_> {
     }
 }
 
+impl HttpSessionData {
+    pub async fn resolve_response_url(&self, server: &Server) -> String {
+        server
+            .eval_if(
+                &server.core.network.http_response_url,
+                self,
+                self.session_id,
+            )
+            .await
+            .unwrap_or_else(|| {
+                format!(
+                    "http{}://{}:{}",
+                    if self.is_tls { "s" } else { "" },
+                    self.local_ip,
+                    self.local_port
+                )
+            })
+    }
+}
+
+impl ResolveVariable for HttpSessionData {
+    fn resolve_variable(&self, variable: u32) -> Variable<'_> {
+        match variable {
+            V_REMOTE_IP => self.remote_ip.to_string().into(),
+            V_REMOTE_PORT => self.remote_port.into(),
+            V_LOCAL_IP => self.local_ip.to_string().into(),
+            V_LOCAL_PORT => self.local_port.into(),
+            V_TLS => self.is_tls.into(),
+            V_PROTOCOL => if self.is_tls { "https" } else { "http" }.into(),
+            V_LISTENER => self.instance.id.as_str().into(),
+            _ => Variable::default(),
+        }
+    }
+}
+
 pub async fn fetch_body(
     req: &mut HttpRequest,
     max_size: usize,
@@ -963,35 +1117,7 @@ impl<T: serde::Serialize> ToHttpResponse for JsonResponse<T> {
 impl ToHttpResponse for &trc::Error {
     fn into_http_response(self) -> HttpResponse {
         match self.as_ref() {
-            trc::EventType::Manage(cause) => {
-                match cause {
-                    trc::ManageEvent::MissingParameter => ManagementApiError::FieldMissing {
-                        field: self.value_as_str(trc::Key::Key).unwrap_or_default(),
-                    },
-                    trc::ManageEvent::AlreadyExists => ManagementApiError::FieldAlreadyExists {
-                        field: self.value_as_str(trc::Key::Key).unwrap_or_default(),
-                        value: self.value_as_str(trc::Key::Value).unwrap_or_default(),
-                    },
-                    trc::ManageEvent::NotFound => ManagementApiError::NotFound {
-                        item: self.value_as_str(trc::Key::Key).unwrap_or_default(),
-                    },
-                    trc::ManageEvent::NotSupported => ManagementApiError::Unsupported {
-                        details: self
-                            .value(trc::Key::Details)
-                            .or_else(|| self.value(trc::Key::Reason))
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("Requested action is unsupported"),
-                    },
-                    trc::ManageEvent::AssertFailed => ManagementApiError::AssertFailed,
-                    trc::ManageEvent::Error => ManagementApiError::Other {
-                        reason: self.value_as_str(trc::Key::Reason),
-                        details: self
-                            .value_as_str(trc::Key::Details)
-                            .unwrap_or("Unknown error"),
-                    },
-                }
-            }
-            .into_http_response(),
+            trc::EventType::Manage(_) => ManagementApiError::from(self).into_http_response(),
 
             _ => self.to_request_error().into_http_response(),
         }
@@ -1044,6 +1170,9 @@ impl ToRequestError for trc::Error {
                     RequestError::blank(402, "TOTP code required", cause.message())
                 }
                 trc::AuthEvent::TooManyAttempts => RequestError::too_many_auth_attempts(),
+                trc::AuthEvent::ChallengeRequired | trc::AuthEvent::ChallengeFailed => {
+                    RequestError::blank(428, "Challenge required", cause.message())
+                }
                 _ => RequestError::unauthorized(),
             },
             trc::EventType::Security(cause) => match cause {
@@ -1051,8 +1180,11 @@ impl ToRequestError for trc::Error {
                 | trc::SecurityEvent::ScanBan
                 | trc::SecurityEvent::AbuseBan
                 | trc::SecurityEvent::LoiterBan
+                | trc::SecurityEvent::AuthenticationLockout
                 | trc::SecurityEvent::IpBlocked => RequestError::too_many_auth_attempts(),
-                trc::SecurityEvent::Unauthorized => RequestError::forbidden(),
+                trc::SecurityEvent::Unauthorized | trc::SecurityEvent::SubmissionSuspended => {
+                    RequestError::forbidden()
+                }
             },
             trc::EventType::Resource(cause) => match cause {
                 trc::ResourceEvent::NotFound => RequestError::not_found(),
@@ -0,0 +1,222 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{
+    auth::{alias::DisposableAlias, AccessToken},
+    Server,
+};
+use directory::backend::internal::manage;
+use hyper::Method;
+use serde_json::json;
+use store::write::now;
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+use super::decode_path_element;
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AliasCreate {
+    #[serde(default)]
+    pub prefix: String,
+    pub description: Option<String>,
+    pub expires_in: Option<u64>,
+}
+
+/// Fields to change on an existing alias. An `expiresIn` of zero removes the
+/// expiration.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AliasUpdate {
+    pub enabled: Option<bool>,
+    pub description: Option<String>,
+    pub expires_in: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AliasItem {
+    pub address: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub active: bool,
+    pub created: u64,
+    pub expires: Option<u64>,
+    pub received: i64,
+}
+
+pub trait DisposableAliasManagement: Sync + Send {
+    fn handle_manage_aliases(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn alias_item(
+        &self,
+        alias: DisposableAlias,
+    ) -> impl Future<Output = trc::Result<AliasItem>> + Send;
+}
+
+impl DisposableAliasManagement for Server {
+    async fn handle_manage_aliases(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let Some(config) = &self.core.jmap.disposable_aliases else {
+            return Err(manage::unsupported("Disposable aliases are disabled"));
+        };
+        let account_id = access_token.primary_id();
+
+        match (path.get(2).copied().map(decode_path_element), req.method()) {
+            (None, &Method::GET) => {
+                let mut items = Vec::new();
+                for alias in self.disposable_aliases(account_id).await? {
+                    items.push(self.alias_item(alias).await?);
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": items,
+                }))
+                .into_http_response())
+            }
+            (None, &Method::POST) => {
+                let request =
+                    serde_json::from_slice::<AliasCreate>(body.as_deref().unwrap_or_default())
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+
+                if self.disposable_aliases(account_id).await?.len() >= config.max_aliases {
+                    return Err(manage::error(
+                        "Too many aliases",
+                        format!(
+                            "Accounts can have at most {} disposable aliases.",
+                            config.max_aliases
+                        )
+                        .into(),
+                    ));
+                }
+
+                let domain = config
+                    .domain
+                    .as_deref()
+                    .or_else(|| {
+                        access_token
+                            .emails
+                            .first()
+                            .and_then(|email| email.rsplit_once('@'))
+                            .map(|(_, domain)| domain)
+                    })
+                    .ok_or_else(|| {
+                        manage::error(
+                            "No alias domain",
+                            "The account has no email address to derive an alias domain from."
+                                .into(),
+                        )
+                    })?;
+                let alias = self
+                    .disposable_alias_create(
+                        account_id,
+                        &request.prefix,
+                        domain,
+                        request.description.filter(|d| !d.is_empty()),
+                        expires_at(request.expires_in, config.max_expiry),
+                    )
+                    .await?;
+
+                trc::event!(
+                    Manage(trc::ManageEvent::AliasCreated),
+                    AccountId = account_id,
+                    To = alias.address.clone(),
+                );
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.alias_item(alias).await?,
+                }))
+                .into_http_response())
+            }
+            (Some(address), &Method::PATCH) => {
+                let request =
+                    serde_json::from_slice::<AliasUpdate>(body.as_deref().unwrap_or_default())
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+                let mut alias = self
+                    .disposable_alias_get(address.as_ref())
+                    .await?
+                    .filter(|alias| alias.account_id == account_id)
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                if let Some(enabled) = request.enabled {
+                    alias.enabled = enabled;
+                }
+                if let Some(description) = request.description {
+                    alias.description = Some(description).filter(|d| !d.is_empty());
+                }
+                if let Some(expires_in) = request.expires_in {
+                    alias.expires = expires_at(Some(expires_in), config.max_expiry);
+                }
+                self.disposable_alias_set(&alias).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.alias_item(alias).await?,
+                }))
+                .into_http_response())
+            }
+            (Some(address), &Method::DELETE) => {
+                let address = address.to_lowercase();
+                if self.disposable_alias_delete(account_id, &address).await? {
+                    trc::event!(
+                        Manage(trc::ManageEvent::AliasDeleted),
+                        AccountId = account_id,
+                        To = address,
+                    );
+
+                    Ok(JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response())
+                } else {
+                    Err(trc::ResourceEvent::NotFound.into_err())
+                }
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+
+    async fn alias_item(&self, alias: DisposableAlias) -> trc::Result<AliasItem> {
+        Ok(AliasItem {
+            received: self.disposable_alias_received(&alias.address).await?,
+            active: alias.is_active(),
+            address: alias.address,
+            description: alias.description,
+            enabled: alias.enabled,
+            created: alias.created,
+            expires: alias.expires,
+        })
+    }
+}
+
+/// Converts a relative expiration into a timestamp, capped at the configured
+/// maximum. Zero means the alias never expires unless a maximum is set.
+fn expires_at(expires_in: Option<u64>, max_expiry: Option<u64>) -> Option<u64> {
+    match (expires_in.filter(|expires_in| *expires_in > 0), max_expiry) {
+        (Some(expires_in), Some(max_expiry)) => Some(now() + expires_in.min(max_expiry)),
+        (Some(expires_in), None) => Some(now() + expires_in),
+        (None, Some(max_expiry)) => Some(now() + max_expiry),
+        (None, None) => None,
+    }
+}
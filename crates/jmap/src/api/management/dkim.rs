@@ -6,7 +6,12 @@
 
 use std::str::FromStr;
 
-use common::{auth::AccessToken, config::smtp::auth::simple_pem_parse, Server};
+use common::{
+    auth::AccessToken,
+    config::smtp::auth::{simple_pem_parse, DkimRotation},
+    dns::{default_origin, DnsRecord},
+    Server,
+};
 use directory::{backend::internal::manage, Permission};
 use hyper::Method;
 use mail_auth::{
@@ -20,12 +25,19 @@ use rsa::pkcs1::DecodeRsaPublicKey;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use store::write::now;
+use trc::DkimEvent;
+use utils::config::Config;
 
-use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    JmapMethods,
+};
 
 use super::decode_path_element;
 use std::future::Future;
 
+const DEFAULT_ROTATION_OVERLAP: u64 = 7 * 86400;
+
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 pub enum Algorithm {
     Rsa,
@@ -40,6 +52,23 @@ struct DkimSignature {
     selector: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DkimRecord {
+    pub name: String,
+    pub content: String,
+    pub status: DkimKeyStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DkimKeyStatus {
+    Active,
+    Pending,
+    Retired,
+}
+
 pub trait DkimManagement: Sync + Send {
     fn handle_manage_dkim(
         &self,
@@ -59,6 +88,16 @@ pub trait DkimManagement: Sync + Send {
         body: Option<Vec<u8>>,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
 
+    fn handle_get_dkim_records(
+        &self,
+        path: Vec<&str>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_rotate_dkim_key(
+        &self,
+        path: Vec<&str>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
     fn create_dkim_key(
         &self,
         algo: Algorithm,
@@ -66,6 +105,30 @@ pub trait DkimManagement: Sync + Send {
         domain: impl Into<String> + Send,
         selector: impl Into<String> + Send,
     ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn dkim_signature_keys(
+        &self,
+        signature_id: &str,
+    ) -> impl Future<Output = trc::Result<Config>> + Send;
+
+    fn dkim_rotate_keys(&self) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn dkim_rotate(
+        &self,
+        signature_id: &str,
+        force: bool,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn dkim_publish(
+        &self,
+        signature_id: &str,
+        rotation: Option<&DkimRotation>,
+        domain: &str,
+        selector: &str,
+        content: Option<String>,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn dkim_reload(&self) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
 impl DkimManagement for Server {
@@ -76,14 +139,26 @@ impl DkimManagement for Server {
         body: Option<Vec<u8>>,
         access_token: &AccessToken,
     ) -> trc::Result<HttpResponse> {
-        match *req.method() {
-            Method::GET => {
+        match (req.method(), path.get(2).copied()) {
+            (&Method::GET, Some("records")) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::DkimSignatureGet)?;
+
+                self.handle_get_dkim_records(path).await
+            }
+            (&Method::GET, _) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::DkimSignatureGet)?;
 
                 self.handle_get_public_key(path).await
             }
-            Method::POST => {
+            (&Method::POST, Some("rotate")) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::DkimSignatureCreate)?;
+
+                self.handle_rotate_dkim_key(path).await
+            }
+            (&Method::POST, _) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::DkimSignatureCreate)?;
 
@@ -181,6 +256,47 @@ impl DkimManagement for Server {
         .into_http_response())
     }
 
+    async fn handle_get_dkim_records(&self, path: Vec<&str>) -> trc::Result<HttpResponse> {
+        let signature_id = match path.get(1) {
+            Some(signature_id) => decode_path_element(signature_id),
+            None => {
+                return Err(trc::ResourceEvent::NotFound.into_err());
+            }
+        };
+
+        let keys = self.dkim_signature_keys(signature_id.as_ref()).await?;
+        let records = dkim_records(&keys, signature_id.as_ref());
+        if records.is_empty() {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": records,
+        }))
+        .into_http_response())
+    }
+
+    async fn handle_rotate_dkim_key(&self, path: Vec<&str>) -> trc::Result<HttpResponse> {
+        let signature_id = match path.get(1) {
+            Some(signature_id) => decode_path_element(signature_id),
+            None => {
+                return Err(trc::ResourceEvent::NotFound.into_err());
+            }
+        };
+
+        if self.dkim_rotate(signature_id.as_ref(), true).await? {
+            self.dkim_reload().await?;
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": dkim_records(
+                &self.dkim_signature_keys(signature_id.as_ref()).await?,
+                signature_id.as_ref()
+            ),
+        }))
+        .into_http_response())
+    }
+
     async fn create_dkim_key(
         &self,
         algo: Algorithm,
@@ -189,45 +305,17 @@ impl DkimManagement for Server {
         selector: impl Into<String>,
     ) -> trc::Result<()> {
         let id = id.as_ref();
-        let (algorithm, pk_type) = match algo {
-            Algorithm::Rsa => ("rsa-sha256", "RSA PRIVATE KEY"),
-            Algorithm::Ed25519 => ("ed25519-sha256", "PRIVATE KEY"),
+        let algorithm = match algo {
+            Algorithm::Rsa => "rsa-sha256",
+            Algorithm::Ed25519 => "ed25519-sha256",
         };
-        let mut pk = format!("-----BEGIN {pk_type}-----\n").into_bytes();
-        let mut lf_count = 65;
-        for ch in base64_encode(
-            match algo {
-                Algorithm::Rsa => DkimKeyPair::generate_rsa(2048),
-                Algorithm::Ed25519 => DkimKeyPair::generate_ed25519(),
-            }
-            .map_err(|err| {
-                manage::error("Failed to generate key", err.to_string().into())
-                    .caused_by(trc::location!())
-            })?
-            .private_key(),
-        )
-        .unwrap_or_default()
-        {
-            pk.push(ch);
-            lf_count -= 1;
-            if lf_count == 0 {
-                pk.push(b'\n');
-                lf_count = 65;
-            }
-        }
-        if lf_count != 65 {
-            pk.push(b'\n');
-        }
-        pk.extend_from_slice(format!("-----END {pk_type}-----\n").as_bytes());
+        let pk = generate_dkim_private_key(algo)?;
 
         self.core
             .storage
             .config
             .set([
-                (
-                    format!("signature.{id}.private-key"),
-                    String::from_utf8(pk).unwrap(),
-                ),
+                (format!("signature.{id}.private-key"), pk),
                 (format!("signature.{id}.domain"), domain.into()),
                 (format!("signature.{id}.selector"), selector.into()),
                 (format!("signature.{id}.algorithm"), algorithm.to_string()),
@@ -247,6 +335,308 @@ impl DkimManagement for Server {
             ])
             .await
     }
+
+    async fn dkim_signature_keys(&self, signature_id: &str) -> trc::Result<Config> {
+        let mut keys = Config::default();
+        let mut has_macros = false;
+        for (key, value) in self
+            .core
+            .storage
+            .config
+            .list(&format!("signature.{signature_id}."), false)
+            .await?
+        {
+            if let Some(key) = key.strip_prefix("signature.") {
+                if !has_macros && value.contains("%{") {
+                    has_macros = true;
+                }
+                keys.keys.insert(key.to_string(), value);
+            }
+        }
+
+        if has_macros {
+            keys.resolve_all_macros().await;
+            keys.log_errors();
+        }
+
+        Ok(keys)
+    }
+
+    async fn dkim_rotate_keys(&self) -> trc::Result<()> {
+        let mut needs_reload = false;
+        for signature_id in self.core.smtp.mail_auth.rotations.keys() {
+            match self.dkim_rotate(signature_id, false).await {
+                Ok(did_rotate) => {
+                    needs_reload |= did_rotate;
+                }
+                Err(err) => {
+                    trc::error!(err
+                        .id(signature_id.to_string())
+                        .details("Failed to rotate DKIM key"));
+                }
+            }
+        }
+
+        if needs_reload {
+            self.dkim_reload().await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn dkim_rotate(&self, signature_id: &str, force: bool) -> trc::Result<bool> {
+        let rotation = self.core.smtp.mail_auth.rotations.get(signature_id);
+        let (interval, overlap) = match rotation {
+            Some(rotation) => (rotation.interval.as_secs(), rotation.overlap.as_secs()),
+            None if force => (0, DEFAULT_ROTATION_OVERLAP),
+            None => return Ok(false),
+        };
+        let keys = self.dkim_signature_keys(signature_id).await?;
+        let (algo, domain, selector, pk) = match (
+            keys.value((signature_id, "algorithm"))
+                .and_then(|algo| algo.parse::<Algorithm>().ok()),
+            keys.value((signature_id, "domain")),
+            keys.value((signature_id, "selector")),
+            keys.value((signature_id, "private-key")),
+        ) {
+            (Some(algo), Some(domain), Some(selector), Some(pk)) => (algo, domain, selector, pk),
+            _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+        };
+        let config = &self.core.storage.config;
+        let now = now();
+
+        // Start tracking keys that were created before the rotation policy was set
+        let last_rotation = match keys
+            .value((signature_id, "rotation.last"))
+            .and_then(|last| last.parse::<u64>().ok())
+        {
+            Some(last_rotation) => last_rotation,
+            None if !force => {
+                config
+                    .set([(
+                        format!("signature.{signature_id}.rotation.last"),
+                        now.to_string(),
+                    )])
+                    .await?;
+                return Ok(false);
+            }
+            None => 0,
+        };
+
+        // Generate the next key and publish it ahead of the rotation, so that
+        // its DNS record has propagated by the time it is used for signing
+        let mut next_key = match (
+            keys.value((signature_id, "rotation.next.selector")),
+            keys.value((signature_id, "rotation.next.private-key")),
+        ) {
+            (Some(selector), Some(pk)) => Some((selector.to_string(), pk.to_string())),
+            _ => None,
+        };
+        if next_key.is_none() && (force || now >= last_rotation + interval - overlap) {
+            let dt = DateTime::from_timestamp(now as i64);
+            let base_selector = format!(
+                "{:04}{:02}{:02}{}",
+                dt.year,
+                dt.month,
+                dt.day,
+                if Algorithm::Rsa == algo { "r" } else { "e" }
+            );
+            let mut next_selector = base_selector.clone();
+            let mut seq = 1;
+            while next_selector == selector
+                || keys.contains_key((signature_id, "rotation.retired", next_selector.as_str()))
+            {
+                seq += 1;
+                next_selector = format!("{base_selector}{seq}");
+            }
+            let next_pk = generate_dkim_private_key(algo)?;
+            let public = obtain_dkim_public_key(algo, &next_pk)?;
+
+            config
+                .set([
+                    (
+                        format!("signature.{signature_id}.rotation.next.selector"),
+                        next_selector.clone(),
+                    ),
+                    (
+                        format!("signature.{signature_id}.rotation.next.private-key"),
+                        next_pk.clone(),
+                    ),
+                ])
+                .await?;
+            self.dkim_publish(
+                signature_id,
+                rotation,
+                domain,
+                &next_selector,
+                Some(dkim_txt_record(algo, &public)),
+            )
+            .await;
+            next_key = Some((next_selector, next_pk));
+        }
+
+        // Activate the next key, keeping the current one published until the overlap expires
+        let mut did_rotate = false;
+        if let Some((next_selector, next_pk)) =
+            next_key.filter(|_| force || now >= last_rotation + interval)
+        {
+            let public = obtain_dkim_public_key(algo, pk)?;
+            config
+                .set([
+                    (
+                        format!("signature.{signature_id}.rotation.retired.{selector}"),
+                        format!("{}:{public}", now + overlap),
+                    ),
+                    (
+                        format!("signature.{signature_id}.selector"),
+                        next_selector.clone(),
+                    ),
+                    (format!("signature.{signature_id}.private-key"), next_pk),
+                    (
+                        format!("signature.{signature_id}.rotation.last"),
+                        now.to_string(),
+                    ),
+                ])
+                .await?;
+            config
+                .clear_prefix(format!("signature.{signature_id}.rotation.next."))
+                .await?;
+
+            trc::event!(
+                Dkim(DkimEvent::KeyRotated),
+                Id = signature_id.to_string(),
+                Domain = domain.to_string(),
+                Details = next_selector,
+                Expires = trc::Value::Timestamp(now + overlap),
+            );
+
+            did_rotate = true;
+        }
+
+        // Remove retired keys past their overlap period
+        for (retired_selector, value) in keys.iterate_prefix((signature_id, "rotation.retired")) {
+            if value
+                .split_once(':')
+                .and_then(|(expires, _)| expires.parse::<u64>().ok())
+                .is_some_and(|expires| expires <= now)
+            {
+                self.dkim_publish(signature_id, rotation, domain, retired_selector, None)
+                    .await;
+                config
+                    .clear(format!(
+                        "signature.{signature_id}.rotation.retired.{retired_selector}"
+                    ))
+                    .await?;
+
+                trc::event!(
+                    Dkim(DkimEvent::KeyRetired),
+                    Id = signature_id.to_string(),
+                    Domain = domain.to_string(),
+                    Details = retired_selector.to_string(),
+                );
+            }
+        }
+
+        Ok(did_rotate)
+    }
+
+    async fn dkim_publish(
+        &self,
+        signature_id: &str,
+        rotation: Option<&DkimRotation>,
+        domain: &str,
+        selector: &str,
+        content: Option<String>,
+    ) {
+        let (rotation, provider_id) = match rotation
+            .and_then(|rotation| Some((rotation, rotation.dns_provider.as_deref()?)))
+        {
+            Some(provider) => provider,
+            None => return,
+        };
+        let name = format!("{selector}._domainkey.{domain}");
+        let origin = rotation
+            .dns_origin
+            .as_deref()
+            .unwrap_or_else(|| default_origin(domain));
+
+        let result = match self.core.dns.get(provider_id) {
+            Some(provider) => match content {
+                Some(content) => {
+                    provider
+                        .create(&name, DnsRecord::TXT { content }, rotation.ttl, origin)
+                        .await
+                }
+                None => provider.delete(&name, origin).await,
+            }
+            .map_err(|err| err.to_string()),
+            None => Err(format!("DNS provider {provider_id:?} does not exist")),
+        };
+
+        match result {
+            Ok(_) => {
+                trc::event!(
+                    Dkim(DkimEvent::DnsRecordPublished),
+                    Id = signature_id.to_string(),
+                    Hostname = name,
+                    Details = origin.to_string(),
+                );
+            }
+            Err(reason) => {
+                trc::event!(
+                    Dkim(DkimEvent::DnsRecordPublishFailed),
+                    Id = signature_id.to_string(),
+                    Hostname = name,
+                    Details = origin.to_string(),
+                    Reason = reason,
+                );
+            }
+        }
+    }
+
+    async fn dkim_reload(&self) -> trc::Result<()> {
+        if let Some(core) = self.reload().await?.new_core {
+            self.inner.shared_core.store(core.into());
+            self.increment_config_version();
+        }
+
+        Ok(())
+    }
+}
+
+pub fn generate_dkim_private_key(algo: Algorithm) -> trc::Result<String> {
+    let pk_type = match algo {
+        Algorithm::Rsa => "RSA PRIVATE KEY",
+        Algorithm::Ed25519 => "PRIVATE KEY",
+    };
+    let mut pk = format!("-----BEGIN {pk_type}-----\n").into_bytes();
+    let mut lf_count = 65;
+    for ch in base64_encode(
+        match algo {
+            Algorithm::Rsa => DkimKeyPair::generate_rsa(2048),
+            Algorithm::Ed25519 => DkimKeyPair::generate_ed25519(),
+        }
+        .map_err(|err| {
+            manage::error("Failed to generate key", err.to_string().into())
+                .caused_by(trc::location!())
+        })?
+        .private_key(),
+    )
+    .unwrap_or_default()
+    {
+        pk.push(ch);
+        lf_count -= 1;
+        if lf_count == 0 {
+            pk.push(b'\n');
+            lf_count = 65;
+        }
+    }
+    if lf_count != 65 {
+        pk.push(b'\n');
+    }
+    pk.extend_from_slice(format!("-----END {pk_type}-----\n").as_bytes());
+
+    Ok(String::from_utf8(pk).unwrap())
 }
 
 pub fn obtain_dkim_public_key(algo: Algorithm, pk: &str) -> trc::Result<String> {
@@ -281,6 +671,69 @@ pub fn obtain_dkim_public_key(algo: Algorithm, pk: &str) -> trc::Result<String>
     }
 }
 
+/// Returns the active, pending and retired DKIM records of a signature,
+/// reading the `<id>.*` keys of the signature configuration.
+pub fn dkim_records(keys: &Config, signature_id: &str) -> Vec<DkimRecord> {
+    let mut records = Vec::new();
+    let (algo, domain) = match (
+        keys.value((signature_id, "algorithm"))
+            .and_then(|algo| algo.parse::<Algorithm>().ok()),
+        keys.value((signature_id, "domain")),
+    ) {
+        (Some(algo), Some(domain)) => (algo, domain),
+        _ => return records,
+    };
+
+    for (status, selector, pk) in [
+        (
+            DkimKeyStatus::Active,
+            keys.value((signature_id, "selector")),
+            keys.value((signature_id, "private-key")),
+        ),
+        (
+            DkimKeyStatus::Pending,
+            keys.value((signature_id, "rotation.next.selector")),
+            keys.value((signature_id, "rotation.next.private-key")),
+        ),
+    ] {
+        if let (Some(selector), Some(pk)) = (selector, pk) {
+            match obtain_dkim_public_key(algo, pk) {
+                Ok(public) => {
+                    records.push(DkimRecord {
+                        name: format!("{selector}._domainkey.{domain}."),
+                        content: dkim_txt_record(algo, &public),
+                        status,
+                        expires: None,
+                    });
+                }
+                Err(err) => {
+                    trc::error!(err);
+                }
+            }
+        }
+    }
+
+    for (selector, value) in keys.iterate_prefix((signature_id, "rotation.retired")) {
+        if let Some((expires, public)) = value.split_once(':') {
+            records.push(DkimRecord {
+                name: format!("{selector}._domainkey.{domain}."),
+                content: dkim_txt_record(algo, public),
+                status: DkimKeyStatus::Retired,
+                expires: expires.parse().ok(),
+            });
+        }
+    }
+
+    records
+}
+
+pub fn dkim_txt_record(algo: Algorithm, public: &str) -> String {
+    match algo {
+        Algorithm::Rsa => format!("v=DKIM1; k=rsa; h=sha256; p={public}"),
+        Algorithm::Ed25519 => format!("v=DKIM1; k=ed25519; h=sha256; p={public}"),
+    }
+}
+
 impl FromStr for Algorithm {
     type Err = ();
 
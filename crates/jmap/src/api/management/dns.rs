@@ -4,9 +4,15 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::{auth::AccessToken, Server};
+use std::time::Instant;
+
+use common::{
+    auth::AccessToken,
+    dns::{self, default_origin},
+    Server,
+};
 use directory::{
-    backend::internal::manage::{self},
+    backend::internal::manage::{self, not_found},
     Permission,
 };
 
@@ -14,24 +20,41 @@ use hyper::Method;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha1::Digest;
-use utils::config::Config;
+use utils::{config::Config, url_params::UrlParams};
 use x509_parser::parse_x509_certificate;
 
 use crate::api::{
-    http::ToHttpResponse,
-    management::dkim::{obtain_dkim_public_key, Algorithm},
-    HttpRequest, HttpResponse, JsonResponse,
+    http::ToHttpResponse, management::dkim::dkim_records, HttpRequest, HttpResponse, JsonResponse,
 };
 
 use super::decode_path_element;
 use std::future::Future;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DnsRecord {
     #[serde(rename = "type")]
-    typ: String,
-    name: String,
-    content: String,
+    pub typ: String,
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsRecordResult {
+    #[serde(flatten)]
+    pub record: DnsRecord,
+    pub status: DnsRecordStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsRecordStatus {
+    Published,
+    Failed,
+    Propagated,
+    Pending,
+    Unverified,
 }
 
 pub trait DnsManagement: Sync + Send {
@@ -46,6 +69,23 @@ pub trait DnsManagement: Sync + Send {
         &self,
         domain_name: &str,
     ) -> impl Future<Output = trc::Result<Vec<DnsRecord>>> + Send;
+
+    fn publish_dns_records(
+        &self,
+        domain_name: &str,
+        provider_id: &str,
+        ttl: u32,
+    ) -> impl Future<Output = trc::Result<Vec<DnsRecordResult>>> + Send;
+
+    fn verify_dns_records(
+        &self,
+        domain_name: &str,
+    ) -> impl Future<Output = trc::Result<Vec<DnsRecordResult>>> + Send;
+
+    fn provision_dns_records(
+        &self,
+        domain_name: &str,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
 impl DnsManagement for Server {
@@ -71,10 +111,195 @@ impl DnsManagement for Server {
                 }))
                 .into_http_response())
             }
+            ("records", Some(domain), &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::DomainUpdate)?;
+
+                // Publish DNS records through the requested or the default provider
+                let domain = decode_path_element(domain);
+                let params = UrlParams::new(req.uri().query());
+                let provisioning = self.core.dns.provisioning.as_ref();
+                let provider_id = params
+                    .get("provider")
+                    .or_else(|| provisioning.map(|p| p.provider.as_str()))
+                    .ok_or_else(|| manage::err_missing("provider"))?;
+                let ttl = params
+                    .parse::<u32>("ttl")
+                    .or_else(|| provisioning.map(|p| p.ttl))
+                    .unwrap_or(3600);
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.publish_dns_records(domain.as_ref(), provider_id, ttl).await?,
+                }))
+                .into_http_response())
+            }
+            ("verify", Some(domain), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::DomainGet)?;
+
+                // Check which DNS records are resolvable
+                let domain = decode_path_element(domain);
+                Ok(JsonResponse::new(json!({
+                    "data": self.verify_dns_records(domain.as_ref()).await?,
+                }))
+                .into_http_response())
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
 
+    async fn publish_dns_records(
+        &self,
+        domain_name: &str,
+        provider_id: &str,
+        ttl: u32,
+    ) -> trc::Result<Vec<DnsRecordResult>> {
+        let provider = self
+            .core
+            .dns
+            .get(provider_id)
+            .ok_or_else(|| not_found(provider_id.to_string()))?;
+        let origin = default_origin(domain_name);
+        let mut results = self
+            .build_dns_records(domain_name)
+            .await?
+            .into_iter()
+            .map(|record| DnsRecordResult {
+                record,
+                status: DnsRecordStatus::Published,
+                reason: None,
+            })
+            .collect::<Vec<_>>();
+
+        // Records sharing a name and type are published as a single record set
+        let mut record_sets: Vec<Vec<usize>> = Vec::new();
+        for (idx, result) in results.iter().enumerate() {
+            if let Some(record_set) = record_sets.iter_mut().find(|record_set| {
+                let record = &results[record_set[0]].record;
+                record.name == result.record.name && record.typ == result.record.typ
+            }) {
+                record_set.push(idx);
+            } else {
+                record_sets.push(vec![idx]);
+            }
+        }
+
+        for record_set in record_sets {
+            let first = &results[record_set[0]].record;
+            let name = first.name.trim_end_matches('.').to_string();
+            let typ = first.typ.clone();
+            let result = match record_set
+                .iter()
+                .map(|idx| {
+                    let record = &results[*idx].record;
+                    dns::DnsRecord::parse(&record.typ, &record.content)
+                })
+                .collect::<Option<Vec<_>>>()
+            {
+                Some(records) => provider
+                    .create_many(&name, records, ttl, origin)
+                    .await
+                    .map_err(|err| err.to_string()),
+                None => Err("Invalid record".to_string()),
+            };
+
+            match result {
+                Ok(_) => {
+                    trc::event!(
+                        Manage(trc::ManageEvent::DnsRecordPublished),
+                        Domain = domain_name.to_string(),
+                        Hostname = name,
+                        Type = typ,
+                        Id = provider_id.to_string(),
+                    );
+                }
+                Err(reason) => {
+                    trc::event!(
+                        Manage(trc::ManageEvent::DnsRecordPublishFailed),
+                        Domain = domain_name.to_string(),
+                        Hostname = name,
+                        Type = typ,
+                        Id = provider_id.to_string(),
+                        Reason = reason.clone(),
+                    );
+
+                    for idx in record_set {
+                        results[idx].status = DnsRecordStatus::Failed;
+                        results[idx].reason = Some(reason.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn verify_dns_records(&self, domain_name: &str) -> trc::Result<Vec<DnsRecordResult>> {
+        let mut results = Vec::new();
+        for record in self.build_dns_records(domain_name).await? {
+            results.push(verify_dns_record(self, record).await);
+        }
+
+        Ok(results)
+    }
+
+    async fn provision_dns_records(&self, domain_name: &str) -> trc::Result<()> {
+        let provisioning = if let Some(provisioning) = &self.core.dns.provisioning {
+            provisioning
+        } else {
+            return Ok(());
+        };
+
+        let mut pending = self
+            .publish_dns_records(domain_name, &provisioning.provider, provisioning.ttl)
+            .await?
+            .into_iter()
+            .filter(|result| result.status == DnsRecordStatus::Published)
+            .map(|result| result.record)
+            .collect::<Vec<_>>();
+
+        // Wait for changes to propagate
+        let wait_until = Instant::now() + provisioning.propagation_timeout;
+        loop {
+            let mut not_propagated = Vec::new();
+            for record in pending {
+                let result = verify_dns_record(self, record).await;
+                match result.status {
+                    DnsRecordStatus::Propagated => {
+                        trc::event!(
+                            Manage(trc::ManageEvent::DnsRecordPropagated),
+                            Domain = domain_name.to_string(),
+                            Hostname = result.record.name,
+                            Type = result.record.typ,
+                        );
+                    }
+                    DnsRecordStatus::Pending => {
+                        not_propagated.push(result.record);
+                    }
+                    _ => (),
+                }
+            }
+            pending = not_propagated;
+
+            if pending.is_empty() || Instant::now() >= wait_until {
+                break;
+            }
+            tokio::time::sleep(provisioning.polling_interval).await;
+        }
+
+        for record in pending {
+            trc::event!(
+                Manage(trc::ManageEvent::DnsRecordPropagationTimeout),
+                Domain = domain_name.to_string(),
+                Hostname = record.name,
+                Type = record.typ,
+                Value = record.content,
+            );
+        }
+
+        Ok(())
+    }
+
     async fn build_dns_records(&self, domain_name: &str) -> trc::Result<Vec<DnsRecord>> {
         // Obtain server name
         let server_name = self
@@ -122,34 +347,20 @@ impl DnsManagement for Server {
 
         // Process DKIM keys
         if has_macros {
-            keys.resolve_macros(&["env", "file", "cfg"]).await;
+            keys.resolve_all_macros().await;
             keys.log_errors();
         }
         for signature_id in signature_ids {
-            if let (Some(algo), Some(pk), Some(selector)) = (
-                keys.value(format!("{signature_id}.algorithm"))
-                    .and_then(|algo| algo.parse::<Algorithm>().ok()),
-                keys.value(format!("{signature_id}.private-key")),
-                keys.value(format!("{signature_id}.selector")),
-            ) {
-                match obtain_dkim_public_key(algo, pk) {
-                    Ok(public) => {
-                        records.push(DnsRecord {
-                            typ: "TXT".to_string(),
-                            name: format!("{selector}._domainkey.{domain_name}.",),
-                            content: match algo {
-                                Algorithm::Rsa => format!("v=DKIM1; k=rsa; h=sha256; p={public}"),
-                                Algorithm::Ed25519 => {
-                                    format!("v=DKIM1; k=ed25519; h=sha256; p={public}")
-                                }
-                            },
-                        });
-                    }
-                    Err(err) => {
-                        trc::error!(err);
-                    }
-                }
-            }
+            // Include keys pending activation and retired keys still within their overlap period
+            records.extend(
+                dkim_records(&keys, &signature_id)
+                    .into_iter()
+                    .map(|record| DnsRecord {
+                        typ: "TXT".to_string(),
+                        name: record.name,
+                        content: record.content,
+                    }),
+            );
         }
 
         // Add SPF records
@@ -300,3 +511,57 @@ impl DnsManagement for Server {
         Ok(records)
     }
 }
+
+/// Checks whether a record is resolvable, only TXT and MX records can be verified.
+async fn verify_dns_record(server: &Server, record: DnsRecord) -> DnsRecordResult {
+    let resolver = &server.core.smtp.resolvers.dns;
+    let result = match record.typ.as_str() {
+        "TXT" => resolver
+            .txt_raw_lookup(record.name.as_str())
+            .await
+            .map(|result| {
+                std::str::from_utf8(&result)
+                    .unwrap_or_default()
+                    .contains(&record.content)
+            }),
+        "MX" => match dns::DnsRecord::parse("MX", &record.content) {
+            Some(dns::DnsRecord::MX { content, priority }) => resolver
+                .mx_lookup(record.name.as_str())
+                .await
+                .map(|result| {
+                    result.iter().any(|mx| {
+                        mx.preference == priority
+                            && mx.exchanges.iter().any(|host| {
+                                host.trim_end_matches('.').eq_ignore_ascii_case(&content)
+                            })
+                    })
+                }),
+            _ => Ok(false),
+        },
+        _ => {
+            return DnsRecordResult {
+                record,
+                status: DnsRecordStatus::Unverified,
+                reason: None,
+            }
+        }
+    };
+
+    match result {
+        Ok(true) => DnsRecordResult {
+            record,
+            status: DnsRecordStatus::Propagated,
+            reason: None,
+        },
+        Ok(false) => DnsRecordResult {
+            record,
+            status: DnsRecordStatus::Pending,
+            reason: None,
+        },
+        Err(err) => DnsRecordResult {
+            record,
+            status: DnsRecordStatus::Pending,
+            reason: Some(err.to_string()),
+        },
+    }
+}
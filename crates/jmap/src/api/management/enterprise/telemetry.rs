@@ -17,7 +17,7 @@ use common::{
     auth::{oauth::GrantType, AccessToken},
     telemetry::{
         metrics::store::{Metric, MetricsStore},
-        tracers::store::{TracingQuery, TracingStore},
+        tracers::store::{value_contains, EventPosition, EventQuery, TracingQuery, TracingStore},
     },
     Server,
 };
@@ -34,13 +34,14 @@ use store::ahash::{AHashMap, AHashSet};
 use trc::{
     ipc::{bitset::Bitset, subscriber::SubscriberBuilder},
     serializers::json::JsonEventSerializer,
-    Collector, DeliveryEvent, EventType, Key, MetricType, QueueEvent, Value,
+    Collector, DeliveryEvent, EventType, Key, MetricType, QueueEvent,
 };
 use utils::{snowflake::SnowflakeIdGenerator, url_params::UrlParams};
 
 use crate::api::{
-    http::ToHttpResponse, management::Timestamp, HttpRequest, HttpResponse, HttpResponseBody,
-    JsonResponse,
+    http::ToHttpResponse,
+    management::{Cursor, Timestamp},
+    HttpRequest, HttpResponse, HttpResponseBody, JsonResponse,
 };
 
 pub trait TelemetryApi: Sync + Send {
@@ -71,7 +72,12 @@ impl TelemetryApi for Server {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::TracingList)?;
 
-                let page: usize = params.parse("page").unwrap_or(0);
+                let cursor = params.parse::<Cursor>("cursor").and_then(|c| c.to_u64());
+                let page: usize = if cursor.is_none() {
+                    params.parse("page").unwrap_or(0)
+                } else {
+                    0
+                };
                 let limit: usize = params.parse("limit").unwrap_or(0);
                 let mut tracing_query = Vec::new();
                 if let Some(typ) = params.parse("type") {
@@ -81,34 +87,11 @@ impl TelemetryApi for Server {
                     tracing_query.push(TracingQuery::QueueId(queue_id));
                 }
                 if let Some(query) = params.get("filter") {
-                    let mut buf = String::with_capacity(query.len());
-                    let mut in_quote = false;
-                    for ch in query.chars() {
-                        if ch.is_ascii_whitespace() {
-                            if in_quote {
-                                buf.push(' ');
-                            } else if !buf.is_empty() {
-                                tracing_query.push(TracingQuery::Keywords(buf));
-                                buf = String::new();
-                            }
-                        } else if ch == '"' {
-                            buf.push(ch);
-                            if in_quote {
-                                if !buf.is_empty() {
-                                    tracing_query.push(TracingQuery::Keywords(buf));
-                                    buf = String::new();
-                                }
-                                in_quote = false;
-                            } else {
-                                in_quote = true;
-                            }
-                        } else {
-                            buf.push(ch);
-                        }
-                    }
-                    if !buf.is_empty() {
-                        tracing_query.push(TracingQuery::Keywords(buf));
-                    }
+                    tracing_query.extend(
+                        parse_keywords(query)
+                            .into_iter()
+                            .map(TracingQuery::Keywords),
+                    );
                 }
                 let before = params
                     .parse::<Timestamp>("before")
@@ -128,17 +111,24 @@ impl TelemetryApi for Server {
                     .and_then(|e| e.trace_store.as_ref())
                     .ok_or_else(|| manage::unsupported("No tracing store has been configured"))?
                     .store;
-                let span_ids = store.query_spans(&tracing_query, after, before).await?;
+                let mut span_ids = store.query_spans(&tracing_query, after, before).await?;
+                let total = span_ids.len();
+
+                // Spans are sorted newest first, resume after the last span id returned
+                if let Some(cursor) = cursor {
+                    span_ids.retain(|span_id| *span_id < cursor);
+                }
 
-                let (total, span_ids) = if limit > 0 {
+                let span_ids: Vec<u64> = if limit > 0 {
                     let offset = page.saturating_sub(1) * limit;
-                    (
-                        span_ids.len(),
-                        span_ids.into_iter().skip(offset).take(limit).collect(),
-                    )
+                    span_ids.into_iter().skip(offset).take(limit).collect()
                 } else {
-                    (span_ids.len(), span_ids)
+                    span_ids
                 };
+                let cursor = span_ids
+                    .last()
+                    .filter(|_| limit != 0 && span_ids.len() == limit)
+                    .map(|span_id| Cursor::encode(span_id.to_be_bytes()));
 
                 if values && !span_ids.is_empty() {
                     let mut values = Vec::with_capacity(span_ids.len());
@@ -163,6 +153,7 @@ impl TelemetryApi for Server {
                             "data": {
                                 "items": JsonEventSerializer::new(values).with_spans(),
                                 "total": total,
+                                "cursor": cursor,
                             },
                     }))
                     .into_http_response())
@@ -171,11 +162,79 @@ impl TelemetryApi for Server {
                             "data": {
                                 "items": span_ids,
                                 "total": total,
+                                "cursor": cursor,
                             },
                     }))
                     .into_http_response())
                 }
             }
+            ("logs", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::TracingList)?;
+
+                let after_position = params
+                    .parse::<Cursor>("cursor")
+                    .and_then(|c| EventPosition::from_bytes(&c.into_inner()));
+                let limit: usize = params.parse("limit").unwrap_or(100);
+                let mut query = EventQuery::default();
+                for typ in params.get("type").unwrap_or_default().split(',') {
+                    let typ = typ.trim();
+                    if !typ.is_empty() {
+                        query.types.push(EventType::try_parse(typ).ok_or_else(|| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .reason(format!("Unknown event type {typ:?}"))
+                        })?);
+                    }
+                }
+                for account in params.get("account").unwrap_or_default().split(',') {
+                    let account = account.trim();
+                    if !account.is_empty() {
+                        query.accounts.push(account.to_string());
+                    }
+                }
+                if let Some(filter) = params.get("filter") {
+                    query.keywords = parse_keywords(filter)
+                        .into_iter()
+                        .map(|keyword| {
+                            keyword
+                                .strip_prefix('"')
+                                .and_then(|keyword| keyword.strip_suffix('"'))
+                                .map(|keyword| keyword.to_string())
+                                .unwrap_or(keyword)
+                        })
+                        .collect();
+                }
+                let before = params
+                    .parse::<Timestamp>("before")
+                    .map(|t| t.into_inner())
+                    .and_then(SnowflakeIdGenerator::from_timestamp)
+                    .unwrap_or(0);
+                let after = params
+                    .parse::<Timestamp>("after")
+                    .map(|t| t.into_inner())
+                    .and_then(SnowflakeIdGenerator::from_timestamp)
+                    .unwrap_or(0);
+                let store = &self
+                    .core
+                    .enterprise
+                    .as_ref()
+                    .and_then(|e| e.trace_store.as_ref())
+                    .ok_or_else(|| manage::unsupported("No tracing store has been configured"))?
+                    .store;
+
+                let (events, position) = store
+                    .query_events(&query, after, before, after_position, limit)
+                    .await?;
+                let cursor = position.map(|position| Cursor::encode(position.to_bytes()));
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "items": JsonEventSerializer::new(events).with_description(),
+                            "cursor": cursor,
+                        },
+                }))
+                .into_http_response())
+            }
             ("traces", Some("live"), &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::TracingLive)?;
@@ -233,27 +292,7 @@ impl TelemetryApi for Server {
                                                     .chain(event.inner.span.as_ref().map_or(([]).iter(), |s| s.keys.iter()))
                                                 {
                                                     if let Some(needle) = key_filters.get(key).or(filter.as_ref()) {
-                                                        let matches = match value {
-                                                            Value::Static(haystack) => haystack.contains(needle),
-                                                            Value::String(haystack) => haystack.contains(needle),
-                                                            Value::Timestamp(haystack) => {
-                                                                DateTime::from_timestamp(*haystack as i64)
-                                                                    .to_rfc3339()
-                                                                    .contains(needle)
-                                                            }
-                                                            Value::Bool(true) => needle == "true",
-                                                            Value::Bool(false) => needle == "false",
-                                                            Value::Ipv4(haystack) => haystack.to_string().contains(needle),
-                                                            Value::Ipv6(haystack) => haystack.to_string().contains(needle),
-                                                            Value::Event(_) |
-                                                            Value::Array(_) |
-                                                            Value::UInt(_) |
-                                                            Value::Int(_) |
-                                                            Value::Float(_) |
-                                                            Value::Duration(_) |
-                                                            Value::Bytes(_) |
-                                                            Value::None => false,
-                                                        };
+                                                        let matches = value_contains(value, needle);
 
                                                         if matches {
                                                             matched_keys.insert(*key);
@@ -458,12 +497,14 @@ impl TelemetryApi for Server {
                     MetricType::QueueCount,
                     MetricType::UserCount,
                     MetricType::DomainCount,
+                    MetricType::BlobDedupRatio,
                 ] {
                     if metric_types.contains(&metric_type) {
                         let value = match metric_type {
                             MetricType::QueueCount => self.total_queued_messages().await?,
                             MetricType::UserCount => self.total_accounts().await?,
                             MetricType::DomainCount => self.total_domains().await?,
+                            MetricType::BlobDedupRatio => self.blob_dedup_ratio().await?,
                             _ => unreachable!(),
                         };
                         Collector::update_gauge(metric_type, value);
@@ -542,3 +583,38 @@ impl TelemetryApi for Server {
         }
     }
 }
+
+/// Splits a search query into keywords, keeping quoted phrases together
+/// along with their quotes.
+fn parse_keywords(query: &str) -> Vec<String> {
+    let mut keywords = Vec::new();
+    let mut buf = String::with_capacity(query.len());
+    let mut in_quote = false;
+    for ch in query.chars() {
+        if ch.is_ascii_whitespace() {
+            if in_quote {
+                buf.push(' ');
+            } else if !buf.is_empty() {
+                keywords.push(buf);
+                buf = String::new();
+            }
+        } else if ch == '"' {
+            buf.push(ch);
+            if in_quote {
+                if !buf.is_empty() {
+                    keywords.push(buf);
+                    buf = String::new();
+                }
+                in_quote = false;
+            } else {
+                in_quote = true;
+            }
+        } else {
+            buf.push(ch);
+        }
+    }
+    if !buf.is_empty() {
+        keywords.push(buf);
+    }
+    keywords
+}
@@ -18,14 +18,17 @@ use jmap_proto::types::collection::Collection;
 use mail_parser::{DateTime, MessageParser};
 use serde_json::json;
 use std::future::Future;
-use store::write::{BatchBuilder, BlobOp, ValueClass};
+use store::{
+    write::{BatchBuilder, BlobOp, ValueClass},
+    U64_LEN,
+};
 use trc::AddContext;
 use utils::{url_params::UrlParams, BlobHash};
 
 use crate::{
     api::{
         http::{HttpSessionData, ToHttpResponse},
-        management::decode_path_element,
+        management::{decode_path_element, Cursor},
         HttpRequest, HttpResponse, JsonResponse,
     },
     blob::download::BlobDownload,
@@ -86,19 +89,44 @@ impl UndeleteApi for Server {
 
                 let params = UrlParams::new(req.uri().query());
                 let limit = params.parse::<usize>("limit").unwrap_or_default();
-                let mut offset = params
-                    .parse::<usize>("page")
-                    .unwrap_or_default()
-                    .saturating_sub(1)
-                    * limit;
+                let cursor = params.parse::<Cursor>("cursor").and_then(|cursor| {
+                    let cursor = cursor.into_inner();
+                    let (deleted_at, hash) = cursor.split_at_checked(U64_LEN)?;
+                    Some((
+                        u64::from_be_bytes(deleted_at.try_into().ok()?),
+                        hash.to_vec(),
+                    ))
+                });
+                let mut offset = if cursor.is_none() {
+                    params
+                        .parse::<usize>("page")
+                        .unwrap_or_default()
+                        .saturating_sub(1)
+                        * limit
+                } else {
+                    0
+                };
 
                 // Sort ascending by deleted_at
                 let total = deleted.len();
-                deleted.sort_by(|a, b| a.deleted_at.cmp(&b.deleted_at));
+                deleted.sort_by(|a, b| {
+                    (a.deleted_at, a.hash.as_slice()).cmp(&(b.deleted_at, b.hash.as_slice()))
+                });
                 let mut results = Vec::with_capacity(if limit > 0 { limit } else { total });
+                let mut last_key = None;
 
                 for blob in deleted {
+                    // Resume after the last deleted blob returned
+                    if cursor.as_ref().is_some_and(|(deleted_at, hash)| {
+                        (blob.deleted_at, blob.hash.as_slice()) <= (*deleted_at, hash.as_slice())
+                    }) {
+                        continue;
+                    }
+
                     if offset == 0 {
+                        let mut key = blob.deleted_at.to_be_bytes().to_vec();
+                        key.extend_from_slice(blob.hash.as_slice());
+                        last_key = Some(key);
                         results.push(DeletedBlob {
                             hash: URL_SAFE_NO_PAD.encode(blob.hash.as_slice()),
                             size: blob.size,
@@ -116,10 +144,15 @@ impl UndeleteApi for Server {
                     }
                 }
 
+                let cursor = last_key
+                    .filter(|_| limit != 0 && results.len() == limit)
+                    .map(Cursor::encode);
+
                 Ok(JsonResponse::new(json!({
                         "data":{
                             "items": results,
                             "total": total,
+                            "cursor": cursor,
                         },
                 }))
                 .into_http_response())
@@ -195,6 +228,7 @@ impl UndeleteApi for Server {
                                             received_at: (request.time as u64).into(),
                                             source: IngestSource::Smtp,
                                             encrypt: false,
+                                            shared_metadata: false,
                                             session_id: session.session_id,
                                         })
                                         .await
@@ -0,0 +1,240 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, time::Duration};
+
+use common::{
+    auth::{oauth::GrantType, AccessToken},
+    Server,
+};
+use directory::Permission;
+use futures_util::{SinkExt, StreamExt};
+use hyper::{upgrade::Upgraded, Method, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use tokio_tungstenite::WebSocketStream;
+use trc::{
+    ipc::{
+        bitset::Bitset,
+        subscriber::{Interests, SubscriberBuilder},
+    },
+    serializers::json::JsonEventSerializer,
+    Collector, EventType, JmapEvent,
+};
+use tungstenite::{protocol::Role, Message};
+use utils::url_params::UrlParams;
+
+use crate::{
+    api::{
+        http::{HttpSessionData, ToHttpResponse},
+        HttpRequest, HttpResponse, HttpResponseBody, JsonResponse,
+    },
+    websocket::upgrade::websocket_accept_key,
+};
+
+#[derive(Debug, serde::Deserialize)]
+struct EventFilter {
+    #[serde(default)]
+    types: Vec<String>,
+}
+
+pub trait LiveEvents: Sync + Send {
+    fn handle_live_events(
+        &self,
+        req: &mut HttpRequest,
+        access_token: &AccessToken,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl LiveEvents for Server {
+    async fn handle_live_events(
+        &self,
+        req: &mut HttpRequest,
+        access_token: &AccessToken,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        // Validate the access token
+        access_token.assert_has_permission(Permission::TracingLive)?;
+
+        // The request is upgraded in place, so the path is not borrowed from it
+        let action = req.uri().path().split('/').nth(3).unwrap_or_default();
+        match (action, req.method()) {
+            ("token", &Method::GET) => {
+                // Issue a live events token valid for 60 seconds
+                Ok(JsonResponse::new(json!({
+                    "data": self.encode_access_token(GrantType::LiveEvents, access_token.primary_id(), "web", 60).await?,
+                }))
+                .into_http_response())
+            }
+            ("live", &Method::GET) => {
+                let params = UrlParams::new(req.uri().query());
+                let interests = parse_interests(params.get("types").unwrap_or_default().split(','))
+                    .map_err(|err| {
+                        trc::EventType::Resource(trc::ResourceEvent::BadParameters).reason(err)
+                    })?;
+                let derived_key = websocket_accept_key(req)?;
+
+                // Stream events once the connection is upgraded
+                let on_upgrade = hyper::upgrade::on(&mut *req);
+                let session_id = session.session_id;
+                tokio::spawn(async move {
+                    match on_upgrade.await {
+                        Ok(upgraded) => {
+                            stream_events(
+                                WebSocketStream::from_raw_socket(
+                                    TokioIo::new(upgraded),
+                                    Role::Server,
+                                    None,
+                                )
+                                .await,
+                                interests,
+                                session_id,
+                            )
+                            .await;
+                        }
+                        Err(err) => {
+                            trc::event!(
+                                Jmap(JmapEvent::WebsocketError),
+                                Details = "Websocket upgrade failed",
+                                SpanId = session_id,
+                                Reason = err.to_string()
+                            );
+                        }
+                    }
+                });
+
+                Ok(HttpResponse {
+                    status: StatusCode::SWITCHING_PROTOCOLS,
+                    content_type: "".into(),
+                    content_disposition: "".into(),
+                    cache_control: "".into(),
+                    body: HttpResponseBody::WebsocketUpgrade(derived_key),
+                })
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+/// Sends each batch of events the subscriber receives as a JSON text message.
+/// Clients replace the event types they are interested in by sending a
+/// message such as `{"types": ["smtp", "delivery.attempt-start"]}`.
+async fn stream_events(
+    mut stream: WebSocketStream<TokioIo<Upgraded>>,
+    interests: Interests,
+    session_id: u64,
+) {
+    let subscriber_id = format!("live-events-{session_id}");
+    let (_, mut rx) = SubscriberBuilder::new(subscriber_id.clone())
+        .with_interests(interests)
+        .with_lossy(false)
+        .register();
+    let heartbeat = Duration::from_secs(30);
+
+    loop {
+        let message = tokio::select! {
+            message = stream.next() => {
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        match serde_json::from_str::<EventFilter>(&text)
+                            .map_err(|err| err.to_string())
+                            .and_then(|filter| {
+                                parse_interests(filter.types.iter().map(|t| t.as_str()))
+                            }) {
+                            Ok(interests) => {
+                                Collector::update_subscriber(
+                                    subscriber_id.clone(),
+                                    interests,
+                                    false,
+                                );
+                                continue;
+                            }
+                            Err(err) => Message::Text(json!({"error": err}).to_string()),
+                        }
+                    }
+                    Some(Ok(Message::Ping(bytes))) => Message::Pong(bytes),
+                    Some(Ok(Message::Close(frame))) => {
+                        let _ = stream.close(frame).await;
+                        break;
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => {
+                        trc::event!(
+                            Jmap(JmapEvent::WebsocketError),
+                            Details = "Websocket error",
+                            SpanId = session_id,
+                            Reason = err.to_string()
+                        );
+                        break;
+                    }
+                    None => break,
+                }
+            }
+            events = tokio::time::timeout(heartbeat, rx.recv()) => {
+                match events {
+                    Ok(Some(events)) => Message::Text(
+                        serde_json::to_string(
+                            &JsonEventSerializer::new(events).with_description(),
+                        )
+                        .unwrap_or_default(),
+                    ),
+                    Ok(None) => break,
+                    Err(_) => Message::Ping(vec![]),
+                }
+            }
+        };
+
+        if let Err(err) = stream.send(message).await {
+            trc::event!(
+                Jmap(JmapEvent::WebsocketError),
+                Details = "Failed to send message",
+                SpanId = session_id,
+                Reason = err.to_string()
+            );
+            break;
+        }
+    }
+
+    Collector::remove_subscriber(subscriber_id);
+}
+
+/// Builds the interest bitset from a list of event names, where a category
+/// such as `smtp` selects all of its events. An empty list selects all events.
+fn parse_interests<'x>(types: impl IntoIterator<Item = &'x str>) -> Result<Interests, String> {
+    let mut interests = Interests::default();
+    let mut is_empty = true;
+
+    for typ in types {
+        let typ = typ.trim();
+        if typ.is_empty() {
+            continue;
+        }
+        is_empty = false;
+
+        if let Some(event) = EventType::try_parse(typ) {
+            interests.set(event);
+        } else {
+            let prefix = format!("{typ}.");
+            let mut found = false;
+            for event in EventType::variants() {
+                if event.name().starts_with(&prefix) {
+                    interests.set(event);
+                    found = true;
+                }
+            }
+            if !found {
+                return Err(format!("Unknown event type {typ:?}"));
+            }
+        }
+    }
+
+    Ok(if !is_empty {
+        interests
+    } else {
+        Box::new(Bitset::all())
+    })
+}
@@ -0,0 +1,127 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use directory::Permission;
+use hyper::Method;
+use serde_json::json;
+use smtp::core::list::{HeldPost, MailingListManager};
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+use super::decode_path_element;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListMember {
+    pub address: String,
+    pub bounces: i64,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeldPostItem {
+    pub id: String,
+    pub sender: String,
+    pub from: String,
+    pub subject: String,
+    pub received: u64,
+    pub expires: u64,
+}
+
+pub trait ManageMailingLists: Sync + Send {
+    fn handle_manage_mailing_list(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageMailingLists for Server {
+    async fn handle_manage_mailing_list(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let list = path
+            .get(1)
+            .map(|address| decode_path_element(address).to_lowercase())
+            .and_then(|address| self.core.smtp.lists.get(&address).cloned())
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+        match (path.get(2).copied(), path.get(3).copied(), req.method()) {
+            (Some("members"), None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailingListMembers)?;
+
+                let mut members = Vec::new();
+                for address in self.list_members(&list).await? {
+                    members.push(ListMember {
+                        bounces: self.list_bounces(&list, &address).await?,
+                        address,
+                    });
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": members,
+                }))
+                .into_http_response())
+            }
+            (Some("held"), None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailingListMembers)?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self
+                        .list_held(&list)
+                        .await?
+                        .into_iter()
+                        .map(HeldPostItem::from)
+                        .collect::<Vec<_>>(),
+                }))
+                .into_http_response())
+            }
+            (Some("held"), Some(id), &Method::POST | &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailingListModerate)?;
+
+                // Approved posts are distributed, rejected ones are discarded
+                let id = id
+                    .parse::<u64>()
+                    .map_err(|_| trc::ResourceEvent::NotFound.into_err())?;
+                if self
+                    .list_release(&list, id, req.method() == Method::POST)
+                    .await?
+                {
+                    Ok(JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response())
+                } else {
+                    Err(trc::ResourceEvent::NotFound.into_err())
+                }
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+impl From<HeldPost> for HeldPostItem {
+    fn from(post: HeldPost) -> Self {
+        HeldPostItem {
+            id: post.id.to_string(),
+            sender: post.sender,
+            from: post.from,
+            subject: post.subject,
+            received: post.received,
+            expires: post.expires,
+        }
+    }
+}
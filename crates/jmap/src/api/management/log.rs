@@ -1,6 +1,6 @@
 use std::{
     fs::{self, File},
-    io,
+    io::{self, Read, Seek, SeekFrom},
     path::Path,
 };
 
@@ -16,6 +16,8 @@ use utils::url_params::UrlParams;
 
 use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
 
+use super::Cursor;
+
 #[derive(Serialize)]
 struct LogEntry {
     timestamp: String,
@@ -51,17 +53,24 @@ impl LogManagement for Server {
 
         let params = UrlParams::new(req.uri().query());
         let filter = params.get("filter").unwrap_or_default().to_string();
-        let page: usize = params.parse("page").unwrap_or(0);
+        let cursor = params
+            .parse::<Cursor>("cursor")
+            .and_then(|cursor| LogPosition::decode(cursor.into_inner()));
+        let page: usize = if cursor.is_none() {
+            params.parse("page").unwrap_or(0)
+        } else {
+            0
+        };
         let limit: usize = params.parse("limit").unwrap_or(100);
         let offset = page.saturating_sub(1) * limit;
 
         // TODO: Use worker pool
         let (tx, rx) = oneshot::channel();
         tokio::task::spawn_blocking(move || {
-            let _ = tx.send(read_log_files(path, &filter, offset, limit));
+            let _ = tx.send(read_log_files(path, &filter, cursor, offset, limit));
         });
 
-        let (total, items) = rx
+        let (total, items, cursor) = rx
             .await
             .map_err(|err| {
                 trc::EventType::Server(trc::ServerEvent::ThreadError)
@@ -79,18 +88,28 @@ impl LogManagement for Server {
             "data": {
                 "items": items,
                 "total": total,
+                "cursor": cursor.map(|cursor| Cursor::encode(cursor.encode())),
             },
         }))
         .into_http_response())
     }
 }
 
+// Position of the last returned entry: the log file name and the number of
+// bytes preceding that entry in the file. Log files are only appended to, so
+// the position remains valid while newer entries are written.
+struct LogPosition {
+    file_name: String,
+    offset: u64,
+}
+
 fn read_log_files(
     path: impl AsRef<Path>,
     filter: &str,
+    cursor: Option<LogPosition>,
     mut offset: usize,
     limit: usize,
-) -> io::Result<(usize, Vec<LogEntry>)> {
+) -> io::Result<(usize, Vec<LogEntry>, Option<LogPosition>)> {
     let mut logs = fs::read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
     let mut total = 0;
 
@@ -102,10 +121,18 @@ fn read_log_files(
     let mut logs = logs.into_iter();
     while let Some(log) = logs.next() {
         if log.file_type()?.is_file() {
-            let mut rev_lines = RevLines::new(File::open(log.path())?);
+            let file_name = log.file_name().to_string_lossy().into_owned();
+            let file = File::open(log.path())?;
+            let mut file_offset = match &cursor {
+                Some(cursor) if file_name > cursor.file_name => continue,
+                Some(cursor) if file_name == cursor.file_name => cursor.offset,
+                _ => file.metadata()?.len(),
+            };
+            let mut rev_lines = RevLines::new(LogSlice::new(file, file_offset));
 
             while let Some(line) = rev_lines.next() {
                 let line = line.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                file_offset = file_offset.saturating_sub(line.len() as u64 + 1);
                 if filter.is_empty() || line.contains(filter) {
                     total += 1;
                     if offset == 0 {
@@ -116,7 +143,15 @@ fn read_log_files(
                                     total += limit;
                                 }
 
-                                return Ok((total, entries));
+                                return Ok((
+                                    total,
+                                    entries,
+                                    LogPosition {
+                                        file_name,
+                                        offset: file_offset,
+                                    }
+                                    .into(),
+                                ));
                             }
                         }
                     } else {
@@ -127,7 +162,60 @@ fn read_log_files(
         }
     }
 
-    Ok((total, entries))
+    Ok((total, entries, None))
+}
+
+impl LogPosition {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.file_name.len() + std::mem::size_of::<u64>());
+        bytes.extend_from_slice(&self.offset.to_be_bytes());
+        bytes.extend_from_slice(self.file_name.as_bytes());
+        bytes
+    }
+
+    fn decode(bytes: Vec<u8>) -> Option<Self> {
+        let (offset, file_name) = bytes.split_at_checked(std::mem::size_of::<u64>())?;
+        Some(Self {
+            offset: u64::from_be_bytes(offset.try_into().ok()?),
+            file_name: String::from_utf8(file_name.to_vec()).ok()?,
+        })
+    }
+}
+
+// Exposes the first `len` bytes of a log file, so that reverse iteration
+// can resume from a previous position.
+struct LogSlice {
+    file: File,
+    len: u64,
+    pos: u64,
+}
+
+impl LogSlice {
+    fn new(file: File, len: u64) -> Self {
+        Self { file, len, pos: 0 }
+    }
+}
+
+impl Read for LogSlice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = (self.len.saturating_sub(self.pos) as usize).min(buf.len());
+        let read = self.file.read(&mut buf[..max])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for LogSlice {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek position"))?;
+        self.pos = self.file.seek(SeekFrom::Start(pos))?;
+        Ok(self.pos)
+    }
 }
 
 impl LogEntry {
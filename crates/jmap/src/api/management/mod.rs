@@ -4,43 +4,66 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod alias;
 pub mod dkim;
 pub mod dns;
 #[cfg(feature = "enterprise")]
 pub mod enterprise;
+pub mod events;
+pub mod list;
 pub mod log;
 pub mod principal;
+pub mod provision;
+pub mod public_folders;
+pub mod quarantine;
 pub mod queue;
+pub mod read_only;
 pub mod reload;
 pub mod report;
+pub mod reputation;
+pub mod search;
 pub mod settings;
 pub mod sieve;
 pub mod stores;
+pub mod suppression;
 pub mod troubleshoot;
 
 use std::{borrow::Cow, str::FromStr, sync::Arc};
 
+use alias::DisposableAliasManagement;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use common::{auth::AccessToken, Server};
 use directory::{backend::internal::manage, Permission};
 use dkim::DkimManagement;
 use dns::DnsManagement;
 #[cfg(feature = "enterprise")]
 use enterprise::telemetry::TelemetryApi;
+use events::LiveEvents;
 use hyper::Method;
+use list::ManageMailingLists;
 use log::LogManagement;
 use mail_parser::DateTime;
 use principal::PrincipalManager;
+use public_folders::ManagePublicFolders;
+use quarantine::ManageQuarantine;
 use queue::QueueManagement;
+use read_only::{is_mutating_request, ManageReadOnly};
 use reload::ManageReload;
 use report::ManageReports;
+use reputation::ManageReputation;
+use search::ManageSearch;
 use serde::Serialize;
 use settings::ManageSettings;
 use sieve::SieveHandler;
 use store::write::now;
 use stores::ManageStore;
+use suppression::ManageSuppression;
 use troubleshoot::TroubleshootApi;
 
-use crate::{auth::oauth::auth::OAuthApiHandler, email::crypto::CryptoHandler};
+use crate::{
+    auth::oauth::auth::OAuthApiHandler,
+    email::{crypto::CryptoHandler, pgp::PgpEscrowHandler},
+};
 
 use super::{
     http::{fetch_body, HttpSessionData},
@@ -72,6 +95,54 @@ pub enum ManagementApiError<'x> {
     },
 }
 
+impl<'x> From<&'x trc::Error> for ManagementApiError<'x> {
+    fn from(err: &'x trc::Error) -> Self {
+        match err.as_ref() {
+            trc::EventType::Manage(cause) => match cause {
+                trc::ManageEvent::MissingParameter => ManagementApiError::FieldMissing {
+                    field: err.value_as_str(trc::Key::Key).unwrap_or_default(),
+                },
+                trc::ManageEvent::AlreadyExists => ManagementApiError::FieldAlreadyExists {
+                    field: err.value_as_str(trc::Key::Key).unwrap_or_default(),
+                    value: err.value_as_str(trc::Key::Value).unwrap_or_default(),
+                },
+                trc::ManageEvent::NotFound => ManagementApiError::NotFound {
+                    item: err.value_as_str(trc::Key::Key).unwrap_or_default(),
+                },
+                trc::ManageEvent::NotSupported => ManagementApiError::Unsupported {
+                    details: err
+                        .value(trc::Key::Details)
+                        .or_else(|| err.value(trc::Key::Reason))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("Requested action is unsupported"),
+                },
+                trc::ManageEvent::AssertFailed => ManagementApiError::AssertFailed,
+                trc::ManageEvent::Error
+                | trc::ManageEvent::BulkOperation
+                | trc::ManageEvent::DnsRecordPublished
+                | trc::ManageEvent::DnsRecordPublishFailed
+                | trc::ManageEvent::DnsRecordPropagated
+                | trc::ManageEvent::DnsRecordPropagationTimeout
+                | trc::ManageEvent::AliasCreated
+                | trc::ManageEvent::AliasDeleted
+                | trc::ManageEvent::SnapshotCreated
+                | trc::ManageEvent::SnapshotRestored => ManagementApiError::Other {
+                    reason: err.value_as_str(trc::Key::Reason),
+                    details: err
+                        .value_as_str(trc::Key::Details)
+                        .unwrap_or("Unknown error"),
+                },
+            },
+            cause => ManagementApiError::Other {
+                reason: err.value_as_str(trc::Key::Reason),
+                details: err
+                    .value_as_str(trc::Key::Details)
+                    .unwrap_or_else(|| cause.message()),
+            },
+        }
+    }
+}
+
 pub trait ManagementApi: Sync + Send {
     fn handle_api_manage_request(
         &self,
@@ -92,6 +163,11 @@ impl ManagementApi for Server {
         let body = fetch_body(req, 1024 * 1024, session.session_id).await;
         let path = req.uri().path().split('/').skip(2).collect::<Vec<_>>();
 
+        // Reject mutations while the server is in read-only mode
+        if self.is_read_only() && is_mutating_request(&path, req.method(), req.uri().query()) {
+            return Err(manage::unsupported("Server is in read-only mode"));
+        }
+
         match path.first().copied().unwrap_or_default() {
             "queue" => self.handle_manage_queue(req, path, &access_token).await,
             "settings" => {
@@ -99,16 +175,41 @@ impl ManagementApi for Server {
                     .await
             }
             "reports" => self.handle_manage_reports(req, path, &access_token).await,
+            "quarantine" => {
+                self.handle_manage_quarantine(req, path, &access_token)
+                    .await
+            }
+            "search" => self.handle_manage_search(req, path, &access_token).await,
+            "reputation" => {
+                self.handle_manage_reputation(req, path, body, &access_token)
+                    .await
+            }
+            "mailing-list" => {
+                self.handle_manage_mailing_list(req, path, &access_token)
+                    .await
+            }
+            "suppression" => {
+                self.handle_manage_suppression(req, path, body, &access_token)
+                    .await
+            }
             "principal" => {
                 self.handle_manage_principal(req, path, body, &access_token)
                     .await
             }
+            "public-folders" => {
+                self.handle_manage_public_folders(req, path, body, &access_token)
+                    .await
+            }
             "dns" => self.handle_manage_dns(req, path, &access_token).await,
             "store" => {
                 self.handle_manage_store(req, path, body, session, &access_token)
                     .await
             }
-            "reload" => self.handle_manage_reload(req, path, &access_token).await,
+            "reload" => {
+                self.handle_manage_reload(req, path, body, &access_token)
+                    .await
+            }
+            "read-only" => self.handle_manage_read_only(req, path, &access_token).await,
             "dkim" => {
                 self.handle_manage_dkim(req, path, body, &access_token)
                     .await
@@ -118,6 +219,10 @@ impl ManagementApi for Server {
                 self.handle_view_logs(req, &access_token).await
             }
             "sieve" => self.handle_run_sieve(req, path, body, &access_token).await,
+            "events" => {
+                self.handle_live_events(req, &access_token, session)
+                    .await
+            }
             "restart" if req.method() == Method::GET => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::Restart)?;
@@ -143,6 +248,24 @@ impl ManagementApi for Server {
 
                     self.handle_crypto_get(access_token).await
                 }
+                ("pgp-escrow", &Method::GET) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageEncryption)?;
+
+                    self.handle_pgp_escrow_get(access_token).await
+                }
+                ("pgp-escrow", &Method::POST) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageEncryption)?;
+
+                    self.handle_pgp_escrow_post(access_token, body).await
+                }
+                ("pgp-escrow", &Method::DELETE) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageEncryption)?;
+
+                    self.handle_pgp_escrow_delete(access_token).await
+                }
                 ("auth", &Method::GET) => {
                     // Validate the access token
                     access_token.assert_has_permission(Permission::ManagePasswords)?;
@@ -155,6 +278,26 @@ impl ManagementApi for Server {
 
                     self.handle_account_auth_post(req, access_token, body).await
                 }
+                ("aliases", _) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageAliases)?;
+
+                    self.handle_manage_aliases(req, path, body, &access_token)
+                        .await
+                }
+                ("delegates", &Method::GET) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageDelegation)?;
+
+                    self.handle_delegates_get(access_token.primary_id()).await
+                }
+                ("delegates", &Method::POST) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageDelegation)?;
+
+                    self.handle_delegates_set(&access_token, access_token.primary_id(), body)
+                        .await
+                }
                 _ => Err(trc::ResourceEvent::NotFound.into_err()),
             },
             "troubleshoot" => {
@@ -203,6 +346,9 @@ pub fn decode_path_element(item: &str) -> Cow<'_, str> {
 pub(super) struct FutureTimestamp(u64);
 pub(super) struct Timestamp(u64);
 
+/// Opaque pagination cursor holding the key of the last item returned.
+pub(super) struct Cursor(Vec<u8>);
+
 impl FromStr for Timestamp {
     type Err = ();
 
@@ -230,12 +376,39 @@ impl FromStr for FutureTimestamp {
     }
 }
 
+impl FromStr for Cursor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        URL_SAFE_NO_PAD
+            .decode(s)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(Cursor)
+            .ok_or(())
+    }
+}
+
 impl FutureTimestamp {
     pub fn into_inner(self) -> u64 {
         self.0
     }
 }
 
+impl Cursor {
+    pub fn encode(key: impl AsRef<[u8]>) -> String {
+        URL_SAFE_NO_PAD.encode(key.as_ref())
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        self.0.as_slice().try_into().ok().map(u64::from_be_bytes)
+    }
+}
+
 impl Timestamp {
     pub fn into_inner(self) -> u64 {
         self.0
@@ -6,11 +6,15 @@
 
 use std::sync::{atomic::Ordering, Arc};
 
-use common::{auth::AccessToken, Server};
+use common::{
+    auth::{delegation::Delegation, AccessToken},
+    ipc::CacheInvalidation,
+    Server,
+};
 use directory::{
     backend::internal::{
         lookup::DirectoryStore,
-        manage::{self, not_found, ManageDirectory, UpdatePrincipal},
+        manage::{self, err_exists, err_missing, not_found, ManageDirectory, UpdatePrincipal},
         PrincipalAction, PrincipalField, PrincipalUpdate, PrincipalValue, SpecialSecrets,
     },
     DirectoryInner, Permission, Principal, QueryBy, Type,
@@ -18,12 +22,21 @@ use directory::{
 
 use hyper::{header, Method};
 use serde_json::json;
+use store::ahash::AHashSet;
 use trc::AddContext;
-use utils::url_params::UrlParams;
+use utils::{
+    map::bitmap::{Bitmap, BitmapItem},
+    url_params::UrlParams,
+};
 
 use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
 
-use super::decode_path_element;
+use super::{
+    decode_path_element,
+    dns::DnsManagement,
+    provision::{AccountDefaultsApply, AccountProvisioning},
+    Cursor, ManagementApiError,
+};
 use std::future::Future;
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -37,6 +50,46 @@ pub enum AccountAuthRequest {
     RemoveAppPassword { name: String },
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "action")]
+#[serde(rename_all = "camelCase")]
+pub enum BulkPrincipalOperation {
+    Create {
+        principal: Principal,
+    },
+    Update {
+        name: String,
+        changes: Vec<PrincipalUpdate>,
+    },
+    Aliases {
+        name: String,
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    Delete {
+        name: String,
+    },
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct BulkPrincipalResponse {
+    pub applied: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkPrincipalResult>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct BulkPrincipalResult {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<serde_json::Value>,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct AccountAuthResponse {
     #[serde(rename = "otpEnabled")]
@@ -45,6 +98,13 @@ pub struct AccountAuthResponse {
     pub app_passwords: Vec<String>,
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct DelegateGrant {
+    pub name: String,
+    #[serde(default)]
+    pub rights: Vec<Delegation>,
+}
+
 pub trait PrincipalManager: Sync + Send {
     fn handle_manage_principal(
         &self,
@@ -66,6 +126,51 @@ pub trait PrincipalManager: Sync + Send {
         body: Option<Vec<u8>>,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
 
+    fn handle_bulk_principal(
+        &self,
+        access_token: &AccessToken,
+        operations: Vec<BulkPrincipalOperation>,
+    ) -> impl Future<Output = trc::Result<BulkPrincipalResponse>> + Send;
+
+    fn handle_delegates_get(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_delegates_set(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn resolve_principal(
+        &self,
+        access_token: &AccessToken,
+        name: &str,
+    ) -> impl Future<Output = trc::Result<(u32, Type)>> + Send;
+
+    fn principal_create(
+        &self,
+        access_token: &AccessToken,
+        principal: Principal,
+    ) -> impl Future<Output = trc::Result<u32>> + Send;
+
+    fn principal_update(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        typ: Type,
+        changes: Vec<PrincipalUpdate>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn principal_delete(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        typ: Type,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
     fn assert_supported_directory(&self) -> trc::Result<()>;
 }
 
@@ -87,72 +192,8 @@ impl PrincipalManager for Server {
                                 .from_json_error(err)
                         })?;
 
-                // Validate the access token
-                access_token.assert_has_permission(match principal.typ() {
-                    Type::Individual => Permission::IndividualCreate,
-                    Type::Group => Permission::GroupCreate,
-                    Type::List => Permission::MailingListCreate,
-                    Type::Domain => Permission::DomainCreate,
-                    Type::Tenant => Permission::TenantCreate,
-                    Type::Role => Permission::RoleCreate,
-                    Type::ApiKey => Permission::ApiKeyCreate,
-                    Type::OauthClient => Permission::OauthClientCreate,
-                    Type::Resource | Type::Location | Type::Other => Permission::PrincipalCreate,
-                })?;
-
-                // SPDX-SnippetBegin
-                // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
-                // SPDX-License-Identifier: LicenseRef-SEL
-
-                #[cfg(feature = "enterprise")]
-                if (matches!(principal.typ(), Type::Tenant)
-                    || principal.has_field(PrincipalField::Tenant))
-                    && !self.core.is_enterprise_edition()
-                {
-                    return Err(manage::enterprise());
-                }
-
-                // SPDX-SnippetEnd
-
-                // Make sure the current directory supports updates
-                if matches!(principal.typ(), Type::Individual) {
-                    self.assert_supported_directory()?;
-                }
-
-                // Validate roles
-                let tenant_id = access_token.tenant.map(|t| t.id);
-                for name in principal
-                    .get_str_array(PrincipalField::Roles)
-                    .unwrap_or_default()
-                {
-                    if let Some(pinfo) = self
-                        .store()
-                        .get_principal_info(name)
-                        .await
-                        .caused_by(trc::location!())?
-                        .filter(|v| v.typ == Type::Role && v.has_tenant_access(tenant_id))
-                        .or_else(|| PrincipalField::Roles.map_internal_roles(name))
-                    {
-                        let role_permissions =
-                            self.get_role_permissions(pinfo.id).await?.finalize_as_ref();
-                        let mut allowed_permissions = role_permissions.clone();
-                        allowed_permissions.intersection(&access_token.permissions);
-                        if allowed_permissions != role_permissions {
-                            return Err(manage::error(
-                                "Invalid role",
-                                format!("Your account cannot grant the {name:?} role").into(),
-                            ));
-                        }
-                    }
-                }
-
                 // Create principal
-                let result = self
-                    .core
-                    .storage
-                    .data
-                    .create_principal(principal, tenant_id, Some(&access_token.permissions))
-                    .await?;
+                let result = self.principal_create(access_token, principal).await?;
 
                 Ok(JsonResponse::new(json!({
                     "data": result,
@@ -163,8 +204,15 @@ impl PrincipalManager for Server {
                 // List principal ids
                 let params = UrlParams::new(req.uri().query());
                 let filter = params.get("filter");
-                let page: usize = params.parse("page").unwrap_or(0);
                 let limit: usize = params.parse("limit").unwrap_or(0);
+                let after = params
+                    .parse::<Cursor>("cursor")
+                    .and_then(|cursor| String::from_utf8(cursor.into_inner()).ok());
+                let page: usize = if after.is_none() {
+                    params.parse("page").unwrap_or(0)
+                } else {
+                    0
+                };
                 let count = params.get("count").is_some();
 
                 // Parse types
@@ -253,9 +301,18 @@ impl PrincipalManager for Server {
                     .core
                     .storage
                     .data
-                    .list_principals(filter, tenant, &types, &fields, page, limit)
+                    .list_principals_after(
+                        filter,
+                        tenant,
+                        &types,
+                        &fields,
+                        after.as_deref(),
+                        page,
+                        limit,
+                    )
                     .await?;
 
+                principals.cursor = principals.cursor.map(Cursor::encode);
                 if count {
                     principals.items.clear();
                 }
@@ -265,29 +322,55 @@ impl PrincipalManager for Server {
                 }))
                 .into_http_response())
             }
-            (Some(name), method) => {
-                // Fetch, update or delete principal
+            (Some(&"bulk"), &Method::POST) => {
+                // Parse operations
+                let operations = serde_json::from_slice::<Vec<BulkPrincipalOperation>>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.handle_bulk_principal(access_token, operations).await?,
+                }))
+                .into_http_response())
+            }
+            (Some(name), method) if path.get(2) == Some(&"delegates") => {
+                // Fetch or update delegates
                 let name = decode_path_element(name);
-                let (account_id, typ) = self
-                    .core
-                    .storage
-                    .data
-                    .get_principal_info(name.as_ref())
-                    .await?
-                    .filter(|p| p.has_tenant_access(access_token.tenant.map(|t| t.id)))
-                    .map(|p| (p.id, p.typ))
-                    .ok_or_else(|| not_found(name.to_string()))?;
+                let (account_id, typ) = self.resolve_principal(access_token, &name).await?;
+                if !matches!(typ, Type::Individual | Type::Group) {
+                    return Err(manage::error(
+                        "Invalid principal type",
+                        "Only individual and group accounts can delegate access.".into(),
+                    ));
+                }
 
-                // SPDX-SnippetBegin
-                // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
-                // SPDX-License-Identifier: LicenseRef-SEL
+                match *method {
+                    Method::GET => {
+                        // Validate the access token
+                        access_token.assert_has_permission(match typ {
+                            Type::Group => Permission::GroupGet,
+                            _ => Permission::IndividualGet,
+                        })?;
 
-                #[cfg(feature = "enterprise")]
-                if matches!(typ, Type::Tenant) && !self.core.is_enterprise_edition() {
-                    return Err(manage::enterprise());
-                }
+                        self.handle_delegates_get(account_id).await
+                    }
+                    Method::POST => {
+                        // Validate the access token
+                        access_token.assert_has_permission(update_permission(typ))?;
 
-                // SPDX-SnippetEnd
+                        self.handle_delegates_set(access_token, account_id, body)
+                            .await
+                    }
+                    _ => Err(trc::ResourceEvent::NotFound.into_err()),
+                }
+            }
+            (Some(name), method) => {
+                // Fetch, update or delete principal
+                let name = decode_path_element(name);
+                let (account_id, typ) = self.resolve_principal(access_token, &name).await?;
 
                 match *method {
                     Method::GET => {
@@ -328,47 +411,7 @@ impl PrincipalManager for Server {
                         .into_http_response())
                     }
                     Method::DELETE => {
-                        // Validate the access token
-                        access_token.assert_has_permission(match typ {
-                            Type::Individual => Permission::IndividualDelete,
-                            Type::Group => Permission::GroupDelete,
-                            Type::List => Permission::MailingListDelete,
-                            Type::Domain => Permission::DomainDelete,
-                            Type::Tenant => Permission::TenantDelete,
-                            Type::Role => Permission::RoleDelete,
-                            Type::ApiKey => Permission::ApiKeyDelete,
-                            Type::OauthClient => Permission::OauthClientDelete,
-                            Type::Resource | Type::Location | Type::Other => {
-                                Permission::PrincipalDelete
-                            }
-                        })?;
-
-                        // Delete account
-                        self.core
-                            .storage
-                            .data
-                            .delete_principal(QueryBy::Id(account_id))
-                            .await?;
-
-                        // Remove FTS index
-                        if matches!(typ, Type::Individual | Type::Group) {
-                            self.core.storage.fts.remove_all(account_id).await?;
-                        }
-
-                        // Remove entries from cache
-                        self.inner
-                            .data
-                            .http_auth_cache
-                            .retain(|_, id| id.item != account_id);
-
-                        if matches!(typ, Type::Role | Type::Tenant) {
-                            // Update permissions cache
-                            self.inner.data.permissions.clear();
-                            self.inner
-                                .data
-                                .permissions_version
-                                .fetch_add(1, Ordering::Relaxed);
-                        }
+                        self.principal_delete(access_token, account_id, typ).await?;
 
                         Ok(JsonResponse::new(json!({
                             "data": (),
@@ -376,22 +419,6 @@ impl PrincipalManager for Server {
                         .into_http_response())
                     }
                     Method::PATCH => {
-                        // Validate the access token
-                        let permission_needed = match typ {
-                            Type::Individual => Permission::IndividualUpdate,
-                            Type::Group => Permission::GroupUpdate,
-                            Type::List => Permission::MailingListUpdate,
-                            Type::Domain => Permission::DomainUpdate,
-                            Type::Tenant => Permission::TenantUpdate,
-                            Type::Role => Permission::RoleUpdate,
-                            Type::ApiKey => Permission::ApiKeyUpdate,
-                            Type::OauthClient => Permission::OauthClientUpdate,
-                            Type::Resource | Type::Location | Type::Other => {
-                                Permission::PrincipalUpdate
-                            }
-                        };
-                        access_token.assert_has_permission(permission_needed)?;
-
                         let changes = serde_json::from_slice::<Vec<PrincipalUpdate>>(
                             body.as_deref().unwrap_or_default(),
                         )
@@ -400,138 +427,9 @@ impl PrincipalManager for Server {
                                 .from_json_error(err)
                         })?;
 
-                        // Validate changes
-                        let mut needs_assert = false;
-                        let mut expire_session = false;
-                        let mut expire_token = false;
-                        let mut is_role_change = false;
-
-                        for change in &changes {
-                            match change.field {
-                                PrincipalField::Secrets => {
-                                    expire_session = true;
-                                    needs_assert = true;
-                                }
-                                PrincipalField::Name
-                                | PrincipalField::Emails
-                                | PrincipalField::Quota
-                                | PrincipalField::UsedQuota
-                                | PrincipalField::Description
-                                | PrincipalField::Type
-                                | PrincipalField::Picture
-                                | PrincipalField::MemberOf
-                                | PrincipalField::Members
-                                | PrincipalField::Lists
-                                | PrincipalField::Urls
-                                | PrincipalField::ExternalMembers => (),
-                                PrincipalField::Tenant => {
-                                    // Tenants are not allowed to change their tenantId
-                                    if access_token.tenant.is_some() {
-                                        trc::bail!(trc::SecurityEvent::Unauthorized
-                                            .into_err()
-                                            .details(permission_needed.name())
-                                            .ctx(
-                                                trc::Key::Reason,
-                                                "Tenants cannot change their tenantId"
-                                            ));
-                                    }
-                                }
-                                PrincipalField::Roles
-                                | PrincipalField::EnabledPermissions
-                                | PrincipalField::DisabledPermissions => {
-                                    if matches!(typ, Type::Role | Type::Tenant) {
-                                        is_role_change = true;
-                                    } else {
-                                        expire_token = true;
-                                    }
-
-                                    if change.field == PrincipalField::Roles
-                                        && matches!(
-                                            change.action,
-                                            PrincipalAction::AddItem | PrincipalAction::Set
-                                        )
-                                    {
-                                        let roles = match &change.value {
-                                            PrincipalValue::String(v) => std::slice::from_ref(v),
-                                            PrincipalValue::StringList(vec) => vec,
-                                            PrincipalValue::Integer(_)
-                                            | PrincipalValue::IntegerList(_) => continue,
-                                        };
-
-                                        // Validate roles
-                                        let tenant_id = access_token.tenant.map(|t| t.id);
-                                        for name in roles {
-                                            if let Some(pinfo) = self
-                                                .store()
-                                                .get_principal_info(name)
-                                                .await
-                                                .caused_by(trc::location!())?
-                                                .filter(|v| {
-                                                    v.typ == Type::Role
-                                                        && v.has_tenant_access(tenant_id)
-                                                })
-                                                .or_else(|| {
-                                                    PrincipalField::Roles.map_internal_roles(name)
-                                                })
-                                            {
-                                                let role_permissions = self
-                                                    .get_role_permissions(pinfo.id)
-                                                    .await?
-                                                    .finalize_as_ref();
-                                                let mut allowed_permissions =
-                                                    role_permissions.clone();
-                                                allowed_permissions
-                                                    .intersection(&access_token.permissions);
-                                                if allowed_permissions != role_permissions {
-                                                    return Err(manage::error(
-                                                        "Invalid role",
-                                                        format!("Your account cannot grant the {name:?} role").into(),
-                                                    ));
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        if needs_assert {
-                            self.assert_supported_directory()?;
-                        }
-
-                        // Update principal
-                        self.core
-                            .storage
-                            .data
-                            .update_principal(
-                                UpdatePrincipal::by_id(account_id)
-                                    .with_updates(changes)
-                                    .with_tenant(access_token.tenant.map(|t| t.id))
-                                    .with_allowed_permissions(&access_token.permissions),
-                            )
+                        self.principal_update(access_token, account_id, typ, changes)
                             .await?;
 
-                        if expire_session {
-                            // Remove entries from cache
-                            self.inner
-                                .data
-                                .http_auth_cache
-                                .retain(|_, id| id.item != account_id);
-                        }
-
-                        if is_role_change {
-                            // Update permissions cache
-                            self.inner.data.permissions.clear();
-                            self.inner
-                                .data
-                                .permissions_version
-                                .fetch_add(1, Ordering::Relaxed);
-                        }
-
-                        if expire_token {
-                            self.inner.data.access_tokens.remove(&account_id);
-                        }
-
                         Ok(JsonResponse::new(json!({
                             "data": (),
                         }))
@@ -708,6 +606,494 @@ impl PrincipalManager for Server {
         .into_http_response())
     }
 
+    async fn handle_bulk_principal(
+        &self,
+        access_token: &AccessToken,
+        operations: Vec<BulkPrincipalOperation>,
+    ) -> trc::Result<BulkPrincipalResponse> {
+        let mut response = BulkPrincipalResponse {
+            results: operations
+                .iter()
+                .map(|op| BulkPrincipalResult {
+                    name: op.name(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        // Validate the whole batch before applying any changes
+        let mut targets = AHashSet::with_capacity(operations.len());
+        for (op, result) in operations.iter().zip(response.results.iter_mut()) {
+            if let Err(err) = validate_bulk_operation(self, access_token, op, &mut targets).await {
+                result.error = serde_json::to_value(ManagementApiError::from(&err)).ok();
+            }
+        }
+        response.failed = response
+            .results
+            .iter()
+            .filter(|result| result.error.is_some())
+            .count();
+
+        if response.failed == 0 {
+            // Apply changes, each operation is atomic on its own
+            response.applied = true;
+            for (op, result) in operations.into_iter().zip(response.results.iter_mut()) {
+                let op_result = match op {
+                    BulkPrincipalOperation::Create { principal } => self
+                        .principal_create(access_token, principal)
+                        .await
+                        .map(Some),
+                    BulkPrincipalOperation::Update { name, changes } => {
+                        match self.resolve_principal(access_token, &name).await {
+                            Ok((account_id, typ)) => self
+                                .principal_update(access_token, account_id, typ, changes)
+                                .await
+                                .map(|_| None),
+                            Err(err) => Err(err),
+                        }
+                    }
+                    BulkPrincipalOperation::Aliases { name, add, remove } => {
+                        let changes = add
+                            .into_iter()
+                            .map(|alias| {
+                                PrincipalUpdate::add_item(
+                                    PrincipalField::Emails,
+                                    PrincipalValue::String(alias),
+                                )
+                            })
+                            .chain(remove.into_iter().map(|alias| {
+                                PrincipalUpdate::remove_item(
+                                    PrincipalField::Emails,
+                                    PrincipalValue::String(alias),
+                                )
+                            }))
+                            .collect();
+                        match self.resolve_principal(access_token, &name).await {
+                            Ok((account_id, typ)) => self
+                                .principal_update(access_token, account_id, typ, changes)
+                                .await
+                                .map(|_| None),
+                            Err(err) => Err(err),
+                        }
+                    }
+                    BulkPrincipalOperation::Delete { name } => {
+                        match self.resolve_principal(access_token, &name).await {
+                            Ok((account_id, typ)) => self
+                                .principal_delete(access_token, account_id, typ)
+                                .await
+                                .map(|_| None),
+                            Err(err) => Err(err),
+                        }
+                    }
+                };
+
+                match op_result {
+                    Ok(id) => {
+                        result.id = id;
+                        response.succeeded += 1;
+                    }
+                    Err(err) => {
+                        result.error = serde_json::to_value(ManagementApiError::from(&err)).ok();
+                        response.failed += 1;
+                    }
+                }
+            }
+        }
+
+        trc::event!(
+            Manage(trc::ManageEvent::BulkOperation),
+            AccountId = access_token.primary_id(),
+            Total = response.results.len(),
+            TotalSuccesses = response.succeeded,
+            TotalFailures = response.failed,
+            Result = response.applied,
+            Details = response
+                .results
+                .iter()
+                .map(|result| result.name.clone())
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(response)
+    }
+
+    async fn handle_delegates_get(&self, account_id: u32) -> trc::Result<HttpResponse> {
+        let mut delegates = Vec::new();
+        for (delegate_id, rights) in self.get_delegates(account_id).await? {
+            if let Some(mut principal) = self
+                .core
+                .storage
+                .directory
+                .query(QueryBy::Id(delegate_id), false)
+                .await?
+            {
+                delegates.push(DelegateGrant {
+                    name: principal.take_str(PrincipalField::Name).unwrap_or_default(),
+                    rights: rights.collect(),
+                });
+            }
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": delegates,
+        }))
+        .into_http_response())
+    }
+
+    async fn handle_delegates_set(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        let grants =
+            serde_json::from_slice::<Vec<DelegateGrant>>(body.as_deref().unwrap_or_default())
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+
+        // Validate delegates
+        let mut delegates = Vec::with_capacity(grants.len());
+        for grant in grants {
+            let (delegate_id, typ) = self.resolve_principal(access_token, &grant.name).await?;
+            if delegate_id == account_id || !matches!(typ, Type::Individual | Type::Group) {
+                return Err(manage::error(
+                    "Invalid delegate",
+                    format!("Principal {} cannot be a delegate.", grant.name).into(),
+                ));
+            }
+            delegates.push((
+                delegate_id,
+                grant
+                    .rights
+                    .into_iter()
+                    .filter(|right| right.is_valid())
+                    .collect::<Bitmap<Delegation>>(),
+            ));
+        }
+
+        for (delegate_id, rights) in delegates {
+            self.set_delegation(account_id, delegate_id, rights).await?;
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": (),
+        }))
+        .into_http_response())
+    }
+
+    async fn resolve_principal(
+        &self,
+        access_token: &AccessToken,
+        name: &str,
+    ) -> trc::Result<(u32, Type)> {
+        let (account_id, typ) = self
+            .core
+            .storage
+            .data
+            .get_principal_info(name)
+            .await?
+            .filter(|p| p.has_tenant_access(access_token.tenant.map(|t| t.id)))
+            .map(|p| (p.id, p.typ))
+            .ok_or_else(|| not_found(name.to_string()))?;
+
+        // SPDX-SnippetBegin
+        // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+        // SPDX-License-Identifier: LicenseRef-SEL
+
+        #[cfg(feature = "enterprise")]
+        if matches!(typ, Type::Tenant) && !self.core.is_enterprise_edition() {
+            return Err(manage::enterprise());
+        }
+
+        // SPDX-SnippetEnd
+
+        Ok((account_id, typ))
+    }
+
+    async fn principal_create(
+        &self,
+        access_token: &AccessToken,
+        mut principal: Principal,
+    ) -> trc::Result<u32> {
+        // Validate the access token
+        access_token.assert_has_permission(create_permission(principal.typ()))?;
+
+        // SPDX-SnippetBegin
+        // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+        // SPDX-License-Identifier: LicenseRef-SEL
+
+        #[cfg(feature = "enterprise")]
+        if (matches!(principal.typ(), Type::Tenant) || principal.has_field(PrincipalField::Tenant))
+            && !self.core.is_enterprise_edition()
+        {
+            return Err(manage::enterprise());
+        }
+
+        // SPDX-SnippetEnd
+
+        // Make sure the current directory supports updates
+        if matches!(principal.typ(), Type::Individual) {
+            self.assert_supported_directory()?;
+        }
+
+        // Apply domain and tenant defaults
+        let provisioning = if matches!(principal.typ(), Type::Individual) {
+            self.apply_account_defaults(access_token, &mut principal)
+                .await?
+        } else {
+            AccountProvisioning::default()
+        };
+
+        // Validate roles
+        let tenant_id = access_token.tenant.map(|t| t.id);
+        for name in principal
+            .get_str_array(PrincipalField::Roles)
+            .unwrap_or_default()
+        {
+            if let Some(pinfo) = self
+                .store()
+                .get_principal_info(name)
+                .await
+                .caused_by(trc::location!())?
+                .filter(|v| v.typ == Type::Role && v.has_tenant_access(tenant_id))
+                .or_else(|| PrincipalField::Roles.map_internal_roles(name))
+            {
+                let role_permissions = self.get_role_permissions(pinfo.id).await?.finalize_as_ref();
+                let mut allowed_permissions = role_permissions.clone();
+                allowed_permissions.intersection(&access_token.permissions);
+                if allowed_permissions != role_permissions {
+                    return Err(manage::error(
+                        "Invalid role",
+                        format!("Your account cannot grant the {name:?} role").into(),
+                    ));
+                }
+            }
+        }
+
+        // Create principal
+        let domain_name = (matches!(principal.typ(), Type::Domain)
+            && self.core.dns.provisioning.is_some())
+        .then(|| principal.name().to_string());
+        let has_emails = principal.has_field(PrincipalField::Emails);
+        let account_id = self
+            .core
+            .storage
+            .data
+            .create_principal(principal, tenant_id, Some(&access_token.permissions))
+            .await?;
+
+        // Accept mail for the new addresses
+        if has_emails {
+            self.invalidate_address_filters().await;
+        }
+
+        // Create default folders and scripts
+        self.provision_account(account_id, provisioning).await?;
+
+        // Publish the DNS records of new domains
+        if let Some(domain_name) = domain_name {
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(err) = server.provision_dns_records(&domain_name).await {
+                    trc::error!(err.details("Failed to provision DNS records"));
+                }
+            });
+        }
+
+        Ok(account_id)
+    }
+
+    async fn principal_update(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        typ: Type,
+        changes: Vec<PrincipalUpdate>,
+    ) -> trc::Result<()> {
+        // Validate the access token
+        let permission_needed = update_permission(typ);
+        access_token.assert_has_permission(permission_needed)?;
+
+        // Validate changes
+        let mut needs_assert = false;
+        let mut expire_session = false;
+        let mut expire_token = false;
+        let mut is_role_change = false;
+        let mut is_address_change = false;
+
+        for change in &changes {
+            match change.field {
+                PrincipalField::Secrets => {
+                    expire_session = true;
+                    needs_assert = true;
+                }
+                PrincipalField::Emails => {
+                    is_address_change |= matches!(
+                        change.action,
+                        PrincipalAction::AddItem | PrincipalAction::Set
+                    );
+                }
+                PrincipalField::Name
+                | PrincipalField::Quota
+                | PrincipalField::UsedQuota
+                | PrincipalField::Description
+                | PrincipalField::Type
+                | PrincipalField::Picture
+                | PrincipalField::MemberOf
+                | PrincipalField::Members
+                | PrincipalField::Lists
+                | PrincipalField::Urls
+                | PrincipalField::ExternalMembers
+                | PrincipalField::Locale => (),
+                PrincipalField::Tenant => {
+                    // Tenants are not allowed to change their tenantId
+                    if access_token.tenant.is_some() {
+                        trc::bail!(trc::SecurityEvent::Unauthorized
+                            .into_err()
+                            .details(permission_needed.name())
+                            .ctx(trc::Key::Reason, "Tenants cannot change their tenantId"));
+                    }
+                }
+                PrincipalField::Roles
+                | PrincipalField::EnabledPermissions
+                | PrincipalField::DisabledPermissions => {
+                    if matches!(typ, Type::Role | Type::Tenant) {
+                        is_role_change = true;
+                    } else {
+                        expire_token = true;
+                    }
+
+                    if change.field == PrincipalField::Roles
+                        && matches!(
+                            change.action,
+                            PrincipalAction::AddItem | PrincipalAction::Set
+                        )
+                    {
+                        let roles = match &change.value {
+                            PrincipalValue::String(v) => std::slice::from_ref(v),
+                            PrincipalValue::StringList(vec) => vec,
+                            PrincipalValue::Integer(_) | PrincipalValue::IntegerList(_) => continue,
+                        };
+
+                        // Validate roles
+                        let tenant_id = access_token.tenant.map(|t| t.id);
+                        for name in roles {
+                            if let Some(pinfo) = self
+                                .store()
+                                .get_principal_info(name)
+                                .await
+                                .caused_by(trc::location!())?
+                                .filter(|v| v.typ == Type::Role && v.has_tenant_access(tenant_id))
+                                .or_else(|| PrincipalField::Roles.map_internal_roles(name))
+                            {
+                                let role_permissions =
+                                    self.get_role_permissions(pinfo.id).await?.finalize_as_ref();
+                                let mut allowed_permissions = role_permissions.clone();
+                                allowed_permissions.intersection(&access_token.permissions);
+                                if allowed_permissions != role_permissions {
+                                    return Err(manage::error(
+                                        "Invalid role",
+                                        format!("Your account cannot grant the {name:?} role")
+                                            .into(),
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if needs_assert {
+            self.assert_supported_directory()?;
+        }
+
+        // Update principal
+        self.core
+            .storage
+            .data
+            .update_principal(
+                UpdatePrincipal::by_id(account_id)
+                    .with_updates(changes)
+                    .with_tenant(access_token.tenant.map(|t| t.id))
+                    .with_allowed_permissions(&access_token.permissions),
+            )
+            .await?;
+
+        if expire_session {
+            // Remove entries from cache
+            self.inner
+                .data
+                .http_auth_cache
+                .retain(|_, id| id.item != account_id);
+        }
+
+        if is_role_change {
+            // Update permissions cache
+            self.inner.data.permissions.clear();
+            self.inner
+                .data
+                .permissions_version
+                .fetch_add(1, Ordering::Relaxed);
+            self.notify_cluster(CacheInvalidation::Permissions);
+        }
+
+        if expire_token {
+            self.invalidate_access_token(account_id);
+        } else if expire_session {
+            self.notify_cluster(CacheInvalidation::AccessToken(account_id));
+        }
+
+        if is_address_change {
+            self.invalidate_address_filters().await;
+        }
+
+        Ok(())
+    }
+
+    async fn principal_delete(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        typ: Type,
+    ) -> trc::Result<()> {
+        // Validate the access token
+        access_token.assert_has_permission(delete_permission(typ))?;
+
+        // Delete account
+        self.core
+            .storage
+            .data
+            .delete_principal(QueryBy::Id(account_id))
+            .await?;
+
+        // Remove FTS index
+        if matches!(typ, Type::Individual | Type::Group) {
+            self.core.storage.fts.remove_all(account_id).await?;
+        }
+
+        // Remove entries from cache
+        self.inner
+            .data
+            .http_auth_cache
+            .retain(|_, id| id.item != account_id);
+        self.notify_cluster(CacheInvalidation::AccessToken(account_id));
+
+        if matches!(typ, Type::Role | Type::Tenant) {
+            // Update permissions cache
+            self.inner.data.permissions.clear();
+            self.inner
+                .data
+                .permissions_version
+                .fetch_add(1, Ordering::Relaxed);
+            self.notify_cluster(CacheInvalidation::Permissions);
+        }
+
+        Ok(())
+    }
+
     fn assert_supported_directory(&self) -> trc::Result<()> {
         let class = match &self.core.storage.directory.store {
             DirectoryInner::Internal(_) => return Ok(()),
@@ -730,3 +1116,103 @@ impl PrincipalManager for Server {
         )))
     }
 }
+
+impl BulkPrincipalOperation {
+    pub fn name(&self) -> String {
+        match self {
+            BulkPrincipalOperation::Create { principal } => principal.name().to_string(),
+            BulkPrincipalOperation::Update { name, .. }
+            | BulkPrincipalOperation::Aliases { name, .. }
+            | BulkPrincipalOperation::Delete { name } => name.clone(),
+        }
+    }
+}
+
+async fn validate_bulk_operation(
+    server: &Server,
+    access_token: &AccessToken,
+    op: &BulkPrincipalOperation,
+    targets: &mut AHashSet<String>,
+) -> trc::Result<()> {
+    let name = op.name().to_lowercase();
+    if name.is_empty() {
+        return Err(err_missing(PrincipalField::Name));
+    } else if !targets.insert(name.clone()) {
+        return Err(manage::error(
+            "Duplicate principal",
+            format!("Principal {name:?} appears more than once in the batch").into(),
+        ));
+    }
+
+    match op {
+        BulkPrincipalOperation::Create { principal } => {
+            access_token.assert_has_permission(create_permission(principal.typ()))?;
+
+            if server
+                .store()
+                .get_principal_info(&name)
+                .await
+                .caused_by(trc::location!())?
+                .is_some()
+            {
+                return Err(err_exists(PrincipalField::Name, name));
+            }
+
+            if matches!(principal.typ(), Type::Individual) {
+                server.assert_supported_directory()?;
+            }
+        }
+        BulkPrincipalOperation::Update { .. } | BulkPrincipalOperation::Aliases { .. } => {
+            let (_, typ) = server.resolve_principal(access_token, &name).await?;
+            access_token.assert_has_permission(update_permission(typ))?;
+        }
+        BulkPrincipalOperation::Delete { .. } => {
+            let (_, typ) = server.resolve_principal(access_token, &name).await?;
+            access_token.assert_has_permission(delete_permission(typ))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn create_permission(typ: Type) -> Permission {
+    match typ {
+        Type::Individual => Permission::IndividualCreate,
+        Type::Group => Permission::GroupCreate,
+        Type::List => Permission::MailingListCreate,
+        Type::Domain => Permission::DomainCreate,
+        Type::Tenant => Permission::TenantCreate,
+        Type::Role => Permission::RoleCreate,
+        Type::ApiKey => Permission::ApiKeyCreate,
+        Type::OauthClient => Permission::OauthClientCreate,
+        Type::Resource | Type::Location | Type::Other => Permission::PrincipalCreate,
+    }
+}
+
+fn update_permission(typ: Type) -> Permission {
+    match typ {
+        Type::Individual => Permission::IndividualUpdate,
+        Type::Group => Permission::GroupUpdate,
+        Type::List => Permission::MailingListUpdate,
+        Type::Domain => Permission::DomainUpdate,
+        Type::Tenant => Permission::TenantUpdate,
+        Type::Role => Permission::RoleUpdate,
+        Type::ApiKey => Permission::ApiKeyUpdate,
+        Type::OauthClient => Permission::OauthClientUpdate,
+        Type::Resource | Type::Location | Type::Other => Permission::PrincipalUpdate,
+    }
+}
+
+fn delete_permission(typ: Type) -> Permission {
+    match typ {
+        Type::Individual => Permission::IndividualDelete,
+        Type::Group => Permission::GroupDelete,
+        Type::List => Permission::MailingListDelete,
+        Type::Domain => Permission::DomainDelete,
+        Type::Tenant => Permission::TenantDelete,
+        Type::Role => Permission::RoleDelete,
+        Type::ApiKey => Permission::ApiKeyDelete,
+        Type::OauthClient => Permission::OauthClientDelete,
+        Type::Resource | Type::Location | Type::Other => Permission::PrincipalDelete,
+    }
+}
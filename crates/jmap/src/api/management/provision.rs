@@ -0,0 +1,412 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{
+    auth::AccessToken,
+    config::jmap::settings::{AccountDefaults, WelcomeMessage},
+    ipc::{DeliveryResult, IngestMessage},
+    Server,
+};
+use directory::{
+    backend::internal::{lookup::DirectoryStore, PrincipalField},
+    Principal, QueryBy,
+};
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{blob::BlobId, collection::Collection, property::Property, value::Value},
+};
+use mail_builder::{mime::make_boundary, MessageBuilder};
+use store::{
+    write::{log::LogInsert, BatchBuilder, BlobOp, DirectoryClass},
+    BlobClass,
+};
+use trc::AddContext;
+
+use crate::{
+    blob::upload::BlobUpload,
+    mailbox::set::MailboxSet,
+    services::ingest::MailDelivery,
+    sieve::set::{SieveScriptSet, SCHEMA},
+    JmapMethods,
+};
+
+pub const DEFAULT_SCRIPT_NAME: &str = "default";
+pub const DOMAIN_SCRIPT_NAME: &str = "domain-default";
+
+/// Settings from the matching domain and tenant defaults, resolved for a
+/// single principal.
+#[derive(Debug, Default)]
+pub struct AccountProvisioning {
+    pub sieve_script: Option<String>,
+    pub spam_threshold: Option<u32>,
+    pub folders: Vec<String>,
+    pub welcome: Option<WelcomeDelivery>,
+}
+
+/// Rendered welcome message and its envelope.
+#[derive(Debug)]
+pub struct WelcomeDelivery {
+    pub sender: String,
+    pub recipient: String,
+    pub message: Vec<u8>,
+}
+
+pub trait AccountDefaultsApply: Sync + Send {
+    fn apply_account_defaults(
+        &self,
+        access_token: &AccessToken,
+        principal: &mut Principal,
+    ) -> impl Future<Output = trc::Result<AccountProvisioning>> + Send;
+
+    fn provision_account(
+        &self,
+        account_id: u32,
+        provisioning: AccountProvisioning,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl AccountDefaultsApply for Server {
+    async fn apply_account_defaults(
+        &self,
+        access_token: &AccessToken,
+        principal: &mut Principal,
+    ) -> trc::Result<AccountProvisioning> {
+        if self.core.jmap.account_defaults.is_empty() {
+            return Ok(AccountProvisioning::default());
+        }
+
+        // Domain settings take precedence over tenant settings
+        let mut scopes = Vec::with_capacity(2);
+        if let Some(domain) = principal
+            .name()
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .or_else(|| {
+                principal
+                    .iter_str(PrincipalField::Emails)
+                    .next()
+                    .and_then(|email| email.rsplit_once('@'))
+                    .map(|(_, domain)| domain)
+            })
+        {
+            scopes.push(domain.to_lowercase());
+        }
+        if let Some(tenant) = principal.get_str(PrincipalField::Tenant) {
+            scopes.push(tenant.to_lowercase());
+        } else if let Some(tenant) = access_token.tenant {
+            if let Some(tenant) = self
+                .store()
+                .query(QueryBy::Id(tenant.id), false)
+                .await
+                .caused_by(trc::location!())?
+            {
+                scopes.push(tenant.name().to_lowercase());
+            }
+        }
+        let defaults = scopes
+            .iter()
+            .filter_map(|scope| {
+                self.core
+                    .jmap
+                    .account_defaults
+                    .iter()
+                    .find(|defaults| &defaults.scope == scope)
+            })
+            .collect::<Vec<_>>();
+
+        // Explicit principal values override the defaults
+        if !principal.has_field(PrincipalField::Quota) {
+            if let Some(quota) = defaults.iter().find_map(|defaults| defaults.quota) {
+                principal.set(PrincipalField::Quota, quota);
+            }
+        }
+        for (field, values) in [
+            (
+                PrincipalField::Roles,
+                first_list(&defaults, |defaults| &defaults.roles),
+            ),
+            (
+                PrincipalField::EnabledPermissions,
+                first_list(&defaults, |defaults| &defaults.enabled_permissions),
+            ),
+            (
+                PrincipalField::DisabledPermissions,
+                first_list(&defaults, |defaults| &defaults.disabled_permissions),
+            ),
+        ] {
+            if !principal.has_field(field) && !values.is_empty() {
+                principal.set(field, values.to_vec());
+            }
+        }
+
+        Ok(AccountProvisioning {
+            sieve_script: defaults
+                .iter()
+                .find_map(|defaults| defaults.sieve_script.clone()),
+            spam_threshold: defaults.iter().find_map(|defaults| defaults.spam_threshold),
+            folders: first_list(&defaults, |defaults| &defaults.folders).to_vec(),
+            welcome: defaults
+                .iter()
+                .find_map(|defaults| defaults.welcome.as_ref())
+                .and_then(|welcome| build_welcome_message(welcome, principal)),
+        })
+    }
+
+    async fn provision_account(
+        &self,
+        account_id: u32,
+        provisioning: AccountProvisioning,
+    ) -> trc::Result<()> {
+        // Create folder structure
+        if !provisioning.folders.is_empty() {
+            self.mailbox_get_or_create(account_id)
+                .await
+                .caused_by(trc::location!())?;
+            for path in &provisioning.folders {
+                if self
+                    .mailbox_create_path(account_id, path)
+                    .await
+                    .caused_by(trc::location!())?
+                    .is_none()
+                {
+                    trc::event!(
+                        Manage(trc::ManageEvent::Error),
+                        AccountId = account_id,
+                        Details = "Failed to create default folder",
+                        Path = path.to_string(),
+                    );
+                }
+            }
+        }
+
+        // Create Sieve scripts, the spam threshold rule includes the domain script
+        let active_script = match (provisioning.sieve_script, provisioning.spam_threshold) {
+            (Some(script), Some(threshold)) => {
+                provision_sieve_script(self, account_id, DOMAIN_SCRIPT_NAME, script.into_bytes())
+                    .await?;
+                Some(spam_threshold_script(threshold, true))
+            }
+            (None, Some(threshold)) => Some(spam_threshold_script(threshold, false)),
+            (Some(script), None) => Some(script),
+            (None, None) => None,
+        };
+        if let Some(script) = active_script {
+            let document_id =
+                provision_sieve_script(self, account_id, DEFAULT_SCRIPT_NAME, script.into_bytes())
+                    .await?;
+            self.sieve_activate_script(account_id, document_id.into())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        // Deliver the welcome message through the regular delivery path, so
+        // that it is filtered, indexed and counted like any other message
+        if let Some(welcome) = provisioning.welcome {
+            let blob_id = self
+                .put_blob(account_id, &welcome.message, false)
+                .await
+                .caused_by(trc::location!())?;
+            let result = self
+                .deliver_message(IngestMessage {
+                    sender_address: welcome.sender,
+                    recipients: vec![welcome.recipient.clone()],
+                    message_blob: blob_id.hash,
+                    message_size: welcome.message.len(),
+                    session_id: 0,
+                })
+                .await;
+            if !matches!(result.first(), Some(DeliveryResult::Success)) {
+                trc::event!(
+                    Manage(trc::ManageEvent::Error),
+                    AccountId = account_id,
+                    To = welcome.recipient,
+                    Details = "Failed to deliver welcome message",
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+async fn provision_sieve_script(
+    server: &Server,
+    account_id: u32,
+    name: &str,
+    mut script: Vec<u8>,
+) -> trc::Result<u32> {
+    let script_size = script.len();
+    let compiled_script = server
+        .core
+        .sieve
+        .untrusted_compiler
+        .compile(&script)
+        .map_err(|err| {
+            trc::ManageEvent::Error
+                .into_err()
+                .ctx(trc::Key::Id, name.to_string())
+                .reason(err)
+                .details("Failed to compile default Sieve script")
+        })?;
+    script.extend(bincode::serialize(&compiled_script).unwrap_or_default());
+
+    // Write script blob
+    let blob_id = BlobId::new(
+        server
+            .put_blob(account_id, &script, false)
+            .await
+            .caused_by(trc::location!())?
+            .hash,
+        BlobClass::Linked {
+            account_id,
+            collection: Collection::SieveScript.into(),
+            document_id: 0,
+        },
+    )
+    .with_section_size(script_size);
+
+    // Write record
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::SieveScript)
+        .create_document()
+        .log(LogInsert())
+        .add(DirectoryClass::UsedQuota(account_id), script_size as i64)
+        .set(
+            BlobOp::Link {
+                hash: blob_id.hash.clone(),
+            },
+            Vec::new(),
+        )
+        .custom(
+            ObjectIndexBuilder::new(SCHEMA).with_changes(
+                Object::with_capacity(3)
+                    .with_property(Property::Name, name.to_string())
+                    .with_property(Property::IsActive, Value::Bool(false))
+                    .with_property(Property::BlobId, Value::BlobId(blob_id)),
+            ),
+        );
+    server
+        .write_batch(batch)
+        .await
+        .and_then(|ids| ids.last_document_id())
+        .caused_by(trc::location!())
+}
+
+fn build_welcome_message(
+    welcome: &WelcomeMessage,
+    principal: &Principal,
+) -> Option<WelcomeDelivery> {
+    let recipient = principal
+        .iter_str(PrincipalField::Emails)
+        .next()
+        .map(|email| email.as_str())
+        .or_else(|| Some(principal.name()).filter(|name| name.contains('@')))?
+        .to_lowercase();
+    let display_name = principal.description().unwrap_or(principal.name());
+    let quota = principal.quota();
+    let vars = [
+        ("{name}", principal.name().to_string()),
+        ("{display_name}", display_name.to_string()),
+        ("{email}", recipient.clone()),
+        (
+            "{domain}",
+            recipient
+                .rsplit_once('@')
+                .map(|(_, domain)| domain.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "{quota}",
+            if quota > 0 {
+                format_size(quota)
+            } else {
+                "unlimited".to_string()
+            },
+        ),
+    ];
+    let render = |template: &str| {
+        vars.iter()
+            .fold(template.to_string(), |text, (name, value)| {
+                text.replace(name, value)
+            })
+    };
+
+    let message = MessageBuilder::new()
+        .from(welcome.from.as_str())
+        .to((display_name, recipient.as_str()))
+        .message_id(format!("<{}@welcome>", make_boundary(".")))
+        .subject(render(&welcome.subject))
+        .text_body(render(&welcome.body))
+        .write_to_vec()
+        .ok()?;
+
+    Some(WelcomeDelivery {
+        sender: welcome.from.clone(),
+        recipient,
+        message,
+    })
+}
+
+/// Formats a size in bytes using the largest unit that keeps the value above one.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KB", "MB", "GB", "TB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || value.fract() == 0.0 {
+        format!("{} {}", value as u64, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn first_list<'x>(
+    defaults: &[&'x AccountDefaults],
+    list: impl Fn(&'x AccountDefaults) -> &'x Vec<String>,
+) -> &'x [String] {
+    defaults
+        .iter()
+        .map(|defaults| list(defaults).as_slice())
+        .find(|values| !values.is_empty())
+        .unwrap_or_default()
+}
+
+/// Builds a script that files messages scoring at or above the threshold
+/// into the Junk folder, optionally including the domain default script.
+pub fn spam_threshold_script(threshold: u32, include_domain_script: bool) -> String {
+    let mut script = format!(
+        concat!(
+            "require [\"fileinto\", \"variables\", \"relational\", ",
+            "\"comparator-i;ascii-numeric\", \"special-use\"{}];\r\n\r\n",
+            "if header :matches \"X-Spam-Status\" \"*score=*\" {{\r\n",
+            "    if allof(not string :matches \"${{2}}\" \"-*\",\r\n",
+            "             string :value \"ge\" :comparator \"i;ascii-numeric\" \"${{2}}\" \"{}\") {{\r\n",
+            "        fileinto :specialuse \"\\\\Junk\" \"Junk\";\r\n",
+            "        stop;\r\n",
+            "    }}\r\n",
+            "}}\r\n",
+        ),
+        if include_domain_script {
+            ", \"include\""
+        } else {
+            ""
+        },
+        threshold
+    );
+    if include_domain_script {
+        script.push_str(&format!(
+            "\r\ninclude :personal \"{DOMAIN_SCRIPT_NAME}\";\r\n"
+        ));
+    }
+    script
+}
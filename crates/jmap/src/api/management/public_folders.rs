@@ -0,0 +1,172 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use directory::{
+    backend::internal::{manage, PrincipalField},
+    Permission, QueryBy,
+};
+use hyper::Method;
+use jmap_proto::types::acl::Acl;
+use serde_json::json;
+use utils::map::bitmap::Bitmap;
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    mailbox::public::PublicFolderMethods,
+};
+
+use super::decode_path_element;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicFolderRequest {
+    pub name: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicFolderAclRequest {
+    pub account: String,
+    #[serde(default)]
+    pub rights: Vec<String>,
+}
+
+pub trait ManagePublicFolders: Sync + Send {
+    fn handle_manage_public_folders(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManagePublicFolders for Server {
+    async fn handle_manage_public_folders(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let account_id = self
+            .public_account_id()
+            .await?
+            .ok_or_else(|| manage::unsupported("Public folders are not enabled"))?;
+
+        match (path.get(1).copied(), path.get(2).copied(), req.method()) {
+            (None, None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::PublicFoldersList)?;
+
+                let mut folders = Vec::new();
+                for folder in self.public_folder_list(account_id).await? {
+                    let mut acl = Vec::with_capacity(folder.acl.len());
+                    for grant in folder.acl {
+                        let account = self
+                            .core
+                            .storage
+                            .directory
+                            .query(QueryBy::Id(grant.account_id), false)
+                            .await?
+                            .and_then(|mut p| p.take_str(PrincipalField::Name))
+                            .unwrap_or_else(|| grant.account_id.to_string());
+                        let rights = grant
+                            .grants
+                            .into_iter()
+                            .map(|right| right.as_str())
+                            .collect::<Vec<_>>();
+                        acl.push(json!({
+                            "account": account,
+                            "rights": rights,
+                        }));
+                    }
+                    folders.push(json!({
+                        "name": folder.name,
+                        "acl": acl,
+                    }));
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": folders,
+                }))
+                .into_http_response())
+            }
+            (None, None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::PublicFoldersManage)?;
+
+                let request = serde_json::from_slice::<PublicFolderRequest>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+
+                if self
+                    .public_folder_create(account_id, &request.name)
+                    .await?
+                    .is_some()
+                {
+                    Ok(JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response())
+                } else {
+                    Err(manage::err_exists("name", request.name))
+                }
+            }
+            (Some(name), Some("acl"), &Method::PUT) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::PublicFoldersManage)?;
+
+                let request = serde_json::from_slice::<PublicFolderAclRequest>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+
+                // Obtain folder and grantee ids
+                let name = decode_path_element(name);
+                let mailbox_id = self
+                    .public_folder_list(account_id)
+                    .await?
+                    .into_iter()
+                    .find(|folder| folder.name == name)
+                    .ok_or_else(|| manage::not_found(name.to_string()))?
+                    .id;
+                let grantee_id = self
+                    .core
+                    .storage
+                    .directory
+                    .query(QueryBy::Name(&request.account), false)
+                    .await?
+                    .ok_or_else(|| manage::not_found(request.account.clone()))?
+                    .id();
+                let mut rights = Bitmap::new();
+                for right in &request.rights {
+                    rights.insert(Acl::from_name(right).ok_or_else(|| {
+                        trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                            .reason(format!("Invalid right {right:?}"))
+                    })?);
+                }
+
+                self.public_folder_set_rights(account_id, mailbox_id, grantee_id, rights)
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use directory::{backend::internal::manage::ManageDirectory, Permission};
+use hyper::Method;
+use serde_json::json;
+use utils::url_params::UrlParams;
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    email::quarantine::{parse_quarantine_id, EmailQuarantine},
+};
+
+use super::{decode_path_element, Cursor};
+
+pub trait ManageQuarantine: Sync + Send {
+    fn handle_manage_quarantine(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageQuarantine for Server {
+    async fn handle_manage_quarantine(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match (
+            path.get(1).copied().map(decode_path_element),
+            path.get(2).copied(),
+            req.method(),
+        ) {
+            (None, None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::QuarantineList)?;
+
+                let params = UrlParams::new(req.uri().query());
+                let text = params.get("text");
+                let cursor = params
+                    .parse::<Cursor>("cursor")
+                    .and_then(|cursor| String::from_utf8(cursor.into_inner()).ok())
+                    .and_then(|id| parse_quarantine_id(&id));
+                let page: usize = if cursor.is_none() {
+                    params.parse::<usize>("page").unwrap_or_default()
+                } else {
+                    0
+                };
+                let limit: usize = params.parse::<usize>("limit").unwrap_or_default();
+                let account_id = if let Some(account) = params.get("account") {
+                    if let Some(account_id) =
+                        self.core.storage.data.get_principal_id(account).await?
+                    {
+                        Some(account_id)
+                    } else {
+                        return Err(trc::ResourceEvent::NotFound.into_err());
+                    }
+                } else {
+                    None
+                };
+
+                let items = self.quarantine_list(account_id, text).await?;
+                let total = items.len();
+                let items = items
+                    .into_iter()
+                    .filter(|item| {
+                        // Items are sorted newest first, resume below the last one returned
+                        cursor.map_or(true, |(cursor_id, cursor_expires)| {
+                            parse_quarantine_id(&item.id).is_some_and(|(id, expires)| {
+                                (expires, id) < (cursor_expires, cursor_id)
+                            })
+                        })
+                    })
+                    .skip(page.saturating_sub(1) * limit)
+                    .take(if limit > 0 { limit } else { total })
+                    .collect::<Vec<_>>();
+                let cursor = items
+                    .last()
+                    .filter(|_| limit != 0 && items.len() == limit)
+                    .map(|item| Cursor::encode(&item.id));
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "items": items,
+                            "total": total,
+                            "cursor": cursor,
+                        },
+                }))
+                .into_http_response())
+            }
+            (Some(id), None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::QuarantineList)?;
+
+                let (id, expires) = parse_quarantine_id(id.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                let item = self
+                    .quarantine_get(id, expires)
+                    .await?
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?
+                    .into_item(id, expires);
+
+                Ok(JsonResponse::new(json!({
+                        "data": item,
+                }))
+                .into_http_response())
+            }
+            (Some(id), Some("release"), &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::QuarantineRelease)?;
+
+                let (id, expires) = parse_quarantine_id(id.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                Ok(JsonResponse::new(json!({
+                        "data": self.quarantine_release(id, expires).await?,
+                }))
+                .into_http_response())
+            }
+            (Some(id), None, &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::QuarantineDelete)?;
+
+                let (id, expires) = parse_quarantine_id(id.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                Ok(JsonResponse::new(json!({
+                        "data": self.quarantine_delete(id, expires).await?,
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
@@ -4,15 +4,22 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::future::Future;
+use std::{future::Future, time::Duration};
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use common::{auth::AccessToken, ipc::QueueEvent, Server};
 use directory::{
-    backend::internal::{manage::ManageDirectory, PrincipalField},
+    backend::internal::{
+        manage::{self, ManageDirectory},
+        PrincipalField,
+    },
     Permission, Type,
 };
-use hyper::Method;
+use http_body_util::{combinators::BoxBody, StreamBody};
+use hyper::{
+    body::{Bytes, Frame},
+    Method, StatusCode,
+};
 use mail_auth::{
     dmarc::URI,
     mta_sts::ReportUri,
@@ -22,19 +29,26 @@ use mail_parser::DateTime;
 use serde::{Deserializer, Serializer};
 use serde_json::json;
 use smtp::{
-    queue::{self, spool::SmtpSpool, ErrorDetails, HostResponse, QueueId, Status},
+    queue::{
+        self, history::DeliveryHistory, route::QueueRoute, spool::SmtpSpool, ErrorDetails,
+        HostResponse, QueueId, Status,
+    },
     reporting::{dmarc::DmarcReporting, tls::TlsReporting},
 };
 use store::{
     write::{key::DeserializeBigEndian, now, Bincode, QueueClass, ReportEvent, ValueClass},
     Deserialize, IterateParams, ValueKey,
 };
-use trc::AddContext;
-use utils::url_params::UrlParams;
+use trc::{
+    ipc::subscriber::{Interests, SubscriberBuilder},
+    serializers::json::JsonEventSerializer,
+    AddContext, Collector, EventType,
+};
+use utils::{config::utils::ParseValue, url_params::UrlParams};
 
-use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, HttpResponseBody, JsonResponse};
 
-use super::{decode_path_element, FutureTimestamp};
+use super::{decode_path_element, Cursor, FutureTimestamp};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct Message {
@@ -51,6 +65,12 @@ pub struct Message {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub env_id: Option<String>,
     pub blob_hash: String,
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub held: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub next_hop: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
@@ -171,16 +191,13 @@ impl QueueManagement for Server {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::MessageQueueList)?;
 
-                let text = params.get("text");
-                let from = params.get("from");
-                let to = params.get("to");
-                let before = params
-                    .parse::<FutureTimestamp>("before")
-                    .map(|t| t.into_inner());
-                let after = params
-                    .parse::<FutureTimestamp>("after")
-                    .map(|t| t.into_inner());
-                let page = params.parse::<usize>("page").unwrap_or_default();
+                let filter = QueueFilter::parse(&params);
+                let cursor = params.parse::<Cursor>("cursor").and_then(|c| c.to_u64());
+                let page = if cursor.is_none() {
+                    params.parse::<usize>("page").unwrap_or_default()
+                } else {
+                    0
+                };
                 let limit = params.parse::<usize>("limit").unwrap_or_default();
                 let values = params.has_key("values");
 
@@ -192,14 +209,10 @@ impl QueueManagement for Server {
                 let mut result_values = Vec::new();
                 let from_key = ValueKey::from(ValueClass::Queue(QueueClass::Message(range_start)));
                 let to_key = ValueKey::from(ValueClass::Queue(QueueClass::Message(range_end)));
-                let has_filters = text.is_some()
-                    || from.is_some()
-                    || to.is_some()
-                    || before.is_some()
-                    || after.is_some();
                 let mut offset = page.saturating_sub(1) * limit;
                 let mut total = 0;
                 let mut total_returned = 0;
+                let mut last_id = None;
                 self.core
                     .storage
                     .data
@@ -212,45 +225,24 @@ impl QueueManagement for Server {
                             let matches = tenant_domains
                                 .as_ref()
                                 .map_or(true, |domains| message.has_domain(domains))
-                                && (!has_filters
-                                    || (text
-                                        .as_ref()
-                                        .map(|text| {
-                                            message.return_path.contains(text)
-                                                || message
-                                                    .recipients
-                                                    .iter()
-                                                    .any(|r| r.address_lcase.contains(text))
-                                        })
-                                        .unwrap_or_else(|| {
-                                            from.as_ref().map_or(true, |from| {
-                                                message.return_path.contains(from)
-                                            }) && to.as_ref().map_or(true, |to| {
-                                                message
-                                                    .recipients
-                                                    .iter()
-                                                    .any(|r| r.address_lcase.contains(to))
-                                            })
-                                        })
-                                        && before.as_ref().map_or(true, |before| {
-                                            message.next_delivery_event() < *before
-                                        })
-                                        && after.as_ref().map_or(true, |after| {
-                                            message.next_delivery_event() > *after
-                                        })));
+                                && filter.matches(&message);
 
                             if matches {
-                                if offset == 0 {
-                                    if limit == 0 || total_returned < limit {
-                                        if values {
-                                            result_values.push(Message::from(&message));
-                                        } else {
-                                            result_ids.push(key.deserialize_be_u64(0)?);
+                                // Resume after the last queue id returned
+                                if cursor.map_or(true, |cursor| message.queue_id > cursor) {
+                                    if offset == 0 {
+                                        if limit == 0 || total_returned < limit {
+                                            if values {
+                                                result_values.push(Message::from(&message));
+                                            } else {
+                                                result_ids.push(key.deserialize_be_u64(0)?);
+                                            }
+                                            last_id = Some(message.queue_id);
+                                            total_returned += 1;
                                         }
-                                        total_returned += 1;
+                                    } else {
+                                        offset -= 1;
                                     }
-                                } else {
-                                    offset -= 1;
                                 }
 
                                 total += 1;
@@ -261,12 +253,16 @@ impl QueueManagement for Server {
                     )
                     .await
                     .caused_by(trc::location!())?;
+                let cursor = last_id
+                    .filter(|_| limit != 0 && total_returned == limit)
+                    .map(|id| Cursor::encode(id.to_be_bytes()));
 
                 Ok(if values {
                     JsonResponse::new(json!({
                             "data":{
                                 "items": result_values,
                                 "total": total,
+                                "cursor": cursor,
                             },
                     }))
                 } else {
@@ -274,6 +270,7 @@ impl QueueManagement for Server {
                             "data": {
                                 "items": result_ids,
                                 "total": total,
+                                "cursor": cursor,
                             },
                     }))
                 }
@@ -292,8 +289,14 @@ impl QueueManagement for Server {
                             .map_or(true, |domains| message.has_domain(domains))
                     })
                 {
+                    let mut result = Message::from(&message);
+                    result.next_hop = self
+                        .queue_route_get(message.queue_id)
+                        .await
+                        .caused_by(trc::location!())?;
+
                     Ok(JsonResponse::new(json!({
-                            "data": Message::from(&message),
+                            "data": result,
                     }))
                     .into_http_response())
                 } else {
@@ -304,13 +307,9 @@ impl QueueManagement for Server {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
 
-                let time = params
-                    .parse::<FutureTimestamp>("at")
-                    .map(|t| t.into_inner())
-                    .unwrap_or_else(now);
-                let item = params.get("filter");
+                let update = MessageUpdate::parse(self, &params)?;
 
-                if let Some(mut message) = self
+                if let Some(message) = self
                     .read_message(queue_id.parse().unwrap_or_default())
                     .await
                     .filter(|message| {
@@ -319,30 +318,8 @@ impl QueueManagement for Server {
                             .map_or(true, |domains| message.has_domain(domains))
                     })
                 {
-                    let prev_event = message.next_event().unwrap_or_default();
-                    let mut found = false;
-
-                    for domain in &mut message.domains {
-                        if matches!(
-                            domain.status,
-                            Status::Scheduled | Status::TemporaryFailure(_)
-                        ) && item
-                            .as_ref()
-                            .map_or(true, |item| domain.domain.contains(item))
-                        {
-                            domain.retry.due = time;
-                            if domain.expires > time {
-                                domain.expires = time + 10;
-                            }
-                            found = true;
-                        }
-                    }
-
+                    let found = update.apply(self, message).await?;
                     if found {
-                        let next_event = message.next_event().unwrap_or_default();
-                        message
-                            .save_changes(self, prev_event.into(), next_event.into())
-                            .await;
                         let _ = self.inner.ipc.queue_tx.send(QueueEvent::Reload).await;
                     }
 
@@ -354,6 +331,136 @@ impl QueueManagement for Server {
                     Err(trc::ResourceEvent::NotFound.into_err())
                 }
             }
+            ("messages", None, &Method::PATCH) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let update = MessageUpdate::parse(self, &params)?;
+                let mut total = 0;
+                for message in matching_messages(
+                    self,
+                    &QueueFilter::parse(&params),
+                    tenant_domains.as_deref(),
+                )
+                .await?
+                {
+                    if update.apply(self, message).await? {
+                        total += 1;
+                    }
+                }
+                if total > 0 {
+                    let _ = self.inner.ipc.queue_tx.send(QueueEvent::Reload).await;
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": total,
+                }))
+                .into_http_response())
+            }
+            ("messages", None, &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueDelete)?;
+
+                // Refuse to empty the whole queue by accident
+                let filter = QueueFilter::parse(&params);
+                if filter.is_empty() {
+                    return Err(trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                        .reason("At least one filter is required"));
+                }
+
+                let mut total = 0;
+                for message in matching_messages(self, &filter, tenant_domains.as_deref()).await? {
+                    let prev_event = message.next_event().unwrap_or_default();
+                    if message.remove(self, prev_event).await {
+                        total += 1;
+                    }
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": total,
+                }))
+                .into_http_response())
+            }
+            ("history", Some(queue_id), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                if !self.core.smtp.queue.history.enable {
+                    return Err(manage::unsupported("Delivery history is not enabled"));
+                }
+
+                let history = self
+                    .history_get(queue_id.parse().unwrap_or_default())
+                    .await?
+                    .into_iter()
+                    .filter(|attempt| {
+                        tenant_domains
+                            .as_ref()
+                            .map_or(true, |domains| domains.contains(&attempt.domain))
+                    })
+                    .collect::<Vec<_>>();
+
+                if !history.is_empty() {
+                    Ok(JsonResponse::new(json!({
+                            "data": history,
+                    }))
+                    .into_http_response())
+                } else {
+                    Err(trc::ResourceEvent::NotFound.into_err())
+                }
+            }
+            ("events", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueLive)?;
+
+                // Subscribe to queue and delivery events, making sure they are
+                // collected even if no tracer is interested in them
+                let mut interests = Interests::default();
+                for event in EventType::variants() {
+                    if matches!(event, EventType::Queue(_) | EventType::Delivery(_)) {
+                        interests.set(event);
+                    }
+                }
+                Collector::union_interests(interests.clone());
+                let (_, mut rx) = SubscriberBuilder::new("live-queue".to_string())
+                    .with_interests(interests)
+                    .with_lossy(false)
+                    .register();
+                let ping_interval = Duration::from_secs(30);
+                let ping_payload = Bytes::from(format!(
+                    "event: ping\ndata: {{\"interval\": {}}}\n\n",
+                    ping_interval.as_millis()
+                ));
+
+                Ok(HttpResponse {
+                    status: StatusCode::OK,
+                    content_type: "text/event-stream".into(),
+                    content_disposition: "".into(),
+                    cache_control: "no-store".into(),
+                    body: HttpResponseBody::Stream(BoxBody::new(StreamBody::new(
+                        async_stream::stream! {
+                            loop {
+                                match tokio::time::timeout(ping_interval, rx.recv()).await {
+                                    Ok(Some(events)) => {
+                                        yield Ok(Frame::data(Bytes::from(format!(
+                                            "event: queue\ndata: {}\n\n",
+                                            serde_json::to_string(
+                                                &JsonEventSerializer::new(events)
+                                                .with_description()).unwrap_or_default()
+                                        ))));
+                                    }
+                                    Ok(None) => {
+                                        break;
+                                    }
+                                    Err(_) => {
+                                        yield Ok(Frame::data(ping_payload.clone()));
+                                    }
+                                }
+                            }
+                        },
+                    ))),
+                })
+            }
             ("messages", Some(queue_id), &Method::DELETE) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::MessageQueueDelete)?;
@@ -451,7 +558,12 @@ impl QueueManagement for Server {
                     "tls" => 1u8.into(),
                     _ => None,
                 });
-                let page: usize = params.parse("page").unwrap_or_default();
+                let cursor = params.parse::<Cursor>("cursor").map(Cursor::into_inner);
+                let page: usize = if cursor.is_none() {
+                    params.parse("page").unwrap_or_default()
+                } else {
+                    0
+                };
                 let limit: usize = params.parse("limit").unwrap_or_default();
 
                 let range_start = params.parse::<u64>("range-start").unwrap_or_default();
@@ -478,6 +590,7 @@ impl QueueManagement for Server {
                 let mut offset = page.saturating_sub(1) * limit;
                 let mut total = 0;
                 let mut total_returned = 0;
+                let mut last_key = None;
                 self.core
                     .storage
                     .data
@@ -492,20 +605,24 @@ impl QueueManagement for Server {
                                     && event.seq_id != 0
                                     && domain.as_ref().map_or(true, |d| event.domain.contains(d))
                                 {
-                                    if offset == 0 {
-                                        if limit == 0 || total_returned < limit {
-                                            result.push(
-                                                if *key.last().unwrap() == 0 {
-                                                    QueueClass::DmarcReportHeader(event)
-                                                } else {
-                                                    QueueClass::TlsReportHeader(event)
-                                                }
-                                                .queue_id(),
-                                            );
-                                            total_returned += 1;
+                                    // Resume after the last report key returned
+                                    if cursor.as_deref().map_or(true, |cursor| key > cursor) {
+                                        if offset == 0 {
+                                            if limit == 0 || total_returned < limit {
+                                                result.push(
+                                                    if *key.last().unwrap() == 0 {
+                                                        QueueClass::DmarcReportHeader(event)
+                                                    } else {
+                                                        QueueClass::TlsReportHeader(event)
+                                                    }
+                                                    .queue_id(),
+                                                );
+                                                last_key = Some(key.to_vec());
+                                                total_returned += 1;
+                                            }
+                                        } else {
+                                            offset -= 1;
                                         }
-                                    } else {
-                                        offset -= 1;
                                     }
 
                                     total += 1;
@@ -517,11 +634,15 @@ impl QueueManagement for Server {
                     )
                     .await
                     .caused_by(trc::location!())?;
+                let cursor = last_key
+                    .filter(|_| limit != 0 && total_returned == limit)
+                    .map(Cursor::encode);
 
                 Ok(JsonResponse::new(json!({
                         "data": {
                             "items": result,
                             "total": total,
+                            "cursor": cursor,
                         },
                 }))
                 .into_http_response())
@@ -610,6 +731,235 @@ impl QueueManagement for Server {
     }
 }
 
+struct QueueFilter<'x> {
+    text: Option<&'x str>,
+    from: Option<&'x str>,
+    to: Option<&'x str>,
+    domain: Option<String>,
+    error: Option<&'x str>,
+    before: Option<u64>,
+    after: Option<u64>,
+    min_age: Option<u64>,
+    max_age: Option<u64>,
+    held: Option<bool>,
+}
+
+impl<'x> QueueFilter<'x> {
+    fn parse(params: &'x UrlParams<'_>) -> Self {
+        let age = |key: &str| {
+            params
+                .get(key)
+                .and_then(|age| Duration::parse_value(age).ok())
+                .map(|age| age.as_secs())
+        };
+
+        QueueFilter {
+            text: params.get("text"),
+            from: params.get("from"),
+            to: params.get("to"),
+            domain: params.get("domain").map(|domain| domain.to_lowercase()),
+            error: params.get("error"),
+            before: params
+                .parse::<FutureTimestamp>("before")
+                .map(|t| t.into_inner()),
+            after: params
+                .parse::<FutureTimestamp>("after")
+                .map(|t| t.into_inner()),
+            min_age: age("min-age"),
+            max_age: age("max-age"),
+            held: params.parse::<bool>("held"),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.is_none()
+            && self.from.is_none()
+            && self.to.is_none()
+            && self.domain.is_none()
+            && self.error.is_none()
+            && self.before.is_none()
+            && self.after.is_none()
+            && self.min_age.is_none()
+            && self.max_age.is_none()
+            && self.held.is_none()
+    }
+
+    fn matches(&self, message: &queue::Message) -> bool {
+        let age = now().saturating_sub(message.created);
+
+        self.text
+            .map(|text| {
+                message.return_path.contains(text)
+                    || message
+                        .recipients
+                        .iter()
+                        .any(|r| r.address_lcase.contains(text))
+            })
+            .unwrap_or_else(|| {
+                self.from
+                    .map_or(true, |from| message.return_path.contains(from))
+                    && self.to.map_or(true, |to| {
+                        message
+                            .recipients
+                            .iter()
+                            .any(|r| r.address_lcase.contains(to))
+                    })
+            })
+            && self.domain.as_ref().map_or(true, |domain| {
+                message.domains.iter().any(|d| d.domain.contains(domain))
+            })
+            && self.error.map_or(true, |error| has_error(message, error))
+            && self
+                .before
+                .map_or(true, |before| message.next_delivery_event() < before)
+            && self
+                .after
+                .map_or(true, |after| message.next_delivery_event() > after)
+            && self.min_age.map_or(true, |min_age| age >= min_age)
+            && self.max_age.map_or(true, |max_age| age <= max_age)
+            && self.held.map_or(true, |held| message.is_held() == held)
+    }
+}
+
+/// Returns whether any domain or recipient of the message failed with an
+/// error of the class, or with any temporary or permanent error.
+fn has_error(message: &queue::Message, class: &str) -> bool {
+    message.domains.iter().any(|domain| match &domain.status {
+        Status::TemporaryFailure(err) => class == "temporary" || err.class() == class,
+        Status::PermanentFailure(err) => class == "permanent" || err.class() == class,
+        _ => false,
+    }) || message.recipients.iter().any(|rcpt| match &rcpt.status {
+        Status::TemporaryFailure(_) => ["temporary", "response"].contains(&class),
+        Status::PermanentFailure(_) => ["permanent", "response"].contains(&class),
+        _ => false,
+    })
+}
+
+enum UpdateAction {
+    Retry,
+    Hold,
+    Release,
+}
+
+struct MessageUpdate<'x> {
+    action: UpdateAction,
+    time: u64,
+    item: Option<&'x str>,
+    next_hop: Option<Option<String>>,
+}
+
+impl<'x> MessageUpdate<'x> {
+    fn parse(server: &Server, params: &'x UrlParams<'_>) -> trc::Result<Self> {
+        let action = match params.get("action").unwrap_or("retry") {
+            "retry" => UpdateAction::Retry,
+            "hold" => UpdateAction::Hold,
+            "release" => UpdateAction::Release,
+            action => {
+                return Err(trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                    .reason(format!("Invalid action {action:?}")))
+            }
+        };
+
+        // Reroute to a relay host, "default" restores the configured routing
+        let next_hop = match params.get("next-hop") {
+            Some("default") => Some(None),
+            Some(next_hop) if server.core.smtp.queue.relay_hosts.contains_key(next_hop) => {
+                Some(Some(next_hop.to_string()))
+            }
+            Some(next_hop) => {
+                return Err(trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                    .reason(format!("Unknown next hop {next_hop:?}")))
+            }
+            None => None,
+        };
+
+        Ok(MessageUpdate {
+            action,
+            time: params
+                .parse::<FutureTimestamp>("at")
+                .map(|t| t.into_inner())
+                .unwrap_or_else(now),
+            item: params.get("filter"),
+            next_hop,
+        })
+    }
+
+    async fn apply(&self, server: &Server, mut message: queue::Message) -> trc::Result<bool> {
+        if let Some(next_hop) = &self.next_hop {
+            server
+                .queue_route_set(&message, next_hop.as_deref())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(match self.action {
+            UpdateAction::Hold => message.hold(server).await,
+            UpdateAction::Release => message.release(server).await,
+            UpdateAction::Retry => {
+                let prev_event = message.next_event().unwrap_or_default();
+                let mut found = false;
+
+                for domain in &mut message.domains {
+                    if matches!(
+                        domain.status,
+                        Status::Scheduled | Status::TemporaryFailure(_)
+                    ) && self.item.map_or(true, |item| domain.domain.contains(item))
+                    {
+                        domain.retry.due = self.time;
+                        if domain.expires > self.time {
+                            domain.expires = self.time + 10;
+                        }
+                        found = true;
+                    }
+                }
+
+                if found {
+                    let next_event = message.next_event().unwrap_or_default();
+                    message
+                        .save_changes(server, prev_event.into(), next_event.into())
+                        .await;
+                }
+
+                found
+            }
+        })
+    }
+}
+
+async fn matching_messages(
+    server: &Server,
+    filter: &QueueFilter<'_>,
+    tenant_domains: Option<&[String]>,
+) -> trc::Result<Vec<queue::Message>> {
+    let mut messages = Vec::new();
+    server
+        .core
+        .storage
+        .data
+        .iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Queue(QueueClass::Message(0))),
+                ValueKey::from(ValueClass::Queue(QueueClass::Message(u64::MAX))),
+            )
+            .ascending(),
+            |key, value| {
+                let message = Bincode::<queue::Message>::deserialize(value)
+                    .add_context(|ctx| ctx.ctx(trc::Key::Key, key))?
+                    .inner;
+                if tenant_domains.map_or(true, |domains| message.has_domain(domains))
+                    && filter.matches(&message)
+                {
+                    messages.push(message);
+                }
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|_| messages)
+}
+
 impl From<&queue::Message> for Message {
     fn from(message: &queue::Message) -> Self {
         let now = now();
@@ -669,6 +1019,8 @@ impl From<&queue::Message> for Message {
                 })
                 .collect(),
             blob_hash: URL_SAFE_NO_PAD.encode::<&[u8]>(message.blob_hash.as_ref()),
+            held: message.is_held(),
+            next_hop: None,
         }
     }
 }
@@ -783,3 +1135,7 @@ where
 fn is_zero(num: &i16) -> bool {
     *num == 0
 }
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
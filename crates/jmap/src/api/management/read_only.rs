@@ -0,0 +1,93 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, ipc::QueueEvent, Server};
+use directory::Permission;
+use hyper::Method;
+use serde_json::json;
+use std::future::Future;
+use utils::url_params::UrlParams;
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+pub trait ManageReadOnly: Sync + Send {
+    fn handle_manage_read_only(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageReadOnly for Server {
+    async fn handle_manage_read_only(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        // Validate the access token
+        access_token.assert_has_permission(Permission::ReadOnlyMode)?;
+
+        match (path.get(1).copied(), req.method()) {
+            (None, &Method::GET) => {}
+            (Some("enable"), &Method::POST) => {
+                self.set_read_only(true);
+            }
+            (Some("disable"), &Method::POST) => {
+                self.set_read_only(false);
+
+                // Resume queue processing
+                if self
+                    .inner
+                    .ipc
+                    .queue_tx
+                    .send(QueueEvent::Reload)
+                    .await
+                    .is_err()
+                {
+                    trc::event!(
+                        Server(trc::ServerEvent::ThreadError),
+                        Reason = "Channel closed.",
+                        CausedBy = trc::location!(),
+                    );
+                }
+            }
+            _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": self.is_read_only(),
+        }))
+        .into_http_response())
+    }
+}
+
+/// Returns `true` unless the management request is known to leave the server
+/// state untouched. Safe routes are listed explicitly, so endpoints added later
+/// are rejected in read-only mode until they are reviewed and added here.
+pub fn is_mutating_request(path: &[&str], method: &Method, query: Option<&str>) -> bool {
+    !match (path.first().copied().unwrap_or_default(), method) {
+        // Read-only mode has to be disabled and logins still issue short-lived codes
+        ("read-only" | "oauth", _) => true,
+        (
+            "queue" | "settings" | "reports" | "quarantine" | "search" | "principal" | "dkim"
+            | "dns" | "reputation" | "account" | "mailing-list" | "suppression" | "public-folders"
+            | "logs" | "events" | "troubleshoot" | "telemetry" | "reload",
+            &Method::GET,
+        ) => true,
+        ("troubleshoot", &Method::POST) => path.get(1).copied() == Some("dmarc"),
+        ("reload", &Method::POST) => path.get(1).copied() == Some("check"),
+        ("store", &Method::GET) => match path.get(1).copied() {
+            Some("blobs" | "usage" | "snapshot" | "trash") => true,
+            Some("fsck" | "check") => !UrlParams::new(query)
+                .parse::<bool>("repair")
+                .unwrap_or(false),
+            _ => false,
+        },
+        _ => false,
+    }
+}
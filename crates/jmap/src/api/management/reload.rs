@@ -13,6 +13,7 @@ use utils::url_params::UrlParams;
 
 use crate::{
     api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    services::gossip::spawn::GossiperBuilder,
     JmapMethods,
 };
 
@@ -21,6 +22,7 @@ pub trait ManageReload: Sync + Send {
         &self,
         req: &HttpRequest,
         path: Vec<&str>,
+        body: Option<Vec<u8>>,
         access_token: &AccessToken,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
 
@@ -37,6 +39,7 @@ impl ManageReload for Server {
         &self,
         req: &HttpRequest,
         path: Vec<&str>,
+        body: Option<Vec<u8>>,
         access_token: &AccessToken,
     ) -> trc::Result<HttpResponse> {
         // Validate the access token
@@ -59,6 +62,23 @@ impl ManageReload for Server {
                 "data": self.reload_certificates().await?.config,
             }))
             .into_http_response()),
+            (Some("check"), &Method::POST) => {
+                let toml =
+                    std::str::from_utf8(body.as_deref().unwrap_or_default()).map_err(|_| {
+                        trc::ResourceEvent::BadParameters
+                            .into_err()
+                            .details("Configuration file is not valid UTF-8")
+                    })?;
+
+                // Validate the candidate configuration without applying it
+                let mut result = self.check_config(toml).await?;
+                GossiperBuilder::try_parse(&mut result.config);
+
+                Ok(JsonResponse::new(json!({
+                    "data": result.report(),
+                }))
+                .into_http_response())
+            }
             (Some("server.blocked-ip"), &Method::GET) => {
                 let result = self.reload_blocked_ips().await?;
 
@@ -27,7 +27,7 @@ use utils::url_params::UrlParams;
 
 use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
 
-use super::decode_path_element;
+use super::{decode_path_element, Cursor};
 
 enum ReportType {
     Dmarc,
@@ -98,7 +98,14 @@ impl ManageReports for Server {
 
                 let params = UrlParams::new(req.uri().query());
                 let filter = params.get("text");
-                let page: usize = params.parse::<usize>("page").unwrap_or_default();
+                let cursor = params
+                    .parse::<Cursor>("cursor")
+                    .and_then(|cursor| ReportCursor::decode(&cursor.into_inner()));
+                let page: usize = if cursor.is_none() {
+                    params.parse::<usize>("page").unwrap_or_default()
+                } else {
+                    0
+                };
                 let limit: usize = params.parse::<usize>("limit").unwrap_or_default();
 
                 let range_start = params.parse::<u64>("range-start").unwrap_or_default();
@@ -146,6 +153,7 @@ impl ManageReports for Server {
                 let mut offset = page.saturating_sub(1) * limit;
                 let mut total = 0;
                 let mut last_id = 0;
+                let mut last_key = None;
                 let has_filters = filter.is_some() || tenant_domains.is_some();
                 self.core
                     .storage
@@ -209,16 +217,19 @@ impl ManageReports for Server {
                             };
 
                             if matches {
-                                if offset == 0 {
-                                    if limit == 0 || results.len() < limit {
-                                        results.push(format!(
-                                            "{}_{}",
-                                            id,
-                                            key.deserialize_be_u64(1)?
-                                        ));
+                                // Reports are sorted newest first, resume below the last one returned
+                                let expires = key.deserialize_be_u64(1)?;
+                                if cursor.as_ref().map_or(true, |cursor| {
+                                    (expires, id) < (cursor.expires, cursor.id)
+                                }) {
+                                    if offset == 0 {
+                                        if limit == 0 || results.len() < limit {
+                                            results.push(format!("{}_{}", id, expires));
+                                            last_key = Some(ReportCursor { id, expires });
+                                        }
+                                    } else {
+                                        offset -= 1;
                                     }
-                                } else {
-                                    offset -= 1;
                                 }
 
                                 total += 1;
@@ -229,11 +240,15 @@ impl ManageReports for Server {
                     )
                     .await
                     .caused_by(trc::location!())?;
+                let cursor = last_key
+                    .filter(|_| limit != 0 && results.len() == limit)
+                    .map(|cursor| Cursor::encode(cursor.encode()));
 
                 Ok(JsonResponse::new(json!({
                         "data": {
                             "items": results,
                             "total": total,
+                            "cursor": cursor,
                         },
                 }))
                 .into_http_response())
@@ -307,6 +322,9 @@ impl ManageReports for Server {
                             }
                             _ => Err(trc::ResourceEvent::NotFound.into_err()),
                         },
+                        ReportClass::Quarantine { .. } => {
+                            Err(trc::ResourceEvent::NotFound.into_err())
+                        }
                     }
                 } else {
                     Err(trc::ResourceEvent::NotFound.into_err())
@@ -347,6 +365,7 @@ impl ManageReports for Server {
                                 ))
                                 .await?
                                 .map_or(true, |report| report.inner.has_domain(domains)),
+                            ReportClass::Quarantine { .. } => false,
                         };
 
                         if !is_tenant_report {
@@ -383,6 +402,27 @@ fn parse_incoming_report_id(class: &str, id: &str) -> Option<ReportClass> {
     }
 }
 
+struct ReportCursor {
+    id: u64,
+    expires: u64,
+}
+
+impl ReportCursor {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(U64_LEN * 2);
+        bytes.extend_from_slice(&self.expires.to_be_bytes());
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            expires: bytes.deserialize_be_u64(0).ok()?,
+            id: bytes.deserialize_be_u64(U64_LEN).ok()?,
+        })
+    }
+}
+
 impl From<&str> for ReportType {
     fn from(s: &str) -> Self {
         match s {
@@ -0,0 +1,77 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use directory::{backend::internal::manage, Permission};
+use hyper::Method;
+use serde_json::json;
+use smtp::core::reputation::{FeedList, SmtpReputation};
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+pub trait ManageReputation: Sync + Send {
+    fn handle_manage_reputation(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageReputation for Server {
+    async fn handle_manage_reputation(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        if self
+            .core
+            .smtp
+            .session
+            .reputation
+            .as_ref()
+            .map_or(true, |config| config.feed.is_none())
+        {
+            return Err(manage::unsupported("Reputation feeds are not enabled"));
+        }
+
+        match (path.get(1).copied(), req.method()) {
+            (Some("feed"), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::ReputationFeedExport)?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.reputation_feed_export().await?,
+                }))
+                .into_http_response())
+            }
+            (Some("feed"), &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::ReputationFeedImport)?;
+
+                let feed =
+                    match serde_json::from_slice::<FeedList>(body.as_deref().unwrap_or_default()) {
+                        Ok(feed) => feed,
+                        Err(err) => {
+                            return Err(trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .reason(err))
+                        }
+                    };
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.reputation_feed_import(feed).await?,
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
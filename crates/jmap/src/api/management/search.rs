@@ -0,0 +1,218 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use directory::{
+    backend::internal::manage::{err_missing, ManageDirectory},
+    Permission,
+};
+use hyper::Method;
+use jmap_proto::{
+    method::query::{Filter, QueryRequest},
+    object::email::QueryArguments,
+    types::{collection::Collection, date::UTCDate, id::Id, property::Property},
+};
+use mail_parser::{GetHeader, HeaderName};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use store::write::Bincode;
+use utils::url_params::UrlParams;
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    email::{metadata::MessageMetadata, query::EmailQuery},
+    JmapMethods,
+};
+
+use super::{Cursor, Timestamp};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSearchResult {
+    pub id: String,
+    pub received_at: String,
+    pub size: usize,
+    #[serde(default)]
+    pub message_id: Option<String>,
+    #[serde(default)]
+    pub from: Vec<String>,
+    #[serde(default)]
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub subject: Option<String>,
+}
+
+pub trait ManageSearch: Sync + Send {
+    fn handle_manage_search(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageSearch for Server {
+    async fn handle_manage_search(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match (path.get(1), req.method()) {
+            (None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageSearch)?;
+
+                let params = UrlParams::new(req.uri().query());
+                let cursor = params
+                    .parse::<Cursor>("cursor")
+                    .and_then(|cursor| cursor.to_u64());
+                let page: usize = if cursor.is_none() {
+                    params.parse::<usize>("page").unwrap_or_default()
+                } else {
+                    0
+                };
+                let limit: usize = params.parse::<usize>("limit").unwrap_or_default();
+                let account_id = self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_id(
+                        params
+                            .get("account")
+                            .ok_or_else(|| err_missing("account"))?,
+                    )
+                    .await?
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                // Build filter
+                let mut filter = Vec::new();
+                if let Some(text) = params.get("text") {
+                    filter.push(Filter::Text(text.to_string()));
+                }
+                if let Some(from) = params.get("from") {
+                    filter.push(Filter::From(from.to_string()));
+                }
+                if let Some(to) = params.get("to") {
+                    filter.push(Filter::To(to.to_string()));
+                }
+                if let Some(participant) = params.get("participant") {
+                    filter.push(Filter::Or);
+                    filter.push(Filter::From(participant.to_string()));
+                    filter.push(Filter::To(participant.to_string()));
+                    filter.push(Filter::Cc(participant.to_string()));
+                    filter.push(Filter::Bcc(participant.to_string()));
+                    filter.push(Filter::Close);
+                }
+                if let Some(subject) = params.get("subject") {
+                    filter.push(Filter::Subject(subject.to_string()));
+                }
+                if let Some(before) = params.parse::<Timestamp>("before") {
+                    filter.push(Filter::Before(UTCDate::from_timestamp(
+                        before.into_inner() as i64
+                    )));
+                }
+                if let Some(after) = params.parse::<Timestamp>("after") {
+                    filter.push(Filter::After(UTCDate::from_timestamp(
+                        after.into_inner() as i64
+                    )));
+                }
+
+                // Run query, messages are sorted by received date in descending order
+                let response = self
+                    .email_query(
+                        QueryRequest {
+                            account_id: Id::from(account_id),
+                            filter,
+                            sort: None,
+                            position: (page > 1 && limit > 0).then(|| ((page - 1) * limit) as i32),
+                            // Resume after the last message returned
+                            anchor: cursor.map(Id::from),
+                            anchor_offset: cursor.map(|_| 1),
+                            limit: (limit > 0).then_some(limit),
+                            calculate_total: Some(true),
+                            arguments: QueryArguments {
+                                collapse_threads: None,
+                            },
+                        },
+                        access_token,
+                    )
+                    .await?;
+
+                let cursor = response
+                    .ids
+                    .last()
+                    .filter(|_| limit != 0 && response.ids.len() == limit)
+                    .map(|id| Cursor::encode(id.id().to_be_bytes()));
+
+                // Obtain message headers
+                let mut items = Vec::with_capacity(response.ids.len());
+                for id in response.ids {
+                    if let Some(metadata) = self
+                        .get_property::<Bincode<MessageMetadata>>(
+                            account_id,
+                            Collection::Email,
+                            id.document_id(),
+                            &Property::BodyStructure,
+                        )
+                        .await?
+                    {
+                        items.push(MessageSearchResult::new(id, metadata.inner));
+                    }
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "items": items,
+                            "total": response.total.unwrap_or_default(),
+                            "cursor": cursor,
+                        },
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+impl MessageSearchResult {
+    fn new(id: Id, metadata: MessageMetadata<'_>) -> Self {
+        let headers = &metadata.contents.root_part().headers;
+        let addresses = |name: HeaderName<'static>| {
+            headers
+                .header_value(&name)
+                .and_then(|value| value.as_address())
+                .map(|addr| {
+                    addr.iter()
+                        .filter_map(|addr| addr.address())
+                        .map(|addr| addr.to_string())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        MessageSearchResult {
+            id: id.to_string(),
+            received_at: UTCDate::from_timestamp(metadata.received_at as i64).to_string(),
+            size: metadata.size,
+            message_id: headers
+                .header_value(&HeaderName::MessageId)
+                .and_then(|value| value.as_text())
+                .map(|value| value.to_string()),
+            from: addresses(HeaderName::From),
+            to: addresses(HeaderName::To),
+            cc: addresses(HeaderName::Cc),
+            subject: headers
+                .header_value(&HeaderName::Subject)
+                .and_then(|value| value.as_text())
+                .map(|value| value.to_string()),
+        }
+    }
+}
@@ -13,7 +13,7 @@ use utils::{config::ConfigKey, map::vec_map::VecMap, url_params::UrlParams};
 
 use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
 
-use super::decode_path_element;
+use super::{decode_path_element, Cursor};
 use std::future::Future;
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -80,10 +80,21 @@ impl ManageSettings for Server {
                 let field = params.get("field");
                 let filter = params.get("filter").unwrap_or_default().to_lowercase();
                 let limit: usize = params.parse("limit").unwrap_or(0);
-                let mut offset =
-                    params.parse::<usize>("page").unwrap_or(0).saturating_sub(1) * limit;
+                let cursor = params
+                    .parse::<Cursor>("cursor")
+                    .and_then(|cursor| String::from_utf8(cursor.into_inner()).ok());
+                let mut offset = if cursor.is_none() {
+                    params.parse::<usize>("page").unwrap_or(0).saturating_sub(1) * limit
+                } else {
+                    0
+                };
                 let has_filter = !filter.is_empty();
 
+                // Settings are sorted by key, resume after the last key returned
+                let is_after_cursor =
+                    |key: &str| cursor.as_deref().map_or(true, |cursor| key > cursor);
+                let mut last_key = None;
+
                 let settings = self.core.storage.config.list(&prefix, true).await?;
                 if !suffix.is_empty() && !settings.is_empty() {
                     // Obtain record ids
@@ -93,16 +104,19 @@ impl ManageSettings for Server {
                         if let Some(id) = key.strip_suffix(&suffix) {
                             if !id.is_empty() {
                                 if !has_filter {
-                                    if offset == 0 {
-                                        if limit == 0 || ids.len() < limit {
-                                            ids.push(id);
+                                    if is_after_cursor(key) {
+                                        if offset == 0 {
+                                            if limit == 0 || ids.len() < limit {
+                                                ids.push((key, id));
+                                                last_key = Some(key);
+                                            }
+                                        } else {
+                                            offset -= 1;
                                         }
-                                    } else {
-                                        offset -= 1;
                                     }
                                     total += 1;
                                 } else {
-                                    ids.push(id);
+                                    ids.push((key, id));
                                 }
                             }
                         }
@@ -110,7 +124,7 @@ impl ManageSettings for Server {
 
                     // Group settings by record id
                     let mut records = Vec::new();
-                    for id in ids {
+                    for (key, id) in ids {
                         let mut record = AHashMap::new();
                         let prefix = format!("{id}.");
                         record.insert("_id".to_string(), id.to_string());
@@ -129,12 +143,15 @@ impl ManageSettings for Server {
                                 .iter()
                                 .any(|(_, v)| v.to_lowercase().contains(&filter))
                             {
-                                if offset == 0 {
-                                    if limit == 0 || records.len() < limit {
-                                        records.push(record);
+                                if is_after_cursor(key) {
+                                    if offset == 0 {
+                                        if limit == 0 || records.len() < limit {
+                                            records.push(record);
+                                            last_key = Some(key);
+                                        }
+                                    } else {
+                                        offset -= 1;
                                     }
-                                } else {
-                                    offset -= 1;
                                 }
                                 total += 1;
                             }
@@ -142,40 +159,51 @@ impl ManageSettings for Server {
                             records.push(record);
                         }
                     }
+                    let cursor = last_key
+                        .filter(|_| limit != 0 && records.len() == limit)
+                        .map(Cursor::encode);
 
                     Ok(JsonResponse::new(json!({
                         "data": {
                             "total": total,
                             "items": records,
+                            "cursor": cursor,
                         },
                     }))
                     .into_http_response())
                 } else {
                     let total = settings.len();
-                    let items = settings
+                    let settings = settings
                         .into_iter()
-                        .filter_map(|(k, v)| {
-                            if filter.is_empty()
-                                || k.to_lowercase().contains(&filter)
-                                || v.to_lowercase().contains(&filter)
-                            {
-                                let k = k.strip_prefix(&prefix).map(|k| k.to_string()).unwrap_or(k);
-                                Some(json!({
-                                    "_id": k,
-                                    "_value": v,
-                                }))
-                            } else {
-                                None
-                            }
+                        .filter(|(k, v)| {
+                            is_after_cursor(k)
+                                && (filter.is_empty()
+                                    || k.to_lowercase().contains(&filter)
+                                    || v.to_lowercase().contains(&filter))
                         })
                         .skip(offset)
                         .take(if limit == 0 { total } else { limit })
                         .collect::<Vec<_>>();
+                    let cursor = settings
+                        .last()
+                        .filter(|_| limit != 0 && settings.len() == limit)
+                        .map(|(k, _)| Cursor::encode(k));
+                    let items = settings
+                        .into_iter()
+                        .map(|(k, v)| {
+                            let k = k.strip_prefix(&prefix).map(|k| k.to_string()).unwrap_or(k);
+                            json!({
+                                "_id": k,
+                                "_value": v,
+                            })
+                        })
+                        .collect::<Vec<_>>();
 
                     Ok(JsonResponse::new(json!({
                         "data": {
                             "total": total,
                             "items": items,
+                            "cursor": cursor,
                         },
                     }))
                     .into_http_response())
@@ -198,20 +226,36 @@ impl ManageSettings for Server {
                     })
                     .unwrap_or_default();
                 let limit: usize = params.parse("limit").unwrap_or(0);
-                let offset = params.parse::<usize>("page").unwrap_or(0).saturating_sub(1) * limit;
+                let cursor = params
+                    .parse::<Cursor>("cursor")
+                    .and_then(|cursor| String::from_utf8(cursor.into_inner()).ok());
+                let offset = if cursor.is_none() {
+                    params.parse::<usize>("page").unwrap_or(0).saturating_sub(1) * limit
+                } else {
+                    0
+                };
 
                 let settings = self.core.storage.config.list(&prefix, true).await?;
                 let total = settings.len();
+
+                // Settings are sorted by key, resume after the last key returned
                 let items = settings
                     .into_iter()
+                    .filter(|(k, _)| cursor.as_ref().map_or(true, |cursor| k > cursor))
                     .skip(offset)
                     .take(if limit == 0 { total } else { limit })
                     .collect::<VecMap<_, _>>();
+                let cursor = items
+                    .keys()
+                    .last()
+                    .filter(|_| limit != 0 && items.len() == limit)
+                    .map(Cursor::encode);
 
                 Ok(JsonResponse::new(json!({
                     "data": {
                         "total": total,
                         "items": items,
+                        "cursor": cursor,
                     },
                 }))
                 .into_http_response())
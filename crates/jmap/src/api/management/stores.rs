@@ -12,25 +12,36 @@ use common::{
     Server,
 };
 use directory::{
-    backend::internal::manage::{self, ManageDirectory},
-    Permission,
+    backend::internal::{
+        manage::{self, ManageDirectory},
+        PrincipalField,
+    },
+    Permission, Type,
 };
 use hyper::Method;
+use mail_parser::DateTime;
 use serde_json::json;
-use utils::url_params::UrlParams;
+use store::{ahash::AHashSet, write::now, CompressionAlgo};
+use utils::{url_params::UrlParams, BlobHash};
 
 use crate::{
     api::{
         http::{HttpSessionData, ToHttpResponse},
         HttpRequest, HttpResponse, JsonResponse,
     },
-    services::index::Indexer,
+    email::{archive::EmailArchive, trash::EmailTrash},
+    mailbox::counters::MailboxCounterCache,
+    services::{
+        fsck::Fsck,
+        index::Indexer,
+        snapshot::{AccountSnapshot, SnapshotInfo},
+    },
 };
 
 use super::decode_path_element;
 #[cfg(feature = "enterprise")]
 use super::enterprise::undelete::UndeleteApi;
-use std::future::Future;
+use std::{future::Future, path::PathBuf};
 
 pub trait ManageStore: Sync + Send {
     fn handle_manage_store(
@@ -104,6 +115,43 @@ impl ManageStore for Server {
                 }))
                 .await
             }
+            (Some("backup"), id, None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::StoreBackup)?;
+
+                let store = if let Some(id) = id {
+                    if let Some(store) = self.core.storage.stores.get(id) {
+                        store.clone()
+                    } else {
+                        return Err(trc::ResourceEvent::NotFound.into_err());
+                    }
+                } else {
+                    self.core.storage.data.clone()
+                };
+                let dest = UrlParams::new(req.uri().query())
+                    .get("path")
+                    .map(PathBuf::from)
+                    .ok_or_else(|| manage::err_missing("path"))?;
+                if dest.exists() {
+                    return Err(manage::err_exists(
+                        "path",
+                        dest.to_string_lossy().into_owned(),
+                    ));
+                }
+
+                store.backup(dest).await.map_err(|err| {
+                    if err.matches(trc::EventType::Store(trc::StoreEvent::NotSupported)) {
+                        manage::unsupported("Online backups are only supported by SQLite")
+                    } else {
+                        err
+                    }
+                })?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
             (Some("purge"), Some("data"), id, &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::PurgeDataStore)?;
@@ -186,6 +234,360 @@ impl ManageStore for Server {
                 }))
                 .into_http_response())
             }
+            (Some("usage"), id, None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::StoreUsage)?;
+
+                let store = if let Some(id) = id {
+                    if let Some(store) = self.core.storage.stores.get(id) {
+                        store.clone()
+                    } else {
+                        return Err(trc::ResourceEvent::NotFound.into_err());
+                    }
+                } else {
+                    self.core.storage.data.clone()
+                };
+                let params = UrlParams::new(req.uri().query());
+                let mut usage = store
+                    .key_space_usage(params.parse("limit").unwrap_or(25))
+                    .await?;
+
+                // Resolve account names
+                for account in &mut usage.accounts {
+                    account.name = self
+                        .core
+                        .storage
+                        .data
+                        .get_principal(account.account_id)
+                        .await?
+                        .map(|principal| principal.name().to_string());
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": usage,
+                }))
+                .into_http_response())
+            }
+            (Some("archive"), id, None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::StoreArchive)?;
+
+                if matches!(self.core.storage.blob.compression, CompressionAlgo::None) {
+                    return Err(trc::ManageEvent::NotSupported
+                        .into_err()
+                        .details("Blob compression must be enabled to archive messages"));
+                }
+
+                let account_ids = if let Some(id) = id {
+                    vec![self
+                        .core
+                        .storage
+                        .data
+                        .get_principal_id(decode_path_element(id).as_ref())
+                        .await?
+                        .ok_or_else(|| trc::ManageEvent::NotFound.into_err())?]
+                } else {
+                    self.core
+                        .storage
+                        .data
+                        .list_principals(
+                            None,
+                            access_token.tenant.map(|t| t.id),
+                            &[Type::Individual, Type::Group],
+                            &[PrincipalField::Name],
+                            0,
+                            0,
+                        )
+                        .await?
+                        .items
+                        .into_iter()
+                        .map(|principal| principal.id())
+                        .collect()
+                };
+                let params = UrlParams::new(req.uri().query());
+                let older_than = params.parse::<u64>("months").unwrap_or(12) * 30 * 86400;
+
+                let mut seen = AHashSet::new();
+                let mut reports = Vec::with_capacity(account_ids.len());
+                for account_id in account_ids {
+                    let mut report = self
+                        .archive_account_messages(account_id, older_than, &mut seen)
+                        .await?;
+                    if report.messages > 0 {
+                        report.name = self
+                            .core
+                            .storage
+                            .data
+                            .get_principal(account_id)
+                            .await?
+                            .map(|principal| principal.name().to_string());
+                        reports.push(report);
+                    }
+                }
+                reports.sort_unstable_by(|a, b| {
+                    b.reclaimed()
+                        .cmp(&a.reclaimed())
+                        .then_with(|| a.account_id.cmp(&b.account_id))
+                });
+
+                Ok(JsonResponse::new(json!({
+                    "data": reports,
+                }))
+                .into_http_response())
+            }
+            (Some("check"), Some("counters"), id, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::StoreRepair)?;
+
+                let account_ids = if let Some(id) = id {
+                    vec![self
+                        .core
+                        .storage
+                        .data
+                        .get_principal_id(decode_path_element(id).as_ref())
+                        .await?
+                        .ok_or_else(|| trc::ManageEvent::NotFound.into_err())?]
+                } else {
+                    self.core
+                        .storage
+                        .data
+                        .list_principals(
+                            None,
+                            access_token.tenant.map(|t| t.id),
+                            &[Type::Individual, Type::Group],
+                            &[PrincipalField::Name],
+                            0,
+                            0,
+                        )
+                        .await?
+                        .items
+                        .into_iter()
+                        .map(|principal| principal.id())
+                        .collect()
+                };
+                let params = UrlParams::new(req.uri().query());
+                let repair = params.parse::<bool>("repair").unwrap_or(false);
+
+                let mut results = Vec::new();
+                for account_id in account_ids {
+                    let mailbox_ids = self.mailbox_check_counters(account_id, repair).await?;
+                    if !mailbox_ids.is_empty() {
+                        results.push(json!({
+                            "accountId": account_id,
+                            "mailboxIds": mailbox_ids,
+                        }));
+                    }
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": results,
+                }))
+                .into_http_response())
+            }
+            (Some("fsck"), id, None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::StoreRepair)?;
+
+                let params = UrlParams::new(req.uri().query());
+                let repair = params.parse::<bool>("repair").unwrap_or(false);
+
+                if let Some(id) = id {
+                    let account_id = self
+                        .core
+                        .storage
+                        .data
+                        .get_principal_id(decode_path_element(id).as_ref())
+                        .await?
+                        .ok_or_else(|| trc::ManageEvent::NotFound.into_err())?;
+
+                    Ok(JsonResponse::new(json!({
+                        "data": self.fsck_account(account_id, repair).await?,
+                    }))
+                    .into_http_response())
+                } else {
+                    let account_ids = self
+                        .core
+                        .storage
+                        .data
+                        .list_principals(
+                            None,
+                            access_token.tenant.map(|t| t.id),
+                            &[Type::Individual, Type::Group],
+                            &[PrincipalField::Name],
+                            0,
+                            0,
+                        )
+                        .await?
+                        .items
+                        .into_iter()
+                        .map(|principal| principal.id())
+                        .collect::<Vec<_>>();
+
+                    // Inconsistencies are reported through the tracing subsystem
+                    let server = self.clone();
+                    tokio::spawn(async move {
+                        for account_id in account_ids {
+                            if let Err(err) = server.fsck_account(account_id, repair).await {
+                                trc::error!(err
+                                    .account_id(account_id)
+                                    .details("Failed to check account consistency"));
+                            }
+                        }
+                    });
+
+                    Ok(JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response())
+                }
+            }
+            (Some("snapshot"), Some(id), None, &Method::GET | &Method::POST | &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::StoreRestore)?;
+
+                let account_id = self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_id(decode_path_element(id).as_ref())
+                    .await?
+                    .ok_or_else(|| trc::ManageEvent::NotFound.into_err())?;
+                let snapshot_json = |info: SnapshotInfo| {
+                    json!({
+                        "createdAt": DateTime::from_timestamp(info.created_at as i64).to_rfc3339(),
+                        "expiresAt": DateTime::from_timestamp(info.expires_at as i64).to_rfc3339(),
+                        "mailboxes": info.mailboxes,
+                        "emails": info.emails,
+                    })
+                };
+
+                let data = if req.method() == Method::POST {
+                    snapshot_json(self.snapshot_create(account_id).await?)
+                } else if req.method() == Method::DELETE {
+                    self.snapshot_delete(account_id).await?;
+                    serde_json::Value::Null
+                } else {
+                    self.snapshot_list(account_id)
+                        .await?
+                        .into_iter()
+                        .map(snapshot_json)
+                        .collect()
+                };
+
+                Ok(JsonResponse::new(json!({
+                    "data": data,
+                }))
+                .into_http_response())
+            }
+            (Some("restore"), Some(id), None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::StoreRestore)?;
+
+                let account_id = self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_id(decode_path_element(id).as_ref())
+                    .await?
+                    .ok_or_else(|| trc::ManageEvent::NotFound.into_err())?;
+                let params = UrlParams::new(req.uri().query());
+                let restore_at = if let Some(at) = params.get("at") {
+                    DateTime::parse_rfc3339(at)
+                        .ok_or_else(|| trc::ResourceEvent::BadParameters.into_err())?
+                        .to_timestamp() as u64
+                } else {
+                    now()
+                };
+
+                Ok(JsonResponse::new(json!({
+                    "data": self
+                        .snapshot_restore(
+                            account_id,
+                            restore_at,
+                            params.get("mailbox"),
+                            session.session_id,
+                        )
+                        .await?,
+                }))
+                .into_http_response())
+            }
+            (Some("trash"), Some(id), None, &Method::GET | &Method::POST | &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::Undelete)?;
+
+                let account_id = self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_id(decode_path_element(id).as_ref())
+                    .await?
+                    .ok_or_else(|| trc::ManageEvent::NotFound.into_err())?;
+
+                let data = if req.method() == Method::POST {
+                    // Restore the requested messages, or the entire trash if none are given
+                    let blob_hashes = match body.as_deref() {
+                        Some(body) if !body.is_empty() => {
+                            serde_json::from_slice::<Vec<String>>(body)
+                                .ok()
+                                .and_then(|hashes| {
+                                    hashes
+                                        .into_iter()
+                                        .map(|hash| {
+                                            BlobHash::try_from_hash_slice(
+                                                URL_SAFE_NO_PAD
+                                                    .decode(hash.as_bytes())
+                                                    .ok()?
+                                                    .as_slice(),
+                                            )
+                                            .ok()
+                                        })
+                                        .collect::<Option<Vec<_>>>()
+                                })
+                                .ok_or_else(|| trc::ResourceEvent::BadParameters.into_err())?
+                                .into()
+                        }
+                        _ => None,
+                    };
+
+                    serde_json::to_value(
+                        self.trash_restore(account_id, blob_hashes, session.session_id)
+                            .await?,
+                    )
+                    .unwrap_or_default()
+                } else if req.method() == Method::DELETE {
+                    self.trash_empty(account_id).await?;
+                    serde_json::Value::Null
+                } else {
+                    self.trash_list(account_id)
+                        .await?
+                        .into_iter()
+                        .map(|entry| {
+                            json!({
+                                "hash": URL_SAFE_NO_PAD.encode(entry.blob_hash.as_slice()),
+                                "subject": entry.email.subject,
+                                "size": entry.email.size,
+                                "mailboxIds": entry.email.mailbox_ids,
+                                "keywords": entry.email.keywords,
+                                "receivedAt": DateTime::from_timestamp(
+                                    entry.email.received_at as i64
+                                )
+                                .to_rfc3339(),
+                                "deletedAt": DateTime::from_timestamp(
+                                    entry.email.deleted_at as i64
+                                )
+                                .to_rfc3339(),
+                                "expiresAt": DateTime::from_timestamp(entry.expires_at as i64)
+                                    .to_rfc3339(),
+                            })
+                        })
+                        .collect()
+                };
+
+                Ok(JsonResponse::new(json!({
+                    "data": data,
+                }))
+                .into_http_response())
+            }
             // SPDX-SnippetBegin
             // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
             // SPDX-License-Identifier: LicenseRef-SEL
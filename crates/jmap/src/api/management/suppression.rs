@@ -0,0 +1,119 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use directory::{backend::internal::manage, Permission};
+use hyper::Method;
+use serde_json::json;
+use smtp::core::bounce::SuppressionManager;
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+use super::decode_path_element;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuppressionRequest {
+    pub address: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+pub trait ManageSuppression: Sync + Send {
+    fn handle_manage_suppression(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageSuppression for Server {
+    async fn handle_manage_suppression(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        if !self.core.smtp.queue.suppression.enable {
+            return Err(manage::unsupported("Suppression lists are not enabled"));
+        }
+
+        let sender = path
+            .get(1)
+            .map(|sender| decode_path_element(sender).to_lowercase())
+            .filter(|sender| sender.contains('@'))
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+        match (path.get(2).copied(), req.method()) {
+            (None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SuppressionList)?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.suppression_list(&sender).await?,
+                }))
+                .into_http_response())
+            }
+            (None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SuppressionManage)?;
+
+                let request = match serde_json::from_slice::<SuppressionRequest>(
+                    body.as_deref().unwrap_or_default(),
+                ) {
+                    Ok(request) if request.address.contains('@') => request,
+                    Ok(_) => {
+                        return Err(trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                            .reason("Invalid recipient address"))
+                    }
+                    Err(err) => {
+                        return Err(
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters).reason(err)
+                        )
+                    }
+                };
+
+                self.suppression_add(
+                    &sender,
+                    &request.address,
+                    request
+                        .reason
+                        .as_deref()
+                        .unwrap_or("Added by administrator"),
+                    0,
+                )
+                .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some(rcpt), &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SuppressionManage)?;
+
+                if self
+                    .suppression_remove(&sender, decode_path_element(rcpt).as_ref())
+                    .await?
+                {
+                    Ok(JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response())
+                } else {
+                    Err(trc::ResourceEvent::NotFound.into_err())
+                }
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
@@ -23,7 +23,7 @@ use crate::{
     changes::{get::ChangesLookup, query::QueryChanges},
     email::{
         copy::EmailCopy, get::EmailGet, import::EmailImport, parse::EmailParse, query::EmailQuery,
-        set::EmailSet, snippet::EmailSearchSnippet,
+        set::EmailSet, share::EmailShare, snippet::EmailSearchSnippet,
     },
     identity::{get::IdentityGet, set::IdentitySet},
     mailbox::{get::MailboxGet, query::MailboxQuery, set::MailboxSet},
@@ -37,7 +37,7 @@ use crate::{
     },
     submission::{get::EmailSubmissionGet, query::EmailSubmissionQuery, set::EmailSubmissionSet},
     thread::get::ThreadGet,
-    vacation::{get::VacationResponseGet, set::VacationResponseSet},
+    vacation::{absence::AbsenceWindows, get::VacationResponseGet, set::VacationResponseSet},
 };
 
 use super::http::HttpSessionData;
@@ -179,7 +179,26 @@ impl RequestHandler for Server {
         // Check permissions
         access_token.assert_has_jmap_permission(&method)?;
 
+        // Reject mutations while the server is in read-only mode
+        if self.is_read_only()
+            && matches!(
+                method,
+                RequestMethod::Set(_)
+                    | RequestMethod::Copy(_)
+                    | RequestMethod::CopyBlob(_)
+                    | RequestMethod::ImportEmail(_)
+                    | RequestMethod::UploadBlob(_)
+                    | RequestMethod::ShareEmail(_)
+                    | RequestMethod::SetAbsence(_)
+            )
+        {
+            return Err(trc::JmapEvent::AccountReadOnly
+                .into_err()
+                .details("Server is in read-only mode"));
+        }
+
         // Handle method
+        let is_get = matches!(method, RequestMethod::Get(_));
         let response = match method {
             RequestMethod::Get(mut req) => match req.take_arguments() {
                 get::RequestArguments::Email(arguments) => {
@@ -335,6 +354,21 @@ impl RequestHandler for Server {
 
                 self.email_parse(req, access_token).await?.into()
             }
+            RequestMethod::ShareEmail(req) => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.email_share(req, access_token, session).await?.into()
+            }
+            RequestMethod::GetAbsence(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.absence_get(req).await?.into()
+            }
+            RequestMethod::SetAbsence(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.absence_set(req).await?.into()
+            }
             RequestMethod::QueryChanges(req) => self.query_changes(req, access_token).await?.into(),
             RequestMethod::SearchSnippet(req) => {
                 access_token.assert_has_access(req.account_id, Collection::Email)?;
@@ -372,6 +406,9 @@ impl RequestHandler for Server {
             AccountId = access_token.primary_id(),
             Elapsed = op_start.elapsed(),
         );
+        if is_get {
+            self.record_interactive_latency(op_start.elapsed());
+        }
 
         Ok(response)
     }
@@ -47,12 +47,13 @@ impl SessionHandler for Server {
         // Add secondary accounts
         for id in access_token.secondary_ids() {
             let is_personal = !access_token.is_member(*id);
-            let is_readonly = is_personal
-                && self
-                    .shared_documents(&access_token, *id, Collection::Mailbox, Acl::AddItems)
-                    .await
-                    .caused_by(trc::location!())?
-                    .is_empty();
+            let is_readonly = self.is_read_only()
+                || (is_personal
+                    && self
+                        .shared_documents(&access_token, *id, Collection::Mailbox, Acl::AddItems)
+                        .await
+                        .caused_by(trc::location!())?
+                        .is_empty());
 
             session.add_account(
                 (*id).into(),
@@ -66,7 +67,12 @@ impl SessionHandler for Server {
                     .unwrap_or_else(|| Id::from(*id).to_string()),
                 is_personal,
                 is_readonly,
-                Some(&[Capability::Mail, Capability::Quota, Capability::Blob]),
+                Some(&[
+                    Capability::Mail,
+                    Capability::Quota,
+                    Capability::Blob,
+                    Capability::SmimeVerify,
+                ]),
                 &self.core.jmap.capabilities.account,
             );
         }
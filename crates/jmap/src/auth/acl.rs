@@ -105,6 +105,17 @@ impl AclMethods for Server {
         check_acls: impl Into<Bitmap<Acl>>,
     ) -> trc::Result<RoaringBitmap> {
         let check_acls = check_acls.into();
+
+        // Delegated mailboxes
+        let mut delegated_acl = access_token.delegated_acl(to_account_id);
+        delegated_acl.intersection(&check_acls);
+        if !delegated_acl.is_empty() && to_collection == Collection::Mailbox {
+            return self
+                .get_document_ids(to_account_id, to_collection)
+                .await
+                .map(|document_ids| document_ids.unwrap_or_default());
+        }
+
         let mut document_ids = RoaringBitmap::new();
         let to_collection = u8::from(to_collection);
         for &grant_account_id in [access_token.primary_id]
@@ -215,6 +226,19 @@ impl AclMethods for Server {
     ) -> trc::Result<bool> {
         let to_collection = to_collection.into();
         let check_acls = check_acls.into();
+
+        // Delegated mailboxes
+        let mut delegated_acl = access_token.delegated_acl(to_account_id);
+        delegated_acl.intersection(&check_acls);
+        if !delegated_acl.is_empty()
+            && matches!(
+                Collection::from(to_collection),
+                Collection::Mailbox | Collection::Email | Collection::Thread
+            )
+        {
+            return Ok(true);
+        }
+
         for &grant_account_id in [access_token.primary_id]
             .iter()
             .chain(access_token.member_of.clone().iter())
@@ -358,7 +382,6 @@ impl AclMethods for Server {
 
     fn refresh_acls(&self, changes: &Object<Value>, current: &Option<HashedValue<Object<Value>>>) {
         if let Value::Acl(acl_changes) = changes.get(&Property::Acl) {
-            let access_tokens = &self.inner.data.access_tokens;
             if let Some(Value::Acl(acl_current)) = current
                 .as_ref()
                 .and_then(|current| current.inner.properties.get(&Property::Acl))
@@ -372,7 +395,7 @@ impl AclMethods for Server {
                         }
                     }
                     if invalidate {
-                        access_tokens.remove(&current_item.account_id);
+                        self.invalidate_access_token(current_item.account_id);
                     }
                 }
 
@@ -385,12 +408,12 @@ impl AclMethods for Server {
                         }
                     }
                     if invalidate {
-                        access_tokens.remove(&change_item.account_id);
+                        self.invalidate_access_token(change_item.account_id);
                     }
                 }
             } else {
                 for value in acl_changes {
-                    access_tokens.remove(&value.account_id);
+                    self.invalidate_access_token(value.account_id);
                 }
             }
         }
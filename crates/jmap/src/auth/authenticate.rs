@@ -17,7 +17,10 @@ use crate::api::{http::HttpSessionData, HttpRequest};
 use common::auth::AccessToken;
 use std::future::Future;
 
-use super::rate_limit::RateLimiter;
+use super::{challenge::ChallengeHandler, rate_limit::RateLimiter};
+
+/// Header used to present and to return the token of a trusted device.
+pub const TRUSTED_DEVICE_HEADER: &str = "X-Trusted-Device";
 
 pub trait Authenticator: Sync + Send {
     fn authenticate_headers(
@@ -44,6 +47,9 @@ impl Authenticator for Server {
                         // Throttle authentication requests
                         self.is_auth_allowed_soft(&session.remote_ip).await?;
 
+                        // Require a challenge after repeated failures
+                        self.assert_auth_challenge(req, session).await?;
+
                         // Decode the base64 encoded credentials
                         decode_plain_auth(token).ok_or_else(|| {
                             trc::AuthEvent::Error
@@ -75,18 +81,30 @@ impl Authenticator for Server {
                     };
 
                     // Authenticate
-                    let access_token = match self
-                        .authenticate(&AuthRequest::from_credentials(
-                            credentials,
-                            session.session_id,
-                            session.remote_ip,
-                        ))
-                        .await
-                    {
+                    let auth_request = AuthRequest::from_credentials(
+                        credentials,
+                        session.session_id,
+                        session.remote_ip,
+                    )
+                    .with_trusted_device(
+                        req.headers()
+                            .get(TRUSTED_DEVICE_HEADER)
+                            .and_then(|value| value.to_str().ok())
+                            .map(|value| value.trim().to_string()),
+                        &session.trusted_device,
+                    )
+                    .with_fingerprint(
+                        req.headers()
+                            .get(header::USER_AGENT)
+                            .and_then(|value| value.to_str().ok())
+                            .map(|value| value.to_string()),
+                    );
+                    let access_token = match self.authenticate(&auth_request).await {
                         Ok(access_token) => access_token,
                         Err(err) => {
                             if err.matches(trc::EventType::Auth(trc::AuthEvent::Failed)) {
                                 let _ = self.is_auth_allowed_hard(&session.remote_ip).await;
+                                let _ = self.record_auth_challenge_failure(session.remote_ip).await;
                             }
                             return Err(err);
                         }
@@ -0,0 +1,296 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, net::IpAddr, time::Duration};
+
+use common::{
+    config::network::{AuthChallenge, ChallengeProvider},
+    Server,
+};
+use hyper::header::CONTENT_TYPE;
+use serde_json::json;
+use store::{
+    blake3,
+    rand::{distributions::Alphanumeric, thread_rng, Rng},
+    write::now,
+};
+use trc::AddContext;
+
+use crate::api::{
+    http::{HttpContext, HttpSessionData, ToHttpResponse},
+    HttpRequest, HttpResponse, JsonResponse,
+};
+
+pub const CHALLENGE_HEADER: &str = "X-Auth-Challenge";
+
+const NONCE_LEN: usize = 16;
+const SIGNATURE_LEN: usize = 32;
+
+pub trait ChallengeHandler: Sync + Send {
+    fn assert_auth_challenge(
+        &self,
+        req: &HttpRequest,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn record_auth_challenge_failure(
+        &self,
+        remote_ip: IpAddr,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn handle_challenge_request(
+        &self,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ChallengeHandler for Server {
+    async fn assert_auth_challenge(
+        &self,
+        req: &HttpRequest,
+        session: &HttpSessionData,
+    ) -> trc::Result<()> {
+        if let Some(challenge) = &self.core.network.auth_challenge {
+            if self
+                .eval_if(
+                    &challenge.enable,
+                    &HttpContext::new(session, req),
+                    session.session_id,
+                )
+                .await
+                .unwrap_or(false)
+                && self
+                    .core
+                    .storage
+                    .lookup
+                    .is_rate_allowed(
+                        format!("achf:{}", session.remote_ip).as_bytes(),
+                        &challenge.threshold,
+                        true,
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+                    .is_some()
+            {
+                let response = req
+                    .headers()
+                    .get(CHALLENGE_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| {
+                        trc::AuthEvent::ChallengeRequired
+                            .into_err()
+                            .ctx(trc::Key::RemoteIp, session.remote_ip)
+                    })?;
+
+                if !verify_challenge_response(self, challenge, response, session.remote_ip).await? {
+                    return Err(trc::AuthEvent::ChallengeFailed
+                        .into_err()
+                        .ctx(trc::Key::RemoteIp, session.remote_ip));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn record_auth_challenge_failure(&self, remote_ip: IpAddr) -> trc::Result<()> {
+        if let Some(challenge) = &self.core.network.auth_challenge {
+            self.core
+                .storage
+                .lookup
+                .is_rate_allowed(
+                    format!("achf:{remote_ip}").as_bytes(),
+                    &challenge.threshold,
+                    false,
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_challenge_request(
+        &self,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        let response = match self
+            .core
+            .network
+            .auth_challenge
+            .as_ref()
+            .map(|c| &c.provider)
+        {
+            Some(ChallengeProvider::ProofOfWork { difficulty, expiry }) => {
+                let expires = now() + expiry;
+                json!({
+                    "type": "pow",
+                    "challenge": pow_challenge(&challenge_key(self), session.remote_ip, expires),
+                    "difficulty": difficulty,
+                    "expires": expires,
+                })
+            }
+            Some(ChallengeProvider::Captcha { site_key, .. }) => json!({
+                "type": "captcha",
+                "siteKey": site_key,
+            }),
+            None => json!({
+                "type": "none",
+            }),
+        };
+
+        Ok(JsonResponse::new(json!({
+            "data": response,
+        }))
+        .no_cache()
+        .into_http_response())
+    }
+}
+
+async fn verify_challenge_response(
+    server: &Server,
+    challenge: &AuthChallenge,
+    response: &str,
+    remote_ip: IpAddr,
+) -> trc::Result<bool> {
+    match &challenge.provider {
+        ChallengeProvider::ProofOfWork { difficulty, .. } => {
+            if let Some((nonce, expires)) =
+                pow_verify(&challenge_key(server), remote_ip, *difficulty, response)
+            {
+                // Each solution can only be used once, the first caller to
+                // increment the counter wins
+                let key = format!("achu:{nonce}").into_bytes();
+                return server
+                    .core
+                    .storage
+                    .lookup
+                    .counter_incr(key, 1, expires.saturating_sub(now()).max(1).into(), true)
+                    .await
+                    .map(|uses| uses == 1)
+                    .caused_by(trc::location!());
+            }
+
+            Ok(false)
+        }
+        ChallengeProvider::Captcha {
+            verify_url, secret, ..
+        } => {
+            let body = form_urlencoded::Serializer::new(String::new())
+                .append_pair("secret", secret)
+                .append_pair("response", response)
+                .append_pair("remoteip", &remote_ip.to_string())
+                .finish();
+            let response = reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .map(|client| {
+                    client
+                        .post(verify_url)
+                        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                        .body(body)
+                })
+                .map_err(|err| {
+                    trc::AuthEvent::Error
+                        .into_err()
+                        .reason(err)
+                        .details("Failed to create HTTP client")
+                })?
+                .send()
+                .await
+                .map_err(|err| {
+                    trc::AuthEvent::Error
+                        .into_err()
+                        .ctx(trc::Key::Url, verify_url.to_string())
+                        .reason(err)
+                        .details("Captcha verification request failed")
+                })?
+                .bytes()
+                .await
+                .map_err(|err| {
+                    trc::AuthEvent::Error
+                        .into_err()
+                        .ctx(trc::Key::Url, verify_url.to_string())
+                        .reason(err)
+                        .details("Failed to read captcha verification response")
+                })?;
+
+            Ok(serde_json::from_slice::<serde_json::Value>(&response)
+                .ok()
+                .and_then(|response| response.get("success").and_then(|v| v.as_bool()))
+                .unwrap_or(false))
+        }
+    }
+}
+
+fn challenge_key(server: &Server) -> [u8; 32] {
+    blake3::derive_key("auth challenge", server.core.oauth.oauth_key.as_bytes())
+}
+
+fn pow_signature(key: &[u8; 32], nonce: &str, expires: u64, remote_ip: IpAddr) -> String {
+    let mut hasher = blake3::Hasher::new_keyed(key);
+    hasher.update(nonce.as_bytes());
+    hasher.update(&expires.to_be_bytes());
+    hasher.update(remote_ip.to_string().as_bytes());
+    hasher.finalize().to_hex()[..SIGNATURE_LEN].to_string()
+}
+
+/// Issues a proof-of-work challenge bound to the client's IP address, formatted as
+/// `<nonce>.<expires>.<signature>`.
+pub fn pow_challenge(key: &[u8; 32], remote_ip: IpAddr, expires: u64) -> String {
+    let nonce = thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(NONCE_LEN)
+        .map(char::from)
+        .collect::<String>();
+    let signature = pow_signature(key, &nonce, expires, remote_ip);
+    format!("{nonce}.{expires}.{signature}")
+}
+
+/// Verifies a `<challenge>:<solution>` response, where the BLAKE3 hash of the
+/// whole response must start with `difficulty` zero bits. Returns the challenge
+/// nonce and expiration time on success.
+pub fn pow_verify(
+    key: &[u8; 32],
+    remote_ip: IpAddr,
+    difficulty: u32,
+    response: &str,
+) -> Option<(String, u64)> {
+    let (challenge, _) = response.rsplit_once(':')?;
+    let mut parts = challenge.splitn(3, '.');
+    let nonce = parts.next()?;
+    let expires = parts.next()?.parse::<u64>().ok()?;
+    let signature = parts.next()?;
+
+    if nonce.len() == NONCE_LEN
+        && expires > now()
+        && pow_signature(key, nonce, expires, remote_ip)
+            .bytes()
+            .zip(signature.bytes())
+            .fold(signature.len() ^ SIGNATURE_LEN, |acc, (a, b)| {
+                acc | (a ^ b) as usize
+            })
+            == 0
+        && pow_leading_zeros(blake3::hash(response.as_bytes()).as_bytes()) >= difficulty
+    {
+        Some((nonce.to_string(), expires))
+    } else {
+        None
+    }
+}
+
+pub fn pow_leading_zeros(hash: &[u8]) -> u32 {
+    let mut zeros = 0;
+    for byte in hash {
+        if *byte == 0 {
+            zeros += 8;
+        } else {
+            zeros += byte.leading_zeros();
+            break;
+        }
+    }
+    zeros
+}
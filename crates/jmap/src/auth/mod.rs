@@ -6,5 +6,6 @@
 
 pub mod acl;
 pub mod authenticate;
+pub mod challenge;
 pub mod oauth;
 pub mod rate_limit;
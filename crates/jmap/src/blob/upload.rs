@@ -53,6 +53,13 @@ pub trait BlobUpload: Sync + Send {
         data: &[u8],
         set_quota: bool,
     ) -> impl Future<Output = trc::Result<BlobId>> + Send;
+
+    fn put_blob_checked(
+        &self,
+        account_id: u32,
+        data: &[u8],
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<BlobId>> + Send;
 }
 
 impl BlobUpload for Server {
@@ -222,39 +229,10 @@ impl BlobUpload for Server {
             }
         }
 
-        // Enforce quota
-        let used = self
-            .core
-            .storage
-            .data
-            .blob_quota(account_id.document_id())
-            .await
-            .caused_by(trc::location!())?;
-
-        if ((self.core.jmap.upload_tmp_quota_size > 0
-            && used.bytes + data.len() > self.core.jmap.upload_tmp_quota_size)
-            || (self.core.jmap.upload_tmp_quota_amount > 0
-                && used.count + 1 > self.core.jmap.upload_tmp_quota_amount))
-            && !access_token.has_permission(Permission::UnlimitedUploads)
-        {
-            let err = Err(trc::LimitEvent::BlobQuota
-                .into_err()
-                .ctx(trc::Key::Size, self.core.jmap.upload_tmp_quota_size)
-                .ctx(trc::Key::Total, self.core.jmap.upload_tmp_quota_amount));
-
-            #[cfg(feature = "test_mode")]
-            if !DISABLE_UPLOAD_QUOTA.load(std::sync::atomic::Ordering::Relaxed) {
-                return err;
-            }
-
-            #[cfg(not(feature = "test_mode"))]
-            return err;
-        }
-
         Ok(UploadResponse {
             account_id,
             blob_id: self
-                .put_blob(account_id.document_id(), data, true)
+                .put_blob_checked(account_id.document_id(), data, &access_token)
                 .await
                 .caused_by(trc::location!())?,
             c_type: content_type.to_string(),
@@ -296,7 +274,7 @@ impl BlobUpload for Server {
 
             // Commit blob
             let mut batch = BatchBuilder::new();
-            batch.set(BlobOp::Commit { hash: hash.clone() }, Vec::new());
+            batch.set(BlobOp::Commit { hash: hash.clone() }, now().serialize());
             self.write_batch(batch).await?;
         }
 
@@ -309,4 +287,49 @@ impl BlobUpload for Server {
             section: None,
         })
     }
+
+    async fn put_blob_checked(
+        &self,
+        account_id: u32,
+        data: &[u8],
+        access_token: &AccessToken,
+    ) -> trc::Result<BlobId> {
+        // Reject temporary blobs while the server is in read-only mode
+        if self.is_read_only() {
+            return Err(trc::JmapEvent::AccountReadOnly
+                .into_err()
+                .details("Server is in read-only mode"));
+        }
+
+        // Enforce quota
+        let used = self
+            .core
+            .storage
+            .data
+            .blob_quota(account_id)
+            .await
+            .caused_by(trc::location!())?;
+
+        if ((self.core.jmap.upload_tmp_quota_size > 0
+            && used.bytes + data.len() > self.core.jmap.upload_tmp_quota_size)
+            || (self.core.jmap.upload_tmp_quota_amount > 0
+                && used.count + 1 > self.core.jmap.upload_tmp_quota_amount))
+            && !access_token.has_permission(Permission::UnlimitedUploads)
+        {
+            let err = Err(trc::LimitEvent::BlobQuota
+                .into_err()
+                .ctx(trc::Key::Size, self.core.jmap.upload_tmp_quota_size)
+                .ctx(trc::Key::Total, self.core.jmap.upload_tmp_quota_amount));
+
+            #[cfg(feature = "test_mode")]
+            if !DISABLE_UPLOAD_QUOTA.load(std::sync::atomic::Ordering::Relaxed) {
+                return err;
+            }
+
+            #[cfg(not(feature = "test_mode"))]
+            return err;
+        }
+
+        self.put_blob(account_id, data, true).await
+    }
 }
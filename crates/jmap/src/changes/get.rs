@@ -13,6 +13,8 @@ use std::future::Future;
 use store::query::log::{Change, Changes, Query};
 use trc::AddContext;
 
+use crate::quota::changes::QuotaChanges;
+
 pub trait ChangesLookup: Sync + Send {
     fn changes(
         &self,
@@ -63,7 +65,7 @@ impl ChangesLookup for Server {
             RequestArguments::Quota => {
                 access_token.assert_is_member(request.account_id)?;
 
-                return Err(trc::JmapEvent::CannotCalculateChanges.into_err());
+                return self.quota_changes(request, access_token).await;
             }
         };
 
@@ -86,6 +88,26 @@ impl ChangesLookup for Server {
         };
         let account_id = request.account_id.document_id();
 
+        // States older than the oldest retained change cannot be resolved after compaction
+        let since_change_id = match &request.since_state {
+            State::Initial => None,
+            State::Exact(change_id) => Some(*change_id),
+            State::Intermediate(intermediate_state) => Some(intermediate_state.from_id),
+        };
+        if let Some(since_change_id) = since_change_id {
+            if self
+                .core
+                .storage
+                .data
+                .get_first_change_id(account_id, collection)
+                .await
+                .caused_by(trc::location!())?
+                .is_some_and(|first_change_id| since_change_id < first_change_id)
+            {
+                return Err(trc::JmapEvent::CannotCalculateChanges.into_err());
+            }
+        }
+
         let (items_sent, mut changelog) = match &request.since_state {
             State::Initial => {
                 let changelog = self.changes_(account_id, collection, Query::All).await?;
@@ -27,9 +27,10 @@ pub trait ChangeLog: Sync + Send {
         account_id: u32,
         changes: ChangeLogBuilder,
     ) -> impl Future<Output = trc::Result<u64>> + Send;
-    fn delete_changes(
+    fn compact_changes(
         &self,
         account_id: u32,
+        collection: Collection,
         before: Duration,
     ) -> impl Future<Output = trc::Result<()>> + Send;
 }
@@ -75,20 +76,29 @@ impl ChangeLog for Server {
             .map(|_| state)
     }
 
-    async fn delete_changes(&self, account_id: u32, before: Duration) -> trc::Result<()> {
+    async fn compact_changes(
+        &self,
+        account_id: u32,
+        collection: Collection,
+        before: Duration,
+    ) -> trc::Result<()> {
         let reference_cid = self.inner.data.jmap_id_gen.past_id(before).ok_or_else(|| {
             trc::StoreEvent::UnexpectedError
                 .caused_by(trc::location!())
                 .ctx(trc::Key::Reason, "Failed to generate reference change id.")
         })?;
 
-        for collection in [
-            Collection::Email,
-            Collection::Mailbox,
-            Collection::Thread,
-            Collection::Identity,
-            Collection::EmailSubmission,
-        ] {
+        // The last change logged before the reference change id is kept, clients
+        // holding that state can still be sent the changes that followed it while
+        // older states are rejected with cannotCalculateChanges.
+        if let Some(change_id) = self
+            .core
+            .storage
+            .data
+            .get_last_change_id_before(account_id, collection, reference_cid)
+            .await
+            .caused_by(trc::location!())?
+        {
             self.core
                 .storage
                 .data
@@ -101,10 +111,11 @@ impl ChangeLog for Server {
                     LogKey {
                         account_id,
                         collection: collection.into(),
-                        change_id: reference_cid,
+                        change_id,
                     },
                 )
-                .await?;
+                .await
+                .caused_by(trc::location!())?;
         }
 
         Ok(())
@@ -0,0 +1,104 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use jmap_proto::types::{collection::Collection, property::Property};
+use store::{
+    ahash::AHashSet,
+    write::{now, Bincode},
+};
+use trc::AddContext;
+use utils::BlobHash;
+
+use crate::JmapMethods;
+
+use super::metadata::MessageMetadata;
+
+/// Space reclaimed by recompressing the messages of an account. Blobs shared
+/// by several accounts are attributed to the first account processed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveReport {
+    pub account_id: u32,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub messages: u64,
+    pub blobs: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+pub trait EmailArchive: Sync + Send {
+    fn archive_account_messages(
+        &self,
+        account_id: u32,
+        older_than: u64,
+        seen: &mut AHashSet<BlobHash>,
+    ) -> impl Future<Output = trc::Result<ArchiveReport>> + Send;
+}
+
+impl EmailArchive for Server {
+    async fn archive_account_messages(
+        &self,
+        account_id: u32,
+        older_than: u64,
+        seen: &mut AHashSet<BlobHash>,
+    ) -> trc::Result<ArchiveReport> {
+        let threshold = now().saturating_sub(older_than);
+        let mut report = ArchiveReport {
+            account_id,
+            ..Default::default()
+        };
+
+        for document_id in self
+            .get_document_ids(account_id, Collection::Email)
+            .await?
+            .unwrap_or_default()
+        {
+            let metadata = match self
+                .get_property::<Bincode<MessageMetadata>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::BodyStructure,
+                )
+                .await?
+            {
+                Some(metadata) if metadata.inner.received_at <= threshold => metadata.inner,
+                _ => continue,
+            };
+            report.messages += 1;
+
+            if !seen.insert(metadata.blob_hash.clone()) {
+                continue;
+            }
+
+            if let Some((before, after)) = self
+                .core
+                .storage
+                .blob
+                .archive_blob(metadata.blob_hash.as_slice())
+                .await
+                .caused_by(trc::location!())?
+            {
+                report.blobs += 1;
+                report.bytes_before += before as u64;
+                report.bytes_after += after as u64;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+impl ArchiveReport {
+    pub fn reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
@@ -56,9 +56,13 @@ use crate::{
 use std::future::Future;
 
 use super::{
-    index::{EmailIndexBuilder, TrimTextValue, VisitValues, MAX_ID_LENGTH, MAX_SORT_FIELD_LENGTH},
+    index::{
+        EmailIndexBuilder, SaveDate, TrimTextValue, VisitValues, MAX_ID_LENGTH,
+        MAX_SORT_FIELD_LENGTH,
+    },
     ingest::{EmailIngest, IngestedEmail, LogEmailInsert},
     metadata::MessageMetadata,
+    smime::SmimeVerification,
 };
 
 pub trait EmailCopy: Sync + Send {
@@ -351,6 +355,16 @@ impl EmailCopy for Server {
             }
         }
 
+        // Obtain S/MIME verification results
+        let smime = self
+            .get_property::<Bincode<SmimeVerification>>(
+                from_account_id,
+                Collection::Email,
+                from_message_id,
+                Property::SmimeStatus,
+            )
+            .await?;
+
         // Set receivedAt
         if let Some(received_at) = received_at {
             metadata.received_at = received_at.timestamp() as u64;
@@ -439,6 +453,7 @@ impl EmailCopy for Server {
             .tag(Property::ThreadId, TagValue::Id(maybe_thread_id), 0)
             .value(Property::MailboxIds, mailbox_ids, F_VALUE | F_BITMAP)
             .value(Property::Keywords, keywords, F_VALUE | F_BITMAP)
+            .save_date(None)
             .value(Property::Cid, change_id, F_VALUE)
             .set(
                 ValueClass::FtsQueue(FtsQueueClass {
@@ -447,6 +462,9 @@ impl EmailCopy for Server {
                 }),
                 0u64.serialize(),
             );
+        if let Some(smime) = smime {
+            batch.value(Property::SmimeStatus, smime, F_VALUE);
+        }
         EmailIndexBuilder::set(metadata).build(
             &mut batch,
             account_id,
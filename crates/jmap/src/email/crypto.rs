@@ -10,6 +10,9 @@ use std::{
 
 use crate::{
     api::{http::ToHttpResponse, HttpResponse, JsonResponse},
+    blob::{download::BlobDownload, upload::BlobUpload},
+    changes::write::ChangeLog,
+    services::index::Indexer,
     JmapMethods,
 };
 use aes::cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyIvInit};
@@ -17,7 +20,7 @@ use common::{auth::AccessToken, Server};
 use directory::backend::internal::manage;
 use jmap_proto::types::{collection::Collection, property::Property};
 use mail_builder::{encoders::base64::base64_encode_mime, mime::make_boundary};
-use mail_parser::{decoders::base64::base64_decode, Message, MessageParser, MimeHeaders};
+use mail_parser::{decoders::base64::base64_decode, Message, MessageParser, MimeHeaders, PartType};
 use openpgp::{
     parse::Parse,
     serialize::stream,
@@ -36,11 +39,22 @@ use rsa::{pkcs1::DecodeRsaPublicKey, Pkcs1v15Encrypt, RsaPublicKey};
 use sequoia_openpgp as openpgp;
 use serde_json::json;
 use store::{
-    write::{BatchBuilder, Bincode, ToBitmaps, F_CLEAR, F_VALUE},
-    Deserialize, Serialize,
+    write::{
+        assert::{AssertValue, HashedValue},
+        BatchBuilder, Bincode, FtsQueueClass, ToBitmaps, ValueClass, F_CLEAR, F_VALUE,
+    },
+    Deserialize, Serialize, ValueKey,
+};
+use trc::AddContext;
+
+use super::{
+    index::EmailIndexBuilder,
+    metadata::MessageMetadata,
+    pgp::{decrypt_message, pgp_recipients, PgpEscrowHandler},
 };
 
-const P: openpgp::policy::StandardPolicy<'static> = openpgp::policy::StandardPolicy::new();
+pub(crate) const P: openpgp::policy::StandardPolicy<'static> =
+    openpgp::policy::StandardPolicy::new();
 
 #[derive(Debug)]
 pub enum EncryptMessageError {
@@ -73,10 +87,14 @@ pub enum EncryptionType {
     PGP {
         algo: Algorithm,
         certs: String,
+        #[serde(default, skip_serializing)]
+        reencrypt: bool,
     },
     SMIME {
         algo: Algorithm,
         certs: String,
+        #[serde(default, skip_serializing)]
+        reencrypt: bool,
     },
     #[default]
     Disabled,
@@ -394,6 +412,30 @@ impl EncryptMessage for Message<'_> {
     }
 }
 
+impl EncryptionParams {
+    /// Returns the IDs of the OpenPGP keys that messages are encrypted to.
+    pub fn pgp_recipients(&self) -> BTreeSet<Vec<u8>> {
+        if self.method != EncryptionMethod::PGP {
+            return BTreeSet::new();
+        }
+
+        self.certs
+            .iter()
+            .filter_map(|cert| openpgp::Cert::from_bytes(cert).ok())
+            .flat_map(|cert| {
+                cert.keys()
+                    .with_policy(&P, None)
+                    .supported()
+                    .alive()
+                    .revoked(false)
+                    .key_flags(KeyFlags::empty().set_transport_encryption())
+                    .map(|key| key.keyid().as_bytes().to_vec())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
 impl Algorithm {
     fn key_size(&self) -> usize {
         match self {
@@ -641,6 +683,12 @@ pub trait CryptoHandler: Sync + Send {
         access_token: Arc<AccessToken>,
         body: Option<Vec<u8>>,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn encrypt_account_messages(
+        &self,
+        account_id: u32,
+        tenant_id: Option<u32>,
+    ) -> impl Future<Output = trc::Result<usize>> + Send;
 }
 
 impl CryptoHandler for Server {
@@ -664,8 +712,16 @@ impl CryptoHandler for Server {
                 let certs = String::from_utf8(certs).unwrap_or_default();
 
                 match method {
-                    EncryptionMethod::PGP => EncryptionType::PGP { algo, certs },
-                    EncryptionMethod::SMIME => EncryptionType::SMIME { algo, certs },
+                    EncryptionMethod::PGP => EncryptionType::PGP {
+                        algo,
+                        certs,
+                        reencrypt: false,
+                    },
+                    EncryptionMethod::SMIME => EncryptionType::SMIME {
+                        algo,
+                        certs,
+                        reencrypt: false,
+                    },
                 }
             })
             .unwrap_or(EncryptionType::Disabled);
@@ -684,9 +740,17 @@ impl CryptoHandler for Server {
         let request = serde_json::from_slice::<EncryptionType>(body.as_deref().unwrap_or_default())
             .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
 
-        let (method, algo, certs) = match request {
-            EncryptionType::PGP { algo, certs } => (EncryptionMethod::PGP, algo, certs),
-            EncryptionType::SMIME { algo, certs } => (EncryptionMethod::SMIME, algo, certs),
+        let (method, algo, certs, reencrypt) = match request {
+            EncryptionType::PGP {
+                algo,
+                certs,
+                reencrypt,
+            } => (EncryptionMethod::PGP, algo, certs, reencrypt),
+            EncryptionType::SMIME {
+                algo,
+                certs,
+                reencrypt,
+            } => (EncryptionMethod::SMIME, algo, certs, reencrypt),
             EncryptionType::Disabled => {
                 // Disable encryption at rest
                 let mut batch = BatchBuilder::new();
@@ -711,13 +775,44 @@ impl CryptoHandler for Server {
         }
 
         // Parse certificates
-        let params = EncryptionParams {
+        let mut params = EncryptionParams {
             method,
             algo,
             certs: try_parse_certs(method, certs.into_bytes())
                 .map_err(|err| manage::error(err, None::<u32>))?,
         };
 
+        // SPDX-SnippetBegin
+        // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+        // SPDX-License-Identifier: LicenseRef-SEL
+
+        // Add the recovery certificates held in escrow
+        #[cfg(feature = "enterprise")]
+        let num_escrow = if let Some(escrow) = self
+            .core
+            .enterprise
+            .as_ref()
+            .filter(|_| self.core.is_enterprise_edition())
+            .and_then(|enterprise| match method {
+                EncryptionMethod::PGP => enterprise.encryption_escrow.pgp.as_ref(),
+                EncryptionMethod::SMIME => enterprise.encryption_escrow.smime.as_ref(),
+            }) {
+            let mut num_escrow = 0;
+            for cert in try_parse_certs(method, escrow.as_bytes().to_vec())
+                .map_err(|err| manage::error(err, None::<u32>))?
+            {
+                if !params.certs.contains(&cert) {
+                    params.certs.push(cert);
+                    num_escrow += 1;
+                }
+            }
+            num_escrow
+        } else {
+            0
+        };
+
+        // SPDX-SnippetEnd
+
         // Try a test encryption
         if let Err(EncryptMessageError::Error(message)) = MessageParser::new()
             .parse("Subject: test\r\ntest\r\n".as_bytes())
@@ -738,11 +833,277 @@ impl CryptoHandler for Server {
             .value(Property::Parameters, &params, F_VALUE);
         self.core.storage.data.write(batch.build()).await?;
 
+        // SPDX-SnippetBegin
+        // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+        // SPDX-License-Identifier: LicenseRef-SEL
+
+        // Let the user know that their messages can be recovered with the escrowed certificates
+        #[cfg(feature = "enterprise")]
+        if num_escrow > 0 {
+            notify_escrow(self, &access_token, num_escrow).await;
+        }
+
+        // SPDX-SnippetEnd
+
+        // Encrypt existing messages in the background
+        if reencrypt {
+            let server = self.clone();
+            let account_id = access_token.primary_id();
+            let tenant_id = access_token.tenant.map(|t| t.id);
+            tokio::spawn(async move {
+                if let Err(err) = server.encrypt_account_messages(account_id, tenant_id).await {
+                    trc::error!(err
+                        .account_id(account_id)
+                        .details("Failed to encrypt existing messages."));
+                }
+            });
+        }
+
         Ok(JsonResponse::new(json!({
             "data": num_certs,
         }))
         .into_http_response())
     }
+
+    async fn encrypt_account_messages(
+        &self,
+        account_id: u32,
+        tenant_id: Option<u32>,
+    ) -> trc::Result<usize> {
+        let params = if let Some(params) = self
+            .get_property::<EncryptionParams>(
+                account_id,
+                Collection::Principal,
+                0,
+                Property::Parameters,
+            )
+            .await?
+        {
+            params
+        } else {
+            return Ok(0);
+        };
+
+        // Messages encrypted to other certificates are re-encrypted when they
+        // can be decrypted with the OpenPGP key escrowed by the account
+        let escrow = self.get_pgp_escrow(account_id).await?;
+        let recipients = params.pgp_recipients();
+        let mut num_encrypted = 0;
+        for document_id in self
+            .get_document_ids(account_id, Collection::Email)
+            .await?
+            .unwrap_or_default()
+        {
+            // Metadata might be shared with other recipients, assert the stored value
+            let stored_hash = if let Some(stored) = self
+                .core
+                .storage
+                .data
+                .get_value::<HashedValue<()>>(ValueKey {
+                    account_id,
+                    collection: Collection::Email.into(),
+                    document_id,
+                    class: ValueClass::Property(Property::BodyStructure.into()),
+                })
+                .await?
+            {
+                stored.hash
+            } else {
+                continue;
+            };
+            let metadata = if let Some(metadata) = self
+                .get_property::<Bincode<MessageMetadata>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::BodyStructure,
+                )
+                .await?
+            {
+                metadata
+            } else {
+                continue;
+            };
+            let raw_message = if let Some(raw_message) = self
+                .get_blob(&metadata.inner.blob_hash, 0..usize::MAX)
+                .await?
+            {
+                raw_message
+            } else {
+                continue;
+            };
+            let decrypted;
+            let mut message = if let Some(message) = MessageParser::new().parse(&raw_message) {
+                message
+            } else {
+                continue;
+            };
+            if message.is_encrypted() {
+                let secret = match &escrow {
+                    Some(secret)
+                        if pgp_recipients(&message)
+                            .map_or(false, |stored| stored != recipients) =>
+                    {
+                        secret
+                    }
+                    _ => continue,
+                };
+                decrypted = match decrypt_message(&message, &[], &[], secret) {
+                    Some(Ok((decrypted, _))) => decrypted,
+                    _ => continue,
+                };
+                message = if let Some(message) = MessageParser::new().parse(&decrypted) {
+                    message
+                } else {
+                    continue;
+                };
+            }
+
+            // Encrypt message
+            let raw_message = match message.encrypt(&params).await {
+                Ok(raw_message) => raw_message,
+                Err(EncryptMessageError::AlreadyEncrypted) => continue,
+                Err(EncryptMessageError::Error(err)) => {
+                    trc::bail!(trc::StoreEvent::CryptoError
+                        .into_err()
+                        .caused_by(trc::location!())
+                        .reason(err));
+                }
+            };
+            let mut message = if let Some(message) = MessageParser::new().parse(&raw_message) {
+                message
+            } else {
+                continue;
+            };
+            remove_contents(&mut message);
+
+            // Store blob
+            let blob_id = self
+                .put_blob(account_id, &raw_message, false)
+                .await
+                .caused_by(trc::location!())?;
+
+            // Replace message metadata
+            let root_part = message.root_part();
+            let new_metadata = MessageMetadata {
+                preview: String::new(),
+                size: raw_message.len(),
+                raw_headers: raw_message
+                    .get(root_part.offset_header..root_part.offset_body)
+                    .unwrap_or_default()
+                    .to_vec(),
+                received_at: metadata.inner.received_at,
+                has_attachments: !message.attachments.is_empty(),
+                blob_hash: blob_id.hash.clone(),
+                contents: message.into(),
+            };
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Email)
+                .update_document(document_id)
+                .assert_value(Property::BodyStructure, AssertValue::Hash(stored_hash));
+            EmailIndexBuilder::clear(metadata.inner).build(&mut batch, account_id, tenant_id);
+            EmailIndexBuilder::set(new_metadata).build(&mut batch, account_id, tenant_id);
+            batch.set(
+                ValueClass::FtsQueue(FtsQueueClass {
+                    seq: self.generate_snowflake_id().caused_by(trc::location!())?,
+                    hash: blob_id.hash,
+                }),
+                0u64.serialize(),
+            );
+            match self.core.storage.data.write(batch.build()).await {
+                Ok(_) => {
+                    num_encrypted += 1;
+                }
+                Err(err) if err.is_assertion_failure() => {
+                    // The message was modified or deleted in the meantime
+                }
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+        }
+
+        if num_encrypted > 0 {
+            self.request_fts_index();
+        }
+
+        Ok(num_encrypted)
+    }
+}
+
+// SPDX-SnippetBegin
+// SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+// SPDX-License-Identifier: LicenseRef-SEL
+
+#[cfg(feature = "enterprise")]
+async fn notify_escrow(server: &Server, access_token: &AccessToken, num_certs: usize) {
+    use crate::services::ingest::MailDelivery;
+    use common::ipc::IngestMessage;
+    use mail_builder::{headers::HeaderType, MessageBuilder};
+
+    let (enterprise, rcpt) = match (server.core.enterprise.as_ref(), access_token.emails.first()) {
+        (Some(enterprise), Some(rcpt)) => (enterprise, rcpt),
+        _ => return,
+    };
+    let from = enterprise.encryption_escrow.notify_from.as_str();
+    let message = MessageBuilder::new()
+        .from(("Account Security", from))
+        .to(rcpt.as_str())
+        .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+        .message_id(format!("<{}@escrow>", make_boundary(".")))
+        .subject("Recovery certificates were added to your encryption settings")
+        .text_body(format!(
+            concat!(
+                "{} recovery certificate(s) held by your organization were added to the ",
+                "encryption-at-rest settings of your account {}.\r\n\r\n",
+                "Messages stored in your account can be decrypted with these certificates ",
+                "as well as with your own keys.\r\n"
+            ),
+            num_certs, rcpt
+        ))
+        .write_to_vec()
+        .unwrap_or_default();
+
+    match server
+        .put_blob(access_token.primary_id(), &message, false)
+        .await
+    {
+        Ok(blob_id) => {
+            server
+                .deliver_message(IngestMessage {
+                    sender_address: from.to_string(),
+                    recipients: vec![rcpt.clone()],
+                    message_blob: blob_id.hash,
+                    message_size: message.len(),
+                    session_id: 0,
+                })
+                .await;
+        }
+        Err(err) => {
+            trc::error!(err
+                .account_id(access_token.primary_id())
+                .details("Failed to deliver escrow notification."));
+        }
+    }
+}
+
+// SPDX-SnippetEnd
+
+pub(crate) fn remove_contents(message: &mut Message<'_>) {
+    for part in &mut message.parts {
+        match &mut part.body {
+            PartType::Text(txt) | PartType::Html(txt) => {
+                *txt = Cow::from("");
+            }
+            PartType::Binary(bin) | PartType::InlineBinary(bin) => {
+                *bin = Cow::from(&[][..]);
+            }
+            PartType::Message(_) => {
+                part.body = PartType::Binary(Cow::from(&[][..]));
+            }
+            PartType::Multipart(_) => (),
+        }
+    }
 }
 
 impl Display for EncryptionMethod {
@@ -16,21 +16,27 @@ use store::{
     roaring::RoaringBitmap,
     write::{
         log::ChangeLogBuilder, BatchBuilder, Bincode, BitmapClass, MaybeDynamicId, TagValue,
-        ValueClass, F_BITMAP, F_CLEAR, F_VALUE,
+        ValueClass, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE,
     },
     BitmapKey, IterateParams, ValueKey, U32_LEN,
 };
 use trc::{AddContext, StoreEvent};
-use utils::codec::leb128::Leb128Reader;
+use utils::{codec::leb128::Leb128Reader, BlobHash};
 
 use crate::{
     changes::write::ChangeLog,
     mailbox::{UidMailbox, JUNK_ID, TOMBSTONE_ID, TRASH_ID},
-    services::state::StateManager,
+    services::{snapshot::AccountSnapshot, state::StateManager},
     JmapMethods,
 };
 
-use super::{index::EmailIndexBuilder, metadata::MessageMetadata};
+use super::{
+    index::EmailIndexBuilder,
+    metadata::MessageMetadata,
+    seen::PrivateSeenBatch,
+    share::EmailShare,
+    trash::{TrashBatch, TrashedEmail},
+};
 use rand::prelude::SliceRandom;
 use std::future::Future;
 
@@ -82,7 +88,7 @@ impl EmailDeletion for Server {
                 document_id,
                 DeleteProperties {
                     mailboxes,
-                    thread_id: None,
+                    ..Default::default()
                 },
             );
         }
@@ -102,6 +108,40 @@ impl EmailDeletion for Server {
                 .thread_id = Some(thread_id);
         }
 
+        // Fetch the metadata of the messages to hold in the trash
+        if self.core.jmap.trash_retention.is_some() {
+            let mut keywords = self
+                .get_properties::<Vec<Keyword>, _, _>(
+                    account_id,
+                    Collection::Email,
+                    &document_ids,
+                    Property::Keywords,
+                )
+                .await?
+                .into_iter()
+                .collect::<AHashMap<_, _>>();
+            for (document_id, metadata) in self
+                .get_properties::<Bincode<MessageMetadata>, _, _>(
+                    account_id,
+                    Collection::Email,
+                    &document_ids,
+                    Property::BodyStructure,
+                )
+                .await?
+            {
+                delete_properties
+                    .entry(document_id)
+                    .or_insert_with(DeleteProperties::default)
+                    .trash = Some((
+                    metadata.inner.blob_hash.clone(),
+                    TrashedEmail::new(
+                        &metadata.inner,
+                        keywords.remove(&document_id).unwrap_or_default(),
+                    ),
+                ));
+            }
+        }
+
         // Obtain all threadIds
         self.core
             .storage
@@ -158,6 +198,18 @@ impl EmailDeletion for Server {
                     changes.log_child_update(Collection::Mailbox, mailbox_id.mailbox_id);
                 }
 
+                // Hold message in the trash
+                if let (Some(retention), Some((blob_hash, mut email))) =
+                    (self.core.jmap.trash_retention, delete_properties.trash)
+                {
+                    email.mailbox_ids = delete_properties
+                        .mailboxes
+                        .iter()
+                        .map(|mailbox_id| mailbox_id.mailbox_id)
+                        .collect();
+                    batch.hold_in_trash(blob_hash, email, retention);
+                }
+
                 batch.value(
                     Property::MailboxIds,
                     delete_properties.mailboxes,
@@ -244,6 +296,7 @@ impl EmailDeletion for Server {
             account_ids.shuffle(&mut rand::thread_rng());
 
             for account_id in account_ids {
+                self.yield_to_interactive().await;
                 self.purge_account(account_id).await;
             }
         }
@@ -280,6 +333,13 @@ impl EmailDeletion for Server {
             }
         }
 
+        // Take a scheduled snapshot before any messages are purged
+        if let Err(err) = self.snapshot_scheduled(account_id).await {
+            trc::error!(err
+                .details("Failed to take account snapshot.")
+                .account_id(account_id));
+        }
+
         // Auto-expunge deleted and junk messages
         if let Some(period) = self.core.jmap.mail_autoexpunge_after {
             if let Err(err) = self.emails_auto_expunge(account_id, period).await {
@@ -296,12 +356,16 @@ impl EmailDeletion for Server {
                 .account_id(account_id));
         }
 
-        // Purge changelogs
-        if let Some(history) = self.core.jmap.changes_max_history {
-            if let Err(err) = self.delete_changes(account_id, history).await {
+        // Compact changelogs
+        for (collection, history) in &self.core.jmap.changes_max_history {
+            if let Err(err) = self
+                .compact_changes(account_id, *collection, *history)
+                .await
+            {
                 trc::error!(err
-                    .details("Failed to purge changes.")
-                    .account_id(account_id));
+                    .details("Failed to compact changes.")
+                    .account_id(account_id)
+                    .collection(*collection));
             }
         }
 
@@ -442,12 +506,62 @@ impl EmailDeletion for Server {
                 .with_collection(Collection::Email)
                 .delete_document(document_id)
                 .clear(Property::Cid)
+                .clear(Property::SmimeStatus)
                 .tag(
                     Property::MailboxIds,
                     TagValue::Id(MaybeDynamicId::Static(TOMBSTONE_ID)),
                     F_CLEAR,
                 );
 
+            // Remove save date
+            if let Some(saved_at) = self
+                .core
+                .storage
+                .data
+                .get_value::<u64>(ValueKey {
+                    account_id,
+                    collection: Collection::Email.into(),
+                    document_id,
+                    class: ValueClass::Property(Property::SavedAt.into()),
+                })
+                .await?
+            {
+                batch.value(Property::SavedAt, saved_at, F_VALUE | F_INDEX | F_CLEAR);
+            }
+
+            // Remove the \Seen state of other users
+            if let Some(seen_by) = self
+                .core
+                .storage
+                .data
+                .get_value::<Bincode<Vec<u32>>>(ValueKey {
+                    account_id,
+                    collection: Collection::Email.into(),
+                    document_id,
+                    class: ValueClass::Property(Property::SeenBy.into()),
+                })
+                .await?
+            {
+                batch.clear_private_seen(seen_by.inner);
+            }
+
+            // Delete public share links
+            if let Some(links) = self
+                .core
+                .storage
+                .data
+                .get_value::<Bincode<Vec<String>>>(ValueKey {
+                    account_id,
+                    collection: Collection::Email.into(),
+                    document_id,
+                    class: ValueClass::Property(Property::ShareLinks.into()),
+                })
+                .await?
+            {
+                self.share_links_delete(&links.inner).await?;
+                batch.clear(Property::ShareLinks);
+            }
+
             // Remove keywords
             if let Some(keywords) = self
                 .core
@@ -474,15 +588,12 @@ impl EmailDeletion for Server {
 
             // Remove message metadata
             if let Some(metadata) = self
-                .core
-                .storage
-                .data
-                .get_value::<Bincode<MessageMetadata>>(ValueKey {
+                .get_property::<Bincode<MessageMetadata>>(
                     account_id,
-                    collection: Collection::Email.into(),
+                    Collection::Email,
                     document_id,
-                    class: ValueClass::Property(Property::BodyStructure.into()),
-                })
+                    Property::BodyStructure,
+                )
                 .await?
             {
                 // SPDX-SnippetBegin
@@ -524,4 +635,5 @@ impl EmailDeletion for Server {
 struct DeleteProperties {
     mailboxes: Vec<UidMailbox>,
     thread_id: Option<u32>,
+    trash: Option<(BlobHash, TrashedEmail)>,
 }
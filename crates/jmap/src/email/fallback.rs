@@ -0,0 +1,209 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fmt::Display, future::Future, sync::atomic::Ordering};
+
+use common::Server;
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_parser::{Addr, Address, HeaderName, HeaderValue};
+use store::{
+    fts::{Field, FtsFilter},
+    roaring::RoaringBitmap,
+    write::Bincode,
+};
+use trc::{AddContext, FtsIndexEvent};
+
+use crate::JmapMethods;
+
+use super::metadata::MessageMetadata;
+
+/// Documents matched by a full-text query, along with whether the results
+/// were obtained from message metadata because the backend was unavailable.
+#[derive(Debug, Default)]
+pub struct FtsQueryResult {
+    pub documents: RoaringBitmap,
+    pub degraded: bool,
+}
+
+pub trait EmailFtsFallback: Sync + Send {
+    fn fts_fallback_filter<T: Into<u8> + Display + Clone + std::fmt::Debug + Sync + Send>(
+        &self,
+        account_id: u32,
+        filters: &[FtsFilter<T>],
+    ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
+
+    fn set_fts_degraded(&self, degraded: bool);
+
+    fn is_fts_degraded(&self) -> bool;
+}
+
+impl EmailFtsFallback for Server {
+    async fn fts_fallback_filter<T: Into<u8> + Display + Clone + std::fmt::Debug + Sync + Send>(
+        &self,
+        account_id: u32,
+        filters: &[FtsFilter<T>],
+    ) -> trc::Result<RoaringBitmap> {
+        let mut results = RoaringBitmap::new();
+
+        for document_id in self
+            .get_document_ids(account_id, Collection::Email)
+            .await?
+            .unwrap_or_default()
+        {
+            if let Some(metadata) = self
+                .get_property::<Bincode<MessageMetadata>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::BodyStructure,
+                )
+                .await
+                .caused_by(trc::location!())?
+            {
+                if metadata.inner.matches(filters) {
+                    results.insert(document_id);
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn set_fts_degraded(&self, degraded: bool) {
+        if self
+            .inner
+            .data
+            .fts_degraded
+            .swap(degraded, Ordering::Relaxed)
+            != degraded
+        {
+            if degraded {
+                trc::event!(FtsIndex(FtsIndexEvent::Degraded));
+            } else {
+                trc::event!(FtsIndex(FtsIndexEvent::Recovered));
+            }
+        }
+    }
+
+    fn is_fts_degraded(&self) -> bool {
+        self.inner.data.fts_degraded.load(Ordering::Relaxed)
+    }
+}
+
+impl MessageMetadata<'_> {
+    // Evaluates a full-text filter using only the headers and preview stored
+    // in the message metadata, attachments are never matched.
+    fn matches<T: Into<u8> + Display + Clone + std::fmt::Debug>(
+        &self,
+        filters: &[FtsFilter<T>],
+    ) -> bool {
+        let root = FtsFilter::And;
+        let mut op = &root;
+        let mut result = true;
+        let mut stack = Vec::new();
+
+        for filter in filters {
+            let matched = match filter {
+                FtsFilter::Exact { field, text, .. } => self.matches_text(field, text, true),
+                FtsFilter::Contains { field, text, .. } => self.matches_text(field, text, false),
+                FtsFilter::Keyword { field, text } => self.matches_keyword(field, text),
+                FtsFilter::And | FtsFilter::Or | FtsFilter::Not => {
+                    stack.push((op, result));
+                    op = filter;
+                    result = !matches!(filter, FtsFilter::Or);
+                    continue;
+                }
+                FtsFilter::End => {
+                    if let Some((prev_op, prev_result)) = stack.pop() {
+                        let matched = result;
+                        op = prev_op;
+                        result = prev_result;
+                        matched
+                    } else {
+                        break;
+                    }
+                }
+            };
+
+            result = match op {
+                FtsFilter::Or => result || matched,
+                FtsFilter::Not => result && !matched,
+                _ => result && matched,
+            };
+        }
+
+        result
+    }
+
+    fn matches_text<T: Into<u8> + Display + Clone + std::fmt::Debug>(
+        &self,
+        field: &Field<T>,
+        text: &str,
+        is_exact: bool,
+    ) -> bool {
+        let text = text.to_lowercase();
+        let matches = |value: &str| {
+            let value = value.to_lowercase();
+            if is_exact {
+                value.contains(&text)
+            } else {
+                text.split_whitespace().all(|word| value.contains(word))
+            }
+        };
+
+        match field {
+            Field::Header(name) => {
+                let name: u8 = name.clone().into();
+                self.contents
+                    .root_part()
+                    .headers
+                    .iter()
+                    .filter(|header| header.name.id() == name)
+                    .any(|header| any_value(&header.value, &matches))
+            }
+            Field::Body => matches(&self.preview),
+            Field::Attachment | Field::Keyword => false,
+        }
+    }
+
+    fn matches_keyword<T: Into<u8> + Display + Clone + std::fmt::Debug>(
+        &self,
+        field: &Field<T>,
+        text: &str,
+    ) -> bool {
+        let headers = &self.contents.root_part().headers;
+        match field {
+            Field::Keyword => headers.iter().any(|header| {
+                !matches!(header.name, HeaderName::Other(_))
+                    && header.name.as_str().eq_ignore_ascii_case(text)
+            }),
+            Field::Header(name) => {
+                let name: u8 = name.clone().into();
+                headers
+                    .iter()
+                    .filter(|header| header.name.id() == name)
+                    .any(|header| any_value(&header.value, |value| value == text))
+            }
+            Field::Body | Field::Attachment => false,
+        }
+    }
+}
+
+fn any_value(value: &HeaderValue<'_>, matches: impl Fn(&str) -> bool) -> bool {
+    let matches_addr = |addr: &Addr<'_>| {
+        addr.name.as_deref().is_some_and(&matches) || addr.address.as_deref().is_some_and(&matches)
+    };
+
+    match value {
+        HeaderValue::Text(text) => matches(text),
+        HeaderValue::TextList(texts) => texts.iter().any(|text| matches(text)),
+        HeaderValue::Address(Address::List(addr_list)) => addr_list.iter().any(matches_addr),
+        HeaderValue::Address(Address::Group(groups)) => groups.iter().any(|group| {
+            group.name.as_deref().is_some_and(&matches) || group.addresses.iter().any(matches_addr)
+        }),
+        _ => false,
+    }
+}
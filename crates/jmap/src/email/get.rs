@@ -34,6 +34,8 @@ use super::{
     cache::ThreadCache,
     headers::IntoForm,
     metadata::{MessageMetadata, MetadataPartType},
+    seen::{replace_seen, PrivateSeen},
+    smime::SmimeVerification,
 };
 
 pub trait EmailGet: Sync + Send {
@@ -123,6 +125,18 @@ impl EmailGet for Server {
             not_found: vec![],
         };
 
+        // Obtain the messages seen by this user if the \Seen state is private
+        let private_seen_ids = if properties.contains(&Property::Keywords)
+            && self.has_private_seen(account_id, access_token.primary_id())
+        {
+            Some(
+                self.private_seen_ids(account_id, access_token.primary_id())
+                    .await?,
+            )
+        } else {
+            None
+        };
+
         // Check if we need to fetch the raw headers or body
         let mut needs_body = false;
         for property in &properties {
@@ -195,6 +209,7 @@ impl EmailGet for Server {
 
             // Prepare response
             let mut email = Object::with_capacity(properties.len());
+            let mut smime = None;
             for property in &properties {
                 match property {
                     Property::Id => {
@@ -252,6 +267,12 @@ impl EmailGet for Server {
                             )
                             .await?
                             .map(|keywords| {
+                                let keywords = match &private_seen_ids {
+                                    Some(seen_ids) => {
+                                        replace_seen(&keywords, seen_ids.contains(id.document_id()))
+                                    }
+                                    None => keywords,
+                                };
                                 let mut obj = Object::with_capacity(keywords.len());
                                 for keyword in keywords {
                                     obj.append(Property::_T(keyword.to_string()), true);
@@ -423,6 +444,35 @@ impl EmailGet for Server {
                         }
                         email.append(Property::BodyValues, body_values);
                     }
+                    Property::SmimeStatus | Property::SmimeErrors | Property::SmimeVerifiedAt => {
+                        // Verification results are cached at delivery time
+                        if smime.is_none() {
+                            smime = Some(
+                                self.get_property::<Bincode<SmimeVerification>>(
+                                    account_id,
+                                    Collection::Email,
+                                    id.document_id(),
+                                    &Property::SmimeStatus,
+                                )
+                                .await?
+                                .map(|smime| smime.inner),
+                            );
+                        }
+
+                        let value = match (property, smime.as_ref().unwrap()) {
+                            (Property::SmimeStatus, Some(smime)) => {
+                                Value::from(smime.status.as_str())
+                            }
+                            (Property::SmimeErrors, Some(smime)) if !smime.errors.is_empty() => {
+                                Value::from(smime.errors.clone())
+                            }
+                            (Property::SmimeVerifiedAt, Some(smime)) => {
+                                Value::Date(UTCDate::from_timestamp(smime.verified_at as i64))
+                            }
+                            _ => Value::Null,
+                        };
+                        email.append(property.clone(), value);
+                    }
 
                     _ => {
                         return Err(trc::JmapEvent::InvalidArguments
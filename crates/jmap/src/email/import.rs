@@ -136,6 +136,7 @@ impl EmailImport for Server {
                     received_at: email.received_at.map(|r| r.into()),
                     source: IngestSource::Jmap,
                     encrypt: self.core.jmap.encrypt && self.core.jmap.encrypt_append,
+                    shared_metadata: false,
                     session_id: session.session_id,
                 })
                 .await
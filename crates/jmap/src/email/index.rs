@@ -17,13 +17,16 @@ use nlp::language::Language;
 use store::{
     backend::MAX_TOKEN_LENGTH,
     fts::{index::FtsDocument, Field},
-    write::{BatchBuilder, Bincode, BlobOp, DirectoryClass, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE},
+    write::{
+        now, BatchBuilder, Bincode, BlobOp, DirectoryClass, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE,
+    },
+    Serialize,
 };
 use utils::BlobHash;
 
 use crate::mailbox::UidMailbox;
 
-use super::metadata::MessageMetadata;
+use super::metadata::{MessageMetadata, StoredMetadata};
 
 pub const MAX_MESSAGE_PARTS: usize = 1000;
 pub const MAX_ID_LENGTH: usize = 100;
@@ -37,6 +40,12 @@ pub struct SortedAddressBuilder {
     pub buf: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum MetadataStorage {
+    Document,
+    Shared { exists: bool },
+}
+
 pub(super) trait IndexMessage {
     #[allow(clippy::too_many_arguments)]
     fn index_message(
@@ -48,6 +57,7 @@ pub(super) trait IndexMessage {
         keywords: Vec<Keyword>,
         mailbox_ids: Vec<UidMailbox>,
         received_at: u64,
+        storage: MetadataStorage,
     ) -> &mut Self;
 
     fn index_headers(&mut self, headers: &[Header<'_>], options: u32);
@@ -67,6 +77,7 @@ impl IndexMessage for BatchBuilder {
         keywords: Vec<Keyword>,
         mailbox_ids: Vec<UidMailbox>,
         received_at: u64,
+        storage: MetadataStorage,
     ) -> &mut Self {
         // Index keywords
         self.value(Property::Keywords, keywords, F_VALUE | F_BITMAP);
@@ -150,26 +161,47 @@ impl IndexMessage for BatchBuilder {
             Vec::new(),
         );
 
-        // Store message metadata
+        // Store message metadata, messages delivered to several recipients
+        // keep a single copy that is shared between them
+        if let MetadataStorage::Shared { exists: true } = storage {
+            self.set(
+                Property::BodyStructure,
+                StoredMetadata::reference(&blob_hash),
+            );
+            return self;
+        }
         let root_part = message.root_part();
-        self.value(
-            Property::BodyStructure,
-            Bincode::new(MessageMetadata {
-                preview: preview.unwrap_or_default().into_owned(),
-                size: message.raw_message.len(),
-                raw_headers: message
-                    .raw_message
-                    .as_ref()
-                    .get(root_part.offset_header..root_part.offset_body)
-                    .unwrap_or_default()
-                    .to_vec(),
-                contents: message.into(),
-                received_at,
-                has_attachments,
-                blob_hash,
-            }),
-            F_VALUE,
-        );
+        let metadata = Bincode::new(MessageMetadata {
+            preview: preview.unwrap_or_default().into_owned(),
+            size: message.raw_message.len(),
+            raw_headers: message
+                .raw_message
+                .as_ref()
+                .get(root_part.offset_header..root_part.offset_body)
+                .unwrap_or_default()
+                .to_vec(),
+            contents: message.into(),
+            received_at,
+            has_attachments,
+            blob_hash: blob_hash.clone(),
+        });
+        match storage {
+            MetadataStorage::Document => {
+                self.value(Property::BodyStructure, metadata, F_VALUE);
+            }
+            MetadataStorage::Shared { .. } => {
+                self.set(
+                    BlobOp::Metadata {
+                        hash: blob_hash.clone(),
+                    },
+                    metadata.serialize(),
+                )
+                .set(
+                    Property::BodyStructure,
+                    StoredMetadata::reference(&blob_hash),
+                );
+            }
+        }
 
         self
     }
@@ -275,6 +307,21 @@ impl IndexMessage for BatchBuilder {
     }
 }
 
+pub trait SaveDate {
+    /// Records the current time as the date the message was saved into a
+    /// mailbox, replacing the previous save date if there is one.
+    fn save_date(&mut self, previous: Option<u64>) -> &mut Self;
+}
+
+impl SaveDate for BatchBuilder {
+    fn save_date(&mut self, previous: Option<u64>) -> &mut Self {
+        if let Some(previous) = previous {
+            self.value(Property::SavedAt, previous, F_INDEX | F_CLEAR);
+        }
+        self.value(Property::SavedAt, now(), F_VALUE | F_INDEX)
+    }
+}
+
 impl<'x> IndexMessageText<'x> for FtsDocument<'x, HeaderName<'x>> {
     fn index_message(mut self, message: &'x Message<'x>) -> Self {
         let mut language = Language::Unknown;
@@ -18,7 +18,7 @@ use jmap_proto::{
     },
 };
 use mail_parser::{
-    parsers::fields::thread::thread_name, HeaderName, HeaderValue, Message, MessageParser, PartType,
+    parsers::fields::thread::thread_name, HeaderName, HeaderValue, Message, MessageParser,
 };
 
 use rand::Rng;
@@ -28,10 +28,11 @@ use store::{
     query::Filter,
     write::{
         log::{ChangeLogBuilder, Changes, LogInsert},
-        now, AssignedIds, BatchBuilder, BitmapClass, FtsQueueClass, MaybeDynamicId,
-        MaybeDynamicValue, SerializeWithId, TagValue, ValueClass, F_BITMAP, F_CLEAR, F_VALUE,
+        now, AssignedIds, BatchBuilder, Bincode, BitmapClass, BlobOp, FtsQueueClass,
+        MaybeDynamicId, MaybeDynamicValue, SerializeWithId, TagValue, ValueClass, F_BITMAP,
+        F_CLEAR, F_VALUE,
     },
-    BitmapKey, BlobClass, Serialize,
+    BitmapKey, BlobClass, Serialize, ValueKey,
 };
 use trc::{AddContext, MessageIngestEvent};
 use utils::map::vec_map::VecMap;
@@ -39,7 +40,7 @@ use utils::map::vec_map::VecMap;
 use crate::{
     blob::upload::BlobUpload,
     changes::write::ChangeLog,
-    email::index::{IndexMessage, VisitValues, MAX_ID_LENGTH},
+    email::index::{IndexMessage, SaveDate, VisitValues, MAX_ID_LENGTH},
     mailbox::{UidMailbox, INBOX_ID, JUNK_ID},
     services::index::Indexer,
     JmapMethods,
@@ -47,8 +48,11 @@ use crate::{
 
 use super::{
     cache::ThreadCache,
-    crypto::{EncryptMessage, EncryptMessageError, EncryptionParams},
-    index::{TrimTextValue, MAX_SORT_FIELD_LENGTH},
+    crypto::{remove_contents, EncryptMessage, EncryptMessageError, EncryptionParams},
+    index::{MetadataStorage, TrimTextValue, MAX_SORT_FIELD_LENGTH},
+    metadata::MessageMetadata,
+    pgp::{PgpEscrowHandler, ProcessPgp},
+    smime::VerifySmime,
 };
 
 #[derive(Default)]
@@ -69,6 +73,7 @@ pub struct IngestEmail<'x> {
     pub received_at: Option<u64>,
     pub source: IngestSource,
     pub encrypt: bool,
+    pub shared_metadata: bool,
     pub session_id: u64,
 }
 
@@ -97,6 +102,12 @@ pub trait EmailIngest: Sync + Send {
         account_id: u32,
         mailbox_id: u32,
     ) -> impl Future<Output = trc::Result<u32>> + Send;
+    fn assign_imap_uids(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        count: u32,
+    ) -> impl Future<Output = trc::Result<u32>> + Send;
 }
 
 impl EmailIngest for Server {
@@ -223,18 +234,72 @@ impl EmailIngest for Server {
             }
         };
 
+        // Obtain the encryption-at-rest settings
+        let encrypt_params = if params.encrypt {
+            self.get_property::<EncryptionParams>(
+                account_id,
+                Collection::Principal,
+                0,
+                Property::Parameters,
+            )
+            .await
+            .caused_by(trc::location!())?
+        } else {
+            None
+        };
+
+        // Verify OpenPGP signatures and decrypt the messages of accounts that
+        // escrowed a key, unless they are to be encrypted at rest
+        if let Some(config) = &self.core.jmap.pgp_verify {
+            let escrow = if config.decrypt && encrypt_params.is_none() {
+                self.get_pgp_escrow(account_id)
+                    .await
+                    .caused_by(trc::location!())?
+            } else {
+                None
+            };
+
+            if let Some(result) = message.process_pgp(config, escrow.as_ref()) {
+                let from = message
+                    .from()
+                    .and_then(|from| from.first())
+                    .and_then(|addr| addr.address())
+                    .map(|addr| addr.to_string());
+                let mut new_raw_message = result
+                    .to_header(&config.authserv_id, from.as_deref())
+                    .into_bytes();
+                new_raw_message.extend_from_slice(
+                    result
+                        .decrypted_message
+                        .as_deref()
+                        .unwrap_or(raw_message.as_ref()),
+                );
+                raw_message = Cow::from(new_raw_message);
+                raw_message_len = raw_message.len() as u64;
+                message = MessageParser::default()
+                    .parse(raw_message.as_ref())
+                    .ok_or_else(|| {
+                        trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
+                            .ctx(trc::Key::Code, 550)
+                            .ctx(
+                                trc::Key::Reason,
+                                "Failed to parse decrypted e-mail message.",
+                            )
+                    })?;
+            }
+        }
+
+        // Verify S/MIME signatures before the message is encrypted at rest
+        let smime = self
+            .core
+            .jmap
+            .smime_verify
+            .as_ref()
+            .and_then(|config| message.verify_smime(&config.trust_anchors, now()));
+
         // Encrypt message
-        if params.encrypt && !message.is_encrypted() {
-            if let Some(encrypt_params) = self
-                .get_property::<EncryptionParams>(
-                    account_id,
-                    Collection::Principal,
-                    0,
-                    Property::Parameters,
-                )
-                .await
-                .caused_by(trc::location!())?
-            {
+        if !message.is_encrypted() {
+            if let Some(encrypt_params) = encrypt_params {
                 match message.encrypt(&encrypt_params).await {
                     Ok(new_raw_message) => {
                         raw_message = Cow::from(new_raw_message);
@@ -251,20 +316,7 @@ impl EmailIngest for Server {
                             })?;
 
                         // Remove contents from parsed message
-                        for part in &mut message.parts {
-                            match &mut part.body {
-                                PartType::Text(txt) | PartType::Html(txt) => {
-                                    *txt = Cow::from("");
-                                }
-                                PartType::Binary(bin) | PartType::InlineBinary(bin) => {
-                                    *bin = Cow::from(&[][..]);
-                                }
-                                PartType::Message(_) => {
-                                    part.body = PartType::Binary(Cow::from(&[][..]));
-                                }
-                                PartType::Multipart(_) => (),
-                            }
-                        }
+                        remove_contents(&mut message);
                     }
                     Err(EncryptMessageError::Error(err)) => {
                         trc::bail!(trc::StoreEvent::CryptoError
@@ -289,6 +341,33 @@ impl EmailIngest for Server {
             .await
             .caused_by(trc::location!())?;
 
+        // Recipients of the same delivery share its metadata, unless the
+        // message was rewritten for this account
+        let received_at = params.received_at.unwrap_or_else(now);
+        let metadata_storage = if params.shared_metadata && matches!(raw_message, Cow::Borrowed(_))
+        {
+            match self
+                .core
+                .storage
+                .data
+                .get_value::<Bincode<MessageMetadata>>(ValueKey::from(ValueClass::Blob(
+                    BlobOp::Metadata {
+                        hash: blob_id.hash.clone(),
+                    },
+                )))
+                .await
+                .caused_by(trc::location!())?
+            {
+                Some(metadata) if metadata.inner.received_at == received_at => {
+                    MetadataStorage::Shared { exists: true }
+                }
+                Some(_) => MetadataStorage::Document,
+                None => MetadataStorage::Shared { exists: false },
+            }
+        } else {
+            MetadataStorage::Document
+        };
+
         // Assign IMAP UIDs
         let mut mailbox_ids = Vec::with_capacity(params.mailbox_ids.len());
         let mut imap_uids = Vec::with_capacity(params.mailbox_ids.len());
@@ -334,8 +413,10 @@ impl EmailIngest for Server {
                 blob_id.hash.clone(),
                 params.keywords,
                 mailbox_ids,
-                params.received_at.unwrap_or_else(now),
+                received_at,
+                metadata_storage,
             )
+            .save_date(None)
             .value(Property::Cid, change_id, F_VALUE)
             .set(Property::ThreadId, maybe_thread_id)
             .tag(Property::ThreadId, TagValue::Id(maybe_thread_id), 0)
@@ -346,6 +427,9 @@ impl EmailIngest for Server {
                 }),
                 0u64.serialize(),
             );
+        if let Some(smime) = smime {
+            batch.value(Property::SmimeStatus, Bincode::new(smime), F_VALUE);
+        }
 
         // Insert and obtain ids
         let ids = self
@@ -545,19 +629,32 @@ impl EmailIngest for Server {
     }
 
     async fn assign_imap_uid(&self, account_id: u32, mailbox_id: u32) -> trc::Result<u32> {
+        self.assign_imap_uids(account_id, mailbox_id, 1).await
+    }
+
+    /// Reserves `count` consecutive UIDs in a mailbox and returns the first one.
+    /// The range is allocated with a single atomic increment, which guarantees that
+    /// no other node can be assigned any of these UIDs.
+    async fn assign_imap_uids(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        count: u32,
+    ) -> trc::Result<u32> {
         // Increment UID next
+        let count = count.max(1);
         let mut batch = BatchBuilder::new();
         batch
             .with_account_id(account_id)
             .with_collection(Collection::Mailbox)
             .update_document(mailbox_id)
-            .add_and_get(Property::EmailIds, 1);
+            .add_and_get(Property::EmailIds, count as i64);
         self.core
             .storage
             .data
             .write(batch.build())
             .await
-            .and_then(|v| v.last_counter_id().map(|id| id as u32))
+            .and_then(|v| v.last_counter_id().map(|id| (id as u32) - count + 1))
     }
 }
 
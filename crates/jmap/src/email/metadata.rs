@@ -6,6 +6,7 @@
 
 use std::borrow::Cow;
 
+use futures_util::{StreamExt, TryStreamExt};
 use mail_parser::{
     decoders::{
         base64::base64_decode, charsets::map::charset_decoder,
@@ -15,7 +16,13 @@ use mail_parser::{
     MessagePartId, MimeHeaders, PartType,
 };
 use serde::{Deserialize, Serialize};
-use utils::BlobHash;
+use store::{
+    ahash::AHashMap,
+    write::{BlobOp, ValueClass},
+    Store, ValueKey,
+};
+use trc::AddContext;
+use utils::{BlobHash, BLOB_HASH_LEN};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MessageMetadata<'x> {
@@ -297,3 +304,95 @@ impl<'x> MimeHeaders<'x> for MessageMetadataPart<'x> {
             .and_then(|header| header.as_text())
     }
 }
+
+// Messages delivered to several local recipients keep a single copy of their
+// metadata next to the message blob, recipients store a reference to it.
+const SHARED_METADATA: [u8; 4] = [u8::MAX; 4];
+const SHARED_METADATA_CONCURRENCY: usize = 32;
+
+pub struct StoredMetadata(Vec<u8>);
+
+impl StoredMetadata {
+    pub fn reference(hash: &BlobHash) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SHARED_METADATA.len() + BLOB_HASH_LEN);
+        bytes.extend_from_slice(&SHARED_METADATA);
+        bytes.extend_from_slice(hash.as_ref());
+        bytes
+    }
+
+    pub fn shared_hash(bytes: &[u8]) -> Option<BlobHash> {
+        bytes
+            .strip_prefix(SHARED_METADATA.as_slice())
+            .and_then(|hash| BlobHash::try_from_hash_slice(hash).ok())
+    }
+
+    pub fn hash(&self) -> Option<BlobHash> {
+        Self::shared_hash(&self.0)
+    }
+
+    pub async fn get_shared<U>(store: &Store, hash: BlobHash) -> trc::Result<U>
+    where
+        U: store::Deserialize + 'static,
+    {
+        store
+            .get_value::<U>(ValueKey::from(ValueClass::Blob(BlobOp::Metadata {
+                hash: hash.clone(),
+            })))
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| {
+                trc::StoreEvent::NotFound
+                    .into_err()
+                    .caused_by(trc::location!())
+                    .ctx(trc::Key::BlobId, hash.to_hex())
+            })
+    }
+
+    // Fetches the shared metadata of several documents, reading each distinct
+    // message once and up to `SHARED_METADATA_CONCURRENCY` of them in parallel.
+    pub async fn get_shared_many<U>(
+        store: &Store,
+        shared: Vec<(u32, BlobHash)>,
+    ) -> trc::Result<Vec<(u32, U)>>
+    where
+        U: store::Deserialize + 'static,
+    {
+        let mut documents: AHashMap<BlobHash, Vec<u32>> = AHashMap::with_capacity(shared.len());
+        for (document_id, hash) in shared {
+            documents.entry(hash).or_default().push(document_id);
+        }
+
+        let mut results = Vec::with_capacity(documents.len());
+        let mut fetches = futures_util::stream::iter(documents)
+            .map(|(hash, document_ids)| async move {
+                Self::get_shared::<StoredMetadata>(store, hash)
+                    .await
+                    .map(|metadata| (document_ids, metadata))
+            })
+            .buffer_unordered(SHARED_METADATA_CONCURRENCY);
+        while let Some((document_ids, metadata)) = fetches.try_next().await? {
+            for document_id in document_ids {
+                results.push((document_id, U::deserialize(&metadata.0)?));
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn resolve<U>(self, store: &Store) -> trc::Result<U>
+    where
+        U: store::Deserialize + 'static,
+    {
+        if let Some(hash) = self.hash() {
+            Self::get_shared(store, hash).await
+        } else {
+            U::deserialize(&self.0)
+        }
+    }
+}
+
+impl store::Deserialize for StoredMetadata {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        Ok(StoredMetadata(bytes.to_vec()))
+    }
+}
@@ -4,11 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod archive;
 pub mod body;
 pub mod cache;
 pub mod copy;
 pub mod crypto;
 pub mod delete;
+pub mod fallback;
 pub mod get;
 pub mod headers;
 pub mod import;
@@ -16,6 +18,14 @@ pub mod index;
 pub mod ingest;
 pub mod metadata;
 pub mod parse;
+pub mod pdf;
+pub mod pgp;
+pub mod quarantine;
 pub mod query;
+pub mod seen;
 pub mod set;
+pub mod share;
+pub mod smime;
 pub mod snippet;
+pub mod train;
+pub mod trash;
@@ -11,12 +11,12 @@ use jmap_proto::{
     types::{property::Property, value::Value},
 };
 use mail_parser::{
-    decoders::html::html_to_text, parsers::preview::preview_text, MessageParser, PartType,
+    decoders::html::html_to_text, parsers::preview::preview_text, Encoding, MessageParser, PartType,
 };
 use std::future::Future;
 use utils::map::vec_map::VecMap;
 
-use crate::blob::download::BlobDownload;
+use crate::blob::{download::BlobDownload, upload::BlobUpload};
 
 use super::{
     body::{ToBodyPart, TruncateBody},
@@ -83,6 +83,16 @@ impl EmailParse for Server {
         let fetch_html_body_values = request.fetch_html_body_values.unwrap_or(false);
         let fetch_all_body_values = request.fetch_all_body_values.unwrap_or(false);
         let max_body_value_bytes = request.max_body_value_bytes.unwrap_or(0);
+        let has_part_blob_ids = body_properties.contains(&Property::BlobId)
+            && properties.iter().any(|property| {
+                matches!(
+                    property,
+                    Property::TextBody
+                        | Property::HtmlBody
+                        | Property::Attachments
+                        | Property::BodyStructure
+                )
+            });
 
         let mut response = ParseEmailResponse {
             account_id: request.account_id,
@@ -107,6 +117,20 @@ impl EmailParse for Server {
                 continue;
             };
 
+            // Parts of an encoded section, such as a base64 message/rfc822 attachment,
+            // cannot be addressed within the original blob, so the decoded message is
+            // stored as a temporary blob which nested parts then point to. This is a
+            // write, so it counts towards the upload quota and fails in read-only mode
+            let part_blob_id = if has_part_blob_ids
+                && blob_id.section.as_ref().is_some_and(|section| {
+                    !matches!(Encoding::from(section.encoding), Encoding::None)
+                }) {
+                self.put_blob_checked(access_token.primary_id(), &raw_message, access_token)
+                    .await?
+            } else {
+                blob_id.clone()
+            };
+
             // Prepare response
             let mut email = Object::with_capacity(properties.len());
             for property in &properties {
@@ -196,7 +220,7 @@ impl EmailParse for Server {
                                     *part_id,
                                     &body_properties,
                                     &raw_message,
-                                    &blob_id,
+                                    &part_blob_id,
                                 )
                             })
                             .collect::<Vec<_>>(),
@@ -205,9 +229,12 @@ impl EmailParse for Server {
                     Property::BodyStructure => {
                         email.append(
                             Property::BodyStructure,
-                            message
-                                .parts
-                                .to_body_part(0, &body_properties, &raw_message, &blob_id),
+                            message.parts.to_body_part(
+                                0,
+                                &body_properties,
+                                &raw_message,
+                                &part_blob_id,
+                            ),
                         );
                     }
                     Property::BodyValues => {
@@ -0,0 +1,337 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, process::Stdio, time::Duration};
+
+use common::{auth::AccessToken, config::jmap::settings::PdfRenderer, Server};
+use jmap_proto::types::{acl::Acl, collection::Collection, property::Property};
+use mail_parser::{Message, MessageParser, MimeHeaders};
+use store::write::Bincode;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{auth::acl::AclMethods, blob::download::BlobDownload, JmapMethods};
+
+use super::{
+    metadata::MessageMetadata,
+    share::{message_headers, render_message_html},
+};
+
+const PAGE_WIDTH: usize = 595;
+const PAGE_HEIGHT: usize = 842;
+const MARGIN: usize = 50;
+const FONT_SIZE: usize = 10;
+const LINE_HEIGHT: usize = 12;
+const LINE_MAX_CHARS: usize = 90;
+const LINES_PER_PAGE: usize = (PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT;
+
+pub trait EmailPdf: Sync + Send {
+    fn email_pdf(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        document_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<u8>>> + Send;
+}
+
+/// Converts a parsed message into a PDF document.
+pub trait MessageRenderer: Sync + Send {
+    fn render_pdf(
+        &self,
+        message: &Message<'_>,
+    ) -> impl Future<Output = trc::Result<Vec<u8>>> + Send;
+}
+
+impl EmailPdf for Server {
+    async fn email_pdf(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        document_id: u32,
+    ) -> trc::Result<Vec<u8>> {
+        if matches!(self.core.jmap.mail_pdf_renderer, PdfRenderer::Disabled) {
+            return Err(trc::ResourceEvent::NotFound
+                .into_err()
+                .details("PDF rendering is disabled"));
+        }
+
+        // Rendering is expensive, limit requests per account
+        if let Some(rate) = &self.core.jmap.mail_pdf_rate {
+            if self
+                .core
+                .storage
+                .lookup
+                .is_rate_allowed(format!("pdf:{account_id}").as_bytes(), rate, false)
+                .await?
+                .is_some()
+            {
+                return Err(trc::LimitEvent::TooManyRequests.into_err());
+            }
+        }
+
+        if !self
+            .owned_or_shared_messages(access_token, account_id, Acl::ReadItems)
+            .await?
+            .contains(document_id)
+        {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        let metadata = self
+            .get_property::<Bincode<MessageMetadata>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                &Property::BodyStructure,
+            )
+            .await?
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?
+            .inner;
+        let raw_message = self
+            .get_blob(&metadata.blob_hash, 0..usize::MAX)
+            .await?
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+        let message = MessageParser::new()
+            .parse(&raw_message)
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+        self.core.jmap.mail_pdf_renderer.render_pdf(&message).await
+    }
+}
+
+impl MessageRenderer for PdfRenderer {
+    async fn render_pdf(&self, message: &Message<'_>) -> trc::Result<Vec<u8>> {
+        match self {
+            PdfRenderer::Builtin => Ok(render_text_pdf(message)),
+            PdfRenderer::Command {
+                command,
+                arguments,
+                timeout,
+            } => {
+                render_with_command(
+                    command,
+                    arguments,
+                    *timeout,
+                    render_message_html(message, None).into_bytes(),
+                )
+                .await
+            }
+            PdfRenderer::Disabled => Err(trc::ResourceEvent::NotFound
+                .into_err()
+                .details("PDF rendering is disabled")),
+        }
+    }
+}
+
+/// Pipes the HTML rendition of the message to an external converter
+/// (such as wkhtmltopdf) and returns its output.
+async fn render_with_command(
+    command: &str,
+    arguments: &[String],
+    timeout: Duration,
+    html: Vec<u8>,
+) -> trc::Result<Vec<u8>> {
+    let mut child = Command::new(command)
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| {
+            trc::ResourceEvent::Error
+                .into_err()
+                .ctx(trc::Key::Path, command.to_string())
+                .reason(err)
+                .details("Failed to execute PDF renderer")
+        })?;
+
+    // Write the input concurrently to avoid blocking on a full pipe
+    if let Some(mut stdin) = child.stdin.take() {
+        tokio::spawn(async move {
+            let _ = stdin.write_all(&html).await;
+        });
+    }
+
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) if output.status.success() && !output.stdout.is_empty() => Ok(output.stdout),
+        Ok(Ok(output)) => Err(trc::ResourceEvent::Error
+            .into_err()
+            .ctx(trc::Key::Path, command.to_string())
+            .ctx(trc::Key::Result, output.status.to_string())
+            .details("PDF renderer failed")),
+        Ok(Err(err)) => Err(trc::ResourceEvent::Error
+            .into_err()
+            .ctx(trc::Key::Path, command.to_string())
+            .reason(err)
+            .details("PDF renderer failed")),
+        Err(_) => Err(trc::ResourceEvent::Error
+            .into_err()
+            .ctx(trc::Key::Path, command.to_string())
+            .details("PDF renderer timed out")),
+    }
+}
+
+/// Renders the message headers, text body and attachment list as a plain
+/// text PDF document using the standard Helvetica font.
+pub fn render_text_pdf(message: &Message<'_>) -> Vec<u8> {
+    // Layout lines
+    let mut lines = Vec::new();
+    wrap_line(
+        &format!("Subject: {}", message.subject().unwrap_or_default()),
+        &mut lines,
+    );
+    for (name, value) in message_headers(message) {
+        wrap_line(&format!("{name}: {value}"), &mut lines);
+    }
+    lines.push(String::new());
+    for line in message.body_text(0).unwrap_or_default().lines() {
+        wrap_line(line, &mut lines);
+    }
+    if !message.attachments.is_empty() {
+        lines.push(String::new());
+        lines.push("Attachments:".to_string());
+        for part in message
+            .attachments
+            .iter()
+            .filter_map(|part_id| message.parts.get(*part_id))
+        {
+            wrap_line(
+                &format!(
+                    "- {} ({} bytes)",
+                    part.attachment_name().unwrap_or("attachment"),
+                    part.contents().len()
+                ),
+                &mut lines,
+            );
+        }
+    }
+    let pages = lines.chunks(LINES_PER_PAGE).collect::<Vec<_>>();
+
+    // Objects 1-3 are the catalog, page tree and font, followed by
+    // a page and content stream object for each page.
+    let mut objects = Vec::with_capacity(3 + pages.len() * 2);
+    objects.push("<< /Type /Catalog /Pages 2 0 R >>".as_bytes().to_vec());
+    objects.push(
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|page_num| format!("{} 0 R", 4 + page_num * 2))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+    );
+    objects.push(
+        concat!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica ",
+            "/Encoding /WinAnsiEncoding >>"
+        )
+        .as_bytes()
+        .to_vec(),
+    );
+    for (page_num, page) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                concat!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] ",
+                    "/Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>"
+                ),
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                5 + page_num * 2
+            )
+            .into_bytes(),
+        );
+
+        let mut stream = format!(
+            "BT\n/F1 {FONT_SIZE} Tf\n{LINE_HEIGHT} TL\n{MARGIN} {} Td\n",
+            PAGE_HEIGHT - MARGIN
+        )
+        .into_bytes();
+        for line in page.iter() {
+            stream.push(b'(');
+            escape_pdf_string(line, &mut stream);
+            stream.extend_from_slice(b") Tj T*\n");
+        }
+        stream.extend_from_slice(b"ET");
+        let mut object = format!("<< /Length {} >>\nstream\n", stream.len()).into_bytes();
+        object.extend_from_slice(&stream);
+        object.extend_from_slice(b"\nendstream");
+        objects.push(object);
+    }
+
+    // Write document
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (obj_num, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", obj_num + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
+
+fn wrap_line(text: &str, lines: &mut Vec<String>) {
+    let mut line = String::new();
+    let mut line_len = 0;
+
+    for word in text.replace('\t', "    ").split(' ') {
+        let word_len = word.chars().count();
+        if line_len > 0 && line_len + 1 + word_len > LINE_MAX_CHARS {
+            lines.push(std::mem::take(&mut line));
+            line_len = 0;
+        }
+        if line_len > 0 {
+            line.push(' ');
+            line_len += 1;
+        }
+
+        // Split words that do not fit in a single line
+        for ch in word.chars() {
+            if line_len == LINE_MAX_CHARS {
+                lines.push(std::mem::take(&mut line));
+                line_len = 0;
+            }
+            line.push(ch);
+            line_len += 1;
+        }
+    }
+
+    lines.push(line);
+}
+
+fn escape_pdf_string(text: &str, buf: &mut Vec<u8>) {
+    for ch in text.chars() {
+        match ch {
+            '(' | ')' | '\\' => {
+                buf.push(b'\\');
+                buf.push(ch as u8);
+            }
+            ' '..='~' => buf.push(ch as u8),
+            '\u{a0}'..='\u{ff}' => {
+                buf.extend_from_slice(format!("\\{:03o}", ch as u32).as_bytes());
+            }
+            _ => buf.push(b'?'),
+        }
+    }
+}
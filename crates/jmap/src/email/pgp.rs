@@ -0,0 +1,742 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{collections::BTreeSet, future::Future, io::Read, sync::Arc};
+
+use common::{auth::AccessToken, config::jmap::settings::PgpVerify, Server};
+use directory::backend::internal::manage;
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_parser::{decoders::base64::base64_decode, Message, MessageParser, MimeHeaders, PartType};
+use openpgp::{
+    cert::{amalgamation::key::ValidErasedKeyAmalgamation, CertParser},
+    crypto::SessionKey,
+    packet::{key::SecretParts, PKESK, SKESK},
+    parse::{
+        stream::{
+            DecryptionHelper, DecryptorBuilder, DetachedVerifierBuilder, GoodChecksum,
+            MessageLayer, MessageStructure, VerificationError, VerificationHelper, VerifierBuilder,
+        },
+        PacketParser, PacketParserResult, Parse,
+    },
+    serialize::SerializeInto,
+    types::{KeyFlags, SymmetricAlgorithm},
+    Cert, Fingerprint, KeyHandle, Packet,
+};
+use sequoia_openpgp as openpgp;
+use serde_json::json;
+use store::write::{BatchBuilder, Bincode, F_CLEAR, F_VALUE};
+
+use crate::{
+    api::{http::ToHttpResponse, HttpResponse, JsonResponse},
+    JmapMethods,
+};
+
+use super::smime::detached_candidates;
+
+// Verifiers keep a reference to the policy, so it has to be a static rather than a constant
+static P: openpgp::policy::StandardPolicy<'static> = openpgp::policy::StandardPolicy::new();
+
+/// Secret key escrowed by an account so that its encrypted messages can be
+/// decrypted at ingestion.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PgpEscrow {
+    pub key: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgpSignature {
+    Pass { fingerprint: String },
+    Neutral { reason: String },
+    Fail { reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgpDecryption {
+    Pass,
+    Fail { reason: String },
+}
+
+#[derive(Debug, Default)]
+pub struct PgpResult {
+    pub signature: Option<PgpSignature>,
+    pub decryption: Option<PgpDecryption>,
+    pub decrypted_message: Option<Vec<u8>>,
+}
+
+#[derive(serde::Deserialize)]
+struct PgpEscrowRequest {
+    key: String,
+}
+
+pub trait ProcessPgp {
+    /// Verifies the OpenPGP signature of a message and, when an escrowed key
+    /// is provided, decrypts its contents. Returns `None` if the message is
+    /// neither signed nor encrypted.
+    fn process_pgp(&self, config: &PgpVerify, escrow: Option<&Cert>) -> Option<PgpResult>;
+}
+
+impl ProcessPgp for Message<'_> {
+    fn process_pgp(&self, config: &PgpVerify, escrow: Option<&Cert>) -> Option<PgpResult> {
+        let from = from_addresses(self);
+        let mut certs = config
+            .keyring
+            .iter()
+            .flat_map(|keyring| CertParser::from_bytes(keyring).into_iter().flatten())
+            .filter_map(|cert| cert.ok())
+            .collect::<Vec<_>>();
+        certs.extend(autocrypt_cert(self, &from));
+
+        let mut result = PgpResult::default();
+        if let Some(secret) = escrow {
+            match decrypt_message(self, &certs, &from, secret) {
+                Some(Ok((raw_message, signature))) => {
+                    result.decryption = Some(PgpDecryption::Pass);
+                    result.signature = signature;
+                    result.decrypted_message = Some(raw_message);
+                }
+                Some(Err(reason)) => {
+                    result.decryption = Some(PgpDecryption::Fail { reason });
+                }
+                None => (),
+            }
+        }
+
+        // Verify the signature of the decrypted message, unless it was signed
+        // and encrypted in a single OpenPGP message
+        if result.signature.is_none() {
+            result.signature = match &result.decrypted_message {
+                Some(raw_message) => MessageParser::default()
+                    .parse(raw_message.as_slice())
+                    .and_then(|message| verify_message(&message, &certs, &from)),
+                None => verify_message(self, &certs, &from),
+            };
+        }
+
+        if result.signature.is_some() || result.decryption.is_some() {
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+impl PgpResult {
+    /// Builds an `Authentication-Results` header (RFC 8601) with the
+    /// outcome of the verification and decryption.
+    pub fn to_header(&self, authserv_id: &str, from: Option<&str>) -> String {
+        let mut header = format!("Authentication-Results: {authserv_id}");
+        match &self.signature {
+            Some(PgpSignature::Pass { fingerprint }) => {
+                header.push_str(";\r\n\tpgp=pass");
+                if let Some(from) = from {
+                    header.push_str(" header.from=");
+                    header.push_str(from);
+                }
+                header.push_str(" (key ");
+                header.push_str(fingerprint);
+                header.push(')');
+            }
+            Some(PgpSignature::Neutral { reason }) => {
+                header.push_str(";\r\n\tpgp=neutral reason=");
+                push_quoted(&mut header, reason);
+            }
+            Some(PgpSignature::Fail { reason }) => {
+                header.push_str(";\r\n\tpgp=fail reason=");
+                push_quoted(&mut header, reason);
+            }
+            None => (),
+        }
+        match &self.decryption {
+            Some(PgpDecryption::Pass) => {
+                header.push_str(";\r\n\tpgp-decrypt=pass");
+            }
+            Some(PgpDecryption::Fail { reason }) => {
+                header.push_str(";\r\n\tpgp-decrypt=fail reason=");
+                push_quoted(&mut header, reason);
+            }
+            None => (),
+        }
+        header.push_str("\r\n");
+        header
+    }
+}
+
+pub trait PgpEscrowHandler: Sync + Send {
+    fn handle_pgp_escrow_get(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_pgp_escrow_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_pgp_escrow_delete(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn get_pgp_escrow(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<Cert>>> + Send;
+}
+
+impl PgpEscrowHandler for Server {
+    async fn handle_pgp_escrow_get(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> trc::Result<HttpResponse> {
+        let fingerprint = self
+            .get_pgp_escrow(access_token.primary_id())
+            .await?
+            .map(|cert| cert.fingerprint().to_hex());
+
+        Ok(JsonResponse::new(json!({
+            "data": fingerprint,
+        }))
+        .into_http_response())
+    }
+
+    async fn handle_pgp_escrow_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        let request =
+            serde_json::from_slice::<PgpEscrowRequest>(body.as_deref().unwrap_or_default())
+                .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+
+        // Make sure decryption is enabled
+        if !self
+            .core
+            .jmap
+            .pgp_verify
+            .as_ref()
+            .map_or(false, |config| config.decrypt)
+        {
+            return Err(manage::unsupported(
+                "OpenPGP decryption has been disabled by the system administrator",
+            ));
+        }
+
+        // Parse the secret key
+        let cert = Cert::from_bytes(request.key.as_bytes())
+            .map_err(|err| manage::error(format!("Failed to parse key: {err}"), None::<u32>))?;
+        if decryption_keys(&cert).next().is_none() {
+            return Err(manage::error(
+                "The key does not contain an unencrypted secret key capable of decryption",
+                None::<u32>,
+            ));
+        }
+        let key = cert
+            .as_tsk()
+            .to_vec()
+            .map_err(|err| manage::error(format!("Failed to serialize key: {err}"), None::<u32>))?;
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(access_token.primary_id())
+            .with_collection(Collection::Principal)
+            .update_document(0)
+            .value(
+                Property::PgpEscrowKey,
+                Bincode::new(PgpEscrow { key }),
+                F_VALUE,
+            );
+        self.core.storage.data.write(batch.build()).await?;
+
+        Ok(JsonResponse::new(json!({
+            "data": cert.fingerprint().to_hex(),
+        }))
+        .into_http_response())
+    }
+
+    async fn handle_pgp_escrow_delete(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> trc::Result<HttpResponse> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(access_token.primary_id())
+            .with_collection(Collection::Principal)
+            .update_document(0)
+            .value(Property::PgpEscrowKey, (), F_VALUE | F_CLEAR);
+        self.core.storage.data.write(batch.build()).await?;
+
+        Ok(JsonResponse::new(json!({
+            "data": (),
+        }))
+        .into_http_response())
+    }
+
+    async fn get_pgp_escrow(&self, account_id: u32) -> trc::Result<Option<Cert>> {
+        Ok(self
+            .get_property::<Bincode<PgpEscrow>>(
+                account_id,
+                Collection::Principal,
+                0,
+                Property::PgpEscrowKey,
+            )
+            .await?
+            .and_then(|escrow| Cert::from_bytes(&escrow.inner.key).ok()))
+    }
+}
+
+struct Helper<'x> {
+    certs: &'x [Cert],
+    from: &'x [String],
+    secret: Option<&'x Cert>,
+    signature: Option<PgpSignature>,
+    good_checksum: bool,
+}
+
+impl<'x> Helper<'x> {
+    fn new(certs: &'x [Cert], from: &'x [String], secret: Option<&'x Cert>) -> Self {
+        Helper {
+            certs,
+            from,
+            secret,
+            signature: None,
+            good_checksum: false,
+        }
+    }
+}
+
+impl VerificationHelper for Helper<'_> {
+    fn get_certs(&mut self, _ids: &[KeyHandle]) -> openpgp::Result<Vec<Cert>> {
+        Ok(self.certs.to_vec())
+    }
+
+    fn check(&mut self, structure: MessageStructure) -> openpgp::Result<()> {
+        for layer in structure {
+            if let MessageLayer::SignatureGroup { results } = layer {
+                for result in results {
+                    let signature = match result {
+                        Ok(GoodChecksum { ka, .. }) => {
+                            self.good_checksum = true;
+                            let cert = ka.cert();
+                            if cert
+                                .userids()
+                                .any(|uid| matches_address(uid.userid().value(), self.from))
+                            {
+                                PgpSignature::Pass {
+                                    fingerprint: cert.fingerprint().to_hex(),
+                                }
+                            } else {
+                                PgpSignature::Fail {
+                                    reason: "Signer key does not match the From address"
+                                        .to_string(),
+                                }
+                            }
+                        }
+                        Err(VerificationError::MissingKey { .. }) => PgpSignature::Neutral {
+                            reason: "No public key available for the signer".to_string(),
+                        },
+                        Err(err) => PgpSignature::Fail {
+                            reason: err.to_string(),
+                        },
+                    };
+
+                    // Keep the most favourable result when there are several signatures
+                    if self
+                        .signature
+                        .as_ref()
+                        .map_or(true, |current| signature.rank() > current.rank())
+                    {
+                        self.signature = Some(signature);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl DecryptionHelper for Helper<'_> {
+    fn decrypt<D>(
+        &mut self,
+        pkesks: &[PKESK],
+        _skesks: &[SKESK],
+        sym_algo: Option<SymmetricAlgorithm>,
+        mut decrypt: D,
+    ) -> openpgp::Result<Option<Fingerprint>>
+    where
+        D: FnMut(SymmetricAlgorithm, &SessionKey) -> bool,
+    {
+        if let Some(cert) = self.secret {
+            for key in decryption_keys(cert) {
+                let mut pair = key.key().clone().into_keypair()?;
+                for pkesk in pkesks {
+                    if pkesk
+                        .decrypt(&mut pair, sym_algo)
+                        .map_or(false, |(algo, session_key)| decrypt(algo, &session_key))
+                    {
+                        return Ok(Some(cert.fingerprint()));
+                    }
+                }
+            }
+        }
+
+        Err(openpgp::Error::MissingSessionKey(
+            "Message is not encrypted to the escrowed key".to_string(),
+        )
+        .into())
+    }
+}
+
+impl PgpSignature {
+    fn rank(&self) -> u8 {
+        match self {
+            PgpSignature::Fail { .. } => 0,
+            PgpSignature::Neutral { .. } => 1,
+            PgpSignature::Pass { .. } => 2,
+        }
+    }
+}
+
+fn verify_message(message: &Message<'_>, certs: &[Cert], from: &[String]) -> Option<PgpSignature> {
+    let root = message.root_part();
+    let content_type = root.content_type();
+
+    if content_type.map_or(false, |ct| {
+        ct.c_type.eq_ignore_ascii_case("multipart")
+            && ct
+                .c_subtype
+                .as_deref()
+                .map_or(false, |st| st.eq_ignore_ascii_case("signed"))
+    }) {
+        if !content_type
+            .and_then(|ct| ct.attribute("protocol"))
+            .map_or(false, |p| {
+                p.eq_ignore_ascii_case("application/pgp-signature")
+            })
+        {
+            return None;
+        }
+
+        // The signed entity is the first part and the signature the second one
+        return Some(match &root.body {
+            PartType::Multipart(parts) if parts.len() == 2 => {
+                let signed_part = &message.parts[parts[0]];
+                let signed_entity = message
+                    .raw_message()
+                    .get(signed_part.offset_header..signed_part.offset_end)
+                    .unwrap_or_default();
+                let signature = message.parts[parts[1]].contents();
+
+                // Stop at the first candidate whose checksum is valid
+                let mut result = None;
+                for candidate in detached_candidates(signed_entity) {
+                    let (signature, good_checksum) =
+                        verify_detached(signature, &candidate, certs, from);
+                    result = Some(signature);
+                    if good_checksum {
+                        break;
+                    }
+                }
+                result.unwrap_or(PgpSignature::Fail {
+                    reason: "Empty signed entity".to_string(),
+                })
+            }
+            _ => PgpSignature::Fail {
+                reason: "Malformed multipart/signed message".to_string(),
+            },
+        });
+    }
+
+    // Look for inline cleartext signatures
+    let text = inline_text(message)?;
+    let start = find(text, b"-----BEGIN PGP SIGNED MESSAGE-----")?;
+    let end = find(&text[start..], b"-----END PGP SIGNATURE-----")
+        .map(|pos| start + pos + b"-----END PGP SIGNATURE-----".len())?;
+    let result = VerifierBuilder::from_bytes(&text[start..end])
+        .and_then(|builder| builder.with_policy(&P, None, Helper::new(certs, from, None)))
+        .and_then(|mut verifier| {
+            std::io::copy(&mut verifier, &mut std::io::sink())?;
+            Ok(verifier.into_helper())
+        });
+
+    Some(match result {
+        Ok(helper) => helper.signature.unwrap_or(PgpSignature::Fail {
+            reason: "No signature found".to_string(),
+        }),
+        Err(err) => PgpSignature::Fail {
+            reason: err.to_string(),
+        },
+    })
+}
+
+/// Verifies a detached signature, also returning whether the checksum was
+/// valid regardless of the signer being trusted.
+fn verify_detached(
+    signature: &[u8],
+    data: &[u8],
+    certs: &[Cert],
+    from: &[String],
+) -> (PgpSignature, bool) {
+    let result = DetachedVerifierBuilder::from_bytes(signature)
+        .and_then(|builder| builder.with_policy(&P, None, Helper::new(certs, from, None)))
+        .and_then(|mut verifier| {
+            let result = verifier.verify_bytes(data);
+            let helper = verifier.into_helper();
+            match (result, helper.signature) {
+                (_, Some(signature)) => Ok((signature, helper.good_checksum)),
+                (Err(err), None) => Err(err),
+                (Ok(_), None) => Ok((
+                    PgpSignature::Fail {
+                        reason: "No signature found".to_string(),
+                    },
+                    false,
+                )),
+            }
+        });
+
+    match result {
+        Ok(result) => result,
+        Err(err) => (
+            PgpSignature::Fail {
+                reason: err.to_string(),
+            },
+            false,
+        ),
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn decrypt_message(
+    message: &Message<'_>,
+    certs: &[Cert],
+    from: &[String],
+    secret: &Cert,
+) -> Option<Result<(Vec<u8>, Option<PgpSignature>), String>> {
+    let root = message.root_part();
+    let raw_message = message.raw_message();
+    let content_type = root.content_type();
+
+    // Keep the outer headers, the MIME headers are replaced by the ones of
+    // the decrypted contents
+    let mut decrypted = Vec::with_capacity(raw_message.len());
+    for header in root.headers() {
+        if !header.name.is_mime_header() {
+            decrypted.extend_from_slice(&raw_message[header.offset_field()..header.offset_end()]);
+        }
+    }
+
+    if content_type.map_or(false, |ct| {
+        ct.c_type.eq_ignore_ascii_case("multipart")
+            && ct
+                .c_subtype
+                .as_deref()
+                .map_or(false, |st| st.eq_ignore_ascii_case("encrypted"))
+    }) {
+        if !content_type
+            .and_then(|ct| ct.attribute("protocol"))
+            .map_or(false, |p| {
+                p.eq_ignore_ascii_case("application/pgp-encrypted")
+            })
+        {
+            return None;
+        }
+
+        // The second part holds the encrypted MIME entity
+        let ciphertext = match &root.body {
+            PartType::Multipart(parts) if parts.len() == 2 => message.parts[parts[1]].contents(),
+            _ => return Some(Err("Malformed multipart/encrypted message".to_string())),
+        };
+
+        Some(
+            decrypt(ciphertext, certs, from, secret).map(|(plaintext, signature)| {
+                decrypted.extend_from_slice(&plaintext);
+                (decrypted, signature)
+            }),
+        )
+    } else {
+        // Look for inline encrypted contents
+        let text = inline_text(message)?;
+        let start = find(text, b"-----BEGIN PGP MESSAGE-----")?;
+        let end = find(&text[start..], b"-----END PGP MESSAGE-----")
+            .map(|pos| start + pos + b"-----END PGP MESSAGE-----".len())?;
+
+        Some(
+            decrypt(&text[start..end], certs, from, secret).map(|(plaintext, signature)| {
+                decrypted.extend_from_slice(
+                    concat!(
+                        "Content-Type: text/plain; charset=utf-8\r\n",
+                        "Content-Transfer-Encoding: 8bit\r\n\r\n"
+                    )
+                    .as_bytes(),
+                );
+                decrypted.extend_from_slice(&text[..start]);
+                decrypted.extend_from_slice(&plaintext);
+                decrypted.extend_from_slice(&text[end..]);
+                (decrypted, signature)
+            }),
+        )
+    }
+}
+
+/// Returns the IDs of the keys an OpenPGP/MIME message is encrypted to.
+pub fn pgp_recipients(message: &Message<'_>) -> Option<BTreeSet<Vec<u8>>> {
+    let root = message.root_part();
+    let ciphertext = match &root.body {
+        PartType::Multipart(parts)
+            if parts.len() == 2
+                && root
+                    .content_type()
+                    .and_then(|ct| ct.attribute("protocol"))
+                    .map_or(false, |p| {
+                        p.eq_ignore_ascii_case("application/pgp-encrypted")
+                    }) =>
+        {
+            message.parts[parts[1]].contents()
+        }
+        _ => return None,
+    };
+
+    // The session key packets precede the encrypted data
+    let mut recipients = BTreeSet::new();
+    let mut ppr = PacketParser::from_bytes(ciphertext).ok()?;
+    while let PacketParserResult::Some(pp) = ppr {
+        match &pp.packet {
+            Packet::PKESK(pkesk) => {
+                recipients.insert(pkesk.recipient().as_bytes().to_vec());
+            }
+            Packet::SKESK(_) => (),
+            _ => break,
+        }
+        ppr = pp.next().ok()?.1;
+    }
+
+    Some(recipients)
+}
+
+fn decrypt(
+    ciphertext: &[u8],
+    certs: &[Cert],
+    from: &[String],
+    secret: &Cert,
+) -> Result<(Vec<u8>, Option<PgpSignature>), String> {
+    let mut decryptor = DecryptorBuilder::from_bytes(ciphertext)
+        .and_then(|builder| builder.with_policy(&P, None, Helper::new(certs, from, Some(secret))))
+        .map_err(|err| err.to_string())?;
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    decryptor
+        .read_to_end(&mut plaintext)
+        .map_err(|err| err.to_string())?;
+
+    Ok((plaintext, decryptor.into_helper().signature))
+}
+
+fn decryption_keys(
+    cert: &Cert,
+) -> impl Iterator<Item = ValidErasedKeyAmalgamation<'_, SecretParts>> {
+    cert.keys()
+        .with_policy(&P, None)
+        .supported()
+        .alive()
+        .revoked(false)
+        .unencrypted_secret()
+        .key_flags(
+            KeyFlags::empty()
+                .set_transport_encryption()
+                .set_storage_encryption(),
+        )
+}
+
+/// Obtains the sender's key from the Autocrypt header, only when it was
+/// issued for the From address.
+fn autocrypt_cert(message: &Message<'_>, from: &[String]) -> Option<Cert> {
+    message
+        .root_part()
+        .headers()
+        .iter()
+        .filter(|header| header.name.as_str().eq_ignore_ascii_case("autocrypt"))
+        .filter_map(|header| header.value().as_text())
+        .find_map(|value| {
+            let mut addr = None;
+            let mut keydata = None;
+            for attribute in value.split(';') {
+                if let Some((name, value)) = attribute.split_once('=') {
+                    match name.trim() {
+                        "addr" => addr = Some(value.trim().to_lowercase()),
+                        "keydata" => keydata = Some(value),
+                        _ => (),
+                    }
+                }
+            }
+
+            if from.contains(&addr?) {
+                let keydata = keydata?
+                    .bytes()
+                    .filter(|ch| !ch.is_ascii_whitespace())
+                    .collect::<Vec<_>>();
+                Cert::from_bytes(&base64_decode(&keydata)?).ok()
+            } else {
+                None
+            }
+        })
+}
+
+fn from_addresses(message: &Message<'_>) -> Vec<String> {
+    message
+        .from()
+        .map(|from| {
+            from.iter()
+                .filter_map(|addr| addr.address())
+                .map(|addr| addr.to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn inline_text<'x>(message: &'x Message<'_>) -> Option<&'x [u8]> {
+    let root = message.root_part();
+    if root.content_type().map_or(true, |ct| {
+        ct.c_type.eq_ignore_ascii_case("text")
+            && ct
+                .c_subtype
+                .as_deref()
+                .map_or(true, |st| st.eq_ignore_ascii_case("plain"))
+    }) {
+        Some(root.contents())
+    } else {
+        None
+    }
+}
+
+fn matches_address(userid: &[u8], from: &[String]) -> bool {
+    let userid = String::from_utf8_lossy(userid).to_lowercase();
+    let address = userid
+        .rsplit_once('<')
+        .and_then(|(_, addr)| addr.strip_suffix('>'))
+        .unwrap_or(userid.trim());
+    from.iter().any(|from| from == address)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn push_quoted(header: &mut String, value: &str) {
+    header.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' | '\\' => {
+                header.push('\\');
+                header.push(ch);
+            }
+            '\r' | '\n' => header.push(' '),
+            _ => header.push(ch),
+        }
+    }
+    header.push('"');
+}
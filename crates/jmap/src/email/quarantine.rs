@@ -0,0 +1,532 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{ipc::IngestMessage, Server};
+use jmap_proto::types::{state::StateChange, type_state::DataType};
+use mail_builder::{mime::make_boundary, MessageBuilder};
+use mail_parser::{DateTime, Message, MessageParser};
+use smtp::inbound::spam::parse_spam_score;
+use store::{
+    ahash::AHashMap,
+    blake3,
+    write::{
+        key::DeserializeBigEndian, now, BatchBuilder, Bincode, BlobOp, ReportClass, ValueClass,
+    },
+    Deserialize, IterateParams, Serialize, ValueKey, U64_LEN,
+};
+use trc::{AddContext, SpamEvent};
+use utils::BlobHash;
+
+use crate::{
+    api::{http::ToHttpResponse, HtmlResponse, HttpResponse},
+    blob::{download::BlobDownload, upload::BlobUpload},
+    mailbox::INBOX_ID,
+    services::{ingest::MailDelivery, state::StateManager},
+};
+
+use super::{
+    ingest::{EmailIngest, IngestEmail, IngestSource, IngestedEmail},
+    share::escape_html,
+};
+
+const SIGNATURE_LEN: usize = 32;
+const DIGEST_KEY: &[u8] = b"quarantine-digest";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct QuarantinedMessage {
+    pub account_id: u32,
+    pub sender: String,
+    pub recipient: String,
+    pub from: String,
+    pub subject: String,
+    pub score: f64,
+    pub received: u64,
+    pub blob_hash: BlobHash,
+    pub size: usize,
+}
+
+/// Quarantined message as listed by the management API, `id` is formatted as
+/// `<id>_<expires>`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantineItem {
+    pub id: String,
+    pub account_id: u32,
+    pub sender: String,
+    pub recipient: String,
+    pub from: String,
+    pub subject: String,
+    pub score: f64,
+    pub received: u64,
+    pub expires: u64,
+    pub size: usize,
+}
+
+impl QuarantinedMessage {
+    pub fn into_item(self, id: u64, expires: u64) -> QuarantineItem {
+        QuarantineItem {
+            id: format!("{id}_{expires}"),
+            account_id: self.account_id,
+            sender: self.sender,
+            recipient: self.recipient,
+            from: self.from,
+            subject: self.subject,
+            score: self.score,
+            received: self.received,
+            expires,
+            size: self.size,
+        }
+    }
+}
+
+pub struct QuarantineMessage<'x> {
+    pub account_id: u32,
+    pub sender: &'x str,
+    pub recipient: &'x str,
+    pub message: &'x Message<'x>,
+    pub blob_hash: &'x BlobHash,
+    pub size: usize,
+    pub score: f64,
+    pub session_id: u64,
+}
+
+pub trait EmailQuarantine: Sync + Send {
+    fn email_quarantine(
+        &self,
+        params: QuarantineMessage<'_>,
+    ) -> impl Future<Output = trc::Result<IngestedEmail>> + Send;
+
+    fn quarantine_get(
+        &self,
+        id: u64,
+        expires: u64,
+    ) -> impl Future<Output = trc::Result<Option<QuarantinedMessage>>> + Send;
+
+    fn quarantine_list(
+        &self,
+        account_id: Option<u32>,
+        text: Option<&str>,
+    ) -> impl Future<Output = trc::Result<Vec<QuarantineItem>>> + Send;
+
+    fn quarantine_release(
+        &self,
+        id: u64,
+        expires: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn quarantine_delete(
+        &self,
+        id: u64,
+        expires: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn quarantine_send_digests(&self) -> impl Future<Output = trc::Result<usize>> + Send;
+
+    fn handle_quarantine_request(
+        &self,
+        token: Option<&str>,
+        release: bool,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl EmailQuarantine for Server {
+    async fn email_quarantine(&self, params: QuarantineMessage<'_>) -> trc::Result<IngestedEmail> {
+        let config = self
+            .core
+            .jmap
+            .spam_quarantine
+            .as_ref()
+            .ok_or_else(|| trc::StoreEvent::NotConfigured.into_err())?;
+        let received = now();
+        let expires = received + config.retention;
+        let id = self.inner.data.queue_id_gen.generate().unwrap_or(received);
+        let record = QuarantinedMessage {
+            account_id: params.account_id,
+            sender: params.sender.to_string(),
+            recipient: params.recipient.to_string(),
+            from: params
+                .message
+                .from()
+                .and_then(|from| from.first())
+                .and_then(|from| from.address())
+                .unwrap_or(params.sender)
+                .to_string(),
+            subject: params.message.subject().unwrap_or_default().to_string(),
+            score: params.score,
+            received,
+            blob_hash: params.blob_hash.clone(),
+            size: params.size,
+        };
+
+        // Keep the message blob until the quarantine entry expires
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(params.account_id)
+            .set(
+                BlobOp::Reserve {
+                    hash: params.blob_hash.clone(),
+                    until: expires,
+                },
+                0u32.serialize(),
+            )
+            .set(
+                ValueClass::Report(ReportClass::Quarantine { id, expires }),
+                Bincode::new(record).serialize(),
+            );
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Spam(SpamEvent::Quarantine),
+            SpanId = params.session_id,
+            AccountId = params.account_id,
+            To = params.recipient.to_string(),
+            Id = id,
+            Value = params.score,
+            Limit = config.threshold,
+        );
+
+        // Nothing was added to the mailbox, skip the state change notification
+        Ok(IngestedEmail {
+            change_id: u64::MAX,
+            size: params.size,
+            ..Default::default()
+        })
+    }
+
+    async fn quarantine_get(
+        &self,
+        id: u64,
+        expires: u64,
+    ) -> trc::Result<Option<QuarantinedMessage>> {
+        self.core
+            .storage
+            .data
+            .get_value::<Bincode<QuarantinedMessage>>(ValueKey::from(ValueClass::Report(
+                ReportClass::Quarantine { id, expires },
+            )))
+            .await
+            .map(|record| record.map(|record| record.inner))
+            .caused_by(trc::location!())
+    }
+
+    async fn quarantine_list(
+        &self,
+        account_id: Option<u32>,
+        text: Option<&str>,
+    ) -> trc::Result<Vec<QuarantineItem>> {
+        let text = text.map(|text| text.to_lowercase());
+        let now = now();
+        let mut results = Vec::new();
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                        id: 0,
+                        expires: now,
+                    })),
+                    ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                        id: u64::MAX,
+                        expires: u64::MAX,
+                    })),
+                )
+                .descending(),
+                |key, value| {
+                    let record = Bincode::<QuarantinedMessage>::deserialize(value)
+                        .caused_by(trc::location!())?
+                        .inner;
+
+                    if account_id.map_or(true, |account_id| account_id == record.account_id)
+                        && text.as_ref().map_or(true, |text| {
+                            [
+                                &record.sender,
+                                &record.recipient,
+                                &record.from,
+                                &record.subject,
+                            ]
+                            .iter()
+                            .any(|value| value.to_lowercase().contains(text.as_str()))
+                        })
+                    {
+                        results.push(record.into_item(
+                            key.deserialize_be_u64(U64_LEN + 1)?,
+                            key.deserialize_be_u64(1)?,
+                        ));
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(results)
+    }
+
+    async fn quarantine_release(&self, id: u64, expires: u64) -> trc::Result<bool> {
+        let record = if let Some(record) = self.quarantine_get(id, expires).await? {
+            record
+        } else {
+            return Ok(false);
+        };
+        let raw_message = self
+            .get_blob(&record.blob_hash, 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| {
+                trc::StoreEvent::NotFound
+                    .into_err()
+                    .details("Quarantined message blob not found")
+                    .caused_by(trc::location!())
+            })?;
+        let access_token = self
+            .get_cached_access_token(record.account_id)
+            .await
+            .caused_by(trc::location!())?;
+
+        // Deliver to the Inbox, bypassing the spam header check
+        let ingested = self
+            .email_ingest(IngestEmail {
+                raw_message: &raw_message,
+                message: MessageParser::new().parse(&raw_message),
+                resource: access_token.as_resource_token(),
+                mailbox_ids: vec![INBOX_ID],
+                keywords: vec![],
+                received_at: Some(record.received),
+                source: IngestSource::Jmap,
+                encrypt: self.core.jmap.encrypt,
+                shared_metadata: false,
+                session_id: 0,
+            })
+            .await
+            .caused_by(trc::location!())?;
+        self.broadcast_state_change(
+            StateChange::new(record.account_id)
+                .with_change(DataType::EmailDelivery, ingested.change_id)
+                .with_change(DataType::Email, ingested.change_id)
+                .with_change(DataType::Mailbox, ingested.change_id)
+                .with_change(DataType::Thread, ingested.change_id),
+        )
+        .await;
+
+        self.quarantine_delete(id, expires).await?;
+
+        trc::event!(
+            Spam(SpamEvent::QuarantineRelease),
+            AccountId = record.account_id,
+            Id = id,
+            DocumentId = ingested.id.document_id(),
+        );
+
+        Ok(true)
+    }
+
+    async fn quarantine_delete(&self, id: u64, expires: u64) -> trc::Result<bool> {
+        let record = if let Some(record) = self.quarantine_get(id, expires).await? {
+            record
+        } else {
+            return Ok(false);
+        };
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(record.account_id)
+            .clear(BlobOp::Reserve {
+                hash: record.blob_hash,
+                until: expires,
+            })
+            .clear(ValueClass::Report(ReportClass::Quarantine { id, expires }));
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(true)
+    }
+
+    async fn quarantine_send_digests(&self) -> trc::Result<usize> {
+        let (config, digest) = if let Some((config, digest)) = self
+            .core
+            .jmap
+            .spam_quarantine
+            .as_ref()
+            .and_then(|config| config.digest.as_ref().map(|digest| (config, digest)))
+        {
+            (config, digest)
+        } else {
+            return Ok(0);
+        };
+
+        // Only list messages quarantined since the last digest
+        let lookup = &self.core.storage.lookup;
+        let last_digest = lookup
+            .key_get::<Bincode<u64>>(DIGEST_KEY.to_vec())
+            .await
+            .caused_by(trc::location!())?
+            .map(|last_digest| last_digest.inner)
+            .unwrap_or_default();
+        lookup
+            .key_set(DIGEST_KEY.to_vec(), Bincode::new(now()).serialize(), None)
+            .await
+            .caused_by(trc::location!())?;
+
+        let mut accounts: AHashMap<u32, Vec<QuarantineItem>> = AHashMap::new();
+        for item in self.quarantine_list(None, None).await? {
+            if item.received > last_digest {
+                accounts.entry(item.account_id).or_default().push(item);
+            }
+        }
+
+        let mut total = 0;
+        for (account_id, items) in accounts {
+            let recipient = items[0].recipient.clone();
+            let mut body = format!(
+                concat!(
+                    "The following messages were classified as spam and held in ",
+                    "quarantine. Messages that are not released are deleted after ",
+                    "{} days.\r\n"
+                ),
+                config.retention / 86400
+            );
+            for item in &items {
+                let (id, expires) = parse_quarantine_id(&item.id).unwrap_or_default();
+                body.push_str(&format!(
+                    "\r\nFrom: {}\r\nSubject: {}\r\nDate: {}\r\nRelease: {}/jmap/quarantine/{}\r\n",
+                    item.from,
+                    item.subject,
+                    DateTime::from_timestamp(item.received as i64).to_rfc822(),
+                    digest.url,
+                    release_token(self, id, expires, account_id),
+                ));
+            }
+
+            let message = MessageBuilder::new()
+                .from(digest.from.as_str())
+                .to(recipient.as_str())
+                .message_id(format!("<{}@quarantine>", make_boundary(".")))
+                .subject(digest.subject.as_str())
+                .text_body(body)
+                .write_to_vec()
+                .unwrap_or_default();
+            let blob_id = self
+                .put_blob(account_id, &message, false)
+                .await
+                .caused_by(trc::location!())?;
+            self.deliver_message(IngestMessage {
+                sender_address: digest.from.clone(),
+                recipients: vec![recipient.clone()],
+                message_blob: blob_id.hash,
+                message_size: message.len(),
+                session_id: 0,
+            })
+            .await;
+
+            trc::event!(
+                Spam(SpamEvent::QuarantineDigest),
+                AccountId = account_id,
+                To = recipient,
+                Total = items.len(),
+            );
+            total += 1;
+        }
+
+        Ok(total)
+    }
+
+    async fn handle_quarantine_request(
+        &self,
+        token: Option<&str>,
+        release: bool,
+    ) -> trc::Result<HttpResponse> {
+        let (id, expires, signature) = token
+            .and_then(parse_release_token)
+            .filter(|_| self.core.jmap.spam_quarantine.is_some())
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+        let record = self
+            .quarantine_get(id, expires)
+            .await?
+            .filter(|record| {
+                release_signature(self, id, expires, record.account_id)
+                    .bytes()
+                    .zip(signature.bytes())
+                    .fold(0, |acc, (a, b)| acc | (a ^ b))
+                    == 0
+            })
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+        let mut html = String::with_capacity(512);
+        html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">");
+        html.push_str("<meta name=\"robots\" content=\"noindex, nofollow\">");
+        html.push_str("<title>Quarantine</title></head>\n<body>\n");
+        if release {
+            self.quarantine_release(id, expires).await?;
+            html.push_str("<p>The message has been delivered to your Inbox.</p>\n");
+        } else {
+            // Release links are opened by link scanners, only POST requests
+            // release the message
+            html.push_str("<dl>\n<dt>From</dt><dd>");
+            escape_html(&record.from, &mut html);
+            html.push_str("</dd>\n<dt>Subject</dt><dd>");
+            escape_html(&record.subject, &mut html);
+            html.push_str("</dd>\n</dl>\n<form method=\"post\">");
+            html.push_str("<button type=\"submit\">Release message</button></form>\n");
+        }
+        html.push_str("</body></html>\n");
+
+        Ok(HtmlResponse::new(html).into_http_response())
+    }
+}
+
+/// Returns the spam score of a message from its `X-Spam-Status` header.
+pub fn quarantine_score(message: &Message<'_>) -> Option<f64> {
+    message
+        .root_part()
+        .headers()
+        .iter()
+        .find(|header| header.name.as_str().eq_ignore_ascii_case("X-Spam-Status"))
+        .and_then(|header| header.value().as_text())
+        .and_then(parse_spam_score)
+}
+
+/// Parses a quarantine id formatted as `<id>_<expires>`.
+pub fn parse_quarantine_id(id: &str) -> Option<(u64, u64)> {
+    let (id, expires) = id.split_once('_')?;
+    Some((id.parse().ok()?, expires.parse().ok()?))
+}
+
+/// Builds the signed token used in digest release links.
+pub fn release_token(server: &Server, id: u64, expires: u64, account_id: u32) -> String {
+    format!(
+        "{id}_{expires}_{}",
+        release_signature(server, id, expires, account_id)
+    )
+}
+
+fn parse_release_token(token: &str) -> Option<(u64, u64, &str)> {
+    let (id, signature) = token.rsplit_once('_')?;
+    let (id, expires) = parse_quarantine_id(id)?;
+    (signature.len() == SIGNATURE_LEN).then_some((id, expires, signature))
+}
+
+fn release_signature(server: &Server, id: u64, expires: u64, account_id: u32) -> String {
+    let key = blake3::derive_key("quarantine release", server.core.oauth.oauth_key.as_bytes());
+    let mut hasher = blake3::Hasher::new_keyed(&key);
+    hasher.update(&id.to_be_bytes());
+    hasher.update(&expires.to_be_bytes());
+    hasher.update(&account_id.to_be_bytes());
+    hasher.finalize().to_hex()[..SIGNATURE_LEN].to_string()
+}
@@ -48,6 +48,7 @@ impl EmailQuery for Server {
     ) -> trc::Result<QueryResponse> {
         let account_id = request.account_id.document_id();
         let mut filters = Vec::with_capacity(request.filter.len());
+        let mut fts_degraded = false;
 
         for cond_group in std::mem::take(&mut request.filter).into_filter_group() {
             match cond_group {
@@ -180,10 +181,11 @@ impl EmailQuery for Server {
                             }
                         }
                     }
-                    filters.push(query::Filter::is_in_set(
-                        self.fts_filter(account_id, Collection::Email, fts_filters)
-                            .await?,
-                    ));
+                    let result = self
+                        .fts_filter(account_id, Collection::Email, fts_filters)
+                        .await?;
+                    fts_degraded |= result.degraded;
+                    filters.push(query::Filter::is_in_set(result.documents));
                 }
                 FilterGroup::Store(cond) => {
                     match cond {
@@ -289,7 +291,11 @@ impl EmailQuery for Server {
                     .await?,
             );
         }
-        let (response, paginate) = self.build_query_response(&result_set, &request).await?;
+        let (mut response, paginate) = self.build_query_response(&result_set, &request).await?;
+        if fts_degraded {
+            // Results were obtained from message metadata
+            response.full_text_degraded = Some(true);
+        }
 
         if let Some(paginate) = paginate {
             // Parse sort criteria
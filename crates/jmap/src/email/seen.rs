@@ -0,0 +1,147 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use jmap_proto::types::{collection::Collection, keyword::Keyword, property::Property};
+use store::{
+    roaring::RoaringBitmap,
+    write::{
+        assert::{AssertValue, HashedValue},
+        BatchBuilder, Bincode, ValueClass, F_CLEAR, F_VALUE,
+    },
+};
+
+use crate::JmapMethods;
+
+/// Users that have marked a message as seen.
+pub type SeenBy = HashedValue<Bincode<Vec<u32>>>;
+
+// Messages in shared accounts can keep the \Seen state of each user apart
+// from the keywords of the message. Every user has a bitmap with the messages
+// they have seen, and each message lists the users that have seen it so their
+// bitmaps can be cleaned up once the message is purged.
+pub trait PrivateSeen: Sync + Send {
+    /// Returns whether a user keeps their own \Seen state on the messages of
+    /// an account.
+    fn has_private_seen(&self, account_id: u32, user_id: u32) -> bool;
+
+    /// Returns the messages of an account that have been seen by a user.
+    fn private_seen_ids(
+        &self,
+        account_id: u32,
+        user_id: u32,
+    ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
+
+    /// Returns the users that have seen a message.
+    fn seen_by(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<SeenBy>>> + Send;
+
+    /// Returns the messages of a mailbox that are included in `unseen_ids`.
+    fn mailbox_private_unseen(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        unseen_ids: &RoaringBitmap,
+    ) -> impl Future<Output = trc::Result<Option<RoaringBitmap>>> + Send;
+}
+
+impl PrivateSeen for Server {
+    fn has_private_seen(&self, account_id: u32, user_id: u32) -> bool {
+        account_id != user_id && self.core.imap.shared_private_seen
+    }
+
+    async fn private_seen_ids(&self, account_id: u32, user_id: u32) -> trc::Result<RoaringBitmap> {
+        self.get_tag(account_id, Collection::Email, Property::SeenBy, user_id)
+            .await
+            .map(|ids| ids.unwrap_or_default())
+    }
+
+    async fn seen_by(&self, account_id: u32, document_id: u32) -> trc::Result<Option<SeenBy>> {
+        self.get_property::<SeenBy>(account_id, Collection::Email, document_id, Property::SeenBy)
+            .await
+    }
+
+    async fn mailbox_private_unseen(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        unseen_ids: &RoaringBitmap,
+    ) -> trc::Result<Option<RoaringBitmap>> {
+        self.get_tag(
+            account_id,
+            Collection::Email,
+            Property::MailboxIds,
+            mailbox_id,
+        )
+        .await
+        .map(|ids| {
+            ids.map(|ids| ids & unseen_ids)
+                .filter(|ids| !ids.is_empty())
+        })
+    }
+}
+
+pub trait PrivateSeenBatch {
+    /// Marks a message as seen or unseen by a user, returns `false` if the
+    /// message was already in that state.
+    fn private_seen(&mut self, seen_by: Option<SeenBy>, user_id: u32, seen: bool) -> bool;
+
+    /// Removes the \Seen state of all users from a message.
+    fn clear_private_seen(&mut self, seen_by: Vec<u32>) -> &mut Self;
+}
+
+impl PrivateSeenBatch for BatchBuilder {
+    fn private_seen(&mut self, seen_by: Option<SeenBy>, user_id: u32, seen: bool) -> bool {
+        let (mut users, assert_value) = match seen_by {
+            Some(seen_by) => (seen_by.inner.inner, AssertValue::Hash(seen_by.hash)),
+            None => (Vec::new(), AssertValue::None),
+        };
+        if users.contains(&user_id) == seen {
+            return false;
+        }
+
+        if seen {
+            users.push(user_id);
+        } else {
+            users.retain(|id| *id != user_id);
+        }
+
+        self.assert_value(ValueClass::Property(Property::SeenBy.into()), assert_value);
+        if !users.is_empty() {
+            self.value(Property::SeenBy, Bincode::new(users), F_VALUE);
+        } else {
+            self.value(Property::SeenBy, (), F_VALUE | F_CLEAR);
+        }
+        self.tag(Property::SeenBy, user_id, if seen { 0 } else { F_CLEAR });
+
+        true
+    }
+
+    fn clear_private_seen(&mut self, seen_by: Vec<u32>) -> &mut Self {
+        for user_id in &seen_by {
+            self.tag(Property::SeenBy, *user_id, F_CLEAR);
+        }
+        self.value(Property::SeenBy, (), F_VALUE | F_CLEAR)
+    }
+}
+
+/// Returns the keywords with the \Seen flag replaced by the given state.
+pub fn replace_seen(keywords: &[Keyword], is_seen: bool) -> Vec<Keyword> {
+    let mut keywords = keywords
+        .iter()
+        .filter(|keyword| *keyword != &Keyword::Seen)
+        .cloned()
+        .collect::<Vec<_>>();
+    if is_seen {
+        keywords.push(Keyword::Seen);
+    }
+    keywords
+}
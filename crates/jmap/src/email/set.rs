@@ -46,7 +46,7 @@ use crate::{
     auth::acl::AclMethods,
     blob::download::BlobDownload,
     changes::{state::StateManager, write::ChangeLog},
-    mailbox::{set::MailboxSet, UidMailbox},
+    mailbox::{set::MailboxSet, UidMailbox, JUNK_ID, TRASH_ID},
     JmapMethods,
 };
 use std::future::Future;
@@ -54,7 +54,10 @@ use std::future::Future;
 use super::{
     delete::EmailDeletion,
     headers::{BuildHeader, ValueToHeader},
+    index::SaveDate,
     ingest::{EmailIngest, IngestEmail, IngestSource},
+    seen::{replace_seen, PrivateSeen, PrivateSeenBatch},
+    train::EmailSpamTrain,
 };
 
 pub trait EmailSet: Sync + Send {
@@ -739,6 +742,7 @@ impl EmailSet for Server {
                     received_at,
                     source: IngestSource::Jmap,
                     encrypt: self.core.jmap.encrypt && self.core.jmap.encrypt_append,
+                    shared_metadata: false,
                     session_id: session.session_id,
                 })
                 .await
@@ -759,6 +763,9 @@ impl EmailSet for Server {
 
         // Process updates
         let mut changes = ChangeLogBuilder::new();
+        let mut spam_ids = Vec::new();
+        let mut ham_ids = Vec::new();
+        let is_private_seen = self.has_private_seen(account_id, access_token.primary_id());
         'update: for (id, object) in request.unwrap_update() {
             // Make sure id won't be destroyed
             if will_destroy.contains(&id) {
@@ -795,6 +802,7 @@ impl EmailSet for Server {
             batch
                 .with_account_id(account_id)
                 .with_collection(Collection::Email);
+            let mut set_seen = None;
 
             for (property, value) in object.properties {
                 let value = match response.eval_object_references(value) {
@@ -825,20 +833,32 @@ impl EmailSet for Server {
                         }
                     }
                     (Property::Keywords, MaybePatchValue::Value(Value::List(keywords_))) => {
-                        keywords.set(
-                            keywords_
-                                .into_iter()
-                                .filter_map(|keyword| keyword.try_unwrap_keyword())
-                                .collect(),
-                        );
+                        let keywords_ = keywords_
+                            .into_iter()
+                            .filter_map(|keyword| keyword.try_unwrap_keyword())
+                            .collect::<Vec<_>>();
+
+                        // The \Seen state of this user is kept apart from the keywords
+                        if is_private_seen {
+                            set_seen = Some(keywords_.contains(&Keyword::Seen));
+                            keywords.set(replace_seen(
+                                &keywords_,
+                                keywords.current().contains(&Keyword::Seen),
+                            ));
+                        } else {
+                            keywords.set(keywords_);
+                        }
                     }
                     (Property::Keywords, MaybePatchValue::Patch(patch)) => {
                         let mut patch = patch.into_iter();
                         if let Some(keyword) = patch.next().unwrap().try_unwrap_keyword() {
-                            keywords.update(
-                                keyword,
-                                patch.next().unwrap().try_unwrap_bool().unwrap_or_default(),
-                            );
+                            let is_set =
+                                patch.next().unwrap().try_unwrap_bool().unwrap_or_default();
+                            if is_private_seen && keyword == Keyword::Seen {
+                                set_seen = Some(is_set);
+                            } else {
+                                keywords.update(keyword, is_set);
+                            }
                         }
                     }
                     (property, _) => {
@@ -848,7 +868,7 @@ impl EmailSet for Server {
                 }
             }
 
-            if !mailboxes.has_changes() && !keywords.has_changes() {
+            if !mailboxes.has_changes() && !keywords.has_changes() && set_seen.is_none() {
                 response.not_updated.append(
                     id,
                     SetError::invalid_properties()
@@ -860,20 +880,24 @@ impl EmailSet for Server {
             // Log change
             batch.update_document(document_id);
             let mut changed_mailboxes = AHashSet::new();
+            let mut is_spam = None;
             changes.log_update(Collection::Email, id);
 
-            // Process keywords
-            if keywords.has_changes() {
-                // Verify permissions on shared accounts
-                if matches!(&can_modify_message_ids, Some(ids) if !ids.contains(document_id)) {
-                    response.not_updated.append(
-                        id,
-                        SetError::forbidden()
-                            .with_description("You are not allowed to modify keywords."),
-                    );
-                    continue 'update;
-                }
+            // Verify permissions on shared accounts
+            if (keywords.has_changes() || set_seen.is_some())
+                && matches!(&can_modify_message_ids, Some(ids) if !ids.contains(document_id))
+            {
+                response.not_updated.append(
+                    id,
+                    SetError::forbidden()
+                        .with_description("You are not allowed to modify keywords."),
+                );
+                continue 'update;
+            }
 
+            // Process keywords
+            let keywords_changed = keywords.has_changes();
+            if keywords_changed {
                 // Set all current mailboxes as changed if the Seen tag changed
                 if keywords
                     .changed_tags()
@@ -886,8 +910,22 @@ impl EmailSet for Server {
 
                 // Update keywords property
                 keywords.update_batch(&mut batch, Property::Keywords);
+            }
+
+            // Process the \Seen state of this user
+            let mut private_seen_changed = false;
+            if let Some(set_seen) = set_seen {
+                let seen_by = self.seen_by(account_id, document_id).await?;
+                if batch.private_seen(seen_by, access_token.primary_id(), set_seen) {
+                    for mailbox_id in mailboxes.current() {
+                        changed_mailboxes.insert(mailbox_id.mailbox_id);
+                    }
+                    private_seen_changed = true;
+                }
+            }
 
-                // Update last change id
+            // Update last change id
+            if keywords_changed || private_seen_changed {
                 if changes.change_id == u64::MAX {
                     changes.change_id = self.assign_change_id(account_id).await?;
                 }
@@ -956,6 +994,15 @@ impl EmailSet for Server {
                     }
                 }
 
+                // Messages moved into or out of Junk are used to train the spam filter
+                if mailboxes.added().iter().any(|m| m.mailbox_id == JUNK_ID) {
+                    is_spam = Some(true);
+                } else if mailboxes.removed().iter().any(|m| m.mailbox_id == JUNK_ID)
+                    && !mailboxes.added().iter().any(|m| m.mailbox_id == TRASH_ID)
+                {
+                    is_spam = Some(false);
+                }
+
                 // Obtain IMAP UIDs for added mailboxes
                 for uid_mailbox in mailboxes.inner_tags_mut() {
                     if uid_mailbox.uid == 0 {
@@ -966,6 +1013,20 @@ impl EmailSet for Server {
                     }
                 }
 
+                // Messages added to a mailbox get a new save date
+                if !mailboxes.added().is_empty() {
+                    batch.save_date(
+                        self.get_property::<u64>(
+                            account_id,
+                            Collection::Email,
+                            document_id,
+                            Property::SavedAt,
+                        )
+                        .await
+                        .caused_by(trc::location!())?,
+                    );
+                }
+
                 // Update mailboxIds property
                 mailboxes.update_batch(&mut batch, Property::MailboxIds);
             }
@@ -981,6 +1042,12 @@ impl EmailSet for Server {
                     Ok(_) => {
                         // Add to updated list
                         response.updated.append(id, None);
+
+                        match is_spam {
+                            Some(true) => spam_ids.push(document_id),
+                            Some(false) => ham_ids.push(document_id),
+                            None => (),
+                        }
                     }
                     Err(err) if err.is_assertion_failure() => {
                         response.not_updated.append(
@@ -997,6 +1064,10 @@ impl EmailSet for Server {
             }
         }
 
+        // Train spam filter
+        self.email_spam_feedback(account_id, spam_ids, true);
+        self.email_spam_feedback(account_id, ham_ids, false);
+
         // Process deletions
         if !will_destroy.is_empty() {
             let email_ids = self
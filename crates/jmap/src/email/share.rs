@@ -0,0 +1,557 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use jmap_proto::{
+    method::share::{ShareEmailRequest, ShareEmailResponse, ShareLink},
+    types::{acl::Acl, collection::Collection, date::UTCDate, property::Property},
+};
+use mail_parser::{Address, Message, MessageParser, MimeHeaders};
+use std::future::Future;
+use store::{
+    blake3,
+    rand::{distributions::Alphanumeric, thread_rng, Rng},
+    write::{
+        assert::{AssertValue, HashedValue},
+        now, BatchBuilder, Bincode, ValueClass, F_CLEAR, F_VALUE,
+    },
+    Serialize,
+};
+use trc::{AddContext, JmapEvent};
+use utils::BlobHash;
+
+use crate::{
+    api::{
+        http::{HttpSessionData, ToHttpResponse},
+        HtmlResponse, HttpResponse,
+    },
+    auth::acl::AclMethods,
+    blob::{download::BlobDownload, DownloadResponse},
+    JmapMethods,
+};
+
+use super::metadata::MessageMetadata;
+
+const LINK_ID_LEN: usize = 16;
+const SIGNATURE_LEN: usize = 32;
+const VIEW_TTL: u64 = 3600;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ShareLinkRecord {
+    pub account_id: u32,
+    pub document_id: u32,
+    pub blob_hash: BlobHash,
+    pub expires: u64,
+    pub created_by: u32,
+}
+
+/// Links created for a message, removed along with the message.
+pub type ShareLinks = HashedValue<Bincode<Vec<String>>>;
+
+pub trait EmailShare: Sync + Send {
+    fn email_share(
+        &self,
+        request: ShareEmailRequest,
+        access_token: &AccessToken,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<ShareEmailResponse>> + Send;
+
+    fn share_link_get(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = trc::Result<Option<(String, ShareLinkRecord)>>> + Send;
+
+    fn handle_share_request(
+        &self,
+        token: Option<&str>,
+        view_id: Option<&str>,
+        attachment: Option<&str>,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn share_links_update(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        link_id: &str,
+        add: bool,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn share_links_delete(
+        &self,
+        link_ids: &[String],
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl EmailShare for Server {
+    async fn email_share(
+        &self,
+        request: ShareEmailRequest,
+        access_token: &AccessToken,
+        session: &HttpSessionData,
+    ) -> trc::Result<ShareEmailResponse> {
+        if request.email_ids.len() > self.core.jmap.set_max_objects {
+            return Err(trc::JmapEvent::RequestTooLarge.into_err());
+        }
+
+        let account_id = request.account_id.document_id();
+        let mut response = ShareEmailResponse {
+            account_id: request.account_id,
+            ..Default::default()
+        };
+
+        // Create links
+        if !request.email_ids.is_empty() {
+            let message_ids = self
+                .owned_or_shared_messages(access_token, account_id, Acl::ReadItems)
+                .await?;
+            let expires_in = request
+                .expires_in
+                .filter(|expires_in| *expires_in > 0)
+                .unwrap_or(self.core.jmap.mail_share_expiry)
+                .min(self.core.jmap.mail_share_max_expiry);
+            let expires = now() + expires_in;
+            let base_url = session.resolve_response_url(self).await;
+
+            for id in request.email_ids {
+                if !message_ids.contains(id.document_id()) {
+                    response.not_found.push(id);
+                    continue;
+                }
+
+                let blob_hash = match self
+                    .get_property::<Bincode<MessageMetadata>>(
+                        account_id,
+                        Collection::Email,
+                        id.document_id(),
+                        &Property::BodyStructure,
+                    )
+                    .await?
+                {
+                    Some(metadata) => metadata.inner.blob_hash,
+                    None => {
+                        response.not_found.push(id);
+                        continue;
+                    }
+                };
+                let link_id = thread_rng()
+                    .sample_iter(Alphanumeric)
+                    .take(LINK_ID_LEN)
+                    .map(char::from)
+                    .collect::<String>();
+                let record = ShareLinkRecord {
+                    account_id,
+                    document_id: id.document_id(),
+                    blob_hash,
+                    expires,
+                    created_by: access_token.primary_id(),
+                };
+                let token = format!("{link_id}{}", share_signature(self, &link_id, &record));
+
+                self.core
+                    .storage
+                    .lookup
+                    .key_set(
+                        format!("share:{link_id}").into_bytes(),
+                        Bincode::new(record).serialize(),
+                        expires_in.into(),
+                    )
+                    .await?;
+                self.share_links_update(account_id, id.document_id(), &link_id, true)
+                    .await?;
+
+                response.created.append(
+                    id,
+                    ShareLink {
+                        id: link_id,
+                        url: format!("{base_url}/jmap/share/{token}"),
+                        expires: UTCDate::from_timestamp(expires as i64),
+                    },
+                );
+            }
+        }
+
+        // Revoke links, only their creator or the account owner can do so
+        for link_id in request.revoke {
+            match self
+                .core
+                .storage
+                .lookup
+                .key_get::<Bincode<ShareLinkRecord>>(format!("share:{link_id}").into_bytes())
+                .await?
+                .map(|record| record.inner)
+            {
+                Some(record)
+                    if record.account_id == account_id
+                        && (record.created_by == access_token.primary_id()
+                            || access_token.is_member(account_id)) =>
+                {
+                    self.share_links_delete(std::slice::from_ref(&link_id))
+                        .await?;
+                    self.share_links_update(account_id, record.document_id, &link_id, false)
+                        .await?;
+                    response.revoked.push(link_id);
+                }
+                _ => {
+                    response.not_revoked.push(link_id);
+                }
+            }
+        }
+
+        Ok(response)
+    }
+
+    async fn share_link_get(&self, token: &str) -> trc::Result<Option<(String, ShareLinkRecord)>> {
+        if token.len() != LINK_ID_LEN + SIGNATURE_LEN || !token.is_ascii() {
+            return Ok(None);
+        }
+        let (link_id, signature) = token.split_at(LINK_ID_LEN);
+
+        // Links are removed from the lookup store once expired or revoked
+        if let Some(record) = self
+            .core
+            .storage
+            .lookup
+            .key_get::<Bincode<ShareLinkRecord>>(format!("share:{link_id}").into_bytes())
+            .await?
+            .map(|record| record.inner)
+        {
+            let expected = share_signature(self, link_id, &record);
+            if record.expires > now()
+                && expected
+                    .bytes()
+                    .zip(signature.bytes())
+                    .fold(0, |acc, (a, b)| acc | (a ^ b))
+                    == 0
+            {
+                return Ok(Some((link_id.to_string(), record)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn handle_share_request(
+        &self,
+        token: Option<&str>,
+        view_id: Option<&str>,
+        attachment: Option<&str>,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        let (link_id, record) = self
+            .share_link_get(token.unwrap_or_default())
+            .await?
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+        let lookup = &self.core.storage.lookup;
+
+        // Attachments can only be downloaded from the page rendered when the
+        // link was consumed, which carries a view id known only to its viewer
+        if let Some(view_id) = view_id {
+            let expected = lookup
+                .key_get::<String>(format!("share-view:{link_id}").into_bytes())
+                .await?
+                .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+            if expected.len() != view_id.len()
+                || expected
+                    .bytes()
+                    .zip(view_id.bytes())
+                    .fold(0, |acc, (a, b)| acc | (a ^ b))
+                    != 0
+                || attachment.is_none()
+            {
+                return Err(trc::ResourceEvent::NotFound.into_err());
+            }
+        } else if attachment.is_some() {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        // Fetch message
+        let metadata = self
+            .get_property::<Bincode<MessageMetadata>>(
+                record.account_id,
+                Collection::Email,
+                record.document_id,
+                &Property::BodyStructure,
+            )
+            .await?
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?
+            .inner;
+        if metadata.blob_hash != record.blob_hash {
+            // The document id has been reused by another message
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+        let raw_message = self
+            .get_blob(&metadata.blob_hash, 0..usize::MAX)
+            .await?
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+        let message = MessageParser::new()
+            .parse(&raw_message)
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+        if let Some(attachment) = attachment {
+            trc::event!(
+                Jmap(JmapEvent::ShareAccess),
+                SpanId = session.session_id,
+                AccountId = record.account_id,
+                DocumentId = record.document_id,
+                Id = link_id,
+                RemoteIp = session.remote_ip,
+                Details = attachment.to_string(),
+            );
+
+            let part = attachment
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| message.attachments.get(idx))
+                .and_then(|part_id| message.parts.get(*part_id))
+                .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+            let mut response = DownloadResponse {
+                filename: part.attachment_name().unwrap_or("attachment").to_string(),
+                content_type: part
+                    .content_type()
+                    .map(|ct| match &ct.c_subtype {
+                        Some(subtype) => format!("{}/{}", ct.c_type, subtype),
+                        None => ct.c_type.to_string(),
+                    })
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+                blob: part.contents().to_vec(),
+            }
+            .into_http_response();
+            response.cache_control = "no-store".into();
+
+            Ok(response)
+        } else {
+            // Links are one-shot, the first view consumes them
+            let accesses = lookup
+                .counter_incr(
+                    format!("share-access:{link_id}").into_bytes(),
+                    1,
+                    record.expires.saturating_sub(now()).into(),
+                    true,
+                )
+                .await?;
+            trc::event!(
+                Jmap(JmapEvent::ShareAccess),
+                SpanId = session.session_id,
+                AccountId = record.account_id,
+                DocumentId = record.document_id,
+                Id = link_id.clone(),
+                RemoteIp = session.remote_ip,
+                Total = accesses,
+            );
+            if accesses != 1 {
+                return Err(trc::ResourceEvent::NotFound.into_err());
+            }
+
+            let view_id = thread_rng()
+                .sample_iter(Alphanumeric)
+                .take(LINK_ID_LEN)
+                .map(char::from)
+                .collect::<String>();
+            lookup
+                .key_set(
+                    format!("share-view:{link_id}").into_bytes(),
+                    view_id.clone().into_bytes(),
+                    record.expires.saturating_sub(now()).min(VIEW_TTL).into(),
+                )
+                .await?;
+
+            let mut response = HtmlResponse::new(render_shared_message(
+                &message,
+                &format!("{}/{view_id}", token.unwrap_or_default()),
+            ))
+            .into_http_response();
+            response.cache_control = "no-store".into();
+
+            Ok(response)
+        }
+    }
+
+    async fn share_links_update(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        link_id: &str,
+        add: bool,
+    ) -> trc::Result<()> {
+        let mut try_count = 0;
+        loop {
+            let (mut links, assert_value) = match self
+                .get_property::<ShareLinks>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::ShareLinks,
+                )
+                .await?
+            {
+                Some(links) => (links.inner.inner, AssertValue::Hash(links.hash)),
+                None => (Vec::new(), AssertValue::None),
+            };
+            if add {
+                links.push(link_id.to_string());
+            } else if links.iter().any(|id| id == link_id) {
+                links.retain(|id| id != link_id);
+            } else {
+                return Ok(());
+            }
+
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Email)
+                .update_document(document_id)
+                .assert_value(
+                    ValueClass::Property(Property::ShareLinks.into()),
+                    assert_value,
+                );
+            if !links.is_empty() {
+                batch.value(Property::ShareLinks, Bincode::new(links), F_VALUE);
+            } else {
+                batch.value(Property::ShareLinks, (), F_VALUE | F_CLEAR);
+            }
+
+            match self.core.storage.data.write(batch.build()).await {
+                Ok(_) => return Ok(()),
+                Err(err) if err.is_assertion_failure() && try_count < 3 => {
+                    try_count += 1;
+                }
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+        }
+    }
+
+    async fn share_links_delete(&self, link_ids: &[String]) -> trc::Result<()> {
+        let lookup = &self.core.storage.lookup;
+        for link_id in link_ids {
+            lookup
+                .key_delete(format!("share:{link_id}").into_bytes())
+                .await
+                .caused_by(trc::location!())?;
+            lookup
+                .counter_delete(format!("share-access:{link_id}").into_bytes())
+                .await
+                .caused_by(trc::location!())?;
+            lookup
+                .key_delete(format!("share-view:{link_id}").into_bytes())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn share_signature(server: &Server, link_id: &str, record: &ShareLinkRecord) -> String {
+    let key = blake3::derive_key("mail share link", server.core.oauth.oauth_key.as_bytes());
+    let mut hasher = blake3::Hasher::new_keyed(&key);
+    hasher.update(link_id.as_bytes());
+    hasher.update(&record.account_id.to_be_bytes());
+    hasher.update(&record.document_id.to_be_bytes());
+    hasher.update(record.blob_hash.as_slice());
+    hasher.update(&record.expires.to_be_bytes());
+    hasher.finalize().to_hex()[..SIGNATURE_LEN].to_string()
+}
+
+/// Renders a message as a standalone HTML page. Message contents are always
+/// rendered as escaped text, active content is never passed through.
+pub fn render_shared_message(message: &Message<'_>, token: &str) -> String {
+    render_message_html(message, Some(token))
+}
+
+/// Renders the message headers, text body and attachment list as HTML, linking
+/// attachments relative to `attachment_base` when provided.
+pub fn render_message_html(message: &Message<'_>, attachment_base: Option<&str>) -> String {
+    let mut html = String::with_capacity(1024);
+    let subject = message.subject().unwrap_or_default();
+
+    html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">");
+    html.push_str("<meta name=\"robots\" content=\"noindex, nofollow\"><title>");
+    escape_html(subject, &mut html);
+    html.push_str("</title></head>\n<body>\n<h1>");
+    escape_html(subject, &mut html);
+    html.push_str("</h1>\n<dl>\n");
+    for (name, value) in message_headers(message) {
+        html.push_str("<dt>");
+        html.push_str(name);
+        html.push_str("</dt><dd>");
+        escape_html(&value, &mut html);
+        html.push_str("</dd>\n");
+    }
+    html.push_str("</dl>\n<pre style=\"white-space: pre-wrap\">");
+    escape_html(&message.body_text(0).unwrap_or_default(), &mut html);
+    html.push_str("</pre>\n");
+
+    if !message.attachments.is_empty() {
+        html.push_str("<ul>\n");
+        for (idx, part) in message
+            .attachments
+            .iter()
+            .filter_map(|part_id| message.parts.get(*part_id))
+            .enumerate()
+        {
+            if let Some(base) = attachment_base {
+                html.push_str(&format!("<li><a href=\"{base}/{idx}\">"));
+                escape_html(part.attachment_name().unwrap_or("attachment"), &mut html);
+                html.push_str("</a>");
+            } else {
+                html.push_str("<li>");
+                escape_html(part.attachment_name().unwrap_or("attachment"), &mut html);
+            }
+            html.push_str(&format!(" ({} bytes)</li>\n", part.contents().len()));
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body></html>\n");
+
+    html
+}
+
+/// Returns the non-empty address and date headers of a message.
+pub fn message_headers(message: &Message<'_>) -> Vec<(&'static str, String)> {
+    [
+        ("From", format_addresses(message.from())),
+        ("To", format_addresses(message.to())),
+        ("Cc", format_addresses(message.cc())),
+        (
+            "Date",
+            message.date().map(|dt| dt.to_rfc822()).unwrap_or_default(),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .collect()
+}
+
+fn format_addresses(address: Option<&Address<'_>>) -> String {
+    address
+        .map(|address| {
+            address
+                .iter()
+                .map(|addr| match (addr.name(), addr.address()) {
+                    (Some(name), Some(address)) => format!("{name} <{address}>"),
+                    (Some(value), None) | (None, Some(value)) => value.to_string(),
+                    (None, None) => String::new(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default()
+}
+
+pub fn escape_html(text: &str, html: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(ch),
+        }
+    }
+}
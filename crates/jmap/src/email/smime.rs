@@ -0,0 +1,567 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::borrow::Cow;
+
+use mail_parser::{Message, MimeHeaders, PartType};
+use rasn::types::OctetString;
+use rasn_cms::{
+    CertificateChoices, ContentInfo, SignedData, SignerIdentifier, SignerInfo,
+    CONTENT_ENVELOPED_DATA, CONTENT_SIGNED_DATA,
+};
+use rsa::{pkcs1::DecodeRsaPublicKey, Pkcs1v15Sign, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use x509_parser::{
+    certificate::X509Certificate,
+    der_parser::asn1_rs::FromDer,
+    extensions::{GeneralName, ParsedExtension},
+};
+
+const MAX_CHAIN_LENGTH: usize = 8;
+const OID_MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+const OID_RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+
+/// Outcome of verifying an S/MIME message at delivery time, as exposed by
+/// the `smimeStatus`, `smimeErrors` and `smimeVerifiedAt` properties of
+/// RFC 9219.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SmimeVerification {
+    pub status: SmimeStatus,
+    pub errors: Vec<String>,
+    pub verified_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SmimeStatus {
+    Unknown,
+    Signed,
+    SignedVerified,
+    SignedFailed,
+    Encrypted,
+}
+
+pub trait VerifySmime {
+    /// Returns `None` if the message is not an S/MIME message.
+    fn verify_smime(&self, trust_anchors: &[Vec<u8>], now: u64) -> Option<SmimeVerification>;
+}
+
+enum SignerResult {
+    Verified,
+    Failed(String),
+    Unsupported(String),
+}
+
+#[derive(Clone, Copy)]
+enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+struct Verifier<'x> {
+    trust_anchors: Vec<X509Certificate<'x>>,
+    from: Vec<String>,
+    now: i64,
+}
+
+impl VerifySmime for Message<'_> {
+    fn verify_smime(&self, trust_anchors: &[Vec<u8>], now: u64) -> Option<SmimeVerification> {
+        let root = self.root_part();
+        let content_type = root.content_type()?;
+        let main_type = content_type.c_type.as_ref();
+        let sub_type = content_type.c_subtype.as_deref().unwrap_or_default();
+
+        let verifier = Verifier {
+            trust_anchors: trust_anchors
+                .iter()
+                .filter_map(|cert| X509Certificate::from_der(cert).ok().map(|(_, cert)| cert))
+                .collect(),
+            from: self
+                .from()
+                .map(|from| {
+                    from.iter()
+                        .filter_map(|addr| addr.address())
+                        .map(|addr| addr.to_lowercase())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            now: now as i64,
+        };
+
+        let (status, errors) = if main_type.eq_ignore_ascii_case("multipart")
+            && sub_type.eq_ignore_ascii_case("signed")
+        {
+            if !content_type
+                .attribute("protocol")
+                .map_or(false, is_pkcs7_signature)
+            {
+                return None;
+            }
+
+            // The signed entity is the first part and the signature the second one
+            match &root.body {
+                PartType::Multipart(parts) if parts.len() == 2 => {
+                    let signed_part = &self.parts[parts[0]];
+                    let signed_entity = self
+                        .raw_message()
+                        .get(signed_part.offset_header..signed_part.offset_end)
+                        .unwrap_or_default();
+
+                    verifier.verify(
+                        self.parts[parts[1]].contents(),
+                        Some(detached_candidates(signed_entity)),
+                    )
+                }
+                _ => (
+                    SmimeStatus::SignedFailed,
+                    vec!["Malformed multipart/signed message".to_string()],
+                ),
+            }
+        } else if main_type.eq_ignore_ascii_case("application")
+            && (sub_type.eq_ignore_ascii_case("pkcs7-mime")
+                || sub_type.eq_ignore_ascii_case("x-pkcs7-mime"))
+        {
+            match content_type
+                .attribute("smime-type")
+                .map(|t| t.to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("enveloped-data" | "authenveloped-data") => {
+                    (SmimeStatus::Encrypted, Vec::new())
+                }
+                Some("signed-data") | None => {
+                    let contents = root.contents();
+                    match rasn::der::decode::<ContentInfo>(contents) {
+                        Ok(info) if &*info.content_type == CONTENT_SIGNED_DATA => {
+                            verifier.verify(contents, None)
+                        }
+                        Ok(info) if &*info.content_type == CONTENT_ENVELOPED_DATA => {
+                            (SmimeStatus::Encrypted, Vec::new())
+                        }
+                        Ok(_) => (SmimeStatus::Unknown, Vec::new()),
+                        Err(_) => (
+                            SmimeStatus::SignedFailed,
+                            vec!["Failed to decode PKCS#7 structure".to_string()],
+                        ),
+                    }
+                }
+                _ => (SmimeStatus::Unknown, Vec::new()),
+            }
+        } else {
+            return None;
+        };
+
+        Some(SmimeVerification {
+            status,
+            errors,
+            verified_at: now,
+        })
+    }
+}
+
+impl Verifier<'_> {
+    fn verify(
+        &self,
+        pkcs7: &[u8],
+        detached: Option<Vec<Cow<'_, [u8]>>>,
+    ) -> (SmimeStatus, Vec<String>) {
+        let signed_data = match rasn::der::decode::<ContentInfo>(pkcs7)
+            .ok()
+            .filter(|info| &*info.content_type == CONTENT_SIGNED_DATA)
+            .and_then(|info| rasn::der::decode::<SignedData>(info.content.as_bytes()).ok())
+        {
+            Some(signed_data) => signed_data,
+            None => {
+                return (
+                    SmimeStatus::SignedFailed,
+                    vec!["Failed to decode PKCS#7 signature".to_string()],
+                );
+            }
+        };
+
+        // Opaque signatures carry the signed content
+        let candidates = match detached {
+            Some(candidates) => candidates,
+            None => match &signed_data.encap_content_info.content {
+                Some(content) => vec![Cow::Borrowed(content.as_ref())],
+                None => {
+                    return (
+                        SmimeStatus::SignedFailed,
+                        vec!["Signature does not contain any content".to_string()],
+                    );
+                }
+            },
+        };
+
+        // Certificates bundled with the signature
+        let certificates = signed_data
+            .certificates
+            .iter()
+            .flatten()
+            .filter_map(|cert| match cert {
+                CertificateChoices::Certificate(cert) => rasn::der::encode(cert.as_ref())
+                    .ok()
+                    .map(|der| (cert.as_ref(), der)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let intermediates = certificates
+            .iter()
+            .filter_map(|(_, der)| X509Certificate::from_der(der).ok().map(|(_, cert)| cert))
+            .collect::<Vec<_>>();
+
+        let mut errors = Vec::new();
+        let mut is_failed = false;
+        for signer in &signed_data.signer_infos {
+            let signer_cert = certificates
+                .iter()
+                .find(|(cert, _)| match &signer.sid {
+                    SignerIdentifier::IssuerAndSerialNumber(sid) => {
+                        cert.tbs_certificate.issuer == sid.issuer
+                            && cert.tbs_certificate.serial_number == sid.serial_number
+                    }
+                    SignerIdentifier::SubjectKeyIdentifier(sid) => cert
+                        .tbs_certificate
+                        .extensions
+                        .iter()
+                        .flat_map(|extensions| extensions.iter())
+                        .any(|ext| {
+                            oid_to_string(&ext.extn_id) == "2.5.29.14"
+                                && rasn::der::decode::<OctetString>(&ext.extn_value)
+                                    .map_or(false, |id| id == *sid)
+                        }),
+                })
+                .and_then(|(_, der)| X509Certificate::from_der(der).ok().map(|(_, cert)| cert));
+
+            let result = match signer_cert {
+                Some(signer_cert) => {
+                    self.verify_signer(signer, &signer_cert, &candidates, &intermediates)
+                }
+                None => SignerResult::Unsupported("Signer certificate not found".to_string()),
+            };
+
+            match result {
+                SignerResult::Verified => return (SmimeStatus::SignedVerified, Vec::new()),
+                SignerResult::Failed(err) => {
+                    is_failed = true;
+                    errors.push(err);
+                }
+                SignerResult::Unsupported(err) => {
+                    errors.push(err);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            (
+                SmimeStatus::SignedFailed,
+                vec!["Message does not contain any signatures".to_string()],
+            )
+        } else if is_failed {
+            (SmimeStatus::SignedFailed, errors)
+        } else {
+            (SmimeStatus::Signed, errors)
+        }
+    }
+
+    fn verify_signer(
+        &self,
+        signer: &SignerInfo,
+        signer_cert: &X509Certificate<'_>,
+        candidates: &[Cow<'_, [u8]>],
+        intermediates: &[X509Certificate<'_>],
+    ) -> SignerResult {
+        let Some(hash) =
+            HashAlgorithm::from_oid(&oid_to_string(&signer.digest_algorithm.algorithm))
+        else {
+            return SignerResult::Unsupported("Unsupported digest algorithm".to_string());
+        };
+        let signature_algorithm = oid_to_string(&signer.signature_algorithm.algorithm);
+        if signature_algorithm != OID_RSA_ENCRYPTION
+            && HashAlgorithm::from_oid(&signature_algorithm).is_none()
+        {
+            return SignerResult::Unsupported("Unsupported signature algorithm".to_string());
+        }
+        let public_key: &[u8] = signer_cert.public_key().subject_public_key.data.as_ref();
+
+        // Verify the signature, either over the signed attributes or the content
+        let result = if let Some(attributes) = &signer.signed_attrs {
+            let Some(message_digest) = attributes
+                .iter()
+                .find(|attr| oid_to_string(&attr.r#type) == OID_MESSAGE_DIGEST)
+                .and_then(|attr| attr.values.iter().next())
+                .and_then(|value| rasn::der::decode::<OctetString>(value.as_bytes()).ok())
+            else {
+                return SignerResult::Failed("Signature has no message digest".to_string());
+            };
+            if !candidates
+                .iter()
+                .any(|content| hash.digest(content) == message_digest.as_ref())
+            {
+                return SignerResult::Failed(
+                    "Message digest mismatch, the message was modified".to_string(),
+                );
+            }
+
+            match rasn::der::encode(attributes) {
+                Ok(attributes) => {
+                    rsa_verify(public_key, hash, &attributes, signer.signature.as_ref())
+                }
+                Err(_) => Err(SignerResult::Failed(
+                    "Failed to encode signed attributes".to_string(),
+                )),
+            }
+        } else {
+            let mut result = Ok(());
+            for content in candidates {
+                result = rsa_verify(public_key, hash, content, signer.signature.as_ref());
+                if !matches!(result, Err(SignerResult::Failed(_))) {
+                    break;
+                }
+            }
+            result
+        };
+        if let Err(result) = result {
+            return result;
+        }
+
+        // The signer must be one of the senders
+        if !self.from.is_empty() {
+            let emails = certificate_emails(signer_cert);
+            if !self.from.iter().any(|from| emails.contains(from)) {
+                return SignerResult::Failed(format!(
+                    "Signer certificate issued to {} does not match the From address",
+                    if emails.is_empty() {
+                        "an unknown address".to_string()
+                    } else {
+                        emails.join(", ")
+                    }
+                ));
+            }
+        }
+
+        match self.verify_chain(signer_cert, intermediates) {
+            Ok(()) => SignerResult::Verified,
+            Err(err) => SignerResult::Failed(err),
+        }
+    }
+
+    fn verify_chain(
+        &self,
+        signer_cert: &X509Certificate<'_>,
+        intermediates: &[X509Certificate<'_>],
+    ) -> Result<(), String> {
+        let mut cert = signer_cert;
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            let validity = cert.validity();
+            if self.now < validity.not_before.timestamp()
+                || self.now > validity.not_after.timestamp()
+            {
+                return Err(format!(
+                    "Certificate {} has expired or is not yet valid",
+                    cert.subject()
+                ));
+            }
+
+            // Trusted when the certificate is a trust anchor or was issued by one
+            if self
+                .trust_anchors
+                .iter()
+                .any(|anchor| anchor.tbs_certificate.as_ref() == cert.tbs_certificate.as_ref())
+            {
+                return Ok(());
+            }
+            if let Some(anchor) = self
+                .trust_anchors
+                .iter()
+                .find(|anchor| anchor.subject().as_raw() == cert.issuer().as_raw())
+            {
+                return verify_certificate(cert, anchor);
+            }
+
+            // Continue with the issuer included in the signature
+            let issuer = intermediates
+                .iter()
+                .find(|issuer| {
+                    issuer.subject().as_raw() == cert.issuer().as_raw()
+                        && issuer.tbs_certificate.as_ref() != cert.tbs_certificate.as_ref()
+                        && issuer.is_ca()
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "Certificate {} was not issued by a trusted authority",
+                        cert.subject()
+                    )
+                })?;
+            verify_certificate(cert, issuer)?;
+            cert = issuer;
+        }
+
+        Err("Certificate chain is too long".to_string())
+    }
+}
+
+impl HashAlgorithm {
+    fn from_oid(oid: &str) -> Option<Self> {
+        match oid {
+            "1.3.14.3.2.26" | "1.2.840.113549.1.1.5" => Some(HashAlgorithm::Sha1),
+            "2.16.840.1.101.3.4.2.1" | "1.2.840.113549.1.1.11" => Some(HashAlgorithm::Sha256),
+            "2.16.840.1.101.3.4.2.2" | "1.2.840.113549.1.1.12" => Some(HashAlgorithm::Sha384),
+            "2.16.840.1.101.3.4.2.3" | "1.2.840.113549.1.1.13" => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha1 => Sha1::digest(data).to_vec(),
+            HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            HashAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+            HashAlgorithm::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    /// DER encoded DigestInfo header that precedes the hash in PKCS#1 v1.5
+    /// signatures.
+    fn digest_info_prefix(&self) -> &'static [u8] {
+        match self {
+            HashAlgorithm::Sha1 => &[
+                0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04,
+                0x14,
+            ],
+            HashAlgorithm::Sha256 => &[
+                0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x01, 0x05, 0x00, 0x04, 0x20,
+            ],
+            HashAlgorithm::Sha384 => &[
+                0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x02, 0x05, 0x00, 0x04, 0x30,
+            ],
+            HashAlgorithm::Sha512 => &[
+                0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x03, 0x05, 0x00, 0x04, 0x40,
+            ],
+        }
+    }
+}
+
+impl SmimeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SmimeStatus::Unknown => "unknown",
+            SmimeStatus::Signed => "signed",
+            SmimeStatus::SignedVerified => "signed/verified",
+            SmimeStatus::SignedFailed => "signed/failed",
+            SmimeStatus::Encrypted => "encrypted",
+        }
+    }
+}
+
+fn rsa_verify(
+    public_key: &[u8],
+    hash: HashAlgorithm,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<(), SignerResult> {
+    let public_key = RsaPublicKey::from_pkcs1_der(public_key)
+        .map_err(|_| SignerResult::Unsupported("Unsupported public key algorithm".to_string()))?;
+    let mut digest_info = hash.digest_info_prefix().to_vec();
+    digest_info.extend_from_slice(&hash.digest(data));
+
+    public_key
+        .verify(Pkcs1v15Sign::new_unprefixed(), &digest_info, signature)
+        .map_err(|_| {
+            SignerResult::Failed("Signature verification failed, the message was modified".into())
+        })
+}
+
+fn verify_certificate(
+    cert: &X509Certificate<'_>,
+    issuer: &X509Certificate<'_>,
+) -> Result<(), String> {
+    let hash = HashAlgorithm::from_oid(&cert.signature_algorithm.algorithm.to_id_string())
+        .ok_or_else(|| {
+            format!(
+                "Certificate {} uses an unsupported algorithm",
+                cert.subject()
+            )
+        })?;
+
+    rsa_verify(
+        issuer.public_key().subject_public_key.data.as_ref(),
+        hash,
+        cert.tbs_certificate.as_ref(),
+        cert.signature_value.data.as_ref(),
+    )
+    .map_err(|_| format!("Certificate {} has an invalid signature", cert.subject()))
+}
+
+fn certificate_emails(cert: &X509Certificate<'_>) -> Vec<String> {
+    let mut emails = Vec::new();
+    for email in cert.subject().iter_email() {
+        if let Ok(email) = email.as_str() {
+            emails.push(email.to_lowercase());
+        }
+    }
+    for ext in cert.extensions() {
+        if let ParsedExtension::SubjectAlternativeName(san) = ext.parsed_extension() {
+            for name in &san.general_names {
+                if let GeneralName::RFC822Name(email) = name {
+                    emails.push(email.to_lowercase());
+                }
+            }
+        }
+    }
+    emails
+}
+
+/// Signers canonicalize line endings to CRLF and the line break preceding
+/// the boundary delimiter belongs to the delimiter, so the signed entity is
+/// tried in each of its possible forms.
+pub(crate) fn detached_candidates(entity: &[u8]) -> Vec<Cow<'_, [u8]>> {
+    let mut candidates = vec![Cow::Borrowed(entity)];
+    if let Some(entity) = entity
+        .strip_suffix(b"\r\n")
+        .or_else(|| entity.strip_suffix(b"\n"))
+    {
+        candidates.push(Cow::Borrowed(entity));
+    }
+    for pos in 0..candidates.len() {
+        let candidate = candidates[pos].as_ref();
+        if candidate
+            .iter()
+            .enumerate()
+            .any(|(pos, ch)| *ch == b'\n' && (pos == 0 || candidate[pos - 1] != b'\r'))
+        {
+            let mut canonical = Vec::with_capacity(candidate.len() + 64);
+            let mut last_ch = 0;
+            for &ch in candidate {
+                if ch == b'\n' && last_ch != b'\r' {
+                    canonical.push(b'\r');
+                }
+                canonical.push(ch);
+                last_ch = ch;
+            }
+            candidates.push(Cow::Owned(canonical));
+        }
+    }
+    candidates
+}
+
+fn is_pkcs7_signature(protocol: &str) -> bool {
+    protocol.eq_ignore_ascii_case("application/pkcs7-signature")
+        || protocol.eq_ignore_ascii_case("application/x-pkcs7-signature")
+}
+
+fn oid_to_string(oid: &[u32]) -> String {
+    oid.iter()
+        .map(|arc| arc.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
@@ -0,0 +1,160 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{scripts::plugins::bayes::bayes_train, Server};
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_parser::MessageParser;
+use smtp::core::reputation::{message_reputation_entities, ReputationSignal, SmtpReputation};
+use store::write::Bincode;
+use trc::AddContext;
+
+use crate::{blob::download::BlobDownload, JmapMethods};
+
+use super::metadata::MessageMetadata;
+
+pub trait EmailSpamTrain: Sync + Send {
+    /// Trains the spam filter and updates the sender reputation in the background
+    /// after messages were moved into (`is_spam`) or out of the Junk folder.
+    fn email_spam_feedback(&self, account_id: u32, document_ids: Vec<u32>, is_spam: bool);
+
+    fn email_spam_train(
+        &self,
+        account_id: u32,
+        document_ids: Vec<u32>,
+        is_spam: bool,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl EmailSpamTrain for Server {
+    fn email_spam_feedback(&self, account_id: u32, document_ids: Vec<u32>, is_spam: bool) {
+        if (self.core.jmap.spam_training.is_none() && self.core.smtp.session.reputation.is_none())
+            || document_ids.is_empty()
+        {
+            return;
+        }
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = server
+                .email_spam_train(account_id, document_ids, is_spam)
+                .await
+            {
+                trc::error!(err
+                    .account_id(account_id)
+                    .details("Failed to train spam filter from user feedback"));
+            }
+        });
+    }
+
+    async fn email_spam_train(
+        &self,
+        account_id: u32,
+        document_ids: Vec<u32>,
+        is_spam: bool,
+    ) -> trc::Result<()> {
+        let training = if let Some(config) = &self.core.jmap.spam_training {
+            let store = if let Some(store_id) = &config.store {
+                self.core.storage.lookups.get(store_id).ok_or_else(|| {
+                    trc::SpamEvent::TrainError
+                        .into_err()
+                        .ctx(trc::Key::Id, store_id.to_string())
+                        .details("Unknown store")
+                })?
+            } else {
+                &self.core.storage.lookup
+            };
+            Some((config, store))
+        } else {
+            None
+        };
+
+        for document_id in document_ids {
+            let metadata = if let Some(metadata) = self
+                .get_property::<Bincode<MessageMetadata>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    &Property::BodyStructure,
+                )
+                .await
+                .caused_by(trc::location!())?
+            {
+                metadata.inner
+            } else {
+                continue;
+            };
+            let raw_message = if let Some(raw_message) = self
+                .get_blob(&metadata.blob_hash, 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())?
+            {
+                raw_message
+            } else {
+                continue;
+            };
+            let message = if let Some(message) = MessageParser::new().parse(&raw_message) {
+                message
+            } else {
+                continue;
+            };
+
+            // Spam reports count as complaints against the sender
+            if self.core.smtp.session.reputation.is_some() {
+                self.reputation_update(
+                    &message_reputation_entities(&message),
+                    if is_spam {
+                        ReputationSignal::Complaint
+                    } else {
+                        ReputationSignal::Ham
+                    },
+                )
+                .await
+                .caused_by(trc::location!())?;
+            }
+
+            let (config, store) = if let Some(training) = training {
+                training
+            } else {
+                continue;
+            };
+            let mut text = message.subject().unwrap_or_default().to_string();
+            for idx in 0..message.text_body.len() {
+                if let Some(body) = message.body_text(idx) {
+                    text.push(' ');
+                    text.push_str(&body);
+                }
+            }
+
+            let mut total = 0;
+            if config.account {
+                total += bayes_train(
+                    self,
+                    store,
+                    account_id.into(),
+                    &text,
+                    is_spam,
+                    config.expiry,
+                )
+                .await?;
+            }
+            if config.global {
+                total += bayes_train(self, store, None, &text, is_spam, None).await?;
+            }
+
+            trc::event!(
+                Spam(trc::SpamEvent::TrainFeedback),
+                AccountId = account_id,
+                DocumentId = document_id,
+                Details = is_spam,
+                Total = total,
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, time::Duration};
+
+use common::{auth::AccessToken, Server};
+use jmap_proto::types::{collection::Collection, keyword::Keyword};
+use mail_parser::{GetHeader, HeaderName, MessageParser};
+use store::{
+    ahash::AHashSet,
+    write::{key::DeserializeBigEndian, now, BatchBuilder, Bincode, BlobOp, ValueClass},
+    Deserialize, IterateParams, Serialize, ValueKey, U32_LEN, U64_LEN,
+};
+use trc::AddContext;
+use utils::{BlobHash, BLOB_HASH_LEN};
+
+use crate::{blob::download::BlobDownload, mailbox::INBOX_ID, JmapMethods};
+
+use super::{
+    ingest::{EmailIngest, IngestEmail, IngestSource},
+    metadata::MessageMetadata,
+};
+
+/// A deleted message held in the trash. The entry is stored as the value of a
+/// blob reservation, which keeps the message blob from being purged until the
+/// retention period expires.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TrashedEmail {
+    pub mailbox_ids: Vec<u32>,
+    pub keywords: Vec<String>,
+    pub subject: Option<String>,
+    pub size: u32,
+    pub received_at: u64,
+    pub deleted_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashEntry {
+    pub blob_hash: BlobHash,
+    pub expires_at: u64,
+    pub email: TrashedEmail,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashRestoreReport {
+    pub account_id: u32,
+    pub restored_emails: u64,
+    pub failed_emails: u64,
+    pub not_found_emails: u64,
+}
+
+pub trait EmailTrash: Sync + Send {
+    /// Returns the messages held in the trash of an account, oldest first.
+    fn trash_list(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<TrashEntry>>> + Send;
+
+    /// Restores the messages with the given blob hashes, or all messages in the
+    /// trash if none are given, to their original mailboxes. Messages whose
+    /// mailboxes no longer exist are restored to the Inbox.
+    fn trash_restore(
+        &self,
+        account_id: u32,
+        blob_hashes: Option<Vec<BlobHash>>,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<TrashRestoreReport>> + Send;
+
+    /// Removes all messages from the trash of an account.
+    fn trash_empty(&self, account_id: u32) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+pub trait TrashBatch {
+    /// Holds a message in the trash for the given retention period.
+    fn hold_in_trash(
+        &mut self,
+        blob_hash: BlobHash,
+        email: TrashedEmail,
+        retention: Duration,
+    ) -> &mut Self;
+}
+
+impl TrashedEmail {
+    pub fn new(metadata: &MessageMetadata<'_>, keywords: Vec<Keyword>) -> Self {
+        TrashedEmail {
+            mailbox_ids: Vec::new(),
+            keywords: keywords
+                .into_iter()
+                .map(|keyword| keyword.to_string())
+                .collect(),
+            subject: metadata
+                .contents
+                .root_part()
+                .headers
+                .header_value(&HeaderName::Subject)
+                .and_then(|value| value.as_text())
+                .map(|value| value.to_string()),
+            size: metadata.size as u32,
+            received_at: metadata.received_at,
+            deleted_at: now(),
+        }
+    }
+}
+
+impl TrashBatch for BatchBuilder {
+    fn hold_in_trash(
+        &mut self,
+        blob_hash: BlobHash,
+        email: TrashedEmail,
+        retention: Duration,
+    ) -> &mut Self {
+        self.set(
+            BlobOp::Reserve {
+                hash: blob_hash,
+                until: email.deleted_at + retention.as_secs(),
+            },
+            Bincode::new(email).serialize(),
+        )
+    }
+}
+
+impl EmailTrash for Server {
+    async fn trash_list(&self, account_id: u32) -> trc::Result<Vec<TrashEntry>> {
+        let from_key = ValueKey {
+            account_id,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Reserve {
+                hash: BlobHash::default(),
+                until: 0,
+            }),
+        };
+        let to_key = ValueKey {
+            account_id: account_id + 1,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Reserve {
+                hash: BlobHash::default(),
+                until: 0,
+            }),
+        };
+
+        let now = now();
+        let mut results = Vec::new();
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
+                    // Other reservations hold either a size or a fixed length undelete record
+                    let expires_at = key.deserialize_be_u64(key.len() - U64_LEN)?;
+                    if value.len() > U32_LEN + U64_LEN + 1 && expires_at > now {
+                        if let Ok(email) = Bincode::<TrashedEmail>::deserialize(value) {
+                            results.push(TrashEntry {
+                                blob_hash: BlobHash::try_from_hash_slice(
+                                    key.get(U32_LEN..U32_LEN + BLOB_HASH_LEN).ok_or_else(|| {
+                                        trc::Error::corrupted_key(
+                                            key,
+                                            value.into(),
+                                            trc::location!(),
+                                        )
+                                    })?,
+                                )
+                                .unwrap(),
+                                expires_at,
+                                email: email.inner,
+                            });
+                        }
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        results.sort_by(|a, b| a.email.deleted_at.cmp(&b.email.deleted_at));
+
+        Ok(results)
+    }
+
+    async fn trash_restore(
+        &self,
+        account_id: u32,
+        blob_hashes: Option<Vec<BlobHash>>,
+        session_id: u64,
+    ) -> trc::Result<TrashRestoreReport> {
+        let mut report = TrashRestoreReport {
+            account_id,
+            ..Default::default()
+        };
+
+        // Obtain the entries to restore, most recently deleted first
+        let mut entries = self.trash_list(account_id).await?;
+        entries.reverse();
+        let mut pending = blob_hashes.map(|hashes| hashes.into_iter().collect::<AHashSet<_>>());
+        if let Some(pending) = &pending {
+            entries.retain(|entry| pending.contains(&entry.blob_hash));
+        }
+
+        let mailbox_ids = self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await?
+            .unwrap_or_default();
+        let resource_token = self
+            .get_resource_token(&AccessToken::from_id(u32::MAX), account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let mut restored = AHashSet::new();
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id);
+
+        for entry in entries {
+            // A message deleted more than once is only restored once
+            if restored.contains(&entry.blob_hash) {
+                batch.clear(BlobOp::Reserve {
+                    hash: entry.blob_hash,
+                    until: entry.expires_at,
+                });
+                continue;
+            }
+            if let Some(pending) = &mut pending {
+                pending.remove(&entry.blob_hash);
+            }
+
+            let raw_message = if let Some(raw_message) = self
+                .get_blob(&entry.blob_hash, 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())?
+            {
+                raw_message
+            } else {
+                report.not_found_emails += 1;
+                continue;
+            };
+
+            let mut restore_ids = entry
+                .email
+                .mailbox_ids
+                .iter()
+                .filter(|mailbox_id| mailbox_ids.contains(**mailbox_id))
+                .copied()
+                .collect::<Vec<_>>();
+            if restore_ids.is_empty() {
+                restore_ids.push(INBOX_ID);
+            }
+
+            match self
+                .email_ingest(IngestEmail {
+                    raw_message: &raw_message,
+                    message: MessageParser::new().parse(&raw_message),
+                    resource: resource_token.clone(),
+                    mailbox_ids: restore_ids,
+                    keywords: entry
+                        .email
+                        .keywords
+                        .into_iter()
+                        .map(Keyword::from)
+                        .collect(),
+                    received_at: entry.email.received_at.into(),
+                    source: IngestSource::Jmap,
+                    encrypt: false,
+                    shared_metadata: false,
+                    session_id,
+                })
+                .await
+            {
+                Ok(_) => {
+                    report.restored_emails += 1;
+                    batch.clear(BlobOp::Reserve {
+                        hash: entry.blob_hash.clone(),
+                        until: entry.expires_at,
+                    });
+                    restored.insert(entry.blob_hash);
+                }
+                Err(err)
+                    if err.matches(trc::EventType::MessageIngest(
+                        trc::MessageIngestEvent::Error,
+                    )) =>
+                {
+                    trc::error!(err
+                        .account_id(account_id)
+                        .details("Failed to restore message from trash"));
+                    report.failed_emails += 1;
+                }
+                Err(err) => {
+                    return Err(err.caused_by(trc::location!()));
+                }
+            }
+        }
+        report.not_found_emails += pending.map_or(0, |pending| pending.len() as u64);
+
+        if !batch.is_empty() {
+            self.write_batch(batch).await?;
+        }
+
+        Ok(report)
+    }
+
+    async fn trash_empty(&self, account_id: u32) -> trc::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id);
+        for entry in self.trash_list(account_id).await? {
+            batch.clear(BlobOp::Reserve {
+                hash: entry.blob_hash,
+                until: entry.expires_at,
+            });
+
+            if batch.ops.len() >= 1000 {
+                self.write_batch(batch).await?;
+                batch = BatchBuilder::new();
+                batch.with_account_id(account_id);
+            }
+        }
+
+        if !batch.is_empty() {
+            self.write_batch(batch).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -74,6 +74,11 @@ impl IdentitySet for Server {
                     .await?
                     .unwrap_or_default()
                     .has_str_value(PrincipalField::Emails, email)
+                    && self
+                        .get_cached_access_token(account_id)
+                        .await?
+                        .delegated_sender(email)
+                        .is_none()
                 {
                     response.not_created.append(
                         id,
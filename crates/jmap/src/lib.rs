@@ -9,10 +9,15 @@ use std::{fmt::Display, future::Future, sync::Arc, time::Duration};
 use changes::state::StateManager;
 use common::{
     auth::{AccessToken, ResourceToken, TenantInfo},
+    ipc::CacheInvalidation,
     manager::boot::{BootManager, IpcReceivers},
     Inner, Server,
 };
 use directory::QueryBy;
+use email::{
+    fallback::{EmailFtsFallback, FtsQueryResult},
+    metadata::StoredMetadata,
+};
 use jmap_proto::{
     method::{
         query::{QueryRequest, QueryResponse},
@@ -22,7 +27,7 @@ use jmap_proto::{
 };
 use services::{
     delivery::spawn_delivery_manager, housekeeper::spawn_housekeeper, index::spawn_index_task,
-    state::spawn_state_manager,
+    notify::spawn_cache_listener, state::spawn_state_manager,
 };
 
 use store::{
@@ -97,6 +102,9 @@ impl SpawnServices for IpcReceivers {
         // Spawn housekeeper
         spawn_housekeeper(inner.clone(), self.housekeeper_rx.take().unwrap());
 
+        // Spawn cache invalidation listener
+        spawn_cache_listener(inner.clone());
+
         // Spawn index task
         spawn_index_task(inner);
     }
@@ -114,24 +122,35 @@ impl JmapMethods for Server {
         U: Deserialize + 'static,
     {
         let property = property.as_ref();
+        let key = ValueKey {
+            account_id,
+            collection: collection.into(),
+            document_id,
+            class: ValueClass::Property(property.into()),
+        };
 
-        self.core
-            .storage
-            .data
-            .get_value::<U>(ValueKey {
-                account_id,
-                collection: collection.into(),
-                document_id,
-                class: ValueClass::Property(property.into()),
-            })
-            .await
-            .add_context(|err| {
-                err.caused_by(trc::location!())
-                    .account_id(account_id)
-                    .collection(collection)
-                    .document_id(document_id)
-                    .id(property.to_string())
-            })
+        if collection == Collection::Email && property == &Property::BodyStructure {
+            // Resolve metadata shared between the recipients of a message
+            match self
+                .core
+                .storage
+                .data
+                .get_value::<StoredMetadata>(key)
+                .await
+            {
+                Ok(Some(metadata)) => metadata.resolve(&self.core.storage.data).await.map(Some),
+                result => result.map(|_| None),
+            }
+        } else {
+            self.core.storage.data.get_value::<U>(key).await
+        }
+        .add_context(|err| {
+            err.caused_by(trc::location!())
+                .account_id(account_id)
+                .collection(collection)
+                .document_id(document_id)
+                .id(property.to_string())
+        })
     }
 
     async fn get_properties<U, I, P>(
@@ -146,10 +165,13 @@ impl JmapMethods for Server {
         P: AsRef<Property> + Sync + Send,
         U: Deserialize + 'static,
     {
+        let is_metadata =
+            collection == Collection::Email && property.as_ref() == &Property::BodyStructure;
         let property: u8 = property.as_ref().into();
         let collection: u8 = collection.into();
         let expected_results = iterate.len();
         let mut results = Vec::with_capacity(expected_results);
+        let mut shared = Vec::new();
 
         self.core
             .storage
@@ -172,8 +194,14 @@ impl JmapMethods for Server {
                 |key, value| {
                     let document_id = key.deserialize_be_u32(key.len() - U32_LEN)?;
                     if iterate.contains(document_id) {
-                        results.push((document_id, U::deserialize(value)?));
-                        Ok(expected_results == 0 || results.len() < expected_results)
+                        match StoredMetadata::shared_hash(value).filter(|_| is_metadata) {
+                            Some(hash) => shared.push((document_id, hash)),
+                            None => results.push((document_id, U::deserialize(value)?)),
+                        }
+                        Ok(
+                            expected_results == 0
+                                || results.len() + shared.len() < expected_results,
+                        )
                     } else {
                         Ok(true)
                     }
@@ -185,8 +213,17 @@ impl JmapMethods for Server {
                     .account_id(account_id)
                     .collection(collection)
                     .id(property.to_string())
-            })
-            .map(|_| results)
+            })?;
+
+        // Resolve metadata shared between the recipients of a message
+        if !shared.is_empty() {
+            results.extend(
+                StoredMetadata::get_shared_many(&self.core.storage.data, shared).await?,
+            );
+            results.sort_unstable_by_key(|(document_id, _)| *document_id);
+        }
+
+        Ok(results)
     }
 
     async fn get_document_ids(
@@ -378,17 +415,46 @@ impl JmapMethods for Server {
         account_id: u32,
         collection: Collection,
         filters: Vec<FtsFilter<T>>,
-    ) -> trc::Result<RoaringBitmap> {
-        self.core
+    ) -> trc::Result<FtsQueryResult> {
+        // Keep a copy of the filters in case the backend is unavailable
+        let fallback_filters = (self.core.jmap.fts_fallback && collection == Collection::Email)
+            .then(|| filters.clone());
+
+        match self
+            .core
             .storage
             .fts
             .query(account_id, collection, filters)
             .await
-            .add_context(|err| {
-                err.caused_by(trc::location!())
+        {
+            Ok(documents) => {
+                self.set_fts_degraded(false);
+                Ok(FtsQueryResult {
+                    documents,
+                    degraded: false,
+                })
+            }
+            Err(err) => {
+                let err = err
+                    .caused_by(trc::location!())
                     .account_id(account_id)
-                    .collection(collection)
-            })
+                    .collection(collection);
+
+                if let Some(filters) = fallback_filters {
+                    trc::error!(err.details("Full-text query failed, falling back to metadata"));
+                    self.set_fts_degraded(true);
+
+                    self.fts_fallback_filter(account_id, &filters)
+                        .await
+                        .map(|documents| FtsQueryResult {
+                            documents,
+                            degraded: true,
+                        })
+                } else {
+                    Err(err)
+                }
+            }
+        }
     }
 
     async fn build_query_response<T: Sync + Send>(
@@ -425,6 +491,7 @@ impl JmapMethods for Server {
                     None
                 },
                 limit: if total > limit { Some(limit) } else { None },
+                full_text_degraded: None,
             },
             if limit_total > 0 {
                 Pagination::new(
@@ -486,6 +553,7 @@ impl JmapMethods for Server {
             .data
             .config_version
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.notify_cluster(CacheInvalidation::Settings);
     }
 }
 
@@ -558,7 +626,7 @@ pub trait JmapMethods: Sync + Send {
         account_id: u32,
         collection: Collection,
         filters: Vec<FtsFilter<T>>,
-    ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
+    ) -> impl Future<Output = trc::Result<FtsQueryResult>> + Send;
 
     fn build_query_response<T: Sync + Send>(
         &self,
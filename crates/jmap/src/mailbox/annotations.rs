@@ -0,0 +1,180 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    object::Object,
+    types::{property::Property, value::Value},
+};
+
+// Annotations are stored under Property::Annotations as an object where
+// shared entries are keyed by their name and the private entries of each
+// account are nested under the account id.
+pub trait MailboxAnnotations {
+    /// Returns the shared annotations and the private annotations of an account.
+    fn annotations(&self, account_id: u32) -> Vec<(String, String)>;
+
+    /// Applies changes to the annotations visible to an account, where a `None`
+    /// value removes the entry. Returns the new annotations value, or `None` if
+    /// nothing changed.
+    fn annotate(
+        &self,
+        account_id: u32,
+        changes: impl IntoIterator<Item = (String, Option<String>)>,
+    ) -> Option<Value>;
+
+    /// Replaces the annotations visible to an account with the ones set
+    /// through the JMAP `annotations` property.
+    fn replace_annotations(
+        &self,
+        account_id: u32,
+        annotations: Value,
+        max_size: usize,
+        max_entries: usize,
+    ) -> Result<Option<Value>, String>;
+}
+
+impl MailboxAnnotations for Object<Value> {
+    fn annotations(&self, account_id: u32) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        if let Value::Object(annotations) = self.get(&Property::Annotations) {
+            let owner = account_id.to_string();
+            for (key, value) in annotations.properties.iter() {
+                match (key, value) {
+                    (Property::_T(entry), Value::Text(value)) => {
+                        entries.push((entry.clone(), value.clone()));
+                    }
+                    (Property::_T(id), Value::Object(private)) if *id == owner => {
+                        for (key, value) in private.properties.iter() {
+                            if let (Property::_T(entry), Value::Text(value)) = (key, value) {
+                                entries.push((entry.clone(), value.clone()));
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        entries
+    }
+
+    fn annotate(
+        &self,
+        account_id: u32,
+        changes: impl IntoIterator<Item = (String, Option<String>)>,
+    ) -> Option<Value> {
+        let mut annotations = match self.get(&Property::Annotations) {
+            Value::Object(annotations) => annotations.clone(),
+            _ => Object::with_capacity(1),
+        };
+        let mut private = match annotations.remove(&Property::_T(account_id.to_string())) {
+            Value::Object(private) => private,
+            _ => Object::with_capacity(1),
+        };
+        let mut has_changes = false;
+
+        for (entry, value) in changes {
+            let target = if entry.starts_with("/private/") {
+                &mut private
+            } else {
+                &mut annotations
+            };
+            let key = Property::_T(entry);
+            match value {
+                Some(value) => {
+                    let value = Value::Text(value);
+                    if target.properties.get(&key) != Some(&value) {
+                        target.set(key, value);
+                        has_changes = true;
+                    }
+                }
+                None => {
+                    if target.properties.remove(&key).is_some() {
+                        has_changes = true;
+                    }
+                }
+            }
+        }
+
+        if has_changes {
+            if !private.properties.is_empty() {
+                annotations.append(Property::_T(account_id.to_string()), Value::Object(private));
+            }
+            Some(if !annotations.properties.is_empty() {
+                Value::Object(annotations)
+            } else {
+                Value::Null
+            })
+        } else {
+            None
+        }
+    }
+
+    fn replace_annotations(
+        &self,
+        account_id: u32,
+        annotations: Value,
+        max_size: usize,
+        max_entries: usize,
+    ) -> Result<Option<Value>, String> {
+        let mut entries = Vec::new();
+        match annotations {
+            Value::Object(annotations) => {
+                for (entry, value) in annotations.properties {
+                    let entry = match entry {
+                        Property::_T(entry) if is_valid_entry(&entry.to_lowercase()) => {
+                            entry.to_lowercase()
+                        }
+                        entry => {
+                            return Err(format!("Invalid annotation name {:?}.", entry.to_string()))
+                        }
+                    };
+                    match value {
+                        Value::Text(value) if value.len() <= max_size => {
+                            entries.push((entry, Some(value)));
+                        }
+                        Value::Text(_) => {
+                            return Err(format!(
+                                "Annotation {entry:?} exceeds the maximum size of {max_size} bytes."
+                            ))
+                        }
+                        Value::Null => (),
+                        _ => return Err(format!("Invalid value for annotation {entry:?}.")),
+                    }
+                }
+            }
+            Value::Null => (),
+            _ => return Err("Annotations must be an object.".to_string()),
+        }
+
+        if entries.len() > max_entries {
+            return Err(format!(
+                "Mailboxes cannot have more than {max_entries} annotations."
+            ));
+        }
+
+        // Entries missing from the request are removed
+        let mut changes = self
+            .annotations(account_id)
+            .into_iter()
+            .filter(|(entry, _)| !entries.iter().any(|(new_entry, _)| new_entry == entry))
+            .map(|(entry, _)| (entry, None))
+            .collect::<Vec<_>>();
+        changes.extend(entries);
+
+        Ok(self.annotate(account_id, changes))
+    }
+}
+
+/// Returns whether an annotation name is placed under either the /private or
+/// the /shared hierarchy, as required by RFC 5464.
+pub fn is_valid_entry(entry: &str) -> bool {
+    (entry.starts_with("/private/") || entry.starts_with("/shared/"))
+        && !entry.ends_with('/')
+        && !entry.contains("//")
+        && !entry
+            .chars()
+            .any(|ch| ch.is_ascii_control() || ch == '*' || ch == '%')
+}
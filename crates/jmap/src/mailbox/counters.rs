@@ -0,0 +1,285 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use jmap_proto::types::{collection::Collection, property::Property};
+use store::{
+    query::log::{Changes, Query},
+    roaring::RoaringBitmap,
+    write::{BatchBuilder, Bincode, F_VALUE},
+};
+use trc::AddContext;
+
+use crate::{changes::get::ChangesLookup, JmapMethods};
+
+use super::get::MailboxGet;
+
+// Email counters are cached under Property::Counters together with the id of the
+// last mailbox change that was logged when they were computed. Any change that
+// alters the contents or the seen state of a mailbox logs a mailbox change, so the
+// cached counters remain valid for as long as no newer change exists for the mailbox
+// and the change log has not been compacted past the cached change id.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MailboxCounters {
+    pub change_id: u64,
+    pub total_emails: u64,
+    pub unread_emails: u64,
+}
+
+pub trait MailboxCounterCache: Sync + Send {
+    /// Returns the email counters of a mailbox, recomputing them only when
+    /// the mailbox has changed since they were cached.
+    fn mailbox_counters(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> impl Future<Output = trc::Result<MailboxCounters>> + Send;
+
+    fn mailbox_compute_counters(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        message_ids: &Option<RoaringBitmap>,
+    ) -> impl Future<Output = trc::Result<MailboxCounters>> + Send;
+
+    /// Compares the cached counters of all mailboxes in an account against the
+    /// stored messages and returns the ids of the mailboxes that do not match,
+    /// optionally replacing the cached counters with the computed ones.
+    fn mailbox_check_counters(
+        &self,
+        account_id: u32,
+        repair: bool,
+    ) -> impl Future<Output = trc::Result<Vec<u32>>> + Send;
+}
+
+impl MailboxCounterCache for Server {
+    async fn mailbox_counters(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> trc::Result<MailboxCounters> {
+        // The last change id has to be obtained before computing the counters
+        let change_id = if let Some(change_id) = self
+            .core
+            .storage
+            .data
+            .get_last_change_id(account_id, Collection::Mailbox)
+            .await
+            .caused_by(trc::location!())?
+        {
+            change_id
+        } else {
+            let message_ids = self.get_document_ids(account_id, Collection::Email).await?;
+            return self
+                .mailbox_compute_counters(account_id, document_id, &message_ids)
+                .await;
+        };
+
+        let cached = self
+            .get_property::<Bincode<MailboxCounters>>(
+                account_id,
+                Collection::Mailbox,
+                document_id,
+                Property::Counters,
+            )
+            .await?
+            .map(|counters| counters.inner);
+        let counters = match cached {
+            Some(counters) if counters.change_id == change_id => {
+                return Ok(counters);
+            }
+            Some(counters)
+                if counters.change_id < change_id
+                    && self
+                        .core
+                        .storage
+                        .data
+                        .get_first_change_id(account_id, Collection::Mailbox)
+                        .await
+                        .caused_by(trc::location!())?
+                        .is_some_and(|first_change_id| counters.change_id >= first_change_id)
+                    && !has_changed(
+                        &self
+                            .changes_(
+                                account_id,
+                                Collection::Mailbox,
+                                Query::Since(counters.change_id),
+                            )
+                            .await?,
+                        document_id,
+                    ) =>
+            {
+                MailboxCounters {
+                    change_id,
+                    ..counters
+                }
+            }
+            _ => {
+                let message_ids = self.get_document_ids(account_id, Collection::Email).await?;
+                MailboxCounters {
+                    change_id,
+                    ..self
+                        .mailbox_compute_counters(account_id, document_id, &message_ids)
+                        .await?
+                }
+            }
+        };
+
+        // Cache the counters
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox)
+            .update_document(document_id)
+            .value(Property::Counters, Bincode::new(counters), F_VALUE);
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(counters)
+    }
+
+    async fn mailbox_compute_counters(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        message_ids: &Option<RoaringBitmap>,
+    ) -> trc::Result<MailboxCounters> {
+        Ok(MailboxCounters {
+            change_id: 0,
+            total_emails: self
+                .get_tag(
+                    account_id,
+                    Collection::Email,
+                    Property::MailboxIds,
+                    document_id,
+                )
+                .await?
+                .map(|v| v.len())
+                .unwrap_or(0),
+            unread_emails: self
+                .mailbox_unread_tags(account_id, document_id, message_ids)
+                .await?
+                .map(|v| v.len())
+                .unwrap_or(0),
+        })
+    }
+
+    async fn mailbox_check_counters(&self, account_id: u32, repair: bool) -> trc::Result<Vec<u32>> {
+        let change_id = self
+            .core
+            .storage
+            .data
+            .get_last_change_id(account_id, Collection::Mailbox)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default();
+        let first_change_id = self
+            .core
+            .storage
+            .data
+            .get_first_change_id(account_id, Collection::Mailbox)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default();
+        let message_ids = self.get_document_ids(account_id, Collection::Email).await?;
+
+        // Obtain cached counters
+        let mut cached = Vec::new();
+        for document_id in self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await?
+            .unwrap_or_default()
+        {
+            if let Some(counters) = self
+                .get_property::<Bincode<MailboxCounters>>(
+                    account_id,
+                    Collection::Mailbox,
+                    document_id,
+                    Property::Counters,
+                )
+                .await?
+            {
+                cached.push((document_id, counters.inner));
+            }
+        }
+
+        // Mailboxes that might have changed after their counters were cached, including
+        // those cached before the change log was compacted, are skipped as their
+        // counters are recomputed on the next lookup.
+        let changes = match cached.iter().map(|(_, counters)| counters.change_id).min() {
+            Some(from_change_id) if from_change_id < change_id => self
+                .changes_(
+                    account_id,
+                    Collection::Mailbox,
+                    Query::Since(from_change_id),
+                )
+                .await?
+                .into(),
+            _ => None,
+        };
+        let mut inconsistent = Vec::new();
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox);
+
+        for (document_id, cached) in cached {
+            if cached.change_id < change_id
+                && (cached.change_id < first_change_id
+                    || changes
+                        .as_ref()
+                        .is_some_and(|changes| has_changed(changes, document_id)))
+            {
+                continue;
+            }
+
+            let counters = self
+                .mailbox_compute_counters(account_id, document_id, &message_ids)
+                .await?;
+            if cached.change_id > change_id
+                || cached.total_emails != counters.total_emails
+                || cached.unread_emails != counters.unread_emails
+            {
+                inconsistent.push(document_id);
+
+                if repair {
+                    batch.update_document(document_id).value(
+                        Property::Counters,
+                        Bincode::new(MailboxCounters {
+                            change_id,
+                            ..counters
+                        }),
+                        F_VALUE,
+                    );
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            self.core
+                .storage
+                .data
+                .write(batch.build())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(inconsistent)
+    }
+}
+
+fn has_changed(changes: &Changes, document_id: u32) -> bool {
+    changes
+        .changes
+        .iter()
+        .any(|change| change.id() == document_id as u64)
+}
@@ -16,11 +16,13 @@ use trc::AddContext;
 use crate::{
     auth::acl::{AclMethods, EffectiveAcl},
     changes::state::StateManager,
-    email::cache::ThreadCache,
+    email::{cache::ThreadCache, seen::PrivateSeen},
     JmapMethods,
 };
 
-use super::{set::MailboxSet, INBOX_ID};
+use super::{
+    annotations::MailboxAnnotations, counters::MailboxCounterCache, set::MailboxSet, INBOX_ID,
+};
 use std::future::Future;
 
 pub trait MailboxGet: Sync + Send {
@@ -91,6 +93,19 @@ impl MailboxGet for Server {
                 .await?;
         }
         let message_ids = self.get_document_ids(account_id, Collection::Email).await?;
+        let private_unseen_ids = if self.has_private_seen(account_id, access_token.primary_id())
+            && properties
+                .iter()
+                .any(|p| matches!(p, Property::UnreadEmails | Property::UnreadThreads))
+        {
+            let mut unseen_ids = message_ids.clone().unwrap_or_default();
+            unseen_ids -= self
+                .private_seen_ids(account_id, access_token.primary_id())
+                .await?;
+            Some(unseen_ids)
+        } else {
+            None
+        };
         let ids = if let Some(ids) = ids {
             ids
         } else {
@@ -109,6 +124,7 @@ impl MailboxGet for Server {
                     | Property::SortOrder
                     | Property::Acl
                     | Property::MyRights
+                    | Property::Annotations
             )
         });
         let mut response = GetResponse {
@@ -150,6 +166,7 @@ impl MailboxGet for Server {
             };
 
             let mut mailbox = Object::with_capacity(properties.len());
+            let mut counters = None;
 
             for property in &properties {
                 let value = match property {
@@ -169,23 +186,38 @@ impl MailboxGet for Server {
                             _ => Value::Null,
                         })
                         .unwrap_or_default(),
-                    Property::TotalEmails => Value::UnsignedInt(
-                        self.get_tag(
+                    Property::UnreadEmails if private_unseen_ids.is_some() => Value::UnsignedInt(
+                        self.mailbox_private_unseen(
                             account_id,
-                            Collection::Email,
-                            Property::MailboxIds,
                             document_id,
+                            private_unseen_ids.as_ref().unwrap(),
                         )
                         .await?
-                        .map(|v| v.len())
-                        .unwrap_or(0),
+                        .map_or(0, |ids| ids.len()),
                     ),
-                    Property::UnreadEmails => Value::UnsignedInt(
-                        self.mailbox_unread_tags(account_id, document_id, &message_ids)
-                            .await?
-                            .map(|v| v.len())
-                            .unwrap_or(0),
+                    Property::UnreadThreads if private_unseen_ids.is_some() => Value::UnsignedInt(
+                        self.mailbox_count_threads(
+                            account_id,
+                            self.mailbox_private_unseen(
+                                account_id,
+                                document_id,
+                                private_unseen_ids.as_ref().unwrap(),
+                            )
+                            .await?,
+                        )
+                        .await? as u64,
                     ),
+                    Property::TotalEmails | Property::UnreadEmails => {
+                        if counters.is_none() {
+                            counters = self.mailbox_counters(account_id, document_id).await?.into();
+                        }
+                        let counters = counters.unwrap_or_default();
+                        Value::UnsignedInt(if property == &Property::TotalEmails {
+                            counters.total_emails
+                        } else {
+                            counters.unread_emails
+                        })
+                    }
                     Property::TotalThreads => Value::UnsignedInt(
                         self.mailbox_count_threads(
                             account_id,
@@ -209,7 +241,8 @@ impl MailboxGet for Server {
                     ),
                     Property::MyRights => {
                         if access_token.is_shared(account_id) {
-                            let acl = values.effective_acl(access_token);
+                            let mut acl = values.effective_acl(access_token);
+                            acl.union(&access_token.delegated_acl(account_id));
                             Object::with_capacity(9)
                                 .with_property(Property::MayReadItems, acl.contains(Acl::ReadItems))
                                 .with_property(Property::MayAddItems, acl.contains(Acl::AddItems))
@@ -270,6 +303,13 @@ impl MailboxGet for Server {
                         )
                         .await
                     }
+                    Property::Annotations => {
+                        let mut annotations = Object::with_capacity(4);
+                        for (entry, value) in values.annotations(access_token.primary_id()) {
+                            annotations.append(Property::_T(entry), value);
+                        }
+                        Value::Object(annotations)
+                    }
 
                     _ => Value::Null,
                 };
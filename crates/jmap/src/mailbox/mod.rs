@@ -14,7 +14,10 @@ use store::{
 };
 use utils::codec::leb128::{Leb128Iterator, Leb128Vec};
 
+pub mod annotations;
+pub mod counters;
 pub mod get;
+pub mod public;
 pub mod query;
 pub mod set;
 
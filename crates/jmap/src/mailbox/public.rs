@@ -0,0 +1,340 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use directory::QueryBy;
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{
+        acl::Acl,
+        collection::Collection,
+        id::Id,
+        property::Property,
+        state::StateChange,
+        type_state::DataType,
+        value::{AclGrant, Value},
+    },
+};
+use store::{
+    ahash::AHashMap,
+    query::Filter,
+    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder},
+};
+use trc::AddContext;
+use utils::map::bitmap::Bitmap;
+
+use crate::{changes::write::ChangeLog, services::state::StateManager, JmapMethods};
+
+use super::set::SCHEMA;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicFolder {
+    pub id: u32,
+    pub name: String,
+    pub acl: Vec<AclGrant>,
+}
+
+// Public folders are the mailboxes of the account configured under
+// `jmap.public-folders.account`, which users access as a shared account.
+// Folders inherit the ACL of their parent when created, and rights granted
+// on a folder are applied to all of its descendants.
+pub trait PublicFolderMethods: Sync + Send {
+    /// Returns the id of the account holding the public folders, if any.
+    fn public_account_id(&self) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
+
+    /// Returns all public folders with their full path and ACL.
+    fn public_folder_list(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<PublicFolder>>> + Send;
+
+    /// Returns the ACL inherited by a folder created under a parent folder.
+    fn public_folder_inherited_acl(
+        &self,
+        account_id: u32,
+        parent_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<AclGrant>>> + Send;
+
+    /// Creates the missing folders of a path, returns `None` if the path
+    /// already exists.
+    fn public_folder_create(
+        &self,
+        account_id: u32,
+        path: &str,
+    ) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
+
+    /// Replaces the rights of an account on a folder and its descendants,
+    /// removing them if empty.
+    fn public_folder_set_rights(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        grantee_id: u32,
+        rights: Bitmap<Acl>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl PublicFolderMethods for Server {
+    async fn public_account_id(&self) -> trc::Result<Option<u32>> {
+        if let Some(public_folders) = &self.core.jmap.public_folders {
+            self.core
+                .storage
+                .directory
+                .query(QueryBy::Name(&public_folders.account), false)
+                .await
+                .caused_by(trc::location!())
+                .map(|principal| principal.map(|principal| principal.id()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn public_folder_list(&self, account_id: u32) -> trc::Result<Vec<PublicFolder>> {
+        let mailbox_ids = self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default();
+        let mailboxes = self
+            .get_properties::<Object<Value>, _, _>(
+                account_id,
+                Collection::Mailbox,
+                &mailbox_ids,
+                Property::Value,
+            )
+            .await
+            .caused_by(trc::location!())?
+            .into_iter()
+            .collect::<AHashMap<_, _>>();
+
+        let mut folders = Vec::with_capacity(mailboxes.len());
+        for (&mailbox_id, mailbox) in &mailboxes {
+            // Build the full path by walking up the parents
+            let mut path = Vec::new();
+            let mut current = Some(mailbox);
+            while let Some(mailbox) = current.take() {
+                path.push(mailbox.get(&Property::Name).as_string().unwrap_or_default());
+                if let Some(parent_id) = mailbox
+                    .get(&Property::ParentId)
+                    .as_id()
+                    .map(|id| id.document_id())
+                    .filter(|id| *id > 0)
+                {
+                    if path.len() <= self.core.jmap.mailbox_max_depth {
+                        current = mailboxes.get(&(parent_id - 1));
+                    }
+                }
+            }
+            path.reverse();
+
+            folders.push(PublicFolder {
+                id: mailbox_id,
+                name: path.join("/"),
+                acl: mailbox
+                    .properties
+                    .get(&Property::Acl)
+                    .and_then(|acl| acl.as_acl())
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+        folders.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(folders)
+    }
+
+    async fn public_folder_inherited_acl(
+        &self,
+        account_id: u32,
+        parent_id: u32,
+    ) -> trc::Result<Vec<AclGrant>> {
+        self.get_property::<Object<Value>>(
+            account_id,
+            Collection::Mailbox,
+            parent_id,
+            Property::Value,
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|parent| {
+            parent
+                .and_then(|parent| {
+                    parent
+                        .properties
+                        .get(&Property::Acl)
+                        .and_then(|acl| acl.as_acl())
+                        .cloned()
+                })
+                .unwrap_or_default()
+        })
+    }
+
+    async fn public_folder_create(&self, account_id: u32, path: &str) -> trc::Result<Option<u32>> {
+        let folders = self.public_folder_list(account_id).await?;
+        let path = path
+            .split('/')
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<_>>();
+        if path.is_empty()
+            || path.len() > self.core.jmap.mailbox_max_depth
+            || path
+                .iter()
+                .any(|item| item.len() > self.core.jmap.mailbox_name_max_len)
+        {
+            return Err(trc::ManageEvent::Error
+                .into_err()
+                .details("Invalid folder name."));
+        }
+
+        // Locate the deepest existing parent
+        let mut parent_id = None;
+        let mut create_from = 0;
+        for pos in (1..=path.len()).rev() {
+            let name = path[..pos].join("/");
+            if let Some(folder) = folders.iter().find(|folder| folder.name == name) {
+                if pos == path.len() {
+                    return Ok(None);
+                }
+                parent_id = Some(folder.id);
+                create_from = pos;
+                break;
+            }
+        }
+
+        // Create missing folders, inheriting the ACL of their parent
+        let mut changes = self.begin_changes(account_id).await?;
+        let acl = if let Some(parent_id) = parent_id {
+            self.public_folder_inherited_acl(account_id, parent_id)
+                .await?
+        } else {
+            Vec::new()
+        };
+        for name in &path[create_from..] {
+            let mut mailbox = Object::with_capacity(4)
+                .with_property(Property::Name, *name)
+                .with_property(
+                    Property::ParentId,
+                    Value::Id(Id::from(parent_id.map_or(0, |id| id + 1))),
+                )
+                .with_property(
+                    Property::Cid,
+                    Value::UnsignedInt(rand::random::<u32>() as u64),
+                );
+            if !acl.is_empty() {
+                mailbox.set(Property::Acl, Value::Acl(acl.clone()));
+            }
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Mailbox)
+                .create_document()
+                .custom(ObjectIndexBuilder::new(SCHEMA).with_changes(mailbox));
+            let mailbox_id = self.write_batch_expect_id(batch).await?;
+            changes.log_insert(Collection::Mailbox, mailbox_id);
+            parent_id = Some(mailbox_id);
+        }
+
+        let change_id = changes.change_id;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox)
+            .custom(changes);
+        self.write_batch(batch).await?;
+        self.broadcast_state_change(
+            StateChange::new(account_id).with_change(DataType::Mailbox, change_id),
+        )
+        .await;
+
+        Ok(parent_id)
+    }
+
+    async fn public_folder_set_rights(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        grantee_id: u32,
+        rights: Bitmap<Acl>,
+    ) -> trc::Result<()> {
+        let mut changes = ChangeLogBuilder::new();
+        let mut mailbox_ids = vec![mailbox_id];
+        while let Some(mailbox_id) = mailbox_ids.pop() {
+            let values = if let Some(values) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::Mailbox,
+                    mailbox_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                values
+            } else {
+                continue;
+            };
+
+            // Add children
+            mailbox_ids.extend(
+                self.filter(
+                    account_id,
+                    Collection::Mailbox,
+                    vec![Filter::eq(Property::ParentId, mailbox_id + 1)],
+                )
+                .await?
+                .results,
+            );
+
+            // Replace the grantee's rights
+            let mut acl = values
+                .inner
+                .properties
+                .get(&Property::Acl)
+                .and_then(|acl| acl.as_acl())
+                .cloned()
+                .unwrap_or_default();
+            acl.retain(|item| item.account_id != grantee_id);
+            if !rights.is_empty() {
+                acl.push(AclGrant {
+                    account_id: grantee_id,
+                    grants: rights,
+                });
+            }
+
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Mailbox)
+                .update_document(mailbox_id)
+                .custom(
+                    ObjectIndexBuilder::new(SCHEMA)
+                        .with_current(values)
+                        .with_changes(
+                            Object::with_capacity(1).with_property(Property::Acl, Value::Acl(acl)),
+                        ),
+                );
+            if !batch.is_empty() {
+                self.write_batch(batch).await?;
+                changes.log_update(Collection::Mailbox, mailbox_id);
+            }
+        }
+
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            self.broadcast_state_change(
+                StateChange::new(account_id).with_change(DataType::Mailbox, change_id),
+            )
+            .await;
+        }
+
+        // Invalidate ACLs
+        self.invalidate_access_token(grantee_id);
+
+        Ok(())
+    }
+}
@@ -39,8 +39,43 @@ impl MailboxQuery for Server {
         let sort_as_tree = request.arguments.sort_as_tree.unwrap_or(false);
         let filter_as_tree = request.arguments.filter_as_tree.unwrap_or(false);
         let mut filters = Vec::with_capacity(request.filter.len());
+        let max_depth = self.core.jmap.mailbox_max_depth;
         let mailbox_ids = self.mailbox_get_or_create(account_id).await?;
 
+        // Hierarchy and prefix filters are evaluated against the mailbox tree
+        let mailboxes = if request.filter.iter().any(|cond| {
+            matches!(
+                cond,
+                Filter::AncestorId(_) | Filter::MaxDepth(_) | Filter::NamePrefix(_)
+            )
+        }) {
+            self.get_properties::<Object<Value>, _, _>(
+                account_id,
+                Collection::Mailbox,
+                &mailbox_ids,
+                Property::Value,
+            )
+            .await?
+            .into_iter()
+            .map(|(document_id, mut value)| {
+                let parent_id = value
+                    .properties
+                    .get(&Property::ParentId)
+                    .and_then(|id| id.as_id().map(|id| id.document_id()))
+                    .unwrap_or(0);
+                let name = value
+                    .properties
+                    .remove(&Property::Name)
+                    .and_then(|name| name.try_unwrap_string())
+                    .unwrap_or_default()
+                    .to_lowercase();
+                (document_id, (parent_id, name))
+            })
+            .collect::<AHashMap<_, _>>()
+        } else {
+            AHashMap::new()
+        };
+
         for cond in std::mem::take(&mut request.filter) {
             match cond {
                 Filter::ParentId(parent_id) => filters.push(query::Filter::eq(
@@ -87,6 +122,41 @@ impl MailboxQuery for Server {
                         filters.push(query::Filter::End);
                     }
                 }
+                Filter::AncestorId(ancestor_id) => {
+                    let ancestor_id = ancestor_id.document_id();
+                    filters.push(query::Filter::is_in_set(
+                        mailboxes
+                            .keys()
+                            .filter(|&&document_id| {
+                                mailbox_ancestors(&mailboxes, document_id, max_depth)
+                                    .any(|id| id == ancestor_id)
+                            })
+                            .copied()
+                            .collect(),
+                    ));
+                }
+                Filter::MaxDepth(depth) => {
+                    filters.push(query::Filter::is_in_set(
+                        mailboxes
+                            .keys()
+                            .filter(|&&document_id| {
+                                mailbox_ancestors(&mailboxes, document_id, max_depth).count()
+                                    <= depth as usize
+                            })
+                            .copied()
+                            .collect(),
+                    ));
+                }
+                Filter::NamePrefix(prefix) => {
+                    let prefix = prefix.to_lowercase();
+                    filters.push(query::Filter::is_in_set(
+                        mailboxes
+                            .iter()
+                            .filter(|(_, (_, name))| name.starts_with(&prefix))
+                            .map(|(document_id, _)| *document_id)
+                            .collect(),
+                    ));
+                }
                 Filter::And | Filter::Or | Filter::Not | Filter::Close => {
                     filters.push(cond.into());
                 }
@@ -254,3 +324,18 @@ impl MailboxQuery for Server {
         Ok(response)
     }
 }
+
+/// Returns the document ids of the ancestors of a mailbox, closest first.
+fn mailbox_ancestors(
+    mailboxes: &AHashMap<u32, (u32, String)>,
+    document_id: u32,
+    max_depth: usize,
+) -> impl Iterator<Item = u32> + '_ {
+    let mut parent_id = mailboxes.get(&document_id).map_or(0, |(id, _)| *id);
+    std::iter::from_fn(move || {
+        let document_id = parent_id.checked_sub(1)?;
+        parent_id = mailboxes.get(&document_id).map_or(0, |(id, _)| *id);
+        Some(document_id)
+    })
+    .take(max_depth)
+}
@@ -4,8 +4,15 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::{auth::AccessToken, config::jmap::settings::SpecialUse, Server};
-use directory::Permission;
+use common::{
+    auth::AccessToken,
+    config::jmap::settings::{DefaultFolder, SpecialUse},
+    Server,
+};
+use directory::{
+    backend::internal::{lookup::DirectoryStore, PrincipalField},
+    Permission, QueryBy,
+};
 use jmap_proto::{
     error::set::{SetError, SetErrorType},
     method::set::{SetRequest, SetResponse},
@@ -43,7 +50,7 @@ use crate::{
     JmapMethods,
 };
 
-use super::{get::MailboxGet, ARCHIVE_ID, DRAFTS_ID, SENT_ID};
+use super::{annotations::MailboxAnnotations, get::MailboxGet, ARCHIVE_ID, DRAFTS_ID, SENT_ID};
 #[allow(unused_imports)]
 use super::{UidMailbox, INBOX_ID, JUNK_ID, TRASH_ID};
 use std::future::Future;
@@ -211,7 +218,8 @@ impl MailboxSet for Server {
             {
                 // Validate ACL
                 if ctx.is_shared {
-                    let acl = mailbox.inner.effective_acl(access_token);
+                    let mut acl = mailbox.inner.effective_acl(access_token);
+                    acl.union(&access_token.delegated_acl(account_id));
                     if !acl.contains(Acl::Modify) {
                         ctx.response.not_updated.append(
                             id,
@@ -477,7 +485,8 @@ impl MailboxSet for Server {
         {
             // Validate ACLs
             if access_token.is_shared(account_id) {
-                let acl = mailbox.inner.effective_acl(access_token);
+                let mut acl = mailbox.inner.effective_acl(access_token);
+                acl.union(&access_token.delegated_acl(account_id));
                 if !acl.contains(Acl::Administer) {
                     if !acl.contains(Acl::Delete) {
                         return Ok(Err(SetError::forbidden()
@@ -496,6 +505,7 @@ impl MailboxSet for Server {
                 .with_collection(Collection::Mailbox)
                 .delete_document(document_id)
                 .value(Property::EmailIds, (), F_VALUE | F_CLEAR)
+                .value(Property::Counters, (), F_VALUE | F_CLEAR)
                 .custom(ObjectIndexBuilder::new(SCHEMA).with_current(mailbox));
 
             match self.core.storage.data.write(batch.build()).await {
@@ -596,6 +606,26 @@ impl MailboxSet for Server {
                 (Property::SortOrder, MaybePatchValue::Value(Value::UnsignedInt(value))) => {
                     Value::UnsignedInt(value)
                 }
+                (Property::Annotations, MaybePatchValue::Value(value)) => {
+                    let no_annotations = Object::with_capacity(0);
+                    match update
+                        .as_ref()
+                        .map_or(&no_annotations, |(_, current)| &current.inner)
+                        .replace_annotations(
+                            ctx.access_token.primary_id(),
+                            value,
+                            self.core.imap.metadata_max_size,
+                            self.core.imap.metadata_max_entries,
+                        ) {
+                        Ok(Some(value)) => value,
+                        Ok(None) => continue,
+                        Err(err) => {
+                            return Ok(Err(SetError::invalid_properties()
+                                .with_property(Property::Annotations)
+                                .with_description(err)));
+                        }
+                    }
+                }
                 (Property::Acl, value) => {
                     match self
                         .acl_set(&mut changes, update.as_ref().map(|(_, obj)| obj), value)
@@ -631,7 +661,13 @@ impl MailboxSet for Server {
                         .with_property(Property::ParentId)
                         .with_description("Mailbox cannot be a parent of itself.")));
                 } else if mailbox_parent_id == 0 {
-                    if depth == 0 && ctx.is_shared {
+                    if depth == 0
+                        && ctx.is_shared
+                        && !ctx
+                            .access_token
+                            .delegated_acl(ctx.account_id)
+                            .contains(Acl::CreateChild)
+                    {
                         return Ok(Err(SetError::forbidden()
                             .with_description("You are not allowed to create root folders.")));
                     }
@@ -654,6 +690,10 @@ impl MailboxSet for Server {
                         && !fields
                             .effective_acl(ctx.access_token)
                             .contains_any([Acl::CreateChild, Acl::Administer].into_iter())
+                        && !ctx
+                            .access_token
+                            .delegated_acl(ctx.account_id)
+                            .contains(Acl::CreateChild)
                     {
                         return Ok(Err(SetError::forbidden().with_description(
                             "You are not allowed to create sub mailboxes under this mailbox.",
@@ -822,8 +862,9 @@ impl MailboxSet for Server {
             .with_collection(Collection::Mailbox);
 
         // Create mailboxes
+        let (folders, locale) = account_folders(self, account_id).await?;
         let mut last_document_id = ARCHIVE_ID;
-        for folder in &self.core.jmap.default_folders {
+        for folder in folders {
             let (role, document_id) = match folder.special_use {
                 SpecialUse::Inbox => ("inbox", INBOX_ID),
                 SpecialUse::Trash => ("trash", TRASH_ID),
@@ -839,7 +880,10 @@ impl MailboxSet for Server {
             };
 
             let mut object = Object::with_capacity(4)
-                .with_property(Property::Name, folder.name.clone())
+                .with_property(
+                    Property::Name,
+                    folder.localized_name(locale.as_deref()).to_string(),
+                )
                 .with_property(Property::ParentId, Value::Id(0u64.into()))
                 .with_property(
                     Property::Cid,
@@ -945,6 +989,57 @@ impl MailboxSet for Server {
     }
 }
 
+/// Returns the folder template for the account's domain along with the account's
+/// locale, falling back to the default folders.
+async fn account_folders(
+    server: &Server,
+    account_id: u32,
+) -> trc::Result<(&[DefaultFolder], Option<String>)> {
+    let jmap = &server.core.jmap;
+    if jmap.folder_templates.is_empty()
+        && jmap
+            .default_folders
+            .iter()
+            .all(|folder| folder.localized.is_empty())
+    {
+        return Ok((&jmap.default_folders, None));
+    }
+
+    let mut principal = if let Some(principal) = server
+        .store()
+        .query(QueryBy::Id(account_id), false)
+        .await
+        .caused_by(trc::location!())?
+    {
+        principal
+    } else {
+        return Ok((&jmap.default_folders, None));
+    };
+    let domain = principal
+        .name()
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .or_else(|| {
+            principal
+                .iter_str(PrincipalField::Emails)
+                .next()
+                .and_then(|email| email.rsplit_once('@'))
+                .map(|(_, domain)| domain)
+        })
+        .map(|domain| domain.to_lowercase());
+    let folders = domain
+        .and_then(|domain| {
+            jmap.folder_templates
+                .iter()
+                .find(|template| template.domains.contains(&domain))
+        })
+        .map_or(jmap.default_folders.as_slice(), |template| {
+            template.folders.as_slice()
+        });
+
+    Ok((folders, principal.take_str(PrincipalField::Locale)))
+}
+
 pub trait MailboxSubscribe {
     fn mailbox_subscribe(&self, account_id: u32, subscribed: bool) -> Option<Value>;
 }
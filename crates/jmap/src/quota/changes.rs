@@ -0,0 +1,64 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use jmap_proto::{
+    method::changes::{ChangesRequest, ChangesResponse},
+    types::{id::Id, property::Property, state::State},
+};
+use std::future::Future;
+
+use super::{quota_state, QuotaList};
+
+pub trait QuotaChanges: Sync + Send {
+    fn quota_changes(
+        &self,
+        request: ChangesRequest,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<ChangesResponse>> + Send;
+}
+
+impl QuotaChanges for Server {
+    async fn quota_changes(
+        &self,
+        request: ChangesRequest,
+        access_token: &AccessToken,
+    ) -> trc::Result<ChangesResponse> {
+        let quotas = self
+            .quota_list(access_token, request.account_id.document_id())
+            .await?;
+        let new_state = quota_state(&quotas);
+        let mut response = ChangesResponse {
+            account_id: request.account_id,
+            old_state: request.since_state.clone(),
+            new_state: new_state.clone(),
+            has_more_changes: false,
+            created: vec![],
+            updated: vec![],
+            destroyed: vec![],
+            updated_properties: None,
+        };
+        let ids = quotas.iter().map(|quota| Id::from(quota.id));
+
+        // Without a change log every quota is reported as updated when the
+        // state differs, narrowed down to "used" if no limit has changed
+        match (&request.since_state, &new_state) {
+            (State::Initial, _) => {
+                response.created = ids.collect();
+            }
+            (State::Exact(old), State::Exact(new)) if old == new => (),
+            (State::Exact(old), State::Exact(new)) => {
+                response.updated = ids.collect();
+                if old >> 32 == new >> 32 {
+                    response.updated_properties = vec![Property::Used].into();
+                }
+            }
+            _ => return Err(trc::JmapEvent::CannotCalculateChanges.into_err()),
+        }
+
+        Ok(response)
+    }
+}
@@ -8,11 +8,11 @@ use common::{auth::AccessToken, Server};
 use jmap_proto::{
     method::get::{GetRequest, GetResponse, RequestArguments},
     object::Object,
-    types::{id::Id, property::Property, state::State, type_state::DataType, value::Value},
+    types::{id::Id, property::Property, type_state::DataType, value::Value},
 };
 use std::future::Future;
 
-use crate::JmapMethods;
+use super::{quota_state, QuotaList};
 
 pub trait QuotaGet: Sync + Send {
     fn quota_get(
@@ -41,42 +41,38 @@ impl QuotaGet for Server {
             Property::Description,
             Property::Types,
         ]);
-        let account_id = request.account_id.document_id();
-        let quota_ids = if access_token.quota > 0 {
-            vec![0u32]
-        } else {
-            vec![]
-        };
+        let quotas = self
+            .quota_list(access_token, request.account_id.document_id())
+            .await?;
         let ids = if let Some(ids) = ids {
             ids
         } else {
-            quota_ids.iter().map(|id| Id::from(*id)).collect()
+            quotas.iter().map(|quota| Id::from(quota.id)).collect()
         };
         let mut response = GetResponse {
             account_id: request.account_id.into(),
-            state: State::Initial.into(),
+            state: quota_state(&quotas).into(),
             list: Vec::with_capacity(ids.len()),
             not_found: vec![],
         };
 
         for id in ids {
-            // Obtain the sieve script object
-            let document_id = id.document_id();
-            if !quota_ids.contains(&document_id) {
+            // Obtain the quota
+            let Some(quota) = quotas.iter().find(|quota| quota.id == id.document_id()) else {
                 response.not_found.push(id.into());
                 continue;
-            }
+            };
 
             let mut result = Object::with_capacity(properties.len());
             for property in &properties {
                 let value = match property {
                     Property::Id => Value::Id(id),
                     Property::ResourceType => "octets".to_string().into(),
-                    Property::Used => (self.get_used_quota(account_id).await? as u64).into(),
-                    Property::HardLimit => access_token.quota.into(),
-                    Property::Scope => "account".to_string().into(),
-                    Property::Name => access_token.name.clone().into(),
-                    Property::Description => access_token.description.clone().into(),
+                    Property::Used => quota.used.into(),
+                    Property::HardLimit => quota.hard_limit.into(),
+                    Property::Scope => quota.scope.to_string().into(),
+                    Property::Name => quota.name.clone().into(),
+                    Property::Description => quota.description.clone().into(),
                     Property::Types => vec![
                         Value::Text(DataType::Email.to_string()),
                         Value::Text(DataType::SieveScript.to_string()),
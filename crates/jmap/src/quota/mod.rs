@@ -4,5 +4,127 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use common::{auth::AccessToken, Server};
+use directory::QueryBy;
+use jmap_proto::types::state::State;
+use std::future::Future;
+use store::blake3;
+use trc::AddContext;
+
+use crate::JmapMethods;
+
+pub mod changes;
 pub mod get;
 pub mod query;
+
+pub const ACCOUNT_QUOTA_ID: u32 = 0;
+pub const TENANT_QUOTA_ID: u32 = 1;
+
+/// A storage quota applying to an account, mapped from the account or
+/// tenant quota counters.
+#[derive(Debug, Clone)]
+pub struct QuotaItem {
+    pub id: u32,
+    pub scope: &'static str,
+    pub name: String,
+    pub description: Option<String>,
+    pub used: u64,
+    pub hard_limit: u64,
+}
+
+pub trait QuotaList: Sync + Send {
+    fn quota_list(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<QuotaItem>>> + Send;
+}
+
+impl QuotaList for Server {
+    async fn quota_list(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+    ) -> trc::Result<Vec<QuotaItem>> {
+        let resource_token = self.get_resource_token(access_token, account_id).await?;
+        let mut quotas = Vec::with_capacity(2);
+
+        if resource_token.quota > 0 {
+            let (name, description) = if access_token.primary_id == account_id {
+                (access_token.name.clone(), access_token.description.clone())
+            } else {
+                principal_name(self, account_id).await?
+            };
+
+            quotas.push(QuotaItem {
+                id: ACCOUNT_QUOTA_ID,
+                scope: "account",
+                name,
+                description,
+                used: self.get_used_quota(account_id).await?.max(0) as u64,
+                hard_limit: resource_token.quota,
+            });
+        }
+
+        // Tenant quotas are only set on enterprise deployments
+        if let Some(tenant) = resource_token.tenant.filter(|tenant| tenant.quota > 0) {
+            let (name, description) = principal_name(self, tenant.id).await?;
+
+            quotas.push(QuotaItem {
+                id: TENANT_QUOTA_ID,
+                scope: "domain",
+                name,
+                description,
+                used: self.get_used_quota(tenant.id).await?.max(0) as u64,
+                hard_limit: tenant.quota,
+            });
+        }
+
+        Ok(quotas)
+    }
+}
+
+/// Quotas have no change log, so the state is derived from their current
+/// values. The high half changes when a limit or name changes and the low
+/// half when only the usage does, which lets Quota/changes report
+/// `updatedProperties`.
+pub fn quota_state(quotas: &[QuotaItem]) -> State {
+    let mut definition = blake3::Hasher::new();
+    let mut usage = blake3::Hasher::new();
+    for quota in quotas {
+        definition.update(&quota.id.to_be_bytes());
+        definition.update(&quota.hard_limit.to_be_bytes());
+        definition.update(quota.name.as_bytes());
+        definition.update(quota.description.as_deref().unwrap_or_default().as_bytes());
+        usage.update(&quota.id.to_be_bytes());
+        usage.update(&quota.used.to_be_bytes());
+    }
+
+    State::Exact(((hash_u32(definition) as u64) << 32) | hash_u32(usage) as u64)
+}
+
+fn hash_u32(hasher: blake3::Hasher) -> u32 {
+    let hash = hasher.finalize();
+    let bytes = hash.as_bytes();
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+async fn principal_name(
+    server: &Server,
+    principal_id: u32,
+) -> trc::Result<(String, Option<String>)> {
+    Ok(server
+        .core
+        .storage
+        .directory
+        .query(QueryBy::Id(principal_id), false)
+        .await
+        .caused_by(trc::location!())?
+        .map(|principal| {
+            (
+                principal.name().to_string(),
+                principal.description().map(|d| d.to_string()),
+            )
+        })
+        .unwrap_or_default())
+}
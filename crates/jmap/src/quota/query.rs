@@ -4,12 +4,19 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::cmp::Ordering;
+
 use common::{auth::AccessToken, Server};
 use jmap_proto::{
-    method::query::{QueryRequest, QueryResponse, RequestArguments},
-    types::{id::Id, state::State},
+    method::query::{Filter, QueryRequest, QueryResponse, RequestArguments, SortProperty},
+    types::type_state::DataType,
 };
 use std::future::Future;
+use store::query::sort::Pagination;
+
+use crate::UpdateResults;
+
+use super::{quota_state, QuotaItem, QuotaList};
 
 pub trait QuotaQuery: Sync + Send {
     fn quota_query(
@@ -25,84 +32,139 @@ impl QuotaQuery for Server {
         request: QueryRequest<RequestArguments>,
         access_token: &AccessToken,
     ) -> trc::Result<QueryResponse> {
-        Ok(QueryResponse {
+        // Validate filters and sort criteria
+        for cond in &request.filter {
+            match cond {
+                Filter::Name(_)
+                | Filter::Scope(_)
+                | Filter::ResourceType(_)
+                | Filter::Type(_)
+                | Filter::And
+                | Filter::Or
+                | Filter::Not
+                | Filter::Close => (),
+                other => {
+                    return Err(trc::JmapEvent::UnsupportedFilter
+                        .into_err()
+                        .details(other.to_string()))
+                }
+            }
+        }
+        let comparators = request.sort.as_deref().unwrap_or_default();
+        for comparator in comparators {
+            if !matches!(comparator.property, SortProperty::Name | SortProperty::Used) {
+                return Err(trc::JmapEvent::UnsupportedSort
+                    .into_err()
+                    .details(comparator.property.to_string()));
+            }
+        }
+
+        // Quotas are few, filter and sort them in memory
+        let quotas = self
+            .quota_list(access_token, request.account_id.document_id())
+            .await?;
+        let mut results = quotas
+            .iter()
+            .filter(|quota| matches_filter(&request.filter, quota))
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| {
+            comparators
+                .iter()
+                .map(|comparator| {
+                    let ordering = match comparator.property {
+                        SortProperty::Name => a.name.cmp(&b.name),
+                        _ => a.used.cmp(&b.used),
+                    };
+                    if comparator.is_ascending {
+                        ordering
+                    } else {
+                        ordering.reverse()
+                    }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or_else(|| a.id.cmp(&b.id))
+        });
+
+        let total = results.len();
+        let limit = request
+            .limit
+            .unwrap_or(self.core.jmap.query_max_results)
+            .min(self.core.jmap.query_max_results);
+        let mut response = QueryResponse {
             account_id: request.account_id,
-            query_state: State::Initial,
+            query_state: quota_state(&quotas),
             can_calculate_changes: false,
             position: 0,
-            ids: if access_token.quota > 0 {
-                vec![Id::new(0)]
+            ids: vec![],
+            total: if request.calculate_total.unwrap_or(false) {
+                Some(total)
             } else {
-                vec![]
+                None
             },
-            total: Some(1),
-            limit: None,
-        })
-
-        /*
+            limit: if total > limit { Some(limit) } else { None },
+            full_text_degraded: None,
+        };
 
-        let account_id = request.account_id.document_id();
-
-        let mut filters = Vec::with_capacity(request.filter.len());
-
-        for cond in std::mem::take(&mut request.filter) {
-            match cond {
-                Filter::Name(value) => filters.push(query::Filter::has_text(
-                    Property::Name,
-                    &value,
-                    Language::None,
-                )),
-                Filter::Type(value) => filters.push(query::Filter::has_text(
-                    Property::Type,
-                    &value,
-                    Language::None,
-                )),
-                Filter::Scope(value) => filters.push(query::Filter::has_text(
-                    Property::Scope,
-                    &value,
-                    Language::None,
-                )),
-                Filter::ResourceType(value) => filters.push(query::Filter::has_text(
-                    Property::ResourceType,
-                    &value,
-                    Language::None,
-                )),
-                Filter::And | Filter::Or | Filter::Not | Filter::Close => {
-                    filters.push(cond.into());
+        if total > 0 && limit > 0 {
+            let mut paginate = Pagination::new(
+                limit.min(total),
+                request.position.unwrap_or(0),
+                request.anchor.map(|a| a.document_id()),
+                request.anchor_offset.unwrap_or(0),
+            );
+            for quota in results {
+                if !paginate.add(0, quota.id) {
+                    break;
                 }
-                other => return Err(trc::JmapEvent::UnsupportedFilter.into_err().details(other.to_string())),
             }
+            response.update_results(paginate.build())?;
         }
 
-        let result_set = self
-            .filter(account_id, Collection::Quota, filters)
-            .await?;
+        Ok(response)
+    }
+}
 
-        let (response, paginate) = self.build_query_response(&result_set, &request).await?;
+/// Evaluates the flattened filter tree against a quota, conditions at the
+/// top level are combined with AND.
+fn matches_filter(filter: &[Filter], quota: &QuotaItem) -> bool {
+    let mut stack: Vec<(Option<&Filter>, Vec<bool>)> = vec![(None, Vec::new())];
 
-        if let Some(paginate) = paginate {
-            // Parse sort criteria
-            let mut comparators = Vec::with_capacity(request.sort.as_ref().map_or(1, |s| s.len()));
-            for comparator in request
-                .sort
-                .and_then(|s| if !s.is_empty() { s.into() } else { None })
-                .unwrap_or_else(|| vec![Comparator::descending(SortProperty::Name)])
-            {
-                comparators.push(match comparator.property {
-                    SortProperty::Name => {
-                        query::Comparator::field(Property::Name, comparator.is_ascending)
-                    }
-                    SortProperty::Used => {
-                        query::Comparator::field(Property::Used, comparator.is_ascending)
-                    }
-                    other => return Err(trc::JmapEvent::UnsupportedSort.into_err().details(other.to_string())),
-                });
+    for cond in filter {
+        let result = match cond {
+            Filter::And | Filter::Or | Filter::Not => {
+                stack.push((Some(cond), Vec::new()));
+                continue;
+            }
+            Filter::Close if stack.len() > 1 => {
+                let (op, results) = stack.pop().unwrap();
+                evaluate(op, &results)
             }
+            Filter::Name(name) => quota.name.to_lowercase().contains(&name.to_lowercase()),
+            Filter::Scope(scope) => quota.scope == scope.as_str(),
+            Filter::ResourceType(resource_type) => resource_type == "octets",
+            Filter::Type(data_type) => [DataType::Email, DataType::SieveScript]
+                .iter()
+                .any(|t| t.to_string() == *data_type),
+            _ => continue,
+        };
+        stack.last_mut().unwrap().1.push(result);
+    }
+
+    // Close any operators left open
+    while stack.len() > 1 {
+        let (op, results) = stack.pop().unwrap();
+        let result = evaluate(op, &results);
+        stack.last_mut().unwrap().1.push(result);
+    }
+
+    let (op, results) = stack.pop().unwrap();
+    evaluate(op, &results)
+}
 
-            // Sort results
-            self.sort(result_set, comparators, paginate, response).await
-        } else {
-            Ok(response)
-        }*/
+fn evaluate(op: Option<&Filter>, results: &[bool]) -> bool {
+    match op {
+        Some(Filter::Or) => results.iter().any(|r| *r),
+        Some(Filter::Not) => !results.iter().any(|r| *r),
+        _ => results.iter().all(|r| *r),
     }
 }
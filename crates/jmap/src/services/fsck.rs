@@ -0,0 +1,404 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use jmap_proto::{
+    object::Object,
+    types::{collection::Collection, keyword::Keyword, property::Property, value::Value},
+};
+use store::{
+    ahash::AHashMap,
+    roaring::RoaringBitmap,
+    write::{
+        key::DeserializeBigEndian, BatchBuilder, BitmapClass, DirectoryClass, MaybeDynamicId,
+        TagValue, F_CLEAR,
+    },
+    BitmapKey, Deserialize, IndexKeyPrefix, IterateParams, U32_LEN,
+};
+use trc::AddContext;
+use utils::codec::leb128::Leb128Reader;
+
+use crate::{
+    mailbox::{counters::MailboxCounterCache, UidMailbox, TOMBSTONE_ID},
+    sieve::set::ObjectBlobId,
+    JmapMethods,
+};
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsckReport {
+    pub account_id: u32,
+    pub missing_document_ids: Vec<u32>,
+    pub orphan_document_ids: Vec<u32>,
+    pub tags: Vec<FsckTag>,
+    pub missing_threads: Vec<u32>,
+    pub orphan_threads: Vec<u32>,
+    pub used_quota: i64,
+    pub expected_quota: i64,
+    pub mailbox_counters: Vec<u32>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsckTag {
+    pub property: String,
+    pub value: String,
+    pub missing: Vec<u32>,
+    pub stale: Vec<u32>,
+}
+
+type TagKey = (Property, TagValue<u32>);
+
+pub trait Fsck: Sync + Send {
+    /// Verifies the document ids, tag bitmaps, thread links, used quota and
+    /// mailbox counters of an account against its stored messages, optionally
+    /// repairing any inconsistencies found.
+    fn fsck_account(
+        &self,
+        account_id: u32,
+        repair: bool,
+    ) -> impl Future<Output = trc::Result<FsckReport>> + Send;
+
+    fn fsck_tags(
+        &self,
+        account_id: u32,
+        property: Property,
+        tags: &mut AHashMap<TagKey, RoaringBitmap>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn fsck_quota(&self, account_id: u32) -> impl Future<Output = trc::Result<i64>> + Send;
+}
+
+impl Fsck for Server {
+    async fn fsck_account(&self, account_id: u32, repair: bool) -> trc::Result<FsckReport> {
+        let mut report = FsckReport {
+            account_id,
+            ..Default::default()
+        };
+
+        // Tombstoned messages no longer have mailbox or thread properties
+        let document_ids = self
+            .get_document_ids(account_id, Collection::Email)
+            .await?
+            .unwrap_or_default();
+        let tombstoned_ids = self
+            .get_tag(
+                account_id,
+                Collection::Email,
+                Property::MailboxIds,
+                TOMBSTONE_ID,
+            )
+            .await?
+            .unwrap_or_default();
+        let mailboxes = self
+            .get_properties::<Vec<UidMailbox>, _, _>(
+                account_id,
+                Collection::Email,
+                &(),
+                Property::MailboxIds,
+            )
+            .await?;
+        let message_ids = mailboxes
+            .iter()
+            .map(|(document_id, _)| *document_id)
+            .collect::<RoaringBitmap>();
+        let missing_document_ids = &message_ids - &document_ids;
+        let orphan_document_ids = &(&document_ids - &message_ids) - &tombstoned_ids;
+
+        // Build the tags expected from the stored properties
+        let mut expected: AHashMap<TagKey, RoaringBitmap> = AHashMap::new();
+        for (document_id, mailboxes) in mailboxes {
+            for mailbox in mailboxes {
+                expected
+                    .entry((Property::MailboxIds, TagValue::Id(mailbox.mailbox_id)))
+                    .or_default()
+                    .insert(document_id);
+            }
+        }
+        if !tombstoned_ids.is_empty() {
+            expected.insert(
+                (Property::MailboxIds, TagValue::Id(TOMBSTONE_ID)),
+                tombstoned_ids,
+            );
+        }
+        for (document_id, keywords) in self
+            .get_properties::<Vec<Keyword>, _, _>(
+                account_id,
+                Collection::Email,
+                &(),
+                Property::Keywords,
+            )
+            .await?
+        {
+            for keyword in keywords {
+                expected
+                    .entry((Property::Keywords, keyword.into()))
+                    .or_default()
+                    .insert(document_id);
+            }
+        }
+        let mut thread_ids = RoaringBitmap::new();
+        for (document_id, thread_id) in self
+            .get_properties::<u32, _, _>(account_id, Collection::Email, &(), Property::ThreadId)
+            .await?
+        {
+            thread_ids.insert(thread_id);
+            expected
+                .entry((Property::ThreadId, TagValue::Id(thread_id)))
+                .or_default()
+                .insert(document_id);
+        }
+
+        // Compare them against the stored tags
+        let mut stored = AHashMap::new();
+        for property in [Property::MailboxIds, Property::Keywords, Property::ThreadId] {
+            self.fsck_tags(account_id, property, &mut stored).await?;
+        }
+        let mut tags = Vec::new();
+        for (key, document_ids) in expected {
+            let stored_ids = stored.remove(&key).unwrap_or_default();
+            if stored_ids != document_ids {
+                tags.push((key, &document_ids - &stored_ids, stored_ids - document_ids));
+            }
+        }
+        for (key, stored_ids) in stored {
+            tags.push((key, RoaringBitmap::new(), stored_ids));
+        }
+
+        // Verify thread links
+        let thread_document_ids = self
+            .get_document_ids(account_id, Collection::Thread)
+            .await?
+            .unwrap_or_default();
+        let missing_threads = &thread_ids - &thread_document_ids;
+        let orphan_threads = thread_document_ids - thread_ids;
+
+        // Verify quota
+        report.used_quota = self.get_used_quota(account_id).await?;
+        report.expected_quota = self.fsck_quota(account_id).await?;
+
+        if repair {
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Email);
+            for document_id in &missing_document_ids {
+                batch.create_document_with_id(document_id);
+            }
+            for document_id in &orphan_document_ids {
+                // Tombstone orphaned messages so they are removed on the next purge
+                batch.update_document(document_id).tag(
+                    Property::MailboxIds,
+                    TagValue::Id(MaybeDynamicId::Static(TOMBSTONE_ID)),
+                    0,
+                );
+            }
+            for ((property, value), missing, stale) in &tags {
+                let value = match value {
+                    TagValue::Id(id) => TagValue::Id(MaybeDynamicId::Static(*id)),
+                    TagValue::Text(text) => TagValue::Text(text.clone()),
+                };
+                for (document_ids, options) in [(missing, 0), (stale, F_CLEAR)] {
+                    for document_id in document_ids {
+                        batch.update_document(document_id).tag(
+                            property.clone(),
+                            value.clone(),
+                            options,
+                        );
+                    }
+                }
+            }
+            batch.with_collection(Collection::Thread);
+            for thread_id in &missing_threads {
+                batch.create_document_with_id(thread_id);
+            }
+            for thread_id in &orphan_threads {
+                batch.delete_document(thread_id);
+            }
+            if report.used_quota != report.expected_quota {
+                batch.add(
+                    DirectoryClass::UsedQuota(account_id),
+                    report.expected_quota - report.used_quota,
+                );
+            }
+            if !batch.is_empty() {
+                self.core
+                    .storage
+                    .data
+                    .write(batch.build())
+                    .await
+                    .caused_by(trc::location!())?;
+            }
+        }
+
+        report.missing_document_ids = missing_document_ids.into_iter().collect();
+        report.orphan_document_ids = orphan_document_ids.into_iter().collect();
+        report.missing_threads = missing_threads.into_iter().collect();
+        report.orphan_threads = orphan_threads.into_iter().collect();
+        report.tags = tags
+            .into_iter()
+            .map(|((property, value), missing, stale)| FsckTag {
+                value: match value {
+                    TagValue::Id(id) if property == Property::Keywords => Keyword::try_from_id(id)
+                        .map(|keyword| keyword.to_string())
+                        .unwrap_or_else(|| id.to_string()),
+                    TagValue::Id(id) => id.to_string(),
+                    TagValue::Text(text) => String::from_utf8_lossy(&text).into_owned(),
+                },
+                property: property.to_string(),
+                missing: missing.into_iter().collect(),
+                stale: stale.into_iter().collect(),
+            })
+            .collect();
+
+        // Verify mailbox counters after the tags have been repaired
+        report.mailbox_counters = self.mailbox_check_counters(account_id, repair).await?;
+
+        if !report.is_consistent() {
+            trc::event!(
+                Store(trc::StoreEvent::DataCorruption),
+                AccountId = account_id,
+                Details = "Account consistency check failed",
+                Result = serde_json::to_string(&report).unwrap_or_default(),
+            );
+        }
+
+        Ok(report)
+    }
+
+    async fn fsck_tags(
+        &self,
+        account_id: u32,
+        property: Property,
+        tags: &mut AHashMap<TagKey, RoaringBitmap>,
+    ) -> trc::Result<()> {
+        // Tags are stored either as ids or as text, each in their own key range
+        for (from_value, to_value) in [
+            (TagValue::Id(0), TagValue::Id(u32::MAX)),
+            (TagValue::Text(vec![]), TagValue::Text(vec![u8::MAX; 32])),
+        ] {
+            let is_text = matches!(from_value, TagValue::Text(_));
+            self.core
+                .storage
+                .data
+                .iterate(
+                    IterateParams::new(
+                        BitmapKey {
+                            account_id,
+                            collection: Collection::Email.into(),
+                            class: BitmapClass::Tag {
+                                field: property.clone().into(),
+                                value: from_value,
+                            },
+                            document_id: 0,
+                        },
+                        BitmapKey {
+                            account_id,
+                            collection: Collection::Email.into(),
+                            class: BitmapClass::Tag {
+                                field: property.clone().into(),
+                                value: to_value,
+                            },
+                            document_id: u32::MAX,
+                        },
+                    )
+                    .no_values(),
+                    |key, _| {
+                        let document_id = key.deserialize_be_u32(key.len() - U32_LEN)?;
+                        let value = key
+                            .get(U32_LEN + 2..key.len() - U32_LEN)
+                            .and_then(|bytes| {
+                                if is_text {
+                                    Some(TagValue::Text(bytes.to_vec()))
+                                } else {
+                                    bytes.read_leb128::<u32>().map(|(id, _)| TagValue::Id(id))
+                                }
+                            })
+                            .ok_or_else(|| {
+                                trc::Error::corrupted_key(key, None, trc::location!())
+                            })?;
+                        tags.entry((property.clone(), value))
+                            .or_default()
+                            .insert(document_id);
+
+                        Ok(true)
+                    },
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
+    async fn fsck_quota(&self, account_id: u32) -> trc::Result<i64> {
+        // Add the size of all messages, including the ones pending to be purged
+        let mut quota = 0i64;
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    IndexKeyPrefix {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        field: Property::Size.into(),
+                    },
+                    IndexKeyPrefix {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        field: u8::from(Property::Size) + 1,
+                    },
+                )
+                .no_values(),
+                |key, _| {
+                    key.get(IndexKeyPrefix::len()..key.len() - U32_LEN)
+                        .ok_or_else(|| trc::Error::corrupted_key(key, None, trc::location!()))
+                        .and_then(u32::deserialize)
+                        .map(|size| {
+                            quota += size as i64;
+                        })?;
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        // Add the size of all Sieve scripts
+        for (_, script) in self
+            .get_properties::<Object<Value>, _, _>(
+                account_id,
+                Collection::SieveScript,
+                &(),
+                Property::Value,
+            )
+            .await?
+        {
+            if let Some(section) = script
+                .blob_id()
+                .and_then(|blob_id| blob_id.section.as_ref())
+            {
+                quota += section.size as i64;
+            }
+        }
+
+        Ok(quota)
+    }
+}
+
+impl FsckReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_document_ids.is_empty()
+            && self.orphan_document_ids.is_empty()
+            && self.tags.is_empty()
+            && self.missing_threads.is_empty()
+            && self.orphan_threads.is_empty()
+            && self.used_quota == self.expected_quota
+            && self.mailbox_counters.is_empty()
+    }
+}
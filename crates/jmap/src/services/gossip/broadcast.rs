@@ -0,0 +1,67 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::net::IpAddr;
+
+use common::{core::BuildServer, ipc::BroadcastEvent};
+use smtp::core::reputation::SmtpReputation;
+use trc::{ClusterEvent, SecurityEvent};
+
+use super::{request::Request, Gossiper};
+
+impl Gossiper {
+    pub async fn broadcast_events(&self, events: Vec<BroadcastEvent>) {
+        for peer in &self.peers {
+            if peer.is_healthy() {
+                self.send_gossip(peer.addr, Request::Broadcast(events.clone()))
+                    .await;
+            }
+        }
+    }
+
+    pub fn handle_broadcast(&self, addr: IpAddr, events: Vec<BroadcastEvent>) {
+        if events.is_empty() || !self.is_known_peer(&addr) {
+            trc::event!(Cluster(ClusterEvent::EmptyPacket), RemoteIp = addr);
+            return;
+        }
+
+        let server = self.inner.build_server();
+        tokio::spawn(async move {
+            for event in events {
+                match event {
+                    BroadcastEvent::ReputationUpdate { entities, signal } => {
+                        if server.core.network.cluster_share.reputation {
+                            if let Err(err) = server.reputation_apply(&entities, signal).await {
+                                trc::error!(err
+                                    .ctx(trc::Key::RemoteIp, addr)
+                                    .details("Failed to apply peer reputation update"));
+                            }
+                        }
+                    }
+                    BroadcastEvent::BlockedIp(ip) => {
+                        if server.core.network.cluster_share.auto_ban {
+                            match server.block_cluster_ip(ip).await {
+                                Ok(true) => {
+                                    trc::event!(
+                                        Security(SecurityEvent::IpBlocked),
+                                        RemoteIp = ip,
+                                        Details = "Banned by cluster peer",
+                                    );
+                                }
+                                Ok(false) => {}
+                                Err(err) => {
+                                    trc::error!(err
+                                        .ctx(trc::Key::RemoteIp, ip)
+                                        .details("Failed to block IP banned by cluster peer"));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
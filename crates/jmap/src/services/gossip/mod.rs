@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod broadcast;
 pub mod heartbeat;
 pub mod leave;
 pub mod peer;
@@ -26,6 +27,7 @@ use self::request::Request;
 const UDP_MAX_PAYLOAD: usize = 65500;
 const HEARTBEAT_WINDOW: usize = 1 << 10;
 const HEARTBEAT_WINDOW_MASK: usize = HEARTBEAT_WINDOW - 1;
+const BROADCAST_BATCH_SIZE: usize = 32;
 
 pub type EpochId = u64;
 pub type GenerationId = u8;
@@ -4,10 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::{
-    core::BuildServer,
-    ipc::{HousekeeperEvent, QueueEvent},
-};
+use common::{core::BuildServer, ipc::QueueEvent};
 use trc::ClusterEvent;
 
 use crate::services::index::Indexer;
@@ -184,40 +181,7 @@ impl Gossiper {
             let server = self.inner.build_server();
 
             tokio::spawn(async move {
-                let result = if update_config {
-                    server.reload().await
-                } else {
-                    server.reload_blocked_ips().await
-                };
-                match result {
-                    Ok(result) => {
-                        if let Some(new_core) = result.new_core {
-                            // Update core
-                            server.inner.shared_core.store(new_core.into());
-
-                            // Reload ACME
-                            if server
-                                .inner
-                                .ipc
-                                .housekeeper_tx
-                                .send(HousekeeperEvent::ReloadSettings)
-                                .await
-                                .is_err()
-                            {
-                                trc::event!(
-                                    Server(trc::ServerEvent::ThreadError),
-                                    Details = "Failed to send setting reload event to housekeeper",
-                                    CausedBy = trc::location!(),
-                                );
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        trc::error!(err
-                            .details("Failed to reload settings")
-                            .caused_by(trc::location!()));
-                    }
-                }
+                server.reload_from_cluster(update_config).await;
             });
         }
     }
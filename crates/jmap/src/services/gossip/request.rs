@@ -6,7 +6,11 @@
 
 use super::{EpochId, PeerStatus};
 
-use common::auth::oauth::crypto::SymmetricEncrypt;
+use common::{
+    auth::oauth::crypto::SymmetricEncrypt,
+    config::smtp::session::{ReputationEntity, ReputationKind, ReputationSignal},
+    ipc::BroadcastEvent,
+};
 use std::net::IpAddr;
 use utils::codec::leb128::Leb128_;
 
@@ -15,16 +19,24 @@ pub enum Request {
     Ping(Vec<PeerStatus>),
     Pong(Vec<PeerStatus>),
     Leave(Vec<PeerStatus>),
+    Broadcast(Vec<BroadcastEvent>),
 }
 
 impl Request {
     const PING: u8 = 0;
     const PONG: u8 = 1;
     const LEAVE: u8 = 2;
+    const BROADCAST: u8 = 3;
+
+    const EVENT_REPUTATION: u8 = 0;
+    const EVENT_BLOCKED_IP: u8 = 1;
 
     pub fn from_bytes(bytes: &[u8]) -> Option<Request> {
         let mut it = bytes.iter();
         let flags = it.next().copied()?;
+        if flags == Self::BROADCAST {
+            return Self::broadcast_from_bytes(it);
+        }
         let is_ipv6 = flags & (1 << 7) != 0;
 
         let mut peers = Vec::with_capacity(bytes.len() / std::mem::size_of::<PeerStatus>());
@@ -73,6 +85,7 @@ impl Request {
             Request::Ping(peers) => (Self::PING, peers),
             Request::Pong(peers) => (Self::PONG, peers),
             Request::Leave(peers) => (Self::LEAVE, peers),
+            Request::Broadcast(events) => return Self::broadcast_to_bytes(events),
         };
 
         debug_assert!(!peers.is_empty());
@@ -113,4 +126,110 @@ impl Request {
 
         bytes
     }
+
+    fn broadcast_from_bytes<'x>(mut it: impl Iterator<Item = &'x u8>) -> Option<Request> {
+        let mut events = Vec::new();
+
+        while let Some(event_type) = it.next().copied() {
+            match event_type {
+                Self::EVENT_REPUTATION => {
+                    let signal = match it.next().copied()? {
+                        0 => ReputationSignal::Ham,
+                        1 => ReputationSignal::Spam,
+                        2 => ReputationSignal::Bounce,
+                        3 => ReputationSignal::Complaint,
+                        _ => return None,
+                    };
+                    let num_entities = usize::from_leb128_it(&mut it)?;
+                    let mut entities = Vec::with_capacity(num_entities.min(16));
+                    for _ in 0..num_entities {
+                        let kind = match it.next().copied()? {
+                            0 => ReputationKind::Ip,
+                            1 => ReputationKind::Domain,
+                            2 => ReputationKind::Dkim,
+                            _ => return None,
+                        };
+                        let len = usize::from_leb128_it(&mut it)?;
+                        let value = (&mut it).take(len).copied().collect::<Vec<_>>();
+                        if value.len() != len {
+                            return None;
+                        }
+                        entities.push(ReputationEntity {
+                            kind,
+                            value: String::from_utf8(value).ok()?,
+                        });
+                    }
+                    events.push(BroadcastEvent::ReputationUpdate { entities, signal });
+                }
+                Self::EVENT_BLOCKED_IP => {
+                    let ip = match it.next().copied()? {
+                        4 => {
+                            let mut octets = [0u8; 4];
+                            for octet in octets.iter_mut() {
+                                *octet = *it.next()?;
+                            }
+                            IpAddr::V4(octets.into())
+                        }
+                        6 => {
+                            let mut octets = [0u8; 16];
+                            for octet in octets.iter_mut() {
+                                *octet = *it.next()?;
+                            }
+                            IpAddr::V6(octets.into())
+                        }
+                        _ => return None,
+                    };
+                    events.push(BroadcastEvent::BlockedIp(ip));
+                }
+                _ => return None,
+            }
+        }
+
+        Request::Broadcast(events).into()
+    }
+
+    fn broadcast_to_bytes(events: &[BroadcastEvent]) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(1 + (events.len() * 32) + SymmetricEncrypt::ENCRYPT_TAG_LEN);
+        bytes.push(Self::BROADCAST);
+
+        for event in events {
+            match event {
+                BroadcastEvent::ReputationUpdate { entities, signal } => {
+                    bytes.push(Self::EVENT_REPUTATION);
+                    bytes.push(match signal {
+                        ReputationSignal::Ham => 0,
+                        ReputationSignal::Spam => 1,
+                        ReputationSignal::Bounce => 2,
+                        ReputationSignal::Complaint => 3,
+                    });
+                    entities.len().to_leb128_bytes(&mut bytes);
+                    for entity in entities {
+                        bytes.push(match entity.kind {
+                            ReputationKind::Ip => 0,
+                            ReputationKind::Domain => 1,
+                            ReputationKind::Dkim => 2,
+                        });
+                        entity.value.len().to_leb128_bytes(&mut bytes);
+                        bytes.extend_from_slice(entity.value.as_bytes());
+                    }
+                }
+                BroadcastEvent::BlockedIp(ip) => {
+                    bytes.push(Self::EVENT_BLOCKED_IP);
+                    match ip {
+                        IpAddr::V4(addr) => {
+                            bytes.push(4);
+                            bytes.extend_from_slice(addr.octets().as_slice());
+                        }
+                        IpAddr::V6(addr) => {
+                            bytes.push(6);
+                            bytes.extend_from_slice(addr.octets().as_slice());
+                        }
+                    }
+                }
+            }
+        }
+
+        bytes
+    }
 }
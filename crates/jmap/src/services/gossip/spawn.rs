@@ -5,9 +5,9 @@
  */
 
 use super::request::Request;
-use super::{Gossiper, Peer, UDP_MAX_PAYLOAD};
+use super::{Gossiper, Peer, BROADCAST_BATCH_SIZE, UDP_MAX_PAYLOAD};
 use common::auth::oauth::crypto::SymmetricEncrypt;
-use common::{Inner, IPC_CHANNEL_BUFFER};
+use common::{ipc::BroadcastEvent, Inner, IPC_CHANNEL_BUFFER};
 use std::net::IpAddr;
 use std::time::{Duration, Instant};
 use std::{net::SocketAddr, sync::Arc};
@@ -62,7 +62,12 @@ impl GossiperBuilder {
         builder.into()
     }
 
-    pub async fn spawn(self, inner: Arc<Inner>, mut shutdown_rx: watch::Receiver<bool>) {
+    pub async fn spawn(
+        self,
+        inner: Arc<Inner>,
+        mut broadcast_rx: mpsc::Receiver<BroadcastEvent>,
+        mut shutdown_rx: watch::Receiver<bool>,
+    ) {
         // Bind port
         let quidnunc = Arc::new(Quidnunc {
             socket: match UdpSocket::bind(SocketAddr::new(self.bind_addr, self.port)).await {
@@ -165,6 +170,9 @@ impl GossiperBuilder {
                                                 Request::Leave(peers) => {
                                                     gossiper.handle_leave(peers).await;
                                                 },
+                                                Request::Broadcast(events) => {
+                                                    gossiper.handle_broadcast(addr.ip(), events);
+                                                },
                                             }
                                         } else {
                                             trc::event!(
@@ -196,6 +204,18 @@ impl GossiperBuilder {
                             }
                         }
                     },
+                    Some(event) = broadcast_rx.recv() => {
+                        // Share local decisions with other nodes
+                        let mut events = vec![event];
+                        while events.len() < BROADCAST_BATCH_SIZE {
+                            if let Ok(event) = broadcast_rx.try_recv() {
+                                events.push(event);
+                            } else {
+                                break;
+                            }
+                        }
+                        gossiper.broadcast_events(events).await;
+                    },
                     _ = tokio::time::sleep(wait) => {
                         // Send ping
                         gossiper.ping_peers().await;
@@ -14,7 +14,8 @@ use common::{
     config::telemetry::OtelMetrics,
     core::BuildServer,
     ipc::{HousekeeperEvent, PurgeType},
-    Inner,
+    listener::{certificates::CertificateWatcher, geoip::GeoIpWatcher},
+    Inner, Server,
 };
 
 #[cfg(feature = "enterprise")]
@@ -29,7 +30,11 @@ use tokio::sync::mpsc;
 use trc::{Collector, MetricType};
 use utils::map::ttl_dashmap::TtlMap;
 
-use crate::{email::delete::EmailDeletion, JmapMethods, LONG_SLUMBER};
+use crate::{
+    api::management::dkim::DkimManagement,
+    email::{delete::EmailDeletion, quarantine::EmailQuarantine},
+    JmapMethods, LONG_SLUMBER,
+};
 
 #[derive(PartialEq, Eq)]
 struct Action {
@@ -43,6 +48,13 @@ enum ActionClass {
     Account,
     Store(usize),
     Acme(String),
+    CertificateSource(String),
+    AddressFilter(String),
+    GeoIp,
+    QuarantineDigest,
+    DkimRotation,
+    SecretLeases,
+    FlushCounters,
     OtelMetrics,
     #[cfg(feature = "enterprise")]
     InternalMetrics,
@@ -58,6 +70,11 @@ struct Queue {
     heap: BinaryHeap<Action>,
 }
 
+const DKIM_ROTATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const FILE_WATCH_DELAY: Duration = Duration::from_secs(2);
+const SECRET_LEASES_INTERVAL: Duration = Duration::from_secs(60);
+const ADDRESS_FILTER_DELAY: Duration = Duration::from_secs(2);
+
 #[cfg(feature = "enterprise")]
 const METRIC_ALERTS_INTERVAL: Duration = Duration::from_secs(5 * 60);
 
@@ -68,6 +85,9 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
 
         // Add all events to queue
         let mut queue = Queue::default();
+        let mut _certificate_watchers;
+        let mut _geoip_watcher;
+        let mut geoip_settings;
         {
             let server = inner.build_server();
 
@@ -91,6 +111,39 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                 );
             }
 
+            // Quarantine digests
+            if let Some(digest) = server
+                .core
+                .jmap
+                .spam_quarantine
+                .as_ref()
+                .and_then(|quarantine| quarantine.digest.as_ref())
+            {
+                queue.schedule(
+                    Instant::now() + digest.frequency.time_to_next(),
+                    ActionClass::QuarantineDigest,
+                );
+            }
+
+            // DKIM key rotation
+            if !server.core.smtp.mail_auth.rotations.is_empty() {
+                queue.schedule(Instant::now(), ActionClass::DkimRotation);
+            }
+
+            // Configuration secret lease renewal
+            queue.schedule(
+                Instant::now() + SECRET_LEASES_INTERVAL,
+                ActionClass::SecretLeases,
+            );
+
+            // Coalesced counter writes
+            if let Some(coalescer) = &server.inner.data.counter_coalescer {
+                queue.schedule(
+                    Instant::now() + coalescer.interval,
+                    ActionClass::FlushCounters,
+                );
+            }
+
             // OTEL Push Metrics
             if let Some(otel) = &server.core.metrics.otel {
                 OtelMetrics::enable_errors();
@@ -100,6 +153,26 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
             // Calculate expensive metrics
             queue.schedule(Instant::now(), ActionClass::CalculateMetrics);
 
+            // Load certificates from external sources
+            _certificate_watchers = watch_certificate_sources(&server);
+            for source in server.core.certificates.sources.values() {
+                queue.schedule(
+                    Instant::now(),
+                    ActionClass::CertificateSource(source.id.clone()),
+                );
+            }
+
+            // Build the address filters of directories
+            for (id, directory) in &server.core.storage.directories {
+                if directory.filter.is_some() {
+                    queue.schedule(Instant::now(), ActionClass::AddressFilter(id.clone()));
+                }
+            }
+
+            // Watch the GeoIP database for updates
+            geoip_settings = server.core.network.geoip.clone();
+            _geoip_watcher = watch_geoip(&server);
+
             // Add all ACME renewals to heap
             for provider in server.core.acme.providers.values() {
                 match server.init_acme(provider).await {
@@ -168,6 +241,29 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                             _ => {}
                         }
 
+                        // Reload quarantine digests
+                        if let Some(digest) = server
+                            .core
+                            .jmap
+                            .spam_quarantine
+                            .as_ref()
+                            .and_then(|quarantine| quarantine.digest.as_ref())
+                        {
+                            if !queue.has_action(&ActionClass::QuarantineDigest) {
+                                queue.schedule(
+                                    Instant::now() + digest.frequency.time_to_next(),
+                                    ActionClass::QuarantineDigest,
+                                );
+                            }
+                        }
+
+                        // Reload DKIM key rotation
+                        if !server.core.smtp.mail_auth.rotations.is_empty()
+                            && !queue.has_action(&ActionClass::DkimRotation)
+                        {
+                            queue.schedule(Instant::now(), ActionClass::DkimRotation);
+                        }
+
                         // SPDX-SnippetBegin
                         // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
                         // SPDX-License-Identifier: LicenseRef-SEL
@@ -197,6 +293,32 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                         }
                         // SPDX-SnippetEnd
 
+                        // Reload certificate sources
+                        server.unload_certificate_sources();
+                        _certificate_watchers = watch_certificate_sources(&server);
+                        for source in server.core.certificates.sources.values() {
+                            let action = ActionClass::CertificateSource(source.id.clone());
+                            queue.remove_action(&action);
+                            queue.schedule(Instant::now(), action);
+                        }
+
+                        // Rebuild the address filters of reloaded directories
+                        for (id, directory) in &server.core.storage.directories {
+                            let action = ActionClass::AddressFilter(id.clone());
+                            queue.remove_action(&action);
+                            if directory.filter.is_some() {
+                                queue.schedule(Instant::now(), action);
+                            }
+                        }
+
+                        // Reload GeoIP database
+                        if geoip_settings != server.core.network.geoip {
+                            geoip_settings = server.core.network.geoip.clone();
+                            _geoip_watcher = watch_geoip(&server);
+                            queue.remove_action(&ActionClass::GeoIp);
+                            queue.schedule(Instant::now(), ActionClass::GeoIp);
+                        }
+
                         // Reload ACME certificates
                         tokio::spawn(async move {
                             for provider in server.core.acme.providers.values() {
@@ -229,6 +351,28 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                         queue.remove_action(&action);
                         queue.schedule(renew_at, action);
                     }
+                    HousekeeperEvent::CertificateSourceChanged(source_id) => {
+                        // Wait for pending writes to complete before reloading
+                        let action = ActionClass::CertificateSource(source_id);
+                        queue.remove_action(&action);
+                        queue.schedule(Instant::now() + FILE_WATCH_DELAY, action);
+                    }
+                    HousekeeperEvent::AddressesChanged => {
+                        // Wait for further changes before rebuilding the filters
+                        let server = inner.build_server();
+                        for (id, directory) in &server.core.storage.directories {
+                            if directory.filter.is_some() {
+                                let action = ActionClass::AddressFilter(id.clone());
+                                queue.remove_action(&action);
+                                queue.schedule(Instant::now() + ADDRESS_FILTER_DELAY, action);
+                            }
+                        }
+                    }
+                    HousekeeperEvent::GeoIpChanged => {
+                        // Wait for pending writes to complete before reloading
+                        queue.remove_action(&ActionClass::GeoIp);
+                        queue.schedule(Instant::now() + FILE_WATCH_DELAY, ActionClass::GeoIp);
+                    }
                     HousekeeperEvent::Purge(purge) => match purge {
                         PurgeType::Data(store) => {
                             // SPDX-SnippetBegin
@@ -318,6 +462,7 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                         }
                     },
                     HousekeeperEvent::Exit => {
+                        inner.build_server().flush_counters().await;
                         trc::event!(Housekeeper(trc::HousekeeperEvent::Stop));
 
                         return;
@@ -378,6 +523,66 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                     }
                                 });
                             }
+                            ActionClass::CertificateSource(source_id) => {
+                                if let Some(source) =
+                                    server.core.certificates.sources.get(&source_id).cloned()
+                                {
+                                    if let Some(refresh) = source.refresh {
+                                        queue.schedule(
+                                            Instant::now() + refresh,
+                                            ActionClass::CertificateSource(source_id),
+                                        );
+                                    }
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        if let Err(err) =
+                                            server.reload_certificate_source(&source).await
+                                        {
+                                            trc::error!(
+                                                err.details("Failed to reload certificates.")
+                                            );
+                                        }
+                                    });
+                                }
+                            }
+                            ActionClass::AddressFilter(directory_id) => {
+                                if let Some(directory) =
+                                    server.get_directory(&directory_id).cloned()
+                                {
+                                    if let Some(filter) = &directory.filter {
+                                        queue.schedule(
+                                            Instant::now() + filter.refresh,
+                                            ActionClass::AddressFilter(directory_id),
+                                        );
+
+                                        let server = server.clone();
+                                        tokio::spawn(async move {
+                                            let result =
+                                                match server.disposable_alias_addresses().await {
+                                                    Ok(aliases) => {
+                                                        directory.refresh_filter(aliases).await
+                                                    }
+                                                    Err(err) => Err(err),
+                                                };
+
+                                            if let Err(err) = result {
+                                                trc::error!(
+                                                    err.details("Failed to build address filter.")
+                                                );
+                                            }
+                                        });
+                                    }
+                                }
+                            }
+                            ActionClass::GeoIp => {
+                                let server = server.clone();
+                                tokio::spawn(async move {
+                                    if let Err(err) = server.reload_geoip().await {
+                                        trc::error!(err.details("Failed to reload GeoIP database."));
+                                    }
+                                });
+                            }
                             ActionClass::Account => {
                                 let server = server.clone();
                                 queue.schedule(
@@ -385,10 +590,14 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                         + server.core.jmap.account_purge_frequency.time_to_next(),
                                     ActionClass::Account,
                                 );
-                                tokio::spawn(async move {
-                                    trc::event!(Housekeeper(trc::HousekeeperEvent::PurgeAccounts));
-                                    server.purge_accounts().await;
-                                });
+                                if !server.is_read_only() {
+                                    tokio::spawn(async move {
+                                        trc::event!(Housekeeper(
+                                            trc::HousekeeperEvent::PurgeAccounts
+                                        ));
+                                        server.purge_accounts().await;
+                                    });
+                                }
                             }
                             ActionClass::Session => {
                                 let server = server.clone();
@@ -426,6 +635,9 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                         Instant::now() + schedule.cron.time_to_next(),
                                         ActionClass::Store(idx),
                                     );
+                                    if server.is_read_only() {
+                                        continue;
+                                    }
                                     tokio::spawn(async move {
                                         let (class, result) = match schedule.store {
                                             PurgeStore::Data(store) => {
@@ -457,6 +669,70 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                     });
                                 }
                             }
+                            ActionClass::QuarantineDigest => {
+                                if let Some(digest) = server
+                                    .core
+                                    .jmap
+                                    .spam_quarantine
+                                    .as_ref()
+                                    .and_then(|quarantine| quarantine.digest.as_ref())
+                                {
+                                    queue.schedule(
+                                        Instant::now() + digest.frequency.time_to_next(),
+                                        ActionClass::QuarantineDigest,
+                                    );
+                                    if server.is_read_only() {
+                                        continue;
+                                    }
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        if let Err(err) = server.quarantine_send_digests().await {
+                                            trc::error!(
+                                                err.details("Failed to send quarantine digests.")
+                                            );
+                                        }
+                                    });
+                                }
+                            }
+                            ActionClass::DkimRotation => {
+                                if !server.core.smtp.mail_auth.rotations.is_empty() {
+                                    queue.schedule(
+                                        Instant::now() + DKIM_ROTATION_INTERVAL,
+                                        ActionClass::DkimRotation,
+                                    );
+                                    if server.is_read_only() {
+                                        continue;
+                                    }
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        if let Err(err) = server.dkim_rotate_keys().await {
+                                            trc::error!(err.details("Failed to rotate DKIM keys."));
+                                        }
+                                    });
+                                }
+                            }
+                            ActionClass::SecretLeases => {
+                                queue.schedule(
+                                    Instant::now() + SECRET_LEASES_INTERVAL,
+                                    ActionClass::SecretLeases,
+                                );
+                                tokio::spawn(utils::config::secrets::renew_leases());
+                            }
+                            ActionClass::FlushCounters => {
+                                if let Some(coalescer) = &server.inner.data.counter_coalescer {
+                                    queue.schedule(
+                                        Instant::now() + coalescer.interval,
+                                        ActionClass::FlushCounters,
+                                    );
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        server.flush_counters().await;
+                                    });
+                                }
+                            }
                             ActionClass::OtelMetrics => {
                                 if let Some(otel) = &server.core.metrics.otel {
                                     queue.schedule(
@@ -540,6 +816,20 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                                 );
                                             }
                                         }
+
+                                        match server.blob_dedup_ratio().await {
+                                            Ok(ratio) => {
+                                                Collector::update_gauge(
+                                                    MetricType::BlobDedupRatio,
+                                                    ratio,
+                                                );
+                                            }
+                                            Err(err) => {
+                                                trc::error!(err.details(
+                                                    "Failed to obtain blob deduplication ratio"
+                                                ));
+                                            }
+                                        }
                                     }
 
                                     match tokio::task::spawn_blocking(memory_stats::memory_stats)
@@ -662,6 +952,43 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
     });
 }
 
+fn watch_certificate_sources(server: &Server) -> Vec<CertificateWatcher> {
+    server
+        .core
+        .certificates
+        .sources
+        .values()
+        .filter_map(|source| {
+            source
+                .watch(server.inner.ipc.housekeeper_tx.clone())
+                .unwrap_or_else(|err| {
+                    trc::event!(
+                        Tls(trc::TlsEvent::CertificateSourceError),
+                        Id = source.id.clone(),
+                        Reason = err,
+                    );
+                    None
+                })
+        })
+        .collect()
+}
+
+fn watch_geoip(server: &Server) -> Option<GeoIpWatcher> {
+    server
+        .core
+        .network
+        .geoip
+        .as_ref()?
+        .watch(server.inner.ipc.housekeeper_tx.clone())
+        .unwrap_or_else(|err| {
+            trc::error!(trc::ResourceEvent::Error
+                .into_err()
+                .details("Failed to watch GeoIP database.")
+                .reason(err));
+            None
+        })
+}
+
 impl Queue {
     pub fn schedule(&mut self, due: Instant, event: ActionClass) {
         trc::event!(
@@ -4,7 +4,10 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use common::{core::BuildServer, Inner, Server};
 use directory::{
@@ -30,7 +33,7 @@ use utils::{BlobHash, BLOB_HASH_LEN};
 use crate::{
     blob::download::BlobDownload,
     changes::write::ChangeLog,
-    email::{index::IndexMessageText, metadata::MessageMetadata},
+    email::{fallback::EmailFtsFallback, index::IndexMessageText, metadata::MessageMetadata},
     JmapMethods,
 };
 
@@ -61,12 +64,14 @@ pub fn spawn_index_task(inner: Arc<Inner>) {
 pub trait Indexer: Sync + Send {
     fn fts_index_queued(&self) -> impl Future<Output = ()> + Send;
     fn try_lock_index(&self, event: &IndexEmail) -> impl Future<Output = bool> + Send;
+    fn unlock_index(&self, event: &IndexEmail) -> impl Future<Output = ()> + Send;
     fn reindex(
         &self,
         account_id: Option<u32>,
         tenant_id: Option<u32>,
     ) -> impl Future<Output = trc::Result<()>> + Send;
     fn request_fts_index(&self);
+    fn schedule_fts_index(&self, delay: Duration);
 }
 
 impl Indexer for Server {
@@ -125,6 +130,9 @@ impl Indexer for Server {
 
         // Add entries to the index
         for event in entries {
+            // Give way to interactive requests
+            self.yield_to_interactive().await;
+
             let op_start = Instant::now();
             // Lock index
             if !self.try_lock_index(&event).await {
@@ -173,8 +181,14 @@ impl Indexer for Server {
                             .document_id(event.document_id)
                             .details("Failed to index email in FTS index"));
 
-                        continue;
+                        // The backend is most likely unavailable, release the lock
+                        // and replay the remaining entries later
+                        self.set_fts_degraded(true);
+                        self.unlock_index(&event).await;
+                        self.schedule_fts_index(self.core.jmap.fts_retry_interval);
+                        break;
                     }
+                    self.set_fts_degraded(false);
 
                     trc::event!(
                         FtsIndex(FtsIndexEvent::Index),
@@ -260,10 +274,33 @@ impl Indexer for Server {
         }
     }
 
+    async fn unlock_index(&self, event: &IndexEmail) {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(event.account_id)
+            .with_collection(Collection::Email)
+            .update_document(event.document_id)
+            .set(event.value_class(), 0u64.serialize());
+        if let Err(err) = self.core.storage.data.write(batch.build()).await {
+            trc::error!(err
+                .account_id(event.account_id)
+                .document_id(event.document_id)
+                .details("Failed to unlock FTS index"));
+        }
+    }
+
     fn request_fts_index(&self) {
         self.inner.ipc.index_tx.notify_one();
     }
 
+    fn schedule_fts_index(&self, delay: Duration) {
+        let index_tx = self.inner.ipc.index_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            index_tx.notify_one();
+        });
+    }
+
     async fn reindex(&self, account_id: Option<u32>, tenant_id: Option<u32>) -> trc::Result<()> {
         let accounts = if let Some(account_id) = account_id {
             RoaringBitmap::from_sorted_iter([account_id]).unwrap()
@@ -12,12 +12,16 @@ use directory::Permission;
 use jmap_proto::types::{state::StateChange, type_state::DataType};
 use mail_parser::MessageParser;
 use std::future::Future;
-use store::ahash::AHashMap;
+use store::{ahash::AHashMap, write::now};
 
 use crate::{
-    email::ingest::{EmailIngest, IngestEmail, IngestSource},
-    mailbox::INBOX_ID,
+    email::{
+        ingest::{EmailIngest, IngestEmail, IngestSource},
+        quarantine::{quarantine_score, EmailQuarantine, QuarantineMessage},
+    },
+    mailbox::{get::MailboxGet, set::MailboxSet, INBOX_ID},
     sieve::{get::SieveScriptGet, ingest::SieveScriptIngest},
+    vacation::absence::{AbsenceMessage, AbsenceWindows},
 };
 
 use super::state::StateManager;
@@ -27,6 +31,13 @@ pub trait MailDelivery: Sync + Send {
         &self,
         message: IngestMessage,
     ) -> impl Future<Output = Vec<DeliveryResult>> + Send;
+
+    fn subaddress_mailbox(
+        &self,
+        account_id: u32,
+        rcpt: &str,
+        session_id: u64,
+    ) -> impl Future<Output = u32> + Send;
 }
 
 impl MailDelivery for Server {
@@ -68,14 +79,37 @@ impl MailDelivery for Server {
             }
         };
 
+        // Parse the message once, recipients of the same message share its
+        // metadata and only store their own mailboxes and keywords
+        let parsed_message = MessageParser::new().parse(&raw_message);
+        let shared_metadata = message.recipients.len() > 1;
+        let received_at = now();
+
+        // Messages scoring above the quarantine threshold are not delivered
+        let quarantine = self
+            .core
+            .jmap
+            .spam_quarantine
+            .as_ref()
+            .zip(parsed_message.as_ref())
+            .and_then(|(config, parsed_message)| {
+                quarantine_score(parsed_message)
+                    .filter(|score| *score >= config.threshold)
+                    .map(|score| (parsed_message, score))
+            });
+
         // Obtain the UIDs for each recipient
         let mut uids: AHashMap<u32, usize> = AHashMap::with_capacity(message.recipients.len());
         let mut results = Vec::with_capacity(message.recipients.len());
         for rcpt in message.recipients {
-            let uid = match self
-                .email_to_id(&self.core.storage.directory, &rcpt, message.session_id)
-                .await
-            {
+            let uid = match self.disposable_alias_deliver(&rcpt).await {
+                Ok(None) => {
+                    self.email_to_id(&self.core.storage.directory, &rcpt, message.session_id)
+                        .await
+                }
+                result => result,
+            };
+            let uid = match uid {
                 Ok(Some(uid)) => uid,
                 Ok(None) => {
                     // Something went wrong
@@ -109,35 +143,93 @@ impl MailDelivery for Server {
                     .map(|_| token)
             }) {
                 Ok(access_token) => {
-                    // Check if there is an active sieve script
-                    match self.sieve_script_get_active(uid).await {
-                        Ok(Some(active_script)) => {
-                            self.sieve_script_ingest(
-                                &access_token,
-                                &raw_message,
-                                &message.sender_address,
-                                &rcpt,
-                                message.session_id,
-                                active_script,
-                            )
+                    if let Some((parsed_message, score)) = quarantine {
+                        // Hold the message outside of the mailbox
+                        self.email_quarantine(QuarantineMessage {
+                            account_id: uid,
+                            sender: &message.sender_address,
+                            recipient: &rcpt,
+                            message: parsed_message,
+                            blob_hash: &message.message_blob,
+                            size: raw_message.len(),
+                            score,
+                            session_id: message.session_id,
+                        })
+                        .await
+                    } else {
+                        // Forward to a delegate during absence windows
+                        let file_into = if let Some(parsed_message) = &parsed_message {
+                            self.absence_forward(AbsenceMessage {
+                                account_id: uid,
+                                sender: &message.sender_address,
+                                recipient: &rcpt,
+                                raw_message: &raw_message,
+                                message: parsed_message,
+                                session_id: message.session_id,
+                            })
                             .await
-                        }
-                        Ok(None) => {
-                            // Ingest message
+                            .unwrap_or_else(|err| {
+                                trc::error!(err
+                                    .details("Failed to process absence windows.")
+                                    .account_id(uid)
+                                    .span_id(message.session_id)
+                                    .caused_by(trc::location!()));
+                                None
+                            })
+                        } else {
+                            None
+                        };
+
+                        if let Some(mailbox_id) = file_into {
                             self.email_ingest(IngestEmail {
                                 raw_message: &raw_message,
-                                message: MessageParser::new().parse(&raw_message),
+                                message: parsed_message.clone(),
                                 resource: access_token.as_resource_token(),
-                                mailbox_ids: vec![INBOX_ID],
+                                mailbox_ids: vec![mailbox_id],
                                 keywords: vec![],
-                                received_at: None,
+                                received_at: Some(received_at),
                                 source: IngestSource::Smtp,
                                 encrypt: self.core.jmap.encrypt,
+                                shared_metadata,
                                 session_id: message.session_id,
                             })
                             .await
+                        } else {
+                            // Check if there is an active sieve script
+                            match self.sieve_script_get_active(uid).await {
+                                Ok(Some(active_script)) => {
+                                    self.sieve_script_ingest(
+                                        &access_token,
+                                        &raw_message,
+                                        &message.sender_address,
+                                        &rcpt,
+                                        message.session_id,
+                                        active_script,
+                                    )
+                                    .await
+                                }
+                                Ok(None) => {
+                                    // Ingest message
+                                    let mailbox_id = self
+                                        .subaddress_mailbox(uid, &rcpt, message.session_id)
+                                        .await;
+                                    self.email_ingest(IngestEmail {
+                                        raw_message: &raw_message,
+                                        message: parsed_message.clone(),
+                                        resource: access_token.as_resource_token(),
+                                        mailbox_ids: vec![mailbox_id],
+                                        keywords: vec![],
+                                        received_at: Some(received_at),
+                                        source: IngestSource::Smtp,
+                                        encrypt: self.core.jmap.encrypt,
+                                        shared_metadata,
+                                        session_id: message.session_id,
+                                    })
+                                    .await
+                                }
+                                Err(err) => Err(err),
+                            }
                         }
-                        Err(err) => Err(err),
                     }
                 }
 
@@ -215,4 +307,33 @@ impl MailDelivery for Server {
 
         results
     }
+
+    /// Files messages sent to a sub-address such as `user+folder@` into the
+    /// matching folder when enabled for the recipient's domain.
+    async fn subaddress_mailbox(&self, account_id: u32, rcpt: &str, session_id: u64) -> u32 {
+        let Some((folder, create)) = self.core.smtp.session.rcpt.subaddress_folder(rcpt) else {
+            return INBOX_ID;
+        };
+
+        let result = if create {
+            self.mailbox_create_path(account_id, folder)
+                .await
+                .map(|result| result.map(|(document_id, _)| document_id))
+        } else {
+            self.mailbox_get_by_name(account_id, folder).await
+        };
+
+        match result {
+            Ok(Some(document_id)) => document_id,
+            Ok(None) => INBOX_ID,
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to obtain sub-address folder.")
+                    .account_id(account_id)
+                    .span_id(session_id)
+                    .caused_by(trc::location!()));
+                INBOX_ID
+            }
+        }
+    }
 }
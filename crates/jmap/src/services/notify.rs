@@ -0,0 +1,59 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{sync::Arc, time::Duration};
+
+use common::{core::BuildServer, Inner};
+use trc::ClusterEvent;
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn spawn_cache_listener(inner: Arc<Inner>) {
+    tokio::spawn(async move {
+        loop {
+            let server = inner.build_server();
+            let channel = if let Some(channel) = &server.core.network.cluster_notify {
+                channel.clone()
+            } else {
+                return;
+            };
+
+            match server.core.storage.data.listen(&channel).await {
+                Ok(mut rx) => {
+                    while let Some(payload) = rx.recv().await {
+                        inner
+                            .build_server()
+                            .handle_cache_invalidation(&payload)
+                            .await;
+                    }
+
+                    trc::event!(
+                        Cluster(ClusterEvent::Error),
+                        Details = "Cache invalidation listener disconnected",
+                    );
+                }
+                Err(err) if err.matches(trc::EventType::Store(trc::StoreEvent::NotSupported)) => {
+                    trc::error!(err
+                        .details("Cache invalidations require a PostgreSQL data store")
+                        .caused_by(trc::location!()));
+                    return;
+                }
+                Err(err) => {
+                    trc::error!(err
+                        .details("Failed to subscribe to cache invalidations")
+                        .caused_by(trc::location!()));
+                }
+            }
+
+            // Changes made while disconnected are missed, so reload everything
+            tokio::time::sleep(RECONNECT_INTERVAL).await;
+            let server = inner.build_server();
+            server.inner.data.permissions.clear();
+            server.inner.data.access_tokens.clear();
+            server.reload_from_cluster(true).await;
+        }
+    });
+}
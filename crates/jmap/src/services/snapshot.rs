@@ -0,0 +1,592 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, time::Duration};
+
+use common::{auth::AccessToken, Server};
+use jmap_proto::{
+    object::Object,
+    types::{collection::Collection, id::Id, keyword::Keyword, property::Property, value::Value},
+};
+use mail_parser::MessageParser;
+use store::{
+    ahash::{AHashMap, AHashSet},
+    query::log::{Change, Query},
+    roaring::RoaringBitmap,
+    write::{now, BatchBuilder, Bincode, BlobOp},
+    Deserialize, Serialize,
+};
+use trc::AddContext;
+use utils::BlobHash;
+
+use crate::{
+    blob::download::BlobDownload,
+    changes::get::ChangesLookup,
+    email::{
+        ingest::{EmailIngest, IngestEmail, IngestSource},
+        metadata::MessageMetadata,
+    },
+    mailbox::{set::MailboxSet, UidMailbox, INBOX_ID},
+    JmapMethods,
+};
+
+/// A point-in-time copy of the mailboxes of an account and the messages they
+/// contained. The snapshot itself is stored as a blob and, together with the
+/// blobs of the messages it references, is held until the snapshot expires.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotInfo {
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub change_id: u64,
+    pub blob_hash: BlobHash,
+    pub mailboxes: u64,
+    pub emails: u64,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotData {
+    pub mailboxes: Vec<SnapshotMailbox>,
+    pub emails: Vec<SnapshotEmail>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotMailbox {
+    pub document_id: u32,
+    pub parent_id: Option<u32>,
+    pub name: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotEmail {
+    pub blob_hash: BlobHash,
+    pub received_at: u64,
+    pub mailbox_ids: Vec<u32>,
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub account_id: u32,
+    pub snapshot_at: u64,
+    pub restored_emails: u64,
+    pub present_emails: u64,
+    pub failed_emails: u64,
+    pub unrecoverable_emails: u64,
+    pub created_mailboxes: Vec<String>,
+}
+
+pub trait AccountSnapshot: Sync + Send {
+    /// Takes a snapshot of the mailboxes and messages of an account.
+    fn snapshot_create(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<SnapshotInfo>> + Send;
+
+    /// Returns the unexpired snapshots of an account, oldest first.
+    fn snapshot_list(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<SnapshotInfo>>> + Send;
+
+    /// Restores an account, or only the mailbox at the given path, to its state
+    /// at the requested time using the most recent snapshot taken before it.
+    /// Messages and mailboxes that still exist are left untouched, messages
+    /// created after the snapshot and deleted before the restore point are
+    /// reported as unrecoverable.
+    fn snapshot_restore(
+        &self,
+        account_id: u32,
+        restore_at: u64,
+        mailbox: Option<&str>,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<RestoreReport>> + Send;
+
+    /// Deletes all snapshots of an account and releases the blobs they hold.
+    fn snapshot_delete(&self, account_id: u32) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn snapshot_data(
+        &self,
+        info: &SnapshotInfo,
+    ) -> impl Future<Output = trc::Result<SnapshotData>> + Send;
+
+    /// Takes a snapshot of an account if the configured interval has elapsed
+    /// since its last snapshot was taken.
+    fn snapshot_scheduled(&self, account_id: u32) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl AccountSnapshot for Server {
+    async fn snapshot_create(&self, account_id: u32) -> trc::Result<SnapshotInfo> {
+        // The last change id has to be obtained before reading the account
+        let change_id = self
+            .core
+            .storage
+            .data
+            .get_last_change_id(account_id, Collection::Email)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default();
+        let created_at = now();
+        let expires_at = created_at + self.core.jmap.snapshot_retention.as_secs();
+        let mut snapshot = SnapshotData::default();
+
+        // Obtain mailboxes
+        for (document_id, mut mailbox) in self
+            .get_properties::<Object<Value>, _, _>(
+                account_id,
+                Collection::Mailbox,
+                &(),
+                Property::Value,
+            )
+            .await?
+        {
+            if let Some(Value::Text(name)) = mailbox.properties.remove(&Property::Name) {
+                snapshot.mailboxes.push(SnapshotMailbox {
+                    document_id,
+                    parent_id: match mailbox.properties.get(&Property::ParentId) {
+                        Some(Value::Id(parent_id)) if parent_id.document_id() > 0 => {
+                            Some(parent_id.document_id() - 1)
+                        }
+                        _ => None,
+                    },
+                    name,
+                });
+            }
+        }
+
+        // Obtain messages
+        let mut mailbox_ids = self
+            .get_properties::<Vec<UidMailbox>, _, _>(
+                account_id,
+                Collection::Email,
+                &(),
+                Property::MailboxIds,
+            )
+            .await?
+            .into_iter()
+            .collect::<AHashMap<_, _>>();
+        let mut keywords = self
+            .get_properties::<Vec<Keyword>, _, _>(
+                account_id,
+                Collection::Email,
+                &(),
+                Property::Keywords,
+            )
+            .await?
+            .into_iter()
+            .collect::<AHashMap<_, _>>();
+        for (document_id, metadata) in self
+            .get_properties::<Bincode<MessageMetadata>, _, _>(
+                account_id,
+                Collection::Email,
+                &(),
+                Property::BodyStructure,
+            )
+            .await?
+        {
+            // Tombstoned messages have no mailboxes
+            let mailbox_ids = mailbox_ids.remove(&document_id).unwrap_or_default();
+            if !mailbox_ids.is_empty() {
+                snapshot.emails.push(SnapshotEmail {
+                    blob_hash: metadata.inner.blob_hash,
+                    received_at: metadata.inner.received_at,
+                    mailbox_ids: mailbox_ids.into_iter().map(|m| m.mailbox_id).collect(),
+                    keywords: keywords
+                        .remove(&document_id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|keyword| keyword.to_string())
+                        .collect(),
+                });
+            }
+        }
+
+        // Reserve the message blobs until the snapshot expires. Blobs that were
+        // reserved by the previous snapshot are released as the new reservation
+        // outlives the old one.
+        let mut snapshots = self.snapshot_list(account_id).await?;
+        let (previous_expires_at, previous_hashes) = if let Some(info) = snapshots.last() {
+            (
+                info.expires_at,
+                self.snapshot_data(info)
+                    .await?
+                    .emails
+                    .into_iter()
+                    .map(|email| email.blob_hash)
+                    .collect::<AHashSet<_>>(),
+            )
+        } else {
+            (0, AHashSet::new())
+        };
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id);
+        for email in &snapshot.emails {
+            if previous_hashes.contains(&email.blob_hash) {
+                batch.clear(BlobOp::Reserve {
+                    hash: email.blob_hash.clone(),
+                    until: previous_expires_at,
+                });
+            }
+            batch.set(
+                BlobOp::Reserve {
+                    hash: email.blob_hash.clone(),
+                    until: expires_at,
+                },
+                0u32.serialize(),
+            );
+
+            if batch.ops.len() >= 1000 {
+                self.write_batch(batch).await?;
+                batch = BatchBuilder::new();
+                batch.with_account_id(account_id);
+            }
+        }
+
+        // Store the snapshot
+        let mailboxes = snapshot.mailboxes.len() as u64;
+        let emails = snapshot.emails.len() as u64;
+        let bytes = Bincode::new(snapshot).serialize();
+        let blob_hash = BlobHash::from(bytes.as_slice());
+        batch.set(
+            BlobOp::Reserve {
+                hash: blob_hash.clone(),
+                until: expires_at,
+            },
+            0u32.serialize(),
+        );
+        self.write_batch(batch).await?;
+        if !self
+            .core
+            .storage
+            .data
+            .blob_exists(&blob_hash)
+            .await
+            .caused_by(trc::location!())?
+        {
+            self.core
+                .storage
+                .blob
+                .put_blob(blob_hash.as_ref(), &bytes)
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        let info = SnapshotInfo {
+            created_at,
+            expires_at,
+            change_id,
+            blob_hash,
+            mailboxes,
+            emails,
+        };
+        snapshots.push(info.clone());
+        self.lookup_store()
+            .key_set(
+                snapshot_key(account_id),
+                Bincode::new(snapshots).serialize(),
+                self.core.jmap.snapshot_retention.as_secs().into(),
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Manage(trc::ManageEvent::SnapshotCreated),
+            AccountId = account_id,
+            Total = info.emails,
+        );
+
+        Ok(info)
+    }
+
+    async fn snapshot_list(&self, account_id: u32) -> trc::Result<Vec<SnapshotInfo>> {
+        let now = now();
+        self.lookup_store()
+            .key_get::<Bincode<Vec<SnapshotInfo>>>(snapshot_key(account_id))
+            .await
+            .caused_by(trc::location!())
+            .map(|snapshots| {
+                snapshots
+                    .map(|snapshots| snapshots.inner)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|info| info.expires_at > now)
+                    .collect()
+            })
+    }
+
+    async fn snapshot_restore(
+        &self,
+        account_id: u32,
+        restore_at: u64,
+        mailbox: Option<&str>,
+        session_id: u64,
+    ) -> trc::Result<RestoreReport> {
+        let info = self
+            .snapshot_list(account_id)
+            .await?
+            .into_iter()
+            .rev()
+            .find(|info| info.created_at <= restore_at)
+            .ok_or_else(|| {
+                trc::ManageEvent::NotFound
+                    .into_err()
+                    .details("No snapshot found for the requested time")
+            })?;
+        let snapshot = self.snapshot_data(&info).await?;
+        let mut report = RestoreReport {
+            account_id,
+            snapshot_at: info.created_at,
+            ..Default::default()
+        };
+
+        // Build the mailbox paths
+        let names = snapshot
+            .mailboxes
+            .iter()
+            .map(|mailbox| (mailbox.document_id, mailbox))
+            .collect::<AHashMap<_, _>>();
+        let mut paths = Vec::with_capacity(snapshot.mailboxes.len());
+        for mailbox in &snapshot.mailboxes {
+            let mut path = vec![mailbox.name.as_str()];
+            let mut parent_id = mailbox.parent_id;
+            while let Some(parent) = parent_id.and_then(|parent_id| names.get(&parent_id)) {
+                if path.len() > self.core.jmap.mailbox_max_depth {
+                    break;
+                }
+                path.push(parent.name.as_str());
+                parent_id = parent.parent_id;
+            }
+            path.reverse();
+            paths.push((mailbox.document_id, path.join("/")));
+        }
+        if let Some(mailbox) = mailbox {
+            paths.retain(|(_, path)| path == mailbox);
+            if paths.is_empty() {
+                return Err(trc::ManageEvent::NotFound
+                    .into_err()
+                    .details("Mailbox not found in snapshot"));
+            }
+        }
+        paths.sort_unstable_by_key(|(_, path)| path.len());
+
+        // Recreate missing mailboxes
+        let mut mailbox_ids = AHashMap::with_capacity(paths.len());
+        for (document_id, path) in paths {
+            if let Some((mailbox_id, created)) = self
+                .mailbox_create_path(account_id, &path)
+                .await
+                .caused_by(trc::location!())?
+            {
+                if created.is_some() {
+                    report.created_mailboxes.push(path);
+                }
+                mailbox_ids.insert(document_id, mailbox_id);
+            }
+        }
+
+        // Obtain the messages currently in the account, excluding tombstoned ones
+        let document_ids = self
+            .get_properties::<Vec<UidMailbox>, _, _>(
+                account_id,
+                Collection::Email,
+                &(),
+                Property::MailboxIds,
+            )
+            .await?
+            .into_iter()
+            .filter(|(_, mailbox_ids)| !mailbox_ids.is_empty())
+            .map(|(document_id, _)| document_id)
+            .collect::<RoaringBitmap>();
+        let mut present = AHashSet::new();
+        for (_, metadata) in self
+            .get_properties::<Bincode<MessageMetadata>, _, _>(
+                account_id,
+                Collection::Email,
+                &document_ids,
+                Property::BodyStructure,
+            )
+            .await?
+        {
+            present.insert(metadata.inner.blob_hash);
+        }
+
+        // Messages created after the snapshot that no longer exist cannot be restored
+        if mailbox.is_none() && restore_at > info.created_at {
+            if let Some(restore_change_id) = self
+                .inner
+                .data
+                .jmap_id_gen
+                .past_id(Duration::from_secs(now().saturating_sub(restore_at)))
+            {
+                let mut inserted = AHashSet::new();
+                for change in self
+                    .changes_(
+                        account_id,
+                        Collection::Email,
+                        Query::RangeInclusive(info.change_id + 1, restore_change_id),
+                    )
+                    .await?
+                    .changes
+                {
+                    if let Change::Insert(id) = change {
+                        inserted.insert(Id::from(id).document_id());
+                    }
+                }
+                report.unrecoverable_emails = inserted
+                    .into_iter()
+                    .filter(|document_id| !document_ids.contains(*document_id))
+                    .count() as u64;
+            }
+        }
+
+        // Restore missing messages
+        let resource_token = self
+            .get_resource_token(&AccessToken::from_id(u32::MAX), account_id)
+            .await
+            .caused_by(trc::location!())?;
+        for email in snapshot.emails {
+            let restore_ids = email
+                .mailbox_ids
+                .iter()
+                .filter_map(|mailbox_id| mailbox_ids.get(mailbox_id).copied())
+                .collect::<Vec<_>>();
+            if restore_ids.is_empty() && mailbox.is_some() {
+                continue;
+            } else if present.contains(&email.blob_hash) {
+                report.present_emails += 1;
+                continue;
+            }
+
+            let raw_message = if let Some(raw_message) = self
+                .get_blob(&email.blob_hash, 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())?
+            {
+                raw_message
+            } else {
+                report.failed_emails += 1;
+                continue;
+            };
+
+            match self
+                .email_ingest(IngestEmail {
+                    raw_message: &raw_message,
+                    message: MessageParser::new().parse(&raw_message),
+                    resource: resource_token.clone(),
+                    mailbox_ids: if !restore_ids.is_empty() {
+                        restore_ids
+                    } else {
+                        vec![INBOX_ID]
+                    },
+                    keywords: email.keywords.into_iter().map(Keyword::from).collect(),
+                    received_at: email.received_at.into(),
+                    source: IngestSource::Jmap,
+                    encrypt: false,
+                    shared_metadata: false,
+                    session_id,
+                })
+                .await
+            {
+                Ok(_) => {
+                    report.restored_emails += 1;
+                    present.insert(email.blob_hash);
+                }
+                Err(err)
+                    if err.matches(trc::EventType::MessageIngest(
+                        trc::MessageIngestEvent::Error,
+                    )) =>
+                {
+                    trc::error!(err
+                        .account_id(account_id)
+                        .details("Failed to restore message from snapshot"));
+                    report.failed_emails += 1;
+                }
+                Err(err) => {
+                    return Err(err.caused_by(trc::location!()));
+                }
+            }
+        }
+
+        trc::event!(
+            Manage(trc::ManageEvent::SnapshotRestored),
+            AccountId = account_id,
+            Total = report.restored_emails,
+            Details = report.created_mailboxes.as_slice(),
+        );
+
+        Ok(report)
+    }
+
+    async fn snapshot_delete(&self, account_id: u32) -> trc::Result<()> {
+        for info in self.snapshot_list(account_id).await? {
+            let mut batch = BatchBuilder::new();
+            batch.with_account_id(account_id);
+            if let Ok(snapshot) = self.snapshot_data(&info).await {
+                for email in snapshot.emails {
+                    batch.clear(BlobOp::Reserve {
+                        hash: email.blob_hash,
+                        until: info.expires_at,
+                    });
+
+                    if batch.ops.len() >= 1000 {
+                        self.write_batch(batch).await?;
+                        batch = BatchBuilder::new();
+                        batch.with_account_id(account_id);
+                    }
+                }
+            }
+            batch.clear(BlobOp::Reserve {
+                hash: info.blob_hash,
+                until: info.expires_at,
+            });
+            self.write_batch(batch).await?;
+        }
+
+        self.lookup_store()
+            .key_delete(snapshot_key(account_id))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn snapshot_data(&self, info: &SnapshotInfo) -> trc::Result<SnapshotData> {
+        let bytes = self
+            .get_blob(&info.blob_hash, 0..usize::MAX)
+            .await?
+            .ok_or_else(|| {
+                trc::StoreEvent::NotFound
+                    .into_err()
+                    .details("Snapshot blob not found")
+                    .caused_by(trc::location!())
+            })?;
+
+        Bincode::<SnapshotData>::deserialize(&bytes)
+            .map(|snapshot| snapshot.inner)
+            .caused_by(trc::location!())
+    }
+
+    async fn snapshot_scheduled(&self, account_id: u32) -> trc::Result<()> {
+        let interval = if let Some(interval) = self.core.jmap.snapshot_interval {
+            interval
+        } else {
+            return Ok(());
+        };
+
+        if !self
+            .snapshot_list(account_id)
+            .await?
+            .last()
+            .is_some_and(|info| info.created_at + interval.as_secs() > now())
+        {
+            self.snapshot_create(account_id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn snapshot_key(account_id: u32) -> Vec<u8> {
+    format!("snapshot:{account_id}").into_bytes()
+}
@@ -6,7 +6,7 @@
 
 use std::sync::Arc;
 
-use common::Server;
+use common::{config::jmap::settings::VacationStrategy, Server};
 use jmap_proto::{
     method::get::{GetRequest, GetResponse, RequestArguments},
     object::Object,
@@ -23,6 +23,7 @@ use crate::{
     blob::{download::BlobDownload, upload::BlobUpload},
     changes::state::StateManager,
     sieve::SeenIds,
+    vacation::{get::VacationResponseGet, merge::VacationMerge},
     JmapMethods,
 };
 
@@ -51,6 +52,12 @@ pub trait SieveScriptGet: Sync + Send {
         account_id: u32,
         document_id: u32,
     ) -> impl Future<Output = trc::Result<(Sieve, Object<Value>)>> + Send;
+
+    fn sieve_script_source(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> impl Future<Output = trc::Result<(Vec<u8>, Object<Value>)>> + Send;
 }
 
 impl SieveScriptGet for Server {
@@ -148,18 +155,47 @@ impl SieveScriptGet for Server {
 
     async fn sieve_script_get_active(&self, account_id: u32) -> trc::Result<Option<ActiveScript>> {
         // Find the currently active script
-        if let Some(document_id) = self
+        let mut active_ids = self
             .filter(
                 account_id,
                 Collection::SieveScript,
                 vec![Filter::eq(Property::IsActive, 1u32)],
             )
             .await?
-            .results
-            .min()
+            .results;
+
+        // Under the merge strategy an enabled vacation response stays active
+        // alongside the user's script
+        let vacation_id = if active_ids.len() > 1
+            && self.core.jmap.sieve_vacation_strategy == VacationStrategy::Merge
         {
-            let (script, mut script_object) =
+            self.get_vacation_sieve_script_id(account_id)
+                .await?
+                .filter(|vacation_id| active_ids.remove(*vacation_id))
+        } else {
+            None
+        };
+
+        if let Some(document_id) = active_ids.min() {
+            let (mut script, mut script_object) =
                 self.sieve_script_compile(account_id, document_id).await?;
+
+            // Run the vacation rule ahead of the active script
+            if let Some(vacation_id) = vacation_id {
+                match self
+                    .vacation_merge(account_id, vacation_id, document_id)
+                    .await
+                {
+                    Ok(Some(merged_script)) => {
+                        script = merged_script;
+                    }
+                    Ok(None) => (),
+                    Err(err) => {
+                        trc::error!(err.account_id(account_id).document_id(document_id));
+                    }
+                }
+            }
+
             Ok(Some(ActiveScript {
                 document_id,
                 script: Arc::new(script),
@@ -208,6 +244,48 @@ impl SieveScriptGet for Server {
         }
     }
 
+    async fn sieve_script_source(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> trc::Result<(Vec<u8>, Object<Value>)> {
+        // Obtain script object
+        let script_object = self
+            .get_property::<Object<Value>>(
+                account_id,
+                Collection::SieveScript,
+                document_id,
+                Property::Value,
+            )
+            .await?
+            .ok_or_else(|| {
+                trc::StoreEvent::NotFound
+                    .into_err()
+                    .caused_by(trc::location!())
+                    .document_id(document_id)
+            })?;
+
+        // Obtain the script source, which precedes the compiled script
+        let script = if let Some((script_offset, blob_id)) = script_object
+            .properties
+            .get(&Property::BlobId)
+            .and_then(|v| v.as_blob_id())
+            .and_then(|v| (v.section.as_ref()?.size, v).into())
+        {
+            self.get_blob(&blob_id.hash, 0..script_offset).await?
+        } else {
+            None
+        }
+        .ok_or_else(|| {
+            trc::StoreEvent::NotFound
+                .into_err()
+                .caused_by(trc::location!())
+                .document_id(document_id)
+        })?;
+
+        Ok((script, script_object))
+    }
+
     #[allow(clippy::blocks_in_conditions)]
     async fn sieve_script_compile(
         &self,
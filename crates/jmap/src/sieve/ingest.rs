@@ -24,6 +24,10 @@ use crate::{
     email::ingest::{EmailIngest, IngestEmail, IngestSource, IngestedEmail},
     mailbox::{get::MailboxGet, set::MailboxSet, INBOX_ID, TRASH_ID},
     sieve::SeenIdHash,
+    vacation::{
+        absence::is_auto_submitted,
+        replies::{VacationReplies, VacationReply},
+    },
     JmapMethods,
 };
 
@@ -110,6 +114,9 @@ impl SieveScriptIngest for Server {
         let mut do_deliver = false;
 
         let mut new_ids = AHashSet::new();
+        let mut vacation_replies: Option<Vec<VacationReply>> = None;
+        let mut new_replies = Vec::new();
+        let mut last_id = None;
         let mut reject_reason = None;
         let mut messages: Vec<SieveMessage> = vec![SieveMessage {
             raw_message: raw_message.into(),
@@ -224,9 +231,27 @@ impl SieveScriptIngest for Server {
                     }
                     Event::DuplicateId { id, expiry, last } => {
                         let id_hash = SeenIdHash::new(&id, expiry + now);
-                        let seen_id = active_script.seen_ids.ids.contains(&id_hash);
+                        let mut seen_id = active_script.seen_ids.ids.contains(&id_hash);
+                        if !seen_id {
+                            // Vacation replies are tracked per account and survive script updates
+                            if vacation_replies.is_none() {
+                                vacation_replies = self
+                                    .vacation_replies(account_id)
+                                    .await
+                                    .caused_by(trc::location!())?
+                                    .into();
+                            }
+                            seen_id = vacation_replies
+                                .as_deref()
+                                .unwrap_or_default()
+                                .iter()
+                                .any(|reply| &reply.hash == id_hash.hash());
+                        }
                         if !seen_id || last {
+                            last_id = Some((id_hash.clone(), expiry + now));
                             new_ids.insert(id_hash);
+                        } else {
+                            last_id = None;
                         }
 
                         input = seen_id.into();
@@ -355,6 +380,23 @@ impl SieveScriptIngest for Server {
                             };
 
                             if message.raw_message.len() <= self.core.jmap.mail_max_size {
+                                // Track auto-replies sent by the vacation extension
+                                if let Some((id_hash, expires)) = last_id.take().filter(|_| {
+                                    MessageParser::new()
+                                        .parse_headers(message.raw_message.as_ref())
+                                        .is_some_and(|parsed| is_auto_submitted(&parsed))
+                                }) {
+                                    new_replies.extend(recipients.iter().map(|rcpt| {
+                                        VacationReply {
+                                            recipient: rcpt.address_lcase.clone(),
+                                            hash: *id_hash.hash(),
+                                            sent_at: now,
+                                            expires,
+                                        }
+                                    }));
+                                    new_ids.remove(&id_hash);
+                                }
+
                                 trc::event!(
                                     Sieve(SieveEvent::SendMessage),
                                     From = mail_from.clone(),
@@ -486,6 +528,7 @@ impl SieveScriptIngest for Server {
                         received_at: None,
                         source: IngestSource::Smtp,
                         encrypt: self.core.jmap.encrypt,
+                        shared_metadata: false,
                         session_id,
                     })
                     .await
@@ -501,6 +544,11 @@ impl SieveScriptIngest for Server {
             }
         }
 
+        // Save vacation replies
+        if !new_replies.is_empty() {
+            let _ = self.vacation_replies_add(account_id, new_replies).await;
+        }
+
         // Save new ids script changes
         if !new_ids.is_empty() || active_script.seen_ids.has_changes {
             active_script.seen_ids.ids.extend(new_ids);
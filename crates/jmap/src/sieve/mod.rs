@@ -44,6 +44,10 @@ impl SeenIdHash {
             expiry,
         }
     }
+
+    pub fn hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
 }
 
 impl PartialOrd for SeenIdHash {
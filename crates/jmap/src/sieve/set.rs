@@ -6,6 +6,7 @@
 
 use common::{
     auth::{AccessToken, ResourceToken},
+    config::jmap::settings::VacationStrategy,
     Server,
 };
 use jmap_proto::{
@@ -41,6 +42,7 @@ use crate::{
     api::http::HttpSessionData,
     blob::{download::BlobDownload, upload::BlobUpload},
     changes::write::ChangeLog,
+    vacation::get::VacationResponseGet,
     JmapMethods,
 };
 use std::future::Future;
@@ -637,6 +639,20 @@ impl SieveScriptSet for Server {
             .await?
             .results;
 
+        // Under the merge strategy the vacation response is enabled and
+        // disabled independently of the active script
+        if self.core.jmap.sieve_vacation_strategy == VacationStrategy::Merge {
+            if let Some(vacation_id) = self.get_vacation_sieve_script_id(account_id).await? {
+                let vacation_active = active_ids.remove(vacation_id);
+                if activate_id == Some(vacation_id) {
+                    active_ids.clear();
+                    if vacation_active {
+                        active_ids.insert(vacation_id);
+                    }
+                }
+            }
+        }
+
         // Check if script is already active
         if activate_id.map_or(false, |id| active_ids.remove(id)) {
             if active_ids.is_empty() {
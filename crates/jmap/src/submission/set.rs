@@ -34,6 +34,7 @@ use jmap_proto::{
 use mail_parser::{HeaderName, HeaderValue};
 use smtp::{
     core::{Session, SessionData, State},
+    inbound::auth::{check_delegated_sender, DelegatedSender},
     queue::spool::SmtpSpool,
 };
 use smtp_proto::{request::parser::Rfc5321Parser, MailFrom, RcptTo};
@@ -578,6 +579,23 @@ impl EmailSubmissionSet for Server {
                     .with_description("Blob for email not found.")));
             };
 
+        // Validate messages sent on behalf of another account
+        let message = match check_delegated_sender(
+            &*self.get_cached_access_token(account_id).await?,
+            &message,
+        ) {
+            DelegatedSender::Valid => message,
+            DelegatedSender::AddHeader(header) => {
+                let mut message_with_sender = header.into_bytes();
+                message_with_sender.extend_from_slice(&message);
+                message_with_sender
+            }
+            DelegatedSender::Unauthorized { .. } => {
+                return Ok(Err(SetError::new(SetErrorType::ForbiddenFrom)
+                    .with_description("Sender header must contain your own address.")));
+            }
+        };
+
         // Begin local SMTP session
         let mut session =
             Session::<NullIo>::local(self.clone(), instance.clone(), SessionData::default());
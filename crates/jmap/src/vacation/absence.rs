@@ -0,0 +1,298 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{listener::stream::NullIo, Server};
+use jmap_proto::{
+    method::absence::{AbsenceResponse, AbsenceWindow, GetAbsenceRequest, SetAbsenceRequest},
+    types::{collection::Collection, date::UTCDate, id::Id, property::Property},
+};
+use mail_parser::Message;
+use smtp::core::{Session, SessionAddress};
+use std::future::Future;
+use store::{
+    rand::{distributions::Alphanumeric, thread_rng, Rng},
+    write::{now, BatchBuilder, Bincode, F_CLEAR, F_VALUE},
+};
+use trc::AddContext;
+use utils::sanitize_email;
+
+use crate::JmapMethods;
+
+const WINDOW_ID_LEN: usize = 8;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AbsenceRule {
+    pub id: String,
+    pub from: u64,
+    pub to: u64,
+    pub forward_to: String,
+    pub file_into: Option<u32>,
+    pub sender_contains: Option<String>,
+    pub subject_contains: Option<String>,
+}
+
+pub struct AbsenceMessage<'x> {
+    pub account_id: u32,
+    pub sender: &'x str,
+    pub recipient: &'x str,
+    pub raw_message: &'x [u8],
+    pub message: &'x Message<'x>,
+    pub session_id: u64,
+}
+
+pub trait AbsenceWindows: Sync + Send {
+    fn absence_get(
+        &self,
+        request: GetAbsenceRequest,
+    ) -> impl Future<Output = trc::Result<AbsenceResponse>> + Send;
+
+    fn absence_set(
+        &self,
+        request: SetAbsenceRequest,
+    ) -> impl Future<Output = trc::Result<AbsenceResponse>> + Send;
+
+    fn absence_rules(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<AbsenceRule>>> + Send;
+
+    fn absence_forward(
+        &self,
+        message: AbsenceMessage<'_>,
+    ) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
+}
+
+impl AbsenceWindows for Server {
+    async fn absence_get(&self, request: GetAbsenceRequest) -> trc::Result<AbsenceResponse> {
+        Ok(AbsenceResponse {
+            account_id: request.account_id,
+            windows: self
+                .absence_rules(request.account_id.document_id())
+                .await?
+                .into_iter()
+                .map(AbsenceWindow::from)
+                .collect(),
+        })
+    }
+
+    async fn absence_set(&self, request: SetAbsenceRequest) -> trc::Result<AbsenceResponse> {
+        if request.windows.len() > self.core.jmap.set_max_objects {
+            return Err(trc::JmapEvent::RequestTooLarge.into_err());
+        }
+
+        let account_id = request.account_id.document_id();
+        let mailbox_ids = self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await?
+            .unwrap_or_default();
+        let mut rules = Vec::with_capacity(request.windows.len());
+
+        for window in request.windows {
+            let from = window.from_date.timestamp().max(0) as u64;
+            let to = window.to_date.timestamp().max(0) as u64;
+            if to <= from {
+                return Err(trc::JmapEvent::InvalidArguments
+                    .into_err()
+                    .details("The toDate of an absence window must be after its fromDate."));
+            }
+            let forward_to = sanitize_email(&window.forward_to).ok_or_else(|| {
+                trc::JmapEvent::InvalidArguments.into_err().details(format!(
+                    "Invalid forwardTo address {:?}.",
+                    window.forward_to
+                ))
+            })?;
+            if let Some(mailbox_id) = window.file_into {
+                if !mailbox_ids.contains(mailbox_id.document_id()) {
+                    return Err(trc::JmapEvent::InvalidArguments
+                        .into_err()
+                        .details(format!("Mailbox {mailbox_id} does not exist.")));
+                }
+            }
+
+            rules.push(AbsenceRule {
+                id: if !window.id.is_empty() {
+                    window.id
+                } else {
+                    thread_rng()
+                        .sample_iter(Alphanumeric)
+                        .take(WINDOW_ID_LEN)
+                        .map(char::from)
+                        .collect::<String>()
+                },
+                from,
+                to,
+                forward_to,
+                file_into: window.file_into.map(|id| id.document_id()),
+                sender_contains: window
+                    .sender_contains
+                    .filter(|text| !text.is_empty())
+                    .map(|text| text.to_lowercase()),
+                subject_contains: window
+                    .subject_contains
+                    .filter(|text| !text.is_empty())
+                    .map(|text| text.to_lowercase()),
+            });
+        }
+
+        // Save absence windows
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Principal)
+            .update_document(0);
+        if !rules.is_empty() {
+            batch.value(Property::Absence, Bincode::new(rules.clone()), F_VALUE);
+        } else {
+            batch.value(Property::Absence, (), F_VALUE | F_CLEAR);
+        }
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(AbsenceResponse {
+            account_id: request.account_id,
+            windows: rules.into_iter().map(AbsenceWindow::from).collect(),
+        })
+    }
+
+    async fn absence_rules(&self, account_id: u32) -> trc::Result<Vec<AbsenceRule>> {
+        self.get_property::<Bincode<Vec<AbsenceRule>>>(
+            account_id,
+            Collection::Principal,
+            0,
+            Property::Absence,
+        )
+        .await
+        .map(|rules| rules.map(|rules| rules.inner).unwrap_or_default())
+    }
+
+    async fn absence_forward(&self, message: AbsenceMessage<'_>) -> trc::Result<Option<u32>> {
+        let now = now();
+        let rule = if let Some(rule) = self
+            .absence_rules(message.account_id)
+            .await?
+            .into_iter()
+            .find(|rule| {
+                rule.from <= now && now < rule.to && rule.matches(message.sender, message.message)
+            }) {
+            rule
+        } else {
+            return Ok(None);
+        };
+
+        // Do not forward bounces, auto-generated messages, messages sent by the delegate
+        // or messages this account has already forwarded
+        if message.sender.is_empty()
+            || message.sender.eq_ignore_ascii_case(&rule.forward_to)
+            || is_auto_submitted(message.message)
+            || has_loop_header(message.message, message.recipient)
+        {
+            trc::event!(
+                MessageIngest(trc::MessageIngestEvent::ForwardLoop),
+                AccountId = message.account_id,
+                From = message.sender.to_string(),
+                To = rule.forward_to.clone(),
+                SpanId = message.session_id,
+            );
+        } else if message.raw_message.len() <= self.core.jmap.mail_max_size {
+            trc::event!(
+                MessageIngest(trc::MessageIngestEvent::Forward),
+                AccountId = message.account_id,
+                From = message.sender.to_string(),
+                To = rule.forward_to.clone(),
+                Size = message.raw_message.len(),
+                SpanId = message.session_id,
+            );
+
+            let mut raw_message =
+                Vec::with_capacity(message.raw_message.len() + message.recipient.len() + 10);
+            raw_message.extend_from_slice(b"X-Loop: ");
+            raw_message.extend_from_slice(message.recipient.as_bytes());
+            raw_message.extend_from_slice(b"\r\n");
+            raw_message.extend_from_slice(message.raw_message);
+
+            Session::<NullIo>::sieve(
+                self.clone(),
+                SessionAddress::new(message.recipient.to_string()),
+                vec![SessionAddress::new(rule.forward_to.clone())],
+                raw_message,
+                message.session_id,
+            )
+            .queue_message()
+            .await;
+        }
+
+        // File into the requested mailbox, if it still exists
+        if let Some(mailbox_id) = rule.file_into {
+            if self
+                .get_document_ids(message.account_id, Collection::Mailbox)
+                .await?
+                .is_some_and(|ids| ids.contains(mailbox_id))
+            {
+                return Ok(Some(mailbox_id));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl AbsenceRule {
+    pub fn matches(&self, sender: &str, message: &Message<'_>) -> bool {
+        self.sender_contains.as_ref().map_or(true, |text| {
+            sender.to_lowercase().contains(text)
+                || message
+                    .from()
+                    .and_then(|from| from.first())
+                    .and_then(|addr| addr.address())
+                    .is_some_and(|addr| addr.to_lowercase().contains(text))
+        }) && self.subject_contains.as_ref().map_or(true, |text| {
+            message
+                .subject()
+                .is_some_and(|subject| subject.to_lowercase().contains(text))
+        })
+    }
+}
+
+pub(crate) fn is_auto_submitted(message: &Message<'_>) -> bool {
+    message.root_part().headers().iter().any(|header| {
+        header.name.as_str().eq_ignore_ascii_case("Auto-Submitted")
+            && header
+                .value
+                .as_text()
+                .is_some_and(|value| !value.trim().eq_ignore_ascii_case("no"))
+    })
+}
+
+fn has_loop_header(message: &Message<'_>, recipient: &str) -> bool {
+    message.root_part().headers().iter().any(|header| {
+        header.name.as_str().eq_ignore_ascii_case("X-Loop")
+            && header.value.as_text().is_some_and(|value| {
+                value
+                    .trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .eq_ignore_ascii_case(recipient)
+            })
+    })
+}
+
+impl From<AbsenceRule> for AbsenceWindow {
+    fn from(rule: AbsenceRule) -> Self {
+        AbsenceWindow {
+            id: rule.id,
+            from_date: UTCDate::from_timestamp(rule.from as i64),
+            to_date: UTCDate::from_timestamp(rule.to as i64),
+            forward_to: rule.forward_to,
+            file_into: rule.file_into.map(Id::from),
+            sender_contains: rule.sender_contains,
+            subject_contains: rule.subject_contains,
+        }
+    }
+}
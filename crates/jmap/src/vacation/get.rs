@@ -16,6 +16,8 @@ use store::query::Filter;
 
 use crate::{changes::state::StateManager, JmapMethods};
 
+use super::merge::VacationMerge;
+
 pub trait VacationResponseGet: Sync + Send {
     fn vacation_response_get(
         &self,
@@ -42,6 +44,7 @@ impl VacationResponseGet for Server {
             Property::Subject,
             Property::TextBody,
             Property::HtmlBody,
+            Property::SieveConflict,
         ]);
         let mut response = GetResponse {
             account_id: request.account_id.into(),
@@ -90,6 +93,15 @@ impl VacationResponseGet for Server {
                             Property::IsEnabled => {
                                 result.append(Property::IsEnabled, obj.remove(&Property::IsActive));
                             }
+                            Property::SieveConflict => {
+                                result.append(
+                                    Property::SieveConflict,
+                                    self.vacation_conflict(account_id, document_id)
+                                        .await?
+                                        .map(Value::Text)
+                                        .unwrap_or(Value::Null),
+                                );
+                            }
                             Property::FromDate
                             | Property::ToDate
                             | Property::Subject
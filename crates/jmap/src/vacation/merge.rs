@@ -0,0 +1,178 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{config::jmap::settings::VacationStrategy, Server};
+use jmap_proto::types::{collection::Collection, property::Property};
+use sieve::Sieve;
+use store::query::Filter;
+
+use crate::{sieve::get::SieveScriptGet, JmapMethods};
+
+const REGION_START: &[u8] = b"# BEGIN JMAP VACATION RESPONSE\r\n";
+const REGION_END: &[u8] = b"# END JMAP VACATION RESPONSE\r\n";
+
+pub trait VacationMerge: Sync + Send {
+    fn vacation_merge(
+        &self,
+        account_id: u32,
+        vacation_id: u32,
+        document_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<Sieve>>> + Send;
+
+    fn vacation_conflict(
+        &self,
+        account_id: u32,
+        vacation_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<String>>> + Send;
+}
+
+impl VacationMerge for Server {
+    async fn vacation_merge(
+        &self,
+        account_id: u32,
+        vacation_id: u32,
+        document_id: u32,
+    ) -> trc::Result<Option<Sieve>> {
+        let (vacation, _) = self.sieve_script_source(account_id, vacation_id).await?;
+        let (script, _) = self.sieve_script_source(account_id, document_id).await?;
+
+        // Scripts that send their own vacation responses take precedence
+        let Some(script) = merge_scripts(&vacation, &script) else {
+            return Ok(None);
+        };
+
+        match self.core.sieve.untrusted_compiler.compile(&script) {
+            Ok(sieve) => Ok(Some(sieve)),
+            Err(err) => Err(trc::StoreEvent::UnexpectedError
+                .caused_by(trc::location!())
+                .reason(err)
+                .details("Merged vacation Sieve script failed to compile.")),
+        }
+    }
+
+    async fn vacation_conflict(
+        &self,
+        account_id: u32,
+        vacation_id: u32,
+    ) -> trc::Result<Option<String>> {
+        if self.core.jmap.sieve_vacation_strategy != VacationStrategy::Merge {
+            return Ok(None);
+        }
+
+        let mut active_ids = self
+            .filter(
+                account_id,
+                Collection::SieveScript,
+                vec![Filter::eq(Property::IsActive, 1u32)],
+            )
+            .await?
+            .results;
+        if !active_ids.remove(vacation_id) {
+            return Ok(None);
+        }
+
+        for document_id in active_ids {
+            let (script, mut script_object) =
+                self.sieve_script_source(account_id, document_id).await?;
+            if requires_vacation(&script) {
+                let name = script_object
+                    .properties
+                    .remove(&Property::Name)
+                    .and_then(|name| name.try_unwrap_string())
+                    .unwrap_or_default();
+
+                return Ok(Some(format!(
+                    "The active Sieve script {name:?} sends its own vacation responses."
+                )));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Places the vacation rule in a delimited region ahead of the first command
+/// of the script, or returns `None` if the script already uses vacation.
+fn merge_scripts(vacation: &[u8], script: &[u8]) -> Option<Vec<u8>> {
+    let (offset, has_vacation) = parse_header(script);
+    if has_vacation {
+        return None;
+    }
+
+    let mut merged = Vec::with_capacity(
+        script.len() + vacation.len() + REGION_START.len() + REGION_END.len() + 2,
+    );
+    merged.extend_from_slice(&script[..offset]);
+    if !merged.is_empty() && !merged.ends_with(b"\n") {
+        merged.extend_from_slice(b"\r\n");
+    }
+    merged.extend_from_slice(REGION_START);
+    merged.extend_from_slice(vacation);
+    if !vacation.ends_with(b"\n") {
+        merged.extend_from_slice(b"\r\n");
+    }
+    merged.extend_from_slice(REGION_END);
+    merged.extend_from_slice(&script[offset..]);
+
+    Some(merged)
+}
+
+fn requires_vacation(script: &[u8]) -> bool {
+    parse_header(script).1
+}
+
+/// Skips the comments and `require` statements at the top of a script,
+/// returning the offset of the first command and whether the vacation
+/// extension was required.
+fn parse_header(script: &[u8]) -> (usize, bool) {
+    let mut pos = 0;
+    let mut has_vacation = false;
+
+    loop {
+        while script.get(pos).map_or(false, |ch| ch.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        let rest = &script[pos..];
+
+        if rest.starts_with(b"#") {
+            match rest.iter().position(|&ch| ch == b'\n') {
+                Some(end) => pos += end + 1,
+                None => pos = script.len(),
+            }
+        } else if rest.starts_with(b"/*") {
+            match rest.windows(2).position(|w| w == b"*/") {
+                Some(end) => pos += end + 2,
+                None => break,
+            }
+        } else if rest
+            .get(..7)
+            .map_or(false, |word| word.eq_ignore_ascii_case(b"require"))
+            && rest.get(7).map_or(false, |ch| {
+                ch.is_ascii_whitespace() || *ch == b'[' || *ch == b'"'
+            })
+        {
+            match rest.iter().position(|&ch| ch == b';') {
+                Some(end) => {
+                    let statement = &rest[..end];
+                    has_vacation |= statement
+                        .windows(10)
+                        .any(|w| w.eq_ignore_ascii_case(b"\"vacation\""))
+                        || statement
+                            .windows(18)
+                            .any(|w| w.eq_ignore_ascii_case(b"\"vacation-seconds\""));
+                    pos += end + 1;
+                }
+                None => break,
+            }
+        } else {
+            break;
+        }
+    }
+
+    (pos, has_vacation)
+}
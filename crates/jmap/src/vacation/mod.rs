@@ -4,5 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod absence;
 pub mod get;
+pub mod merge;
+pub mod replies;
 pub mod set;
@@ -0,0 +1,127 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use jmap_proto::types::{collection::Collection, property::Property};
+use store::write::{now, BatchBuilder, Bincode, F_CLEAR, F_VALUE};
+use trc::AddContext;
+
+use crate::JmapMethods;
+
+/// Auto-reply sent by a Sieve vacation action, identified by the hash of
+/// the vacation :handle and the address it was sent to.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct VacationReply {
+    pub recipient: String,
+    pub hash: [u8; 32],
+    pub sent_at: u64,
+    pub expires: u64,
+}
+
+pub trait VacationReplies: Sync + Send {
+    fn vacation_replies(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<VacationReply>>> + Send;
+
+    fn vacation_replies_add(
+        &self,
+        account_id: u32,
+        replies: Vec<VacationReply>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn vacation_replies_clear(
+        &self,
+        account_id: u32,
+        recipient: Option<&str>,
+    ) -> impl Future<Output = trc::Result<usize>> + Send;
+}
+
+impl VacationReplies for Server {
+    async fn vacation_replies(&self, account_id: u32) -> trc::Result<Vec<VacationReply>> {
+        let now = now();
+        self.get_property::<Bincode<Vec<VacationReply>>>(
+            account_id,
+            Collection::Principal,
+            0,
+            Property::VacationReplies,
+        )
+        .await
+        .map(|replies| {
+            replies
+                .map(|replies| replies.inner)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|reply| reply.expires > now)
+                .collect()
+        })
+    }
+
+    async fn vacation_replies_add(
+        &self,
+        account_id: u32,
+        replies: Vec<VacationReply>,
+    ) -> trc::Result<()> {
+        let mut current = self.vacation_replies(account_id).await?;
+        for reply in replies {
+            if let Some(current) = current
+                .iter_mut()
+                .find(|current| current.hash == reply.hash && current.recipient == reply.recipient)
+            {
+                *current = reply;
+            } else {
+                current.push(reply);
+            }
+        }
+
+        write_replies(self, account_id, current).await
+    }
+
+    async fn vacation_replies_clear(
+        &self,
+        account_id: u32,
+        recipient: Option<&str>,
+    ) -> trc::Result<usize> {
+        let mut replies = self.vacation_replies(account_id).await?;
+        let total = replies.len();
+        if let Some(recipient) = recipient {
+            replies.retain(|reply| !reply.recipient.eq_ignore_ascii_case(recipient));
+        } else {
+            replies.clear();
+        }
+
+        let removed = total - replies.len();
+        write_replies(self, account_id, replies).await?;
+        Ok(removed)
+    }
+}
+
+async fn write_replies(
+    server: &Server,
+    account_id: u32,
+    replies: Vec<VacationReply>,
+) -> trc::Result<()> {
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::Principal)
+        .update_document(0);
+    if !replies.is_empty() {
+        batch.value(Property::VacationReplies, Bincode::new(replies), F_VALUE);
+    } else {
+        batch.value(Property::VacationReplies, (), F_VALUE | F_CLEAR);
+    }
+    server
+        .core
+        .storage
+        .data
+        .write(batch.build())
+        .await
+        .caused_by(trc::location!())
+        .map(|_| ())
+}
@@ -6,7 +6,7 @@
 
 use std::borrow::Cow;
 
-use common::{auth::AccessToken, Server};
+use common::{auth::AccessToken, config::jmap::settings::VacationStrategy, Server};
 use jmap_proto::{
     error::set::{SetError, SetErrorType},
     method::set::{RequestArguments, SetRequest, SetResponse},
@@ -36,7 +36,7 @@ use crate::{
     JmapMethods,
 };
 
-use super::get::VacationResponseGet;
+use super::{get::VacationResponseGet, merge::VacationMerge, replies::VacationReplies};
 
 pub trait VacationResponseSet: Sync + Send {
     fn vacation_response_set(
@@ -305,6 +305,7 @@ impl VacationResponseSet for Server {
             };
 
             // Write changes
+            let is_enabled = obj.get(&Property::IsActive) == &Value::Bool(true);
             batch.custom(obj);
             let document_id = if !batch.is_empty() {
                 let ids = self.write_batch(batch).await?;
@@ -317,20 +318,60 @@ impl VacationResponseSet for Server {
                 document_id.unwrap_or(u32::MAX)
             };
 
-            // Deactivate other sieve scripts
-            if !was_active && is_active {
-                self.sieve_activate_script(account_id, document_id.into())
-                    .await?;
+            // Report scripts that conflict with the vacation response
+            let mut conflict = None;
+            match self.core.jmap.sieve_vacation_strategy {
+                VacationStrategy::Merge if is_enabled => {
+                    conflict = self.vacation_conflict(account_id, document_id).await?;
+                }
+                VacationStrategy::Replace if !was_active && is_active => {
+                    // Deactivate other sieve scripts
+                    let mut names = Vec::new();
+                    for (document_id, activated) in self
+                        .sieve_activate_script(account_id, document_id.into())
+                        .await?
+                    {
+                        if !activated {
+                            if let Some(name) = self
+                                .get_property::<Object<Value>>(
+                                    account_id,
+                                    Collection::SieveScript,
+                                    document_id,
+                                    Property::Value,
+                                )
+                                .await?
+                                .and_then(|mut obj| obj.properties.remove(&Property::Name))
+                                .and_then(|name| name.try_unwrap_string())
+                            {
+                                names.push(format!("{name:?}"));
+                            }
+                        }
+                    }
+                    if !names.is_empty() {
+                        conflict = Some(format!(
+                            "The Sieve script {} was deactivated.",
+                            names.join(", ")
+                        ));
+                    }
+                }
+                _ => (),
             }
 
             // Add result
             if let Some(create_id) = create_id {
-                response.created.insert(
-                    create_id,
-                    Object::with_capacity(1).with_property(Property::Id, Id::singleton()),
-                );
+                let mut result =
+                    Object::with_capacity(2).with_property(Property::Id, Id::singleton());
+                if let Some(conflict) = conflict {
+                    result.append(Property::SieveConflict, conflict);
+                }
+                response.created.insert(create_id, result);
             } else {
-                response.updated.append(Id::singleton(), None);
+                response.updated.append(
+                    Id::singleton(),
+                    conflict.map(|conflict| {
+                        Object::with_capacity(1).with_property(Property::SieveConflict, conflict)
+                    }),
+                );
             }
         } else if !will_destroy.is_empty() {
             for id in will_destroy {
@@ -339,6 +380,7 @@ impl VacationResponseSet for Server {
                     {
                         self.sieve_script_delete(&resource_token, document_id, false)
                             .await?;
+                        self.vacation_replies_clear(account_id, None).await?;
                         batch.log(Changes::delete([document_id]));
                         response.destroyed.push(id);
                         continue;
@@ -34,43 +34,7 @@ impl WebSocketUpgrade for Server {
         access_token: Arc<AccessToken>,
         session: HttpSessionData,
     ) -> trc::Result<HttpResponse> {
-        let headers = req.headers();
-        if headers
-            .get(hyper::header::CONNECTION)
-            .and_then(|h| h.to_str().ok())
-            != Some("Upgrade")
-            || headers
-                .get(hyper::header::UPGRADE)
-                .and_then(|h| h.to_str().ok())
-                != Some("websocket")
-        {
-            return Err(trc::ResourceEvent::BadParameters
-                .into_err()
-                .details("WebSocket upgrade failed")
-                .ctx(
-                    trc::Key::Reason,
-                    "Missing or Invalid Connection or Upgrade headers.",
-                ));
-        }
-        let derived_key = match (
-            headers
-                .get("Sec-WebSocket-Key")
-                .and_then(|h| h.to_str().ok()),
-            headers
-                .get("Sec-WebSocket-Version")
-                .and_then(|h| h.to_str().ok()),
-        ) {
-            (Some(key), Some("13")) => derive_accept_key(key.as_bytes()),
-            _ => {
-                return Err(trc::ResourceEvent::BadParameters
-                    .into_err()
-                    .details("WebSocket upgrade failed")
-                    .ctx(
-                        trc::Key::Reason,
-                        "Missing or Invalid Sec-WebSocket-Key headers.",
-                    ));
-            }
-        };
+        let derived_key = websocket_accept_key(&req)?;
 
         // Spawn WebSocket connection
         let jmap = self.clone();
@@ -111,3 +75,43 @@ impl WebSocketUpgrade for Server {
         })
     }
 }
+
+/// Validates the headers of a WebSocket upgrade request and returns the
+/// value of the Sec-WebSocket-Accept header to respond with.
+pub fn websocket_accept_key(req: &HttpRequest) -> trc::Result<String> {
+    let headers = req.headers();
+    if headers
+        .get(hyper::header::CONNECTION)
+        .and_then(|h| h.to_str().ok())
+        != Some("Upgrade")
+        || headers
+            .get(hyper::header::UPGRADE)
+            .and_then(|h| h.to_str().ok())
+            != Some("websocket")
+    {
+        return Err(trc::ResourceEvent::BadParameters
+            .into_err()
+            .details("WebSocket upgrade failed")
+            .ctx(
+                trc::Key::Reason,
+                "Missing or Invalid Connection or Upgrade headers.",
+            ));
+    }
+    match (
+        headers
+            .get("Sec-WebSocket-Key")
+            .and_then(|h| h.to_str().ok()),
+        headers
+            .get("Sec-WebSocket-Version")
+            .and_then(|h| h.to_str().ok()),
+    ) {
+        (Some(key), Some("13")) => Ok(derive_accept_key(key.as_bytes())),
+        _ => Err(trc::ResourceEvent::BadParameters
+            .into_err()
+            .details("WebSocket upgrade failed")
+            .ctx(
+                trc::Key::Reason,
+                "Missing or Invalid Sec-WebSocket-Key headers.",
+            )),
+    }
+}
@@ -6,13 +6,16 @@
 
 use std::time::Duration;
 
-use common::{config::server::ServerProtocol, core::BuildServer, manager::boot::BootManager};
+use common::{
+    config::server::ServerProtocol, core::BuildServer, listener::health::HealthSessionManager,
+    manager::boot::BootManager,
+};
 use directory::backend::internal::MigrateDirectory;
 use imap::core::ImapSessionManager;
 use jmap::{api::JmapSessionManager, services::gossip::spawn::GossiperBuilder, StartServices};
 use managesieve::core::ManageSieveSessionManager;
 use pop3::Pop3SessionManager;
-use smtp::{core::SmtpSessionManager, StartQueueManager};
+use smtp::{core::SmtpSessionManager, queue::spool::SmtpSpool, StartQueueManager};
 use trc::Collector;
 use utils::wait_for_shutdown;
 
@@ -84,23 +87,43 @@ async fn main() -> std::io::Result<()> {
                 acceptor,
                 shutdown_rx,
             ),
+            ServerProtocol::Health => server.spawn(
+                HealthSessionManager::new(init.inner.clone()),
+                init.inner.clone(),
+                acceptor,
+                shutdown_rx,
+            ),
         };
     });
 
     // Spawn gossip
+    let inner = init.inner.clone();
     if let Some(gossiper) = gossiper {
-        gossiper.spawn(init.inner, shutdown_rx.clone()).await;
+        gossiper
+            .spawn(
+                init.inner,
+                init.ipc_rxs.broadcast_rx.take().unwrap(),
+                shutdown_rx.clone(),
+            )
+            .await;
     }
 
     // Wait for shutdown signal
     wait_for_shutdown().await;
 
-    // Shutdown collector
-    Collector::shutdown();
-
     // Stop services
     let _ = shutdown_tx.send(true);
 
+    // Wait for in-flight sessions and deliveries, releasing
+    // any deliveries that did not complete in time
+    let server = inner.build_server();
+    for event in server.drain().await {
+        server.unlock_event(event).await;
+    }
+
+    // Shutdown collector
+    Collector::shutdown();
+
     // Wait for services to finish
     tokio::time::sleep(Duration::from_secs(1)).await;
 
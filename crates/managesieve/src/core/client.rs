@@ -93,6 +93,8 @@ impl<T: SessionStream> Session<T> {
                 Command::DeleteScript => self.handle_deletescript(request).await,
                 Command::RenameScript => self.handle_renamescript(request).await,
                 Command::CheckScript => self.handle_checkscript(request).await,
+                Command::ListVacation => self.handle_listvacation().await,
+                Command::ClearVacation => self.handle_clearvacation(request).await,
                 Command::HaveSpace => self.handle_havespace(request).await,
                 Command::Capability => self.handle_capability("").await,
                 Command::Authenticate => self.handle_authenticate(request).await,
@@ -177,7 +179,25 @@ impl<T: SessionStream> Session<T> {
             | Command::DeleteScript
             | Command::RenameScript
             | Command::CheckScript
+            | Command::ListVacation
+            | Command::ClearVacation
             | Command::Unauthenticate => {
+                if self.server.is_read_only()
+                    && matches!(
+                        command.command,
+                        Command::PutScript
+                            | Command::SetActive
+                            | Command::DeleteScript
+                            | Command::RenameScript
+                            | Command::ClearVacation
+                    )
+                {
+                    return Err(trc::ManageSieveEvent::Error
+                        .into_err()
+                        .code(ResponseCode::TryLater)
+                        .details("Server is in read-only mode."));
+                }
+
                 if let State::Authenticated { access_token, .. } = &self.state {
                     if let Some(rate) = &self.server.core.imap.rate_requests {
                         if self
@@ -72,6 +72,8 @@ pub enum Command {
     DeleteScript,
     RenameScript,
     CheckScript,
+    ListVacation,
+    ClearVacation,
     #[default]
     Noop,
     Unauthenticate,
@@ -92,6 +94,8 @@ impl CommandParser for Command {
             b"DELETESCRIPT" => Some(Command::DeleteScript),
             b"RENAMESCRIPT" => Some(Command::RenameScript),
             b"CHECKSCRIPT" => Some(Command::CheckScript),
+            b"LISTVACATION" => Some(Command::ListVacation),
+            b"CLEARVACATION" => Some(Command::ClearVacation),
             b"NOOP" => Some(Command::Noop),
             b"UNAUTHENTICATE" => Some(Command::Unauthenticate),
             _ => None,
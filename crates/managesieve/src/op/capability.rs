@@ -54,6 +54,9 @@ impl<T: SessionStream> Session<T> {
                 response.extend_from_slice(sieve.max_redirects.to_string().as_bytes());
                 response.extend_from_slice(b"\"\r\n");
             }
+            if sieve.extensions.iter().any(|ext| ext == "vacation") {
+                response.extend_from_slice(b"\"VACATION-TRACKING\"\r\n");
+            }
         } else {
             response.extend_from_slice(b"\"SIEVE\" \"\"\r\n");
         }
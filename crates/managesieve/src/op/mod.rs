@@ -21,6 +21,7 @@ pub mod noop;
 pub mod putscript;
 pub mod renamescript;
 pub mod setactive;
+pub mod vacation;
 
 impl<T: SessionStream> Session<T> {
     pub async fn handle_start_tls(&self) -> trc::Result<Vec<u8>> {
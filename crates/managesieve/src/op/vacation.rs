@@ -0,0 +1,92 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use common::listener::SessionStream;
+use directory::Permission;
+use imap_proto::receiver::Request;
+use jmap::vacation::replies::VacationReplies;
+use jmap_proto::types::date::UTCDate;
+use trc::AddContext;
+
+use crate::core::{Command, Session, StatusResponse};
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_listvacation(&mut self) -> trc::Result<Vec<u8>> {
+        // Validate access
+        self.assert_has_permission(Permission::SieveListVacation)?;
+
+        let op_start = Instant::now();
+        let account_id = self.state.access_token().primary_id();
+        let mut replies = self
+            .server
+            .vacation_replies(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        replies.sort_unstable_by(|a, b| b.sent_at.cmp(&a.sent_at));
+
+        let mut response = Vec::with_capacity(128);
+        for reply in &replies {
+            response.push(b'\"');
+            for ch in reply.recipient.as_bytes() {
+                if [b'\\', b'\"'].contains(ch) {
+                    response.push(b'\\');
+                }
+                response.push(*ch);
+            }
+            response.extend_from_slice(
+                format!(
+                    "\" \"{}\" \"{}\"\r\n",
+                    UTCDate::from_timestamp(reply.sent_at as i64),
+                    UTCDate::from_timestamp(reply.expires as i64)
+                )
+                .as_bytes(),
+            );
+        }
+
+        trc::event!(
+            ManageSieve(trc::ManageSieveEvent::ListVacation),
+            SpanId = self.session_id,
+            Total = replies.len(),
+            Elapsed = op_start.elapsed()
+        );
+
+        Ok(StatusResponse::ok("").serialize(response))
+    }
+
+    pub async fn handle_clearvacation(
+        &mut self,
+        request: Request<Command>,
+    ) -> trc::Result<Vec<u8>> {
+        // Validate access
+        self.assert_has_permission(Permission::SieveClearVacation)?;
+
+        let op_start = Instant::now();
+        let recipient = request
+            .tokens
+            .into_iter()
+            .next()
+            .and_then(|s| s.unwrap_string().ok());
+
+        let account_id = self.state.access_token().primary_id();
+        let removed = self
+            .server
+            .vacation_replies_clear(account_id, recipient.as_deref())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            ManageSieve(trc::ManageSieveEvent::ClearVacation),
+            SpanId = self.session_id,
+            To = recipient,
+            Total = removed,
+            Elapsed = op_start.elapsed()
+        );
+
+        Ok(StatusResponse::ok("Cleared.").into_bytes())
+    }
+}
@@ -231,6 +231,14 @@ impl<T: SessionStream> Session<T> {
             | Command::Utf8
             | Command::Stat
             | Command::Rset => {
+                if self.server.is_read_only()
+                    && matches!(command, Command::Dele { .. } | Command::DeleMany { .. })
+                {
+                    return Err(trc::Pop3Event::Error
+                        .into_err()
+                        .details("Server is in read-only mode."));
+                }
+
                 if let State::Authenticated { mailbox, .. } = &self.state {
                     if let Some(rate) = &self.server.core.imap.rate_requests {
                         if self
@@ -0,0 +1,297 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashSet;
+use common::{config::smtp::session::SubmissionAnomaly, listener::SessionStream};
+use mail_builder::{headers::HeaderType, mime::make_boundary, MessageBuilder};
+use store::{
+    write::{now, Bincode},
+    Serialize,
+};
+use trc::SecurityEvent;
+use utils::config::Rate;
+
+use crate::{queue::Message, reporting::SmtpReporting};
+
+use super::Session;
+
+impl<T: SessionStream> Session<T> {
+    /// Returns `true` if submission has been suspended for the authenticated account.
+    pub async fn is_submission_suspended(&self) -> bool {
+        if let (Some(_), Some(token)) = (
+            &self.server.core.smtp.session.anomaly,
+            &self.data.authenticated_as,
+        ) {
+            match self
+                .server
+                .lookup_store()
+                .key_exists(format!("sas:{}", token.primary_id()).into_bytes())
+                .await
+            {
+                Ok(is_suspended) => is_suspended,
+                Err(err) => {
+                    trc::error!(err
+                        .span_id(self.data.session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to obtain submission suspension status"));
+                    false
+                }
+            }
+        } else {
+            false
+        }
+    }
+
+    /// Tracks the countries and autonomous systems an account authenticates
+    /// from, suspending submission when too many previously unseen ones are
+    /// used within a short period.
+    pub async fn track_auth_network(&self) {
+        if let (Some(anomaly), Some(token)) = (
+            &self.server.core.smtp.session.anomaly,
+            &self.data.authenticated_as,
+        ) {
+            let account_id = token.primary_id();
+            let remote_ip = self.data.remote_ip;
+            let networks = [
+                self.server
+                    .geoip_country(remote_ip)
+                    .map(|country| ("new-country", format!("c:{country}"))),
+                self.server
+                    .geoip_asn(remote_ip)
+                    .map(|asn| ("new-asn", format!("a:{asn}"))),
+            ];
+            let store = self.server.lookup_store();
+            let mut reason = None;
+
+            for (network_reason, network) in networks.into_iter().flatten() {
+                let network_key = format!("san:{account_id}:{network}").into_bytes();
+                match store.key_exists(network_key.clone()).await {
+                    Ok(true) => (),
+                    Ok(false) => {
+                        reason.get_or_insert(network_reason);
+                    }
+                    Err(err) => {
+                        trc::error!(err
+                            .span_id(self.data.session_id)
+                            .caused_by(trc::location!()));
+                        return;
+                    }
+                }
+                if let Err(err) = store
+                    .key_set(network_key, vec![1], anomaly.network_expiry.into())
+                    .await
+                {
+                    trc::error!(err
+                        .span_id(self.data.session_id)
+                        .caused_by(trc::location!()));
+                }
+            }
+
+            if let (Some(reason), Some(rate)) = (reason, &anomaly.new_networks) {
+                if self
+                    .is_anomaly_rate_exceeded(format!("sann:{account_id}"), rate)
+                    .await
+                {
+                    self.suspend_submission(anomaly, reason, rate).await;
+                }
+            }
+        }
+    }
+
+    /// Records a submission by an authenticated account, returns `false` if this
+    /// message triggered a suspension.
+    pub async fn track_submission(&self, message: &Message) -> bool {
+        if let (Some(anomaly), Some(token)) = (
+            &self.server.core.smtp.session.anomaly,
+            &self.data.authenticated_as,
+        ) {
+            let account_id = token.primary_id();
+
+            // Sudden volume spikes
+            if let Some(rate) = &anomaly.account_volume {
+                if self
+                    .is_anomaly_rate_exceeded(format!("sav:{account_id}"), rate)
+                    .await
+                {
+                    self.suspend_submission(anomaly, "account-volume", rate)
+                        .await;
+                    return false;
+                }
+            }
+            if let Some(rate) = &anomaly.ip_volume {
+                if self
+                    .is_anomaly_rate_exceeded(format!("saiv:{}", self.data.remote_ip), rate)
+                    .await
+                {
+                    self.suspend_submission(anomaly, "ip-volume", rate).await;
+                    return false;
+                }
+            }
+
+            // Many unique recipients
+            if let Some(rate) = &anomaly.unique_recipients {
+                if self
+                    .is_unique_recipients_exceeded(account_id, message, rate)
+                    .await
+                {
+                    self.suspend_submission(anomaly, "unique-recipients", rate)
+                        .await;
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Adds the recipients of a message to the addresses the account sent to
+    /// within the rate period, returns `true` if they exceed the rate.
+    async fn is_unique_recipients_exceeded(
+        &self,
+        account_id: u32,
+        message: &Message,
+        rate: &Rate,
+    ) -> bool {
+        let store = self.server.lookup_store();
+        let key = format!("sar:{account_id}").into_bytes();
+        let mut recipients = match store.key_get::<Bincode<Vec<(u64, u64)>>>(key.clone()).await {
+            Ok(recipients) => recipients
+                .map(|recipients| recipients.inner)
+                .unwrap_or_default(),
+            Err(err) => {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!()));
+                return false;
+            }
+        };
+
+        // Entries expire one period after the address was first seen
+        let now = now();
+        recipients.retain(|(_, expires)| *expires > now);
+        let mut seen = recipients
+            .iter()
+            .map(|(hash, _)| *hash)
+            .collect::<AHashSet<_>>();
+        let expires = now + rate.period.as_secs();
+        let num_recipients = recipients.len();
+        for rcpt in &message.recipients {
+            let hash = blake3::hash(rcpt.address_lcase.as_bytes());
+            let hash = u64::from_be_bytes(hash.as_bytes()[..8].try_into().unwrap());
+            if seen.insert(hash) {
+                recipients.push((hash, expires));
+            }
+        }
+        if recipients.len() == num_recipients {
+            return false;
+        }
+
+        let is_exceeded = recipients.len() > rate.requests as usize;
+        if let Err(err) = store
+            .key_set(
+                key,
+                Bincode::new(recipients).serialize(),
+                rate.period.as_secs().into(),
+            )
+            .await
+        {
+            trc::error!(err
+                .span_id(self.data.session_id)
+                .caused_by(trc::location!()));
+        }
+
+        is_exceeded
+    }
+
+    async fn is_anomaly_rate_exceeded(&self, key: String, rate: &Rate) -> bool {
+        match self
+            .server
+            .lookup_store()
+            .is_rate_allowed(key.as_bytes(), rate, false)
+            .await
+        {
+            Ok(result) => result.is_some(),
+            Err(err) => {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!()));
+                false
+            }
+        }
+    }
+
+    async fn suspend_submission(&self, anomaly: &SubmissionAnomaly, reason: &str, rate: &Rate) {
+        let token = if let Some(token) = &self.data.authenticated_as {
+            token
+        } else {
+            return;
+        };
+        let account_id = token.primary_id();
+
+        if let Err(err) = self
+            .server
+            .lookup_store()
+            .key_set(
+                format!("sas:{account_id}").into_bytes(),
+                vec![1],
+                anomaly.suspend_duration.into(),
+            )
+            .await
+        {
+            trc::error!(err
+                .span_id(self.data.session_id)
+                .caused_by(trc::location!())
+                .details("Failed to suspend submission"));
+        }
+
+        trc::event!(
+            Security(SecurityEvent::SubmissionSuspended),
+            SpanId = self.data.session_id,
+            AccountName = token.name.clone(),
+            AccountId = account_id,
+            RemoteIp = self.data.remote_ip,
+            Reason = reason.to_string(),
+            Limit = vec![
+                trc::Value::from(rate.requests),
+                trc::Value::from(rate.period)
+            ],
+        );
+
+        // Alert administrators
+        if !anomaly.notify_to.is_empty() {
+            let message = MessageBuilder::new()
+                .from(("Mail Security", anomaly.notify_from.as_str()))
+                .to(anomaly
+                    .notify_to
+                    .iter()
+                    .map(|addr| addr.as_str())
+                    .collect::<Vec<_>>())
+                .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+                .message_id(format!("<{}@anomaly>", make_boundary(".")))
+                .subject(format!("Submission suspended for account {}", token.name))
+                .text_body(format!(
+                    concat!(
+                        "Message submission for account {} has been suspended for {} ",
+                        "seconds after detecting anomalous activity ({}) from {}.\r\n\r\n",
+                        "Please verify whether the account has been compromised.\r\n"
+                    ),
+                    token.name, anomaly.suspend_duration, reason, self.data.remote_ip
+                ))
+                .write_to_vec()
+                .unwrap_or_default();
+
+            self.server
+                .send_autogenerated(
+                    anomaly.notify_from.clone(),
+                    anomaly.notify_to.iter().cloned(),
+                    message,
+                    None,
+                    self.data.session_id,
+                )
+                .await;
+        }
+    }
+}
@@ -0,0 +1,314 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{
+    addresses::{verp_decode, verp_encode},
+    listener::SessionStream,
+    Server,
+};
+use store::{
+    write::{now, Bincode},
+    Serialize,
+};
+use trc::{AddContext, SmtpEvent};
+
+use crate::queue::{Message, Recipient};
+
+use super::{list::failed_recipients, Session};
+
+/// Recipient that bounced a message from a sender, further messages from the
+/// sender to it are rejected until the entry expires or is removed.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SuppressedRecipient {
+    pub address: String,
+    pub reason: String,
+    pub added: u64,
+    pub expires: Option<u64>,
+}
+
+pub trait SuppressionManager: Sync + Send {
+    fn suppression_add(
+        &self,
+        sender: &str,
+        rcpt: &str,
+        reason: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn suppression_remove(
+        &self,
+        sender: &str,
+        rcpt: &str,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn suppression_get(
+        &self,
+        sender: &str,
+        rcpt: &str,
+    ) -> impl Future<Output = trc::Result<Option<SuppressedRecipient>>> + Send;
+
+    fn suppression_list(
+        &self,
+        sender: &str,
+    ) -> impl Future<Output = trc::Result<Vec<SuppressedRecipient>>> + Send;
+}
+
+impl SuppressionManager for Server {
+    async fn suppression_add(
+        &self,
+        sender: &str,
+        rcpt: &str,
+        reason: &str,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        let sender = sender.to_lowercase();
+        let rcpt = rcpt.to_lowercase();
+        let added = now();
+        let expiry = self.core.smtp.queue.suppression.expiry;
+        let entry = SuppressedRecipient {
+            address: rcpt.clone(),
+            reason: reason.to_string(),
+            added,
+            expires: expiry.map(|expiry| added + expiry),
+        };
+        self.core
+            .storage
+            .lookup
+            .key_set(
+                suppression_key(&sender, &rcpt),
+                Bincode::new(entry).serialize(),
+                expiry,
+            )
+            .await
+            .caused_by(trc::location!())?;
+        let mut index = suppression_index(self, &sender).await?;
+        if !index.contains(&rcpt) {
+            index.push(rcpt.clone());
+            set_suppression_index(self, &sender, index).await?;
+        }
+
+        trc::event!(
+            Smtp(SmtpEvent::RecipientSuppressed),
+            SpanId = session_id,
+            From = sender,
+            To = rcpt,
+            Reason = reason.to_string(),
+        );
+
+        Ok(())
+    }
+
+    async fn suppression_remove(&self, sender: &str, rcpt: &str) -> trc::Result<bool> {
+        let sender = sender.to_lowercase();
+        let rcpt = rcpt.to_lowercase();
+        let mut index = suppression_index(self, &sender).await?;
+        if !index.contains(&rcpt) {
+            return Ok(false);
+        }
+
+        self.core
+            .storage
+            .lookup
+            .key_delete(suppression_key(&sender, &rcpt))
+            .await
+            .caused_by(trc::location!())?;
+        index.retain(|item| *item != rcpt);
+        set_suppression_index(self, &sender, index).await?;
+
+        Ok(true)
+    }
+
+    async fn suppression_get(
+        &self,
+        sender: &str,
+        rcpt: &str,
+    ) -> trc::Result<Option<SuppressedRecipient>> {
+        self.core
+            .storage
+            .lookup
+            .key_get::<Bincode<SuppressedRecipient>>(suppression_key(
+                &sender.to_lowercase(),
+                &rcpt.to_lowercase(),
+            ))
+            .await
+            .caused_by(trc::location!())
+            .map(|entry| entry.map(|entry| entry.inner))
+    }
+
+    async fn suppression_list(&self, sender: &str) -> trc::Result<Vec<SuppressedRecipient>> {
+        let sender = sender.to_lowercase();
+        let index = suppression_index(self, &sender).await?;
+        let mut entries = Vec::with_capacity(index.len());
+        for rcpt in &index {
+            if let Some(entry) = self.suppression_get(&sender, rcpt).await? {
+                entries.push(entry);
+            }
+        }
+
+        // Drop entries that expired
+        if entries.len() != index.len() {
+            set_suppression_index(
+                self,
+                &sender,
+                entries.iter().map(|entry| entry.address.clone()).collect(),
+            )
+            .await?;
+        }
+
+        Ok(entries)
+    }
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Adds the recipients reported as failed in a delivery status
+    /// notification to the suppression list of the local sender it is
+    /// addressed to. VERP addresses identify the original recipient even if
+    /// the report lists a different one.
+    pub async fn handle_bounces(&self, headers: &[u8], raw_message: &[u8]) {
+        let mut message = Vec::with_capacity(headers.len() + raw_message.len());
+        message.extend_from_slice(headers);
+        message.extend_from_slice(raw_message);
+        let failed = failed_recipients(&message);
+        if failed.is_empty() {
+            return;
+        }
+
+        let directory = &self.server.core.storage.directory;
+        let lists = &self.server.core.smtp.lists;
+        for rcpt in &self.data.rcpt_to {
+            if lists.get_by_bounce(&rcpt.address_lcase).is_some()
+                || !directory
+                    .is_local_domain(&rcpt.domain)
+                    .await
+                    .unwrap_or_default()
+            {
+                continue;
+            }
+
+            let (sender, failed) = match verp_decode(&rcpt.address_lcase) {
+                Some((sender, original_rcpt)) => (sender, vec![original_rcpt]),
+                None => (rcpt.address_lcase.clone(), failed.clone()),
+            };
+            for failed_rcpt in failed {
+                if let Err(err) = self
+                    .server
+                    .suppression_add(
+                        &sender,
+                        &failed_rcpt,
+                        "Delivery status notification received",
+                        self.data.session_id,
+                    )
+                    .await
+                {
+                    trc::error!(err
+                        .span_id(self.data.session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to process bounce"));
+                }
+            }
+        }
+    }
+
+    /// Returns whether the recipient bounced previous messages from the
+    /// sender of this transaction.
+    pub async fn is_suppressed(&self, rcpt: &str) -> bool {
+        let sender = self
+            .data
+            .mail_from
+            .as_ref()
+            .map(|addr| addr.address_lcase.as_str())
+            .unwrap_or_default();
+        if sender.is_empty() {
+            return false;
+        }
+
+        match self.server.suppression_get(sender, rcpt).await {
+            Ok(entry) => entry.is_some(),
+            Err(err) => {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to check suppression list"));
+
+                false
+            }
+        }
+    }
+}
+
+impl Message {
+    /// Splits the message into one message per recipient, each one with a
+    /// return path that encodes the recipient it was sent to.
+    pub fn into_verp(self, server: &Server) -> Vec<Message> {
+        let mut messages = Vec::with_capacity(self.recipients.len());
+        for (rcpt_idx, rcpt) in self.recipients.iter().enumerate() {
+            let return_path = verp_encode(&self.return_path, &rcpt.address);
+            messages.push(Message {
+                queue_id: if rcpt_idx == 0 {
+                    self.queue_id
+                } else {
+                    server
+                        .inner
+                        .data
+                        .queue_id_gen
+                        .generate()
+                        .unwrap_or_else(now)
+                },
+                created: self.created,
+                blob_hash: self.blob_hash.clone(),
+                return_path_lcase: return_path.to_lowercase(),
+                return_path,
+                return_path_domain: self.return_path_domain.clone(),
+                recipients: vec![Recipient {
+                    domain_idx: 0,
+                    ..rcpt.clone()
+                }],
+                domains: vec![self.domains[rcpt.domain_idx].clone()],
+                flags: self.flags,
+                env_id: self.env_id.clone(),
+                priority: self.priority,
+                size: self.size,
+                quota_keys: Vec::new(),
+                span_id: self.span_id,
+            });
+        }
+        messages
+    }
+}
+
+async fn suppression_index(server: &Server, sender: &str) -> trc::Result<Vec<String>> {
+    server
+        .core
+        .storage
+        .lookup
+        .key_get::<Bincode<Vec<String>>>(format!("suppress:{sender}").into_bytes())
+        .await
+        .caused_by(trc::location!())
+        .map(|index| index.map(|index| index.inner).unwrap_or_default())
+}
+
+async fn set_suppression_index(
+    server: &Server,
+    sender: &str,
+    index: Vec<String>,
+) -> trc::Result<()> {
+    let key = format!("suppress:{sender}").into_bytes();
+    let lookup = &server.core.storage.lookup;
+    if !index.is_empty() {
+        lookup
+            .key_set(key, Bincode::new(index).serialize(), None)
+            .await
+    } else {
+        lookup.key_delete(key).await
+    }
+    .caused_by(trc::location!())
+}
+
+fn suppression_key(sender: &str, rcpt: &str) -> Vec<u8> {
+    format!("suppress:{sender}:{rcpt}").into_bytes()
+}
@@ -0,0 +1,766 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, future::Future};
+
+use common::{
+    addresses::{verp_decode, verp_encode},
+    config::smtp::list::{MailingList, Moderation},
+    listener::SessionStream,
+    Server,
+};
+use directory::backend::internal::{
+    lookup::DirectoryStore,
+    manage::{ManageDirectory, UpdatePrincipal},
+    PrincipalField, PrincipalUpdate, PrincipalValue,
+};
+use mail_builder::{headers::HeaderType, mime::make_boundary, MessageBuilder};
+use mail_parser::{MessageParser, MimeHeaders, PartType};
+use store::{
+    write::{now, Bincode},
+    Serialize,
+};
+use trc::{AddContext, SmtpEvent};
+
+use crate::{
+    queue::{Message, Status, RCPT_DSN_SENT},
+    reporting::SmtpReporting,
+};
+
+use super::Session;
+
+/// Headers replaced on every post, so that members only see the ones added
+/// by this list.
+const LIST_HEADERS: [&str; 10] = [
+    "List-Id",
+    "List-Post",
+    "List-Help",
+    "List-Owner",
+    "List-Subscribe",
+    "List-Unsubscribe",
+    "List-Unsubscribe-Post",
+    "List-Archive",
+    "Precedence",
+    "Subject",
+];
+
+/// Post waiting for a moderator to approve or reject it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HeldPost {
+    pub id: u64,
+    pub sender: String,
+    pub from: String,
+    pub subject: String,
+    pub received: u64,
+    pub expires: u64,
+    pub message: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListPostStatus {
+    Distributed(usize),
+    Held(u64),
+    Loop,
+}
+
+pub trait MailingListManager: Sync + Send {
+    fn list_post(
+        &self,
+        list: &MailingList,
+        sender: &str,
+        message: &[u8],
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<ListPostStatus>> + Send;
+
+    fn list_distribute(
+        &self,
+        list: &MailingList,
+        message: &[u8],
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<usize>> + Send;
+
+    fn list_members(
+        &self,
+        list: &MailingList,
+    ) -> impl Future<Output = trc::Result<Vec<String>>> + Send;
+
+    fn list_held(
+        &self,
+        list: &MailingList,
+    ) -> impl Future<Output = trc::Result<Vec<HeldPost>>> + Send;
+
+    fn list_held_get(
+        &self,
+        list: &MailingList,
+        id: u64,
+    ) -> impl Future<Output = trc::Result<Option<HeldPost>>> + Send;
+
+    fn list_release(
+        &self,
+        list: &MailingList,
+        id: u64,
+        approve: bool,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn list_bounces(
+        &self,
+        list: &MailingList,
+        member: &str,
+    ) -> impl Future<Output = trc::Result<i64>> + Send;
+
+    fn list_bounce(
+        &self,
+        list: &MailingList,
+        member: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_unsubscribe(
+        &self,
+        list: &MailingList,
+        member: &str,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl MailingListManager for Server {
+    async fn list_post(
+        &self,
+        list: &MailingList,
+        sender: &str,
+        message: &[u8],
+        session_id: u64,
+    ) -> trc::Result<ListPostStatus> {
+        let parsed = MessageParser::new().parse_headers(message);
+        let list_id = list_id(list);
+
+        // Posts that were already distributed by this list are dropped
+        if parsed
+            .as_ref()
+            .and_then(|message| message.header_raw("List-Id"))
+            .is_some_and(|value| value.contains(list_id.as_str()))
+        {
+            trc::event!(
+                Smtp(SmtpEvent::LoopDetected),
+                SpanId = session_id,
+                To = list.address.clone(),
+            );
+
+            return Ok(ListPostStatus::Loop);
+        }
+
+        let from = parsed
+            .as_ref()
+            .and_then(|message| message.from())
+            .and_then(|addr| addr.first())
+            .and_then(|addr| addr.address())
+            .map(|addr| addr.to_lowercase())
+            .unwrap_or_default();
+        let needs_approval = match list.moderation {
+            Moderation::Open => false,
+            Moderation::Members => {
+                let members = self.list_members(list).await?;
+                !members
+                    .iter()
+                    .any(|member| member == sender || *member == from)
+            }
+            Moderation::Moderated => true,
+        };
+
+        if !needs_approval {
+            return self
+                .list_distribute(list, message, session_id)
+                .await
+                .map(ListPostStatus::Distributed);
+        }
+
+        // Hold the post until a moderator approves it
+        let received = now();
+        let post = HeldPost {
+            id: self.inner.data.queue_id_gen.generate().unwrap_or(received),
+            sender: sender.to_string(),
+            from,
+            subject: parsed
+                .as_ref()
+                .and_then(|message| message.subject())
+                .unwrap_or_default()
+                .to_string(),
+            received,
+            expires: received + list.hold_expiry,
+            message: message.to_vec(),
+        };
+        let lookup = &self.core.storage.lookup;
+        lookup
+            .key_set(
+                held_key(list, post.id),
+                Bincode::new(post.clone()).serialize(),
+                list.hold_expiry.into(),
+            )
+            .await
+            .caused_by(trc::location!())?;
+        let mut index = held_index(self, list).await?;
+        index.push(post.id);
+        set_held_index(self, list, index).await?;
+
+        trc::event!(
+            Smtp(SmtpEvent::ListPostHeld),
+            SpanId = session_id,
+            To = list.address.clone(),
+            From = post.sender.clone(),
+            Id = post.id,
+        );
+
+        // Let the moderators know there is a post waiting for them
+        if !list.moderators.is_empty() {
+            let notice = MessageBuilder::new()
+                .from(list.address.as_str())
+                .to(list
+                    .moderators
+                    .iter()
+                    .map(|addr| addr.as_str())
+                    .collect::<Vec<_>>())
+                .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+                .message_id(format!("<{}@list>", make_boundary(".")))
+                .subject(format!("Post to {} held for moderation", list.address))
+                .text_body(format!(
+                    concat!(
+                        "A post from {} with subject {:?} is waiting for approval.\r\n\r\n",
+                        "The post will be discarded if it is not approved within {} days.\r\n"
+                    ),
+                    post.sender,
+                    post.subject,
+                    list.hold_expiry / 86400
+                ))
+                .write_to_vec()
+                .unwrap_or_default();
+
+            self.send_autogenerated(
+                list.bounce_address.clone(),
+                list.moderators.iter().cloned(),
+                notice,
+                None,
+                session_id,
+            )
+            .await;
+        }
+
+        Ok(ListPostStatus::Held(post.id))
+    }
+
+    async fn list_distribute(
+        &self,
+        list: &MailingList,
+        message: &[u8],
+        session_id: u64,
+    ) -> trc::Result<usize> {
+        let mut rcpts = self.list_members(list).await?;
+        if let Some(archive) = &list.archive {
+            rcpts.push(archive.clone());
+        }
+        rcpts.sort_unstable();
+        rcpts.dedup();
+        rcpts.retain(|rcpt| *rcpt != list.address && *rcpt != list.bounce_address);
+
+        let total = rcpts.len();
+        if total > 0 {
+            // Bounces are sent to the list so failing members can be removed
+            let message = list_message(list, message);
+            if list.verp {
+                for rcpt in rcpts {
+                    self.send_autogenerated(
+                        verp_encode(&list.bounce_address, &rcpt),
+                        std::iter::once(rcpt),
+                        message.clone(),
+                        None,
+                        session_id,
+                    )
+                    .await;
+                }
+            } else {
+                self.send_autogenerated(
+                    list.bounce_address.clone(),
+                    rcpts.into_iter(),
+                    message,
+                    None,
+                    session_id,
+                )
+                .await;
+            }
+        }
+
+        trc::event!(
+            Smtp(SmtpEvent::ListPostDistributed),
+            SpanId = session_id,
+            To = list.address.clone(),
+            Total = total,
+        );
+
+        Ok(total)
+    }
+
+    async fn list_members(&self, list: &MailingList) -> trc::Result<Vec<String>> {
+        self.core
+            .storage
+            .directory
+            .expn(&list.address)
+            .await
+            .map(|members| {
+                members
+                    .into_iter()
+                    .map(|member| member.to_lowercase())
+                    .collect()
+            })
+    }
+
+    async fn list_held(&self, list: &MailingList) -> trc::Result<Vec<HeldPost>> {
+        let index = held_index(self, list).await?;
+        let mut posts = Vec::with_capacity(index.len());
+        for id in &index {
+            if let Some(post) = self.list_held_get(list, *id).await? {
+                posts.push(post);
+            }
+        }
+
+        // Drop posts that expired without being moderated
+        if posts.len() != index.len() {
+            set_held_index(self, list, posts.iter().map(|post| post.id).collect()).await?;
+        }
+
+        Ok(posts)
+    }
+
+    async fn list_held_get(&self, list: &MailingList, id: u64) -> trc::Result<Option<HeldPost>> {
+        self.core
+            .storage
+            .lookup
+            .key_get::<Bincode<HeldPost>>(held_key(list, id))
+            .await
+            .caused_by(trc::location!())
+            .map(|post| post.map(|post| post.inner))
+    }
+
+    async fn list_release(&self, list: &MailingList, id: u64, approve: bool) -> trc::Result<bool> {
+        let Some(post) = self.list_held_get(list, id).await? else {
+            return Ok(false);
+        };
+
+        self.core
+            .storage
+            .lookup
+            .key_delete(held_key(list, id))
+            .await
+            .caused_by(trc::location!())?;
+        let mut index = held_index(self, list).await?;
+        index.retain(|item| *item != id);
+        set_held_index(self, list, index).await?;
+
+        if approve {
+            self.list_distribute(list, &post.message, id).await?;
+        }
+
+        trc::event!(
+            Smtp(if approve {
+                SmtpEvent::ListPostApproved
+            } else {
+                SmtpEvent::ListPostRejected
+            }),
+            To = list.address.clone(),
+            From = post.sender,
+            Id = id,
+        );
+
+        Ok(true)
+    }
+
+    async fn list_bounces(&self, list: &MailingList, member: &str) -> trc::Result<i64> {
+        self.core
+            .storage
+            .lookup
+            .counter_get(bounce_key(list, member))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn list_bounce(
+        &self,
+        list: &MailingList,
+        member: &str,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        let member = member.to_lowercase();
+        if !self.list_members(list).await?.contains(&member) {
+            return Ok(());
+        }
+
+        let lookup = &self.core.storage.lookup;
+        let bounces = lookup
+            .counter_incr(
+                bounce_key(list, &member),
+                1,
+                list.bounce_expiry.into(),
+                true,
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Smtp(SmtpEvent::ListBounce),
+            SpanId = session_id,
+            To = list.address.clone(),
+            From = member.clone(),
+            Total = bounces,
+        );
+
+        if bounces >= list.max_bounces as i64 && self.list_unsubscribe(list, &member).await? {
+            lookup
+                .counter_delete(bounce_key(list, &member))
+                .await
+                .caused_by(trc::location!())?;
+
+            trc::event!(
+                Smtp(SmtpEvent::ListUnsubscribed),
+                SpanId = session_id,
+                To = list.address.clone(),
+                From = member,
+                Total = bounces,
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn list_unsubscribe(&self, list: &MailingList, member: &str) -> trc::Result<bool> {
+        let store = &self.core.storage.data;
+        let Some(list_id) = store.email_to_id(&list.address).await? else {
+            return Ok(false);
+        };
+
+        // Members can be accounts or external addresses
+        let mut update = None;
+        for member_id in store.get_members(list_id).await? {
+            if let Some(principal) = store
+                .get_principal(member_id)
+                .await?
+                .filter(|principal| principal.has_str_value(PrincipalField::Emails, member))
+            {
+                update = PrincipalUpdate::remove_item(
+                    PrincipalField::Members,
+                    PrincipalValue::String(principal.name().to_string()),
+                )
+                .into();
+                break;
+            }
+        }
+        if update.is_none()
+            && store
+                .get_principal(list_id)
+                .await?
+                .is_some_and(|list| list.has_str_value(PrincipalField::ExternalMembers, member))
+        {
+            update = PrincipalUpdate::remove_item(
+                PrincipalField::ExternalMembers,
+                PrincipalValue::String(member.to_string()),
+            )
+            .into();
+        }
+
+        if let Some(update) = update {
+            store
+                .update_principal(UpdatePrincipal::by_id(list_id).with_updates(vec![update]))
+                .await
+                .caused_by(trc::location!())?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Distributes the message to the managed lists among its recipients and
+    /// records bounces addressed to them. Returns a response if there are no
+    /// recipients left to queue the message for.
+    pub async fn handle_mailing_lists(
+        &mut self,
+        headers: &[u8],
+        raw_message: &[u8],
+    ) -> Option<Cow<'static, [u8]>> {
+        let lists = &self.server.core.smtp.lists;
+        let mut posts = Vec::new();
+        let mut bounces = Vec::new();
+        self.data.rcpt_to.retain(|rcpt| {
+            if let Some(list) = lists.get(&rcpt.address_lcase) {
+                posts.push(list.clone());
+                false
+            } else if let Some(list) = lists.get_by_bounce(&rcpt.address_lcase) {
+                let member = verp_decode(&rcpt.address_lcase).map(|(_, member)| member);
+                bounces.push((list.clone(), member));
+                false
+            } else {
+                true
+            }
+        });
+        if posts.is_empty() && bounces.is_empty() {
+            return None;
+        }
+
+        let mut message = Vec::with_capacity(headers.len() + raw_message.len());
+        message.extend_from_slice(headers);
+        message.extend_from_slice(raw_message);
+        let sender = self
+            .data
+            .mail_from
+            .as_ref()
+            .map(|addr| addr.address_lcase.as_str())
+            .unwrap_or_default();
+
+        for list in posts {
+            if let Err(err) = self
+                .server
+                .list_post(&list, sender, &message, self.data.session_id)
+                .await
+            {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to process mailing list post"));
+
+                return Some((&b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into());
+            }
+        }
+
+        if !bounces.is_empty() {
+            let failed = failed_recipients(&message);
+            for (list, member) in bounces {
+                // VERP addresses identify the member even if the report lists
+                // a forwarding address
+                let members = match member {
+                    Some(member) if !failed.is_empty() => vec![member],
+                    Some(_) => vec![],
+                    None => failed.clone(),
+                };
+                for member in &members {
+                    if let Err(err) = self
+                        .server
+                        .list_bounce(&list, member, self.data.session_id)
+                        .await
+                    {
+                        trc::error!(err
+                            .span_id(self.data.session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to process mailing list bounce"));
+                    }
+                }
+            }
+        }
+
+        if self.data.rcpt_to.is_empty() {
+            self.data.messages_sent += 1;
+            Some((&b"250 2.0.0 Message queued for delivery.\r\n"[..]).into())
+        } else {
+            None
+        }
+    }
+}
+
+impl Message {
+    /// Returns the index of the recipients that failed permanently and were
+    /// not notified yet, along with the reason.
+    pub fn permanent_failures(&self) -> Vec<(usize, String)> {
+        let mut failed = Vec::new();
+        for (rcpt_idx, rcpt) in self.recipients.iter().enumerate() {
+            if rcpt.has_flag(RCPT_DSN_SENT) {
+                continue;
+            }
+            match &rcpt.status {
+                Status::PermanentFailure(_) => {
+                    failed.push((rcpt_idx, rcpt.status.to_string()));
+                }
+                Status::Scheduled => {
+                    let status = &self.domains[rcpt.domain_idx].status;
+                    if matches!(status, Status::PermanentFailure(_)) {
+                        failed.push((rcpt_idx, status.to_string()));
+                    }
+                }
+                _ => (),
+            }
+        }
+        failed
+    }
+
+    /// Returns the recipients that failed permanently, marking them so that
+    /// no DSN is generated for them.
+    pub fn take_permanent_failures(&mut self) -> Vec<String> {
+        self.permanent_failures()
+            .into_iter()
+            .map(|(rcpt_idx, _)| {
+                let rcpt = &mut self.recipients[rcpt_idx];
+                rcpt.flags |= RCPT_DSN_SENT;
+                rcpt.address_lcase.clone()
+            })
+            .collect()
+    }
+}
+
+/// Prepends the List-* headers and tags the subject. Any list headers already
+/// present, such as the ones added by another list manager, are removed.
+fn list_message(list: &MailingList, message: &[u8]) -> Vec<u8> {
+    let mut headers = Vec::with_capacity(message.len() + 256);
+    let mut subject: Option<Vec<u8>> = None;
+    let mut skip = false;
+    let mut is_subject = false;
+    let mut pos = 0;
+
+    while pos < message.len() {
+        let end = message[pos..]
+            .iter()
+            .position(|&ch| ch == b'\n')
+            .map_or(message.len(), |p| pos + p + 1);
+        let line = &message[pos..end];
+
+        if matches!(line, b"\r\n" | b"\n") {
+            break;
+        } else if !matches!(line.first(), Some(b' ' | b'\t')) {
+            let name = line
+                .iter()
+                .position(|&ch| ch == b':')
+                .map_or(&[][..], |p| &line[..p]);
+            skip = LIST_HEADERS
+                .iter()
+                .any(|header| header.as_bytes().eq_ignore_ascii_case(name));
+            is_subject = name.eq_ignore_ascii_case(b"Subject");
+            if is_subject {
+                subject = Some(line[name.len() + 1..].to_vec());
+            }
+        } else if is_subject {
+            if let Some(subject) = &mut subject {
+                subject.extend_from_slice(line);
+            }
+        }
+
+        if !skip {
+            headers.extend_from_slice(line);
+        }
+        pos = end;
+    }
+
+    let mut result = Vec::with_capacity(message.len() + 256);
+    let list_id = list_id(list);
+    result.extend_from_slice(b"List-Id: ");
+    if let Some(name) = &list.name {
+        result.extend_from_slice(format!("{name:?} ").as_bytes());
+    }
+    result.extend_from_slice(format!("<{list_id}>\r\n").as_bytes());
+    result.extend_from_slice(format!("List-Post: <mailto:{}>\r\n", list.address).as_bytes());
+    if let Some(unsubscribe) = &list.unsubscribe {
+        result.extend_from_slice(format!("List-Unsubscribe: <{unsubscribe}>\r\n").as_bytes());
+    }
+    if let Some(archive_url) = &list.archive_url {
+        result.extend_from_slice(format!("List-Archive: <{archive_url}>\r\n").as_bytes());
+    }
+    result.extend_from_slice(b"Precedence: list\r\n");
+
+    // Tag the subject unless a reply already carries the tag
+    let subject = subject
+        .map(|subject| String::from_utf8_lossy(&subject).trim().to_string())
+        .unwrap_or_default();
+    result.extend_from_slice(b"Subject: ");
+    match &list.subject_tag {
+        Some(tag) if !subject.contains(tag.as_str()) => {
+            result.extend_from_slice(tag.as_bytes());
+            if !subject.is_empty() {
+                result.push(b' ');
+            }
+        }
+        _ => (),
+    }
+    result.extend_from_slice(subject.as_bytes());
+    result.extend_from_slice(b"\r\n");
+    result.extend_from_slice(&headers);
+    result.extend_from_slice(&message[pos..]);
+    result
+}
+
+/// Returns the recipients reported as failed in a delivery status
+/// notification.
+pub(crate) fn failed_recipients(message: &[u8]) -> Vec<String> {
+    let mut failed = Vec::new();
+    let Some(message) = MessageParser::default().parse(message) else {
+        return failed;
+    };
+
+    for part in &message.parts {
+        if !part.is_content_type("message", "delivery-status")
+            || matches!(part.body, PartType::Multipart(_))
+        {
+            continue;
+        }
+        let Some(status) = message.raw_message().get(part.offset_body..part.offset_end) else {
+            continue;
+        };
+
+        // Per-recipient fields are separated from each other by blank lines
+        let status = String::from_utf8_lossy(status).replace("\r\n", "\n");
+        for fields in status.split("\n\n") {
+            let mut rcpt = None;
+            let mut is_failed = false;
+            for line in fields.lines() {
+                if let Some((name, value)) = line.split_once(':') {
+                    let name = name.trim();
+                    if name.eq_ignore_ascii_case("Final-Recipient") {
+                        rcpt = value
+                            .split_once(';')
+                            .map(|(_, addr)| addr.trim().to_lowercase())
+                            .filter(|addr| addr.contains('@'));
+                    } else if name.eq_ignore_ascii_case("Action") {
+                        is_failed = value.trim().eq_ignore_ascii_case("failed");
+                    }
+                }
+            }
+
+            if let (Some(rcpt), true) = (rcpt, is_failed) {
+                failed.push(rcpt);
+            }
+        }
+    }
+
+    failed
+}
+
+fn list_id(list: &MailingList) -> String {
+    list.address.replace('@', ".")
+}
+
+async fn held_index(server: &Server, list: &MailingList) -> trc::Result<Vec<u64>> {
+    server
+        .core
+        .storage
+        .lookup
+        .key_get::<Bincode<Vec<u64>>>(format!("list-held:{}", list.address).into_bytes())
+        .await
+        .caused_by(trc::location!())
+        .map(|index| index.map(|index| index.inner).unwrap_or_default())
+}
+
+async fn set_held_index(server: &Server, list: &MailingList, index: Vec<u64>) -> trc::Result<()> {
+    let key = format!("list-held:{}", list.address).into_bytes();
+    let lookup = &server.core.storage.lookup;
+    if !index.is_empty() {
+        lookup
+            .key_set(key, Bincode::new(index).serialize(), None)
+            .await
+    } else {
+        lookup.key_delete(key).await
+    }
+    .caused_by(trc::location!())
+}
+
+fn held_key(list: &MailingList, id: u64) -> Vec<u8> {
+    format!("list-held:{}:{id}", list.address).into_bytes()
+}
+
+fn bounce_key(list: &MailingList, member: &str) -> Vec<u8> {
+    format!("list-bounces:{}:{member}", list.address).into_bytes()
+}
@@ -33,7 +33,11 @@ use crate::{
     queue::{DomainPart, QueueId},
 };
 
+pub mod anomaly;
+pub mod bounce;
+pub mod list;
 pub mod params;
+pub mod reputation;
 pub mod throttle;
 
 #[derive(Clone)]
@@ -223,6 +227,7 @@ static SIEVE: LazyLock<Arc<ServerInstance>> = LazyLock::new(|| {
         limiter: ConcurrencyLimiter::new(0),
         shutdown_rx: tokio::sync::watch::channel(false).1,
         proxy_networks: vec![],
+        proxy_required: false,
         span_id_gen: Arc::new(SnowflakeIdGenerator::new()),
     })
 });
@@ -0,0 +1,582 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{
+    config::smtp::session::{ReputationAction, ReputationFeed, SenderReputation},
+    ipc::BroadcastEvent,
+    listener::SessionStream,
+    Server,
+};
+use mail_auth::{common::verify::VerifySignature, DkimOutput, DkimResult, SpfResult};
+use mail_parser::{HeaderName, Message};
+use store::{
+    write::{now, Bincode},
+    Serialize,
+};
+use trc::{AddContext, SpamEvent};
+
+use super::Session;
+
+pub use common::config::smtp::session::{ReputationEntity, ReputationKind, ReputationSignal};
+
+const FEED_VERSION: u32 = 1;
+const FEED_MAX_SAMPLES: f64 = 100.0;
+const LISTED_KEY: &[u8] = b"rep:listed";
+
+/// Rolling counters for a sending IP, domain or DKIM identity, decayed
+/// exponentially so that older observations weigh less over time.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Reputation {
+    pub ham: f64,
+    pub spam: f64,
+    pub bounces: f64,
+    pub complaints: f64,
+    pub updated: u64,
+}
+
+/// Reputation feed exchanged between independent deployments. Entities are
+/// identified by a SHA-256 hash so that only deployments that have seen an
+/// entity themselves are able to match it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FeedList {
+    pub version: u32,
+    pub generated: u64,
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FeedEntry {
+    pub kind: ReputationKind,
+    pub hash: String,
+    pub badness: f64,
+    pub samples: u32,
+    #[serde(default)]
+    pub banned: bool,
+}
+
+pub trait SmtpReputation: Sync + Send {
+    fn reputation_get(
+        &self,
+        entity: &ReputationEntity,
+    ) -> impl Future<Output = trc::Result<Option<Reputation>>> + Send;
+
+    fn reputation_update(
+        &self,
+        entities: &[ReputationEntity],
+        signal: ReputationSignal,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn reputation_apply(
+        &self,
+        entities: &[ReputationEntity],
+        signal: ReputationSignal,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn reputation_score(
+        &self,
+        entities: &[ReputationEntity],
+    ) -> impl Future<Output = trc::Result<Option<f64>>> + Send;
+
+    fn reputation_feed_export(&self) -> impl Future<Output = trc::Result<FeedList>> + Send;
+
+    fn reputation_feed_import(
+        &self,
+        feed: FeedList,
+    ) -> impl Future<Output = trc::Result<usize>> + Send;
+}
+
+impl SmtpReputation for Server {
+    async fn reputation_get(&self, entity: &ReputationEntity) -> trc::Result<Option<Reputation>> {
+        let config = if let Some(config) = &self.core.smtp.session.reputation {
+            config
+        } else {
+            return Ok(None);
+        };
+
+        let reputation = local_reputation(self, config, entity).await?;
+        if let Some(feed) = &config.feed {
+            // Add the observations imported from reputation feeds
+            if let Some(imported) = self
+                .lookup_store()
+                .key_get::<Bincode<Reputation>>(entity.feed_key())
+                .await
+                .caused_by(trc::location!())?
+            {
+                let mut imported = imported.inner;
+                imported.decay(config.half_life, now());
+                let mut reputation = reputation.unwrap_or_else(|| Reputation {
+                    updated: imported.updated,
+                    ..Default::default()
+                });
+                reputation.ham += imported.ham * feed.weight;
+                reputation.spam += imported.spam * feed.weight;
+                reputation.bounces += imported.bounces * feed.weight;
+                reputation.complaints += imported.complaints * feed.weight;
+                return Ok(Some(reputation));
+            }
+        }
+
+        Ok(reputation)
+    }
+
+    async fn reputation_update(
+        &self,
+        entities: &[ReputationEntity],
+        signal: ReputationSignal,
+    ) -> trc::Result<()> {
+        self.reputation_apply(entities, signal).await?;
+
+        // Share with other nodes in the cluster
+        if self.core.network.cluster_share.reputation && self.core.smtp.session.reputation.is_some()
+        {
+            let _ = self
+                .inner
+                .ipc
+                .broadcast_tx
+                .try_send(BroadcastEvent::ReputationUpdate {
+                    entities: entities.to_vec(),
+                    signal,
+                });
+        }
+
+        Ok(())
+    }
+
+    async fn reputation_apply(
+        &self,
+        entities: &[ReputationEntity],
+        signal: ReputationSignal,
+    ) -> trc::Result<()> {
+        let config = if let Some(config) = &self.core.smtp.session.reputation {
+            config
+        } else {
+            return Ok(());
+        };
+
+        for entity in entities {
+            let mut reputation = local_reputation(self, config, entity)
+                .await?
+                .unwrap_or_default();
+            reputation.decay(config.half_life, now());
+            match signal {
+                ReputationSignal::Ham => reputation.ham += 1.0,
+                ReputationSignal::Spam => reputation.spam += 1.0,
+                ReputationSignal::Bounce => reputation.bounces += 1.0,
+                ReputationSignal::Complaint => reputation.complaints += 1.0,
+            }
+
+            // List senders with a poor reputation in the exported feed
+            if let Some(feed) = &config.feed {
+                if reputation.samples() >= config.min_samples
+                    && reputation.badness() >= feed.threshold
+                {
+                    list_entity(self, config, feed, entity).await?;
+                }
+            }
+
+            self.lookup_store()
+                .key_set(
+                    entity.key(),
+                    Bincode::new(reputation).serialize(),
+                    config.expiry.into(),
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
+    async fn reputation_score(&self, entities: &[ReputationEntity]) -> trc::Result<Option<f64>> {
+        let config = if let Some(config) = &self.core.smtp.session.reputation {
+            config
+        } else {
+            return Ok(None);
+        };
+
+        let mut total = 0.0;
+        let mut count = 0;
+        for entity in entities {
+            if let Some(reputation) = self
+                .reputation_get(entity)
+                .await?
+                .filter(|reputation| reputation.samples() >= config.min_samples)
+            {
+                total += reputation.score(config);
+                count += 1;
+            }
+        }
+
+        Ok((count > 0).then(|| total / count as f64))
+    }
+
+    async fn reputation_feed_export(&self) -> trc::Result<FeedList> {
+        let mut entries = Vec::new();
+
+        if let Some((config, feed)) = self
+            .core
+            .smtp
+            .session
+            .reputation
+            .as_ref()
+            .and_then(|config| config.feed.as_ref().map(|feed| (config, feed)))
+        {
+            // Add senders with a poor reputation
+            for entity in listed_entities(self).await? {
+                if let Some(reputation) =
+                    local_reputation(self, config, &entity)
+                        .await?
+                        .filter(|reputation| {
+                            reputation.samples() >= config.min_samples
+                                && reputation.badness() >= feed.threshold
+                        })
+                {
+                    entries.push(FeedEntry {
+                        kind: entity.kind,
+                        hash: entity.feed_hash(),
+                        badness: (reputation.badness() * 100.0).round() / 100.0,
+                        samples: reputation.samples().min(FEED_MAX_SAMPLES).round() as u32,
+                        banned: false,
+                    });
+                }
+            }
+
+            // Add auto-banned IP addresses
+            let blocked_ips = self
+                .inner
+                .data
+                .blocked_ips
+                .read()
+                .iter()
+                .copied()
+                .collect::<Vec<_>>();
+            for ip in blocked_ips {
+                let hash = ReputationEntity {
+                    kind: ReputationKind::Ip,
+                    value: ip.to_string(),
+                }
+                .feed_hash();
+                if let Some(entry) = entries.iter_mut().find(|entry| entry.hash == hash) {
+                    entry.banned = true;
+                } else {
+                    entries.push(FeedEntry {
+                        kind: ReputationKind::Ip,
+                        hash,
+                        badness: 1.0,
+                        samples: FEED_MAX_SAMPLES as u32,
+                        banned: true,
+                    });
+                }
+            }
+        }
+
+        Ok(FeedList {
+            version: FEED_VERSION,
+            generated: now(),
+            entries,
+        })
+    }
+
+    async fn reputation_feed_import(&self, feed: FeedList) -> trc::Result<usize> {
+        let (config, feed_config) = if let Some((config, feed)) = self
+            .core
+            .smtp
+            .session
+            .reputation
+            .as_ref()
+            .and_then(|config| config.feed.as_ref().map(|feed| (config, feed)))
+        {
+            (config, feed)
+        } else {
+            return Ok(0);
+        };
+
+        if feed.version != FEED_VERSION {
+            return Err(trc::ResourceEvent::BadParameters
+                .into_err()
+                .details("Unsupported reputation feed version")
+                .ctx(trc::Key::Version, feed.version));
+        }
+
+        let mut imported = 0;
+        let now = now();
+        for entry in feed.entries {
+            if entry.hash.len() != 64
+                || !entry.hash.chars().all(|ch| ch.is_ascii_hexdigit())
+                || !(0.0..=1.0).contains(&entry.badness)
+            {
+                continue;
+            }
+
+            let (badness, samples) = if entry.banned {
+                (1.0, FEED_MAX_SAMPLES)
+            } else {
+                (entry.badness, (entry.samples as f64).min(FEED_MAX_SAMPLES))
+            };
+            let reputation = Reputation {
+                ham: (1.0 - badness) * samples,
+                spam: badness * samples,
+                updated: now,
+                ..Default::default()
+            };
+
+            self.lookup_store()
+                .key_set(
+                    format!("rep:feed:{}", entry.hash.to_ascii_lowercase()).into_bytes(),
+                    Bincode::new(reputation).serialize(),
+                    feed_config.expiry.min(config.expiry).into(),
+                )
+                .await
+                .caused_by(trc::location!())?;
+            imported += 1;
+        }
+
+        Ok(imported)
+    }
+}
+
+async fn local_reputation(
+    server: &Server,
+    config: &SenderReputation,
+    entity: &ReputationEntity,
+) -> trc::Result<Option<Reputation>> {
+    Ok(server
+        .lookup_store()
+        .key_get::<Bincode<Reputation>>(entity.key())
+        .await
+        .caused_by(trc::location!())?
+        .map(|reputation| {
+            let mut reputation = reputation.inner;
+            reputation.decay(config.half_life, now());
+            reputation
+        }))
+}
+
+async fn listed_entities(server: &Server) -> trc::Result<Vec<ReputationEntity>> {
+    server
+        .lookup_store()
+        .key_get::<Bincode<Vec<ReputationEntity>>>(LISTED_KEY.to_vec())
+        .await
+        .caused_by(trc::location!())
+        .map(|listed| listed.map(|listed| listed.inner).unwrap_or_default())
+}
+
+async fn list_entity(
+    server: &Server,
+    config: &SenderReputation,
+    feed: &ReputationFeed,
+    entity: &ReputationEntity,
+) -> trc::Result<()> {
+    let mut listed = listed_entities(server).await?;
+    if !listed.contains(entity) {
+        if listed.len() >= feed.max_entries {
+            listed.drain(..=listed.len() - feed.max_entries);
+        }
+        listed.push(entity.clone());
+        server
+            .lookup_store()
+            .key_set(
+                LISTED_KEY.to_vec(),
+                Bincode::new(listed).serialize(),
+                config.expiry.into(),
+            )
+            .await
+            .caused_by(trc::location!())?;
+    }
+
+    Ok(())
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Returns the action to take when the connecting IP has a poor reputation.
+    pub async fn reputation_throttle(&self) -> Option<ReputationAction> {
+        let config = self.server.core.smtp.session.reputation.as_ref()?;
+        let throttle = config.throttle.as_ref()?;
+        let entity = ReputationEntity {
+            kind: ReputationKind::Ip,
+            value: self.data.remote_ip.to_string(),
+        };
+
+        match self.server.reputation_get(&entity).await {
+            Ok(Some(reputation))
+                if reputation.samples() >= config.min_samples
+                    && reputation.badness() >= throttle.threshold =>
+            {
+                trc::event!(
+                    Spam(SpamEvent::ReputationThrottle),
+                    SpanId = self.data.session_id,
+                    RemoteIp = self.data.remote_ip,
+                    Value = reputation.badness(),
+                    Limit = throttle.threshold,
+                    Result = match throttle.action {
+                        ReputationAction::Delay(_) => "delay",
+                        ReputationAction::Reject => "reject",
+                    },
+                );
+
+                Some(throttle.action)
+            }
+            Ok(_) => None,
+            Err(err) => {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to obtain IP reputation"));
+                None
+            }
+        }
+    }
+
+    /// Returns the entities whose reputation is tracked for this session: the remote IP,
+    /// the envelope sender domain when SPF or DMARC passed and any passing DKIM domains.
+    pub fn reputation_entities(
+        &self,
+        dkim_output: &[DkimOutput<'_>],
+        dmarc_pass: bool,
+    ) -> Vec<ReputationEntity> {
+        if self.server.core.smtp.session.reputation.is_none() || self.is_authenticated() {
+            return vec![];
+        }
+
+        let mut entities = vec![ReputationEntity {
+            kind: ReputationKind::Ip,
+            value: self.data.remote_ip.to_string(),
+        }];
+        if let Some(mail_from) = self.data.mail_from.as_ref().filter(|mail_from| {
+            !mail_from.domain.is_empty()
+                && (dmarc_pass
+                    || self
+                        .data
+                        .spf_mail_from
+                        .as_ref()
+                        .is_some_and(|spf| spf.result() == SpfResult::Pass))
+        }) {
+            entities.push(ReputationEntity {
+                kind: ReputationKind::Domain,
+                value: mail_from.domain.clone(),
+            });
+        }
+        for output in dkim_output {
+            if let (DkimResult::Pass, Some(signature)) = (output.result(), output.signature()) {
+                let entity = ReputationEntity {
+                    kind: ReputationKind::Dkim,
+                    value: signature.domain().to_lowercase(),
+                };
+                if !entities.contains(&entity) {
+                    entities.push(entity);
+                }
+            }
+        }
+
+        entities
+    }
+
+    pub async fn reputation_signal(&self, entities: &[ReputationEntity], signal: ReputationSignal) {
+        if !entities.is_empty() {
+            if let Err(err) = self.server.reputation_update(entities, signal).await {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to update sender reputation"));
+            }
+        }
+    }
+}
+
+impl Reputation {
+    pub fn decay(&mut self, half_life: u64, now: u64) {
+        if self.updated != 0 && now > self.updated {
+            let factor = 0.5f64.powf((now - self.updated) as f64 / half_life as f64);
+            self.ham *= factor;
+            self.spam *= factor;
+            self.bounces *= factor;
+            self.complaints *= factor;
+        }
+        self.updated = now;
+    }
+
+    pub fn samples(&self) -> f64 {
+        self.ham + self.spam + self.bounces + self.complaints
+    }
+
+    /// Returns the share of negative observations, from 0.0 to 1.0, with
+    /// complaints weighing twice as much as other signals.
+    pub fn badness(&self) -> f64 {
+        let bad = self.spam + self.bounces + self.complaints * 2.0;
+        let total = self.ham + bad;
+        if total > 0.0 {
+            bad / total
+        } else {
+            0.5
+        }
+    }
+
+    /// Returns the adjustment to apply to the spam score, ranging from
+    /// `-factor` for senders of ham only to `factor` for senders of spam only.
+    pub fn score(&self, config: &SenderReputation) -> f64 {
+        (self.badness() - 0.5) * 2.0 * config.factor
+    }
+}
+
+/// Obtains the reputation entities of a delivered message from the trace headers
+/// added by this server, used when a user reports the message as spam.
+pub fn message_reputation_entities(message: &Message<'_>) -> Vec<ReputationEntity> {
+    let mut entities = Vec::new();
+    let headers = message.root_part().headers();
+
+    if let Some(ip) = headers
+        .iter()
+        .find(|header| header.name == HeaderName::Received)
+        .and_then(|header| header.value.as_received())
+        .and_then(|received| received.from_ip())
+    {
+        entities.push(ReputationEntity {
+            kind: ReputationKind::Ip,
+            value: ip.to_string(),
+        });
+    }
+
+    if let Some(results) = headers
+        .iter()
+        .find(|header| {
+            header
+                .name
+                .as_str()
+                .eq_ignore_ascii_case("Authentication-Results")
+        })
+        .and_then(|header| header.value.as_text())
+    {
+        for result in results.split(';').skip(1) {
+            let mut parts = result.split_ascii_whitespace();
+            let (kind, property) = match parts.next() {
+                Some(method) if method.eq_ignore_ascii_case("dkim=pass") => {
+                    (ReputationKind::Dkim, "header.d=")
+                }
+                Some(method) if method.eq_ignore_ascii_case("spf=pass") => {
+                    (ReputationKind::Domain, "smtp.mailfrom=")
+                }
+                _ => continue,
+            };
+            if let Some(value) = parts.find_map(|part| part.strip_prefix(property)) {
+                let domain = value
+                    .rsplit_once('@')
+                    .map_or(value, |(_, domain)| domain)
+                    .to_lowercase();
+                let entity = ReputationEntity {
+                    kind,
+                    value: domain,
+                };
+                if !entity.value.is_empty() && !entities.contains(&entity) {
+                    entities.push(entity);
+                }
+            }
+        }
+    }
+
+    entities
+}
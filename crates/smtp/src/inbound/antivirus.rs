@@ -0,0 +1,326 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use common::{
+    config::smtp::session::{Antivirus, AntivirusAction, AntivirusScanner},
+    listener::SessionStream,
+};
+use mail_parser::{Message, MessageParser, PartType};
+use store::{write::Bincode, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use trc::SmtpEvent;
+
+use crate::core::Session;
+
+use super::FilterResponse;
+
+const CLAMD_CHUNK_SIZE: usize = 64 * 1024;
+const MAX_RESPONSE_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Verdict {
+    Clean,
+    Infected(String),
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Scans the message parts for malware, returns the headers to add or
+    /// the response to reject the message with.
+    pub async fn run_antivirus(
+        &self,
+        message: &[u8],
+    ) -> Result<Vec<(String, String)>, FilterResponse> {
+        let antivirus = match &self.server.core.smtp.session.data.antivirus {
+            Some(antivirus)
+                if self
+                    .server
+                    .eval_if(&antivirus.enable, self, self.data.session_id)
+                    .await
+                    .unwrap_or(false) =>
+            {
+                antivirus
+            }
+            _ => return Ok(Vec::new()),
+        };
+        let Some(message) = MessageParser::new().parse(message) else {
+            return Ok(Vec::new());
+        };
+
+        let mut parts = Vec::new();
+        collect_parts(&message, antivirus.max_part_size, &mut parts);
+
+        for contents in parts {
+            let time = Instant::now();
+            let hash = blake3::hash(contents);
+
+            let virus = match self.scan_part(antivirus, hash.as_bytes(), contents).await {
+                Ok(Verdict::Clean) => continue,
+                Ok(Verdict::Infected(virus)) => virus,
+                Err(err) => {
+                    trc::event!(
+                        Smtp(SmtpEvent::MalwareScanError),
+                        SpanId = self.data.session_id,
+                        Reason = err,
+                        Elapsed = time.elapsed(),
+                    );
+
+                    if antivirus.tempfail_on_error {
+                        return Err(FilterResponse::temp_fail());
+                    } else {
+                        continue;
+                    }
+                }
+            };
+
+            trc::event!(
+                Smtp(SmtpEvent::MalwareFound),
+                SpanId = self.data.session_id,
+                Details = virus.clone(),
+                Elapsed = time.elapsed(),
+            );
+
+            let virus = virus
+                .chars()
+                .filter(|ch| !ch.is_ascii_control())
+                .collect::<String>();
+            return match antivirus.action {
+                AntivirusAction::Reject => Err(FilterResponse {
+                    message: format!("550 5.7.1 Message rejected, malware found ({virus}).\r\n")
+                        .into(),
+                    disconnect: false,
+                }),
+                AntivirusAction::Quarantine => Ok(vec![
+                    ("X-Virus-Status".to_string(), format!("Infected ({virus})")),
+                    (
+                        "X-Quarantine".to_string(),
+                        format!("Malware found ({virus})"),
+                    ),
+                ]),
+                AntivirusAction::Tag => Ok(vec![(
+                    "X-Virus-Status".to_string(),
+                    format!("Infected ({virus})"),
+                )]),
+            };
+        }
+
+        Ok(Vec::new())
+    }
+
+    async fn scan_part(
+        &self,
+        antivirus: &Antivirus,
+        hash: &[u8],
+        contents: &[u8],
+    ) -> Result<Verdict, String> {
+        // Check the cache first
+        let key = [b"av:".as_slice(), hash].concat();
+        if !antivirus.cache_ttl.is_zero() {
+            match self
+                .server
+                .lookup_store()
+                .key_get::<Bincode<Verdict>>(key.clone())
+                .await
+            {
+                Ok(Some(cached)) => return Ok(cached.inner),
+                Ok(None) => {}
+                Err(err) => {
+                    trc::error!(err
+                        .span_id(self.data.session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to obtain cached malware verdict."));
+                }
+            }
+        }
+
+        let verdict = match tokio::time::timeout(antivirus.timeout, async {
+            match &antivirus.scanner {
+                AntivirusScanner::Clamd { address } => clamd_scan(address, contents).await,
+                AntivirusScanner::Icap { address, host, url } => {
+                    icap_scan(address, host, url, contents).await
+                }
+            }
+        })
+        .await
+        {
+            Ok(result) => result?,
+            Err(_) => return Err("Malware scanner timed out".to_string()),
+        };
+
+        // Cache the result
+        if !antivirus.cache_ttl.is_zero() {
+            if let Err(err) = self
+                .server
+                .lookup_store()
+                .key_set(
+                    key,
+                    Bincode::new(verdict.clone()).serialize(),
+                    antivirus.cache_ttl.as_secs().into(),
+                )
+                .await
+            {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to cache malware verdict."));
+            }
+        }
+
+        Ok(verdict)
+    }
+}
+
+/// Streams the contents to clamd using the INSTREAM command.
+pub async fn clamd_scan(address: &str, contents: &[u8]) -> Result<Verdict, String> {
+    let mut stream = TcpStream::connect(address)
+        .await
+        .map_err(|err| format!("Failed to connect to clamd at {address}: {err}"))?;
+
+    let mut request = Vec::with_capacity(contents.len() + 32);
+    request.extend_from_slice(b"zINSTREAM\0");
+    for chunk in contents.chunks(CLAMD_CHUNK_SIZE) {
+        request.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        request.extend_from_slice(chunk);
+    }
+    request.extend_from_slice(&[0, 0, 0, 0]);
+    stream
+        .write_all(&request)
+        .await
+        .map_err(|err| format!("Failed to write to clamd: {err}"))?;
+
+    let mut response = Vec::with_capacity(128);
+    let mut buf = [0u8; 1024];
+    loop {
+        let len = stream
+            .read(&mut buf)
+            .await
+            .map_err(|err| format!("Failed to read from clamd: {err}"))?;
+        response.extend_from_slice(&buf[..len]);
+        if len == 0 || response.contains(&0) || response.len() > MAX_RESPONSE_SIZE {
+            break;
+        }
+    }
+
+    // Responses are formatted as "stream: OK" or "stream: <name> FOUND"
+    let response = String::from_utf8_lossy(&response);
+    let response = response.trim_end_matches(['\0', '\r', '\n']);
+    let result = response.strip_prefix("stream:").unwrap_or(response).trim();
+    if result == "OK" {
+        Ok(Verdict::Clean)
+    } else if let Some(virus) = result.strip_suffix(" FOUND") {
+        Ok(Verdict::Infected(virus.trim().to_string()))
+    } else {
+        Err(format!("Unexpected clamd response: {response:?}"))
+    }
+}
+
+/// Sends the contents to an ICAP server as a RESPMOD request (RFC 3507),
+/// a 204 response means the contents are clean.
+pub async fn icap_scan(
+    address: &str,
+    host: &str,
+    url: &str,
+    contents: &[u8],
+) -> Result<Verdict, String> {
+    let mut stream = TcpStream::connect(address)
+        .await
+        .map_err(|err| format!("Failed to connect to ICAP server at {address}: {err}"))?;
+
+    let http_header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+        contents.len()
+    );
+    let mut request = format!(
+        "RESPMOD {url} ICAP/1.0\r\nHost: {host}\r\nAllow: 204\r\nEncapsulated: res-hdr=0, res-body={}\r\n\r\n{http_header}",
+        http_header.len()
+    )
+    .into_bytes();
+    if !contents.is_empty() {
+        request.extend_from_slice(format!("{:x}\r\n", contents.len()).as_bytes());
+        request.extend_from_slice(contents);
+        request.extend_from_slice(b"\r\n");
+    }
+    request.extend_from_slice(b"0\r\n\r\n");
+    stream
+        .write_all(&request)
+        .await
+        .map_err(|err| format!("Failed to write to ICAP server: {err}"))?;
+
+    let mut response = Vec::with_capacity(256);
+    let mut buf = [0u8; 1024];
+    loop {
+        let len = stream
+            .read(&mut buf)
+            .await
+            .map_err(|err| format!("Failed to read from ICAP server: {err}"))?;
+        response.extend_from_slice(&buf[..len]);
+        if len == 0
+            || response.windows(4).any(|w| w == b"\r\n\r\n")
+            || response.len() > MAX_RESPONSE_SIZE
+        {
+            break;
+        }
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let mut lines = response.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line
+        .strip_prefix("ICAP/1.0 ")
+        .and_then(|line| line.split(' ').next())
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| format!("Invalid ICAP response: {status_line:?}"))?;
+
+    match status {
+        204 => Ok(Verdict::Clean),
+        200 => {
+            // Servers report infections using any of these headers, responses
+            // without them returned the contents unmodified
+            for line in lines.take_while(|line| !line.is_empty()) {
+                if let Some((name, value)) = line.split_once(':') {
+                    let name = name.trim();
+                    let value = value.trim();
+                    if name.eq_ignore_ascii_case("X-Infection-Found") {
+                        let virus = value
+                            .split(';')
+                            .filter_map(|param| param.trim().split_once('='))
+                            .find(|(name, _)| name.eq_ignore_ascii_case("Threat"))
+                            .map(|(_, threat)| threat.trim())
+                            .unwrap_or(value);
+                        return Ok(Verdict::Infected(virus.to_string()));
+                    } else if name.eq_ignore_ascii_case("X-Virus-ID")
+                        || name.eq_ignore_ascii_case("X-Violations-Found")
+                    {
+                        return Ok(Verdict::Infected(value.to_string()));
+                    }
+                }
+            }
+            Ok(Verdict::Clean)
+        }
+        status => Err(format!("ICAP server returned status {status}")),
+    }
+}
+
+/// Collects the leaf parts of a message, including those of attached
+/// messages, that are small enough to be scanned.
+fn collect_parts<'x>(message: &'x Message<'x>, max_size: usize, parts: &mut Vec<&'x [u8]>) {
+    for part in &message.parts {
+        match &part.body {
+            PartType::Multipart(_) => (),
+            PartType::Message(message) => collect_parts(message, max_size, parts),
+            _ => {
+                let contents = part.contents();
+                if !contents.is_empty() && contents.len() <= max_size {
+                    parts.push(contents);
+                }
+            }
+        }
+    }
+}
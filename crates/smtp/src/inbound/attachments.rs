@@ -0,0 +1,316 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{
+    config::smtp::session::{AttachmentAction, AttachmentPolicy, AttachmentStage},
+    expr::functions::ResolveVariable,
+    listener::SessionStream,
+    Server,
+};
+use mail_parser::{Message, MessageParser, MessagePart, MimeHeaders, PartType};
+use trc::SmtpEvent;
+
+use crate::core::Session;
+
+use super::FilterResponse;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub part_id: usize,
+    pub name: String,
+    pub reason: String,
+    pub action: AttachmentAction,
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Applies the DATA stage attachment policy, returns the message with the
+    /// offending attachments removed or the response to reject it with.
+    pub async fn run_attachment_policies(
+        &self,
+        message: &[u8],
+    ) -> Result<Option<Vec<u8>>, FilterResponse> {
+        let Some(policy) = attachment_policy(
+            &self.server,
+            AttachmentStage::Data,
+            self,
+            self.data.session_id,
+        )
+        .await
+        else {
+            return Ok(None);
+        };
+        let Some(parsed) = MessageParser::new().parse(message) else {
+            return Ok(None);
+        };
+
+        let violations = check_attachments(policy, &parsed);
+        if violations.is_empty() {
+            return Ok(None);
+        }
+
+        // Attachments that make up the whole message cannot be stripped
+        if let Some(violation) = violations
+            .iter()
+            .find(|v| v.action == AttachmentAction::Reject || v.part_id == 0)
+        {
+            trc::event!(
+                Smtp(SmtpEvent::AttachmentBlocked),
+                SpanId = self.data.session_id,
+                Id = policy.id.clone(),
+                Details = violation.name.clone(),
+                Reason = violation.reason.clone(),
+            );
+
+            return Err(FilterResponse {
+                message: format!(
+                    "550 5.7.1 Message rejected, attachment {:?} {}.\r\n",
+                    violation.name, violation.reason
+                )
+                .into(),
+                disconnect: false,
+            });
+        }
+
+        for violation in &violations {
+            trc::event!(
+                Smtp(SmtpEvent::AttachmentStripped),
+                SpanId = self.data.session_id,
+                Id = policy.id.clone(),
+                Details = violation.name.clone(),
+                Reason = violation.reason.clone(),
+            );
+        }
+
+        Ok(Some(strip_attachments(&parsed, &violations)))
+    }
+}
+
+/// Returns the first enabled attachment policy for the stage.
+pub async fn attachment_policy<'x>(
+    server: &'x Server,
+    stage: AttachmentStage,
+    resolver: &impl ResolveVariable,
+    session_id: u64,
+) -> Option<&'x AttachmentPolicy> {
+    for policy in &server.core.smtp.session.data.attachment_policies {
+        if policy.stage == stage
+            && server
+                .eval_if(&policy.enable, resolver, session_id)
+                .await
+                .unwrap_or(false)
+        {
+            return Some(policy);
+        }
+    }
+
+    None
+}
+
+/// Returns the attachments that violate the policy, attached messages are
+/// inspected as well but are reported as a whole.
+pub fn check_attachments(policy: &AttachmentPolicy, message: &Message<'_>) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for &part_id in &message.attachments {
+        let Some(part) = message.parts.get(part_id) else {
+            continue;
+        };
+        let name = attachment_name(part);
+        if let Some((reason, action)) = check_part(policy, part, &name) {
+            violations.push(Violation {
+                part_id,
+                name,
+                reason,
+                action,
+            });
+        }
+    }
+
+    violations
+}
+
+fn check_part(
+    policy: &AttachmentPolicy,
+    part: &MessagePart<'_>,
+    name: &str,
+) -> Option<(String, AttachmentAction)> {
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty());
+    if let Some(extension) = extension
+        .as_ref()
+        .filter(|ext| policy.block_extensions.contains(ext.as_str()))
+    {
+        return Some((
+            format!("has a blocked file extension (.{extension})"),
+            policy.action,
+        ));
+    }
+
+    let contents = part.contents();
+    if let Some(max_size) = policy
+        .max_size
+        .filter(|max_size| contents.len() > *max_size)
+    {
+        return Some((
+            format!("exceeds the maximum size of {max_size} bytes"),
+            policy.oversize,
+        ));
+    }
+
+    // Attached messages are inspected recursively
+    if let PartType::Message(nested) = &part.body {
+        return nested.attachments.iter().find_map(|part_id| {
+            let part = nested.parts.get(*part_id)?;
+            let nested_name = attachment_name(part);
+            check_part(policy, part, &nested_name).map(|(reason, action)| {
+                (format!("contains {nested_name:?} which {reason}"), action)
+            })
+        });
+    }
+
+    let file_type = detect_file_type(contents)?;
+    if policy.block_types.contains(file_type) {
+        return Some((
+            format!("has a blocked file type ({file_type})"),
+            policy.action,
+        ));
+    }
+    if let Some(action) = policy.mismatch {
+        let content_type = part
+            .content_type()
+            .map(|ct| {
+                format!("{}/{}", ct.ctype(), ct.subtype().unwrap_or_default()).to_ascii_lowercase()
+            })
+            .unwrap_or_default();
+        if let Some(expected) = extension
+            .as_deref()
+            .and_then(extension_file_type)
+            .filter(|expected| *expected != file_type)
+            .or_else(|| mime_file_type(&content_type).filter(|expected| *expected != file_type))
+        {
+            return Some((
+                format!("is declared as {expected} but contains {file_type} data"),
+                action,
+            ));
+        }
+    }
+
+    None
+}
+
+/// Detects the true file type of the contents using their magic bytes.
+pub fn detect_file_type(contents: &[u8]) -> Option<&'static str> {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"MZ", "exe"),
+        (b"\x7fELF", "elf"),
+        (b"\xfe\xed\xfa\xce", "macho"),
+        (b"\xfe\xed\xfa\xcf", "macho"),
+        (b"\xce\xfa\xed\xfe", "macho"),
+        (b"\xcf\xfa\xed\xfe", "macho"),
+        (b"\xca\xfe\xba\xbe", "macho"),
+        (b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1", "ole"),
+        (b"#!", "script"),
+        (b"%PDF-", "pdf"),
+        (b"PK\x03\x04", "zip"),
+        (b"Rar!\x1a\x07", "rar"),
+        (b"7z\xbc\xaf\x27\x1c", "7z"),
+        (b"\x1f\x8b", "gzip"),
+        (b"\x89PNG\r\n\x1a\n", "png"),
+        (b"\xff\xd8\xff", "jpeg"),
+        (b"GIF87a", "gif"),
+        (b"GIF89a", "gif"),
+    ];
+
+    MAGIC
+        .iter()
+        .find(|(magic, _)| contents.starts_with(magic))
+        .map(|(_, file_type)| *file_type)
+}
+
+fn extension_file_type(extension: &str) -> Option<&'static str> {
+    match extension {
+        "exe" | "dll" | "scr" | "com" | "cpl" | "sys" => Some("exe"),
+        "doc" | "xls" | "ppt" | "msi" | "msg" => Some("ole"),
+        "pdf" => Some("pdf"),
+        "zip" | "docx" | "xlsx" | "pptx" | "odt" | "ods" | "odp" | "jar" | "apk" | "epub" => {
+            Some("zip")
+        }
+        "rar" => Some("rar"),
+        "7z" => Some("7z"),
+        "gz" | "tgz" => Some("gzip"),
+        "png" => Some("png"),
+        "jpg" | "jpeg" => Some("jpeg"),
+        "gif" => Some("gif"),
+        _ => None,
+    }
+}
+
+fn mime_file_type(content_type: &str) -> Option<&'static str> {
+    match content_type {
+        "application/pdf" => Some("pdf"),
+        "application/zip" | "application/x-zip-compressed" => Some("zip"),
+        "application/x-rar-compressed" | "application/vnd.rar" => Some("rar"),
+        "application/x-7z-compressed" => Some("7z"),
+        "application/gzip" | "application/x-gzip" => Some("gzip"),
+        "application/msword" | "application/vnd.ms-excel" | "application/vnd.ms-powerpoint" => {
+            Some("ole")
+        }
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpeg"),
+        "image/gif" => Some("gif"),
+        _ => None,
+    }
+}
+
+/// Replaces the offending attachments with a text part explaining why they
+/// were removed.
+pub fn strip_attachments(message: &Message<'_>, violations: &[Violation]) -> Vec<u8> {
+    let raw_message = message.raw_message();
+    let mut parts = violations
+        .iter()
+        .filter_map(|violation| {
+            message
+                .parts
+                .get(violation.part_id)
+                .map(|part| (part.offset_header, part.offset_end, violation))
+        })
+        .collect::<Vec<_>>();
+    parts.sort_unstable_by_key(|(offset_start, _, _)| *offset_start);
+
+    let mut stripped = Vec::with_capacity(raw_message.len());
+    let mut offset = 0;
+    for (offset_start, offset_end, violation) in parts {
+        if offset_start < offset || offset_end > raw_message.len() {
+            continue;
+        }
+        stripped.extend_from_slice(&raw_message[offset..offset_start]);
+        stripped.extend_from_slice(
+            format!(
+                concat!(
+                    "Content-Type: text/plain; charset=\"utf-8\"\r\n",
+                    "Content-Disposition: inline\r\n",
+                    "\r\n",
+                    "The attachment {:?} was removed from this message because it {}."
+                ),
+                violation.name, violation.reason
+            )
+            .as_bytes(),
+        );
+        offset = offset_end;
+    }
+    stripped.extend_from_slice(&raw_message[offset..]);
+
+    stripped
+}
+
+fn attachment_name(part: &MessagePart<'_>) -> String {
+    part.attachment_name()
+        .unwrap_or("unnamed")
+        .chars()
+        .filter(|ch| !ch.is_control() && *ch != '"' && *ch != '\\')
+        .collect()
+}
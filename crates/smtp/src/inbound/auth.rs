@@ -4,23 +4,28 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::borrow::Cow;
+
 use common::{
     auth::{
+        delegation::Delegation,
         sasl::{
             sasl_decode_challenge_oauth, sasl_decode_challenge_plain, sasl_decode_challenge_xoauth,
         },
-        AuthRequest,
+        AccessToken, AuthRequest,
     },
     listener::SessionStream,
 };
 use directory::Permission;
-use mail_parser::decoders::base64::base64_decode;
+use mail_parser::{decoders::base64::base64_decode, MessageParser};
 use mail_send::Credentials;
 use smtp_proto::{IntoString, AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_XOAUTH2};
 use trc::{AuthEvent, SmtpEvent};
 
 use crate::core::Session;
 
+use super::FilterResponse;
+
 pub struct SaslToken {
     mechanism: u64,
     credentials: Credentials<String>,
@@ -137,6 +142,7 @@ impl<T: SessionStream> Session<T> {
                 Ok(access_token) => {
                     self.data.authenticated_as = access_token.into();
                     self.eval_post_auth_params().await;
+                    self.track_auth_network().await;
                     self.write(b"235 2.7.0 Authentication succeeded.\r\n")
                         .await?;
                     return Ok(false);
@@ -231,4 +237,108 @@ impl<T: SessionStream> Session<T> {
             .map(|token| token.emails.as_slice())
             .unwrap_or_default()
     }
+
+    /// Validates the Sender header of messages sent on behalf of another account,
+    /// returns the Sender header to add when it is missing.
+    pub fn delegated_sender_header(
+        &self,
+        raw_message: &[u8],
+    ) -> Result<Option<String>, FilterResponse> {
+        let token = if let Some(token) = &self.data.authenticated_as {
+            token
+        } else {
+            return Ok(None);
+        };
+
+        match check_delegated_sender(token, raw_message) {
+            DelegatedSender::Valid => Ok(None),
+            DelegatedSender::AddHeader(header) => Ok(Some(header)),
+            DelegatedSender::Unauthorized {
+                delegator,
+                from,
+                sender,
+            } => {
+                trc::event!(
+                    Smtp(SmtpEvent::SenderUnauthorized),
+                    SpanId = self.data.session_id,
+                    AccountName = delegator,
+                    From = from,
+                    Details = sender,
+                );
+
+                Err(FilterResponse {
+                    message: Cow::Borrowed(
+                        "550 5.7.1 Sender header must contain your own address.\r\n",
+                    ),
+                    disconnect: false,
+                })
+            }
+        }
+    }
+}
+
+pub enum DelegatedSender {
+    Valid,
+    AddHeader(String),
+    Unauthorized {
+        delegator: String,
+        from: String,
+        sender: String,
+    },
+}
+
+/// Enforces send-on-behalf semantics: messages with a delegator's address in the
+/// From header must identify the delegate in the Sender header, which is added
+/// when missing. Send-as delegates may use the delegator's address as is.
+pub fn check_delegated_sender(token: &AccessToken, raw_message: &[u8]) -> DelegatedSender {
+    if token.delegated_from.is_empty() {
+        return DelegatedSender::Valid;
+    }
+    let message = if let Some(message) = MessageParser::new().parse_headers(raw_message) {
+        message
+    } else {
+        return DelegatedSender::Valid;
+    };
+    let from = if let Some(from) = message
+        .from()
+        .and_then(|addr| addr.first())
+        .and_then(|addr| addr.address())
+    {
+        from.to_lowercase()
+    } else {
+        return DelegatedSender::Valid;
+    };
+    let is_own_address = |address: &str| {
+        address == token.name
+            || token
+                .emails
+                .iter()
+                .any(|e| e == address || (e.starts_with('@') && address.ends_with(e.as_str())))
+    };
+    if is_own_address(&from) {
+        return DelegatedSender::Valid;
+    }
+    let delegator = match token.delegated_sender(&from) {
+        Some(delegator) if !delegator.rights.contains(Delegation::SendAs) => delegator,
+        _ => return DelegatedSender::Valid,
+    };
+
+    match message
+        .sender()
+        .and_then(|addr| addr.first())
+        .and_then(|addr| addr.address())
+    {
+        Some(sender) if is_own_address(&sender.to_lowercase()) => DelegatedSender::Valid,
+        Some(sender) => DelegatedSender::Unauthorized {
+            delegator: delegator.name.clone(),
+            from,
+            sender: sender.to_string(),
+        },
+        None => token
+            .emails
+            .iter()
+            .find(|e| !e.starts_with('@'))
+            .map(|sender| DelegatedSender::AddHeader(format!("Sender: <{sender}>\r\n")))
+            .unwrap_or(DelegatedSender::Valid),
+    }
 }
@@ -0,0 +1,187 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use ahash::AHashSet;
+use common::listener::SessionStream;
+use directory::QueryBy;
+
+use crate::core::Session;
+
+pub const CLASSIFICATION_HEADER_PREFIX: &str = "X-Stalwart-";
+
+pub struct Classification {
+    pub started: Instant,
+    pub timings: Vec<(&'static str, Duration)>,
+    pub verdicts: Vec<(&'static str, &'static str)>,
+    pub spam: Vec<(String, String)>,
+}
+
+impl Default for Classification {
+    fn default() -> Self {
+        Classification {
+            started: Instant::now(),
+            timings: Vec::with_capacity(6),
+            verdicts: Vec::with_capacity(5),
+            spam: Vec::new(),
+        }
+    }
+}
+
+impl Classification {
+    pub fn timing(&mut self, stage: &'static str, time: Instant) {
+        self.timings.push((stage, time.elapsed()));
+    }
+
+    pub fn verdict(&mut self, method: &'static str, result: &'static str) {
+        if !result.is_empty() {
+            self.verdicts.push((method, result));
+        }
+    }
+
+    pub fn spam_header(&mut self, name: &str, value: &str) {
+        if let Some(name) = name
+            .strip_prefix("X-Spam-")
+            .or_else(|| name.strip_prefix("x-spam-"))
+        {
+            self.spam.push((
+                format!("Spam-{name}"),
+                value.trim_end_matches(['\r', '\n']).to_string(),
+            ));
+        }
+    }
+
+    pub fn write_header(&self, headers: &mut Vec<u8>, name: &str, value: &str) {
+        headers.extend_from_slice(CLASSIFICATION_HEADER_PREFIX.as_bytes());
+        headers.extend_from_slice(name.as_bytes());
+        headers.extend_from_slice(b": ");
+        headers.extend_from_slice(value.as_bytes());
+        headers.extend_from_slice(b"\r\n");
+    }
+
+    pub fn write_headers(&self, headers: &mut Vec<u8>, tenants: &[u32]) {
+        if !tenants.is_empty() {
+            self.write_header(
+                headers,
+                "Tenant",
+                &tenants
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+        }
+
+        if !self.verdicts.is_empty() {
+            self.write_header(
+                headers,
+                "Verdict",
+                &self
+                    .verdicts
+                    .iter()
+                    .map(|(method, result)| format!("{method}={result}"))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            );
+        }
+
+        for (name, value) in &self.spam {
+            self.write_header(headers, name, value);
+        }
+
+        let mut timings = self
+            .timings
+            .iter()
+            .map(|(stage, elapsed)| format!("{stage}={}ms", elapsed.as_millis()))
+            .collect::<Vec<_>>();
+        timings.push(format!("total={}ms", self.started.elapsed().as_millis()));
+        self.write_header(headers, "Timings", &timings.join("; "));
+    }
+}
+
+impl<T: SessionStream> Session<T> {
+    pub async fn classification_tenants(&self) -> Vec<u32> {
+        // Authenticated senders are classified under their own tenant
+        if let Some(token) = &self.data.authenticated_as {
+            return token.tenant.map(|t| vec![t.id]).unwrap_or_default();
+        }
+
+        // Otherwise use the tenants owning the recipient domains
+        let mut tenants = Vec::new();
+        if let Some(directory) = self
+            .server
+            .eval_if::<String, _>(
+                &self.server.core.smtp.session.rcpt.directory,
+                self,
+                self.data.session_id,
+            )
+            .await
+            .and_then(|name| self.server.get_directory(&name))
+        {
+            let mut domains = AHashSet::new();
+            for rcpt in &self.data.rcpt_to {
+                if domains.insert(rcpt.domain.as_str()) {
+                    match directory.query(QueryBy::Name(&rcpt.domain), false).await {
+                        Ok(Some(principal)) => {
+                            if let Some(tenant_id) = principal.tenant() {
+                                if !tenants.contains(&tenant_id) {
+                                    tenants.push(tenant_id);
+                                }
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            trc::error!(err
+                                .span_id(self.data.session_id)
+                                .details("Failed to obtain recipient domain tenant"));
+                        }
+                    }
+                }
+            }
+        }
+
+        tenants
+    }
+}
+
+/// Removes any classification headers from the message header section,
+/// returns `None` when the message does not contain any.
+pub fn strip_classification_headers(message: &[u8]) -> Option<Vec<u8>> {
+    let prefix = CLASSIFICATION_HEADER_PREFIX.as_bytes();
+    let mut result: Option<Vec<u8>> = None;
+    let mut skip = false;
+    let mut pos = 0;
+
+    while pos < message.len() {
+        let end = message[pos..]
+            .iter()
+            .position(|&ch| ch == b'\n')
+            .map_or(message.len(), |p| pos + p + 1);
+        let line = &message[pos..end];
+
+        if matches!(line, b"\r\n" | b"\n") {
+            break;
+        } else if !matches!(line.first(), Some(b' ' | b'\t')) {
+            skip = line.len() > prefix.len() && line[..prefix.len()].eq_ignore_ascii_case(prefix);
+            if skip && result.is_none() {
+                result = Some(message[..pos].to_vec());
+            }
+        }
+
+        if !skip {
+            if let Some(result) = &mut result {
+                result.extend_from_slice(line);
+            }
+        }
+        pos = end;
+    }
+
+    result.map(|mut result| {
+        result.extend_from_slice(&message[pos..]);
+        result
+    })
+}
@@ -12,7 +12,10 @@ use std::{
 };
 
 use common::{
-    config::smtp::{auth::VerifyStrategy, session::Stage},
+    config::smtp::{
+        auth::VerifyStrategy,
+        session::{OutboundSpamAction, Stage},
+    },
     listener::SessionStream,
     psl,
     scripts::ScriptModification,
@@ -28,22 +31,29 @@ use smtp_proto::{
 };
 use store::write::now;
 use tokio::{io::AsyncWriteExt, process::Command};
-use trc::SmtpEvent;
+use trc::{SmtpEvent, SpamEvent};
 use utils::config::Rate;
 
 use crate::{
-    core::{Session, SessionAddress, State},
+    core::{
+        reputation::{ReputationSignal, SmtpReputation},
+        Session, SessionAddress, State,
+    },
     inbound::milter::Modification,
     queue::{self, quota::HasQueueQuota, Message, MessageSource, QueueEnvelope, Schedule},
     reporting::analysis::AnalyzeReport,
     scripts::ScriptResult,
 };
 
-use super::{ArcSeal, AuthResult, DkimSign};
+use super::{
+    classify::{strip_classification_headers, Classification},
+    ArcSeal, AuthResult, DkimSign,
+};
 
 impl<T: SessionStream> Session<T> {
     pub async fn queue_message(&mut self) -> Cow<'static, [u8]> {
         // Authenticate message
+        let mut classification = Classification::default();
         let raw_message = Arc::new(std::mem::take(&mut self.data.message));
         let auth_message = if let Some(auth_message) = AuthenticatedMessage::parse_with_opts(
             &raw_message,
@@ -80,6 +90,12 @@ impl<T: SessionStream> Session<T> {
                 .into();
         }
 
+        // Validate messages sent on behalf of another account
+        let sender_header = match self.delegated_sender_header(&raw_message) {
+            Ok(sender_header) => sender_header,
+            Err(response) => return response.into_bytes(),
+        };
+
         // Verify DKIM
         let dkim = self
             .server
@@ -132,6 +148,7 @@ impl<T: SessionStream> Session<T> {
                 Result = dkim_output.iter().map(trc::Event::from).collect::<Vec<_>>(),
                 Elapsed = time.elapsed(),
             );
+            classification.timing("dkim", time);
 
             if rejected {
                 // 'Strict' mode violates the advice of Section 6.1 of RFC6376
@@ -186,6 +203,7 @@ impl<T: SessionStream> Session<T> {
                 Result = trc::Event::from(arc_output.result()),
                 Elapsed = time.elapsed(),
             );
+            classification.timing("arc", time);
 
             if strict && !pass {
                 return if matches!(arc_output.result(), DkimResult::TempError(_)) {
@@ -283,6 +301,7 @@ impl<T: SessionStream> Session<T> {
                     Result = trc::Event::from(&dmarc_result),
                     Elapsed = time.elapsed(),
                 );
+                classification.timing("dmarc", time);
 
                 // Send DMARC report
                 if dmarc_output.requested_reports() && !is_report {
@@ -310,6 +329,47 @@ impl<T: SessionStream> Session<T> {
             _ => (None, None),
         };
 
+        // Record authentication verdicts
+        classification.verdict(
+            "spf",
+            self.data
+                .spf_mail_from
+                .as_ref()
+                .map(|r| r.result().as_str())
+                .unwrap_or_default(),
+        );
+        classification.verdict(
+            "dkim",
+            dkim_output
+                .iter()
+                .find(|r| matches!(r.result(), DkimResult::Pass))
+                .or_else(|| dkim_output.first())
+                .map(|r| r.result().as_str())
+                .unwrap_or_default(),
+        );
+        classification.verdict(
+            "arc",
+            arc_output
+                .as_ref()
+                .map(|a| a.result().as_str())
+                .unwrap_or_default(),
+        );
+        classification.verdict(
+            "dmarc",
+            dmarc_result
+                .as_ref()
+                .map(|a| a.as_str())
+                .unwrap_or_default(),
+        );
+        classification.verdict(
+            "iprev",
+            self.data
+                .iprev
+                .as_ref()
+                .map(|r| r.result().as_str())
+                .unwrap_or_default(),
+        );
+
         // Analyze reports
         if is_report {
             self.server
@@ -348,6 +408,11 @@ impl<T: SessionStream> Session<T> {
             auth_results.write_header(&mut headers);
         }
 
+        // Add Sender header for messages sent on behalf of another account
+        if let Some(sender_header) = sender_header {
+            headers.extend_from_slice(sender_header.as_bytes());
+        }
+
         // Add Received-SPF header
         if let Some(spf_output) = &self.data.spf_mail_from {
             if self
@@ -385,6 +450,7 @@ impl<T: SessionStream> Session<T> {
 
         // Run Milter filters
         let mut modifications = Vec::new();
+        let time = Instant::now();
         match self.run_milters(Stage::Data, (&auth_message).into()).await {
             Ok(modifications_) => {
                 if !modifications_.is_empty() {
@@ -395,10 +461,12 @@ impl<T: SessionStream> Session<T> {
                 return response.into_bytes();
             }
         };
+        classification.timing("milter", time);
 
         // Run MTA Hooks
+        let time = Instant::now();
         match self
-            .run_mta_hooks(Stage::Data, (&auth_message).into(), message_id.into())
+            .run_mta_hooks(Stage::Data, (&auth_message).into(), message_id.into(), &[])
             .await
         {
             Ok(modifications_) => {
@@ -411,6 +479,7 @@ impl<T: SessionStream> Session<T> {
                 return response.into_bytes();
             }
         };
+        classification.timing("hooks", time);
 
         // Apply modifications
         let mut edited_message = if !modifications.is_empty() {
@@ -531,6 +600,87 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Attachment policies
+        let time = Instant::now();
+        match self
+            .run_attachment_policies(edited_message.as_ref().unwrap_or(&raw_message))
+            .await
+        {
+            Ok(Some(stripped_message)) => {
+                edited_message = stripped_message.into();
+            }
+            Ok(None) => (),
+            Err(response) => {
+                return response.into_bytes();
+            }
+        }
+        classification.timing("attachments", time);
+
+        // Malware scanner
+        let time = Instant::now();
+        match self
+            .run_antivirus(edited_message.as_ref().unwrap_or(&raw_message))
+            .await
+        {
+            Ok(antivirus_headers) => {
+                for (name, value) in antivirus_headers {
+                    headers.extend_from_slice(name.as_bytes());
+                    headers.extend_from_slice(b": ");
+                    headers.extend_from_slice(value.as_bytes());
+                    headers.extend_from_slice(b"\r\n");
+                }
+            }
+            Err(response) => {
+                return response.into_bytes();
+            }
+        }
+        classification.timing("antivirus", time);
+
+        // External spam classifier
+        let time = Instant::now();
+        match self
+            .run_rspamd(edited_message.as_ref().unwrap_or(&raw_message))
+            .await
+        {
+            Ok(spam_headers) => {
+                for (name, value) in spam_headers {
+                    classification.spam_header(&name, &value);
+                    headers.extend_from_slice(name.as_bytes());
+                    headers.extend_from_slice(b": ");
+                    headers.extend_from_slice(value.as_bytes());
+                    headers.extend_from_slice(b"\r\n");
+                }
+            }
+            Err(response) => {
+                return response.into_bytes();
+            }
+        }
+        classification.timing("rspamd", time);
+
+        // Sender reputation
+        let reputation_entities = self.reputation_entities(
+            &dkim_output,
+            matches!(dmarc_result, Some(DmarcResult::Pass)),
+        );
+        let reputation_score = match self.server.reputation_score(&reputation_entities).await {
+            Ok(score) => score,
+            Err(err) => {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to obtain sender reputation"));
+                None
+            }
+        };
+        if let Some(score) = reputation_score {
+            trc::event!(
+                Spam(SpamEvent::Reputation),
+                SpanId = self.data.session_id,
+                RemoteIp = self.data.remote_ip,
+                Value = score,
+            );
+        }
+
         // Sieve filtering
         if let Some((script, script_id)) = self
             .server
@@ -542,7 +692,7 @@ impl<T: SessionStream> Session<T> {
                     .map(|s| (s, name))
             })
         {
-            let params = self
+            let mut params = self
                 .build_script_parameters("data")
                 .with_message(edited_message.as_ref().unwrap_or(&raw_message))
                 .with_auth_headers(&headers)
@@ -590,7 +740,11 @@ impl<T: SessionStream> Session<T> {
                         .map(|a| a.as_str())
                         .unwrap_or_default(),
                 );
+            if let Some(score) = reputation_score {
+                params = params.set_variable("reputation.score", score);
+            }
 
+            let time = Instant::now();
             let modifications = match self.run_script(script_id, script.clone(), params).await {
                 ScriptResult::Accept { modifications } => modifications,
                 ScriptResult::Replace {
@@ -607,11 +761,13 @@ impl<T: SessionStream> Session<T> {
                     return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
                 }
             };
+            classification.timing("script", time);
 
             // Apply modifications
             for modification in modifications {
                 match modification {
                     ScriptModification::AddHeader { name, value } => {
+                        classification.spam_header(&name, &value);
                         headers.extend_from_slice(name.as_bytes());
                         headers.extend_from_slice(b": ");
                         headers.extend_from_slice(value.as_bytes());
@@ -626,6 +782,120 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Update the sender reputation with the spam filter verdict
+        if let Some((_, status)) = classification
+            .spam
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Spam-Status"))
+        {
+            let status = status.trim_start();
+            let signal = if status.starts_with("Yes") {
+                Some(ReputationSignal::Spam)
+            } else if status.starts_with("No") {
+                Some(ReputationSignal::Ham)
+            } else {
+                None
+            };
+            if let Some(signal) = signal {
+                self.reputation_signal(&reputation_entities, signal).await;
+            }
+        }
+
+        // Run MTA Hooks on the classified message
+        let time = Instant::now();
+        match self
+            .run_mta_hooks(
+                Stage::Classify,
+                (&auth_message).into(),
+                message_id.into(),
+                &classification.spam,
+            )
+            .await
+        {
+            Ok(modifications) => {
+                // Only headers can be added once the message has been classified
+                for modification in modifications {
+                    if let Modification::AddHeader { name, value } = modification {
+                        headers.extend_from_slice(name.as_bytes());
+                        headers.extend_from_slice(b": ");
+                        headers.extend_from_slice(value.as_bytes());
+                        if !value.ends_with('\n') {
+                            headers.extend_from_slice(b"\r\n");
+                        }
+                    }
+                }
+            }
+            Err(response) => {
+                return response.into_bytes();
+            }
+        };
+        classification.timing("classify", time);
+
+        // Scan submissions from authenticated senders
+        let outbound_spam = self
+            .scan_outbound_spam(edited_message.as_ref().unwrap_or(&raw_message))
+            .await;
+        if outbound_spam == Some(OutboundSpamAction::Reject) {
+            return (&b"550 5.7.1 Message rejected as possible spam.\r\n"[..]).into();
+        }
+
+        // Add classification headers, removing any supplied by the sender
+        if self
+            .server
+            .eval_if(&dc.add_classification, self, self.data.session_id)
+            .await
+            .unwrap_or(false)
+        {
+            if let Some(stripped) =
+                strip_classification_headers(edited_message.as_ref().unwrap_or(&raw_message))
+            {
+                edited_message = stripped.into();
+            }
+            for tag in &dc.classification_tags {
+                if let Some(value) = self
+                    .server
+                    .eval_if::<String, _>(&tag.value, self, self.data.session_id)
+                    .await
+                    .filter(|value| !value.is_empty())
+                {
+                    classification.write_header(&mut headers, &tag.name, &value);
+                }
+            }
+            classification.write_headers(&mut headers, &self.classification_tenants().await);
+        }
+
+        // Add bounced recipients to the suppression list of local senders
+        if self.server.core.smtp.queue.suppression.enable
+            && self
+                .data
+                .mail_from
+                .as_ref()
+                .is_some_and(|addr| addr.address.is_empty())
+        {
+            self.handle_bounces(
+                &headers,
+                edited_message.as_deref().unwrap_or(raw_message.as_slice()),
+            )
+            .await;
+        }
+
+        // Distribute posts to managed mailing lists
+        if !self.server.core.smtp.lists.is_empty() {
+            if let Some(response) = self
+                .handle_mailing_lists(
+                    &headers,
+                    edited_message.as_deref().unwrap_or(raw_message.as_slice()),
+                )
+                .await
+            {
+                return response;
+            }
+        }
+
+        // Mailing list handling borrows the session mutably, borrow the configuration again
+        let dc = &self.server.core.smtp.session.data;
+        let ac = &self.server.core.smtp.mail_auth;
+
         // Build message
         let mail_from = self.data.mail_from.clone().unwrap();
         let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
@@ -633,6 +903,14 @@ impl<T: SessionStream> Session<T> {
             .build_message(mail_from, rcpt_to, message_id, self.data.session_id)
             .await;
 
+        // Held messages are not delivered until released by an administrator
+        if outbound_spam == Some(OutboundSpamAction::Hold) {
+            for domain in &mut message.domains {
+                domain.retry.due = domain.expires;
+                domain.notify.due = domain.expires;
+            }
+        }
+
         // Add Return-Path
         if self
             .server
@@ -696,18 +974,41 @@ impl<T: SessionStream> Session<T> {
         // Update size
         message.size = raw_message.len() + headers.len();
 
+        // Track submission patterns of authenticated senders
+        if !self.track_submission(&message).await {
+            return (b"451 4.7.1 Submission temporarily suspended for this account.\r\n"[..])
+                .into();
+        }
+
+        // Encode each recipient in the return path of its own copy
+        let queue_id = message.queue_id;
+        let mut messages = if !message.return_path.is_empty()
+            && self
+                .server
+                .eval_if(&dc.verp, self, self.data.session_id)
+                .await
+                .unwrap_or(false)
+        {
+            message.into_verp(&self.server)
+        } else {
+            vec![message]
+        };
+
         // Verify queue quota
-        if self.server.has_quota(&mut message).await {
-            // Prepare webhook event
-            let queue_id = message.queue_id;
+        for message in &mut messages {
+            if !self.server.has_quota(message).await {
+                return (b"452 4.3.1 Mail system full, try again later.\r\n"[..]).into();
+            }
+        }
 
-            // Queue message
-            let source = if !self.is_authenticated() {
-                MessageSource::Unauthenticated
-            } else {
-                MessageSource::Authenticated
-            };
-            if message
+        // Queue message
+        let source = if !self.is_authenticated() {
+            MessageSource::Unauthenticated
+        } else {
+            MessageSource::Authenticated
+        };
+        for message in messages {
+            if !message
                 .queue(
                     Some(&headers),
                     raw_message,
@@ -717,15 +1018,13 @@ impl<T: SessionStream> Session<T> {
                 )
                 .await
             {
-                self.state = State::Accepted(queue_id);
-                self.data.messages_sent += 1;
-                (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
-            } else {
-                (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
+                return (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into();
             }
-        } else {
-            (b"452 4.3.1 Mail system full, try again later.\r\n"[..]).into()
         }
+
+        self.state = State::Accepted(queue_id);
+        self.data.messages_sent += 1;
+        (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
     }
 
     pub async fn build_message(
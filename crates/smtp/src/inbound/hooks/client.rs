@@ -4,7 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::{config::smtp::session::MTAHook, HttpLimitResponse};
+use common::{
+    config::smtp::session::{MTAHook, WasmHook},
+    scripts::wasm,
+    HttpLimitResponse,
+};
 
 use super::{Request, Response};
 
@@ -45,3 +49,21 @@ pub(super) async fn send_mta_hook_request(
         ))
     }
 }
+
+pub(super) async fn run_wasm_hook(
+    wasm_hook: &WasmHook,
+    request: Request,
+) -> Result<Response, String> {
+    let request = serde_json::to_vec(&request)
+        .map_err(|err| format!("Failed to serialize Hook request: {}", err))?;
+    let module = wasm_hook.module.clone();
+    let limits = wasm_hook.limits;
+    let response = tokio::task::spawn_blocking(move || {
+        wasm::call_module(&module, limits, "on_hook", &request)
+    })
+    .await
+    .map_err(|err| format!("Hook task failed: {err}"))??;
+
+    serde_json::from_slice(&response)
+        .map_err(|err| format!("Failed to parse Hook response: {}", err))
+}
@@ -8,7 +8,8 @@ use std::time::Instant;
 
 use ahash::AHashMap;
 use common::{
-    config::smtp::session::{MTAHook, Stage},
+    config::smtp::session::{MTAHook, Stage, WasmHook},
+    expr::if_block::IfBlock,
     listener::SessionStream,
     DAEMON_NAME,
 };
@@ -27,7 +28,16 @@ use crate::{
     queue::QueueId,
 };
 
-use super::{client::send_mta_hook_request, Action, Queue, Response};
+use super::{
+    client::{run_wasm_hook, send_mta_hook_request},
+    Action, Queue,
+};
+
+#[derive(Clone, Copy)]
+enum Hook<'x> {
+    Http(&'x MTAHook),
+    Wasm(&'x WasmHook),
+}
 
 impl<T: SessionStream> Session<T> {
     pub async fn run_mta_hooks(
@@ -35,18 +45,24 @@ impl<T: SessionStream> Session<T> {
         stage: Stage,
         message: Option<&AuthenticatedMessage<'_>>,
         queue_id: Option<QueueId>,
+        server_headers: &[(String, String)],
     ) -> Result<Vec<Modification>, FilterResponse> {
-        let mta_hooks = &self.server.core.smtp.session.hooks;
-        if mta_hooks.is_empty() {
+        let session_config = &self.server.core.smtp.session;
+        if session_config.hooks.is_empty() && session_config.wasm_hooks.is_empty() {
             return Ok(Vec::new());
         }
 
         let mut modifications = Vec::new();
-        for mta_hook in mta_hooks {
-            if !mta_hook.run_on_stage.contains(&stage)
+        for mta_hook in session_config
+            .hooks
+            .iter()
+            .map(Hook::Http)
+            .chain(session_config.wasm_hooks.iter().map(Hook::Wasm))
+        {
+            if !mta_hook.run_on_stage(stage)
                 || !self
                     .server
-                    .eval_if(&mta_hook.enable, self, self.data.session_id)
+                    .eval_if(mta_hook.enable(), self, self.data.session_id)
                     .await
                     .unwrap_or(false)
             {
@@ -54,7 +70,12 @@ impl<T: SessionStream> Session<T> {
             }
 
             let time = Instant::now();
-            match self.run_mta_hook(stage, mta_hook, message, queue_id).await {
+            let request = self.build_mta_hook_request(stage, message, queue_id, server_headers);
+            let result = match mta_hook {
+                Hook::Http(mta_hook) => send_mta_hook_request(mta_hook, request).await,
+                Hook::Wasm(wasm_hook) => run_wasm_hook(wasm_hook, request).await,
+            };
+            match result {
                 Ok(response) => {
                     trc::event!(
                         MtaHook(match response.action {
@@ -64,7 +85,7 @@ impl<T: SessionStream> Session<T> {
                             Action::Quarantine => MtaHookEvent::ActionQuarantine,
                         }),
                         SpanId = self.data.session_id,
-                        Id = mta_hook.id.clone(),
+                        Id = mta_hook.id().to_string(),
                         Elapsed = time.elapsed(),
                     );
 
@@ -155,12 +176,12 @@ impl<T: SessionStream> Session<T> {
                     trc::event!(
                         MtaHook(MtaHookEvent::Error),
                         SpanId = self.data.session_id,
-                        Id = mta_hook.id.clone(),
+                        Id = mta_hook.id().to_string(),
                         Reason = err,
                         Elapsed = time.elapsed(),
                     );
 
-                    if mta_hook.tempfail_on_error {
+                    if mta_hook.tempfail_on_error() {
                         return Err(FilterResponse::server_failure());
                     }
                 }
@@ -170,16 +191,15 @@ impl<T: SessionStream> Session<T> {
         Ok(modifications)
     }
 
-    pub async fn run_mta_hook(
+    fn build_mta_hook_request(
         &self,
         stage: Stage,
-        mta_hook: &MTAHook,
         message: Option<&AuthenticatedMessage<'_>>,
         queue_id: Option<QueueId>,
-    ) -> Result<Response, String> {
-        // Build request
+        server_headers: &[(String, String)],
+    ) -> Request {
         let (tls_version, tls_cipher) = self.stream.tls_version_and_cipher();
-        let request = Request {
+        Request {
             context: Context {
                 stage: stage.into(),
                 client: Client {
@@ -243,13 +263,41 @@ impl<T: SessionStream> Session<T> {
                         )
                     })
                     .collect(),
-                server_headers: vec![],
+                server_headers: server_headers.to_vec(),
                 contents: String::from_utf8_lossy(message.raw_body()).into_owned(),
                 size: message.raw_message().len(),
             }),
-        };
+        }
+    }
+}
+
+impl Hook<'_> {
+    fn id(&self) -> &str {
+        match self {
+            Hook::Http(hook) => &hook.id,
+            Hook::Wasm(hook) => &hook.id,
+        }
+    }
+
+    fn enable(&self) -> &IfBlock {
+        match self {
+            Hook::Http(hook) => &hook.enable,
+            Hook::Wasm(hook) => &hook.enable,
+        }
+    }
 
-        send_mta_hook_request(mta_hook, request).await
+    fn run_on_stage(&self, stage: Stage) -> bool {
+        match self {
+            Hook::Http(hook) => hook.run_on_stage.contains(&stage),
+            Hook::Wasm(hook) => hook.run_on_stage.contains(&stage),
+        }
+    }
+
+    fn tempfail_on_error(&self) -> bool {
+        match self {
+            Hook::Http(hook) => hook.tempfail_on_error,
+            Hook::Wasm(hook) => hook.tempfail_on_error,
+        }
     }
 }
 
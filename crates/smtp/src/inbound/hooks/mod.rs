@@ -96,6 +96,8 @@ pub enum Stage {
     Rcpt,
     #[serde(rename = "data")]
     Data,
+    #[serde(rename = "classify")]
+    Classify,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -201,6 +203,7 @@ impl From<common::config::smtp::session::Stage> for Stage {
             common::config::smtp::session::Stage::Mail => Stage::Mail,
             common::config::smtp::session::Stage::Rcpt => Stage::Rcpt,
             common::config::smtp::session::Stage::Data => Stage::Data,
+            common::config::smtp::session::Stage::Classify => Stage::Classify,
         }
     }
 }
@@ -6,7 +6,11 @@
 
 use std::time::{Duration, Instant, SystemTime};
 
-use common::{config::smtp::session::Stage, listener::SessionStream, scripts::ScriptModification};
+use common::{
+    config::smtp::session::{RewriteDirection, Stage},
+    listener::SessionStream,
+    scripts::ScriptModification,
+};
 use mail_auth::{IprevOutput, IprevResult, SpfOutput, SpfResult};
 use smtp_proto::{MailFrom, MtPriority, MAIL_BY_NOTIFY, MAIL_BY_RETURN, MAIL_REQUIRETLS};
 use trc::SmtpEvent;
@@ -51,6 +55,26 @@ impl<T: SessionStream> Session<T> {
             return self
                 .write(b"503 5.5.1 You must authenticate first.\r\n")
                 .await;
+        } else if self.server.is_read_only() {
+            trc::event!(
+                Smtp(SmtpEvent::MailFromNotAllowed),
+                Reason = "Server is in read-only mode",
+                SpanId = self.data.session_id,
+            );
+
+            return self
+                .write(b"450 4.3.2 Server is temporarily not accepting messages.\r\n")
+                .await;
+        } else if self.is_submission_suspended().await {
+            trc::event!(
+                Smtp(SmtpEvent::MailFromNotAllowed),
+                Reason = "Submission suspended for account",
+                SpanId = self.data.session_id,
+            );
+
+            return self
+                .write(b"451 4.7.1 Submission temporarily suspended for this account.\r\n")
+                .await;
         } else if self.data.iprev.is_none() && self.params.iprev.verify() {
             let time = Instant::now();
             let iprev = self
@@ -189,7 +213,7 @@ impl<T: SessionStream> Session<T> {
         }
 
         // MTAHook filtering
-        if let Err(message) = self.run_mta_hooks(Stage::Mail, None, None).await {
+        if let Err(message) = self.run_mta_hooks(Stage::Mail, None, None, &[]).await {
             self.data.mail_from = None;
             return self.write(message.message.as_bytes()).await;
         }
@@ -224,6 +248,20 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Canonical maps
+        if let Some(new_address) = self
+            .rewrite_address(
+                &self.data.mail_from.as_ref().unwrap().address_lcase,
+                RewriteDirection::Sender,
+            )
+            .await
+        {
+            let mail_from = self.data.mail_from.as_mut().unwrap();
+            mail_from.address_lcase = new_address.to_lowercase();
+            mail_from.domain = mail_from.address_lcase.domain_part().to_string();
+            mail_from.address = new_address;
+        }
+
         // Make sure that the authenticated user is allowed to send from this address
         match self.authenticated_as() {
             Some(authenticated_as) if self.params.auth_match_sender => {
@@ -232,6 +270,12 @@ impl<T: SessionStream> Session<T> {
                     && !self.authenticated_emails().iter().any(|e| {
                         e == address_lcase || (e.starts_with('@') && address_lcase.ends_with(e))
                     })
+                    && self
+                        .data
+                        .authenticated_as
+                        .as_ref()
+                        .and_then(|token| token.delegated_sender(address_lcase))
+                        .is_none()
                 {
                     trc::event!(
                         Smtp(SmtpEvent::MailFromUnauthorized),
@@ -12,15 +12,22 @@ use mail_auth::{
     AuthenticationResults, DkimResult, DmarcResult, IprevResult, SpfResult,
 };
 
+pub mod antivirus;
+pub mod attachments;
 pub mod auth;
+pub mod classify;
 pub mod data;
 pub mod ehlo;
 pub mod hooks;
 pub mod mail;
 pub mod milter;
 pub mod rcpt;
+pub mod rewrite;
+pub mod rspamd;
 pub mod session;
+pub mod spam;
 pub mod spawn;
+pub mod verify;
 pub mod vrfy;
 
 #[derive(Debug, Default)]
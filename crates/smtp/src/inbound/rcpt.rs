@@ -4,7 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::{config::smtp::session::Stage, listener::SessionStream, scripts::ScriptModification};
+use common::{
+    config::smtp::session::{RewriteDirection, Stage},
+    listener::SessionStream,
+    scripts::ScriptModification,
+};
 use directory::backend::RcptType;
 use smtp_proto::{
     RcptTo, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
@@ -12,7 +16,8 @@ use smtp_proto::{
 use trc::{SecurityEvent, SmtpEvent};
 
 use crate::{
-    core::{Session, SessionAddress},
+    core::{reputation::ReputationSignal, Session, SessionAddress},
+    inbound::verify::RcptVerification,
     queue::DomainPart,
     scripts::ScriptResult,
 };
@@ -94,6 +99,7 @@ impl<T: SessionStream> Session<T> {
 
         if rcpt_script.is_some()
             || !self.server.core.smtp.session.rcpt.rewrite.is_empty()
+            || !self.server.core.smtp.session.rewrite.is_empty()
             || self
                 .server
                 .core
@@ -139,7 +145,7 @@ impl<T: SessionStream> Session<T> {
             }
 
             // MTAHook filtering
-            if let Err(message) = self.run_mta_hooks(Stage::Rcpt, None, None).await {
+            if let Err(message) = self.run_mta_hooks(Stage::Rcpt, None, None, &[]).await {
                 self.data.rcpt_to.pop();
                 return self.write(message.message.as_bytes()).await;
             }
@@ -170,6 +176,20 @@ impl<T: SessionStream> Session<T> {
                 }
             }
 
+            // Canonical maps, applied before aliases and lists are expanded
+            if let Some(new_address) = self
+                .rewrite_address(
+                    &self.data.rcpt_to.last().unwrap().address_lcase,
+                    RewriteDirection::Recipient,
+                )
+                .await
+            {
+                let rcpt = self.data.rcpt_to.last_mut().unwrap();
+                rcpt.address_lcase = new_address.to_lowercase();
+                rcpt.domain = rcpt.address_lcase.domain_part().to_string();
+                rcpt.address = new_address;
+            }
+
             // Check for duplicates
             let rcpt = self.data.rcpt_to.last().unwrap();
             if self.data.rcpt_to.iter().filter(|r| r == &rcpt).count() > 1 {
@@ -184,10 +204,61 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Reject recipients that bounced previous messages from this sender
+        if self.server.core.smtp.queue.suppression.enable
+            && self
+                .is_suppressed(&self.data.rcpt_to.last().unwrap().address_lcase)
+                .await
+        {
+            let rcpt_to = self.data.rcpt_to.pop().unwrap().address_lcase;
+            trc::event!(
+                Smtp(SmtpEvent::RcptToSuppressed),
+                SpanId = self.data.session_id,
+                To = rcpt_to,
+            );
+
+            return self
+                .write(b"550 5.1.1 Recipient address is suppressed after previous bounces.\r\n")
+                .await;
+        }
+
+        // Verify address using an external service
+        let is_verified = match self
+            .verify_rcpt_external(&self.data.rcpt_to.last().unwrap().address_lcase)
+            .await
+        {
+            RcptVerification::Skip => false,
+            RcptVerification::Accept => true,
+            RcptVerification::Reject(message) => {
+                trc::event!(
+                    Smtp(SmtpEvent::MailboxDoesNotExist),
+                    SpanId = self.data.session_id,
+                    To = self.data.rcpt_to.last().unwrap().address_lcase.clone(),
+                );
+
+                let entities = self.reputation_entities(&[], false);
+                self.reputation_signal(&entities, ReputationSignal::Bounce)
+                    .await;
+
+                let rcpt_to = self.data.rcpt_to.pop().unwrap().address_lcase;
+                return self
+                    .rcpt_error(format!("550 5.1.2 {message}\r\n").as_bytes(), rcpt_to)
+                    .await;
+            }
+            RcptVerification::TempFail => {
+                self.data.rcpt_to.pop();
+                return self
+                    .write(b"451 4.4.3 Unable to verify address at this time.\r\n")
+                    .await;
+            }
+        };
+
         // Verify address
         let rcpt = self.data.rcpt_to.last().unwrap();
         let mut rcpt_members = None;
-        if let Some(directory) = self
+        if is_verified {
+            // Recipient was accepted by the external verification service
+        } else if let Some(directory) = self
             .server
             .eval_if::<String, _>(
                 &self.server.core.smtp.session.rcpt.directory,
@@ -206,7 +277,17 @@ impl<T: SessionStream> Session<T> {
                     {
                         Ok(RcptType::Mailbox) => {}
                         Ok(RcptType::List(members)) => {
-                            rcpt_members = Some(members);
+                            // Managed lists are expanded once the message is received
+                            if self
+                                .server
+                                .core
+                                .smtp
+                                .lists
+                                .get(&rcpt.address_lcase)
+                                .is_none()
+                            {
+                                rcpt_members = Some(members);
+                            }
                         }
                         Ok(RcptType::Invalid) => {
                             trc::event!(
@@ -215,6 +296,11 @@ impl<T: SessionStream> Session<T> {
                                 To = rcpt.address_lcase.clone(),
                             );
 
+                            // Invalid recipients count as bounces towards the sender reputation
+                            let entities = self.reputation_entities(&[], false);
+                            self.reputation_signal(&entities, ReputationSignal::Bounce)
+                                .await;
+
                             let rcpt_to = self.data.rcpt_to.pop().unwrap().address_lcase;
                             return self
                                 .rcpt_error(b"550 5.1.2 Mailbox does not exist.\r\n", rcpt_to)
@@ -0,0 +1,119 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{
+    config::smtp::session::{RewriteDirection, RewriteMap},
+    listener::SessionStream,
+};
+use trc::SmtpEvent;
+
+use crate::{core::Session, queue::DomainPart};
+
+impl<T: SessionStream> Session<T> {
+    /// Runs an envelope address through the canonical maps configured for
+    /// the given direction. Rules are applied in order, each one receiving
+    /// the output of the previous one.
+    pub async fn rewrite_address(
+        &self,
+        address: &str,
+        direction: RewriteDirection,
+    ) -> Option<String> {
+        if address.is_empty() {
+            return None;
+        }
+
+        let mut result: Option<String> = None;
+
+        for rule in &self.server.core.smtp.session.rewrite {
+            let is_match = if direction == RewriteDirection::Sender {
+                rule.direction.is_sender()
+            } else {
+                rule.direction.is_recipient()
+            };
+            if !is_match
+                || !self
+                    .server
+                    .eval_if(&rule.enable, self, self.data.session_id)
+                    .await
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let current = result.as_deref().unwrap_or(address);
+            let rewritten = match &rule.map {
+                RewriteMap::Regex {
+                    pattern,
+                    replacement,
+                } => pattern
+                    .is_match(current)
+                    .then(|| pattern.replace(current, replacement.as_str()).into_owned()),
+                RewriteMap::Lookup { store } => self.lookup_canonical(store, current).await,
+            };
+
+            if let Some(rewritten) = rewritten.filter(|rewritten| {
+                rewritten != current
+                    && (rewritten.contains('@')
+                        || (rewritten.is_empty() && direction == RewriteDirection::Sender))
+            }) {
+                if direction == RewriteDirection::Sender {
+                    trc::event!(
+                        Smtp(SmtpEvent::MailFromRewritten),
+                        SpanId = self.data.session_id,
+                        Id = rule.id.clone(),
+                        Details = current.to_string(),
+                        From = rewritten.clone(),
+                    );
+                } else {
+                    trc::event!(
+                        Smtp(SmtpEvent::RcptToRewritten),
+                        SpanId = self.data.session_id,
+                        Id = rule.id.clone(),
+                        Details = current.to_string(),
+                        To = rewritten.clone(),
+                    );
+                }
+
+                result = Some(rewritten);
+            }
+        }
+
+        result
+    }
+
+    /// Looks up the full address first and then its `@domain` part. A value
+    /// starting with `@` replaces the domain and keeps the local part.
+    async fn lookup_canonical(&self, store: &str, address: &str) -> Option<String> {
+        let store = self.server.get_lookup_store(store, self.data.session_id);
+        let domain = address.domain_part();
+        let mut keys = vec![address.to_string()];
+        if !domain.is_empty() {
+            keys.push(format!("@{domain}"));
+        }
+
+        for key in keys {
+            match store.key_get::<String>(key.into_bytes()).await {
+                Ok(Some(value)) if value.starts_with('@') => {
+                    let local_part = address
+                        .rsplit_once('@')
+                        .map_or(address, |(local_part, _)| local_part);
+                    return Some(format!("{local_part}{value}"));
+                }
+                Ok(Some(value)) => return Some(value),
+                Ok(None) => (),
+                Err(err) => {
+                    trc::error!(err
+                        .span_id(self.data.session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to lookup canonical address."));
+                    return None;
+                }
+            }
+        }
+
+        None
+    }
+}
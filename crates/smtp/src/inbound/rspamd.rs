@@ -0,0 +1,248 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, time::Instant};
+
+use ahash::AHashMap;
+use common::{config::smtp::session::Rspamd, listener::SessionStream, HttpLimitResponse};
+use serde::{Deserialize, Serialize};
+use trc::SpamEvent;
+
+use crate::core::Session;
+
+use super::FilterResponse;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RspamdResponse {
+    #[serde(default)]
+    pub is_skipped: bool,
+    #[serde(default)]
+    pub score: f64,
+    #[serde(default)]
+    pub required_score: f64,
+    #[serde(default)]
+    pub action: String,
+    #[serde(default)]
+    pub symbols: AHashMap<String, RspamdSymbol>,
+    #[serde(default)]
+    pub messages: AHashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub milter: Option<RspamdMilter>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RspamdSymbol {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub score: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RspamdMilter {
+    #[serde(default)]
+    pub add_headers: AHashMap<String, serde_json::Value>,
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Classifies the message using an Rspamd compatible service, returns the
+    /// headers to add or the response to reject the message with.
+    pub async fn run_rspamd(
+        &self,
+        message: &[u8],
+    ) -> Result<Vec<(String, String)>, FilterResponse> {
+        let rspamd = match &self.server.core.smtp.session.data.rspamd {
+            Some(rspamd)
+                if self
+                    .server
+                    .eval_if(&rspamd.enable, self, self.data.session_id)
+                    .await
+                    .unwrap_or(false) =>
+            {
+                rspamd
+            }
+            _ => return Ok(Vec::new()),
+        };
+
+        let time = Instant::now();
+        match self.send_rspamd_request(rspamd, message).await {
+            Ok(response) if response.is_skipped => Ok(Vec::new()),
+            Ok(response) => {
+                trc::event!(
+                    Spam(SpamEvent::Classify),
+                    SpanId = self.data.session_id,
+                    Id = "rspamd",
+                    Details = response.action.clone(),
+                    Result = response.score,
+                    Elapsed = time.elapsed(),
+                );
+
+                rspamd_verdict(rspamd, response)
+            }
+            Err(err) => {
+                trc::event!(
+                    Spam(SpamEvent::ClassifyError),
+                    SpanId = self.data.session_id,
+                    Id = "rspamd",
+                    Url = rspamd.url.clone(),
+                    Reason = err,
+                    Elapsed = time.elapsed(),
+                );
+
+                if rspamd.tempfail_on_error {
+                    Err(FilterResponse::temp_fail())
+                } else {
+                    Ok(Vec::new())
+                }
+            }
+        }
+    }
+
+    async fn send_rspamd_request(
+        &self,
+        rspamd: &Rspamd,
+        message: &[u8],
+    ) -> Result<RspamdResponse, String> {
+        let mut request = reqwest::Client::builder()
+            .timeout(rspamd.timeout)
+            .danger_accept_invalid_certs(rspamd.tls_allow_invalid_certs)
+            .build()
+            .map_err(|err| format!("Failed to create HTTP client: {}", err))?
+            .post(&rspamd.url)
+            .headers(rspamd.headers.clone())
+            .header("IP", self.data.remote_ip.to_string())
+            .header("MTA-Name", self.hostname.as_str())
+            .header("Queue-Id", format!("{:X}", self.data.session_id));
+        if !self.data.helo_domain.is_empty() {
+            request = request.header("Helo", self.data.helo_domain.as_str());
+        }
+        if let Some(from) = &self.data.mail_from {
+            request = request.header("From", from.address.as_str());
+        }
+        for rcpt in &self.data.rcpt_to {
+            request = request.header("Rcpt", rcpt.address.as_str());
+        }
+        if let Some(user) = self.authenticated_as() {
+            request = request.header("User", user);
+        }
+        if let Some(ptr) = self
+            .data
+            .iprev
+            .as_ref()
+            .and_then(|ip_rev| ip_rev.ptr.as_ref())
+            .and_then(|ptrs| ptrs.first())
+        {
+            request = request.header("Hostname", ptr.as_str());
+        }
+
+        let response = request
+            .body(message.to_vec())
+            .send()
+            .await
+            .map_err(|err| format!("Rspamd request failed: {err}"))?;
+
+        if response.status().is_success() {
+            serde_json::from_slice(
+                response
+                    .bytes_with_limit(rspamd.max_response_size)
+                    .await
+                    .map_err(|err| format!("Failed to read Rspamd response: {}", err))?
+                    .ok_or_else(|| "Rspamd response too large".to_string())?
+                    .as_ref(),
+            )
+            .map_err(|err| format!("Failed to parse Rspamd response: {}", err))
+        } else {
+            Err(format!(
+                "Rspamd request failed with code {}: {}",
+                response.status().as_u16(),
+                response.status().canonical_reason().unwrap_or("Unknown")
+            ))
+        }
+    }
+}
+
+/// Maps an Rspamd action and score into spam headers or an SMTP response.
+pub fn rspamd_verdict(
+    rspamd: &Rspamd,
+    response: RspamdResponse,
+) -> Result<Vec<(String, String)>, FilterResponse> {
+    let smtp_message = response
+        .messages
+        .get("smtp_message")
+        .and_then(|message| message.as_str())
+        .map(|message| message.trim());
+    let is_spam = match response.action.as_str() {
+        "reject" => {
+            return Err(FilterResponse {
+                message: format!(
+                    "550 5.7.1 {}\r\n",
+                    smtp_message.unwrap_or("Message rejected as spam.")
+                )
+                .into(),
+                disconnect: false,
+            });
+        }
+        "soft reject" => {
+            return Err(FilterResponse {
+                message: format!(
+                    "451 4.7.1 {}\r\n",
+                    smtp_message.unwrap_or("Try again later.")
+                )
+                .into(),
+                disconnect: false,
+            });
+        }
+        "greylist" => {
+            return Err(FilterResponse {
+                message: Cow::Borrowed("451 4.7.1 Greylisted, please try again later.\r\n"),
+                disconnect: false,
+            });
+        }
+        "add header" | "rewrite subject" => true,
+        _ => false,
+    };
+
+    let mut headers = Vec::with_capacity(2);
+    headers.push((
+        "X-Spam-Status".to_string(),
+        format!(
+            "{}, score={:.2}",
+            if is_spam { "Yes" } else { "No" },
+            response.score
+        ),
+    ));
+    if rspamd.add_result_header && !response.symbols.is_empty() {
+        let mut symbols = response.symbols.into_iter().collect::<Vec<_>>();
+        symbols.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        headers.push((
+            "X-Spam-Result".to_string(),
+            symbols
+                .into_iter()
+                .map(|(name, symbol)| format!("{name} ({:.2})", symbol.score))
+                .collect::<Vec<_>>()
+                .join(",\r\n\t"),
+        ));
+    }
+    if let Some(milter) = response.milter {
+        for (name, value) in milter.add_headers {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                serde_json::Value::Object(mut value) => match value.remove("value") {
+                    Some(serde_json::Value::String(value)) => value,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if !name.eq_ignore_ascii_case("X-Spam-Status")
+                && !name.eq_ignore_ascii_case("X-Spam-Result")
+            {
+                headers.push((name, value));
+            }
+        }
+    }
+
+    Ok(headers)
+}
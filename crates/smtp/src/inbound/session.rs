@@ -5,7 +5,10 @@
  */
 
 use common::{
-    config::{server::ServerProtocol, smtp::session::Mechanism},
+    config::{
+        server::ServerProtocol,
+        smtp::session::{Mechanism, Stage},
+    },
     expr::{self, functions::ResolveVariable, *},
     listener::SessionStream,
 };
@@ -108,6 +111,10 @@ impl<T: SessionStream> Session<T> {
                                     );
 
                                     self.write(b"503 5.5.1 Already authenticated.\r\n").await?;
+                                } else if let Err(message) =
+                                    self.run_mta_hooks(Stage::Auth, None, None, &[]).await
+                                {
+                                    self.write(message.message.as_bytes()).await?;
                                 } else if let Some(mut token) =
                                     SaslToken::from_mechanism(mechanism & auth)
                                 {
@@ -0,0 +1,78 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{
+    config::smtp::session::OutboundSpamAction, listener::SessionStream, scripts::ScriptModification,
+};
+use trc::SpamEvent;
+
+use crate::{core::Session, scripts::ScriptResult};
+
+impl<T: SessionStream> Session<T> {
+    /// Runs the spam filter on messages submitted by authenticated senders, returns
+    /// the action to take when the message scores at or above the outbound threshold.
+    pub async fn scan_outbound_spam(&self, raw_message: &[u8]) -> Option<OutboundSpamAction> {
+        let config = self.server.core.smtp.session.data.outbound_spam.as_ref()?;
+        let token = self.data.authenticated_as.as_ref()?;
+        let script = self
+            .server
+            .get_trusted_sieve_script(&config.script, self.data.session_id)?;
+
+        let params = self
+            .build_script_parameters("data")
+            .with_message(raw_message);
+        let score = match self
+            .run_script(config.script.clone(), script.clone(), params)
+            .await
+        {
+            ScriptResult::Accept { modifications }
+            | ScriptResult::Replace { modifications, .. } => {
+                modifications
+                    .iter()
+                    .find_map(|modification| match modification {
+                        ScriptModification::AddHeader { name, value }
+                            if name.eq_ignore_ascii_case("X-Spam-Status") =>
+                        {
+                            parse_spam_score(value)
+                        }
+                        _ => None,
+                    })?
+            }
+            // The filter would have rejected or discarded an inbound message
+            ScriptResult::Reject(_) | ScriptResult::Discard => f64::MAX,
+        };
+
+        if score >= config.threshold {
+            trc::event!(
+                Spam(SpamEvent::Outbound),
+                SpanId = self.data.session_id,
+                AccountName = token.name.clone(),
+                RemoteIp = self.data.remote_ip,
+                Value = score,
+                Limit = config.threshold,
+                Result = match config.action {
+                    OutboundSpamAction::Reject => "reject",
+                    OutboundSpamAction::Hold => "hold",
+                },
+            );
+
+            Some(config.action)
+        } else {
+            None
+        }
+    }
+}
+
+/// Obtains the score from a spam status header such as `Yes, score=7.5`.
+pub fn parse_spam_score(value: &str) -> Option<f64> {
+    value
+        .split_once("score=")?
+        .1
+        .split(|ch: char| !(ch.is_ascii_digit() || ch == '.' || ch == '-'))
+        .next()?
+        .parse()
+        .ok()
+}
@@ -7,9 +7,9 @@
 use std::time::Instant;
 
 use common::{
-    config::smtp::session::Stage,
+    config::smtp::session::{ReputationAction, Stage},
     core::BuildServer,
-    listener::{self, SessionManager, SessionStream},
+    listener::{self, drain::drain_timeout, SessionManager, SessionStream},
 };
 use tokio_rustls::server::TlsStream;
 use trc::{SecurityEvent, SmtpEvent};
@@ -118,11 +118,25 @@ impl<T: SessionStream> Session<T> {
         }
 
         // MTAHook filtering
-        if let Err(message) = self.run_mta_hooks(Stage::Connect, None, None).await {
+        if let Err(message) = self.run_mta_hooks(Stage::Connect, None, None, &[]).await {
             let _ = self.write(message.message.as_bytes()).await;
             return false;
         }
 
+        // Throttle IPs with a poor reputation
+        match self.reputation_throttle().await {
+            Some(ReputationAction::Delay(delay)) => {
+                tokio::time::sleep(delay).await;
+            }
+            Some(ReputationAction::Reject) => {
+                let _ = self
+                    .write(b"421 4.7.0 Your IP address has a poor reputation, try again later.\r\n")
+                    .await;
+                return false;
+            }
+            None => (),
+        }
+
         // Obtain hostname
         self.hostname = self
             .server
@@ -156,11 +170,12 @@ impl<T: SessionStream> Session<T> {
     pub async fn handle_conn(&mut self) -> bool {
         let mut buf = vec![0; 8192];
         let mut shutdown_rx = self.instance.shutdown_rx.clone();
+        let mut drain_deadline = None;
 
         loop {
             tokio::select! {
                 result = tokio::time::timeout(
-                    self.params.timeout,
+                    drain_timeout(self.params.timeout, drain_deadline),
                     self.read(&mut buf)) => {
                         match result {
                             Ok(Ok(bytes_read)) => {
@@ -168,7 +183,13 @@ impl<T: SessionStream> Session<T> {
                                     if Instant::now() < self.data.valid_until && bytes_read <= self.data.bytes_left  {
                                         self.data.bytes_left -= bytes_read;
                                         match self.ingest(&buf[..bytes_read]).await {
-                                            Ok(true) => (),
+                                            Ok(true) => {
+                                                // Close drained sessions once the transaction is over
+                                                if drain_deadline.is_some() && self.data.mail_from.is_none() {
+                                                    self.close_on_shutdown().await;
+                                                    break;
+                                                }
+                                            }
                                             Ok(false) => {
                                                 return true;
                                             }
@@ -233,6 +254,10 @@ impl<T: SessionStream> Session<T> {
                             Ok(Err(_)) => {
                                 break;
                             }
+                            Err(_) if drain_deadline.is_some() => {
+                                self.close_on_shutdown().await;
+                                break;
+                            }
                             Err(_) => {
                                 trc::event!(
                                     Network(trc::NetworkEvent::Timeout),
@@ -248,15 +273,15 @@ impl<T: SessionStream> Session<T> {
                             }
                         }
                 },
-                _ = shutdown_rx.changed() => {
-                    trc::event!(
-                        Network(trc::NetworkEvent::Closed),
-                        SpanId = self.data.session_id,
-                        Reason = "Server shutting down",
-                        CausedBy = trc::location!()
-                    );
-                    self.write(b"421 4.3.0 Server shutting down.\r\n").await.ok();
-                    break;
+                _ = shutdown_rx.changed(), if drain_deadline.is_none() => {
+                    // Allow in-flight transactions to complete within the grace period
+                    let grace_period = self.server.core.network.grace_period;
+                    if self.data.mail_from.is_some() && !grace_period.is_zero() {
+                        drain_deadline = Some(Instant::now() + grace_period);
+                    } else {
+                        self.close_on_shutdown().await;
+                        break;
+                    }
                 }
             };
         }
@@ -264,6 +289,18 @@ impl<T: SessionStream> Session<T> {
         false
     }
 
+    async fn close_on_shutdown(&mut self) {
+        trc::event!(
+            Network(trc::NetworkEvent::Closed),
+            SpanId = self.data.session_id,
+            Reason = "Server shutting down",
+            CausedBy = trc::location!()
+        );
+        self.write(b"421 4.3.0 Server shutting down.\r\n")
+            .await
+            .ok();
+    }
+
     pub async fn into_tls(self) -> Result<Session<TlsStream<T>>, ()> {
         Ok(Session {
             hostname: self.hostname,
@@ -0,0 +1,203 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{config::smtp::session::RcptVerify, listener::SessionStream, HttpLimitResponse};
+use store::{write::Bincode, Serialize};
+use trc::SmtpEvent;
+
+use crate::core::Session;
+
+pub enum RcptVerification {
+    Skip,
+    Accept,
+    Reject(String),
+    TempFail,
+}
+
+#[derive(serde::Serialize)]
+struct Request<'x> {
+    address: &'x str,
+    sender: &'x str,
+    #[serde(rename = "remoteIp")]
+    remote_ip: &'x str,
+    #[serde(rename = "authenticatedAs")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authenticated_as: Option<&'x str>,
+}
+
+#[derive(serde::Deserialize)]
+struct Response {
+    action: Action,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Action {
+    Accept,
+    Reject,
+    Tempfail,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+enum CachedResult {
+    Accept,
+    Reject(String),
+}
+
+impl<T: SessionStream> Session<T> {
+    pub async fn verify_rcpt_external(&self, address: &str) -> RcptVerification {
+        let config = if let Some(config) = &self.server.core.smtp.session.rcpt.verify {
+            config
+        } else {
+            return RcptVerification::Skip;
+        };
+        if !self
+            .server
+            .eval_if(&config.enable, self, self.data.session_id)
+            .await
+            .unwrap_or(false)
+        {
+            return RcptVerification::Skip;
+        }
+
+        // Check the cache first
+        let key = format!("rv:{address}").into_bytes();
+        match self
+            .server
+            .lookup_store()
+            .key_get::<Bincode<CachedResult>>(key.clone())
+            .await
+        {
+            Ok(Some(cached)) => {
+                return match cached.inner {
+                    CachedResult::Accept => RcptVerification::Accept,
+                    CachedResult::Reject(message) => RcptVerification::Reject(message),
+                };
+            }
+            Ok(None) => {}
+            Err(err) => {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to obtain cached recipient verification."));
+            }
+        }
+
+        let request = Request {
+            address,
+            sender: self
+                .data
+                .mail_from
+                .as_ref()
+                .map(|from| from.address.as_str())
+                .unwrap_or_default(),
+            remote_ip: &self.data.remote_ip_str,
+            authenticated_as: self.authenticated_as(),
+        };
+
+        let (result, expiry) = match send_verify_request(config, &request).await {
+            Ok(response) => match response.action {
+                Action::Accept => (CachedResult::Accept, config.cache_valid),
+                Action::Reject => (
+                    CachedResult::Reject(
+                        response
+                            .message
+                            .map(|message| {
+                                message
+                                    .chars()
+                                    .filter(|ch| !ch.is_ascii_control())
+                                    .collect::<String>()
+                            })
+                            .filter(|message| !message.trim().is_empty())
+                            .unwrap_or_else(|| "Mailbox does not exist.".to_string()),
+                    ),
+                    config.cache_invalid,
+                ),
+                Action::Tempfail => return RcptVerification::TempFail,
+            },
+            Err(err) => {
+                trc::event!(
+                    Smtp(SmtpEvent::RcptVerifyError),
+                    SpanId = self.data.session_id,
+                    To = address.to_string(),
+                    Reason = err,
+                );
+
+                return if config.tempfail_on_error {
+                    RcptVerification::TempFail
+                } else {
+                    RcptVerification::Accept
+                };
+            }
+        };
+
+        let verification = match &result {
+            CachedResult::Accept => RcptVerification::Accept,
+            CachedResult::Reject(message) => RcptVerification::Reject(message.clone()),
+        };
+
+        // Cache the result
+        if !expiry.is_zero() {
+            if let Err(err) = self
+                .server
+                .lookup_store()
+                .key_set(
+                    key,
+                    Bincode::new(result).serialize(),
+                    expiry.as_secs().into(),
+                )
+                .await
+            {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to cache recipient verification."));
+            }
+        }
+
+        verification
+    }
+}
+
+async fn send_verify_request(
+    config: &RcptVerify,
+    request: &Request<'_>,
+) -> Result<Response, String> {
+    let response = reqwest::Client::builder()
+        .timeout(config.timeout)
+        .danger_accept_invalid_certs(config.tls_allow_invalid_certs)
+        .build()
+        .map_err(|err| format!("Failed to create HTTP client: {}", err))?
+        .post(&config.url)
+        .headers(config.headers.clone())
+        .body(
+            serde_json::to_string(request)
+                .map_err(|err| format!("Failed to serialize verification request: {}", err))?,
+        )
+        .send()
+        .await
+        .map_err(|err| format!("Verification request failed: {err}"))?;
+
+    if response.status().is_success() {
+        serde_json::from_slice(
+            response
+                .bytes_with_limit(config.max_response_size)
+                .await
+                .map_err(|err| format!("Failed to parse verification response: {}", err))?
+                .ok_or_else(|| "Verification response too large".to_string())?
+                .as_ref(),
+        )
+        .map_err(|err| format!("Failed to parse verification response: {}", err))
+    } else {
+        Err(format!(
+            "Verification request failed with code {}: {}",
+            response.status().as_u16(),
+            response.status().canonical_reason().unwrap_or("Unknown")
+        ))
+    }
+}
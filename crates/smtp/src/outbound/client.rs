@@ -20,6 +20,7 @@ use smtp_proto::{
     EhloResponse, Response, AUTH_CRAM_MD5, AUTH_DIGEST_MD5, AUTH_LOGIN, AUTH_OAUTHBEARER,
     AUTH_PLAIN, AUTH_XOAUTH2, EXT_START_TLS,
 };
+use store::CompressionAlgo;
 use tokio::{
     io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::{TcpSocket, TcpStream},
@@ -27,7 +28,10 @@ use tokio::{
 use tokio_rustls::{client::TlsStream, TlsConnector};
 use trc::DeliveryEvent;
 
-use crate::queue::{Error, Message, Status};
+use crate::{
+    inbound::classify::strip_classification_headers,
+    queue::{Error, Message, Status},
+};
 
 use super::session::SessionParams;
 
@@ -176,43 +180,82 @@ impl<T: AsyncRead + AsyncWrite + Unpin> SmtpClient<T> {
         bdat_cmd: &Option<String>,
         params: &SessionParams<'_>,
     ) -> Result<(), Status<(), Error>> {
+        // Large messages are streamed in chunks rather than loaded into memory,
+        // ranged reads are not possible on compressed blobs
+        if bdat_cmd.is_some()
+            && params.max_buffer > 0
+            && message.size > params.max_buffer
+            && matches!(
+                params.server.blob_store().compression,
+                CompressionAlgo::None
+            )
+        {
+            return self.send_message_chunked(message, params).await;
+        }
+
         match params
             .server
             .blob_store()
             .get_blob(message.blob_hash.as_slice(), 0..usize::MAX)
             .await
         {
-            Ok(Some(raw_message)) => tokio::time::timeout(params.timeout_data, async {
-                if let Some(bdat_cmd) = bdat_cmd {
-                    trc::event!(
-                        Delivery(DeliveryEvent::RawOutput),
-                        SpanId = self.session_id,
-                        Contents = bdat_cmd.clone(),
-                        Size = bdat_cmd.len()
-                    );
-
-                    self.write_chunks(&[bdat_cmd.as_bytes(), &raw_message])
-                        .await
-                } else {
-                    trc::event!(
-                        Delivery(DeliveryEvent::RawOutput),
-                        SpanId = self.session_id,
-                        Contents = "DATA\r\n",
-                        Size = 6
-                    );
-
-                    self.write_chunks(&[b"DATA\r\n"]).await?;
-                    self.read().await?.assert_code(354)?;
-                    self.write_message(&raw_message)
-                        .await
-                        .map_err(mail_send::Error::from)
-                }
-            })
-            .await
-            .map_err(|_| Status::timeout(params.hostname, "sending message"))?
-            .map_err(|err| {
-                Status::from_smtp_error(params.hostname, bdat_cmd.as_deref().unwrap_or("DATA"), err)
-            }),
+            Ok(Some(raw_message)) => {
+                // Remove classification headers before the message leaves the server
+                let (raw_message, bdat_cmd) = match params
+                    .server
+                    .core
+                    .smtp
+                    .session
+                    .data
+                    .strip_classification
+                    .then(|| strip_classification_headers(&raw_message))
+                    .flatten()
+                {
+                    Some(stripped) => {
+                        let bdat_cmd = bdat_cmd
+                            .as_ref()
+                            .map(|_| format!("BDAT {} LAST\r\n", stripped.len()));
+                        (stripped, bdat_cmd)
+                    }
+                    None => (raw_message, bdat_cmd.clone()),
+                };
+
+                tokio::time::timeout(params.timeout_data, async {
+                    if let Some(bdat_cmd) = &bdat_cmd {
+                        trc::event!(
+                            Delivery(DeliveryEvent::RawOutput),
+                            SpanId = self.session_id,
+                            Contents = bdat_cmd.clone(),
+                            Size = bdat_cmd.len()
+                        );
+
+                        self.write_chunks(&[bdat_cmd.as_bytes(), &raw_message])
+                            .await
+                    } else {
+                        trc::event!(
+                            Delivery(DeliveryEvent::RawOutput),
+                            SpanId = self.session_id,
+                            Contents = "DATA\r\n",
+                            Size = 6
+                        );
+
+                        self.write_chunks(&[b"DATA\r\n"]).await?;
+                        self.read().await?.assert_code(354)?;
+                        self.write_message(&raw_message)
+                            .await
+                            .map_err(mail_send::Error::from)
+                    }
+                })
+                .await
+                .map_err(|_| Status::timeout(params.hostname, "sending message"))?
+                .map_err(|err| {
+                    Status::from_smtp_error(
+                        params.hostname,
+                        bdat_cmd.as_deref().unwrap_or("DATA"),
+                        err,
+                    )
+                })
+            }
             Ok(None) => {
                 trc::event!(
                     Queue(trc::QueueEvent::BlobNotFound),
@@ -237,6 +280,91 @@ impl<T: AsyncRead + AsyncWrite + Unpin> SmtpClient<T> {
         }
     }
 
+    /// Sends the message as a series of BDAT chunks of at most `max_buffer`
+    /// bytes, each read from the blob store as it is sent.
+    async fn send_message_chunked(
+        &mut self,
+        message: &Message,
+        params: &SessionParams<'_>,
+    ) -> Result<(), Status<(), Error>> {
+        let blob_store = params.server.blob_store();
+        let mut offset = 0;
+
+        loop {
+            let mut chunk = match blob_store
+                .get_blob(
+                    message.blob_hash.as_slice(),
+                    offset..offset + params.max_buffer,
+                )
+                .await
+            {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => {
+                    trc::event!(
+                        Queue(trc::QueueEvent::BlobNotFound),
+                        SpanId = message.span_id,
+                        BlobId = message.blob_hash.to_hex(),
+                        CausedBy = trc::location!()
+                    );
+                    return Err(Status::TemporaryFailure(Error::Io(
+                        "Queue system error.".to_string(),
+                    )));
+                }
+                Err(err) => {
+                    trc::error!(err
+                        .span_id(message.span_id)
+                        .details("Failed to fetch blobId")
+                        .caused_by(trc::location!()));
+
+                    return Err(Status::TemporaryFailure(Error::Io(
+                        "Queue system error.".to_string(),
+                    )));
+                }
+            };
+            let is_first = offset == 0;
+            let is_last = chunk.len() < params.max_buffer || offset + chunk.len() >= message.size;
+            offset += chunk.len();
+
+            // Remove classification headers, as long as the header block fits in the first chunk
+            if is_first
+                && params.server.core.smtp.session.data.strip_classification
+                && chunk.windows(4).any(|window| window == b"\r\n\r\n")
+            {
+                if let Some(stripped) = strip_classification_headers(&chunk) {
+                    chunk = stripped;
+                }
+            }
+
+            let bdat_cmd = if is_last {
+                format!("BDAT {} LAST\r\n", chunk.len())
+            } else {
+                format!("BDAT {}\r\n", chunk.len())
+            };
+            trc::event!(
+                Delivery(DeliveryEvent::RawOutput),
+                SpanId = self.session_id,
+                Contents = bdat_cmd.clone(),
+                Size = bdat_cmd.len()
+            );
+
+            // The response to the last chunk is read by the caller
+            tokio::time::timeout(params.timeout_data, async {
+                self.write_chunks(&[bdat_cmd.as_bytes(), &chunk]).await?;
+                if !is_last {
+                    self.read().await?.assert_code(250)?;
+                }
+                Ok::<_, mail_send::Error>(())
+            })
+            .await
+            .map_err(|_| Status::timeout(params.hostname, "sending message"))?
+            .map_err(|err| Status::from_smtp_error(params.hostname, bdat_cmd.trim_end(), err))?;
+
+            if is_last {
+                return Ok(());
+            }
+        }
+    }
+
     pub async fn say_helo(
         &mut self,
         params: &SessionParams<'_>,
@@ -4,11 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use crate::inbound::attachments::{attachment_policy, check_attachments};
 use crate::outbound::client::{from_error_status, from_mail_send_error, SmtpClient};
 use crate::outbound::dane::dnssec::TlsaLookup;
 use crate::outbound::lookup::DnsLookup;
 use crate::outbound::mta_sts::lookup::MtaStsLookup;
 use crate::outbound::mta_sts::verify::VerifyPolicy;
+use crate::outbound::pool::IpPoolManager;
 use crate::outbound::{client::StartTlsResult, dane::verify::TlsaVerify};
 use crate::queue::dsn::SendDsn;
 use crate::queue::spool::SmtpSpool;
@@ -16,7 +18,7 @@ use crate::queue::throttle::IsAllowed;
 use crate::reporting::SmtpReporting;
 use common::config::{
     server::ServerProtocol,
-    smtp::{queue::RequireOptional, report::AggregateFrequency},
+    smtp::{queue::RequireOptional, report::AggregateFrequency, session::AttachmentStage},
 };
 use common::ipc::{OnHold, PolicyType, QueueEvent, TlsEvent};
 use common::Server;
@@ -24,13 +26,14 @@ use mail_auth::{
     mta_sts::TlsRpt,
     report::tlsrpt::{FailureDetails, ResultType},
 };
-use smtp_proto::MAIL_REQUIRETLS;
+use mail_parser::MessageParser;
+use smtp_proto::{Response, MAIL_REQUIRETLS};
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
     time::{Duration, Instant},
 };
 use store::write::{now, BatchBuilder, QueueClass, ValueClass};
-use trc::{DaneEvent, DeliveryEvent, MtaStsEvent, ServerEvent, TlsRptEvent};
+use trc::{DaneEvent, DeliveryEvent, MtaStsEvent, ServerEvent, SmtpEvent, TlsRptEvent};
 
 use crate::{
     queue::{ErrorDetails, Message},
@@ -38,17 +41,31 @@ use crate::{
 };
 
 use super::{lookup::ToNextHop, mta_sts, session::SessionParams, NextHop, TlsStrategy};
-use crate::queue::{throttle, DeliveryAttempt, Domain, Error, QueueEnvelope, Status};
+use crate::queue::{
+    history::{DeliveryRecord, TlsMode},
+    route::QueueRoute,
+    throttle, DeliveryAttempt, Domain, Error, HostResponse, QueueEnvelope, Status,
+};
 
 impl DeliveryAttempt {
     pub async fn try_deliver(mut self, server: Server) {
         tokio::spawn(async move {
             // Lock message
             if let Some(event) = server.try_lock_event(self.event).await {
+                server
+                    .inner
+                    .data
+                    .smtp_in_flight
+                    .lock()
+                    .insert(event.queue_id, event.clone());
                 self.event = event;
 
                 // Fetch message
-                if let Some(mut message) = server.read_message(self.event.queue_id).await {
+                if let Some(mut message) = server
+                    .read_message(self.event.queue_id)
+                    .await
+                    .filter(|message| !message.is_held())
+                {
                     // Generate span id
                     message.span_id = server.inner.data.span_id_gen.generate().unwrap_or_else(now);
                     let span_id = message.span_id;
@@ -82,7 +99,9 @@ impl DeliveryAttempt {
 
                     // Attempt delivery
                     let start_time = Instant::now();
-                    self.deliver_task(server, message).await;
+                    let queue_id = message.queue_id;
+                    self.deliver_task(server.clone(), message).await;
+                    server.inner.data.smtp_in_flight.lock().remove(&queue_id);
 
                     trc::event!(
                         Delivery(DeliveryEvent::AttemptEnd),
@@ -90,7 +109,7 @@ impl DeliveryAttempt {
                         Elapsed = start_time.elapsed(),
                     );
                 } else {
-                    // Message no longer exists, delete queue event.
+                    // Message no longer exists or is held, delete queue event.
                     let mut batch = BatchBuilder::new();
                     batch.clear(ValueClass::Queue(QueueClass::MessageEvent(
                         store::write::QueueEvent {
@@ -104,6 +123,12 @@ impl DeliveryAttempt {
                             .details("Failed to delete queue event.")
                             .caused_by(trc::location!()));
                     }
+                    server
+                        .inner
+                        .data
+                        .smtp_in_flight
+                        .lock()
+                        .remove(&self.event.queue_id);
                 }
             }
         });
@@ -232,6 +257,19 @@ impl DeliveryAttempt {
         let mut on_hold = Vec::new();
         let no_ip = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
         let mut recipients = std::mem::take(&mut message.recipients);
+        let mut raw_message: Option<Option<Vec<u8>>> = None;
+
+        // Messages rerouted through the management API use their own next hop
+        let reroute = server
+            .queue_route_get(message.queue_id)
+            .await
+            .unwrap_or_else(|err| {
+                trc::error!(err
+                    .span_id(span_id)
+                    .details("Failed to obtain message route"));
+                None
+            });
+
         'next_domain: for domain_idx in 0..message.domains.len() {
             // Only process domains due for delivery
             let domain = &message.domains[domain_idx];
@@ -251,6 +289,59 @@ impl DeliveryAttempt {
             // Build envelope
             let mut envelope = QueueEnvelope::new(&message, domain_idx);
 
+            // Enforce attachment policies, violations fail the domain and generate an NDN
+            if let Some(policy) = attachment_policy(
+                &server,
+                AttachmentStage::Delivery,
+                &envelope,
+                message.span_id,
+            )
+            .await
+            {
+                if raw_message.is_none() {
+                    raw_message = server
+                        .blob_store()
+                        .get_blob(message.blob_hash.as_slice(), 0..usize::MAX)
+                        .await
+                        .unwrap_or_default()
+                        .into();
+                }
+                if let Some(violation) = raw_message
+                    .as_ref()
+                    .and_then(|raw_message| raw_message.as_deref())
+                    .and_then(|raw_message| MessageParser::new().parse(raw_message))
+                    .and_then(|parsed| check_attachments(policy, &parsed).into_iter().next())
+                {
+                    trc::event!(
+                        Smtp(SmtpEvent::AttachmentBlocked),
+                        SpanId = message.span_id,
+                        Id = policy.id.clone(),
+                        Domain = domain.domain.clone(),
+                        Details = violation.name.clone(),
+                        Reason = violation.reason.clone(),
+                    );
+
+                    message.domains[domain_idx].set_status(
+                        Status::PermanentFailure(Error::UnexpectedResponse(HostResponse {
+                            hostname: ErrorDetails {
+                                entity: "localhost".to_string(),
+                                details: "DATA".to_string(),
+                            },
+                            response: Response {
+                                code: 550,
+                                esc: [5, 7, 1],
+                                message: format!(
+                                    "Attachment {:?} {}",
+                                    violation.name, violation.reason
+                                ),
+                            },
+                        })),
+                        &[],
+                    );
+                    continue 'next_domain;
+                }
+            }
+
             // Throttle recipient domain
             let mut in_flight = Vec::new();
             for throttle in &queue_config.throttle.rcpt {
@@ -270,12 +361,40 @@ impl DeliveryAttempt {
                 }
             }
 
-            // Obtain next hop
-            let (mut remote_hosts, is_smtp) = match server
-                .eval_if::<String, _>(&queue_config.next_hop, &envelope, message.span_id)
-                .await
-                .and_then(|name| server.get_relay_host(&name, message.span_id))
-            {
+            // Obtain next hop, rerouted messages and smarthost routes take precedence
+            let next_hop = if let Some(next_hop) = &reroute {
+                trc::event!(
+                    Delivery(DeliveryEvent::RouteSelected),
+                    SpanId = message.span_id,
+                    Domain = domain.domain.clone(),
+                    Hostname = next_hop.clone(),
+                );
+
+                server.get_relay_host(next_hop, message.span_id)
+            } else if let Some(route) = queue_config.routes.iter().find(|route| {
+                route.matches(
+                    &message.return_path_lcase,
+                    &message.return_path_domain,
+                    &domain.domain,
+                    message.size,
+                )
+            }) {
+                trc::event!(
+                    Delivery(DeliveryEvent::RouteSelected),
+                    SpanId = message.span_id,
+                    Domain = domain.domain.clone(),
+                    Id = route.id.clone(),
+                    Hostname = route.relay.clone(),
+                );
+
+                server.get_relay_host(&route.relay, message.span_id)
+            } else {
+                server
+                    .eval_if::<String, _>(&queue_config.next_hop, &envelope, message.span_id)
+                    .await
+                    .and_then(|name| server.get_relay_host(&name, message.span_id))
+            };
+            let (mut remote_hosts, is_smtp) = match next_hop {
                 Some(next_hop) if next_hop.protocol == ServerProtocol::Http => {
                     // Deliver message locally
                     let delivery_result = message
@@ -700,10 +819,14 @@ impl DeliveryAttempt {
                     .eval_if(&queue_config.tls.dane, &envelope, message.span_id)
                     .await
                     .unwrap_or(RequireOptional::Optional);
-                tls_strategy.tls = server
-                    .eval_if(&queue_config.tls.start, &envelope, message.span_id)
-                    .await
-                    .unwrap_or(RequireOptional::Optional);
+                tls_strategy.tls = if let Some(tls_start) = remote_host.tls_start() {
+                    tls_start
+                } else {
+                    server
+                        .eval_if(&queue_config.tls.start, &envelope, message.span_id)
+                        .await
+                        .unwrap_or(RequireOptional::Optional)
+                };
 
                 // Lookup DANE policy
                 let dane_policy = if tls_strategy.try_dane() && is_smtp {
@@ -866,12 +989,31 @@ impl DeliveryAttempt {
 
                 // Try each IP address
                 'next_ip: for remote_ip in resolve_result.remote_ips {
-                    // Set source IP, if any
-                    let source_ip = if remote_ip.is_ipv4() {
+                    // Set source IP, if any, addresses from IP pools take precedence
+                    // unless the relay host binds to its own addresses
+                    let pool_ip = if remote_host.source_ips().is_empty() {
+                        match server
+                            .ip_pool_select(
+                                &message.return_path_domain,
+                                remote_ip.is_ipv4(),
+                                message.span_id,
+                            )
+                            .await
+                        {
+                            Ok(pool_ip) => pool_ip,
+                            Err(status) => {
+                                last_status = status;
+                                continue 'next_ip;
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    let source_ip = pool_ip.or(if remote_ip.is_ipv4() {
                         resolve_result.source_ipv4
                     } else {
                         resolve_result.source_ipv6
-                    };
+                    });
                     envelope.local_ip = source_ip.unwrap_or(no_ip);
 
                     // Throttle remote host
@@ -893,6 +1035,11 @@ impl DeliveryAttempt {
                         }
                     }
 
+                    // Keep track of the attempt in the delivery history
+                    let attempt_time = Instant::now();
+                    let mut attempt =
+                        DeliveryRecord::new(&domain.domain, envelope.mx, remote_ip, source_ip);
+
                     // Connect
                     let time = Instant::now();
                     let conn_timeout = server
@@ -943,6 +1090,9 @@ impl DeliveryAttempt {
                             );
 
                             last_status = Status::from_smtp_error(envelope.mx, "", err);
+                            message
+                                .record_attempt(&server, attempt.finish(&last_status, attempt_time))
+                                .await;
                             continue 'next_ip;
                         }
                     };
@@ -982,6 +1132,10 @@ impl DeliveryAttempt {
                             .eval_if(&queue_config.timeout.data, &envelope, message.span_id)
                             .await
                             .unwrap_or_else(|| Duration::from_secs(5 * 60)),
+                        max_buffer: server
+                            .eval_if(&queue_config.max_buffer, &envelope, message.span_id)
+                            .await
+                            .unwrap_or(16777216),
                     };
 
                     // Prepare TLS connector
@@ -1012,6 +1166,9 @@ impl DeliveryAttempt {
                             );
 
                             last_status = status;
+                            message
+                                .record_attempt(&server, attempt.finish(&last_status, attempt_time))
+                                .await;
                             continue 'next_host;
                         }
 
@@ -1041,6 +1198,12 @@ impl DeliveryAttempt {
                                 );
 
                                 last_status = status;
+                                message
+                                    .record_attempt(
+                                        &server,
+                                        attempt.finish(&last_status, attempt_time),
+                                    )
+                                    .await;
                                 continue 'next_host;
                             }
                         };
@@ -1078,6 +1241,7 @@ impl DeliveryAttempt {
                                         ),
                                         Elapsed = time.elapsed(),
                                     );
+                                    attempt.tls = TlsMode::StartTls;
 
                                     // Verify DANE
                                     if let Some(dane_policy) = &dane_policy {
@@ -1108,6 +1272,12 @@ impl DeliveryAttempt {
                                             }
 
                                             last_status = status;
+                                            message
+                                                .record_attempt(
+                                                    &server,
+                                                    attempt.finish(&last_status, attempt_time),
+                                                )
+                                                .await;
                                             continue 'next_host;
                                         }
                                     }
@@ -1181,6 +1351,12 @@ impl DeliveryAttempt {
                                     if is_strict_tls {
                                         last_status =
                                             Status::from_starttls_error(envelope.mx, response);
+                                        message
+                                            .record_attempt(
+                                                &server,
+                                                attempt.finish(&last_status, attempt_time),
+                                            )
+                                            .await;
                                         continue 'next_host;
                                     } else {
                                         // TLS is not required, proceed in plain-text
@@ -1231,6 +1407,12 @@ impl DeliveryAttempt {
                                     } else {
                                         Status::from_tls_error(envelope.mx, error).into_temporary()
                                     };
+                                    message
+                                        .record_attempt(
+                                            &server,
+                                            attempt.finish(&last_status, attempt_time),
+                                        )
+                                        .await;
                                     continue 'next_host;
                                 }
                             }
@@ -1259,7 +1441,10 @@ impl DeliveryAttempt {
                             .unwrap_or_else(|| Duration::from_secs(3 * 60));
                         let mut smtp_client =
                             match smtp_client.into_tls(tls_connector, envelope.mx).await {
-                                Ok(smtp_client) => smtp_client,
+                                Ok(smtp_client) => {
+                                    attempt.tls = TlsMode::Implicit;
+                                    smtp_client
+                                }
                                 Err(error) => {
                                     trc::event!(
                                         Delivery(DeliveryEvent::ImplicitTlsError),
@@ -1270,6 +1455,12 @@ impl DeliveryAttempt {
                                     );
 
                                     last_status = Status::from_tls_error(envelope.mx, error);
+                                    message
+                                        .record_attempt(
+                                            &server,
+                                            attempt.finish(&last_status, attempt_time),
+                                        )
+                                        .await;
                                     continue 'next_host;
                                 }
                             };
@@ -1289,6 +1480,9 @@ impl DeliveryAttempt {
                             );
 
                             last_status = status;
+                            message
+                                .record_attempt(&server, attempt.finish(&last_status, attempt_time))
+                                .await;
                             continue 'next_host;
                         }
 
@@ -1302,6 +1496,33 @@ impl DeliveryAttempt {
                             .await
                     };
 
+                    // Record the outcome of the attempt
+                    message
+                        .record_attempt(
+                            &server,
+                            attempt
+                                .finish(&delivery_result, attempt_time)
+                                .with_response(
+                                    recipients.iter().filter(|r| r.domain_idx == domain_idx),
+                                ),
+                        )
+                        .await;
+
+                    // Track the outcome for the pool address
+                    if let Some(pool_ip) = pool_ip {
+                        if let Err(err) = server
+                            .ip_pool_record(
+                                pool_ip,
+                                matches!(delivery_result, Status::Completed(_)),
+                            )
+                            .await
+                        {
+                            trc::error!(err
+                                .span_id(message.span_id)
+                                .details("Failed to record IP pool delivery outcome"));
+                        }
+                    }
+
                     // Update status for the current domain and continue with the next one
                     let schedule = server
                         .eval_if::<Vec<Duration>, _>(
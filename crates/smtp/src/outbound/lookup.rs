@@ -179,6 +179,15 @@ impl DnsLookup for Server {
                 std::cmp::Ordering::Less => (),
             }
 
+            // Relay hosts with their own source addresses override the defaults
+            let source_ips = remote_host.source_ips();
+            if !source_ips.is_empty() {
+                let (ipv4, ipv6): (Vec<&IpAddr>, Vec<&IpAddr>) =
+                    source_ips.iter().partition(|ip| ip.is_ipv4());
+                result.source_ipv4 = ipv4.choose(&mut rand::thread_rng()).map(|ip| **ip);
+                result.source_ipv6 = ipv6.choose(&mut rand::thread_rng()).map(|ip| **ip);
+            }
+
             Ok(result)
         } else {
             Err(Status::TemporaryFailure(Error::DnsError(format!(
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::borrow::Cow;
+use std::{borrow::Cow, net::IpAddr};
 
 use common::{
     config::{
@@ -24,6 +24,7 @@ pub mod delivery;
 pub mod local;
 pub mod lookup;
 pub mod mta_sts;
+pub mod pool;
 pub mod session;
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -287,6 +288,22 @@ impl NextHop<'_> {
         }
     }
 
+    #[inline(always)]
+    fn tls_start(&self) -> Option<RequireOptional> {
+        match self {
+            NextHop::MX(_) => None,
+            NextHop::Relay(host) => host.tls_start,
+        }
+    }
+
+    #[inline(always)]
+    fn source_ips(&self) -> &[IpAddr] {
+        match self {
+            NextHop::MX(_) => &[],
+            NextHop::Relay(host) => &host.source_ips,
+        }
+    }
+
     #[inline(always)]
     fn is_smtp(&self) -> bool {
         match self {
@@ -0,0 +1,229 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, net::IpAddr, time::Duration};
+
+use common::{
+    config::smtp::queue::{IpPool, IpPoolStrategy},
+    Server,
+};
+use store::write::now;
+use trc::{AddContext, DeliveryEvent};
+use utils::config::Rate;
+
+use crate::queue::{Error, Status};
+
+const DAY: u64 = 86400;
+
+pub trait IpPoolManager: Sync + Send {
+    fn ip_pool_select(
+        &self,
+        sender_domain: &str,
+        ipv4: bool,
+        session_id: u64,
+    ) -> impl Future<Output = Result<Option<IpAddr>, Status<(), Error>>> + Send;
+
+    fn ip_pool_record(
+        &self,
+        source_ip: IpAddr,
+        success: bool,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn ip_pool_stats(
+        &self,
+        source_ip: IpAddr,
+    ) -> impl Future<Output = trc::Result<(i64, i64)>> + Send;
+}
+
+impl IpPoolManager for Server {
+    async fn ip_pool_select(
+        &self,
+        sender_domain: &str,
+        ipv4: bool,
+        session_id: u64,
+    ) -> Result<Option<IpAddr>, Status<(), Error>> {
+        let Some(pool) = self
+            .core
+            .smtp
+            .queue
+            .ip_pools
+            .iter()
+            .find(|pool| pool.matches(sender_domain))
+        else {
+            return Ok(None);
+        };
+        let addresses = pool
+            .addresses
+            .iter()
+            .filter(|ip| ip.is_ipv4() == ipv4)
+            .copied()
+            .collect::<Vec<_>>();
+        if addresses.is_empty() {
+            return Ok(None);
+        }
+
+        let start = match pool.strategy {
+            IpPoolStrategy::RoundRobin => {
+                let mut next = self.inner.data.smtp_ip_pool_next.lock();
+                let next = next.entry(pool.id.clone()).or_default();
+                *next = next.wrapping_add(1);
+                next.wrapping_sub(1)
+            }
+            IpPoolStrategy::SenderDomain => {
+                let hash = blake3::hash(sender_domain.as_bytes());
+                u64::from_be_bytes(hash.as_bytes()[..8].try_into().unwrap()) as usize
+            }
+        };
+
+        for offset in 0..addresses.len() {
+            let source_ip = addresses[start.wrapping_add(offset) % addresses.len()];
+            match is_ip_available(self, pool, source_ip).await {
+                Ok(Some(reason)) => {
+                    trc::event!(
+                        Delivery(DeliveryEvent::IpPoolSkipped),
+                        SpanId = session_id,
+                        Id = pool.id.clone(),
+                        LocalIp = source_ip,
+                        Reason = reason,
+                    );
+                }
+                Ok(None) => {
+                    trc::event!(
+                        Delivery(DeliveryEvent::IpPoolSelected),
+                        SpanId = session_id,
+                        Id = pool.id.clone(),
+                        LocalIp = source_ip,
+                    );
+
+                    return Ok(Some(source_ip));
+                }
+                Err(err) => {
+                    trc::error!(err
+                        .span_id(session_id)
+                        .details("Failed to check IP pool limits"));
+
+                    return Ok(Some(source_ip));
+                }
+            }
+        }
+
+        trc::event!(
+            Delivery(DeliveryEvent::IpPoolExhausted),
+            SpanId = session_id,
+            Id = pool.id.clone(),
+        );
+
+        Err(Status::TemporaryFailure(Error::RateLimited))
+    }
+
+    async fn ip_pool_record(&self, source_ip: IpAddr, success: bool) -> trc::Result<()> {
+        if self
+            .core
+            .smtp
+            .queue
+            .ip_pools
+            .iter()
+            .any(|pool| pool.addresses.contains(&source_ip))
+        {
+            self.counter_incr_coalesced(
+                outcome_key(source_ip, now() / DAY, success),
+                1,
+                (2 * DAY).into(),
+            )
+            .await
+            .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
+    async fn ip_pool_stats(&self, source_ip: IpAddr) -> trc::Result<(i64, i64)> {
+        let day = now() / DAY;
+        Ok((
+            self.counter_get_coalesced(outcome_key(source_ip, day, true))
+                .await
+                .caused_by(trc::location!())?,
+            self.counter_get_coalesced(outcome_key(source_ip, day, false))
+                .await
+                .caused_by(trc::location!())?,
+        ))
+    }
+}
+
+/// Returns the reason an address cannot be used right now, if any. Checking
+/// the hourly limit counts the delivery against it.
+async fn is_ip_available(
+    server: &Server,
+    pool: &IpPool,
+    source_ip: IpAddr,
+) -> trc::Result<Option<String>> {
+    // Skip addresses with a high failure rate today
+    if let Some(max_failure_rate) = pool.max_failure_rate {
+        let (delivered, failed) = server.ip_pool_stats(source_ip).await?;
+        let total = delivered + failed;
+        if total > 0 && total as u64 >= pool.min_samples {
+            let failure_rate = failed as f64 / total as f64;
+            if failure_rate > max_failure_rate {
+                return Ok(Some(format!(
+                    "failure rate {failure_rate:.2} exceeds {max_failure_rate:.2}"
+                )));
+            }
+        }
+    }
+
+    // Obtain the hourly limit, ramping up during warmup
+    let lookup = server.lookup_store();
+    let days = if !pool.warmup.is_empty() {
+        let first_use = lookup
+            .counter_get(warmup_key(source_ip))
+            .await
+            .caused_by(trc::location!())? as u64;
+        if first_use == 0 {
+            lookup
+                .counter_incr(warmup_key(source_ip), now() as i64, None, false)
+                .await
+                .caused_by(trc::location!())?;
+            0
+        } else {
+            now().saturating_sub(first_use) / DAY
+        }
+    } else {
+        u64::MAX
+    };
+
+    if let Some(limit) = pool.hourly_limit(days) {
+        let rate = Rate {
+            requests: limit,
+            period: Duration::from_secs(3600),
+        };
+        if lookup
+            .is_rate_allowed(hourly_key(source_ip).as_bytes(), &rate, false)
+            .await
+            .caused_by(trc::location!())?
+            .is_some()
+        {
+            return Ok(Some(format!("hourly limit of {limit} messages reached")));
+        }
+    }
+
+    Ok(None)
+}
+
+fn warmup_key(source_ip: IpAddr) -> Vec<u8> {
+    format!("ip-pool-warmup:{source_ip}").into_bytes()
+}
+
+fn hourly_key(source_ip: IpAddr) -> String {
+    format!("ip-pool-hourly:{source_ip}")
+}
+
+fn outcome_key(source_ip: IpAddr, day: u64, success: bool) -> Vec<u8> {
+    format!(
+        "ip-pool-{}:{source_ip}:{day}",
+        if success { "delivered" } else { "failed" }
+    )
+    .into_bytes()
+}
@@ -34,6 +34,7 @@ pub struct SessionParams<'x> {
     pub timeout_mail: Duration,
     pub timeout_rcpt: Duration,
     pub timeout_data: Duration,
+    pub max_buffer: usize,
     pub session_id: u64,
 }
 
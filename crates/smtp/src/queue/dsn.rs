@@ -4,12 +4,15 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use ahash::AHashMap;
+use common::addresses::verp_decode;
+use common::config::smtp::queue::DsnKind;
 use common::Server;
 use mail_builder::headers::content_type::ContentType;
 use mail_builder::headers::HeaderType;
 use mail_builder::mime::{make_boundary, BodyPart, MimePart};
 use mail_builder::MessageBuilder;
-use mail_parser::DateTime;
+use mail_parser::{DateTime, MessageParser};
 use smtp_proto::{
     Response, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
 };
@@ -18,6 +21,8 @@ use std::future::Future;
 use std::time::Duration;
 use store::write::now;
 
+use crate::core::bounce::SuppressionManager;
+use crate::core::list::MailingListManager;
 use crate::outbound::client::from_error_status;
 use crate::reporting::SmtpReporting;
 
@@ -37,7 +42,44 @@ impl SendDsn for Server {
         // Send DSN events
         self.log_dsn(message).await;
 
-        if !message.return_path.is_empty() {
+        if let Some(list) = self
+            .core
+            .smtp
+            .lists
+            .get_by_bounce(&message.return_path_lcase)
+        {
+            // Count failures towards the list members instead of bouncing
+            for member in message.take_permanent_failures() {
+                if let Err(err) = self.list_bounce(list, &member, message.span_id).await {
+                    trc::error!(err
+                        .span_id(message.span_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to process mailing list bounce"));
+                }
+            }
+        } else if !message.return_path.is_empty() {
+            // Suppress recipients that failed permanently for this sender
+            if self.core.smtp.queue.suppression.enable {
+                let sender = verp_decode(&message.return_path_lcase)
+                    .map_or_else(|| message.return_path_lcase.clone(), |(sender, _)| sender);
+                for (rcpt_idx, reason) in message.permanent_failures() {
+                    if let Err(err) = self
+                        .suppression_add(
+                            &sender,
+                            &message.recipients[rcpt_idx].address_lcase,
+                            &reason,
+                            message.span_id,
+                        )
+                        .await
+                    {
+                        trc::error!(err
+                            .span_id(message.span_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to update suppression list"));
+                    }
+                }
+            }
+
             // Build DSN
             if let Some(dsn) = message.build_dsn(self).await {
                 let mut dsn_message = self.new_message("", "", "", message.span_id);
@@ -256,60 +298,18 @@ impl Message {
         let has_success = !txt_success.is_empty();
         let has_delay = !txt_delay.is_empty();
         let has_failure = !txt_failed.is_empty();
-
-        let mut txt = String::with_capacity(txt_len + 128);
-        let (subject, is_mixed) = if has_success && !has_delay && !has_failure {
-            txt.push_str(
-                "Your message has been successfully delivered to the following recipients:\r\n\r\n",
-            );
-            ("Successfully delivered message", false)
+        let kind = if has_success && !has_delay && !has_failure {
+            DsnKind::Success
         } else if has_delay && !has_success && !has_failure {
-            txt.push_str("There was a temporary problem delivering your message to the following recipients:\r\n\r\n");
-            ("Warning: Delay in message delivery", false)
+            DsnKind::Delay
         } else if has_failure && !has_success && !has_delay {
-            txt.push_str(
-                "Your message could not be delivered to the following recipients:\r\n\r\n",
-            );
-            ("Failed to deliver message", false)
+            DsnKind::Failure
         } else if has_success {
-            txt.push_str("Your message has been partially delivered:\r\n\r\n");
-            ("Partially delivered message", true)
+            DsnKind::Partial
         } else {
-            txt.push_str("Your message could not be delivered to some recipients:\r\n\r\n");
-            (
-                "Warning: Temporary and permanent failures during message delivery",
-                true,
-            )
+            DsnKind::Mixed
         };
-
-        if has_success {
-            if is_mixed {
-                txt.push_str(
-                    "    ----- Delivery to the following addresses was successful -----\r\n",
-                );
-            }
-
-            txt.push_str(&txt_success);
-            txt.push_str("\r\n");
-        }
-
-        if has_delay {
-            if is_mixed {
-                txt.push_str(
-                    "    ----- There was a temporary problem delivering to these addresses -----\r\n",
-                );
-            }
-            txt.push_str(&txt_delay);
-            txt.push_str("\r\n");
-        }
-
-        if has_failure {
-            if is_mixed {
-                txt.push_str("    ----- Delivery to the following addresses failed -----\r\n");
-            }
-            txt.push_str(&txt_failed);
-            txt.push_str("\r\n");
-        }
+        let is_mixed = matches!(kind, DsnKind::Partial | DsnKind::Mixed);
 
         // Update next delay notification time
         if has_delay {
@@ -413,11 +413,86 @@ impl Message {
             }
         };
 
+        // Use the template for the sender domain and the language of the message
+        let languages = message_languages(&headers);
+        let template = config.dsn.template(&self.return_path_domain, &languages);
+        let vars = [
+            (
+                "{brand}",
+                template.map_or("", |template| template.brand.as_str()),
+            ),
+            (
+                "{support}",
+                template.map_or("", |template| template.support.as_str()),
+            ),
+            ("{sender}", self.return_path.as_str()),
+            ("{hostname}", reporting_mta.as_str()),
+        ];
+        let render = |text: &str| {
+            vars.iter()
+                .fold(text.replace("\r\n", "\n"), |text, (name, value)| {
+                    text.replace(name, value)
+                })
+                .replace('\n', "\r\n")
+        };
+        let text = |texts: Option<&AHashMap<DsnKind, String>>, kind: DsnKind| {
+            texts
+                .and_then(|texts| texts.get(&kind))
+                .map(|text| render(text))
+        };
+
+        // Build text response
+        let mut txt = String::with_capacity(txt_len + 128);
+        txt.push_str(
+            &text(template.map(|template| &template.intro), kind)
+                .unwrap_or_else(|| default_intro(kind).to_string()),
+        );
+        txt.push_str("\r\n\r\n");
+
+        for (section_kind, section) in [
+            (DsnKind::Success, &txt_success),
+            (DsnKind::Delay, &txt_delay),
+            (DsnKind::Failure, &txt_failed),
+        ] {
+            if !section.is_empty() {
+                if is_mixed {
+                    let _ = write!(
+                        txt,
+                        "    ----- {} -----\r\n",
+                        text(template.map(|template| &template.heading), section_kind)
+                            .unwrap_or_else(|| default_heading(section_kind).to_string())
+                    );
+                }
+                txt.push_str(section);
+                txt.push_str("\r\n");
+            }
+        }
+
+        if let Some(footer) = template.and_then(|template| template.footer.as_deref()) {
+            txt.push_str(&render(footer));
+            txt.push_str("\r\n");
+        }
+
+        let subject = text(template.map(|template| &template.subject), kind)
+            .unwrap_or_else(|| default_subject(kind).to_string());
+        let language = template.and_then(|template| {
+            languages
+                .iter()
+                .find(|language| template.has_language(language))
+        });
+
         // Build message
-        MessageBuilder::new()
+        let mut builder = MessageBuilder::new()
             .from((from_name.as_str(), from_addr.as_str()))
             .header("To", HeaderType::Text(self.return_path.as_str().into()))
-            .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+            .header("Auto-Submitted", HeaderType::Text("auto-generated".into()));
+        if let Some(language) = language {
+            builder = builder.header(
+                "Content-Language",
+                HeaderType::Text(language.as_str().into()),
+            );
+        }
+        builder
             .message_id(format!("<{}@{}>", make_boundary("."), reporting_mta))
             .subject(subject)
             .body(MimePart::new(
@@ -482,6 +557,65 @@ impl Message {
     }
 }
 
+/// Returns the languages of a message, in order of preference, from its
+/// Content-Language and Accept-Language headers.
+fn message_languages(headers: &str) -> Vec<String> {
+    let mut languages: Vec<String> = Vec::new();
+    let Some(message) = MessageParser::new().parse_headers(headers.as_bytes()) else {
+        return languages;
+    };
+
+    for header in ["Content-Language", "Accept-Language"] {
+        if let Some(value) = message.header_raw(header) {
+            for language in value.split(',') {
+                let language = language
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_lowercase();
+                if !language.is_empty() && language != "*" && !languages.contains(&language) {
+                    languages.push(language);
+                }
+            }
+        }
+    }
+
+    languages
+}
+
+fn default_subject(kind: DsnKind) -> &'static str {
+    match kind {
+        DsnKind::Success => "Successfully delivered message",
+        DsnKind::Delay => "Warning: Delay in message delivery",
+        DsnKind::Failure => "Failed to deliver message",
+        DsnKind::Partial => "Partially delivered message",
+        DsnKind::Mixed => "Warning: Temporary and permanent failures during message delivery",
+    }
+}
+
+fn default_intro(kind: DsnKind) -> &'static str {
+    match kind {
+        DsnKind::Success => {
+            "Your message has been successfully delivered to the following recipients:"
+        }
+        DsnKind::Delay => {
+            "There was a temporary problem delivering your message to the following recipients:"
+        }
+        DsnKind::Failure => "Your message could not be delivered to the following recipients:",
+        DsnKind::Partial => "Your message has been partially delivered:",
+        DsnKind::Mixed => "Your message could not be delivered to some recipients:",
+    }
+}
+
+fn default_heading(kind: DsnKind) -> &'static str {
+    match kind {
+        DsnKind::Success => "Delivery to the following addresses was successful",
+        DsnKind::Delay => "There was a temporary problem delivering to these addresses",
+        _ => "Delivery to the following addresses failed",
+    }
+}
+
 impl HostResponse<String> {
     fn write_dsn_text(&self, addr: &str, dsn: &mut String) {
         let _ = write!(
@@ -0,0 +1,146 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, net::IpAddr, time::Instant};
+
+use common::Server;
+use store::{
+    write::{now, Bincode},
+    Serialize,
+};
+use trc::AddContext;
+
+use super::{Error, Message, QueueId, Recipient, Status};
+
+/// Outcome of a single connection to a remote host while delivering a
+/// message to one of its domains.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryRecord {
+    pub time: u64,
+    pub domain: String,
+    pub mx: String,
+    pub remote_ip: IpAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_ip: Option<IpAddr>,
+    pub tls: TlsMode,
+    pub status: Status<String, String>,
+    pub duration: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsMode {
+    None,
+    StartTls,
+    Implicit,
+}
+
+pub trait DeliveryHistory: Sync + Send {
+    fn history_add(
+        &self,
+        queue_id: QueueId,
+        attempt: DeliveryRecord,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn history_get(
+        &self,
+        queue_id: QueueId,
+    ) -> impl Future<Output = trc::Result<Vec<DeliveryRecord>>> + Send;
+}
+
+impl DeliveryHistory for Server {
+    async fn history_add(&self, queue_id: QueueId, attempt: DeliveryRecord) -> trc::Result<()> {
+        let config = &self.core.smtp.queue.history;
+        let mut history = self.history_get(queue_id).await?;
+        history.push(attempt);
+
+        // Keep only the most recent attempts
+        if history.len() > config.max_attempts {
+            history.drain(..history.len() - config.max_attempts);
+        }
+
+        self.lookup_store()
+            .key_set(
+                history_key(queue_id),
+                Bincode::new(history).serialize(),
+                config.retention,
+            )
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn history_get(&self, queue_id: QueueId) -> trc::Result<Vec<DeliveryRecord>> {
+        self.lookup_store()
+            .key_get::<Bincode<Vec<DeliveryRecord>>>(history_key(queue_id))
+            .await
+            .caused_by(trc::location!())
+            .map(|history| history.map(|history| history.inner).unwrap_or_default())
+    }
+}
+
+impl DeliveryRecord {
+    pub fn new(domain: &str, mx: &str, remote_ip: IpAddr, local_ip: Option<IpAddr>) -> Self {
+        DeliveryRecord {
+            time: now(),
+            domain: domain.to_string(),
+            mx: mx.to_string(),
+            remote_ip,
+            local_ip,
+            tls: TlsMode::None,
+            status: Status::Scheduled,
+            duration: 0,
+        }
+    }
+
+    /// Returns a copy of the attempt with its final status and the time
+    /// elapsed since the connection was started.
+    pub fn finish(&self, status: &Status<(), Error>, started: Instant) -> Self {
+        DeliveryRecord {
+            status: match status {
+                Status::Scheduled => Status::Scheduled,
+                Status::Completed(_) => Status::Completed(String::new()),
+                Status::TemporaryFailure(err) => Status::TemporaryFailure(err.to_string()),
+                Status::PermanentFailure(err) => Status::PermanentFailure(err.to_string()),
+            },
+            duration: started.elapsed().as_millis() as u64,
+            ..self.clone()
+        }
+    }
+
+    /// Uses the response of the first recipient accepted by the remote host
+    /// as the response of a completed attempt.
+    pub fn with_response<'x>(
+        mut self,
+        mut recipients: impl Iterator<Item = &'x Recipient>,
+    ) -> Self {
+        if let Status::Completed(response) = &mut self.status {
+            if let Some(accepted) = recipients.find_map(|rcpt| match &rcpt.status {
+                Status::Completed(accepted) => Some(accepted.response.to_string()),
+                _ => None,
+            }) {
+                *response = accepted;
+            }
+        }
+        self
+    }
+}
+
+impl Message {
+    pub async fn record_attempt(&self, server: &Server, attempt: DeliveryRecord) {
+        if server.core.smtp.queue.history.enable {
+            if let Err(err) = server.history_add(self.queue_id, attempt).await {
+                trc::error!(err
+                    .span_id(self.span_id)
+                    .details("Failed to record delivery attempt"));
+            }
+        }
+    }
+}
+
+fn history_key(queue_id: QueueId) -> Vec<u8> {
+    format!("delivery-history:{queue_id}").into_bytes()
+}
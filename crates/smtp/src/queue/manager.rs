@@ -22,6 +22,9 @@ use super::{spool::SmtpSpool, DeliveryAttempt, Message, Status};
 pub(crate) const SHORT_WAIT: Duration = Duration::from_millis(1);
 pub(crate) const LONG_WAIT: Duration = Duration::from_secs(86400 * 365);
 
+/// Due time of the queue event of held messages, which is never reached.
+pub const HELD_DUE: u64 = u64::MAX;
+
 pub struct Queue {
     pub core: Arc<Inner>,
     pub on_hold: Vec<OnHold<QueueEventLock>>,
@@ -63,8 +66,14 @@ impl Queue {
     }
 
     pub async fn process_events(&mut self) {
-        // Deliver any concurrency limited messages
+        // Pause deliveries while the server is in read-only mode
         let server = self.core.build_server();
+        if server.is_read_only() {
+            self.next_wake_up = LONG_WAIT;
+            return;
+        }
+
+        // Deliver any concurrency limited messages
         while let Some(queue_event) = self.next_on_hold() {
             DeliveryAttempt::new(queue_event)
                 .try_deliver(server.clone())
@@ -80,7 +89,7 @@ impl Queue {
                     .try_deliver(server.clone())
                     .await;
             } else {
-                self.next_wake_up = Duration::from_secs(queue_event.due - now);
+                self.next_wake_up = Duration::from_secs(queue_event.due - now).min(LONG_WAIT);
             }
         }
     }
@@ -130,10 +139,12 @@ impl Message {
             }
         }
 
-        if has_events {
-            next_event.into()
-        } else {
+        if !has_events {
             None
+        } else if self.is_held() {
+            HELD_DUE.into()
+        } else {
+            next_event.into()
         }
     }
 
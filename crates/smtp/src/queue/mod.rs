@@ -21,8 +21,10 @@ use store::write::now;
 use utils::BlobHash;
 
 pub mod dsn;
+pub mod history;
 pub mod manager;
 pub mod quota;
+pub mod route;
 pub mod spool;
 pub mod throttle;
 
@@ -94,6 +96,8 @@ pub struct Recipient {
 pub const RCPT_DSN_SENT: u64 = 1 << 32;
 pub const RCPT_STATUS_CHANGED: u64 = 2 << 32;
 
+pub const MESSAGE_HELD: u64 = 1 << 32;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status<T, E> {
     #[serde(rename = "scheduled")]
@@ -275,6 +279,7 @@ impl<'x> ResolveVariable for QueueEnvelope<'x> {
                 .into(),
             V_MX => self.mx.into(),
             V_PRIORITY => self.message.priority.into(),
+            V_SIZE => self.message.size.into(),
             V_REMOTE_IP => self.remote_ip.to_string().into(),
             V_LOCAL_IP => self.local_ip.to_string().into(),
             _ => "".into(),
@@ -294,6 +299,7 @@ impl ResolveVariable for Message {
                 .collect::<Vec<_>>()
                 .into(),
             V_PRIORITY => self.priority.into(),
+            V_SIZE => self.size.into(),
             _ => "".into(),
         }
     }
@@ -360,6 +366,23 @@ impl DomainPart for String {
     }
 }
 
+impl Error {
+    /// Short name of the kind of error, used to search the queue.
+    pub fn class(&self) -> &'static str {
+        match self {
+            Error::DnsError(_) => "dns",
+            Error::UnexpectedResponse(_) => "response",
+            Error::ConnectionError(_) => "connection",
+            Error::TlsError(_) => "tls",
+            Error::DaneError(_) => "dane",
+            Error::MtaStsError(_) => "mta-sts",
+            Error::RateLimited => "rate-limit",
+            Error::ConcurrencyLimited => "concurrency-limit",
+            Error::Io(_) => "io",
+        }
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
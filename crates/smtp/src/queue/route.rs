@@ -0,0 +1,77 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use store::write::now;
+use trc::{AddContext, QueueEvent};
+
+use super::{Message, QueueId};
+
+const DAY: u64 = 86400;
+
+pub trait QueueRoute: Sync + Send {
+    fn queue_route_set(
+        &self,
+        message: &Message,
+        next_hop: Option<&str>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn queue_route_get(
+        &self,
+        queue_id: QueueId,
+    ) -> impl Future<Output = trc::Result<Option<String>>> + Send;
+}
+
+impl QueueRoute for Server {
+    async fn queue_route_set(&self, message: &Message, next_hop: Option<&str>) -> trc::Result<()> {
+        let lookup = self.lookup_store();
+        if let Some(next_hop) = next_hop {
+            // Keep the route until all domains of the message expire
+            let expires = message
+                .domains
+                .iter()
+                .map(|domain| domain.expires)
+                .max()
+                .unwrap_or_default()
+                .saturating_sub(now())
+                + DAY;
+            lookup
+                .key_set(
+                    route_key(message.queue_id),
+                    next_hop.as_bytes().to_vec(),
+                    expires.into(),
+                )
+                .await
+                .caused_by(trc::location!())?;
+        } else {
+            lookup
+                .key_delete(route_key(message.queue_id))
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        trc::event!(
+            Queue(QueueEvent::Rerouted),
+            QueueId = message.queue_id,
+            Hostname = next_hop.map(|next_hop| next_hop.to_string()),
+        );
+
+        Ok(())
+    }
+
+    async fn queue_route_get(&self, queue_id: QueueId) -> trc::Result<Option<String>> {
+        self.lookup_store()
+            .key_get::<String>(route_key(queue_id))
+            .await
+            .caused_by(trc::location!())
+    }
+}
+
+fn route_key(queue_id: QueueId) -> Vec<u8> {
+    format!("queue-route:{queue_id}").into_bytes()
+}
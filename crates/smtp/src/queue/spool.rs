@@ -18,6 +18,7 @@ use utils::BlobHash;
 
 use super::{
     Domain, Message, MessageSource, QueueEnvelope, QueueId, QuotaKey, Recipient, Schedule, Status,
+    MESSAGE_HELD,
 };
 
 pub const LOCK_EXPIRY: u64 = 300;
@@ -38,6 +39,8 @@ pub trait SmtpSpool: Sync + Send {
         event: QueueEventLock,
     ) -> impl Future<Output = Option<QueueEventLock>> + Send;
 
+    fn unlock_event(&self, event: QueueEventLock) -> impl Future<Output = ()> + Send;
+
     fn read_message(&self, id: QueueId) -> impl Future<Output = Option<Message>> + Send;
 }
 
@@ -159,6 +162,36 @@ impl SmtpSpool for Server {
         }
     }
 
+    async fn unlock_event(&self, event: QueueEventLock) {
+        let mut batch = BatchBuilder::new();
+        batch.assert_value(
+            ValueClass::Queue(QueueClass::MessageEvent(store::write::QueueEvent {
+                due: event.due,
+                queue_id: event.queue_id,
+            })),
+            event.lock_expiry,
+        );
+        batch.set(
+            ValueClass::Queue(QueueClass::MessageEvent(store::write::QueueEvent {
+                due: event.due,
+                queue_id: event.queue_id,
+            })),
+            0u64.serialize(),
+        );
+        match self.store().write(batch.build()).await {
+            Ok(_) => (),
+            Err(err) if err.is_assertion_failure() => {
+                // The event was updated by the delivery task
+            }
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to unlock event.")
+                    .span_id(event.queue_id)
+                    .caused_by(trc::location!()));
+            }
+        }
+    }
+
     async fn read_message(&self, id: QueueId) -> Option<Message> {
         match self
             .store()
@@ -302,7 +335,7 @@ impl Message {
                 BlobOp::Commit {
                     hash: self.blob_hash.clone(),
                 },
-                vec![],
+                now().serialize(),
             )
             .set(
                 ValueClass::Queue(QueueClass::Message(self.queue_id)),
@@ -485,6 +518,42 @@ impl Message {
         }
     }
 
+    pub fn is_held(&self) -> bool {
+        (self.flags & MESSAGE_HELD) != 0
+    }
+
+    /// Stops delivery attempts until the message is released. Returns false
+    /// if the message was already held.
+    pub async fn hold(mut self, server: &Server) -> bool {
+        if self.is_held() {
+            return false;
+        }
+
+        let prev_event = self.next_event();
+        self.flags |= MESSAGE_HELD;
+        let next_event = self.next_event();
+
+        trc::event!(Queue(trc::QueueEvent::Held), QueueId = self.queue_id);
+
+        self.save_changes(server, prev_event, next_event).await
+    }
+
+    /// Schedules delivery of a held message. Returns false if the message
+    /// was not held.
+    pub async fn release(mut self, server: &Server) -> bool {
+        if !self.is_held() {
+            return false;
+        }
+
+        let prev_event = self.next_event();
+        self.flags &= !MESSAGE_HELD;
+        let next_event = self.next_event();
+
+        trc::event!(Queue(trc::QueueEvent::Released), QueueId = self.queue_id);
+
+        self.save_changes(server, prev_event, next_event).await
+    }
+
     pub fn has_domain(&self, domains: &[String]) -> bool {
         self.domains.iter().any(|d| domains.contains(&d.domain))
             || self
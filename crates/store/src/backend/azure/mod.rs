@@ -8,6 +8,9 @@ use std::{fmt::Display, io::Write, ops::Range, time::Duration};
 
 use azure_core::error::ErrorKind;
 use azure_core::{ExponentialRetryOptions, RetryOptions, StatusCode, TransportOptions};
+use azure_identity::{
+    TokenCredentialOptions, VirtualMachineManagedIdentityCredential, WorkloadIdentityCredential,
+};
 use azure_storage::StorageCredentials;
 use azure_storage_blobs::prelude::{ClientBuilder, ContainerClient};
 use futures::stream::StreamExt;
@@ -34,11 +37,12 @@ impl AzureStore {
         let credentials = match (
             config.value((&prefix, "azure-access-key")),
             config.value((&prefix, "sas-token")),
+            config.value((&prefix, "identity")),
         ) {
-            (Some(access_key), None) => {
+            (Some(access_key), None, None) => {
                 StorageCredentials::access_key(storage_account.clone(), access_key.to_string())
             }
-            (None, Some(sas_token)) => match StorageCredentials::sas_token(sas_token) {
+            (None, Some(sas_token), None) => match StorageCredentials::sas_token(sas_token) {
                 Ok(cred) => cred,
                 Err(err) => {
                     config.new_build_error(
@@ -48,12 +52,37 @@ impl AzureStore {
                     return None;
                 }
             },
+            // The managed identity of the VM or container instance, obtained
+            // from the instance metadata service
+            (None, None, Some("managed")) => StorageCredentials::token_credential(Arc::new(
+                VirtualMachineManagedIdentityCredential::new(TokenCredentialOptions::default()),
+            )),
+            // Federated credentials injected by AKS workload identity through the
+            // AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_FEDERATED_TOKEN_FILE variables
+            (None, None, Some("workload")) => {
+                match WorkloadIdentityCredential::create(TokenCredentialOptions::default()) {
+                    Ok(cred) => StorageCredentials::token_credential(Arc::new(cred)),
+                    Err(err) => {
+                        config.new_build_error(
+                            prefix.as_str(),
+                            format!("Failed to create workload identity credentials: {err:?}"),
+                        );
+                        return None;
+                    }
+                }
+            }
+            (None, None, Some(identity)) => {
+                let err =
+                    format!("Invalid identity {identity:?}, expected \"managed\" or \"workload\"");
+                config.new_build_error((&prefix, "identity"), err);
+                return None;
+            }
             _ => {
                 config.new_build_error(
                     prefix.as_str(),
                     concat!(
                         "Failed to create credentials: exactly one of ",
-                        "'azure-access-key' and 'sas-token' must be specified"
+                        "'azure-access-key', 'sas-token' and 'identity' must be specified"
                     ),
                 );
                 return None;
@@ -84,11 +113,24 @@ impl AzureStore {
             .unwrap_or(3)
             * 2;
 
+        // Azure Storage throttles by returning 503 with a Retry-After header, which
+        // the SDK honours, so shorter delays than the SDK defaults are used otherwise.
+        let initial_delay = config
+            .property_or_default::<Duration>((&prefix, "retry.initial-delay"), "200ms")
+            .unwrap_or_else(|| Duration::from_millis(200));
+        let max_delay = config
+            .property_or_default::<Duration>((&prefix, "retry.max-delay"), "10s")
+            .unwrap_or_else(|| Duration::from_secs(10));
+
         Some(AzureStore {
             client: ClientBuilder::new(storage_account, credentials)
                 .transport(TransportOptions::new(transport))
                 .retry(RetryOptions::exponential(
-                    ExponentialRetryOptions::default().max_retries(max_retries),
+                    ExponentialRetryOptions::default()
+                        .max_retries(max_retries)
+                        .initial_delay(initial_delay)
+                        .max_delay(max_delay)
+                        .max_total_elapsed(timeout.saturating_mul(max_retries.max(1))),
                 ))
                 .container_client(container),
             prefix: config.value((&prefix, "key-prefix")).map(|s| s.to_string()),
@@ -83,7 +83,13 @@ impl DistributedBlob {
                 BlobBackend::S3(store) => store.get_blob(key, read_range).await,
                 #[cfg(feature = "azure")]
                 BlobBackend::Azure(store) => store.get_blob(key, read_range).await,
-                BlobBackend::Composite(_) => unimplemented!(),
+                #[cfg(feature = "gcs")]
+                BlobBackend::Gcs(store) => store.get_blob(key, read_range).await,
+                #[cfg(feature = "wasm")]
+                BlobBackend::Wasm(store) => store.get_blob(key, read_range).await,
+                BlobBackend::Composite(_) | BlobBackend::Tiered(_) | BlobBackend::Replicated(_) => {
+                    unimplemented!()
+                }
             }
         })
         .await
@@ -115,7 +121,13 @@ impl DistributedBlob {
                 BlobBackend::S3(store) => store.put_blob(key, data).await,
                 #[cfg(feature = "azure")]
                 BlobBackend::Azure(store) => store.put_blob(key, data).await,
-                BlobBackend::Composite(_) => unimplemented!(),
+                #[cfg(feature = "gcs")]
+                BlobBackend::Gcs(store) => store.put_blob(key, data).await,
+                #[cfg(feature = "wasm")]
+                BlobBackend::Wasm(store) => store.put_blob(key, data).await,
+                BlobBackend::Composite(_) | BlobBackend::Tiered(_) | BlobBackend::Replicated(_) => {
+                    unimplemented!()
+                }
             }
         })
         .await
@@ -147,7 +159,13 @@ impl DistributedBlob {
                 BlobBackend::S3(store) => store.delete_blob(key).await,
                 #[cfg(feature = "azure")]
                 BlobBackend::Azure(store) => store.delete_blob(key).await,
-                BlobBackend::Composite(_) => unimplemented!(),
+                #[cfg(feature = "gcs")]
+                BlobBackend::Gcs(store) => store.delete_blob(key).await,
+                #[cfg(feature = "wasm")]
+                BlobBackend::Wasm(store) => store.delete_blob(key).await,
+                BlobBackend::Composite(_) | BlobBackend::Tiered(_) | BlobBackend::Replicated(_) => {
+                    unimplemented!()
+                }
             }
         })
         .await
@@ -11,3 +11,5 @@
 pub mod distributed_blob;
 #[cfg(any(feature = "postgres", feature = "mysql"))]
 pub mod read_replica;
+pub mod replicated_blob;
+pub mod tiered_blob;
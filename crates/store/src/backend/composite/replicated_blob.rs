@@ -0,0 +1,367 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: LicenseRef-SEL
+ *
+ * This file is subject to the Stalwart Enterprise License Agreement (SEL) and
+ * is NOT open source software.
+ *
+ */
+
+use std::{
+    future::Future,
+    ops::Range,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use ahash::AHashSet;
+use parking_lot::Mutex;
+use trc::AddContext;
+use utils::{
+    config::{utils::AsKey, Config},
+    BlobHash, BLOB_HASH_LEN,
+};
+
+use crate::{
+    write::{key::DeserializeBigEndian, now, BlobOp, ValueClass},
+    BlobStore, IterateParams, Store, Stores, ValueKey, U32_LEN, U64_LEN,
+};
+
+pub struct ReplicatedBlob {
+    pub primary: BlobStore,
+    pub secondary: BlobStore,
+    pub mode: ReplicationMode,
+    pub reconcile_window: u64,
+    pub failure_threshold: u32,
+    pub retry_interval: u64,
+    pub misses: Arc<Mutex<AHashSet<Vec<u8>>>>,
+    pub pending_deletes: Mutex<AHashSet<Vec<u8>>>,
+    pub health: PrimaryHealth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicationMode {
+    Sync,
+    Async,
+}
+
+/// Tracks consecutive primary failures. Once the threshold is reached the
+/// primary is skipped until the retry interval elapses, after which the next
+/// request probes it again.
+#[derive(Default)]
+pub struct PrimaryHealth {
+    failures: AtomicU32,
+    retry_at: AtomicU64,
+}
+
+impl ReplicatedBlob {
+    pub fn open(config: &mut Config, prefix: impl AsKey, stores: &Stores) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let primary = replica(config, (&prefix, "primary"), stores)?;
+        let secondary = replica(config, (&prefix, "secondary"), stores)?;
+        let reconcile_window = config
+            .property_or_default::<Duration>((&prefix, "reconcile-window"), "2d")
+            .unwrap_or_else(|| Duration::from_secs(2 * 86400))
+            .as_secs();
+        let mode = match config.value((&prefix, "replication")).unwrap_or("async") {
+            "sync" => ReplicationMode::Sync,
+            "async" => ReplicationMode::Async,
+            other => {
+                let err = format!("Invalid replication mode {other:?}, expected sync or async");
+                config.new_parse_error((&prefix, "replication"), err);
+                ReplicationMode::Async
+            }
+        };
+
+        Some(Self {
+            primary,
+            secondary,
+            mode,
+            reconcile_window,
+            failure_threshold: config
+                .property_or_default::<u32>((&prefix, "failover.threshold"), "3")
+                .unwrap_or(3)
+                .max(1),
+            retry_interval: config
+                .property_or_default::<Duration>((&prefix, "failover.retry-interval"), "30s")
+                .unwrap_or_else(|| Duration::from_secs(30))
+                .as_secs(),
+            misses: Default::default(),
+            pending_deletes: Default::default(),
+            health: Default::default(),
+        })
+    }
+
+    pub async fn get_blob(
+        &self,
+        key: &[u8],
+        read_range: Range<usize>,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        Box::pin(async move {
+            if !self.is_primary_available() {
+                return self.secondary.get_blob(key, read_range).await;
+            }
+
+            match self.primary.get_blob(key, read_range.clone()).await {
+                Ok(Some(data)) => {
+                    self.primary_succeeded();
+                    Ok(Some(data))
+                }
+                Ok(None) => {
+                    self.primary_succeeded();
+                    self.secondary.get_blob(key, read_range).await
+                }
+                Err(err) => {
+                    self.primary_failed(err.details("Failed to read blob from primary replica."));
+                    self.secondary.get_blob(key, read_range).await
+                }
+            }
+        })
+        .await
+    }
+
+    // Returns a boxed future that is declared `Send`, as the asynchronous replication
+    // task awaits `BlobStore::put_blob`, which in turn awaits this function.
+    pub fn put_blob<'x>(
+        &'x self,
+        key: &'x [u8],
+        data: &'x [u8],
+    ) -> Pin<Box<dyn Future<Output = trc::Result<()>> + Send + 'x>> {
+        Box::pin(async move {
+            if !self.is_primary_available() {
+                return self.put_secondary_only(key, data).await;
+            }
+            match self.primary.put_blob(key, data).await {
+                Ok(_) => self.primary_succeeded(),
+                Err(err) => {
+                    self.primary_failed(err.details("Failed to write blob to primary replica."));
+                    return self.put_secondary_only(key, data).await;
+                }
+            }
+
+            match self.mode {
+                ReplicationMode::Sync => {
+                    // The write only succeeds once both replicas have the blob, the
+                    // copy left on the primary is repaired by the next reconciliation
+                    if let Err(err) = self.secondary.put_blob(key, data).await {
+                        self.misses.lock().insert(key.to_vec());
+                        return Err(err
+                            .details("Failed to replicate blob to secondary replica.")
+                            .caused_by(trc::location!()));
+                    }
+                }
+                ReplicationMode::Async => {
+                    let secondary = self.secondary.clone();
+                    let misses = self.misses.clone();
+                    let key = key.to_vec();
+                    let data = data.to_vec();
+                    tokio::spawn(async move {
+                        if let Err(err) = secondary.put_blob(&key, &data).await {
+                            trc::error!(err
+                                .details("Failed to replicate blob to secondary replica.")
+                                .caused_by(trc::location!()));
+                            misses.lock().insert(key);
+                        }
+                    });
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    pub async fn delete_blob(&self, key: &[u8]) -> trc::Result<bool> {
+        Box::pin(async move {
+            let deleted = if self.is_primary_available() {
+                match self.primary.delete_blob(key).await {
+                    Ok(deleted) => {
+                        self.primary_succeeded();
+                        deleted
+                    }
+                    Err(err) => {
+                        self.primary_failed(
+                            err.details("Failed to delete blob from primary replica."),
+                        );
+                        self.pending_deletes.lock().insert(key.to_vec());
+                        false
+                    }
+                }
+            } else {
+                // Deleted from the primary once it is back
+                self.pending_deletes.lock().insert(key.to_vec());
+                false
+            };
+            self.misses.lock().remove(key);
+
+            match self.secondary.delete_blob(key).await {
+                Ok(secondary_deleted) => Ok(deleted || secondary_deleted),
+                Err(err) => {
+                    trc::error!(err
+                        .details("Failed to delete blob from secondary replica.")
+                        .caused_by(trc::location!()));
+                    self.pending_deletes.lock().insert(key.to_vec());
+                    Ok(deleted)
+                }
+            }
+        })
+        .await
+    }
+
+    async fn put_secondary_only(&self, key: &[u8], data: &[u8]) -> trc::Result<()> {
+        // Keep the blob on the secondary until the primary is back
+        self.secondary.put_blob(key, data).await?;
+        self.misses.lock().insert(key.to_vec());
+        Ok(())
+    }
+
+    pub fn is_primary_available(&self) -> bool {
+        self.health.retry_at.load(Ordering::Relaxed) <= now()
+    }
+
+    fn primary_succeeded(&self) {
+        if self.health.failures.swap(0, Ordering::Relaxed) >= self.failure_threshold {
+            self.health.retry_at.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn primary_failed(&self, err: trc::Error) {
+        let failures = self.health.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.failure_threshold {
+            self.health
+                .retry_at
+                .store(now() + self.retry_interval, Ordering::Relaxed);
+            trc::error!(err
+                .ctx(trc::Key::Total, failures)
+                .details("Primary blob replica is unavailable, failing over to secondary.")
+                .caused_by(trc::location!()));
+        } else {
+            trc::error!(err.caused_by(trc::location!()));
+        }
+    }
+
+    pub async fn reconcile(&self, store: &Store) -> trc::Result<usize> {
+        // Nothing can be repaired while the primary is down
+        if !self.is_primary_available() {
+            return Ok(0);
+        }
+
+        // Retry deletions that failed on either replica
+        let pending_deletes = std::mem::take(&mut *self.pending_deletes.lock());
+        for key in pending_deletes {
+            for replica in [&self.primary, &self.secondary] {
+                if let Err(err) = replica.delete_blob(&key).await {
+                    trc::error!(err
+                        .details("Failed to delete blob from replica.")
+                        .caused_by(trc::location!()));
+                    self.pending_deletes.lock().insert(key.clone());
+                }
+            }
+        }
+
+        // Verify the blobs committed within the reconciliation window
+        let from_key = ValueKey {
+            account_id: 0,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Link {
+                hash: BlobHash::default(),
+            }),
+        };
+        let to_key = ValueKey {
+            account_id: u32::MAX,
+            collection: u8::MAX,
+            document_id: u32::MAX,
+            class: ValueClass::Blob(BlobOp::Link {
+                hash: BlobHash::new_max(),
+            }),
+        };
+        let threshold = now().saturating_sub(self.reconcile_window);
+        let mut keys = std::mem::take(&mut *self.misses.lock());
+        keys.retain(|key| !self.pending_deletes.lock().contains(key));
+        store
+            .iterate(
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
+                    if key.deserialize_be_u32(key.len() - U32_LEN)? == u32::MAX
+                        && key.deserialize_be_u32(BLOB_HASH_LEN)? == u32::MAX
+                        && value.len() == U64_LEN
+                        && (threshold..u64::MAX).contains(&value.deserialize_be_u64(0)?)
+                    {
+                        keys.insert(
+                            key.get(0..BLOB_HASH_LEN)
+                                .ok_or_else(|| {
+                                    trc::Error::corrupted_key(key, None, trc::location!())
+                                })?
+                                .to_vec(),
+                        );
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        // Copy missing blobs between replicas, keys that fail are retried on the next run
+        let mut repaired = 0;
+        for key in keys {
+            match self.repair(&key).await {
+                Ok(true) => repaired += 1,
+                Ok(false) => {}
+                Err(err) => {
+                    trc::error!(err
+                        .details("Failed to repair blob replica.")
+                        .caused_by(trc::location!()));
+                    self.misses.lock().insert(key);
+                }
+            }
+        }
+
+        Ok(repaired)
+    }
+
+    async fn repair(&self, key: &[u8]) -> trc::Result<bool> {
+        let primary = self
+            .primary
+            .get_blob(key, 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?;
+        let secondary = self
+            .secondary
+            .get_blob(key, 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?;
+
+        match (primary, secondary) {
+            (Some(data), None) => self
+                .secondary
+                .put_blob(key, &data)
+                .await
+                .caused_by(trc::location!())
+                .map(|_| true),
+            (None, Some(data)) => self
+                .primary
+                .put_blob(key, &data)
+                .await
+                .caused_by(trc::location!())
+                .map(|_| true),
+            _ => Ok(false),
+        }
+    }
+}
+
+fn replica(config: &mut Config, key: impl AsKey, stores: &Stores) -> Option<BlobStore> {
+    let key = key.as_key();
+    let store_id = config.value_require(&key)?.to_string();
+    if let Some(store) = stores.blob_stores.get(&store_id) {
+        Some(store.clone())
+    } else {
+        config.new_build_error(key, format!("Blob store {store_id} not found"));
+        None
+    }
+}
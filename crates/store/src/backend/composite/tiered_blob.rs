@@ -0,0 +1,180 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: LicenseRef-SEL
+ *
+ * This file is subject to the Stalwart Enterprise License Agreement (SEL) and
+ * is NOT open source software.
+ *
+ */
+
+use std::{ops::Range, time::Duration};
+
+use trc::AddContext;
+use utils::{
+    config::{utils::AsKey, Config},
+    BlobHash, BLOB_HASH_LEN,
+};
+
+use crate::{
+    write::{
+        assert::AssertValue, key::DeserializeBigEndian, now, BatchBuilder, BlobOp, ValueClass,
+    },
+    BlobStore, IterateParams, Serialize, Store, Stores, ValueKey, U32_LEN, U64_LEN,
+};
+
+pub struct TieredBlob {
+    pub hot: BlobStore,
+    pub cold: BlobStore,
+    pub migrate_after: u64,
+}
+
+impl TieredBlob {
+    pub fn open(config: &mut Config, prefix: impl AsKey, stores: &Stores) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let hot = tier(config, (&prefix, "hot"), stores)?;
+        let cold = tier(config, (&prefix, "cold"), stores)?;
+        let migrate_after = config
+            .property_or_default::<Duration>((&prefix, "migrate-after"), "30d")
+            .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+            .as_secs();
+
+        Some(Self {
+            hot,
+            cold,
+            migrate_after,
+        })
+    }
+
+    pub async fn get_blob(
+        &self,
+        key: &[u8],
+        read_range: Range<usize>,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        Box::pin(async move {
+            match self.hot.get_blob(key, read_range.clone()).await? {
+                Some(data) => Ok(Some(data)),
+                None => self.cold.get_blob(key, read_range).await,
+            }
+        })
+        .await
+    }
+
+    pub async fn put_blob(&self, key: &[u8], data: &[u8]) -> trc::Result<()> {
+        Box::pin(async move { self.hot.put_blob(key, data).await }).await
+    }
+
+    pub async fn delete_blob(&self, key: &[u8]) -> trc::Result<bool> {
+        Box::pin(async move {
+            let hot = self.hot.delete_blob(key).await?;
+            let cold = self.cold.delete_blob(key).await?;
+            Ok(hot || cold)
+        })
+        .await
+    }
+
+    pub async fn migrate(&self, store: &Store) -> trc::Result<usize> {
+        // Obtain the blobs that were committed to the hot tier before the threshold
+        let from_key = ValueKey {
+            account_id: 0,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Link {
+                hash: BlobHash::default(),
+            }),
+        };
+        let to_key = ValueKey {
+            account_id: u32::MAX,
+            collection: u8::MAX,
+            document_id: u32::MAX,
+            class: ValueClass::Blob(BlobOp::Link {
+                hash: BlobHash::new_max(),
+            }),
+        };
+        let threshold = now().saturating_sub(self.migrate_after);
+        let mut hashes = Vec::new();
+        store
+            .iterate(
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
+                    if key.deserialize_be_u32(key.len() - U32_LEN)? == u32::MAX
+                        && key.deserialize_be_u32(BLOB_HASH_LEN)? == u32::MAX
+                    {
+                        // Blobs committed before tiering was enabled have no timestamp
+                        let committed_at = if value.len() == U64_LEN {
+                            value.deserialize_be_u64(0)?
+                        } else {
+                            0
+                        };
+
+                        if committed_at <= threshold {
+                            hashes.push(
+                                BlobHash::try_from_hash_slice(
+                                    key.get(0..BLOB_HASH_LEN).ok_or_else(|| {
+                                        trc::Error::corrupted_key(key, None, trc::location!())
+                                    })?,
+                                )
+                                .unwrap(),
+                            );
+                        }
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        // Move blobs to the cold tier
+        let mut migrated = 0;
+        for hash in hashes {
+            if let Some(data) = self
+                .hot
+                .get_blob(hash.as_ref(), 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())?
+            {
+                self.cold
+                    .put_blob(hash.as_ref(), &data)
+                    .await
+                    .caused_by(trc::location!())?;
+                migrated += 1;
+            }
+
+            // Mark the blob as migrated, unless it was purged in the meantime
+            let mut batch = BatchBuilder::new();
+            batch
+                .assert_value(
+                    ValueClass::Blob(BlobOp::Commit { hash: hash.clone() }),
+                    AssertValue::Some,
+                )
+                .set(
+                    ValueClass::Blob(BlobOp::Commit { hash: hash.clone() }),
+                    u64::MAX.serialize(),
+                );
+            match store.write(batch.build()).await {
+                Ok(_) => {
+                    self.hot
+                        .delete_blob(hash.as_ref())
+                        .await
+                        .caused_by(trc::location!())?;
+                }
+                Err(err) if err.is_assertion_failure() => {}
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+        }
+
+        Ok(migrated)
+    }
+}
+
+fn tier(config: &mut Config, key: impl AsKey, stores: &Stores) -> Option<BlobStore> {
+    let key = key.as_key();
+    let store_id = config.value_require(&key)?.to_string();
+    if let Some(store) = stores.blob_stores.get(&store_id) {
+        Some(store.clone())
+    } else {
+        config.new_build_error(key, format!("Blob store {store_id} not found"));
+        None
+    }
+}
@@ -0,0 +1,225 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::atomic::Ordering;
+
+use etcd_client::{Compare, CompareOp, PutOptions, Txn, TxnOp};
+use utils::lru_cache::LruCached;
+
+use crate::{
+    write::{key::DeserializeBigEndian, now},
+    Deserialize, U64_LEN,
+};
+
+use super::{into_error, EtcdStore};
+
+// Leases are only used to remove expired keys from etcd, which rounds short
+// TTLs up to its minimum lease duration. The expiration time is also stored
+// at the beginning of every value so that expired keys are never returned.
+const NEVER_EXPIRES: u64 = 0;
+
+impl EtcdStore {
+    pub async fn key_set(
+        &self,
+        key: Vec<u8>,
+        value: Vec<u8>,
+        expires: Option<u64>,
+    ) -> trc::Result<()> {
+        let key = self.build_key(key);
+        let options = self.put_options(expires).await?;
+        self.client
+            .kv_client()
+            .put(
+                key.as_slice(),
+                serialize_value(expires_at(expires), &value),
+                options.into(),
+            )
+            .await
+            .map_err(into_error)?;
+        self.evict(&key);
+
+        Ok(())
+    }
+
+    pub async fn key_incr(
+        &self,
+        key: Vec<u8>,
+        value: i64,
+        expires: Option<u64>,
+    ) -> trc::Result<i64> {
+        let key = self.build_key(key);
+        let mut kv = self.client.kv_client();
+
+        // etcd has no atomic increments, counters are updated with compare-and-swap
+        for _ in 0..self.max_retries {
+            let response = kv.get(key.as_slice(), None).await.map_err(into_error)?;
+            let (compare, counter, expires_at, options) = if let Some(current) =
+                response.kvs().first()
+            {
+                let compare =
+                    Compare::mod_revision(key.as_slice(), CompareOp::Equal, current.mod_revision());
+                if let Some(value) = deserialize_value(current.value())? {
+                    // Keep the expiration time and lease of the existing counter
+                    (
+                        compare,
+                        deserialize_counter(&key, &value)?,
+                        current.value().deserialize_be_u64(0)?,
+                        PutOptions::new().with_ignore_lease(),
+                    )
+                } else {
+                    // Expired counters start over with a new lease
+                    (
+                        compare,
+                        0,
+                        expires_at(expires),
+                        self.put_options(expires).await?,
+                    )
+                }
+            } else {
+                (
+                    Compare::version(key.as_slice(), CompareOp::Equal, 0),
+                    0,
+                    expires_at(expires),
+                    self.put_options(expires).await?,
+                )
+            };
+            let counter = counter + value;
+
+            if kv
+                .txn(Txn::new().when([compare]).and_then([TxnOp::put(
+                    key.as_slice(),
+                    serialize_value(expires_at, &counter.to_be_bytes()),
+                    options.into(),
+                )]))
+                .await
+                .map_err(into_error)?
+                .succeeded()
+            {
+                self.evict(&key);
+                return Ok(counter);
+            }
+        }
+
+        Err(into_error("Counter update failed after too many retries"))
+    }
+
+    pub async fn key_delete(&self, key: Vec<u8>) -> trc::Result<()> {
+        let key = self.build_key(key);
+        self.client
+            .kv_client()
+            .delete(key.as_slice(), None)
+            .await
+            .map_err(into_error)?;
+        self.evict(&key);
+
+        Ok(())
+    }
+
+    pub async fn key_get<T: Deserialize + std::fmt::Debug + 'static>(
+        &self,
+        key: Vec<u8>,
+    ) -> trc::Result<Option<T>> {
+        if let Some(value) = self.get_value(key, true).await? {
+            T::deserialize(&value).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn counter_get(&self, key: Vec<u8>) -> trc::Result<i64> {
+        // Counters change too often to be worth caching
+        if let Some(value) = self.get_value(key.clone(), false).await? {
+            deserialize_counter(&key, &value)
+        } else {
+            Ok(0)
+        }
+    }
+
+    pub async fn key_exists(&self, key: Vec<u8>) -> trc::Result<bool> {
+        self.get_value(key, true).await.map(|value| value.is_some())
+    }
+
+    async fn get_value(&self, key: Vec<u8>, use_cache: bool) -> trc::Result<Option<Vec<u8>>> {
+        let key = self.build_key(key);
+        let cache = self
+            .cache
+            .as_ref()
+            .filter(|cache| use_cache && cache.active.load(Ordering::Relaxed));
+        let generation = if let Some(cache) = cache {
+            // Cached values keep their expiration time, which is checked on every read
+            if let Some(value) = cache.entries.get(&key) {
+                return value.map_or(Ok(None), |value| deserialize_value(&value));
+            }
+            cache.generation.load(Ordering::Relaxed)
+        } else {
+            0
+        };
+
+        let response = self
+            .client
+            .kv_client()
+            .get(key.as_slice(), None)
+            .await
+            .map_err(into_error)?;
+        let value = response.kvs().first().map(|kv| kv.value().to_vec());
+
+        // Skip caching if the key might have changed while it was being read
+        if let Some(cache) = cache {
+            let mut entries = cache.entries.lock();
+            if cache.generation.load(Ordering::Relaxed) == generation {
+                entries.insert(key, value.clone());
+            }
+        }
+
+        value.map_or(Ok(None), |value| deserialize_value(&value))
+    }
+
+    async fn put_options(&self, expires: Option<u64>) -> trc::Result<PutOptions> {
+        if let Some(expires) = expires {
+            self.client
+                .lease_client()
+                .grant(expires.max(1) as i64, None)
+                .await
+                .map(|lease| PutOptions::new().with_lease(lease.id()))
+                .map_err(into_error)
+        } else {
+            Ok(PutOptions::new())
+        }
+    }
+
+    fn evict(&self, key: &[u8]) {
+        if let Some(cache) = &self.cache {
+            cache.evict([key]);
+        }
+    }
+}
+
+fn expires_at(expires: Option<u64>) -> u64 {
+    expires.map_or(NEVER_EXPIRES, |expires| now() + expires)
+}
+
+fn serialize_value(expires_at: u64, value: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(U64_LEN + value.len());
+    result.extend_from_slice(&expires_at.to_be_bytes());
+    result.extend_from_slice(value);
+    result
+}
+
+fn deserialize_value(bytes: &[u8]) -> trc::Result<Option<Vec<u8>>> {
+    let expires = bytes.deserialize_be_u64(0)?;
+    if expires == NEVER_EXPIRES || expires > now() {
+        Ok(Some(bytes[U64_LEN..].to_vec()))
+    } else {
+        Ok(None)
+    }
+}
+
+fn deserialize_counter(key: &[u8], bytes: &[u8]) -> trc::Result<i64> {
+    bytes
+        .try_into()
+        .map(i64::from_be_bytes)
+        .map_err(|_| trc::Error::corrupted_key(key, bytes.into(), trc::location!()))
+}
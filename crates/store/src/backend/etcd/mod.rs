@@ -0,0 +1,156 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, AtomicU64},
+        Arc,
+    },
+    time::Duration,
+};
+
+use etcd_client::{Certificate, Client, ConnectOptions, Identity, TlsOptions};
+use tokio::sync::oneshot;
+use utils::{
+    config::{utils::AsKey, Config},
+    lru_cache::{LruCache, LruCached},
+};
+
+pub mod lookup;
+pub mod watch;
+
+pub struct EtcdStore {
+    client: Client,
+    prefix: Vec<u8>,
+    max_retries: usize,
+    cache: Option<Arc<EtcdCache>>,
+    _watch_stop: Option<oneshot::Sender<()>>,
+}
+
+/// Local copy of recently read keys, kept up to date by watching the key prefix.
+struct EtcdCache {
+    entries: LruCache<Vec<u8>, Option<Vec<u8>>>,
+    generation: AtomicU64,
+    active: AtomicBool,
+}
+
+impl EtcdStore {
+    pub async fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let endpoints = config
+            .values((&prefix, "endpoints"))
+            .map(|(_, v)| v.to_string())
+            .collect::<Vec<_>>();
+        if endpoints.is_empty() {
+            config.new_build_error((&prefix, "endpoints"), "No etcd endpoints specified");
+            return None;
+        }
+
+        let mut options = ConnectOptions::new()
+            .with_timeout(
+                config
+                    .property_or_default::<Duration>((&prefix, "timeout"), "10s")
+                    .unwrap_or_else(|| Duration::from_secs(10)),
+            )
+            .with_connect_timeout(
+                config
+                    .property_or_default::<Duration>((&prefix, "connect-timeout"), "10s")
+                    .unwrap_or_else(|| Duration::from_secs(10)),
+            );
+        if let Some(user) = config.value((&prefix, "user")).map(|s| s.to_string()) {
+            let password = config
+                .value((&prefix, "password"))
+                .unwrap_or_default()
+                .to_string();
+            options = options.with_user(user, password);
+        }
+        if config
+            .property_or_default::<bool>((&prefix, "tls.enable"), "false")
+            .unwrap_or_default()
+        {
+            let mut tls = TlsOptions::new();
+            if let Some(ca_cert) = config.value((&prefix, "tls.ca-cert")) {
+                tls = tls.ca_certificate(Certificate::from_pem(ca_cert));
+            }
+            match (
+                config.value((&prefix, "tls.client-cert")),
+                config.value((&prefix, "tls.client-key")),
+            ) {
+                (Some(cert), Some(key)) => {
+                    tls = tls.identity(Identity::from_pem(cert, key));
+                }
+                (None, None) => {}
+                _ => {
+                    config.new_build_error(
+                        (&prefix, "tls"),
+                        "Both a client certificate and a private key are required for mutual TLS",
+                    );
+                }
+            }
+            options = options.with_tls(tls);
+        }
+
+        let client = Client::connect(endpoints, Some(options))
+            .await
+            .map_err(|err| {
+                config.new_build_error(prefix.as_str(), format!("Failed to connect to etcd: {err}"))
+            })
+            .ok()?;
+        let cache_size = config
+            .property_or_default::<usize>((&prefix, "cache.size"), "0")
+            .unwrap_or_default();
+
+        let mut store = Self {
+            client,
+            prefix: config
+                .value((&prefix, "key-prefix"))
+                .unwrap_or_default()
+                .as_bytes()
+                .to_vec(),
+            max_retries: config
+                .property_or_default((&prefix, "transaction.max-retries"), "10")
+                .unwrap_or(10),
+            cache: (cache_size > 0).then(|| {
+                Arc::new(EtcdCache {
+                    entries: LruCache::with_capacity(cache_size),
+                    generation: AtomicU64::new(0),
+                    active: AtomicBool::new(false),
+                })
+            }),
+            _watch_stop: None,
+        };
+
+        store._watch_stop = store.cache.clone().map(|cache| store.spawn_watcher(cache));
+
+        Some(store)
+    }
+
+    fn build_key(&self, key: Vec<u8>) -> Vec<u8> {
+        if !self.prefix.is_empty() {
+            let mut result = Vec::with_capacity(self.prefix.len() + key.len());
+            result.extend_from_slice(&self.prefix);
+            result.extend_from_slice(&key);
+            result
+        } else {
+            key
+        }
+    }
+}
+
+#[inline(always)]
+fn into_error(err: impl Display) -> trc::Error {
+    trc::StoreEvent::EtcdError.reason(err)
+}
+
+impl std::fmt::Debug for EtcdStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EtcdStore")
+            .field("prefix", &String::from_utf8_lossy(&self.prefix))
+            .field("cache", &self.cache.is_some())
+            .finish()
+    }
+}
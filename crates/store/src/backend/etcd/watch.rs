@@ -0,0 +1,107 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use etcd_client::WatchOptions;
+use futures::future::{select, Either};
+use tokio::sync::oneshot;
+
+use super::{into_error, EtcdCache, EtcdStore};
+
+const WATCH_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+impl EtcdStore {
+    /// Watches the key prefix and evicts cached keys as soon as they are
+    /// changed, deleted or expired by any node. The watcher stops when the
+    /// returned sender is dropped.
+    pub(super) fn spawn_watcher(&self, cache: Arc<EtcdCache>) -> oneshot::Sender<()> {
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+        let mut client = self.client.watch_client();
+        let prefix = self.prefix.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match client
+                    .watch(prefix.clone(), Some(WatchOptions::new().with_prefix()))
+                    .await
+                {
+                    Ok((_watcher, mut stream)) => {
+                        // Changes made before the watch started are unknown
+                        cache.clear();
+                        cache.active.store(true, Ordering::Relaxed);
+
+                        loop {
+                            match select(&mut stop_rx, Box::pin(stream.message())).await {
+                                Either::Left(_) => return,
+                                Either::Right((Ok(Some(response)), _)) => {
+                                    if response.canceled() {
+                                        break;
+                                    }
+                                    cache.evict(
+                                        response
+                                            .events()
+                                            .iter()
+                                            .filter_map(|event| event.kv())
+                                            .map(|kv| kv.key()),
+                                    );
+                                }
+                                Either::Right((Ok(None), _)) => break,
+                                Either::Right((Err(err), _)) => {
+                                    trc::error!(into_error(err)
+                                        .details("etcd watch failed")
+                                        .caused_by(trc::location!()));
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        trc::error!(into_error(err)
+                            .details("Failed to watch etcd keys")
+                            .caused_by(trc::location!()));
+                    }
+                }
+
+                // Nothing can be cached while the watch is down
+                cache.active.store(false, Ordering::Relaxed);
+                cache.clear();
+                match select(
+                    &mut stop_rx,
+                    Box::pin(tokio::time::sleep(WATCH_RETRY_INTERVAL)),
+                )
+                .await
+                {
+                    Either::Left(_) => return,
+                    Either::Right(_) => {}
+                }
+            }
+        });
+
+        stop_tx
+    }
+}
+
+impl EtcdCache {
+    // The generation is increased while holding the lock, which lets readers
+    // detect whether an entry was evicted while they were fetching it.
+    pub(super) fn evict<'x>(&self, keys: impl IntoIterator<Item = &'x [u8]>) {
+        let mut entries = self.entries.lock();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        for key in keys {
+            entries.remove(key);
+        }
+    }
+
+    pub(super) fn clear(&self) {
+        let mut entries = self.entries.lock();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        entries.clear();
+    }
+}
@@ -12,7 +12,7 @@ use utils::BLOB_HASH_LEN;
 
 use crate::{backend::foundationdb::into_error, write::key::KeySerializer, SUBSPACE_BLOBS};
 
-use super::{FdbStore, MAX_VALUE_SIZE};
+use super::{FdbStore, TransactionClass, MAX_VALUE_SIZE};
 
 impl FdbStore {
     pub(crate) async fn get_blob(
@@ -35,7 +35,7 @@ impl FdbStore {
             .write(block_end as u16)
             .finalize();
         let key_len = begin.len();
-        let trx = self.read_trx().await?;
+        let trx = self.read_trx(TransactionClass::Read).await?;
         let mut values = trx.get_ranges_keyvalues(
             RangeOption {
                 begin: KeySelector::first_greater_or_equal(begin),
@@ -103,7 +103,7 @@ impl FdbStore {
                 },
             1,
         ) - 1;
-        let mut trx = self.db.create_trx().map_err(into_error)?;
+        let mut trx = self.create_trx(TransactionClass::Write)?;
 
         for (chunk_pos, chunk_bytes) in data.chunks(MAX_VALUE_SIZE).enumerate() {
             trx.set(
@@ -117,7 +117,7 @@ impl FdbStore {
             if chunk_pos == last_chunk || (chunk_pos > 0 && chunk_pos % N_CHUNKS == 0) {
                 self.commit(trx, false).await?;
                 if chunk_pos < last_chunk {
-                    trx = self.db.create_trx().map_err(into_error)?;
+                    trx = self.create_trx(TransactionClass::Write)?;
                 } else {
                     break;
                 }
@@ -132,7 +132,7 @@ impl FdbStore {
             return Ok(false);
         }
 
-        let trx = self.db.create_trx().map_err(into_error)?;
+        let trx = self.create_trx(TransactionClass::Write)?;
         trx.clear_range(
             &KeySerializer::new(key.len() + 3)
                 .write(SUBSPACE_BLOBS)
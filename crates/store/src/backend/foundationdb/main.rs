@@ -7,9 +7,15 @@
 use std::time::Duration;
 
 use foundationdb::{api, options::DatabaseOption, Database};
-use utils::config::{utils::AsKey, Config};
+use utils::config::{
+    utils::{AsKey, ParseValue},
+    Config,
+};
 
-use super::FdbStore;
+use super::{
+    salt::{KeySalt, DEFAULT_SALTED_SUBSPACES, UNSALTABLE_SUBSPACES},
+    FdbStore, TransactionClass, TransactionPriority, TransactionSettings, TRANSACTION_TIMEOUT,
+};
 
 impl FdbStore {
     pub async fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
@@ -102,10 +108,108 @@ impl FdbStore {
                 .ok()?;
         }
 
-        Some(Self {
+        // Parse transaction options for each operation class
+        let mut transactions = TransactionClass::ALL.map(TransactionSettings::default_for);
+        for class in TransactionClass::ALL {
+            let settings = &mut transactions[class as usize];
+            if let Some(priority) =
+                config.property((prefix.as_str(), "transaction", class.as_str(), "priority"))
+            {
+                settings.priority = priority;
+            }
+            if !class.is_read_only() {
+                continue;
+            }
+            if let Some(causal_read_risky) = config.property((
+                prefix.as_str(),
+                "transaction",
+                class.as_str(),
+                "causal-read-risky",
+            )) {
+                settings.causal_read_risky = causal_read_risky;
+            }
+            let key = (
+                prefix.as_str(),
+                "transaction",
+                class.as_str(),
+                "read-version-cache",
+            );
+            match config.property::<Option<Duration>>(key) {
+                Some(Some(ttl)) if ttl >= TRANSACTION_TIMEOUT => {
+                    config.new_parse_error(
+                        key,
+                        format!(
+                            "Read versions can be cached for less than {}ms",
+                            TRANSACTION_TIMEOUT.as_millis()
+                        ),
+                    );
+                }
+                Some(ttl) => {
+                    settings.read_version_ttl = ttl;
+                }
+                None => (),
+            }
+        }
+
+        // Parse key salting
+        let buckets = config
+            .property_or_default::<u16>((&prefix, "key-salt.buckets"), "0")
+            .unwrap_or_default();
+        let mut salt = KeySalt {
+            buckets: u8::try_from(buckets).unwrap_or_else(|_| {
+                config.new_parse_error(
+                    (&prefix, "key-salt.buckets"),
+                    "Key salting supports up to 255 buckets",
+                );
+                0
+            }),
+            subspaces: Vec::new(),
+        };
+        let mut invalid_subspaces = Vec::new();
+        for (key, value) in config.values((&prefix, "key-salt.subspaces")) {
+            match value.as_bytes() {
+                [subspace]
+                    if subspace.is_ascii_lowercase()
+                        && !UNSALTABLE_SUBSPACES.contains(subspace) =>
+                {
+                    salt.subspaces.push(*subspace);
+                }
+                _ => {
+                    invalid_subspaces.push((key.to_string(), value.to_string()));
+                }
+            }
+        }
+        for (key, value) in invalid_subspaces {
+            config.new_parse_error(key, format!("Subspace {value:?} cannot be salted"));
+        }
+        if salt.subspaces.is_empty() {
+            salt.subspaces = DEFAULT_SALTED_SUBSPACES.to_vec();
+        }
+
+        let store = Self {
             guard,
             db,
             version: Default::default(),
-        })
+            transactions,
+            salt,
+        };
+
+        if let Err(err) = store.check_key_salt().await {
+            config.new_build_error((&prefix, "key-salt"), err);
+            return None;
+        }
+
+        Some(store)
+    }
+}
+
+impl ParseValue for TransactionPriority {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "default" | "normal" => Ok(TransactionPriority::Default),
+            "batch" | "low" => Ok(TransactionPriority::Batch),
+            "immediate" | "high" => Ok(TransactionPriority::Immediate),
+            priority => Err(format!("Invalid transaction priority: {priority}")),
+        }
     }
 }
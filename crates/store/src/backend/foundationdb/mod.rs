@@ -6,11 +6,15 @@
 
 use std::time::{Duration, Instant};
 
-use foundationdb::{api::NetworkAutoStop, Database, FdbError, Transaction};
+use foundationdb::{
+    api::NetworkAutoStop, options::TransactionOption, Database, FdbError, Transaction,
+};
+use salt::KeySalt;
 
 pub mod blob;
 pub mod main;
 pub mod read;
+pub mod salt;
 pub mod write;
 
 const MAX_VALUE_SIZE: usize = 100000;
@@ -22,6 +26,8 @@ pub struct FdbStore {
     db: Database,
     guard: NetworkAutoStop,
     version: parking_lot::Mutex<ReadVersion>,
+    transactions: [TransactionSettings; TransactionClass::COUNT],
+    salt: KeySalt,
 }
 
 pub(crate) struct TimedTransaction {
@@ -31,19 +37,83 @@ pub(crate) struct TimedTransaction {
 
 pub(crate) struct ReadVersion {
     version: i64,
-    expires: Instant,
+    obtained: Instant,
+}
+
+/// Groups of operations that share the same transaction options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransactionClass {
+    /// Point reads such as values, bitmaps, counters and blobs.
+    Read,
+    /// Range scans that may span multiple transactions.
+    Scan,
+    /// Regular writes.
+    Write,
+    /// Writes to the full-text index.
+    Index,
+    /// Purges of expired or deleted data.
+    Purge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TransactionSettings {
+    pub priority: TransactionPriority,
+    pub causal_read_risky: bool,
+    pub read_version_ttl: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransactionPriority {
+    Default,
+    Batch,
+    Immediate,
+}
+
+impl TransactionClass {
+    pub const COUNT: usize = 5;
+    pub const ALL: [TransactionClass; TransactionClass::COUNT] = [
+        TransactionClass::Read,
+        TransactionClass::Scan,
+        TransactionClass::Write,
+        TransactionClass::Index,
+        TransactionClass::Purge,
+    ];
+
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, TransactionClass::Read | TransactionClass::Scan)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionClass::Read => "read",
+            TransactionClass::Scan => "scan",
+            TransactionClass::Write => "write",
+            TransactionClass::Index => "index",
+            TransactionClass::Purge => "purge",
+        }
+    }
+}
+
+impl TransactionSettings {
+    pub fn default_for(class: TransactionClass) -> Self {
+        TransactionSettings {
+            priority: TransactionPriority::Default,
+            causal_read_risky: false,
+            read_version_ttl: (class == TransactionClass::Read).then_some(TRANSACTION_EXPIRY),
+        }
+    }
 }
 
 impl ReadVersion {
     pub fn new(version: i64) -> Self {
         Self {
             version,
-            expires: Instant::now() + TRANSACTION_EXPIRY,
+            obtained: Instant::now(),
         }
     }
 
-    pub fn is_expired(&self) -> bool {
-        self.expires < Instant::now()
+    pub fn is_valid(&self, ttl: Duration) -> bool {
+        self.version > 0 && self.obtained.elapsed() < ttl
     }
 }
 
@@ -51,7 +121,7 @@ impl Default for ReadVersion {
     fn default() -> Self {
         Self {
             version: 0,
-            expires: Instant::now(),
+            obtained: Instant::now(),
         }
     }
 }
@@ -63,10 +133,10 @@ impl AsRef<Transaction> for TimedTransaction {
 }
 
 impl TimedTransaction {
-    pub fn new(trx: Transaction) -> Self {
+    pub fn new(trx: Transaction, started: Instant) -> Self {
         Self {
             trx,
-            expires: Instant::now() + TRANSACTION_TIMEOUT,
+            expires: started + TRANSACTION_TIMEOUT,
         }
     }
 
@@ -75,6 +145,31 @@ impl TimedTransaction {
     }
 }
 
+impl FdbStore {
+    pub(crate) fn create_trx(&self, class: TransactionClass) -> trc::Result<Transaction> {
+        let settings = &self.transactions[class as usize];
+        let trx = self.db.create_trx().map_err(into_error)?;
+
+        match settings.priority {
+            TransactionPriority::Default => (),
+            TransactionPriority::Batch => {
+                trx.set_option(TransactionOption::PriorityBatch)
+                    .map_err(into_error)?;
+            }
+            TransactionPriority::Immediate => {
+                trx.set_option(TransactionOption::PrioritySystemImmediate)
+                    .map_err(into_error)?;
+            }
+        }
+        if settings.causal_read_risky {
+            trx.set_option(TransactionOption::CausalReadRisky)
+                .map_err(into_error)?;
+        }
+
+        Ok(trx)
+    }
+}
+
 #[inline(always)]
 fn into_error(error: FdbError) -> trc::Error {
     trc::StoreEvent::FoundationdbError
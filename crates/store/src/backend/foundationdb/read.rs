@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::time::Instant;
+
 use foundationdb::{
     future::FdbSlice,
     options::{self, StreamingMode},
@@ -21,7 +23,9 @@ use crate::{
     BitmapKey, Deserialize, IterateParams, Key, ValueKey, U32_LEN, WITH_SUBSPACE,
 };
 
-use super::{into_error, FdbStore, ReadVersion, TimedTransaction, MAX_VALUE_SIZE};
+use super::{
+    into_error, FdbStore, ReadVersion, TimedTransaction, TransactionClass, MAX_VALUE_SIZE,
+};
 
 #[allow(dead_code)]
 pub(crate) enum ChunkedValue {
@@ -35,8 +39,8 @@ impl FdbStore {
     where
         U: Deserialize,
     {
-        let key = key.serialize(WITH_SUBSPACE);
-        let trx = self.read_trx().await?;
+        let key = self.salt.salt(key.serialize(WITH_SUBSPACE));
+        let trx = self.read_trx(TransactionClass::Read).await?;
 
         match read_chunked_value(&key, &trx, true).await? {
             ChunkedValue::Single(bytes) => U::deserialize(&bytes).map(Some),
@@ -54,7 +58,7 @@ impl FdbStore {
         key.document_id = u32::MAX;
         let end = key.serialize(WITH_SUBSPACE);
         let key_len = begin.len();
-        let trx = self.read_trx().await?;
+        let trx = self.read_trx(TransactionClass::Read).await?;
         let mut values = trx.get_ranges_keyvalues(
             RangeOption {
                 begin: KeySelector::first_greater_or_equal(begin),
@@ -84,6 +88,12 @@ impl FdbStore {
         let mut begin = params.begin.serialize(WITH_SUBSPACE);
         let end = params.end.serialize(WITH_SUBSPACE);
 
+        if self.salt.is_salted(begin[0]) {
+            return self
+                .iterate_salted(begin, end, params.ascending, params.first, cb)
+                .await;
+        }
+
         if !params.first {
             let mut begin_selector = KeySelector::first_greater_or_equal(&begin);
 
@@ -128,7 +138,7 @@ impl FdbStore {
                 }
             }
         } else {
-            let trx = self.read_trx().await?;
+            let trx = self.read_trx(TransactionClass::Read).await?;
             let mut values = trx.get_ranges_keyvalues(
                 RangeOption {
                     begin: KeySelector::first_greater_or_equal(&begin),
@@ -152,9 +162,9 @@ impl FdbStore {
         &self,
         key: impl Into<ValueKey<ValueClass<u32>>> + Sync + Send,
     ) -> trc::Result<i64> {
-        let key = key.into().serialize(WITH_SUBSPACE);
+        let key = self.salt.salt(key.into().serialize(WITH_SUBSPACE));
         if let Some(bytes) = self
-            .read_trx()
+            .read_trx(TransactionClass::Read)
             .await?
             .get(&key, true)
             .await
@@ -166,28 +176,40 @@ impl FdbStore {
         }
     }
 
-    pub(crate) async fn read_trx(&self) -> trc::Result<Transaction> {
-        let (is_expired, mut read_version) = {
-            let version = self.version.lock();
-            (version.is_expired(), version.version)
-        };
-        let trx = self.db.create_trx().map_err(into_error)?;
-
-        if is_expired {
-            read_version = trx.get_read_version().await.map_err(into_error)?;
-            *self.version.lock() = ReadVersion::new(read_version);
-        } else {
-            trx.set_read_version(read_version);
-        }
-
-        Ok(trx)
+    pub(crate) async fn read_trx(&self, class: TransactionClass) -> trc::Result<Transaction> {
+        self.versioned_trx(class).await.map(|(trx, _)| trx)
     }
 
     pub(crate) async fn timed_read_trx(&self) -> trc::Result<TimedTransaction> {
-        self.db
-            .create_trx()
-            .map_err(into_error)
-            .map(TimedTransaction::new)
+        self.versioned_trx(TransactionClass::Scan)
+            .await
+            .map(|(trx, started)| TimedTransaction::new(trx, started))
+    }
+
+    async fn versioned_trx(&self, class: TransactionClass) -> trc::Result<(Transaction, Instant)> {
+        let trx = self.create_trx(class)?;
+
+        if let Some(ttl) = self.transactions[class as usize].read_version_ttl {
+            let cached_version = {
+                let version = self.version.lock();
+                version
+                    .is_valid(ttl)
+                    .then_some((version.version, version.obtained))
+            };
+
+            if let Some((read_version, obtained)) = cached_version {
+                trx.set_read_version(read_version);
+                return Ok((trx, obtained));
+            }
+
+            let read_version = trx.get_read_version().await.map_err(into_error)?;
+            let mut version = self.version.lock();
+            if read_version >= version.version {
+                *version = ReadVersion::new(read_version);
+            }
+        }
+
+        Ok((trx, Instant::now()))
     }
 }
 
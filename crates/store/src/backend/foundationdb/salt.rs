@@ -0,0 +1,225 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::cmp::Ordering;
+
+use foundationdb::{options::StreamingMode, KeySelector, RangeOption};
+use futures::TryStreamExt;
+
+use crate::{
+    SUBSPACE_BITMAP_ID, SUBSPACE_BITMAP_TAG, SUBSPACE_BITMAP_TEXT, SUBSPACE_BLOBS,
+    SUBSPACE_COUNTER, SUBSPACE_INDEXES, SUBSPACE_LOGS, SUBSPACE_PROPERTY, SUBSPACE_QUOTA,
+};
+
+use super::{into_error, FdbStore, TransactionClass};
+
+// Stored outside of all subspaces, records the salting settings the data was written with
+const KEY_SALT_LAYOUT: &[u8] = b"\x00key-salt";
+
+pub(crate) const DEFAULT_SALTED_SUBSPACES: [u8; 3] =
+    [SUBSPACE_LOGS, SUBSPACE_INDEXES, SUBSPACE_PROPERTY];
+
+// Subspaces that are scanned by raw key range inside the backend
+pub(crate) const UNSALTABLE_SUBSPACES: [u8; 6] = [
+    SUBSPACE_BITMAP_ID,
+    SUBSPACE_BITMAP_TAG,
+    SUBSPACE_BITMAP_TEXT,
+    SUBSPACE_COUNTER,
+    SUBSPACE_QUOTA,
+    SUBSPACE_BLOBS,
+];
+
+/// Inserts a salt byte after the subspace of the keys in the selected
+/// subspaces, which spreads the keys of a single account across `buckets`
+/// shards instead of one contiguous range.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct KeySalt {
+    pub buckets: u8,
+    pub subspaces: Vec<u8>,
+}
+
+impl KeySalt {
+    #[inline(always)]
+    pub fn is_salted(&self, subspace: u8) -> bool {
+        self.buckets > 1 && self.subspaces.contains(&subspace)
+    }
+
+    pub fn salt(&self, key: Vec<u8>) -> Vec<u8> {
+        match key.split_first() {
+            Some((subspace, rest)) if self.is_salted(*subspace) => with_salt(
+                &key,
+                (xxhash_rust::xxh3::xxh3_64(rest) % self.buckets as u64) as u8,
+            ),
+            _ => key,
+        }
+    }
+
+    fn layout(&self) -> Vec<u8> {
+        if self.buckets > 1 {
+            let mut subspaces = self.subspaces.clone();
+            subspaces.sort_unstable();
+            let mut layout = Vec::with_capacity(subspaces.len() + 1);
+            layout.push(self.buckets);
+            layout.extend(subspaces);
+            layout
+        } else {
+            vec![]
+        }
+    }
+}
+
+pub(crate) fn with_salt(key: &[u8], salt: u8) -> Vec<u8> {
+    let mut salted = Vec::with_capacity(key.len() + 1);
+    salted.push(key[0]);
+    salted.push(salt);
+    salted.extend_from_slice(&key[1..]);
+    salted
+}
+
+fn without_salt(key: &[u8]) -> Vec<u8> {
+    let mut unsalted = Vec::with_capacity(key.len().saturating_sub(1));
+    unsalted.extend_from_slice(key.get(..1).unwrap_or_default());
+    unsalted.extend_from_slice(key.get(2..).unwrap_or_default());
+    unsalted
+}
+
+impl FdbStore {
+    pub(crate) async fn iterate_salted(
+        &self,
+        begin: Vec<u8>,
+        end: Vec<u8>,
+        ascending: bool,
+        first: bool,
+        mut cb: impl for<'x> FnMut(&'x [u8], &'x [u8]) -> trc::Result<bool> + Sync + Send,
+    ) -> trc::Result<()> {
+        let mut resume_from: Option<Vec<u8>> = None;
+
+        loop {
+            let trx = self.timed_read_trx().await?;
+            let mut buckets = Vec::with_capacity(self.salt.buckets as usize);
+
+            // Open one range per bucket, resuming after the last returned key
+            for salt in 0..self.salt.buckets {
+                let (begin, end) = match (&resume_from, ascending) {
+                    (None, _) => (
+                        KeySelector::first_greater_or_equal(with_salt(&begin, salt)),
+                        KeySelector::first_greater_than(with_salt(&end, salt)),
+                    ),
+                    (Some(last), true) => (
+                        KeySelector::first_greater_than(with_salt(last, salt)),
+                        KeySelector::first_greater_than(with_salt(&end, salt)),
+                    ),
+                    (Some(last), false) => (
+                        KeySelector::first_greater_or_equal(with_salt(&begin, salt)),
+                        KeySelector::first_greater_or_equal(with_salt(last, salt)),
+                    ),
+                };
+                let mut values = trx.as_ref().get_ranges_keyvalues(
+                    RangeOption {
+                        begin,
+                        end,
+                        mode: if first {
+                            StreamingMode::Small
+                        } else {
+                            StreamingMode::WantAll
+                        },
+                        reverse: !ascending,
+                        ..Default::default()
+                    },
+                    true,
+                );
+                let head = values.try_next().await.map_err(into_error)?;
+                buckets.push((values, head));
+            }
+
+            // Merge the buckets back into key order
+            loop {
+                let next = buckets
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, (_, head))| {
+                        head.as_ref()
+                            .map(|value| (idx, value.key().get(2..).unwrap_or_default()))
+                    })
+                    .reduce(|a, b| match (a.1.cmp(b.1), ascending) {
+                        (Ordering::Greater, true) | (Ordering::Less, false) => b,
+                        _ => a,
+                    })
+                    .map(|(idx, _)| idx);
+                let Some(idx) = next else {
+                    return Ok(());
+                };
+
+                let (values, head) = &mut buckets[idx];
+                let value = head.take().unwrap();
+                let key = value.key();
+                if !cb(key.get(2..).unwrap_or_default(), value.value())? || first {
+                    return Ok(());
+                }
+                *head = values.try_next().await.map_err(into_error)?;
+
+                if trx.is_expired() && buckets.iter().any(|(_, head)| head.is_some()) {
+                    resume_from = Some(without_salt(key));
+                    break;
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn check_key_salt(&self) -> Result<(), String> {
+        let layout = self.salt.layout();
+        let trx = self
+            .create_trx(TransactionClass::Write)
+            .map_err(|err| err.to_string())?;
+        let stored = trx
+            .get(KEY_SALT_LAYOUT, false)
+            .await
+            .map_err(|err| err.message().to_string())?;
+
+        match stored {
+            Some(stored) if stored.as_ref() == layout.as_slice() => Ok(()),
+            Some(_) => Err(concat!(
+                "Key salting settings differ from the ones used to write the existing data, ",
+                "export and re-import the data to change them"
+            )
+            .to_string()),
+            None if layout.is_empty() => Ok(()),
+            None => {
+                // Data written before salting was enabled has to be migrated first
+                for subspace in &self.salt.subspaces {
+                    let values = trx
+                        .get_range(
+                            &RangeOption {
+                                begin: KeySelector::first_greater_or_equal(vec![*subspace]),
+                                end: KeySelector::first_greater_or_equal(vec![*subspace + 1]),
+                                limit: Some(1),
+                                ..Default::default()
+                            },
+                            0,
+                            true,
+                        )
+                        .await
+                        .map_err(|err| err.message().to_string())?;
+                    if !values.is_empty() {
+                        return Err(format!(
+                            concat!(
+                                "Subspace {:?} contains unsalted data, ",
+                                "export and re-import the data to enable key salting"
+                            ),
+                            char::from(*subspace)
+                        ));
+                    }
+                }
+
+                trx.set(KEY_SALT_LAYOUT, &layout);
+                self.commit(trx, false)
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            }
+        }
+    }
+}
@@ -21,7 +21,7 @@ use crate::{
     backend::deserialize_i64_le,
     write::{
         key::{DeserializeBigEndian, KeySerializer},
-        AssignedIds, Batch, BitmapClass, Operation, RandomAvailableId, ValueOp,
+        AssignedIds, Batch, BitmapClass, Operation, RandomAvailableId, ValueClass, ValueOp,
         MAX_COMMIT_ATTEMPTS, MAX_COMMIT_TIME,
     },
     BitmapKey, IndexKey, Key, LogKey, SUBSPACE_COUNTER, SUBSPACE_QUOTA, U32_LEN, WITH_SUBSPACE,
@@ -30,13 +30,27 @@ use crate::{
 use super::{
     into_error,
     read::{read_chunked_value, ChunkedValue},
-    FdbStore, ReadVersion, MAX_VALUE_SIZE,
+    salt::with_salt,
+    FdbStore, ReadVersion, TransactionClass, MAX_VALUE_SIZE,
 };
 
 impl FdbStore {
     pub(crate) async fn write(&self, batch: Batch) -> trc::Result<AssignedIds> {
         let start = Instant::now();
         let mut retry_count = 0;
+        let trx_class = if batch.ops.iter().any(|op| {
+            matches!(
+                op,
+                Operation::Value {
+                    class: ValueClass::FtsIndex(_),
+                    ..
+                }
+            )
+        }) {
+            TransactionClass::Index
+        } else {
+            TransactionClass::Write
+        };
 
         loop {
             let mut account_id = u32::MAX;
@@ -45,7 +59,7 @@ impl FdbStore {
             let mut change_id = u64::MAX;
             let mut result = AssignedIds::default();
 
-            let trx = self.db.create_trx().map_err(into_error)?;
+            let trx = self.create_trx(trx_class)?;
 
             for op in &batch.ops {
                 match op {
@@ -70,13 +84,13 @@ impl FdbStore {
                         change_id = *change_id_;
                     }
                     Operation::Value { class, op } => {
-                        let mut key = class.serialize(
+                        let mut key = self.salt.salt(class.serialize(
                             account_id,
                             collection,
                             document_id,
                             WITH_SUBSPACE,
                             (&result).into(),
-                        );
+                        ));
                         let do_chunk = !class.is_counter(collection);
 
                         match op {
@@ -138,14 +152,16 @@ impl FdbStore {
                         }
                     }
                     Operation::Index { field, key, set } => {
-                        let key = IndexKey {
-                            account_id,
-                            collection,
-                            document_id,
-                            field: *field,
-                            key,
-                        }
-                        .serialize(WITH_SUBSPACE);
+                        let key = self.salt.salt(
+                            IndexKey {
+                                account_id,
+                                collection,
+                                document_id,
+                                field: *field,
+                                key,
+                            }
+                            .serialize(WITH_SUBSPACE),
+                        );
 
                         if *set {
                             trx.set(&key, &[]);
@@ -227,25 +243,27 @@ impl FdbStore {
                         }
                     }
                     Operation::Log { set } => {
-                        let key = LogKey {
-                            account_id,
-                            collection,
-                            change_id,
-                        }
-                        .serialize(WITH_SUBSPACE);
+                        let key = self.salt.salt(
+                            LogKey {
+                                account_id,
+                                collection,
+                                change_id,
+                            }
+                            .serialize(WITH_SUBSPACE),
+                        );
                         trx.set(&key, set.resolve(&result)?.as_ref());
                     }
                     Operation::AssertValue {
                         class,
                         assert_value,
                     } => {
-                        let key = class.serialize(
+                        let key = self.salt.salt(class.serialize(
                             account_id,
                             collection,
                             document_id,
                             WITH_SUBSPACE,
                             (&result).into(),
-                        );
+                        ));
 
                         let matches = match read_chunked_value(&key, &trx, false).await {
                             Ok(ChunkedValue::Single(bytes)) => assert_value.matches(bytes.as_ref()),
@@ -305,7 +323,7 @@ impl FdbStore {
         // Obtain all zero counters
         let mut delete_keys = Vec::new();
         for subspace in [SUBSPACE_COUNTER, SUBSPACE_QUOTA] {
-            let trx = self.db.create_trx().map_err(into_error)?;
+            let trx = self.create_trx(TransactionClass::Purge)?;
             let from_key = [subspace, 0u8];
             let to_key = [subspace, u8::MAX, u8::MAX, u8::MAX, u8::MAX, u8::MAX];
 
@@ -336,7 +354,7 @@ impl FdbStore {
         for chunk in delete_keys.chunks(1024) {
             let mut retry_count = 0;
             loop {
-                let trx = self.db.create_trx().map_err(into_error)?;
+                let trx = self.create_trx(TransactionClass::Purge)?;
                 for key in chunk {
                     trx.atomic_op(key, &integer, MutationType::CompareAndClear);
                 }
@@ -356,8 +374,14 @@ impl FdbStore {
         let from = from.serialize(WITH_SUBSPACE);
         let to = to.serialize(WITH_SUBSPACE);
 
-        let trx = self.db.create_trx().map_err(into_error)?;
-        trx.clear_range(&from, &to);
+        let trx = self.create_trx(TransactionClass::Purge)?;
+        if self.salt.is_salted(from[0]) {
+            for salt in 0..self.salt.buckets {
+                trx.clear_range(&with_salt(&from, salt), &with_salt(&to, salt));
+            }
+        } else {
+            trx.clear_range(&from, &to);
+        }
         self.commit(trx, false).await.map(|_| ())
     }
 }
@@ -0,0 +1,219 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fmt::Display, future::Future, io::Write, ops::Range, time::Duration};
+
+use google_cloud_storage::{
+    client::{google_cloud_auth::credentials::CredentialsFile, Client, ClientConfig},
+    http::{
+        objects::{
+            delete::DeleteObjectRequest,
+            download::Range as DownloadRange,
+            get::GetObjectRequest,
+            upload::{Media, UploadObjectRequest, UploadType},
+        },
+        Error,
+    },
+};
+use rand::Rng;
+use utils::{
+    codec::base32_custom::Base32Writer,
+    config::{utils::AsKey, Config},
+};
+
+pub struct GcsStore {
+    client: Client,
+    bucket: String,
+    prefix: Option<String>,
+    max_retries: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl GcsStore {
+    pub async fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let bucket = config.value_require((&prefix, "bucket"))?.to_string();
+
+        // Without explicit credentials, Application Default Credentials are used,
+        // which includes the metadata server used by GKE workload identity.
+        let credentials_file = config
+            .value((&prefix, "credentials-file"))
+            .map(|s| s.to_string());
+        let credentials = config
+            .value((&prefix, "credentials"))
+            .map(|s| s.to_string());
+        let client_config = if config
+            .property_or_default::<bool>((&prefix, "anonymous"), "false")
+            .unwrap_or_default()
+        {
+            Ok(ClientConfig::default().anonymous())
+        } else {
+            match (credentials_file, credentials) {
+                (Some(path), None) => match CredentialsFile::new_from_file(path).await {
+                    Ok(credentials) => ClientConfig::default().with_credentials(credentials).await,
+                    Err(err) => Err(err),
+                },
+                (None, Some(json)) => match CredentialsFile::new_from_str(&json).await {
+                    Ok(credentials) => ClientConfig::default().with_credentials(credentials).await,
+                    Err(err) => Err(err),
+                },
+                (None, None) => ClientConfig::default().with_auth().await,
+                (Some(_), Some(_)) => {
+                    config.new_build_error(
+                        prefix.as_str(),
+                        concat!(
+                            "Failed to create credentials: only one of ",
+                            "'credentials-file' and 'credentials' can be specified"
+                        ),
+                    );
+                    return None;
+                }
+            }
+        };
+        let mut client_config = client_config
+            .map_err(|err| {
+                config.new_build_error(
+                    prefix.as_str(),
+                    format!("Failed to create credentials: {err}"),
+                )
+            })
+            .ok()?;
+        if let Some(endpoint) = config.value((&prefix, "endpoint")) {
+            client_config.storage_endpoint = endpoint.trim_end_matches('/').to_string();
+        }
+
+        // Google recommends truncated exponential backoff starting at one second
+        // and doubling up to 32 seconds.
+        Some(GcsStore {
+            client: Client::new(client_config),
+            bucket,
+            prefix: config.value((&prefix, "key-prefix")).map(|s| s.to_string()),
+            max_retries: config
+                .property_or_default((&prefix, "max-retries"), "3")
+                .unwrap_or(3),
+            initial_delay: config
+                .property_or_default((&prefix, "retry.initial-delay"), "1s")
+                .unwrap_or_else(|| Duration::from_secs(1)),
+            max_delay: config
+                .property_or_default((&prefix, "retry.max-delay"), "32s")
+                .unwrap_or_else(|| Duration::from_secs(32)),
+        })
+    }
+
+    pub(crate) async fn get_blob(
+        &self,
+        key: &[u8],
+        range: Range<usize>,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        let request = &GetObjectRequest {
+            bucket: self.bucket.clone(),
+            object: self.build_key(key),
+            ..Default::default()
+        };
+        let range = &DownloadRange(
+            (range.start != 0).then_some(range.start as u64),
+            (range.end != usize::MAX).then(|| range.end.saturating_sub(1) as u64),
+        );
+
+        match self
+            .with_retries(|| self.client.download_object(request, range))
+            .await
+        {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if status_code(&err) == Some(404) => Ok(None),
+            Err(err) => Err(into_error(err)),
+        }
+    }
+
+    pub(crate) async fn put_blob(&self, key: &[u8], data: &[u8]) -> trc::Result<()> {
+        let request = &UploadObjectRequest {
+            bucket: self.bucket.clone(),
+            ..Default::default()
+        };
+        let upload_type = &UploadType::Simple(Media::new(self.build_key(key)));
+
+        self.with_retries(|| {
+            self.client
+                .upload_object(request, data.to_vec(), upload_type)
+        })
+        .await
+        .map(|_| ())
+        .map_err(into_error)
+    }
+
+    pub(crate) async fn delete_blob(&self, key: &[u8]) -> trc::Result<bool> {
+        let request = &DeleteObjectRequest {
+            bucket: self.bucket.clone(),
+            object: self.build_key(key),
+            ..Default::default()
+        };
+
+        match self
+            .with_retries(|| self.client.delete_object(request))
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(err) if status_code(&err) == Some(404) => Ok(false),
+            Err(err) => Err(into_error(err)),
+        }
+    }
+
+    async fn with_retries<T, F, Fut>(&self, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut retry_count = 0;
+
+        loop {
+            match f().await {
+                Err(err) if retry_count < self.max_retries && is_retryable(&err) => {
+                    let delay = self
+                        .initial_delay
+                        .saturating_mul(1 << retry_count.min(16))
+                        .min(self.max_delay);
+                    let jitter = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 4);
+                    tokio::time::sleep(delay + Duration::from_millis(jitter)).await;
+                    retry_count += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn build_key(&self, key: &[u8]) -> String {
+        if let Some(prefix) = &self.prefix {
+            let mut writer =
+                Base32Writer::with_raw_capacity(prefix.len() + ((key.len() + 3) / 4 * 5));
+            writer.push_string(prefix);
+            writer.write_all(key).unwrap();
+            writer.finalize()
+        } else {
+            Base32Writer::from_bytes(key).finalize()
+        }
+    }
+}
+
+fn status_code(err: &Error) -> Option<u16> {
+    match err {
+        Error::Response(response) => Some(response.code),
+        _ => None,
+    }
+}
+
+fn is_retryable(err: &Error) -> bool {
+    match err {
+        Error::Response(response) => matches!(response.code, 408 | 429 | 500..=599),
+        Error::HttpClient(err) => err.is_timeout() || err.is_connect() || err.is_request(),
+        _ => false,
+    }
+}
+
+#[inline(always)]
+fn into_error(err: impl Display) -> trc::Error {
+    trc::StoreEvent::GcsError.reason(err)
+}
@@ -0,0 +1,173 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arc_swap::ArcSwap;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use utils::config::{utils::AsKey, Config};
+
+use crate::Value;
+
+use super::MemoryStore;
+
+/// Lookup list or map read from a local file. When watching is enabled the
+/// file is reloaded as soon as it changes on disk, and the previous contents
+/// are kept if the new version cannot be parsed. Writers should replace the
+/// file atomically (write elsewhere, then rename) so partial contents are
+/// never loaded.
+pub struct FileStore {
+    path: PathBuf,
+    entries: Arc<ArcSwap<MemoryStore>>,
+    _watcher: Option<RecommendedWatcher>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    List,
+    Map,
+}
+
+impl FileStore {
+    pub fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let path = PathBuf::from(config.value_require((&prefix, "path"))?);
+        let format = match config.value((&prefix, "format")).unwrap_or("list") {
+            "list" => FileFormat::List,
+            "map" => FileFormat::Map,
+            other => {
+                let err = format!("Invalid file format {other:?}");
+                config.new_parse_error((&prefix, "format"), err);
+                return None;
+            }
+        };
+
+        let entries = Arc::new(ArcSwap::from_pointee(
+            load(&path, format)
+                .map_err(|err| config.new_build_error((&prefix, "path"), err))
+                .ok()?,
+        ));
+        let watcher = if config
+            .property_or_default((&prefix, "watch"), "true")
+            .unwrap_or(true)
+        {
+            watch(path.clone(), format, entries.clone())
+                .map_err(|err| config.new_build_error((&prefix, "watch"), err))
+                .ok()?
+                .into()
+        } else {
+            None
+        };
+
+        Some(FileStore {
+            path,
+            entries,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value<'static>> {
+        self.entries.load().get(key).cloned()
+    }
+}
+
+fn load(path: &Path, format: FileFormat) -> Result<MemoryStore, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    parse(&contents, format).map_err(|err| format!("Failed to parse {}: {err}", path.display()))
+}
+
+/// Parses one entry per line, ignoring blank lines and `#` comments. Lists
+/// contain only keys, maps separate the key from its value with whitespace.
+fn parse(contents: &str, format: FileFormat) -> Result<MemoryStore, String> {
+    let mut store = MemoryStore::default();
+
+    for (line_num, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match format {
+            FileFormat::List => {
+                store.insert(line, "true");
+            }
+            FileFormat::Map => {
+                if let Some((key, value)) = line.split_once(char::is_whitespace) {
+                    store.insert(key, value.trim());
+                } else {
+                    return Err(format!("Missing value on line {}", line_num + 1));
+                }
+            }
+        }
+    }
+
+    Ok(store)
+}
+
+fn watch(
+    path: PathBuf,
+    format: FileFormat,
+    entries: Arc<ArcSwap<MemoryStore>>,
+) -> Result<RecommendedWatcher, String> {
+    // Tools usually rename a new file over the old one, so the parent
+    // directory is watched rather than the file itself
+    let directory = path
+        .parent()
+        .filter(|path| !path.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let file_name = path.file_name().map(|name| name.to_os_string());
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if !event.is_ok_and(|event| {
+            (event.kind.is_create() || event.kind.is_modify())
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == file_name.as_deref())
+        }) {
+            return;
+        }
+
+        match load(&path, format) {
+            Ok(store) => {
+                let total = store.len();
+                entries.store(Arc::new(store));
+
+                trc::event!(
+                    Resource(trc::ResourceEvent::LookupReloaded),
+                    Path = path.display().to_string(),
+                    Total = total,
+                );
+            }
+            Err(err) => {
+                trc::event!(
+                    Resource(trc::ResourceEvent::Error),
+                    Path = path.display().to_string(),
+                    Details = err,
+                );
+            }
+        }
+    })
+    .map_err(|err| format!("Failed to create file watcher: {err}"))?;
+    watcher
+        .watch(&directory, RecursiveMode::NonRecursive)
+        .map_err(|err| format!("Failed to watch {}: {err}", directory.display()))?;
+
+    Ok(watcher)
+}
+
+impl Debug for FileStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileStore")
+            .field("path", &self.path)
+            .finish()
+    }
+}
@@ -9,6 +9,8 @@ use utils::{config::Config, glob::GlobPattern};
 
 use crate::{LookupStore, Stores, Value};
 
+pub mod file;
+
 #[derive(Debug, Default)]
 pub struct MemoryStore {
     entries: AHashMap<String, Value<'static>>,
@@ -23,6 +25,92 @@ impl MemoryStore {
                 .find_map(|(pattern, value)| pattern.matches(id).then_some(value))
         })
     }
+
+    pub fn insert(&mut self, key: &str, value: &str) {
+        // Detect if the key is a glob pattern
+        let mut last_ch = '\0';
+        let mut has_escape = false;
+        let mut is_glob = false;
+        for ch in key.chars() {
+            match ch {
+                '\\' => {
+                    has_escape = true;
+                }
+                '*' | '?' if last_ch != '\\' => {
+                    is_glob = true;
+                }
+                _ => {}
+            }
+
+            last_ch = ch;
+        }
+
+        // Detect value type
+        let value = if !value.is_empty() {
+            let mut has_integers = false;
+            let mut has_floats = false;
+            let mut has_others = false;
+
+            for (pos, ch) in value.as_bytes().iter().enumerate() {
+                match ch {
+                    b'.' if !has_floats && has_integers => {
+                        has_floats = true;
+                    }
+                    b'0'..=b'9' => {
+                        has_integers = true;
+                    }
+                    b'-' if pos == 0 && value.len() > 1 => {}
+                    _ => {
+                        has_others = true;
+                    }
+                }
+            }
+
+            if has_others {
+                if value == "true" {
+                    Value::Integer(1.into())
+                } else if value == "false" {
+                    Value::Integer(0.into())
+                } else {
+                    Value::Text(value.to_string().into())
+                }
+            } else if has_floats {
+                value
+                    .parse()
+                    .map(Value::Float)
+                    .unwrap_or_else(|_| Value::Text(value.to_string().into()))
+            } else {
+                value
+                    .parse()
+                    .map(Value::Integer)
+                    .unwrap_or_else(|_| Value::Text(value.to_string().into()))
+            }
+        } else {
+            Value::Text("".into())
+        };
+
+        // Add entry
+        if is_glob {
+            self.globs.push((GlobPattern::compile(key, false), value));
+        } else {
+            self.entries.insert(
+                if has_escape {
+                    key.replace('\\', "")
+                } else {
+                    key.to_string()
+                },
+                value,
+            );
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.globs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.globs.is_empty()
+    }
 }
 
 impl Stores {
@@ -35,84 +123,10 @@ impl Stores {
                 .split_once('.')
                 .filter(|(id, key)| !id.is_empty() && !key.is_empty())
             {
-                // Detect if the key is a glob pattern
-                let mut last_ch = '\0';
-                let mut has_escape = false;
-                let mut is_glob = false;
-                for ch in key.chars() {
-                    match ch {
-                        '\\' => {
-                            has_escape = true;
-                        }
-                        '*' | '?' if last_ch != '\\' => {
-                            is_glob = true;
-                        }
-                        _ => {}
-                    }
-
-                    last_ch = ch;
-                }
-
-                // Detect value type
-                let value = if !value.is_empty() {
-                    let mut has_integers = false;
-                    let mut has_floats = false;
-                    let mut has_others = false;
-
-                    for (pos, ch) in value.as_bytes().iter().enumerate() {
-                        match ch {
-                            b'.' if !has_floats && has_integers => {
-                                has_floats = true;
-                            }
-                            b'0'..=b'9' => {
-                                has_integers = true;
-                            }
-                            b'-' if pos == 0 && value.len() > 1 => {}
-                            _ => {
-                                has_others = true;
-                            }
-                        }
-                    }
-
-                    if has_others {
-                        if value == "true" {
-                            Value::Integer(1.into())
-                        } else if value == "false" {
-                            Value::Integer(0.into())
-                        } else {
-                            Value::Text(value.to_string().into())
-                        }
-                    } else if has_floats {
-                        value
-                            .parse()
-                            .map(Value::Float)
-                            .unwrap_or_else(|_| Value::Text(value.to_string().into()))
-                    } else {
-                        value
-                            .parse()
-                            .map(Value::Integer)
-                            .unwrap_or_else(|_| Value::Text(value.to_string().into()))
-                    }
-                } else {
-                    Value::Text("".into())
-                };
-
-                // Add entry
-                let store = lookups
+                lookups
                     .entry(id.to_string())
-                    .or_insert_with(MemoryStore::default);
-                if is_glob {
-                    store.globs.push((GlobPattern::compile(key, false), value));
-                } else {
-                    store.entries.insert(
-                        if has_escape {
-                            key.replace('\\', "")
-                        } else {
-                            key.to_string()
-                        },
-                        value,
-                    );
-                }
+                    .or_insert_with(MemoryStore::default)
+                    .insert(key, value);
             } else {
                 errors.push(key.to_string());
             }
@@ -18,6 +18,8 @@ pub mod mysql;
 pub mod postgres;
 #[cfg(feature = "redis")]
 pub mod redis;
+#[cfg(feature = "etcd")]
+pub mod etcd;
 #[cfg(feature = "rocks")]
 pub mod rocksdb;
 #[cfg(feature = "s3")]
@@ -26,6 +28,10 @@ pub mod s3;
 pub mod sqlite;
 #[cfg(feature = "azure")]
 pub mod azure;
+#[cfg(feature = "gcs")]
+pub mod gcs;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub const MAX_TOKEN_LENGTH: usize = (u8::MAX >> 1) as usize;
 pub const MAX_TOKEN_MASK: usize = MAX_TOKEN_LENGTH - 1;
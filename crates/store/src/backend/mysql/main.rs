@@ -6,7 +6,9 @@
 
 use std::time::Duration;
 
-use mysql_async::{prelude::Queryable, OptsBuilder, Pool, PoolConstraints, PoolOpts, SslOpts};
+use mysql_async::{
+    prelude::Queryable, ClientIdentity, OptsBuilder, Pool, PoolConstraints, PoolOpts, SslOpts,
+};
 use utils::config::{utils::AsKey, Config};
 
 use crate::*;
@@ -48,11 +50,68 @@ impl MysqlStore {
             let allow_invalid = config
                 .property_or_default::<bool>((&prefix, "tls.allow-invalid-certs"), "false")
                 .unwrap_or_default();
-            opts = opts.ssl_opts(Some(
-                SslOpts::default()
-                    .with_danger_accept_invalid_certs(allow_invalid)
-                    .with_danger_skip_domain_validation(allow_invalid),
-            ));
+            let mut ssl_opts = SslOpts::default()
+                .with_danger_accept_invalid_certs(allow_invalid)
+                .with_danger_skip_domain_validation(allow_invalid);
+            if let Some(ca_cert) = config.value((&prefix, "tls.ca-cert")) {
+                ssl_opts = ssl_opts.with_root_certs(vec![pem_buf(ca_cert).into()]);
+            }
+
+            // Client certificate for mutual TLS
+            match (
+                config.value((&prefix, "tls.client-cert")),
+                config.value((&prefix, "tls.client-key")),
+            ) {
+                (Some(cert), Some(key)) => {
+                    ssl_opts = ssl_opts.with_client_identity(
+                        ClientIdentity::new(pem_buf(cert).into(), pem_buf(key).into()).into(),
+                    );
+                }
+                (None, None) => {}
+                _ => {
+                    config.new_build_error(
+                        (&prefix, "tls"),
+                        "Both a client certificate and a private key are required for mutual TLS",
+                    );
+                }
+            }
+            opts = opts.ssl_opts(Some(ssl_opts));
+        }
+
+        // The MySQL driver does not support sending connection attributes
+        if config
+            .iterate_prefix((&prefix, "attributes"))
+            .next()
+            .is_some()
+        {
+            config.new_build_warning(
+                (&prefix, "attributes"),
+                "Connection attributes are not supported by the MySQL driver",
+            );
+        }
+
+        // Session variables set on every new connection
+        let mut init = Vec::new();
+        for (name, value) in config
+            .iterate_prefix((&prefix, "session"))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+        {
+            if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+            {
+                init.push(format!("SET SESSION {name} = {}", session_value(&value)));
+            } else {
+                config.new_parse_error(
+                    (prefix.as_str(), "session", name.as_str()),
+                    "Invalid session variable name",
+                );
+            }
+        }
+        if !init.is_empty() {
+            opts = opts.init(init);
         }
 
         // Configure connection pool
@@ -165,3 +224,20 @@ impl MysqlStore {
         Ok(())
     }
 }
+
+fn pem_buf(value: &str) -> Vec<u8> {
+    value.as_bytes().to_vec()
+}
+
+// Integers and keywords such as ON or DEFAULT are passed through, anything else is quoted.
+fn session_value(value: &str) -> String {
+    if value.parse::<i64>().is_ok()
+        || ["ON", "OFF", "DEFAULT", "TRUE", "FALSE"]
+            .iter()
+            .any(|keyword| value.eq_ignore_ascii_case(keyword))
+    {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+    }
+}
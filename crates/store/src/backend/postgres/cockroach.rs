@@ -0,0 +1,111 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use rand::Rng;
+use utils::config::Config;
+
+use crate::{
+    SUBSPACE_FTS_QUEUE, SUBSPACE_QUEUE_EVENT, SUBSPACE_QUEUE_MESSAGE, SUBSPACE_REPORT_IN,
+    SUBSPACE_REPORT_OUT, SUBSPACE_TELEMETRY_INDEX, SUBSPACE_TELEMETRY_METRIC,
+    SUBSPACE_TELEMETRY_SPAN,
+};
+
+use super::PostgresDialect;
+
+// Tables whose keys start with a timestamp or a sequence number, which
+// would send all inserts to the same range unless their keys are hashed.
+const SEQUENTIAL_TABLES: &[u8] = &[
+    SUBSPACE_FTS_QUEUE,
+    SUBSPACE_QUEUE_MESSAGE,
+    SUBSPACE_QUEUE_EVENT,
+    SUBSPACE_REPORT_OUT,
+    SUBSPACE_REPORT_IN,
+    SUBSPACE_TELEMETRY_SPAN,
+    SUBSPACE_TELEMETRY_METRIC,
+    SUBSPACE_TELEMETRY_INDEX,
+];
+
+// Tables holding historical data that is never read back as part of a
+// transaction, which can be scanned from a slightly stale snapshot.
+const STALE_READ_TABLES: &[u8] = &[
+    SUBSPACE_REPORT_IN,
+    SUBSPACE_TELEMETRY_SPAN,
+    SUBSPACE_TELEMETRY_METRIC,
+    SUBSPACE_TELEMETRY_INDEX,
+];
+
+const MIN_BACKOFF_MS: u64 = 10;
+const MAX_BACKOFF_MS: u64 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct CockroachSettings {
+    pub read_staleness: Option<Duration>,
+    pub hash_buckets: Option<u32>,
+}
+
+impl CockroachSettings {
+    pub fn parse(config: &mut Config, prefix: &str) -> Self {
+        CockroachSettings {
+            read_staleness: config
+                .property_or_default::<Option<Duration>>((prefix, "read-only.staleness"), "5s")
+                .unwrap_or_default(),
+            hash_buckets: config
+                .property_or_default::<Option<u32>>((prefix, "hash-sharded.buckets"), "16")
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl PostgresDialect {
+    pub fn is_cockroach(&self) -> bool {
+        matches!(self, PostgresDialect::CockroachDb(_))
+    }
+
+    /// Returns the primary key definition for a table, hash sharding
+    /// tables with sequential keys on CockroachDB.
+    pub fn primary_key(&self, table: u8) -> String {
+        match self {
+            PostgresDialect::CockroachDb(CockroachSettings {
+                hash_buckets: Some(buckets),
+                ..
+            }) if SEQUENTIAL_TABLES.contains(&table) => {
+                format!("PRIMARY KEY (k) USING HASH WITH (bucket_count = {buckets})")
+            }
+            _ => "PRIMARY KEY (k)".to_string(),
+        }
+    }
+
+    /// Returns the `AS OF SYSTEM TIME` clause to use when scanning a table
+    /// outside a transaction, if the table can be read from a stale snapshot.
+    pub fn as_of_system_time(&self, table: u8) -> String {
+        match self {
+            PostgresDialect::CockroachDb(CockroachSettings {
+                read_staleness: Some(staleness),
+                ..
+            }) if STALE_READ_TABLES.contains(&table) => {
+                format!(" AS OF SYSTEM TIME '-{}ms'", staleness.as_millis())
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Returns how long to wait before retrying a failed transaction.
+    /// CockroachDB runs every transaction as serializable, so contended
+    /// writes back off exponentially rather than for a random interval.
+    pub fn retry_backoff(&self, retry_count: u32) -> Duration {
+        let mut rng = rand::thread_rng();
+        Duration::from_millis(if self.is_cockroach() {
+            let backoff = MIN_BACKOFF_MS
+                .saturating_mul(1 << retry_count.min(16))
+                .min(MAX_BACKOFF_MS);
+            rng.gen_range(backoff / 2..=backoff)
+        } else {
+            rng.gen_range(50..=300)
+        })
+    }
+}
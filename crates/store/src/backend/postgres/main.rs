@@ -8,7 +8,7 @@ use std::time::Duration;
 
 use crate::{backend::postgres::tls::MakeRustlsConnect, *};
 
-use super::{into_error, PostgresStore};
+use super::{cockroach::CockroachSettings, into_error, PostgresDialect, PostgresStore};
 
 use deadpool_postgres::{Config, ManagerConfig, PoolConfig, RecyclingMethod, Runtime};
 use tokio_postgres::NoTls;
@@ -21,6 +21,14 @@ impl PostgresStore {
         create_tables: bool,
     ) -> Option<Self> {
         let prefix = prefix.as_key();
+        let dialect = if config
+            .value((&prefix, "type"))
+            .is_some_and(|t| t == "cockroachdb")
+        {
+            PostgresDialect::CockroachDb(CockroachSettings::parse(config, &prefix))
+        } else {
+            PostgresDialect::PostgreSql
+        };
         let mut cfg = Config::new();
         cfg.dbname = config
             .value_require((&prefix, "database"))?
@@ -40,19 +48,25 @@ impl PostgresStore {
         if let Some(max_conn) = config.property::<usize>((&prefix, "pool.max-connections")) {
             cfg.pool = PoolConfig::new(max_conn).into();
         }
+        let tls = config
+            .property_or_default::<bool>((&prefix, "tls.enable"), "false")
+            .unwrap_or_default()
+            .then(|| {
+                MakeRustlsConnect::new(rustls_client_config(
+                    config
+                        .property_or_default((&prefix, "tls.allow-invalid-certs"), "false")
+                        .unwrap_or_default(),
+                ))
+            });
+        let pg_config = cfg
+            .get_pg_config()
+            .map_err(|e| {
+                config.new_build_error(prefix.as_str(), format!("Invalid connection settings: {e}"))
+            })
+            .ok()?;
         let db = Self {
-            conn_pool: if config
-                .property_or_default::<bool>((&prefix, "tls.enable"), "false")
-                .unwrap_or_default()
-            {
-                cfg.create_pool(
-                    Some(Runtime::Tokio1),
-                    MakeRustlsConnect::new(rustls_client_config(
-                        config
-                            .property_or_default((&prefix, "tls.allow-invalid-certs"), "false")
-                            .unwrap_or_default(),
-                    )),
-                )
+            conn_pool: if let Some(tls) = &tls {
+                cfg.create_pool(Some(Runtime::Tokio1), tls.clone())
             } else {
                 cfg.create_pool(Some(Runtime::Tokio1), NoTls)
             }
@@ -63,6 +77,9 @@ impl PostgresStore {
                 )
             })
             .ok()?,
+            pg_config,
+            tls,
+            dialect,
         };
 
         if create_tables {
@@ -97,12 +114,14 @@ impl PostgresStore {
             SUBSPACE_TELEMETRY_METRIC,
             SUBSPACE_TELEMETRY_INDEX,
         ] {
+            let primary_key = self.dialect.primary_key(table);
             let table = char::from(table);
             conn.execute(
                 &format!(
                     "CREATE TABLE IF NOT EXISTS {table} (
-                        k BYTEA PRIMARY KEY,
-                        v BYTEA NOT NULL
+                        k BYTEA NOT NULL,
+                        v BYTEA NOT NULL,
+                        {primary_key}
                     )"
                 ),
                 &[],
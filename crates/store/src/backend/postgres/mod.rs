@@ -6,17 +6,30 @@
 
 use std::fmt::Display;
 
+use cockroach::CockroachSettings;
 use deadpool_postgres::Pool;
+use tls::MakeRustlsConnect;
 
 pub mod blob;
+pub mod cockroach;
 pub mod lookup;
 pub mod main;
+pub mod notify;
 pub mod read;
 pub mod tls;
 pub mod write;
 
 pub struct PostgresStore {
     pub(crate) conn_pool: Pool,
+    pub(crate) pg_config: tokio_postgres::Config,
+    pub(crate) tls: Option<MakeRustlsConnect>,
+    pub(crate) dialect: PostgresDialect,
+}
+
+#[derive(Debug, Clone)]
+pub enum PostgresDialect {
+    PostgreSql,
+    CockroachDb(CockroachSettings),
 }
 
 #[inline(always)]
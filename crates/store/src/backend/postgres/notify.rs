@@ -0,0 +1,111 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use futures::{
+    future::{select, Either},
+    pin_mut, StreamExt,
+};
+use tokio::sync::mpsc;
+use tokio_postgres::{
+    tls::{MakeTlsConnect, TlsConnect},
+    AsyncMessage, NoTls, Socket,
+};
+
+use super::{into_error, PostgresStore};
+
+const NOTIFY_CHANNEL_BUFFER: usize = 1024;
+
+impl PostgresStore {
+    pub(crate) async fn notify(&self, channel: &str, payload: &str) -> trc::Result<()> {
+        self.assert_notify_support()?;
+        let conn = self.conn_pool.get().await.map_err(into_error)?;
+        let s = conn
+            .prepare_cached("SELECT pg_notify($1, $2)")
+            .await
+            .map_err(into_error)?;
+        conn.execute(&s, &[&channel, &payload])
+            .await
+            .map(|_| ())
+            .map_err(into_error)
+    }
+
+    pub(crate) async fn listen(&self, channel: &str) -> trc::Result<mpsc::Receiver<String>> {
+        self.assert_notify_support()?;
+        if let Some(tls) = &self.tls {
+            self.listen_with(channel, tls.clone()).await
+        } else {
+            self.listen_with(channel, NoTls).await
+        }
+    }
+
+    async fn listen_with<T>(&self, channel: &str, tls: T) -> trc::Result<mpsc::Receiver<String>>
+    where
+        T: MakeTlsConnect<Socket>,
+        T::Stream: Send + 'static,
+        T::TlsConnect: Send,
+        <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+    {
+        // Notifications are delivered to the session that issued LISTEN, so a dedicated
+        // connection is opened instead of borrowing one from the pool.
+        let (client, mut connection) = self.pg_config.connect(tls).await.map_err(into_error)?;
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+
+        // The connection has to be driven while the subscription is being made
+        {
+            let query = format!("LISTEN \"{}\"", channel.replace('"', "\"\""));
+            let listen = client.batch_execute(&query);
+            pin_mut!(listen);
+            loop {
+                match select(listen.as_mut(), messages.next()).await {
+                    Either::Left((result, _)) => {
+                        result.map_err(into_error)?;
+                        break;
+                    }
+                    Either::Right((Some(Ok(_)), _)) => {}
+                    Either::Right((Some(Err(err)), _)) => return Err(into_error(err)),
+                    Either::Right((None, _)) => return Err(into_error("Connection closed")),
+                }
+            }
+        }
+
+        let (tx, rx) = mpsc::channel(NOTIFY_CHANNEL_BUFFER);
+        tokio::spawn(async move {
+            // Keep the client alive for as long as the subscription is in use
+            let _client = client;
+
+            while let Some(message) = messages.next().await {
+                match message {
+                    Ok(AsyncMessage::Notification(notification)) => {
+                        if tx.send(notification.payload().to_string()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        trc::error!(into_error(err)
+                            .details("PostgreSQL notification listener stopped.")
+                            .caused_by(trc::location!()));
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+}
+
+impl PostgresStore {
+    fn assert_notify_support(&self) -> trc::Result<()> {
+        if !self.dialect.is_cockroach() {
+            Ok(())
+        } else {
+            Err(trc::StoreEvent::NotSupported
+                .into_err()
+                .details("CockroachDB does not support LISTEN/NOTIFY"))
+        }
+    }
+}
@@ -78,7 +78,12 @@ impl PostgresStore {
         mut cb: impl for<'x> FnMut(&'x [u8], &'x [u8]) -> trc::Result<bool> + Sync + Send,
     ) -> trc::Result<()> {
         let conn = self.conn_pool.get().await.map_err(into_error)?;
-        let table = char::from(params.begin.subspace());
+        let subspace = params.begin.subspace();
+        let table = format!(
+            "{}{}",
+            char::from(subspace),
+            self.dialect.as_of_system_time(subspace)
+        );
         let begin = params.begin.serialize(0);
         let end = params.end.serialize(0);
         let keys = if params.values { "k, v" } else { "k" };
@@ -4,12 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use ahash::AHashMap;
 use deadpool_postgres::Object;
 use futures::{pin_mut, TryStreamExt};
-use rand::Rng;
 use roaring::RoaringBitmap;
 use tokio_postgres::{error::SqlState, IsolationLevel};
 
@@ -64,8 +63,7 @@ impl PostgresStore {
                         }
                     }
 
-                    let backoff = rand::thread_rng().gen_range(50..=300);
-                    tokio::time::sleep(Duration::from_millis(backoff)).await;
+                    tokio::time::sleep(self.dialect.retry_backoff(retry_count)).await;
                     retry_count += 1;
                 }
             }
@@ -84,7 +82,11 @@ impl PostgresStore {
         let mut asserted_values = AHashMap::new();
         let trx = conn
             .build_transaction()
-            .isolation_level(IsolationLevel::ReadCommitted)
+            .isolation_level(if self.dialect.is_cockroach() {
+                IsolationLevel::Serializable
+            } else {
+                IsolationLevel::ReadCommitted
+            })
             .start()
             .await?;
         let mut result = AssignedIds::default();
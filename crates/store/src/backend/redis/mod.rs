@@ -22,6 +22,7 @@ pub mod pool;
 #[derive(Debug)]
 pub struct RedisStore {
     pool: RedisPool,
+    hash_tags: bool,
 }
 
 struct RedisConnectionManager {
@@ -67,6 +68,9 @@ impl RedisStore {
                         .unwrap_or_default();
 
                     Self {
+                        hash_tags: config
+                            .property_or_default((&prefix, "hash-tags"), "false")
+                            .unwrap_or_default(),
                         pool: RedisPool::Single(
                             build_pool(config, &prefix, RedisConnectionManager { client, timeout })
                                 .map_err(|err| {
@@ -120,6 +124,9 @@ impl RedisStore {
                         .unwrap_or_else(|| Duration::from_secs(10));
 
                     Self {
+                        hash_tags: config
+                            .property_or_default((&prefix, "hash-tags"), "true")
+                            .unwrap_or(true),
                         pool: RedisPool::Cluster(
                             build_pool(
                                 config,
@@ -146,6 +153,25 @@ impl RedisStore {
     }
 }
 
+impl RedisStore {
+    /// Builds the key of one of the buckets that belong to a key, such as the
+    /// time windows of a rate limiter. With hash tags enabled the key is
+    /// wrapped in braces, so all its buckets map to the same cluster slot and
+    /// can be used together in multi-key commands and transactions.
+    pub fn bucket_key(&self, key: &[u8], bucket: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(key.len() + bucket.len() + 2);
+        if self.hash_tags && !key.is_empty() {
+            result.push(b'{');
+            result.extend_from_slice(key);
+            result.push(b'}');
+        } else {
+            result.extend_from_slice(key);
+        }
+        result.extend_from_slice(bucket);
+        result
+    }
+}
+
 fn build_pool<M: Manager>(
     config: &mut Config,
     prefix: &str,
@@ -4,17 +4,29 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::{path::PathBuf, time::Duration};
+
 use r2d2::Pool;
+use rusqlite::{backup::Backup, Connection};
 use tokio::sync::oneshot;
 use utils::config::{utils::AsKey, Config};
 
 use crate::*;
 
-use super::{into_error, pool::SqliteConnectionManager, SqliteStore};
+use super::{into_error, pool::SqliteConnectionManager, SqliteStore, WalCheckpoint};
+
+const BACKUP_PAGES_PER_STEP: std::ffi::c_int = 1024;
+const BACKUP_STEP_PAUSE: Duration = Duration::from_millis(10);
 
 impl SqliteStore {
     pub fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
         let prefix = prefix.as_key();
+        let wal_autocheckpoint = config
+            .property::<u32>((&prefix, "wal.autocheckpoint"))
+            .unwrap_or(1000);
+        let wal_size_limit = config
+            .property::<u64>((&prefix, "wal.size-limit"))
+            .map_or(-1, |limit| limit as i64);
         let db = Self {
             conn_pool: Pool::builder()
                 .max_size(
@@ -24,12 +36,17 @@ impl SqliteStore {
                 )
                 .build(
                     SqliteConnectionManager::file(config.value_require((&prefix, "path"))?)
-                        .with_init(|c| {
-                            c.execute_batch(concat!(
-                                "PRAGMA journal_mode = WAL; ",
-                                "PRAGMA synchronous = NORMAL; ",
-                                "PRAGMA temp_store = memory;",
-                                "PRAGMA busy_timeout = 30000;"
+                        .with_init(move |c| {
+                            c.execute_batch(&format!(
+                                concat!(
+                                    "PRAGMA journal_mode = WAL; ",
+                                    "PRAGMA synchronous = NORMAL; ",
+                                    "PRAGMA temp_store = memory;",
+                                    "PRAGMA busy_timeout = 30000;",
+                                    "PRAGMA wal_autocheckpoint = {};",
+                                    "PRAGMA journal_size_limit = {};"
+                                ),
+                                wal_autocheckpoint, wal_size_limit
                             ))
                         }),
                 )
@@ -56,6 +73,9 @@ impl SqliteStore {
                     )
                 })
                 .ok()?,
+            wal_checkpoint: config
+                .property_or_default::<Option<WalCheckpoint>>((&prefix, "wal.checkpoint"), "false")
+                .unwrap_or_default(),
         };
 
         if let Err(err) = db.create_tables() {
@@ -80,6 +100,7 @@ impl SqliteStore {
                 .map_err(|err| {
                     into_error(err).ctx(trc::Key::Reason, "Failed to build worker pool")
                 })?,
+            wal_checkpoint: None,
         };
         db.create_tables()?;
         Ok(db)
@@ -156,6 +177,21 @@ impl SqliteStore {
         Ok(())
     }
 
+    pub(crate) async fn backup(&self, dest: PathBuf) -> trc::Result<()> {
+        let conn = self.conn_pool.get().map_err(into_error)?;
+
+        tokio::task::spawn_blocking(move || {
+            let mut dest_conn = Connection::open(&dest).map_err(into_error)?;
+            let result = Backup::new(&conn, &mut dest_conn)
+                .map_err(into_error)?
+                .run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)
+                .map_err(into_error);
+            result
+        })
+        .await
+        .map_err(|err| trc::EventType::Server(trc::ServerEvent::ThreadError).reason(err))?
+    }
+
     pub async fn spawn_worker<U, V>(&self, mut f: U) -> trc::Result<V>
     where
         U: FnMut() -> trc::Result<V> + Send,
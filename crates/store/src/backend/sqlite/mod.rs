@@ -7,6 +7,7 @@
 use std::fmt::Display;
 
 use r2d2::Pool;
+use utils::config::utils::ParseValue;
 
 use self::pool::SqliteConnectionManager;
 
@@ -20,6 +21,39 @@ pub mod write;
 pub struct SqliteStore {
     pub(crate) conn_pool: Pool<SqliteConnectionManager>,
     pub(crate) worker_pool: rayon::ThreadPool,
+    pub(crate) wal_checkpoint: Option<WalCheckpoint>,
+}
+
+/// Checkpoint mode used to flush the write-ahead log when the store is purged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalCheckpoint {
+    Passive,
+    Full,
+    Restart,
+    Truncate,
+}
+
+impl WalCheckpoint {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WalCheckpoint::Passive => "PASSIVE",
+            WalCheckpoint::Full => "FULL",
+            WalCheckpoint::Restart => "RESTART",
+            WalCheckpoint::Truncate => "TRUNCATE",
+        }
+    }
+}
+
+impl ParseValue for WalCheckpoint {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "passive" => Ok(WalCheckpoint::Passive),
+            "full" => Ok(WalCheckpoint::Full),
+            "restart" => Ok(WalCheckpoint::Restart),
+            "truncate" => Ok(WalCheckpoint::Truncate),
+            mode => Err(format!("Invalid WAL checkpoint mode: {mode}")),
+        }
+    }
 }
 
 #[inline(always)]
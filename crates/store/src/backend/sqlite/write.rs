@@ -258,6 +258,7 @@ impl SqliteStore {
 
     pub(crate) async fn purge_store(&self) -> trc::Result<()> {
         let conn = self.conn_pool.get().map_err(into_error)?;
+        let wal_checkpoint = self.wal_checkpoint;
         self.spawn_worker(move || {
             for subspace in [SUBSPACE_QUOTA, SUBSPACE_COUNTER] {
                 conn.prepare_cached(&format!("DELETE FROM {} WHERE v = 0", char::from(subspace),))
@@ -266,6 +267,16 @@ impl SqliteStore {
                     .map_err(into_error)?;
             }
 
+            // Flush the write-ahead log
+            if let Some(wal_checkpoint) = wal_checkpoint {
+                conn.query_row(
+                    &format!("PRAGMA wal_checkpoint({})", wal_checkpoint.as_str()),
+                    [],
+                    |_| Ok(()),
+                )
+                .map_err(into_error)?;
+            }
+
             Ok(())
         })
         .await
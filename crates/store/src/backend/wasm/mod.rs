@@ -0,0 +1,427 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{ops::Range, path::PathBuf, sync::LazyLock, time::Duration};
+
+use ahash::AHashSet;
+use tokio::runtime::Handle;
+use utils::config::{utils::AsKey, Config};
+use wasmtime::{Caller, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+use wasmtime_wasi::{
+    preview1::{self, WasiP1Ctx},
+    DirPerms, FilePerms, WasiCtxBuilder,
+};
+
+const STATUS_OK: u8 = 0;
+const STATUS_NOT_FOUND: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut config = wasmtime::Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config).expect("Failed to create WebAssembly engine")
+});
+
+/// Blob store implemented by a WASI (preview 1) plugin. The module exports its
+/// `memory`, an `alloc(len) -> ptr` function and the `blob_get(key)`,
+/// `blob_put(key, data)` and `blob_delete(key)` entry points, which receive
+/// the key as a lowercase hex string and return their reply packed as
+/// `ptr << 32 | len`. Replies start with a status byte: `0` followed by the
+/// blob contents, `1` if the blob does not exist or `2` followed by an error.
+///
+/// Plugins only see the environment variables and the directory granted in
+/// the configuration. Allow-listed hosts can be reached through the
+/// `mailwpro.http_request(request, body)` import, which takes a JSON request
+/// (`method`, `url`, `headers`) and replies with a status byte followed by the
+/// big-endian HTTP status code and the response body.
+pub struct WasmStore {
+    module: Module,
+    linker: Linker<PluginState>,
+    fuel: u64,
+    memory: usize,
+    env: Vec<(String, String)>,
+    directory: Option<PathBuf>,
+    http: Option<HttpBridge>,
+}
+
+struct PluginState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+    http: Option<HttpBridge>,
+    runtime: Handle,
+}
+
+#[derive(Clone)]
+struct HttpBridge {
+    client: reqwest::Client,
+    allowed_hosts: AHashSet<String>,
+    max_size: usize,
+}
+
+#[derive(serde::Deserialize)]
+struct HttpRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+}
+
+enum Reply {
+    Ok(Vec<u8>),
+    NotFound,
+}
+
+impl WasmStore {
+    pub async fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let path = config.value_require((&prefix, "module"))?.to_string();
+        let module = Module::from_file(&ENGINE, &path)
+            .map_err(|err| {
+                config.new_build_error(
+                    (&prefix, "module"),
+                    format!("Failed to load WebAssembly module {path}: {err}"),
+                )
+            })
+            .ok()?;
+
+        let mut linker = Linker::new(&ENGINE);
+        if let Err(err) =
+            preview1::add_to_linker_sync(&mut linker, |state: &mut PluginState| &mut state.wasi)
+                .and_then(|_| {
+                    linker
+                        .func_wrap("mailwpro", "http_request", http_request)
+                        .map(|_| ())
+                })
+        {
+            config.new_build_error(
+                prefix.as_str(),
+                format!("Failed to link WebAssembly module: {err}"),
+            );
+            return None;
+        }
+
+        let directory = config.value((&prefix, "directory")).map(PathBuf::from);
+        if let Some(directory) = directory.as_ref().filter(|path| !path.exists()) {
+            tokio::fs::create_dir_all(directory)
+                .await
+                .map_err(|e| {
+                    config.new_build_error(
+                        (&prefix, "directory"),
+                        format!("Failed to create directory: {e}"),
+                    )
+                })
+                .ok()?;
+        }
+
+        let memory = config
+            .property_or_default((&prefix, "limits.memory"), "134217728")
+            .unwrap_or(134217728);
+        let allowed_hosts = config
+            .values((&prefix, "http.allowed-hosts"))
+            .map(|(_, host)| host.to_ascii_lowercase())
+            .collect::<AHashSet<_>>();
+        let http = if !allowed_hosts.is_empty() {
+            let timeout = config
+                .property_or_default::<Duration>((&prefix, "http.timeout"), "30s")
+                .unwrap_or_else(|| Duration::from_secs(30));
+            match reqwest::Client::builder().timeout(timeout).build() {
+                Ok(client) => Some(HttpBridge {
+                    client,
+                    allowed_hosts,
+                    max_size: memory,
+                }),
+                Err(err) => {
+                    config.new_build_error(
+                        prefix.as_str(),
+                        format!("Failed to create HTTP client: {err:?}"),
+                    );
+                    return None;
+                }
+            }
+        } else {
+            None
+        };
+
+        Some(WasmStore {
+            module,
+            linker,
+            fuel: config
+                .property_or_default((&prefix, "limits.fuel"), "1000000000")
+                .unwrap_or(1000000000),
+            memory,
+            env: config
+                .iterate_prefix((&prefix, "env"))
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            directory,
+            http,
+        })
+    }
+
+    pub(crate) async fn get_blob(
+        &self,
+        key: &[u8],
+        range: Range<usize>,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        match self.call("blob_get", key, &[]).await? {
+            Reply::Ok(data) => Ok(Some(if range.start != 0 || range.end != usize::MAX {
+                data.get(range.start..std::cmp::min(range.end, data.len()))
+                    .unwrap_or_default()
+                    .to_vec()
+            } else {
+                data
+            })),
+            Reply::NotFound => Ok(None),
+        }
+    }
+
+    pub(crate) async fn put_blob(&self, key: &[u8], data: &[u8]) -> trc::Result<()> {
+        self.call("blob_put", key, data).await.map(|_| ())
+    }
+
+    pub(crate) async fn delete_blob(&self, key: &[u8]) -> trc::Result<bool> {
+        self.call("blob_delete", key, &[])
+            .await
+            .map(|reply| matches!(reply, Reply::Ok(_)))
+    }
+
+    async fn call(&self, function: &'static str, key: &[u8], data: &[u8]) -> trc::Result<Reply> {
+        // Plugins run synchronously on a blocking thread, host calls that
+        // need the async runtime are bridged through its handle
+        let runtime = Handle::current();
+        let key = key
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        let data = data.to_vec();
+        let store = self.prepare(runtime)?;
+        let module = self.module.clone();
+        let linker = self.linker.clone();
+
+        let output = tokio::task::spawn_blocking(move || {
+            call_plugin(store, &module, &linker, function, key.as_bytes(), &data)
+        })
+        .await
+        .map_err(|err| {
+            trc::EventType::Server(trc::ServerEvent::ThreadError)
+                .reason(err)
+                .caused_by(trc::location!())
+        })?
+        .map_err(|err| {
+            trc::StoreEvent::PluginError
+                .into_err()
+                .details(err)
+                .ctx(trc::Key::Id, function)
+        })?;
+
+        match output.split_first() {
+            Some((&STATUS_OK, data)) => Ok(Reply::Ok(data.to_vec())),
+            Some((&STATUS_NOT_FOUND, _)) => Ok(Reply::NotFound),
+            Some((&STATUS_ERROR, message)) => Err(trc::StoreEvent::PluginError
+                .into_err()
+                .details(String::from_utf8_lossy(message).into_owned())
+                .ctx(trc::Key::Id, function)),
+            _ => Err(trc::StoreEvent::PluginError
+                .into_err()
+                .details("Invalid plugin reply")
+                .ctx(trc::Key::Id, function)),
+        }
+    }
+
+    fn prepare(&self, runtime: Handle) -> trc::Result<Store<PluginState>> {
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.inherit_stderr();
+        for (name, value) in &self.env {
+            wasi.env(name, value);
+        }
+        if let Some(directory) = &self.directory {
+            wasi.preopened_dir(directory, "/data", DirPerms::all(), FilePerms::all())
+                .map_err(|err| {
+                    trc::StoreEvent::PluginError
+                        .into_err()
+                        .details("Failed to open plugin directory")
+                        .reason(err)
+                })?;
+        }
+
+        let mut store = Store::new(
+            &ENGINE,
+            PluginState {
+                wasi: wasi.build_p1(),
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(self.memory)
+                    .instances(1)
+                    .build(),
+                http: self.http.clone(),
+                runtime,
+            },
+        );
+        store.limiter(|state: &mut PluginState| &mut state.limits);
+        store.set_fuel(self.fuel).map_err(|err| {
+            trc::StoreEvent::PluginError
+                .into_err()
+                .details("Failed to set fuel")
+                .reason(err)
+        })?;
+
+        Ok(store)
+    }
+}
+
+fn call_plugin(
+    mut store: Store<PluginState>,
+    module: &Module,
+    linker: &Linker<PluginState>,
+    function: &str,
+    key: &[u8],
+    data: &[u8],
+) -> Result<Vec<u8>, String> {
+    let instance = linker
+        .instantiate(&mut store, module)
+        .map_err(|err| format!("Failed to instantiate module: {err}"))?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| "Module does not export its memory".to_string())?;
+    let alloc = instance
+        .get_typed_func::<u32, u32>(&mut store, "alloc")
+        .map_err(|err| format!("Invalid alloc export: {err}"))?;
+
+    let copy_in = |store: &mut Store<PluginState>, bytes: &[u8]| {
+        let len = u32::try_from(bytes.len())
+            .map_err(|_| "Input exceeds the module address space".to_string())?;
+        let ptr = alloc
+            .call(&mut *store, len)
+            .map_err(|err| format!("Module failed to allocate memory: {err}"))?;
+        memory
+            .write(&mut *store, ptr as usize, bytes)
+            .map_err(|err| format!("Failed to write module input: {err}"))?;
+        Ok::<_, String>((ptr, len))
+    };
+    let (key_ptr, key_len) = copy_in(&mut store, key)?;
+
+    let result = if function == "blob_put" {
+        let (data_ptr, data_len) = copy_in(&mut store, data)?;
+        let entry: TypedFunc<(u32, u32, u32, u32), u64> = instance
+            .get_typed_func(&mut store, function)
+            .map_err(|err| format!("Invalid {function} export: {err}"))?;
+        entry.call(&mut store, (key_ptr, key_len, data_ptr, data_len))
+    } else {
+        let entry: TypedFunc<(u32, u32), u64> = instance
+            .get_typed_func(&mut store, function)
+            .map_err(|err| format!("Invalid {function} export: {err}"))?;
+        entry.call(&mut store, (key_ptr, key_len))
+    }
+    .map_err(|err| format!("Module {function} failed: {err}"))?;
+
+    let mut output = vec![0u8; (result & 0xFFFF_FFFF) as usize];
+    memory
+        .read(&store, (result >> 32) as usize, &mut output)
+        .map_err(|err| format!("Failed to read module output: {err}"))?;
+
+    Ok(output)
+}
+
+fn http_request(
+    mut caller: Caller<'_, PluginState>,
+    request_ptr: u32,
+    request_len: u32,
+    body_ptr: u32,
+    body_len: u32,
+) -> wasmtime::Result<u64> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| wasmtime::Error::msg("Module does not export its memory"))?;
+    let mut request = vec![0u8; request_len as usize];
+    memory.read(&caller, request_ptr as usize, &mut request)?;
+    let mut body = vec![0u8; body_len as usize];
+    memory.read(&caller, body_ptr as usize, &mut body)?;
+
+    let state = caller.data();
+    let output = match state
+        .http
+        .as_ref()
+        .ok_or_else(|| "Outgoing HTTP requests are not allowed".to_string())
+        .and_then(|http| http.send(&state.runtime, &request, body))
+    {
+        Ok((status, body)) => {
+            let mut output = Vec::with_capacity(body.len() + 3);
+            output.push(STATUS_OK);
+            output.extend_from_slice(&status.to_be_bytes());
+            output.extend_from_slice(&body);
+            output
+        }
+        Err(err) => {
+            let mut output = Vec::with_capacity(err.len() + 1);
+            output.push(STATUS_ERROR);
+            output.extend_from_slice(err.as_bytes());
+            output
+        }
+    };
+
+    // Copy the reply into the guest
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(|export| export.into_func())
+        .ok_or_else(|| wasmtime::Error::msg("Module does not export alloc"))?
+        .typed::<u32, u32>(&caller)?;
+    let output_ptr = alloc.call(&mut caller, output.len() as u32)?;
+    memory.write(&mut caller, output_ptr as usize, &output)?;
+
+    Ok(((output_ptr as u64) << 32) | output.len() as u64)
+}
+
+impl HttpBridge {
+    fn send(
+        &self,
+        runtime: &Handle,
+        request: &[u8],
+        body: Vec<u8>,
+    ) -> Result<(u16, Vec<u8>), String> {
+        let request: HttpRequest = serde_json::from_slice(request)
+            .map_err(|err| format!("Invalid HTTP request: {err}"))?;
+        let url = reqwest::Url::parse(&request.url)
+            .map_err(|err| format!("Invalid URL {:?}: {err}", request.url))?;
+        if !url
+            .host_str()
+            .is_some_and(|host| self.allowed_hosts.contains(&host.to_ascii_lowercase()))
+        {
+            return Err(format!("Host not allowed: {:?}", url.host_str()));
+        }
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .map_err(|err| format!("Invalid HTTP method {:?}: {err}", request.method))?;
+
+        runtime.block_on(async {
+            let mut builder = self.client.request(method, url);
+            for (name, value) in request.headers {
+                builder = builder.header(name, value);
+            }
+            if !body.is_empty() {
+                builder = builder.body(body);
+            }
+            let response = builder
+                .send()
+                .await
+                .map_err(|err| format!("HTTP request failed: {err}"))?;
+            let status = response.status().as_u16();
+            if response
+                .content_length()
+                .is_some_and(|size| size as usize > self.max_size)
+            {
+                return Err("HTTP response is too large".to_string());
+            }
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|err| format!("Failed to read HTTP response: {err}"))?;
+            if bytes.len() > self.max_size {
+                return Err("HTTP response is too large".to_string());
+            }
+
+            Ok((status, bytes.to_vec()))
+        })
+    }
+}
@@ -4,12 +4,15 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::sync::Arc;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
 
 use utils::config::{cron::SimpleCron, utils::ParseValue, Config};
 
 use crate::{
-    backend::fs::FsStore,
+    backend::{fs::FsStore, memory::file::FileStore},
     write::purge::{PurgeSchedule, PurgeStore},
     BlobStore, CompressionAlgo, LookupStore, QueryStore, Store, Stores,
 };
@@ -38,9 +41,18 @@ use crate::backend::elastic::ElasticSearchStore;
 #[cfg(feature = "redis")]
 use crate::backend::redis::RedisStore;
 
+#[cfg(feature = "etcd")]
+use crate::backend::etcd::EtcdStore;
+
 #[cfg(feature = "azure")]
 use crate::backend::azure::AzureStore;
 
+#[cfg(feature = "gcs")]
+use crate::backend::gcs::GcsStore;
+
+#[cfg(feature = "wasm")]
+use crate::backend::wasm::WasmStore;
+
 impl Stores {
     pub async fn parse_all(config: &mut Config) -> Self {
         let mut stores = Self::parse(config).await;
@@ -82,6 +94,19 @@ impl Stores {
             };
             let prefix = ("store", id);
             let store_id = id.to_string();
+
+            // Reuse stores whose settings did not change, otherwise a new instance
+            // is built while in-flight operations finish using the previous one.
+            // Composite stores are always rebuilt as their backends might have changed.
+            let settings = config.store_settings(id);
+            if is_reload
+                && self.settings.get(id) == Some(&settings)
+                && self.contains_store(id)
+                && !is_composite_store(&protocol)
+            {
+                continue;
+            }
+
             let compression_algo = config
                 .property_or_default::<CompressionAlgo>(("store", id, "compression"), "none")
                 .unwrap_or(CompressionAlgo::None);
@@ -96,6 +121,12 @@ impl Stores {
                             .values()
                             .any(|store| matches!(store, Store::RocksDb(_)))
                     {
+                        if self.stores.contains_key(id) {
+                            config.new_build_warning(
+                                prefix,
+                                "Changes to this store require a restart to take effect",
+                            );
+                        }
                         continue;
                     }
 
@@ -118,6 +149,12 @@ impl Stores {
                             .values()
                             .any(|store| matches!(store, Store::FoundationDb(_)))
                     {
+                        if self.stores.contains_key(id) {
+                            config.new_build_warning(
+                                prefix,
+                                "Changes to this store require a restart to take effect",
+                            );
+                        }
                         continue;
                     }
 
@@ -132,7 +169,7 @@ impl Stores {
                     }
                 }
                 #[cfg(feature = "postgres")]
-                "postgresql" => {
+                "postgresql" | "cockroachdb" => {
                     if let Some(db) =
                         PostgresStore::open(config, prefix, config.is_active_store(id))
                             .await
@@ -171,6 +208,12 @@ impl Stores {
                             .values()
                             .any(|store| matches!(store, Store::SQLite(_)))
                     {
+                        if self.stores.contains_key(id) {
+                            config.new_build_warning(
+                                prefix,
+                                "Changes to this store require a restart to take effect",
+                            );
+                        }
                         continue;
                     }
 
@@ -184,6 +227,12 @@ impl Stores {
                         self.lookup_stores.insert(store_id.clone(), db.into());
                     }
                 }
+                "file" => {
+                    if let Some(db) = FileStore::open(config, prefix) {
+                        self.lookup_stores
+                            .insert(store_id, LookupStore::File(Arc::new(db)));
+                    }
+                }
                 "fs" => {
                     if let Some(db) = FsStore::open(config, prefix).await.map(BlobStore::from) {
                         self.blob_stores
@@ -215,8 +264,14 @@ impl Stores {
                         self.lookup_stores.insert(store_id, db);
                     }
                 }
+                #[cfg(feature = "etcd")]
+                "etcd" => {
+                    if let Some(db) = EtcdStore::open(config, prefix).await.map(LookupStore::from) {
+                        self.lookup_stores.insert(store_id, db);
+                    }
+                }
                 #[cfg(feature = "enterprise")]
-                "sql-read-replica" | "distributed-blob" => {
+                "sql-read-replica" | "distributed-blob" | "tiered-blob" | "replicated-blob" => {
                     composite_stores.push((store_id, protocol));
                 }
                 #[cfg(feature = "azure")]
@@ -226,6 +281,20 @@ impl Stores {
                             .insert(store_id, db.with_compression(compression_algo));
                     }
                 }
+                #[cfg(feature = "gcs")]
+                "gcs" => {
+                    if let Some(db) = GcsStore::open(config, prefix).await.map(BlobStore::from) {
+                        self.blob_stores
+                            .insert(store_id, db.with_compression(compression_algo));
+                    }
+                }
+                #[cfg(feature = "wasm")]
+                "wasm" => {
+                    if let Some(db) = WasmStore::open(config, prefix).await.map(BlobStore::from) {
+                        self.blob_stores
+                            .insert(store_id, db.with_compression(compression_algo));
+                    }
+                }
                 unknown => {
                     config.new_parse_warning(
                         ("store", id, "type"),
@@ -233,6 +302,8 @@ impl Stores {
                     );
                 }
             }
+
+            self.settings.insert(id.to_string(), settings);
         }
 
         #[cfg(feature = "enterprise")]
@@ -278,6 +349,30 @@ impl Stores {
                         self.blob_stores.insert(id, store);
                     }
                 }
+                "tiered-blob" => {
+                    if let Some(db) = crate::backend::composite::tiered_blob::TieredBlob::open(
+                        config, prefix, self,
+                    ) {
+                        let store = BlobStore {
+                            backend: crate::BlobBackend::Tiered(db.into()),
+                            compression,
+                        };
+                        self.blob_stores.insert(id, store);
+                    }
+                }
+                "replicated-blob" => {
+                    if let Some(db) =
+                        crate::backend::composite::replicated_blob::ReplicatedBlob::open(
+                            config, prefix, self,
+                        )
+                    {
+                        let store = BlobStore {
+                            backend: crate::BlobBackend::Replicated(db.into()),
+                            compression,
+                        };
+                        self.blob_stores.insert(id, store);
+                    }
+                }
                 _ => (),
             }
         }
@@ -378,6 +473,41 @@ impl Stores {
     }
 }
 
+impl Stores {
+    fn contains_store(&self, id: &str) -> bool {
+        self.stores.contains_key(id)
+            || self.blob_stores.contains_key(id)
+            || self.fts_stores.contains_key(id)
+            || self.lookup_stores.contains_key(id)
+    }
+}
+
+fn is_composite_store(protocol: &str) -> bool {
+    matches!(
+        protocol,
+        "sql-read-replica" | "distributed-blob" | "tiered-blob" | "replicated-blob"
+    )
+}
+
+trait StoreSettings {
+    fn store_settings(&self, id: &str) -> u64;
+}
+
+impl StoreSettings for Config {
+    // Hashes the connection settings of a store, lookup queries and
+    // purge schedules are excluded as they do not require reconnecting
+    fn store_settings(&self, id: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (key, value) in self.iterate_prefix(("store", id)) {
+            if !key.starts_with("query.") && !key.starts_with("purge.") {
+                key.hash(&mut hasher);
+                value.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
 #[allow(dead_code)]
 trait IsActiveStore {
     fn is_active_store(&self, id: &str) -> bool;
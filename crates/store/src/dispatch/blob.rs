@@ -15,8 +15,39 @@ impl BlobStore {
     pub async fn get_blob(&self, key: &[u8], range: Range<usize>) -> trc::Result<Option<Vec<u8>>> {
         let read_range = match self.compression {
             CompressionAlgo::None => range.clone(),
-            CompressionAlgo::Lz4 => 0..usize::MAX,
+            CompressionAlgo::Lz4 | CompressionAlgo::Zstd => 0..usize::MAX,
         };
+        let decompressed = match self.compression {
+            CompressionAlgo::None => return self.get_stored_blob(key, read_range).await,
+            CompressionAlgo::Lz4 | CompressionAlgo::Zstd => {
+                match self
+                    .get_stored_blob(key, read_range)
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    Some(data) => decompress(key, data)?,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        if range.end > decompressed.len() {
+            Ok(Some(decompressed))
+        } else {
+            Ok(Some(
+                decompressed
+                    .get(range.start..range.end)
+                    .unwrap_or_default()
+                    .to_vec(),
+            ))
+        }
+    }
+
+    async fn get_stored_blob(
+        &self,
+        key: &[u8],
+        read_range: Range<usize>,
+    ) -> trc::Result<Option<Vec<u8>>> {
         let start_time = Instant::now();
         let result = match &self.backend {
             BlobBackend::Store(store) => match store {
@@ -39,8 +70,16 @@ impl BlobStore {
             BlobBackend::S3(store) => store.get_blob(key, read_range).await,
             #[cfg(feature = "azure")]
             BlobBackend::Azure(store) => store.get_blob(key, read_range).await,
+            #[cfg(feature = "gcs")]
+            BlobBackend::Gcs(store) => store.get_blob(key, read_range).await,
+            #[cfg(feature = "wasm")]
+            BlobBackend::Wasm(store) => store.get_blob(key, read_range).await,
             #[cfg(feature = "enterprise")]
             BlobBackend::Composite(store) => store.get_blob(key, read_range).await,
+            #[cfg(feature = "enterprise")]
+            BlobBackend::Tiered(store) => store.get_blob(key, read_range).await,
+            #[cfg(feature = "enterprise")]
+            BlobBackend::Replicated(store) => store.get_blob(key, read_range).await,
         };
 
         trc::event!(
@@ -52,77 +91,83 @@ impl BlobStore {
                 .map_or(0, |data| data.as_ref().map_or(0, |data| data.len())),
         );
 
-        let decompressed = match self.compression {
-            CompressionAlgo::Lz4 => match result.caused_by(trc::location!())? {
-                Some(data)
-                    if data.last().copied().unwrap_or_default()
-                        == CompressionAlgo::Lz4.marker() =>
-                {
-                    lz4_flex::decompress_size_prepended(
-                        data.get(..data.len() - 1).unwrap_or_default(),
-                    )
-                    .map_err(|err| {
-                        trc::StoreEvent::DecompressError
-                            .reason(err)
-                            .ctx(trc::Key::Key, key)
-                            .ctx(trc::Key::CausedBy, trc::location!())
-                    })?
-                }
-                Some(data) => {
-                    trc::event!(Store(StoreEvent::BlobMissingMarker), Key = key,);
-                    data
-                }
-                None => return Ok(None),
-            },
-            _ => return result,
-        };
-
-        if range.end > decompressed.len() {
-            Ok(Some(decompressed))
-        } else {
-            Ok(Some(
-                decompressed
-                    .get(range.start..range.end)
-                    .unwrap_or_default()
-                    .to_vec(),
-            ))
-        }
+        result
     }
 
     pub async fn put_blob(&self, key: &[u8], data: &[u8]) -> trc::Result<()> {
         let data: Cow<[u8]> = match self.compression {
             CompressionAlgo::None => data.into(),
-            CompressionAlgo::Lz4 => {
-                let mut compressed = lz4_flex::compress_prepend_size(data);
-                compressed.push(CompressionAlgo::Lz4.marker());
-                compressed.into()
-            }
+            algo => algo.compress(data, zstd::DEFAULT_COMPRESSION_LEVEL)?.into(),
+        };
+
+        self.put_stored_blob(key, data.as_ref()).await
+    }
+
+    /// Recompresses a blob with Zstandard at a high compression level and
+    /// returns its stored size before and after. Missing blobs and blobs
+    /// already compressed with Zstandard are skipped.
+    pub async fn archive_blob(&self, key: &[u8]) -> trc::Result<Option<(usize, usize)>> {
+        if matches!(self.compression, CompressionAlgo::None) {
+            return Err(trc::StoreEvent::NotSupported
+                .into_err()
+                .details("Blob compression is disabled"));
+        }
+
+        let stored = match self
+            .get_stored_blob(key, 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?
+        {
+            Some(data) if data.last().copied() != Some(CompressionAlgo::Zstd.marker()) => data,
+            _ => return Ok(None),
         };
+        let stored_size = stored.len();
+        let compressed =
+            CompressionAlgo::Zstd.compress(&decompress(key, stored)?, ARCHIVE_LEVEL)?;
 
+        if compressed.len() < stored_size {
+            self.put_stored_blob(key, &compressed)
+                .await
+                .caused_by(trc::location!())?;
+            Ok(Some((stored_size, compressed.len())))
+        } else {
+            Ok(Some((stored_size, stored_size)))
+        }
+    }
+
+    async fn put_stored_blob(&self, key: &[u8], data: &[u8]) -> trc::Result<()> {
         let start_time = Instant::now();
         let result = match &self.backend {
             BlobBackend::Store(store) => match store {
                 #[cfg(feature = "sqlite")]
-                Store::SQLite(store) => store.put_blob(key, data.as_ref()).await,
+                Store::SQLite(store) => store.put_blob(key, data).await,
                 #[cfg(feature = "foundation")]
-                Store::FoundationDb(store) => store.put_blob(key, data.as_ref()).await,
+                Store::FoundationDb(store) => store.put_blob(key, data).await,
                 #[cfg(feature = "postgres")]
-                Store::PostgreSQL(store) => store.put_blob(key, data.as_ref()).await,
+                Store::PostgreSQL(store) => store.put_blob(key, data).await,
                 #[cfg(feature = "mysql")]
-                Store::MySQL(store) => store.put_blob(key, data.as_ref()).await,
+                Store::MySQL(store) => store.put_blob(key, data).await,
                 #[cfg(feature = "rocks")]
-                Store::RocksDb(store) => store.put_blob(key, data.as_ref()).await,
+                Store::RocksDb(store) => store.put_blob(key, data).await,
                 #[cfg(all(feature = "enterprise", any(feature = "postgres", feature = "mysql")))]
-                Store::SQLReadReplica(store) => store.put_blob(key, data.as_ref()).await,
+                Store::SQLReadReplica(store) => store.put_blob(key, data).await,
                 Store::None => Err(trc::StoreEvent::NotConfigured.into()),
             },
-            BlobBackend::Fs(store) => store.put_blob(key, data.as_ref()).await,
+            BlobBackend::Fs(store) => store.put_blob(key, data).await,
             #[cfg(feature = "s3")]
-            BlobBackend::S3(store) => store.put_blob(key, data.as_ref()).await,
+            BlobBackend::S3(store) => store.put_blob(key, data).await,
             #[cfg(feature = "azure")]
-            BlobBackend::Azure(store) => store.put_blob(key, data.as_ref()).await,
+            BlobBackend::Azure(store) => store.put_blob(key, data).await,
+            #[cfg(feature = "gcs")]
+            BlobBackend::Gcs(store) => store.put_blob(key, data).await,
+            #[cfg(feature = "wasm")]
+            BlobBackend::Wasm(store) => store.put_blob(key, data).await,
             #[cfg(feature = "enterprise")]
-            BlobBackend::Composite(store) => store.put_blob(key, data.as_ref()).await,
+            BlobBackend::Composite(store) => store.put_blob(key, data).await,
+            #[cfg(feature = "enterprise")]
+            BlobBackend::Tiered(store) => store.put_blob(key, data).await,
+            #[cfg(feature = "enterprise")]
+            BlobBackend::Replicated(store) => store.put_blob(key, data).await,
         }
         .caused_by(trc::location!());
 
@@ -159,8 +204,16 @@ impl BlobStore {
             BlobBackend::S3(store) => store.delete_blob(key).await,
             #[cfg(feature = "azure")]
             BlobBackend::Azure(store) => store.delete_blob(key).await,
+            #[cfg(feature = "gcs")]
+            BlobBackend::Gcs(store) => store.delete_blob(key).await,
+            #[cfg(feature = "wasm")]
+            BlobBackend::Wasm(store) => store.delete_blob(key).await,
             #[cfg(feature = "enterprise")]
             BlobBackend::Composite(store) => store.delete_blob(key).await,
+            #[cfg(feature = "enterprise")]
+            BlobBackend::Tiered(store) => store.delete_blob(key).await,
+            #[cfg(feature = "enterprise")]
+            BlobBackend::Replicated(store) => store.delete_blob(key).await,
         }
         .caused_by(trc::location!());
 
@@ -182,22 +235,64 @@ impl BlobStore {
 }
 
 const MAGIC_MARKER: u8 = 0xa0;
+const ARCHIVE_LEVEL: i32 = 19;
 
 impl CompressionAlgo {
     pub fn marker(&self) -> u8 {
         match self {
             CompressionAlgo::Lz4 => MAGIC_MARKER | 0x01,
-            //CompressionAlgo::Zstd => MAGIC_MARKER | 0x02,
+            CompressionAlgo::Zstd => MAGIC_MARKER | 0x02,
             CompressionAlgo::None => 0,
         }
     }
+
+    fn compress(&self, data: &[u8], level: i32) -> trc::Result<Vec<u8>> {
+        let mut compressed = match self {
+            CompressionAlgo::Lz4 => lz4_flex::compress_prepend_size(data),
+            CompressionAlgo::Zstd => zstd::bulk::compress(data, level).map_err(|err| {
+                trc::StoreEvent::UnexpectedError
+                    .reason(err)
+                    .details("Failed to compress blob")
+                    .ctx(trc::Key::CausedBy, trc::location!())
+            })?,
+            CompressionAlgo::None => return Ok(data.to_vec()),
+        };
+        compressed.push(self.marker());
+        Ok(compressed)
+    }
+}
+
+// Blobs are decompressed based on their marker rather than the configured
+// algorithm, which allows archived blobs to be read back by any store
+// that has compression enabled.
+fn decompress(key: &[u8], data: Vec<u8>) -> trc::Result<Vec<u8>> {
+    let marker = data.last().copied().unwrap_or_default();
+    let compressed = data.get(..data.len().saturating_sub(1)).unwrap_or_default();
+    if marker == CompressionAlgo::Lz4.marker() {
+        lz4_flex::decompress_size_prepended(compressed).map_err(|err| {
+            trc::StoreEvent::DecompressError
+                .reason(err)
+                .ctx(trc::Key::Key, key)
+                .ctx(trc::Key::CausedBy, trc::location!())
+        })
+    } else if marker == CompressionAlgo::Zstd.marker() {
+        zstd::stream::decode_all(compressed).map_err(|err| {
+            trc::StoreEvent::DecompressError
+                .reason(err)
+                .ctx(trc::Key::Key, key)
+                .ctx(trc::Key::CausedBy, trc::location!())
+        })
+    } else {
+        trc::event!(Store(StoreEvent::BlobMissingMarker), Key = key,);
+        Ok(data)
+    }
 }
 
 impl ParseValue for CompressionAlgo {
     fn parse_value(value: &str) -> Result<Self, String> {
         match value {
             "lz4" => Ok(CompressionAlgo::Lz4),
-            //"zstd" => Ok(CompressionAlgo::Zstd),
+            "zstd" => Ok(CompressionAlgo::Zstd),
             "none" | "false" | "disable" | "disabled" => Ok(CompressionAlgo::None),
             algo => Err(format!("Invalid compression algorithm: {algo}",)),
         }
@@ -68,6 +68,8 @@ impl LookupStore {
             }
             #[cfg(feature = "redis")]
             LookupStore::Redis(store) => store.key_set(key, value, expires).await,
+            #[cfg(feature = "etcd")]
+            LookupStore::Etcd(store) => store.key_set(key, value, expires).await,
             LookupStore::Query(lookup) => lookup
                 .store
                 .query::<usize>(
@@ -76,7 +78,9 @@ impl LookupStore {
                 )
                 .await
                 .map(|_| ()),
-            LookupStore::Memory(_) => Err(trc::StoreEvent::NotSupported.into_err()),
+            LookupStore::Memory(_) | LookupStore::File(_) => {
+                Err(trc::StoreEvent::NotSupported.into_err())
+            }
         }
         .caused_by(trc::location!())
     }
@@ -124,7 +128,9 @@ impl LookupStore {
             }
             #[cfg(feature = "redis")]
             LookupStore::Redis(store) => store.key_incr(key, value, expires).await,
-            LookupStore::Query(_) | LookupStore::Memory(_) => {
+            #[cfg(feature = "etcd")]
+            LookupStore::Etcd(store) => store.key_incr(key, value, expires).await,
+            LookupStore::Query(_) | LookupStore::Memory(_) | LookupStore::File(_) => {
                 Err(trc::StoreEvent::NotSupported.into_err())
             }
         }
@@ -143,7 +149,9 @@ impl LookupStore {
             }
             #[cfg(feature = "redis")]
             LookupStore::Redis(store) => store.key_delete(key).await,
-            LookupStore::Query(_) | LookupStore::Memory(_) => {
+            #[cfg(feature = "etcd")]
+            LookupStore::Etcd(store) => store.key_delete(key).await,
+            LookupStore::Query(_) | LookupStore::Memory(_) | LookupStore::File(_) => {
                 Err(trc::StoreEvent::NotSupported.into_err())
             }
         }
@@ -162,7 +170,9 @@ impl LookupStore {
             }
             #[cfg(feature = "redis")]
             LookupStore::Redis(store) => store.key_delete(key).await,
-            LookupStore::Query(_) | LookupStore::Memory(_) => {
+            #[cfg(feature = "etcd")]
+            LookupStore::Etcd(store) => store.key_delete(key).await,
+            LookupStore::Query(_) | LookupStore::Memory(_) | LookupStore::File(_) => {
                 Err(trc::StoreEvent::NotSupported.into_err())
             }
         }
@@ -182,6 +192,8 @@ impl LookupStore {
                 .map(|value| value.and_then(|v| v.into())),
             #[cfg(feature = "redis")]
             LookupStore::Redis(store) => store.key_get(key).await,
+            #[cfg(feature = "etcd")]
+            LookupStore::Etcd(store) => store.key_get(key).await,
             LookupStore::Query(lookup) => lookup
                 .store
                 .query::<Option<Row>>(
@@ -196,6 +208,9 @@ impl LookupStore {
             LookupStore::Memory(store) => Ok(store
                 .get(std::str::from_utf8(&key).unwrap_or_default())
                 .map(|value| T::from(value.clone()))),
+            LookupStore::File(store) => Ok(store
+                .get(std::str::from_utf8(&key).unwrap_or_default())
+                .map(T::from)),
         }
         .caused_by(trc::location!())
     }
@@ -211,7 +226,9 @@ impl LookupStore {
             }
             #[cfg(feature = "redis")]
             LookupStore::Redis(store) => store.counter_get(key).await,
-            LookupStore::Query(_) | LookupStore::Memory(_) => {
+            #[cfg(feature = "etcd")]
+            LookupStore::Etcd(store) => store.counter_get(key).await,
+            LookupStore::Query(_) | LookupStore::Memory(_) | LookupStore::File(_) => {
                 Err(trc::StoreEvent::NotSupported.into_err())
             }
         }
@@ -228,6 +245,8 @@ impl LookupStore {
                 .map(|value| matches!(value, Some(LookupValue::Value(())))),
             #[cfg(feature = "redis")]
             LookupStore::Redis(store) => store.key_exists(key).await,
+            #[cfg(feature = "etcd")]
+            LookupStore::Etcd(store) => store.key_exists(key).await,
             LookupStore::Query(lookup) => lookup
                 .store
                 .query::<Option<Row>>(
@@ -239,6 +258,9 @@ impl LookupStore {
             LookupStore::Memory(store) => Ok(store
                 .get(std::str::from_utf8(&key).unwrap_or_default())
                 .is_some()),
+            LookupStore::File(store) => Ok(store
+                .get(std::str::from_utf8(&key).unwrap_or_default())
+                .is_some()),
         }
         .caused_by(trc::location!())
     }
@@ -254,9 +276,7 @@ impl LookupStore {
         let range_end = (range_start * rate.period.as_secs()) + rate.period.as_secs();
         let expires_in = range_end - now;
 
-        let mut bucket = Vec::with_capacity(key.len() + U64_LEN);
-        bucket.extend_from_slice(key);
-        bucket.extend_from_slice(range_start.to_be_bytes().as_slice());
+        let bucket = self.bucket_key(key, range_start.to_be_bytes().as_slice());
 
         let requests = if !soft_check {
             self.counter_incr(bucket, 1, expires_in.into(), true)
@@ -273,6 +293,19 @@ impl LookupStore {
         }
     }
 
+    fn bucket_key(&self, key: &[u8], bucket: &[u8]) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "redis")]
+            LookupStore::Redis(store) => store.bucket_key(key, bucket),
+            _ => {
+                let mut result = Vec::with_capacity(key.len() + bucket.len());
+                result.extend_from_slice(key);
+                result.extend_from_slice(bucket);
+                result
+            }
+        }
+    }
+
     pub async fn purge_lookup_store(&self) -> trc::Result<()> {
         match self {
             LookupStore::Store(store) => {
@@ -355,7 +388,9 @@ impl LookupStore {
             }
             #[cfg(feature = "redis")]
             LookupStore::Redis(_) => {}
-            LookupStore::Query(_) | LookupStore::Memory(_) => {}
+            #[cfg(feature = "etcd")]
+            LookupStore::Etcd(_) => {}
+            LookupStore::Query(_) | LookupStore::Memory(_) | LookupStore::File(_) => {}
         }
 
         Ok(())
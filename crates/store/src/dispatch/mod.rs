@@ -12,6 +12,8 @@ pub mod blob;
 pub mod fts;
 pub mod lookup;
 pub mod store;
+pub mod stream;
+pub mod usage;
 
 impl Store {
     pub fn id(&self) -> &'static str {
@@ -6,6 +6,7 @@
 
 use std::{
     ops::{BitAndAssign, Range},
+    path::PathBuf,
     time::Instant,
 };
 
@@ -15,11 +16,12 @@ use trc::{AddContext, StoreEvent};
 use crate::{
     write::{
         key::{DeserializeBigEndian, KeySerializer},
-        now, AnyClass, AnyKey, AssignedIds, Batch, BatchBuilder, BitmapClass, BitmapHash,
+        now, AnyClass, AnyKey, AssignedIds, Batch, BatchBuilder, BitmapClass, DirectoryClass,
         Operation, ReportClass, ValueClass, ValueOp,
     },
-    BitmapKey, Deserialize, IterateParams, Key, Store, ValueKey, SUBSPACE_BITMAP_ID,
-    SUBSPACE_BITMAP_TAG, SUBSPACE_BITMAP_TEXT, SUBSPACE_INDEXES, SUBSPACE_LOGS, U32_LEN,
+    BitmapKey, Deserialize, IterateParams, Key, Store, ValueKey, SUBSPACE_ACL, SUBSPACE_BITMAP_ID,
+    SUBSPACE_BITMAP_TAG, SUBSPACE_BITMAP_TEXT, SUBSPACE_FTS_INDEX, SUBSPACE_INDEXES, SUBSPACE_LOGS,
+    U32_LEN,
 };
 
 use super::DocumentSet;
@@ -154,7 +156,9 @@ impl Store {
         .caused_by(trc::location!())
     }
 
-    pub async fn write(&self, batch: Batch) -> trc::Result<AssignedIds> {
+    pub async fn write(&self, mut batch: Batch) -> trc::Result<AssignedIds> {
+        batch.advance_watermarks();
+
         #[cfg(feature = "test_mode")]
         if std::env::var("PARANOID_WRITE").map_or(false, |v| v == "1") {
             let mut account_id = u32::MAX;
@@ -303,6 +307,18 @@ impl Store {
         )
         .await
         .caused_by(trc::location!())?;
+        self.delete_range(
+            ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                id: 0,
+                expires: 0,
+            })),
+            ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                id: u64::MAX,
+                expires: now,
+            })),
+        )
+        .await
+        .caused_by(trc::location!())?;
 
         match self {
             #[cfg(feature = "sqlite")]
@@ -341,6 +357,54 @@ impl Store {
         .caused_by(trc::location!())
     }
 
+    /// Copies the store to a new database file while the server is running.
+    /// Only supported by SQLite.
+    #[allow(unreachable_patterns)]
+    #[allow(unused_variables)]
+    pub async fn backup(&self, dest: PathBuf) -> trc::Result<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::SQLite(store) => store.backup(dest).await,
+            Self::None => Err(trc::StoreEvent::NotConfigured.into()),
+            _ => Err(trc::StoreEvent::NotSupported
+                .into_err()
+                .details("Online backups are only supported by SQLite")),
+        }
+        .caused_by(trc::location!())
+    }
+
+    /// Publishes a message to the other nodes sharing the store.
+    /// Only supported by PostgreSQL.
+    #[allow(unreachable_patterns)]
+    #[allow(unused_variables)]
+    pub async fn notify(&self, channel: &str, payload: &str) -> trc::Result<()> {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::PostgreSQL(store) => store.notify(channel, payload).await,
+            Self::None => Err(trc::StoreEvent::NotConfigured.into()),
+            _ => Err(trc::StoreEvent::NotSupported
+                .into_err()
+                .details("Notifications are only supported by PostgreSQL")),
+        }
+        .caused_by(trc::location!())
+    }
+
+    /// Subscribes to the messages published on a channel by any node sharing the store.
+    /// Only supported by PostgreSQL.
+    #[allow(unreachable_patterns)]
+    #[allow(unused_variables)]
+    pub async fn listen(&self, channel: &str) -> trc::Result<tokio::sync::mpsc::Receiver<String>> {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::PostgreSQL(store) => store.listen(channel).await,
+            Self::None => Err(trc::StoreEvent::NotConfigured.into()),
+            _ => Err(trc::StoreEvent::NotSupported
+                .into_err()
+                .details("Notifications are only supported by PostgreSQL")),
+        }
+        .caused_by(trc::location!())
+    }
+
     pub async fn delete_documents(
         &self,
         subspace: u8,
@@ -421,12 +485,62 @@ impl Store {
     }
 
     pub async fn purge_account(&self, account_id: u32) -> trc::Result<()> {
+        // Blob links are keyed by hash, unlink them before the range deletes
+        self.blob_hash_unlink_account(account_id)
+            .await
+            .caused_by(trc::location!())?;
+
+        // ACLs are keyed by grantee, collect the ones on documents owned by this account
+        let mut acls = Vec::new();
+        self.iterate(
+            IterateParams::new(
+                AnyKey {
+                    subspace: SUBSPACE_ACL,
+                    key: KeySerializer::new(U32_LEN).write(0u32).finalize(),
+                },
+                AnyKey {
+                    subspace: SUBSPACE_ACL,
+                    key: KeySerializer::new(U32_LEN).write(u32::MAX).finalize(),
+                },
+            )
+            .no_values(),
+            |key, _| {
+                if key.deserialize_be_u32(U32_LEN)? == account_id {
+                    acls.push((
+                        key.deserialize_be_u32(0)?,
+                        key.get(U32_LEN * 2).copied().ok_or_else(|| {
+                            trc::Error::corrupted_key(key, None, trc::location!())
+                        })?,
+                        key.deserialize_be_u32(U32_LEN * 2 + 1)?,
+                    ));
+                }
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+        for acls in acls.chunks(1000) {
+            let mut batch = BatchBuilder::new();
+            batch.with_account_id(account_id);
+            for (grant_account_id, collection, document_id) in acls {
+                batch
+                    .with_collection(*collection)
+                    .update_document(*document_id)
+                    .clear(ValueClass::Acl(*grant_account_id));
+            }
+            self.write(batch.build())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
         for subspace in [
             SUBSPACE_BITMAP_ID,
             SUBSPACE_BITMAP_TAG,
             SUBSPACE_BITMAP_TEXT,
             SUBSPACE_LOGS,
             SUBSPACE_INDEXES,
+            SUBSPACE_ACL,
+            SUBSPACE_FTS_INDEX,
         ] {
             self.delete_range(
                 AnyKey {
@@ -442,37 +556,22 @@ impl Store {
             .caused_by(trc::location!())?;
         }
 
-        for (from_class, to_class) in [
-            (ValueClass::Acl(account_id), ValueClass::Acl(account_id + 1)),
-            (ValueClass::Property(0), ValueClass::Property(0)),
-            (
-                ValueClass::FtsIndex(BitmapHash {
-                    hash: [0u8; 8],
-                    len: 0,
-                }),
-                ValueClass::FtsIndex(BitmapHash {
-                    hash: [u8::MAX; 8],
-                    len: u8::MAX,
-                }),
-            ),
-        ] {
-            self.delete_range(
-                ValueKey {
-                    account_id,
-                    collection: 0,
-                    document_id: 0,
-                    class: from_class,
-                },
-                ValueKey {
-                    account_id: account_id + 1,
-                    collection: 0,
-                    document_id: 0,
-                    class: to_class,
-                },
-            )
-            .await
-            .caused_by(trc::location!())?;
-        }
+        self.delete_range(
+            ValueKey {
+                account_id,
+                collection: 0,
+                document_id: 0,
+                class: ValueClass::Property(0),
+            },
+            ValueKey {
+                account_id: account_id + 1,
+                collection: 0,
+                document_id: 0,
+                class: ValueClass::Property(0),
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
 
         // Delete property counters (TODO: make this more elegant)
         self.delete_range(
@@ -492,6 +591,13 @@ impl Store {
         .await
         .caused_by(trc::location!())?;
 
+        // Reset the backup watermark
+        let mut batch = BatchBuilder::new();
+        batch.clear(DirectoryClass::BackupWatermark(account_id));
+        self.write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
         Ok(())
     }
 
@@ -738,6 +844,20 @@ impl Store {
         self.purge_blobs(blob_store).await.unwrap();
         self.purge_store().await.unwrap();
 
+        // Backup watermarks are kept after the data they track is deleted
+        self.delete_range(
+            AnyKey {
+                subspace: SUBSPACE_QUOTA,
+                key: &[7u8],
+            },
+            AnyKey {
+                subspace: SUBSPACE_QUOTA,
+                key: &[8u8],
+            },
+        )
+        .await
+        .unwrap();
+
         let store = self.clone();
         let mut failed = false;
 
@@ -0,0 +1,115 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::VecDeque;
+
+use futures::{stream, Stream};
+use trc::AddContext;
+
+use crate::{write::AnyKey, IterateParams, Key, Store};
+
+pub const DEFAULT_CHUNK_SIZE: usize = 1000;
+
+struct StreamState {
+    store: Store,
+    subspace: u8,
+    begin: Vec<u8>,
+    end: Vec<u8>,
+    ascending: bool,
+    values: bool,
+    chunk_size: usize,
+    buffer: VecDeque<(Vec<u8>, Vec<u8>)>,
+    last_key: Option<Vec<u8>>,
+    is_done: bool,
+}
+
+impl Store {
+    /// Streams the key-value pairs in a range. Keys are fetched in chunks of
+    /// `chunk_size` entries, each with its own iteration, so only one chunk is
+    /// held in memory and no read transaction is kept open while the caller
+    /// consumes the stream. The task yields to the runtime after every chunk.
+    pub fn stream<T: Key>(
+        &self,
+        params: IterateParams<T>,
+        chunk_size: usize,
+    ) -> impl Stream<Item = trc::Result<(Vec<u8>, Vec<u8>)>> + Send + 'static {
+        let state = StreamState {
+            store: self.clone(),
+            subspace: params.begin.subspace(),
+            begin: params.begin.serialize(0),
+            end: params.end.serialize(0),
+            ascending: params.ascending,
+            values: params.values,
+            chunk_size: chunk_size.max(1),
+            buffer: VecDeque::new(),
+            last_key: None,
+            is_done: false,
+        };
+
+        stream::try_unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.buffer.pop_front() {
+                    return Ok(Some((item, state)));
+                } else if state.is_done {
+                    return Ok(None);
+                }
+
+                state.fetch_chunk().await?;
+                tokio::task::yield_now().await;
+            }
+        })
+    }
+}
+
+impl StreamState {
+    async fn fetch_chunk(&mut self) -> trc::Result<()> {
+        // Resume right after the last key returned. Descending scans cannot
+        // express the key preceding it, so it is fetched again and skipped.
+        let (begin, end, skip_key) = match (self.last_key.take(), self.ascending) {
+            (Some(mut last_key), true) => {
+                last_key.push(0);
+                (last_key, self.end.clone(), None)
+            }
+            (Some(last_key), false) => (self.begin.clone(), last_key.clone(), Some(last_key)),
+            (None, _) => (self.begin.clone(), self.end.clone(), None),
+        };
+        if begin > end {
+            self.is_done = true;
+            return Ok(());
+        }
+
+        let chunk_size = self.chunk_size;
+        let buffer = &mut self.buffer;
+        self.store
+            .iterate(
+                IterateParams::new(
+                    AnyKey {
+                        subspace: self.subspace,
+                        key: begin,
+                    },
+                    AnyKey {
+                        subspace: self.subspace,
+                        key: end,
+                    },
+                )
+                .set_ascending(self.ascending)
+                .set_values(self.values),
+                |key, value| {
+                    if skip_key.as_deref() != Some(key) {
+                        buffer.push_back((key.to_vec(), value.to_vec()));
+                    }
+                    Ok(buffer.len() < chunk_size)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        self.is_done = self.buffer.len() < chunk_size;
+        self.last_key = self.buffer.back().map(|(key, _)| key.clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+use trc::AddContext;
+
+use crate::{
+    write::AnyKey, IterateParams, Store, SUBSPACE_ACL, SUBSPACE_BITMAP_ID, SUBSPACE_BITMAP_TAG,
+    SUBSPACE_BITMAP_TEXT, SUBSPACE_BLOBS, SUBSPACE_BLOB_LINK, SUBSPACE_BLOB_RESERVE,
+    SUBSPACE_COUNTER, SUBSPACE_DIRECTORY, SUBSPACE_FTS_INDEX, SUBSPACE_FTS_QUEUE, SUBSPACE_INDEXES,
+    SUBSPACE_LOGS, SUBSPACE_LOOKUP_VALUE, SUBSPACE_PROPERTY, SUBSPACE_QUEUE_EVENT,
+    SUBSPACE_QUEUE_MESSAGE, SUBSPACE_QUOTA, SUBSPACE_REPORT_IN, SUBSPACE_REPORT_OUT,
+    SUBSPACE_SETTINGS, SUBSPACE_TELEMETRY_INDEX, SUBSPACE_TELEMETRY_METRIC,
+    SUBSPACE_TELEMETRY_SPAN, U32_LEN,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Subsystem {
+    Indexes,
+    Bitmaps,
+    ChangeLog,
+    Properties,
+    Blobs,
+    FullText,
+    Queue,
+    Reports,
+    Directory,
+    Lookup,
+    Settings,
+    Telemetry,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub keys: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubsystemUsage {
+    pub subsystem: Subsystem,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountUsage {
+    pub account_id: u32,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub usage: Usage,
+    pub subsystems: Vec<SubsystemUsage>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySpaceUsage {
+    #[serde(flatten)]
+    pub total: Usage,
+    pub subsystems: Vec<SubsystemUsage>,
+    pub accounts: Vec<AccountUsage>,
+}
+
+// Subspaces scanned by the usage report, along with whether
+// their keys are prefixed by the account id
+const SUBSPACES: &[(u8, Subsystem, bool)] = &[
+    (SUBSPACE_INDEXES, Subsystem::Indexes, true),
+    (SUBSPACE_BITMAP_ID, Subsystem::Bitmaps, true),
+    (SUBSPACE_BITMAP_TAG, Subsystem::Bitmaps, true),
+    (SUBSPACE_BITMAP_TEXT, Subsystem::Bitmaps, true),
+    (SUBSPACE_LOGS, Subsystem::ChangeLog, true),
+    (SUBSPACE_PROPERTY, Subsystem::Properties, true),
+    (SUBSPACE_COUNTER, Subsystem::Properties, false),
+    (SUBSPACE_QUOTA, Subsystem::Properties, false),
+    (SUBSPACE_ACL, Subsystem::Properties, false),
+    (SUBSPACE_BLOBS, Subsystem::Blobs, false),
+    (SUBSPACE_BLOB_LINK, Subsystem::Blobs, false),
+    (SUBSPACE_BLOB_RESERVE, Subsystem::Blobs, true),
+    (SUBSPACE_FTS_INDEX, Subsystem::FullText, true),
+    (SUBSPACE_FTS_QUEUE, Subsystem::FullText, false),
+    (SUBSPACE_QUEUE_MESSAGE, Subsystem::Queue, false),
+    (SUBSPACE_QUEUE_EVENT, Subsystem::Queue, false),
+    (SUBSPACE_REPORT_OUT, Subsystem::Reports, false),
+    (SUBSPACE_REPORT_IN, Subsystem::Reports, false),
+    (SUBSPACE_DIRECTORY, Subsystem::Directory, false),
+    (SUBSPACE_LOOKUP_VALUE, Subsystem::Lookup, false),
+    (SUBSPACE_SETTINGS, Subsystem::Settings, false),
+    (SUBSPACE_TELEMETRY_SPAN, Subsystem::Telemetry, false),
+    (SUBSPACE_TELEMETRY_INDEX, Subsystem::Telemetry, false),
+    (SUBSPACE_TELEMETRY_METRIC, Subsystem::Telemetry, false),
+];
+
+impl Store {
+    /// Scans the entire key space and reports the number of keys and bytes
+    /// used by each subsystem, along with the `max_accounts` largest accounts.
+    pub async fn key_space_usage(&self, max_accounts: usize) -> trc::Result<KeySpaceUsage> {
+        let mut subsystems: AHashMap<Subsystem, Usage> = AHashMap::new();
+        let mut accounts: AHashMap<u32, AHashMap<Subsystem, Usage>> = AHashMap::new();
+
+        for (subspace, subsystem, by_account) in SUBSPACES.iter().copied() {
+            let mut subsystem_usage = Usage::default();
+
+            self.iterate(
+                IterateParams::new(
+                    AnyKey {
+                        subspace,
+                        key: vec![0u8],
+                    },
+                    AnyKey {
+                        subspace,
+                        key: vec![u8::MAX; 10],
+                    },
+                ),
+                |key, value| {
+                    let bytes = (key.len() + value.len()) as u64;
+                    subsystem_usage.add(bytes);
+
+                    if by_account {
+                        if let Some(account_id) = key
+                            .get(0..U32_LEN)
+                            .map(|id| u32::from_be_bytes(id.try_into().unwrap()))
+                            .filter(|id| *id != u32::MAX)
+                        {
+                            accounts
+                                .entry(account_id)
+                                .or_default()
+                                .entry(subsystem)
+                                .or_default()
+                                .add(bytes);
+                        }
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+            subsystems
+                .entry(subsystem)
+                .or_default()
+                .merge(subsystem_usage);
+        }
+
+        // Build report
+        let mut report = KeySpaceUsage::default();
+        for usage in subsystems.values() {
+            report.total.merge(*usage);
+        }
+        report.subsystems = sorted_usage(subsystems);
+        report.accounts = accounts
+            .into_iter()
+            .map(|(account_id, subsystems)| {
+                let mut usage = Usage::default();
+                for subsystem_usage in subsystems.values() {
+                    usage.merge(*subsystem_usage);
+                }
+
+                AccountUsage {
+                    account_id,
+                    name: None,
+                    usage,
+                    subsystems: sorted_usage(subsystems),
+                }
+            })
+            .collect();
+        report.accounts.sort_unstable_by(|a, b| {
+            b.usage
+                .bytes
+                .cmp(&a.usage.bytes)
+                .then_with(|| a.account_id.cmp(&b.account_id))
+        });
+        report.accounts.truncate(max_accounts);
+
+        Ok(report)
+    }
+}
+
+impl Usage {
+    fn add(&mut self, bytes: u64) {
+        self.keys += 1;
+        self.bytes += bytes;
+    }
+
+    fn merge(&mut self, other: Usage) {
+        self.keys += other.keys;
+        self.bytes += other.bytes;
+    }
+}
+
+fn sorted_usage(usage: AHashMap<Subsystem, Usage>) -> Vec<SubsystemUsage> {
+    let mut usage = usage
+        .into_iter()
+        .map(|(subsystem, usage)| SubsystemUsage { subsystem, usage })
+        .collect::<Vec<_>>();
+    usage.sort_unstable_by(|a, b| {
+        b.usage
+            .bytes
+            .cmp(&a.usage.bytes)
+            .then_with(|| a.subsystem.cmp(&b.subsystem))
+    });
+    usage
+}
@@ -20,7 +20,7 @@ pub enum Field<T: Into<u8> + Display + Clone + std::fmt::Debug> {
     Keyword,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum FtsFilter<T: Into<u8> + Display + Clone + std::fmt::Debug> {
     Exact {
         field: Field<T>,
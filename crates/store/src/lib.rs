@@ -15,7 +15,10 @@ pub mod write;
 
 pub use ahash;
 use ahash::AHashMap;
-use backend::{fs::FsStore, memory::MemoryStore};
+use backend::{
+    fs::FsStore,
+    memory::{file::FileStore, MemoryStore},
+};
 pub use blake3;
 pub use parking_lot;
 pub use rand;
@@ -46,9 +49,18 @@ use backend::elastic::ElasticSearchStore;
 #[cfg(feature = "redis")]
 use backend::redis::RedisStore;
 
+#[cfg(feature = "etcd")]
+use backend::etcd::EtcdStore;
+
 #[cfg(feature = "azure")]
 use backend::azure::AzureStore;
 
+#[cfg(feature = "gcs")]
+use backend::gcs::GcsStore;
+
+#[cfg(feature = "wasm")]
+use backend::wasm::WasmStore;
+
 pub trait Deserialize: Sized + Sync + Send {
     fn deserialize(bytes: &[u8]) -> trc::Result<Self>;
 }
@@ -173,6 +185,7 @@ pub struct Stores {
     pub fts_stores: AHashMap<String, FtsStore>,
     pub lookup_stores: AHashMap<String, LookupStore>,
     pub purge_schedules: Vec<PurgeSchedule>,
+    pub settings: AHashMap<String, u64>,
 }
 
 #[derive(Clone, Default)]
@@ -203,6 +216,7 @@ pub struct BlobStore {
 pub enum CompressionAlgo {
     None,
     Lz4,
+    Zstd,
 }
 
 #[derive(Clone)]
@@ -213,8 +227,16 @@ pub enum BlobBackend {
     S3(Arc<S3Store>),
     #[cfg(feature = "azure")]
     Azure(Arc<AzureStore>),
+    #[cfg(feature = "gcs")]
+    Gcs(Arc<GcsStore>),
+    #[cfg(feature = "wasm")]
+    Wasm(Arc<WasmStore>),
     #[cfg(feature = "enterprise")]
     Composite(Arc<backend::composite::distributed_blob::DistributedBlob>),
+    #[cfg(feature = "enterprise")]
+    Tiered(Arc<backend::composite::tiered_blob::TieredBlob>),
+    #[cfg(feature = "enterprise")]
+    Replicated(Arc<backend::composite::replicated_blob::ReplicatedBlob>),
 }
 
 #[derive(Clone)]
@@ -230,7 +252,10 @@ pub enum LookupStore {
     Query(Arc<QueryStore>),
     #[cfg(feature = "redis")]
     Redis(Arc<RedisStore>),
+    #[cfg(feature = "etcd")]
+    Etcd(Arc<EtcdStore>),
     Memory(Arc<MemoryStore>),
+    File(Arc<FileStore>),
 }
 
 #[derive(Debug)]
@@ -303,6 +328,26 @@ impl From<AzureStore> for BlobStore {
     }
 }
 
+#[cfg(feature = "gcs")]
+impl From<GcsStore> for BlobStore {
+    fn from(store: GcsStore) -> Self {
+        BlobStore {
+            backend: BlobBackend::Gcs(Arc::new(store)),
+            compression: CompressionAlgo::None,
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<WasmStore> for BlobStore {
+    fn from(store: WasmStore) -> Self {
+        BlobStore {
+            backend: BlobBackend::Wasm(Arc::new(store)),
+            compression: CompressionAlgo::None,
+        }
+    }
+}
+
 #[cfg(feature = "elastic")]
 impl From<ElasticSearchStore> for FtsStore {
     fn from(store: ElasticSearchStore) -> Self {
@@ -317,6 +362,13 @@ impl From<RedisStore> for LookupStore {
     }
 }
 
+#[cfg(feature = "etcd")]
+impl From<EtcdStore> for LookupStore {
+    fn from(store: EtcdStore) -> Self {
+        Self::Etcd(Arc::new(store))
+    }
+}
+
 impl From<Store> for FtsStore {
     fn from(store: Store) -> Self {
         Self::Store(store)
@@ -756,8 +808,12 @@ impl Stores {
             #[cfg(any(feature = "postgres", feature = "mysql"))]
             self.stores
                 .retain(|_, store| !matches!(store, Store::SQLReadReplica(_)));
-            self.blob_stores
-                .retain(|_, store| !matches!(store.backend, BlobBackend::Composite(_)));
+            self.blob_stores.retain(|_, store| {
+                !matches!(
+                    store.backend,
+                    BlobBackend::Composite(_) | BlobBackend::Tiered(_) | BlobBackend::Replicated(_)
+                )
+            });
         }
     }
 }
@@ -79,6 +79,47 @@ impl Store {
         .map(|_| results)
     }
 
+    /// Returns the accounts that have been granted access to a document,
+    /// along with their permissions.
+    pub async fn acl_grants(
+        &self,
+        to_account_id: u32,
+        to_collection: u8,
+        to_document_id: u32,
+    ) -> trc::Result<Vec<(u32, u64)>> {
+        let from_key = ValueKey {
+            account_id: 0,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Acl(0),
+        };
+        let to_key = ValueKey {
+            account_id: u32::MAX,
+            collection: u8::MAX,
+            document_id: u32::MAX,
+            class: ValueClass::Acl(u32::MAX),
+        };
+
+        let mut results = Vec::new();
+        self.iterate(
+            IterateParams::new(from_key, to_key).ascending(),
+            |key, value| {
+                let acl_item = AclItem::deserialize(key)?;
+                if acl_item.to_account_id == to_account_id
+                    && acl_item.to_collection == to_collection
+                    && acl_item.to_document_id == to_document_id
+                {
+                    results.push((key.deserialize_be_u32(0)?, u64::deserialize(value)?));
+                }
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|_| results)
+    }
+
     pub async fn acl_revoke_all(&self, account_id: u32) -> trc::Result<()> {
         let from_key = ValueKey {
             account_id: 0,
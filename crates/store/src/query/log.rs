@@ -107,8 +107,41 @@ impl Store {
         account_id: u32,
         collection: impl Into<u8> + Sync + Send,
     ) -> trc::Result<Option<u64>> {
-        let collection = collection.into();
+        self.get_change_id(account_id, collection.into(), u64::MAX, false)
+            .await
+    }
+
+    pub async fn get_first_change_id(
+        &self,
+        account_id: u32,
+        collection: impl Into<u8> + Sync + Send,
+    ) -> trc::Result<Option<u64>> {
+        self.get_change_id(account_id, collection.into(), u64::MAX, true)
+            .await
+    }
 
+    /// Returns the id of the last change logged before the given change id.
+    pub async fn get_last_change_id_before(
+        &self,
+        account_id: u32,
+        collection: impl Into<u8> + Sync + Send,
+        change_id: u64,
+    ) -> trc::Result<Option<u64>> {
+        if change_id > 0 {
+            self.get_change_id(account_id, collection.into(), change_id - 1, false)
+                .await
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_change_id(
+        &self,
+        account_id: u32,
+        collection: u8,
+        to_change_id: u64,
+        ascending: bool,
+    ) -> trc::Result<Option<u64>> {
         let from_key = LogKey {
             account_id,
             collection,
@@ -117,25 +150,25 @@ impl Store {
         let to_key = LogKey {
             account_id,
             collection,
-            change_id: u64::MAX,
+            change_id: to_change_id,
         };
 
-        let mut last_change_id = None;
+        let mut change_id = None;
 
         self.iterate(
             IterateParams::new(from_key, to_key)
-                .descending()
+                .set_ascending(ascending)
                 .no_values()
                 .only_first(),
             |key, _| {
-                last_change_id = key.deserialize_be_u64(key.len() - U64_LEN)?.into();
+                change_id = key.deserialize_be_u64(key.len() - U64_LEN)?.into();
                 Ok(false)
             },
         )
         .await
         .caused_by(trc::location!())?;
 
-        Ok(last_change_id)
+        Ok(change_id)
     }
 }
 
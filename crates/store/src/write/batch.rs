@@ -5,9 +5,9 @@
  */
 
 use super::{
-    assert::ToAssertValue, Batch, BatchBuilder, BitmapClass, HasFlag, IntoOperations,
-    MaybeDynamicId, MaybeDynamicValue, Operation, Serialize, TagValue, ToBitmaps, ValueClass,
-    ValueOp, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE,
+    assert::ToAssertValue, Batch, BatchBuilder, BitmapClass, DirectoryClass, HasFlag,
+    IntoOperations, MaybeDynamicId, MaybeDynamicValue, Operation, Serialize, TagValue, ToBitmaps,
+    ValueClass, ValueOp, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE,
 };
 
 impl BatchBuilder {
@@ -240,6 +240,57 @@ impl Batch {
             _ => None,
         })
     }
+
+    // Bumps the backup watermark of every account whose ACLs, principal or
+    // memberships are modified by this batch. These changes are not written
+    // to the change log, everything else is detected by comparing change ids,
+    // which avoids adding a counter update to every batch.
+    pub fn advance_watermarks(&mut self) {
+        let mut account_id = u32::MAX;
+        let mut changed_accounts = Vec::new();
+
+        for op in &self.ops {
+            let changed_account_id = match op {
+                Operation::AccountId {
+                    account_id: account_id_,
+                } => {
+                    account_id = *account_id_;
+                    continue;
+                }
+                Operation::Value {
+                    class: ValueClass::Directory(directory),
+                    ..
+                } => match directory {
+                    DirectoryClass::Principal(MaybeDynamicId::Static(principal_id))
+                    | DirectoryClass::MemberOf {
+                        principal_id: MaybeDynamicId::Static(principal_id),
+                        ..
+                    }
+                    | DirectoryClass::Members {
+                        principal_id: MaybeDynamicId::Static(principal_id),
+                        ..
+                    } => *principal_id,
+                    _ => continue,
+                },
+                Operation::Value {
+                    class: ValueClass::Acl(_),
+                    ..
+                } => account_id,
+                _ => continue,
+            };
+
+            if changed_account_id != u32::MAX && !changed_accounts.contains(&changed_account_id) {
+                changed_accounts.push(changed_account_id);
+            }
+        }
+
+        for account_id in changed_accounts {
+            self.ops.push(Operation::Value {
+                class: ValueClass::Directory(DirectoryClass::BackupWatermark(account_id)),
+                op: ValueOp::AtomicAdd(1),
+            });
+        }
+    }
 }
 
 impl Default for BatchBuilder {
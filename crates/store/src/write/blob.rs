@@ -21,6 +21,23 @@ pub struct BlobQuota {
     pub count: usize,
 }
 
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BlobUsage {
+    pub blobs: usize,
+    pub links: usize,
+}
+
+impl BlobUsage {
+    // Percentage of links that did not require storing a new blob
+    pub fn dedup_ratio(&self) -> u64 {
+        if self.links > 0 {
+            ((self.links - self.blobs) * 100 / self.links) as u64
+        } else {
+            0
+        }
+    }
+}
+
 impl Store {
     pub async fn blob_exists(&self, hash: impl AsRef<BlobHash> + Sync + Send) -> trc::Result<bool> {
         self.get_value::<()>(ValueKey {
@@ -36,6 +53,41 @@ impl Store {
         .caused_by(trc::location!())
     }
 
+    pub async fn blob_ref_count(
+        &self,
+        hash: impl AsRef<BlobHash> + Sync + Send,
+    ) -> trc::Result<u64> {
+        // References are derived from the link keys themselves, so linking the
+        // same document twice or purging an account by range keeps them exact
+        let hash = hash.as_ref();
+        let from_key = ValueKey {
+            account_id: 0,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Link { hash: hash.clone() }),
+        };
+        let to_key = ValueKey {
+            account_id: u32::MAX,
+            collection: u8::MAX,
+            document_id: u32::MAX,
+            class: ValueClass::Blob(BlobOp::Link { hash: hash.clone() }),
+        };
+        let mut ref_count = 0;
+        self.iterate(
+            IterateParams::new(from_key, to_key).ascending().no_values(),
+            |key, _| {
+                if key.deserialize_be_u32(key.len() - U32_LEN)? != u32::MAX {
+                    ref_count += 1;
+                }
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+        Ok(ref_count)
+    }
+
     pub async fn blob_quota(&self, account_id: u32) -> trc::Result<BlobQuota> {
         let from_key = ValueKey {
             account_id,
@@ -79,6 +131,21 @@ impl Store {
         Ok(quota)
     }
 
+    pub async fn blob_usage(&self) -> trc::Result<BlobUsage> {
+        Ok(BlobUsage {
+            blobs: self
+                .get_counter(ValueKey::from(ValueClass::Blob(BlobOp::UsageBlobs)))
+                .await
+                .caused_by(trc::location!())?
+                .max(0) as usize,
+            links: self
+                .get_counter(ValueKey::from(ValueClass::Blob(BlobOp::UsageLinks)))
+                .await
+                .caused_by(trc::location!())?
+                .max(0) as usize,
+        })
+    }
+
     pub async fn blob_has_access(
         &self,
         hash: impl AsRef<BlobHash> + Sync + Send,
@@ -176,6 +243,7 @@ impl Store {
             }),
         };
         let mut last_hash = BlobHash::default();
+        let mut usage = BlobUsage::default();
         self.iterate(
             IterateParams::new(from_key, to_key).ascending().no_values(),
             |key, _| {
@@ -189,7 +257,9 @@ impl Store {
                 if document_id != u32::MAX {
                     if last_hash != hash {
                         last_hash = hash;
+                        usage.blobs += 1;
                     }
+                    usage.links += 1;
                 } else if last_hash != hash && !active_hashes.contains(&hash) {
                     // Unlinked or expired blob, delete.
                     delete_keys.push((0, BlobOp::Commit { hash }));
@@ -201,15 +271,36 @@ impl Store {
         .await
         .caused_by(trc::location!())?;
 
-        // Delete expired or unlinked blobs
+        // Blobs linked after the scan above are still referenced, keep them
+        let mut unlinked_keys = Vec::with_capacity(delete_keys.len());
+        for (account_id, op) in delete_keys {
+            if let BlobOp::Commit { hash } = &op {
+                if self
+                    .blob_ref_count(hash)
+                    .await
+                    .caused_by(trc::location!())?
+                    > 0
+                {
+                    continue;
+                }
+            }
+            unlinked_keys.push((account_id, op));
+        }
+        let mut delete_keys = unlinked_keys;
+
+        // Delete expired or unlinked blobs, along with any metadata shared
+        // between the documents that linked them
+        let mut shared_metadata = Vec::new();
         for (_, op) in &delete_keys {
             if let BlobOp::Commit { hash } = op {
                 blob_store
                     .delete_blob(hash.as_ref())
                     .await
                     .caused_by(trc::location!())?;
+                shared_metadata.push((0, BlobOp::Metadata { hash: hash.clone() }));
             }
         }
+        delete_keys.extend(shared_metadata);
 
         // Delete hashes
         let mut batch = BatchBuilder::new();
@@ -237,6 +328,40 @@ impl Store {
                 .caused_by(trc::location!())?;
         }
 
+        // Record the usage observed while validating links, the blob metrics
+        // read these counters rather than scanning the links again
+        let last_usage = self.blob_usage().await.caused_by(trc::location!())?;
+        if usage != last_usage {
+            let mut batch = BatchBuilder::new();
+            batch
+                .add(
+                    BlobOp::UsageBlobs,
+                    usage.blobs as i64 - last_usage.blobs as i64,
+                )
+                .add(
+                    BlobOp::UsageLinks,
+                    usage.links as i64 - last_usage.links as i64,
+                );
+            self.write(batch.build())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        // Move aged blobs to the cold tier or repair missing replicas
+        #[cfg(feature = "enterprise")]
+        match &blob_store.backend {
+            crate::BlobBackend::Tiered(tiered) => {
+                tiered.migrate(self).await.caused_by(trc::location!())?;
+            }
+            crate::BlobBackend::Replicated(replicated) => {
+                replicated
+                    .reconcile(self)
+                    .await
+                    .caused_by(trc::location!())?;
+            }
+            _ => {}
+        }
+
         Ok(())
     }
 
@@ -302,11 +427,7 @@ impl Store {
                 batch.with_collection(collection);
                 last_collection = collection;
             }
-            batch.update_document(document_id);
-            batch.ops.push(Operation::Value {
-                class: ValueClass::Blob(op),
-                op: ValueOp::Clear,
-            });
+            batch.update_document(document_id).clear(op);
         }
         if !batch.is_empty() {
             self.write(batch.build())
@@ -0,0 +1,133 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+use crate::LookupStore;
+
+/// Merges increments to the same counter in memory and writes them to the
+/// lookup store in a single update, either periodically or once too many
+/// counters are pending. Only meant for counters whose value is not needed
+/// right away, as pending increments are lost if the server stops abruptly.
+///
+/// Rate limiters are not coalesced: they decide on the updated value, and
+/// increments held back on one node would let bursts through on the others.
+pub struct CounterCoalescer {
+    pending: Mutex<AHashMap<Vec<u8>, PendingIncr>>,
+    flushing: AtomicBool,
+    pub max_pending: usize,
+    pub interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingIncr {
+    value: i64,
+    expires: Option<u64>,
+}
+
+impl CounterCoalescer {
+    pub fn new(max_pending: usize, interval: Duration) -> Self {
+        CounterCoalescer {
+            pending: Mutex::new(AHashMap::new()),
+            flushing: AtomicBool::new(false),
+            max_pending: max_pending.max(1),
+            interval,
+        }
+    }
+
+    /// Adds an increment to a counter, returns `true` when the number of
+    /// pending counters has reached the flush threshold and no other flush is
+    /// in progress. The caller is then expected to call `flush_claimed`.
+    pub fn add(&self, key: Vec<u8>, value: i64, expires: Option<u64>) -> bool {
+        let mut pending = self.pending.lock();
+        pending
+            .entry(key)
+            .and_modify(|incr| {
+                incr.value += value;
+                incr.expires = expires;
+            })
+            .or_insert(PendingIncr { value, expires });
+        pending.len() >= self.max_pending && !self.flushing.swap(true, Ordering::AcqRel)
+    }
+
+    /// Returns the sum of the increments not yet written for a counter.
+    pub fn pending(&self, key: &[u8]) -> i64 {
+        self.pending.lock().get(key).map_or(0, |incr| incr.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.lock().is_empty()
+    }
+
+    /// Writes all pending increments to the store, returning the number of
+    /// counters updated. Increments that could not be written are kept for
+    /// the next flush. Does nothing if another flush is in progress.
+    pub async fn flush(&self, store: &LookupStore) -> trc::Result<usize> {
+        if !self.flushing.swap(true, Ordering::AcqRel) {
+            self.flush_claimed(store).await
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Same as `flush`, for callers that were told to flush by `add`.
+    pub async fn flush_claimed(&self, store: &LookupStore) -> trc::Result<usize> {
+        let _guard = FlushGuard(&self.flushing);
+        let pending = std::mem::take(&mut *self.pending.lock());
+        let mut entries = pending.into_iter();
+        let mut total = 0;
+
+        while let Some((key, incr)) = entries.next() {
+            if incr.value == 0 {
+                continue;
+            }
+
+            match store
+                .counter_incr(key.clone(), incr.value, incr.expires, false)
+                .await
+            {
+                Ok(_) => {
+                    total += 1;
+                }
+                Err(err) => {
+                    self.requeue(std::iter::once((key, incr)).chain(entries));
+                    return Err(err.caused_by(trc::location!()));
+                }
+            }
+        }
+
+        Ok(total)
+    }
+
+    fn requeue(&self, entries: impl Iterator<Item = (Vec<u8>, PendingIncr)>) {
+        // Increments added while flushing are newer, so their expiration wins
+        let mut pending = self.pending.lock();
+        for (key, incr) in entries {
+            pending
+                .entry(key)
+                .and_modify(|newer| newer.value += incr.value)
+                .or_insert(incr);
+        }
+    }
+}
+
+struct FlushGuard<'x>(&'x AtomicBool);
+
+impl Drop for FlushGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
@@ -292,6 +292,14 @@ impl<T: ResolveId> ValueClass<T> {
                     .write((*id >> 32) as u32)
                     .write(u8::MAX)
                     .write(*id as u32),
+                BlobOp::Metadata { hash } => serializer
+                    .write(u32::MAX)
+                    .write(u8::MAX)
+                    .write(u8::MAX)
+                    .write(u32::MAX)
+                    .write::<&[u8]>(hash.as_ref()),
+                BlobOp::UsageBlobs => serializer.write(9u8).write(0u8),
+                BlobOp::UsageLinks => serializer.write(9u8).write(1u8),
             },
             ValueClass::Config(key) => serializer.write(key.as_slice()),
             ValueClass::Lookup(lookup) => match lookup {
@@ -305,6 +313,8 @@ impl<T: ResolveId> ValueClass<T> {
                     .write(2u8)
                     .write_leb128(uid.resolve_id(assigned_ids)),
                 DirectoryClass::UsedQuota(uid) => serializer.write(4u8).write_leb128(*uid),
+                DirectoryClass::BackupWatermark(uid) => serializer.write(7u8).write(*uid),
+                DirectoryClass::CredentialRevision(uid) => serializer.write(8u8).write(*uid),
                 DirectoryClass::MemberOf {
                     principal_id,
                     member_of,
@@ -364,6 +374,9 @@ impl<T: ResolveId> ValueClass<T> {
                 ReportClass::Arf { id, expires } => {
                     serializer.write(2u8).write(*expires).write(*id)
                 }
+                ReportClass::Quarantine { id, expires } => {
+                    serializer.write(3u8).write(*expires).write(*id)
+                }
             },
             ValueClass::Telemetry(telemetry) => match telemetry {
                 TelemetryClass::Span { span_id } => serializer.write(*span_id),
@@ -533,7 +546,10 @@ impl<T> ValueClass<T> {
             | ValueClass::Config(v) => v.len(),
             ValueClass::Directory(d) => match d {
                 DirectoryClass::NameToId(v) | DirectoryClass::EmailToId(v) => v.len(),
-                DirectoryClass::Principal(_) | DirectoryClass::UsedQuota(_) => U32_LEN,
+                DirectoryClass::Principal(_)
+                | DirectoryClass::UsedQuota(_)
+                | DirectoryClass::BackupWatermark(_)
+                | DirectoryClass::CredentialRevision(_) => U32_LEN,
                 DirectoryClass::Members { .. } | DirectoryClass::MemberOf { .. } => U32_LEN * 2,
             },
             ValueClass::Blob(op) => match op {
@@ -541,6 +557,8 @@ impl<T> ValueClass<T> {
                 BlobOp::Commit { .. } | BlobOp::Link { .. } | BlobOp::LinkId { .. } => {
                     BLOB_HASH_LEN + U32_LEN * 2 + 2
                 }
+                BlobOp::Metadata { .. } => BLOB_HASH_LEN + U32_LEN * 2 + 3,
+                BlobOp::UsageBlobs | BlobOp::UsageLinks => 2,
             },
             ValueClass::FtsQueue { .. } => BLOB_HASH_LEN + U64_LEN * 2,
             ValueClass::Queue(q) => match q {
@@ -581,6 +599,8 @@ impl<T> ValueClass<T> {
                 BlobOp::Commit { .. } | BlobOp::Link { .. } | BlobOp::LinkId { .. } => {
                     SUBSPACE_BLOB_LINK
                 }
+                BlobOp::Metadata { .. } => SUBSPACE_PROPERTY,
+                BlobOp::UsageBlobs | BlobOp::UsageLinks => SUBSPACE_QUOTA,
             },
             ValueClass::Config(_) => SUBSPACE_SETTINGS,
             ValueClass::Lookup(lookup) => match lookup {
@@ -588,7 +608,9 @@ impl<T> ValueClass<T> {
                 LookupClass::Counter(_) => SUBSPACE_COUNTER,
             },
             ValueClass::Directory(directory) => match directory {
-                DirectoryClass::UsedQuota(_) => SUBSPACE_QUOTA,
+                DirectoryClass::UsedQuota(_)
+                | DirectoryClass::BackupWatermark(_)
+                | DirectoryClass::CredentialRevision(_) => SUBSPACE_QUOTA,
                 _ => SUBSPACE_DIRECTORY,
             },
             ValueClass::Queue(queue) => match queue {
@@ -612,8 +634,13 @@ impl<T> ValueClass<T> {
 
     pub fn is_counter(&self, collection: u8) -> bool {
         match self {
-            ValueClass::Directory(DirectoryClass::UsedQuota(_))
+            ValueClass::Directory(
+                DirectoryClass::UsedQuota(_)
+                | DirectoryClass::BackupWatermark(_)
+                | DirectoryClass::CredentialRevision(_),
+            )
             | ValueClass::Lookup(LookupClass::Counter(_))
+            | ValueClass::Blob(BlobOp::UsageBlobs | BlobOp::UsageLinks)
             | ValueClass::Queue(QueueClass::QuotaCount(_) | QueueClass::QuotaSize(_)) => true,
             ValueClass::Property(84) if collection == 1 => true, // TODO: Find a more elegant way to do this
             _ => false,
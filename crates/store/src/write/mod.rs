@@ -28,6 +28,7 @@ use self::assert::AssertValue;
 pub mod assert;
 pub mod batch;
 pub mod blob;
+pub mod coalesce;
 pub mod hash;
 pub mod key;
 pub mod log;
@@ -183,6 +184,8 @@ pub enum DirectoryClass<T> {
     Members { principal_id: T, has_member: T },
     Principal(T),
     UsedQuota(u32),
+    BackupWatermark(u32),
+    CredentialRevision(u32),
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
@@ -202,6 +205,7 @@ pub enum ReportClass {
     Tls { id: u64, expires: u64 },
     Dmarc { id: u64, expires: u64 },
     Arf { id: u64, expires: u64 },
+    Quarantine { id: u64, expires: u64 },
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
@@ -249,6 +253,9 @@ pub enum BlobOp {
     Commit { hash: BlobHash },
     Link { hash: BlobHash },
     LinkId { hash: BlobHash, id: u64 },
+    Metadata { hash: BlobHash },
+    UsageBlobs,
+    UsageLinks,
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
@@ -200,6 +200,8 @@ impl FtsIndexEvent {
             FtsIndexEvent::LockBusy => "Full-text search index lock is busy",
             FtsIndexEvent::BlobNotFound => "Blob not found for full-text indexing",
             FtsIndexEvent::MetadataNotFound => "Metadata not found for full-text indexing",
+            FtsIndexEvent::Degraded => "Full-text search backend unavailable",
+            FtsIndexEvent::Recovered => "Full-text search backend recovered",
         }
     }
 
@@ -210,6 +212,10 @@ impl FtsIndexEvent {
             FtsIndexEvent::LockBusy => "The full-text search index lock is busy",
             FtsIndexEvent::BlobNotFound => "The blob was not found for full-text indexing",
             FtsIndexEvent::MetadataNotFound => "The metadata was not found for full-text indexing",
+            FtsIndexEvent::Degraded => {
+                "The full-text search backend failed, queries are falling back to message metadata and index writes are queued for replay"
+            }
+            FtsIndexEvent::Recovered => "The full-text search backend is available again",
         }
     }
 }
@@ -248,6 +254,8 @@ impl ImapEvent {
             ImapEvent::Subscribe => "IMAP SUBSCRIBE command",
             ImapEvent::Unsubscribe => "IMAP UNSUBSCRIBE command",
             ImapEvent::Thread => "IMAP THREAD command",
+            ImapEvent::GetMetadata => "IMAP GETMETADATA command",
+            ImapEvent::SetMetadata => "IMAP SETMETADATA command",
             ImapEvent::Error => "IMAP error occurred",
             ImapEvent::RawInput => "Raw IMAP input received",
             ImapEvent::RawOutput => "Raw IMAP output sent",
@@ -289,6 +297,8 @@ impl ImapEvent {
             ImapEvent::Subscribe => "Client subscribed to a mailbox",
             ImapEvent::Unsubscribe => "Client unsubscribed from a mailbox",
             ImapEvent::Thread => "Client requested message threads",
+            ImapEvent::GetMetadata => "Client requested mailbox or server annotations",
+            ImapEvent::SetMetadata => "Client set mailbox or server annotations",
             ImapEvent::Error => "An error occurred during an IMAP command",
             ImapEvent::RawInput => "Raw IMAP input received",
             ImapEvent::RawOutput => "Raw IMAP output sent",
@@ -358,6 +368,8 @@ impl ManageSieveEvent {
             ManageSieveEvent::HaveSpace => "ManageSieve HAVESPACE command",
             ManageSieveEvent::ListScripts => "ManageSieve LIST scripts command",
             ManageSieveEvent::SetActive => "ManageSieve SET ACTIVE command",
+            ManageSieveEvent::ListVacation => "ManageSieve LIST VACATION command",
+            ManageSieveEvent::ClearVacation => "ManageSieve CLEAR VACATION command",
             ManageSieveEvent::Capabilities => "ManageSieve CAPABILITIES command",
             ManageSieveEvent::StartTls => "ManageSieve STARTTLS command",
             ManageSieveEvent::Unauthenticate => "ManageSieve UNAUTHENTICATE command",
@@ -382,6 +394,8 @@ impl ManageSieveEvent {
             ManageSieveEvent::HaveSpace => "Client checked for space",
             ManageSieveEvent::ListScripts => "Client listed scripts",
             ManageSieveEvent::SetActive => "Client set an active script",
+            ManageSieveEvent::ListVacation => "Client listed the recipients of vacation replies",
+            ManageSieveEvent::ClearVacation => "Client cleared the recipients of vacation replies",
             ManageSieveEvent::Capabilities => "Client requested server capabilities",
             ManageSieveEvent::StartTls => "Client requested TLS",
             ManageSieveEvent::Unauthenticate => "Client unauthenticated",
@@ -431,6 +445,7 @@ impl SmtpEvent {
             SmtpEvent::LhloExpected => "LHLO command expected",
             SmtpEvent::MailFromUnauthenticated => "MAIL FROM without authentication",
             SmtpEvent::MailFromUnauthorized => "MAIL FROM unauthorized",
+            SmtpEvent::SenderUnauthorized => "Sender header unauthorized",
             SmtpEvent::MailFromRewritten => "MAIL FROM address rewritten",
             SmtpEvent::MailFromMissing => "MAIL FROM address missing",
             SmtpEvent::MailFromNotAllowed => "MAIL FROM not allowed",
@@ -480,6 +495,19 @@ impl SmtpEvent {
             SmtpEvent::UnsupportedParameter => "Unsupported parameter",
             SmtpEvent::SyntaxError => "Syntax error",
             SmtpEvent::RequestTooLarge => "Request too large",
+            SmtpEvent::RcptVerifyError => "Recipient verification failed",
+            SmtpEvent::ListPostDistributed => "Mailing list post distributed",
+            SmtpEvent::ListPostHeld => "Mailing list post held",
+            SmtpEvent::ListPostApproved => "Mailing list post approved",
+            SmtpEvent::ListPostRejected => "Mailing list post rejected",
+            SmtpEvent::ListBounce => "Mailing list bounce received",
+            SmtpEvent::ListUnsubscribed => "Mailing list member unsubscribed",
+            SmtpEvent::MalwareFound => "Malware found in message",
+            SmtpEvent::MalwareScanError => "Malware scan failed",
+            SmtpEvent::AttachmentBlocked => "Attachment blocked",
+            SmtpEvent::AttachmentStripped => "Attachment stripped",
+            SmtpEvent::RcptToSuppressed => "Suppressed recipient rejected",
+            SmtpEvent::RecipientSuppressed => "Recipient added to suppression list",
             SmtpEvent::ConnectionStart => "SMTP connection started",
             SmtpEvent::ConnectionEnd => "SMTP connection ended",
         }
@@ -535,6 +563,9 @@ impl SmtpEvent {
             SmtpEvent::MailFromUnauthorized => {
                 "The remote client is not authorized to send mail from the given address"
             }
+            SmtpEvent::SenderUnauthorized => {
+                "The Sender header does not match the delegate sending on behalf of another account"
+            }
             SmtpEvent::MailFromRewritten => "The envelope sender address was rewritten",
             SmtpEvent::MailFromMissing => {
                 "The remote client issued an RCPT TO command before MAIL FROM"
@@ -603,6 +634,31 @@ impl SmtpEvent {
             SmtpEvent::UnsupportedParameter => "The command contained an unsupported parameter",
             SmtpEvent::SyntaxError => "The command contained a syntax error",
             SmtpEvent::RequestTooLarge => "The request was too large",
+            SmtpEvent::RcptVerifyError => {
+                "The external recipient verification service could not be reached"
+            }
+            SmtpEvent::ListPostDistributed => {
+                "A post was distributed to the members of a mailing list"
+            }
+            SmtpEvent::ListPostHeld => "A post to a mailing list was held for moderation",
+            SmtpEvent::ListPostApproved => "A moderator approved a held mailing list post",
+            SmtpEvent::ListPostRejected => "A moderator rejected a held mailing list post",
+            SmtpEvent::ListBounce => "A delivery failure was recorded for a mailing list member",
+            SmtpEvent::ListUnsubscribed => {
+                "A mailing list member was unsubscribed after repeated bounces"
+            }
+            SmtpEvent::MalwareFound => "The malware scanner found an infected message part",
+            SmtpEvent::MalwareScanError => "The malware scanner could not be reached",
+            SmtpEvent::AttachmentBlocked => "An attachment policy blocked the message",
+            SmtpEvent::AttachmentStripped => {
+                "An attachment policy removed an attachment from the message"
+            }
+            SmtpEvent::RcptToSuppressed => {
+                "A recipient was rejected because it bounced previous messages from the sender"
+            }
+            SmtpEvent::RecipientSuppressed => {
+                "A recipient was added to the suppression list of a sender after a bounce"
+            }
             SmtpEvent::ConnectionStart => "A new SMTP connection was started",
             SmtpEvent::ConnectionEnd => "The SMTP connection was ended",
             SmtpEvent::StartTlsAlready => "TLS is already active",
@@ -651,6 +707,10 @@ impl DeliveryEvent {
             DeliveryEvent::DsnPermFail => "DSN permanent failure notification",
             DeliveryEvent::RawInput => "Raw SMTP input received",
             DeliveryEvent::RawOutput => "Raw SMTP output sent",
+            DeliveryEvent::RouteSelected => "Smarthost route selected",
+            DeliveryEvent::IpPoolSelected => "Source IP selected from pool",
+            DeliveryEvent::IpPoolSkipped => "Source IP skipped",
+            DeliveryEvent::IpPoolExhausted => "Source IP pool exhausted",
         }
     }
 
@@ -708,6 +768,18 @@ impl DeliveryEvent {
             }
             DeliveryEvent::RawInput => "Raw SMTP input received",
             DeliveryEvent::RawOutput => "Raw SMTP output sent",
+            DeliveryEvent::RouteSelected => {
+                "A smarthost route matched the message and selected its relay host"
+            }
+            DeliveryEvent::IpPoolSelected => {
+                "A source IP address was selected from the outbound IP pool"
+            }
+            DeliveryEvent::IpPoolSkipped => {
+                "A source IP address was skipped due to its hourly limit or failure rate"
+            }
+            DeliveryEvent::IpPoolExhausted => {
+                "No source IP address in the outbound IP pool is currently available"
+            }
         }
     }
 }
@@ -727,6 +799,9 @@ impl QueueEvent {
             QueueEvent::QueueReport => "Queued report for delivery",
             QueueEvent::QueueDsn => "Queued DSN for delivery",
             QueueEvent::QueueAutogenerated => "Queued autogenerated message for delivery",
+            QueueEvent::Held => "Message held",
+            QueueEvent::Released => "Message released",
+            QueueEvent::Rerouted => "Message rerouted",
         }
     }
 
@@ -746,6 +821,9 @@ impl QueueEvent {
             QueueEvent::QueueReport => "A new report was queued for delivery",
             QueueEvent::QueueDsn => "A delivery status notification was queued for delivery",
             QueueEvent::QueueAutogenerated => "A system generated message was queued for delivery",
+            QueueEvent::Held => "The message was held and will not be delivered until released",
+            QueueEvent::Released => "The held message was released for delivery",
+            QueueEvent::Rerouted => "The message was rerouted to a different next hop",
         }
     }
 }
@@ -1025,6 +1103,13 @@ impl SpamEvent {
             SpamEvent::Classify => "Classifying message for spam",
             SpamEvent::ClassifyError => "Error classifying message for spam",
             SpamEvent::NotEnoughTrainingData => "Not enough training data for spam filter",
+            SpamEvent::Outbound => "Outbound spam detected",
+            SpamEvent::TrainFeedback => "Spam filter trained from user feedback",
+            SpamEvent::Quarantine => "Message quarantined",
+            SpamEvent::QuarantineRelease => "Quarantined message released",
+            SpamEvent::QuarantineDigest => "Quarantine digest sent",
+            SpamEvent::Reputation => "Sender reputation applied",
+            SpamEvent::ReputationThrottle => "Connection throttled due to poor reputation",
         }
     }
 
@@ -1040,6 +1125,27 @@ impl SpamEvent {
             SpamEvent::NotEnoughTrainingData => {
                 "There is not enough training data for the spam filter"
             }
+            SpamEvent::Outbound => {
+                "A message submitted by an authenticated sender was classified as spam"
+            }
+            SpamEvent::TrainFeedback => {
+                "The spam filter was trained after a user moved a message into or out of Junk"
+            }
+            SpamEvent::Quarantine => {
+                "A message scored above the quarantine threshold and was held outside the mailbox"
+            }
+            SpamEvent::QuarantineRelease => {
+                "A quarantined message was released and delivered to the Inbox"
+            }
+            SpamEvent::QuarantineDigest => {
+                "A digest listing quarantined messages was sent to an account"
+            }
+            SpamEvent::Reputation => {
+                "The stored reputation of the sending IP and domains adjusted the spam score"
+            }
+            SpamEvent::ReputationThrottle => {
+                "A connection was delayed or rejected because the sending IP has a poor reputation"
+            }
         }
     }
 }
@@ -1091,6 +1197,8 @@ impl TlsEvent {
             TlsEvent::CertificateNotFound => "TLS certificate not found",
             TlsEvent::NoCertificatesAvailable => "No TLS certificates available",
             TlsEvent::MultipleCertificatesAvailable => "Multiple TLS certificates available",
+            TlsEvent::CertificatesReloaded => "TLS certificates reloaded",
+            TlsEvent::CertificateSourceError => "TLS certificate source error",
         }
     }
 
@@ -1102,6 +1210,12 @@ impl TlsEvent {
             TlsEvent::CertificateNotFound => "The TLS certificate was not found",
             TlsEvent::NoCertificatesAvailable => "No TLS certificates are available",
             TlsEvent::MultipleCertificatesAvailable => "Multiple TLS certificates are available",
+            TlsEvent::CertificatesReloaded => {
+                "TLS certificates were reloaded from an external certificate source"
+            }
+            TlsEvent::CertificateSourceError => {
+                "Failed to load TLS certificates from an external certificate source"
+            }
         }
     }
 }
@@ -1121,6 +1235,10 @@ impl NetworkEvent {
             NetworkEvent::Timeout => "Network timeout",
             NetworkEvent::Closed => "Network connection closed",
             NetworkEvent::ProxyError => "Proxy protocol error",
+            NetworkEvent::ProxyConnection => "Connection received through a proxy",
+            NetworkEvent::ProxyUntrusted => "Connection not received through a trusted proxy",
+            NetworkEvent::HealthCheck => "Health check requested",
+            NetworkEvent::HealthCheckFailed => "Health check failed",
             NetworkEvent::SetOptError => "Network set option error",
         }
     }
@@ -1139,6 +1257,14 @@ impl NetworkEvent {
             NetworkEvent::Timeout => "A network timeout occurred",
             NetworkEvent::Closed => "The network connection was closed",
             NetworkEvent::ProxyError => "An error occurred with the proxy protocol",
+            NetworkEvent::ProxyConnection => {
+                "A connection was received through a trusted proxy using the PROXY protocol"
+            }
+            NetworkEvent::ProxyUntrusted => {
+                "A connection was rejected because it did not originate from a trusted proxy"
+            }
+            NetworkEvent::HealthCheck => "A health check agent connected to the health listener",
+            NetworkEvent::HealthCheckFailed => "One or more server components are not healthy",
             NetworkEvent::SetOptError => "An error occurred while setting network options",
         }
     }
@@ -1157,6 +1283,9 @@ impl ServerEvent {
             ServerEvent::StartupError => "Server startup error",
             ServerEvent::ThreadError => "Server thread error",
             ServerEvent::Licensing => "Server licensing event",
+            ServerEvent::DrainStart => "Draining connections",
+            ServerEvent::DrainComplete => "Connections drained",
+            ServerEvent::DrainTimeout => "Connection draining timed out",
         }
     }
 
@@ -1167,6 +1296,13 @@ impl ServerEvent {
             ServerEvent::StartupError => "An error occurred while starting the server",
             ServerEvent::ThreadError => "An error occurred with a server thread",
             ServerEvent::Licensing => "A licensing event occurred",
+            ServerEvent::DrainStart => {
+                "The server is waiting for in-flight sessions and deliveries to finish"
+            }
+            ServerEvent::DrainComplete => "All in-flight sessions and deliveries have finished",
+            ServerEvent::DrainTimeout => {
+                "The grace period expired before all sessions and deliveries finished"
+            }
         }
     }
 }
@@ -1333,6 +1469,8 @@ impl ConfigEvent {
             ConfigEvent::ImportExternal => "Importing external configuration",
             ConfigEvent::ExternalKeyIgnored => "External configuration key ignored",
             ConfigEvent::AlreadyUpToDate => "Configuration already up to date",
+            ConfigEvent::SecretLeaseRenewed => "Secret lease renewed",
+            ConfigEvent::SecretLeaseError => "Failed to renew secret lease",
         }
     }
 
@@ -1351,6 +1489,10 @@ impl ConfigEvent {
             ConfigEvent::ImportExternal => "An external configuration is being imported",
             ConfigEvent::ExternalKeyIgnored => "An external configuration key is ignored",
             ConfigEvent::AlreadyUpToDate => "The configuration is already up to date",
+            ConfigEvent::SecretLeaseRenewed => "The lease of a configuration secret was renewed",
+            ConfigEvent::SecretLeaseError => {
+                "The lease of a configuration secret could not be renewed"
+            }
         }
     }
 }
@@ -1400,6 +1542,10 @@ impl DkimEvent {
             DkimEvent::SignatureExpired => "DKIM signature expired",
             DkimEvent::SignatureLength => "DKIM signature length issue",
             DkimEvent::SignerNotFound => "DKIM signer not found",
+            DkimEvent::KeyRotated => "DKIM key rotated",
+            DkimEvent::KeyRetired => "DKIM key retired",
+            DkimEvent::DnsRecordPublished => "DKIM DNS record published",
+            DkimEvent::DnsRecordPublishFailed => "Failed to publish DKIM DNS record",
         }
     }
 
@@ -1423,6 +1569,14 @@ impl DkimEvent {
             DkimEvent::SignatureExpired => "The DKIM signature has expired",
             DkimEvent::SignatureLength => "The DKIM signature length is incorrect",
             DkimEvent::SignerNotFound => "The DKIM signer was not found",
+            DkimEvent::KeyRotated => "A new DKIM key has been activated for signing",
+            DkimEvent::KeyRetired => "A DKIM key past its overlap period has been removed",
+            DkimEvent::DnsRecordPublished => {
+                "A DKIM DNS record has been updated using the DNS provider"
+            }
+            DkimEvent::DnsRecordPublishFailed => {
+                "The DKIM DNS record could not be updated using the DNS provider"
+            }
         }
     }
 }
@@ -1541,8 +1695,11 @@ impl StoreEvent {
             StoreEvent::LdapError => "LDAP error",
             StoreEvent::ElasticsearchError => "ElasticSearch error",
             StoreEvent::RedisError => "Redis error",
+            StoreEvent::EtcdError => "etcd error",
             StoreEvent::S3Error => "S3 error",
             StoreEvent::AzureError => "Azure error",
+            StoreEvent::GcsError => "Google Cloud Storage error",
+            StoreEvent::PluginError => "Plugin error",
             StoreEvent::FilesystemError => "Filesystem error",
             StoreEvent::PoolError => "Connection pool error",
             StoreEvent::DataCorruption => "Data corruption detected",
@@ -1554,6 +1711,7 @@ impl StoreEvent {
             StoreEvent::UnexpectedError => "Unexpected store error",
             StoreEvent::CryptoError => "Store crypto error",
             StoreEvent::BlobMissingMarker => "Blob missing marker",
+            StoreEvent::BackgroundThrottled => "Background task throttled",
             StoreEvent::SqlQuery => "SQL query executed",
             StoreEvent::LdapQuery => "LDAP query executed",
             StoreEvent::LdapBind => "LDAP bind operation",
@@ -1576,8 +1734,11 @@ impl StoreEvent {
             StoreEvent::LdapError => "An LDAP error occurred",
             StoreEvent::ElasticsearchError => "An ElasticSearch error occurred",
             StoreEvent::RedisError => "A Redis error occurred",
+            StoreEvent::EtcdError => "An etcd error occurred",
             StoreEvent::S3Error => "An S3 error occurred",
             StoreEvent::AzureError => "An Azure error occurred",
+            StoreEvent::GcsError => "A Google Cloud Storage error occurred",
+            StoreEvent::PluginError => "A WebAssembly store plugin error occurred",
             StoreEvent::FilesystemError => "A filesystem error occurred",
             StoreEvent::PoolError => "A connection pool error occurred",
             StoreEvent::DataCorruption => "Data corruption was detected",
@@ -1589,6 +1750,9 @@ impl StoreEvent {
             StoreEvent::UnexpectedError => "An unexpected store error occurred",
             StoreEvent::CryptoError => "A store crypto error occurred",
             StoreEvent::BlobMissingMarker => "The blob is missing a marker",
+            StoreEvent::BackgroundThrottled => {
+                "A background task was delayed to reduce interactive request latency"
+            }
             StoreEvent::SqlQuery => "An SQL query was executed",
             StoreEvent::LdapQuery => "An LDAP query was executed",
             StoreEvent::LdapBind => "An LDAP bind operation was executed",
@@ -1609,6 +1773,8 @@ impl MessageIngestEvent {
             MessageIngestEvent::ImapAppend => "Message appended via IMAP",
             MessageIngestEvent::JmapAppend => "Message appended via JMAP",
             MessageIngestEvent::Duplicate => "Skipping duplicate message",
+            MessageIngestEvent::Forward => "Message forwarded to delegate",
+            MessageIngestEvent::ForwardLoop => "Mail loop detected, not forwarding",
             MessageIngestEvent::Error => "Message ingestion error",
         }
     }
@@ -1620,6 +1786,12 @@ impl MessageIngestEvent {
             MessageIngestEvent::ImapAppend => "The message has been appended via IMAP",
             MessageIngestEvent::JmapAppend => "The message has been appended via JMAP",
             MessageIngestEvent::Duplicate => "The message is a duplicate and has been skipped",
+            MessageIngestEvent::Forward => {
+                "The message has been forwarded to a delegate during an absence window"
+            }
+            MessageIngestEvent::ForwardLoop => {
+                "The message has already been forwarded by this account and was not forwarded again"
+            }
             MessageIngestEvent::Error => "An error occurred while ingesting the message",
         }
     }
@@ -1650,6 +1822,7 @@ impl JmapEvent {
             JmapEvent::WebsocketStart => "JMAP WebSocket connection started",
             JmapEvent::WebsocketStop => "JMAP WebSocket connection stopped",
             JmapEvent::WebsocketError => "JMAP WebSocket error",
+            JmapEvent::ShareAccess => "Shared email link accessed",
         }
     }
 
@@ -1679,6 +1852,7 @@ impl JmapEvent {
             JmapEvent::WebsocketStart => "The JMAP WebSocket connection has started",
             JmapEvent::WebsocketStop => "The JMAP WebSocket connection has stopped",
             JmapEvent::WebsocketError => "An error occurred with the JMAP WebSocket connection",
+            JmapEvent::ShareAccess => "A public link to an email has been accessed",
         }
     }
 }
@@ -1723,7 +1897,16 @@ impl ManageEvent {
             ManageEvent::AssertFailed => "Management assertion failed",
             ManageEvent::NotFound => "Managed resource not found",
             ManageEvent::NotSupported => "Management operation not supported",
+            ManageEvent::BulkOperation => "Bulk management operation",
             ManageEvent::Error => "Management error",
+            ManageEvent::DnsRecordPublished => "DNS record published",
+            ManageEvent::DnsRecordPublishFailed => "Failed to publish DNS record",
+            ManageEvent::DnsRecordPropagated => "DNS record propagated",
+            ManageEvent::DnsRecordPropagationTimeout => "DNS record propagation timeout",
+            ManageEvent::AliasCreated => "Disposable alias created",
+            ManageEvent::AliasDeleted => "Disposable alias deleted",
+            ManageEvent::SnapshotCreated => "Account snapshot created",
+            ManageEvent::SnapshotRestored => "Account restored from snapshot",
         }
     }
 
@@ -1734,7 +1917,24 @@ impl ManageEvent {
             ManageEvent::AssertFailed => "A management assertion has failed",
             ManageEvent::NotFound => "The managed resource was not found",
             ManageEvent::NotSupported => "The management operation is not supported",
+            ManageEvent::BulkOperation => "A bulk management operation was performed",
             ManageEvent::Error => "A management error occurred",
+            ManageEvent::DnsRecordPublished => {
+                "A DNS record for a domain was published through a DNS provider"
+            }
+            ManageEvent::DnsRecordPublishFailed => {
+                "A DNS record for a domain could not be published through a DNS provider"
+            }
+            ManageEvent::DnsRecordPropagated => "A published DNS record is now resolvable",
+            ManageEvent::DnsRecordPropagationTimeout => {
+                "A published DNS record did not propagate before the timeout"
+            }
+            ManageEvent::AliasCreated => "A user created a disposable alias for their mailbox",
+            ManageEvent::AliasDeleted => "A user deleted one of their disposable aliases",
+            ManageEvent::SnapshotCreated => "A point-in-time snapshot of an account was taken",
+            ManageEvent::SnapshotRestored => {
+                "Mailboxes and messages were restored from an account snapshot"
+            }
         }
     }
 }
@@ -1749,6 +1949,8 @@ impl AuthEvent {
             AuthEvent::Error => "Authentication error",
             AuthEvent::TokenExpired => "OAuth token expired",
             AuthEvent::ClientRegistration => "OAuth Client registration",
+            AuthEvent::ChallengeRequired => "Authentication challenge required",
+            AuthEvent::ChallengeFailed => "Authentication challenge failed",
         }
     }
 
@@ -1761,6 +1963,10 @@ impl AuthEvent {
             AuthEvent::Error => "An error occurred with authentication",
             AuthEvent::TokenExpired => "OAuth authentication token has expired",
             AuthEvent::ClientRegistration => "OAuth client successfully registered",
+            AuthEvent::ChallengeRequired => {
+                "A challenge must be solved before authenticating from this IP address"
+            }
+            AuthEvent::ChallengeFailed => "The authentication challenge response was invalid",
         }
     }
 }
@@ -1773,6 +1979,8 @@ impl ResourceEvent {
             ResourceEvent::Error => "Resource error",
             ResourceEvent::DownloadExternal => "Downloading external resource",
             ResourceEvent::WebadminUnpacked => "Webadmin resource unpacked",
+            ResourceEvent::GeoIpLoaded => "GeoIP database loaded",
+            ResourceEvent::LookupReloaded => "Lookup list reloaded",
         }
     }
 
@@ -1783,6 +1991,8 @@ impl ResourceEvent {
             ResourceEvent::Error => "An error occurred with the resource",
             ResourceEvent::DownloadExternal => "The external resource is being downloaded",
             ResourceEvent::WebadminUnpacked => "The webadmin resource has been unpacked",
+            ResourceEvent::GeoIpLoaded => "The GeoIP database has been loaded",
+            ResourceEvent::LookupReloaded => "A file-backed lookup list has been reloaded",
         }
     }
 }
@@ -1796,6 +2006,8 @@ impl SecurityEvent {
             SecurityEvent::IpBlocked => "Blocked IP address",
             SecurityEvent::ScanBan => "Banned due to scan",
             SecurityEvent::Unauthorized => "Unauthorized access",
+            SecurityEvent::AuthenticationLockout => "Login temporarily locked",
+            SecurityEvent::SubmissionSuspended => "Message submission suspended",
         }
     }
 
@@ -1811,6 +2023,12 @@ impl SecurityEvent {
             SecurityEvent::LoiterBan => "IP address was banned due to multiple loitering events",
             SecurityEvent::IpBlocked => "Rejected connection from blocked IP address",
             SecurityEvent::Unauthorized => "Account does not have permission to access resource",
+            SecurityEvent::AuthenticationLockout => {
+                "Logins for an account were temporarily locked due to multiple authentication errors"
+            }
+            SecurityEvent::SubmissionSuspended => {
+                "Message submission for an account was suspended due to anomalous sending patterns"
+            }
         }
     }
 }
@@ -20,7 +20,7 @@ impl EventType {
                 | StoreEvent::SqlQuery
                 | StoreEvent::LdapQuery
                 | StoreEvent::LdapBind => Level::Trace,
-                StoreEvent::NotFound => Level::Debug,
+                StoreEvent::NotFound | StoreEvent::BackgroundThrottled => Level::Debug,
                 StoreEvent::AssertValueFailed
                 | StoreEvent::FoundationdbError
                 | StoreEvent::MysqlError
@@ -30,8 +30,11 @@ impl EventType {
                 | StoreEvent::LdapError
                 | StoreEvent::ElasticsearchError
                 | StoreEvent::RedisError
+                | StoreEvent::EtcdError
                 | StoreEvent::S3Error
                 | StoreEvent::AzureError
+                | StoreEvent::GcsError
+                | StoreEvent::PluginError
                 | StoreEvent::FilesystemError
                 | StoreEvent::PoolError
                 | StoreEvent::DataCorruption
@@ -43,6 +46,7 @@ impl EventType {
                 | StoreEvent::CryptoError => Level::Error,
                 StoreEvent::BlobMissingMarker => Level::Warn,
             },
+            EventType::Jmap(JmapEvent::ShareAccess) => Level::Info,
             EventType::Jmap(_) => Level::Debug,
             EventType::Imap(event) => match event {
                 ImapEvent::ConnectionStart | ImapEvent::ConnectionEnd => Level::Debug,
@@ -75,6 +79,8 @@ impl EventType {
                 | ImapEvent::Subscribe
                 | ImapEvent::Unsubscribe
                 | ImapEvent::Thread
+                | ImapEvent::GetMetadata
+                | ImapEvent::SetMetadata
                 | ImapEvent::Error
                 | ImapEvent::IdleStart
                 | ImapEvent::IdleStop => Level::Debug,
@@ -91,6 +97,8 @@ impl EventType {
                 | ManageSieveEvent::HaveSpace
                 | ManageSieveEvent::ListScripts
                 | ManageSieveEvent::SetActive
+                | ManageSieveEvent::ListVacation
+                | ManageSieveEvent::ClearVacation
                 | ManageSieveEvent::Capabilities
                 | ManageSieveEvent::StartTls
                 | ManageSieveEvent::Unauthenticate
@@ -124,6 +132,7 @@ impl EventType {
                 | SmtpEvent::LhloExpected
                 | SmtpEvent::MailFromUnauthenticated
                 | SmtpEvent::MailFromUnauthorized
+                | SmtpEvent::SenderUnauthorized
                 | SmtpEvent::MailFromRewritten
                 | SmtpEvent::MailFromMissing
                 | SmtpEvent::MultipleMailFrom
@@ -158,7 +167,10 @@ impl EventType {
                 | SmtpEvent::PipeSuccess
                 | SmtpEvent::PipeError
                 | SmtpEvent::Error => Level::Debug,
-                SmtpEvent::MissingLocalHostname | SmtpEvent::RemoteIdNotFound => Level::Warn,
+                SmtpEvent::MissingLocalHostname
+                | SmtpEvent::RemoteIdNotFound
+                | SmtpEvent::RcptVerifyError
+                | SmtpEvent::MalwareScanError => Level::Warn,
                 SmtpEvent::ConcurrencyLimitExceeded
                 | SmtpEvent::TransferLimitExceeded
                 | SmtpEvent::RateLimitExceeded
@@ -196,7 +208,18 @@ impl EventType {
                 | SmtpEvent::AuthMechanismNotSupported
                 | SmtpEvent::ExpnDisabled
                 | SmtpEvent::RequestTooLarge
-                | SmtpEvent::TooManyRecipients => Level::Info,
+                | SmtpEvent::TooManyRecipients
+                | SmtpEvent::ListPostDistributed
+                | SmtpEvent::ListPostHeld
+                | SmtpEvent::ListPostApproved
+                | SmtpEvent::ListPostRejected
+                | SmtpEvent::ListBounce
+                | SmtpEvent::ListUnsubscribed
+                | SmtpEvent::MalwareFound
+                | SmtpEvent::AttachmentBlocked
+                | SmtpEvent::AttachmentStripped
+                | SmtpEvent::RcptToSuppressed
+                | SmtpEvent::RecipientSuppressed => Level::Info,
                 SmtpEvent::RawInput | SmtpEvent::RawOutput => Level::Trace,
             },
             EventType::Network(event) => match event {
@@ -204,13 +227,18 @@ impl EventType {
                 | NetworkEvent::WriteError
                 | NetworkEvent::FlushError
                 | NetworkEvent::Closed => Level::Trace,
-                NetworkEvent::Timeout | NetworkEvent::AcceptError => Level::Debug,
+                NetworkEvent::Timeout
+                | NetworkEvent::AcceptError
+                | NetworkEvent::ProxyConnection
+                | NetworkEvent::HealthCheck => Level::Debug,
                 NetworkEvent::ListenStart | NetworkEvent::ListenStop => Level::Info,
                 NetworkEvent::ListenError
                 | NetworkEvent::BindError
                 | NetworkEvent::SetOptError
                 | NetworkEvent::SplitError => Level::Error,
-                NetworkEvent::ProxyError => Level::Warn,
+                NetworkEvent::ProxyError
+                | NetworkEvent::ProxyUntrusted
+                | NetworkEvent::HealthCheckFailed => Level::Warn,
             },
             EventType::Limit(cause) => match cause {
                 LimitEvent::SizeRequest => Level::Debug,
@@ -224,10 +252,23 @@ impl EventType {
                 LimitEvent::TooManyRequests => Level::Warn,
                 LimitEvent::TenantQuota => Level::Info,
             },
-            EventType::Manage(_) => Level::Debug,
+            EventType::Manage(event) => match event {
+                ManageEvent::BulkOperation
+                | ManageEvent::DnsRecordPublished
+                | ManageEvent::DnsRecordPropagated
+                | ManageEvent::AliasCreated
+                | ManageEvent::AliasDeleted
+                | ManageEvent::SnapshotCreated
+                | ManageEvent::SnapshotRestored => Level::Info,
+                ManageEvent::DnsRecordPublishFailed | ManageEvent::DnsRecordPropagationTimeout => {
+                    Level::Warn
+                }
+                _ => Level::Debug,
+            },
             EventType::Auth(cause) => match cause {
                 AuthEvent::Failed | AuthEvent::TokenExpired => Level::Debug,
                 AuthEvent::MissingTotp => Level::Trace,
+                AuthEvent::ChallengeRequired | AuthEvent::ChallengeFailed => Level::Debug,
                 AuthEvent::TooManyAttempts => Level::Warn,
                 AuthEvent::Error => Level::Error,
                 AuthEvent::Success | AuthEvent::ClientRegistration => Level::Info,
@@ -242,14 +283,20 @@ impl EventType {
                 | ConfigEvent::MissingSetting
                 | ConfigEvent::UnusedSetting
                 | ConfigEvent::AlreadyUpToDate
-                | ConfigEvent::ExternalKeyIgnored => Level::Debug,
-                ConfigEvent::ParseWarning | ConfigEvent::BuildWarning => Level::Warn,
+                | ConfigEvent::ExternalKeyIgnored
+                | ConfigEvent::SecretLeaseRenewed => Level::Debug,
+                ConfigEvent::ParseWarning
+                | ConfigEvent::BuildWarning
+                | ConfigEvent::SecretLeaseError => Level::Warn,
                 ConfigEvent::ImportExternal => Level::Info,
             },
             EventType::Resource(cause) => match cause {
                 ResourceEvent::NotFound => Level::Debug,
                 ResourceEvent::BadParameters | ResourceEvent::Error => Level::Error,
-                ResourceEvent::DownloadExternal | ResourceEvent::WebadminUnpacked => Level::Info,
+                ResourceEvent::DownloadExternal
+                | ResourceEvent::WebadminUnpacked
+                | ResourceEvent::GeoIpLoaded
+                | ResourceEvent::LookupReloaded => Level::Info,
             },
             EventType::Arc(event) => match event {
                 ArcEvent::ChainTooLong
@@ -260,7 +307,10 @@ impl EventType {
                 ArcEvent::SealerNotFound => Level::Warn,
             },
             EventType::Dkim(event) => match event {
-                DkimEvent::SignerNotFound => Level::Warn,
+                DkimEvent::SignerNotFound | DkimEvent::DnsRecordPublishFailed => Level::Warn,
+                DkimEvent::KeyRotated | DkimEvent::KeyRetired | DkimEvent::DnsRecordPublished => {
+                    Level::Info
+                }
                 _ => Level::Debug,
             },
             EventType::MailAuth(_) => Level::Debug,
@@ -279,10 +329,13 @@ impl EventType {
                 EvalEvent::DirectoryNotFound => Level::Warn,
             },
             EventType::Server(event) => match event {
-                ServerEvent::Startup | ServerEvent::Shutdown | ServerEvent::Licensing => {
-                    Level::Info
-                }
+                ServerEvent::Startup
+                | ServerEvent::Shutdown
+                | ServerEvent::Licensing
+                | ServerEvent::DrainStart
+                | ServerEvent::DrainComplete => Level::Info,
                 ServerEvent::StartupError | ServerEvent::ThreadError => Level::Error,
+                ServerEvent::DrainTimeout => Level::Warn,
             },
             EventType::Acme(event) => match event {
                 AcmeEvent::DnsRecordCreated
@@ -314,9 +367,9 @@ impl EventType {
                 | AcmeEvent::DnsRecordLookupFailed => Level::Debug,
             },
             EventType::Tls(event) => match event {
-                TlsEvent::Handshake => Level::Info,
+                TlsEvent::Handshake | TlsEvent::CertificatesReloaded => Level::Info,
                 TlsEvent::HandshakeError | TlsEvent::CertificateNotFound => Level::Debug,
-                TlsEvent::NotConfigured => Level::Error,
+                TlsEvent::NotConfigured | TlsEvent::CertificateSourceError => Level::Error,
                 TlsEvent::NoCertificatesAvailable | TlsEvent::MultipleCertificatesAvailable => {
                     Level::Warn
                 }
@@ -342,8 +395,15 @@ impl EventType {
                 SpamEvent::Train
                 | SpamEvent::Classify
                 | SpamEvent::NotEnoughTrainingData
-                | SpamEvent::TrainBalance => Level::Debug,
-                SpamEvent::ListUpdated => Level::Info,
+                | SpamEvent::TrainBalance
+                | SpamEvent::Reputation => Level::Debug,
+                SpamEvent::ListUpdated
+                | SpamEvent::Outbound
+                | SpamEvent::TrainFeedback
+                | SpamEvent::Quarantine
+                | SpamEvent::QuarantineRelease
+                | SpamEvent::QuarantineDigest
+                | SpamEvent::ReputationThrottle => Level::Info,
             },
             EventType::Http(event) => match event {
                 HttpEvent::ConnectionStart | HttpEvent::ConnectionEnd => Level::Debug,
@@ -378,8 +438,8 @@ impl EventType {
                 HousekeeperEvent::Schedule => Level::Debug,
             },
             EventType::FtsIndex(event) => match event {
-                FtsIndexEvent::Index => Level::Info,
-                FtsIndexEvent::LockBusy => Level::Warn,
+                FtsIndexEvent::Index | FtsIndexEvent::Recovered => Level::Info,
+                FtsIndexEvent::LockBusy | FtsIndexEvent::Degraded => Level::Warn,
                 FtsIndexEvent::BlobNotFound
                 | FtsIndexEvent::Locked
                 | FtsIndexEvent::MetadataNotFound => Level::Debug,
@@ -430,6 +490,8 @@ impl EventType {
                 | DeliveryEvent::Completed
                 | DeliveryEvent::Failed
                 | DeliveryEvent::DomainDeliveryStart
+                | DeliveryEvent::RouteSelected
+                | DeliveryEvent::IpPoolSkipped
                 | DeliveryEvent::MxLookupFailed
                 | DeliveryEvent::IpLookupFailed
                 | DeliveryEvent::NullMx
@@ -451,6 +513,7 @@ impl EventType {
                 | DeliveryEvent::DoubleBounce => Level::Info,
                 DeliveryEvent::ConcurrencyLimitExceeded
                 | DeliveryEvent::RateLimitExceeded
+                | DeliveryEvent::IpPoolExhausted
                 | DeliveryEvent::MissingOutboundHostname => Level::Warn,
                 DeliveryEvent::DsnSuccess
                 | DeliveryEvent::DsnTempFail
@@ -460,7 +523,8 @@ impl EventType {
                 | DeliveryEvent::Ehlo
                 | DeliveryEvent::Auth
                 | DeliveryEvent::MailFrom
-                | DeliveryEvent::RcptTo => Level::Debug,
+                | DeliveryEvent::RcptTo
+                | DeliveryEvent::IpPoolSelected => Level::Debug,
                 DeliveryEvent::RawInput | DeliveryEvent::RawOutput => Level::Trace,
             },
             EventType::Queue(event) => match event {
@@ -472,7 +536,10 @@ impl EventType {
                 | QueueEvent::RateLimitExceeded
                 | QueueEvent::ConcurrencyLimitExceeded
                 | QueueEvent::Rescheduled
-                | QueueEvent::QuotaExceeded => Level::Info,
+                | QueueEvent::QuotaExceeded
+                | QueueEvent::Held
+                | QueueEvent::Released
+                | QueueEvent::Rerouted => Level::Info,
                 QueueEvent::LockBusy | QueueEvent::Locked | QueueEvent::BlobNotFound => {
                     Level::Debug
                 }
@@ -532,7 +599,9 @@ impl EventType {
                 | MessageIngestEvent::Spam
                 | MessageIngestEvent::ImapAppend
                 | MessageIngestEvent::JmapAppend
-                | MessageIngestEvent::Duplicate => Level::Info,
+                | MessageIngestEvent::Duplicate
+                | MessageIngestEvent::Forward
+                | MessageIngestEvent::ForwardLoop => Level::Info,
                 MessageIngestEvent::Error => Level::Error,
             },
             EventType::Security(_) => Level::Info,
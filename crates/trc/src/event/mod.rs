@@ -306,8 +306,11 @@ impl StoreEvent {
             Self::LdapError => "LDAP error",
             Self::ElasticsearchError => "ElasticSearch error",
             Self::RedisError => "Redis error",
+            Self::EtcdError => "etcd error",
             Self::S3Error => "S3 error",
             Self::AzureError => "Azure error",
+            Self::GcsError => "Google Cloud Storage error",
+            Self::PluginError => "Plugin error",
             Self::FilesystemError => "Filesystem error",
             Self::PoolError => "Connection pool error",
             Self::DataCorruption => "Data corruption",
@@ -393,6 +396,15 @@ impl ManageEvent {
             Self::NotFound => "Not found",
             Self::NotSupported => "Operation not supported",
             Self::Error => "Management API Error",
+            Self::BulkOperation => "Bulk operation",
+            Self::DnsRecordPublished => "DNS record published",
+            Self::DnsRecordPublishFailed => "DNS record publish failed",
+            Self::DnsRecordPropagated => "DNS record propagated",
+            Self::DnsRecordPropagationTimeout => "DNS record propagation timed out",
+            Self::AliasCreated => "Alias created",
+            Self::AliasDeleted => "Alias deleted",
+            Self::SnapshotCreated => "Snapshot created",
+            Self::SnapshotRestored => "Snapshot restored",
         }
     }
 }
@@ -49,6 +49,7 @@ static SERVER_MEMORY: AtomicGauge = AtomicGauge::new(MetricType::ServerMemory);
 static QUEUE_COUNT: AtomicGauge = AtomicGauge::new(MetricType::QueueCount);
 static USER_COUNT: AtomicGauge = AtomicGauge::new(MetricType::UserCount);
 static DOMAIN_COUNT: AtomicGauge = AtomicGauge::new(MetricType::DomainCount);
+static BLOB_DEDUP_RATIO: AtomicGauge = AtomicGauge::new(MetricType::BlobDedupRatio);
 
 const CONN_SMTP_IN: usize = 0;
 const CONN_SMTP_OUT: usize = 1;
@@ -226,9 +227,19 @@ impl Collector {
     }
 
     pub fn collect_gauges(is_enterprise: bool) -> impl Iterator<Item = &'static AtomicGauge> {
-        static E_GAUGES: &[&AtomicGauge] =
-            &[&SERVER_MEMORY, &QUEUE_COUNT, &USER_COUNT, &DOMAIN_COUNT];
-        static C_GAUGES: &[&AtomicGauge] = &[&SERVER_MEMORY, &USER_COUNT, &DOMAIN_COUNT];
+        static E_GAUGES: &[&AtomicGauge] = &[
+            &SERVER_MEMORY,
+            &QUEUE_COUNT,
+            &USER_COUNT,
+            &DOMAIN_COUNT,
+            &BLOB_DEDUP_RATIO,
+        ];
+        static C_GAUGES: &[&AtomicGauge] = &[
+            &SERVER_MEMORY,
+            &USER_COUNT,
+            &DOMAIN_COUNT,
+            &BLOB_DEDUP_RATIO,
+        ];
 
         if is_enterprise { E_GAUGES } else { C_GAUGES }
             .iter()
@@ -315,6 +326,7 @@ impl Collector {
             MetricType::SieveRequestTime => CONNECTION_METRICS[CONN_SIEVE].elapsed.average(),
             MetricType::UserCount => USER_COUNT.get() as f64,
             MetricType::DomainCount => DOMAIN_COUNT.get() as f64,
+            MetricType::BlobDedupRatio => BLOB_DEDUP_RATIO.get() as f64,
         }
     }
 
@@ -324,6 +336,7 @@ impl Collector {
             MetricType::QueueCount => QUEUE_COUNT.set(value),
             MetricType::UserCount => USER_COUNT.set(value),
             MetricType::DomainCount => DOMAIN_COUNT.set(value),
+            MetricType::BlobDedupRatio => BLOB_DEDUP_RATIO.set(value),
             _ => {}
         }
     }
@@ -410,7 +423,7 @@ const fn init_conn_metrics() -> [ConnectionMetrics; TOTAL_CONN_TYPES] {
 impl EventType {
     pub fn is_metric(&self) -> bool {
         match self {
-            EventType::Server(ServerEvent::ThreadError) => true,
+            EventType::Server(ServerEvent::ThreadError | ServerEvent::DrainTimeout) => true,
             EventType::Purge(PurgeEvent::Error) => true,
             EventType::Eval(
                 EvalEvent::Error | EvalEvent::StoreNotFound | EvalEvent::DirectoryNotFound,
@@ -439,8 +452,11 @@ impl EventType {
                 | StoreEvent::LdapError
                 | StoreEvent::ElasticsearchError
                 | StoreEvent::RedisError
+                | StoreEvent::EtcdError
                 | StoreEvent::S3Error
                 | StoreEvent::AzureError
+                | StoreEvent::GcsError
+                | StoreEvent::PluginError
                 | StoreEvent::FilesystemError
                 | StoreEvent::PoolError
                 | StoreEvent::DataCorruption
@@ -452,6 +468,7 @@ impl EventType {
                 | StoreEvent::UnexpectedError
                 | StoreEvent::CryptoError
                 | StoreEvent::BlobMissingMarker
+                | StoreEvent::BackgroundThrottled
                 | StoreEvent::DataWrite
                 | StoreEvent::DataIterate
                 | StoreEvent::BlobRead
@@ -463,6 +480,7 @@ impl EventType {
                 JmapEvent::MethodCall
                 | JmapEvent::WebsocketStart
                 | JmapEvent::WebsocketError
+                | JmapEvent::ShareAccess
                 | JmapEvent::UnsupportedFilter
                 | JmapEvent::UnsupportedSort
                 | JmapEvent::Forbidden
@@ -505,6 +523,7 @@ impl EventType {
                 | SmtpEvent::DidNotSayEhlo
                 | SmtpEvent::MailFromUnauthenticated
                 | SmtpEvent::MailFromUnauthorized
+                | SmtpEvent::SenderUnauthorized
                 | SmtpEvent::MailFromMissing
                 | SmtpEvent::MultipleMailFrom
                 | SmtpEvent::MailboxDoesNotExist
@@ -518,7 +537,17 @@ impl EventType {
                 | SmtpEvent::CommandNotImplemented
                 | SmtpEvent::InvalidCommand
                 | SmtpEvent::SyntaxError
-                | SmtpEvent::RequestTooLarge,
+                | SmtpEvent::RequestTooLarge
+                | SmtpEvent::RcptVerifyError
+                | SmtpEvent::ListPostDistributed
+                | SmtpEvent::ListPostHeld
+                | SmtpEvent::ListUnsubscribed
+                | SmtpEvent::MalwareFound
+                | SmtpEvent::MalwareScanError
+                | SmtpEvent::AttachmentBlocked
+                | SmtpEvent::AttachmentStripped
+                | SmtpEvent::RcptToSuppressed
+                | SmtpEvent::RecipientSuppressed,
             ) => true,
             EventType::Http(
                 HttpEvent::Error
@@ -526,7 +555,11 @@ impl EventType {
                 | HttpEvent::ResponseBody
                 | HttpEvent::XForwardedMissing,
             ) => true,
-            EventType::Network(NetworkEvent::Timeout) => true,
+            EventType::Network(
+                NetworkEvent::Timeout
+                | NetworkEvent::ProxyUntrusted
+                | NetworkEvent::HealthCheckFailed,
+            ) => true,
             EventType::Security(_) => true,
             EventType::Limit(_) => true,
             EventType::Manage(_) => false,
@@ -562,7 +595,7 @@ impl EventType {
             ) => true,
             EventType::Spf(_) => true,
             EventType::MailAuth(_) => true,
-            EventType::Tls(TlsEvent::HandshakeError) => true,
+            EventType::Tls(TlsEvent::HandshakeError | TlsEvent::CertificateSourceError) => true,
             EventType::Sieve(
                 SieveEvent::ActionAccept
                 | SieveEvent::ActionAcceptReplace
@@ -580,6 +613,11 @@ impl EventType {
                 | SpamEvent::ListUpdated
                 | SpamEvent::Train
                 | SpamEvent::TrainError
+                | SpamEvent::TrainFeedback
+                | SpamEvent::Quarantine
+                | SpamEvent::QuarantineRelease
+                | SpamEvent::QuarantineDigest
+                | SpamEvent::ReputationThrottle
                 | SpamEvent::Classify
                 | SpamEvent::ClassifyError
                 | SpamEvent::NotEnoughTrainingData,
@@ -598,7 +636,8 @@ impl EventType {
             EventType::FtsIndex(
                 FtsIndexEvent::Index
                 | FtsIndexEvent::BlobNotFound
-                | FtsIndexEvent::MetadataNotFound,
+                | FtsIndexEvent::MetadataNotFound
+                | FtsIndexEvent::Degraded,
             ) => true,
             EventType::Milter(
                 MilterEvent::ActionAccept
@@ -632,6 +671,9 @@ impl EventType {
                 | DeliveryEvent::ConcurrencyLimitExceeded
                 | DeliveryEvent::RateLimitExceeded
                 | DeliveryEvent::DoubleBounce
+                | DeliveryEvent::RouteSelected
+                | DeliveryEvent::IpPoolSkipped
+                | DeliveryEvent::IpPoolExhausted
                 | DeliveryEvent::DsnSuccess
                 | DeliveryEvent::DsnTempFail
                 | DeliveryEvent::DsnPermFail,
@@ -646,7 +688,10 @@ impl EventType {
                 | QueueEvent::BlobNotFound
                 | QueueEvent::RateLimitExceeded
                 | QueueEvent::ConcurrencyLimitExceeded
-                | QueueEvent::QuotaExceeded,
+                | QueueEvent::QuotaExceeded
+                | QueueEvent::Held
+                | QueueEvent::Released
+                | QueueEvent::Rerouted,
             ) => true,
             EventType::TlsRpt(_) => false,
             EventType::MtaSts(
@@ -205,6 +205,8 @@ pub enum SecurityEvent {
     LoiterBan,
     IpBlocked,
     Unauthorized,
+    AuthenticationLockout,
+    SubmissionSuspended,
 }
 
 #[event_type]
@@ -241,6 +243,8 @@ pub enum FtsIndexEvent {
     LockBusy,
     BlobNotFound,
     MetadataNotFound,
+    Degraded,
+    Recovered,
 }
 
 #[event_type]
@@ -280,6 +284,8 @@ pub enum ImapEvent {
     Subscribe,
     Unsubscribe,
     Thread,
+    GetMetadata,
+    SetMetadata,
 
     // Errors
     Error,
@@ -332,6 +338,8 @@ pub enum ManageSieveEvent {
     HaveSpace,
     ListScripts,
     SetActive,
+    ListVacation,
+    ClearVacation,
     Capabilities,
     StartTls,
     Unauthenticate,
@@ -382,6 +390,7 @@ pub enum SmtpEvent {
     LhloExpected,
     MailFromUnauthenticated,
     MailFromUnauthorized,
+    SenderUnauthorized,
     MailFromNotAllowed,
     MailFromRewritten,
     MailFromMissing,
@@ -431,6 +440,19 @@ pub enum SmtpEvent {
     UnsupportedParameter,
     SyntaxError,
     RequestTooLarge,
+    RcptVerifyError,
+    ListPostDistributed,
+    ListPostHeld,
+    ListPostApproved,
+    ListPostRejected,
+    ListBounce,
+    ListUnsubscribed,
+    MalwareFound,
+    MalwareScanError,
+    AttachmentBlocked,
+    AttachmentStripped,
+    RcptToSuppressed,
+    RecipientSuppressed,
 }
 
 #[event_type]
@@ -473,6 +495,10 @@ pub enum DeliveryEvent {
     DsnPermFail,
     RawInput,
     RawOutput,
+    RouteSelected,
+    IpPoolSelected,
+    IpPoolSkipped,
+    IpPoolExhausted,
 }
 
 #[event_type]
@@ -489,6 +515,9 @@ pub enum QueueEvent {
     RateLimitExceeded,
     ConcurrencyLimitExceeded,
     QuotaExceeded,
+    Held,
+    Released,
+    Rerouted,
 }
 
 #[event_type]
@@ -609,6 +638,13 @@ pub enum SpamEvent {
     Classify,
     ClassifyError,
     NotEnoughTrainingData,
+    Outbound,
+    TrainFeedback,
+    Quarantine,
+    QuarantineRelease,
+    QuarantineDigest,
+    Reputation,
+    ReputationThrottle,
 }
 
 #[event_type]
@@ -635,6 +671,8 @@ pub enum TlsEvent {
     CertificateNotFound,
     NoCertificatesAvailable,
     MultipleCertificatesAvailable,
+    CertificatesReloaded,
+    CertificateSourceError,
 }
 
 #[event_type]
@@ -651,6 +689,10 @@ pub enum NetworkEvent {
     Timeout,
     Closed,
     ProxyError,
+    ProxyConnection,
+    ProxyUntrusted,
+    HealthCheck,
+    HealthCheckFailed,
     SetOptError,
 }
 
@@ -661,6 +703,9 @@ pub enum ServerEvent {
     StartupError,
     ThreadError,
     Licensing,
+    DrainStart,
+    DrainComplete,
+    DrainTimeout,
 }
 
 #[event_type]
@@ -739,6 +784,8 @@ pub enum ConfigEvent {
     ImportExternal,
     ExternalKeyIgnored,
     AlreadyUpToDate,
+    SecretLeaseRenewed,
+    SecretLeaseError,
 }
 
 #[event_type]
@@ -771,6 +818,10 @@ pub enum DkimEvent {
     SignatureExpired,
     SignatureLength,
     SignerNotFound,
+    KeyRotated,
+    KeyRetired,
+    DnsRecordPublished,
+    DnsRecordPublishFailed,
 }
 
 #[event_type]
@@ -828,8 +879,11 @@ pub enum StoreEvent {
     LdapError,
     ElasticsearchError,
     RedisError,
+    EtcdError,
     S3Error,
     AzureError,
+    GcsError,
+    PluginError,
     FilesystemError,
     PoolError,
     DataCorruption,
@@ -843,6 +897,7 @@ pub enum StoreEvent {
 
     // Warnings
     BlobMissingMarker,
+    BackgroundThrottled,
 
     // Traces
     DataWrite,
@@ -863,6 +918,8 @@ pub enum MessageIngestEvent {
     ImapAppend,
     JmapAppend,
     Duplicate,
+    Forward,
+    ForwardLoop,
     Error,
 }
 
@@ -897,6 +954,7 @@ pub enum JmapEvent {
     WebsocketStart,
     WebsocketStop,
     WebsocketError,
+    ShareAccess,
 }
 
 #[event_type]
@@ -920,7 +978,16 @@ pub enum ManageEvent {
     AssertFailed,
     NotFound,
     NotSupported,
+    BulkOperation,
     Error,
+    DnsRecordPublished,
+    DnsRecordPublishFailed,
+    DnsRecordPropagated,
+    DnsRecordPropagationTimeout,
+    AliasCreated,
+    AliasDeleted,
+    SnapshotCreated,
+    SnapshotRestored,
 }
 
 #[event_type]
@@ -931,6 +998,8 @@ pub enum AuthEvent {
     MissingTotp,
     TooManyAttempts,
     ClientRegistration,
+    ChallengeRequired,
+    ChallengeFailed,
     Error,
 }
 
@@ -941,6 +1010,8 @@ pub enum ResourceEvent {
     Error,
     DownloadExternal,
     WebadminUnpacked,
+    GeoIpLoaded,
+    LookupReloaded,
 }
 
 #[event_type]
@@ -978,6 +1049,7 @@ pub enum MetricType {
     SieveRequestTime,
     UserCount,
     DomainCount,
+    BlobDedupRatio,
 }
 
 pub const TOTAL_EVENT_COUNT: usize = total_event_count!();
@@ -866,6 +866,77 @@ impl EventType {
             EventType::Security(SecurityEvent::ScanBan) => 558,
             EventType::Store(StoreEvent::AzureError) => 559,
             EventType::TlsRpt(TlsRptEvent::RecordNotFound) => 560,
+            EventType::Jmap(JmapEvent::ShareAccess) => 561,
+            EventType::Security(SecurityEvent::AuthenticationLockout) => 562,
+            EventType::Auth(AuthEvent::ChallengeRequired) => 563,
+            EventType::Auth(AuthEvent::ChallengeFailed) => 564,
+            EventType::Manage(ManageEvent::BulkOperation) => 565,
+            EventType::Security(SecurityEvent::SubmissionSuspended) => 566,
+            EventType::Spam(SpamEvent::Outbound) => 567,
+            EventType::Spam(SpamEvent::TrainFeedback) => 568,
+            EventType::Smtp(SmtpEvent::SenderUnauthorized) => 569,
+            EventType::Spam(SpamEvent::Quarantine) => 570,
+            EventType::Spam(SpamEvent::QuarantineRelease) => 571,
+            EventType::Spam(SpamEvent::QuarantineDigest) => 572,
+            EventType::MessageIngest(MessageIngestEvent::Forward) => 573,
+            EventType::MessageIngest(MessageIngestEvent::ForwardLoop) => 574,
+            EventType::Spam(SpamEvent::Reputation) => 575,
+            EventType::Spam(SpamEvent::ReputationThrottle) => 576,
+            EventType::Dkim(DkimEvent::KeyRotated) => 577,
+            EventType::Dkim(DkimEvent::KeyRetired) => 578,
+            EventType::Dkim(DkimEvent::DnsRecordPublished) => 579,
+            EventType::Dkim(DkimEvent::DnsRecordPublishFailed) => 580,
+            EventType::Manage(ManageEvent::DnsRecordPublished) => 581,
+            EventType::Manage(ManageEvent::DnsRecordPublishFailed) => 582,
+            EventType::Manage(ManageEvent::DnsRecordPropagated) => 583,
+            EventType::Manage(ManageEvent::DnsRecordPropagationTimeout) => 584,
+            EventType::Smtp(SmtpEvent::RcptVerifyError) => 585,
+            EventType::Tls(TlsEvent::CertificatesReloaded) => 586,
+            EventType::Tls(TlsEvent::CertificateSourceError) => 587,
+            EventType::Network(NetworkEvent::ProxyConnection) => 588,
+            EventType::Network(NetworkEvent::ProxyUntrusted) => 589,
+            EventType::Network(NetworkEvent::HealthCheck) => 590,
+            EventType::Network(NetworkEvent::HealthCheckFailed) => 591,
+            EventType::Store(StoreEvent::BackgroundThrottled) => 592,
+            EventType::Server(ServerEvent::DrainStart) => 593,
+            EventType::Server(ServerEvent::DrainComplete) => 594,
+            EventType::Server(ServerEvent::DrainTimeout) => 595,
+            EventType::FtsIndex(FtsIndexEvent::Degraded) => 596,
+            EventType::FtsIndex(FtsIndexEvent::Recovered) => 597,
+            EventType::ManageSieve(ManageSieveEvent::ListVacation) => 598,
+            EventType::ManageSieve(ManageSieveEvent::ClearVacation) => 599,
+            EventType::Config(ConfigEvent::SecretLeaseRenewed) => 600,
+            EventType::Config(ConfigEvent::SecretLeaseError) => 601,
+            EventType::Resource(ResourceEvent::GeoIpLoaded) => 602,
+            EventType::Store(StoreEvent::PluginError) => 603,
+            EventType::Resource(ResourceEvent::LookupReloaded) => 604,
+            EventType::Manage(ManageEvent::AliasCreated) => 605,
+            EventType::Manage(ManageEvent::AliasDeleted) => 606,
+            EventType::Smtp(SmtpEvent::ListPostDistributed) => 607,
+            EventType::Smtp(SmtpEvent::ListPostHeld) => 608,
+            EventType::Smtp(SmtpEvent::ListPostApproved) => 609,
+            EventType::Smtp(SmtpEvent::ListPostRejected) => 610,
+            EventType::Smtp(SmtpEvent::ListBounce) => 611,
+            EventType::Smtp(SmtpEvent::ListUnsubscribed) => 612,
+            EventType::Smtp(SmtpEvent::MalwareFound) => 613,
+            EventType::Smtp(SmtpEvent::MalwareScanError) => 614,
+            EventType::Smtp(SmtpEvent::AttachmentBlocked) => 615,
+            EventType::Smtp(SmtpEvent::AttachmentStripped) => 616,
+            EventType::Delivery(DeliveryEvent::RouteSelected) => 617,
+            EventType::Delivery(DeliveryEvent::IpPoolSelected) => 618,
+            EventType::Delivery(DeliveryEvent::IpPoolSkipped) => 619,
+            EventType::Delivery(DeliveryEvent::IpPoolExhausted) => 620,
+            EventType::Smtp(SmtpEvent::RcptToSuppressed) => 621,
+            EventType::Smtp(SmtpEvent::RecipientSuppressed) => 622,
+            EventType::Queue(QueueEvent::Held) => 623,
+            EventType::Queue(QueueEvent::Released) => 624,
+            EventType::Queue(QueueEvent::Rerouted) => 625,
+            EventType::Imap(ImapEvent::GetMetadata) => 626,
+            EventType::Imap(ImapEvent::SetMetadata) => 627,
+            EventType::Manage(ManageEvent::SnapshotCreated) => 628,
+            EventType::Manage(ManageEvent::SnapshotRestored) => 629,
+            EventType::Store(StoreEvent::EtcdError) => 630,
+            EventType::Store(StoreEvent::GcsError) => 631,
         }
     }
 
@@ -1472,6 +1543,77 @@ impl EventType {
             558 => Some(EventType::Security(SecurityEvent::ScanBan)),
             559 => Some(EventType::Store(StoreEvent::AzureError)),
             560 => Some(EventType::TlsRpt(TlsRptEvent::RecordNotFound)),
+            561 => Some(EventType::Jmap(JmapEvent::ShareAccess)),
+            562 => Some(EventType::Security(SecurityEvent::AuthenticationLockout)),
+            563 => Some(EventType::Auth(AuthEvent::ChallengeRequired)),
+            564 => Some(EventType::Auth(AuthEvent::ChallengeFailed)),
+            565 => Some(EventType::Manage(ManageEvent::BulkOperation)),
+            566 => Some(EventType::Security(SecurityEvent::SubmissionSuspended)),
+            567 => Some(EventType::Spam(SpamEvent::Outbound)),
+            568 => Some(EventType::Spam(SpamEvent::TrainFeedback)),
+            569 => Some(EventType::Smtp(SmtpEvent::SenderUnauthorized)),
+            570 => Some(EventType::Spam(SpamEvent::Quarantine)),
+            571 => Some(EventType::Spam(SpamEvent::QuarantineRelease)),
+            572 => Some(EventType::Spam(SpamEvent::QuarantineDigest)),
+            573 => Some(EventType::MessageIngest(MessageIngestEvent::Forward)),
+            574 => Some(EventType::MessageIngest(MessageIngestEvent::ForwardLoop)),
+            575 => Some(EventType::Spam(SpamEvent::Reputation)),
+            576 => Some(EventType::Spam(SpamEvent::ReputationThrottle)),
+            577 => Some(EventType::Dkim(DkimEvent::KeyRotated)),
+            578 => Some(EventType::Dkim(DkimEvent::KeyRetired)),
+            579 => Some(EventType::Dkim(DkimEvent::DnsRecordPublished)),
+            580 => Some(EventType::Dkim(DkimEvent::DnsRecordPublishFailed)),
+            581 => Some(EventType::Manage(ManageEvent::DnsRecordPublished)),
+            582 => Some(EventType::Manage(ManageEvent::DnsRecordPublishFailed)),
+            583 => Some(EventType::Manage(ManageEvent::DnsRecordPropagated)),
+            584 => Some(EventType::Manage(ManageEvent::DnsRecordPropagationTimeout)),
+            585 => Some(EventType::Smtp(SmtpEvent::RcptVerifyError)),
+            586 => Some(EventType::Tls(TlsEvent::CertificatesReloaded)),
+            587 => Some(EventType::Tls(TlsEvent::CertificateSourceError)),
+            588 => Some(EventType::Network(NetworkEvent::ProxyConnection)),
+            589 => Some(EventType::Network(NetworkEvent::ProxyUntrusted)),
+            590 => Some(EventType::Network(NetworkEvent::HealthCheck)),
+            591 => Some(EventType::Network(NetworkEvent::HealthCheckFailed)),
+            592 => Some(EventType::Store(StoreEvent::BackgroundThrottled)),
+            593 => Some(EventType::Server(ServerEvent::DrainStart)),
+            594 => Some(EventType::Server(ServerEvent::DrainComplete)),
+            595 => Some(EventType::Server(ServerEvent::DrainTimeout)),
+            596 => Some(EventType::FtsIndex(FtsIndexEvent::Degraded)),
+            597 => Some(EventType::FtsIndex(FtsIndexEvent::Recovered)),
+            598 => Some(EventType::ManageSieve(ManageSieveEvent::ListVacation)),
+            599 => Some(EventType::ManageSieve(ManageSieveEvent::ClearVacation)),
+            600 => Some(EventType::Config(ConfigEvent::SecretLeaseRenewed)),
+            601 => Some(EventType::Config(ConfigEvent::SecretLeaseError)),
+            602 => Some(EventType::Resource(ResourceEvent::GeoIpLoaded)),
+            603 => Some(EventType::Store(StoreEvent::PluginError)),
+            604 => Some(EventType::Resource(ResourceEvent::LookupReloaded)),
+            605 => Some(EventType::Manage(ManageEvent::AliasCreated)),
+            606 => Some(EventType::Manage(ManageEvent::AliasDeleted)),
+            607 => Some(EventType::Smtp(SmtpEvent::ListPostDistributed)),
+            608 => Some(EventType::Smtp(SmtpEvent::ListPostHeld)),
+            609 => Some(EventType::Smtp(SmtpEvent::ListPostApproved)),
+            610 => Some(EventType::Smtp(SmtpEvent::ListPostRejected)),
+            611 => Some(EventType::Smtp(SmtpEvent::ListBounce)),
+            612 => Some(EventType::Smtp(SmtpEvent::ListUnsubscribed)),
+            613 => Some(EventType::Smtp(SmtpEvent::MalwareFound)),
+            614 => Some(EventType::Smtp(SmtpEvent::MalwareScanError)),
+            615 => Some(EventType::Smtp(SmtpEvent::AttachmentBlocked)),
+            616 => Some(EventType::Smtp(SmtpEvent::AttachmentStripped)),
+            617 => Some(EventType::Delivery(DeliveryEvent::RouteSelected)),
+            618 => Some(EventType::Delivery(DeliveryEvent::IpPoolSelected)),
+            619 => Some(EventType::Delivery(DeliveryEvent::IpPoolSkipped)),
+            620 => Some(EventType::Delivery(DeliveryEvent::IpPoolExhausted)),
+            621 => Some(EventType::Smtp(SmtpEvent::RcptToSuppressed)),
+            622 => Some(EventType::Smtp(SmtpEvent::RecipientSuppressed)),
+            623 => Some(EventType::Queue(QueueEvent::Held)),
+            624 => Some(EventType::Queue(QueueEvent::Released)),
+            625 => Some(EventType::Queue(QueueEvent::Rerouted)),
+            626 => Some(EventType::Imap(ImapEvent::GetMetadata)),
+            627 => Some(EventType::Imap(ImapEvent::SetMetadata)),
+            628 => Some(EventType::Manage(ManageEvent::SnapshotCreated)),
+            629 => Some(EventType::Manage(ManageEvent::SnapshotRestored)),
+            630 => Some(EventType::Store(StoreEvent::EtcdError)),
+            631 => Some(EventType::Store(StoreEvent::GcsError)),
             _ => None,
         }
     }
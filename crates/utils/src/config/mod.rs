@@ -7,6 +7,7 @@
 pub mod cron;
 pub mod ipmask;
 pub mod parser;
+pub mod secrets;
 pub mod utils;
 
 use std::{collections::BTreeMap, time::Duration};
@@ -20,9 +21,12 @@ pub struct Config {
     pub keys: BTreeMap<String, String>,
     pub warnings: AHashMap<String, ConfigWarning>,
     pub errors: AHashMap<String, ConfigError>,
-    #[cfg(debug_assertions)]
     #[serde(skip)]
     pub keys_read: parking_lot::Mutex<ahash::AHashSet<String>>,
+    #[serde(skip)]
+    pub defaults: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub macros: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -65,12 +69,15 @@ impl Config {
     }
 
     pub async fn resolve_all_macros(&mut self) {
-        self.resolve_macros(&["env", "file", "cfg"]).await;
+        self.resolve_macros(&["env", "file", "vault", "aws-sm", "cfg"])
+            .await;
     }
 
     async fn resolve_macro_type(&mut self, class: &str) {
         let macro_start = format!("%{{{class}:");
         let mut replacements = AHashMap::new();
+        let secrets =
+            matches!(class, "vault" | "aws-sm").then(|| secrets::SecretSettings::parse(self));
         'outer: for (key, value) in &self.keys {
             if value.contains(&macro_start) && value.contains("}%") {
                 let mut result = String::with_capacity(value.len());
@@ -143,6 +150,30 @@ impl Config {
                                         }
                                     }
                                 }
+                                "vault" | "aws-sm" => {
+                                    match secrets::resolve(
+                                        secrets.as_deref().unwrap_or_else(|| unreachable!()),
+                                        class,
+                                        location,
+                                    )
+                                    .await
+                                    {
+                                        Ok(value) => {
+                                            result.push_str(&value);
+                                        }
+                                        Err(err) => {
+                                            self.errors.insert(
+                                                key.clone(),
+                                                ConfigError::Macro {
+                                                    error: format!(
+                                                        "Failed to obtain secret {location:?}: {err}"
+                                                    ),
+                                                },
+                                            );
+                                            continue 'outer;
+                                        }
+                                    }
+                                }
                                 _ => {
                                     unreachable!()
                                 }
@@ -156,6 +187,9 @@ impl Config {
                     }
                 }
 
+                self.macros
+                    .entry(key.clone())
+                    .or_insert_with(|| value.clone());
                 replacements.insert(key.clone(), result);
             }
         }
@@ -231,8 +265,9 @@ impl Clone for Config {
             keys: self.keys.clone(),
             warnings: self.warnings.clone(),
             errors: self.errors.clone(),
-            #[cfg(debug_assertions)]
             keys_read: Default::default(),
+            defaults: Default::default(),
+            macros: self.macros.clone(),
         }
     }
 }
@@ -0,0 +1,556 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    fmt::Write,
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
+};
+
+use ahash::AHashMap;
+use chrono::Utc;
+use parking_lot::Mutex;
+use reqwest::Method;
+use ring::{digest, hmac};
+use serde_json::{json, Map, Value};
+
+use super::Config;
+
+const MAX_SECRET_RESPONSE_SIZE: usize = 1024 * 1024;
+const DEFAULT_VAULT_FIELD: &str = "value";
+const AWS_CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+const AWS_TARGET: &str = "secretsmanager.GetSecretValue";
+
+static SETTINGS: LazyLock<Mutex<Option<Arc<SecretSettings>>>> = LazyLock::new(Default::default);
+static SECRETS: LazyLock<Mutex<AHashMap<String, CachedSecret>>> = LazyLock::new(Default::default);
+
+/// Settings of the `%{vault:..}%` and `%{aws-sm:..}%` macro resolvers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretSettings {
+    pub vault: Option<VaultSettings>,
+    pub aws: Option<AwsSettings>,
+    pub cache_ttl: Duration,
+    pub timeout: Duration,
+    pub tls_allow_invalid_certs: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultSettings {
+    pub url: String,
+    pub token: String,
+    pub namespace: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwsSettings {
+    pub endpoint: String,
+    pub host: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedSecret {
+    value: Value,
+    expires: Instant,
+    lease: Option<Lease>,
+}
+
+#[derive(Debug, Clone)]
+struct Lease {
+    id: String,
+    duration: Duration,
+    renew_at: Instant,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct VaultResponse {
+    #[serde(default)]
+    lease_id: String,
+    #[serde(default)]
+    lease_duration: u64,
+    #[serde(default)]
+    renewable: bool,
+    #[serde(default)]
+    data: Option<Map<String, Value>>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AwsSecret {
+    #[serde(rename = "SecretString")]
+    secret_string: Option<String>,
+}
+
+impl SecretSettings {
+    /// Parses the `secrets.*` settings, falling back to the environment
+    /// variables used by the Vault and AWS command line tools. Configurations
+    /// without secrets settings, such as those built from database keys, reuse
+    /// the last settings loaded.
+    pub fn parse(config: &mut Config) -> Arc<Self> {
+        if !config.has_prefix("secrets") {
+            if let Some(settings) = SETTINGS.lock().clone() {
+                return settings;
+            }
+        }
+
+        let vault = match (
+            value_or_env(config, "secrets.vault.url", &["VAULT_ADDR"]),
+            value_or_env(config, "secrets.vault.token", &["VAULT_TOKEN"]),
+        ) {
+            (Some(url), Some(token)) => Some(VaultSettings {
+                url: url.trim_end_matches('/').to_string(),
+                token,
+                namespace: value_or_env(config, "secrets.vault.namespace", &["VAULT_NAMESPACE"]),
+            }),
+            (Some(_), None) => {
+                config.new_build_error("secrets.vault.token", "Missing Vault token");
+                None
+            }
+            _ => None,
+        };
+
+        let aws = match (
+            value_or_env(
+                config,
+                "secrets.aws.region",
+                &["AWS_REGION", "AWS_DEFAULT_REGION"],
+            ),
+            value_or_env(config, "secrets.aws.access-key", &["AWS_ACCESS_KEY_ID"]),
+            value_or_env(config, "secrets.aws.secret-key", &["AWS_SECRET_ACCESS_KEY"]),
+        ) {
+            (Some(region), Some(access_key), Some(secret_key)) => {
+                let endpoint = config
+                    .value("secrets.aws.endpoint")
+                    .map(|endpoint| endpoint.trim_end_matches('/').to_string())
+                    .unwrap_or_else(|| format!("https://secretsmanager.{region}.amazonaws.com"));
+                let host = endpoint
+                    .split_once("://")
+                    .map_or(endpoint.as_str(), |(_, host)| host)
+                    .split('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+
+                Some(AwsSettings {
+                    endpoint,
+                    host,
+                    region,
+                    access_key,
+                    secret_key,
+                    session_token: value_or_env(
+                        config,
+                        "secrets.aws.session-token",
+                        &["AWS_SESSION_TOKEN"],
+                    ),
+                })
+            }
+            (_, Some(_), None) | (_, None, Some(_)) => {
+                config.new_build_error(
+                    "secrets.aws",
+                    "Both an access key and a secret key are required",
+                );
+                None
+            }
+            _ => None,
+        };
+
+        let settings = Arc::new(SecretSettings {
+            vault,
+            aws,
+            cache_ttl: config
+                .property_or_default("secrets.cache.ttl", "1h")
+                .unwrap_or(Duration::from_secs(3600)),
+            timeout: config
+                .property_or_default("secrets.timeout", "30s")
+                .unwrap_or(Duration::from_secs(30)),
+            tls_allow_invalid_certs: config
+                .property_or_default("secrets.tls.allow-invalid-certs", "false")
+                .unwrap_or_default(),
+        });
+        *SETTINGS.lock() = Some(settings.clone());
+        settings
+    }
+
+    fn client(&self) -> Result<reqwest::Client, String> {
+        reqwest::Client::builder()
+            .timeout(self.timeout)
+            .danger_accept_invalid_certs(self.tls_allow_invalid_certs)
+            .build()
+            .map_err(|err| format!("Failed to create HTTP client: {err}"))
+    }
+}
+
+/// Resolves a `%{vault:<path>#<field>}%` or `%{aws-sm:<name>#<key>}%` macro.
+/// Secrets are fetched once and cached until their lease or the cache TTL
+/// expires, so that reloads do not request new credentials from the backend.
+pub async fn resolve(
+    settings: &SecretSettings,
+    class: &str,
+    location: &str,
+) -> Result<String, String> {
+    let (name, field) = location
+        .rsplit_once('#')
+        .map_or((location, None), |(name, field)| (name, Some(field)));
+    let cache_key = format!("{class}:{name}");
+
+    let cached = SECRETS
+        .lock()
+        .get(&cache_key)
+        .filter(|secret| secret.expires > Instant::now())
+        .map(|secret| secret.value.clone());
+    let value = match cached {
+        Some(value) => value,
+        None => {
+            let secret = match class {
+                "vault" => {
+                    fetch_vault(
+                        settings,
+                        settings
+                            .vault
+                            .as_ref()
+                            .ok_or_else(|| "Vault is not configured".to_string())?,
+                        name,
+                    )
+                    .await?
+                }
+                _ => {
+                    fetch_aws(
+                        settings,
+                        settings
+                            .aws
+                            .as_ref()
+                            .ok_or_else(|| "AWS Secrets Manager is not configured".to_string())?,
+                        name,
+                    )
+                    .await?
+                }
+            };
+            let value = secret.value.clone();
+            SECRETS.lock().insert(cache_key, secret);
+            value
+        }
+    };
+
+    secret_field(value, field).ok_or_else(|| match field {
+        Some(field) => format!("Secret {name:?} does not contain field {field:?}"),
+        None => format!("Secret {name:?} does not contain field {DEFAULT_VAULT_FIELD:?}"),
+    })
+}
+
+/// Renews the Vault leases of cached secrets that are past two thirds of
+/// their lease duration.
+pub async fn renew_leases() {
+    let now = Instant::now();
+    let due = SECRETS
+        .lock()
+        .iter()
+        .filter_map(|(key, secret)| {
+            secret
+                .lease
+                .as_ref()
+                .filter(|lease| lease.renew_at <= now)
+                .map(|lease| (key.clone(), lease.clone()))
+        })
+        .collect::<Vec<_>>();
+    if due.is_empty() {
+        return;
+    }
+    let Some(settings) = SETTINGS.lock().clone() else {
+        return;
+    };
+    let Some(vault) = &settings.vault else {
+        return;
+    };
+
+    for (key, lease) in due {
+        match vault_request(
+            &settings,
+            vault,
+            Method::PUT,
+            "sys/leases/renew",
+            Some(json!({
+                "lease_id": lease.id,
+                "increment": lease.duration.as_secs(),
+            })),
+        )
+        .await
+        {
+            Ok(response) => {
+                let now = Instant::now();
+                let duration = Duration::from_secs(response.lease_duration);
+                if let Some(secret) = SECRETS.lock().get_mut(&key) {
+                    secret.expires = now + duration;
+                    secret.lease = (response.renewable && !duration.is_zero())
+                        .then(|| Lease::new(lease.id.clone(), duration, now));
+                }
+
+                trc::event!(
+                    Config(trc::ConfigEvent::SecretLeaseRenewed),
+                    Id = lease.id,
+                    Details = key,
+                    Expires = duration,
+                );
+            }
+            Err(err) => {
+                trc::event!(
+                    Config(trc::ConfigEvent::SecretLeaseError),
+                    Id = lease.id,
+                    Details = key,
+                    Reason = err,
+                );
+            }
+        }
+    }
+}
+
+async fn fetch_vault(
+    settings: &SecretSettings,
+    vault: &VaultSettings,
+    path: &str,
+) -> Result<CachedSecret, String> {
+    let response =
+        vault_request(settings, vault, Method::GET, path.trim_matches('/'), None).await?;
+    let mut data = response
+        .data
+        .ok_or_else(|| format!("Vault secret {path:?} contains no data"))?;
+
+    // KV version 2 engines return the secret nested next to its metadata
+    if data.contains_key("metadata") {
+        if let Some(Value::Object(inner)) = data.remove("data") {
+            data = inner;
+        }
+    }
+
+    let now = Instant::now();
+    let (expires, lease) = if !response.lease_id.is_empty() && response.lease_duration > 0 {
+        let duration = Duration::from_secs(response.lease_duration);
+        (
+            now + duration,
+            response
+                .renewable
+                .then(|| Lease::new(response.lease_id, duration, now)),
+        )
+    } else {
+        (now + settings.cache_ttl, None)
+    };
+
+    Ok(CachedSecret {
+        value: Value::Object(data),
+        expires,
+        lease,
+    })
+}
+
+async fn vault_request(
+    settings: &SecretSettings,
+    vault: &VaultSettings,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> Result<VaultResponse, String> {
+    let mut request = settings
+        .client()?
+        .request(method, format!("{}/v1/{path}", vault.url))
+        .header("X-Vault-Token", &vault.token);
+    if let Some(namespace) = &vault.namespace {
+        request = request.header("X-Vault-Namespace", namespace);
+    }
+    if let Some(body) = body {
+        request = request
+            .header("content-type", "application/json")
+            .body(body.to_string());
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|err| format!("Vault request failed: {err}"))?;
+    let status = response.status();
+    let response = response_bytes(response)
+        .await
+        .map_err(|err| format!("Failed to read Vault response: {err}"))?;
+
+    if status.is_success() {
+        serde_json::from_slice(&response)
+            .map_err(|err| format!("Failed to parse Vault response: {err}"))
+    } else {
+        Err(format!(
+            "Vault request to {path:?} failed with code {}",
+            status.as_u16()
+        ))
+    }
+}
+
+async fn fetch_aws(
+    settings: &SecretSettings,
+    aws: &AwsSettings,
+    name: &str,
+) -> Result<CachedSecret, String> {
+    let body = json!({ "SecretId": name }).to_string();
+    let now = Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    // Build canonical request
+    let mut canonical_headers = format!(
+        "content-type:{AWS_CONTENT_TYPE}\nhost:{}\nx-amz-date:{amz_date}\n",
+        aws.host
+    );
+    let mut signed_headers = "content-type;host;x-amz-date".to_string();
+    if let Some(token) = &aws.session_token {
+        let _ = writeln!(canonical_headers, "x-amz-security-token:{token}");
+        signed_headers.push_str(";x-amz-security-token");
+    }
+    let _ = writeln!(canonical_headers, "x-amz-target:{AWS_TARGET}");
+    signed_headers.push_str(";x-amz-target");
+    let canonical_request = format!(
+        "POST\n/\n\n{canonical_headers}\n{signed_headers}\n{}",
+        hex_digest(body.as_bytes())
+    );
+
+    // Sign request
+    let scope = format!("{date}/{}/secretsmanager/aws4_request", aws.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex_digest(canonical_request.as_bytes())
+    );
+    let mut key = format!("AWS4{}", aws.secret_key).into_bytes();
+    for part in [
+        date.as_str(),
+        aws.region.as_str(),
+        "secretsmanager",
+        "aws4_request",
+    ] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex_encode(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    let mut request = settings
+        .client()?
+        .post(format!("{}/", aws.endpoint))
+        .header("content-type", AWS_CONTENT_TYPE)
+        .header("x-amz-date", &amz_date)
+        .header("x-amz-target", AWS_TARGET)
+        .header(
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                aws.access_key
+            ),
+        );
+    if let Some(token) = &aws.session_token {
+        request = request.header("x-amz-security-token", token);
+    }
+
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .map_err(|err| format!("AWS Secrets Manager request failed: {err}"))?;
+    let status = response.status();
+    let response = response_bytes(response)
+        .await
+        .map_err(|err| format!("Failed to read AWS Secrets Manager response: {err}"))?;
+
+    if status.is_success() {
+        serde_json::from_slice::<AwsSecret>(&response)
+            .map_err(|err| format!("Failed to parse AWS Secrets Manager response: {err}"))?
+            .secret_string
+            .map(|secret| CachedSecret {
+                value: Value::String(secret),
+                expires: Instant::now() + settings.cache_ttl,
+                lease: None,
+            })
+            .ok_or_else(|| format!("Secret {name:?} has no string value"))
+    } else {
+        Err(format!(
+            "AWS Secrets Manager request for {name:?} failed with status {status}: {}",
+            serde_json::from_slice::<Value>(&response)
+                .ok()
+                .as_ref()
+                .and_then(|error| error.get("message").or_else(|| error.get("Message")))
+                .and_then(|message| message.as_str())
+                .unwrap_or_default()
+        ))
+    }
+}
+
+/// Obtains a field from a secret. Vault secrets are objects with a default
+/// `value` field, while AWS secrets are strings that may contain a JSON
+/// object when a field is requested.
+fn secret_field(value: Value, field: Option<&str>) -> Option<String> {
+    let value = match (value, field) {
+        (Value::Object(mut fields), field) => {
+            fields.remove(field.unwrap_or(DEFAULT_VAULT_FIELD))?
+        }
+        (Value::String(secret), Some(field)) => serde_json::from_str::<Value>(&secret)
+            .ok()?
+            .get_mut(field)?
+            .take(),
+        (value, None) => value,
+        _ => return None,
+    };
+
+    match value {
+        Value::String(value) => Some(value),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+fn value_or_env(config: &Config, key: &str, vars: &[&str]) -> Option<String> {
+    config
+        .value(key)
+        .map(|value| value.to_string())
+        .or_else(|| vars.iter().find_map(|var| std::env::var(var).ok()))
+        .filter(|value| !value.is_empty())
+}
+
+async fn response_bytes(response: reqwest::Response) -> Result<Vec<u8>, String> {
+    if response
+        .content_length()
+        .map_or(false, |len| len as usize > MAX_SECRET_RESPONSE_SIZE)
+    {
+        return Err("Response too large".to_string());
+    }
+
+    let bytes = response.bytes().await.map_err(|err| err.to_string())?;
+    if bytes.len() <= MAX_SECRET_RESPONSE_SIZE {
+        Ok(bytes.to_vec())
+    } else {
+        Err("Response too large".to_string())
+    }
+}
+
+impl Lease {
+    fn new(id: String, duration: Duration, now: Instant) -> Self {
+        Lease {
+            id,
+            duration,
+            renew_at: now + duration * 2 / 3,
+        }
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+fn hex_digest(data: &[u8]) -> String {
+    hex_encode(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+fn hex_encode(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(data.len() * 2);
+    for byte in data {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
@@ -24,7 +24,6 @@ impl Config {
     pub fn property<T: ParseValue>(&mut self, key: impl AsKey) -> Option<T> {
         let key = key.as_key();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(key.clone());
 
         if let Some(value) = self.keys.get(&key) {
@@ -47,12 +46,14 @@ impl Config {
     ) -> Option<T> {
         let key = key.as_key();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(key.clone());
 
         let value = match self.keys.get(&key) {
             Some(value) => value.as_str(),
-            None => default,
+            None => {
+                self.defaults.insert(key.clone(), default.to_string());
+                default
+            }
         };
         match T::parse_value(value) {
             Ok(value) => Some(value),
@@ -72,7 +73,10 @@ impl Config {
         let key = key.as_key();
         let value = match self.value_or_else(key.as_str(), or_else.clone()) {
             Some(value) => value,
-            None => default,
+            None => {
+                self.defaults.insert(key.clone(), default.to_string());
+                default
+            }
         };
 
         match T::parse_value(value) {
@@ -87,7 +91,6 @@ impl Config {
     pub fn property_require<T: ParseValue>(&mut self, key: impl AsKey) -> Option<T> {
         let key = key.as_key();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(key.clone());
 
         if let Some(value) = self.keys.get(&key) {
@@ -143,7 +146,6 @@ impl Config {
     ) -> impl Iterator<Item = &'x str> + 'x {
         let prefix = prefix.as_prefix();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(prefix.clone());
 
         self.keys
@@ -156,7 +158,6 @@ impl Config {
         let prefix = prefix.as_prefix();
         let mut results = Vec::new();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(prefix.clone());
 
         for (key, value) in &self.keys {
@@ -179,7 +180,6 @@ impl Config {
     pub fn value(&self, key: impl AsKey) -> Option<&str> {
         let key = key.as_key();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(key.clone());
 
         self.keys.get(&key).map(|s| s.as_str())
@@ -192,7 +192,6 @@ impl Config {
     pub fn value_require(&mut self, key: impl AsKey) -> Option<&str> {
         let key = key.as_key();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(key.clone());
 
         if let Some(value) = self.keys.get(&key) {
@@ -222,11 +221,8 @@ impl Config {
     pub fn value_or_else(&self, key: impl AsKey, or_else: impl AsKey) -> Option<&str> {
         let key = key.as_key();
 
-        #[cfg(debug_assertions)]
-        {
-            self.keys_read.lock().insert(key.clone());
-            self.keys_read.lock().insert(or_else.clone().as_key());
-        }
+        self.keys_read.lock().insert(key.clone());
+        self.keys_read.lock().insert(or_else.clone().as_key());
 
         self.keys
             .get(&key)
@@ -238,7 +234,6 @@ impl Config {
         let full_prefix = prefix.as_key();
         let prefix = prefix.as_prefix();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(prefix.clone());
 
         self.keys.iter().filter_map(move |(key, value)| {
@@ -253,7 +248,6 @@ impl Config {
     pub fn iterate_prefix(&self, prefix: impl AsKey) -> impl Iterator<Item = (&str, &str)> {
         let prefix = prefix.as_prefix();
 
-        #[cfg(debug_assertions)]
         self.keys_read.lock().insert(prefix.clone());
 
         self.keys
@@ -268,11 +262,8 @@ impl Config {
     ) -> impl Iterator<Item = (&str, &str)> {
         let mut prefix = prefix.as_prefix();
 
-        #[cfg(debug_assertions)]
-        {
-            self.keys_read.lock().insert(prefix.clone());
-            self.keys_read.lock().insert(or_else.clone().as_prefix());
-        }
+        self.keys_read.lock().insert(prefix.clone());
+        self.keys_read.lock().insert(or_else.clone().as_prefix());
 
         self.values(if self.keys.keys().any(|k| k.starts_with(&prefix)) {
             prefix.truncate(prefix.len() - 1);
@@ -327,7 +318,6 @@ impl Config {
         self.warnings.insert(key.as_key(), ConfigWarning::Missing);
     }
 
-    #[cfg(debug_assertions)]
     pub fn warn_unread_keys(&mut self) {
         let mut keys = self.keys.clone();
 
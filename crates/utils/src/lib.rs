@@ -12,6 +12,7 @@ pub mod glob;
 pub mod lru_cache;
 pub mod map;
 pub mod snowflake;
+pub mod suffixlist;
 pub mod url_params;
 
 use rustls::{
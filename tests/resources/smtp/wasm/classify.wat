@@ -0,0 +1,14 @@
+;; Accepts the message adding a header
+(module
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+  (data (i32.const 0) "{\"action\":\"accept\",\"modifications\":[{\"type\":\"addHeader\",\"name\":\"X-Policy\",\"value\":\"classified\"}]}")
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+    (if (i32.gt_u (global.get $heap) (i32.mul (memory.size) (i32.const 65536)))
+      (then (drop (memory.grow (i32.add (i32.div_u (local.get $len) (i32.const 65536)) (i32.const 1))))))
+    (local.get $ptr))
+  (func (export "on_hook") (param $ptr i32) (param $len i32) (result i64)
+    (i64.const 97)))
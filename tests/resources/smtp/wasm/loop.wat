@@ -0,0 +1,9 @@
+;; Never returns, exhausting its fuel
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param $len i32) (result i32)
+    (i32.const 0))
+  (func (export "on_hook") (param $ptr i32) (param $len i32) (result i64)
+    (loop $forever
+      (br $forever))
+    (i64.const 0)))
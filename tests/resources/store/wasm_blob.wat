@@ -0,0 +1,107 @@
+;; Blob store plugin that keeps each blob in a file named after its key
+;; inside the preopened data directory.
+(module
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read"
+    (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_close"
+    (func $fd_close (param i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_filestat_get"
+    (func $fd_filestat_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_unlink_file"
+    (func $path_unlink_file (param i32 i32 i32) (result i32)))
+
+  (memory (export "memory") 1)
+
+  ;; 0: opened fd, 4: bytes transferred, 8: iovec, 64: filestat
+  (data (i32.const 16) "\00\01")
+  (data (i32.const 32) "\02Plugin I/O error")
+
+  (global $heap (mut i32) (i32.const 1024))
+
+  (func $alloc (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+    (if (i32.gt_u (global.get $heap) (i32.mul (memory.size) (i32.const 65536)))
+      (then
+        (if (i32.eq
+              (memory.grow (i32.add (i32.div_u (local.get $len) (i32.const 65536)) (i32.const 1)))
+              (i32.const -1))
+          (then unreachable))))
+    (local.get $ptr))
+
+  (func $reply (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))
+
+  (func $error (result i64)
+    (call $reply (i32.const 32) (i32.const 17)))
+
+  (func (export "blob_get") (param $key i32) (param $key_len i32) (result i64)
+    (local $errno i32) (local $fd i32) (local $ptr i32) (local $pos i32) (local $remaining i32)
+    (local.set $errno
+      (call $path_open (i32.const 3) (i32.const 0) (local.get $key) (local.get $key_len)
+        (i32.const 0) (i64.const 0x1FFFFFFF) (i64.const 0x1FFFFFFF) (i32.const 0) (i32.const 0)))
+    (if (i32.eq (local.get $errno) (i32.const 44))
+      (then (return (call $reply (i32.const 17) (i32.const 1)))))
+    (if (local.get $errno) (then (return (call $error))))
+    (local.set $fd (i32.load (i32.const 0)))
+    (if (call $fd_filestat_get (local.get $fd) (i32.const 64))
+      (then
+        (drop (call $fd_close (local.get $fd)))
+        (return (call $error))))
+    (local.set $remaining (i32.wrap_i64 (i64.load (i32.const 96))))
+    (local.set $ptr (call $alloc (i32.add (local.get $remaining) (i32.const 1))))
+    (i32.store8 (local.get $ptr) (i32.const 0))
+    (local.set $pos (i32.add (local.get $ptr) (i32.const 1)))
+    (block $done
+      (loop $read
+        (br_if $done (i32.eqz (local.get $remaining)))
+        (i32.store (i32.const 8) (local.get $pos))
+        (i32.store (i32.const 12) (local.get $remaining))
+        (if (call $fd_read (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 4))
+          (then
+            (drop (call $fd_close (local.get $fd)))
+            (return (call $error))))
+        (br_if $done (i32.eqz (i32.load (i32.const 4))))
+        (local.set $pos (i32.add (local.get $pos) (i32.load (i32.const 4))))
+        (local.set $remaining (i32.sub (local.get $remaining) (i32.load (i32.const 4))))
+        (br $read)))
+    (drop (call $fd_close (local.get $fd)))
+    (call $reply (local.get $ptr) (i32.sub (local.get $pos) (local.get $ptr))))
+
+  (func (export "blob_put") (param $key i32) (param $key_len i32) (param $data i32) (param $data_len i32) (result i64)
+    (local $fd i32)
+    ;; O_CREAT | O_TRUNC
+    (if (call $path_open (i32.const 3) (i32.const 0) (local.get $key) (local.get $key_len)
+          (i32.const 9) (i64.const 0x1FFFFFFF) (i64.const 0x1FFFFFFF) (i32.const 0) (i32.const 0))
+      (then (return (call $error))))
+    (local.set $fd (i32.load (i32.const 0)))
+    (block $done
+      (loop $write
+        (br_if $done (i32.eqz (local.get $data_len)))
+        (i32.store (i32.const 8) (local.get $data))
+        (i32.store (i32.const 12) (local.get $data_len))
+        (if (call $fd_write (local.get $fd) (i32.const 8) (i32.const 1) (i32.const 4))
+          (then
+            (drop (call $fd_close (local.get $fd)))
+            (return (call $error))))
+        (local.set $data (i32.add (local.get $data) (i32.load (i32.const 4))))
+        (local.set $data_len (i32.sub (local.get $data_len) (i32.load (i32.const 4))))
+        (br $write)))
+    (drop (call $fd_close (local.get $fd)))
+    (call $reply (i32.const 16) (i32.const 1)))
+
+  (func (export "blob_delete") (param $key i32) (param $key_len i32) (result i64)
+    (local $errno i32)
+    (local.set $errno (call $path_unlink_file (i32.const 3) (local.get $key) (local.get $key_len)))
+    (if (i32.eq (local.get $errno) (i32.const 44))
+      (then (return (call $reply (i32.const 17) (i32.const 1)))))
+    (if (local.get $errno) (then (return (call $error))))
+    (call $reply (i32.const 16) (i32.const 1)))
+)
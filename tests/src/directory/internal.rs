@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::time::Duration;
+
 use ahash::AHashSet;
 use directory::{
     backend::{
@@ -14,7 +16,11 @@ use directory::{
         },
         RcptType,
     },
-    Principal, QueryBy, Type,
+    core::{
+        filter::AddressFilter,
+        secret::{PasswordHashing, PasswordScheme},
+    },
+    Directory, DirectoryInner, Principal, QueryBy, Type,
 };
 use jmap_proto::types::collection::Collection;
 use mail_send::Credentials;
@@ -622,6 +628,31 @@ async fn internal_directory() {
             vec!["list"]
         );
 
+        // Paginate accounts using cursors
+        let mut cursor = None;
+        let mut names = Vec::new();
+        loop {
+            let list = store
+                .list_principals_after(
+                    None,
+                    None,
+                    &[Type::Individual, Type::Group, Type::List],
+                    &[],
+                    cursor.as_deref(),
+                    0,
+                    2,
+                )
+                .await
+                .unwrap();
+            assert!(list.items.len() <= 2);
+            names.extend(list.items.into_iter().map(|p| p.name().to_string()));
+            if list.cursor.is_none() {
+                break;
+            }
+            cursor = list.cursor;
+        }
+        assert_eq!(names, vec!["jane", "john.doe", "list", "sales", "support"]);
+
         // Write records on John's and Jane's accounts
         let mut document_id = u32::MAX;
         for account_id in [john_id, jane_id] {
@@ -743,6 +774,135 @@ async fn internal_directory() {
                 .unwrap(),
             Some("hello".to_string())
         );
+
+        // Legacy password hashes should be upgraded on login
+        let legacy_hashing = PasswordHashing {
+            scheme: PasswordScheme::Sha512Crypt,
+            ..Default::default()
+        };
+        let legacy_secret = legacy_hashing.hash("legacy_secret").unwrap();
+        assert!(legacy_secret.starts_with("$6$"));
+        assert!(!legacy_hashing.needs_rehash(&legacy_secret));
+        assert!(PasswordHashing::default().needs_rehash(&legacy_secret));
+        for legacy_secret in [
+            "{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=",
+            "{SSHA}9Hw4gEnbJ0BCgLORU+eLKqF4VXJ5cHNhbHQ=",
+            "$5$rounds=5000$salt$hash",
+            "$pbkdf2-sha256$i=1000$salt$hash",
+            "$argon2i$v=19$m=4096,t=3,p=1$salt$hash",
+            "plaintext",
+        ] {
+            assert!(
+                PasswordHashing::default().needs_rehash(legacy_secret),
+                "{legacy_secret}"
+            );
+        }
+        let legacy_id = store
+            .create_principal(
+                TestPrincipal {
+                    name: "legacy".to_string(),
+                    secrets: vec![legacy_secret.clone()],
+                    ..Default::default()
+                }
+                .into(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let directory = Directory {
+            store: DirectoryInner::Internal(store.clone()),
+            cache: None,
+            filter: None,
+            password: PasswordHashing::default(),
+        };
+        for _ in 0..2 {
+            assert_eq!(
+                directory
+                    .query(
+                        QueryBy::Credentials(&Credentials::new(
+                            "legacy".to_string(),
+                            "legacy_secret".to_string()
+                        )),
+                        false
+                    )
+                    .await
+                    .unwrap()
+                    .map(|p| p.id()),
+                Some(legacy_id)
+            );
+            let secrets = store
+                .query(QueryBy::Id(legacy_id), false)
+                .await
+                .unwrap()
+                .unwrap()
+                .into_test()
+                .secrets;
+            assert_eq!(secrets.len(), 1);
+            assert!(secrets[0].starts_with("$argon2id$"), "{secrets:?}");
+            assert!(!PasswordHashing::default().needs_rehash(&secrets[0]));
+        }
+
+        // Unknown recipients should be rejected by the address filter once built,
+        // a low false positive rate keeps the assertions below deterministic
+        let directory = Directory {
+            store: DirectoryInner::Internal(store.clone()),
+            cache: None,
+            filter: AddressFilter::new(0.000001, Duration::from_secs(3600)).into(),
+            password: PasswordHashing::default(),
+        };
+        let filter = directory.filter.as_ref().unwrap();
+        assert!(!filter.is_active());
+        assert!(!filter.is_unknown("john.doe@example.org"));
+        assert!(directory
+            .refresh_filter(vec!["jane-x1y2z3@example.org".to_string()])
+            .await
+            .unwrap());
+        assert!(filter.is_active());
+        assert!(filter.is_unknown("john.doe@example.org"));
+        assert!(!filter.is_unknown("jane@example.org"));
+        assert!(!filter.is_unknown("jane-x1y2z3@example.org"));
+        assert_eq!(
+            directory.rcpt("jane@example.org").await.unwrap(),
+            RcptType::Mailbox
+        );
+        assert_eq!(
+            directory.rcpt("john.doe@example.org").await.unwrap(),
+            RcptType::Invalid
+        );
+        assert_eq!(
+            directory.email_to_id("jane@example.org").await.unwrap(),
+            Some(jane_id)
+        );
+        assert_eq!(
+            directory.email_to_id("john.doe@example.org").await.unwrap(),
+            None
+        );
+
+        // Adding addresses to the directory should disable the filter until rebuilt
+        store
+            .update_principal(UpdatePrincipal::by_id(jane_id).with_updates(vec![
+                PrincipalUpdate::add_item(
+                    PrincipalField::Emails,
+                    PrincipalValue::String("jane.doe@example.org".to_string()),
+                ),
+            ]))
+            .await
+            .unwrap();
+        assert!(!filter.is_active());
+        assert!(!filter.is_unknown("jane.doe@example.org"));
+        assert_eq!(
+            directory.rcpt("jane.doe@example.org").await.unwrap(),
+            RcptType::Mailbox
+        );
+        assert!(directory.refresh_filter(vec![]).await.unwrap());
+        assert!(!filter.is_unknown("jane.doe@example.org"));
+        assert!(filter.is_unknown("john.doe@example.org"));
+
+        // Invalidated filters are not used either
+        directory.invalidate_filter();
+        assert!(!filter.is_active());
+        assert!(!filter.is_unknown("john.doe@example.org"));
     }
 }
 
@@ -53,6 +53,7 @@ path = "{TMP}/rocksdb"
 
 [store."foundationdb"]
 type = "foundationdb"
+key-salt.buckets = 4
 
 [store."sqlite"]
 type = "sqlite"
@@ -246,6 +246,26 @@ pub async fn test(mut imap_john: &mut ImapConnection, _imap_check: &mut ImapConn
         .await
         .assert_contains("copy test");
 
+    // Bill marks the message as seen, which should not affect Jane
+    imap_bill
+        .send(&format!("UID STORE {} +FLAGS (\\Seen)", uid))
+        .await;
+    imap_bill
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\\Seen");
+    imap_bill.send("UID SEARCH SEEN").await;
+    imap_bill
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains(&format!("* SEARCH {}", uid));
+
+    imap_jane.send(&format!("UID FETCH {} (FLAGS)", uid)).await;
+    imap_jane
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("\\Seen", 0);
+
     // Bill now moves the message to his own Inbox
     imap_bill.send(&format!("UID MOVE {} INBOX", uid)).await;
     let uid_moved = imap_bill
@@ -282,6 +302,15 @@ pub async fn test(mut imap_john: &mut ImapConnection, _imap_check: &mut ImapConn
         .await
         .assert_contains("copy test");
 
+    // The \Seen state of Bill is kept after moving the message
+    imap_bill
+        .send(&format!("UID FETCH {} (FLAGS)", uid_moved))
+        .await;
+    imap_bill
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\\Seen");
+
     // Jane stops sharing with Bill, and removes Insert access to John
     imap_jane.send("DELETEACL INBOX foobar@example.com").await;
     imap_jane.assert_read(Type::Tagged, ResponseType::Ok).await;
@@ -4,11 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use ahash::AHashSet;
 use imap_proto::ResponseType;
 
-use super::{AssertResult, ImapConnection, Type};
+use super::{expand_uid_list, AssertResult, ImapConnection, Type};
 
-pub async fn test(_imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
     println!("Running COPY/MOVE tests...");
 
     // Check status
@@ -152,4 +153,75 @@ pub async fn test(_imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
         .assert_contains("\"Burrata al Tartufo\" (UIDNEXT 5 MESSAGES 0 UNSEEN 0 SIZE 0)")
         .assert_contains("\"Scamorza Affumicata\" (UIDNEXT 9 MESSAGES 4 UNSEEN 4 SIZE 5851)")
         .assert_contains("\"INBOX\" (UIDNEXT 11 MESSAGES 10 UNSEEN 10 SIZE 12193)");
+
+    // Copy and append concurrently from two sessions sharing the same store,
+    // as two cluster nodes would
+    imap_check.send("CREATE \"Mozzarella di Bufala\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for imap in [&mut *imap_check, &mut *imap] {
+        imap.send("SELECT INBOX").await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+    let mut reported = Vec::new();
+    for num in 0..5 {
+        let message = format!("From: bill@example.com\r\nSubject: Race #{num}\r\n\r\nTest.\r\n");
+        let (copied, (appended, copied_node)) = tokio::join!(
+            async {
+                imap_check
+                    .send(&format!("COPY {} \"Mozzarella di Bufala\"", (num * 2) + 1))
+                    .await;
+                imap_check
+                    .assert_read(Type::Tagged, ResponseType::Ok)
+                    .await
+                    .into_copy_uid()
+            },
+            async {
+                imap.send(&format!(
+                    "APPEND \"Mozzarella di Bufala\" {{{}+}}\r\n{}",
+                    message.len(),
+                    message
+                ))
+                .await;
+                let appended = imap
+                    .assert_read(Type::Tagged, ResponseType::Ok)
+                    .await
+                    .into_append_uid();
+                imap.send(&format!("COPY {} \"Mozzarella di Bufala\"", (num * 2) + 2))
+                    .await;
+                let copied = imap
+                    .assert_read(Type::Tagged, ResponseType::Ok)
+                    .await
+                    .into_copy_uid();
+                (appended, copied)
+            }
+        );
+        for uids in [copied, appended, copied_node] {
+            reported.extend(expand_uid_list(&uids));
+        }
+    }
+
+    // Reported UIDs must be unique and match the committed messages
+    let expected = reported.iter().copied().collect::<AHashSet<_>>();
+    assert_eq!(expected.len(), 15, "{reported:?}");
+    imap_check.send("SELECT \"Mozzarella di Bufala\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("UID SEARCH ALL").await;
+    let stored = imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .find_map(|line| {
+            line.strip_prefix("* SEARCH").map(|uids| {
+                uids.split_ascii_whitespace()
+                    .map(|uid| uid.parse::<u32>().unwrap())
+                    .collect::<AHashSet<_>>()
+            })
+        })
+        .unwrap();
+    assert_eq!(stored, expected);
+
+    imap_check.send("SELECT \"Burrata al Tartufo\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("DELETE \"Mozzarella di Bufala\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
 }
@@ -20,7 +20,7 @@ pub async fn test(imap: &mut ImapConnection, _imap_check: &mut ImapConnection) {
 
     // Fetch all properties available from JMAP
     imap.send(concat!(
-        "FETCH 10 (FLAGS INTERNALDATE PREVIEW EMAILID THREADID ",
+        "FETCH 10 (FLAGS INTERNALDATE SAVEDATE PREVIEW EMAILID THREADID ",
         "RFC822.SIZE UID ENVELOPE BODYSTRUCTURE)"
     ))
     .await;
@@ -30,6 +30,7 @@ pub async fn test(imap: &mut ImapConnection, _imap_check: &mut ImapConnection) {
         .assert_contains("RFC822.SIZE 1457")
         .assert_contains("UID 10")
         .assert_contains("INTERNALDATE")
+        .assert_contains("SAVEDATE \"")
         .assert_contains("THREADID (")
         .assert_contains("EMAILID (")
         .assert_contains("but then I thought, why not do both?")
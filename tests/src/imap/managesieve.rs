@@ -32,6 +32,11 @@ pub async fn test() {
         .send("AUTHENTICATE \"PLAIN\" \"AGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0\"")
         .await;
     sieve.assert_read(ResponseType::Ok).await;
+    sieve.send("CAPABILITY").await;
+    sieve
+        .assert_read(ResponseType::Ok)
+        .await
+        .assert_contains("VACATION-TRACKING");
     /*sieve
     .assert_read(ResponseType::Ok)
     .await
@@ -138,6 +143,17 @@ pub async fn test() {
         .await
         .assert_count("minimalist script", 0)
         .assert_count("holidays", 0);
+
+    // Vacation reply tracking
+    sieve.send("LISTVACATION").await;
+    sieve
+        .assert_read(ResponseType::Ok)
+        .await
+        .assert_count("@", 0);
+    sieve.send("CLEARVACATION \"bill@remote.org\"").await;
+    sieve.assert_read(ResponseType::Ok).await;
+    sieve.send("CLEARVACATION").await;
+    sieve.assert_read(ResponseType::Ok).await;
 }
 
 pub struct SieveConnection {
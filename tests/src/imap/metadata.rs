@@ -0,0 +1,110 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running METADATA tests...");
+
+    // Metadata capabilities should be advertised
+    imap.send("CAPABILITY").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("METADATA")
+        .assert_contains("METADATA-SERVER");
+
+    // Set mailbox annotations
+    imap.send(concat!(
+        "SETMETADATA INBOX (/private/comment \"My comment\" ",
+        "/shared/comment \"Shared comment\" /private/vendor/a \"A\" ",
+        "/private/vendor/a/b \"B\")"
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Annotations should be visible from other sessions
+    imap.send("GETMETADATA INBOX (/private/comment /shared/comment)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"/private/comment\" \"My comment\"")
+        .assert_contains("\"/shared/comment\" \"Shared comment\"");
+    imap_check
+        .send("GETMETADATA INBOX (/private/comment /shared/comment)")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"/private/comment\" \"My comment\"")
+        .assert_contains("\"/shared/comment\" \"Shared comment\"");
+
+    // Depth and maximum size
+    imap.send("GETMETADATA (DEPTH 1) INBOX /private/vendor")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"/private/vendor/a\" \"A\"")
+        .assert_count("/private/vendor/a/b", 0);
+    imap.send("GETMETADATA (DEPTH infinity) INBOX /private/vendor")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"/private/vendor/a/b\" \"B\"");
+    imap.send("GETMETADATA (MAXSIZE 5) INBOX /private/comment")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("[METADATA LONGENTRIES 10]")
+        .assert_count("My comment", 0);
+
+    // Remove annotations
+    imap.send("SETMETADATA INBOX (/private/comment NIL /private/vendor/a NIL)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("GETMETADATA INBOX (/private/comment /private/vendor/a)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"/private/comment\" NIL")
+        .assert_contains("\"/private/vendor/a\" NIL");
+
+    // Invalid entries and unknown mailboxes
+    imap.send("SETMETADATA INBOX (/comment \"Invalid\")").await;
+    imap.assert_read(Type::Tagged, ResponseType::Bad).await;
+    imap.send("GETMETADATA Unknown /private/comment").await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[NONEXISTENT]");
+
+    // Server annotations
+    imap.send("SETMETADATA \"\" (/private/vendor/theme \"dark\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("GETMETADATA \"\" /private/vendor/theme").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* METADATA \"\" (\"/private/vendor/theme\" \"dark\")");
+    imap.send("GETMETADATA \"\" /shared/admin").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"/shared/admin\" NIL");
+
+    // Shared server annotations can only be set by administrators
+    imap.send("SETMETADATA \"\" (/shared/admin \"mailto:admin@example.com\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+
+    // Cleanup
+    for command in [
+        "SETMETADATA INBOX (/shared/comment NIL /private/vendor/a/b NIL)",
+        "SETMETADATA \"\" (/private/vendor/theme NIL)",
+    ] {
+        imap.send(command).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+}
@@ -14,7 +14,9 @@ pub mod fetch;
 pub mod idle;
 pub mod mailbox;
 pub mod managesieve;
+pub mod metadata;
 pub mod pop;
+pub mod public;
 pub mod search;
 pub mod store;
 pub mod thread;
@@ -32,6 +34,7 @@ use common::{
         telemetry::Telemetry,
     },
     core::BuildServer,
+    listener::health::HealthSessionManager,
     manager::boot::build_ipc,
     Core, Data, Inner, Server,
 };
@@ -146,6 +149,7 @@ path = "{TMP}/rocks.db"
 
 [store."foundationdb"]
 type = "foundationdb"
+key-salt.buckets = 4
 
 [store."postgresql"]
 type = "postgresql"
@@ -185,6 +189,9 @@ private-key = "%{file:{PK}}%"
 [imap.protocol]
 uidplus = true
 
+[imap.shared]
+private-seen = true
+
 [storage]
 data = "{STORE}"
 fts = "{STORE}"
@@ -242,6 +249,9 @@ subscribe = false
 name = "Drafts"
 subscribe = false
 
+[jmap.public-folders]
+account = "public@example.com"
+
 [store."auth"]
 type = "sqlite"
 path = "{TMP}/auth.db"
@@ -366,6 +376,12 @@ async fn init_imap_tests(store_id: &str, delete_if_exists: bool) -> IMAPTest {
                 acceptor,
                 shutdown_rx,
             ),
+            ServerProtocol::Health => server.spawn(
+                HealthSessionManager::new(inner.clone()),
+                inner.clone(),
+                acceptor,
+                shutdown_rx,
+            ),
         };
     });
 
@@ -419,6 +435,9 @@ async fn init_imap_tests(store_id: &str, delete_if_exists: bool) -> IMAPTest {
     store
         .add_to_group("jane.smith@example.com", "support@example.com")
         .await;
+    store
+        .create_test_group("public@example.com", "Public Folders", &[])
+        .await;
 
     IMAPTest {
         server: inner.build_server(),
@@ -472,6 +491,8 @@ pub async fn imap_tests() {
     idle::test(&mut imap, &mut imap_check).await;
     condstore::test(&mut imap, &mut imap_check).await;
     acl::test(&mut imap, &mut imap_check).await;
+    metadata::test(&mut imap, &mut imap_check).await;
+    public::test(&mut imap, &mut imap_check, &handle).await;
 
     // Logout
     for imap in [&mut imap, &mut imap_check] {
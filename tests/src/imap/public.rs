@@ -0,0 +1,158 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::QueryBy;
+use imap_proto::ResponseType;
+use jmap::mailbox::public::PublicFolderMethods;
+use jmap_proto::types::acl::Acl;
+use utils::map::bitmap::Bitmap;
+
+use super::{AssertResult, IMAPTest, ImapConnection, Type};
+
+pub async fn test(
+    imap_john: &mut ImapConnection,
+    _imap_check: &mut ImapConnection,
+    handle: &IMAPTest,
+) {
+    println!("Running public folder tests...");
+
+    // Create a public folder and grant John access to it
+    let server = &handle.server;
+    let account_id = server
+        .public_account_id()
+        .await
+        .unwrap()
+        .expect("Missing public folders account");
+    let john_id = server
+        .core
+        .storage
+        .directory
+        .query(QueryBy::Name("jdoe@example.com"), false)
+        .await
+        .unwrap()
+        .unwrap()
+        .id();
+    let mailbox_id = server
+        .public_folder_create(account_id, "Announcements")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        server
+            .public_folder_create(account_id, "Announcements")
+            .await
+            .unwrap(),
+        None
+    );
+    server
+        .public_folder_set_rights(
+            account_id,
+            mailbox_id,
+            john_id,
+            Bitmap::from_iter([Acl::Read, Acl::ReadItems, Acl::CreateChild, Acl::Administer]),
+        )
+        .await
+        .unwrap();
+
+    // John should see the folder under the public namespace
+    imap_john.send("LIST \"\" \"*\"").await;
+    imap_john
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* LIST (\\NoSelect) \"/\" \"#public\"")
+        .assert_equals("* LIST () \"/\" \"#public/Announcements\"")
+        .assert_count("Shared Folders", 3);
+    imap_john.send("NAMESPACE").await;
+    imap_john
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals(concat!(
+            "* NAMESPACE ((\"\" \"/\")) ((\"Shared Folders\" \"/\")) ",
+            "((\"#public\" \"/\"))"
+        ));
+    imap_john.send("STATUS \"#public\" (MESSAGES)").await;
+    imap_john.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Folders can't be created at the root of the public namespace
+    imap_john.send("CREATE \"#public/Other\"").await;
+    imap_john.assert_read(Type::Tagged, ResponseType::No).await;
+
+    // Sub folders inherit the ACL of their parent
+    imap_john
+        .send("CREATE \"#public/Announcements/Archive\"")
+        .await;
+    imap_john.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_john
+        .send("GETACL \"#public/Announcements/Archive\"")
+        .await;
+    imap_john
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"jdoe@example.com\"");
+
+    // Rights granted on a folder apply to all its descendants
+    let mut imap_bill = ImapConnection::connect(b"_p ").await;
+    imap_bill
+        .assert_read(Type::Untagged, ResponseType::Ok)
+        .await;
+    imap_bill
+        .send("AUTHENTICATE PLAIN {36+}\r\nAGZvb2JhckBleGFtcGxlLmNvbQBzZWNyZXQ=")
+        .await;
+    imap_bill.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_bill.send("LIST \"\" \"*\"").await;
+    imap_bill
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("#public", 0);
+
+    imap_john
+        .send("SETACL \"#public/Announcements\" foobar@example.com lr")
+        .await;
+    imap_john.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_bill.send("LIST \"\" \"*\"").await;
+    imap_bill
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"#public/Announcements\"")
+        .assert_contains("\"#public/Announcements/Archive\"");
+    imap_bill
+        .send("MYRIGHTS \"#public/Announcements/Archive\"")
+        .await;
+    imap_bill
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("rl");
+
+    // Bill is not allowed to create sub folders
+    imap_bill
+        .send("CREATE \"#public/Announcements/Drafts\"")
+        .await;
+    imap_bill.assert_read(Type::Tagged, ResponseType::No).await;
+
+    // Revoking rights also applies to all descendants
+    imap_john
+        .send("DELETEACL \"#public/Announcements\" foobar@example.com")
+        .await;
+    imap_john.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_bill.send("LIST \"\" \"*\"").await;
+    imap_bill
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("#public", 0);
+
+    // Folders are also listed through the server
+    let folders = server.public_folder_list(account_id).await.unwrap();
+    assert_eq!(
+        folders
+            .iter()
+            .map(|folder| folder.name.as_str())
+            .collect::<Vec<_>>(),
+        ["Announcements", "Announcements/Archive"]
+    );
+    assert!(folders
+        .iter()
+        .all(|folder| folder.acl.len() == 1 && folder.acl[0].account_id == john_id));
+}
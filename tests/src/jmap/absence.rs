@@ -0,0 +1,262 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use chrono::{TimeDelta, Utc};
+use common::Server;
+use jmap::{mailbox::INBOX_ID, JmapMethods};
+use jmap_client::mailbox::Role;
+use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty,
+        delivery::SmtpConnection,
+        email_submission::{
+            assert_message_delivery, expect_nothing, spawn_mock_smtp_server, MockMessage,
+        },
+        jmap_json_request,
+        mailbox::destroy_all_mailboxes,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Absence window tests...");
+
+    // Create test account
+    let server = params.server.clone();
+    let john_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    let account_id = Id::from(john_id).to_string();
+    let mailbox_id = params
+        .client
+        .set_default_account_id(&account_id)
+        .mailbox_create("While I was out", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+
+    // Start mock SMTP server
+    let (mut smtp_rx, smtp_settings) = spawn_mock_smtp_server();
+    server.core.smtp.resolvers.dns.ipv4_add(
+        "localhost",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+
+    // Windows ending before they start are rejected
+    let from_date = (Utc::now() - TimeDelta::try_days(1).unwrap_or_default())
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string();
+    let to_date = (Utc::now() + TimeDelta::try_days(1).unwrap_or_default())
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string();
+    let response = absence_set(&account_id, &format!(
+        r#"[{{ "fromDate": "{to_date}", "toDate": "{from_date}", "forwardTo": "jane@remote.org" }}]"#
+    ))
+    .await;
+    assert_eq!(response["methodResponses"][0][0], "error", "{response}");
+
+    // Schedule an absence window that forwards urgent messages to a delegate
+    let response = absence_set(
+        &account_id,
+        &format!(
+            r#"[{{
+                "fromDate": "{from_date}",
+                "toDate": "{to_date}",
+                "forwardTo": "Jane@remote.org",
+                "fileInto": "{mailbox_id}",
+                "subjectContains": "URGENT"
+            }}]"#
+        ),
+    )
+    .await;
+    let window = &response["methodResponses"][0][1]["windows"][0];
+    assert_eq!(window["forwardTo"], "jane@remote.org", "{response}");
+    assert_eq!(window["fileInto"], mailbox_id.as_str(), "{response}");
+    assert!(!window["id"].as_str().unwrap().is_empty(), "{response}");
+    let response = jmap_json_request(
+        format!(r#"[[ "Absence/get", {{ "accountId": "{account_id}" }}, "0" ]]"#),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        &response["methodResponses"][0][1]["windows"][0], window,
+        "{response}"
+    );
+
+    // Matching messages are forwarded and filed
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Urgent: TPS reports\r\n",
+            "\r\n",
+            "I'm going to need those TPS reports ASAP."
+        ),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new(
+            "<jdoe@example.com>",
+            ["<jane@remote.org>"],
+            "X-Loop: jdoe@example.com",
+        ),
+    )
+    .await;
+    let mailbox_document_id = Id::from_bytes(mailbox_id.as_bytes()).unwrap().document_id();
+    assert_eq!(
+        mailbox_count(&server, john_id, mailbox_document_id).await,
+        1
+    );
+    assert_eq!(mailbox_count(&server, john_id, INBOX_ID).await, 0);
+
+    // Other messages are delivered as usual
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: TPS reports\r\n",
+            "\r\n",
+            "Did you get the memo?"
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+    assert_eq!(mailbox_count(&server, john_id, INBOX_ID).await, 1);
+
+    // Already forwarded, auto-generated and delegate messages are not forwarded again
+    for (sender, headers) in [
+        ("bill@remote.org", "X-Loop: <jdoe@example.com>\r\n"),
+        ("bill@remote.org", "Auto-Submitted: auto-replied\r\n"),
+        ("jane@remote.org", ""),
+        ("", ""),
+    ] {
+        lmtp.ingest(
+            sender,
+            &["jdoe@example.com"],
+            &format!(
+                concat!(
+                    "From: bill@remote.org\r\n",
+                    "To: jdoe@example.com\r\n",
+                    "{}",
+                    "Subject: Urgent: TPS reports\r\n",
+                    "\r\n",
+                    "Listen, are you gonna have those TPS reports for us this afternoon?"
+                ),
+                headers
+            ),
+        )
+        .await;
+        expect_nothing(&mut smtp_rx).await;
+    }
+    assert_eq!(
+        mailbox_count(&server, john_id, mailbox_document_id).await,
+        5
+    );
+
+    // Removing all windows disables forwarding and filing
+    let response = absence_set(&account_id, "[]").await;
+    assert_eq!(
+        response["methodResponses"][0][1]["windows"]
+            .as_array()
+            .unwrap()
+            .len(),
+        0,
+        "{response}"
+    );
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Urgent: TPS reports\r\n",
+            "\r\n",
+            "Never mind."
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+    assert_eq!(mailbox_count(&server, john_id, INBOX_ID).await, 2);
+
+    // Stop the mock SMTP server
+    smtp_settings.lock().do_stop = true;
+    absence_set(
+        &account_id,
+        &format!(
+            r#"[{{ "fromDate": "{from_date}", "toDate": "{to_date}", "forwardTo": "jane@remote.org" }}]"#
+        ),
+    )
+    .await;
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Out of office\r\n",
+            "\r\n",
+            "Enjoy Kokomo."
+        ),
+    )
+    .await;
+    lmtp.quit().await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new("<jdoe@example.com>", ["<jane@remote.org>"], "@Kokomo"),
+    )
+    .await;
+    absence_set(&account_id, "[]").await;
+
+    // Remove test data
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn absence_set(account_id: &str, windows: &str) -> serde_json::Value {
+    jmap_json_request(
+        format!(
+            r#"[[ "Absence/set", {{ "accountId": "{account_id}", "windows": {windows} }}, "0" ]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await
+}
+
+async fn mailbox_count(server: &Server, account_id: u32, mailbox_id: u32) -> u64 {
+    server
+        .get_tag(
+            account_id,
+            Collection::Email,
+            Property::MailboxIds,
+            mailbox_id,
+        )
+        .await
+        .unwrap()
+        .map_or(0, |bm| bm.len())
+}
@@ -0,0 +1,186 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use futures::future::join_all;
+use jmap::{api::management::alias::AliasItem, JmapMethods};
+use jmap_proto::types::{collection::Collection, id::Id};
+use serde_json::json;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running disposable alias tests...");
+    let server = params.server.clone();
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "rita@example.com",
+            "secret",
+            "Rita Hayworth",
+            &["rita@example.com"],
+        )
+        .await;
+    let api = ManagementApi::new(8899, "rita@example.com", "secret");
+
+    // Mint an alias, only letters and digits are kept from the prefix
+    let alias = api
+        .post::<AliasItem>(
+            "/api/account/aliases",
+            &json!({
+                "prefix": "Shop!",
+                "description": "Online store",
+            }),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(alias.address.starts_with("shop-"), "{}", alias.address);
+    assert!(alias.address.ends_with("@example.com"), "{}", alias.address);
+    assert_eq!(alias.description.as_deref(), Some("Online store"));
+    assert!(alias.enabled && alias.active);
+    assert_eq!(alias.expires, None);
+    assert_eq!(alias.received, 0);
+
+    // Expiring aliases count towards the limit
+    let expiring = api
+        .post::<AliasItem>("/api/account/aliases", &json!({"expiresIn": 3600}))
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(expiring.address.starts_with("alias-"));
+    assert!(expiring.expires.is_some());
+    assert!(matches!(
+        api.post::<AliasItem>("/api/account/aliases", &json!({}))
+            .await
+            .unwrap(),
+        crate::jmap::Response::Error { .. }
+    ));
+
+    // Messages sent to the alias are delivered to the mailbox and counted
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest(
+        "bill@remote.org",
+        &[alias.address.as_str()],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "Subject: Your order\r\n",
+            "\r\n",
+            "Your order has shipped."
+        ),
+    )
+    .await;
+    assert_eq!(
+        server
+            .get_document_ids(account_id, Collection::Email)
+            .await
+            .unwrap()
+            .unwrap()
+            .len(),
+        1
+    );
+    let aliases = api
+        .get::<Vec<AliasItem>>("/api/account/aliases")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(aliases.len(), 2);
+    assert_eq!(
+        aliases
+            .iter()
+            .find(|item| item.address == alias.address)
+            .unwrap()
+            .received,
+        1
+    );
+
+    // Disabled aliases are rejected at RCPT time
+    let alias = api
+        .patch::<AliasItem>(
+            &format!("/api/account/aliases/{}", alias.address),
+            &json!({"enabled": false}),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(!alias.enabled && !alias.active);
+    lmtp.mail_from("bill@remote.org", 2).await;
+    lmtp.rcpt_to(&alias.address, 5).await;
+    lmtp.rset().await;
+
+    // Other accounts cannot manage the alias
+    assert!(!matches!(
+        ManagementApi::new(8899, "jdoe@example.com", "12345")
+            .delete::<()>(&format!("/api/account/aliases/{}", alias.address))
+            .await
+            .unwrap(),
+        crate::jmap::Response::Data { .. }
+    ));
+
+    // Delete both aliases
+    for address in [&alias.address, &expiring.address] {
+        api.delete::<()>(&format!("/api/account/aliases/{address}"))
+            .await
+            .unwrap()
+            .unwrap_data();
+    }
+    assert!(server
+        .disposable_aliases(account_id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        server
+            .disposable_alias_received(&alias.address)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // Concurrent changes to the alias index should not be lost
+    let addresses =
+        join_all((0..3).map(|_| {
+            server.disposable_alias_create(account_id, "race", "example.com", None, None)
+        }))
+        .await
+        .into_iter()
+        .map(|alias| alias.unwrap().address)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        server.disposable_aliases(account_id).await.unwrap().len(),
+        3
+    );
+    for result in join_all(
+        addresses
+            .iter()
+            .map(|address| server.disposable_alias_delete(account_id, address)),
+    )
+    .await
+    {
+        assert!(result.unwrap());
+    }
+    assert!(server
+        .disposable_aliases(account_id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Remove test data
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -438,6 +438,52 @@ pub async fn test(params: &mut JMAPTest) {
         .await
         .unwrap();
 
+    // With private \Seen enabled, John's \Seen state should not affect Jane
+    let mut core = server.inner.shared_core.load_full().as_ref().clone();
+    core.imap.shared_private_seen = true;
+    server.inner.shared_core.store(core.into());
+    jane_client
+        .email_set_keyword(&email_id_2, "$seen", false)
+        .await
+        .unwrap();
+    john_client
+        .set_default_account_id(jane_id.to_string())
+        .email_set_keyword(&email_id_2, "$seen", true)
+        .await
+        .unwrap();
+    assert!(john_client
+        .set_default_account_id(jane_id.to_string())
+        .email_get(&email_id_2, [Property::Keywords].into())
+        .await
+        .unwrap()
+        .unwrap()
+        .keywords()
+        .contains(&"$seen"));
+    assert!(!jane_client
+        .email_get(&email_id_2, [Property::Keywords].into())
+        .await
+        .unwrap()
+        .unwrap()
+        .keywords()
+        .contains(&"$seen"));
+    let john_unread = john_client
+        .set_default_account_id(jane_id.to_string())
+        .mailbox_get(&inbox_id, [mailbox::Property::UnreadEmails].into())
+        .await
+        .unwrap()
+        .unwrap()
+        .unread_emails();
+    let jane_unread = jane_client
+        .mailbox_get(&inbox_id, [mailbox::Property::UnreadEmails].into())
+        .await
+        .unwrap()
+        .unwrap()
+        .unread_emails();
+    assert_eq!(john_unread + 1, jane_unread);
+    let mut core = server.inner.shared_core.load_full().as_ref().clone();
+    core.imap.shared_private_seen = false;
+    server.inner.shared_core.store(core.into());
+
     // Try to create a child
     assert_forbidden(
         john_client
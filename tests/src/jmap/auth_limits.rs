@@ -6,19 +6,29 @@
 
 use std::{
     net::{IpAddr, Ipv4Addr},
-    sync::Arc,
-    time::Duration,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 
-use common::listener::blocked::BLOCKED_IP_KEY;
+use common::{
+    auth::AuthRequest, config::network::LoginProtection, core::BuildServer,
+    listener::blocked::BLOCKED_IP_KEY, Server,
+};
+use directory::backend::internal::{
+    manage::{ManageDirectory, UpdatePrincipal},
+    PrincipalField, PrincipalUpdate, PrincipalValue,
+};
 use imap_proto::ResponseType;
+use jmap::auth::challenge::{pow_challenge, pow_leading_zeros, pow_verify};
 use jmap_client::{
     client::{Client, Credentials},
     core::set::{SetError, SetErrorType},
+    email,
     mailbox::{self},
 };
 use jmap_proto::types::id::Id;
 use store::write::now;
+use totp_rs::TOTP;
 
 use crate::{
     directory::internal::TestInternalDirectory,
@@ -255,6 +265,31 @@ pub async fn test(params: &mut JMAPTest) {
         client.upload(None, b"sleep".to_vec(), None).await,
         Err(jmap_client::Error::Problem(err)) if err.status() == Some(400)));
 
+    // Test login lockout, delays and trusted devices
+    for lockout_account_id in test_login_protection(&server).await {
+        // Locked out users are notified
+        params
+            .client
+            .set_default_account_id(Id::from(lockout_account_id).to_string());
+        let mut notified = false;
+        for _ in 0..30 {
+            if !params
+                .client
+                .email_query(None::<email::query::Filter>, None::<Vec<_>>)
+                .await
+                .unwrap()
+                .ids()
+                .is_empty()
+            {
+                notified = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(notified, "Lockout notification was not delivered");
+        destroy_all_mailboxes(params).await;
+    }
+
     // Destroy test accounts
     params.client.set_default_account_id(&account_id);
     destroy_all_mailboxes(params).await;
@@ -265,3 +300,239 @@ pub async fn test(params: &mut JMAPTest) {
         .webhook
         .assert_contains(&["auth.failed", "auth.success", "security.authentication-ban"]);
 }
+
+async fn test_login_protection(server: &Server) -> [u32; 2] {
+    const TOTP_URL: &str =
+        "otpauth://totp/Test:jlock@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Test";
+
+    // Enable login protection
+    let mut core = server.inner.shared_core.load_full().as_ref().clone();
+    core.network.login_protection = Some(LoginProtection {
+        window: 300,
+        delay_after: 2,
+        delay_base: Duration::from_millis(300),
+        delay_max: Duration::from_millis(600),
+        lockout_after: 4,
+        lockout_duration: 2,
+        lockout_duration_max: 8,
+        notify_from: Some("security@example.com".to_string()),
+        trusted_device_expiry: 3600,
+    });
+    server.inner.shared_core.store(core.into());
+    let server = server.inner.build_server();
+
+    // Create an account with two-factor authentication enabled
+    let store = server.core.storage.data.clone();
+    let account_id = store
+        .create_test_user(
+            "jlock@example.com",
+            "secret",
+            "John Lock",
+            &["jlock@example.com"],
+        )
+        .await;
+    store
+        .update_principal(UpdatePrincipal::by_id(account_id).with_updates(vec![
+            PrincipalUpdate::add_item(
+                PrincipalField::Secrets,
+                PrincipalValue::String(TOTP_URL.to_string()),
+            ),
+        ]))
+        .await
+        .unwrap();
+    let totp = TOTP::from_url(TOTP_URL).unwrap();
+    let with_totp = |password: &str| format!("{password}${}", totp.generate_current().unwrap());
+    let ip = |n: u8| IpAddr::V4(Ipv4Addr::new(10, 0, 1, n));
+
+    // A login with a second factor issues a trusted device token
+    let issued = OnceLock::new();
+    server
+        .authenticate(
+            &AuthRequest::from_plain("jlock@example.com", with_totp("secret"), 0, ip(1))
+                .with_trusted_device(None, &issued),
+        )
+        .await
+        .unwrap();
+    let device_token = issued.get().cloned().expect("Missing trusted device token");
+
+    // Passwords alone do not issue tokens
+    let not_issued = OnceLock::new();
+    let _ = server
+        .authenticate(
+            &AuthRequest::from_plain("jlock@example.com", "secret", 0, ip(1))
+                .with_trusted_device(None, &not_issued),
+        )
+        .await;
+    assert!(not_issued.get().is_none());
+
+    // Failures are counted per login, address and client, rotating the device
+    // token does not reset the counter
+    let attempt = |password: String, n: u8| {
+        AuthRequest::from_plain("jlock@example.com", password, 0, ip(n))
+            .with_fingerprint(Some("Test Client/1.0".to_string()))
+    };
+    for n in 0..2 {
+        let issued = OnceLock::new();
+        let err = server
+            .authenticate(
+                &attempt("wrong".to_string(), 10)
+                    .with_trusted_device(Some(format!("invalid-token-{n}")), &issued),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.matches(trc::EventType::Auth(trc::AuthEvent::Failed)));
+    }
+
+    // Further attempts are delayed
+    let time = Instant::now();
+    let err = server
+        .authenticate(&attempt("wrong".to_string(), 10))
+        .await
+        .unwrap_err();
+    assert!(err.matches(trc::EventType::Auth(trc::AuthEvent::Failed)));
+    assert!(time.elapsed() >= Duration::from_millis(300));
+
+    // The login is locked out after the last allowed failure, even when using
+    // the correct credentials
+    let _ = server
+        .authenticate(&attempt("wrong".to_string(), 10))
+        .await
+        .unwrap_err();
+    let assert_locked = |result: trc::Result<_>| {
+        let err = result.unwrap_err();
+        assert!(
+            err.matches(trc::EventType::Security(
+                trc::SecurityEvent::AuthenticationLockout
+            )),
+            "{err:?}"
+        );
+    };
+    for _ in 0..2 {
+        let issued = OnceLock::new();
+        assert_locked(
+            server
+                .authenticate(
+                    &attempt(with_totp("secret"), 10)
+                        .with_trusted_device(Some(format!("{device_token}x")), &issued),
+                )
+                .await,
+        );
+    }
+
+    // Failures from elsewhere do not lock out the legitimate user
+    server
+        .authenticate(&attempt("secret".to_string(), 11))
+        .await
+        .unwrap();
+    server
+        .authenticate(
+            &AuthRequest::from_plain("jlock@example.com", with_totp("secret"), 0, ip(10))
+                .with_fingerprint(Some("Other Client/2.0".to_string())),
+        )
+        .await
+        .unwrap();
+
+    // Failing again after the lockout expires locks the login again, for longer
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    let err = server
+        .authenticate(&attempt("wrong".to_string(), 10))
+        .await
+        .unwrap_err();
+    assert!(err.matches(trc::EventType::Auth(trc::AuthEvent::Failed)));
+    assert_locked(
+        server
+            .authenticate(&attempt("secret".to_string(), 10))
+            .await,
+    );
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    assert_locked(
+        server
+            .authenticate(&attempt("secret".to_string(), 10))
+            .await,
+    );
+
+    // Trusted devices can still log in
+    let issued = OnceLock::new();
+    server
+        .authenticate(
+            &attempt(with_totp("secret"), 10)
+                .with_trusted_device(Some(device_token.clone()), &issued),
+        )
+        .await
+        .unwrap();
+
+    // Device tokens are bound to the login they were issued for
+    let other_account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jlock2@example.com",
+            "secret",
+            "John Lock II",
+            &["jlock2@example.com"],
+        )
+        .await;
+    for _ in 0..4 {
+        let _ = server
+            .authenticate(&AuthRequest::from_plain(
+                "jlock2@example.com",
+                "wrong",
+                0,
+                ip(40),
+            ))
+            .await;
+    }
+    let issued = OnceLock::new();
+    let err = server
+        .authenticate(
+            &AuthRequest::from_plain("jlock2@example.com", "secret", 0, ip(41))
+                .with_trusted_device(Some(device_token), &issued),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.matches(trc::EventType::Security(
+        trc::SecurityEvent::AuthenticationLockout
+    )));
+
+    // Disable login protection
+    let mut core = server.inner.shared_core.load_full().as_ref().clone();
+    core.network.login_protection = None;
+    server.inner.shared_core.store(core.into());
+
+    [account_id, other_account_id]
+}
+
+#[test]
+fn auth_challenge_pow() {
+    let key = [7u8; 32];
+    let remote_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let other_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let challenge = pow_challenge(&key, remote_ip, now() + 60);
+
+    // Solve the challenge
+    let difficulty = 8;
+    let response = (0u64..)
+        .map(|counter| format!("{challenge}:{counter}"))
+        .find(|response| {
+            pow_leading_zeros(store::blake3::hash(response.as_bytes()).as_bytes()) >= difficulty
+        })
+        .unwrap();
+    assert!(pow_verify(&key, remote_ip, difficulty, &response).is_some());
+
+    // Solutions are bound to the key, IP address and difficulty
+    assert!(pow_verify(&[8u8; 32], remote_ip, difficulty, &response).is_none());
+    assert!(pow_verify(&key, other_ip, difficulty, &response).is_none());
+    assert!(pow_verify(&key, remote_ip, 64, &response).is_none());
+    assert!(pow_verify(&key, remote_ip, difficulty, &challenge).is_none());
+
+    // Tampered and expired challenges are rejected
+    let (nonce, rest) = response.split_once('.').unwrap();
+    let last = if nonce.ends_with('X') { 'Y' } else { 'X' };
+    let tampered = format!("{}{last}.{rest}", &nonce[..nonce.len() - 1]);
+    assert!(pow_verify(&key, remote_ip, 0, &tampered).is_none());
+    let expired = pow_challenge(&key, remote_ip, now() - 1);
+    assert!(pow_verify(&key, remote_ip, 0, &format!("{expired}:0")).is_none());
+
+    assert_eq!(pow_leading_zeros(&[0, 0x10, 0xff]), 11);
+}
@@ -13,6 +13,16 @@ use common::auth::oauth::{
     introspect::OAuthIntrospect,
     oidc::StandardClaims,
     registration::{ClientRegistrationRequest, ClientRegistrationResponse},
+    GrantType,
+};
+use directory::{
+    backend::internal::{
+        lookup::DirectoryStore,
+        manage::{ManageDirectory, UpdatePrincipal},
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+    },
+    core::secret::{PasswordHashing, PasswordScheme},
+    QueryBy,
 };
 use imap_proto::ResponseType;
 use jmap::auth::oauth::{
@@ -396,6 +406,87 @@ pub async fn test(params: &mut JMAPTest) {
         .purge_lookup_store()
         .await
         .unwrap();
+
+    // Re-hashing a password on login keeps tokens valid, changing it revokes
+    // the long-lived ones while short-lived tokens are not bound to it
+    let legacy_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jlegacy@example.com",
+            &PasswordHashing {
+                scheme: PasswordScheme::Sha512Crypt,
+                ..Default::default()
+            }
+            .hash("legacy")
+            .unwrap(),
+            "John Legacy",
+            &["jlegacy@example.com"],
+        )
+        .await;
+    let refresh_token = server
+        .encode_access_token(GrantType::RefreshToken, legacy_id, "rehash", 7200)
+        .await
+        .unwrap();
+    let access_token = server
+        .encode_access_token(GrantType::AccessToken, legacy_id, "rehash", 3600)
+        .await
+        .unwrap();
+    for token in [&refresh_token, &access_token] {
+        assert!(server.validate_access_token(None, token).await.is_ok());
+    }
+    assert_eq!(
+        server
+            .core
+            .storage
+            .directory
+            .query(
+                QueryBy::Credentials(&mail_send::Credentials::new(
+                    "jlegacy@example.com".to_string(),
+                    "legacy".to_string()
+                )),
+                false
+            )
+            .await
+            .unwrap()
+            .map(|p| p.id()),
+        Some(legacy_id)
+    );
+    assert!(server
+        .core
+        .storage
+        .data
+        .query(QueryBy::Id(legacy_id), false)
+        .await
+        .unwrap()
+        .unwrap()
+        .iter_str(PrincipalField::Secrets)
+        .all(|secret| secret.starts_with("$argon2id$")));
+    for token in [&refresh_token, &access_token] {
+        assert!(server.validate_access_token(None, token).await.is_ok());
+    }
+    server
+        .core
+        .storage
+        .data
+        .update_principal(UpdatePrincipal::by_id(legacy_id).with_updates(vec![
+            PrincipalUpdate::set(
+                PrincipalField::Secrets,
+                PrincipalValue::String("changed".to_string()),
+            ),
+        ]))
+        .await
+        .unwrap();
+    assert!(server
+        .validate_access_token(None, &refresh_token)
+        .await
+        .is_err());
+    assert!(server
+        .validate_access_token(None, &access_token)
+        .await
+        .is_ok());
+
     params.client.set_default_account_id(john_id);
     destroy_all_mailboxes(params).await;
     assert_is_empty(server).await;
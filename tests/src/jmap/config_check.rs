@@ -0,0 +1,97 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::manager::reload::{ConfigChange, SettingSource};
+use utils::config::{ConfigError, ConfigWarning};
+
+use super::JMAPTest;
+
+const CONFIG: &str = r#"
+[storage]
+data = "{STORE}"
+fts = "{STORE}"
+blob = "{STORE}"
+lookup = "{STORE}"
+directory = "{STORE}"
+
+[directory."{STORE}"]
+type = "internal"
+store = "{STORE}"
+
+[jmap.protocol.get]
+max-objects = "many"
+
+[jmap.protocol.set]
+max-objectz = 100
+
+[lookup.default]
+hostname = "%{env:CONFIG_CHECK_HOSTNAME}%"
+"#;
+
+pub async fn test(params: &JMAPTest) {
+    println!("Running configuration check tests...");
+    let server = params.server.clone();
+
+    std::env::set_var("CONFIG_CHECK_HOSTNAME", "mx.example.org");
+    let report = server
+        .check_config(&CONFIG.replace("{STORE}", &std::env::var("STORE").unwrap()))
+        .await
+        .unwrap()
+        .report();
+
+    // Type mismatches are reported as errors
+    assert!(
+        matches!(
+            report.errors.get("jmap.protocol.get.max-objects"),
+            Some(ConfigError::Parse { .. })
+        ),
+        "{:?}",
+        report.errors
+    );
+
+    // Unknown keys are reported as warnings
+    assert_eq!(
+        report.warnings.get("jmap.protocol.set.max-objectz"),
+        Some(&ConfigWarning::Unread {
+            value: "100".to_string()
+        })
+    );
+
+    // Macros and defaults are shown with their effective value
+    let hostname = report
+        .effective
+        .iter()
+        .find(|setting| setting.key == "lookup.default.hostname")
+        .unwrap();
+    assert_eq!(hostname.value, "mx.example.org");
+    assert!(matches!(
+        &hostname.source,
+        SettingSource::Macro { expression } if expression == "%{env:CONFIG_CHECK_HOSTNAME}%"
+    ));
+    let retry = report
+        .effective
+        .iter()
+        .find(|setting| setting.key == "storage.full-text.fallback.retry")
+        .unwrap();
+    assert_eq!(retry.value, "1m");
+    assert!(matches!(retry.source, SettingSource::Default));
+
+    // Settings not present in the running configuration are previewed
+    assert!(report.changes.iter().any(|change| matches!(
+        change,
+        ConfigChange::Added { key, value }
+            if key == "jmap.protocol.set.max-objectz" && value == "100"
+    )));
+
+    // Invalid files are rejected before being parsed
+    let report = server
+        .check_config("[storage\ndata = 1")
+        .await
+        .unwrap()
+        .report();
+    assert!(report.errors.contains_key("*"));
+    assert!(report.changes.is_empty());
+}
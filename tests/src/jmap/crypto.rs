@@ -4,13 +4,18 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::path::PathBuf;
+use std::{collections::BTreeSet, path::PathBuf, time::Duration};
 
-use jmap::email::crypto::{
-    try_parse_certs, Algorithm, EncryptMessage, EncryptionMethod, EncryptionParams, EncryptionType,
+use jmap::email::{
+    crypto::{
+        try_parse_certs, Algorithm, EncryptMessage, EncryptionMethod, EncryptionParams,
+        EncryptionType,
+    },
+    pgp::pgp_recipients as jmap_pgp_recipients,
 };
 use jmap_proto::types::id::Id;
 use mail_parser::{MessageParser, MimeHeaders};
+use serde_json::json;
 
 use crate::{
     directory::internal::TestInternalDirectory,
@@ -61,10 +66,12 @@ pub async fn test(params: &mut JMAPTest) {
                 EncryptionMethod::PGP => EncryptionType::PGP {
                     algo,
                     certs: certs.clone(),
+                    reencrypt: false,
                 },
                 EncryptionMethod::SMIME => EncryptionType::SMIME {
                     algo,
                     certs: certs.clone(),
+                    reencrypt: false,
                 },
             };
 
@@ -172,6 +179,110 @@ pub async fn test(params: &mut JMAPTest) {
             panic!("Unexpected message: {:#?}", message)
         }
     }
+
+    // Escrow the account's OpenPGP key, messages that it can decrypt are
+    // re-encrypted when the certificates change
+    api.post::<String>(
+        "/api/account/pgp-escrow",
+        &json!({"key": read_resource("pgp_escrow.asc")}),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    let escrow_certs = read_resource("pgp_escrow_pub.asc");
+    let pgp_certs = read_resource("cert_pgp.pem");
+    let escrow_recipients = pgp_recipients_of(&escrow_certs);
+    let pgp_recipients = pgp_recipients_of(&pgp_certs);
+    assert_ne!(escrow_recipients, pgp_recipients);
+    for (certs, expected) in [
+        // The plain text message is encrypted to the escrowed key, the one
+        // encrypted to another key can not be decrypted and is left as is
+        (
+            escrow_certs,
+            [
+                ("should be encrypted", &pgp_recipients),
+                ("plain text", &escrow_recipients),
+            ],
+        ),
+        // The message encrypted to the escrowed key is re-encrypted to the new certificate
+        (
+            pgp_certs,
+            [
+                ("should be encrypted", &pgp_recipients),
+                ("plain text", &pgp_recipients),
+            ],
+        ),
+    ] {
+        assert_eq!(
+            api.post::<u32>(
+                "/api/account/crypto",
+                &EncryptionType::PGP {
+                    algo: Algorithm::Aes256,
+                    certs,
+                    reencrypt: true,
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap_data(),
+            1
+        );
+
+        let mut attempts = 0;
+        loop {
+            let mut request = client.build();
+            request.get_email();
+            let mut stored = Vec::new();
+            for email in request.send_get_email().await.unwrap().take_list() {
+                let raw_message = client.download(email.blob_id().unwrap()).await.unwrap();
+                let message = MessageParser::new().parse(&raw_message).unwrap();
+                let subject = message.subject().unwrap_or_default().to_string();
+                if subject.contains("already encrypted") {
+                    assert!(
+                        String::from_utf8_lossy(&raw_message)
+                            .contains("xjMEZMYfNhYJKwYBBAHaRw8BAQdAYy"),
+                        "expected message to be left intact"
+                    );
+                } else {
+                    stored.push((subject, jmap_pgp_recipients(&message)));
+                }
+            }
+            if expected.iter().all(|(subject, recipients)| {
+                stored.iter().any(|(stored_subject, stored_recipients)| {
+                    stored_subject.contains(subject)
+                        && stored_recipients.as_ref() == Some(*recipients)
+                })
+            }) {
+                break;
+            }
+            attempts += 1;
+            assert!(attempts < 50, "unexpected recipients: {stored:?}");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+    api.delete::<()>("/api/account/pgp-escrow")
+        .await
+        .unwrap()
+        .unwrap_data();
+}
+
+fn pgp_recipients_of(certs: &str) -> BTreeSet<Vec<u8>> {
+    EncryptionParams {
+        method: EncryptionMethod::PGP,
+        algo: Algorithm::Aes256,
+        certs: try_parse_certs(EncryptionMethod::PGP, certs.as_bytes().to_vec()).unwrap(),
+    }
+    .pgp_recipients()
+}
+
+fn read_resource(file_name: &str) -> String {
+    std::fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources")
+            .join("crypto")
+            .join(file_name),
+    )
+    .unwrap()
 }
 
 #[tokio::test]
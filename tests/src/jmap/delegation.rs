@@ -0,0 +1,264 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::auth::delegation::Delegation;
+use jmap::{api::management::principal::DelegateGrant, mailbox::INBOX_ID};
+use jmap_proto::types::{acl::Acl, collection::Collection, id::Id};
+use smtp::inbound::auth::{check_delegated_sender, DelegatedSender};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, jmap_raw_request, mailbox::destroy_all_mailboxes, ManagementApi},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running delegation tests...");
+    let server = params.server.clone();
+
+    let john_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    let jane_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jane.smith@example.com",
+            "abcde",
+            "Jane Smith",
+            &["jane.smith@example.com"],
+        )
+        .await;
+
+    // John delegates send-on-behalf and read access to Jane
+    ManagementApi::new(8899, "jdoe@example.com", "12345")
+        .post::<()>(
+            "/api/account/delegates",
+            &vec![DelegateGrant {
+                name: "jane.smith@example.com".to_string(),
+                rights: vec![Delegation::SendOnBehalf, Delegation::ReadMailbox],
+            }],
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+
+    // Delegating to oneself is not allowed
+    assert!(matches!(
+        ManagementApi::new(8899, "jdoe@example.com", "12345")
+            .post::<()>(
+                "/api/account/delegates",
+                &vec![DelegateGrant {
+                    name: "jdoe@example.com".to_string(),
+                    rights: vec![Delegation::SendAs],
+                }],
+            )
+            .await
+            .unwrap(),
+        crate::jmap::Response::Error { .. }
+    ));
+
+    // Administrators can list delegates
+    let delegates = ManagementApi::new(8899, "admin", "secret")
+        .get::<Vec<DelegateGrant>>("/api/principal/jdoe@example.com/delegates")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(delegates.len(), 1);
+    assert_eq!(delegates[0].name, "jane.smith@example.com");
+    assert_eq!(
+        delegates[0].rights,
+        vec![Delegation::ReadMailbox, Delegation::SendOnBehalf]
+    );
+
+    // Jane can read John's mailboxes but not modify them
+    let access_token = server.get_access_token(jane_id).await.unwrap();
+    assert!(!access_token.is_member(john_id));
+    assert!(access_token.has_access(john_id, Collection::Mailbox));
+    assert!(access_token.has_access(john_id, Collection::Email));
+    assert!(!access_token.has_access(john_id, Collection::SieveScript));
+    let acl = access_token.delegated_acl(john_id);
+    assert!(acl.contains(Acl::ReadItems));
+    assert!(!acl.contains(Acl::AddItems));
+
+    // Sending on behalf of John requires a Sender header with Jane's address
+    assert!(access_token.delegated_sender("jdoe@example.com").is_some());
+    assert!(access_token.delegated_sender("bill@example.com").is_none());
+    assert!(matches!(
+        check_delegated_sender(
+            &access_token,
+            b"From: John Doe <jdoe@example.com>\r\nSubject: hi\r\n\r\nhello\r\n"
+        ),
+        DelegatedSender::AddHeader(header) if header == "Sender: <jane.smith@example.com>\r\n"
+    ));
+    assert!(matches!(
+        check_delegated_sender(
+            &access_token,
+            concat!(
+                "From: John Doe <jdoe@example.com>\r\n",
+                "Sender: jane.smith@example.com\r\n",
+                "Subject: hi\r\n\r\nhello\r\n"
+            )
+            .as_bytes()
+        ),
+        DelegatedSender::Valid
+    ));
+    assert!(matches!(
+        check_delegated_sender(
+            &access_token,
+            concat!(
+                "From: John Doe <jdoe@example.com>\r\n",
+                "Sender: bill@example.com\r\n",
+                "Subject: hi\r\n\r\nhello\r\n"
+            )
+            .as_bytes()
+        ),
+        DelegatedSender::Unauthorized { .. }
+    ));
+    assert!(matches!(
+        check_delegated_sender(
+            &access_token,
+            b"From: jane.smith@example.com\r\nSubject: hi\r\n\r\nhello\r\n"
+        ),
+        DelegatedSender::Valid
+    ));
+
+    // Upgrade Jane to send-as and full mailbox access
+    ManagementApi::new(8899, "admin", "secret")
+        .post::<()>(
+            "/api/principal/jdoe@example.com/delegates",
+            &vec![DelegateGrant {
+                name: "jane.smith@example.com".to_string(),
+                rights: vec![Delegation::SendAs, Delegation::ManageMailbox],
+            }],
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    let access_token = server.get_access_token(jane_id).await.unwrap();
+    assert!(!access_token.is_member(john_id));
+    let acl = access_token.delegated_acl(john_id);
+    assert!(acl.contains(Acl::ModifyItems));
+    assert!(!acl.contains(Acl::Administer));
+
+    // Jane can manage John's mailboxes
+    let john_account = Id::from(john_id).to_string();
+    let inbox_id = Id::from(INBOX_ID).to_string();
+    jmap_raw_request(
+        r#"[[ "Mailbox/get", { "accountId": "$$" }, "0" ]]"#.replace("$$", &john_account),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let response = serde_json::from_str::<serde_json::Value>(
+        &jmap_raw_request(
+            r#"[[ "Mailbox/set", {
+                "accountId": "$$",
+                "create": { "m1": { "name": "Delegated", "parentId": "%%" } }
+              }, "0" ]]"#
+                .replace("$$", &john_account)
+                .replace("%%", &inbox_id),
+            "jane.smith@example.com",
+            "abcde",
+        )
+        .await,
+    )
+    .unwrap();
+    assert!(
+        response["methodResponses"][0][1]["created"]["m1"]["id"].is_string(),
+        "{response}"
+    );
+
+    // Jane can not submit mail, manage Sieve scripts or identities as John
+    for request in [
+        r#"[[ "EmailSubmission/set", {
+            "accountId": "$$",
+            "create": { "s1": { "emailId": "a", "identityId": "b" } }
+          }, "0" ]]"#,
+        r#"[[ "SieveScript/set", {
+            "accountId": "$$",
+            "create": { "s1": { "name": "delegated", "blobId": "a" } }
+          }, "0" ]]"#,
+        r#"[[ "Identity/set", {
+            "accountId": "$$",
+            "create": { "i1": { "name": "John", "email": "jdoe@example.com" } }
+          }, "0" ]]"#,
+    ] {
+        let response = serde_json::from_str::<serde_json::Value>(
+            &jmap_raw_request(
+                request.replace("$$", &john_account),
+                "jane.smith@example.com",
+                "abcde",
+            )
+            .await,
+        )
+        .unwrap();
+        assert_eq!(
+            response["methodResponses"][0][1]["type"], "forbidden",
+            "{response}"
+        );
+    }
+
+    // Jane can not share John's mailboxes
+    let response = serde_json::from_str::<serde_json::Value>(
+        &jmap_raw_request(
+            r#"[[ "Mailbox/set", {
+                "accountId": "$$",
+                "update": { "%%": { "acl/jane.smith@example.com": [ "read", "administer" ] } }
+              }, "0" ]]"#
+                .replace("$$", &john_account)
+                .replace("%%", &inbox_id),
+            "jane.smith@example.com",
+            "abcde",
+        )
+        .await,
+    )
+    .unwrap();
+    assert_eq!(
+        response["methodResponses"][0][1]["notUpdated"][&inbox_id]["type"], "forbidden",
+        "{response}"
+    );
+    assert!(matches!(
+        check_delegated_sender(
+            &access_token,
+            b"From: jdoe@example.com\r\nSubject: hi\r\n\r\nhello\r\n"
+        ),
+        DelegatedSender::Valid
+    ));
+
+    // Revoke all rights
+    ManagementApi::new(8899, "jdoe@example.com", "12345")
+        .post::<()>(
+            "/api/account/delegates",
+            &vec![DelegateGrant {
+                name: "jane.smith@example.com".to_string(),
+                rights: vec![],
+            }],
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    let access_token = server.get_access_token(jane_id).await.unwrap();
+    assert!(access_token.delegated_from.is_empty());
+    assert!(!access_token.is_member(john_id));
+    assert!(!access_token.has_access(john_id, Collection::Mailbox));
+    assert!(access_token.delegated_sender("jdoe@example.com").is_none());
+    assert!(server.get_delegates(john_id).await.unwrap().is_empty());
+
+    params.client.set_default_account_id(john_account);
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -7,10 +7,15 @@
 use std::time::Duration;
 
 use jmap::{
-    mailbox::{INBOX_ID, JUNK_ID},
+    email::metadata::{MessageMetadata, StoredMetadata},
+    mailbox::{get::MailboxGet, INBOX_ID, JUNK_ID},
     JmapMethods,
 };
 use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+use store::{
+    write::{Bincode, ValueClass},
+    ValueKey,
+};
 
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf},
@@ -295,8 +300,105 @@ pub async fn test(params: &mut JMAPTest) {
         );
     }
 
+    // Recipients of the same delivery share a single copy of its metadata
+    let mut shared_hashes = Vec::new();
+    for account_id in [&account_id_2, &account_id_3] {
+        let account_id = Id::from_bytes(account_id.as_bytes()).unwrap().document_id();
+        let document_id = server
+            .get_document_ids(account_id, Collection::Email)
+            .await
+            .unwrap()
+            .unwrap()
+            .max()
+            .unwrap();
+        let stored = server
+            .core
+            .storage
+            .data
+            .get_value::<StoredMetadata>(ValueKey {
+                account_id,
+                collection: Collection::Email.into(),
+                document_id,
+                class: ValueClass::Property(Property::BodyStructure.into()),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let metadata = server
+            .get_property::<Bincode<MessageMetadata>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::BodyStructure,
+            )
+            .await
+            .unwrap()
+            .unwrap()
+            .inner;
+        assert_eq!(stored.hash().as_ref(), Some(&metadata.blob_hash));
+        assert!(String::from_utf8_lossy(&metadata.raw_headers).contains("Subject: Holidays"));
+        shared_hashes.push(metadata.blob_hash);
+    }
+    assert_eq!(shared_hashes[0], shared_hashes[1]);
+
+    // Per-domain sub-addressing files messages into folders
+    let sally_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "sally@filing.example",
+            "secret",
+            "Sally Files",
+            &["sally@filing.example"],
+        )
+        .await;
+    let account_id_4 = Id::from(sally_id).to_string();
+    for rcpt in [
+        "sally-reports@filing.example",
+        "sally+reports@filing.example",
+        "nobody@filing.example",
+    ] {
+        lmtp.ingest(
+            "bill@example.com",
+            &[rcpt],
+            &format!(
+                concat!(
+                    "From: bill@example.com\r\n",
+                    "To: {}\r\n",
+                    "Subject: Filing\r\n",
+                    "\r\n",
+                    "Please file this under reports."
+                ),
+                rcpt
+            ),
+        )
+        .await;
+    }
+    let reports_id = server
+        .mailbox_get_by_name(sally_id, "reports")
+        .await
+        .unwrap()
+        .expect("Folder was not created");
+    for (mailbox_id, num_messages) in [(reports_id, 2), (INBOX_ID, 1)] {
+        assert_eq!(
+            server
+                .get_tag(
+                    sally_id,
+                    Collection::Email,
+                    Property::MailboxIds,
+                    mailbox_id
+                )
+                .await
+                .unwrap()
+                .map_or(0, |bm| bm.len()),
+            num_messages,
+            "for mailbox {mailbox_id}"
+        );
+    }
+
     // Remove test data
-    for account_id in [&account_id_1, &account_id_2, &account_id_3] {
+    for account_id in [&account_id_1, &account_id_2, &account_id_3, &account_id_4] {
         params.client.set_default_account_id(account_id);
         destroy_all_mailboxes(params).await;
     }
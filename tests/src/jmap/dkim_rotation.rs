@@ -0,0 +1,202 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::core::BuildServer;
+use jmap::api::management::dkim::{DkimKeyStatus, DkimManagement, DkimRecord};
+use serde_json::json;
+use store::write::now;
+
+use crate::jmap::ManagementApi;
+
+use super::JMAPTest;
+
+const SIGNATURE_ID: &str = "ed25519-rotate.org";
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running DKIM key rotation tests...");
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let config = params.server.core.storage.config.clone();
+
+    // Create a signature and enable rotation
+    api.post::<()>(
+        "/api/dkim",
+        &json!({
+            "id": SIGNATURE_ID,
+            "algorithm": "Ed25519",
+            "domain": "rotate.org",
+            "selector": "initial"
+        }),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    config
+        .set([
+            (
+                format!("signature.{SIGNATURE_ID}.rotation.interval"),
+                "30d".to_string(),
+            ),
+            (
+                format!("signature.{SIGNATURE_ID}.rotation.overlap"),
+                "7d".to_string(),
+            ),
+        ])
+        .await
+        .unwrap();
+    params.server.dkim_reload().await.unwrap();
+    let server = params.server.inner.build_server();
+    assert!(server
+        .core
+        .smtp
+        .mail_auth
+        .rotations
+        .contains_key(SIGNATURE_ID));
+    assert_eq!(
+        records(&api).await,
+        vec![("initial".to_string(), DkimKeyStatus::Active)]
+    );
+
+    // The first check starts tracking the key without rotating it
+    assert!(!server.dkim_rotate(SIGNATURE_ID, false).await.unwrap());
+    let last_rotation = config
+        .get(format!("signature.{SIGNATURE_ID}.rotation.last"))
+        .await
+        .unwrap()
+        .unwrap()
+        .parse::<u64>()
+        .unwrap();
+    assert!(last_rotation.abs_diff(now()) < 5);
+    assert!(!server.dkim_rotate(SIGNATURE_ID, false).await.unwrap());
+    assert_eq!(records(&api).await.len(), 1);
+
+    // The next key is published within the overlap period before the rotation
+    set_last_rotation(&params.server, 25).await;
+    assert!(!server.dkim_rotate(SIGNATURE_ID, false).await.unwrap());
+    let current = records(&api).await;
+    assert_eq!(current.len(), 2, "{current:?}");
+    assert_eq!(current[0], ("initial".to_string(), DkimKeyStatus::Active));
+    assert_eq!(current[1].1, DkimKeyStatus::Pending);
+    let next_selector = current[1].0.clone();
+    assert!(next_selector.ends_with('e'), "{next_selector}");
+
+    // Once the interval expires the next key becomes active and the previous one is retired
+    set_last_rotation(&params.server, 31).await;
+    assert!(server.dkim_rotate(SIGNATURE_ID, false).await.unwrap());
+    server.dkim_reload().await.unwrap();
+    assert_eq!(
+        config
+            .get(format!("signature.{SIGNATURE_ID}.selector"))
+            .await
+            .unwrap()
+            .unwrap(),
+        next_selector
+    );
+    let dkim_records = api
+        .get::<Vec<DkimRecord>>(&format!("/api/dkim/{SIGNATURE_ID}/records"))
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(dkim_records.len(), 2, "{dkim_records:?}");
+    assert_eq!(dkim_records[0].status, DkimKeyStatus::Active);
+    assert_eq!(
+        dkim_records[0].name,
+        format!("{next_selector}._domainkey.rotate.org.")
+    );
+    assert!(dkim_records[0].content.starts_with("v=DKIM1; k=ed25519;"));
+    assert_eq!(dkim_records[1].status, DkimKeyStatus::Retired);
+    assert_eq!(dkim_records[1].name, "initial._domainkey.rotate.org.");
+    assert!(dkim_records[1]
+        .expires
+        .is_some_and(|expires| expires.abs_diff(now() + 7 * 86400) < 5));
+
+    // Rotated and retired keys are included in the domain's DNS records
+    let dns_records = api
+        .get::<Vec<serde_json::Value>>("/api/dns/records/rotate.org")
+        .await
+        .unwrap()
+        .unwrap_data();
+    for record in &dkim_records {
+        assert!(
+            dns_records
+                .iter()
+                .any(|r| r["name"] == record.name.as_str()
+                    && r["content"] == record.content.as_str()),
+            "{dns_records:?}"
+        );
+    }
+
+    // Retired keys are removed once the overlap period ends
+    config
+        .set([(
+            format!("signature.{SIGNATURE_ID}.rotation.retired.initial"),
+            format!("{}:{}", now() - 1, "abc"),
+        )])
+        .await
+        .unwrap();
+    let server = params.server.inner.build_server();
+    assert!(!server.dkim_rotate(SIGNATURE_ID, false).await.unwrap());
+    assert_eq!(
+        records(&api).await,
+        vec![(next_selector.clone(), DkimKeyStatus::Active)]
+    );
+
+    // Keys can be rotated on demand
+    let dkim_records = api
+        .post::<Vec<DkimRecord>>(&format!("/api/dkim/{SIGNATURE_ID}/rotate"), &())
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(dkim_records.len(), 2, "{dkim_records:?}");
+    assert_eq!(dkim_records[0].status, DkimKeyStatus::Active);
+    assert_ne!(
+        dkim_records[0].name,
+        format!("{next_selector}._domainkey.rotate.org.")
+    );
+    assert_eq!(dkim_records[1].status, DkimKeyStatus::Retired);
+    assert_eq!(
+        dkim_records[1].name,
+        format!("{next_selector}._domainkey.rotate.org.")
+    );
+
+    // Remove test data
+    config
+        .clear_prefix(format!("signature.{SIGNATURE_ID}."))
+        .await
+        .unwrap();
+    params.server.dkim_reload().await.unwrap();
+}
+
+async fn records(api: &ManagementApi) -> Vec<(String, DkimKeyStatus)> {
+    api.get::<Vec<DkimRecord>>(&format!("/api/dkim/{SIGNATURE_ID}/records"))
+        .await
+        .unwrap()
+        .unwrap_data()
+        .into_iter()
+        .map(|record| {
+            (
+                record
+                    .name
+                    .strip_suffix("._domainkey.rotate.org.")
+                    .unwrap()
+                    .to_string(),
+                record.status,
+            )
+        })
+        .collect()
+}
+
+async fn set_last_rotation(server: &common::Server, days_ago: u64) {
+    server
+        .core
+        .storage
+        .config
+        .set([(
+            format!("signature.{SIGNATURE_ID}.rotation.last"),
+            (now() - days_ago * 86400).to_string(),
+        )])
+        .await
+        .unwrap();
+}
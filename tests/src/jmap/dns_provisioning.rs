@@ -0,0 +1,189 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::core::BuildServer;
+use directory::{backend::internal::PrincipalField, Principal, Type};
+use hyper::{Method, StatusCode};
+use jmap::api::{
+    management::dns::{DnsRecord, DnsRecordResult, DnsRecordStatus},
+    HttpResponse,
+};
+use mail_auth::MX;
+use store::parking_lot::Mutex;
+
+use crate::{
+    http_server::{spawn_mock_http_server, HttpMessage},
+    jmap::ManagementApi,
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running DNS provisioning tests...");
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let config = params.server.core.storage.config.clone();
+
+    // Spawn mock Route53 endpoint
+    let changes: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let changes_ = changes.clone();
+    let _tx = spawn_mock_http_server(Arc::new(move |req: HttpMessage| {
+        assert!(
+            req.headers
+                .get("authorization")
+                .is_some_and(|auth| auth.starts_with("AWS4-HMAC-SHA256 Credential=AKID/")),
+            "{req:?}"
+        );
+
+        match (&req.method, req.uri.path()) {
+            (&Method::GET, "/2013-04-01/hostedzonesbyname") => HttpResponse::new_text(
+                StatusCode::OK,
+                "text/xml",
+                concat!(
+                    "<ListHostedZonesByNameResponse><HostedZones><HostedZone>",
+                    "<Id>/hostedzone/Z1234</Id><Name>provision.org.</Name>",
+                    "</HostedZone></HostedZones></ListHostedZonesByNameResponse>"
+                ),
+            ),
+            (&Method::POST, "/2013-04-01/hostedzone/Z1234/rrset") => {
+                changes_
+                    .lock()
+                    .push(String::from_utf8(req.body.unwrap_or_default()).unwrap_or_default());
+                HttpResponse::new_text(
+                    StatusCode::OK,
+                    "text/xml",
+                    "<ChangeResourceRecordSetsResponse/>",
+                )
+            }
+            _ => HttpResponse::new_empty(StatusCode::NOT_FOUND),
+        }
+    }))
+    .await;
+
+    // Configure the provider and enable domain provisioning
+    config
+        .set([
+            ("dns-provider.r53.provider", "route53"),
+            ("dns-provider.r53.access-key", "AKID"),
+            ("dns-provider.r53.secret-key", "secret"),
+            ("dns-provider.r53.endpoint", "https://127.0.0.1:9090"),
+            ("dns-provider.r53.allow-invalid-certs", "true"),
+            ("dns-provisioning.provider", "r53"),
+            ("dns-provisioning.propagation-timeout", "1s"),
+            ("dns-provisioning.polling-interval", "100ms"),
+        ])
+        .await
+        .unwrap();
+    api.get::<serde_json::Value>("/api/reload")
+        .await
+        .unwrap()
+        .unwrap_data();
+
+    // Publish the records of an existing domain
+    let records = api
+        .get::<Vec<DnsRecord>>("/api/dns/records/provision.org")
+        .await
+        .unwrap()
+        .unwrap_data();
+    let results = api
+        .post::<Vec<DnsRecordResult>>("/api/dns/records/provision.org", &())
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(results.len(), records.len());
+    for result in &results {
+        assert_eq!(result.status, DnsRecordStatus::Published, "{result:?}");
+    }
+    let published = std::mem::take(&mut *changes.lock());
+    let mx = records.iter().find(|r| r.typ == "MX").unwrap();
+    let dmarc = records
+        .iter()
+        .find(|r| r.name == "_dmarc.provision.org.")
+        .unwrap();
+    for record in [mx, dmarc] {
+        assert!(
+            published.iter().any(|change| change.contains(&format!(
+                "<Name>{}</Name><Type>{}</Type><TTL>3600</TTL>",
+                record.name, record.typ
+            ))),
+            "{record:?} {published:?}"
+        );
+    }
+    assert!(published.iter().all(|change| change.contains("UPSERT")));
+
+    // Records are reported as propagated once they resolve
+    let (priority, exchange) = mx.content.split_once(' ').unwrap();
+    let server = params.server.inner.build_server();
+    server.core.smtp.resolvers.dns.mx_add(
+        "provision.org",
+        vec![MX {
+            exchanges: vec![exchange.trim_end_matches('.').to_string()],
+            preference: priority.parse().unwrap(),
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    let results = api
+        .get::<Vec<DnsRecordResult>>("/api/dns/verify/provision.org")
+        .await
+        .unwrap()
+        .unwrap_data();
+    for result in results {
+        match result.record.typ.as_str() {
+            "MX" => assert_eq!(result.status, DnsRecordStatus::Propagated),
+            "TXT" => assert_eq!(result.status, DnsRecordStatus::Pending),
+            _ => assert_eq!(result.status, DnsRecordStatus::Unverified),
+        }
+    }
+
+    // Records are published automatically when a domain is created
+    api.post::<u32>(
+        "/api/principal",
+        &Principal::new(u32::MAX, Type::Domain)
+            .with_field(PrincipalField::Name, "auto.provision.org"),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    let mut published = false;
+    for _ in 0..50 {
+        if changes
+            .lock()
+            .iter()
+            .any(|change| change.contains("<Name>_dmarc.auto.provision.org.</Name>"))
+        {
+            published = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(published, "{:?}", changes.lock());
+
+    // Unknown providers are rejected
+    assert_eq!(
+        api.post::<Vec<DnsRecordResult>>("/api/dns/records/provision.org?provider=unknown", &())
+            .await
+            .unwrap()
+            .unwrap_error()
+            .0,
+        "notFound"
+    );
+
+    // Remove test data
+    api.delete::<()>("/api/principal/auto.provision.org")
+        .await
+        .unwrap()
+        .unwrap_data();
+    config.clear_prefix("dns-provider.r53.").await.unwrap();
+    config.clear_prefix("dns-provisioning.").await.unwrap();
+    api.get::<serde_json::Value>("/api/reload")
+        .await
+        .unwrap()
+        .unwrap_data();
+}
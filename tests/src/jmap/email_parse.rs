@@ -6,6 +6,7 @@
 
 use std::{fs, path::PathBuf};
 
+use directory::backend::internal::manage::ManageDirectory;
 use jmap_client::{
     email::{self, Header, HeaderForm},
     mailbox::Role,
@@ -116,29 +117,27 @@ pub async fn test(params: &mut JMAPTest) {
             .await
             .unwrap();
 
-        if !test_name.contains("_b64") {
-            for parts in [
-                email.text_body().unwrap(),
-                email.html_body().unwrap(),
-                email.attachments().unwrap(),
-            ] {
-                for part in parts {
-                    let blob_id = part.blob_id().unwrap();
-
-                    let inner_blob = params.client.download(blob_id).await.unwrap();
-
-                    test_file.set_extension(format!("part{}", part.part_id().unwrap()));
-
-                    //fs::write(&test_file, inner_blob).unwrap();
-                    let expected_inner_blob = fs::read(&test_file).unwrap();
-
-                    assert_eq!(
-                        inner_blob,
-                        expected_inner_blob,
-                        "file: {}",
-                        test_file.display()
-                    );
-                }
+        for parts in [
+            email.text_body().unwrap(),
+            email.html_body().unwrap(),
+            email.attachments().unwrap(),
+        ] {
+            for part in parts {
+                let blob_id = part.blob_id().unwrap();
+
+                let inner_blob = params.client.download(blob_id).await.unwrap();
+
+                test_file.set_extension(format!("part{}", part.part_id().unwrap()));
+
+                //fs::write(&test_file, inner_blob).unwrap();
+                let expected_inner_blob = fs::read(&test_file).unwrap();
+
+                assert_eq!(
+                    inner_blob,
+                    expected_inner_blob,
+                    "file: {}",
+                    test_file.display()
+                );
             }
         }
 
@@ -153,6 +152,82 @@ pub async fn test(params: &mut JMAPTest) {
         }
     }
 
+    // Test parsing messages nested inside a base64 encoded digest attachment
+    let mut test_file = test_dir.clone();
+    test_file.push("digest.eml");
+    let email = params
+        .client
+        .email_import(
+            fs::read(&test_file).unwrap(),
+            [mailbox_id.clone()],
+            None::<Vec<String>>,
+            None,
+        )
+        .await
+        .unwrap();
+    let digest_blob_id = params
+        .client
+        .email_get(email.id().unwrap(), Some([email::Property::Attachments]))
+        .await
+        .unwrap()
+        .unwrap()
+        .attachments()
+        .unwrap()
+        .first()
+        .unwrap()
+        .blob_id()
+        .unwrap()
+        .to_string();
+    let admin_id = params
+        .server
+        .store()
+        .get_principal_id("admin")
+        .await
+        .unwrap()
+        .unwrap();
+    let prev_quota = params.server.store().blob_quota(admin_id).await.unwrap();
+    let digest = params
+        .client
+        .email_parse(
+            &digest_blob_id,
+            [email::Property::Subject, email::Property::Attachments].into(),
+            [email::BodyProperty::BlobId, email::BodyProperty::Type].into(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(digest.subject(), Some("Weekly digest"));
+
+    // The decoded digest is stored as a temporary blob charged to the upload quota
+    let quota = params.server.store().blob_quota(admin_id).await.unwrap();
+    assert_eq!(quota.count, prev_quota.count + 1);
+    assert!(quota.bytes > prev_quota.bytes);
+    let posts = digest.attachments().unwrap();
+    assert_eq!(posts.len(), 2);
+    for (post, (subject, text)) in posts.iter().zip([
+        ("First post", "Hello from the first post."),
+        ("Second post", "Hello from the second post."),
+    ]) {
+        assert_eq!(post.content_type(), Some("message/rfc822"));
+        let post = params
+            .client
+            .email_parse(
+                post.blob_id().unwrap(),
+                [email::Property::Subject, email::Property::TextBody].into(),
+                [email::BodyProperty::BlobId].into(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(post.subject(), Some(subject));
+        let text_blob = params
+            .client
+            .download(post.text_body().unwrap()[0].blob_id().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8(text_blob).unwrap().trim(), text);
+    }
+
     // Test header parsing on a temporary blob
     let mut test_file = test_dir;
     test_file.push("headers.eml");
@@ -0,0 +1,125 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine};
+use jmap::email::pdf::render_text_pdf;
+use jmap_client::mailbox::Role;
+use jmap_proto::types::id::Id;
+use mail_parser::MessageParser;
+use reqwest::header;
+
+use crate::jmap::{assert_is_empty, mailbox::destroy_all_mailboxes};
+
+use super::JMAPTest;
+
+const MESSAGE: &str = concat!(
+    "From: Alice <alice@example.com>\r\n",
+    "To: Bob <bob@example.com>\r\n",
+    "Subject: Quarterly (draft) report\r\n",
+    "Content-Type: multipart/mixed; boundary=\"x\"\r\n\r\n",
+    "--x\r\n",
+    "Content-Type: text/html\r\n\r\n",
+    "<p>Quarterly <b>numbers</b> for caf\u{e9}</p><script>alert(1)</script>\r\n",
+    "--x\r\n",
+    "Content-Type: text/plain\r\n",
+    "Content-Disposition: attachment; filename=\"numbers.txt\"\r\n\r\n",
+    "1, 2, 3\r\n",
+    "--x--\r\n"
+);
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email PDF export tests...");
+    let server = params.server.clone();
+    let account_id = Id::new(1).to_string();
+
+    // Builtin renderer
+    let pdf = render_text_pdf(&MessageParser::new().parse(MESSAGE).unwrap());
+    let pdf = String::from_utf8_lossy(&pdf);
+    assert!(pdf.starts_with("%PDF-1.4"), "{pdf}");
+    assert!(pdf.trim_end().ends_with("%%EOF"), "{pdf}");
+    assert!(
+        pdf.contains("(Subject: Quarterly \\(draft\\) report) Tj"),
+        "{pdf}"
+    );
+    assert!(pdf.contains("caf\\351"), "{pdf}");
+    assert!(pdf.contains("- numbers.txt ("), "{pdf}");
+    assert!(!pdf.contains("<script>"), "{pdf}");
+
+    // Long lines are wrapped across lines and pages
+    let long_message = format!("Subject: long\r\n\r\n{}", "word ".repeat(10000).trim_end());
+    let pdf = render_text_pdf(&MessageParser::new().parse(&long_message).unwrap());
+    let pdf = String::from_utf8_lossy(&pdf);
+    assert!(pdf.contains("/Count 10 "), "{pdf}");
+
+    // Export a stored message
+    let mailbox_id = params
+        .client
+        .set_default_account_id(&account_id)
+        .mailbox_create("JMAP PDF", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+    let email_id = params
+        .client
+        .email_import(
+            MESSAGE.as_bytes().to_vec(),
+            [mailbox_id.clone()],
+            None::<Vec<String>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let (status, content_type, pdf) =
+        fetch_pdf(&format!("{account_id}/{email_id}"), "admin", "secret").await;
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "application/pdf");
+    assert!(pdf.starts_with(b"%PDF-1.4"));
+
+    // Unknown messages are rejected
+    let unknown_id = Id::new(u32::MAX as u64).to_string();
+    assert_eq!(
+        fetch_pdf(&format!("{account_id}/{unknown_id}"), "admin", "secret")
+            .await
+            .0,
+        404
+    );
+
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn fetch_pdf(path: &str, username: &str, secret: &str) -> (u16, String, Vec<u8>) {
+    let response = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .timeout(Duration::from_millis(1000))
+        .build()
+        .unwrap()
+        .get(format!("https://127.0.0.1:8899/jmap/pdf/{path}"))
+        .header(
+            header::AUTHORIZATION,
+            format!(
+                "Basic {}",
+                general_purpose::STANDARD.encode(format!("{username}:{secret}"))
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    (
+        response.status().as_u16(),
+        response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+        response.bytes().await.unwrap_or_default().to_vec(),
+    )
+}
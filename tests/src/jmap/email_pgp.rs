@@ -0,0 +1,221 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use jmap::mailbox::INBOX_ID;
+use jmap_proto::types::id::Id;
+use serde_json::json;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, jmap_raw_request, mailbox::destroy_all_mailboxes, wait_for_index,
+        ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+const JANE_KEY: &str = "AFF758D4B59E467B05CB1CC68988D94A056D8A34";
+const ROGUE_KEY: &str = "F5B39001825BF9C2B77B254B14EA68EA1FAE5FDC";
+const ESCROW_KEY: &str = "A262161A40D22A978FF34F16EF06747D135F6C17";
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running OpenPGP verification tests...");
+    let server = params.server.clone();
+    let account_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .create_test_user(
+                "pgp@example.com",
+                "secret",
+                "PGP Test",
+                &["pgp@example.com"],
+            )
+            .await,
+    )
+    .to_string();
+    params.client.set_default_account_id(&account_id);
+    let mailbox_id = Id::from(INBOX_ID).to_string();
+    let api = ManagementApi::new(8899, "pgp@example.com", "secret");
+
+    let mut test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_dir.push("resources");
+    test_dir.push("jmap");
+    test_dir.push("email_pgp");
+
+    // Signatures are verified against the keyring or the Autocrypt key
+    for (file_name, expected) in [
+        (
+            "signed.eml",
+            vec!["pgp=pass", "header.from=jane@example.com", JANE_KEY],
+        ),
+        ("clearsigned.eml", vec!["pgp=pass", JANE_KEY]),
+        ("autocrypt.eml", vec!["pgp=pass", ROGUE_KEY]),
+        ("tampered.eml", vec!["pgp=fail"]),
+        ("forged.eml", vec!["pgp=fail", "does not match the From"]),
+        ("unknown.eml", vec!["pgp=neutral", "No public key"]),
+    ] {
+        let email_id = import(params, &test_dir, file_name, &mailbox_id).await;
+        let results = authentication_results(&account_id, &email_id).await;
+        for expected in expected {
+            assert!(results.contains(expected), "{file_name}: {results}");
+        }
+        assert!(!results.contains("pgp-decrypt"), "{file_name}: {results}");
+    }
+
+    // Encrypted messages are left untouched without an escrowed key
+    let email_id = import(params, &test_dir, "encrypted.eml", &mailbox_id).await;
+    assert_eq!(
+        authentication_results(&account_id, &email_id).await,
+        serde_json::Value::Null.to_string()
+    );
+    wait_for_index(&server).await;
+    assert!(query_text(&account_id, "Bluebird").await.is_empty());
+
+    // Invalid keys are rejected
+    api.post::<String>("/api/account/pgp-escrow", &json!({"key": "not a key"}))
+        .await
+        .unwrap()
+        .expect_error("Failed to parse key");
+    assert_eq!(
+        api.get::<Option<String>>("/api/account/pgp-escrow")
+            .await
+            .unwrap()
+            .unwrap_data(),
+        None
+    );
+
+    // Escrow the account's secret key
+    let key = fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources")
+            .join("crypto")
+            .join("pgp_escrow.asc"),
+    )
+    .unwrap();
+    assert_eq!(
+        api.post::<String>("/api/account/pgp-escrow", &json!({"key": key}))
+            .await
+            .unwrap()
+            .unwrap_data(),
+        ESCROW_KEY
+    );
+    assert_eq!(
+        api.get::<Option<String>>("/api/account/pgp-escrow")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .as_deref(),
+        Some(ESCROW_KEY)
+    );
+
+    // Encrypted messages are now decrypted and searchable
+    for (file_name, search_term, expected) in [
+        (
+            "encrypted.eml",
+            "Bluebird",
+            vec!["pgp-decrypt=pass", "pgp=pass", JANE_KEY],
+        ),
+        (
+            "inline_encrypted.eml",
+            "Kingfisher",
+            vec!["pgp-decrypt=pass"],
+        ),
+    ] {
+        let email_id = import(params, &test_dir, file_name, &mailbox_id).await;
+        let results = authentication_results(&account_id, &email_id).await;
+        for expected in expected {
+            assert!(results.contains(expected), "{file_name}: {results}");
+        }
+
+        wait_for_index(&server).await;
+        assert_eq!(
+            query_text(&account_id, search_term).await,
+            vec![email_id],
+            "{file_name}"
+        );
+    }
+
+    // Remove the escrowed key
+    api.delete::<()>("/api/account/pgp-escrow")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(
+        api.get::<Option<String>>("/api/account/pgp-escrow")
+            .await
+            .unwrap()
+            .unwrap_data(),
+        None
+    );
+
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn import(
+    params: &mut JMAPTest,
+    test_dir: &Path,
+    file_name: &str,
+    mailbox_id: &str,
+) -> String {
+    params
+        .client
+        .email_import(
+            fs::read(test_dir.join(file_name)).unwrap(),
+            [mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id()
+}
+
+async fn authentication_results(account_id: &str, email_id: &str) -> String {
+    let response = jmap_raw_request(
+        r#"[[ "Email/get", {
+            "accountId": "$$",
+            "ids": [ "%%" ],
+            "properties": [ "header:Authentication-Results:asText" ]
+          }, "0" ]]"#
+            .replace("$$", account_id)
+            .replace("%%", email_id),
+        "pgp@example.com",
+        "secret",
+    )
+    .await;
+    let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+    response["methodResponses"][0][1]["list"][0]["header:Authentication-Results:asText"].to_string()
+}
+
+async fn query_text(account_id: &str, text: &str) -> Vec<String> {
+    let response = jmap_raw_request(
+        r#"[[ "Email/query", {
+            "accountId": "$$",
+            "filter": { "text": "%%" }
+          }, "0" ]]"#
+            .replace("$$", account_id)
+            .replace("%%", text),
+        "pgp@example.com",
+        "secret",
+    )
+    .await;
+    let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+    response["methodResponses"][0][1]["ids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|id| id.as_str().unwrap().to_string())
+        .collect()
+}
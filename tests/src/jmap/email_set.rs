@@ -4,11 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{fs, path::PathBuf};
+use std::{fs, path::PathBuf, time::Duration};
 
 use crate::jmap::{assert_is_empty, mailbox::destroy_all_mailboxes};
 use ahash::AHashSet;
-use jmap::mailbox::INBOX_ID;
+use common::{scripts::plugins::bayes::bayes_token_key, Server};
+use jmap::mailbox::{INBOX_ID, JUNK_ID};
 use jmap_client::{
     client::Client,
     core::set::{SetError, SetErrorType},
@@ -17,6 +18,7 @@ use jmap_client::{
     Error, Set,
 };
 use jmap_proto::types::id::Id;
+use nlp::bayes::{TokenHash, Weights};
 
 use super::{find_values, replace_blob_ids, replace_boundaries, replace_values, JMAPTest};
 
@@ -29,6 +31,7 @@ pub async fn test(params: &mut JMAPTest) {
 
     create(&mut params.client, &mailbox_id).await;
     update(&mut params.client, &mailbox_id).await;
+    spam_training(&mut params.client, &server, &mailbox_id).await;
 
     destroy_all_mailboxes(params).await;
     assert_is_empty(server).await;
@@ -295,6 +298,62 @@ async fn update(client: &mut Client, root_mailbox_id: &str) {
         .unwrap();
 }
 
+async fn spam_training(client: &mut Client, server: &Server, inbox_id: &str) {
+    let junk_id = Id::from(JUNK_ID).to_string();
+    let email_id = client
+        .email_import(
+            concat!(
+                "From: promo@example.com\r\n",
+                "Subject: Cheap watches for sale\r\n\r\n",
+                "Buy cheap replica watches today, limited time offer!\r\n"
+            )
+            .as_bytes()
+            .to_vec(),
+            [inbox_id],
+            None::<Vec<String>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+
+    // Moving a message to Junk trains it as spam
+    client
+        .email_set_mailboxes(&email_id, [&junk_id])
+        .await
+        .unwrap();
+    assert_eq!(
+        account_training_counts(server, 1).await,
+        Weights { spam: 1, ham: 0 }
+    );
+
+    // Moving it out of Junk trains it as ham
+    client
+        .email_set_mailboxes(&email_id, [inbox_id])
+        .await
+        .unwrap();
+    assert_eq!(
+        account_training_counts(server, 1).await,
+        Weights { spam: 1, ham: 1 }
+    );
+
+    client.email_destroy(&email_id).await.unwrap();
+}
+
+async fn account_training_counts(server: &Server, account_id: u32) -> Weights {
+    // Training runs in the background
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    Weights::from(
+        server
+            .core
+            .storage
+            .lookup
+            .counter_get(bayes_token_key(Some(account_id), &TokenHash::default()))
+            .await
+            .unwrap(),
+    )
+}
+
 pub async fn assert_email_properties(
     client: &mut Client,
     message_id: &str,
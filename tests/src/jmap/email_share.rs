@@ -0,0 +1,255 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use jmap_client::{mailbox::Role, principal::ACL};
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, emails_purge_tombstoned, jmap_json_request, mailbox::destroy_all_mailboxes,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email Share tests...");
+    let server = params.server.clone();
+    let account_id = Id::new(1).to_string();
+    let unknown_id = Id::new(u32::MAX as u64).to_string();
+
+    let mailbox_id = params
+        .client
+        .set_default_account_id(&account_id)
+        .mailbox_create("JMAP Share", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+    let email_id = params
+        .client
+        .email_import(
+            concat!(
+                "From: Alice <alice@example.com>\r\n",
+                "To: Bob <bob@example.com>\r\n",
+                "Subject: <script>alert(1)</script> report\r\n",
+                "Content-Type: multipart/mixed; boundary=\"x\"\r\n\r\n",
+                "--x\r\n",
+                "Content-Type: text/html\r\n\r\n",
+                "<p>Quarterly <b>numbers</b></p><script>alert(2)</script>\r\n",
+                "--x\r\n",
+                "Content-Type: text/plain\r\n",
+                "Content-Disposition: attachment; filename=\"numbers.txt\"\r\n\r\n",
+                "1, 2, 3\r\n",
+                "--x--\r\n"
+            )
+            .as_bytes()
+            .to_vec(),
+            [mailbox_id.clone()],
+            None::<Vec<String>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+
+    // Create a share link
+    let response = jmap_json_request(
+        format!(
+            r#"[[ "Email/share", {{
+                "accountId": "{account_id}",
+                "emailIds": ["{email_id}", "{unknown_id}"],
+                "expiresIn": 3600
+            }}, "0" ]]"#
+        ),
+        "admin",
+        "secret",
+    )
+    .await;
+    let result = &response["methodResponses"][0][1];
+    assert_eq!(
+        result["notFound"].as_array().unwrap().len(),
+        1,
+        "{response}"
+    );
+    let link = &result["created"][&email_id];
+    let link_id = link["id"].as_str().unwrap().to_string();
+    let url = link["url"].as_str().unwrap().to_string();
+    assert!(url.contains("/jmap/share/"), "{url}");
+    let url = format!(
+        "https://127.0.0.1:8899/jmap/share/{}",
+        url.rsplit_once('/').unwrap().1
+    );
+
+    // Tampered tokens are rejected
+    let mut tampered = url.clone();
+    let last = if tampered.ends_with('0') { '1' } else { '0' };
+    tampered.pop();
+    tampered.push(last);
+    assert_eq!(fetch(&tampered).await.0, 404);
+
+    // Attachments cannot be downloaded before the message is viewed
+    assert_eq!(fetch(&format!("{url}/0")).await.0, 404);
+
+    // Fetch the shared message
+    let (status, html) = fetch(&url).await;
+    assert_eq!(status, 200);
+    assert!(
+        html.contains("&lt;script&gt;alert(1)&lt;/script&gt; report"),
+        "{html}"
+    );
+    assert!(html.contains("Quarterly"), "{html}");
+    assert!(!html.contains("<script>"), "{html}");
+    assert!(html.contains("numbers.txt"), "{html}");
+
+    // Links are one-shot, a second view is rejected
+    assert_eq!(fetch(&url).await.0, 404);
+
+    // Download the attachment using the view id of the rendered page
+    let attachment_url = html
+        .split_once("<a href=\"")
+        .and_then(|(_, href)| href.split_once('"'))
+        .map(|(href, _)| format!("https://127.0.0.1:8899/jmap/share/{href}"))
+        .unwrap_or_else(|| panic!("{html}"));
+    let (status, attachment) = fetch(&attachment_url).await;
+    assert_eq!(status, 200);
+    assert_eq!(attachment.trim(), "1, 2, 3");
+    assert_eq!(
+        fetch(&format!("{}1", attachment_url.trim_end_matches('0')))
+            .await
+            .0,
+        404
+    );
+    assert_eq!(fetch(&format!("{url}/{}/0", "x".repeat(16))).await.0, 404);
+
+    // Revoke the link
+    let response = jmap_json_request(
+        format!(
+            r#"[[ "Email/share", {{
+                "accountId": "{account_id}",
+                "revoke": ["{link_id}", "unknown"]
+            }}, "0" ]]"#
+        ),
+        "admin",
+        "secret",
+    )
+    .await;
+    let result = &response["methodResponses"][0][1];
+    assert_eq!(result["revoked"][0].as_str(), Some(link_id.as_str()));
+    assert_eq!(result["notRevoked"][0].as_str(), Some("unknown"));
+    assert_eq!(fetch(&url).await.0, 404);
+
+    // Users with shared access can only revoke the links they created
+    server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jshare@example.com",
+            "secret",
+            "J Share",
+            &["jshare@example.com"],
+        )
+        .await;
+    params
+        .client
+        .mailbox_update_acl(
+            &mailbox_id,
+            "jshare@example.com",
+            [ACL::Read, ACL::ReadItems],
+        )
+        .await
+        .unwrap();
+    let (admin_link_id, admin_url) = create_link(&account_id, &email_id, "admin", "secret").await;
+    let (user_link_id, _) =
+        create_link(&account_id, &email_id, "jshare@example.com", "secret").await;
+    let response = jmap_json_request(
+        format!(
+            r#"[[ "Email/share", {{
+                "accountId": "{account_id}",
+                "revoke": ["{admin_link_id}", "{user_link_id}"]
+            }}, "0" ]]"#
+        ),
+        "jshare@example.com",
+        "secret",
+    )
+    .await;
+    let result = &response["methodResponses"][0][1];
+    assert_eq!(
+        result["revoked"][0].as_str(),
+        Some(user_link_id.as_str()),
+        "{response}"
+    );
+    assert_eq!(
+        result["notRevoked"][0].as_str(),
+        Some(admin_link_id.as_str()),
+        "{response}"
+    );
+    assert_eq!(fetch(&admin_url).await.0, 200);
+
+    // Links are deleted along with the message
+    params.client.email_destroy(&email_id).await.unwrap();
+    emails_purge_tombstoned(&server).await;
+    assert!(!server
+        .core
+        .storage
+        .lookup
+        .key_exists(format!("share:{admin_link_id}").into_bytes())
+        .await
+        .unwrap());
+    assert_eq!(fetch(&admin_url).await.0, 404);
+
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn create_link(
+    account_id: &str,
+    email_id: &str,
+    login: &str,
+    secret: &str,
+) -> (String, String) {
+    let response = jmap_json_request(
+        format!(
+            r#"[[ "Email/share", {{
+                "accountId": "{account_id}",
+                "emailIds": ["{email_id}"]
+            }}, "0" ]]"#
+        ),
+        login,
+        secret,
+    )
+    .await;
+    let link = &response["methodResponses"][0][1]["created"][email_id];
+    let url = link["url"].as_str().unwrap_or_else(|| panic!("{response}"));
+
+    (
+        link["id"].as_str().unwrap().to_string(),
+        format!(
+            "https://127.0.0.1:8899/jmap/share/{}",
+            url.rsplit_once('/').unwrap().1
+        ),
+    )
+}
+
+async fn fetch(url: &str) -> (u16, String) {
+    let response = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .timeout(Duration::from_millis(1000))
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap();
+
+    (
+        response.status().as_u16(),
+        response.text().await.unwrap_or_default(),
+    )
+}
@@ -0,0 +1,149 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fs, path::PathBuf};
+
+use jmap::mailbox::INBOX_ID;
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, jmap_raw_request, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running S/MIME verification tests...");
+    let server = params.server.clone();
+    let account_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .create_test_user(
+                "smime@example.com",
+                "secret",
+                "S/MIME Test",
+                &["smime@example.com"],
+            )
+            .await,
+    )
+    .to_string();
+    params.client.set_default_account_id(&account_id);
+    let mailbox_id = Id::from(INBOX_ID).to_string();
+
+    let mut test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_dir.push("resources");
+    test_dir.push("jmap");
+    test_dir.push("email_smime");
+
+    for (file_name, expected_status, expected_error) in [
+        ("signed.eml", "signed/verified", None),
+        ("opaque.eml", "signed/verified", None),
+        (
+            "tampered.eml",
+            "signed/failed",
+            Some("message was modified"),
+        ),
+        (
+            "untrusted.eml",
+            "signed/failed",
+            Some("not issued by a trusted authority"),
+        ),
+        (
+            "forged.eml",
+            "signed/failed",
+            Some("does not match the From"),
+        ),
+        ("encrypted.eml", "encrypted", None),
+    ] {
+        let email_id = params
+            .client
+            .email_import(
+                fs::read(test_dir.join(file_name)).unwrap(),
+                [&mailbox_id],
+                None::<Vec<&str>>,
+                None,
+            )
+            .await
+            .unwrap()
+            .take_id();
+
+        let response = jmap_raw_request(
+            r#"[[ "Email/get", {
+                "accountId": "$$",
+                "ids": [ "%%" ],
+                "properties": [ "smimeStatus", "smimeErrors", "smimeVerifiedAt" ]
+              }, "0" ]]"#
+                .replace("$$", &account_id)
+                .replace("%%", &email_id),
+            "smime@example.com",
+            "secret",
+        )
+        .await;
+        let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+        let email = &response["methodResponses"][0][1]["list"][0];
+        assert_eq!(
+            email["smimeStatus"], expected_status,
+            "{file_name}: {email}"
+        );
+        assert!(email["smimeVerifiedAt"].is_string(), "{file_name}: {email}");
+        if let Some(expected_error) = expected_error {
+            assert!(
+                email["smimeErrors"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .any(|err| err.as_str().unwrap().contains(expected_error)),
+                "{file_name}: {email}"
+            );
+        } else {
+            assert!(email["smimeErrors"].is_null(), "{file_name}: {email}");
+        }
+    }
+
+    // Messages without S/MIME have no status
+    let email_id = params
+        .client
+        .email_import(
+            concat!(
+                "From: jane@example.com\r\n",
+                "To: smime@example.com\r\n",
+                "Subject: Plain report\r\n",
+                "\r\n",
+                "The quarterly report numbers are final."
+            )
+            .as_bytes()
+            .to_vec(),
+            [&mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let response = jmap_raw_request(
+        r#"[[ "Email/get", {
+            "accountId": "$$",
+            "ids": [ "%%" ],
+            "properties": [ "smimeStatus", "smimeErrors", "smimeVerifiedAt" ]
+          }, "0" ]]"#
+            .replace("$$", &account_id)
+            .replace("%%", &email_id),
+        "smime@example.com",
+        "secret",
+    )
+    .await;
+    let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+    let email = &response["methodResponses"][0][1]["list"][0];
+    for property in ["smimeStatus", "smimeErrors", "smimeVerifiedAt"] {
+        assert!(email[property].is_null(), "{email}");
+    }
+
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -19,10 +19,11 @@ use common::{
     },
     telemetry::{
         metrics::store::{Metric, MetricsStore, SharedMetricHistory},
-        tracers::store::{TracingQuery, TracingStore},
+        tracers::store::{EventQuery, TracingQuery, TracingStore},
     },
     Core, Server,
 };
+use directory::backend::internal::manage::ManageDirectory;
 use imap_proto::ResponseType;
 use jmap::api::management::enterprise::undelete::{UndeleteRequest, UndeleteResponse};
 use store::{
@@ -108,6 +109,7 @@ pub async fn test(params: &mut JMAPTest) {
         metrics_alerts: parse_metric_alerts(&mut config),
         logo_url: None,
         ai_apis: Default::default(),
+        encryption_escrow: Default::default(),
     }
     .into();
     config.assert_no_errors();
@@ -173,6 +175,7 @@ impl EnterpriseCore for Core {
             metrics_alerts: vec![],
             logo_url: None,
             ai_apis: Default::default(),
+            encryption_escrow: Default::default(),
         }
         .into();
         self
@@ -300,6 +303,67 @@ async fn tracing(params: &mut JMAPTest) {
         assert!(spans[0] > spans[1], "keyword: {keyword}");
     }
 
+    // Search individual events
+    let (events, cursor) = store
+        .query_events(
+            &EventQuery {
+                types: vec![EventType::Smtp(SmtpEvent::RcptTo)],
+                keywords: vec!["jdoe@example.com".to_string()],
+                ..Default::default()
+            },
+            0,
+            0,
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].inner.typ, EventType::Smtp(SmtpEvent::RcptTo));
+    assert_eq!(cursor, None);
+
+    // Filter events by account
+    let account_id = store
+        .get_principal_id("jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    for (account, expect_match) in [(account_id.to_string(), true), ("12345".to_string(), false)] {
+        let (events, _) = store
+            .query_events(
+                &EventQuery {
+                    accounts: vec![account],
+                    ..Default::default()
+                },
+                0,
+                0,
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            events
+                .iter()
+                .any(|event| matches!(event.inner.typ, EventType::MessageIngest(_))),
+            expect_match
+        );
+    }
+
+    // Paginate over all events
+    let (events, first_page) = store
+        .query_events(&EventQuery::default(), 0, 0, None, 1)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    let (events, second_page) = store
+        .query_events(&EventQuery::default(), 0, 0, first_page, 1)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_ne!(first_page, second_page);
+    assert!(second_page.is_some());
+
     // Purge should delete the span entries
     tokio::time::sleep(Duration::from_millis(800)).await;
     store.purge_spans(Duration::from_secs(1)).await.unwrap();
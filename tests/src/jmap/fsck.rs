@@ -0,0 +1,124 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap::{services::fsck::FsckReport, JmapMethods};
+use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
+use store::write::{BatchBuilder, DirectoryClass};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, wait_for_index,
+        ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running consistency check tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jfsck@example.com",
+            "12345",
+            "Jane Fsck",
+            &["jfsck@example.com"],
+        )
+        .await;
+
+    let mut lmtp = SmtpConnection::connect().await;
+    for num in 0..2 {
+        lmtp.ingest(
+            "bill@example.com",
+            &["jfsck@example.com"],
+            &format!(
+                concat!(
+                    "From: bill@example.com\r\n",
+                    "To: jfsck@example.com\r\n",
+                    "Subject: TPS Report #{}\r\n",
+                    "\r\n",
+                    "I'm going to need those TPS reports ASAP."
+                ),
+                num,
+            ),
+        )
+        .await;
+    }
+    wait_for_index(&server).await;
+
+    // A freshly populated account is consistent
+    let report = api
+        .get::<FsckReport>("/api/store/fsck/jfsck@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!(report.account_id, account_id);
+    assert!(report.used_quota > 0);
+
+    // Corrupt the account
+    let document_ids = server
+        .get_document_ids(account_id, Collection::Email)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(document_ids.len(), 2);
+    let mut document_ids = document_ids.into_iter();
+    let missing_id = document_ids.next().unwrap();
+    let tagged_id = document_ids.next().unwrap();
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::Email)
+        .delete_document(missing_id)
+        .update_document(tagged_id)
+        .tag(Property::Keywords, Keyword::Flagged, 0)
+        .with_collection(Collection::Thread)
+        .create_document_with_id(1000)
+        .add(DirectoryClass::UsedQuota(account_id), 100);
+    server.core.storage.data.write(batch.build()).await.unwrap();
+
+    // Inconsistencies are reported and then repaired
+    for repair in [false, true] {
+        let report = api
+            .get::<FsckReport>(&format!(
+                "/api/store/fsck/jfsck@example.com?repair={repair}"
+            ))
+            .await
+            .unwrap()
+            .unwrap_data();
+        assert!(!report.is_consistent());
+        assert_eq!(report.missing_document_ids, vec![missing_id]);
+        assert!(report.orphan_document_ids.is_empty());
+        assert_eq!(report.tags.len(), 1, "{report:?}");
+        assert_eq!(report.tags[0].property, "keywords");
+        assert_eq!(report.tags[0].value, "$flagged");
+        assert!(report.tags[0].missing.is_empty());
+        assert_eq!(report.tags[0].stale, vec![tagged_id]);
+        assert!(report.missing_threads.is_empty());
+        assert_eq!(report.orphan_threads, vec![1000]);
+        assert_eq!(report.used_quota, report.expected_quota + 100);
+    }
+    let report = api
+        .get::<FsckReport>("/api/store/fsck/jfsck@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(report.is_consistent(), "{report:?}");
+
+    // Remove test data
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
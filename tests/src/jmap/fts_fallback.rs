@@ -0,0 +1,150 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap::{email::fallback::EmailFtsFallback, JmapMethods};
+use jmap_proto::types::{collection::Collection, id::Id};
+use mail_parser::HeaderName;
+use nlp::language::Language;
+use store::fts::{Field, FtsFilter};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, wait_for_index,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running full-text fallback tests...");
+    let server = params.server.clone();
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jfallback@example.com",
+            "12345",
+            "Jane Fallback",
+            &["jfallback@example.com"],
+        )
+        .await;
+
+    let mut lmtp = SmtpConnection::connect().await;
+    for (num, (from, subject, body)) in [
+        ("bill@example.com", "TPS Report", "Need those reports."),
+        ("milton@example.com", "Red stapler", "Seen my stapler?"),
+        ("bill@example.com", "Saturday", "Need you to come in."),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        lmtp.ingest(
+            from,
+            &["jfallback@example.com"],
+            &format!(
+                concat!(
+                    "From: {}\r\n",
+                    "To: jfallback@example.com\r\n",
+                    "Subject: {}\r\n",
+                    "Message-ID: <msg{}@example.com>\r\n",
+                    "\r\n",
+                    "{}"
+                ),
+                from, subject, num, body
+            ),
+        )
+        .await;
+    }
+    wait_for_index(&server).await;
+
+    // Metadata searches return the same results as the full-text index
+    for filters in [
+        vec![FtsFilter::has_text(
+            Field::Header(HeaderName::From),
+            "bill",
+            Language::None,
+        )],
+        vec![FtsFilter::has_english_text(
+            Field::Header(HeaderName::Subject),
+            "stapler",
+        )],
+        vec![FtsFilter::has_english_text(
+            Field::<HeaderName>::Body,
+            "need",
+        )],
+        vec![FtsFilter::has_keyword(
+            Field::Header(HeaderName::MessageId),
+            "msg1@example.com",
+        )],
+        vec![FtsFilter::has_keyword(Field::Keyword, "subject")],
+        vec![
+            FtsFilter::Or,
+            FtsFilter::has_english_text(Field::Header(HeaderName::Subject), "saturday"),
+            FtsFilter::has_english_text(Field::Header(HeaderName::Subject), "stapler"),
+            FtsFilter::End,
+        ],
+        vec![
+            FtsFilter::has_english_text(Field::<HeaderName>::Body, "need"),
+            FtsFilter::Not,
+            FtsFilter::has_english_text(Field::Header(HeaderName::Subject), "report"),
+            FtsFilter::End,
+        ],
+    ] {
+        let expected = server
+            .fts_filter(account_id, Collection::Email, filters.clone())
+            .await
+            .unwrap();
+        assert!(!expected.degraded);
+        assert!(!expected.documents.is_empty(), "{filters:?}");
+        assert_eq!(
+            server
+                .fts_fallback_filter(account_id, &filters)
+                .await
+                .unwrap(),
+            expected.documents,
+            "{filters:?}"
+        );
+    }
+
+    // Attachments are not available in metadata
+    assert!(server
+        .fts_fallback_filter(
+            account_id,
+            &[FtsFilter::has_english_text(
+                Field::<HeaderName>::Attachment,
+                "stapler"
+            )]
+        )
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Degradation is cleared once the backend is available again
+    server.set_fts_degraded(true);
+    assert!(server.is_fts_degraded());
+    server
+        .fts_filter(
+            account_id,
+            Collection::Email,
+            vec![FtsFilter::has_english_text(
+                Field::<HeaderName>::Body,
+                "need",
+            )],
+        )
+        .await
+        .unwrap();
+    assert!(!server.is_fts_degraded());
+
+    // Remove test data
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -0,0 +1,102 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::listener::health::HealthReport;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::JMAPTest;
+
+pub async fn test(_params: &JMAPTest) {
+    println!("Running health check tests...");
+
+    // Agent check without a service name checks all components
+    assert_eq!(agent_check(None).await, "up ready\n");
+    assert_eq!(agent_check(Some("imap")).await, "up ready\n");
+    assert_eq!(agent_check(Some("jmap")).await, "up ready\n");
+
+    // Readiness reports the status of each component
+    let (status, report) = fetch_health("ready").await;
+    assert_eq!(status, 200);
+    assert!(report.healthy);
+    for component in [
+        "store.data",
+        "store.lookup",
+        "listener.jmap",
+        "listener.imap",
+        "listener.health",
+    ] {
+        let health = report
+            .components
+            .iter()
+            .find(|health| health.component == component)
+            .unwrap_or_else(|| panic!("Missing component {component}"));
+        assert!(health.healthy, "{health:?}");
+    }
+    assert!(report
+        .components
+        .iter()
+        .filter(|health| health.component.starts_with("store."))
+        .all(|health| health.latency_ms.is_some()));
+
+    // Listeners can be filtered by protocol
+    let (status, report) = fetch_health("ready/imap").await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        report
+            .components
+            .iter()
+            .map(|health| health.component.as_str())
+            .collect::<Vec<_>>(),
+        ["store.data", "store.lookup", "listener.imap"]
+    );
+
+    // Liveness does not check any components
+    let (status, _) = fetch_health_raw("live").await;
+    assert_eq!(status, 200);
+}
+
+async fn agent_check(service: Option<&str>) -> String {
+    let mut stream = TcpStream::connect("127.0.0.1:9890").await.unwrap();
+    if let Some(service) = service {
+        stream
+            .write_all(format!("{service}\n").as_bytes())
+            .await
+            .unwrap();
+    }
+    let mut response = String::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    response
+}
+
+async fn fetch_health(path: &str) -> (u16, HealthReport) {
+    let (status, body) = fetch_health_raw(path).await;
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn fetch_health_raw(path: &str) -> (u16, Vec<u8>) {
+    let response = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .timeout(Duration::from_millis(1000))
+        .build()
+        .unwrap()
+        .get(format!("https://127.0.0.1:8899/healthz/{path}"))
+        .send()
+        .await
+        .unwrap();
+
+    (
+        response.status().as_u16(),
+        response.bytes().await.unwrap().to_vec(),
+    )
+}
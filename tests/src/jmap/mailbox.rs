@@ -17,7 +17,7 @@ use jmap_proto::types::{id::Id, state::State};
 use serde::{Deserialize, Serialize};
 use store::ahash::AHashMap;
 
-use crate::jmap::assert_is_empty;
+use crate::jmap::{assert_is_empty, jmap_raw_request};
 
 use super::{wait_for_index, JMAPTest};
 
@@ -163,6 +163,52 @@ pub async fn test(params: &mut JMAPTest) {
         Vec::<&str>::new()
     );
 
+    // Filter by hierarchy depth, ancestor and name prefix
+    for (filter, expected_ids) in [
+        (
+            r#"{"maxDepth": 0}"#.to_string(),
+            vec!["drafts", "inbox", "sent", "spam", "trash"],
+        ),
+        (
+            format!(r#"{{"ancestorId": "{}", "maxDepth": 3}}"#, id_map["l.1"]),
+            vec!["1.1", "1.2", "1.1.1", "1.2.1"],
+        ),
+        (
+            format!(r#"{{"ancestorId": "{}"}}"#, id_map["spam"]),
+            vec!["spam2", "spam1"],
+        ),
+        (
+            r#"{"namePrefix": "sub-level"}"#.to_string(),
+            vec!["1.1", "1.2"],
+        ),
+    ] {
+        let response = jmap_raw_request(
+            format!(
+                r#"[[ "Mailbox/query", {{
+                    "accountId": "{}",
+                    "filter": {},
+                    "sort": [{{"property": "name"}}]
+                  }}, "0" ]]"#,
+                Id::from(0u64),
+                filter
+            ),
+            "admin",
+            "secret",
+        )
+        .await;
+        let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+        assert_eq!(
+            response["methodResponses"][0][1]["ids"]
+                .as_array()
+                .unwrap_or_else(|| panic!("Unexpected response: {response}"))
+                .iter()
+                .map(|id| id_map.get(id.as_str().unwrap()).unwrap())
+                .collect::<Vec<_>>(),
+            expected_ids,
+            "{filter}"
+        );
+    }
+
     // Filter by role
     assert_eq!(
         client
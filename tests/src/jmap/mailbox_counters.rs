@@ -0,0 +1,155 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap::mailbox::{
+    counters::{MailboxCounterCache, MailboxCounters},
+    INBOX_ID,
+};
+use jmap_client::email::query::Filter;
+use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+use store::write::{BatchBuilder, Bincode, F_VALUE};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, wait_for_index,
+        ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CounterReport {
+    account_id: u32,
+    mailbox_ids: Vec<u32>,
+}
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running mailbox counter tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jcounter@example.com",
+            "12345",
+            "Jane Counter",
+            &["jcounter@example.com"],
+        )
+        .await;
+
+    let mut lmtp = SmtpConnection::connect().await;
+    for num in 0..3 {
+        lmtp.ingest(
+            "bill@example.com",
+            &["jcounter@example.com"],
+            &format!(
+                concat!(
+                    "From: bill@example.com\r\n",
+                    "To: jcounter@example.com\r\n",
+                    "Subject: TPS Report #{}\r\n",
+                    "\r\n",
+                    "I'm going to need those TPS reports ASAP."
+                ),
+                num,
+            ),
+        )
+        .await;
+    }
+    wait_for_index(&server).await;
+
+    // Counters are computed and cached on the first lookup
+    let counters = server.mailbox_counters(account_id, INBOX_ID).await.unwrap();
+    assert_eq!(counters.total_emails, 3);
+    assert_eq!(counters.unread_emails, 3);
+    assert_eq!(
+        server.mailbox_counters(account_id, INBOX_ID).await.unwrap(),
+        counters
+    );
+
+    // Changing the seen state of a message invalidates the cached counters
+    let client = params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    let email_id = client
+        .email_query(None::<Filter>, None::<Vec<_>>)
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .unwrap();
+    client
+        .email_set_keyword(&email_id, "$seen", true)
+        .await
+        .unwrap();
+    let counters = server.mailbox_counters(account_id, INBOX_ID).await.unwrap();
+    assert_eq!(counters.total_emails, 3);
+    assert_eq!(counters.unread_emails, 2);
+    let mailbox = client
+        .mailbox_get(&Id::from(INBOX_ID).to_string(), None::<Vec<_>>)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(mailbox.total_emails(), 3);
+    assert_eq!(mailbox.unread_emails(), 2);
+
+    // Corrupt the cached counters
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::Mailbox)
+        .update_document(INBOX_ID)
+        .value(
+            Property::Counters,
+            Bincode::new(MailboxCounters {
+                total_emails: 10,
+                ..counters
+            }),
+            F_VALUE,
+        );
+    server.core.storage.data.write(batch.build()).await.unwrap();
+    assert_eq!(
+        server
+            .mailbox_counters(account_id, INBOX_ID)
+            .await
+            .unwrap()
+            .total_emails,
+        10
+    );
+
+    // Inconsistent counters are reported and then repaired
+    for repair in [false, true] {
+        let reports = api
+            .get::<Vec<CounterReport>>(&format!(
+                "/api/store/check/counters/jcounter@example.com?repair={repair}"
+            ))
+            .await
+            .unwrap()
+            .unwrap_data();
+        assert_eq!(reports.len(), 1, "{reports:?}");
+        assert_eq!(reports[0].account_id, account_id);
+        assert_eq!(reports[0].mailbox_ids, vec![INBOX_ID]);
+    }
+    assert!(api
+        .get::<Vec<CounterReport>>("/api/store/check/counters/jcounter@example.com")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .is_empty());
+    assert_eq!(
+        server.mailbox_counters(account_id, INBOX_ID).await.unwrap(),
+        counters
+    );
+
+    // Remove test data
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -0,0 +1,241 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::Server;
+use jmap::{
+    api::management::list::{HeldPostItem, ListMember},
+    JmapMethods,
+};
+use jmap_client::email::{query::Filter, Property};
+use jmap_proto::types::{collection::Collection, id::Id};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running mailing list tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    // Create the list members and the shared archive
+    let mut account_ids = Vec::new();
+    for (email, name) in [
+        ("ann@example.com", "Ann Lee"),
+        ("bob@example.com", "Bob Ray"),
+    ] {
+        account_ids.push(
+            server
+                .core
+                .storage
+                .data
+                .create_test_user(email, "secret", name, &[email])
+                .await,
+        );
+    }
+    let archive_id = server
+        .core
+        .storage
+        .data
+        .create_test_group(
+            "team-archive@example.com",
+            "Team Archive",
+            &["team-archive@example.com"],
+        )
+        .await;
+    account_ids.push(archive_id);
+    server
+        .core
+        .storage
+        .data
+        .create_test_list(
+            "team@example.com",
+            "Team",
+            &["ann@example.com", "bob@example.com"],
+        )
+        .await;
+    let (ann_id, bob_id) = (account_ids[0], account_ids[1]);
+
+    // Posts from members are tagged and distributed to members and the archive
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest(
+        "bob@example.com",
+        &["team@example.com"],
+        concat!(
+            "From: bob@example.com\r\n",
+            "To: team@example.com\r\n",
+            "Subject: Kickoff\r\n",
+            "\r\n",
+            "Meeting at noon."
+        ),
+    )
+    .await;
+    for account_id in [ann_id, bob_id, archive_id] {
+        wait_for_messages(&server, account_id, 1).await;
+    }
+    let email_id = params
+        .client
+        .set_default_account_id(Id::from(ann_id).to_string())
+        .email_query(None::<Filter>, None::<Vec<_>>)
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .unwrap();
+    assert_eq!(
+        params
+            .client
+            .email_get(&email_id, [Property::Subject].into())
+            .await
+            .unwrap()
+            .unwrap()
+            .subject()
+            .unwrap(),
+        "[team] Kickoff"
+    );
+
+    // Posts from non-members are held until a moderator approves them
+    for subject in ["Question", "Buy now"] {
+        lmtp.ingest(
+            "eve@remote.org",
+            &["team@example.com"],
+            &format!(
+                concat!(
+                    "From: eve@remote.org\r\n",
+                    "To: team@example.com\r\n",
+                    "Subject: {}\r\n",
+                    "\r\n",
+                    "Hello list."
+                ),
+                subject
+            ),
+        )
+        .await;
+    }
+    let held = api
+        .get::<Vec<HeldPostItem>>("/api/mailing-list/team@example.com/held")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(held.len(), 2);
+    assert!(held
+        .iter()
+        .all(|post| post.sender == "eve@remote.org" && post.from == "eve@remote.org"));
+    let question = held.iter().find(|post| post.subject == "Question").unwrap();
+    let spam = held.iter().find(|post| post.subject == "Buy now").unwrap();
+    assert_eq!(count_messages(&server, ann_id).await, 1);
+
+    api.post::<()>(
+        &format!("/api/mailing-list/team@example.com/held/{}", question.id),
+        &(),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    api.delete::<()>(&format!(
+        "/api/mailing-list/team@example.com/held/{}",
+        spam.id
+    ))
+    .await
+    .unwrap()
+    .unwrap_data();
+    for account_id in [ann_id, bob_id, archive_id] {
+        wait_for_messages(&server, account_id, 2).await;
+    }
+    assert!(api
+        .get::<Vec<HeldPostItem>>("/api/mailing-list/team@example.com/held")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .is_empty());
+
+    // Members are unsubscribed after too many bounces
+    for expected_bounces in [1, 0] {
+        lmtp.ingest(
+            "",
+            &["team-bounces@example.com"],
+            concat!(
+                "From: MAILER-DAEMON@remote.org\r\n",
+                "To: team-bounces@example.com\r\n",
+                "Subject: Undelivered Mail Returned to Sender\r\n",
+                "MIME-Version: 1.0\r\n",
+                "Content-Type: multipart/report; report-type=delivery-status;\r\n",
+                "\tboundary=\"dsn\"\r\n",
+                "\r\n",
+                "--dsn\r\n",
+                "Content-Type: text/plain\r\n",
+                "\r\n",
+                "Your message could not be delivered.\r\n",
+                "--dsn\r\n",
+                "Content-Type: message/delivery-status\r\n",
+                "\r\n",
+                "Reporting-MTA: dns; mx.remote.org\r\n",
+                "\r\n",
+                "Final-Recipient: rfc822; bob@example.com\r\n",
+                "Action: failed\r\n",
+                "Status: 5.1.1\r\n",
+                "\r\n",
+                "--dsn--\r\n"
+            ),
+        )
+        .await;
+
+        let members = api
+            .get::<Vec<ListMember>>("/api/mailing-list/team@example.com/members")
+            .await
+            .unwrap()
+            .unwrap_data();
+        if expected_bounces > 0 {
+            assert_eq!(members.len(), 2);
+            assert_eq!(
+                members
+                    .iter()
+                    .find(|member| member.address == "bob@example.com")
+                    .unwrap()
+                    .bounces,
+                expected_bounces
+            );
+        } else {
+            assert_eq!(members.len(), 1);
+            assert_eq!(members[0].address, "ann@example.com");
+            assert_eq!(members[0].bounces, 0);
+        }
+    }
+
+    // Remove test data
+    for account_id in account_ids {
+        params
+            .client
+            .set_default_account_id(Id::from(account_id).to_string());
+        destroy_all_mailboxes(params).await;
+    }
+    assert_is_empty(server).await;
+}
+
+async fn count_messages(server: &Server, account_id: u32) -> u64 {
+    server
+        .get_document_ids(account_id, Collection::Email)
+        .await
+        .unwrap()
+        .map_or(0, |ids| ids.len())
+}
+
+async fn wait_for_messages(server: &Server, account_id: u32, expected: u64) {
+    for _ in 0..50 {
+        if count_messages(server, account_id).await >= expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Timed out waiting for {expected} messages in account {account_id}");
+}
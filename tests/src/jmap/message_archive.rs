@@ -0,0 +1,186 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use common::Server;
+use jmap::{
+    email::{
+        archive::{ArchiveReport, EmailArchive},
+        metadata::MessageMetadata,
+    },
+    JmapMethods,
+};
+use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+use store::{ahash::AHashSet, write::Bincode, CompressionAlgo};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, wait_for_index,
+        ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running message archive tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jarchive@example.com",
+            "12345",
+            "Jane Archive",
+            &["jarchive@example.com"],
+        )
+        .await;
+
+    let mut lmtp = SmtpConnection::connect().await;
+    for num in 0..3 {
+        lmtp.ingest(
+            "bill@example.com",
+            &["jarchive@example.com"],
+            &format!(
+                concat!(
+                    "From: bill@example.com\r\n",
+                    "To: jarchive@example.com\r\n",
+                    "Subject: TPS Report #{}\r\n",
+                    "\r\n",
+                    "{}"
+                ),
+                num,
+                "I'm going to need those TPS reports ASAP.\r\n".repeat(200)
+            ),
+        )
+        .await;
+    }
+    wait_for_index(&server).await;
+
+    // Archiving requires blob compression
+    assert_eq!(
+        api.get::<Vec<ArchiveReport>>("/api/store/archive?months=0")
+            .await
+            .unwrap()
+            .unwrap_error()
+            .0,
+        "unsupported"
+    );
+
+    // Obtain the stored messages
+    let mut messages = Vec::new();
+    for document_id in server
+        .get_document_ids(account_id, Collection::Email)
+        .await
+        .unwrap()
+        .unwrap()
+    {
+        let blob_hash = server
+            .get_property::<Bincode<MessageMetadata>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::BodyStructure,
+            )
+            .await
+            .unwrap()
+            .unwrap()
+            .inner
+            .blob_hash;
+        let contents = server
+            .core
+            .storage
+            .blob
+            .get_blob(blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .unwrap()
+            .unwrap();
+        messages.push((blob_hash, contents));
+    }
+    assert_eq!(messages.len(), 3);
+
+    // Enable compression on the blob store
+    let mut core = server.core.as_ref().clone();
+    core.storage.blob = core
+        .storage
+        .blob
+        .clone()
+        .with_compression(CompressionAlgo::Lz4);
+    let archiver = Server {
+        inner: server.inner.clone(),
+        core: Arc::new(core),
+    };
+
+    // Recent messages are skipped
+    let report = archiver
+        .archive_account_messages(account_id, 86400, &mut AHashSet::new())
+        .await
+        .unwrap();
+    assert_eq!(report.messages, 0);
+    assert_eq!(report.blobs, 0);
+
+    // Old messages are recompressed
+    let report = archiver
+        .archive_account_messages(account_id, 0, &mut AHashSet::new())
+        .await
+        .unwrap();
+    assert_eq!(report.messages, 3);
+    assert_eq!(report.blobs, 3);
+    assert!(report.reclaimed() > 0, "{report:?}");
+    for (blob_hash, contents) in &messages {
+        let stored = server
+            .core
+            .storage
+            .blob
+            .get_blob(blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.last().copied(), Some(CompressionAlgo::Zstd.marker()));
+        assert_eq!(
+            archiver
+                .core
+                .storage
+                .blob
+                .get_blob(blob_hash.as_slice(), 0..usize::MAX)
+                .await
+                .unwrap()
+                .as_ref(),
+            Some(contents)
+        );
+        assert_eq!(
+            archiver
+                .core
+                .storage
+                .blob
+                .get_blob(blob_hash.as_slice(), 4..10)
+                .await
+                .unwrap()
+                .as_deref(),
+            contents.get(4..10)
+        );
+    }
+
+    // Archived messages are not processed again
+    let report = archiver
+        .archive_account_messages(account_id, 0, &mut AHashSet::new())
+        .await
+        .unwrap();
+    assert_eq!(report.messages, 3);
+    assert_eq!(report.blobs, 0);
+
+    // Remove test data
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -0,0 +1,168 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap::api::management::search::MessageSearchResult;
+use jmap_proto::types::id::Id;
+use serde::Deserialize;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, wait_for_index,
+        ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+#[derive(Debug, Deserialize)]
+struct SearchResults {
+    items: Vec<MessageSearchResult>,
+    total: usize,
+}
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running admin message search tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jsearch@example.com",
+            "12345",
+            "Jane Search",
+            &["jsearch@example.com"],
+        )
+        .await;
+
+    let mut lmtp = SmtpConnection::connect().await;
+    for (from, cc, subject, body) in [
+        (
+            "bill@example.com",
+            "audit@example.com",
+            "Invoice 1234",
+            "Please wire the funds today.",
+        ),
+        (
+            "mallory@example.net",
+            "jsearch@example.com",
+            "Wire transfer",
+            "The account details have changed.",
+        ),
+        (
+            "bill@example.com",
+            "jsearch@example.com",
+            "Lunch",
+            "See you at noon.",
+        ),
+    ] {
+        lmtp.ingest(
+            from,
+            &["jsearch@example.com"],
+            &format!(
+                concat!(
+                    "From: {}\r\n",
+                    "To: jsearch@example.com\r\n",
+                    "Cc: {}\r\n",
+                    "Subject: {}\r\n",
+                    "Message-ID: <{}@example.com>\r\n",
+                    "\r\n",
+                    "{}"
+                ),
+                from,
+                cc,
+                subject,
+                subject.to_lowercase().replace(' ', "-"),
+                body
+            ),
+        )
+        .await;
+    }
+    wait_for_index(&server).await;
+
+    // Search all messages in the account
+    let results = search(&api, "").await;
+    assert_eq!(results.total, 3);
+    assert_eq!(results.items.len(), 3);
+    let message = results
+        .items
+        .iter()
+        .find(|item| item.subject.as_deref() == Some("Invoice 1234"))
+        .unwrap();
+    assert_eq!(message.from, vec!["bill@example.com".to_string()]);
+    assert_eq!(message.to, vec!["jsearch@example.com".to_string()]);
+    assert_eq!(message.cc, vec!["audit@example.com".to_string()]);
+    assert_eq!(
+        message.message_id.as_deref(),
+        Some("invoice-1234@example.com")
+    );
+
+    // Search by text, participants and subject
+    assert_eq!(
+        subjects(&api, "&text=wire").await,
+        ["Invoice 1234", "Wire transfer"]
+    );
+    assert_eq!(
+        subjects(&api, "&from=bill@example.com").await,
+        ["Invoice 1234", "Lunch"]
+    );
+    assert_eq!(
+        subjects(&api, "&participant=audit@example.com").await,
+        ["Invoice 1234"]
+    );
+    assert_eq!(subjects(&api, "&subject=lunch").await, ["Lunch"]);
+    assert_eq!(
+        subjects(&api, "&from=bill@example.com&text=wire").await,
+        ["Invoice 1234"]
+    );
+
+    // Search by date
+    assert_eq!(subjects(&api, "&after=2000-01-01T00:00:00Z").await.len(), 3);
+    assert!(subjects(&api, "&before=2000-01-01T00:00:00Z")
+        .await
+        .is_empty());
+
+    // Limit results
+    let results = search(&api, "&limit=2").await;
+    assert_eq!(results.total, 3);
+    assert_eq!(results.items.len(), 2);
+
+    // Unknown accounts are rejected
+    assert!(api
+        .get::<SearchResults>("/api/search?account=unknown@example.com")
+        .await
+        .unwrap()
+        .try_unwrap_data()
+        .is_none());
+
+    // Remove test data
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn search(api: &ManagementApi, query: &str) -> SearchResults {
+    api.get::<SearchResults>(&format!("/api/search?account=jsearch@example.com{query}"))
+        .await
+        .unwrap()
+        .unwrap_data()
+}
+
+async fn subjects(api: &ManagementApi, query: &str) -> Vec<String> {
+    let mut subjects = search(api, query)
+        .await
+        .items
+        .into_iter()
+        .filter_map(|item| item.subject)
+        .collect::<Vec<_>>();
+    subjects.sort();
+    subjects
+}
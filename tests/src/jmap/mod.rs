@@ -22,6 +22,7 @@ use common::{
         telemetry::Telemetry,
     },
     core::BuildServer,
+    listener::health::HealthSessionManager,
     manager::{
         boot::build_ipc,
         config::{ConfigManager, Patterns},
@@ -53,32 +54,55 @@ use crate::{
     add_test_certs, directory::internal::TestInternalDirectory, store::TempDir, AssertConfig,
 };
 
+pub mod absence;
+pub mod alias;
 pub mod auth_acl;
 pub mod auth_limits;
 pub mod auth_oauth;
 pub mod blob;
+pub mod config_check;
 pub mod crypto;
+pub mod delegation;
 pub mod delivery;
+pub mod dkim_rotation;
+pub mod dns_provisioning;
 pub mod email_changes;
 pub mod email_copy;
 pub mod email_get;
 pub mod email_parse;
+pub mod email_pdf;
+pub mod email_pgp;
 pub mod email_query;
 pub mod email_query_changes;
 pub mod email_search_snippet;
 pub mod email_set;
+pub mod email_share;
+pub mod email_smime;
 pub mod email_submission;
 pub mod enterprise;
 pub mod event_source;
+pub mod fsck;
+pub mod fts_fallback;
+pub mod health;
 pub mod mailbox;
+pub mod mailbox_counters;
+pub mod mailing_list;
+pub mod message_archive;
+pub mod message_search;
 pub mod permissions;
 pub mod purge;
 pub mod push_subscription;
+pub mod quarantine;
 pub mod quota;
+pub mod read_only;
 pub mod sieve_script;
+pub mod snapshot;
+pub mod store_backup;
+pub mod store_usage;
 pub mod stress_test;
 pub mod thread_get;
 pub mod thread_merge;
+pub mod trash;
 pub mod vacation_response;
 pub mod webhooks;
 pub mod websocket;
@@ -105,6 +129,11 @@ greeting = 'Test LMTP instance'
 protocol = 'lmtp'
 tls.implicit = false
 
+[server.listener.health]
+bind = ["127.0.0.1:9890"]
+protocol = "health"
+tls.implicit = false
+
 [server.listener.pop3]
 bind = ["127.0.0.1:4110"]
 protocol = "pop3"
@@ -133,6 +162,13 @@ relay = [ { if = "!is_empty(authenticated_as)", then = true },
           { else = false } ]
 directory = "'{STORE}'"
 
+[session.rcpt.addressing."filing"]
+domains = ["filing.example"]
+catch-all = "sally@filing.example"
+sub-addressing.delimiter = "+-"
+sub-addressing.file-into = true
+sub-addressing.create-folder = true
+
 [session.rcpt.errors]
 total = 5
 wait = "1ms"
@@ -180,6 +216,7 @@ path = "{TMP}/rocks.db"
 
 [store."foundationdb"]
 type = "foundationdb"
+key-salt.buckets = 4
 
 [store."postgresql"]
 type = "postgresql"
@@ -215,10 +252,20 @@ fts = "{STORE}"
 blob = "{STORE}"
 lookup = "{STORE}"
 directory = "{STORE}"
+trash.retention = "1d"
 
 [spam.header]
 is-spam  = "X-Spam-Status: Yes"
 
+[spam.bayes.user-training]
+enable = true
+
+[spam.quarantine]
+enable = true
+threshold = 15.0
+digest.url = "https://127.0.0.1:8899"
+digest.from = "quarantine@example.com"
+
 [jmap.protocol.get]
 max-objects = 100000
 
@@ -254,6 +301,26 @@ attempts.interval = "500ms"
 [jmap.email]
 auto-expunge = "1s"
 
+[jmap.email.smime]
+trust-anchors = "%{file:{SMIME_CA}}%"
+
+[jmap.email.pgp]
+verify = true
+decrypt = true
+keyring = "%{file:{PGP_KEYRING}}%"
+
+[jmap.account.alias]
+enable = true
+max-aliases = 2
+
+[mailing-list."team"]
+address = "team@example.com"
+name = "Team"
+subject-tag = "[team]"
+moderation = "members"
+archive.address = "team-archive@example.com"
+bounce.max = 2
+
 [jmap.protocol.changes]
 max-history = "1s"
 
@@ -351,6 +418,32 @@ events = ["auth.*", "delivery.dsn*", "message-ingest.*", "security.authenticatio
 signature-key = "ovos-moles"
 throttle = "100ms"
 
+[jmap.folder-templates.acme]
+domains = ["defaults.org"]
+
+[jmap.folder-templates.acme.folders.sent]
+name = "Sent"
+localized.de = "Gesendet"
+localized.fr = "Envoyés"
+
+[account.defaults.acme]
+scope = "defaults.org"
+quota = 1024
+roles = ["user"]
+permissions.disabled = ["imap-authenticate"]
+spam-threshold = 7
+folders = ["Projects", "Projects/Archive"]
+welcome.from = "postmaster@defaults.org"
+welcome.subject = "Welcome, {display_name}"
+welcome.body = "Your {email} mailbox has {quota} of storage."
+sieve = '''
+require "fileinto";
+
+if header :contains "List-Id" "announce" {
+    fileinto "Projects";
+}
+'''
+
 [sieve.untrusted.scripts."common"]
 contents = '''
 require "reject";
@@ -375,6 +468,10 @@ pub async fn jmap_tests() {
     email_get::test(&mut params).await;
     email_set::test(&mut params).await;
     email_parse::test(&mut params).await;
+    email_share::test(&mut params).await;
+    email_smime::test(&mut params).await;
+    email_pgp::test(&mut params).await;
+    email_pdf::test(&mut params).await;
     email_search_snippet::test(&mut params).await;
     email_changes::test(&mut params).await;
     email_query_changes::test(&mut params).await;
@@ -383,20 +480,39 @@ pub async fn jmap_tests() {
     thread_merge::test(&mut params).await;
     mailbox::test(&mut params).await;
     delivery::test(&mut params).await;
+    quarantine::test(&mut params).await;
+    dkim_rotation::test(&mut params).await;
+    message_search::test(&mut params).await;
+    dns_provisioning::test(&mut params).await;
     auth_acl::test(&mut params).await;
+    delegation::test(&mut params).await;
+    alias::test(&mut params).await;
+    mailing_list::test(&mut params).await;
     auth_limits::test(&mut params).await;
     auth_oauth::test(&mut params).await;
     event_source::test(&mut params).await;
     push_subscription::test(&mut params).await;
     sieve_script::test(&mut params).await;
     vacation_response::test(&mut params).await;
+    absence::test(&mut params).await;
     email_submission::test(&mut params).await;
     websocket::test(&mut params).await;
     quota::test(&mut params).await;
     crypto::test(&mut params).await;
     blob::test(&mut params).await;
     permissions::test(&params).await;
-    purge::test(&mut params).await;*/
+    purge::test(&mut params).await;
+    store_usage::test(&mut params).await;
+    health::test(&params).await;
+    fts_fallback::test(&mut params).await;
+    config_check::test(&params).await;
+    read_only::test(&mut params).await;
+    message_archive::test(&mut params).await;
+    mailbox_counters::test(&mut params).await;
+    fsck::test(&mut params).await;
+    snapshot::test(&mut params).await;
+    trash::test(&mut params).await;
+    store_backup::test(&mut params).await;*/
     enterprise::test(&mut params).await;
 
     if delete {
@@ -557,6 +673,24 @@ async fn init_jmap_tests(store_id: &str, delete_if_exists: bool) -> JMAPTest {
         add_test_certs(SERVER)
             .replace("{STORE}", store_id)
             .replace("{TMP}", &temp_dir.path.display().to_string())
+            .replace(
+                "{SMIME_CA}",
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("resources")
+                    .join("crypto")
+                    .join("smime_ca.pem")
+                    .to_str()
+                    .unwrap(),
+            )
+            .replace(
+                "{PGP_KEYRING}",
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("resources")
+                    .join("crypto")
+                    .join("pgp_keyring.asc")
+                    .to_str()
+                    .unwrap(),
+            )
             .replace(
                 "{LEVEL}",
                 &std::env::var("LOG").unwrap_or_else(|_| "disable".to_string()),
@@ -642,6 +776,12 @@ async fn init_jmap_tests(store_id: &str, delete_if_exists: bool) -> JMAPTest {
                 acceptor,
                 shutdown_rx,
             ),
+            ServerProtocol::Health => server.spawn(
+                HealthSessionManager::new(inner.clone()),
+                inner.clone(),
+                acceptor,
+                shutdown_rx,
+            ),
         };
     });
 
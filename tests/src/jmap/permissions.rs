@@ -13,7 +13,17 @@ use directory::{
     backend::internal::{PrincipalField, PrincipalUpdate, PrincipalValue},
     Permission, Principal, Type,
 };
-use jmap::{services::ingest::MailDelivery, JmapMethods};
+use jmap::{
+    api::management::{
+        principal::{BulkPrincipalOperation, BulkPrincipalResponse},
+        provision::format_size,
+    },
+    mailbox::INBOX_ID,
+    services::ingest::MailDelivery,
+    JmapMethods,
+};
+use jmap_proto::types::{collection::Collection, property::Property};
+use store::query::Filter;
 use utils::BlobHash;
 
 use crate::jmap::assert_is_empty;
@@ -733,6 +743,232 @@ pub async fn test(params: &JMAPTest) {
         .unwrap()
         .unwrap_data();
 
+    // Invalid bulk operations are rejected as a whole
+    for operations in [
+        vec![
+            BulkPrincipalOperation::Create {
+                principal: Principal::new(u32::MAX, Type::Domain)
+                    .with_field(PrincipalField::Name, "bulk.org"),
+            },
+            BulkPrincipalOperation::Delete {
+                name: "unknown-principal".to_string(),
+            },
+        ],
+        vec![
+            BulkPrincipalOperation::Create {
+                principal: Principal::new(u32::MAX, Type::Domain)
+                    .with_field(PrincipalField::Name, "bulk.org"),
+            },
+            BulkPrincipalOperation::Create {
+                principal: Principal::new(u32::MAX, Type::Domain)
+                    .with_field(PrincipalField::Name, "bulk.org"),
+            },
+        ],
+    ] {
+        let response = api
+            .post::<BulkPrincipalResponse>("/api/principal/bulk", &operations)
+            .await
+            .unwrap()
+            .unwrap_data();
+        assert!(!response.applied);
+        assert_eq!(response.succeeded, 0);
+        assert_eq!(response.failed, 1);
+        assert!(response.results[0].error.is_none());
+        assert!(response.results[1].error.is_some());
+        api.get::<Principal>("/api/principal/bulk.org")
+            .await
+            .unwrap()
+            .expect_error("notFound");
+    }
+
+    // Bulk create, update and delete principals
+    let response = api
+        .post::<BulkPrincipalResponse>(
+            "/api/principal/bulk",
+            &vec![
+                BulkPrincipalOperation::Create {
+                    principal: Principal::new(u32::MAX, Type::Domain)
+                        .with_field(PrincipalField::Name, "bulk.org"),
+                },
+                BulkPrincipalOperation::Create {
+                    principal: Principal::new(u32::MAX, Type::Individual)
+                        .with_field(PrincipalField::Name, "bulk1"),
+                },
+                BulkPrincipalOperation::Create {
+                    principal: Principal::new(u32::MAX, Type::Individual)
+                        .with_field(PrincipalField::Name, "bulk2"),
+                },
+            ],
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(response.applied);
+    assert_eq!(response.succeeded, 3);
+    assert!(response.results.iter().all(|r| r.id.is_some()));
+    let response = api
+        .post::<BulkPrincipalResponse>(
+            "/api/principal/bulk",
+            &vec![
+                BulkPrincipalOperation::Aliases {
+                    name: "bulk1".to_string(),
+                    add: vec!["bulk1@bulk.org".to_string(), "sales@bulk.org".to_string()],
+                    remove: vec![],
+                },
+                BulkPrincipalOperation::Update {
+                    name: "bulk2".to_string(),
+                    changes: vec![PrincipalUpdate::set(
+                        PrincipalField::Description,
+                        PrincipalValue::String("Bulk user".to_string()),
+                    )],
+                },
+            ],
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(response.applied);
+    assert_eq!(response.succeeded, 2);
+    assert_eq!(
+        api.get::<Principal>("/api/principal/bulk1")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .get_str_array(PrincipalField::Emails)
+            .unwrap_or_default(),
+        ["bulk1@bulk.org", "sales@bulk.org"]
+    );
+    assert_eq!(
+        api.get::<Principal>("/api/principal/bulk2")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .get_str(PrincipalField::Description),
+        Some("Bulk user")
+    );
+    let response = api
+        .post::<BulkPrincipalResponse>(
+            "/api/principal/bulk",
+            &["bulk1", "bulk2", "bulk.org"]
+                .into_iter()
+                .map(|name| BulkPrincipalOperation::Delete {
+                    name: name.to_string(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(response.applied);
+    assert_eq!(response.succeeded, 3);
+
+    // Domain defaults are applied to new accounts unless overridden
+    api.post::<u32>(
+        "/api/principal",
+        &Principal::new(u32::MAX, Type::Domain).with_field(PrincipalField::Name, "defaults.org"),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    let defaults_id = api
+        .post::<u32>(
+            "/api/principal",
+            &Principal::new(u32::MAX, Type::Individual)
+                .with_field(PrincipalField::Name, "jane@defaults.org")
+                .with_field(PrincipalField::Locale, "de-AT"),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    let principal = api
+        .get::<Principal>("/api/principal/jane@defaults.org")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(principal.get_int(PrincipalField::Quota), Some(1024));
+    assert_eq!(
+        principal
+            .get_str_array(PrincipalField::DisabledPermissions)
+            .unwrap_or_default(),
+        [Permission::ImapAuthenticate.name()]
+    );
+    assert_eq!(
+        server
+            .get_document_ids(defaults_id, Collection::SieveScript)
+            .await
+            .unwrap()
+            .unwrap_or_default()
+            .len(),
+        2
+    );
+    let mailbox_ids = server
+        .get_document_ids(defaults_id, Collection::Mailbox)
+        .await
+        .unwrap()
+        .unwrap_or_default();
+    assert_eq!(
+        mailbox_ids.len() as usize,
+        server.core.jmap.default_folders.len() + 2
+    );
+
+    // Folder names are localized using the domain template
+    for (name, expected) in [("Gesendet", 1), ("Sent", 0), ("Sent Items", 0)] {
+        assert_eq!(
+            server
+                .filter(
+                    defaults_id,
+                    Collection::Mailbox,
+                    vec![Filter::eq(Property::Name, name)],
+                )
+                .await
+                .unwrap()
+                .results
+                .len(),
+            expected,
+            "{name}"
+        );
+    }
+    let john_id = api
+        .post::<u32>(
+            "/api/principal",
+            &Principal::new(u32::MAX, Type::Individual)
+                .with_field(PrincipalField::Name, "john@defaults.org")
+                .with_field(PrincipalField::Description, "John Doe")
+                .with_field(PrincipalField::Emails, "john@defaults.org")
+                .with_field(PrincipalField::Quota, 2048u64),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(
+        api.get::<Principal>("/api/principal/john@defaults.org")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .get_int(PrincipalField::Quota),
+        Some(2048)
+    );
+
+    // A welcome message is delivered to the Inbox
+    assert_eq!(
+        server
+            .get_tag(john_id, Collection::Email, Property::MailboxIds, INBOX_ID)
+            .await
+            .unwrap()
+            .unwrap_or_default()
+            .len(),
+        1
+    );
+    assert_eq!(format_size(2048), "2 KB");
+    assert_eq!(format_size(1536), "1.5 KB");
+
+    for name in ["jane@defaults.org", "john@defaults.org", "defaults.org"] {
+        api.delete::<()>(&format!("/api/principal/{name}"))
+            .await
+            .unwrap()
+            .unwrap_data();
+    }
+
     assert_is_empty(server).await;
 }
 
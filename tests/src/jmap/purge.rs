@@ -7,14 +7,17 @@
 use ahash::AHashSet;
 use common::Server;
 use directory::{backend::internal::manage::ManageDirectory, QueryBy};
+use futures::{pin_mut, TryStreamExt};
 use imap_proto::ResponseType;
 use jmap::{
     email::delete::EmailDeletion,
     mailbox::{INBOX_ID, JUNK_ID, TRASH_ID},
     JmapMethods,
 };
-use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+use jmap_client::core::error::{MethodError, MethodErrorType};
+use jmap_proto::types::{collection::Collection, id::Id, property::Property, state::State};
 use store::{
+    dispatch::stream::DEFAULT_CHUNK_SIZE,
     write::{key::DeserializeBigEndian, TagValue},
     IterateParams, LogKey, U32_LEN, U64_LEN,
 };
@@ -62,6 +65,7 @@ pub async fn test(params: &mut JMAPTest) {
     let mut message_ids = Vec::new();
     let mut pass = 0;
     let mut changes = AHashSet::new();
+    let mut states = Vec::new();
 
     loop {
         pass += 1;
@@ -89,6 +93,16 @@ pub async fn test(params: &mut JMAPTest) {
                     .unwrap()
                     .take_id(),
             );
+            if pass == 1 {
+                states.push(
+                    client
+                        .email_changes(State::Initial.to_string(), None)
+                        .await
+                        .unwrap()
+                        .new_state()
+                        .to_string(),
+                );
+            }
         }
 
         if pass == 1 {
@@ -184,18 +198,39 @@ pub async fn test(params: &mut JMAPTest) {
         .assert_contains("\"Deleted Items\" (MESSAGES 1)")
         .assert_contains("\"Junk Mail\" (MESSAGES 1)");
 
-    // Compare changes
+    // Compare changes, only the last change of each collection before the horizon is kept
     let new_changes = get_changes(&server).await;
     assert!(!changes.is_empty());
     assert!(!new_changes.is_empty());
-    for change in changes {
-        assert!(
-            !new_changes.contains(&change),
-            "Change {:?} was not purged",
+    for change in &changes {
+        let is_horizon = !changes
+            .iter()
+            .any(|(change_id, collection)| *collection == change.1 && *change_id > change.0);
+        assert_eq!(
+            new_changes.contains(change),
+            is_horizon,
+            "Change {:?} was not compacted",
             change
         );
     }
 
+    // States older than the horizon can no longer be resolved
+    assert!(matches!(
+        client.email_changes(states.first().unwrap(), None).await,
+        Err(jmap_client::Error::Method(MethodError {
+            p_type: MethodErrorType::CannotCalculateChanges
+        }))
+    ));
+    assert_eq!(
+        client
+            .email_changes(states.last().unwrap(), None)
+            .await
+            .unwrap()
+            .created()
+            .len(),
+        3
+    );
+
     // Delete account
     server
         .core
@@ -209,34 +244,31 @@ pub async fn test(params: &mut JMAPTest) {
 
 async fn get_changes(server: &Server) -> AHashSet<(u64, u8)> {
     let mut changes = AHashSet::new();
-    server
-        .core
-        .storage
-        .data
-        .iterate(
-            IterateParams::new(
-                LogKey {
-                    account_id: 0,
-                    collection: 0,
-                    change_id: 0,
-                },
-                LogKey {
-                    account_id: u32::MAX,
-                    collection: u8::MAX,
-                    change_id: u64::MAX,
-                },
-            )
-            .ascending()
-            .no_values(),
-            |key, _| {
-                changes.insert((
-                    key.deserialize_be_u64(key.len() - U64_LEN).unwrap(),
-                    key[U32_LEN],
-                ));
-                Ok(true)
+    let stream = server.core.storage.data.stream(
+        IterateParams::new(
+            LogKey {
+                account_id: 0,
+                collection: 0,
+                change_id: 0,
+            },
+            LogKey {
+                account_id: u32::MAX,
+                collection: u8::MAX,
+                change_id: u64::MAX,
             },
         )
-        .await
-        .unwrap();
+        .ascending()
+        .no_values(),
+        DEFAULT_CHUNK_SIZE,
+    );
+    pin_mut!(stream);
+    while let Some((key, _)) = stream.try_next().await.unwrap() {
+        changes.insert((
+            key.as_slice()
+                .deserialize_be_u64(key.len() - U64_LEN)
+                .unwrap(),
+            key[U32_LEN],
+        ));
+    }
     changes
 }
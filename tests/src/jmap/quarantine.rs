@@ -0,0 +1,227 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::Server;
+use jmap::{
+    email::quarantine::{parse_quarantine_id, release_token, EmailQuarantine, QuarantineItem},
+    mailbox::{INBOX_ID, JUNK_ID},
+    JmapMethods,
+};
+use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+use serde::Deserialize;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+#[derive(Debug, Deserialize)]
+struct QuarantineList {
+    items: Vec<QuarantineItem>,
+    total: usize,
+}
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running spam quarantine tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    let john_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+
+    // Messages above the quarantine threshold are held outside the mailbox
+    let mut lmtp = SmtpConnection::connect().await;
+    for (subject, score) in [("Cheap watches", "25.3"), ("Quarterly report", "13.9")] {
+        lmtp.ingest(
+            "bill@example.com",
+            &["jdoe@example.com"],
+            &format!(
+                concat!(
+                    "From: bill@example.com\r\n",
+                    "To: jdoe@example.com\r\n",
+                    "Subject: {}\r\n",
+                    "X-Spam-Status: Yes, score={}\r\n",
+                    "\r\n",
+                    "Limited time offer."
+                ),
+                subject, score
+            ),
+        )
+        .await;
+    }
+    assert_eq!(mailbox_count(&server, john_id, INBOX_ID).await, 0);
+    assert_eq!(mailbox_count(&server, john_id, JUNK_ID).await, 1);
+
+    // Administrators can search the quarantine
+    let list = api
+        .get::<QuarantineList>("/api/quarantine?account=jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(list.total, 1);
+    let item = list.items.into_iter().next().unwrap();
+    assert_eq!(item.subject, "Cheap watches");
+    assert_eq!(item.from, "bill@example.com");
+    assert_eq!(item.account_id, john_id);
+    assert_eq!(item.score, 25.3);
+    assert_eq!(
+        api.get::<QuarantineList>("/api/quarantine?text=watches")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .total,
+        1
+    );
+    assert_eq!(
+        api.get::<QuarantineList>("/api/quarantine?text=invoice")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .total,
+        0
+    );
+    assert_eq!(
+        api.get::<QuarantineItem>(&format!("/api/quarantine/{}", item.id))
+            .await
+            .unwrap()
+            .unwrap_data(),
+        item
+    );
+
+    // Users receive a digest with release links
+    assert_eq!(server.quarantine_send_digests().await.unwrap(), 1);
+    assert_eq!(mailbox_count(&server, john_id, INBOX_ID).await, 1);
+    assert_eq!(server.quarantine_send_digests().await.unwrap(), 0);
+
+    let (id, expires) = parse_quarantine_id(&item.id).unwrap();
+    let url = format!(
+        "https://127.0.0.1:8899/jmap/quarantine/{}",
+        release_token(&server, id, expires, john_id)
+    );
+    let (status, html) = fetch(&url, false).await;
+    assert_eq!(status, 200);
+    assert!(html.contains("Cheap watches"), "{html}");
+    assert!(html.contains("<form method=\"post\">"), "{html}");
+    assert_eq!(
+        fetch(
+            &format!(
+                "https://127.0.0.1:8899/jmap/quarantine/{}",
+                release_token(&server, id, expires, john_id + 1)
+            ),
+            true
+        )
+        .await
+        .0,
+        404
+    );
+
+    // Releasing delivers the message to the Inbox
+    assert_eq!(fetch(&url, true).await.0, 200);
+    assert_eq!(mailbox_count(&server, john_id, INBOX_ID).await, 2);
+    assert_eq!(fetch(&url, false).await.0, 404);
+
+    // Administrators can release or delete messages
+    for action in ["release", "delete"] {
+        lmtp.ingest(
+            "bill@example.com",
+            &["jdoe@example.com"],
+            concat!(
+                "From: bill@example.com\r\n",
+                "To: jdoe@example.com\r\n",
+                "Subject: Act now\r\n",
+                "X-Spam-Status: Yes, score=31.0\r\n",
+                "\r\n",
+                "Limited time offer."
+            ),
+        )
+        .await;
+        let list = api
+            .get::<QuarantineList>("/api/quarantine")
+            .await
+            .unwrap()
+            .unwrap_data();
+        assert_eq!(list.total, 1);
+        let id = &list.items[0].id;
+        if action == "release" {
+            assert!(api
+                .post::<bool>(&format!("/api/quarantine/{id}/release"), &())
+                .await
+                .unwrap()
+                .unwrap_data());
+        } else {
+            assert!(api
+                .delete::<bool>(&format!("/api/quarantine/{id}"))
+                .await
+                .unwrap()
+                .unwrap_data());
+        }
+        assert!(server.quarantine_list(None, None).await.unwrap().is_empty());
+    }
+    assert_eq!(mailbox_count(&server, john_id, INBOX_ID).await, 3);
+
+    // Remove test data
+    server
+        .core
+        .storage
+        .lookup
+        .key_delete(b"quarantine-digest".to_vec())
+        .await
+        .unwrap();
+    params
+        .client
+        .set_default_account_id(Id::from(john_id).to_string());
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn mailbox_count(server: &Server, account_id: u32, mailbox_id: u32) -> u64 {
+    server
+        .get_tag(
+            account_id,
+            Collection::Email,
+            Property::MailboxIds,
+            mailbox_id,
+        )
+        .await
+        .unwrap()
+        .map_or(0, |bm| bm.len())
+}
+
+async fn fetch(url: &str, post: bool) -> (u16, String) {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .timeout(Duration::from_millis(1000))
+        .build()
+        .unwrap();
+    let response = if post {
+        client.post(url)
+    } else {
+        client.get(url)
+    }
+    .send()
+    .await
+    .unwrap();
+
+    (
+        response.status().as_u16(),
+        response.text().await.unwrap_or_default(),
+    )
+}
@@ -130,6 +130,41 @@ pub async fn test(params: &mut JMAPTest) {
         "{}",
         response
     );
+    let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+    let quota_state = response["methodResponses"][0][1]["state"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let quota_id = response["methodResponses"][0][1]["list"][0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Quotas can be filtered by scope
+    for (scope, expected_ids) in [("account", 1), ("domain", 0)] {
+        let response = jmap_raw_request(
+            r#"[[ "Quota/query", {
+                "accountId": "$$",
+                "filter": { "scope": "%%" },
+                "sort": [{ "property": "used" }],
+                "calculateTotal": true
+              }, "0" ]]"#
+                .replace("$$", &account_id.to_string())
+                .replace("%%", scope),
+            "robert@example.com",
+            "aabbcc",
+        )
+        .await;
+        let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+        let response = &response["methodResponses"][0][1];
+        assert_eq!(response["total"], expected_ids, "{response}");
+        assert_eq!(
+            response["ids"].as_array().unwrap().len(),
+            expected_ids,
+            "{response}"
+        );
+        assert_eq!(response["queryState"], quota_state.as_str(), "{response}");
+    }
 
     // Test Email/import quota
     let inbox_id = Id::new(INBOX_ID as u64).to_string();
@@ -178,6 +213,32 @@ pub async fn test(params: &mut JMAPTest) {
     assert!(response.contains("\"used\":1024"), "{}", response);
     assert!(response.contains("\"hardLimit\":1024"), "{}", response);
 
+    // Usage changes are reported by Quota/changes
+    let response = jmap_raw_request(
+        r#"[[ "Quota/changes", {
+            "accountId": "$$",
+            "sinceState": "%%"
+          }, "0" ]]"#
+            .replace("$$", &account_id.to_string())
+            .replace("%%", &quota_state),
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    let response = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+    let response = &response["methodResponses"][0][1];
+    assert_ne!(response["newState"], quota_state.as_str(), "{response}");
+    assert_eq!(
+        response["updated"],
+        serde_json::json!([quota_id]),
+        "{response}"
+    );
+    assert_eq!(
+        response["updatedProperties"],
+        serde_json::json!(["used"]),
+        "{response}"
+    );
+
     // Delete messages and check available quota
     for message_id in message_ids {
         client.email_destroy(&message_id).await.unwrap();
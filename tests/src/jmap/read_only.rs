@@ -0,0 +1,130 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::{backend::internal::PrincipalField, Principal, Type};
+use hyper::Method;
+use jmap::api::management::read_only::is_mutating_request;
+
+use crate::jmap::ManagementApi;
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running read-only mode tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    // Only known safe routes are allowed
+    for (path, method, query) in [
+        ("queue/messages", Method::GET, None),
+        ("principal", Method::GET, None),
+        ("store/usage", Method::GET, None),
+        ("store/fsck", Method::GET, None),
+        ("store/check/counters", Method::GET, Some("repair=false")),
+        ("troubleshoot/dmarc", Method::POST, None),
+        ("reload/check", Method::POST, None),
+        ("read-only/disable", Method::POST, None),
+    ] {
+        let path = path.split('/').collect::<Vec<_>>();
+        assert!(
+            !is_mutating_request(&path, &method, query),
+            "{path:?} {method}"
+        );
+    }
+    for (path, method, query) in [
+        ("principal", Method::POST, None),
+        ("queue/messages", Method::DELETE, None),
+        ("store/purge/blob", Method::GET, None),
+        ("store/reindex", Method::GET, None),
+        ("store/archive", Method::GET, None),
+        ("store/fsck", Method::GET, Some("repair=true")),
+        ("store/check/counters", Method::GET, Some("repair=true")),
+        ("update/spam-filter", Method::GET, None),
+        ("sieve/test", Method::POST, None),
+        ("restart", Method::GET, None),
+        ("unknown", Method::GET, None),
+    ] {
+        let path = path.split('/').collect::<Vec<_>>();
+        assert!(
+            is_mutating_request(&path, &method, query),
+            "{path:?} {method}"
+        );
+    }
+
+    // Enable read-only mode
+    assert!(api
+        .post::<bool>("/api/read-only/enable", &())
+        .await
+        .unwrap()
+        .unwrap_data());
+    assert!(server.is_read_only());
+
+    // Mutating endpoints are rejected
+    assert_eq!(
+        api.post::<u32>(
+            "/api/principal",
+            &Principal::new(u32::MAX, Type::Individual)
+                .with_field(PrincipalField::Name, "jread@example.com"),
+        )
+        .await
+        .unwrap()
+        .unwrap_error()
+        .0,
+        "unsupported"
+    );
+    for (method, query) in [
+        (Method::DELETE, "/api/queue/messages"),
+        (Method::GET, "/api/store/purge/blob"),
+        (Method::GET, "/api/store/reindex"),
+        (Method::GET, "/api/store/fsck?repair=true"),
+        (Method::GET, "/api/update/spam-filter"),
+    ] {
+        assert_eq!(
+            api.request::<serde_json::Value>(method, query)
+                .await
+                .unwrap()
+                .unwrap_error()
+                .0,
+            "unsupported",
+            "{query}"
+        );
+    }
+
+    // Reads are still allowed
+    api.get::<serde_json::Value>("/api/principal")
+        .await
+        .unwrap()
+        .unwrap_data();
+    api.get::<serde_json::Value>("/api/queue/messages")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(api
+        .get::<bool>("/api/read-only")
+        .await
+        .unwrap()
+        .unwrap_data());
+
+    // Disable read-only mode
+    assert!(!api
+        .post::<bool>("/api/read-only/disable", &())
+        .await
+        .unwrap()
+        .unwrap_data());
+    assert!(!server.is_read_only());
+    api.post::<u32>(
+        "/api/principal",
+        &Principal::new(u32::MAX, Type::Individual)
+            .with_field(PrincipalField::Name, "jread@example.com"),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    api.delete::<()>("/api/principal/jread@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+}
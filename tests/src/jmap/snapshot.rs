@@ -0,0 +1,169 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use hyper::Method;
+use jmap::{mailbox::INBOX_ID, services::snapshot::RestoreReport};
+use jmap_client::{email::query::Filter, mailbox::Role};
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, mailbox::destroy_all_mailboxes, ManagementApi},
+};
+
+use super::JMAPTest;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot {
+    #[allow(dead_code)]
+    created_at: String,
+    mailboxes: u64,
+    emails: u64,
+}
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running snapshot tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jsnapshot@example.com",
+            "12345",
+            "Jane Snapshot",
+            &["jsnapshot@example.com"],
+        )
+        .await;
+    let client = params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    let inbox_id = Id::from(INBOX_ID).to_string();
+    let projects_id = client
+        .mailbox_create("Projects", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+
+    // Populate the account
+    let mut email_ids = Vec::new();
+    for (num, mailbox_id) in [&inbox_id, &inbox_id, &projects_id].into_iter().enumerate() {
+        email_ids.push(
+            client
+                .email_import(
+                    format!(
+                        concat!(
+                            "From: bill@example.com\r\n",
+                            "To: jsnapshot@example.com\r\n",
+                            "Subject: TPS Report #{}\r\n",
+                            "\r\n",
+                            "I'm going to need those TPS reports ASAP."
+                        ),
+                        num
+                    )
+                    .into_bytes(),
+                    [mailbox_id],
+                    ["$seen"].into(),
+                    None,
+                )
+                .await
+                .unwrap()
+                .take_id(),
+        );
+    }
+
+    // Take a snapshot
+    let snapshot = api
+        .request::<Snapshot>(Method::POST, "/api/store/snapshot/jsnapshot@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(snapshot.emails, 3);
+    assert!(snapshot.mailboxes > 1);
+    assert_eq!(
+        api.get::<Vec<Snapshot>>("/api/store/snapshot/jsnapshot@example.com")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .len(),
+        1
+    );
+
+    // Delete a mailbox and a message
+    client.mailbox_destroy(&projects_id, true).await.unwrap();
+    client.email_destroy(&email_ids[1]).await.unwrap();
+    assert_eq!(
+        client
+            .email_query(None::<Filter>, None::<Vec<_>>)
+            .await
+            .unwrap()
+            .ids()
+            .len(),
+        1
+    );
+
+    // Restore the deleted mailbox
+    let report = api
+        .request::<RestoreReport>(
+            Method::POST,
+            "/api/store/restore/jsnapshot@example.com?mailbox=Projects",
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(report.account_id, account_id);
+    assert_eq!(report.created_mailboxes, vec!["Projects".to_string()]);
+    assert_eq!(report.restored_emails, 1);
+    assert_eq!(report.present_emails, 0);
+    assert_eq!(report.failed_emails, 0);
+
+    // Restore the rest of the account
+    let report = api
+        .request::<RestoreReport>(Method::POST, "/api/store/restore/jsnapshot@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(report.created_mailboxes.is_empty(), "{report:?}");
+    assert_eq!(report.restored_emails, 1);
+    assert_eq!(report.present_emails, 2);
+    assert_eq!(report.failed_emails, 0);
+    assert_eq!(report.unrecoverable_emails, 0);
+
+    // Keywords are restored along with the messages
+    assert_eq!(
+        client
+            .email_query(Filter::has_keyword("$seen").into(), None::<Vec<_>>)
+            .await
+            .unwrap()
+            .ids()
+            .len(),
+        3
+    );
+
+    // Restoring to a time before the first snapshot fails
+    assert_eq!(
+        api.request::<RestoreReport>(
+            Method::POST,
+            "/api/store/restore/jsnapshot@example.com?at=2000-01-01T00:00:00Z",
+        )
+        .await
+        .unwrap()
+        .unwrap_error()
+        .0,
+        "notFound"
+    );
+
+    // Remove test data
+    api.request::<()>(Method::DELETE, "/api/store/snapshot/jsnapshot@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -0,0 +1,48 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use hyper::Method;
+
+use crate::jmap::ManagementApi;
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running online backup tests...");
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let dest = params.temp_dir.path.join("sqlite-backup.db");
+    let _ = std::fs::remove_file(&dest);
+    let query = format!("/api/store/backup/sqlite?path={}", dest.display());
+
+    // Back up the SQLite store while the server is running
+    api.request::<()>(Method::POST, &query)
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(std::fs::metadata(&dest).unwrap().len() > 0);
+
+    // Existing files are never overwritten
+    assert_eq!(
+        api.request::<()>(Method::POST, &query)
+            .await
+            .unwrap()
+            .unwrap_error()
+            .0,
+        "fieldAlreadyExists"
+    );
+
+    // A destination path is required
+    assert_eq!(
+        api.request::<()>(Method::POST, "/api/store/backup/sqlite")
+            .await
+            .unwrap()
+            .unwrap_error()
+            .0,
+        "fieldMissing"
+    );
+
+    std::fs::remove_file(&dest).unwrap();
+}
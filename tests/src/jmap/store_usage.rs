@@ -0,0 +1,127 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::types::id::Id;
+use store::dispatch::usage::{KeySpaceUsage, Subsystem};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, wait_for_index,
+        ManagementApi,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running storage usage tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jusage@example.com",
+            "12345",
+            "Jane Usage",
+            &["jusage@example.com"],
+        )
+        .await;
+
+    let mut lmtp = SmtpConnection::connect().await;
+    for num in 0..5 {
+        lmtp.ingest(
+            "bill@example.com",
+            &["jusage@example.com"],
+            &format!(
+                concat!(
+                    "From: bill@example.com\r\n",
+                    "To: jusage@example.com\r\n",
+                    "Subject: TPS Report #{}\r\n",
+                    "\r\n",
+                    "I'm going to need those TPS reports ASAP."
+                ),
+                num
+            ),
+        )
+        .await;
+    }
+    wait_for_index(&server).await;
+
+    // Storage is reported per subsystem
+    let usage = api
+        .get::<KeySpaceUsage>("/api/store/usage")
+        .await
+        .unwrap()
+        .unwrap_data();
+    for subsystem in [
+        Subsystem::Indexes,
+        Subsystem::Bitmaps,
+        Subsystem::ChangeLog,
+        Subsystem::Properties,
+        Subsystem::Directory,
+    ] {
+        let subsystem_usage = usage
+            .subsystems
+            .iter()
+            .find(|usage| usage.subsystem == subsystem)
+            .unwrap();
+        assert!(subsystem_usage.usage.keys > 0, "{subsystem:?}");
+        assert!(subsystem_usage.usage.bytes > 0, "{subsystem:?}");
+    }
+    assert_eq!(
+        usage.total.keys,
+        usage.subsystems.iter().map(|s| s.usage.keys).sum::<u64>()
+    );
+    assert_eq!(
+        usage.total.bytes,
+        usage.subsystems.iter().map(|s| s.usage.bytes).sum::<u64>()
+    );
+
+    // Accounts are reported by name, largest first
+    let account = usage
+        .accounts
+        .iter()
+        .find(|account| account.account_id == account_id)
+        .unwrap();
+    assert_eq!(account.name.as_deref(), Some("jusage@example.com"));
+    assert!(account
+        .subsystems
+        .iter()
+        .any(|usage| usage.subsystem == Subsystem::ChangeLog && usage.usage.keys > 0));
+    assert!(usage
+        .accounts
+        .windows(2)
+        .all(|accounts| accounts[0].usage.bytes >= accounts[1].usage.bytes));
+
+    // Limit the number of accounts
+    let usage = api
+        .get::<KeySpaceUsage>("/api/store/usage?limit=1")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(usage.accounts.len(), 1);
+
+    // Unknown stores are rejected
+    assert_eq!(
+        api.get::<KeySpaceUsage>("/api/store/usage/unknown")
+            .await
+            .unwrap()
+            .unwrap_error()
+            .0,
+        "notFound"
+    );
+
+    // Remove test data
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -0,0 +1,144 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use hyper::Method;
+use jmap::{email::trash::TrashRestoreReport, mailbox::INBOX_ID};
+use jmap_client::{core::query::Filter, email, mailbox::Role};
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, mailbox::destroy_all_mailboxes, ManagementApi},
+};
+
+use super::JMAPTest;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrashEntry {
+    hash: String,
+    subject: Option<String>,
+    keywords: Vec<String>,
+}
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running trash tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jtrash@example.com",
+            "12345",
+            "Jane Trash",
+            &["jtrash@example.com"],
+        )
+        .await;
+    let client = params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    let inbox_id = Id::from(INBOX_ID).to_string();
+    let projects_id = client
+        .mailbox_create("Projects", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+
+    // Import and then delete two messages
+    for (num, mailbox_id) in [&inbox_id, &projects_id].into_iter().enumerate() {
+        let email_id = client
+            .email_import(
+                format!(
+                    concat!(
+                        "From: bill@example.com\r\n",
+                        "To: jtrash@example.com\r\n",
+                        "Subject: TPS Report #{}\r\n",
+                        "\r\n",
+                        "I'm going to need those TPS reports ASAP."
+                    ),
+                    num
+                )
+                .into_bytes(),
+                [mailbox_id],
+                ["$flagged"].into(),
+                None,
+            )
+            .await
+            .unwrap()
+            .take_id();
+        client.email_destroy(&email_id).await.unwrap();
+    }
+
+    // Deleted messages are held in the trash
+    let entries = api
+        .get::<Vec<TrashEntry>>("/api/store/trash/jtrash@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(entries.len(), 2, "{entries:?}");
+    let entry = entries
+        .iter()
+        .find(|entry| entry.subject.as_deref() == Some("TPS Report #1"))
+        .unwrap();
+    assert_eq!(entry.keywords, vec!["$flagged".to_string()]);
+
+    // Restore a single message to its original mailbox
+    let report = api
+        .post::<TrashRestoreReport>(
+            "/api/store/trash/jtrash@example.com",
+            &vec![entry.hash.clone()],
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(report.account_id, account_id);
+    assert_eq!(report.restored_emails, 1);
+    assert_eq!(report.failed_emails, 0);
+    assert_eq!(report.not_found_emails, 0);
+    assert_eq!(
+        client
+            .email_query(
+                Filter::and(vec![
+                    email::query::Filter::in_mailbox(&projects_id),
+                    email::query::Filter::has_keyword("$flagged")
+                ])
+                .into(),
+                None::<Vec<_>>
+            )
+            .await
+            .unwrap()
+            .ids()
+            .len(),
+        1
+    );
+    assert_eq!(
+        api.get::<Vec<TrashEntry>>("/api/store/trash/jtrash@example.com")
+            .await
+            .unwrap()
+            .unwrap_data()
+            .len(),
+        1
+    );
+
+    // Empty the trash
+    api.request::<()>(Method::DELETE, "/api/store/trash/jtrash@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(api
+        .get::<Vec<TrashEntry>>("/api/store/trash/jtrash@example.com")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .is_empty());
+
+    // Remove test data
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -6,6 +6,8 @@
 
 use chrono::{TimeDelta, Utc};
 
+use jmap::vacation::replies::VacationReplies;
+use jmap_client::mailbox;
 use jmap_proto::types::id::Id;
 use std::time::Instant;
 
@@ -17,6 +19,7 @@ use crate::{
         email_submission::{
             assert_message_delivery, expect_nothing, spawn_mock_smtp_server, MockMessage,
         },
+        jmap_json_request,
         mailbox::destroy_all_mailboxes,
     },
 };
@@ -106,6 +109,12 @@ pub async fn test(params: &mut JMAPTest) {
 
     expect_nothing(&mut smtp_rx).await;
 
+    // Recipients of vacation responses are tracked per account
+    let document_id = Id::from_bytes(account_id.as_bytes()).unwrap().document_id();
+    let replies = server.vacation_replies(document_id).await.unwrap();
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].recipient, "bill@remote.org");
+
     // Messages from MAILER-DAEMON should not
     // trigger a vacation response
     lmtp.ingest(
@@ -157,6 +166,151 @@ pub async fn test(params: &mut JMAPTest) {
         )
         .await
         .unwrap();
+
+    // Tracking survives script updates until the recipient is cleared
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: TPS Report -- one more thing\r\n",
+            "\r\n",
+            "Did you get the memo about the new cover sheets?",
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+
+    assert_eq!(
+        server
+            .vacation_replies_clear(document_id, Some("bill@remote.org"))
+            .await
+            .unwrap(),
+        1
+    );
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: TPS Report -- last reminder\r\n",
+            "\r\n",
+            "I'll go ahead and send you another copy of that memo.",
+        ),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new("<jdoe@example.com>", ["<bill@remote.org>"], "@Kokomo"),
+    )
+    .await;
+
+    // The vacation response runs ahead of the active Sieve script
+    let filter_id = client
+        .sieve_script_create(
+            "filter",
+            b"require [\"fileinto\", \"mailbox\"];\r\nfileinto :create \"Filtered\";\r\n".to_vec(),
+            true,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let response = jmap_json_request(
+        format!(r#"[[ "VacationResponse/get", {{ "accountId": "{account_id}" }}, "0" ]]"#),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let vacation = &response["methodResponses"][0][1]["list"][0];
+    assert_eq!(vacation["isEnabled"], true, "{response}");
+    assert!(vacation["sieveConflict"].is_null(), "{response}");
+    lmtp.ingest(
+        "ted@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: ted@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Cover sheets\r\n",
+            "\r\n",
+            "Yeah, we're putting new cover sheets on all the TPS reports.",
+        ),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new("<jdoe@example.com>", ["<ted@remote.org>"], "@Kokomo"),
+    )
+    .await;
+    assert!(!client
+        .mailbox_query(
+            mailbox::query::Filter::name("Filtered").into(),
+            None::<Vec<_>>
+        )
+        .await
+        .unwrap()
+        .ids()
+        .is_empty());
+
+    // Scripts that send their own vacation responses are reported as conflicts
+    let autoreply_id = client
+        .sieve_script_create(
+            "autoreply",
+            b"require \"vacation\";\r\nvacation \"Gone fishing\";\r\n".to_vec(),
+            true,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let response = jmap_json_request(
+        format!(r#"[[ "VacationResponse/get", {{ "accountId": "{account_id}" }}, "0" ]]"#),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let vacation = &response["methodResponses"][0][1]["list"][0];
+    assert_eq!(vacation["isEnabled"], true, "{response}");
+    assert!(
+        vacation["sieveConflict"]
+            .as_str()
+            .is_some_and(|conflict| conflict.contains("autoreply")),
+        "{response}"
+    );
+    lmtp.ingest(
+        "lisa@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: lisa@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Birthday cake\r\n",
+            "\r\n",
+            "We're having cake for Lumbergh's birthday.",
+        ),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new("<jdoe@example.com>", ["<lisa@remote.org>"], "Gone fishing"),
+    )
+    .await;
+
+    // Deactivating the user's scripts leaves the vacation response enabled
+    client.sieve_script_deactivate().await.unwrap();
+    for script_id in [&filter_id, &autoreply_id] {
+        client.sieve_script_destroy(script_id).await.unwrap();
+    }
+    let response = jmap_json_request(
+        format!(r#"[[ "VacationResponse/get", {{ "accountId": "{account_id}" }}, "0" ]]"#),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["list"][0]["isEnabled"], true,
+        "{response}"
+    );
+
     smtp_settings.lock().do_stop = true;
     lmtp.ingest(
         "jane_smith@remote.org",
@@ -180,6 +334,11 @@ pub async fn test(params: &mut JMAPTest) {
 
     // Remove test data
     client.vacation_response_destroy().await.unwrap();
+    assert!(server
+        .vacation_replies(document_id)
+        .await
+        .unwrap()
+        .is_empty());
     destroy_all_mailboxes(params).await;
     assert_is_empty(server).await;
 }
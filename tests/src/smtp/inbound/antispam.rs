@@ -41,6 +41,9 @@ const CONFIG: &str = r#"
 [spam.header]
 is-spam = "X-Spam-Status: Yes"
 
+[spam.export]
+path = "{PATH}/spam_export.jsonl"
+
 [lookup.spam-config]
 add-spam = true
 add-spam-result = true
@@ -55,10 +58,13 @@ threshold-discard = 0
 threshold-reject = 0
 directory = ""
 lookup = ""
+protected-names = true
+brand-domains = "paypal.com,microsoft.com"
 llm-model = "dummy"
 llm-prompt = "You are an AI assistant specialized in analyzing email content to detect unsolicited, commercial, or harmful messages. Format your response as follows, separated by commas: Category,Confidence,Explanation
 Here's the email to analyze, please provide your analysis based on the above instructions, ensuring your response is in the specified comma-separated format:"
 add-llm-result = false
+export-enable = true
 
 [session.rcpt]
 relay = true
@@ -117,6 +123,7 @@ allow-invalid-certs = true
                 "hta" = "BAD|NZ" }
 "spam-trap" = {"spamtrap@*"}
 "spam-allow" = {"stalw.art"}
+"spam-protected-names" = {"jane doe" = "jane.doe@gmail.com", "example.org:john smith" = ""}
 
 [sieve.trusted.scripts]
 "#;
@@ -516,6 +523,35 @@ async fn antispam() {
             }
         }
     }
+
+    // Make sure the exported decisions contain only tags, scores and dispositions
+    let export = fs::read_to_string(tmp_dir.temp_dir.join("spam_export.jsonl")).unwrap();
+    let mut has_spam = false;
+    for line in export.lines() {
+        assert!(!line.contains('@'), "{line}");
+        let record = serde_json::from_str::<serde_json::Value>(line).unwrap();
+        let record = record.as_object().unwrap();
+        let mut keys = record.keys().map(|k| k.as_str()).collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, ["disposition", "features", "score", "timestamp"]);
+        let score = record["score"].as_f64().unwrap();
+        let disposition = record["disposition"].as_str().unwrap();
+        assert_eq!(disposition == "spam", score >= 5.0, "{line}");
+        for (tag, score) in record["features"].as_object().unwrap() {
+            assert!(
+                tag.chars()
+                    .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_'),
+                "{line}"
+            );
+            assert!(score.is_f64(), "{line}");
+        }
+        has_spam |= disposition == "spam"
+            && record["features"]
+                .as_object()
+                .unwrap()
+                .contains_key("RDNS_NONE");
+    }
+    assert!(has_spam, "{export}");
 }
 
 #[test]
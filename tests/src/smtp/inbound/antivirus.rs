@@ -0,0 +1,306 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use common::Core;
+use smtp::core::Session;
+use store::Stores;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+};
+use utils::config::Config;
+
+use crate::smtp::{
+    inbound::TestMessage,
+    session::{DummyIo, TestSession, VerifyResponse},
+    QueueReceiver, TempDir, TestSMTP,
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[session.rcpt]
+relay = true
+
+[session.data.antivirus]
+type = "clamd"
+address = "127.0.0.1:9340"
+url = "icap://127.0.0.1:9341/avscan"
+action = "reject"
+on-error = "tempfail"
+timeout = "1s"
+"#;
+
+const CLEAN_MESSAGE: &str = concat!(
+    "From: john@doe.org\r\n",
+    "To: bill@foobar.org\r\n",
+    "Subject: Quarterly report\r\n",
+    "\r\n",
+    "The quarterly report is ready."
+);
+
+const INFECTED_MESSAGE: &str = concat!(
+    "From: john@doe.org\r\n",
+    "To: bill@foobar.org\r\n",
+    "Subject: Invoice\r\n",
+    "MIME-Version: 1.0\r\n",
+    "Content-Type: multipart/mixed; boundary=\"boundary\"\r\n",
+    "\r\n",
+    "--boundary\r\n",
+    "Content-Type: text/plain\r\n",
+    "\r\n",
+    "Please find the invoice attached.\r\n",
+    "--boundary\r\n",
+    "Content-Type: application/octet-stream; name=\"invoice.exe\"\r\n",
+    "Content-Transfer-Encoding: base64\r\n",
+    "\r\n",
+    "TUFMV0FSRS1URVNULVBBWUxPQUQ=\r\n",
+    "--boundary--\r\n"
+);
+
+const SIGNATURE: &[u8] = b"MALWARE-TEST-PAYLOAD";
+
+#[tokio::test]
+async fn antivirus() {
+    // Enable logging
+    crate::enable_logging();
+
+    let clamd_requests = Arc::new(AtomicUsize::new(0));
+    let icap_requests = Arc::new(AtomicUsize::new(0));
+    let _tx_clamd = spawn_mock_scanner("127.0.0.1:9340", clamd_requests.clone(), handle_clamd);
+    let _tx_icap = spawn_mock_scanner("127.0.0.1:9341", icap_requests.clone(), handle_icap);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Infected messages are rejected
+    let (mut session, mut qr, _tmp_dir) = build_session("smtp_antivirus_clamd", CONFIG).await;
+    session
+        .send_message("john@doe.org", &["bill@foobar.org"], CLEAN_MESSAGE, "250")
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_not_contains("X-Virus-Status");
+    assert_eq!(clamd_requests.load(Ordering::Relaxed), 1);
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org"],
+            INFECTED_MESSAGE,
+            "550 5.7.1 Message rejected, malware found (Test-Malware)",
+        )
+        .await;
+    qr.assert_no_events();
+    assert_eq!(clamd_requests.load(Ordering::Relaxed), 3);
+
+    // Verdicts are cached by content hash
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org"],
+            INFECTED_MESSAGE,
+            "550 5.7.1",
+        )
+        .await;
+    session
+        .send_message("john@doe.org", &["bill@foobar.org"], CLEAN_MESSAGE, "250")
+        .await;
+    qr.expect_message().await;
+    assert_eq!(clamd_requests.load(Ordering::Relaxed), 3);
+
+    // Infected messages are tagged or quarantined
+    for (action, header) in [
+        ("tag", "X-Virus-Status: Infected (Test-Malware)"),
+        ("quarantine", "X-Quarantine: Malware found (Test-Malware)"),
+    ] {
+        let (mut session, mut qr, _tmp_dir) = build_session(
+            &format!("smtp_antivirus_{action}"),
+            &CONFIG.replace("action = \"reject\"", &format!("action = \"{action}\"")),
+        )
+        .await;
+        session
+            .send_message(
+                "john@doe.org",
+                &["bill@foobar.org"],
+                INFECTED_MESSAGE,
+                "250",
+            )
+            .await;
+        qr.expect_message()
+            .await
+            .read_lines(&qr)
+            .await
+            .assert_contains(header);
+    }
+
+    // ICAP servers are also supported
+    let (mut session, mut qr, _tmp_dir) = build_session(
+        "smtp_antivirus_icap",
+        &CONFIG.replace("type = \"clamd\"", "type = \"icap\""),
+    )
+    .await;
+    session
+        .send_message("john@doe.org", &["bill@foobar.org"], CLEAN_MESSAGE, "250")
+        .await;
+    qr.expect_message().await;
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org"],
+            INFECTED_MESSAGE,
+            "550 5.7.1 Message rejected, malware found (Test-Malware)",
+        )
+        .await;
+    qr.assert_no_events();
+    assert_eq!(icap_requests.load(Ordering::Relaxed), 3);
+
+    // Scanner errors fail closed or open depending on the policy
+    let (mut session, mut qr, _tmp_dir) = build_session(
+        "smtp_antivirus_fail_closed",
+        &CONFIG.replace("127.0.0.1:9340", "127.0.0.1:9349"),
+    )
+    .await;
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org"],
+            CLEAN_MESSAGE,
+            "451 4.3.5",
+        )
+        .await;
+    qr.assert_no_events();
+    let (mut session, mut qr, _tmp_dir) = build_session(
+        "smtp_antivirus_fail_open",
+        &CONFIG
+            .replace("127.0.0.1:9340", "127.0.0.1:9349")
+            .replace("on-error = \"tempfail\"", "on-error = \"accept\""),
+    )
+    .await;
+    session
+        .send_message("john@doe.org", &["bill@foobar.org"], CLEAN_MESSAGE, "250")
+        .await;
+    qr.expect_message().await;
+}
+
+async fn build_session(name: &str, config: &str) -> (Session<DummyIo>, QueueReceiver, TempDir) {
+    let tmp_dir = TempDir::new(name, true);
+    let mut config = Config::new(tmp_dir.update_config(config)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    let test = TestSMTP::from_core(core);
+    let mut session = Session::test(test.server);
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+    (session, test.queue_receiver, tmp_dir)
+}
+
+fn spawn_mock_scanner(
+    address: &'static str,
+    requests: Arc<AtomicUsize>,
+    handler: fn(&[u8]) -> Option<Vec<u8>>,
+) -> watch::Sender<bool> {
+    let (tx, mut rx) = watch::channel(true);
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind(address).await.unwrap_or_else(|e| {
+            panic!("Failed to bind mock malware scanner to {address}: {e}");
+        });
+        loop {
+            tokio::select! {
+                stream = listener.accept() => {
+                    let (stream, _) = stream.unwrap();
+                    requests.fetch_add(1, Ordering::Relaxed);
+                    tokio::spawn(handle_connection(stream, handler));
+                },
+                _ = rx.changed() => {
+                    break;
+                }
+            };
+        }
+    });
+
+    tx
+}
+
+async fn handle_connection(mut stream: TcpStream, handler: fn(&[u8]) -> Option<Vec<u8>>) {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let len = stream.read(&mut buf).await.unwrap();
+        if len == 0 {
+            return;
+        }
+        request.extend_from_slice(&buf[..len]);
+        if let Some(response) = handler(&request) {
+            stream.write_all(&response).await.unwrap();
+            return;
+        }
+    }
+}
+
+/// Returns the clamd response once the INSTREAM terminator was received.
+fn handle_clamd(request: &[u8]) -> Option<Vec<u8>> {
+    let mut data = request.strip_prefix(b"zINSTREAM\0")?;
+    let mut contents = Vec::new();
+    loop {
+        let len = u32::from_be_bytes(data.get(..4)?.try_into().unwrap()) as usize;
+        if len == 0 {
+            break;
+        }
+        contents.extend_from_slice(data.get(4..4 + len)?);
+        data = &data[4 + len..];
+    }
+
+    Some(if contains(&contents, SIGNATURE) {
+        b"stream: Test-Malware FOUND\0".to_vec()
+    } else {
+        b"stream: OK\0".to_vec()
+    })
+}
+
+/// Returns the ICAP response once the last chunk was received.
+fn handle_icap(request: &[u8]) -> Option<Vec<u8>> {
+    if !request.ends_with(b"0\r\n\r\n") {
+        return None;
+    }
+    assert!(request.starts_with(b"RESPMOD icap://127.0.0.1:9341/avscan ICAP/1.0\r\n"));
+
+    Some(if contains(request, SIGNATURE) {
+        concat!(
+            "ICAP/1.0 200 OK\r\n",
+            "X-Infection-Found: Type=0; Resolution=2; Threat=Test-Malware;\r\n",
+            "Encapsulated: null-body=0\r\n\r\n"
+        )
+        .as_bytes()
+        .to_vec()
+    } else {
+        b"ICAP/1.0 204 No Content\r\n\r\n".to_vec()
+    })
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
@@ -0,0 +1,179 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    session::{TestSession, VerifyResponse},
+    TestSMTP,
+};
+
+const CONFIG: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.data.attachments."domain-partner"]
+enable = "rcpt_domain = 'partner.org'"
+block.extensions = ["scr"]
+
+[session.data.attachments."global"]
+block.extensions = ["exe", "scr"]
+block.types = ["elf"]
+max-size = 100
+oversize = "strip"
+mismatch = "reject"
+
+[session.data.attachments."remote"]
+stage = "delivery"
+enable = "rcpt_domain = 'foobar.net'"
+block.extensions = ["zip"]
+"#;
+
+#[tokio::test]
+async fn attachments() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut local = TestSMTP::new("smtp_attachments", CONFIG).await;
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+
+    // Attachments that comply with the policy are accepted
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org"],
+            &message("report.pdf", "application/pdf", b"%PDF-1.4 report"),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("JVBERi0xLjQgcmVwb3J0");
+
+    // Blocked extensions, blocked file types and extension mismatches are rejected
+    for (name, content_type, contents, response) in [
+        (
+            "setup.exe",
+            "application/octet-stream",
+            b"MZ\x90\x00 setup".as_slice(),
+            "550 5.7.1 Message rejected, attachment \"setup.exe\" has a blocked file extension (.exe).",
+        ),
+        (
+            "notes.txt",
+            "text/plain",
+            b"\x7fELF\x02\x01 tool".as_slice(),
+            "550 5.7.1 Message rejected, attachment \"notes.txt\" has a blocked file type (elf).",
+        ),
+        (
+            "invoice.pdf",
+            "application/pdf",
+            b"MZ\x90\x00 setup".as_slice(),
+            "550 5.7.1 Message rejected, attachment \"invoice.pdf\" is declared as pdf but contains exe data.",
+        ),
+    ] {
+        session
+            .send_message(
+                "john@doe.org",
+                &["bill@foobar.org"],
+                &message(name, content_type, contents),
+                response,
+            )
+            .await;
+        local.queue_receiver.assert_no_events();
+    }
+
+    // Oversized attachments are stripped
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org"],
+            &message("data.bin", "application/octet-stream", &[b'A'; 200]),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("Please find the file attached.")
+        .assert_contains("The attachment \"data.bin\" was removed from this message because it exceeds the maximum size of 100 bytes.")
+        .assert_not_contains("QUFBQUFB");
+
+    // Per-domain policies override the global one
+    session
+        .send_message(
+            "john@doe.org",
+            &["jane@partner.org"],
+            &message("setup.exe", "application/octet-stream", b"MZ\x90\x00 setup"),
+            "250",
+        )
+        .await;
+    local.queue_receiver.expect_message().await;
+
+    // Policies enforced at delivery generate an NDN
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.net"],
+            &message("archive.zip", "application/zip", b"PK\x03\x04 archive"),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(local.build_smtp())
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("<bill@foobar.net>")
+        .assert_contains("Action: failed")
+        .assert_contains("Status: 5.7.1");
+    local.queue_receiver.read_event().await.assert_reload();
+}
+
+fn message(name: &str, content_type: &str, contents: &[u8]) -> String {
+    format!(
+        concat!(
+            "From: john@doe.org\r\n",
+            "To: bill@foobar.org\r\n",
+            "Subject: Attachment\r\n",
+            "MIME-Version: 1.0\r\n",
+            "Content-Type: multipart/mixed; boundary=\"boundary\"\r\n",
+            "\r\n",
+            "--boundary\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Please find the file attached.\r\n",
+            "--boundary\r\n",
+            "Content-Type: {}; name=\"{}\"\r\n",
+            "Content-Disposition: attachment; filename=\"{}\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "{}\r\n",
+            "--boundary--\r\n"
+        ),
+        content_type,
+        name,
+        name,
+        STANDARD.encode(contents)
+    )
+}
@@ -16,7 +16,10 @@ use crate::{
     },
     AssertConfig,
 };
-use smtp::core::{Session, State};
+use smtp::{
+    core::{Session, State},
+    inbound::spam::parse_spam_score,
+};
 
 const CONFIG: &str = r#"
 [storage]
@@ -161,3 +164,204 @@ async fn auth() {
         .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "503 5.5.1")
         .await;
 }
+
+const ANOMALY_CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+directory = "local"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = ["john@example.org"]
+
+[[directory."local".principals]]
+name = "jane"
+description = "Jane Doe"
+secret = "secret"
+email = ["jane@example.org"]
+
+[session.auth]
+require = true
+mechanisms = "[plain]"
+directory = "'local'"
+
+[session.auth.anomaly]
+enable = true
+recipients = "3/1h"
+suspend = "1h"
+
+[session.auth.anomaly.volume]
+account = "2/1h"
+ip = false
+
+[session.auth.anomaly.network]
+rate = false
+"#;
+
+#[tokio::test]
+async fn auth_anomaly() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_auth_anomaly_test", true);
+    let mut config = Config::new(tmp_dir.update_config(ANOMALY_CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    let mut session = Session::test(TestSMTP::from_core(core).server);
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+
+    // Submission is suspended once the volume threshold is exceeded
+    for expected_code in ["250", "250", "451 4.7.1"] {
+        session
+            .send_message(
+                "john@example.org",
+                &["bill@foobar.org"],
+                "Subject: test\r\n\r\ntest",
+                expected_code,
+            )
+            .await;
+    }
+    session.mail_from("john@example.org", "451 4.7.1").await;
+
+    // Submission is suspended once too many unique recipients are used
+    let mut session = Session::test(session.server.clone());
+    session.data.remote_ip_str = "10.0.0.2".to_string();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGphbmUAc2VjcmV0", "235 2.7.0")
+        .await;
+    session
+        .send_message(
+            "jane@example.org",
+            &["bill@foobar.org", "mike@foobar.org"],
+            "Subject: test\r\n\r\ntest",
+            "250",
+        )
+        .await;
+    session
+        .send_message(
+            "jane@example.org",
+            &["bill@foobar.org", "ann@foobar.org", "joe@foobar.org"],
+            "Subject: test\r\n\r\ntest",
+            "451 4.7.1",
+        )
+        .await;
+    session.mail_from("jane@example.org", "451 4.7.1").await;
+}
+
+const OUTBOUND_SPAM_CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+directory = "local"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = ["john@example.org"]
+
+[session.auth]
+require = true
+mechanisms = "[plain]"
+directory = "'local'"
+
+[session.data.outbound-spam]
+enable = true
+script = "outbound-spam"
+threshold = 6.0
+action = "hold"
+
+[sieve.trusted.scripts."outbound-spam"]
+contents = '''
+if header :contains "Subject" "lottery" {
+    eval "add_header('X-Spam-Status', 'Yes, score=12.5')";
+} else {
+    eval "add_header('X-Spam-Status', 'No, score=0.5')";
+}
+'''
+"#;
+
+#[tokio::test]
+async fn auth_outbound_spam() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_auth_outbound_spam_test", true);
+    let mut config = Config::new(tmp_dir.update_config(OUTBOUND_SPAM_CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    // Spam scores are obtained from the status header
+    assert_eq!(parse_spam_score("Yes, score=12.5"), Some(12.5));
+    assert_eq!(parse_spam_score("No, score=-1.2"), Some(-1.2));
+    assert_eq!(parse_spam_score("No"), None);
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.stream.tls = true;
+    session.ehlo("mx.foobar.org").await;
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "235 2.7.0")
+        .await;
+
+    // Clean messages are scheduled for delivery
+    session
+        .send_message(
+            "john@example.org",
+            &["bill@foobar.org"],
+            "Subject: meeting\r\n\r\ntest",
+            "250",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert!(message.domains[0].retry.due < message.domains[0].expires);
+
+    // Spam is held until released by an administrator
+    session
+        .send_message(
+            "john@example.org",
+            &["bill@foobar.org"],
+            "Subject: you won the lottery\r\n\r\ntest",
+            "250",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert_eq!(message.domains[0].retry.due, message.domains[0].expires);
+    assert_eq!(message.domains[0].notify.due, message.domains[0].expires);
+}
@@ -0,0 +1,169 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use smtp::core::bounce::SuppressionManager;
+
+use crate::smtp::{inbound::TestQueueEvent, session::TestSession, TestSMTP};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@test.org"
+
+[session.rcpt]
+directory = "'local'"
+relay = true
+
+[session.data]
+verp = "sender_domain = 'news.test.org'"
+
+[queue.suppression]
+enable = true
+"#;
+
+#[tokio::test]
+async fn bounce() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut local = TestSMTP::new("smtp_bounce", CONFIG).await;
+    let server = local.build_smtp();
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.foobar.org").await;
+
+    // Bounces addressed to local senders suppress the failed recipients
+    session
+        .send_message("<>", &["john@test.org"], &dsn("bill@foobar.org"), "250")
+        .await;
+    local.queue_receiver.expect_message().await;
+    assert_eq!(
+        suppressed(&server, "john@test.org").await,
+        ["bill@foobar.org"]
+    );
+
+    // VERP addresses identify the original recipient
+    session
+        .send_message(
+            "<>",
+            &["john+jane=foobar.org@test.org"],
+            &dsn("jane@forwarded.org"),
+            "250",
+        )
+        .await;
+    local.queue_receiver.expect_message().await;
+    assert_eq!(
+        suppressed(&server, "john@test.org").await,
+        ["bill@foobar.org", "jane@foobar.org"]
+    );
+
+    // Suppressed recipients are rejected for that sender only
+    session.mail_from("john@test.org", "250").await;
+    session.rcpt_to("bill@foobar.org", "550 5.1.1").await;
+    session.rcpt_to("jane@foobar.org", "550 5.1.1").await;
+    session.rcpt_to("mike@foobar.org", "250").await;
+    session.rset().await;
+    session.mail_from("jane@test.org", "250").await;
+    session.rcpt_to("bill@foobar.org", "250").await;
+    session.rset().await;
+
+    // Removed entries are accepted again
+    assert!(server
+        .suppression_remove("john@test.org", "bill@foobar.org")
+        .await
+        .unwrap());
+    assert!(!server
+        .suppression_remove("john@test.org", "bill@foobar.org")
+        .await
+        .unwrap());
+    session.mail_from("john@test.org", "250").await;
+    session.rcpt_to("bill@foobar.org", "250").await;
+    session.rset().await;
+
+    // Each recipient of a VERP sender is queued with its own return path
+    session
+        .send_message(
+            "news@news.test.org",
+            &["bill@foobar.org", "mike@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    for _ in 0..2 {
+        local.queue_receiver.read_event().await.assert_reload();
+    }
+    let mut return_paths = local
+        .queue_receiver
+        .read_queued_messages()
+        .await
+        .into_iter()
+        .filter(|message| message.return_path_domain == "news.test.org")
+        .map(|message| {
+            assert_eq!(message.recipients.len(), 1);
+            assert_eq!(message.domains.len(), 1);
+            message.return_path
+        })
+        .collect::<Vec<_>>();
+    return_paths.sort_unstable();
+    assert_eq!(
+        return_paths,
+        [
+            "news+bill=foobar.org@news.test.org",
+            "news+mike=foobar.org@news.test.org"
+        ]
+    );
+}
+
+async fn suppressed(server: &Server, sender: &str) -> Vec<String> {
+    let mut addresses = server
+        .suppression_list(sender)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.address)
+        .collect::<Vec<_>>();
+    addresses.sort_unstable();
+    addresses
+}
+
+fn dsn(rcpt: &str) -> String {
+    format!(
+        concat!(
+            "From: MAILER-DAEMON@foobar.org\r\n",
+            "To: john@test.org\r\n",
+            "Subject: Undelivered Mail Returned to Sender\r\n",
+            "MIME-Version: 1.0\r\n",
+            "Content-Type: multipart/report; report-type=delivery-status;\r\n",
+            "\tboundary=\"dsn\"\r\n",
+            "\r\n",
+            "--dsn\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Your message could not be delivered.\r\n",
+            "--dsn\r\n",
+            "Content-Type: message/delivery-status\r\n",
+            "\r\n",
+            "Reporting-MTA: dns; mx.foobar.org\r\n",
+            "\r\n",
+            "Final-Recipient: rfc822; {}\r\n",
+            "Action: failed\r\n",
+            "Status: 5.1.1\r\n",
+            "\r\n",
+            "--dsn--\r\n"
+        ),
+        rcpt
+    )
+}
@@ -16,7 +16,7 @@ use crate::{
     },
     AssertConfig,
 };
-use smtp::core::Session;
+use smtp::{core::Session, inbound::classify::strip_classification_headers};
 
 const CONFIG: &str = r#"
 [storage]
@@ -78,6 +78,11 @@ date = [{if = "remote_ip = '10.0.0.3'", then = true},
         {else = false}]
 return-path =  [{if = "remote_ip = '10.0.0.3'", then = true},
             {else = false}]
+classification =  [{if = "remote_ip = '10.0.0.3'", then = true},
+                   {else = false}]
+
+[session.data.classification.tag]
+Category = "'inbound'"
 
 [[queue.quota]]
 match = "sender = 'john@doe.org'"
@@ -177,7 +182,23 @@ async fn data() {
         .assert_contains("Return-Path: ")
         .assert_contains("Received: ")
         .assert_contains("Authentication-Results: ")
-        .assert_contains("Received-SPF: ");
+        .assert_contains("Received-SPF: ")
+        .assert_contains("X-Stalwart-Category: inbound")
+        .assert_contains("X-Stalwart-Verdict: spf=")
+        .assert_contains("X-Stalwart-Timings: ");
+
+    // Classification headers are removed before leaving the server
+    assert_eq!(
+        strip_classification_headers(
+            b"X-Stalwart-Verdict: spf=pass;\r\n\tdkim=none\r\nSubject: test\r\nx-stalwart-timings: total=1ms\r\n\r\nX-Stalwart-Body: kept\r\n"
+        )
+        .unwrap(),
+        b"Subject: test\r\n\r\nX-Stalwart-Body: kept\r\n"
+    );
+    assert_eq!(
+        strip_classification_headers(b"Subject: test\r\n\r\nbody"),
+        None
+    );
 
     // Only one message is allowed in the queue from john@doe.org
     session.data.remote_ip_str = "10.0.0.2".to_string();
@@ -0,0 +1,114 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::config::server::ServerProtocol;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use crate::smtp::TestSMTP;
+
+const CONFIG: &str = r#"
+[session.rcpt]
+relay = true
+
+[server.shutdown]
+grace-period = "2s"
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn connection_draining() {
+    // Enable logging
+    crate::enable_logging();
+
+    let test = TestSMTP::new("smtp_connection_draining", CONFIG).await;
+    let shutdown_tx = test.start(&[ServerProtocol::Smtp]).await;
+    let server = test.server.clone();
+
+    // Open an idle session and a session with a transaction in progress
+    let mut idle = SmtpClient::connect().await;
+    idle.cmd("EHLO mx.test.org", "250").await;
+    let mut busy = SmtpClient::connect().await;
+    busy.cmd("EHLO mx.test.org", "250").await;
+    busy.cmd("MAIL FROM:<john@test.org>", "250").await;
+    busy.cmd("RCPT TO:<bill@foobar.org>", "250").await;
+    assert_eq!(server.in_flight_sessions(), 2);
+
+    // Idle sessions are closed right away while the transaction is allowed to finish
+    shutdown_tx.send(true).unwrap();
+    idle.expect("421 4.3.0").await;
+    busy.cmd("DATA", "354").await;
+    busy.cmd(
+        "From: john@test.org\r\nSubject: Draining\r\n\r\nTest message.\r\n.",
+        "250",
+    )
+    .await;
+    busy.expect("421 4.3.0").await;
+    assert!(server.drain().await.is_empty());
+    assert_eq!(server.in_flight_sessions(), 0);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn connection_draining_timeout() {
+    // Enable logging
+    crate::enable_logging();
+
+    let test = TestSMTP::new("smtp_connection_draining_timeout", CONFIG).await;
+    let shutdown_tx = test.start(&[ServerProtocol::Smtp]).await;
+    let server = test.server.clone();
+
+    // Sessions that do not complete their transaction are closed after the grace period
+    let mut busy = SmtpClient::connect().await;
+    busy.cmd("EHLO mx.test.org", "250").await;
+    busy.cmd("MAIL FROM:<john@test.org>", "250").await;
+    shutdown_tx.send(true).unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    busy.cmd("RCPT TO:<bill@foobar.org>", "250").await;
+    busy.expect("421 4.3.0").await;
+    assert!(server.drain().await.is_empty());
+}
+
+struct SmtpClient {
+    reader: BufReader<TcpStream>,
+}
+
+impl SmtpClient {
+    async fn connect() -> Self {
+        let mut client = SmtpClient {
+            reader: BufReader::new(TcpStream::connect("127.0.0.1:9925").await.unwrap()),
+        };
+        client.expect("220").await;
+        client
+    }
+
+    async fn cmd(&mut self, cmd: &str, code: &str) {
+        self.reader
+            .get_mut()
+            .write_all(format!("{cmd}\r\n").as_bytes())
+            .await
+            .unwrap();
+        self.expect(code).await;
+    }
+
+    async fn expect(&mut self, code: &str) {
+        loop {
+            let mut line = String::new();
+            tokio::time::timeout(Duration::from_secs(5), self.reader.read_line(&mut line))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(line.starts_with(code), "Expected {code:?}, got {line:?}");
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+        }
+    }
+}
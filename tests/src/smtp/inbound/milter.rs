@@ -27,6 +27,7 @@ use smtp::{
             receiver::{FrameResult, Receiver},
             Action, Command, Macros, MilterClient, Modification, Options, Response,
         },
+        rspamd::{RspamdMilter, RspamdResponse, RspamdSymbol},
     },
 };
 use store::Stores;
@@ -95,6 +96,58 @@ enable = true
 stages = ["data"]
 "#;
 
+const CONFIG_WASM_HOOK: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[session.rcpt]
+relay = true
+
+[session.hook."reject"]
+module = "{WASM}/reject.wat"
+enable = [{if = "rcpt = 'blocked@foobar.org'", then = true},
+          {else = false}]
+stages = ["rcpt"]
+
+[session.hook."loop"]
+module = "{WASM}/loop.wat"
+enable = [{if = "sender = 'loop@doe.org'", then = true},
+          {else = false}]
+stages = ["mail"]
+limits.fuel = 100000
+
+[session.hook."classify"]
+module = "{WASM}/classify.wat"
+enable = true
+stages = ["classify"]
+"#;
+
+const CONFIG_RSPAMD: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[session.rcpt]
+relay = true
+
+[session.data.rspamd]
+url = "http://127.0.0.1:9334"
+enable = true
+"#;
+
 #[tokio::test]
 async fn milter_session() {
     // Enable logging
@@ -355,6 +408,135 @@ async fn mta_hook_session() {
         .assert_contains("123456");
 }
 
+#[tokio::test]
+async fn wasm_hook_session() {
+    // Configure tests
+    let tmp_dir = TempDir::new("smtp_wasm_hook_test", true);
+    let wasm_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join("smtp")
+        .join("wasm");
+    let mut config = Config::new(
+        tmp_dir.update_config(CONFIG_WASM_HOOK.replace("{WASM}", wasm_path.to_str().unwrap())),
+    )
+    .unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    assert_eq!(core.smtp.session.wasm_hooks.len(), 3);
+
+    // Build session
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server);
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+
+    // Modules can reject with a custom response
+    session.mail_from("john@doe.org", "250").await;
+    session
+        .rcpt_to(
+            "blocked@foobar.org",
+            "550 5.7.1 Recipient blocked by policy",
+        )
+        .await;
+    session.rset().await;
+
+    // Modules running out of fuel fail temporarily
+    session.mail_from("loop@doe.org", "451 4.3.5").await;
+
+    // Modules are invoked once the message has been classified
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("X-Policy: classified")
+        .assert_contains("Subject: Is dinner ready?");
+}
+
+#[tokio::test]
+async fn rspamd_session() {
+    // Configure tests
+    let tmp_dir = TempDir::new("smtp_rspamd_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG_RSPAMD)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    let _rx = spawn_mock_rspamd_server();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Build session
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server);
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+
+    // Test reject using the message returned by Rspamd
+    session
+        .send_message(
+            "reject@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "550 5.7.1 Gtube pattern",
+        )
+        .await;
+    qr.assert_no_events();
+
+    // Test greylisting
+    session
+        .send_message(
+            "greylist@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "451 4.7.1",
+        )
+        .await;
+    qr.assert_no_events();
+
+    // Test spam headers
+    session
+        .send_message(
+            "spam@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("X-Spam-Status: Yes, score=12.50")
+        .assert_contains("X-Spam-Result: BAYES_SPAM (5.10)")
+        .assert_contains("R_SPF_FAIL (1.00)")
+        .assert_contains("X-Spamd-Bar: ++++");
+
+    // Test ham headers
+    session
+        .send_message(
+            "ham@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("X-Spam-Status: No, score=0.50")
+        .assert_not_contains("X-Spam-Result");
+}
+
 #[test]
 fn milter_address_modifications() {
     let test_message = fs::read_to_string(
@@ -832,6 +1014,114 @@ pub fn spawn_mock_mta_hook_server() -> watch::Sender<bool> {
     tx
 }
 
+pub fn spawn_mock_rspamd_server() -> watch::Sender<bool> {
+    let (tx, rx) = watch::channel(true);
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.0.0.1:9334")
+            .await
+            .unwrap_or_else(|e| {
+                panic!("Failed to bind mock Rspamd server to 127.0.0.1:9334: {e}");
+            });
+        let mut rx_ = rx.clone();
+        loop {
+            tokio::select! {
+                stream = listener.accept() => {
+                    match stream {
+                        Ok((stream, _)) => {
+                            let _ = http1::Builder::new()
+                            .keep_alive(false)
+                            .serve_connection(
+                                TokioIo::new(stream),
+                                service_fn(|req: hyper::Request<body::Incoming>| {
+                                    let from = req
+                                        .headers()
+                                        .get("From")
+                                        .and_then(|v| v.to_str().ok())
+                                        .unwrap_or_default()
+                                        .to_string();
+
+                                    async move {
+                                        let response = handle_rspamd(&from);
+
+                                        Ok::<_, hyper::Error>(
+                                            Resource::new("application/json", serde_json::to_string(&response).unwrap().into_bytes())
+                                            .into_http_response().build(),
+                                        )
+                                    }
+                                }),
+                            )
+                            .await;
+                        }
+                        Err(err) => {
+                            panic!("Something went wrong: {err}" );
+                        }
+                    }
+                },
+                _ = rx_.changed() => {
+                    break;
+                }
+            };
+        }
+    });
+
+    tx
+}
+
+fn handle_rspamd(from: &str) -> RspamdResponse {
+    match from.split_once('@').unwrap().0 {
+        "reject" => RspamdResponse {
+            action: "reject".to_string(),
+            score: 100.0,
+            messages: [(
+                "smtp_message".to_string(),
+                serde_json::Value::String("Gtube pattern".to_string()),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        },
+        "greylist" => RspamdResponse {
+            action: "greylist".to_string(),
+            score: 4.0,
+            ..Default::default()
+        },
+        "spam" => RspamdResponse {
+            action: "add header".to_string(),
+            score: 12.5,
+            required_score: 15.0,
+            symbols: [("BAYES_SPAM", 5.1), ("R_SPF_FAIL", 1.0)]
+                .into_iter()
+                .map(|(name, score)| {
+                    (
+                        name.to_string(),
+                        RspamdSymbol {
+                            name: name.to_string(),
+                            score,
+                        },
+                    )
+                })
+                .collect(),
+            milter: RspamdMilter {
+                add_headers: [(
+                    "X-Spamd-Bar".to_string(),
+                    serde_json::json!({"value": "++++", "order": 0}),
+                )]
+                .into_iter()
+                .collect(),
+            }
+            .into(),
+            ..Default::default()
+        },
+        _ => RspamdResponse {
+            action: "no action".to_string(),
+            score: 0.5,
+            required_score: 15.0,
+            ..Default::default()
+        },
+    }
+}
+
 fn handle_mta_hook(request: Request, tests: Arc<Vec<HeaderTest>>) -> hooks::Response {
     match request
         .envelope
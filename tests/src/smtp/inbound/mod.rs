@@ -21,15 +21,21 @@ use smtp::queue::{DeliveryAttempt, Message, QueueId};
 use super::{QueueReceiver, ReportReceiver};
 
 pub mod antispam;
+pub mod antivirus;
+pub mod attachments;
 pub mod auth;
 pub mod basic;
+pub mod bounce;
 pub mod data;
 pub mod dmarc;
+pub mod drain;
 pub mod ehlo;
 pub mod limits;
 pub mod mail;
 pub mod milter;
 pub mod rcpt;
+pub mod rcpt_verify;
+pub mod reputation;
 pub mod rewrite;
 pub mod scripts;
 pub mod sign;
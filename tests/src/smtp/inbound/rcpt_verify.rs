@@ -0,0 +1,211 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use common::{manager::webadmin::Resource, Core};
+use hyper::{body, server::conn::http1, service::service_fn, StatusCode};
+use hyper_util::rt::TokioIo;
+use jmap::api::{
+    http::{fetch_body, ToHttpResponse},
+    HttpResponse,
+};
+use serde::Deserialize;
+use smtp::core::Session;
+use store::Stores;
+use tokio::{net::TcpListener, sync::watch};
+use utils::config::Config;
+
+use crate::smtp::{
+    session::{TestSession, VerifyResponse},
+    TempDir, TestSMTP,
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[session.rcpt]
+relay = false
+
+[session.rcpt.errors]
+total = 100
+wait = "1ms"
+
+[session.rcpt.verify]
+enable = "rcpt_domain = 'crm.org'"
+url = "http://127.0.0.1:9335/verify"
+headers = ["X-Api-Key: secret"]
+timeout = "1s"
+cache.valid = "1h"
+cache.invalid = "1h"
+options.tempfail-on-error = true
+"#;
+
+#[derive(Deserialize)]
+struct VerifyRequest {
+    address: String,
+    sender: String,
+}
+
+#[tokio::test]
+async fn rcpt_verify() {
+    // Enable logging
+    crate::enable_logging();
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let _tx = spawn_mock_verify_server(requests.clone());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let tmp_dir = TempDir::new("smtp_rcpt_verify_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    let mut session = Session::test(TestSMTP::from_core(core).server);
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+    session.mail_from("bill@doe.org", "250").await;
+
+    // Addresses outside the verified domains are not sent to the service
+    session.rcpt_to("jane@example.org", "550 5.1.2").await;
+    assert_eq!(requests.load(Ordering::Relaxed), 0);
+
+    // Addresses accepted by the service are relayed
+    session.rcpt_to("john@crm.org", "250").await;
+    assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+    // Rejected addresses include the reason provided by the service
+    session
+        .ingest(b"RCPT TO:<unknown@crm.org>\r\n")
+        .await
+        .unwrap();
+    session
+        .response()
+        .assert_code("550 5.1.2")
+        .assert_contains("Unknown CRM contact");
+    assert_eq!(requests.load(Ordering::Relaxed), 2);
+
+    // Results are cached
+    session.cmd("RSET", "250").await;
+    session.mail_from("bill@doe.org", "250").await;
+    session.rcpt_to("john@crm.org", "250").await;
+    session.rcpt_to("unknown@crm.org", "550 5.1.2").await;
+    assert_eq!(requests.load(Ordering::Relaxed), 2);
+
+    // Temporary failures are not cached
+    session.rcpt_to("tempfail@crm.org", "451 4.4.3").await;
+    session.rcpt_to("tempfail@crm.org", "451 4.4.3").await;
+    assert_eq!(requests.load(Ordering::Relaxed), 4);
+
+    // Service errors fail closed
+    session.rcpt_to("error@crm.org", "451 4.4.3").await;
+    assert_eq!(requests.load(Ordering::Relaxed), 5);
+
+    // Service errors fail open when tempfail-on-error is disabled
+    let tmp_dir = TempDir::new("smtp_rcpt_verify_open_test", true);
+    let mut config = Config::new(tmp_dir.update_config(&CONFIG.replace(
+        "options.tempfail-on-error = true",
+        "options.tempfail-on-error = false",
+    )))
+    .unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    let mut session = Session::test(TestSMTP::from_core(core).server);
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+    session.mail_from("bill@doe.org", "250").await;
+    session.rcpt_to("error@crm.org", "250").await;
+    assert_eq!(requests.load(Ordering::Relaxed), 6);
+}
+
+fn spawn_mock_verify_server(requests: Arc<AtomicUsize>) -> watch::Sender<bool> {
+    let (tx, mut rx) = watch::channel(true);
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.0.0.1:9335")
+            .await
+            .unwrap_or_else(|e| {
+                panic!("Failed to bind mock verification server to 127.0.0.1:9335: {e}");
+            });
+        loop {
+            tokio::select! {
+                stream = listener.accept() => {
+                    let (stream, _) = stream.unwrap();
+                    let requests = requests.clone();
+                    let _ = http1::Builder::new()
+                        .keep_alive(false)
+                        .serve_connection(
+                            TokioIo::new(stream),
+                            service_fn(move |mut req: hyper::Request<body::Incoming>| {
+                                let requests = requests.clone();
+
+                                async move {
+                                    requests.fetch_add(1, Ordering::Relaxed);
+                                    assert_eq!(
+                                        req.headers()
+                                            .get("X-Api-Key")
+                                            .and_then(|v| v.to_str().ok()),
+                                        Some("secret")
+                                    );
+                                    let request = serde_json::from_slice::<VerifyRequest>(
+                                        &fetch_body(&mut req, 1024 * 1024, 0).await.unwrap(),
+                                    )
+                                    .unwrap();
+                                    assert_eq!(request.sender, "bill@doe.org");
+
+                                    let response = match request.address.as_str() {
+                                        "john@crm.org" => r#"{"action":"accept"}"#,
+                                        "unknown@crm.org" => {
+                                            r#"{"action":"reject","message":"Unknown CRM contact."}"#
+                                        }
+                                        "tempfail@crm.org" => r#"{"action":"tempfail"}"#,
+                                        _ => {
+                                            return Ok::<_, hyper::Error>(
+                                                HttpResponse::new_empty(
+                                                    StatusCode::INTERNAL_SERVER_ERROR,
+                                                )
+                                                .build(),
+                                            );
+                                        }
+                                    };
+
+                                    Ok::<_, hyper::Error>(
+                                        Resource::new(
+                                            "application/json",
+                                            response.as_bytes().to_vec(),
+                                        )
+                                        .into_http_response()
+                                        .build(),
+                                    )
+                                }
+                            }),
+                        )
+                        .await;
+                },
+                _ = rx.changed() => {
+                    break;
+                }
+            };
+        }
+    });
+
+    tx
+}
@@ -0,0 +1,425 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{ipc::BroadcastEvent, Core};
+use jmap::services::gossip::request::Request;
+use mail_parser::MessageParser;
+use store::Stores;
+use utils::config::Config;
+
+use crate::{
+    smtp::{
+        session::{TestSession, VerifyResponse},
+        TempDir, TestSMTP,
+    },
+    AssertConfig,
+};
+use smtp::core::{
+    reputation::{
+        message_reputation_entities, FeedEntry, FeedList, Reputation, ReputationEntity,
+        ReputationKind, ReputationSignal, SmtpReputation,
+    },
+    Session,
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+directory = "local"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = ["john@foobar.org"]
+
+[session.rcpt]
+directory = "'local'"
+
+[session.rcpt.errors]
+wait = "5ms"
+
+[session.data]
+script = "'spam-check'"
+
+[session.reputation]
+enable = true
+min-samples = 2
+factor = 5.0
+
+[session.reputation.throttle]
+threshold = 0.9
+action = "reject"
+
+[sieve.trusted.scripts."spam-check"]
+contents = '''
+require ["variables", "reject"];
+
+if eval "env.reputation.score > 4" {
+    reject "550 5.7.1 Poor sender reputation.";
+    stop;
+}
+if header :contains "Subject" "lottery" {
+    eval "add_header('X-Spam-Status', 'Yes, score=12.5')";
+} else {
+    eval "add_header('X-Spam-Status', 'No, score=0.5')";
+}
+'''
+"#;
+
+const SHARING_CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+directory = "local"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[directory."local"]
+type = "memory"
+
+[cluster]
+bind-addr = "127.0.0.1"
+share.reputation = true
+
+[session.reputation]
+enable = true
+min-samples = 2
+factor = 5.0
+
+[session.reputation.feed]
+enable = true
+threshold = 0.8
+weight = 0.5
+"#;
+
+#[tokio::test]
+async fn sender_reputation() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_reputation_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+    let test = TestSMTP::from_core(core);
+    let spammer = ReputationEntity {
+        kind: ReputationKind::Ip,
+        value: "10.0.0.1".to_string(),
+    };
+    let sender = ReputationEntity {
+        kind: ReputationKind::Ip,
+        value: "10.0.0.2".to_string(),
+    };
+
+    // Spam verdicts lower the reputation until the spam score is raised
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    assert!(session.init_conn().await);
+    session.ehlo("mx.spammer.org").await;
+    for expected_code in ["250", "250", "550 5.7.1"] {
+        session
+            .send_message(
+                "bill@spammer.org",
+                &["john@foobar.org"],
+                "Subject: you won the lottery\r\n\r\ntest",
+                expected_code,
+            )
+            .await;
+    }
+    let reputation = test.server.reputation_get(&spammer).await.unwrap().unwrap();
+    assert_eq!(reputation.spam.round(), 2.0);
+    assert_eq!(reputation.ham, 0.0);
+    assert_eq!(
+        test.server
+            .reputation_score(&[spammer.clone()])
+            .await
+            .unwrap(),
+        Some(5.0)
+    );
+
+    // Connections from IPs with a poor reputation are rejected
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    assert!(!session.init_conn().await);
+    session.response().assert_code("421 4.7.0");
+
+    // Ham verdicts and invalid recipients are tracked for other senders
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.2".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    assert!(session.init_conn().await);
+    session.ehlo("mx.example.org").await;
+    for _ in 0..4 {
+        session
+            .send_message(
+                "jane@example.org",
+                &["john@foobar.org"],
+                "Subject: meeting\r\n\r\ntest",
+                "250",
+            )
+            .await;
+    }
+    session.mail_from("jane@example.org", "250").await;
+    session.rcpt_to("unknown@foobar.org", "550 5.1.2").await;
+    let reputation = test.server.reputation_get(&sender).await.unwrap().unwrap();
+    assert_eq!(reputation.ham.round(), 4.0);
+    assert_eq!(reputation.bounces.round(), 1.0);
+    assert_eq!(
+        test.server
+            .reputation_score(&[sender.clone()])
+            .await
+            .unwrap()
+            .map(|score| score.round()),
+        Some(-3.0)
+    );
+    assert_eq!(
+        test.server
+            .reputation_score(&[
+                sender,
+                ReputationEntity {
+                    kind: ReputationKind::Domain,
+                    value: "example.org".to_string(),
+                }
+            ])
+            .await
+            .unwrap()
+            .map(|score| score.round()),
+        Some(-3.0)
+    );
+
+    // Counters decay over time
+    let mut reputation = Reputation {
+        spam: 4.0,
+        complaints: 2.0,
+        updated: 1000,
+        ..Default::default()
+    };
+    reputation.decay(3600, 1000 + 3600);
+    assert_eq!(reputation.spam, 2.0);
+    assert_eq!(reputation.complaints, 1.0);
+    assert_eq!(reputation.updated, 1000 + 3600);
+
+    // Complaint entities are obtained from the trace headers
+    let message = MessageParser::new()
+        .parse(concat!(
+            "Received: from mx.example.com (mx.example.com [192.0.2.1])\r\n",
+            "\tby mx.foobar.org (Stalwart SMTP) with ESMTPS id 1234;\r\n",
+            "\tMon, 1 Jan 2024 00:00:00 +0000\r\n",
+            "Authentication-Results: mx.foobar.org;\r\n",
+            "\tdkim=pass header.d=Example.com header.s=default header.b=abc;\r\n",
+            "\tspf=pass (mx.foobar.org: domain of bill@example.net designates ",
+            "192.0.2.1 as permitted sender) smtp.mailfrom=bill@example.net;\r\n",
+            "\tdmarc=pass header.from=example.com\r\n",
+            "Received: from attacker.org ([203.0.113.1])\r\n",
+            "\tby mx.example.com; Mon, 1 Jan 2024 00:00:00 +0000\r\n",
+            "Subject: test\r\n",
+            "\r\n",
+            "test"
+        ))
+        .unwrap();
+    assert_eq!(
+        message_reputation_entities(&message),
+        vec![
+            ReputationEntity {
+                kind: ReputationKind::Ip,
+                value: "192.0.2.1".to_string(),
+            },
+            ReputationEntity {
+                kind: ReputationKind::Dkim,
+                value: "example.com".to_string(),
+            },
+            ReputationEntity {
+                kind: ReputationKind::Domain,
+                value: "example.net".to_string(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reputation_sharing() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut servers = Vec::new();
+    for name in ["smtp_reputation_share_a", "smtp_reputation_share_b"] {
+        let tmp_dir = TempDir::new(name, true);
+        let mut config = Config::new(tmp_dir.update_config(SHARING_CONFIG)).unwrap();
+        let stores = Stores::parse_all(&mut config).await;
+        let core = Core::parse(&mut config, stores, Default::default()).await;
+        config.assert_no_errors();
+        servers.push((TestSMTP::from_core(core), tmp_dir));
+    }
+    let (mut local, _) = servers.remove(0);
+    let (remote, _) = servers.remove(0);
+    let spammer = ReputationEntity {
+        kind: ReputationKind::Ip,
+        value: "10.0.0.1".to_string(),
+    };
+    let sender = ReputationEntity {
+        kind: ReputationKind::Domain,
+        value: "example.org".to_string(),
+    };
+
+    // Local reputation updates are broadcast to the cluster
+    for _ in 0..3 {
+        local
+            .server
+            .reputation_update(&[spammer.clone()], ReputationSignal::Spam)
+            .await
+            .unwrap();
+        assert_eq!(
+            local.broadcast_rx.try_recv().unwrap(),
+            BroadcastEvent::ReputationUpdate {
+                entities: vec![spammer.clone()],
+                signal: ReputationSignal::Spam,
+            }
+        );
+    }
+
+    // Updates received from peers are not broadcast again
+    for _ in 0..3 {
+        local
+            .server
+            .reputation_apply(&[sender.clone()], ReputationSignal::Ham)
+            .await
+            .unwrap();
+    }
+    assert!(local.broadcast_rx.try_recv().is_err());
+
+    // Broadcast events are serialized in gossip packets
+    let events = vec![
+        BroadcastEvent::ReputationUpdate {
+            entities: vec![spammer.clone(), sender.clone()],
+            signal: ReputationSignal::Complaint,
+        },
+        BroadcastEvent::BlockedIp("10.0.0.9".parse().unwrap()),
+        BroadcastEvent::BlockedIp("2001:db8::1".parse().unwrap()),
+    ];
+    match Request::from_bytes(&Request::Broadcast(events.clone()).to_bytes()) {
+        Some(Request::Broadcast(decoded)) => assert_eq!(decoded, events),
+        other => panic!("Unexpected request: {other:?}"),
+    }
+
+    // Senders with a poor reputation and banned IPs are exported
+    local
+        .server
+        .inner
+        .data
+        .blocked_ips
+        .write()
+        .insert("10.0.0.9".parse().unwrap());
+    let banned = ReputationEntity {
+        kind: ReputationKind::Ip,
+        value: "10.0.0.9".to_string(),
+    };
+    let feed = local.server.reputation_feed_export().await.unwrap();
+    assert_eq!(feed.version, 1);
+    assert_eq!(
+        feed.entries,
+        vec![
+            FeedEntry {
+                kind: ReputationKind::Ip,
+                hash: spammer.feed_hash(),
+                badness: 1.0,
+                samples: 3,
+                banned: false,
+            },
+            FeedEntry {
+                kind: ReputationKind::Ip,
+                hash: banned.feed_hash(),
+                badness: 1.0,
+                samples: 100,
+                banned: true,
+            }
+        ]
+    );
+    assert!(!serde_json::to_string(&feed).unwrap().contains("10.0.0."));
+
+    // Imported feeds are weighted when scoring senders
+    assert!(remote
+        .server
+        .reputation_get(&spammer)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        remote
+            .server
+            .reputation_feed_import(
+                serde_json::from_str(&serde_json::to_string(&feed).unwrap()).unwrap()
+            )
+            .await
+            .unwrap(),
+        2
+    );
+    let reputation = remote
+        .server
+        .reputation_get(&spammer)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((reputation.spam * 10.0).round(), 15.0);
+    assert_eq!(reputation.ham, 0.0);
+    assert_eq!(
+        remote.server.reputation_score(&[spammer]).await.unwrap(),
+        None
+    );
+    assert_eq!(
+        remote.server.reputation_score(&[banned]).await.unwrap(),
+        Some(5.0)
+    );
+
+    // Invalid entries and unknown versions are rejected
+    assert_eq!(
+        remote
+            .server
+            .reputation_feed_import(FeedList {
+                version: 1,
+                generated: 0,
+                entries: vec![FeedEntry {
+                    kind: ReputationKind::Domain,
+                    hash: "example.org".to_string(),
+                    badness: 1.0,
+                    samples: 10,
+                    banned: false,
+                }],
+            })
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(remote
+        .server
+        .reputation_feed_import(FeedList {
+            version: 2,
+            generated: 0,
+            entries: vec![],
+        })
+        .await
+        .is_err());
+}
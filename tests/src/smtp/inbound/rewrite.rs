@@ -7,6 +7,7 @@
 use common::Core;
 
 use smtp::core::Session;
+use store::Stores;
 use utils::config::Config;
 
 use crate::smtp::{session::TestSession, TestSMTP};
@@ -64,6 +65,35 @@ if allof( envelope :localpart :contains "to" ".",
 
 "#;
 
+const CANONICAL_CONFIG: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.rewrite."1-legacy"]
+type = "regex"
+direction = "recipient"
+pattern = "^([^.]+)\\.([^.]+)@legacy\\.org$"
+replacement = "$1-$2@example.org"
+
+[session.rewrite."2-canonical"]
+type = "lookup"
+direction = "both"
+store = "canonical"
+
+[session.rewrite."3-submission"]
+type = "regex"
+direction = "sender"
+enable = "!is_empty(authenticated_as)"
+pattern = "^(.+)@example\\.org$"
+replacement = "$1@mail.example.org"
+
+[lookup."canonical"]
+"jdoe@example.org" = "john.doe@example.org"
+"@old.example.org" = "@example.org"
+"@moved.example.org" = "postmaster@example.org"
+"j-doe@example.org" = "jane.doe@example.org"
+"#;
+
 #[tokio::test]
 async fn address_rewrite() {
     // Enable logging
@@ -112,3 +142,68 @@ async fn address_rewrite() {
         "marysmith@foobar.org"
     );
 }
+
+#[tokio::test]
+async fn canonical_maps() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Prepare config
+    let mut config = Config::new(CANONICAL_CONFIG).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+
+    // Init session
+    let mut session = Session::test(TestSMTP::from_core(core).server);
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+
+    // Sender rewrite using a lookup map, the submission rule is skipped
+    session.mail_from("jdoe@example.org", "250").await;
+    assert_eq!(
+        session.data.mail_from.as_ref().unwrap().address,
+        "john.doe@example.org"
+    );
+
+    // Null senders are never rewritten
+    session.reset();
+    session.mail_from("", "250").await;
+    assert_eq!(session.data.mail_from.as_ref().unwrap().address, "");
+
+    // Domain maps keep the local part unless a full address is provided
+    session.rcpt_to("jane@old.example.org", "250").await;
+    assert_eq!(
+        session.data.rcpt_to.last().unwrap().address,
+        "jane@example.org"
+    );
+    session.rcpt_to("anyone@moved.example.org", "250").await;
+    assert_eq!(
+        session.data.rcpt_to.last().unwrap().address,
+        "postmaster@example.org"
+    );
+
+    // Rules are chained, the output of the regex is looked up next
+    session.rcpt_to("j.doe@legacy.org", "250").await;
+    assert_eq!(
+        session.data.rcpt_to.last().unwrap().address,
+        "jane.doe@example.org"
+    );
+    session.rcpt_to("jdoe@example.org", "250").await;
+    assert_eq!(
+        session.data.rcpt_to.last().unwrap().address,
+        "john.doe@example.org"
+    );
+
+    // Rewritten duplicates are removed
+    session.rcpt_to("bob@old.example.org", "250").await;
+    session.rcpt_to("bob@example.org", "250").await;
+    assert_eq!(session.data.rcpt_to.len(), 5);
+
+    // Unmatched addresses are left untouched
+    session.rcpt_to("bill@example.com", "250").await;
+    assert_eq!(
+        session.data.rcpt_to.last().unwrap().address,
+        "bill@example.com"
+    );
+}
@@ -46,6 +46,11 @@ max-recipients = 100
 [session.extensions]
 dsn = true
 future-release = "1h"
+
+[remote."backup"]
+address = "127.0.0.1"
+port = 9925
+protocol = "smtp"
 "#;
 
 const REMOTE: &str = r#"
@@ -446,6 +451,104 @@ async fn manage_queue() {
         }
     }
 
+    // Test queue search filters
+    for (query, expected_ids) in [
+        ("/api/queue/messages?domain=example3.com", vec!["c"]),
+        ("/api/queue/messages?domain=foobar.org", vec!["f"]),
+        ("/api/queue/messages?error=temporary", vec!["f"]),
+        ("/api/queue/messages?error=permanent", vec!["a", "c"]),
+        ("/api/queue/messages?error=dns", vec![]),
+        ("/api/queue/messages?max-age=1h", vec!["a", "c", "f"]),
+        ("/api/queue/messages?min-age=1h", vec![]),
+    ] {
+        assert_eq!(
+            api.queue_ids(query, &id_map_rev).await,
+            HashSet::from_iter(expected_ids.into_iter().map(|s| s.to_string())),
+            "failed for {query}"
+        );
+    }
+
+    // Hold and release messages
+    assert_eq!(
+        api.request::<usize>(
+            Method::PATCH,
+            "/api/queue/messages?action=hold&domain=example3.com"
+        )
+        .await
+        .unwrap()
+        .unwrap_data(),
+        1
+    );
+    assert_eq!(
+        api.queue_ids("/api/queue/messages?held=true", &id_map_rev)
+            .await,
+        HashSet::from_iter(["c".to_string()])
+    );
+    let id = id_map.get("c").unwrap();
+    assert!(api.get_messages(&[*id]).await[0].as_ref().unwrap().held);
+    for (action, expected) in [("hold", false), ("release", true), ("release", false)] {
+        assert_eq!(
+            api.request::<bool>(
+                Method::PATCH,
+                &format!("/api/queue/messages/{id}?action={action}")
+            )
+            .await
+            .unwrap()
+            .unwrap_data(),
+            expected,
+            "failed for {action}"
+        );
+    }
+    assert!(api
+        .queue_ids("/api/queue/messages?held=true", &id_map_rev)
+        .await
+        .is_empty());
+
+    // Reroute messages to a different next hop
+    let id = id_map.get("a").unwrap();
+    api.request::<bool>(
+        Method::PATCH,
+        &format!("/api/queue/messages/{id}?next-hop=unknown"),
+    )
+    .await
+    .unwrap()
+    .expect_request_error("Unknown next hop");
+    for (next_hop, expected) in [("backup", Some("backup")), ("default", None)] {
+        assert!(api
+            .request::<bool>(
+                Method::PATCH,
+                &format!("/api/queue/messages/{id}?next-hop={next_hop}&at=2200-01-01T00:00:00Z")
+            )
+            .await
+            .unwrap()
+            .unwrap_data());
+        assert_eq!(
+            api.get_messages(&[*id]).await[0]
+                .as_ref()
+                .unwrap()
+                .next_hop
+                .as_deref(),
+            expected
+        );
+    }
+
+    // Bulk deletion requires a filter
+    api.request::<usize>(Method::DELETE, "/api/queue/messages")
+        .await
+        .unwrap()
+        .expect_request_error("filter");
+    assert_eq!(
+        api.request::<usize>(Method::DELETE, "/api/queue/messages?error=permanent")
+            .await
+            .unwrap()
+            .unwrap_data(),
+        2
+    );
+    assert_eq!(
+        api.queue_ids("/api/queue/messages", &id_map_rev).await,
+        HashSet::from_iter(["f".to_string()])
+    );
+
     // Test authentication error
     assert_eq!(
         reqwest::Client::builder()
@@ -472,6 +575,21 @@ fn assert_timestamp(timestamp: &DateTime, expected: i64, ctx: &str, message: &Me
 }
 
 impl ManagementApi {
+    async fn queue_ids(
+        &self,
+        query: &str,
+        id_map_rev: &AHashMap<QueueId, String>,
+    ) -> HashSet<String> {
+        self.request::<List<QueueId>>(Method::GET, query)
+            .await
+            .unwrap()
+            .unwrap_data()
+            .items
+            .into_iter()
+            .map(|id| id_map_rev.get(&id).unwrap().clone())
+            .collect()
+    }
+
     async fn get_messages(&self, ids: &[QueueId]) -> Vec<Option<Message>> {
         let mut results = Vec::with_capacity(ids.len());
 
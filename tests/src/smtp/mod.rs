@@ -8,7 +8,7 @@ use std::path::PathBuf;
 
 use common::{
     config::server::{Listeners, ServerProtocol},
-    ipc::{QueueEvent, ReportingEvent},
+    ipc::{BroadcastEvent, QueueEvent, ReportingEvent},
     manager::boot::build_ipc,
     Core, Data, Inner, Server,
 };
@@ -94,6 +94,7 @@ pub struct TestSMTP {
     pub temp_dir: Option<TempDir>,
     pub queue_receiver: QueueReceiver,
     pub report_receiver: ReportReceiver,
+    pub broadcast_rx: mpsc::Receiver<BroadcastEvent>,
 }
 
 const CONFIG: &str = r#"
@@ -159,6 +160,7 @@ impl TestSMTP {
             report_receiver: ReportReceiver {
                 report_rx: ipc_rxs.report_rx.take().unwrap(),
             },
+            broadcast_rx: ipc_rxs.broadcast_rx.take().unwrap(),
             server: Server {
                 core: shared_core.load_full(),
                 inner: Inner {
@@ -214,7 +216,10 @@ impl TestSMTP {
                         acceptor,
                         shutdown_rx,
                     ),
-                    ServerProtocol::Imap | ServerProtocol::Pop3 | ServerProtocol::ManageSieve => {
+                    ServerProtocol::Imap
+                    | ServerProtocol::Pop3
+                    | ServerProtocol::ManageSieve
+                    | ServerProtocol::Health => {
                         unreachable!()
                     }
                 };
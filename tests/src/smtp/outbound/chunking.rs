@@ -0,0 +1,142 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+
+use crate::smtp::{
+    inbound::TestMessage,
+    session::{TestSession, VerifyResponse},
+    TestSMTP,
+};
+
+const LOCAL: &str = r#"
+[queue.outbound]
+next-hop = [{if = "size > 5000", then = "'large'"},
+            {else = false}]
+
+[queue.outbound.limits]
+buffer = 64
+
+[session.rcpt]
+relay = true
+
+[remote.large]
+address = large.foobar.net
+port = 9925
+protocol = 'smtp'
+
+[remote.large.tls]
+implicit = false
+allow-invalid-certs = true
+"#;
+
+const REMOTE: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.ehlo]
+reject-non-fqdn = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn chunking() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_chunking_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+    let mut local = TestSMTP::new("smtp_chunking_local", LOCAL).await;
+
+    // Add mock DNS entries, foobar.net can only be reached through the relay
+    let core = local.build_smtp();
+    for (domain, exchange) in [
+        ("foobar.org", "mx.foobar.org"),
+        ("foobar.net", "_dns_error.foobar.net"),
+    ] {
+        core.core.smtp.resolvers.dns.mx_add(
+            domain,
+            vec![MX {
+                exchanges: vec![exchange.to_string()],
+                preference: 10,
+            }],
+            Instant::now() + Duration::from_secs(10),
+        );
+    }
+    for host in ["mx.foobar.org", "large.foobar.net"] {
+        core.core.smtp.resolvers.dns.ipv4_add(
+            host,
+            vec!["127.0.0.1".parse().unwrap()],
+            Instant::now() + Duration::from_secs(10),
+        );
+    }
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Messages larger than the buffer are streamed in multiple BDAT chunks
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], &message(20), "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone())
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("Subject: Chunked delivery")
+        .assert_contains("Line 000: The quick brown fox jumps over the lazy dog.")
+        .assert_contains("Line 019: The quick brown fox jumps over the lazy dog.");
+
+    // Large messages are routed to a different relay host
+    session
+        .send_message("john@test.org", &["jane@foobar.net"], &message(200), "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone())
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("To: jane@foobar.net")
+        .assert_contains("Line 199: The quick brown fox jumps over the lazy dog.");
+}
+
+fn message(lines: usize) -> String {
+    let mut message = concat!(
+        "From: john@test.org\r\n",
+        "To: jane@foobar.net\r\n",
+        "Subject: Chunked delivery\r\n",
+        "\r\n"
+    )
+    .to_string();
+    for line in 0..lines {
+        message.push_str(&format!(
+            "Line {line:03}: The quick brown fox jumps over the lazy dog.\r\n"
+        ));
+    }
+    message
+}
@@ -0,0 +1,122 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+use smtp::queue::{
+    history::{DeliveryHistory, TlsMode},
+    Status,
+};
+use store::write::now;
+
+use crate::smtp::{session::TestSession, TestSMTP};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[queue.outbound]
+hostname = "'badtls.foobar.org'"
+
+[queue.outbound.tls]
+starttls = [ { if = "retry_num > 0 && last_error == 'tls'", then = "disable"},
+             { else = "optional" }]
+
+[queue.history]
+enable = true
+retention = "1d"
+"#;
+
+const REMOTE: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.ehlo]
+reject-non-fqdn = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn delivery_history() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_history_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+    let mut local = TestSMTP::new("smtp_history_local", LOCAL).await;
+
+    // Add mock DNS entries
+    let core = local.build_smtp();
+    core.core.smtp.resolvers.dns.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.core.smtp.resolvers.dns.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+
+    // The first attempt fails during STARTTLS
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone())
+        .await;
+    let mut retry = local.queue_receiver.expect_message().await;
+    let queue_id = retry.queue_id;
+    let history = core.history_get(queue_id).await.unwrap();
+    assert_eq!(history.len(), 1, "{history:?}");
+    assert_eq!(history[0].domain, "foobar.org");
+    assert_eq!(history[0].mx, "mx.foobar.org");
+    assert_eq!(history[0].remote_ip.to_string(), "127.0.0.1");
+    assert_eq!(history[0].tls, TlsMode::None);
+    assert!(
+        matches!(&history[0].status, Status::TemporaryFailure(err) if err.starts_with("TLS error")),
+        "{history:?}"
+    );
+
+    // The second attempt is delivered in plain text
+    let prev_due = retry.domains[0].retry.due;
+    let next_due = now();
+    retry.domains[0].retry.due = next_due;
+    retry
+        .save_changes(&core, prev_due.into(), next_due.into())
+        .await;
+    local
+        .queue_receiver
+        .delivery_attempt(queue_id)
+        .await
+        .try_deliver(core.clone())
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    remote.queue_receiver.expect_message().await;
+
+    // Attempts are appended to the history of the message
+    let history = core.history_get(queue_id).await.unwrap();
+    assert_eq!(history.len(), 2, "{history:?}");
+    assert_eq!(history[1].tls, TlsMode::None);
+    assert!(
+        matches!(&history[1].status, Status::Completed(response) if response.starts_with("250")),
+        "{history:?}"
+    );
+}
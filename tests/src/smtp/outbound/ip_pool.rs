@@ -0,0 +1,120 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+use smtp::outbound::pool::IpPoolManager;
+
+use crate::smtp::{
+    inbound::TestMessage,
+    session::{TestSession, VerifyResponse},
+    TestSMTP,
+};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[queue.pool."01-marketing"]
+sender-domain = "news.test.org"
+addresses = ["127.0.0.4"]
+
+[queue.pool."02-default"]
+addresses = ["127.0.0.2", "127.0.0.3"]
+strategy = "round-robin"
+warmup.schedule = [1]
+limits.hourly = 1000
+reputation.max-failure-rate = 0.5
+"#;
+
+const REMOTE: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.ehlo]
+reject-non-fqdn = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn ip_pool() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_ip_pool_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+    let mut local = TestSMTP::new("smtp_ip_pool_local", LOCAL).await;
+
+    // Add mock DNS entries
+    let core = local.build_smtp();
+    core.core.smtp.resolvers.dns.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.core.smtp.resolvers.dns.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Addresses are selected by sender domain and then in round-robin order
+    for (sender, source_ip) in [
+        ("john@news.test.org", "[127.0.0.4]"),
+        ("john@test.org", "[127.0.0.2]"),
+        ("jane@test.org", "[127.0.0.3]"),
+    ] {
+        session
+            .send_message(sender, &["bill@foobar.org"], "test:no_dkim", "250")
+            .await;
+        local
+            .queue_receiver
+            .expect_message_then_deliver()
+            .await
+            .try_deliver(core.clone())
+            .await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        remote
+            .queue_receiver
+            .expect_message()
+            .await
+            .read_lines(&remote.queue_receiver)
+            .await
+            .assert_contains(source_ip);
+    }
+
+    // Successful deliveries are tracked per address
+    for ip in ["127.0.0.2", "127.0.0.3"] {
+        assert_eq!(
+            core.ip_pool_stats(ip.parse().unwrap()).await.unwrap(),
+            (1, 0)
+        );
+    }
+
+    // Addresses in warmup cannot exceed their hourly limit
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone())
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    remote.queue_receiver.assert_no_events();
+}
@@ -4,12 +4,16 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod chunking;
 pub mod dane;
 pub mod extensions;
 pub mod fallback_relay;
+pub mod history;
+pub mod ip_pool;
 pub mod ip_lookup;
 pub mod lmtp;
 pub mod mta_sts;
+pub mod routing;
 pub mod smtp;
 pub mod throttle;
 pub mod tls;
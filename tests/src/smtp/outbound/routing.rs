@@ -0,0 +1,179 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+
+use crate::smtp::{
+    inbound::TestMessage,
+    session::{TestSession, VerifyResponse},
+    TestSMTP,
+};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[queue.route."01-sales"]
+sender-domain = "*.test.org"
+relay = "sales"
+
+[queue.route."02-partner"]
+rcpt-domain = "foobar.net"
+size.max = 5000
+relay = "partner"
+
+[remote.sales]
+address = sales.foobar.net
+port = 9925
+protocol = 'smtp'
+source-ip = ["127.0.0.1"]
+
+[remote.sales.tls]
+implicit = false
+starttls = "disable"
+
+[remote.partner]
+address = partner.foobar.net
+port = 9925
+protocol = 'smtp'
+
+[remote.partner.tls]
+implicit = false
+allow-invalid-certs = true
+starttls = "require"
+"#;
+
+const REMOTE: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.ehlo]
+reject-non-fqdn = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn routing() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_routing_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+    let mut local = TestSMTP::new("smtp_routing_local", LOCAL).await;
+
+    // Add mock DNS entries, foobar.net can only be reached through a smarthost
+    let core = local.build_smtp();
+    core.core.smtp.resolvers.dns.mx_add(
+        "foobar.net",
+        vec![MX {
+            exchanges: vec!["_dns_error.foobar.net".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    for host in ["sales.foobar.net", "partner.foobar.net"] {
+        core.core.smtp.resolvers.dns.ipv4_add(
+            host,
+            vec!["127.0.0.1".parse().unwrap()],
+            Instant::now() + Duration::from_secs(10),
+        );
+    }
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Route by sender domain
+    session
+        .send_message(
+            "john@sales.test.org",
+            &["bill@foobar.net"],
+            &message("john@sales.test.org", 5),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone())
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("From: john@sales.test.org")
+        .assert_not_contains("using TLSv1.3 with cipher");
+
+    // Route by recipient domain and size
+    session
+        .send_message(
+            "jane@example.org",
+            &["bill@foobar.net"],
+            &message("jane@example.org", 5),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone())
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("From: jane@example.org")
+        .assert_contains("using TLSv1.3 with cipher");
+
+    // Messages that do not match any route fall back to the MX
+    session
+        .send_message(
+            "jane@example.org",
+            &["bill@foobar.net"],
+            &message("jane@example.org", 200),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone())
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    remote.queue_receiver.assert_no_events();
+}
+
+fn message(from: &str, lines: usize) -> String {
+    let mut message = format!(
+        concat!(
+            "From: {}\r\n",
+            "To: bill@foobar.net\r\n",
+            "Subject: Smarthost routing\r\n",
+            "\r\n"
+        ),
+        from
+    );
+    for line in 0..lines {
+        message.push_str(&format!(
+            "Line {line:03}: The quick brown fox jumps over the lazy dog.\r\n"
+        ));
+    }
+    message
+}
@@ -30,6 +30,15 @@ from-name = "'Mail Delivery Subsystem'"
 from-address = "'MAILER-DAEMON@example.org'"
 sign = "['rsa']"
 
+[report.dsn.template."de"]
+domains = ["*.test.org"]
+languages = ["de"]
+brand = "Beispiel"
+subject.failure = "Unzustellbar: Nachricht an {sender}"
+intro.failure = "{brand} konnte Ihre Nachricht nicht zustellen"
+footer = "Hilfe: {support}"
+support = "hilfe@test.org"
+
 "#;
 
 #[tokio::test]
@@ -158,6 +167,48 @@ async fn generate_dsn() {
     // Load queue
     let queue = qr.read_queued_messages().await;
     assert_eq!(queue.len(), 4);
+
+    // Templates are selected by sender domain and message language
+    for (return_path, language, expected) in [
+        ("sender@mail.test.org", "de-AT", true),
+        ("sender@mail.test.org", "fr", false),
+        ("sender@foobar.org", "de", false),
+    ] {
+        let original = format!(
+            "From: {return_path}\r\nContent-Language: {language}\r\nSubject: Test\r\n\r\nTest\r\n"
+        );
+        message.blob_hash = BlobHash::from(original.as_bytes());
+        qr.blob_store
+            .put_blob(message.blob_hash.as_slice(), original.as_bytes())
+            .await
+            .unwrap();
+        message.return_path = return_path.to_string();
+        message.return_path_domain = return_path.split_once('@').unwrap().1.to_string();
+        message.recipients.truncate(1);
+        message.recipients[0].flags = flags;
+        core.send_dsn(&mut message).await;
+
+        let dsn_message = qr.expect_message().await;
+        let dsn = String::from_utf8(
+            qr.blob_store
+                .get_blob(dsn_message.blob_hash.as_slice(), 0..usize::MAX)
+                .await
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap();
+        for text in [
+            "Content-Language: de-at\r\n",
+            &format!("Subject: Unzustellbar: Nachricht an {return_path}\r\n"),
+            "Beispiel konnte Ihre Nachricht nicht zustellen:\r\n",
+            "Hilfe: hilfe@test.org\r\n",
+        ] {
+            assert_eq!(dsn.contains(text), expected, "{text:?} in {dsn}");
+        }
+        if !expected {
+            assert!(dsn.contains("Subject: Failed to deliver message\r\n"));
+        }
+    }
 }
 
 impl QueueReceiver {
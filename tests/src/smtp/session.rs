@@ -362,6 +362,7 @@ impl TestServerInstance for ServerInstance {
             limiter: ConcurrencyLimiter::new(100),
             shutdown_rx,
             proxy_networks: vec![],
+            proxy_required: false,
             span_id_gen: Arc::new(SnowflakeIdGenerator::new()),
         }
     }
@@ -4,27 +4,209 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::path::PathBuf;
+
 use ahash::AHashMap;
 use store::{
-    write::{blob::BlobQuota, now, BatchBuilder, BlobOp},
+    write::{
+        blob::{BlobQuota, BlobUsage},
+        now, BatchBuilder, BlobOp,
+    },
     BlobClass, BlobStore, Serialize, Stores,
 };
 use utils::{config::Config, BlobHash};
 
 use crate::store::{TempDir, CONFIG};
 
+const COMPOSITE_CONFIG: &str = r#"
+[store."tiered"]
+type = "tiered-blob"
+hot = "fs"
+cold = "sqlite"
+migrate-after = "0s"
+
+[store."replicated"]
+type = "replicated-blob"
+primary = "fs"
+secondary = "sqlite"
+
+[store."broken"]
+type = "fs"
+path = "{TMP}/broken"
+
+[store."failover"]
+type = "replicated-blob"
+primary = "broken"
+secondary = "sqlite"
+replication = "sync"
+failover.threshold = 1
+failover.retry-interval = "1h"
+
+[store."unreplicated"]
+type = "replicated-blob"
+primary = "fs"
+secondary = "broken"
+replication = "sync"
+
+[store."wasm"]
+type = "wasm"
+module = "{RESOURCES}/store/wasm_blob.wat"
+directory = "{TMP}/wasm"
+limits.memory = 268435456
+"#;
+
 #[tokio::test]
 pub async fn blob_tests() {
     let temp_dir = TempDir::new("blob_tests", true);
-    let mut config =
-        Config::new(CONFIG.replace("{TMP}", temp_dir.path.as_path().to_str().unwrap())).unwrap();
+    // A file in place of the blob directory makes every write fail
+    std::fs::write(temp_dir.path.join("broken"), b"").unwrap();
+    let mut config = Config::new(
+        format!("{CONFIG}{COMPOSITE_CONFIG}")
+            .replace("{TMP}", temp_dir.path.as_path().to_str().unwrap())
+            .replace(
+                "{RESOURCES}",
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("resources")
+                    .to_str()
+                    .unwrap(),
+            ),
+    )
+    .unwrap();
     let stores = Stores::parse_all(&mut config).await;
 
     for (store_id, blob_store) in &stores.blob_stores {
+        if ["replicated", "failover", "unreplicated", "broken"].contains(&store_id.as_str()) {
+            // Replication and failover are tested separately below
+            continue;
+        }
         println!("Testing blob store {}...", store_id);
         test_store(blob_store.clone()).await;
     }
 
+    // Aged blobs should be moved from the hot to the cold tier
+    println!("Testing blob tiering...");
+    let store = stores.stores.get("sqlite").unwrap().clone();
+    let tiered = stores.blob_stores.get("tiered").unwrap().clone();
+    let hot = stores.blob_stores.get("fs").unwrap().clone();
+    let cold = stores.blob_stores.get("sqlite").unwrap().clone();
+    store.destroy().await;
+    let hash = BlobHash::from(b"tiered".as_slice());
+    tiered
+        .put_blob(hash.as_ref(), b"tiered".as_slice())
+        .await
+        .unwrap();
+    store
+        .write(
+            BatchBuilder::new()
+                .with_account_id(0)
+                .with_collection(0)
+                .update_document(0)
+                .set(BlobOp::Link { hash: hash.clone() }, vec![])
+                .set(BlobOp::Commit { hash: hash.clone() }, now().serialize())
+                .build_batch(),
+        )
+        .await
+        .unwrap();
+    for (blob_store, expected) in [(&hot, true), (&cold, false), (&tiered, true)] {
+        assert_eq!(
+            blob_store
+                .get_blob(hash.as_ref(), 0..usize::MAX)
+                .await
+                .unwrap()
+                .is_some(),
+            expected
+        );
+    }
+    store.purge_blobs(tiered.clone()).await.unwrap();
+    for (blob_store, expected) in [(&hot, false), (&cold, true), (&tiered, true)] {
+        assert_eq!(
+            blob_store
+                .get_blob(hash.as_ref(), 0..usize::MAX)
+                .await
+                .unwrap()
+                .is_some(),
+            expected
+        );
+    }
+
+    // Blobs missing from a replica should be copied over
+    println!("Testing blob replication...");
+    let replicated = stores.blob_stores.get("replicated").unwrap().clone();
+    let hash = BlobHash::from(b"replicated".as_slice());
+    hot.put_blob(hash.as_ref(), b"replicated".as_slice())
+        .await
+        .unwrap();
+    store
+        .write(
+            BatchBuilder::new()
+                .with_account_id(0)
+                .with_collection(0)
+                .update_document(1)
+                .set(BlobOp::Link { hash: hash.clone() }, vec![])
+                .set(BlobOp::Commit { hash: hash.clone() }, now().serialize())
+                .build_batch(),
+        )
+        .await
+        .unwrap();
+    assert!(cold
+        .get_blob(hash.as_ref(), 0..usize::MAX)
+        .await
+        .unwrap()
+        .is_none());
+    store.purge_blobs(replicated.clone()).await.unwrap();
+    for blob_store in [&hot, &cold, &replicated] {
+        assert_eq!(
+            blob_store
+                .get_blob(hash.as_ref(), 0..usize::MAX)
+                .await
+                .unwrap(),
+            Some(b"replicated".to_vec())
+        );
+    }
+
+    // Reads should fail over to the secondary replica
+    hot.delete_blob(hash.as_ref()).await.unwrap();
+    assert_eq!(
+        replicated
+            .get_blob(hash.as_ref(), 0..usize::MAX)
+            .await
+            .unwrap(),
+        Some(b"replicated".to_vec())
+    );
+
+    // Writes should fail over to the secondary replica when the primary is down
+    println!("Testing blob failover...");
+    let failover = stores.blob_stores.get("failover").unwrap().clone();
+    let hash = BlobHash::from(b"failover".as_slice());
+    failover
+        .put_blob(hash.as_ref(), b"failover".as_slice())
+        .await
+        .unwrap();
+    for blob_store in [&cold, &failover] {
+        assert_eq!(
+            blob_store
+                .get_blob(hash.as_ref(), 0..usize::MAX)
+                .await
+                .unwrap(),
+            Some(b"failover".to_vec())
+        );
+    }
+    assert!(failover.delete_blob(hash.as_ref()).await.unwrap());
+    assert!(cold
+        .get_blob(hash.as_ref(), 0..usize::MAX)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Synchronous writes should fail when the secondary replica is down
+    let unreplicated = stores.blob_stores.get("unreplicated").unwrap().clone();
+    let hash = BlobHash::from(b"unreplicated".as_slice());
+    assert!(unreplicated
+        .put_blob(hash.as_ref(), b"unreplicated".as_slice())
+        .await
+        .is_err());
+    hot.delete_blob(hash.as_ref()).await.unwrap();
+
     for (store_id, store) in stores.stores {
         println!("Testing blob management on store {}...", store_id);
 
@@ -272,6 +454,39 @@ pub async fn blob_tests() {
             .await
             .unwrap());
 
+        // Each linked blob is referenced once, nothing is shared yet
+        assert_eq!(
+            store.blob_usage().await.unwrap(),
+            BlobUsage { blobs: 3, links: 3 }
+        );
+        assert_eq!(BlobUsage { blobs: 1, links: 4 }.dedup_ratio(), 75);
+
+        // Linked blobs are reference counted, linking the same document twice
+        // does not add a reference
+        store
+            .write(
+                BatchBuilder::new()
+                    .with_account_id(0)
+                    .with_collection(0)
+                    .update_document(2)
+                    .set(
+                        BlobOp::Link {
+                            hash: BlobHash::from(b"789".as_slice()),
+                        },
+                        vec![],
+                    )
+                    .build_batch(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .blob_ref_count(BlobHash::from(b"789".as_slice()))
+                .await
+                .unwrap(),
+            1
+        );
+
         // Unlink blob
         store
             .write(
@@ -287,6 +502,14 @@ pub async fn blob_tests() {
             .await
             .unwrap();
 
+        assert_eq!(
+            store
+                .blob_ref_count(BlobHash::from(b"789".as_slice()))
+                .await
+                .unwrap(),
+            0
+        );
+
         // Purge and make sure blob is deleted
         store.purge_blobs(blob_store.clone()).await.unwrap();
         for (pos, (blob, blob_class)) in [
@@ -346,8 +569,15 @@ pub async fn blob_tests() {
             );
         }
 
-        // Unlink all blobs from accountId 1 and purge
-        store.blob_hash_unlink_account(1).await.unwrap();
+        // Purge accountId 1, its blobs should no longer be referenced
+        store.purge_account(1).await.unwrap();
+        assert_eq!(
+            store
+                .blob_ref_count(BlobHash::from(b"123".as_slice()))
+                .await
+                .unwrap(),
+            0
+        );
         store.purge_blobs(blob_store.clone()).await.unwrap();
 
         // Make sure only accountId 0's blobs are left
@@ -5,7 +5,10 @@
  */
 
 use ahash::AHashSet;
-use common::{manager::backup::BackupParams, Core};
+use common::{
+    manager::{backup::BackupParams, restore::verify_backup_chain},
+    Core,
+};
 use jmap_proto::types::{collection::Collection, property::Property};
 use store::{
     rand,
@@ -250,9 +253,66 @@ pub async fn test(db: Store) {
     snapshot.assert_is_eq(&Snapshot::new(&db).await);
     println!(" GREAT SUCCESS!");
 
+    // Export a full backup followed by an incremental one
+    println!("Exporting incremental store...");
+    let full_dir = TempDir::new("art_vandelay_full_tests", true);
+    core.backup(BackupParams::new(full_dir.path.clone())).await;
+
+    // Change an ACL and a membership, neither of which write to the change log
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(3)
+        .with_collection(Collection::Mailbox)
+        .update_document(10)
+        .set(ValueClass::Acl(7), vec![3, 7, 10, 1]);
+    db.write(batch.build()).await.unwrap();
+    let mut batch = BatchBuilder::new();
+    batch.set(
+        ValueClass::Directory(DirectoryClass::MemberOf {
+            principal_id: MaybeDynamicId::Static(5),
+            member_of: MaybeDynamicId::Static(7),
+        }),
+        vec![],
+    );
+    db.write(batch.build()).await.unwrap();
+
+    let incremental_dir = TempDir::new("art_vandelay_incremental_tests", true);
+    core.backup(BackupParams::new(incremental_dir.path.clone()).with_since(full_dir.path.clone()))
+        .await;
+    let manifest_path = incremental_dir.path.join("manifest.json");
+    let mut manifest =
+        serde_json::from_slice::<serde_json::Value>(&std::fs::read(&manifest_path).unwrap())
+            .unwrap();
+    assert_eq!(manifest["accounts"], serde_json::json!([3, 5]));
+
+    // Verify backup chain
+    println!("Verifying incremental store...");
+    assert_eq!(
+        verify_backup_chain(incremental_dir.path.clone()).await,
+        Vec::<String>::new()
+    );
+
+    // Restore the chain
+    println!("Importing incremental store...");
+    let snapshot = Snapshot::new(&db).await;
+    db.destroy().await;
+    core.restore(full_dir.path.clone()).await;
+    core.restore(incremental_dir.path.clone()).await;
+    snapshot.assert_is_eq(&Snapshot::new(&db).await);
+
+    // Increments missing a changed account must fail verification
+    manifest["accounts"] = serde_json::json!([3]);
+    std::fs::write(&manifest_path, serde_json::to_vec(&manifest).unwrap()).unwrap();
+    assert!(verify_backup_chain(incremental_dir.path.clone())
+        .await
+        .iter()
+        .any(|error| error.contains("account 5 changed but was not exported")));
+
     // Destroy store
     db.destroy().await;
     temp_dir.delete();
+    full_dir.delete();
+    incremental_dir.delete();
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -308,6 +368,11 @@ impl Snapshot {
             db.iterate(
                 IterateParams::new(from_key, to_key).set_values(with_values),
                 |key, value| {
+                    // Backup watermarks are advanced by the restore itself
+                    if subspace == SUBSPACE_QUOTA && key.first() == Some(&7) {
+                        return Ok(true);
+                    }
+
                     keys.insert(KeyValue {
                         subspace,
                         key: key.to_vec(),
@@ -6,7 +6,7 @@
 
 use std::time::Duration;
 
-use store::{LookupStore, Stores};
+use store::{write::coalesce::CounterCoalescer, LookupStore, Stores};
 use utils::config::{Config, Rate};
 
 use crate::{
@@ -32,11 +32,10 @@ pub async fn lookup_tests() {
         if let LookupStore::Store(store) = &store {
             store.destroy().await;
         } else {
-            // Reset redis counter
-            store
-                .key_set("abc".as_bytes().to_vec(), "0".as_bytes().to_vec(), None)
-                .await
-                .unwrap();
+            // Reset counters
+            for key in ["abc", "ijk", "lmn"] {
+                store.counter_delete(key.as_bytes().to_vec()).await.unwrap();
+            }
         }
 
         // Test key
@@ -97,6 +96,28 @@ pub async fn lookup_tests() {
         store.purge_lookup_store().await.unwrap();
         assert_eq!(0, store.counter_get(key.clone()).await.unwrap());
 
+        // Test coalesced counters
+        let key = "ijk".as_bytes().to_vec();
+        let other_key = "lmn".as_bytes().to_vec();
+        let coalescer = CounterCoalescer::new(2, Duration::from_secs(60));
+        assert!(!coalescer.add(key.clone(), 1, None));
+        assert!(!coalescer.add(key.clone(), 2, None));
+        assert_eq!(3, coalescer.pending(&key));
+        assert_eq!(0, store.counter_get(key.clone()).await.unwrap());
+        assert!(coalescer.add(other_key.clone(), 1, None));
+        assert!(!coalescer.add(other_key.clone(), 0, None));
+        assert_eq!(0, coalescer.flush(&store).await.unwrap());
+        assert_eq!(2, coalescer.flush_claimed(&store).await.unwrap());
+        assert!(coalescer.is_empty());
+        assert_eq!(0, coalescer.pending(&key));
+        assert_eq!(3, store.counter_get(key.clone()).await.unwrap());
+        assert_eq!(1, store.counter_get(other_key.clone()).await.unwrap());
+        coalescer.add(key.clone(), -3, None);
+        coalescer.add(other_key.clone(), -1, None);
+        assert_eq!(2, coalescer.flush(&store).await.unwrap());
+        assert_eq!(0, store.counter_get(key.clone()).await.unwrap());
+        assert_eq!(0, store.counter_get(other_key.clone()).await.unwrap());
+
         // Test rate limiter
         assert!(store
             .is_rate_allowed("rate".as_bytes(), &rate, false)
@@ -121,3 +142,100 @@ pub async fn lookup_tests() {
         }
     }
 }
+
+#[tokio::test]
+pub async fn file_lookup_tests() {
+    let temp_dir = TempDir::new("file_lookup_tests", true);
+    let list_path = temp_dir.path.join("blocklist.txt");
+    let map_path = temp_dir.path.join("aliases.map");
+    std::fs::write(
+        &list_path,
+        "# Blocked senders\nspam@example.org\n*.spammer.net\n",
+    )
+    .unwrap();
+    std::fs::write(&map_path, "john@example.org   jdoe@example.org\n").unwrap();
+
+    let mut config = Config::new(format!(
+        concat!(
+            "[store.\"blocklist\"]\n",
+            "type = \"file\"\n",
+            "path = \"{}\"\n",
+            "[store.\"aliases\"]\n",
+            "type = \"file\"\n",
+            "format = \"map\"\n",
+            "path = \"{}\"\n",
+        ),
+        list_path.to_str().unwrap(),
+        map_path.to_str().unwrap()
+    ))
+    .unwrap()
+    .assert_no_errors();
+    let stores = Stores::parse_all(&mut config).await;
+    let blocklist = stores.lookup_stores.get("blocklist").unwrap().clone();
+    let aliases = stores.lookup_stores.get("aliases").unwrap().clone();
+
+    // Lists contain keys and globs, maps contain values
+    for (key, expected) in [
+        ("spam@example.org", true),
+        ("mail.spammer.net", true),
+        ("ham@example.org", false),
+    ] {
+        assert_eq!(
+            blocklist.key_exists(key.as_bytes().to_vec()).await.unwrap(),
+            expected,
+            "{key}"
+        );
+    }
+    assert_eq!(
+        aliases
+            .key_get::<String>(b"john@example.org".to_vec())
+            .await
+            .unwrap(),
+        Some("jdoe@example.org".to_string())
+    );
+
+    // Files replaced on disk are reloaded
+    let tmp_path = temp_dir.path.join("blocklist.tmp");
+    std::fs::write(&tmp_path, "ham@example.org\n").unwrap();
+    std::fs::rename(&tmp_path, &list_path).unwrap();
+    wait_for_key(&blocklist, "ham@example.org", true).await;
+    assert!(!blocklist
+        .key_exists(b"spam@example.org".to_vec())
+        .await
+        .unwrap());
+
+    // Files modified in place are reloaded
+    std::fs::write(
+        &map_path,
+        "john@example.org jdoe@example.org\njane@example.org jane.doe@example.org\n",
+    )
+    .unwrap();
+    wait_for_key(&aliases, "jane@example.org", true).await;
+
+    // Invalid contents are discarded and the previous version is kept
+    let tmp_path = temp_dir.path.join("aliases.tmp");
+    std::fs::write(&tmp_path, "bill@example.org\n").unwrap();
+    std::fs::rename(&tmp_path, &map_path).unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(
+        aliases
+            .key_get::<String>(b"jane@example.org".to_vec())
+            .await
+            .unwrap(),
+        Some("jane.doe@example.org".to_string())
+    );
+    assert!(!aliases
+        .key_exists(b"bill@example.org".to_vec())
+        .await
+        .unwrap());
+}
+
+async fn wait_for_key(store: &LookupStore, key: &str, expected: bool) {
+    for _ in 0..50 {
+        if store.key_exists(key.as_bytes().to_vec()).await.unwrap() == expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Timed out waiting for key {key:?} to be reloaded");
+}
@@ -8,8 +8,12 @@ pub mod assign_id;
 pub mod blob;
 pub mod import_export;
 pub mod lookup;
+pub mod notify;
 pub mod ops;
+pub mod priority;
 pub mod query;
+pub mod reload;
+pub mod stream;
 
 use std::io::Read;
 
@@ -31,6 +35,12 @@ region = "eu-central-1"
 endpoint = "http://localhost:9000"
 bucket = "tmp"
 
+[store."gcs"]
+type = "gcs"
+bucket = "tmp"
+endpoint = "http://localhost:4443"
+anonymous = true
+
 [store."fs"]
 type = "fs"
 path = "{TMP}"
@@ -41,6 +51,7 @@ path = "{TMP}/rocksdb"
 
 [store."foundationdb"]
 type = "foundationdb"
+key-salt.buckets = 4
 
 [store."sqlite"]
 type = "sqlite"
@@ -54,6 +65,15 @@ database = "stalwart"
 user = "postgres"
 password = "mysecretpassword"
 
+[store."cockroachdb"]
+type = "cockroachdb"
+host = "localhost"
+port = 26257
+database = "stalwart"
+user = "root"
+hash-sharded.buckets = 8
+read-only.staleness = "1s"
+
 [store."mysql"]
 type = "mysql"
 host = "localhost"
@@ -61,11 +81,20 @@ port = 3307
 database = "stalwart"
 user = "root"
 password = "password"
+session.sql_mode = "STRICT_TRANS_TABLES,NO_ZERO_DATE"
+session.wait_timeout = 600
 
 [store."redis"]
 type = "redis"
 urls = "redis://127.0.0.1"
 redis-type = "single"
+hash-tags = true
+
+[store."etcd"]
+type = "etcd"
+endpoints = "http://127.0.0.1:2379"
+key-prefix = "test/"
+cache.size = 1000
 
 "#;
 
@@ -94,6 +123,8 @@ pub async fn store_tests() {
     import_export::test(store.clone()).await;
     assign_id::test(store.clone()).await;
     ops::test(store.clone()).await;
+    stream::test(store.clone()).await;
+    notify::test(store.clone()).await;
     query::test(store.clone(), FtsStore::Store(store.clone()), insert).await;
 
     if insert {
@@ -0,0 +1,61 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::ipc::CacheInvalidation;
+use store::Store;
+
+pub async fn test(store: Store) {
+    // Only PostgreSQL supports notifications
+    let mut rx1 = match store.listen("cache_invalidation").await {
+        Ok(rx) => rx,
+        Err(err) if err.matches(trc::EventType::Store(trc::StoreEvent::NotSupported)) => {
+            return;
+        }
+        Err(err) => panic!("Failed to subscribe: {err:?}"),
+    };
+
+    println!("Running notification tests...");
+
+    // Notifications are delivered to every subscriber of the channel
+    let mut rx2 = store.listen("cache_invalidation").await.unwrap();
+    let mut rx_other = store.listen("other_channel").await.unwrap();
+    for event in [
+        CacheInvalidation::Settings,
+        CacheInvalidation::AccessToken(12345),
+    ] {
+        store
+            .notify("cache_invalidation", &event.to_payload(7))
+            .await
+            .unwrap();
+    }
+    for rx in [&mut rx1, &mut rx2] {
+        for expected in [
+            CacheInvalidation::Settings,
+            CacheInvalidation::AccessToken(12345),
+        ] {
+            let payload = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                CacheInvalidation::parse_payload(&payload),
+                Some((7, expected))
+            );
+        }
+    }
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), rx_other.recv())
+            .await
+            .is_err()
+    );
+
+    // Malformed payloads are rejected
+    for payload in ["settings", "7:unknown", "7:access-token:abc", "x:settings"] {
+        assert_eq!(CacheInvalidation::parse_payload(payload), None);
+    }
+}
@@ -0,0 +1,78 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::{priority::StorePriority, Core};
+use utils::config::Config;
+
+use crate::smtp::TestSMTP;
+
+const CONFIG: &str = r#"
+[storage.priority]
+target-latency = "50ms"
+max-delay = "300ms"
+window = "1s"
+"#;
+
+#[tokio::test]
+async fn store_priority() {
+    let mut config = Config::new(CONFIG).unwrap();
+    let priority = StorePriority::parse(&mut config);
+    assert_eq!(
+        priority,
+        StorePriority {
+            target_latency: Some(Duration::from_millis(50)),
+            max_delay: Duration::from_millis(300),
+            window: Duration::from_secs(1),
+        }
+    );
+
+    let mut core = Core::default();
+    core.storage.priority = priority;
+    let server = TestSMTP::from_core(core).server;
+
+    // Background tasks are not delayed when there are no interactive requests
+    let start_time = Instant::now();
+    server.yield_to_interactive().await;
+    assert!(start_time.elapsed() < Duration::from_millis(50));
+
+    // Background tasks are delayed while interactive requests are slow
+    for _ in 0..5 {
+        server.record_interactive_latency(Duration::from_millis(200));
+    }
+    let start_time = Instant::now();
+    server.yield_to_interactive().await;
+    let elapsed = start_time.elapsed();
+    assert!(elapsed >= Duration::from_millis(300), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(600), "{elapsed:?}");
+
+    // Fast interactive requests lower the average latency
+    for _ in 0..50 {
+        server.record_interactive_latency(Duration::from_millis(1));
+    }
+    let start_time = Instant::now();
+    server.yield_to_interactive().await;
+    assert!(start_time.elapsed() < Duration::from_millis(50));
+
+    // The average latency expires after the window
+    for _ in 0..5 {
+        server.record_interactive_latency(Duration::from_millis(200));
+    }
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let start_time = Instant::now();
+    server.yield_to_interactive().await;
+    assert!(start_time.elapsed() < Duration::from_millis(50));
+
+    // Throttling is disabled by default
+    let server = TestSMTP::from_core(Core::default()).server;
+    for _ in 0..5 {
+        server.record_interactive_latency(Duration::from_millis(200));
+    }
+    let start_time = Instant::now();
+    server.yield_to_interactive().await;
+    assert!(start_time.elapsed() < Duration::from_millis(50));
+}
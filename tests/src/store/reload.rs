@@ -0,0 +1,73 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use store::{backend::fs::FsStore, BlobBackend, Stores};
+use utils::config::Config;
+
+use crate::{store::TempDir, AssertConfig};
+
+const CONFIG: &str = r#"
+[store."fs"]
+type = "fs"
+path = "{TMP}/blobs"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/sqlite.db"
+pool.max-connections = 10
+"#;
+
+#[tokio::test]
+async fn store_reload() {
+    let temp_dir = TempDir::new("store_reload_tests", true);
+    let config_str = CONFIG.replace("{TMP}", &temp_dir.path.to_string_lossy());
+    let mut config = Config::new(&config_str).unwrap().assert_no_errors();
+    let stores = Stores::parse_all(&mut config).await;
+    let blob_store = fs_store(&stores);
+
+    // Unchanged stores are reused
+    let mut config = Config::new(&config_str).unwrap();
+    let mut reloaded = stores.clone();
+    reloaded.parse_stores(&mut config).await;
+    assert!(Arc::ptr_eq(&blob_store, &fs_store(&reloaded)));
+    assert!(config.warnings.is_empty(), "{:?}", config.warnings);
+
+    // Lookup queries do not require reconnecting
+    let mut config = Config::new(format!(
+        "{config_str}\nquery.name = \"SELECT name FROM test\"\n"
+    ))
+    .unwrap();
+    let mut reloaded = stores.clone();
+    reloaded.parse_stores(&mut config).await;
+    assert!(Arc::ptr_eq(&blob_store, &fs_store(&reloaded)));
+
+    // Changed stores are rebuilt, embedded stores require a restart
+    let mut config = Config::new(
+        config_str
+            .replace("/blobs", "/blobs-new")
+            .replace("pool.max-connections = 10", "pool.max-connections = 20"),
+    )
+    .unwrap();
+    let mut reloaded = stores.clone();
+    reloaded.parse_stores(&mut config).await;
+    assert!(!Arc::ptr_eq(&blob_store, &fs_store(&reloaded)));
+    assert!(
+        config.warnings.contains_key("store.sqlite"),
+        "{:?}",
+        config.warnings
+    );
+
+    temp_dir.delete();
+}
+
+fn fs_store(stores: &Stores) -> Arc<FsStore> {
+    match &stores.blob_stores.get("fs").unwrap().backend {
+        BlobBackend::Fs(store) => store.clone(),
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,99 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use futures::{pin_mut, TryStreamExt};
+use store::{
+    write::{BatchBuilder, ValueClass},
+    IterateParams, Store, ValueKey,
+};
+
+const TOTAL_KEYS: usize = 2500;
+
+pub async fn test(db: Store) {
+    println!("Running streaming iterator tests...");
+
+    let mut batch = BatchBuilder::new();
+    for n in 0..TOTAL_KEYS {
+        batch.set(
+            ValueClass::Config(format!("stream{n:05}").into_bytes()),
+            format!("value{n:05}").into_bytes(),
+        );
+        if batch.ops.len() >= 500 {
+            db.write(batch.build_batch()).await.unwrap();
+            batch = BatchBuilder::new();
+        }
+    }
+    if !batch.is_empty() {
+        db.write(batch.build_batch()).await.unwrap();
+    }
+
+    // Chunk boundaries should not skip or repeat keys in either direction
+    for chunk_size in [1, 100, 999, TOTAL_KEYS, TOTAL_KEYS * 2] {
+        for ascending in [true, false] {
+            let mut expected = (0..TOTAL_KEYS).collect::<Vec<_>>();
+            if !ascending {
+                expected.reverse();
+            }
+            let results =
+                collect(&db, params(b"stream", b"stream\xff", ascending), chunk_size).await;
+            assert_eq!(results.len(), TOTAL_KEYS, "chunk_size={chunk_size}");
+            for ((key, value), n) in results.into_iter().zip(expected) {
+                assert_eq!(key, format!("stream{n:05}").into_bytes());
+                assert_eq!(value, format!("value{n:05}").into_bytes());
+            }
+        }
+    }
+
+    // Keys only
+    let stream = db.stream(params(b"stream", b"stream\xff", true).no_values(), 100);
+    pin_mut!(stream);
+    let mut total = 0;
+    while let Some((_, value)) = stream.try_next().await.unwrap() {
+        assert!(value.is_empty());
+        total += 1;
+    }
+    assert_eq!(total, TOTAL_KEYS);
+
+    // Partial and empty ranges
+    assert_eq!(
+        collect(&db, params(b"stream00100", b"stream00199", true), 30)
+            .await
+            .len(),
+        100
+    );
+    assert!(collect(&db, params(b"streamz", b"streamz\xff", false), 30)
+        .await
+        .is_empty());
+
+    // Clean up
+    let mut batch = BatchBuilder::new();
+    for n in 0..TOTAL_KEYS {
+        batch.clear(ValueClass::Config(format!("stream{n:05}").into_bytes()));
+        if batch.ops.len() >= 500 {
+            db.write(batch.build_batch()).await.unwrap();
+            batch = BatchBuilder::new();
+        }
+    }
+    if !batch.is_empty() {
+        db.write(batch.build_batch()).await.unwrap();
+    }
+}
+
+async fn collect(
+    db: &Store,
+    params: IterateParams<ValueKey<ValueClass<u32>>>,
+    chunk_size: usize,
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+    db.stream(params, chunk_size).try_collect().await.unwrap()
+}
+
+fn params(from: &[u8], to: &[u8], ascending: bool) -> IterateParams<ValueKey<ValueClass<u32>>> {
+    IterateParams::new(
+        ValueKey::from(ValueClass::Config(from.to_vec())),
+        ValueKey::from(ValueClass::Config(to.to_vec())),
+    )
+    .set_ascending(ascending)
+}